@@ -0,0 +1,99 @@
+// Disk I/O Performance Benchmarks
+// Compares DiskManager page reads and writes submitted through the kernel
+// io_uring against the pread/pwrite fallback, for a range of batch sizes
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rusty_db::storage::disk::IoUringMode;
+use rusty_db::storage::{DiskManager, Page};
+use std::hint::black_box;
+use tempfile::TempDir;
+
+const PAGE_SIZE: usize = 4096;
+const NUM_PAGES: u64 = 1024;
+const BATCH_SIZES: [u64; 3] = [1, 16, 128];
+
+fn create_disk_manager(mode: IoUringMode) -> Option<(DiskManager, TempDir)> {
+    let temp_dir = TempDir::new().unwrap();
+    let disk_manager = DiskManager::new(temp_dir.path().to_str().unwrap(), PAGE_SIZE).unwrap();
+    disk_manager.set_io_uring_mode(mode).unwrap();
+
+    // Without kernel support both modes would measure the fallback
+    if mode != IoUringMode::Sync && !disk_manager.io_uring_kernel_backed().unwrap() {
+        return None;
+    }
+
+    // Lay out the file so reads never run past its end
+    for page_id in 0..NUM_PAGES {
+        disk_manager
+            .write_page_io_uring(&Page::new(page_id, PAGE_SIZE))
+            .unwrap();
+        if page_id % 128 == 127 {
+            disk_manager.submit_io_uring_batch().unwrap();
+            disk_manager.wait_io_uring_completions(128).unwrap();
+        }
+    }
+
+    Some((disk_manager, temp_dir))
+}
+
+fn run_batch(disk_manager: &DiskManager, pages: &[Page], read: bool) {
+    for page in pages {
+        if read {
+            disk_manager.read_page_io_uring(page.id).unwrap();
+        } else {
+            disk_manager.write_page_io_uring(page).unwrap();
+        }
+    }
+    disk_manager.submit_io_uring_batch().unwrap();
+
+    let mut completed = 0;
+    while completed < pages.len() {
+        let completions = disk_manager
+            .wait_io_uring_completions(pages.len() - completed)
+            .unwrap();
+        completed += completions.len();
+        black_box(completions);
+    }
+}
+
+fn bench_page_io(c: &mut Criterion, name: &str, read: bool) {
+    let mut group = c.benchmark_group(name);
+
+    for (label, mode) in [
+        ("io_uring", IoUringMode::Auto),
+        ("pread_pwrite", IoUringMode::Sync),
+    ] {
+        let Some((disk_manager, _temp_dir)) = create_disk_manager(mode) else {
+            eprintln!("io_uring is not available, skipping the {} path", label);
+            continue;
+        };
+
+        for batch in BATCH_SIZES {
+            group.throughput(Throughput::Bytes(batch * PAGE_SIZE as u64));
+            group.bench_with_input(BenchmarkId::new(label, batch), &batch, |b, &batch| {
+                let mut next = 0;
+                b.iter(|| {
+                    // Spread batches over the file rather than rereading one
+                    let pages: Vec<Page> = (0..batch)
+                        .map(|i| Page::new((next + i * 7) % NUM_PAGES, PAGE_SIZE))
+                        .collect();
+                    next = (next + batch * 7) % NUM_PAGES;
+                    run_batch(&disk_manager, &pages, read);
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_page_writes(c: &mut Criterion) {
+    bench_page_io(c, "page_writes", false);
+}
+
+fn bench_page_reads(c: &mut Criterion) {
+    bench_page_io(c, "page_reads", true);
+}
+
+criterion_group!(benches, bench_page_writes, bench_page_reads);
+criterion_main!(benches);
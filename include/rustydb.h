@@ -89,6 +89,15 @@ typedef struct rustydb_handle_t rustydb_handle_t;
  */
 typedef struct rustydb_result_t rustydb_result_t;
 
+/**
+ * Opaque handle to a prepared statement.
+ *
+ * This handle represents a statement bound once, to be executed any number
+ * of times with different parameter values.
+ * It must be created by rustydb_prepare() and freed with rustydb_stmt_finalize().
+ */
+typedef struct rustydb_stmt_t rustydb_stmt_t;
+
 /* ============================================================================
  * Connection Management
  * ========================================================================== */
@@ -212,6 +221,79 @@ int64_t rustydb_result_rows_affected(const rustydb_result_t* result);
  */
 const char* rustydb_result_data_json(const rustydb_result_t* result);
 
+/* ============================================================================
+ * Prepared Statements
+ * ========================================================================== */
+
+/**
+ * Prepare a SQL statement.
+ *
+ * Parses and binds a single statement. Parameters are written $1, $2, ...
+ * or ?, and are given values with the rustydb_bind_* functions; the values
+ * are never spliced into the SQL text.
+ *
+ * @param handle Database handle
+ * @param sql Null-terminated SQL statement
+ * @return Non-NULL statement handle on success, NULL on failure
+ *
+ * @note The returned statement must be freed with rustydb_stmt_finalize()
+ * @note On failure, use rustydb_error_message() to get error details
+ *
+ * Example:
+ *   rustydb_stmt_t* stmt = rustydb_prepare(db, "SELECT name FROM users WHERE id = $1");
+ *   rustydb_bind_int64(db, stmt, 1, 42);
+ *   rustydb_result_t* result = rustydb_stmt_execute(db, stmt);
+ */
+rustydb_stmt_t* rustydb_prepare(rustydb_handle_t* handle, const char* sql);
+
+/**
+ * Get the number of parameters of a prepared statement.
+ *
+ * @param stmt Statement handle
+ * @return Number of parameters, or -1 if stmt is NULL
+ */
+int rustydb_stmt_param_count(const rustydb_stmt_t* stmt);
+
+/**
+ * Bind a value to a parameter.
+ *
+ * Parameters are numbered from 1 and are NULL until bound. Values are
+ * converted to the parameter's type when the statement executes. Text is
+ * copied, so it may be freed once the call returns.
+ *
+ * @param handle Database handle, which receives any error
+ * @param stmt Statement handle
+ * @param index Parameter number, counting from 1
+ * @return RUSTYDB_OK on success, RUSTYDB_ERROR if the index is out of range
+ */
+int rustydb_bind_null(rustydb_handle_t* handle, rustydb_stmt_t* stmt, int index);
+int rustydb_bind_int64(rustydb_handle_t* handle, rustydb_stmt_t* stmt, int index, int64_t value);
+int rustydb_bind_double(rustydb_handle_t* handle, rustydb_stmt_t* stmt, int index, double value);
+int rustydb_bind_text(rustydb_handle_t* handle, rustydb_stmt_t* stmt, int index, const char* value);
+
+/**
+ * Execute a prepared statement with its bound parameters.
+ *
+ * Parameters keep their values afterwards, so the statement can be run
+ * again with only some of them rebound.
+ *
+ * @param handle Database handle
+ * @param stmt Statement handle
+ * @return Non-NULL result handle on success, NULL on failure
+ *
+ * @note The returned result must be freed with rustydb_free_result()
+ */
+rustydb_result_t* rustydb_stmt_execute(rustydb_handle_t* handle, rustydb_stmt_t* stmt);
+
+/**
+ * Free a prepared statement.
+ *
+ * @param stmt Statement handle (can be NULL)
+ *
+ * @note Calling with NULL is safe (no-op)
+ */
+void rustydb_stmt_finalize(rustydb_stmt_t* stmt);
+
 /* ============================================================================
  * Transaction Control
  * ========================================================================== */
@@ -245,11 +327,12 @@ int rustydb_begin(rustydb_handle_t* handle);
  * @return RUSTYDB_OK on success, RUSTYDB_ERROR on failure
  *
  * @note Use rustydb_error_message() to get error details on failure
+ * @note A failed commit, such as one losing a write conflict to another
+ *       transaction, rolls the transaction back
  *
  * Example:
  *   if (rustydb_commit(db) != RUSTYDB_OK) {
  *       fprintf(stderr, "Commit failed: %s\n", rustydb_error_message(db));
- *       rustydb_rollback(db);
  *       return 1;
  *   }
  */
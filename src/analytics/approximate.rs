@@ -61,9 +61,10 @@ impl HyperLogLog {
         // Extract register index from first p bits
         let register_index = (hash & ((1 << self.precision) - 1)) as usize;
 
-        // Count leading zeros in remaining bits plus 1
+        // Count leading zeros in remaining bits plus 1; the shift itself
+        // leaves `precision` zeros at the top that are not part of them
         let remaining_bits = hash >> self.precision;
-        let leading_zeros = remaining_bits.leading_zeros() as u8 + 1;
+        let leading_zeros = (remaining_bits.leading_zeros() - self.precision as u32) as u8 + 1;
 
         // Update register with maximum leading zeros seen
         if leading_zeros > self.registers[register_index] {
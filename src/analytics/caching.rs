@@ -642,6 +642,7 @@ pub struct PreloadRule {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::Value;
 
     #[test]
     fn test_multi_level_cache() {
@@ -649,7 +650,7 @@ mod tests {
 
         let result = QueryResult::new(
             vec!["id".to_string(), "name".to_string()],
-            vec![vec!["1".to_string(), "Alice".to_string()]],
+            vec![vec![Value::Integer(1), Value::String("Alice".to_string())]],
         );
 
         cache.put("query1".to_string(), result.clone());
@@ -994,6 +995,7 @@ pub enum InvalidationType {
 #[cfg(test)]
 mod semantic_tests {
     use crate::analytics::caching::{MemoryPressureHandler, QueryNormalizer, SemanticQueryCache};
+    use crate::common::Value;
     use crate::execution::QueryResult;
 
     #[test]
@@ -1014,7 +1016,7 @@ mod semantic_tests {
     fn test_semantic_cache() {
         let cache = SemanticQueryCache::new();
 
-        let result = QueryResult::new(vec!["id".to_string()], vec![vec!["1".to_string()]]);
+        let result = QueryResult::new(vec!["id".to_string()], vec![vec![Value::Integer(1)]]);
 
         cache.put("SELECT * FROM users", result.clone()).unwrap();
 
@@ -9,6 +9,7 @@
 
 use crate::error::{DbError, Result};
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 
 // =============================================================================
 // Window Function Types
@@ -126,7 +127,7 @@ impl WindowFrame {
 }
 
 // Frame type determining how boundaries are interpreted.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum FrameType {
     // Physical row offsets
     Rows,
@@ -136,6 +137,19 @@ pub enum FrameType {
     Groups,
 }
 
+// Rows taken back out of a frame (the EXCLUDE clause).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FrameExclusion {
+    // Keep every row
+    NoOthers,
+    // The current row
+    CurrentRow,
+    // The current row and its peers
+    Group,
+    // The current row's peers, but not the row itself
+    Ties,
+}
+
 // Frame boundary specification.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum FrameBound {
@@ -151,6 +165,123 @@ pub enum FrameBound {
     UnboundedFollowing,
 }
 
+// =============================================================================
+// Peer Groups
+// =============================================================================
+
+// An ordered partition split into peer groups: runs of rows with equal
+// ORDER BY keys, all of them when there is no ORDER BY. Ranks,
+// distributions, GROUPS frames and exclusions are measured in these.
+#[derive(Debug, Clone)]
+pub struct PeerGroups {
+    groups: Vec<Range<usize>>,
+    group_of: Vec<usize>,
+}
+
+impl PeerGroups {
+    // Split `len` rows, where `peers(a, b)` tells whether rows `a` and `b`
+    // have equal keys.
+    pub fn new(len: usize, peers: impl Fn(usize, usize) -> bool) -> Self {
+        let mut groups: Vec<Range<usize>> = Vec::new();
+        let mut group_of = Vec::with_capacity(len);
+        for i in 0..len {
+            match groups.last_mut() {
+                Some(group) if peers(group.start, i) => group.end = i + 1,
+                _ => groups.push(i..i + 1),
+            }
+            group_of.push(groups.len() - 1);
+        }
+        Self { groups, group_of }
+    }
+
+    // Row `i` and its peers.
+    pub fn peers(&self, i: usize) -> Range<usize> {
+        self.groups[self.group_of[i]].clone()
+    }
+
+    // 1-based rank of row `i`, with gaps after ties.
+    pub fn rank(&self, i: usize) -> usize {
+        self.peers(i).start + 1
+    }
+
+    // 1-based rank of row `i` without gaps.
+    pub fn dense_rank(&self, i: usize) -> usize {
+        self.group_of[i] + 1
+    }
+
+    // (rank - 1) / (rows - 1), or 0 for a single row.
+    pub fn percent_rank(&self, i: usize) -> f64 {
+        match self.group_of.len() {
+            1 => 0.0,
+            n => self.peers(i).start as f64 / (n - 1) as f64,
+        }
+    }
+
+    // Share of the rows up to row `i`'s last peer.
+    pub fn cume_dist(&self, i: usize) -> f64 {
+        self.peers(i).end as f64 / self.group_of.len() as f64
+    }
+
+    // Where a frame bound of row `i` falls: the first row in the frame for
+    // a start, the row after the last one for an end. RANGE offsets compare
+    // key values, which only the caller can do; here they stand for the
+    // current row's peers.
+    pub fn bound(&self, i: usize, frame_type: FrameType, bound: &FrameBound, end: bool) -> usize {
+        let n = self.group_of.len();
+        let group = self.group_of[i];
+        let peers = &self.groups[group];
+        match (bound, frame_type) {
+            (FrameBound::UnboundedPreceding, _) => 0,
+            (FrameBound::UnboundedFollowing, _) => n,
+            (FrameBound::CurrentRow, FrameType::Rows) => i + end as usize,
+            (FrameBound::Preceding(k), FrameType::Rows) => (i + end as usize).saturating_sub(*k),
+            (FrameBound::Following(k), FrameType::Rows) => {
+                i.saturating_add(*k).saturating_add(end as usize).min(n)
+            }
+            (FrameBound::Preceding(k), FrameType::Groups) => match end {
+                false => self.groups[group.saturating_sub(*k)].start,
+                true => group.checked_sub(*k).map_or(0, |g| self.groups[g].end),
+            },
+            (FrameBound::Following(k), FrameType::Groups) => match end {
+                false => self
+                    .groups
+                    .get(group.saturating_add(*k))
+                    .map_or(n, |peers| peers.start),
+                true => self.groups[group.saturating_add(*k).min(self.groups.len() - 1)].end,
+            },
+            (_, FrameType::Groups | FrameType::Range) => match end {
+                false => peers.start,
+                true => peers.end,
+            },
+        }
+    }
+
+    // The rows of `bounds` that row `i`'s exclusion leaves in.
+    pub fn excluding(&self, i: usize, bounds: Range<usize>, exclude: FrameExclusion) -> Vec<usize> {
+        let peers = self.peers(i);
+        bounds
+            .filter(|row| match exclude {
+                FrameExclusion::NoOthers => true,
+                FrameExclusion::CurrentRow => *row != i,
+                FrameExclusion::Group => !peers.contains(row),
+                FrameExclusion::Ties => *row == i || !peers.contains(row),
+            })
+            .collect()
+    }
+}
+
+// 1-based NTILE bucket of row `i` of `n` split into `buckets` groups whose
+// sizes differ by at most one, the larger ones first.
+pub fn ntile(i: usize, n: usize, buckets: usize) -> usize {
+    let (size, larger) = (n / buckets, n % buckets);
+    let boundary = larger * (size + 1);
+    let bucket = match i < boundary {
+        true => i / (size + 1),
+        false => larger + (i - boundary) / size,
+    };
+    bucket + 1
+}
+
 // =============================================================================
 // Window Function Execution
 // =============================================================================
@@ -174,6 +305,11 @@ pub fn apply_window_function(
     value_column: usize,
 ) -> Result<Vec<String>> {
     let mut result = Vec::with_capacity(data.len());
+    let peers = PeerGroups::new(data.len(), |a, b| {
+        order_by
+            .iter()
+            .all(|&column| data[a].get(column) == data[b].get(column))
+    });
 
     match function {
         WindowFunction::RowNumber => {
@@ -183,11 +319,11 @@ pub fn apply_window_function(
         }
 
         WindowFunction::Rank => {
-            result = compute_rank(data, order_by, false);
+            result.extend((0..data.len()).map(|i| peers.rank(i).to_string()));
         }
 
         WindowFunction::DenseRank => {
-            result = compute_rank(data, order_by, true);
+            result.extend((0..data.len()).map(|i| peers.dense_rank(i).to_string()));
         }
 
         WindowFunction::Lead { offset, default } => {
@@ -250,30 +386,25 @@ pub fn apply_window_function(
         }
 
         WindowFunction::NTile { buckets } => {
-            let bucket_size = (data.len() + buckets - 1) / buckets;
+            if *buckets == 0 {
+                return Err(DbError::Execution(
+                    "NTILE needs at least one bucket".to_string(),
+                ));
+            }
             for i in 0..data.len() {
-                let bucket = (i / bucket_size) + 1;
-                result.push(bucket.min(*buckets).to_string());
+                result.push(ntile(i, data.len(), *buckets).to_string());
             }
         }
 
         WindowFunction::PercentRank => {
-            let n = data.len();
-            for i in 0..n {
-                let percent_rank = if n > 1 {
-                    i as f64 / (n - 1) as f64
-                } else {
-                    0.0
-                };
-                result.push(format!("{:.6}", percent_rank));
+            for i in 0..data.len() {
+                result.push(format!("{:.6}", peers.percent_rank(i)));
             }
         }
 
         WindowFunction::CumeDist => {
-            let n = data.len();
-            for i in 0..n {
-                let cume_dist = (i + 1) as f64 / n as f64;
-                result.push(format!("{:.6}", cume_dist));
+            for i in 0..data.len() {
+                result.push(format!("{:.6}", peers.cume_dist(i)));
             }
         }
     }
@@ -281,57 +412,6 @@ pub fn apply_window_function(
     Ok(result)
 }
 
-// Compute RANK or DENSE_RANK.
-fn compute_rank(data: &[Vec<String>], order_by: &[usize], dense: bool) -> Vec<String> {
-    let mut result = Vec::with_capacity(data.len());
-
-    if dense {
-        // DENSE_RANK: no gaps
-        let mut dense_rank = 1;
-        let mut prev_values: Option<Vec<String>> = None;
-
-        for row in data {
-            let current_values: Vec<String> = order_by
-                .iter()
-                .filter_map(|&i| row.get(i).cloned())
-                .collect();
-
-            if let Some(prev) = &prev_values {
-                if current_values != *prev {
-                    dense_rank += 1;
-                }
-            }
-
-            result.push(dense_rank.to_string());
-            prev_values = Some(current_values);
-        }
-    } else {
-        // RANK: with gaps
-        let mut rank = 1;
-        let mut current_rank = 1;
-        let mut prev_values: Option<Vec<String>> = None;
-
-        for row in data {
-            let current_values: Vec<String> = order_by
-                .iter()
-                .filter_map(|&i| row.get(i).cloned())
-                .collect();
-
-            if let Some(prev) = &prev_values {
-                if current_values != *prev {
-                    current_rank = rank;
-                }
-            }
-
-            result.push(current_rank.to_string());
-            prev_values = Some(current_values);
-            rank += 1;
-        }
-    }
-
-    result
-}
-
 // =============================================================================
 // Window Function Builder
 // =============================================================================
@@ -490,6 +570,28 @@ mod tests {
         assert_eq!(result, vec!["1", "1", "2", "2"]);
     }
 
+    #[test]
+    fn test_peer_groups() {
+        let keys = [1, 1, 2, 3, 3];
+        let peers = PeerGroups::new(keys.len(), |a, b| keys[a] == keys[b]);
+        assert_eq!(peers.rank(3), 4);
+        assert_eq!(peers.dense_rank(3), 3);
+        assert_eq!(peers.cume_dist(0), 0.4);
+
+        let bound = |frame_type, bound, end| peers.bound(2, frame_type, &bound, end);
+        assert_eq!(bound(FrameType::Groups, FrameBound::Preceding(1), false), 0);
+        assert_eq!(bound(FrameType::Groups, FrameBound::Following(1), true), 5);
+        assert_eq!(bound(FrameType::Rows, FrameBound::Following(1), true), 4);
+        assert_eq!(bound(FrameType::Range, FrameBound::CurrentRow, false), 2);
+
+        assert_eq!(peers.excluding(0, 0..5, FrameExclusion::Ties), vec![0, 2, 3, 4]);
+        assert_eq!(peers.excluding(0, 0..5, FrameExclusion::Group), vec![2, 3, 4]);
+        assert_eq!(
+            (0..7).map(|i| ntile(i, 7, 3)).collect::<Vec<_>>(),
+            vec![1, 1, 1, 2, 2, 3, 3]
+        );
+    }
+
     #[test]
     fn test_window_frame() {
         let frame = WindowFrame::sliding(3);
@@ -0,0 +1,1595 @@
+// Catalog GraphQL Schema
+//
+// A strongly typed GraphQL schema generated from the catalog. Every table
+// becomes an object type with one field per column, a filter and an order
+// input type, and a cursor-paginated connection. Foreign keys become fields
+// leading from a row to the row it references and back to the rows that
+// reference it; those rows are read with one query per page, not per row.
+// Tables, columns and relations whose names are not valid GraphQL names, or
+// whose generated names are already taken, are left out.
+//
+// The schema is rebuilt on first use after DDL or a new constraint changes
+// the catalog or constraint version.
+//
+// Every statement carries the caller's row-level security predicates:
+// reads, updates and deletes only reach the rows the caller may see, and an
+// inserted row must satisfy them, with columns left to their defaults
+// counting as NULL. Statements are checked against the database's SQL
+// firewall as the caller's user before they run.
+
+use super::complexity::AuthorizationContext;
+use crate::catalog::{is_system_table, DataType, Schema as TableSchema};
+use crate::common::Value;
+use crate::constraints::ForeignKey;
+use crate::database::Database;
+use crate::error::DbError;
+use crate::execution::{Executor, QueryResult};
+use crate::parser::SqlParser;
+use crate::security::FgacContext;
+use async_graphql::dynamic::{
+    Enum, EnumItem, Field, FieldFuture, FieldValue, InputObject, InputValue, Object,
+    ObjectAccessor, ResolverContext, Scalar, Schema, TypeRef, ValueAccessor,
+};
+use async_graphql::{Error, Request, Response, ServerError, Value as GqlValue};
+use base64::{engine::general_purpose, Engine as _};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+// Rows in a page when neither `first` nor `last` is given
+const DEFAULT_PAGE_SIZE: usize = 100;
+// Largest `first` or `last` accepted
+const MAX_PAGE_SIZE: usize = 1000;
+// Deepest selection accepted; relations can nest without end
+const MAX_DEPTH: usize = 16;
+
+// Scalars beyond the built-in ones that columns map to
+const CUSTOM_SCALARS: [&str; 6] = ["BigInt", "Date", "DateTime", "Decimal", "Interval", "UUID"];
+
+// Scalars with a `{Scalar}Comparison` filter input
+const COMPARABLE_SCALARS: [&str; 10] = [
+    TypeRef::INT,
+    TypeRef::FLOAT,
+    TypeRef::STRING,
+    TypeRef::BOOLEAN,
+    "BigInt",
+    "Date",
+    "DateTime",
+    "Decimal",
+    "Interval",
+    "UUID",
+];
+
+// Comparison input fields and the SQL operators they stand for
+const COMPARISONS: [(&str, &str); 6] = [
+    ("eq", "="),
+    ("ne", "<>"),
+    ("lt", "<"),
+    ("lte", "<="),
+    ("gt", ">"),
+    ("gte", ">="),
+];
+
+// Filter fields that combine filters rather than name a column
+const FILTER_COMBINATORS: [&str; 3] = ["and", "or", "not"];
+
+/// GraphQL schema over the tables of a database, kept in step with its
+/// catalog and foreign keys
+pub struct CatalogSchema {
+    database: Arc<Database>,
+    // Schema and the catalog and constraint versions it was built from
+    built: RwLock<Option<((u64, u64), Schema)>>,
+}
+
+impl CatalogSchema {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            database,
+            built: RwLock::new(None),
+        }
+    }
+
+    /// The schema for the current catalog, rebuilt if it changed since the
+    /// last call
+    pub fn schema(&self) -> Result<Schema, DbError> {
+        // Read before building, so a change made meanwhile triggers another
+        // rebuild
+        let version = (
+            self.database.catalog().version(),
+            self.database.constraints().version(),
+        );
+        if let Some((built, schema)) = self.built.read().as_ref() {
+            if *built == version {
+                return Ok(schema.clone());
+            }
+        }
+        let schema = build_schema(&self.database, version.0)?;
+        *self.built.write() = Some((version, schema.clone()));
+        Ok(schema)
+    }
+
+    /// Run a request on behalf of the caller `auth` describes
+    pub async fn execute(
+        &self,
+        request: impl Into<Request>,
+        auth: Arc<AuthorizationContext>,
+    ) -> Response {
+        match self.schema() {
+            Ok(schema) => schema.execute(request.into().data(auth)).await,
+            Err(e) => Response::from_errors(vec![ServerError::new(e.to_string(), None)]),
+        }
+    }
+}
+
+// A table as the schema exposes it
+struct TableType {
+    table: String,
+    // Object type name, the table name in PascalCase
+    type_name: String,
+    columns: Vec<ColumnField>,
+    // Position in `columns` of the primary key
+    primary_key: Option<usize>,
+    relations: Vec<Relation>,
+}
+
+// A column exposed as a field of the same name
+struct ColumnField {
+    name: String,
+    // Position of the column in the table's rows
+    index: usize,
+    data_type: DataType,
+    nullable: bool,
+    has_default: bool,
+}
+
+// One side of a foreign key, as a field of the table on that side
+struct Relation {
+    field: String,
+    // Positions in `columns` of this table's and the other table's key
+    columns: Vec<usize>,
+    target: String,
+    target_type: String,
+    target_columns: Vec<usize>,
+    // The referenced side: any number of rows reference each row
+    many: bool,
+}
+
+impl TableType {
+    fn new(schema: &TableSchema) -> Option<Self> {
+        if !is_graphql_name(&schema.name) {
+            return None;
+        }
+        let type_name = pascal_case(&schema.name);
+        if !is_graphql_name(&type_name) {
+            return None;
+        }
+        let columns: Vec<ColumnField> = schema
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| is_graphql_name(&column.name))
+            .map(|(index, column)| ColumnField {
+                name: column.name.clone(),
+                index,
+                data_type: column.data_type.clone(),
+                nullable: column.nullable,
+                has_default: column.default.is_some(),
+            })
+            .collect();
+        if columns.is_empty() {
+            return None;
+        }
+        let primary_key = schema
+            .primary_key
+            .as_ref()
+            .and_then(|key| columns.iter().position(|column| column.name == *key));
+        Some(Self {
+            table: schema.name.clone(),
+            type_name,
+            columns,
+            primary_key,
+            relations: Vec::new(),
+        })
+    }
+
+    // Type names, and root fields prefixed by their root type, the table
+    // takes up in the schema
+    fn schema_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = [
+            "",
+            "Filter",
+            "OrderBy",
+            "Connection",
+            "Edge",
+            "Input",
+            "Patch",
+        ]
+        .iter()
+        .map(|suffix| format!("{}{}", self.type_name, suffix))
+        .collect();
+        names.push(format!("Query.{}", self.table));
+        names.push(format!("Query.{}ByPk", self.table));
+        names
+    }
+
+    fn column(&self, name: &str) -> Result<&ColumnField, Error> {
+        self.columns
+            .iter()
+            .find(|column| column.name == name)
+            .ok_or_else(|| Error::new(format!("Unknown column {} of {}", name, self.table)))
+    }
+
+    fn positions(&self, names: &[String]) -> Option<Vec<usize>> {
+        names
+            .iter()
+            .map(|name| self.columns.iter().position(|column| column.name == *name))
+            .collect()
+    }
+
+    // Columns a filter can compare; list columns can't be
+    fn comparable_columns(&self) -> impl Iterator<Item = &ColumnField> {
+        self.orderable_columns()
+            .filter(|column| !FILTER_COMBINATORS.contains(&column.name.as_str()))
+    }
+
+    fn orderable_columns(&self) -> impl Iterator<Item = &ColumnField> {
+        self.columns
+            .iter()
+            .filter(|column| !matches!(column.data_type, DataType::Vector(_) | DataType::Array(_)))
+    }
+
+    // Values of the columns at `positions` in `row`, unless one is NULL
+    fn key(&self, positions: &[usize], row: &[Value]) -> Option<Vec<Value>> {
+        positions
+            .iter()
+            .map(|&position| match &row[self.columns[position].index] {
+                Value::Null => None,
+                value => Some(value.clone()),
+            })
+            .collect()
+    }
+
+    fn add_relation(&mut self, relation: Relation) {
+        let taken = self
+            .columns
+            .iter()
+            .any(|column| column.name == relation.field)
+            || self
+                .relations
+                .iter()
+                .any(|other| other.field == relation.field);
+        if !taken {
+            self.relations.push(relation);
+        }
+    }
+}
+
+// Whether `name` can name a GraphQL type or field as it is
+fn is_graphql_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        && !name.starts_with("__")
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+// Turn foreign keys between exposed columns into relation fields on both
+// tables: `{referenced}By{Columns}` leads to the referenced row and
+// `{table}By{Columns}` to the referencing rows
+fn add_relations(tables: &mut BTreeMap<String, TableType>, foreign_keys: &[ForeignKey]) {
+    for fk in foreign_keys {
+        let (Some(table), Some(referenced)) =
+            (tables.get(&fk.table), tables.get(&fk.referenced_table))
+        else {
+            continue;
+        };
+        let (Some(columns), Some(referenced_columns)) = (
+            table.positions(&fk.columns),
+            referenced.positions(&fk.referenced_columns),
+        ) else {
+            continue;
+        };
+        if columns.is_empty() || columns.len() != referenced_columns.len() {
+            continue;
+        }
+
+        let by: String = fk
+            .columns
+            .iter()
+            .map(|column| pascal_case(column))
+            .collect();
+        let forward = Relation {
+            field: format!("{}By{}", fk.referenced_table, by),
+            columns: columns.clone(),
+            target: referenced.table.clone(),
+            target_type: referenced.type_name.clone(),
+            target_columns: referenced_columns.clone(),
+            many: false,
+        };
+        let reverse = Relation {
+            field: format!("{}By{}", fk.table, by),
+            columns: referenced_columns,
+            target: table.table.clone(),
+            target_type: table.type_name.clone(),
+            target_columns: columns,
+            many: true,
+        };
+        if let Some(table) = tables.get_mut(&fk.table) {
+            table.add_relation(forward);
+        }
+        if let Some(referenced) = tables.get_mut(&fk.referenced_table) {
+            referenced.add_relation(reverse);
+        }
+    }
+}
+
+// What resolvers read from, shared through the schema's data
+struct Source {
+    database: Arc<Database>,
+    catalog_version: u64,
+    tables: BTreeMap<String, Arc<TableType>>,
+}
+
+impl Source {
+    fn table(&self, name: &str) -> Result<&Arc<TableType>, DbError> {
+        self.tables
+            .get(name)
+            .ok_or_else(|| DbError::NotFound(format!("Table {} is not in the schema", name)))
+    }
+
+    // `statement` on `table` with the caller's row-level security predicates
+    // added to the WHERE clause it ends with
+    fn secured(
+        &self,
+        statement: String,
+        table: &str,
+        auth: &AuthorizationContext,
+    ) -> Result<String, DbError> {
+        self.database.security().fgac.inject_predicates(
+            &statement,
+            &table.to_string(),
+            &security_context(auth),
+        )
+    }
+
+    // `SELECT {what}` of the rows of `table` matching `condition` that the
+    // caller may read
+    fn readable(
+        &self,
+        what: &str,
+        table: &TableType,
+        condition: &str,
+        auth: &AuthorizationContext,
+    ) -> Result<String, DbError> {
+        if !auth.can_read(&table.table)? {
+            return Err(DbError::PermissionDenied(format!(
+                "Cannot read table {}",
+                table.table
+            )));
+        }
+        self.secured(
+            format!("SELECT {} FROM {} WHERE ({})", what, table.table, condition),
+            &table.table,
+            auth,
+        )
+    }
+
+    // Run `sql`, its `$n` parameters bound to `params` as their types, once
+    // the SQL firewall lets the caller run it
+    fn run(
+        &self,
+        executor: &Executor,
+        sql: &str,
+        params: Vec<(Value, DataType)>,
+        auth: &AuthorizationContext,
+    ) -> Result<QueryResult, DbError> {
+        let statement = SqlParser::new()
+            .parse(sql)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::SqlParse(format!("Empty statement: {}", sql)))?;
+        self.database
+            .firewall()
+            .check(&auth.firewall_identity(), sql)?;
+        let (values, types): (Vec<Value>, Vec<DataType>) = params.into_iter().unzip();
+        let mut prepared = executor.prepare(statement, types.into_iter().map(Some).collect())?;
+        executor.execute_prepared(&mut prepared, values)
+    }
+}
+
+// Row-level security context of the caller
+fn security_context(auth: &AuthorizationContext) -> FgacContext {
+    FgacContext {
+        user_id: auth.user_id().to_string(),
+        roles: auth.roles().clone(),
+        session_attributes: HashMap::new(),
+        ip_address: None,
+        timestamp: chrono::Utc::now().timestamp(),
+        app_context: HashMap::new(),
+    }
+}
+
+
+// Rows read together, such as one page of a connection
+struct Batch {
+    table: Arc<TableType>,
+    rows: Vec<Vec<Value>>,
+    // Position of the first row among all rows the query selects
+    offset: usize,
+    // Rows related through each relation field, read on first use
+    related: Mutex<HashMap<String, Arc<Related>>>,
+}
+
+// Rows related to a batch, by the values of their relation key
+struct Related {
+    batch: Arc<Batch>,
+    by_key: HashMap<Vec<Value>, Vec<usize>>,
+}
+
+// A row of a batch, the value of a table's object type and of its edges
+#[derive(Clone)]
+struct Row {
+    batch: Arc<Batch>,
+    index: usize,
+}
+
+// A page of a connection, the value of its connection and page info types
+#[derive(Clone)]
+struct Page {
+    batch: Arc<Batch>,
+    // Rows the query selects across all pages
+    total: usize,
+}
+
+impl Batch {
+    fn new(table: Arc<TableType>, rows: Vec<Vec<Value>>, offset: usize) -> Arc<Self> {
+        Arc::new(Self {
+            table,
+            rows,
+            offset,
+            related: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn rows(self: &Arc<Self>) -> impl Iterator<Item = Row> + '_ {
+        (0..self.rows.len()).map(move |index| Row {
+            batch: self.clone(),
+            index,
+        })
+    }
+
+    // Rows related to those of this batch through `relation`, read with a
+    // single query the first time any row asks for them
+    fn related(
+        &self,
+        relation: &Relation,
+        source: &Source,
+        auth: &AuthorizationContext,
+    ) -> Result<Arc<Related>, DbError> {
+        let mut loaded = self.related.lock();
+        if let Some(related) = loaded.get(&relation.field) {
+            return Ok(related.clone());
+        }
+
+        let target = source.table(&relation.target)?;
+        let keys: HashSet<Vec<Value>> = self
+            .rows
+            .iter()
+            .filter_map(|row| self.table.key(&relation.columns, row))
+            .collect();
+        let rows = if keys.is_empty() {
+            Vec::new()
+        } else {
+            let mut params = Vec::new();
+            let condition = key_condition(target, &relation.target_columns, keys, &mut params);
+            let sql = source.readable("*", target, &condition, auth)?;
+            source.run(&source.database.executor(), &sql, params, auth)?.rows
+        };
+
+        let mut by_key: HashMap<Vec<Value>, Vec<usize>> = HashMap::new();
+        for (index, row) in rows.iter().enumerate() {
+            if let Some(key) = target.key(&relation.target_columns, row) {
+                by_key.entry(key).or_default().push(index);
+            }
+        }
+        let related = Arc::new(Related {
+            batch: Batch::new(target.clone(), rows, 0),
+            by_key,
+        });
+        loaded.insert(relation.field.clone(), related.clone());
+        Ok(related)
+    }
+}
+
+impl Row {
+    fn values(&self) -> &[Value] {
+        &self.batch.rows[self.index]
+    }
+
+    fn cursor(&self) -> String {
+        general_purpose::STANDARD.encode(format!("cursor:{}", self.batch.offset + self.index))
+    }
+}
+
+fn decode_cursor(cursor: &str) -> Result<usize, Error> {
+    general_purpose::STANDARD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|text| text.strip_prefix("cursor:")?.parse().ok())
+        .ok_or_else(|| Error::new(format!("Invalid cursor {}", cursor)))
+}
+
+// Append `value` to `params` as a parameter of `data_type`, returning its
+// placeholder
+fn bind(value: Value, data_type: &DataType, params: &mut Vec<(Value, DataType)>) -> String {
+    params.push((value, data_type.clone()));
+    format!("${}", params.len())
+}
+
+fn bind_input(
+    value: ValueAccessor<'_>,
+    data_type: &DataType,
+    params: &mut Vec<(Value, DataType)>,
+) -> Result<String, Error> {
+    let json = value.as_value().clone().into_json()?;
+    Ok(bind(Value::from_json(&json), data_type, params))
+}
+
+fn output_value(value: &Value) -> Result<GqlValue, Error> {
+    Ok(GqlValue::from_json(value.to_json())?)
+}
+
+// Condition matching the rows of `table` whose columns at `positions` equal
+// one of `keys`
+fn key_condition(
+    table: &TableType,
+    positions: &[usize],
+    keys: HashSet<Vec<Value>>,
+    params: &mut Vec<(Value, DataType)>,
+) -> String {
+    if let [position] = positions {
+        let column = &table.columns[*position];
+        let list: Vec<String> = keys
+            .into_iter()
+            .flatten()
+            .map(|value| bind(value, &column.data_type, params))
+            .collect();
+        return format!("{} IN ({})", column.name, list.join(", "));
+    }
+    keys.into_iter()
+        .map(|key| {
+            let equalities: Vec<String> = positions
+                .iter()
+                .zip(key)
+                .map(|(&position, value)| {
+                    let column = &table.columns[position];
+                    format!(
+                        "{} = {}",
+                        column.name,
+                        bind(value, &column.data_type, params)
+                    )
+                })
+                .collect();
+            format!("({})", equalities.join(" AND "))
+        })
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+// Argument `name`, unless absent or null
+fn arg<'b>(args: &'b ObjectAccessor<'_>, name: &str) -> Option<ValueAccessor<'b>> {
+    args.get(name).filter(|value| !value.is_null())
+}
+
+// SQL condition a `{Table}Filter` value stands for
+fn filter_condition(
+    table: &TableType,
+    filter: &ObjectAccessor<'_>,
+    params: &mut Vec<(Value, DataType)>,
+) -> Result<String, Error> {
+    let mut conditions = Vec::new();
+    for (name, value) in filter.iter() {
+        if value.is_null() {
+            continue;
+        }
+        match name.as_str() {
+            combinator @ ("and" | "or") => {
+                let parts = value
+                    .list()?
+                    .iter()
+                    .map(|filter| filter_condition(table, &filter.object()?, params))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let (joiner, empty) = match combinator {
+                    "and" => (" AND ", "TRUE"),
+                    _ => (" OR ", "FALSE"),
+                };
+                conditions.push(if parts.is_empty() {
+                    empty.to_string()
+                } else {
+                    format!("({})", parts.join(joiner))
+                });
+            }
+            "not" => conditions.push(format!(
+                "NOT ({})",
+                filter_condition(table, &value.object()?, params)?
+            )),
+            name => {
+                let column = table.column(name)?;
+                for (op, operand) in value.object()?.iter() {
+                    if operand.is_null() {
+                        continue;
+                    }
+                    conditions.push(match op.as_str() {
+                        "isNull" => format!(
+                            "{} IS {}NULL",
+                            column.name,
+                            if operand.boolean()? { "" } else { "NOT " }
+                        ),
+                        "in" => {
+                            let list = operand
+                                .list()?
+                                .iter()
+                                .map(|item| bind_input(item, &column.data_type, params))
+                                .collect::<Result<Vec<_>, Error>>()?;
+                            match list.is_empty() {
+                                true => "FALSE".to_string(),
+                                false => format!("{} IN ({})", column.name, list.join(", ")),
+                            }
+                        }
+                        "like" => format!(
+                            "{} LIKE {}",
+                            column.name,
+                            bind_input(operand, &DataType::Text, params)?
+                        ),
+                        op => {
+                            let (_, symbol) = COMPARISONS
+                                .iter()
+                                .find(|(name, _)| *name == op)
+                                .ok_or_else(|| Error::new(format!("Unknown comparison {}", op)))?;
+                            format!(
+                                "{} {} {}",
+                                column.name,
+                                symbol,
+                                bind_input(operand, &column.data_type, params)?
+                            )
+                        }
+                    });
+                }
+            }
+        }
+    }
+    Ok(match conditions.is_empty() {
+        true => "TRUE".to_string(),
+        false => conditions.join(" AND "),
+    })
+}
+
+// ORDER BY clause for an `orderBy` argument; the primary key breaks ties so
+// that pages never overlap
+fn order_clause(table: &TableType, order_by: Option<ValueAccessor<'_>>) -> Result<String, Error> {
+    let mut keys = Vec::new();
+    let mut ordered = Vec::new();
+    if let Some(order_by) = order_by {
+        for item in order_by.list()?.iter() {
+            for (name, direction) in item.object()?.iter() {
+                if direction.is_null() {
+                    continue;
+                }
+                let column = table.column(name.as_str())?;
+                keys.push(format!("{} {}", column.name, direction.enum_name()?));
+                ordered.push(column.index);
+            }
+        }
+    }
+    if let Some(key) = table.primary_key.map(|position| &table.columns[position]) {
+        if !ordered.contains(&key.index) {
+            keys.push(format!("{} ASC", key.name));
+        }
+    }
+    Ok(match keys.is_empty() {
+        true => String::new(),
+        false => format!(" ORDER BY {}", keys.join(", ")),
+    })
+}
+
+fn page_size(args: &ObjectAccessor<'_>, name: &str) -> Result<Option<usize>, Error> {
+    let Some(value) = arg(args, name) else {
+        return Ok(None);
+    };
+    let size = value.i64()?;
+    if !(0..=MAX_PAGE_SIZE as i64).contains(&size) {
+        return Err(Error::new(format!(
+            "{} must be between 0 and {}",
+            name, MAX_PAGE_SIZE
+        )));
+    }
+    Ok(Some(size as usize))
+}
+
+// Positions start..end, of `total` rows, the pagination arguments select
+fn page_bounds(args: &ObjectAccessor<'_>, total: usize) -> Result<(usize, usize), Error> {
+    let mut end = match arg(args, "before") {
+        Some(cursor) => decode_cursor(cursor.string()?)?.min(total),
+        None => total,
+    };
+    let mut start = match arg(args, "after") {
+        Some(cursor) => (decode_cursor(cursor.string()?)? + 1).min(end),
+        None => 0,
+    };
+    let first = page_size(args, "first")?;
+    let last = page_size(args, "last")?;
+    if let Some(first) = first.or(last.is_none().then_some(DEFAULT_PAGE_SIZE)) {
+        end = end.min(start + first);
+    }
+    if let Some(last) = last {
+        start = start.max(end.saturating_sub(last));
+    }
+    Ok((start, end))
+}
+
+fn connection(ctx: &ResolverContext<'_>, table: &Arc<TableType>) -> Result<Page, Error> {
+    let source = ctx.data::<Arc<Source>>()?;
+    let auth = ctx.data::<Arc<AuthorizationContext>>()?;
+    let mut params = Vec::new();
+    let condition = match arg(&ctx.args, "filter") {
+        Some(filter) => filter_condition(table, &filter.object()?, &mut params)?,
+        None => "TRUE".to_string(),
+    };
+
+    let executor = source.database.executor();
+    let count = source.readable("COUNT(*)", table, &condition, auth)?;
+    let total = match source.run(&executor, &count, params.clone(), auth)?.rows.first() {
+        Some(row) => match row.first() {
+            Some(Value::Integer(count)) => *count as usize,
+            _ => 0,
+        },
+        None => 0,
+    };
+
+    let (start, end) = page_bounds(&ctx.args, total)?;
+    let sql = format!(
+        "{}{} LIMIT {} OFFSET {}",
+        source.readable("*", table, &condition, auth)?,
+        order_clause(table, arg(&ctx.args, "orderBy"))?,
+        end - start,
+        start
+    );
+    let rows = source.run(&executor, &sql, params, auth)?.rows;
+    Ok(Page {
+        batch: Batch::new(table.clone(), rows, start),
+        total,
+    })
+}
+
+fn row_by_key(
+    ctx: &ResolverContext<'_>,
+    table: &Arc<TableType>,
+    key: usize,
+) -> Result<Option<Row>, Error> {
+    let source = ctx.data::<Arc<Source>>()?;
+    let auth = ctx.data::<Arc<AuthorizationContext>>()?;
+    let column = &table.columns[key];
+    let mut params = Vec::new();
+    let condition = format!(
+        "{} = {}",
+        column.name,
+        bind_input(
+            ctx.args.try_get(&column.name)?,
+            &column.data_type,
+            &mut params
+        )?
+    );
+    let sql = source.readable("*", table, &condition, auth)?;
+    let rows = source.run(&source.database.executor(), &sql, params, auth)?.rows;
+    let row = Batch::new(table.clone(), rows, 0).rows().next();
+    Ok(row)
+}
+
+fn insert(ctx: &ResolverContext<'_>, table: &TableType) -> Result<usize, Error> {
+    let source = ctx.data::<Arc<Source>>()?;
+    let auth = ctx.data::<Arc<AuthorizationContext>>()?;
+    if !auth.can_write(&table.table)? {
+        return Err(
+            DbError::PermissionDenied(format!("Cannot write table {}", table.table)).into(),
+        );
+    }
+
+    // Each row is one statement, and all of them one transaction
+    let executor = source.database.executor();
+    let transaction = executor.begin_transaction(None)?;
+    let executor = executor.with_transaction(transaction.clone());
+    let mut inserted = 0;
+    let result =
+        ctx.args
+            .try_get("rows")?
+            .list()?
+            .iter()
+            .try_for_each(|row| -> Result<(), Error> {
+                inserted += insert_row(&executor, source, table, &row.object()?, auth)?;
+                Ok(())
+            });
+    match result {
+        Ok(()) => {
+            transaction.commit()?;
+            Ok(inserted)
+        }
+        Err(e) => {
+            let _ = transaction.rollback();
+            Err(e)
+        }
+    }
+}
+
+fn insert_row(
+    executor: &Executor,
+    source: &Source,
+    table: &TableType,
+    row: &ObjectAccessor<'_>,
+    auth: &AuthorizationContext,
+) -> Result<usize, Error> {
+    let mut names = Vec::new();
+    let mut placeholders = Vec::new();
+    let mut params = Vec::new();
+    for column in &table.columns {
+        if let Some(value) = row.get(&column.name) {
+            names.push(column.name.as_str());
+            placeholders.push(bind_input(value, &column.data_type, &mut params)?);
+        }
+    }
+    if names.is_empty() {
+        return Err(Error::new(format!(
+            "A row inserted into {} needs at least one column",
+            table.table
+        )));
+    }
+
+    // The row as a one-row table, for the security predicates to test
+    let given: HashMap<&str, &String> = names.iter().copied().zip(&placeholders).collect();
+    let values: Vec<String> = table
+        .columns
+        .iter()
+        .map(|column| match given.get(column.name.as_str()) {
+            Some(placeholder) => format!("{} AS {}", placeholder, column.name),
+            None => format!("NULL AS {}", column.name),
+        })
+        .collect();
+    let check = format!(
+        "SELECT 1 FROM (SELECT {}) AS {} WHERE (TRUE)",
+        values.join(", "),
+        table.table
+    );
+    let secured = source.secured(check.clone(), &table.table, auth)?;
+    if secured != check && source.run(executor, &secured, params.clone(), auth)?.rows.is_empty() {
+        return Err(DbError::Security(format!(
+            "Row violates the row-level security policies of {}",
+            table.table
+        ))
+        .into());
+    }
+
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table.table,
+        names.join(", "),
+        placeholders.join(", ")
+    );
+    Ok(source.run(executor, &sql, params, auth)?.rows_affected)
+}
+
+fn update(ctx: &ResolverContext<'_>, table: &TableType) -> Result<usize, Error> {
+    let source = ctx.data::<Arc<Source>>()?;
+    let auth = ctx.data::<Arc<AuthorizationContext>>()?;
+    if !auth.can_write(&table.table)? {
+        return Err(
+            DbError::PermissionDenied(format!("Cannot write table {}", table.table)).into(),
+        );
+    }
+
+    let mut params = Vec::new();
+    let mut assignments = Vec::new();
+    for (name, value) in ctx.args.try_get("set")?.object()?.iter() {
+        let column = table.column(name.as_str())?;
+        assignments.push(format!(
+            "{} = {}",
+            column.name,
+            bind_input(value, &column.data_type, &mut params)?
+        ));
+    }
+    if assignments.is_empty() {
+        return Err(Error::new("set must name at least one column"));
+    }
+    let condition = filter_condition(table, &ctx.args.try_get("filter")?.object()?, &mut params)?;
+    let sql = source.secured(
+        format!(
+            "UPDATE {} SET {} WHERE ({})",
+            table.table,
+            assignments.join(", "),
+            condition
+        ),
+        &table.table,
+        auth,
+    )?;
+    Ok(source.run(&source.database.executor(), &sql, params, auth)?.rows_affected)
+}
+
+fn delete(ctx: &ResolverContext<'_>, table: &TableType) -> Result<usize, Error> {
+    let source = ctx.data::<Arc<Source>>()?;
+    let auth = ctx.data::<Arc<AuthorizationContext>>()?;
+    if !auth.can_delete(&table.table)? {
+        return Err(
+            DbError::PermissionDenied(format!("Cannot delete from table {}", table.table)).into(),
+        );
+    }
+
+    let mut params = Vec::new();
+    let condition = filter_condition(table, &ctx.args.try_get("filter")?.object()?, &mut params)?;
+    let sql = source.secured(
+        format!("DELETE FROM {} WHERE ({})", table.table, condition),
+        &table.table,
+        auth,
+    )?;
+    Ok(source.run(&source.database.executor(), &sql, params, auth)?.rows_affected)
+}
+
+// Scalar of a column's values, or of their elements for list columns
+fn scalar(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Integer => TypeRef::INT,
+        DataType::BigInt => "BigInt",
+        DataType::Float | DataType::Double | DataType::Vector(_) => TypeRef::FLOAT,
+        DataType::Varchar(_) | DataType::Text => TypeRef::STRING,
+        DataType::Boolean => TypeRef::BOOLEAN,
+        DataType::Date => "Date",
+        DataType::Timestamp | DataType::TimestampTz => "DateTime",
+        DataType::Numeric(_) => "Decimal",
+        DataType::Interval => "Interval",
+        DataType::Uuid => "UUID",
+        DataType::Array(element) => scalar(element),
+    }
+}
+
+// GraphQL type of a column's values
+fn value_type(data_type: &DataType, non_null: bool) -> TypeRef {
+    let name = scalar(data_type);
+    match (data_type, non_null) {
+        (DataType::Vector(_), true) => TypeRef::named_nn_list_nn(name),
+        (DataType::Vector(_), false) => TypeRef::named_nn_list(name),
+        (DataType::Array(_), true) => TypeRef::named_list_nn(name),
+        (DataType::Array(_), false) => TypeRef::named_list(name),
+        (_, true) => TypeRef::named_nn(name),
+        (_, false) => TypeRef::named(name),
+    }
+}
+
+fn comparison_type(scalar: &str) -> InputObject {
+    let mut object = InputObject::new(format!("{}Comparison", scalar))
+        .description(format!("Conditions on a {} column", scalar));
+    for (name, _) in COMPARISONS {
+        object = object.field(InputValue::new(name, TypeRef::named(scalar)));
+    }
+    object = object
+        .field(InputValue::new("in", TypeRef::named_nn_list(scalar)))
+        .field(InputValue::new("isNull", TypeRef::named(TypeRef::BOOLEAN)));
+    if scalar == TypeRef::STRING {
+        object = object.field(InputValue::new("like", TypeRef::named(TypeRef::STRING)));
+    }
+    object
+}
+
+fn object_type(table: &TableType) -> Object {
+    let mut object =
+        Object::new(&table.type_name).description(format!("A row of table {}", table.table));
+    for column in &table.columns {
+        let index = column.index;
+        object = object.field(Field::new(
+            &column.name,
+            value_type(&column.data_type, !column.nullable),
+            move |ctx| {
+                FieldFuture::new(async move {
+                    let row = ctx.parent_value.try_downcast_ref::<Row>()?;
+                    Ok(Some(FieldValue::value(output_value(&row.values()[index])?)))
+                })
+            },
+        ));
+    }
+    for relation in &table.relations {
+        let ty = match relation.many {
+            true => TypeRef::named_nn_list_nn(&relation.target_type),
+            false => TypeRef::named(&relation.target_type),
+        };
+        let field = relation.field.clone();
+        object = object.field(Field::new(&relation.field, ty, move |ctx| {
+            let field = field.clone();
+            FieldFuture::new(async move {
+                let row = ctx.parent_value.try_downcast_ref::<Row>()?;
+                let source = ctx.data::<Arc<Source>>()?;
+                let auth = ctx.data::<Arc<AuthorizationContext>>()?;
+                let table = &row.batch.table;
+                let relation = table
+                    .relations
+                    .iter()
+                    .find(|relation| relation.field == field)
+                    .ok_or_else(|| Error::new(format!("Unknown relation {}", field)))?;
+                let related = row.batch.related(relation, source, auth)?;
+                let indexes = table
+                    .key(&relation.columns, row.values())
+                    .and_then(|key| related.by_key.get(&key))
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let mut rows = indexes.iter().map(|&index| {
+                    FieldValue::owned_any(Row {
+                        batch: related.batch.clone(),
+                        index,
+                    })
+                });
+                match relation.many {
+                    true => Ok(Some(FieldValue::list(rows))),
+                    false => Ok(rows.next()),
+                }
+            })
+        }));
+    }
+    object
+}
+
+fn filter_type(table: &TableType) -> InputObject {
+    let name = format!("{}Filter", table.type_name);
+    let mut object =
+        InputObject::new(&name).description(format!("Rows of {} to select", table.table));
+    for column in table.comparable_columns() {
+        object = object.field(InputValue::new(
+            &column.name,
+            TypeRef::named(format!("{}Comparison", scalar(&column.data_type))),
+        ));
+    }
+    object
+        .field(InputValue::new("and", TypeRef::named_nn_list(&name)))
+        .field(InputValue::new("or", TypeRef::named_nn_list(&name)))
+        .field(InputValue::new("not", TypeRef::named(&name)))
+}
+
+fn order_type(table: &TableType) -> Option<InputObject> {
+    let mut columns = table.orderable_columns().peekable();
+    columns.peek()?;
+    let mut object = InputObject::new(format!("{}OrderBy", table.type_name));
+    for column in columns {
+        object = object.field(InputValue::new(
+            &column.name,
+            TypeRef::named("SortDirection"),
+        ));
+    }
+    Some(object)
+}
+
+// `{Table}Input` for inserts, where columns without a value or default are
+// required, or `{Table}Patch` for updates
+fn input_type(table: &TableType, patch: bool) -> InputObject {
+    let mut object = InputObject::new(format!(
+        "{}{}",
+        table.type_name,
+        if patch { "Patch" } else { "Input" }
+    ));
+    for column in &table.columns {
+        let required = !patch && !column.nullable && !column.has_default;
+        object = object.field(InputValue::new(
+            &column.name,
+            value_type(&column.data_type, required),
+        ));
+    }
+    object
+}
+
+// Rows of a page, as both its edges and its nodes
+fn page_rows(ctx: ResolverContext<'_>) -> FieldFuture<'_> {
+    FieldFuture::new(async move {
+        let page = ctx.parent_value.try_downcast_ref::<Page>()?;
+        Ok(Some(FieldValue::list(
+            page.batch.rows().map(FieldValue::owned_any),
+        )))
+    })
+}
+
+fn connection_types(table: &TableType) -> (Object, Object) {
+    let connection = Object::new(format!("{}Connection", table.type_name))
+        .field(Field::new(
+            "edges",
+            TypeRef::named_nn_list_nn(format!("{}Edge", table.type_name)),
+            page_rows,
+        ))
+        .field(Field::new(
+            "nodes",
+            TypeRef::named_nn_list_nn(&table.type_name),
+            page_rows,
+        ))
+        .field(Field::new(
+            "pageInfo",
+            TypeRef::named_nn("PageInfo"),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let page = ctx.parent_value.try_downcast_ref::<Page>()?;
+                    Ok(Some(FieldValue::owned_any(page.clone())))
+                })
+            },
+        ))
+        .field(Field::new(
+            "totalCount",
+            TypeRef::named_nn(TypeRef::INT),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let page = ctx.parent_value.try_downcast_ref::<Page>()?;
+                    Ok(Some(FieldValue::value(page.total as i64)))
+                })
+            },
+        ));
+    let edge = Object::new(format!("{}Edge", table.type_name))
+        .field(Field::new(
+            "cursor",
+            TypeRef::named_nn(TypeRef::STRING),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let row = ctx.parent_value.try_downcast_ref::<Row>()?;
+                    Ok(Some(FieldValue::value(row.cursor())))
+                })
+            },
+        ))
+        .field(Field::new(
+            "node",
+            TypeRef::named_nn(&table.type_name),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let row = ctx.parent_value.try_downcast_ref::<Row>()?;
+                    Ok(Some(FieldValue::owned_any(row.clone())))
+                })
+            },
+        ));
+    (connection, edge)
+}
+
+fn page_info_type() -> Object {
+    let flag = |name: &str, next: bool| {
+        Field::new(name, TypeRef::named_nn(TypeRef::BOOLEAN), move |ctx| {
+            FieldFuture::new(async move {
+                let page = ctx.parent_value.try_downcast_ref::<Page>()?;
+                let end = page.batch.offset + page.batch.rows.len();
+                Ok(Some(FieldValue::value(match next {
+                    true => end < page.total,
+                    false => page.batch.offset > 0,
+                })))
+            })
+        })
+    };
+    let cursor = |name: &str, last: bool| {
+        Field::new(name, TypeRef::named(TypeRef::STRING), move |ctx| {
+            FieldFuture::new(async move {
+                let page = ctx.parent_value.try_downcast_ref::<Page>()?;
+                let mut rows = page.batch.rows();
+                let row = if last { rows.last() } else { rows.next() };
+                Ok(row.map(|row| FieldValue::value(row.cursor())))
+            })
+        })
+    };
+    Object::new("PageInfo")
+        .field(flag("hasNextPage", true))
+        .field(flag("hasPreviousPage", false))
+        .field(cursor("startCursor", false))
+        .field(cursor("endCursor", true))
+}
+
+fn connection_field(table: &Arc<TableType>) -> Field {
+    let resolved = table.clone();
+    let mut field = Field::new(
+        &table.table,
+        TypeRef::named_nn(format!("{}Connection", table.type_name)),
+        move |ctx| {
+            let table = resolved.clone();
+            FieldFuture::new(
+                async move { Ok(Some(FieldValue::owned_any(connection(&ctx, &table)?))) },
+            )
+        },
+    )
+    .description(format!("Rows of table {}", table.table))
+    .argument(InputValue::new(
+        "filter",
+        TypeRef::named(format!("{}Filter", table.type_name)),
+    ))
+    .argument(InputValue::new("first", TypeRef::named(TypeRef::INT)))
+    .argument(InputValue::new("after", TypeRef::named(TypeRef::STRING)))
+    .argument(InputValue::new("last", TypeRef::named(TypeRef::INT)))
+    .argument(InputValue::new("before", TypeRef::named(TypeRef::STRING)));
+    if table.orderable_columns().next().is_some() {
+        field = field.argument(InputValue::new(
+            "orderBy",
+            TypeRef::named_nn_list(format!("{}OrderBy", table.type_name)),
+        ));
+    }
+    field
+}
+
+fn by_key_field(table: &Arc<TableType>, key: usize) -> Field {
+    let resolved = table.clone();
+    let column = &table.columns[key];
+    Field::new(
+        format!("{}ByPk", table.table),
+        TypeRef::named(&table.type_name),
+        move |ctx| {
+            let table = resolved.clone();
+            FieldFuture::new(async move {
+                Ok(row_by_key(&ctx, &table, key)?.map(FieldValue::owned_any))
+            })
+        },
+    )
+    .description(format!(
+        "The row of table {} with the given key",
+        table.table
+    ))
+    .argument(InputValue::new(
+        &column.name,
+        value_type(&column.data_type, true),
+    ))
+}
+
+fn mutation_fields(table: &Arc<TableType>) -> [Field; 3] {
+    let result = TypeRef::named_nn("MutationResult");
+    let (inserted, updated, deleted) = (table.clone(), table.clone(), table.clone());
+    [
+        Field::new(
+            format!("insert{}", table.type_name),
+            result.clone(),
+            move |ctx| {
+                let table = inserted.clone();
+                FieldFuture::new(
+                    async move { Ok(Some(FieldValue::owned_any(insert(&ctx, &table)?))) },
+                )
+            },
+        )
+        .argument(InputValue::new(
+            "rows",
+            TypeRef::named_nn_list_nn(format!("{}Input", table.type_name)),
+        )),
+        Field::new(
+            format!("update{}", table.type_name),
+            result.clone(),
+            move |ctx| {
+                let table = updated.clone();
+                FieldFuture::new(
+                    async move { Ok(Some(FieldValue::owned_any(update(&ctx, &table)?))) },
+                )
+            },
+        )
+        .argument(InputValue::new(
+            "filter",
+            TypeRef::named_nn(format!("{}Filter", table.type_name)),
+        ))
+        .argument(InputValue::new(
+            "set",
+            TypeRef::named_nn(format!("{}Patch", table.type_name)),
+        )),
+        Field::new(format!("delete{}", table.type_name), result, move |ctx| {
+            let table = deleted.clone();
+            FieldFuture::new(async move { Ok(Some(FieldValue::owned_any(delete(&ctx, &table)?))) })
+        })
+        .argument(InputValue::new(
+            "filter",
+            TypeRef::named_nn(format!("{}Filter", table.type_name)),
+        )),
+    ]
+}
+
+fn build_schema(database: &Arc<Database>, catalog_version: u64) -> Result<Schema, DbError> {
+    let catalog = database.catalog();
+    let mut names = catalog.list_tables();
+    names.sort();
+
+    let mut used: HashSet<String> = [
+        "Query",
+        "Mutation",
+        "PageInfo",
+        "SortDirection",
+        "MutationResult",
+        "ID",
+        "Query.catalogVersion",
+    ]
+    .iter()
+    .chain(&COMPARABLE_SCALARS)
+    .map(|name| name.to_string())
+    .chain(
+        COMPARABLE_SCALARS
+            .iter()
+            .map(|name| format!("{}Comparison", name)),
+    )
+    .collect();
+    let mut tables = BTreeMap::new();
+    for name in names {
+        if is_system_table(&name) {
+            continue;
+        }
+        let Some(table) = TableType::new(&catalog.get_table(&name)?) else {
+            continue;
+        };
+        let schema_names = table.schema_names();
+        if schema_names.iter().any(|name| used.contains(name)) {
+            continue;
+        }
+        used.extend(schema_names);
+        tables.insert(name, table);
+    }
+    add_relations(&mut tables, &database.constraints().foreign_keys());
+    let tables: BTreeMap<String, Arc<TableType>> = tables
+        .into_iter()
+        .map(|(name, table)| (name, Arc::new(table)))
+        .collect();
+
+    let mut query = Object::new("Query").field(Field::new(
+        "catalogVersion",
+        TypeRef::named_nn("BigInt"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let source = ctx.data::<Arc<Source>>()?;
+                Ok(Some(FieldValue::value(source.catalog_version)))
+            })
+        },
+    ));
+    let mut mutation = Object::new("Mutation");
+    let mut builder = Schema::build("Query", (!tables.is_empty()).then_some("Mutation"), None);
+    for name in CUSTOM_SCALARS {
+        builder = builder.register(Scalar::new(name));
+    }
+    for name in COMPARABLE_SCALARS {
+        builder = builder.register(comparison_type(name));
+    }
+    builder = builder
+        .register(page_info_type())
+        .register(
+            Enum::new("SortDirection")
+                .item(EnumItem::new("ASC"))
+                .item(EnumItem::new("DESC")),
+        )
+        .register(Object::new("MutationResult").field(Field::new(
+            "affectedRows",
+            TypeRef::named_nn(TypeRef::INT),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let affected = ctx.parent_value.try_downcast_ref::<usize>()?;
+                    Ok(Some(FieldValue::value(*affected as i64)))
+                })
+            },
+        )));
+
+    for table in tables.values() {
+        let (connection, edge) = connection_types(table);
+        builder = builder
+            .register(object_type(table))
+            .register(filter_type(table))
+            .register(input_type(table, false))
+            .register(input_type(table, true))
+            .register(connection)
+            .register(edge);
+        if let Some(order) = order_type(table) {
+            builder = builder.register(order);
+        }
+        query = query.field(connection_field(table));
+        if let Some(key) = table.primary_key {
+            query = query.field(by_key_field(table, key));
+        }
+        for field in mutation_fields(table) {
+            mutation = mutation.field(field);
+        }
+    }
+    if !tables.is_empty() {
+        builder = builder.register(mutation);
+    }
+
+    builder
+        .register(query)
+        .data(Arc::new(Source {
+            database: database.clone(),
+            catalog_version,
+            tables,
+        }))
+        .limit_depth(MAX_DEPTH)
+        .finish()
+        .map_err(|e| DbError::Internal(format!("Invalid catalog GraphQL schema: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_config;
+    use crate::security::fgac::{PolicyType, RowLevelPolicy};
+    use serde_json::json;
+    use std::path::Path;
+
+    fn sql(database: &Database, sql: &str) -> crate::Result<QueryResult> {
+        database
+            .executor()
+            .execute(SqlParser::new().parse(sql)?.remove(0))
+    }
+
+    async fn graphql(
+        schema: &CatalogSchema,
+        auth: &Arc<AuthorizationContext>,
+        query: &str,
+    ) -> serde_json::Value {
+        let response = schema.execute(query, auth.clone()).await;
+        assert!(
+            response.errors.is_empty(),
+            "{}: {:?}",
+            query,
+            response.errors
+        );
+        response.data.into_json().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_catalog_schema() -> crate::Result<()> {
+        let config = test_config("catalog-graphql");
+        let database = Database::open(config.clone()).await?;
+        sql(
+            &database,
+            "CREATE TABLE users (id INT NOT NULL, name VARCHAR(20))",
+        )?;
+        sql(
+            &database,
+            "CREATE TABLE orders (id INT NOT NULL, user_id INT, total DOUBLE,
+                CONSTRAINT orders_user FOREIGN KEY (user_id) REFERENCES users (id))",
+        )?;
+        let users = database.catalog().get_table("users")?;
+        database
+            .catalog()
+            .alter_table(users.with_primary_key("id".to_string()))?;
+
+        let schema = CatalogSchema::new(database.clone());
+        let admin = Arc::new(AuthorizationContext::new(
+            "admin".to_string(),
+            vec!["admin".to_string()],
+            vec![],
+        ));
+        let inserted = graphql(
+            &schema,
+            &admin,
+            r#"mutation {
+                insertUsers(rows: [
+                    {id: 1, name: "ann"}, {id: 2, name: "bob"}, {id: 3, name: "cy"}
+                ]) { affectedRows }
+                insertOrders(rows: [
+                    {id: 1, user_id: 1, total: 5.0},
+                    {id: 2, user_id: 1, total: 20.0},
+                    {id: 3, user_id: 3, total: 7.5}
+                ]) { affectedRows }
+            }"#,
+        )
+        .await;
+        assert_eq!(
+            inserted,
+            json!({"insertUsers": {"affectedRows": 3}, "insertOrders": {"affectedRows": 3}})
+        );
+
+        // Pages follow the primary key; relations come back per row
+        let page = graphql(
+            &schema,
+            &admin,
+            "{ users(first: 2) { totalCount pageInfo { hasNextPage endCursor } \
+             nodes { id name ordersByUserId { id } } } }",
+        )
+        .await;
+        assert_eq!(page["users"]["totalCount"], json!(3));
+        assert_eq!(page["users"]["pageInfo"]["hasNextPage"], json!(true));
+        assert_eq!(
+            page["users"]["nodes"],
+            json!([
+                {"id": 1, "name": "ann", "ordersByUserId": [{"id": 1}, {"id": 2}]},
+                {"id": 2, "name": "bob", "ordersByUserId": []},
+            ])
+        );
+        let cursor = page["users"]["pageInfo"]["endCursor"].as_str().unwrap();
+        let next = graphql(
+            &schema,
+            &admin,
+            &format!(
+                "{{ users(first: 2, after: \"{}\") \
+                 {{ pageInfo {{ hasNextPage hasPreviousPage }} nodes {{ id }} }} }}",
+                cursor
+            ),
+        )
+        .await;
+        assert_eq!(
+            next["users"],
+            json!({
+                "pageInfo": {"hasNextPage": false, "hasPreviousPage": true},
+                "nodes": [{"id": 3}],
+            })
+        );
+
+        let filtered = graphql(
+            &schema,
+            &admin,
+            "{ orders(filter: {or: [{total: {gt: 10}}, {user_id: {eq: 3}}]}, \
+             orderBy: [{total: DESC}]) { nodes { id usersByUserId { name } } } \
+             usersByPk(id: 2) { name } }",
+        )
+        .await;
+        assert_eq!(
+            filtered,
+            json!({
+                "orders": {"nodes": [
+                    {"id": 2, "usersByUserId": {"name": "ann"}},
+                    {"id": 3, "usersByUserId": {"name": "cy"}},
+                ]},
+                "usersByPk": {"name": "bob"},
+            })
+        );
+
+        let changed = graphql(
+            &schema,
+            &admin,
+            r#"mutation {
+                updateUsers(filter: {name: {like: "b%"}}, set: {name: "bo"}) { affectedRows }
+                deleteOrders(filter: {total: {lt: 10}}) { affectedRows }
+            }"#,
+        )
+        .await;
+        assert_eq!(
+            changed,
+            json!({"updateUsers": {"affectedRows": 1}, "deleteOrders": {"affectedRows": 2}})
+        );
+
+        // Row-level security narrows reads and rejects rows outside it
+        database.security().fgac.add_row_policy(RowLevelPolicy {
+            id: "p1".to_string(),
+            name: "low_ids".to_string(),
+            table_id: "users".to_string(),
+            policy_type: PolicyType::Permissive,
+            predicate: "id < 3".to_string(),
+            principals: vec!["guest".to_string()],
+            enabled: true,
+            priority: 0,
+            created_at: 0,
+            updated_at: 0,
+            description: None,
+        })?;
+        let guest = Arc::new(AuthorizationContext::new(
+            "guest".to_string(),
+            vec!["guest".to_string()],
+            vec!["read:*".to_string(), "write:*".to_string()],
+        ));
+        let visible = graphql(&schema, &guest, "{ users { totalCount nodes { id } } }").await;
+        assert_eq!(
+            visible["users"],
+            json!({"totalCount": 2, "nodes": [{"id": 1}, {"id": 2}]})
+        );
+        let rejected = schema
+            .execute(
+                r#"mutation { insertUsers(rows: [{id: 7, name: "zed"}]) { affectedRows } }"#,
+                guest.clone(),
+            )
+            .await;
+        assert!(!rejected.errors.is_empty());
+        assert!(sql(&database, "SELECT id FROM users WHERE id = 7")?
+            .rows
+            .is_empty());
+
+        // DDL shows up in the next request
+        sql(&database, "CREATE TABLE items (sku VARCHAR(10))")?;
+        let items = graphql(&schema, &admin, "{ items { totalCount } }").await;
+        assert_eq!(items, json!({"items": {"totalCount": 0}}));
+
+        database.shutdown().await?;
+        let _ = std::fs::remove_dir_all(Path::new(&config.data_dir).parent().unwrap());
+        Ok(())
+    }
+}
@@ -10,6 +10,7 @@
 
 use crate::api::RowType;
 use crate::error::DbError;
+use crate::security::sql_firewall::FirewallIdentity;
 use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextExecute};
 use async_graphql::parser::types::ExecutableDocument;
 use futures_util::Future;
@@ -203,7 +204,7 @@ pub struct RateLimit {
 
 // Authorization context for field-level security
 pub struct AuthorizationContext {
-    _user_id: String,
+    user_id: String,
     roles: HashSet<String>,
     permissions: HashSet<String>,
 }
@@ -211,12 +212,25 @@ pub struct AuthorizationContext {
 impl AuthorizationContext {
     pub fn new(user_id: String, roles: Vec<String>, permissions: Vec<String>) -> Self {
         Self {
-            _user_id: user_id,
+            user_id,
             roles: roles.into_iter().collect(),
             permissions: permissions.into_iter().collect(),
         }
     }
 
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    // Who the SQL firewall checks this caller's statements as
+    pub fn firewall_identity(&self) -> FirewallIdentity {
+        FirewallIdentity::new(self.user_id.clone())
+    }
+
+    pub fn roles(&self) -> &HashSet<String> {
+        &self.roles
+    }
+
     pub fn has_role(&self, role: &str) -> bool {
         self.roles.contains(role)
     }
@@ -4,16 +4,20 @@
 
 use super::complexity::{QueryCache, RateLimiter};
 use super::models::{
-    AggregateInput, AggregateResult, ColumnStatistics, DatabaseSchema, OrderBy, PageInfo,
-    RowConnection, RowType, TableStatistics, TableType, WhereClause,
+    AggregateInput, AggregateResult, ColumnStatistics, DatabaseSchema, FieldValue, OrderBy,
+    PageInfo, RowConnection, RowType, TableStatistics, TableType, WhereClause,
 };
 use super::queries::{QueryPlan, SearchResult};
-use super::types::{BigInt, DateTime, IsolationLevel, Json};
+use super::types::{BigInt, DataType, DateTime, IsolationLevel, Json};
 use crate::api::{
     JoinInput, PersistedQueries, RowChange, RowDeleted, RowInserted, RowUpdated,
     SubscriptionManager, TableChange, TransactionOperation, TransactionResult,
 };
+use crate::common::Value;
+use crate::database::Database;
 use crate::error::DbError;
+use crate::parser::SqlParser;
+use crate::security::sql_firewall::FirewallIdentity;
 use async_graphql::{Result as GqlResult, ID};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -24,6 +28,24 @@ use futures_util;
 // GRAPHQL ENGINE - Core Implementation
 // ============================================================================
 
+// GraphQL type of a SQL value
+fn field_type(value: &Value) -> DataType {
+    match value {
+        Value::Null | Value::Text => DataType::Null,
+        Value::Boolean(_) => DataType::Boolean,
+        Value::Integer(_) => DataType::Integer,
+        Value::Float(_) => DataType::Float,
+        Value::Bytes(_) => DataType::Bytes,
+        Value::Date(_) => DataType::Date,
+        Value::Timestamp(_) | Value::TimestampTz(_) => DataType::Timestamp,
+        Value::Numeric(_) => DataType::Decimal,
+        Value::Uuid(_) => DataType::Uuid,
+        Value::Json(_) => DataType::Json,
+        Value::Array(_) => DataType::Array,
+        _ => DataType::String,
+    }
+}
+
 // Main GraphQL engine that interfaces with the database
 pub struct GraphQLEngine {
     // Would connect to actual database components
@@ -34,6 +56,8 @@ pub struct GraphQLEngine {
     rate_limiter: Arc<RateLimiter>,
     #[allow(dead_code)]
     persisted_queries: Arc<PersistedQueries>,
+    // Database SQL runs against; without one, SQL is rejected
+    database: Option<Arc<Database>>,
 }
 
 impl GraphQLEngine {
@@ -43,9 +67,16 @@ impl GraphQLEngine {
             query_cache: Arc::new(QueryCache::new(1000, 300)),
             rate_limiter: Arc::new(RateLimiter::new()),
             persisted_queries: Arc::new(PersistedQueries::new()),
+            database: None,
         }
     }
 
+    // Run queries against a shared database handle
+    pub fn with_database(mut self, database: Arc<Database>) -> Self {
+        self.database = Some(database);
+        self
+    }
+
     // Schema operations
     pub async fn get_schemas(&self) -> GqlResult<Vec<DatabaseSchema>> {
         // Mock implementation - would query actual catalog
@@ -177,12 +208,61 @@ impl GraphQLEngine {
         Ok(0)
     }
 
+    // Run `sql` after checking it against the database's SQL firewall as
+    // `identity`
     pub async fn execute_sql(
         &self,
-        _sql: &str,
-        _params: Option<Vec<Json>>,
+        sql: &str,
+        params: Option<Vec<Json>>,
+        identity: &FirewallIdentity,
     ) -> Result<(Vec<RowType>, i64), DbError> {
-        Ok((vec![], 0))
+        let database = self.database.as_ref().ok_or_else(|| {
+            DbError::NotImplemented("GraphQL is not attached to a database".to_string())
+        })?;
+        if params.is_some_and(|params| !params.is_empty()) {
+            return Err(DbError::NotImplemented(
+                "SQL parameters are not supported".to_string(),
+            ));
+        }
+
+        let statements = SqlParser::new().parse(sql)?;
+        database.firewall().check(identity, sql)?;
+        let statement = statements
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::InvalidInput("No SQL statements".to_string()))?;
+        let result = database.executor().execute(statement)?;
+
+        let now = DateTime::now();
+        let rows = result
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| RowType {
+                id: ID(i.to_string()),
+                table_name: String::new(),
+                fields: result
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| {
+                        let field = FieldValue {
+                            column_name: column.clone(),
+                            value: Json(value.to_json()),
+                            data_type: field_type(value),
+                        };
+                        (column.clone(), field)
+                    })
+                    .collect(),
+                created_at: now.clone(),
+                updated_at: None,
+                created_by: String::new(),
+                updated_by: None,
+                version: 1,
+            })
+            .collect();
+
+        Ok((rows, result.rows_affected as i64))
     }
 
     pub async fn search(
@@ -3,6 +3,7 @@
 // Comprehensive GraphQL API implementation for RustyDB
 
 pub mod builders;
+pub mod catalog_schema;
 pub mod cluster_subscriptions;
 pub mod complexity;
 pub mod config_queries;
@@ -30,6 +31,7 @@ pub mod websocket_transport;
 // Re-export main types and functions
 // Note: types::DatabaseSchema is shadowed by models::DatabaseSchema
 pub use builders::*;
+pub use catalog_schema::CatalogSchema;
 pub use complexity::*;
 pub use engine::*;
 pub use helpers::*;
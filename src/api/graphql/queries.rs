@@ -198,7 +198,10 @@ impl QueryRoot {
         let start = Instant::now();
         let engine = ctx.data::<Arc<GraphQLEngine>>()?;
 
-        match engine.execute_sql(&sql, params).await {
+        match engine
+            .execute_sql(&sql, params, &auth.firewall_identity())
+            .await
+        {
             Ok((rows, total_count)) => {
                 let execution_time = start.elapsed().as_secs_f64() * 1000.0;
                 Ok(QueryResult::Success(QuerySuccess {
@@ -5,33 +5,27 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     Json as AxumJson,
 };
-use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::SystemTime;
 use uuid::Uuid;
 
-use super::{CATALOG, SQL_PARSER, TXN_MANAGER};
+use super::{
+    api_identity, new_executor, parse_checked, result_columns, result_rows, sql_error,
+    table_store, CATALOG, TRANSACTIONS,
+};
 use crate::api::rest::types::*;
 use crate::catalog::{Column, DataType, Schema};
+use crate::common::Value;
 use crate::error::DbError;
-use crate::execution::Executor;
+use crate::execution::Session;
+use crate::transaction::IsolationLevel;
 
 /// Helper function to format DataType enum as a string for display
 fn format_data_type(data_type: &DataType) -> String {
-    match data_type {
-        DataType::Integer => "INTEGER".to_string(),
-        DataType::BigInt => "BIGINT".to_string(),
-        DataType::Float => "FLOAT".to_string(),
-        DataType::Double => "DOUBLE".to_string(),
-        DataType::Varchar(size) => format!("VARCHAR({})", size),
-        DataType::Text => "TEXT".to_string(),
-        DataType::Boolean => "BOOLEAN".to_string(),
-        DataType::Date => "DATE".to_string(),
-        DataType::Timestamp => "TIMESTAMP".to_string(),
-    }
+    data_type.to_string()
 }
 
 // Execute a SQL query
@@ -49,6 +43,7 @@ fn format_data_type(data_type: &DataType) -> String {
 )]
 pub async fn execute_query(
     State(state): State<Arc<ApiState>>,
+    headers: HeaderMap,
     AxumJson(request): AxumJson<QueryRequest>,
 ) -> ApiResult<AxumJson<QueryResponse>> {
     let query_id = Uuid::new_v4();
@@ -74,10 +69,8 @@ pub async fn execute_query(
         );
     }
 
-    // Parse SQL
-    let stmts = SQL_PARSER
-        .parse(&request.sql)
-        .map_err(|e| ApiError::new("SQL_PARSE_ERROR", &e.to_string()))?;
+    // Parse SQL and check it against the SQL firewall
+    let stmts = parse_checked(&request.sql, &api_identity(&headers)).map_err(sql_error)?;
 
     // Get first statement
     let stmt = stmts
@@ -85,15 +78,43 @@ pub async fn execute_query(
         .next()
         .ok_or_else(|| ApiError::new("SQL_PARSE_ERROR", "No valid SQL statement found"))?;
 
-    // Execute query
+    // Execute query, in the transaction the request names if any
     let executor = {
         let catalog_guard = CATALOG.read();
         let catalog_snapshot = (*catalog_guard).clone();
-        Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone())
+        new_executor(catalog_snapshot)
     };
-    let result = executor
-        .execute(stmt)
-        .map_err(|e| ApiError::new("EXECUTION_ERROR", &e.to_string()))?;
+    let session = request
+        .transaction_id
+        .map(|TransactionId(id)| transaction_session(id))
+        .transpose()?;
+    // The session lock is released before the next await
+    let result = {
+        let mut session = session.as_ref().map(|session| session.lock());
+        // Parameters are bound as typed values, never spliced into the SQL
+        let result = match (request.params.as_deref(), session.as_deref_mut()) {
+            (Some(params), session) if !params.is_empty() => {
+                let params = params.iter().map(Value::from_json).collect();
+                let executor = match session {
+                    Some(session) => session.executor(&executor),
+                    None => executor,
+                };
+                executor
+                    .prepare(stmt, Vec::new())
+                    .and_then(|mut prepared| executor.execute_prepared(&mut prepared, params))
+            }
+            (_, Some(session)) => session.run(&executor, stmt),
+            (_, None) => executor.execute(stmt),
+        };
+        // A COMMIT or ROLLBACK in the SQL ends the transaction
+        if let (Some(session), Some(TransactionId(id))) = (&session, &request.transaction_id) {
+            if !session.in_transaction() {
+                TRANSACTIONS.write().remove(id);
+            }
+        }
+        result
+    }
+    .map_err(|e| ApiError::new("EXECUTION_ERROR", &e.to_string()))?;
 
     let execution_time = start.elapsed().unwrap_or_default().as_millis() as u64;
 
@@ -104,31 +125,8 @@ pub async fn execute_query(
     }
 
     // Convert QueryResult to QueryResponse
-    let columns_meta: Vec<ColumnMetadata> = result
-        .columns
-        .iter()
-        .map(|name| ColumnMetadata {
-            name: name.clone(),
-            data_type: "TEXT".to_string(),
-            nullable: true,
-            precision: None,
-            scale: None,
-        })
-        .collect();
-
-    let rows: Vec<HashMap<String, serde_json::Value>> = result
-        .rows
-        .iter()
-        .map(|row| {
-            let mut map = HashMap::new();
-            for (i, val) in row.iter().enumerate() {
-                if let Some(col_name) = result.columns.get(i) {
-                    map.insert(col_name.clone(), serde_json::Value::String(val.clone()));
-                }
-            }
-            map
-        })
-        .collect();
+    let columns_meta = result_columns(&result);
+    let rows = result_rows(&result);
 
     let response = QueryResponse {
         query_id: query_id.to_string(),
@@ -158,6 +156,7 @@ pub async fn execute_query(
 )]
 pub async fn execute_batch(
     State(_state): State<Arc<ApiState>>,
+    headers: HeaderMap,
     AxumJson(request): AxumJson<BatchRequest>,
 ) -> ApiResult<AxumJson<BatchResponse>> {
     let batch_id = Uuid::new_v4();
@@ -177,12 +176,13 @@ pub async fn execute_batch(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
+    let identity = api_identity(&headers);
 
     for (index, statement) in request.statements.iter().enumerate() {
         let stmt_start = SystemTime::now();
 
-        let result = match SQL_PARSER.parse(statement) {
+        let result = match parse_checked(statement, &identity) {
             Ok(stmts) => {
                 if let Some(stmt) = stmts.into_iter().next() {
                     executor.execute(stmt)
@@ -192,7 +192,7 @@ pub async fn execute_batch(
                     ))
                 }
             }
-            Err(e) => Err(DbError::SqlParse(e.to_string())),
+            Err(e) => Err(e),
         };
 
         let success = result.is_ok();
@@ -308,15 +308,18 @@ pub async fn create_table(
         .columns
         .iter()
         .map(|col| {
-            let data_type = parse_data_type(&col.data_type);
-            Column {
+            let data_type = col
+                .data_type
+                .parse()
+                .map_err(|e: DbError| ApiError::new("INVALID_INPUT", e.to_string()))?;
+            Ok(Column {
                 name: col.name.clone(),
                 data_type,
                 nullable: col.nullable,
                 default: col.default_value.as_ref().map(|v| v.to_string()),
-            }
+            })
         })
-        .collect();
+        .collect::<ApiResult<_>>()?;
 
     // Create schema
     let schema = if let Some(pk) = &request.primary_key {
@@ -329,10 +332,19 @@ pub async fn create_table(
         Schema::new(name.clone(), columns)
     };
 
-    // Add to catalog
+    // Add to catalog and create the table's row storage
     let catalog = CATALOG.write();
     match catalog.create_table(schema) {
-        Ok(_) => Ok(StatusCode::CREATED),
+        Ok(_) => {
+            if let Err(e) = table_store().reset_table(&name) {
+                let _ = catalog.drop_table(&name);
+                return Err(ApiError::new(
+                    "DATABASE_ERROR",
+                    format!("Failed to create table storage: {}", e),
+                ));
+            }
+            Ok(StatusCode::CREATED)
+        }
         Err(e) => {
             if e.to_string().contains("already exists") {
                 Err(ApiError::new(
@@ -349,32 +361,6 @@ pub async fn create_table(
     }
 }
 
-/// Helper function to parse data type string into DataType enum
-fn parse_data_type(type_str: &str) -> DataType {
-    let upper = type_str.to_uppercase();
-    if upper.starts_with("VARCHAR") {
-        // Extract size from VARCHAR(n)
-        let size = upper
-            .trim_start_matches("VARCHAR")
-            .trim_matches(|c| c == '(' || c == ')' || c == ' ')
-            .parse::<usize>()
-            .unwrap_or(255);
-        DataType::Varchar(size)
-    } else {
-        match upper.as_str() {
-            "INT" | "INTEGER" => DataType::Integer,
-            "BIGINT" => DataType::BigInt,
-            "FLOAT" | "REAL" => DataType::Float,
-            "DOUBLE" | "DOUBLE PRECISION" => DataType::Double,
-            "TEXT" => DataType::Text,
-            "BOOL" | "BOOLEAN" => DataType::Boolean,
-            "DATE" => DataType::Date,
-            "TIMESTAMP" | "DATETIME" => DataType::Timestamp,
-            _ => DataType::Text,
-        }
-    }
-}
-
 /// Update table schema
 #[utoipa::path(
     put,
@@ -402,37 +388,31 @@ pub async fn update_table(
         }
     }
 
-    // Drop and recreate with new schema (simple approach)
-    // A production system would support ALTER TABLE operations
-    let catalog = CATALOG.write();
-
-    // Drop existing table
-    if let Err(e) = catalog.drop_table(&name) {
-        return Err(ApiError::new(
-            "DATABASE_ERROR",
-            format!("Failed to update table: {}", e),
-        ));
-    }
-
-    // Create new schema with updated columns
+    // Replace the table's schema in a single catalog change
     let columns: Vec<Column> = request
         .columns
         .iter()
-        .map(|col| Column {
-            name: col.name.clone(),
-            data_type: parse_data_type(&col.data_type),
-            nullable: col.nullable,
-            default: col.default_value.as_ref().map(|v| v.to_string()),
+        .map(|col| {
+            Ok(Column {
+                name: col.name.clone(),
+                data_type: col
+                    .data_type
+                    .parse()
+                    .map_err(|e: DbError| ApiError::new("INVALID_INPUT", e.to_string()))?,
+                nullable: col.nullable,
+                default: col.default_value.as_ref().map(|v| v.to_string()),
+            })
         })
-        .collect();
+        .collect::<ApiResult<_>>()?;
 
     let schema = Schema::new(name.clone(), columns);
 
-    match catalog.create_table(schema) {
+    let catalog = CATALOG.read();
+    match catalog.alter_table(schema) {
         Ok(_) => Ok(StatusCode::OK),
         Err(e) => Err(ApiError::new(
             "DATABASE_ERROR",
-            format!("Failed to recreate table: {}", e),
+            format!("Failed to update table: {}", e),
         )),
     }
 }
@@ -454,7 +434,18 @@ pub async fn delete_table(
     let catalog = CATALOG.write();
 
     match catalog.drop_table(&name) {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Ok(_) => {
+            let store = table_store();
+            if store.has_table(&name) {
+                store.drop_table(&name).map_err(|e| {
+                    ApiError::new(
+                        "DATABASE_ERROR",
+                        format!("Failed to drop table storage: {}", e),
+                    )
+                })?;
+            }
+            Ok(StatusCode::NO_CONTENT)
+        }
         Err(e) => Err(ApiError::new(
             "NOT_FOUND",
             format!("Table '{}' not found: {}", name, e),
@@ -531,24 +522,29 @@ pub async fn begin_transaction(
     State(_state): State<Arc<ApiState>>,
     AxumJson(request): AxumJson<TransactionRequest>,
 ) -> ApiResult<AxumJson<TransactionResponse>> {
-    // Begin a real transaction using the transaction manager
-    let txn_id = match TXN_MANAGER.begin() {
-        Ok(id) => id,
-        Err(e) => {
-            return Err(ApiError::new(
-                "TRANSACTION_ERROR",
-                format!("Failed to begin transaction: {}", e),
-            ));
-        }
-    };
-
-    let isolation_level = request
+    let isolation = request
         .isolation_level
-        .unwrap_or_else(|| "READ_COMMITTED".to_string());
+        .as_deref()
+        .map(parse_isolation_level)
+        .transpose()?;
+
+    // The transaction lives in a session of its own until commit or rollback
+    let executor = new_executor(CATALOG.read().clone());
+    let mut session = Session::new();
+    let txn_id = session.begin(&executor, isolation).map_err(|e| {
+        ApiError::new(
+            "TRANSACTION_ERROR",
+            format!("Failed to begin transaction: {}", e),
+        )
+    })?;
+    let isolation_level = session.isolation().unwrap_or_default();
+    TRANSACTIONS
+        .write()
+        .insert(txn_id, Arc::new(parking_lot::Mutex::new(session)));
 
     let response = TransactionResponse {
         transaction_id: TransactionId(txn_id),
-        isolation_level,
+        isolation_level: isolation_level.to_string(),
         started_at: SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
@@ -576,16 +572,17 @@ pub async fn commit_transaction(
     State(_state): State<Arc<ApiState>>,
     Path(id): Path<u64>,
 ) -> ApiResult<StatusCode> {
-    // Verify transaction exists
-    if !TXN_MANAGER.is_active(id) {
-        return Err(ApiError::new(
+    // Verify transaction exists; either way it ends here
+    let session = TRANSACTIONS.write().remove(&id).ok_or_else(|| {
+        ApiError::new(
             "NOT_FOUND",
             format!("Transaction {} not found or already completed", id),
-        ));
-    }
+        )
+    })?;
 
     // Commit the transaction
-    match TXN_MANAGER.commit(id) {
+    let result = session.lock().commit();
+    match result {
         Ok(_) => Ok(StatusCode::OK),
         Err(e) => Err(ApiError::new(
             "TRANSACTION_ERROR",
@@ -611,16 +608,17 @@ pub async fn rollback_transaction(
     State(_state): State<Arc<ApiState>>,
     Path(id): Path<u64>,
 ) -> ApiResult<StatusCode> {
-    // Verify transaction exists
-    if !TXN_MANAGER.is_active(id) {
-        return Err(ApiError::new(
+    // Verify transaction exists; either way it ends here
+    let session = TRANSACTIONS.write().remove(&id).ok_or_else(|| {
+        ApiError::new(
             "NOT_FOUND",
             format!("Transaction {} not found or already completed", id),
-        ));
-    }
+        )
+    })?;
 
     // Abort/rollback the transaction
-    match TXN_MANAGER.abort(id) {
+    let result = session.lock().rollback();
+    match result {
         Ok(_) => Ok(StatusCode::OK),
         Err(e) => Err(ApiError::new(
             "TRANSACTION_ERROR",
@@ -628,3 +626,34 @@ pub async fn rollback_transaction(
         )),
     }
 }
+
+// Session of a transaction opened through the API
+fn transaction_session(id: u64) -> ApiResult<Arc<parking_lot::Mutex<Session>>> {
+    TRANSACTIONS.read().get(&id).cloned().ok_or_else(|| {
+        ApiError::new(
+            "NOT_FOUND",
+            format!("Transaction {} not found or already completed", id),
+        )
+    })
+}
+
+// Accepts the SQL spelling ("READ COMMITTED") as well as "READ_COMMITTED"
+fn parse_isolation_level(level: &str) -> ApiResult<IsolationLevel> {
+    let level = level.replace(['_', '-'], " ").to_uppercase();
+    match level
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .as_str()
+    {
+        "READ UNCOMMITTED" => Ok(IsolationLevel::ReadUncommitted),
+        "READ COMMITTED" => Ok(IsolationLevel::ReadCommitted),
+        "REPEATABLE READ" => Ok(IsolationLevel::RepeatableRead),
+        "SERIALIZABLE" => Ok(IsolationLevel::Serializable),
+        "SNAPSHOT" => Ok(IsolationLevel::SnapshotIsolation),
+        _ => Err(ApiError::new(
+            "INVALID_INPUT",
+            format!("Unknown isolation level: {}", level),
+        )),
+    }
+}
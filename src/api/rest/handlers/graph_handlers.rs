@@ -578,5 +578,10 @@ fn value_to_json(value: &Value) -> serde_json::Value {
         Value::Json(j) => j.clone(),
         Value::Array(arr) => serde_json::Value::Array(arr.iter().map(value_to_json).collect()),
         Value::Text => serde_json::Value::Null,
+        Value::Vector(_)
+        | Value::Numeric(_)
+        | Value::Interval(_)
+        | Value::TimestampTz(_)
+        | Value::Uuid(_) => value.to_json(),
     }
 }
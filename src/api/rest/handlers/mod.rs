@@ -69,16 +69,179 @@ mod transaction_ws_handlers;
 mod enterprise_websocket_handlers;
 mod transaction_ws_types;
 
+use crate::api::rest::types::{ApiError, ColumnMetadata};
 use crate::catalog::Catalog;
-use crate::parser::SqlParser;
+use crate::common::Value;
+use crate::constraints::ConstraintManager;
+use crate::database::Database;
+use crate::error::DbError;
+use crate::execution::{Executor, QueryResult, Session};
+use crate::parser::{SqlParser, SqlStatement};
+use crate::security::sql_firewall::{FirewallIdentity, SqlFirewall};
+use crate::storage::TableStore;
 use crate::transaction::TransactionManager;
-use parking_lot::RwLock;
+use axum::http::HeaderMap;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 lazy_static::lazy_static! {
     pub static ref CATALOG: Arc<RwLock<Catalog>> = Arc::new(RwLock::new(Catalog::new()));
-    pub static ref TXN_MANAGER: Arc<TransactionManager> = Arc::new(TransactionManager::new());
+    pub static ref TXN_MANAGER: Arc<TransactionManager> = INSTALLED_TXN_MANAGER
+        .get_or_init(|| Arc::new(TransactionManager::new()))
+        .clone();
     pub static ref SQL_PARSER: SqlParser = SqlParser::new();
+    /// Sessions holding the transactions opened through the API, by
+    /// transaction id
+    pub static ref TRANSACTIONS: RwLock<HashMap<u64, Arc<Mutex<Session>>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Table store shared by all REST/GraphQL/WebSocket executors
+static TABLE_STORE: once_cell::sync::OnceCell<Arc<TableStore>> = once_cell::sync::OnceCell::new();
+
+/// Transaction manager behind `TXN_MANAGER`, when one is installed
+static INSTALLED_TXN_MANAGER: once_cell::sync::OnceCell<Arc<TransactionManager>> =
+    once_cell::sync::OnceCell::new();
+
+/// Constraints of the installed database, enforced by every API executor
+static CONSTRAINTS: once_cell::sync::OnceCell<Arc<ConstraintManager>> =
+    once_cell::sync::OnceCell::new();
+
+/// SQL firewall of the installed database; API statements are not checked
+/// until one is installed
+static FIREWALL: once_cell::sync::OnceCell<Arc<SqlFirewall>> = once_cell::sync::OnceCell::new();
+
+/// Role the SQL firewall checks API statements as; API requests carry no
+/// database user of their own
+pub const API_ROLE: &str = "api";
+
+/// Header naming the client application, the API's `application_name`
+pub const APPLICATION_NAME_HEADER: &str = "x-application-name";
+
+/// Attach the API handlers to a running database
+///
+/// Installs its table store, catalog, transaction manager, constraints and
+/// SQL firewall, so the API sees the same tables and transactions as the
+/// native protocol and its statements pass the same firewall. Must be called
+/// before the first request is served.
+pub fn install_database(database: &Database) -> crate::Result<()> {
+    install_table_store(database.table_store().clone())?;
+    INSTALLED_TXN_MANAGER
+        .set(database.txn_manager().clone())
+        .map_err(|_| DbError::Internal("Transaction manager already initialized".to_string()))?;
+    CONSTRAINTS
+        .set(database.constraints().clone())
+        .map_err(|_| DbError::Internal("Constraints already initialized".to_string()))?;
+    FIREWALL
+        .set(database.firewall().clone())
+        .map_err(|_| DbError::Internal("SQL firewall already initialized".to_string()))?;
+    install_catalog(database.catalog().clone());
+    Ok(())
+}
+
+/// Who the SQL firewall checks a request's statements as: `API_ROLE`, and
+/// the application its `X-Application-Name` header names
+pub fn api_identity(headers: &HeaderMap) -> FirewallIdentity {
+    FirewallIdentity {
+        application: headers
+            .get(APPLICATION_NAME_HEADER)
+            .and_then(|name| name.to_str().ok())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string),
+        ..FirewallIdentity::new(API_ROLE)
+    }
+}
+
+/// Parse SQL received through the API, then check it against the installed
+/// SQL firewall as `identity`
+pub fn parse_checked(sql: &str, identity: &FirewallIdentity) -> crate::Result<Vec<SqlStatement>> {
+    let statements = SQL_PARSER.parse(sql)?;
+    if let Some(firewall) = FIREWALL.get() {
+        firewall.check(identity, sql)?;
+    }
+    Ok(statements)
+}
+
+/// API error for SQL that `parse_checked` rejected
+pub fn sql_error(error: DbError) -> ApiError {
+    match error {
+        DbError::PermissionDenied(message) => ApiError::new("FORBIDDEN", message),
+        error => ApiError::new("SQL_PARSE_ERROR", error.to_string()),
+    }
+}
+
+/// Attach the API handlers to the server's table store
+///
+/// Must be called before the first request is served; otherwise the handlers
+/// fall back to a scratch store that is discarded on exit.
+pub fn install_table_store(store: Arc<TableStore>) -> crate::Result<()> {
+    TABLE_STORE
+        .set(store)
+        .map_err(|_| DbError::Internal("Table store already initialized".to_string()))
+}
+
+/// Attach the API handlers to the server's catalog
+///
+/// Catalog clones share their state, so DDL issued through the API is visible
+/// to the native protocol and vice versa.
+pub fn install_catalog(catalog: Catalog) {
+    *CATALOG.write() = catalog;
+}
+
+/// Get the table store used by the API handlers
+pub fn table_store() -> Arc<TableStore> {
+    TABLE_STORE
+        .get_or_init(|| {
+            Arc::new(TableStore::temporary().expect("failed to create scratch table store"))
+        })
+        .clone()
+}
+
+/// Build an executor over a catalog snapshot and the shared table store
+pub fn new_executor(catalog: Catalog) -> Executor {
+    let executor =
+        Executor::new_with_storage(Arc::new(catalog), TXN_MANAGER.clone(), table_store());
+    match CONSTRAINTS.get() {
+        Some(constraints) => executor.with_constraints(constraints.clone()),
+        None => executor,
+    }
+}
+
+/// Column metadata for an executor result, typed from its column types
+pub fn result_columns(result: &QueryResult) -> Vec<ColumnMetadata> {
+    result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| ColumnMetadata {
+            name: name.clone(),
+            data_type: result
+                .column_types
+                .get(i)
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| "TEXT".to_string()),
+            nullable: true,
+            precision: None,
+            scale: None,
+        })
+        .collect()
+}
+
+/// Rows of an executor result as JSON objects keyed by column name
+pub fn result_rows(result: &QueryResult) -> Vec<HashMap<String, serde_json::Value>> {
+    result
+        .rows
+        .iter()
+        .map(|row| {
+            result
+                .columns
+                .iter()
+                .cloned()
+                .zip(row.iter().map(Value::to_json))
+                .collect()
+        })
+        .collect()
 }
 
 // Re-export all handler functions for convenience
@@ -5,20 +5,25 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json as AxumJson,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use super::{CATALOG, SQL_PARSER, TXN_MANAGER};
+use super::{
+    api_identity, new_executor, parse_checked, result_columns, result_rows, sql_error, CATALOG,
+};
 use crate::api::rest::types::*;
 use crate::catalog::{Column, DataType};
-use crate::execution::Executor;
+use crate::common::Value;
+use crate::constraints::ReferentialAction;
+use crate::error::DbError;
+use crate::execution::QueryResult;
 use crate::parser::{AlterAction, ConstraintType, SqlStatement};
+use crate::procedures::{ParameterMode, ProcedureParameter};
 
 // ============================================================================
 // Request/Response Types
@@ -120,7 +125,7 @@ pub async fn create_database(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -148,7 +153,7 @@ pub async fn drop_database(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -180,7 +185,7 @@ pub async fn backup_database(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -216,7 +221,10 @@ pub async fn alter_table(
 
             let column = Column {
                 name: col_def.name,
-                data_type: parse_data_type(&col_def.data_type),
+                data_type: col_def
+                    .data_type
+                    .parse::<DataType>()
+                    .map_err(|e| ApiError::new("INVALID_INPUT", e.to_string()))?,
                 nullable: col_def.nullable,
                 default: col_def.default_value,
             };
@@ -240,7 +248,10 @@ pub async fn alter_table(
 
             AlterAction::AlterColumn {
                 column_name: col_name,
-                new_type: parse_data_type(&col_def.data_type),
+                new_type: col_def
+                    .data_type
+                    .parse::<DataType>()
+                    .map_err(|e| ApiError::new("INVALID_INPUT", e.to_string()))?,
             }
         }
         "modify_column" => {
@@ -253,7 +264,10 @@ pub async fn alter_table(
 
             AlterAction::ModifyColumn {
                 column_name: col_name,
-                new_type: parse_data_type(&col_def.data_type),
+                new_type: col_def
+                    .data_type
+                    .parse::<DataType>()
+                    .map_err(|e| ApiError::new("INVALID_INPUT", e.to_string()))?,
                 nullable: Some(col_def.nullable),
             }
         }
@@ -265,9 +279,12 @@ pub async fn alter_table(
             let constraint_type = match constraint.constraint_type.as_str() {
                 "primary_key" => ConstraintType::PrimaryKey(constraint.columns),
                 "foreign_key" => ConstraintType::ForeignKey {
+                    name: None,
                     columns: constraint.columns,
                     ref_table: constraint.ref_table.unwrap_or_default(),
                     ref_columns: constraint.ref_columns.unwrap_or_default(),
+                    on_delete: ReferentialAction::NoAction,
+                    on_update: ReferentialAction::NoAction,
                 },
                 "unique" => ConstraintType::Unique(constraint.columns),
                 "check" => ConstraintType::Check(constraint.check_expression.unwrap_or_default()),
@@ -298,7 +315,7 @@ pub async fn alter_table(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -335,7 +352,7 @@ pub async fn create_view(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -363,7 +380,7 @@ pub async fn drop_view(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -421,12 +438,14 @@ pub async fn create_index(
         table: table.to_string(),
         columns,
         unique,
+        filter: None,
+        hnsw: None,
     };
 
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -454,7 +473,7 @@ pub async fn drop_index(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -482,22 +501,32 @@ pub async fn create_procedure(
     State(_state): State<Arc<ApiState>>,
     AxumJson(request): AxumJson<ProcedureRequest>,
 ) -> ApiResult<StatusCode> {
-    let parameters: Vec<(String, DataType)> = request
+    let parameters = request
         .parameters
         .iter()
-        .map(|p| (p.name.clone(), parse_data_type(&p.data_type)))
-        .collect();
+        .map(|p| {
+            Ok(ProcedureParameter {
+                name: p.name.clone(),
+                data_type: p
+                    .data_type
+                    .parse()
+                    .map_err(|e: DbError| ApiError::new("INVALID_INPUT", e.to_string()))?,
+                mode: ParameterMode::In,
+            })
+        })
+        .collect::<ApiResult<_>>()?;
 
     let stmt = SqlStatement::CreateProcedure {
         name: request.name,
         parameters,
         body: request.body,
+        or_replace: false,
     };
 
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -522,24 +551,30 @@ pub async fn execute_procedure(
     Path(name): Path<String>,
     AxumJson(request): AxumJson<ExecProcedureRequest>,
 ) -> ApiResult<AxumJson<QueryResponse>> {
-    let arguments: Vec<String> = request.arguments.iter().map(|v| v.to_string()).collect();
-
-    let stmt = SqlStatement::ExecProcedure { name, arguments };
+    let arguments = request.arguments.iter().map(Value::from_json).collect();
 
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     let result = executor
-        .execute(stmt)
+        .call_procedure(&name, arguments)
         .map_err(|e| ApiError::new("EXECUTION_ERROR", &e.to_string()))?;
 
+    // OUT parameters come back as a single row
+    let (columns, row): (Vec<String>, Vec<Value>) = result.output_parameters.into_iter().unzip();
+    let outputs = if columns.is_empty() {
+        QueryResult::with_affected(0)
+    } else {
+        QueryResult::new(columns, vec![row])
+    };
+
     let response = QueryResponse {
         query_id: Uuid::new_v4().to_string(),
-        row_count: result.rows.len(),
-        rows: vec![],
-        columns: vec![],
+        row_count: outputs.rows.len(),
+        rows: result_rows(&outputs),
+        columns: result_columns(&outputs),
         affected_rows: Some(result.rows_affected),
         execution_time_ms: 0,
         plan: None,
@@ -567,15 +602,13 @@ pub async fn execute_procedure(
 )]
 pub async fn execute_union(
     State(_state): State<Arc<ApiState>>,
+    headers: HeaderMap,
     AxumJson(request): AxumJson<UnionRequest>,
 ) -> ApiResult<AxumJson<QueryResponse>> {
     // Parse both queries
-    let left_stmts = SQL_PARSER
-        .parse(&request.left_query)
-        .map_err(|e| ApiError::new("SQL_PARSE_ERROR", &e.to_string()))?;
-    let right_stmts = SQL_PARSER
-        .parse(&request.right_query)
-        .map_err(|e| ApiError::new("SQL_PARSE_ERROR", &e.to_string()))?;
+    let identity = api_identity(&headers);
+    let left_stmts = parse_checked(&request.left_query, &identity).map_err(sql_error)?;
+    let right_stmts = parse_checked(&request.right_query, &identity).map_err(sql_error)?;
 
     let left_stmt = left_stmts
         .into_iter()
@@ -595,41 +628,19 @@ pub async fn execute_union(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     let result = executor
         .execute(stmt)
         .map_err(|e| ApiError::new("EXECUTION_ERROR", &e.to_string()))?;
 
-    let rows: Vec<HashMap<String, serde_json::Value>> = result
-        .rows
-        .iter()
-        .map(|row| {
-            let mut map = HashMap::new();
-            for (i, val) in row.iter().enumerate() {
-                if let Some(col_name) = result.columns.get(i) {
-                    map.insert(col_name.clone(), serde_json::Value::String(val.clone()));
-                }
-            }
-            map
-        })
-        .collect();
+    let rows = result_rows(&result);
 
     let response = QueryResponse {
         query_id: Uuid::new_v4().to_string(),
         row_count: rows.len(),
         rows,
-        columns: result
-            .columns
-            .iter()
-            .map(|name| ColumnMetadata {
-                name: name.clone(),
-                data_type: "TEXT".to_string(),
-                nullable: true,
-                precision: None,
-                scale: None,
-            })
-            .collect(),
+        columns: result_columns(&result),
         affected_rows: None,
         execution_time_ms: 0,
         plan: None,
@@ -659,7 +670,7 @@ pub async fn truncate_table(
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
     drop(catalog_guard);
-    let executor = Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+    let executor = new_executor(catalog_snapshot);
 
     executor
         .execute(stmt)
@@ -667,32 +678,3 @@ pub async fn truncate_table(
 
     Ok(StatusCode::OK)
 }
-
-// ============================================================================
-// Helper Functions
-// ============================================================================
-
-/// Parse data type string into DataType enum
-fn parse_data_type(type_str: &str) -> DataType {
-    let upper = type_str.to_uppercase();
-    if upper.starts_with("VARCHAR") {
-        let size = upper
-            .trim_start_matches("VARCHAR")
-            .trim_matches(|c| c == '(' || c == ')' || c == ' ')
-            .parse::<usize>()
-            .unwrap_or(255);
-        DataType::Varchar(size)
-    } else {
-        match upper.as_str() {
-            "INT" | "INTEGER" => DataType::Integer,
-            "BIGINT" => DataType::BigInt,
-            "FLOAT" | "REAL" => DataType::Float,
-            "DOUBLE" | "DOUBLE PRECISION" => DataType::Double,
-            "TEXT" => DataType::Text,
-            "BOOL" | "BOOLEAN" => DataType::Boolean,
-            "DATE" => DataType::Date,
-            "TIMESTAMP" | "DATETIME" => DataType::Timestamp,
-            _ => DataType::Text,
-        }
-    }
-}
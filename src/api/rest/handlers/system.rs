@@ -290,11 +290,11 @@ pub async fn get_security_features(
     );
 
     features.insert(
-        "injection_prevention".to_string(),
+        "sql_firewall".to_string(),
         SecurityFeatureStatus {
             enabled: true,
             status: "active".to_string(),
-            description: "SQL injection prevention".to_string(),
+            description: "SQL firewall with per-role and per-application allow-lists".to_string(),
             last_check: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
@@ -7,7 +7,7 @@ use axum::{
         ws::{WebSocket, WebSocketUpgrade},
         Path, Query, State,
     },
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{Json as AxumJson, Response},
 };
 use futures::{SinkExt, StreamExt};
@@ -24,8 +24,8 @@ use super::websocket_types::{
     CreateSubscriptionResponse, DeleteSubscriptionResponse, DisconnectRequest, DisconnectResponse,
     SubscriptionInfo, SubscriptionList, WebSocketStatus,
 };
-use super::{CATALOG, SQL_PARSER, TXN_MANAGER};
-use crate::execution::Executor;
+use super::{api_identity, new_executor, parse_checked, CATALOG};
+use crate::security::sql_firewall::FirewallIdentity;
 
 // ============================================================================
 // Request/Response Types
@@ -99,8 +99,13 @@ pub async fn ws_upgrade_handler(
     ),
     tag = "websocket"
 )]
-pub async fn ws_query_stream(ws: WebSocketUpgrade, State(state): State<Arc<ApiState>>) -> Response {
-    ws.on_upgrade(|socket| handle_query_stream_websocket(socket, state))
+pub async fn ws_query_stream(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    State(state): State<Arc<ApiState>>,
+) -> Response {
+    let identity = api_identity(&headers);
+    ws.on_upgrade(|socket| handle_query_stream_websocket(socket, state, identity))
 }
 
 /// WebSocket handler for live metrics streaming
@@ -234,7 +239,11 @@ async fn handle_generic_websocket(mut socket: WebSocket, _state: Arc<ApiState>)
 }
 
 /// Query streaming WebSocket handler
-async fn handle_query_stream_websocket(mut socket: WebSocket, _state: Arc<ApiState>) {
+async fn handle_query_stream_websocket(
+    mut socket: WebSocket,
+    _state: Arc<ApiState>,
+    identity: FirewallIdentity,
+) {
     use axum::extract::ws::Message;
 
     while let Some(msg) = socket.recv().await {
@@ -248,21 +257,32 @@ async fn handle_query_stream_websocket(mut socket: WebSocket, _state: Arc<ApiSta
                             let catalog_guard = CATALOG.read();
                             (*catalog_guard).clone()
                         }; // catalog_guard dropped here
-                        let executor =
-                            Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone());
+                        let executor = new_executor(catalog_snapshot);
 
                         // Execute query
-                        match SQL_PARSER.parse(&request.sql) {
+                        match parse_checked(&request.sql, &identity) {
                             Ok(stmts) => {
                                 if let Some(stmt) = stmts.into_iter().next() {
                                     match executor.execute(stmt) {
                                         Ok(result) => {
                                             // Stream results
+                                            let rows: Vec<Vec<serde_json::Value>> = result
+                                                .rows
+                                                .iter()
+                                                .map(|row| {
+                                                    row.iter().map(|v| v.to_json()).collect()
+                                                })
+                                                .collect();
                                             let response = WebSocketMessage {
                                                 message_type: "query_result".to_string(),
                                                 data: json!({
                                                     "columns": result.columns,
-                                                    "rows": result.rows,
+                                                    "column_types": result
+                                                        .column_types
+                                                        .iter()
+                                                        .map(|t| t.to_string())
+                                                        .collect::<Vec<_>>(),
+                                                    "rows": rows,
                                                     "rows_affected": result.rows_affected,
                                                     "status": "success"
                                                 }),
@@ -102,22 +102,25 @@ use super::handlers::system::{
     get_clustering_status, get_replication_status_info, get_security_features, get_server_config,
     get_server_info,
 };
-use super::handlers::{CATALOG, SQL_PARSER, TXN_MANAGER};
+use super::handlers::{api_identity, new_executor, parse_checked, CATALOG};
 use super::middleware::{auth_middleware, rate_limit_middleware, request_logger_middleware};
 use super::types::{ApiMetrics, ApiState, QueryRequest, RateLimiter};
 use crate::api::graphql::{
-    AuthorizationContext, GraphQLEngine, MutationRoot, QueryRoot, SubscriptionRoot,
+    AuthorizationContext, CatalogSchema, GraphQLEngine, MutationRoot, QueryRoot, SubscriptionRoot,
 };
 use crate::api::ApiConfig;
+use crate::database::Database;
 use crate::error::DbError;
 use crate::execution::Executor;
 use crate::networking::{
     create_api_router, create_default_manager, NetworkConfig, NodeAddress, NodeId, NodeInfo,
 };
+use crate::security::sql_firewall::FirewallIdentity;
 use async_graphql::{http::GraphQLPlaygroundConfig, Schema};
 use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
 use axum::{
     extract::{ws::WebSocket, State, WebSocketUpgrade},
+    http::HeaderMap,
     middleware,
     response::{Html, IntoResponse, Response},
     routing::{delete, get, post, put},
@@ -187,6 +190,11 @@ pub struct RestApiServer {
     config: ApiConfig,
     state: Arc<ApiState>,
     graphql_schema: GraphQLSchema,
+    // Typed GraphQL over the tables of the attached database
+    catalog_schema: Option<Arc<CatalogSchema>>,
+    // When set, GraphQL queries run against it and `run` returns once it
+    // starts shutting down
+    database: Option<Arc<Database>>,
 }
 
 impl RestApiServer {
@@ -210,28 +218,38 @@ impl RestApiServer {
             network_manager: Some(network_manager),
         });
 
-        // Build GraphQL schema with engine and authorization context
-        let graphql_engine = Arc::new(GraphQLEngine::new());
-
-        // Create admin authorization context
-        let auth_context = Arc::new(AuthorizationContext::new(
-            "admin".to_string(),
-            vec!["admin".to_string()],
-            vec!["admin.*".to_string()],
-        ));
-
-        let graphql_schema = Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
-            .data(graphql_engine)
-            .data(auth_context)
-            .finish();
+        let graphql_schema = Self::build_graphql_schema(GraphQLEngine::new());
 
         Ok(Self {
             config,
             state,
             graphql_schema,
+            catalog_schema: None,
+            database: None,
         })
     }
 
+    // Serve the API from a shared database handle
+    //
+    // The REST and WebSocket handlers reach it through
+    // `handlers::install_database`; this points GraphQL at it and ties the
+    // server's lifetime to the database's.
+    pub fn with_database(mut self, database: Arc<Database>) -> Self {
+        self.graphql_schema =
+            Self::build_graphql_schema(GraphQLEngine::new().with_database(database.clone()));
+        self.catalog_schema = Some(Arc::new(CatalogSchema::new(database.clone())));
+        self.database = Some(database);
+        self
+    }
+
+    // Build GraphQL schema with engine and authorization context
+    fn build_graphql_schema(graphql_engine: GraphQLEngine) -> GraphQLSchema {
+        Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
+            .data(Arc::new(graphql_engine))
+            .data(admin_authorization())
+            .finish()
+    }
+
     // Build the router with all endpoints and middleware
     fn build_router(&self) -> Router {
         // Public auth routes (no authentication required)
@@ -288,6 +306,12 @@ impl RestApiServer {
             .route("/graphql", post(graphql_handler).get(graphql_playground))
             .route("/graphql/ws", get(graphql_subscription))
             .with_state(graphql_schema);
+        let catalog_graphql_router = match &self.catalog_schema {
+            Some(schema) => Router::new()
+                .route("/graphql/data", post(catalog_graphql_handler))
+                .with_state(schema.clone()),
+            None => Router::new(),
+        };
 
         // Create networking router if NetworkManager is available
         let networking_router = self
@@ -299,6 +323,7 @@ impl RestApiServer {
         let mut router = Router::new()
             // Merge GraphQL router
             .merge(graphql_router)
+            .merge(catalog_graphql_router)
             // Merge auth routes
             .merge(auth_routes)
             // Merge protected routes
@@ -1505,7 +1530,17 @@ impl RestApiServer {
 
         tracing::info!("REST API server listening on {}", addr);
 
+        // In-flight requests are allowed to finish once shutdown starts; the
+        // server counts as one open connection so the database waits for them
+        let _connection = self.database.as_ref().and_then(Database::open_connection);
+        let database = self.database.clone();
         axum::serve(listener, router)
+            .with_graceful_shutdown(async move {
+                match database {
+                    Some(database) => database.shutdown_started().await,
+                    None => std::future::pending().await,
+                }
+            })
             .await
             .map_err(|e| DbError::Network(format!("Server error: {}", e)))?;
 
@@ -1521,14 +1556,19 @@ impl Default for RestApiServer {
 }
 
 // WebSocket handler for streaming query results
-async fn websocket_stream(ws: WebSocketUpgrade, State(state): State<Arc<ApiState>>) -> Response {
-    ws.on_upgrade(|socket| handle_websocket(socket, state))
+async fn websocket_stream(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    State(state): State<Arc<ApiState>>,
+) -> Response {
+    let identity = api_identity(&headers);
+    ws.on_upgrade(|socket| handle_websocket(socket, state, identity))
 }
 
 fn get_executor() -> Executor {
     let catalog_guard = CATALOG.read();
     let catalog_snapshot = (*catalog_guard).clone();
-    Executor::new(Arc::new(catalog_snapshot), TXN_MANAGER.clone())
+    new_executor(catalog_snapshot)
 }
 
 // TODO: BACKPRESSURE NEEDED - WebSocket Message Queue
@@ -1539,7 +1579,7 @@ fn get_executor() -> Executor {
 //   2. Implement slow-consumer detection and disconnect/drop messages if queue full
 //   3. Add per-connection message rate limiting
 // See: diagrams/06_network_api_flow.md - Issues #3.5, #5.5
-async fn handle_websocket(mut socket: WebSocket, _state: Arc<ApiState>) {
+async fn handle_websocket(mut socket: WebSocket, _state: Arc<ApiState>, identity: FirewallIdentity) {
     use axum::extract::ws::Message;
 
     // ISSUE: This loop has no bounds on the receive queue
@@ -1550,7 +1590,7 @@ async fn handle_websocket(mut socket: WebSocket, _state: Arc<ApiState>) {
                     if let Ok(request) = serde_json::from_str::<QueryRequest>(&text) {
                         let executor = get_executor();
 
-                        let response = match SQL_PARSER.parse(&request.sql) {
+                        let response = match parse_checked(&request.sql, &identity) {
                             Ok(stmts) => {
                                 let stmt = match stmts.into_iter().next() {
                                     Some(s) => s,
@@ -1565,11 +1605,7 @@ async fn handle_websocket(mut socket: WebSocket, _state: Arc<ApiState>) {
                                             .rows
                                             .iter()
                                             .map(|row| {
-                                                row.iter()
-                                                    .map(|val| {
-                                                        serde_json::Value::String(val.clone())
-                                                    })
-                                                    .collect()
+                                                row.iter().map(|val| val.to_json()).collect()
                                             })
                                             .collect();
 
@@ -1612,6 +1648,15 @@ async fn handle_websocket(mut socket: WebSocket, _state: Arc<ApiState>) {
 // GRAPHQL HANDLERS
 // ============================================================================
 
+// Admin authorization context the GraphQL endpoints run with
+fn admin_authorization() -> Arc<AuthorizationContext> {
+    Arc::new(AuthorizationContext::new(
+        "admin".to_string(),
+        vec!["admin".to_string()],
+        vec!["admin.*".to_string()],
+    ))
+}
+
 // GraphQL query/mutation handler
 async fn graphql_handler(
     State(schema): State<GraphQLSchema>,
@@ -1620,6 +1665,17 @@ async fn graphql_handler(
     schema.execute(req.into_inner()).await.into()
 }
 
+// Typed GraphQL query/mutation handler over the catalog's tables
+async fn catalog_graphql_handler(
+    State(schema): State<Arc<CatalogSchema>>,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    schema
+        .execute(req.into_inner(), admin_authorization())
+        .await
+        .into()
+}
+
 // GraphQL Playground UI
 async fn graphql_playground() -> impl IntoResponse {
     Html(async_graphql::http::playground_source(
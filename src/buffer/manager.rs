@@ -48,8 +48,9 @@ use crate::common::PageId;
 use crate::error::{DbError, Result};
 use crate::storage::disk::DiskManager;
 use crate::storage::page::Page;
+use crate::transaction::wal::WALManager;
 
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -409,6 +410,15 @@ pub struct BufferPoolManager {
     /// Disk manager for persistent storage I/O
     disk_manager: Option<Arc<DiskManager>>,
 
+    /// Log the write-ahead rule is enforced against (see `set_wal`)
+    wal: Arc<RwLock<Option<Arc<WALManager>>>>,
+
+    /// Serializes page faults so a page is never loaded into two frames
+    fault_lock: Mutex<()>,
+
+    /// Next page id `new_page` hands out when there is no disk manager
+    next_page_id: AtomicU64,
+
     /// Prefetch request queue (page_id -> priority)
     /// BOUNDED: max_prefetch_queue_size IS enforced in prefetch_pages()
     /// Implementation at lines 996-999: breaks when queue.len() >= max_prefetch_queue_size
@@ -483,6 +493,9 @@ impl BufferPoolManager {
             free_frames,
             eviction_policy,
             disk_manager,
+            wal: Arc::new(RwLock::new(None)),
+            fault_lock: Mutex::new(()),
+            next_page_id: AtomicU64::new(0),
             prefetch_queue,
             prefetch_workers: Mutex::new(Vec::new()),
             page_reads: AtomicU64::new(0),
@@ -531,7 +544,7 @@ impl BufferPoolManager {
     #[inline]
     pub fn pin_page(&self, page_id: PageId) -> Result<FrameGuard> {
         // Fast path: page already in buffer pool
-        if let Some(frame_id) = self.page_table.lookup(page_id) {
+        while let Some(frame_id) = self.page_table.lookup(page_id) {
             // SAFETY: frame_id is guaranteed to be valid
             let frame = unsafe { self.frames.get_unchecked(frame_id as usize) };
 
@@ -541,20 +554,50 @@ impl BufferPoolManager {
             }
 
             // Pin and record access
-            frame.pin();
-            self.eviction_policy.record_pin(frame_id);
+            let guard = FrameGuard::new(frame.clone());
 
-            return Ok(FrameGuard::new(frame.clone()));
+            // The frame may have been evicted between the lookup and the
+            // pin; look the page up again if so
+            if frame.page_id() == page_id && !frame.io_in_progress() {
+                self.eviction_policy.record_pin(frame_id);
+                return Ok(guard);
+            }
         }
 
         // Slow path: page fault - need to load from disk
         self.pin_page_slow_path(page_id)
     }
 
+    /// Allocate a new page and pin it
+    ///
+    /// The page is allocated through the disk manager; without one, page
+    /// ids are handed out from a counter.
+    pub fn new_page(&self) -> Result<FrameGuard> {
+        let page_id = match &self.disk_manager {
+            Some(disk_manager) => disk_manager.allocate_page()?,
+            None => self.next_page_id.fetch_add(1, Ordering::Relaxed),
+        };
+        self.pin_page(page_id)
+    }
+
+    /// Enforce the write-ahead rule against `wal`: a page is only written
+    /// back once the log is durable up to its LSN (see `BufferFrame::mark_dirty`)
+    pub fn set_wal(&self, wal: Arc<WALManager>) {
+        *self.wal.write() = Some(wal);
+    }
+
     /// Slow path for pin_page (page fault)
     #[cold]
     #[inline(never)]
     fn pin_page_slow_path(&self, page_id: PageId) -> Result<FrameGuard> {
+        let fault = self.fault_lock.lock();
+
+        // Another thread may have loaded the page while we waited
+        if self.page_table.lookup(page_id).is_some() {
+            drop(fault);
+            return self.pin_page(page_id);
+        }
+
         // Allocate a frame
         let frame_id = self.allocate_frame()?;
         let frame = &self.frames[frame_id as usize];
@@ -564,14 +607,19 @@ impl BufferPoolManager {
 
         // Load page from disk
         let start = Instant::now();
-        self.load_page_from_disk(page_id, frame)?;
+        if let Err(e) = self.load_page_from_disk(page_id, frame) {
+            frame.reset();
+            self.free_frames.deallocate(frame_id);
+            return Err(e);
+        }
         let elapsed = start.elapsed().as_micros() as u64;
         self.io_wait_time_us.fetch_add(elapsed, Ordering::Relaxed);
 
-        // Update frame metadata
+        // Update frame metadata; pinned before it is published so it
+        // cannot be chosen for eviction
         frame.set_page_id(page_id);
         frame.set_dirty(false);
-        frame.pin();
+        let guard = FrameGuard::new(frame.clone());
 
         // Add to page table
         self.page_table.insert(page_id, frame_id);
@@ -585,7 +633,7 @@ impl BufferPoolManager {
         // Update statistics
         self.page_reads.fetch_add(1, Ordering::Relaxed);
 
-        Ok(FrameGuard::new(frame.clone()))
+        Ok(guard)
     }
 
     /// Allocate a frame (either from free list or by eviction)
@@ -614,7 +662,10 @@ impl BufferPoolManager {
         // Flush if dirty
         if victim_frame.is_dirty() {
             let start = Instant::now();
-            self.flush_page(victim_frame)?;
+            if let Err(e) = self.flush_page(victim_frame) {
+                victim_frame.set_io_in_progress(false);
+                return Err(e);
+            }
             let elapsed = start.elapsed().as_micros() as u64;
             self.io_wait_time_us.fetch_add(elapsed, Ordering::Relaxed);
         }
@@ -686,12 +737,18 @@ impl BufferPoolManager {
             return Ok(());
         }
 
-        // Write to disk
-        self.write_page_to_disk(page_id, frame)?;
-
+        // Cleared before the write so a change made while it is under way
+        // dirties the page again
         frame.set_dirty(false);
+        if let Err(e) = self.write_page_to_disk(page_id, frame) {
+            frame.set_dirty(true);
+            return Err(e);
+        }
         self.page_writes.fetch_add(1, Ordering::Relaxed);
 
+        if let Some(wal) = self.wal.read().as_ref() {
+            wal.page_flushed(page_id);
+        }
         Ok(())
     }
 
@@ -756,6 +813,8 @@ impl BufferPoolManager {
     /// Start background flusher thread
     fn start_background_flusher(&self) {
         let frames = self.frames.clone();
+        let disk_manager = self.disk_manager.clone();
+        let wal = self.wal.clone();
         let shutdown = self.shutdown.clone();
         let interval = self.config.background_flush_interval;
         let max_batch_size = self.config.max_flush_batch_size;
@@ -786,12 +845,21 @@ impl BufferPoolManager {
                         }
                     }
 
-                    // Flush batch (simplified - in real impl would call flush_batch)
+                    // Pin each frame while it is written so it is not
+                    // evicted (and reused for another page) meanwhile
                     for frame in batch {
-                        if frame.is_dirty() {
+                        if !frame.try_pin() {
+                            continue;
+                        }
+                        let page_id = frame.page_id();
+                        if frame.is_dirty() && page_id != INVALID_PAGE_ID {
                             frame.set_dirty(false);
-                            _writes_count += 1;
+                            match Self::write_frame(&disk_manager, &wal, page_id, &frame) {
+                                Ok(()) => _writes_count += 1,
+                                Err(_) => frame.set_dirty(true),
+                            }
                         }
+                        frame.unpin();
                     }
 
                     _flush_count += 1;
@@ -861,10 +929,32 @@ impl BufferPoolManager {
     fn write_page_to_disk(&self, page_id: PageId, frame: &BufferFrame) -> Result<()> {
         let start = Instant::now();
 
-        match &self.disk_manager {
+        Self::write_frame(&self.disk_manager, &self.wal, page_id, frame)?;
+
+        // Track I/O wait time
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        self.io_wait_time_us
+            .fetch_add(elapsed_us, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Write a frame's page through `disk_manager`, first flushing `wal`
+    /// up to the page LSN (shared with the background flusher)
+    fn write_frame(
+        disk_manager: &Option<Arc<DiskManager>>,
+        wal: &RwLock<Option<Arc<WALManager>>>,
+        page_id: PageId,
+        frame: &BufferFrame,
+    ) -> Result<()> {
+        match disk_manager {
             Some(disk_manager) => {
-                // Read the page data from the frame
+                // Read the page data from the frame; the LSN is read under
+                // the latch so it matches the data written
                 let data = frame.read_data();
+                if let Some(wal) = wal.read().as_ref() {
+                    wal.flush_to(frame.page_lsn())?;
+                }
 
                 // Create a Page struct for the disk manager
                 let page = Page::from_bytes(page_id, data.data().to_vec());
@@ -876,12 +966,6 @@ impl BufferPoolManager {
                 // No disk manager - no-op for testing scenarios
             }
         }
-
-        // Track I/O wait time
-        let elapsed_us = start.elapsed().as_micros() as u64;
-        self.io_wait_time_us
-            .fetch_add(elapsed_us, Ordering::Relaxed);
-
         Ok(())
     }
 
@@ -892,8 +976,11 @@ impl BufferPoolManager {
         if let Some(frame_id) = self.page_table.lookup(page_id) {
             let frame = &self.frames[frame_id as usize];
             if frame.is_dirty() {
-                self.write_page_to_disk(page_id, frame)?;
                 frame.set_dirty(false);
+                if let Err(e) = self.write_page_to_disk(page_id, frame) {
+                    frame.set_dirty(true);
+                    return Err(e);
+                }
                 self.page_writes.fetch_add(1, Ordering::Relaxed);
             }
         }
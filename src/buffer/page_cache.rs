@@ -363,10 +363,12 @@ impl BufferFrame {
     }
 
     // Reset frame to empty state
+    //
+    // The pin count is left alone: a thread that pinned the frame while it
+    // was being evicted still unpins it.
     #[cold]
     pub fn reset(&self) {
         self.page_id.store(INVALID_PAGE_ID, Ordering::Release);
-        self.pin_count.store(0, Ordering::Release);
         self.dirty.store(false, Ordering::Release);
         self.io_in_progress.store(false, Ordering::Release);
         self.ref_bit.store(false, Ordering::Release);
@@ -137,6 +137,9 @@ impl CacheEntry {
                     Value::Json(j) => j.to_string().len() + 32,
                     Value::Array(a) => a.len() * 64, // Rough estimate
                     Value::Text => 4,
+                    Value::Vector(v) => v.len() * 8 + 24,
+                    Value::Numeric(_) | Value::Interval(_) | Value::Uuid(_) => 16,
+                    Value::TimestampTz(_) => 8,
                 };
             }
         }
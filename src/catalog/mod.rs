@@ -1,10 +1,35 @@
+use crate::common::{decimal, Decimal, Interval, Value, MICROS_PER_DAY};
+use crate::constraints::ForeignKey;
 use crate::error::DbError;
+use crate::index::hnsw::HnswOptions;
+use crate::index::partial::Predicate;
+use crate::procedures::StoredProcedure;
+use crate::storage::TableStore;
+use crate::triggers::Trigger;
 use crate::Result;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use sqlparser::ast::{ArrayElemTypeDef, CharacterLength, DataType as SqlDataType, TimezoneInfo};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Token;
 use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+mod statistics;
+mod system;
+
+pub use statistics::{ColumnStats, TableStats};
+use system::SystemTables;
+pub use system::{is_system_table, SYSTEM_TABLES};
+
+// Most dimensions a VECTOR(n) column may be declared with
+pub const MAX_VECTOR_DIMENSIONS: usize = 16_000;
+
 // Column definition
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Column {
@@ -26,6 +51,360 @@ pub enum DataType {
     Boolean,
     Date,
     Timestamp,
+    // Vector of floats with a fixed number of dimensions
+    Vector(usize),
+    // Exact decimal, with the precision and scale it is rounded to if given
+    Numeric(Option<(u32, u32)>),
+    Interval,
+    // Timestamp with time zone, held in UTC
+    TimestampTz,
+    Uuid,
+    // One-dimensional array of values of the element type
+    Array(Box<DataType>),
+}
+
+impl DataType {
+    // Convert a value to this column type, rejecting values that don't fit
+    pub fn coerce(&self, value: Value) -> Result<Value> {
+        if value.is_null() {
+            return Ok(Value::Null);
+        }
+        let mismatch = |value: &Value| {
+            DbError::InvalidInput(format!(
+                "Invalid value '{}' for type {}",
+                value.to_display_string(),
+                self
+            ))
+        };
+
+        match self {
+            DataType::Integer | DataType::BigInt => {
+                let int = match &value {
+                    Value::Integer(i) => *i,
+                    Value::Float(f) if f.is_finite() => f.round() as i64,
+                    Value::Numeric(d) => d.to_i64().ok_or_else(|| mismatch(&value))?,
+                    Value::Boolean(b) => *b as i64,
+                    Value::String(s) => match Value::from_sql_literal(s) {
+                        Value::Integer(i) => i,
+                        Value::Float(f) if f.is_finite() => f.round() as i64,
+                        _ => return Err(mismatch(&value)),
+                    },
+                    _ => return Err(mismatch(&value)),
+                };
+                if *self == DataType::Integer && i32::try_from(int).is_err() {
+                    return Err(DbError::InvalidInput(format!(
+                        "Value {} is out of range for type INTEGER",
+                        int
+                    )));
+                }
+                Ok(Value::Integer(int))
+            }
+            DataType::Float | DataType::Double => match &value {
+                Value::Integer(i) => Ok(Value::Float(*i as f64)),
+                Value::Float(f) => Ok(Value::Float(*f)),
+                Value::Numeric(d) => Ok(Value::Float(d.to_f64())),
+                Value::String(s) => match Value::from_sql_literal(s).as_f64() {
+                    Some(f) => Ok(Value::Float(f)),
+                    None => Err(mismatch(&value)),
+                },
+                _ => Err(mismatch(&value)),
+            },
+            DataType::Varchar(max) => {
+                let text = value.to_display_string();
+                if text.chars().count() > *max {
+                    return Err(DbError::InvalidInput(format!(
+                        "Value too long for type VARCHAR({})",
+                        max
+                    )));
+                }
+                Ok(Value::String(text))
+            }
+            DataType::Text => Ok(Value::String(value.to_display_string())),
+            DataType::Boolean => value
+                .as_bool()
+                .map(Value::Boolean)
+                .ok_or_else(|| mismatch(&value)),
+            DataType::Date => match &value {
+                Value::Date(_) => Ok(value),
+                Value::Timestamp(t) | Value::TimestampTz(t) => {
+                    Ok(Value::Date(t.div_euclid(MICROS_PER_DAY)))
+                }
+                Value::String(s) => Value::parse_date(s)
+                    .or_else(|| Value::parse_timestamp(s).map(|t| t.div_euclid(MICROS_PER_DAY)))
+                    .map(Value::Date)
+                    .ok_or_else(|| mismatch(&value)),
+                _ => Err(mismatch(&value)),
+            },
+            DataType::Timestamp => match &value {
+                Value::Timestamp(_) => Ok(value),
+                Value::TimestampTz(t) => Ok(Value::Timestamp(*t)),
+                Value::Date(d) => Ok(Value::Timestamp(d.saturating_mul(MICROS_PER_DAY))),
+                Value::String(s) => Value::parse_timestamp(s)
+                    .map(Value::Timestamp)
+                    .ok_or_else(|| mismatch(&value)),
+                _ => Err(mismatch(&value)),
+            },
+            DataType::Vector(dims) => {
+                let vector = match &value {
+                    Value::Vector(v) => v.clone(),
+                    Value::String(s) => Value::parse_vector(s).ok_or_else(|| mismatch(&value))?,
+                    Value::Array(items) => items
+                        .iter()
+                        .map(Value::as_f64)
+                        .collect::<Option<Vec<f64>>>()
+                        .ok_or_else(|| mismatch(&value))?,
+                    _ => return Err(mismatch(&value)),
+                };
+                if vector.len() != *dims {
+                    return Err(DbError::InvalidInput(format!(
+                        "Expected {} dimensions for type VECTOR({}), got {}",
+                        dims,
+                        dims,
+                        vector.len()
+                    )));
+                }
+                if vector.iter().any(|f| !f.is_finite()) {
+                    return Err(mismatch(&value));
+                }
+                Ok(Value::Vector(vector))
+            }
+            DataType::Numeric(modifier) => {
+                let decimal = match &value {
+                    Value::Numeric(d) => Some(*d),
+                    Value::Integer(i) => Some(Decimal::from_i64(*i)),
+                    Value::Float(f) => Decimal::from_f64(*f),
+                    Value::String(s) => Decimal::parse(s),
+                    _ => None,
+                }
+                .ok_or_else(|| mismatch(&value))?;
+                let Some((precision, scale)) = modifier else {
+                    return Ok(Value::Numeric(decimal));
+                };
+                decimal
+                    .fit(*precision, *scale)
+                    .map(Value::Numeric)
+                    .ok_or_else(|| {
+                        DbError::InvalidInput(format!(
+                            "Numeric field overflow: {} does not fit type {}",
+                            decimal, self
+                        ))
+                    })
+            }
+            DataType::Interval => match &value {
+                Value::Interval(_) => Ok(value),
+                Value::String(s) => Interval::parse(s)
+                    .map(Value::Interval)
+                    .ok_or_else(|| mismatch(&value)),
+                _ => Err(mismatch(&value)),
+            },
+            DataType::TimestampTz => match &value {
+                Value::TimestampTz(_) => Ok(value),
+                Value::Timestamp(t) => Ok(Value::TimestampTz(*t)),
+                Value::Date(d) => Ok(Value::TimestampTz(d.saturating_mul(MICROS_PER_DAY))),
+                Value::String(s) => Value::parse_timestamptz(s)
+                    .map(Value::TimestampTz)
+                    .ok_or_else(|| mismatch(&value)),
+                _ => Err(mismatch(&value)),
+            },
+            DataType::Uuid => match &value {
+                Value::Uuid(_) => Ok(value),
+                Value::String(s) => uuid::Uuid::parse_str(s.trim())
+                    .map(Value::Uuid)
+                    .map_err(|_| mismatch(&value)),
+                Value::Bytes(b) => uuid::Uuid::from_slice(b)
+                    .map(Value::Uuid)
+                    .map_err(|_| mismatch(&value)),
+                _ => Err(mismatch(&value)),
+            },
+            DataType::Array(element) => {
+                let items = match &value {
+                    Value::Array(items) => items.clone(),
+                    Value::String(s) => Value::parse_array(s).ok_or_else(|| mismatch(&value))?,
+                    _ => return Err(mismatch(&value)),
+                };
+                items
+                    .into_iter()
+                    .map(|item| match item {
+                        Value::Array(_) => Err(DbError::InvalidInput(format!(
+                            "Arrays of arrays are not supported, got '{}' for type {}",
+                            value, self
+                        ))),
+                        item => element.coerce(item),
+                    })
+                    .collect::<Result<Vec<_>>>()
+                    .map(Value::Array)
+            }
+        }
+    }
+
+    // Type of a computed value, if it has one
+    pub fn of_value(value: &Value) -> Option<DataType> {
+        match value {
+            Value::Integer(_) => Some(DataType::BigInt),
+            Value::Float(_) => Some(DataType::Double),
+            Value::Boolean(_) => Some(DataType::Boolean),
+            Value::Date(_) => Some(DataType::Date),
+            Value::Timestamp(_) => Some(DataType::Timestamp),
+            Value::Vector(v) => Some(DataType::Vector(v.len())),
+            Value::Numeric(_) => Some(DataType::Numeric(None)),
+            Value::Interval(_) => Some(DataType::Interval),
+            Value::TimestampTz(_) => Some(DataType::TimestampTz),
+            Value::Uuid(_) => Some(DataType::Uuid),
+            Value::Array(items) => {
+                let element = items.iter().find_map(DataType::of_value);
+                Some(DataType::Array(Box::new(element.unwrap_or(DataType::Text))))
+            }
+            Value::String(_) | Value::Json(_) | Value::Bytes(_) => Some(DataType::Text),
+            Value::Null | Value::Text => None,
+        }
+    }
+
+    // Map a SQL type name onto a column type; unknown types are stored as text
+    pub fn from_sql(data_type: &SqlDataType) -> Result<Self> {
+        Ok(match data_type {
+            SqlDataType::Int(_) | SqlDataType::Integer(_) | SqlDataType::Int4(_) => {
+                DataType::Integer
+            }
+            SqlDataType::BigInt(_) | SqlDataType::Int8(_) => DataType::BigInt,
+            SqlDataType::Float(_) | SqlDataType::Real | SqlDataType::Float4 => DataType::Float,
+            SqlDataType::Double(_) | SqlDataType::DoublePrecision | SqlDataType::Float8 => {
+                DataType::Double
+            }
+            SqlDataType::Varchar(len) => {
+                let size = len
+                    .map(|l| match l {
+                        CharacterLength::IntegerLength { length, .. } => length as usize,
+                        _ => 255,
+                    })
+                    .unwrap_or(255);
+                DataType::Varchar(size)
+            }
+            SqlDataType::Text => DataType::Text,
+            SqlDataType::Boolean | SqlDataType::Bool => DataType::Boolean,
+            SqlDataType::Date => DataType::Date,
+            SqlDataType::Timestamp(_, TimezoneInfo::WithTimeZone | TimezoneInfo::Tz) => {
+                DataType::TimestampTz
+            }
+            SqlDataType::Timestamp(_, _) | SqlDataType::Datetime(_) => DataType::Timestamp,
+            SqlDataType::Array(
+                ArrayElemTypeDef::SquareBracket(element, _)
+                | ArrayElemTypeDef::AngleBracket(element)
+                | ArrayElemTypeDef::Parenthesis(element),
+            ) => match Self::from_sql(element)? {
+                DataType::Array(_) => {
+                    return Err(DbError::SqlParse(format!(
+                        "Invalid type {}: arrays of arrays are not supported",
+                        data_type
+                    )))
+                }
+                element => DataType::Array(Box::new(element)),
+            },
+            other => {
+                // VECTOR(n) arrives as a custom type, and the spellings of
+                // NUMERIC, INTERVAL and UUID vary between parser versions,
+                // so these go by their names
+                let name = other.to_string().to_ascii_uppercase();
+                let (base, modifiers) = match name.find('(') {
+                    Some(at) => (name[..at].trim(), Some(&name[at..])),
+                    None => (name.trim(), None),
+                };
+                let modifiers: Option<Vec<&str>> = modifiers.and_then(|m| {
+                    let m = m.strip_prefix('(')?.strip_suffix(')')?;
+                    Some(m.split(',').map(str::trim).collect())
+                });
+                match base {
+                    "VECTOR" => {
+                        let dims = modifiers
+                            .and_then(|m| match m[..] {
+                                [dims] => dims.parse::<usize>().ok(),
+                                _ => None,
+                            })
+                            .filter(|n| (1..=MAX_VECTOR_DIMENSIONS).contains(n))
+                            .ok_or_else(|| {
+                                DbError::SqlParse(format!(
+                                    "Invalid type {}: VECTOR takes a dimension from 1 to {}",
+                                    other, MAX_VECTOR_DIMENSIONS
+                                ))
+                            })?;
+                        DataType::Vector(dims)
+                    }
+                    "NUMERIC" | "DECIMAL" | "DEC" => {
+                        let invalid = || {
+                            DbError::SqlParse(format!(
+                                "Invalid type {}: NUMERIC takes a precision from 1 to {} \
+                             and a scale from 0 to the precision",
+                                other,
+                                decimal::MAX_PRECISION
+                            ))
+                        };
+                        let Some(modifiers) = modifiers else {
+                            return Ok(DataType::Numeric(None));
+                        };
+                        let numbers = modifiers
+                            .iter()
+                            .map(|m| m.parse::<u32>().ok())
+                            .collect::<Option<Vec<u32>>>()
+                            .ok_or_else(invalid)?;
+                        let (precision, scale) = match numbers[..] {
+                            [precision] => (precision, 0),
+                            [precision, scale] => (precision, scale),
+                            _ => return Err(invalid()),
+                        };
+                        if !(1..=decimal::MAX_PRECISION).contains(&precision) || scale > precision {
+                            return Err(invalid());
+                        }
+                        DataType::Numeric(Some((precision, scale)))
+                    }
+                    "UUID" => DataType::Uuid,
+                    base if base.starts_with("INTERVAL") => DataType::Interval,
+                    _ => DataType::Text,
+                }
+            }
+        })
+    }
+}
+
+// Parse a type name as written in DDL, e.g. `NUMERIC(10,2)` or `TEXT[]`
+impl FromStr for DataType {
+    type Err = DbError;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut parser = Parser::new(&GenericDialect {})
+            .try_with_sql(text)
+            .map_err(|e| DbError::SqlParse(e.to_string()))?;
+        let data_type = parser
+            .parse_data_type()
+            .map_err(|e| DbError::SqlParse(e.to_string()))?;
+        if parser.peek_token().token != Token::EOF {
+            return Err(DbError::SqlParse(format!("Invalid type: {}", text.trim())));
+        }
+        Self::from_sql(&data_type)
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::Integer => write!(f, "INTEGER"),
+            DataType::BigInt => write!(f, "BIGINT"),
+            DataType::Float => write!(f, "FLOAT"),
+            DataType::Double => write!(f, "DOUBLE"),
+            DataType::Varchar(size) => write!(f, "VARCHAR({})", size),
+            DataType::Text => write!(f, "TEXT"),
+            DataType::Boolean => write!(f, "BOOLEAN"),
+            DataType::Date => write!(f, "DATE"),
+            DataType::Timestamp => write!(f, "TIMESTAMP"),
+            DataType::Vector(dims) => write!(f, "VECTOR({})", dims),
+            DataType::Numeric(None) => write!(f, "NUMERIC"),
+            DataType::Numeric(Some((precision, scale))) => {
+                write!(f, "NUMERIC({},{})", precision, scale)
+            }
+            DataType::Interval => write!(f, "INTERVAL"),
+            DataType::TimestampTz => write!(f, "TIMESTAMPTZ"),
+            DataType::Uuid => write!(f, "UUID"),
+            DataType::Array(element) => write!(f, "{}[]", element),
+        }
+    }
 }
 
 // Table schema
@@ -62,11 +441,68 @@ pub struct View {
     pub query: String,
 }
 
+// Index definition
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexDefinition {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    // Rows a partial index covers; `None` indexes every row
+    #[serde(default)]
+    pub predicate: Option<Predicate>,
+    // Graph parameters of an HNSW index over a vector column; `None` for a
+    // B+Tree index
+    #[serde(default)]
+    pub hnsw: Option<HnswOptions>,
+}
+
+// A single DDL change, as written to the catalog WAL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CatalogChange {
+    CreateTable(Schema),
+    AlterTable(Schema),
+    DropTable(String),
+    // Creates or replaces the view
+    CreateView(View),
+    DropView(String),
+    CreateIndex(IndexDefinition),
+    DropIndex(String),
+    // Replaces the statistics of a table
+    SetStatistics(TableStats),
+    // Creates or replaces the procedure
+    CreateProcedure(StoredProcedure),
+    DropProcedure(String),
+    // Creates or replaces the trigger
+    CreateTrigger(Trigger),
+    DropTrigger(String),
+    CreateForeignKey(ForeignKey),
+    DropForeignKey(String),
+}
+
 // Catalog manages database metadata
+//
+// A catalog created with `new` lives only in memory. One created with `open`
+// is stored in system tables and every change is WAL-logged before it is
+// applied. Each change bumps the catalog version, which callers caching plans
+// compare against to detect schema changes.
 #[derive(Clone)]
 pub struct Catalog {
     schemas: Arc<RwLock<HashMap<String, Schema>>>,
     views: Arc<RwLock<HashMap<String, View>>>,
+    indexes: Arc<RwLock<HashMap<String, IndexDefinition>>>,
+    statistics: Arc<RwLock<HashMap<String, Arc<TableStats>>>>,
+    procedures: Arc<RwLock<HashMap<String, StoredProcedure>>>,
+    triggers: Arc<RwLock<HashMap<String, Trigger>>>,
+    foreign_keys: Arc<RwLock<HashMap<String, ForeignKey>>>,
+    // Rows written to each table since it was last analyzed; not persisted
+    modified_rows: Arc<Mutex<HashMap<String, u64>>>,
+    // Catalog version at which each table was last created or altered
+    table_versions: Arc<RwLock<HashMap<String, u64>>>,
+    version: Arc<AtomicU64>,
+    // Serializes changes so versions are assigned in log order
+    ddl_lock: Arc<Mutex<()>>,
+    system: Option<Arc<SystemTables>>,
 }
 
 impl Catalog {
@@ -74,21 +510,119 @@ impl Catalog {
         Self {
             schemas: Arc::new(RwLock::new(HashMap::new())),
             views: Arc::new(RwLock::new(HashMap::new())),
+            indexes: Arc::new(RwLock::new(HashMap::new())),
+            statistics: Arc::new(RwLock::new(HashMap::new())),
+            procedures: Arc::new(RwLock::new(HashMap::new())),
+            triggers: Arc::new(RwLock::new(HashMap::new())),
+            foreign_keys: Arc::new(RwLock::new(HashMap::new())),
+            modified_rows: Arc::new(Mutex::new(HashMap::new())),
+            table_versions: Arc::new(RwLock::new(HashMap::new())),
+            version: Arc::new(AtomicU64::new(0)),
+            ddl_lock: Arc::new(Mutex::new(())),
+            system: None,
+        }
+    }
+
+    /// Open the persistent catalog stored in `store`'s system tables
+    ///
+    /// Changes logged to `wal_path` but not yet applied to the system tables
+    /// are redone first. Table storage is then reconciled with the catalog:
+    /// missing heap files are created and heap files of tables that no longer
+    /// exist are dropped.
+    pub fn open(store: Arc<TableStore>, wal_path: impl Into<PathBuf>) -> Result<Self> {
+        let system = SystemTables::open(store, wal_path.into())?;
+        let snapshot = system.recover()?;
+
+        let mut catalog = Self::new();
+        catalog.version.store(snapshot.version, Ordering::SeqCst);
+        for (schema, version) in snapshot.schemas {
+            catalog
+                .table_versions
+                .write()
+                .insert(schema.name.clone(), version);
+            catalog.schemas.write().insert(schema.name.clone(), schema);
+        }
+        for view in snapshot.views {
+            catalog.views.write().insert(view.name.clone(), view);
+        }
+        for index in snapshot.indexes {
+            catalog.indexes.write().insert(index.name.clone(), index);
+        }
+        for stats in snapshot.statistics {
+            catalog
+                .statistics
+                .write()
+                .insert(stats.table.clone(), Arc::new(stats));
         }
+        for procedure in snapshot.procedures {
+            catalog
+                .procedures
+                .write()
+                .insert(procedure.name.clone(), procedure);
+        }
+        for trigger in snapshot.triggers {
+            catalog
+                .triggers
+                .write()
+                .insert(trigger.name.clone(), trigger);
+        }
+        for fk in snapshot.foreign_keys {
+            catalog.foreign_keys.write().insert(fk.name.clone(), fk);
+        }
+
+        let store = system.store();
+        for name in catalog.list_tables() {
+            if !store.has_table(&name) {
+                store.create_table(&name)?;
+            }
+        }
+        for name in store.list_tables() {
+            if !is_system_table(&name) && !catalog.schemas.read().contains_key(&name) {
+                store.drop_table(&name)?;
+            }
+        }
+
+        catalog.system = Some(Arc::new(system));
+        Ok(catalog)
+    }
+
+    /// Current catalog version; changes whenever any DDL is applied
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    /// Catalog version at which `name` was last created or altered
+    pub fn table_version(&self, name: &str) -> Option<u64> {
+        self.table_versions.read().get(name).copied()
     }
 
     pub fn create_table(&self, schema: Schema) -> Result<()> {
-        let mut schemas = self.schemas.write();
+        if is_system_table(&schema.name) {
+            return Err(DbError::Catalog(format!(
+                "Table name {} is reserved for the system catalog",
+                schema.name
+            )));
+        }
 
-        if schemas.contains_key(&schema.name) {
+        let _ddl = self.ddl_lock.lock();
+        if self.schemas.read().contains_key(&schema.name) {
             return Err(DbError::Catalog(format!(
                 "Table {} already exists",
                 schema.name
             )));
         }
 
-        schemas.insert(schema.name.clone(), schema);
-        Ok(())
+        self.commit(CatalogChange::CreateTable(schema))
+    }
+
+    /// Replace the schema of an existing table in a single change
+    pub fn alter_table(&self, schema: Schema) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if !self.schemas.read().contains_key(&schema.name) {
+            return Err(DbError::Catalog(format!("Table {} not found", schema.name)));
+        }
+
+        self.commit(CatalogChange::AlterTable(schema))
     }
 
     pub fn get_table(&self, name: &str) -> Result<Schema> {
@@ -100,14 +634,14 @@ impl Catalog {
             .ok_or_else(|| DbError::Catalog(format!("Table {} not found", name)))
     }
 
+    /// Drop a table together with its indexes, statistics and foreign keys
     pub fn drop_table(&self, name: &str) -> Result<()> {
-        let mut schemas = self.schemas.write();
-
-        schemas
-            .remove(name)
-            .ok_or_else(|| DbError::Catalog(format!("Table {} not found", name)))?;
+        let _ddl = self.ddl_lock.lock();
+        if !self.schemas.read().contains_key(name) {
+            return Err(DbError::Catalog(format!("Table {} not found", name)));
+        }
 
-        Ok(())
+        self.commit(CatalogChange::DropTable(name.to_string()))
     }
 
     pub fn list_tables(&self) -> Vec<String> {
@@ -115,14 +649,18 @@ impl Catalog {
     }
 
     pub fn create_view(&self, name: String, query: String) -> Result<()> {
-        let mut views = self.views.write();
-
-        if views.contains_key(&name) {
+        let _ddl = self.ddl_lock.lock();
+        if self.views.read().contains_key(&name) {
             return Err(DbError::Catalog(format!("View {} already exists", name)));
         }
 
-        views.insert(name.clone(), View { name, query });
-        Ok(())
+        self.commit(CatalogChange::CreateView(View { name, query }))
+    }
+
+    /// Create a view, replacing any existing view of the same name
+    pub fn replace_view(&self, name: String, query: String) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        self.commit(CatalogChange::CreateView(View { name, query }))
     }
 
     pub fn get_view(&self, name: &str) -> Result<View> {
@@ -135,18 +673,289 @@ impl Catalog {
     }
 
     pub fn drop_view(&self, name: &str) -> Result<()> {
-        let mut views = self.views.write();
-
-        views
-            .remove(name)
-            .ok_or_else(|| DbError::Catalog(format!("View {} not found", name)))?;
+        let _ddl = self.ddl_lock.lock();
+        if !self.views.read().contains_key(name) {
+            return Err(DbError::Catalog(format!("View {} not found", name)));
+        }
 
-        Ok(())
+        self.commit(CatalogChange::DropView(name.to_string()))
     }
 
     pub fn list_views(&self) -> Vec<String> {
         self.views.read().keys().cloned().collect()
     }
+
+    pub fn create_index(&self, index: IndexDefinition) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if self.indexes.read().contains_key(&index.name) {
+            return Err(DbError::Catalog(format!(
+                "Index {} already exists",
+                index.name
+            )));
+        }
+
+        let schema = self.get_table(&index.table)?;
+        let predicate_columns = index.predicate.iter().flat_map(|p| p.columns());
+        if let Some(missing) = index
+            .columns
+            .iter()
+            .map(String::as_str)
+            .chain(predicate_columns)
+            .find(|c| schema.get_column_index(c).is_none())
+        {
+            return Err(DbError::Catalog(format!(
+                "Column {} not found in table {}",
+                missing, index.table
+            )));
+        }
+
+        self.commit(CatalogChange::CreateIndex(index))
+    }
+
+    pub fn get_index(&self, name: &str) -> Result<IndexDefinition> {
+        self.indexes
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| DbError::Catalog(format!("Index {} not found", name)))
+    }
+
+    pub fn drop_index(&self, name: &str) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if !self.indexes.read().contains_key(name) {
+            return Err(DbError::Catalog(format!("Index {} not found", name)));
+        }
+
+        self.commit(CatalogChange::DropIndex(name.to_string()))
+    }
+
+    /// Indexes defined on `table`
+    pub fn list_indexes(&self, table: &str) -> Vec<IndexDefinition> {
+        self.indexes
+            .read()
+            .values()
+            .filter(|index| index.table == table)
+            .cloned()
+            .collect()
+    }
+
+    /// Store the statistics ANALYZE gathered for a table, replacing any it
+    /// had
+    pub fn set_statistics(&self, stats: TableStats) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if !self.schemas.read().contains_key(&stats.table) {
+            return Err(DbError::Catalog(format!("Table {} not found", stats.table)));
+        }
+
+        self.commit(CatalogChange::SetStatistics(stats))
+    }
+
+    /// Statistics of `table`; `None` until it is analyzed
+    pub fn get_statistics(&self, table: &str) -> Option<Arc<TableStats>> {
+        self.statistics.read().get(table).cloned()
+    }
+
+    pub fn create_procedure(&self, procedure: StoredProcedure) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if self.procedures.read().contains_key(&procedure.name) {
+            return Err(DbError::Catalog(format!(
+                "Procedure {} already exists",
+                procedure.name
+            )));
+        }
+
+        self.commit(CatalogChange::CreateProcedure(procedure))
+    }
+
+    /// Create a procedure, replacing any existing procedure of the same name
+    pub fn replace_procedure(&self, procedure: StoredProcedure) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        self.commit(CatalogChange::CreateProcedure(procedure))
+    }
+
+    pub fn get_procedure(&self, name: &str) -> Result<StoredProcedure> {
+        self.procedures
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| DbError::Catalog(format!("Procedure {} not found", name)))
+    }
+
+    pub fn drop_procedure(&self, name: &str) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if !self.procedures.read().contains_key(name) {
+            return Err(DbError::Catalog(format!("Procedure {} not found", name)));
+        }
+
+        self.commit(CatalogChange::DropProcedure(name.to_string()))
+    }
+
+    pub fn list_procedures(&self) -> Vec<String> {
+        self.procedures.read().keys().cloned().collect()
+    }
+
+    pub fn create_trigger(&self, trigger: Trigger) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if self.triggers.read().contains_key(&trigger.name) {
+            return Err(DbError::Catalog(format!(
+                "Trigger {} already exists",
+                trigger.name
+            )));
+        }
+
+        self.commit(CatalogChange::CreateTrigger(trigger))
+    }
+
+    /// Create a trigger, replacing any existing trigger of the same name
+    pub fn replace_trigger(&self, trigger: Trigger) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        self.commit(CatalogChange::CreateTrigger(trigger))
+    }
+
+    pub fn get_trigger(&self, name: &str) -> Result<Trigger> {
+        self.triggers
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| DbError::Catalog(format!("Trigger {} not found", name)))
+    }
+
+    pub fn drop_trigger(&self, name: &str) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if !self.triggers.read().contains_key(name) {
+            return Err(DbError::Catalog(format!("Trigger {} not found", name)));
+        }
+
+        self.commit(CatalogChange::DropTrigger(name.to_string()))
+    }
+
+    pub fn list_triggers(&self) -> Vec<String> {
+        self.triggers.read().keys().cloned().collect()
+    }
+
+    /// Triggers on the table or view `table`, in order of name
+    pub fn table_triggers(&self, table: &str) -> Vec<Trigger> {
+        let mut triggers: Vec<Trigger> = self
+            .triggers
+            .read()
+            .values()
+            .filter(|trigger| trigger.table == table)
+            .cloned()
+            .collect();
+        triggers.sort_by(|a, b| a.name.cmp(&b.name));
+        triggers
+    }
+
+    pub fn create_foreign_key(&self, fk: ForeignKey) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if self.foreign_keys.read().contains_key(&fk.name) {
+            return Err(DbError::Catalog(format!(
+                "Constraint {} already exists",
+                fk.name
+            )));
+        }
+
+        self.commit(CatalogChange::CreateForeignKey(fk))
+    }
+
+    pub fn get_foreign_key(&self, name: &str) -> Result<ForeignKey> {
+        self.foreign_keys
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| DbError::Catalog(format!("Constraint {} not found", name)))
+    }
+
+    pub fn drop_foreign_key(&self, name: &str) -> Result<()> {
+        let _ddl = self.ddl_lock.lock();
+        if !self.foreign_keys.read().contains_key(name) {
+            return Err(DbError::Catalog(format!("Constraint {} not found", name)));
+        }
+
+        self.commit(CatalogChange::DropForeignKey(name.to_string()))
+    }
+
+    /// All foreign keys, in order of name
+    pub fn list_foreign_keys(&self) -> Vec<ForeignKey> {
+        let mut fks: Vec<ForeignKey> = self.foreign_keys.read().values().cloned().collect();
+        fks.sort_by(|a, b| a.name.cmp(&b.name));
+        fks
+    }
+
+    /// Count `rows` more rows written to `table` and return how many have
+    /// been written since it was last analyzed
+    pub fn record_modified_rows(&self, table: &str, rows: u64) -> u64 {
+        let mut modified = self.modified_rows.lock();
+        let count = modified.entry(table.to_string()).or_insert(0);
+        *count += rows;
+        *count
+    }
+
+    // Log and apply a validated change; the caller holds `ddl_lock`
+    fn commit(&self, change: CatalogChange) -> Result<()> {
+        let version = self.version() + 1;
+        if let Some(system) = &self.system {
+            system.persist(version, &change)?;
+        }
+
+        match change {
+            CatalogChange::CreateTable(schema) | CatalogChange::AlterTable(schema) => {
+                self.table_versions
+                    .write()
+                    .insert(schema.name.clone(), version);
+                self.schemas.write().insert(schema.name.clone(), schema);
+            }
+            CatalogChange::DropTable(name) => {
+                self.indexes.write().retain(|_, index| index.table != name);
+                self.foreign_keys.write().retain(|_, fk| fk.table != name);
+                self.statistics.write().remove(&name);
+                self.modified_rows.lock().remove(&name);
+                self.table_versions.write().remove(&name);
+                self.schemas.write().remove(&name);
+            }
+            CatalogChange::CreateView(view) => {
+                self.views.write().insert(view.name.clone(), view);
+            }
+            CatalogChange::DropView(name) => {
+                self.views.write().remove(&name);
+            }
+            CatalogChange::CreateIndex(index) => {
+                self.indexes.write().insert(index.name.clone(), index);
+            }
+            CatalogChange::DropIndex(name) => {
+                self.indexes.write().remove(&name);
+            }
+            CatalogChange::SetStatistics(stats) => {
+                self.modified_rows.lock().remove(&stats.table);
+                self.statistics
+                    .write()
+                    .insert(stats.table.clone(), Arc::new(stats));
+            }
+            CatalogChange::CreateProcedure(procedure) => {
+                self.procedures
+                    .write()
+                    .insert(procedure.name.clone(), procedure);
+            }
+            CatalogChange::DropProcedure(name) => {
+                self.procedures.write().remove(&name);
+            }
+            CatalogChange::CreateTrigger(trigger) => {
+                self.triggers.write().insert(trigger.name.clone(), trigger);
+            }
+            CatalogChange::DropTrigger(name) => {
+                self.triggers.write().remove(&name);
+            }
+            CatalogChange::CreateForeignKey(fk) => {
+                self.foreign_keys.write().insert(fk.name.clone(), fk);
+            }
+            CatalogChange::DropForeignKey(name) => {
+                self.foreign_keys.write().remove(&name);
+            }
+        }
+
+        self.version.store(version, Ordering::SeqCst);
+        Ok(())
+    }
 }
 
 impl Default for Catalog {
@@ -189,4 +998,275 @@ mod tests {
 
         Ok(())
     }
+
+    fn users_schema() -> Schema {
+        Schema::new(
+            "users".to_string(),
+            vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                    default: None,
+                },
+                Column {
+                    name: "email".to_string(),
+                    data_type: DataType::Varchar(255),
+                    nullable: true,
+                    default: Some("'none'".to_string()),
+                },
+            ],
+        )
+        .with_primary_key("id".to_string())
+    }
+
+    fn open_catalog(dir: &std::path::Path) -> Result<Catalog> {
+        let store = Arc::new(TableStore::open(
+            &dir.join("data").display().to_string(),
+            4096,
+            16,
+        )?);
+        Catalog::open(store, dir.join("wal").join("catalog.wal"))
+    }
+
+    #[test]
+    fn test_versions_track_ddl() -> Result<()> {
+        let catalog = Catalog::new();
+        assert_eq!(catalog.version(), 0);
+
+        catalog.create_table(users_schema())?;
+        let created = catalog.table_version("users").unwrap();
+
+        let mut altered = users_schema();
+        altered.columns.pop();
+        catalog.alter_table(altered)?;
+        assert!(catalog.table_version("users").unwrap() > created);
+        assert_eq!(catalog.get_table("users")?.columns.len(), 1);
+
+        catalog.drop_table("users")?;
+        assert_eq!(catalog.table_version("users"), None);
+        assert_eq!(catalog.version(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_table_names_reserved() {
+        let schema = Schema::new("sys_tables".to_string(), users_schema().columns);
+        assert!(Catalog::new().create_table(schema).is_err());
+    }
+
+    #[test]
+    fn test_catalog_survives_reopen() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        {
+            let catalog = open_catalog(dir.path())?;
+            catalog.create_table(users_schema())?;
+            catalog.create_table(Schema::new("scratch".to_string(), users_schema().columns))?;
+            catalog.create_view("active".to_string(), "SELECT * FROM users".to_string())?;
+            catalog.create_index(IndexDefinition {
+                name: "idx_users_email".to_string(),
+                table: "users".to_string(),
+                columns: vec!["email".to_string()],
+                unique: true,
+                predicate: Some(Predicate::IsNotNull("email".to_string())),
+                hnsw: None,
+            })?;
+            catalog.drop_table("scratch")?;
+        }
+
+        let catalog = open_catalog(dir.path())?;
+        assert_eq!(catalog.version(), 5);
+        assert_eq!(catalog.list_tables(), vec!["users".to_string()]);
+
+        let users = catalog.get_table("users")?;
+        assert_eq!(users.columns, users_schema().columns);
+        assert_eq!(users.primary_key.as_deref(), Some("id"));
+        assert_eq!(catalog.get_view("active")?.query, "SELECT * FROM users");
+        let index = catalog.get_index("idx_users_email")?;
+        assert!(index.unique);
+        assert_eq!(
+            index.predicate,
+            Some(Predicate::IsNotNull("email".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_foreign_keys_survive_reopen() -> Result<()> {
+        use crate::constraints::ReferentialAction;
+
+        let dir = tempfile::tempdir()?;
+        let fk = |name: &str, table: &str| ForeignKey {
+            name: name.to_string(),
+            table: table.to_string(),
+            columns: vec!["id".to_string()],
+            referenced_table: "users".to_string(),
+            referenced_columns: vec!["id".to_string()],
+            on_delete: ReferentialAction::Cascade,
+            on_update: ReferentialAction::NoAction,
+        };
+
+        {
+            let catalog = open_catalog(dir.path())?;
+            catalog.create_table(users_schema())?;
+            catalog.create_table(Schema::new("orders".to_string(), users_schema().columns))?;
+            catalog.create_table(Schema::new("scratch".to_string(), users_schema().columns))?;
+            catalog.create_foreign_key(fk("orders_id_fkey", "orders"))?;
+            catalog.create_foreign_key(fk("scratch_id_fkey", "scratch"))?;
+            assert!(catalog
+                .create_foreign_key(fk("orders_id_fkey", "orders"))
+                .is_err());
+            // Dropping a table drops its foreign keys
+            catalog.drop_table("scratch")?;
+        }
+
+        {
+            let catalog = open_catalog(dir.path())?;
+            let fks = catalog.list_foreign_keys();
+            assert_eq!(fks.len(), 1);
+            assert_eq!(fks[0].table, "orders");
+            assert!(matches!(fks[0].on_delete, ReferentialAction::Cascade));
+
+            catalog.drop_foreign_key("orders_id_fkey")?;
+            assert!(catalog.get_foreign_key("orders_id_fkey").is_err());
+        }
+
+        assert!(open_catalog(dir.path())?.list_foreign_keys().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_logged_change_redone_after_crash() -> Result<()> {
+        use crate::transaction::wal::{LogRecord, WALConfig, WALManager};
+
+        let dir = tempfile::tempdir()?;
+        let version = {
+            let catalog = open_catalog(dir.path())?;
+            catalog.create_table(users_schema())?;
+            catalog.version()
+        };
+
+        // A change that reached the log but not the system tables
+        let wal = WALManager::new(
+            dir.path().join("wal").join("catalog.wal"),
+            WALConfig::default(),
+        )?;
+        let change = CatalogChange::DropTable("users".to_string());
+        wal.append_durable(LogRecord::CatalogUpdate {
+            version: version + 1,
+            change: serde_json::to_vec(&change)?,
+        })?;
+        drop(wal);
+
+        let catalog = open_catalog(dir.path())?;
+        assert!(catalog.get_table("users").is_err());
+        assert_eq!(catalog.version(), version + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statistics_survive_reopen() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        // Enough values that the statistics span several system table rows
+        let stats = TableStats {
+            table: "users".to_string(),
+            row_count: 5000,
+            columns: vec![ColumnStats {
+                name: "email".to_string(),
+                null_fraction: 0.25,
+                distinct: 3000.0,
+                most_common: vec![(Value::String("a@example.com".to_string()), 0.01)],
+                histogram: (0..=100)
+                    .map(|i| Value::String(format!("user{:04}@example.com", i * 30)))
+                    .collect(),
+            }],
+        };
+
+        {
+            let catalog = open_catalog(dir.path())?;
+            catalog.create_table(users_schema())?;
+            assert!(catalog.get_statistics("users").is_none());
+            catalog.set_statistics(stats.clone())?;
+            assert_eq!(catalog.record_modified_rows("users", 10), 10);
+        }
+
+        let catalog = open_catalog(dir.path())?;
+        assert_eq!(catalog.get_statistics("users").as_deref(), Some(&stats));
+        assert_eq!(catalog.record_modified_rows("users", 1), 1);
+
+        let missing = TableStats {
+            table: "nope".to_string(),
+            ..stats
+        };
+        assert!(catalog.set_statistics(missing).is_err());
+
+        catalog.drop_table("users")?;
+        catalog.create_table(users_schema())?;
+        assert!(catalog.get_statistics("users").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_coerce_numeric_interval_uuid_array() -> Result<()> {
+        let text = |s: &str| Value::String(s.to_string());
+
+        let money = DataType::Numeric(Some((6, 2)));
+        assert_eq!(money.coerce(text("1234.565"))?.to_string(), "1234.57");
+        assert_eq!(money.coerce(Value::Float(0.1))?.to_string(), "0.10");
+        assert_eq!(money.coerce(Value::Integer(7))?.to_string(), "7.00");
+        assert!(money.coerce(text("12345.6")).is_err());
+        assert!(money.coerce(text("ten")).is_err());
+        assert_eq!(
+            DataType::Integer.coerce(DataType::Numeric(None).coerce(text("2.5"))?)?,
+            Value::Integer(3)
+        );
+
+        assert_eq!(
+            DataType::Interval
+                .coerce(text("1 day 2 hours"))?
+                .to_string(),
+            "1 day 02:00:00"
+        );
+        assert!(DataType::Interval.coerce(Value::Integer(1)).is_err());
+        assert_eq!(
+            DataType::TimestampTz.coerce(text("2024-01-01 02:00:00+02"))?,
+            DataType::TimestampTz.coerce(text("2024-01-01 00:00:00"))?
+        );
+        assert!(DataType::Uuid.coerce(text("not-a-uuid")).is_err());
+
+        let ints = DataType::Array(Box::new(DataType::Integer));
+        assert_eq!(
+            ints.coerce(text("{1, 2, NULL}"))?,
+            Value::Array(vec![Value::Integer(1), Value::Integer(2), Value::Null])
+        );
+        assert!(ints.coerce(text("{1,x}")).is_err());
+        assert!(ints
+            .coerce(Value::Array(vec![Value::Array(vec![Value::Integer(1)])]))
+            .is_err());
+        assert_eq!(ints.to_string(), "INTEGER[]");
+        assert_eq!(money.to_string(), "NUMERIC(6,2)");
+        Ok(())
+    }
+    #[test]
+    fn test_data_type_from_str() -> Result<()> {
+        for (text, data_type) in [
+            ("int", DataType::Integer),
+            ("DOUBLE PRECISION", DataType::Double),
+            ("varchar(40)", DataType::Varchar(40)),
+            ("NUMERIC(10, 2)", DataType::Numeric(Some((10, 2)))),
+            ("timestamp with time zone", DataType::TimestampTz),
+            ("INTERVAL", DataType::Interval),
+            ("uuid", DataType::Uuid),
+            ("VECTOR(3)", DataType::Vector(3)),
+            ("TEXT[]", DataType::Array(Box::new(DataType::Text))),
+        ] {
+            assert_eq!(text.parse::<DataType>()?, data_type, "{}", text);
+            assert_eq!(data_type.to_string().parse::<DataType>()?, data_type);
+        }
+        for invalid in ["NUMERIC(4,5)", "VECTOR(0)", "INT[][]", "INT NOT NULL"] {
+            assert!(invalid.parse::<DataType>().is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
 }
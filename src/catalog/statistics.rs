@@ -0,0 +1,244 @@
+// Planner statistics gathered by ANALYZE
+//
+// For each column of a table ANALYZE records the fraction of NULLs, an
+// estimate of the number of distinct values, the most common values with the
+// fraction of rows holding each, and the bounds of an equi-depth histogram
+// over the remaining values. The planner turns these into selectivities for
+// predicates comparing a column with a constant and for equi-joins.
+
+use crate::common::{Value, MICROS_PER_DAY};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+// Selectivity of a range over a column whose histogram cannot place it
+const DEFAULT_RANGE_SELECTIVITY: f64 = 0.33;
+
+/// Statistics of one table, as of its last ANALYZE
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableStats {
+    pub table: String,
+    /// Rows in the table when it was analyzed
+    pub row_count: u64,
+    pub columns: Vec<ColumnStats>,
+}
+
+impl TableStats {
+    pub fn column(&self, name: &str) -> Option<&ColumnStats> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+/// Value distribution of one column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStats {
+    pub name: String,
+    /// Fraction of rows where the column is NULL
+    pub null_fraction: f64,
+    /// Estimated number of distinct non-NULL values
+    pub distinct: f64,
+    /// Most common values and the fraction of rows holding each, most
+    /// common first
+    pub most_common: Vec<(Value, f64)>,
+    /// Ascending bounds of equally populated buckets over the values not in
+    /// `most_common`; empty when there are too few such values
+    pub histogram: Vec<Value>,
+}
+
+impl ColumnStats {
+    /// Fraction of rows where the column equals `value`
+    pub fn equal_selectivity(&self, value: &Value) -> f64 {
+        if value.is_null() {
+            return 0.0;
+        }
+        if let Some((_, frequency)) = self
+            .most_common
+            .iter()
+            .find(|(common, _)| common.sql_eq(value) == Some(true))
+        {
+            return *frequency;
+        }
+
+        // The other values share the rows the common ones leave evenly
+        let others = (self.distinct - self.most_common.len() as f64).max(1.0);
+        (self.other_fraction() / others).clamp(0.0, 1.0)
+    }
+
+    /// Fraction of rows where the column lies between `lower` and `upper`
+    pub fn range_selectivity(&self, lower: Bound<&Value>, upper: Bound<&Value>) -> f64 {
+        let common: f64 = self
+            .most_common
+            .iter()
+            .filter(|(value, _)| within(value, lower, upper))
+            .map(|(_, frequency)| frequency)
+            .sum();
+
+        let low = match lower {
+            Bound::Unbounded => Some(0.0),
+            Bound::Included(value) | Bound::Excluded(value) => self.histogram_position(value),
+        };
+        let high = match upper {
+            Bound::Unbounded => Some(1.0),
+            Bound::Included(value) | Bound::Excluded(value) => self.histogram_position(value),
+        };
+        let others = match (low, high) {
+            (Some(low), Some(high)) => (high - low).max(0.0),
+            _ => DEFAULT_RANGE_SELECTIVITY,
+        };
+
+        (common + others * self.other_fraction()).clamp(0.0, 1.0)
+    }
+
+    /// Fraction of rows where the column is NULL, or with `negated` is not
+    pub fn null_selectivity(&self, negated: bool) -> f64 {
+        if negated {
+            1.0 - self.null_fraction
+        } else {
+            self.null_fraction
+        }
+    }
+
+    /// Fraction of the pairs of rows of two tables whose columns are equal
+    pub fn join_selectivity(&self, other: &ColumnStats) -> f64 {
+        let distinct = self.distinct.max(other.distinct).max(1.0);
+        (1.0 - self.null_fraction) * (1.0 - other.null_fraction) / distinct
+    }
+
+    // Fraction of rows that are neither NULL nor one of the common values
+    fn other_fraction(&self) -> f64 {
+        let common: f64 = self
+            .most_common
+            .iter()
+            .map(|(_, frequency)| frequency)
+            .sum();
+        (1.0 - self.null_fraction - common).max(0.0)
+    }
+
+    // Fraction of the histogram's values below `value`, interpolating
+    // within a bucket when the values are numbers or times
+    fn histogram_position(&self, value: &Value) -> Option<f64> {
+        let bounds = &self.histogram;
+        if bounds.len() < 2 {
+            return None;
+        }
+        let buckets = (bounds.len() - 1) as f64;
+
+        if value.sql_cmp(&bounds[0])? != Ordering::Greater {
+            return Some(0.0);
+        }
+        if value.sql_cmp(&bounds[bounds.len() - 1])? != Ordering::Less {
+            return Some(1.0);
+        }
+
+        // bounds[bucket] < value < bounds[bucket + 1], or equal to the latter
+        let mut bucket = 0;
+        for (i, bound) in bounds.iter().enumerate().skip(1) {
+            if value.sql_cmp(bound)? != Ordering::Greater {
+                bucket = i - 1;
+                break;
+            }
+        }
+        let within = match (
+            ordinal(&bounds[bucket]),
+            ordinal(value),
+            ordinal(&bounds[bucket + 1]),
+        ) {
+            (Some(low), Some(value), Some(high)) if high > low => (value - low) / (high - low),
+            _ => 0.5,
+        };
+        Some((bucket as f64 + within.clamp(0.0, 1.0)) / buckets)
+    }
+}
+
+// Whether `value` lies between the bounds
+fn within(value: &Value, lower: Bound<&Value>, upper: Bound<&Value>) -> bool {
+    let above = match lower {
+        Bound::Unbounded => true,
+        Bound::Included(low) => matches!(
+            value.sql_cmp(low),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        Bound::Excluded(low) => value.sql_cmp(low) == Some(Ordering::Greater),
+    };
+    let below = match upper {
+        Bound::Unbounded => true,
+        Bound::Included(high) => {
+            matches!(value.sql_cmp(high), Some(Ordering::Less | Ordering::Equal))
+        }
+        Bound::Excluded(high) => value.sql_cmp(high) == Some(Ordering::Less),
+    };
+    above && below
+}
+
+// Position of a value on a number line, for interpolating within a bucket
+fn ordinal(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(_) | Value::Float(_) | Value::Numeric(_) => value.as_f64(),
+        Value::Date(days) => Some(days.saturating_mul(MICROS_PER_DAY) as f64),
+        Value::Timestamp(micros) | Value::TimestampTz(micros) => Some(*micros as f64),
+        Value::Interval(interval) => Some(interval.span() as f64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A hundred distinct values spread evenly over 0..100, with 7 in a fifth
+    // of the rows and NULL in a tenth
+    fn column() -> ColumnStats {
+        ColumnStats {
+            name: "n".to_string(),
+            null_fraction: 0.1,
+            distinct: 100.0,
+            most_common: vec![(Value::Integer(7), 0.2)],
+            histogram: (0..=10).map(|i| Value::Integer(i * 10)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_equal_selectivity() {
+        let stats = column();
+        assert_eq!(stats.equal_selectivity(&Value::Integer(7)), 0.2);
+        let other = stats.equal_selectivity(&Value::Integer(50));
+        assert!((other - 0.7 / 99.0).abs() < 1e-9);
+        assert_eq!(stats.equal_selectivity(&Value::Null), 0.0);
+    }
+
+    #[test]
+    fn test_range_selectivity() {
+        let stats = column();
+        let below_50 =
+            stats.range_selectivity(Bound::Unbounded, Bound::Excluded(&Value::Integer(50)));
+        assert!((below_50 - (0.2 + 0.5 * 0.7)).abs() < 1e-9);
+
+        let above_75 =
+            stats.range_selectivity(Bound::Included(&Value::Integer(75)), Bound::Unbounded);
+        assert!((above_75 - 0.25 * 0.7).abs() < 1e-9);
+
+        // Outside the histogram nothing but the common value matches
+        let below_0 =
+            stats.range_selectivity(Bound::Unbounded, Bound::Excluded(&Value::Integer(0)));
+        assert_eq!(below_0, 0.0);
+        let around_7 = stats.range_selectivity(
+            Bound::Included(&Value::Integer(7)),
+            Bound::Included(&Value::Integer(7)),
+        );
+        assert!((around_7 - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_join_selectivity() {
+        let stats = column();
+        let other = ColumnStats {
+            name: "m".to_string(),
+            null_fraction: 0.0,
+            distinct: 400.0,
+            most_common: Vec::new(),
+            histogram: Vec::new(),
+        };
+        assert!((stats.join_selectivity(&other) - 0.9 / 400.0).abs() < 1e-12);
+        assert!((stats.null_selectivity(true) - 0.9).abs() < 1e-12);
+    }
+}
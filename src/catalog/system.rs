@@ -0,0 +1,446 @@
+// System tables backing the persistent catalog
+//
+// Every catalog change is first appended to the catalog WAL and forced to
+// disk, then applied to the system tables in the table store. At startup the
+// system tables are loaded and any WAL records newer than the catalog version
+// they record are redone, so a crash between the two steps loses nothing.
+// Applying a change is idempotent: each change replaces the rows describing
+// the objects it touches.
+
+use super::{CatalogChange, Column, DataType, IndexDefinition, Schema, TableStats, View};
+use crate::common::Value;
+use crate::constraints::ForeignKey;
+use crate::error::DbError;
+use crate::procedures::StoredProcedure;
+use crate::storage::{RowId, TableStore};
+use crate::transaction::wal::{LogRecord, WALConfig, WALManager};
+use crate::triggers::Trigger;
+use crate::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+// sys_tables(name, version, primary_key)
+pub const SYS_TABLES: &str = "sys_tables";
+// sys_columns(table_name, position, name, data_type, nullable, default)
+pub const SYS_COLUMNS: &str = "sys_columns";
+// sys_views(name, query)
+pub const SYS_VIEWS: &str = "sys_views";
+// sys_indexes(name, table_name, columns, unique, predicate, hnsw)
+pub const SYS_INDEXES: &str = "sys_indexes";
+// sys_statistics(table_name, part, statistics); the statistics of a table
+// are JSON split over numbered rows, as they may not fit in one
+pub const SYS_STATISTICS: &str = "sys_statistics";
+// sys_procedures(name, part, definition); the definition is JSON split over
+// numbered rows like statistics
+pub const SYS_PROCEDURES: &str = "sys_procedures";
+// sys_triggers(name, part, definition), stored like procedures
+pub const SYS_TRIGGERS: &str = "sys_triggers";
+// sys_foreign_keys(name, table_name, definition)
+pub const SYS_FOREIGN_KEYS: &str = "sys_foreign_keys";
+// sys_catalog(key, value); holds the catalog version
+pub const SYS_CATALOG: &str = "sys_catalog";
+
+pub const SYSTEM_TABLES: [&str; 9] = [
+    SYS_TABLES,
+    SYS_COLUMNS,
+    SYS_VIEWS,
+    SYS_INDEXES,
+    SYS_STATISTICS,
+    SYS_PROCEDURES,
+    SYS_TRIGGERS,
+    SYS_FOREIGN_KEYS,
+    SYS_CATALOG,
+];
+
+const VERSION_KEY: &str = "version";
+
+// Bytes of JSON stored per row of sys_statistics, sys_procedures and
+// sys_triggers
+const PART_SIZE: usize = 2048;
+
+pub fn is_system_table(name: &str) -> bool {
+    SYSTEM_TABLES.iter().any(|t| t.eq_ignore_ascii_case(name))
+}
+
+// Catalog contents read back from the system tables
+#[derive(Default)]
+pub(crate) struct CatalogSnapshot {
+    pub version: u64,
+    pub schemas: Vec<(Schema, u64)>,
+    pub views: Vec<View>,
+    pub indexes: Vec<IndexDefinition>,
+    pub statistics: Vec<TableStats>,
+    pub procedures: Vec<StoredProcedure>,
+    pub triggers: Vec<Trigger>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+pub(crate) struct SystemTables {
+    store: Arc<TableStore>,
+    wal: WALManager,
+}
+
+impl SystemTables {
+    pub fn open(store: Arc<TableStore>, wal_path: PathBuf) -> Result<Self> {
+        for name in SYSTEM_TABLES {
+            if !store.has_table(name) {
+                store.create_table(name)?;
+            }
+        }
+
+        if let Some(parent) = wal_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // DDL is rare and must be durable before it returns
+        let config = WALConfig {
+            enable_group_commit: false,
+            ..WALConfig::default()
+        };
+        let wal = WALManager::new(wal_path, config)?;
+
+        Ok(Self { store, wal })
+    }
+
+    pub fn store(&self) -> &Arc<TableStore> {
+        &self.store
+    }
+
+    // Redo logged changes missing from the system tables, then load them
+    pub fn recover(&self) -> Result<CatalogSnapshot> {
+        let mut version = self.load_version()?;
+
+        for entry in self.wal.read_from(0)? {
+            if let LogRecord::CatalogUpdate {
+                version: logged,
+                change,
+            } = entry.record
+            {
+                if logged > version {
+                    let change: CatalogChange = serde_json::from_slice(&change)?;
+                    self.apply(logged, &change)?;
+                    version = logged;
+                }
+            }
+        }
+        self.store.flush()?;
+
+        // Everything in the log is now reflected in the system tables
+        self.wal.truncate(self.wal.current_lsn())?;
+
+        self.load()
+    }
+
+    // Make `change` durable: log it, then write it to the system tables
+    pub fn persist(&self, version: u64, change: &CatalogChange) -> Result<()> {
+        let payload = serde_json::to_vec(change)?;
+        self.wal.append_durable(LogRecord::CatalogUpdate {
+            version,
+            change: payload,
+        })?;
+        self.apply(version, change)?;
+        self.store.flush()
+    }
+
+    fn apply(&self, version: u64, change: &CatalogChange) -> Result<()> {
+        match change {
+            CatalogChange::CreateTable(schema) | CatalogChange::AlterTable(schema) => {
+                self.delete_table_rows(&schema.name)?;
+                self.insert_schema(schema, version)?;
+            }
+            CatalogChange::DropTable(name) => {
+                self.delete_table_rows(name)?;
+                self.delete_where(SYS_INDEXES, |row| row[1] == *name)?;
+                self.delete_where(SYS_STATISTICS, |row| row[0] == *name)?;
+                self.delete_where(SYS_FOREIGN_KEYS, |row| row[1] == *name)?;
+            }
+            CatalogChange::CreateView(view) => {
+                self.delete_where(SYS_VIEWS, |row| row[0] == view.name)?;
+                self.insert_text(SYS_VIEWS, &[view.name.clone(), view.query.clone()])?;
+            }
+            CatalogChange::DropView(name) => {
+                self.delete_where(SYS_VIEWS, |row| row[0] == *name)?;
+            }
+            CatalogChange::CreateIndex(index) => {
+                self.delete_where(SYS_INDEXES, |row| row[0] == index.name)?;
+                self.insert_text(
+                    SYS_INDEXES,
+                    &[
+                        index.name.clone(),
+                        index.table.clone(),
+                        serde_json::to_string(&index.columns)?,
+                        index.unique.to_string(),
+                        serde_json::to_string(&index.predicate)?,
+                        serde_json::to_string(&index.hnsw)?,
+                    ],
+                )?;
+            }
+            CatalogChange::DropIndex(name) => {
+                self.delete_where(SYS_INDEXES, |row| row[0] == *name)?;
+            }
+            CatalogChange::SetStatistics(stats) => {
+                self.delete_where(SYS_STATISTICS, |row| row[0] == stats.table)?;
+                self.insert_parts(SYS_STATISTICS, &stats.table, &serde_json::to_string(stats)?)?;
+            }
+            CatalogChange::CreateProcedure(procedure) => {
+                self.delete_where(SYS_PROCEDURES, |row| row[0] == procedure.name)?;
+                self.insert_parts(
+                    SYS_PROCEDURES,
+                    &procedure.name,
+                    &serde_json::to_string(procedure)?,
+                )?;
+            }
+            CatalogChange::DropProcedure(name) => {
+                self.delete_where(SYS_PROCEDURES, |row| row[0] == *name)?;
+            }
+            CatalogChange::CreateTrigger(trigger) => {
+                self.delete_where(SYS_TRIGGERS, |row| row[0] == trigger.name)?;
+                self.insert_parts(
+                    SYS_TRIGGERS,
+                    &trigger.name,
+                    &serde_json::to_string(trigger)?,
+                )?;
+            }
+            CatalogChange::DropTrigger(name) => {
+                self.delete_where(SYS_TRIGGERS, |row| row[0] == *name)?;
+            }
+            CatalogChange::CreateForeignKey(fk) => {
+                self.delete_where(SYS_FOREIGN_KEYS, |row| row[0] == fk.name)?;
+                self.insert_text(
+                    SYS_FOREIGN_KEYS,
+                    &[fk.name.clone(), fk.table.clone(), serde_json::to_string(fk)?],
+                )?;
+            }
+            CatalogChange::DropForeignKey(name) => {
+                self.delete_where(SYS_FOREIGN_KEYS, |row| row[0] == *name)?;
+            }
+        }
+
+        self.delete_where(SYS_CATALOG, |row| row[0] == VERSION_KEY)?;
+        self.insert_text(SYS_CATALOG, &[VERSION_KEY.to_string(), version.to_string()])?;
+        Ok(())
+    }
+
+    fn insert_schema(&self, schema: &Schema, version: u64) -> Result<()> {
+        self.insert_text(
+            SYS_TABLES,
+            &[
+                schema.name.clone(),
+                version.to_string(),
+                schema.primary_key.clone().unwrap_or_default(),
+            ],
+        )?;
+
+        for (position, column) in schema.columns.iter().enumerate() {
+            self.insert_text(
+                SYS_COLUMNS,
+                &[
+                    schema.name.clone(),
+                    position.to_string(),
+                    column.name.clone(),
+                    serde_json::to_string(&column.data_type)?,
+                    column.nullable.to_string(),
+                    serde_json::to_string(&column.default)?,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn delete_table_rows(&self, name: &str) -> Result<()> {
+        self.delete_where(SYS_TABLES, |row| row[0] == name)?;
+        self.delete_where(SYS_COLUMNS, |row| row[0] == name)
+    }
+
+    fn delete_where(&self, table: &str, predicate: impl Fn(&[String]) -> bool) -> Result<()> {
+        for (rid, row) in self.scan_text(table)? {
+            if predicate(&row) {
+                self.store.delete_row(table, rid)?;
+            }
+        }
+        Ok(())
+    }
+
+    // System table fields are stored as text
+    fn insert_text(&self, table: &str, fields: &[String]) -> Result<()> {
+        let row: Vec<Value> = fields.iter().cloned().map(Value::String).collect();
+        self.store.insert_row(table, &row)?;
+        Ok(())
+    }
+
+    // Store `json` as rows of (key, part, text)
+    fn insert_parts(&self, table: &str, key: &str, json: &str) -> Result<()> {
+        for (part, text) in split_text(json, PART_SIZE).into_iter().enumerate() {
+            self.insert_text(
+                table,
+                &[key.to_string(), part.to_string(), text.to_string()],
+            )?;
+        }
+        Ok(())
+    }
+
+    // The JSON stored by `insert_parts` under each key of `table`
+    fn load_parts(&self, table: &str) -> Result<Vec<String>> {
+        let mut parts: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+        for (_, row) in self.scan_text(table)? {
+            check_width(&row, 3, table)?;
+            parts
+                .entry(row[0].clone())
+                .or_default()
+                .push((parse_field(&row[1], table)?, row[2].clone()));
+        }
+        Ok(parts
+            .into_values()
+            .map(|mut key_parts| {
+                key_parts.sort_by_key(|(part, _)| *part);
+                key_parts.into_iter().map(|(_, text)| text).collect()
+            })
+            .collect())
+    }
+
+    fn scan_text(&self, table: &str) -> Result<Vec<(RowId, Vec<String>)>> {
+        Ok(self
+            .store
+            .scan(table)?
+            .into_iter()
+            .map(|(rid, row)| (rid, row.iter().map(Value::to_display_string).collect()))
+            .collect())
+    }
+
+    fn load_version(&self) -> Result<u64> {
+        match self
+            .scan_text(SYS_CATALOG)?
+            .into_iter()
+            .find(|(_, row)| row[0] == VERSION_KEY)
+        {
+            Some((_, row)) => parse_field(&row[1], SYS_CATALOG),
+            None => Ok(0),
+        }
+    }
+
+    fn load(&self) -> Result<CatalogSnapshot> {
+        let mut columns: HashMap<String, Vec<(usize, Column)>> = HashMap::new();
+        for (_, row) in self.scan_text(SYS_COLUMNS)? {
+            check_width(&row, 6, SYS_COLUMNS)?;
+            let data_type: DataType = serde_json::from_str(&row[3])?;
+            let default: Option<String> = serde_json::from_str(&row[5])?;
+            columns.entry(row[0].clone()).or_default().push((
+                parse_field(&row[1], SYS_COLUMNS)?,
+                Column {
+                    name: row[2].clone(),
+                    data_type,
+                    nullable: parse_field(&row[4], SYS_COLUMNS)?,
+                    default,
+                },
+            ));
+        }
+
+        let mut schemas = Vec::new();
+        for (_, row) in self.scan_text(SYS_TABLES)? {
+            check_width(&row, 3, SYS_TABLES)?;
+            let mut table_columns = columns.remove(&row[0]).unwrap_or_default();
+            table_columns.sort_by_key(|(position, _)| *position);
+
+            let mut schema = Schema::new(
+                row[0].clone(),
+                table_columns.into_iter().map(|(_, c)| c).collect(),
+            );
+            if !row[2].is_empty() {
+                schema = schema.with_primary_key(row[2].clone());
+            }
+            schemas.push((schema, parse_field(&row[1], SYS_TABLES)?));
+        }
+
+        let mut views = Vec::new();
+        for (_, row) in self.scan_text(SYS_VIEWS)? {
+            check_width(&row, 2, SYS_VIEWS)?;
+            views.push(View {
+                name: row[0].clone(),
+                query: row[1].clone(),
+            });
+        }
+
+        let mut indexes = Vec::new();
+        for (_, row) in self.scan_text(SYS_INDEXES)? {
+            // Rows written before vector indexes existed lack the hnsw field
+            if row.len() != 5 {
+                check_width(&row, 6, SYS_INDEXES)?;
+            }
+            indexes.push(IndexDefinition {
+                name: row[0].clone(),
+                table: row[1].clone(),
+                columns: serde_json::from_str(&row[2])?,
+                unique: parse_field(&row[3], SYS_INDEXES)?,
+                predicate: serde_json::from_str(&row[4])?,
+                hnsw: match row.get(5) {
+                    Some(field) => serde_json::from_str(field)?,
+                    None => None,
+                },
+            });
+        }
+
+        let mut statistics = Vec::new();
+        for json in self.load_parts(SYS_STATISTICS)? {
+            statistics.push(serde_json::from_str(&json)?);
+        }
+        let mut procedures = Vec::new();
+        for json in self.load_parts(SYS_PROCEDURES)? {
+            procedures.push(serde_json::from_str(&json)?);
+        }
+        let mut triggers = Vec::new();
+        for json in self.load_parts(SYS_TRIGGERS)? {
+            triggers.push(serde_json::from_str(&json)?);
+        }
+
+        let mut foreign_keys = Vec::new();
+        for (_, row) in self.scan_text(SYS_FOREIGN_KEYS)? {
+            check_width(&row, 3, SYS_FOREIGN_KEYS)?;
+            foreign_keys.push(serde_json::from_str(&row[2])?);
+        }
+
+        Ok(CatalogSnapshot {
+            version: self.load_version()?,
+            schemas,
+            views,
+            indexes,
+            statistics,
+            procedures,
+            triggers,
+            foreign_keys,
+        })
+    }
+}
+
+// Split `text` into pieces of at most `size` bytes, on character boundaries
+fn split_text(text: &str, size: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (part, tail) = rest.split_at(end);
+        parts.push(part);
+        rest = tail;
+    }
+    parts
+}
+
+fn check_width(row: &[String], width: usize, table: &str) -> Result<()> {
+    if row.len() != width {
+        return Err(DbError::Corruption(format!(
+            "{} row has {} fields, expected {}",
+            table,
+            row.len(),
+            width
+        )));
+    }
+    Ok(())
+}
+
+fn parse_field<T: std::str::FromStr>(value: &str, table: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| DbError::Corruption(format!("Invalid value '{}' in {}", value, table)))
+}
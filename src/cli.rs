@@ -3,13 +3,17 @@
 // Interactive SQL client for RustyDB.
 // Connects to a RustyDB server and allows executing SQL queries.
 
+use bytes::BytesMut;
 use rusty_db::error::DbError;
-use rusty_db::execution::QueryResult;
-use rusty_db::network::protocol::{Request, Response};
+use rusty_db::network::protocol::{
+    encode_frame, read_frame, Request, Response, INITIAL_BUFFER_SIZE, MAX_BINCODE_SIZE,
+    PROTOCOL_VERSION,
+};
 use rusty_db::Result;
 use rusty_db::VERSION;
 use std::io;
-use tokio::io::{stdin, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{stdin, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
 
 #[tokio::main]
@@ -26,15 +30,45 @@ async fn main() -> Result<()> {
     let addr = "127.0.0.1:5432";
     println!("Connecting to RustyDB server at {}...", addr);
 
-    let mut stream = TcpStream::connect(addr)
+    let stream = TcpStream::connect(addr)
         .await
         .map_err(|e| DbError::Network(format!("Failed to connect: {}", e)))?;
+    let (mut reader, mut writer) = stream.into_split();
+    let mut read_buf = BytesMut::with_capacity(INITIAL_BUFFER_SIZE);
+
+    send(
+        &mut writer,
+        &Request::Startup {
+            protocol_version: PROTOCOL_VERSION,
+            user: std::env::var("USER").unwrap_or_else(|_| "rustydb".to_string()),
+            application_name: Some("rusty-db-cli".to_string()),
+        },
+    )
+    .await?;
+    match read_frame(&mut reader, &mut read_buf, MAX_BINCODE_SIZE).await? {
+        Some(Response::Ready { server_version, .. }) => {
+            println!(
+                "Connected successfully! (server version {})",
+                server_version
+            );
+        }
+        Some(Response::Error(msg)) => {
+            return Err(DbError::Network(format!(
+                "Server refused connection: {}",
+                msg
+            )));
+        }
+        _ => {
+            return Err(DbError::Network(
+                "Unexpected handshake response".to_string(),
+            ));
+        }
+    }
 
-    println!("Connected successfully!");
-    println!("Type SQL commands or 'exit' to quit.");
+    println!("Type SQL commands or 'exit' to quit. Ctrl-C cancels a running query.");
     println!();
 
-    let mut reader = BufReader::new(stdin());
+    let mut input_reader = BufReader::new(stdin());
     let mut input = String::new();
 
     loop {
@@ -42,7 +76,7 @@ async fn main() -> Result<()> {
         io::Write::flush(&mut io::stdout())?;
 
         input.clear();
-        reader
+        input_reader
             .read_line(&mut input)
             .await
             .map_err(|e| DbError::Io(e.into()))?;
@@ -59,37 +93,84 @@ async fn main() -> Result<()> {
         }
 
         // Send query
-        let request = Request::Query {
-            sql: cmd.to_string(),
-        };
-        let request_bytes = bincode::encode_to_vec(&request, bincode::config::standard())
-            .map_err(|e| DbError::Serialization(e.to_string()))?;
-
-        stream
-            .write_all(&request_bytes)
-            .await
-            .map_err(|e| DbError::Network(e.to_string()))?;
-
-        // Read response
-        let mut buffer = vec![0u8; 8192];
-        let n = stream
-            .read(&mut buffer)
-            .await
-            .map_err(|e| DbError::Network(e.to_string()))?;
-
-        if n == 0 {
+        send(
+            &mut writer,
+            &Request::Query {
+                sql: cmd.to_string(),
+            },
+        )
+        .await?;
+
+        if !print_response(&mut reader, &mut writer, &mut read_buf).await? {
             println!("Connection closed by server");
             break;
         }
 
-        let response: Response =
-            bincode::decode_from_slice(&buffer[..n], bincode::config::standard())
-                .map(|(r, _)| r)
-                .map_err(|e| DbError::Serialization(e.to_string()))?;
+        println!();
+    }
+
+    Ok(())
+}
+
+async fn send(writer: &mut OwnedWriteHalf, request: &Request) -> Result<()> {
+    let mut frame = BytesMut::new();
+    encode_frame(request, &mut frame)?;
+    writer
+        .write_all(&frame)
+        .await
+        .map_err(|e| DbError::Network(e.to_string()))
+}
+
+// Print one query's response as it streams in; false if the server hung up
+async fn print_response(
+    reader: &mut OwnedReadHalf,
+    writer: &mut OwnedWriteHalf,
+    read_buf: &mut BytesMut,
+) -> Result<bool> {
+    let mut cancel_sent = false;
+
+    loop {
+        let response = tokio::select! {
+            response = read_frame(reader, read_buf, MAX_BINCODE_SIZE) => response?,
+            _ = tokio::signal::ctrl_c(), if !cancel_sent => {
+                send(writer, &Request::Cancel).await?;
+                cancel_sent = true;
+                continue;
+            }
+        };
+        let Some(response) = response else {
+            return Ok(false);
+        };
 
         match response {
-            Response::QueryResult(result) => {
-                print_result(&result);
+            Response::RowDescription { columns, .. } => {
+                // Print column headers
+                for col in &columns {
+                    print!("{:20}", col);
+                }
+                println!();
+
+                // Print separator
+                for _ in &columns {
+                    print!("{}", "-".repeat(20));
+                }
+                println!();
+                continue;
+            }
+            Response::RowBatch { rows } => {
+                for row in &rows {
+                    for value in row {
+                        print!("{:20}", value.to_display_string());
+                    }
+                    println!();
+                }
+                continue;
+            }
+            Response::CommandComplete { rows_affected } => {
+                println!("{} row(s) affected", rows_affected);
+            }
+            Response::Cancelled => {
+                println!("Query cancelled");
             }
             Response::Ok => {
                 println!("OK");
@@ -103,36 +184,15 @@ async fn main() -> Result<()> {
             Response::Pong => {
                 println!("PONG");
             }
-        }
-
-        println!();
-    }
-
-    Ok(())
-}
-
-fn print_result(result: &QueryResult) {
-    if !result.columns.is_empty() {
-        // Print column headers
-        for col in &result.columns {
-            print!("{:20}", col);
-        }
-        println!();
-
-        // Print separator
-        for _ in &result.columns {
-            print!("{}", "-".repeat(20));
-        }
-        println!();
-
-        // Print rows
-        for row in &result.rows {
-            for value in row {
-                print!("{:20}", value);
+            Response::ParameterDescription { param_types } => {
+                println!("Prepared with {} parameter(s)", param_types.len());
+            }
+            Response::Ready { .. } => {
+                return Err(DbError::Network(
+                    "Unexpected handshake response".to_string(),
+                ));
             }
-            println!();
         }
+        return Ok(true);
     }
-
-    println!("{} row(s) affected", result.rows_affected);
 }
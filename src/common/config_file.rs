@@ -0,0 +1,398 @@
+// # Configuration File
+//
+// Loads `rustydb.toml` into a `DatabaseConfig`. The file has one table per
+// settings group (`[storage]`, `[network]`, ...) and every key is optional:
+// anything left out keeps its default. Durations are written as whole
+// seconds or milliseconds, named by a `_secs` / `_ms` suffix.
+//
+// ```toml
+// [storage]
+// data_dir = "data"
+// page_size = 8192
+//
+// [network]
+// port = 5432
+//
+// [firewall]
+// mode = "Enforcing"
+// trusted_roles = ["admin"]
+// ```
+
+use super::{DatabaseConfig, IsolationLevel};
+use crate::error::{DbError, Result};
+use crate::security::sql_firewall::{FirewallAction, FirewallMode};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    storage: StorageSection,
+    transaction: TransactionSection,
+    network: NetworkSection,
+    security: SecuritySection,
+    firewall: FirewallSection,
+    clustering: ClusteringSection,
+    performance: PerformanceSection,
+    monitoring: MonitoringSection,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StorageSection {
+    data_dir: Option<String>,
+    wal_dir: Option<String>,
+    page_size: Option<usize>,
+    buffer_pool_size: Option<usize>,
+    checkpoint_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TransactionSection {
+    default_isolation: Option<IsolationLevel>,
+    lock_timeout_secs: Option<u64>,
+    deadlock_detection_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct NetworkSection {
+    listen_address: Option<String>,
+    port: Option<u16>,
+    // 0 disables the PostgreSQL listener
+    pg_port: Option<u16>,
+    api_port: Option<u16>,
+    enable_rest_api: Option<bool>,
+    max_connections: Option<usize>,
+    connection_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SecuritySection {
+    enable_tls: Option<bool>,
+    enable_encryption: Option<bool>,
+    password_min_length: Option<usize>,
+    session_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FirewallSection {
+    mode: Option<FirewallMode>,
+    action: Option<FirewallAction>,
+    trusted_roles: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ClusteringSection {
+    cluster_enabled: Option<bool>,
+    node_id: Option<String>,
+    seed_nodes: Option<Vec<String>>,
+    replication_factor: Option<usize>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PerformanceSection {
+    worker_threads: Option<usize>,
+    enable_jit: Option<bool>,
+    enable_vectorization: Option<bool>,
+    // 0 disables the timeout
+    query_timeout_secs: Option<u64>,
+    max_memory_mb: Option<usize>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct MonitoringSection {
+    enable_metrics: Option<bool>,
+    metrics_port: Option<u16>,
+    slow_query_threshold_ms: Option<u64>,
+}
+
+fn set<T>(field: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *field = value;
+    }
+}
+
+fn secs(value: Option<u64>) -> Option<Duration> {
+    value.map(Duration::from_secs)
+}
+
+impl ConfigFile {
+    fn apply(self, config: &mut DatabaseConfig) {
+        let storage = self.storage;
+        set(&mut config.data_dir, storage.data_dir);
+        set(&mut config.wal_dir, storage.wal_dir);
+        set(&mut config.page_size, storage.page_size);
+        set(&mut config.buffer_pool_size, storage.buffer_pool_size);
+        set(
+            &mut config.checkpoint_interval,
+            secs(storage.checkpoint_interval_secs),
+        );
+
+        let transaction = self.transaction;
+        set(&mut config.default_isolation, transaction.default_isolation);
+        set(
+            &mut config.lock_timeout,
+            secs(transaction.lock_timeout_secs),
+        );
+        set(
+            &mut config.deadlock_detection_interval,
+            secs(transaction.deadlock_detection_interval_secs),
+        );
+
+        let network = self.network;
+        set(&mut config.listen_address, network.listen_address);
+        set(&mut config.port, network.port);
+        set(&mut config.pg_port, network.pg_port);
+        set(&mut config.api_port, network.api_port);
+        set(&mut config.enable_rest_api, network.enable_rest_api);
+        set(&mut config.max_connections, network.max_connections);
+        set(
+            &mut config.connection_timeout,
+            secs(network.connection_timeout_secs),
+        );
+
+        let security = self.security;
+        set(&mut config.enable_tls, security.enable_tls);
+        set(&mut config.enable_encryption, security.enable_encryption);
+        set(
+            &mut config.password_min_length,
+            security.password_min_length,
+        );
+        set(
+            &mut config.session_timeout,
+            secs(security.session_timeout_secs),
+        );
+
+        let firewall = self.firewall;
+        set(&mut config.firewall.mode, firewall.mode);
+        set(&mut config.firewall.action, firewall.action);
+        if let Some(roles) = firewall.trusted_roles {
+            config.firewall.trusted_roles = roles.into_iter().collect();
+        }
+
+        let clustering = self.clustering;
+        set(&mut config.cluster_enabled, clustering.cluster_enabled);
+        set(&mut config.node_id, clustering.node_id);
+        set(&mut config.seed_nodes, clustering.seed_nodes);
+        set(
+            &mut config.replication_factor,
+            clustering.replication_factor,
+        );
+
+        let performance = self.performance;
+        set(&mut config.worker_threads, performance.worker_threads);
+        set(&mut config.enable_jit, performance.enable_jit);
+        set(
+            &mut config.enable_vectorization,
+            performance.enable_vectorization,
+        );
+        if let Some(timeout) = performance.query_timeout_secs {
+            config.query_timeout = (timeout > 0).then(|| Duration::from_secs(timeout));
+        }
+        set(&mut config.max_memory_mb, performance.max_memory_mb);
+
+        let monitoring = self.monitoring;
+        set(&mut config.enable_metrics, monitoring.enable_metrics);
+        set(&mut config.metrics_port, monitoring.metrics_port);
+        set(
+            &mut config.slow_query_threshold,
+            monitoring
+                .slow_query_threshold_ms
+                .map(Duration::from_millis),
+        );
+    }
+
+    fn from_config(config: &DatabaseConfig) -> Self {
+        Self {
+            storage: StorageSection {
+                data_dir: Some(config.data_dir.clone()),
+                wal_dir: Some(config.wal_dir.clone()),
+                page_size: Some(config.page_size),
+                buffer_pool_size: Some(config.buffer_pool_size),
+                checkpoint_interval_secs: Some(config.checkpoint_interval.as_secs()),
+            },
+            transaction: TransactionSection {
+                default_isolation: Some(config.default_isolation),
+                lock_timeout_secs: Some(config.lock_timeout.as_secs()),
+                deadlock_detection_interval_secs: Some(
+                    config.deadlock_detection_interval.as_secs(),
+                ),
+            },
+            network: NetworkSection {
+                listen_address: Some(config.listen_address.clone()),
+                port: Some(config.port),
+                pg_port: Some(config.pg_port),
+                api_port: Some(config.api_port),
+                enable_rest_api: Some(config.enable_rest_api),
+                max_connections: Some(config.max_connections),
+                connection_timeout_secs: Some(config.connection_timeout.as_secs()),
+            },
+            security: SecuritySection {
+                enable_tls: Some(config.enable_tls),
+                enable_encryption: Some(config.enable_encryption),
+                password_min_length: Some(config.password_min_length),
+                session_timeout_secs: Some(config.session_timeout.as_secs()),
+            },
+            firewall: FirewallSection {
+                mode: Some(config.firewall.mode),
+                action: Some(config.firewall.action),
+                trusted_roles: Some({
+                    let mut roles: Vec<String> =
+                        config.firewall.trusted_roles.iter().cloned().collect();
+                    roles.sort();
+                    roles
+                }),
+            },
+            clustering: ClusteringSection {
+                cluster_enabled: Some(config.cluster_enabled),
+                node_id: Some(config.node_id.clone()),
+                seed_nodes: Some(config.seed_nodes.clone()),
+                replication_factor: Some(config.replication_factor),
+            },
+            performance: PerformanceSection {
+                worker_threads: Some(config.worker_threads),
+                enable_jit: Some(config.enable_jit),
+                enable_vectorization: Some(config.enable_vectorization),
+                query_timeout_secs: Some(config.query_timeout.map_or(0, |d| d.as_secs())),
+                max_memory_mb: Some(config.max_memory_mb),
+            },
+            monitoring: MonitoringSection {
+                enable_metrics: Some(config.enable_metrics),
+                metrics_port: Some(config.metrics_port),
+                slow_query_threshold_ms: Some(config.slow_query_threshold.as_millis() as u64),
+            },
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// Parse a configuration file's contents, starting from the defaults
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        Self::parse(contents)
+            .map_err(|e| DbError::Configuration(format!("invalid configuration: {}", e)))
+    }
+
+    fn parse(contents: &str) -> std::result::Result<Self, toml::de::Error> {
+        let file: ConfigFile = toml::from_str(contents)?;
+        let mut config = Self::default();
+        file.apply(&mut config);
+        Ok(config)
+    }
+
+    /// Load a configuration file
+    ///
+    /// Relative `data_dir` and `wal_dir` paths are taken relative to the
+    /// directory holding the file, not the process's working directory.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            DbError::Configuration(format!("cannot read {}: {}", path.display(), e))
+        })?;
+        let mut config = Self::parse(&contents)
+            .map_err(|e| DbError::Configuration(format!("{}: {}", path.display(), e)))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        for dir in [&mut config.data_dir, &mut config.wal_dir] {
+            if Path::new(dir.as_str()).is_relative() {
+                *dir = base.join(dir.as_str()).display().to_string();
+            }
+        }
+        Ok(config)
+    }
+
+    /// Render the configuration in the format `load` reads back
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string(&ConfigFile::from_config(self))
+            .map_err(|e| DbError::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_keys_keep_defaults() {
+        let config = DatabaseConfig::from_toml_str(
+            r#"
+            [storage]
+            page_size = 4096
+
+            [network]
+            port = 6543
+            pg_port = 0
+            enable_rest_api = false
+
+            [transaction]
+            default_isolation = "Serializable"
+
+            [performance]
+            query_timeout_secs = 0
+
+            # Sections this server does not know about are ignored
+            [instance]
+            name = "default"
+            "#,
+        )
+        .unwrap();
+
+        let defaults = DatabaseConfig::default();
+        assert_eq!(config.page_size, 4096);
+        assert_eq!(config.port, 6543);
+        assert_eq!(config.pg_port, 0);
+        assert!(!config.enable_rest_api);
+        assert_eq!(config.default_isolation, IsolationLevel::Serializable);
+        assert_eq!(config.query_timeout, None);
+        assert_eq!(config.buffer_pool_size, defaults.buffer_pool_size);
+        assert_eq!(config.api_port, defaults.api_port);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config = DatabaseConfig {
+            data_dir: "/srv/rustydb/data".to_string(),
+            seed_nodes: vec!["10.0.0.2:7000".to_string()],
+            slow_query_threshold: Duration::from_millis(250),
+            ..DatabaseConfig::default()
+        };
+
+        let parsed = DatabaseConfig::from_toml_str(&config.to_toml_string().unwrap()).unwrap();
+        assert_eq!(parsed.data_dir, config.data_dir);
+        assert_eq!(parsed.seed_nodes, config.seed_nodes);
+        assert_eq!(parsed.slow_query_threshold, config.slow_query_threshold);
+        assert_eq!(parsed.query_timeout, config.query_timeout);
+    }
+
+    #[test]
+    fn test_firewall_section() {
+        let config = DatabaseConfig::from_toml_str(
+            r#"
+            [firewall]
+            mode = "Enforcing"
+            trusted_roles = ["admin", "dba"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.firewall.mode, FirewallMode::Enforcing);
+        assert_eq!(config.firewall.action, FirewallAction::Block);
+        assert!(config.firewall.trusted_roles.contains("dba"));
+
+        let parsed = DatabaseConfig::from_toml_str(&config.to_toml_string().unwrap()).unwrap();
+        assert_eq!(parsed.firewall.mode, FirewallMode::Enforcing);
+        assert_eq!(parsed.firewall.trusted_roles, config.firewall.trusted_roles);
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let err = DatabaseConfig::from_toml_str("[network]\nport = \"high\"\n").unwrap_err();
+        assert!(matches!(err, DbError::Configuration(_)));
+    }
+}
@@ -0,0 +1,536 @@
+// Exact decimal numbers
+//
+// A `Decimal` is an integer mantissa scaled by a power of ten, the value of
+// a NUMERIC column. It holds up to 38 significant digits. Addition,
+// subtraction and multiplication are exact while the result fits in 38
+// digits; division, and rounding to fewer places, round halves away from
+// zero as NUMERIC does in PostgreSQL.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Neg;
+
+/// Most digits a decimal holds, and the largest NUMERIC precision
+pub const MAX_PRECISION: u32 = 38;
+
+// Fewest decimal places a quotient keeps
+const DIVISION_SCALE: u32 = 16;
+
+// One more than the largest mantissa
+const MANTISSA_LIMIT: u128 = 10u128.pow(MAX_PRECISION);
+
+/// A decimal number, `mantissa / 10^scale`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+fn pow10(exponent: u32) -> Option<u128> {
+    10u128.checked_pow(exponent)
+}
+
+// Number of digits in `n`, 0 for 0
+fn digits(mut n: u128) -> u32 {
+    let mut count = 0;
+    while n > 0 {
+        n /= 10;
+        count += 1;
+    }
+    count
+}
+
+// `n / divisor` with halves rounded up
+fn div_round(n: u128, divisor: u128) -> u128 {
+    let (quotient, remainder) = (n / divisor, n % divisor);
+    if remainder >= divisor - remainder {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        mantissa: 0,
+        scale: 0,
+    };
+
+    /// `mantissa / 10^scale`, if it has at most 38 digits and places
+    pub fn new(mantissa: i128, scale: u32) -> Option<Self> {
+        Self::from_parts(mantissa < 0, mantissa.unsigned_abs(), scale)
+    }
+
+    fn from_parts(negative: bool, magnitude: u128, scale: u32) -> Option<Self> {
+        if magnitude >= MANTISSA_LIMIT || scale > MAX_PRECISION {
+            return None;
+        }
+        let mantissa = magnitude as i128;
+        Some(Self {
+            mantissa: if negative { -mantissa } else { mantissa },
+            scale,
+        })
+    }
+
+    // Like `from_parts`, but places that do not fit are rounded away
+    fn rounded(negative: bool, magnitude: u128, scale: u32) -> Option<Self> {
+        let excess = digits(magnitude)
+            .saturating_sub(MAX_PRECISION)
+            .max(scale.saturating_sub(MAX_PRECISION))
+            .min(scale);
+        let mut magnitude = match pow10(excess) {
+            Some(unit) => div_round(magnitude, unit),
+            None => 0,
+        };
+        let mut scale = scale - excess;
+        // Rounding up can carry into a 39th digit, leaving a trailing zero
+        if magnitude >= MANTISSA_LIMIT && scale > 0 {
+            magnitude /= 10;
+            scale -= 1;
+        }
+        Self::from_parts(negative, magnitude, scale)
+    }
+
+    pub fn from_i64(value: i64) -> Self {
+        Self {
+            mantissa: value as i128,
+            scale: 0,
+        }
+    }
+
+    /// Decimal of the shortest text that reads back as `value`
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        Self::parse(&value.to_string())
+    }
+
+    /// Parse a number such as `-12.50`, `.5` or `1.5e3`
+    ///
+    /// Places beyond the 38th are rounded away. `None` if the text is not a
+    /// number or has more than 38 digits before its decimal point.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (number, exponent) = match unsigned.find(['e', 'E']) {
+            Some(at) => (&unsigned[..at], unsigned[at + 1..].parse::<i32>().ok()?),
+            None => (unsigned, 0),
+        };
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        if !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+
+        let all_digits = format!("{}{}", whole, fraction);
+        let all_digits = all_digits.trim_start_matches('0').as_bytes();
+        let scale = fraction.len() as i64 - exponent as i64;
+        // Digits past the 38th place are dropped, rounding on the first
+        let dropped = (scale - MAX_PRECISION as i64).max(0) as usize;
+        let kept = all_digits.len().saturating_sub(dropped);
+        let mut magnitude: u128 = 0;
+        for digit in &all_digits[..kept] {
+            magnitude = magnitude
+                .checked_mul(10)?
+                .checked_add((digit - b'0') as u128)?;
+        }
+        if dropped > 0 && all_digits.len() >= dropped && all_digits[kept] >= b'5' {
+            magnitude += 1;
+        }
+        let scale = scale - dropped as i64;
+        if magnitude == 0 {
+            return Some(Self {
+                mantissa: 0,
+                scale: scale.clamp(0, MAX_PRECISION as i64) as u32,
+            });
+        }
+        if scale < 0 {
+            let lift = pow10(u32::try_from(-scale).ok()?)?;
+            return Self::from_parts(negative, magnitude.checked_mul(lift)?, 0);
+        }
+        Self::rounded(negative, magnitude, scale as u32)
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// Decimal places
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa < 0
+    }
+
+    /// Digits of the mantissa, without leading zeros
+    pub fn precision(&self) -> u32 {
+        digits(self.mantissa.unsigned_abs())
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            mantissa: self.mantissa.abs(),
+            scale: self.scale,
+        }
+    }
+
+    /// The same number without trailing fractional zeros
+    pub fn normalized(&self) -> Self {
+        let mut normalized = *self;
+        while normalized.scale > 0 && normalized.mantissa % 10 == 0 {
+            normalized.mantissa /= 10;
+            normalized.scale -= 1;
+        }
+        normalized
+    }
+
+    /// Nearest float
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or_default()
+    }
+
+    /// Nearest integer, halves rounded away from zero
+    pub fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self.rescale(0)?.mantissa).ok()
+    }
+
+    /// The number with `scale` places, halves rounded away from zero when
+    /// places are dropped; `None` if it no longer fits in 38 digits
+    pub fn rescale(&self, scale: u32) -> Option<Self> {
+        let magnitude = self.mantissa.unsigned_abs();
+        let magnitude = if scale >= self.scale {
+            magnitude.checked_mul(pow10(scale - self.scale)?)?
+        } else {
+            div_round(magnitude, pow10(self.scale - scale)?)
+        };
+        Self::from_parts(self.is_negative(), magnitude, scale)
+    }
+
+    /// Round to `places` decimal places; negative places round to tens,
+    /// hundreds and so on
+    pub fn round(&self, places: i32) -> Option<Self> {
+        if places >= 0 {
+            return self.rescale((places as u32).min(MAX_PRECISION));
+        }
+        let magnitude = match pow10(self.scale + places.unsigned_abs()) {
+            Some(unit) => div_round(self.mantissa.unsigned_abs(), unit)
+                .checked_mul(pow10(places.unsigned_abs())?)?,
+            // Every decimal is less than half of such a unit
+            None => 0,
+        };
+        Self::from_parts(self.is_negative(), magnitude, 0)
+    }
+
+    /// Largest integer not above the number
+    pub fn floor(&self) -> Self {
+        self.whole(self.is_negative())
+    }
+
+    /// Smallest integer not below the number
+    pub fn ceil(&self) -> Self {
+        self.whole(!self.is_negative())
+    }
+
+    // The integer part, moved away from zero if there is a fraction and
+    // `away_from_zero`
+    fn whole(&self, away_from_zero: bool) -> Self {
+        let unit = 10u128.pow(self.scale);
+        let magnitude = self.mantissa.unsigned_abs();
+        let mut whole = magnitude / unit;
+        if away_from_zero && magnitude % unit != 0 {
+            whole += 1;
+        }
+        Self::from_parts(self.is_negative(), whole, 0).unwrap_or(*self)
+    }
+
+    /// The number as a NUMERIC(precision, scale) column stores it, rounded
+    /// to `scale` places; `None` if it then has more than `precision` digits
+    pub fn fit(&self, precision: u32, scale: u32) -> Option<Self> {
+        let fitted = self.rescale(scale)?;
+        (fitted.precision() <= precision).then_some(fitted)
+    }
+
+    // Both mantissas at the larger of the two scales
+    fn aligned(&self, other: &Self) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        let lift = |d: &Self| d.mantissa.checked_mul(10i128.checked_pow(scale - d.scale)?);
+        Some((lift(self)?, lift(other)?, scale))
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        let (a, b, scale) = self.aligned(other)?;
+        let sum = a.checked_add(b)?;
+        Self::rounded(sum < 0, sum.unsigned_abs(), scale)
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        self.checked_add(&-*other)
+    }
+
+    /// Product with the places of both sides, as many as fit
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        let product = |a: &Self, b: &Self| {
+            let magnitude = a
+                .mantissa
+                .unsigned_abs()
+                .checked_mul(b.mantissa.unsigned_abs())?;
+            Self::rounded(
+                a.is_negative() != b.is_negative(),
+                magnitude,
+                a.scale + b.scale,
+            )
+        };
+        // Trailing zeros only cost digits, so drop them if the product
+        // would not fit otherwise
+        product(self, other).or_else(|| product(&self.normalized(), &other.normalized()))
+    }
+
+    /// Quotient with at least 16 places and as many as either side has;
+    /// `None` when dividing by zero or the quotient does not fit
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() {
+            return None;
+        }
+        let scale = self.scale.max(other.scale).max(DIVISION_SCALE);
+        let (dividend, divisor) = (self.mantissa.unsigned_abs(), other.mantissa.unsigned_abs());
+        // self / other = dividend / divisor * 10^(other.scale - self.scale),
+        // so `scale` places take this many digits after the integer part of
+        // dividend / divisor
+        let wanted = scale + other.scale - self.scale;
+
+        // Digit of remainder * 10 / divisor, leaving the new remainder;
+        // adding ten times keeps within u128 where multiplying might not
+        let next_digit = |remainder: &mut u128| {
+            let base = *remainder;
+            let mut digit = 0;
+            *remainder = 0;
+            for _ in 0..10 {
+                *remainder += base;
+                if *remainder >= divisor {
+                    *remainder -= divisor;
+                    digit += 1;
+                }
+            }
+            digit
+        };
+        let mut quotient = dividend / divisor;
+        let mut remainder = dividend % divisor;
+        let mut places = 0;
+        while places < wanted {
+            let mut next = remainder;
+            let digit = next_digit(&mut next);
+            match quotient.checked_mul(10).map(|q| q + digit) {
+                Some(q) if q < MANTISSA_LIMIT => {
+                    quotient = q;
+                    remainder = next;
+                    places += 1;
+                }
+                _ => break,
+            }
+        }
+        if next_digit(&mut remainder) >= 5 {
+            quotient += 1;
+        }
+
+        let negative = self.is_negative() != other.is_negative();
+        let scale = places as i64 + self.scale as i64 - other.scale as i64;
+        if scale < 0 {
+            let lift = pow10(scale.unsigned_abs() as u32)?;
+            return Self::from_parts(negative, quotient.checked_mul(lift)?, 0);
+        }
+        Self::rounded(negative, quotient, scale as u32)
+    }
+
+    /// Remainder of truncating division, with the sign of `self`
+    pub fn checked_rem(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() {
+            return None;
+        }
+        let (a, b, scale) = self.aligned(other)?;
+        Self::new(a % b, scale)
+    }
+
+    // Compare the absolute values
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.mantissa.unsigned_abs(), other.mantissa.unsigned_abs());
+        match self.scale.cmp(&other.scale) {
+            Ordering::Equal => a.cmp(&b),
+            // A mantissa too large to lift exceeds every mantissa
+            Ordering::Less => pow10(other.scale - self.scale)
+                .and_then(|lift| a.checked_mul(lift))
+                .map_or(Ordering::Greater, |a| a.cmp(&b)),
+            Ordering::Greater => pow10(self.scale - other.scale)
+                .and_then(|lift| b.checked_mul(lift))
+                .map_or(Ordering::Less, |b| a.cmp(&b)),
+        }
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal {
+            mantissa: -self.mantissa,
+            scale: self.scale,
+        }
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalized();
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.is_negative(), other.is_negative()) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (true, true) => self.cmp_magnitude(other).reverse(),
+            (false, false) => self.cmp_magnitude(other),
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(dec("12.50").to_string(), "12.50");
+        assert_eq!(dec("-0.05").to_string(), "-0.05");
+        assert_eq!(dec(".5").to_string(), "0.5");
+        assert_eq!(dec("+7").to_string(), "7");
+        assert_eq!(dec("1.5e3").to_string(), "1500");
+        assert_eq!(dec("25e-3").to_string(), "0.025");
+        assert_eq!(dec("-0").to_string(), "0");
+        assert_eq!(dec(&"9".repeat(38)).precision(), 38);
+        assert!(Decimal::parse(&"9".repeat(39)).is_none());
+        assert!(Decimal::parse("1.2.3").is_none());
+        assert!(Decimal::parse("abc").is_none());
+        assert!(Decimal::parse(".").is_none());
+        // Places past the 38th are rounded away
+        assert_eq!(
+            dec(&format!("0.{}5", "0".repeat(38))).to_string(),
+            format!("0.{}1", "0".repeat(37))
+        );
+        assert_eq!(Decimal::from_f64(0.1).unwrap(), dec("0.1"));
+        assert!(Decimal::from_f64(f64::NAN).is_none());
+    }
+
+    #[test]
+    fn test_exact_arithmetic() {
+        assert_eq!(dec("0.1").checked_add(&dec("0.2")).unwrap(), dec("0.3"));
+        assert_eq!(
+            dec("1.10").checked_sub(&dec("2")).unwrap().to_string(),
+            "-0.90"
+        );
+        assert_eq!(
+            dec("1.10").checked_mul(&dec("2.5")).unwrap().to_string(),
+            "2.750"
+        );
+        assert_eq!(
+            dec("10").checked_div(&dec("3")).unwrap().to_string(),
+            "3.3333333333333333"
+        );
+        assert_eq!(
+            dec("2").checked_div(&dec("3")).unwrap().to_string(),
+            "0.6666666666666667"
+        );
+        assert_eq!(dec("-7.5").checked_div(&dec("2.5")).unwrap(), dec("-3"));
+        assert_eq!(dec("1").checked_div(&dec("0.01")).unwrap(), dec("100"));
+        assert!(dec("1").checked_div(&Decimal::ZERO).is_none());
+        assert_eq!(dec("7.5").checked_rem(&dec("2")).unwrap(), dec("1.5"));
+        assert_eq!(dec("-7.5").checked_rem(&dec("2")).unwrap(), dec("-1.5"));
+        let big = dec(&"9".repeat(38));
+        assert!(big.checked_add(&dec("1")).is_none());
+        assert!(big.checked_mul(&dec("10")).is_none());
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(dec("2.345").rescale(2).unwrap().to_string(), "2.35");
+        assert_eq!(dec("-2.345").rescale(2).unwrap().to_string(), "-2.35");
+        assert_eq!(dec("2.344").rescale(2).unwrap().to_string(), "2.34");
+        assert_eq!(dec("2.5").rescale(3).unwrap().to_string(), "2.500");
+        assert_eq!(dec("1234.5").round(-2).unwrap().to_string(), "1200");
+        assert_eq!(dec("1250").round(-2).unwrap().to_string(), "1300");
+        assert_eq!(dec("-2.5").to_i64(), Some(-3));
+        assert_eq!(dec("-2.5").floor().to_string(), "-3");
+        assert_eq!(dec("-2.5").ceil().to_string(), "-2");
+        assert_eq!(dec("2.01").ceil().to_string(), "3");
+        assert_eq!(dec("123.456").fit(5, 2).unwrap().to_string(), "123.46");
+        assert!(dec("1234.5").fit(5, 2).is_none());
+        assert!(dec("999.995").fit(5, 2).is_none());
+    }
+
+    #[test]
+    fn test_ordering_and_hashing() {
+        use std::collections::HashSet;
+
+        assert_eq!(dec("1.50"), dec("1.5"));
+        assert!(dec("-2") < dec("-1.5"));
+        assert!(dec("0.1") < dec("0.25"));
+        assert!(dec(&"9".repeat(38)) > dec("0.5"));
+        assert!(dec("-0.001") < Decimal::ZERO);
+        let set: HashSet<Decimal> = [dec("1.50"), dec("1.5"), dec("1.500")]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 1);
+    }
+}
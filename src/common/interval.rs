@@ -0,0 +1,389 @@
+// Time intervals
+//
+// An `Interval` keeps months, days and microseconds apart, as PostgreSQL
+// does, because a month has no fixed length: adding a month to January 31
+// lands on the last day of February. Intervals compare by their length with
+// a month counted as 30 days, so `1 mon` equals `30 days`.
+
+use super::MICROS_PER_DAY;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+const DAYS_PER_MONTH: i64 = 30;
+
+/// A span of months, days and microseconds
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+// `value` truncated, if it fits in an i32
+fn whole_i32(value: f64) -> Option<i32> {
+    (value.abs() <= i32::MAX as f64).then_some(value as i32)
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Self {
+        Self {
+            months,
+            days,
+            micros,
+        }
+    }
+
+    // An interval of fractional fields, each fraction spilling into the
+    // next smaller field
+    fn fractional(months: f64, days: f64, micros: f64) -> Option<Self> {
+        let days = days + months.fract() * DAYS_PER_MONTH as f64;
+        let micros = (micros + days.fract() * MICROS_PER_DAY as f64).round();
+        if !micros.is_finite() || micros.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        Some(Self {
+            months: whole_i32(months.trunc())?,
+            days: whole_i32(days.trunc())?,
+            micros: micros as i64,
+        })
+    }
+
+    /// Length in microseconds, with a month counted as 30 days
+    pub fn span(&self) -> i128 {
+        (self.months as i128 * DAYS_PER_MONTH as i128 + self.days as i128) * MICROS_PER_DAY as i128
+            + self.micros as i128
+    }
+
+    /// The interval of length `span`, in months of 30 days, days and
+    /// microseconds
+    pub fn from_span(span: i128) -> Option<Self> {
+        let day = MICROS_PER_DAY as i128;
+        let month = DAYS_PER_MONTH as i128 * day;
+        Some(Self::new(
+            i32::try_from(span / month).ok()?,
+            i32::try_from(span % month / day).ok()?,
+            (span % day) as i64,
+        ))
+    }
+
+    /// Parse an interval such as `1 year 2 mons`, `3 days 04:05:06`,
+    /// `-1.5 hours`, `2 weeks ago` or the ISO 8601 form `P1Y2M3DT4H5M6S`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some(iso) = text.strip_prefix(['P', 'p']) {
+            return Self::parse_iso(iso);
+        }
+        let mut interval = Interval::default();
+        let mut tokens = text.split_whitespace().peekable();
+        let mut parsed = false;
+        while let Some(token) = tokens.next() {
+            if token.eq_ignore_ascii_case("ago") && parsed && tokens.peek().is_none() {
+                return interval.checked_neg();
+            }
+            let field = if token.contains(':') {
+                Self::parse_time(token)?
+            } else {
+                let number: f64 = token.parse().ok()?;
+                // A bare number counts days before a time and seconds
+                // otherwise
+                let unit = match tokens.peek() {
+                    Some(unit) if unit.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                        tokens.next()?
+                    }
+                    Some(time) if time.contains(':') => "day",
+                    _ => "second",
+                };
+                Self::of_unit(number, unit)?
+            };
+            interval = interval.checked_add(&field)?;
+            parsed = true;
+        }
+        parsed.then_some(interval)
+    }
+
+    // `number` of `unit`
+    fn of_unit(number: f64, unit: &str) -> Option<Self> {
+        if !number.is_finite() {
+            return None;
+        }
+        let micros = |per: i64| Self::fractional(0.0, 0.0, number * per as f64);
+        match unit.to_ascii_lowercase().as_str() {
+            "century" | "centuries" => Self::fractional(number * 1200.0, 0.0, 0.0),
+            "decade" | "decades" => Self::fractional(number * 120.0, 0.0, 0.0),
+            "y" | "yr" | "yrs" | "year" | "years" => Self::fractional(number * 12.0, 0.0, 0.0),
+            "mon" | "mons" | "month" | "months" => Self::fractional(number, 0.0, 0.0),
+            "w" | "week" | "weeks" => Self::fractional(0.0, number * 7.0, 0.0),
+            "d" | "day" | "days" => Self::fractional(0.0, number, 0.0),
+            "h" | "hr" | "hrs" | "hour" | "hours" => micros(MICROS_PER_HOUR),
+            "m" | "min" | "mins" | "minute" | "minutes" => micros(MICROS_PER_MINUTE),
+            "s" | "sec" | "secs" | "second" | "seconds" => micros(MICROS_PER_SECOND),
+            "ms" | "msec" | "msecs" | "millisecond" | "milliseconds" => micros(1000),
+            "us" | "usec" | "usecs" | "microsecond" | "microseconds" => micros(1),
+            _ => None,
+        }
+    }
+
+    // Parse `[-]hh:mm[:ss[.ffffff]]`
+    fn parse_time(text: &str) -> Option<Self> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let mut parts = text.split(':');
+        let hours: u32 = parts.next()?.parse().ok()?;
+        let minutes: u32 = parts.next()?.parse().ok()?;
+        let seconds: f64 = match parts.next() {
+            Some(seconds) => seconds.parse().ok()?,
+            None => 0.0,
+        };
+        if parts.next().is_some() || minutes >= 60 || !(0.0..60.0).contains(&seconds) {
+            return None;
+        }
+        let micros = hours as i64 * MICROS_PER_HOUR
+            + minutes as i64 * MICROS_PER_MINUTE
+            + (seconds * MICROS_PER_SECOND as f64).round() as i64;
+        Some(Self::new(0, 0, if negative { -micros } else { micros }))
+    }
+
+    // Parse the part of an ISO 8601 duration after its `P`
+    fn parse_iso(text: &str) -> Option<Self> {
+        let mut interval = Interval::default();
+        let mut in_time = false;
+        let mut number = String::new();
+        for c in text.chars().map(|c| c.to_ascii_uppercase()) {
+            if c.is_ascii_digit() || c == '.' || c == '-' {
+                number.push(c);
+                continue;
+            }
+            if c == 'T' && number.is_empty() && !in_time {
+                in_time = true;
+                continue;
+            }
+            let unit = match (c, in_time) {
+                ('Y', false) => "year",
+                ('M', false) => "month",
+                ('W', false) => "week",
+                ('D', false) => "day",
+                ('H', true) => "hour",
+                ('M', true) => "minute",
+                ('S', true) => "second",
+                _ => return None,
+            };
+            let field = Self::of_unit(number.parse().ok()?, unit)?;
+            interval = interval.checked_add(&field)?;
+            number.clear();
+        }
+        (number.is_empty() && !text.is_empty()).then_some(interval)
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(Self {
+            months: self.months.checked_add(other.months)?,
+            days: self.days.checked_add(other.days)?,
+            micros: self.micros.checked_add(other.micros)?,
+        })
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    pub fn checked_neg(&self) -> Option<Self> {
+        Some(Self {
+            months: self.months.checked_neg()?,
+            days: self.days.checked_neg()?,
+            micros: self.micros.checked_neg()?,
+        })
+    }
+
+    /// The interval times `factor`, fractions of months and days spilling
+    /// into the smaller fields
+    pub fn checked_mul(&self, factor: f64) -> Option<Self> {
+        Self::fractional(
+            self.months as f64 * factor,
+            self.days as f64 * factor,
+            self.micros as f64 * factor,
+        )
+    }
+
+    pub fn checked_div(&self, divisor: f64) -> Option<Self> {
+        if divisor == 0.0 {
+            return None;
+        }
+        Self::fractional(
+            self.months as f64 / divisor,
+            self.days as f64 / divisor,
+            self.micros as f64 / divisor,
+        )
+    }
+
+    /// Time from the timestamp `earlier` to `later`, in days and
+    /// microseconds, both microseconds since the epoch
+    pub fn between(later: i64, earlier: i64) -> Option<Self> {
+        let micros = later.checked_sub(earlier)?;
+        Some(Self::new(
+            0,
+            i32::try_from(micros / MICROS_PER_DAY).ok()?,
+            micros % MICROS_PER_DAY,
+        ))
+    }
+
+    /// The timestamp `timestamp` (microseconds since the epoch) moved by the
+    /// interval; whole months keep the day of the month, or land on the last
+    /// day of a shorter month
+    pub fn add_to(&self, timestamp: i64) -> Option<i64> {
+        let time = chrono::DateTime::from_timestamp_micros(timestamp)?.naive_utc();
+        let months = chrono::Months::new(self.months.unsigned_abs());
+        let time = if self.months >= 0 {
+            time.checked_add_months(months)?
+        } else {
+            time.checked_sub_months(months)?
+        };
+        let time = time.checked_add_signed(chrono::Duration::days(self.days as i64))?;
+        time.and_utc().timestamp_micros().checked_add(self.micros)
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.span() == other.span()
+    }
+}
+
+impl Eq for Interval {}
+
+impl Hash for Interval {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.span().hash(state);
+    }
+}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.span().cmp(&other.span())
+    }
+}
+
+/// PostgreSQL's default output, such as `1 year 2 mons 3 days 04:05:06`
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        let mut field = |count: i32, unit: &str| {
+            if count != 0 {
+                let plural = if count == 1 { "" } else { "s" };
+                parts.push(format!("{} {}{}", count, unit, plural));
+            }
+        };
+        field(self.months / 12, "year");
+        field(self.months % 12, "mon");
+        field(self.days, "day");
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 {
+                "-"
+            } else if self.months < 0 || self.days < 0 {
+                "+"
+            } else {
+                ""
+            };
+            let micros = self.micros.unsigned_abs();
+            let seconds = micros / MICROS_PER_SECOND as u64;
+            let mut time = format!(
+                "{}{:02}:{:02}:{:02}",
+                sign,
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            );
+            let fraction = micros % MICROS_PER_SECOND as u64;
+            if fraction != 0 {
+                let fraction = format!(".{:06}", fraction);
+                time.push_str(fraction.trim_end_matches('0'));
+            }
+            parts.push(time);
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Value;
+
+    fn interval(text: &str) -> Interval {
+        Interval::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(interval("1 year 2 months").to_string(), "1 year 2 mons");
+        assert_eq!(
+            interval("3 days 04:05:06.5").to_string(),
+            "3 days 04:05:06.5"
+        );
+        assert_eq!(interval("2 hours 30 minutes").to_string(), "02:30:00");
+        assert_eq!(interval("1.5 days").to_string(), "1 day 12:00:00");
+        assert_eq!(interval("-1 day").to_string(), "-1 days");
+        assert_eq!(interval("-1 day 02:00").to_string(), "-1 days +02:00:00");
+        assert_eq!(interval("2 weeks ago").to_string(), "-14 days");
+        assert_eq!(interval("1 10:00:00").to_string(), "1 day 10:00:00");
+        assert_eq!(interval("90").to_string(), "00:01:30");
+        assert_eq!(
+            interval("P1Y2M3DT4H5M6S").to_string(),
+            "1 year 2 mons 3 days 04:05:06"
+        );
+        assert_eq!(interval("PT0S").to_string(), "00:00:00");
+        assert!(Interval::parse("").is_none());
+        assert!(Interval::parse("1 fortnight").is_none());
+        assert!(Interval::parse("10:75").is_none());
+        assert!(Interval::parse("P1H").is_none());
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert_eq!(interval("1 mon"), interval("30 days"));
+        assert!(interval("1 day") < interval("25 hours"));
+        assert!(interval("-1 hour") < interval("0 seconds"));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let jan_31 = Value::parse_timestamp("2024-01-31 10:00:00").unwrap();
+        let moved = interval("1 month 1 day 1 hour").add_to(jan_31).unwrap();
+        assert_eq!(Value::parse_timestamp("2024-03-01 11:00:00"), Some(moved));
+        let back = interval("-1 month").add_to(jan_31).unwrap();
+        assert_eq!(Value::parse_timestamp("2023-12-31 10:00:00"), Some(back));
+
+        let jan_1 = Value::parse_timestamp("2024-01-01 10:00:00").unwrap();
+        let jan_2 = Value::parse_timestamp("2024-01-02 12:30:00").unwrap();
+        let between = Interval::between(jan_2, jan_1).unwrap();
+        assert_eq!(between.to_string(), "1 day 02:30:00");
+        assert_eq!(
+            interval("1 mon").checked_mul(1.5).unwrap().to_string(),
+            "1 mon 15 days"
+        );
+        assert_eq!(
+            interval("1 day").checked_div(4.0).unwrap().to_string(),
+            "06:00:00"
+        );
+        assert!(interval("1 day").checked_div(0.0).is_none());
+        assert_eq!(
+            interval("1 day")
+                .checked_sub(&interval("1 hour"))
+                .unwrap()
+                .to_string(),
+            "1 day -01:00:00"
+        );
+    }
+}
@@ -36,6 +36,7 @@
 // }
 // ```
 
+use crate::security::sql_firewall::FirewallPolicy;
 use crate::Result;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
@@ -191,6 +192,21 @@ pub enum Value {
     /// Array of values
     Array(Vec<Value>),
     Text,
+
+    /// Fixed-dimension vector of floats, for similarity search
+    Vector(Vec<f64>),
+
+    /// Exact decimal number
+    Numeric(Decimal),
+
+    /// Span of months, days and microseconds
+    Interval(Interval),
+
+    /// Timestamp with time zone (microseconds since epoch, in UTC)
+    TimestampTz(i64),
+
+    /// UUID
+    Uuid(uuid::Uuid),
 }
 
 impl Value {
@@ -213,6 +229,25 @@ impl Value {
             Value::Json(_) => "JSON",
             Value::Array(_) => "ARRAY",
             Value::Text => "TEXT",
+            Value::Vector(_) => "VECTOR",
+            Value::Numeric(_) => "NUMERIC",
+            Value::Interval(_) => "INTERVAL",
+            Value::TimestampTz(_) => "TIMESTAMPTZ",
+            Value::Uuid(_) => "UUID",
+        }
+    }
+
+    /// Approximate size in bytes, for memory budgets and spill decisions
+    pub fn estimated_size(&self) -> usize {
+        match self {
+            Value::String(s) => s.len(),
+            Value::Bytes(b) => b.len(),
+            Value::Json(j) => j.to_string().len(),
+            Value::Array(a) => a.iter().map(Value::estimated_size).sum(),
+            Value::Vector(v) => v.len() * 8,
+            Value::Numeric(_) | Value::Interval(_) | Value::Uuid(_) => 16,
+            Value::Null | Value::Text => 1,
+            _ => 8,
         }
     }
 
@@ -225,11 +260,369 @@ impl Value {
             Value::Float(f) => f.to_string(),
             Value::String(s) => s.clone(),
             Value::Bytes(b) => format!("<{} bytes>", b.len()),
-            Value::Date(d) => format!("DATE({})", d),
-            Value::Timestamp(t) => format!("TIMESTAMP({})", t),
+            Value::Date(d) => chrono::DateTime::from_timestamp(d.saturating_mul(86_400), 0)
+                .map(|dt| dt.date_naive().to_string())
+                .unwrap_or_else(|| format!("DATE({})", d)),
+            Value::Timestamp(t) => chrono::DateTime::from_timestamp_micros(*t)
+                .map(|dt| dt.naive_utc().format("%Y-%m-%d %H:%M:%S%.f").to_string())
+                .unwrap_or_else(|| format!("TIMESTAMP({})", t)),
+            Value::TimestampTz(t) => chrono::DateTime::from_timestamp_micros(*t)
+                .map(|dt| dt.naive_utc().format("%Y-%m-%d %H:%M:%S%.f+00").to_string())
+                .unwrap_or_else(|| format!("TIMESTAMPTZ({})", t)),
             Value::Json(j) => j.to_string(),
-            Value::Array(a) => format!("[{}]", a.len()),
+            Value::Array(a) => {
+                let items: Vec<String> = a
+                    .iter()
+                    .map(|item| match item {
+                        Value::Null => "NULL".to_string(),
+                        item => Self::quote_array_item(&item.to_display_string()),
+                    })
+                    .collect();
+                format!("{{{}}}", items.join(","))
+            }
             Value::Text => "TEXT".to_string(),
+            Value::Vector(v) => {
+                let items: Vec<String> = v.iter().map(f64::to_string).collect();
+                format!("[{}]", items.join(","))
+            }
+            Value::Numeric(d) => d.to_string(),
+            Value::Interval(i) => i.to_string(),
+            Value::Uuid(u) => u.to_string(),
+        }
+    }
+
+    // An element of an array literal, quoted if it would not read back
+    fn quote_array_item(text: &str) -> String {
+        let plain = !text.is_empty()
+            && !text.eq_ignore_ascii_case("NULL")
+            && !text
+                .chars()
+                .any(|c| matches!(c, '{' | '}' | ',' | '"' | '\\') || c.is_whitespace());
+        if plain {
+            return text.to_string();
+        }
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+// ============================================================================
+// SQL Semantics
+// ============================================================================
+
+/// Microseconds in a day, for mixing `Date` and `Timestamp` values
+pub const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+impl Value {
+    /// Parse an untyped SQL literal
+    ///
+    /// Quoted text is always a string; otherwise NULL, TRUE/FALSE and numbers
+    /// are recognized and anything else is kept as a string.
+    pub fn from_sql_literal(text: &str) -> Value {
+        let text = text.trim();
+        if text.len() >= 2 && text.starts_with('\'') && text.ends_with('\'') {
+            return Value::String(text[1..text.len() - 1].replace("''", "'"));
+        }
+        if text.eq_ignore_ascii_case("NULL") {
+            return Value::Null;
+        }
+        if text.eq_ignore_ascii_case("TRUE") {
+            return Value::Boolean(true);
+        }
+        if text.eq_ignore_ascii_case("FALSE") {
+            return Value::Boolean(false);
+        }
+        if let Ok(i) = text.parse::<i64>() {
+            return Value::Integer(i);
+        }
+        // f64 parsing also accepts words like "inf" and "NaN"
+        let numeric = text
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'));
+        if numeric {
+            if let Ok(f) = text.parse::<f64>() {
+                return Value::Float(f);
+            }
+        }
+        Value::String(text.to_string())
+    }
+
+    /// Numeric value of an integer, float or decimal
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            Value::Numeric(d) => Some(d.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// SQL truth value; `None` for NULL and non-boolean values
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            Value::Integer(i) => Some(*i != 0),
+            Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "1" => Some(true),
+                "false" | "f" | "no" | "n" | "0" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Parse a `YYYY-MM-DD` date into days since the epoch
+    pub fn parse_date(text: &str) -> Option<i64> {
+        let date = chrono::NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()?;
+        let epoch = chrono::NaiveDate::from_ymd_opt(1970, 1, 1)?;
+        Some(date.signed_duration_since(epoch).num_days())
+    }
+
+    /// Parse a timestamp (or a bare date) into microseconds since the epoch
+    pub fn parse_timestamp(text: &str) -> Option<i64> {
+        let text = text.trim();
+        for format in [
+            "%Y-%m-%d %H:%M:%S%.f",
+            "%Y-%m-%dT%H:%M:%S%.f",
+            "%Y-%m-%d %H:%M",
+        ] {
+            if let Ok(ts) = chrono::NaiveDateTime::parse_from_str(text, format) {
+                return Some(ts.and_utc().timestamp_micros());
+            }
+        }
+        Self::parse_date(text).map(|days| days.saturating_mul(MICROS_PER_DAY))
+    }
+
+    /// Parse a timestamp with an optional UTC offset, such as `+02`,
+    /// `-05:30`, `Z` or `UTC`, into microseconds since the epoch in UTC; a
+    /// timestamp without one is taken as UTC
+    pub fn parse_timestamptz(text: &str) -> Option<i64> {
+        let text = text.trim();
+        let upper = text.to_ascii_uppercase();
+        if let Some(zone) = ["UTC", "GMT", "Z"]
+            .iter()
+            .find(|zone| upper.ends_with(*zone))
+        {
+            return Self::parse_timestamp(&text[..text.len() - zone.len()]);
+        }
+        // A sign after the time starts an offset; the date has signs too
+        let (local, offset) = match text.rfind(['+', '-']) {
+            Some(at) if text[..at].contains(':') => (&text[..at], &text[at..]),
+            _ => return Self::parse_timestamp(text),
+        };
+        let (sign, offset) = offset.split_at(1);
+        let (hours, minutes) = match offset.split_once(':') {
+            Some(parts) => parts,
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let hours: i64 = hours.parse::<u8>().ok()?.into();
+        let minutes: i64 = minutes.parse::<u8>().ok()?.into();
+        if hours > 15 || minutes >= 60 {
+            return None;
+        }
+        let offset = (hours * 3600 + minutes * 60) * 1_000_000;
+        let local = Self::parse_timestamp(local)?;
+        if sign == "-" {
+            local.checked_add(offset)
+        } else {
+            local.checked_sub(offset)
+        }
+    }
+
+    /// Parse an array literal such as `{1,"a b",NULL}` into its elements,
+    /// as strings or NULL; arrays of arrays are not accepted
+    pub fn parse_array(text: &str) -> Option<Vec<Value>> {
+        let inner = text.trim().strip_prefix('{')?.strip_suffix('}')?;
+        let mut items = Vec::new();
+        if inner.trim().is_empty() {
+            return Some(items);
+        }
+        let mut chars = inner.trim().chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            let item = if chars.next_if_eq(&'"').is_some() {
+                let mut item = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => item.push(chars.next()?),
+                        c => item.push(c),
+                    }
+                }
+                Value::String(item)
+            } else {
+                let mut item = String::new();
+                while let Some(c) = chars.next_if(|c| *c != ',') {
+                    if matches!(c, '{' | '}' | '"' | '\\') {
+                        return None;
+                    }
+                    item.push(c);
+                }
+                match item.trim() {
+                    "" => return None,
+                    null if null.eq_ignore_ascii_case("NULL") => Value::Null,
+                    item => Value::String(item.to_string()),
+                }
+            };
+            items.push(item);
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.next() {
+                None => return Some(items),
+                Some(',') => continue,
+                Some(_) => return None,
+            }
+        }
+    }
+
+    /// Microseconds since the epoch of a date or timestamp
+    pub fn time_micros(&self) -> Option<i64> {
+        match self {
+            Value::Date(d) => Some(d.saturating_mul(MICROS_PER_DAY)),
+            Value::Timestamp(t) | Value::TimestampTz(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    /// Parse a vector literal such as `[1, 2.5, -3]`
+    pub fn parse_vector(text: &str) -> Option<Vec<f64>> {
+        let inner = text.trim().strip_prefix('[')?.strip_suffix(']')?.trim();
+        if inner.is_empty() {
+            return Some(Vec::new());
+        }
+        inner
+            .split(',')
+            .map(|item| item.trim().parse::<f64>().ok().filter(|f| f.is_finite()))
+            .collect()
+    }
+
+    /// Compare two values with SQL semantics
+    ///
+    /// Returns `None` when either side is NULL or the values are not
+    /// comparable. Integers, decimals and floats compare numerically (a
+    /// decimal against an integer exactly), dates and timestamps with or
+    /// without time zone on a common time line, and a string compared with
+    /// a non-string is read as a literal of the other side's type.
+    pub fn sql_cmp(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => None,
+            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+            (Value::Numeric(a), Value::Numeric(b)) => Some(a.cmp(b)),
+            (Value::Numeric(a), Value::Integer(b)) => Some(a.cmp(&Decimal::from_i64(*b))),
+            (Value::Integer(a), Value::Numeric(b)) => Some(Decimal::from_i64(*a).cmp(b)),
+            (
+                Value::Integer(_) | Value::Float(_) | Value::Numeric(_),
+                Value::Integer(_) | Value::Float(_) | Value::Numeric(_),
+            ) => self.as_f64()?.partial_cmp(&other.as_f64()?),
+            (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Bytes(a), Value::Bytes(b)) => Some(a.cmp(b)),
+            (Value::Vector(a), Value::Vector(b)) => {
+                for (x, y) in a.iter().zip(b) {
+                    match x.partial_cmp(y)? {
+                        Ordering::Equal => continue,
+                        ordering => return Some(ordering),
+                    }
+                }
+                Some(a.len().cmp(&b.len()))
+            }
+            (Value::Date(a), Value::Date(b)) => Some(a.cmp(b)),
+            (
+                Value::Date(_) | Value::Timestamp(_) | Value::TimestampTz(_),
+                Value::Date(_) | Value::Timestamp(_) | Value::TimestampTz(_),
+            ) => Some(self.time_micros()?.cmp(&other.time_micros()?)),
+            (Value::Interval(a), Value::Interval(b)) => Some(a.cmp(b)),
+            (Value::Uuid(a), Value::Uuid(b)) => Some(a.cmp(b)),
+            (Value::Array(a), Value::Array(b)) => {
+                for (x, y) in a.iter().zip(b) {
+                    match x.sql_cmp(y)? {
+                        Ordering::Equal => continue,
+                        ordering => return Some(ordering),
+                    }
+                }
+                Some(a.len().cmp(&b.len()))
+            }
+            (Value::String(s), typed) => Self::coerce_str(s, typed)?.sql_cmp(typed),
+            (typed, Value::String(s)) => typed.sql_cmp(&Self::coerce_str(s, typed)?),
+            _ => None,
+        }
+    }
+
+    /// SQL equality; `None` when either side is NULL
+    pub fn sql_eq(&self, other: &Value) -> Option<bool> {
+        self.sql_cmp(other)
+            .map(|ordering| ordering == Ordering::Equal)
+    }
+
+    /// Total order used for sorting: NULLs sort after every other value and
+    /// values SQL cannot compare fall back to the structural order
+    pub fn sort_cmp(&self, other: &Value) -> Ordering {
+        match (self.is_null(), other.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.sql_cmp(other).unwrap_or_else(|| self.cmp(other)),
+        }
+    }
+
+    // Read `text` as a literal of the same kind as `like`
+    fn coerce_str(text: &str, like: &Value) -> Option<Value> {
+        match like {
+            Value::Integer(_) | Value::Float(_) => match Value::from_sql_literal(text) {
+                number @ (Value::Integer(_) | Value::Float(_)) => Some(number),
+                _ => None,
+            },
+            Value::Boolean(_) => Value::String(text.to_string())
+                .as_bool()
+                .map(Value::Boolean),
+            Value::Numeric(_) => Decimal::parse(text).map(Value::Numeric),
+            Value::Date(_) | Value::Timestamp(_) => match Self::parse_date(text) {
+                Some(days) => Some(Value::Date(days)),
+                None => Self::parse_timestamp(text).map(Value::Timestamp),
+            },
+            Value::TimestampTz(_) => Self::parse_timestamptz(text).map(Value::TimestampTz),
+            Value::Interval(_) => Interval::parse(text).map(Value::Interval),
+            Value::Uuid(_) => uuid::Uuid::parse_str(text.trim()).ok().map(Value::Uuid),
+            Value::Vector(_) => Self::parse_vector(text).map(Value::Vector),
+            _ => None,
+        }
+    }
+
+    /// JSON representation used by the HTTP and WebSocket APIs; decimals
+    /// are sent as strings so that no digits are lost
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null | Value::Text => serde_json::Value::Null,
+            Value::Boolean(b) => serde_json::Value::Bool(*b),
+            Value::Integer(i) => serde_json::Value::Number((*i).into()),
+            Value::Float(f) => serde_json::Number::from_f64(*f)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            Value::Json(j) => j.clone(),
+            Value::Array(a) => serde_json::Value::Array(a.iter().map(Value::to_json).collect()),
+            Value::Vector(v) => serde_json::Value::Array(
+                v.iter()
+                    .map(|f| {
+                        serde_json::Number::from_f64(*f)
+                            .map_or(serde_json::Value::Null, serde_json::Value::Number)
+                    })
+                    .collect(),
+            ),
+            other => serde_json::Value::String(other.to_display_string()),
+        }
+    }
+
+    /// Value of a JSON parameter; objects are kept as JSON
+    pub fn from_json(json: &serde_json::Value) -> Value {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Boolean(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Integer(i),
+                None => n.as_f64().map_or(Value::Null, Value::Float),
+            },
+            serde_json::Value::String(s) => Value::String(s.clone()),
+            serde_json::Value::Array(a) => Value::Array(a.iter().map(Value::from_json).collect()),
+            object => Value::Json(object.clone()),
         }
     }
 }
@@ -253,6 +646,13 @@ impl PartialEq for Value {
             (Value::Timestamp(a), Value::Timestamp(b)) => a == b,
             (Value::Json(a), Value::Json(b)) => a == b,
             (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Vector(a), Value::Vector(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.to_bits() == y.to_bits())
+            }
+            (Value::Numeric(a), Value::Numeric(b)) => a == b,
+            (Value::Interval(a), Value::Interval(b)) => a == b,
+            (Value::TimestampTz(a), Value::TimestampTz(b)) => a == b,
+            (Value::Uuid(a), Value::Uuid(b)) => a == b,
             _ => false,
         }
     }
@@ -275,6 +675,11 @@ impl std::hash::Hash for Value {
             Value::Json(j) => j.to_string().hash(state),
             Value::Array(a) => a.hash(state),
             Value::Text => "TEXT".hash(state),
+            Value::Vector(v) => v.iter().for_each(|f| f.to_bits().hash(state)),
+            Value::Numeric(d) => d.hash(state),
+            Value::Interval(i) => i.hash(state),
+            Value::TimestampTz(t) => t.hash(state),
+            Value::Uuid(u) => u.hash(state),
         }
     }
 }
@@ -303,6 +708,11 @@ impl PartialOrd for Value {
             (Value::Date(a), Value::Date(b)) => a.partial_cmp(b),
             (Value::Timestamp(a), Value::Timestamp(b)) => a.partial_cmp(b),
             (Value::Array(a), Value::Array(b)) => a.partial_cmp(b),
+            (Value::Vector(_), Value::Vector(_)) => self.sql_cmp(other),
+            (Value::Numeric(a), Value::Numeric(b)) => a.partial_cmp(b),
+            (Value::Interval(a), Value::Interval(b)) => a.partial_cmp(b),
+            (Value::TimestampTz(a), Value::TimestampTz(b)) => a.partial_cmp(b),
+            (Value::Uuid(a), Value::Uuid(b)) => a.partial_cmp(b),
             _ => None,
         }
     }
@@ -1030,6 +1440,8 @@ pub struct DatabaseConfig {
     // Network configuration
     pub listen_address: String,
     pub port: u16,
+    // PostgreSQL wire protocol listener; 0 disables it
+    pub pg_port: u16,
     pub api_port: u16,
     pub enable_rest_api: bool,
     pub max_connections: usize,
@@ -1040,6 +1452,8 @@ pub struct DatabaseConfig {
     pub enable_encryption: bool,
     pub password_min_length: usize,
     pub session_timeout: Duration,
+    // SQL firewall applied to statements from every front end
+    pub firewall: FirewallPolicy,
 
     // Clustering configuration
     pub cluster_enabled: bool,
@@ -1078,6 +1492,8 @@ impl Default for DatabaseConfig {
             // Network
             listen_address: "127.0.0.1".to_string(),
             port: 5432,
+            // 5433 and 5434 are the cluster and replication ports
+            pg_port: 5435,
             api_port: 8080,
             enable_rest_api: true,
             max_connections: 100,
@@ -1088,6 +1504,7 @@ impl Default for DatabaseConfig {
             enable_encryption: true,
             password_min_length: 8,
             session_timeout: Duration::from_secs(3600),
+            firewall: FirewallPolicy::default(),
 
             // Clustering
             cluster_enabled: false,
@@ -1174,6 +1591,17 @@ pub mod concurrent_map;
 pub mod bounded_map;
 pub use bounded_map::BoundedHashMap;
 
+/// Loading `DatabaseConfig` from `rustydb.toml`
+pub mod config_file;
+
+/// Exact decimal numbers for NUMERIC values
+pub mod decimal;
+pub use decimal::Decimal;
+
+/// Month, day and time spans for INTERVAL values
+pub mod interval;
+pub use interval::Interval;
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -1191,6 +1619,142 @@ mod tests {
             "hello"
         );
         assert_eq!(Value::Boolean(true).to_display_string(), "true");
+        assert_eq!(Value::Date(0).to_display_string(), "1970-01-01");
+        assert_eq!(
+            Value::Timestamp(MICROS_PER_DAY + 1_500_000).to_display_string(),
+            "1970-01-02 00:00:01.500"
+        );
+    }
+
+    #[test]
+    fn test_sql_literal_parsing() {
+        assert_eq!(Value::from_sql_literal("NULL"), Value::Null);
+        assert_eq!(
+            Value::from_sql_literal("'NULL'"),
+            Value::String("NULL".to_string())
+        );
+        assert_eq!(Value::from_sql_literal("-7"), Value::Integer(-7));
+        assert_eq!(Value::from_sql_literal("2.5"), Value::Float(2.5));
+        assert_eq!(
+            Value::from_sql_literal("'it''s'"),
+            Value::String("it's".to_string())
+        );
+        assert_eq!(
+            Value::from_sql_literal("NaN"),
+            Value::String("NaN".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sql_comparison() {
+        // Numbers compare numerically, not lexically
+        assert_eq!(
+            Value::Integer(10).sql_cmp(&Value::Integer(9)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(Value::Integer(2).sql_eq(&Value::Float(2.0)), Some(true));
+        assert_eq!(
+            Value::Integer(10).sql_cmp(&Value::String("9".to_string())),
+            Some(Ordering::Greater)
+        );
+
+        // NULL is never equal (or unequal) to anything
+        assert_eq!(Value::Null.sql_eq(&Value::Null), None);
+        assert_eq!(Value::Integer(1).sql_cmp(&Value::Null), None);
+
+        let day = Value::parse_date("2024-03-01").unwrap();
+        assert_eq!(
+            Value::Date(day).sql_cmp(&Value::String("2024-02-29".to_string())),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            Value::Date(day).sql_eq(&Value::Timestamp(day * MICROS_PER_DAY)),
+            Some(true)
+        );
+        assert_eq!(
+            Value::Integer(1).sql_cmp(&Value::String("abc".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_numeric_interval_uuid_values() {
+        let price = Value::Numeric(Decimal::parse("19.90").unwrap());
+        assert_eq!(price.to_display_string(), "19.90");
+        assert_eq!(price.sql_cmp(&Value::Integer(20)), Some(Ordering::Less));
+        assert_eq!(price.sql_eq(&Value::String("19.9".to_string())), Some(true));
+        assert_eq!(price.to_json(), serde_json::json!("19.90"));
+
+        let noon = Value::parse_timestamp("2024-05-01 12:00:00").unwrap();
+        assert_eq!(
+            Value::parse_timestamptz("2024-05-01 14:30:00+02:30"),
+            Some(noon)
+        );
+        assert_eq!(
+            Value::parse_timestamptz("2024-05-01T07:00:00-05"),
+            Some(noon)
+        );
+        assert_eq!(
+            Value::parse_timestamptz("2024-05-01 12:00:00 UTC"),
+            Some(noon)
+        );
+        assert_eq!(Value::parse_timestamptz("2024-05-01 12:00:00"), Some(noon));
+        assert_eq!(Value::parse_timestamptz("2024-05-01 12:00:00+16"), None);
+        assert_eq!(
+            Value::TimestampTz(noon).to_display_string(),
+            "2024-05-01 12:00:00+00"
+        );
+        assert_eq!(
+            Value::TimestampTz(noon).sql_eq(&Value::Timestamp(noon)),
+            Some(true)
+        );
+
+        let week = Value::Interval(Interval::parse("7 days").unwrap());
+        assert_eq!(
+            week.sql_cmp(&Value::String("1 week 1 hour".to_string())),
+            Some(Ordering::Less)
+        );
+
+        let id = Value::Uuid(uuid::Uuid::from_u128(
+            0xa0ee_bc99_9c0b_4ef8_bb6d_6bb9_bd38_0a11,
+        ));
+        assert_eq!(
+            id.sql_eq(&Value::String(
+                "A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11".to_string()
+            )),
+            Some(true)
+        );
+        assert_eq!(
+            id.to_display_string(),
+            "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11"
+        );
+    }
+
+    #[test]
+    fn test_array_literals() {
+        let array = Value::Array(vec![
+            Value::Integer(1),
+            Value::Null,
+            Value::String("a b".to_string()),
+            Value::String("NULL".to_string()),
+            Value::String("x\"y".to_string()),
+        ]);
+        let text = array.to_display_string();
+        assert_eq!(text, r#"{1,NULL,"a b","NULL","x\"y"}"#);
+        assert_eq!(
+            Value::parse_array(&text),
+            Some(vec![
+                Value::String("1".to_string()),
+                Value::Null,
+                Value::String("a b".to_string()),
+                Value::String("NULL".to_string()),
+                Value::String("x\"y".to_string()),
+            ])
+        );
+        assert_eq!(Value::parse_array("{}"), Some(Vec::new()));
+        assert_eq!(Value::parse_array("{{1,2},{3,4}}"), None);
+        assert_eq!(Value::parse_array("{1,,2}"), None);
+        assert_eq!(Value::parse_array("[1,2]"), None);
     }
 
     #[test]
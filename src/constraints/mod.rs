@@ -3,6 +3,7 @@ use crate::Result;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 /// Foreign key constraint
@@ -50,16 +51,22 @@ pub struct CheckConstraint {
 }
 
 /// Cascade action for foreign key operations
+///
+/// Actions target the rows of `table` whose `column` equals `key`; the
+/// executor compares `key` against the column's type. An update `value` of
+/// `None` sets the column to NULL.
 #[derive(Debug, Clone)]
 pub enum CascadeAction {
     Delete {
         table: String,
-        condition: String,
+        column: String,
+        key: String,
     },
     Update {
         table: String,
         column: String,
-        value: String,
+        key: String,
+        value: Option<String>,
     },
 }
 
@@ -68,6 +75,9 @@ pub struct ConstraintManager {
     foreign_keys: Arc<RwLock<HashMap<String, Vec<ForeignKey>>>>,
     unique_constraints: Arc<RwLock<HashMap<String, Vec<UniqueConstraint>>>>,
     check_constraints: Arc<RwLock<HashMap<String, Vec<CheckConstraint>>>>,
+    // Bumped whenever a constraint is added or dropped, so derived state
+    // (such as the catalog GraphQL schema) knows when to rebuild
+    version: AtomicU64,
 }
 
 impl ConstraintManager {
@@ -76,23 +86,59 @@ impl ConstraintManager {
             foreign_keys: Arc::new(RwLock::new(HashMap::new())),
             unique_constraints: Arc::new(RwLock::new(HashMap::new())),
             check_constraints: Arc::new(RwLock::new(HashMap::new())),
+            version: AtomicU64::new(0),
         }
     }
 
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    /// All foreign keys, ordered by table and then by declaration
+    pub fn foreign_keys(&self) -> Vec<ForeignKey> {
+        let fks = self.foreign_keys.read();
+        let mut tables: Vec<&String> = fks.keys().collect();
+        tables.sort();
+        tables
+            .into_iter()
+            .flat_map(|table| fks[table].iter().cloned())
+            .collect()
+    }
+
     pub fn add_foreign_key(&self, fk: ForeignKey) -> Result<()> {
         let mut fks = self.foreign_keys.write();
         fks.entry(fk.table.clone())
             .or_insert_with(Vec::new)
             .push(fk);
+        self.version.fetch_add(1, Ordering::SeqCst);
         Ok(())
     }
 
+    pub fn drop_foreign_key(&self, name: &str) -> Result<()> {
+        let mut fks = self.foreign_keys.write();
+        for constraints in fks.values_mut() {
+            constraints.retain(|fk| fk.name != name);
+        }
+        fks.retain(|_, constraints| !constraints.is_empty());
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Forget the constraints declared on a dropped table
+    pub fn drop_table(&self, table: &str) {
+        self.foreign_keys.write().remove(table);
+        self.unique_constraints.write().remove(table);
+        self.check_constraints.write().remove(table);
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+
     pub fn add_unique_constraint(&self, constraint: UniqueConstraint) -> Result<()> {
         let mut constraints = self.unique_constraints.write();
         constraints
             .entry(constraint.table.clone())
             .or_insert_with(Vec::new)
             .push(constraint);
+        self.version.fetch_add(1, Ordering::SeqCst);
         Ok(())
     }
 
@@ -102,6 +148,7 @@ impl ConstraintManager {
             .entry(constraint.table.clone())
             .or_insert_with(Vec::new)
             .push(constraint);
+        self.version.fetch_add(1, Ordering::SeqCst);
         Ok(())
     }
 
@@ -167,17 +214,16 @@ impl ConstraintManager {
                                 ReferentialAction::Cascade => {
                                     actions.push(CascadeAction::Delete {
                                         table: referencing_table.clone(),
-                                        condition: format!(
-                                            "{}={}",
-                                            fk.columns[0], referenced_value
-                                        ),
+                                        column: fk.columns[0].clone(),
+                                        key: referenced_value.clone(),
                                     });
                                 }
                                 ReferentialAction::SetNull => {
                                     actions.push(CascadeAction::Update {
                                         table: referencing_table.clone(),
                                         column: fk.columns[0].clone(),
-                                        value: "NULL".to_string(),
+                                        key: referenced_value.clone(),
+                                        value: None,
                                     });
                                 }
                                 ReferentialAction::Restrict => {
@@ -261,6 +307,10 @@ mod tests {
         };
 
         cm.add_foreign_key(fk)?;
+        assert_eq!(cm.foreign_keys().len(), 1);
+
+        cm.drop_foreign_key("fk_user_dept")?;
+        assert!(cm.foreign_keys().is_empty());
         Ok(())
     }
 }
@@ -0,0 +1,485 @@
+// # Database Handle
+//
+// `Database` owns one running database: the table store and its buffer pool,
+// the data and index WALs, the catalog, the transaction manager, security and
+// monitoring. Every front end (native protocol, REST, GraphQL, WebSocket)
+// is handed the same `Arc<Database>`, so they all see the same tables and
+// transactions.
+//
+// Startup runs crash recovery before the catalog is loaded. Shutdown is
+// orderly: front ends stop accepting work, open connections are given the
+// connection timeout to finish, then the buffer pool is flushed, a
+// checkpoint is written and the logs are closed.
+
+use crate::catalog::Catalog;
+use crate::common::DatabaseConfig;
+use crate::constraints::ConstraintManager;
+use crate::error::{DbError, Result};
+use crate::execution::Executor;
+use crate::monitoring::MonitoringHub;
+use crate::security::sql_firewall::SqlFirewall;
+use crate::security::IntegratedSecurityManager;
+use crate::storage::TableStore;
+use crate::transaction::recovery::{ARIESRecoveryManager, RecoveryConfig};
+use crate::transaction::wal::{CheckpointConfig, CheckpointCoordinator, WALConfig, WALManager};
+use crate::transaction::TransactionManager;
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+
+pub struct Database {
+    config: DatabaseConfig,
+    table_store: Arc<TableStore>,
+    data_wal: Arc<WALManager>,
+    index_wal: Arc<WALManager>,
+    checkpointer: CheckpointCoordinator,
+    index_checkpointer: CheckpointCoordinator,
+    catalog: Catalog,
+    txn_manager: Arc<TransactionManager>,
+    security: Arc<IntegratedSecurityManager>,
+    // Foreign keys and other constraints declared outside table schemas;
+    // every executor enforces these, and the catalog persists foreign keys
+    constraints: Arc<ConstraintManager>,
+    monitoring: Arc<MonitoringHub>,
+    // Flips to true once shutdown starts
+    shutdown: watch::Sender<bool>,
+    connections: AtomicUsize,
+    // Signalled when the last connection closes
+    drained: Notify,
+    checkpoint_task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Database {
+    /// Open (or create) the database described by `config`
+    ///
+    /// Recovers the data WAL against the table pages and the index WAL
+    /// against the index pages before loading the catalog, and starts the
+    /// periodic checkpoint.
+    pub async fn open(config: DatabaseConfig) -> Result<Arc<Self>> {
+        std::fs::create_dir_all(&config.data_dir)?;
+        std::fs::create_dir_all(&config.wal_dir)?;
+
+        let table_store = Arc::new(TableStore::open(
+            &config.data_dir,
+            config.page_size,
+            config.buffer_pool_size,
+        )?);
+
+        // The executor is synchronous, so commits write through instead of
+        // waiting for a group flush
+        let wal_config = WALConfig {
+            enable_group_commit: false,
+            ..WALConfig::default()
+        };
+        let data_wal = Arc::new(WALManager::new(
+            Path::new(&config.wal_dir).join("data"),
+            wal_config.clone(),
+        )?);
+        table_store.attach_wal(data_wal.clone());
+        // Index pages have a log of their own: their mini-transactions commit
+        // independently of the row changes they index
+        let index_wal = Arc::new(WALManager::new(
+            Path::new(&config.wal_dir).join("index"),
+            wal_config,
+        )?);
+        table_store.indexes().attach_wal(index_wal.clone());
+
+        let recovery = ARIESRecoveryManager::new(data_wal.clone(), RecoveryConfig::default())
+            .with_pages(table_store.clone());
+        recovery.recover().await?;
+        let stats = recovery.get_stats();
+        tracing::info!(
+            "Crash recovery complete ({} records redone, {} transactions rolled back)",
+            stats.records_redone,
+            stats.transactions_rolled_back
+        );
+        let mut rebuild_indexes = stats.records_redone > 0 || stats.transactions_rolled_back > 0;
+        let index_recovery =
+            ARIESRecoveryManager::new(index_wal.clone(), RecoveryConfig::default())
+                .with_pages(table_store.indexes().clone());
+        index_recovery.recover().await?;
+        let stats = index_recovery.get_stats();
+        tracing::info!(
+            "Index recovery complete ({} records redone, {} changes rolled back)",
+            stats.records_redone,
+            stats.transactions_rolled_back
+        );
+        rebuild_indexes |= stats.records_redone > 0 || stats.transactions_rolled_back > 0;
+
+        let catalog = Catalog::open(
+            table_store.clone(),
+            PathBuf::from(&config.wal_dir).join("catalog.wal"),
+        )?;
+        // Index trees change in a log of their own after the rows they
+        // index; if recovery replayed either log, they may disagree
+        for table in catalog.list_tables() {
+            let schema = catalog.get_table(&table)?;
+            for index in catalog.list_indexes(&table) {
+                table_store.load_index(&schema, index, rebuild_indexes)?;
+            }
+        }
+
+        let monitoring = Arc::new(MonitoringHub::new(Path::new(&config.data_dir).join("diag")));
+        monitoring.initialize_default_metrics();
+
+        let checkpoint_config = CheckpointConfig {
+            interval_secs: config.checkpoint_interval.as_secs(),
+            ..CheckpointConfig::default()
+        };
+        let checkpointer = CheckpointCoordinator::new(data_wal.clone(), checkpoint_config.clone());
+        let index_checkpointer = CheckpointCoordinator::new(index_wal.clone(), checkpoint_config);
+
+        let security = Arc::new(IntegratedSecurityManager::new());
+        security.firewall.set_policy(config.firewall.clone());
+
+        let constraints = Arc::new(ConstraintManager::new());
+        for fk in catalog.list_foreign_keys() {
+            constraints.add_foreign_key(fk)?;
+        }
+
+        let database = Arc::new(Self {
+            txn_manager: Arc::new(TransactionManager::with_isolation(
+                config.default_isolation.into(),
+            )),
+            security,
+            constraints,
+            config,
+            table_store,
+            data_wal,
+            index_wal,
+            checkpointer,
+            index_checkpointer,
+            catalog,
+            monitoring,
+            shutdown: watch::channel(false).0,
+            connections: AtomicUsize::new(0),
+            drained: Notify::new(),
+            checkpoint_task: Mutex::new(None),
+        });
+
+        if !database.config.checkpoint_interval.is_zero() {
+            let task = tokio::spawn(Self::checkpoint_loop(
+                Arc::downgrade(&database),
+                database.config.checkpoint_interval,
+            ));
+            *database.checkpoint_task.lock() = Some(task);
+        }
+
+        Ok(database)
+    }
+
+    // Holds only a weak reference so the task never keeps the database open
+    async fn checkpoint_loop(database: Weak<Self>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let Some(database) = database.upgrade() else {
+                return;
+            };
+            if let Err(e) = database.checkpoint().await {
+                tracing::error!("Checkpoint failed: {}", e);
+            }
+        }
+    }
+
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+
+    pub fn table_store(&self) -> &Arc<TableStore> {
+        &self.table_store
+    }
+
+    pub fn data_wal(&self) -> &Arc<WALManager> {
+        &self.data_wal
+    }
+
+    pub fn index_wal(&self) -> &Arc<WALManager> {
+        &self.index_wal
+    }
+
+    /// The shared catalog; clones see the same tables
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    pub fn txn_manager(&self) -> &Arc<TransactionManager> {
+        &self.txn_manager
+    }
+
+    pub fn security(&self) -> &Arc<IntegratedSecurityManager> {
+        &self.security
+    }
+
+    /// The SQL firewall every front end checks parsed statements against;
+    /// it audits to the security manager's audit log
+    pub fn firewall(&self) -> &Arc<SqlFirewall> {
+        &self.security.firewall
+    }
+
+    pub fn constraints(&self) -> &Arc<ConstraintManager> {
+        &self.constraints
+    }
+
+    pub fn monitoring(&self) -> &Arc<MonitoringHub> {
+        &self.monitoring
+    }
+
+    /// Build an executor over the shared catalog, transactions, rows and
+    /// constraints
+    pub fn executor(&self) -> Executor {
+        Executor::new_with_storage(
+            Arc::new(self.catalog.clone()),
+            self.txn_manager.clone(),
+            self.table_store.clone(),
+        )
+        .with_constraints(self.constraints.clone())
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Resolves once shutdown has started; front ends stop accepting new
+    /// connections when it does
+    pub async fn shutdown_started(&self) {
+        let mut rx = self.shutdown.subscribe();
+        let _ = rx.wait_for(|stopping| *stopping).await;
+    }
+
+    /// Register a client connection for the lifetime of the returned guard
+    ///
+    /// Returns `None` once shutdown has started or `max_connections` clients
+    /// are already connected.
+    pub fn open_connection(self: &Arc<Self>) -> Option<ConnectionGuard> {
+        if self.is_shutting_down() {
+            return None;
+        }
+        let max = self.config.max_connections;
+        self.connections
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .ok()?;
+        Some(ConnectionGuard {
+            database: self.clone(),
+        })
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections.load(Ordering::Acquire)
+    }
+
+    /// Write back all dirty pages, then checkpoint the data and index WALs
+    /// so the log before the checkpoints can be removed
+    pub async fn checkpoint(&self) -> Result<()> {
+        self.table_store.flush()?;
+        self.checkpointer.checkpoint().await?;
+        self.index_checkpointer.checkpoint().await?;
+        Ok(())
+    }
+
+    /// Stop the database
+    ///
+    /// Front ends stop accepting connections, open ones get the configured
+    /// connection timeout to finish, and transactions still open after that
+    /// are aborted. The buffer pool is then flushed, a final checkpoint is
+    /// written, and the WALs are closed.
+    pub async fn shutdown(&self) -> Result<()> {
+        if self.shutdown.send_replace(true) {
+            return Err(DbError::Internal(
+                "Database is already shutting down".to_string(),
+            ));
+        }
+        if let Some(task) = self.checkpoint_task.lock().take() {
+            task.abort();
+        }
+
+        let drain = async {
+            while self.connection_count() > 0 {
+                let drained = self.drained.notified();
+                if self.connection_count() == 0 {
+                    break;
+                }
+                drained.await;
+            }
+        };
+        if tokio::time::timeout(self.config.connection_timeout, drain)
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                "{} connections still open after {:?}; shutting down anyway",
+                self.connection_count(),
+                self.config.connection_timeout
+            );
+        }
+
+        for txn_id in self.txn_manager.active_transaction_ids() {
+            if let Err(e) = self.txn_manager.abort(txn_id) {
+                tracing::warn!("Failed to abort transaction {}: {}", txn_id, e);
+            }
+        }
+
+        self.checkpoint().await?;
+        self.data_wal.shutdown()?;
+        self.index_wal.shutdown()
+    }
+}
+
+/// A client connection counted against `max_connections`; shutdown waits
+/// for every guard to be dropped
+pub struct ConnectionGuard {
+    database: Arc<Database>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if self.database.connections.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.database.drained.notify_waiters();
+        }
+    }
+}
+
+// Configuration of a small database in a fresh directory named after `name`
+// under the system temp dir; tests remove it when they finish
+#[cfg(test)]
+pub(crate) fn test_config(name: &str) -> DatabaseConfig {
+    let dir = std::env::temp_dir().join(format!("rustydb-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    DatabaseConfig {
+        data_dir: dir.join("data").display().to_string(),
+        wal_dir: dir.join("wal").display().to_string(),
+        page_size: 4096,
+        buffer_pool_size: 64,
+        connection_timeout: Duration::from_secs(5),
+        ..DatabaseConfig::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SqlParser;
+
+    fn run(database: &Database, sql: &str) -> Result<crate::execution::QueryResult> {
+        let stmt = SqlParser::new().parse(sql)?.remove(0);
+        database.executor().execute(stmt)
+    }
+
+    #[tokio::test]
+    async fn test_rows_survive_shutdown_and_reopen() -> Result<()> {
+        let config = test_config("database-reopen");
+        {
+            let database = Database::open(config.clone()).await?;
+            run(&database, "CREATE TABLE t (id INT, name VARCHAR(20))")?;
+            run(&database, "INSERT INTO t VALUES (1, 'a'), (2, 'b')")?;
+            database.shutdown().await?;
+        }
+
+        let database = Database::open(config.clone()).await?;
+        let result = run(&database, "SELECT id FROM t")?;
+        assert_eq!(result.rows.len(), 2);
+        database.shutdown().await?;
+
+        let _ = std::fs::remove_dir_all(Path::new(&config.data_dir).parent().unwrap());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_foreign_keys_survive_reopen() -> Result<()> {
+        let config = test_config("database-foreign-keys");
+        {
+            let database = Database::open(config.clone()).await?;
+            run(&database, "CREATE TABLE users (id INT NOT NULL)")?;
+            run(
+                &database,
+                "CREATE TABLE orders (id INT, user_id INT REFERENCES users (id) ON DELETE CASCADE)",
+            )?;
+            run(&database, "CREATE TABLE notes (id INT, user_id INT)")?;
+            run(
+                &database,
+                "ALTER TABLE notes ADD CONSTRAINT notes_user FOREIGN KEY (user_id) REFERENCES users (id)",
+            )?;
+            database.shutdown().await?;
+        }
+
+        let database = Database::open(config.clone()).await?;
+        let names: Vec<String> = database
+            .constraints()
+            .foreign_keys()
+            .into_iter()
+            .map(|fk| fk.name)
+            .collect();
+        assert_eq!(names, vec!["notes_user", "orders_user_id_fkey"]);
+        assert!(run(&database, "DROP TABLE users").is_err());
+
+        // Every executor enforces the shared constraints
+        run(&database, "INSERT INTO users VALUES (1)")?;
+        run(&database, "INSERT INTO orders VALUES (10, 1)")?;
+        run(&database, "DELETE FROM users WHERE id = 1")?;
+        assert!(run(&database, "SELECT id FROM orders")?.rows.is_empty());
+
+        run(&database, "ALTER TABLE notes DROP CONSTRAINT notes_user")?;
+        assert_eq!(database.catalog().list_foreign_keys().len(), 1);
+        assert_eq!(database.constraints().foreign_keys().len(), 1);
+        database.shutdown().await?;
+
+        let _ = std::fs::remove_dir_all(Path::new(&config.data_dir).parent().unwrap());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_connections() -> Result<()> {
+        let config = test_config("database-drain");
+        let database = Database::open(config.clone()).await?;
+
+        let connection = database.open_connection().expect("connection refused");
+        assert_eq!(database.connection_count(), 1);
+
+        let closer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(connection);
+        });
+        database.shutdown().await?;
+        closer.await.unwrap();
+
+        assert_eq!(database.connection_count(), 0);
+        assert!(database.open_connection().is_none());
+        assert!(database.shutdown().await.is_err());
+
+        let _ = std::fs::remove_dir_all(Path::new(&config.data_dir).parent().unwrap());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_firewall_is_configured_from_config() -> Result<()> {
+        use crate::security::sql_firewall::{FirewallIdentity, FirewallMode, FirewallScope};
+
+        let mut config = test_config("database-firewall");
+        config.firewall.mode = FirewallMode::Enforcing;
+        config.firewall.trusted_roles.insert("admin".to_string());
+        let database = Database::open(config.clone()).await?;
+
+        let firewall = database.firewall();
+        firewall.allow(FirewallScope::Role("app".to_string()), "SELECT 1");
+        firewall.check(&FirewallIdentity::new("app"), "SELECT 2")?;
+        firewall.check(&FirewallIdentity::new("admin"), "DROP TABLE t")?;
+        assert!(matches!(
+            firewall.check(&FirewallIdentity::new("app"), "DROP TABLE t"),
+            Err(DbError::PermissionDenied(_))
+        ));
+        // Blocked statements go to the database's audit log
+        assert_eq!(database.security().audit.get_statistics().total_records, 1);
+
+        database.shutdown().await?;
+        let _ = std::fs::remove_dir_all(Path::new(&config.data_dir).parent().unwrap());
+        Ok(())
+    }
+}
@@ -173,6 +173,49 @@ impl DbError {
     pub(crate) fn not_supported(p0: String) -> DbError {
         DbError::NotImplemented(p0)
     }
+
+    // PostgreSQL SQLSTATE code for this error, as reported to wire clients
+    pub fn sqlstate(&self) -> &'static str {
+        match self {
+            DbError::SqlParse(_) | DbError::ParseError(_) => "42601",
+            DbError::Catalog(msg) if msg.contains("not found") => "42P01",
+            DbError::Catalog(msg) if msg.contains("already exists") => "42P07",
+            DbError::Catalog(_) => "42000",
+            DbError::NotFound(_) => "42704",
+            DbError::AlreadyExists(_) => "42710",
+            DbError::Execution(msg) if msg.contains("Division by zero") => "22012",
+            DbError::Execution(msg) if msg.contains("overflow") => "22003",
+            DbError::Execution(_) | DbError::InvalidInput(_) | DbError::Validation(_) => "22000",
+            DbError::InvalidArgument(_) => "22023",
+            DbError::ConstraintViolation(_) => "23000",
+            DbError::NotImplemented(_) => "0A000",
+            DbError::Transaction(msg) if msg.contains("aborted") => "25P02",
+            DbError::Transaction(_) => "25000",
+            DbError::Conflict(_) => "40001",
+            DbError::Deadlock => "40P01",
+            DbError::LockTimeout | DbError::LockError(_) => "55P03",
+            DbError::InvalidState(_) | DbError::InvalidOperation(_) => "55000",
+            DbError::Authentication(_) => "28P01",
+            DbError::PermissionDenied(_)
+            | DbError::Authorization(_)
+            | DbError::Security(_)
+            | DbError::InjectionAttempt(_) => "42501",
+            DbError::OutOfMemory(_) | DbError::Memory(_) => "53200",
+            DbError::LimitExceeded(_)
+            | DbError::ResourceExhausted(_)
+            | DbError::QuotaExceeded(_)
+            | DbError::BulkheadFull(_) => "53000",
+            DbError::Timeout(_) => "57014",
+            DbError::Unavailable(_) | DbError::CircuitBreakerOpen(_) => "57P03",
+            DbError::Network(msg) if msg.starts_with("Protocol violation") => "08P01",
+            DbError::Network(_) => "08006",
+            DbError::InvalidRequest => "08P01",
+            DbError::Io(_) => "58030",
+            DbError::Corruption(_) | DbError::PageNotFound(_) => "XX001",
+            DbError::Configuration(_) => "F0000",
+            _ => "XX000",
+        }
+    }
 }
 
 impl Clone for DbError {
@@ -0,0 +1,883 @@
+// Access path selection
+//
+// Runs on optimized plans and replaces table scans with index lookups. A
+// filter over a table scan reads the table through an index when its
+// conjuncts fix a prefix of the key columns (equalities or IN lists) and
+// possibly bound the key column after them; if everything above the filter
+// only needs key columns, the rows come from the index alone. A join whose
+// right side is a table with an index on the join columns looks up the
+// matching rows for each left row instead of scanning the table.
+//
+// Index scans may return rows outside their bounds (rows changed since the
+// snapshot, bound values the index cannot compare), so the filter or join
+// condition a lookup came from always stays in the plan to recheck them.
+//
+// A query that sorts a table by its distance to a constant vector and keeps
+// the first k rows reads the k nearest rows from an HNSW index on the vector
+// column instead of the whole table. The sort stays above the scan, since
+// the search returns rows in no particular order.
+
+use crate::catalog::{Catalog, Column, DataType, Schema};
+use crate::common::Value;
+use crate::error::DbError;
+use crate::execution::expressions::{BinaryOperator, UnaryOperator};
+use crate::execution::planner::{IndexBound, IndexBounds, PlanNode, ScalarExpr};
+use crate::index::partial::{ColumnValue, ComparisonOp, Predicate};
+use crate::index::table_index::TableIndex;
+use crate::parser::JoinType;
+use crate::storage::TableStore;
+
+// Most lookups the IN lists of one index scan may expand to
+const MAX_PREFIXES: usize = 64;
+
+pub struct AccessPathSelector<'a> {
+    catalog: &'a Catalog,
+    store: &'a TableStore,
+}
+
+// What a conjunct says about one column of a table
+enum Sarg {
+    Equal(ScalarExpr),
+    In(Vec<ScalarExpr>),
+    Lower(IndexBound),
+    Upper(IndexBound),
+}
+
+// How well an index serves a lookup: a unique match beats any number of
+// equality columns, which beat a range alone
+type Score = (bool, usize, bool);
+
+impl<'a> AccessPathSelector<'a> {
+    pub fn new(catalog: &'a Catalog, store: &'a TableStore) -> Self {
+        Self { catalog, store }
+    }
+
+    pub fn select(&self, plan: PlanNode) -> PlanNode {
+        let mut plan = self.rewrite(plan);
+        self.subqueries(&mut plan);
+        plan
+    }
+
+    fn rewrite(&self, plan: PlanNode) -> PlanNode {
+        match plan {
+            PlanNode::Filter { input, predicate } => {
+                let conjuncts = predicate.clone().into_conjuncts();
+                let input = match *input {
+                    PlanNode::TableScan { table, columns } => {
+                        self.scan(table, columns, &conjuncts, None)
+                    }
+                    PlanNode::Join {
+                        join_type,
+                        left,
+                        right,
+                        condition,
+                    } => self.join(join_type, *left, *right, condition, &conjuncts),
+                    input => self.rewrite(input),
+                };
+                PlanNode::Filter {
+                    input: Box::new(input),
+                    predicate,
+                }
+            }
+            PlanNode::Join {
+                join_type,
+                left,
+                right,
+                condition,
+            } => self.join(join_type, *left, *right, condition, &[]),
+            PlanNode::Project {
+                input,
+                exprs,
+                columns,
+            } => {
+                let needed: Vec<&ScalarExpr> = exprs.iter().collect();
+                PlanNode::Project {
+                    input: Box::new(self.covered(*input, &needed)),
+                    exprs,
+                    columns,
+                }
+            }
+            PlanNode::Aggregate {
+                input,
+                group_by,
+                aggregates,
+                having,
+            } => {
+                let needed: Vec<&ScalarExpr> = group_by
+                    .iter()
+                    .chain(aggregates.iter().filter_map(|agg| agg.arg.as_ref()))
+                    .collect();
+                PlanNode::Aggregate {
+                    input: Box::new(self.covered(*input, &needed)),
+                    group_by,
+                    aggregates,
+                    having,
+                }
+            }
+            PlanNode::Sort { input, order_by } => PlanNode::Sort {
+                input: Box::new(self.rewrite(*input)),
+                order_by,
+            },
+            PlanNode::Window {
+                input,
+                partition_by,
+                order_by,
+                functions,
+            } => PlanNode::Window {
+                input: Box::new(self.rewrite(*input)),
+                partition_by,
+                order_by,
+                functions,
+            },
+            PlanNode::Limit {
+                mut input,
+                limit,
+                offset,
+            } => {
+                let k = limit.saturating_add(offset.unwrap_or(0));
+                if !self.nearest_neighbors(&mut input, k) {
+                    input = Box::new(self.rewrite(*input));
+                }
+                PlanNode::Limit {
+                    input,
+                    limit,
+                    offset,
+                }
+            }
+            PlanNode::Distinct { input } => PlanNode::Distinct {
+                input: Box::new(self.rewrite(*input)),
+            },
+            PlanNode::Subquery { plan, alias } => PlanNode::Subquery {
+                plan: Box::new(self.rewrite(*plan)),
+                alias,
+            },
+            PlanNode::SetOperation {
+                op,
+                all,
+                left,
+                right,
+            } => PlanNode::SetOperation {
+                op,
+                all,
+                left: Box::new(self.rewrite(*left)),
+                right: Box::new(self.rewrite(*right)),
+            },
+            PlanNode::RecursiveUnion {
+                name,
+                columns,
+                base,
+                recursive,
+                all,
+                search,
+                cycle,
+            } => PlanNode::RecursiveUnion {
+                name,
+                columns,
+                base: Box::new(self.rewrite(*base)),
+                recursive: Box::new(self.rewrite(*recursive)),
+                all,
+                search,
+                cycle,
+            },
+            plan @ (PlanNode::TableScan { .. }
+            | PlanNode::IndexScan { .. }
+            | PlanNode::IndexOnlyScan { .. }
+            | PlanNode::NearestNeighborScan { .. }
+            | PlanNode::IndexNestedLoopJoin { .. }
+            | PlanNode::WorkTableScan { .. }
+            | PlanNode::Values { .. }) => plan,
+        }
+    }
+
+    // Input of an operator that reads nothing but `needed` from it: a
+    // filtered table scan can then be answered from an index alone
+    fn covered(&self, input: PlanNode, needed: &[&ScalarExpr]) -> PlanNode {
+        match input {
+            PlanNode::Filter { input, predicate } => match *input {
+                PlanNode::TableScan { table, columns }
+                    if !predicate.has_subquery() && !needed.iter().any(|e| e.has_subquery()) =>
+                {
+                    let mut columns_read = Vec::new();
+                    predicate.referenced_columns(&mut columns_read);
+                    for expr in needed {
+                        expr.referenced_columns(&mut columns_read);
+                    }
+                    let conjuncts = predicate.clone().into_conjuncts();
+                    PlanNode::Filter {
+                        input: Box::new(self.scan(
+                            table,
+                            columns,
+                            &conjuncts,
+                            Some(columns_read.as_slice()),
+                        )),
+                        predicate,
+                    }
+                }
+                input => self.rewrite(PlanNode::Filter {
+                    input: Box::new(input),
+                    predicate,
+                }),
+            },
+            PlanNode::Sort { input, order_by } => {
+                let mut needed = needed.to_vec();
+                needed.extend(order_by.iter().map(|key| &key.expr));
+                let input = self.covered(*input, &needed);
+                PlanNode::Sort {
+                    input: Box::new(input),
+                    order_by,
+                }
+            }
+            PlanNode::Limit {
+                input,
+                limit,
+                offset,
+            } => PlanNode::Limit {
+                input: Box::new(self.covered(*input, needed)),
+                limit,
+                offset,
+            },
+            input => self.rewrite(input),
+        }
+    }
+
+    // A table scan whose rows must satisfy `conjuncts`; `columns_read` are
+    // the only columns needed from it, if known
+    fn scan(
+        &self,
+        table: String,
+        columns: Vec<String>,
+        conjuncts: &[ScalarExpr],
+        columns_read: Option<&[usize]>,
+    ) -> PlanNode {
+        match self.best_index(&table, &columns, conjuncts, 0, columns_read) {
+            Some((index, bounds, true)) => PlanNode::IndexOnlyScan {
+                table,
+                index,
+                columns,
+                bounds,
+            },
+            Some((index, bounds, false)) => PlanNode::IndexScan {
+                table,
+                index,
+                columns,
+                bounds,
+            },
+            None => PlanNode::TableScan { table, columns },
+        }
+    }
+
+    // Narrow each side of a join by the conjuncts of the filter above it
+    // and of its condition that only read that side and that every output
+    // row built from the side's rows must satisfy. A table on the right is
+    // looked up by the join columns instead if it has an index on them
+    fn join(
+        &self,
+        join_type: JoinType,
+        left: PlanNode,
+        right: PlanNode,
+        condition: Option<ScalarExpr>,
+        filter: &[ScalarExpr],
+    ) -> PlanNode {
+        let width = left.output_columns().len();
+        // Cross joins pair every row whatever their condition says
+        let terms = match (&join_type, &condition) {
+            (JoinType::Cross, _) | (_, None) => Vec::new(),
+            (_, Some(condition)) => condition.clone().into_conjuncts(),
+        };
+        // Whether a conjunct of the filter or the condition holds for every
+        // output row built from a row of the left or the right side
+        let narrows = |left_side: bool, from_condition: bool| match join_type {
+            JoinType::Inner | JoinType::Cross => true,
+            JoinType::Left => left_side != from_condition,
+            JoinType::Right => left_side == from_condition,
+            JoinType::Full => false,
+        };
+
+        let mut left_terms = Vec::new();
+        let mut right_terms = Vec::new();
+        let candidates = filter
+            .iter()
+            .map(|term| (term, false))
+            .chain(terms.iter().map(|term| (term, true)));
+        for (term, from_condition) in candidates {
+            if term.has_subquery() {
+                continue;
+            }
+            let mut columns = Vec::new();
+            term.referenced_columns(&mut columns);
+            if columns.is_empty() {
+                continue;
+            }
+            if columns.iter().all(|&c| c < width) && narrows(true, from_condition) {
+                left_terms.push(term.clone());
+            } else if columns.iter().all(|&c| c >= width) && narrows(false, from_condition) {
+                let mut term = term.clone();
+                term.remap_columns(&|c| c - width);
+                right_terms.push(term);
+            }
+        }
+
+        let left = Box::new(self.side(left, &left_terms));
+        let lookup_right = matches!(
+            join_type,
+            JoinType::Inner | JoinType::Left | JoinType::Cross
+        );
+        if let (PlanNode::TableScan { table, columns }, true) = (&right, lookup_right) {
+            // The conjuncts a right row must satisfy to join a left row
+            let mut keys = terms;
+            if join_type != JoinType::Left {
+                keys.extend(filter.iter().cloned());
+            }
+            if let Some((index, bounds, _)) = self.best_index(table, columns, &keys, width, None) {
+                let mut columns_used = Vec::new();
+                bounds
+                    .exprs()
+                    .for_each(|expr| expr.referenced_columns(&mut columns_used));
+                if !columns_used.is_empty() {
+                    let (join_type, condition) = match join_type {
+                        JoinType::Cross => (JoinType::Inner, None),
+                        join_type => (join_type, condition),
+                    };
+                    return PlanNode::IndexNestedLoopJoin {
+                        join_type,
+                        left,
+                        table: table.clone(),
+                        index,
+                        columns: columns.clone(),
+                        bounds,
+                        condition,
+                    };
+                }
+            }
+        }
+        PlanNode::Join {
+            join_type,
+            left,
+            right: Box::new(self.side(right, &right_terms)),
+            condition,
+        }
+    }
+
+    // Replace the table scan under a sort by distance to a constant vector
+    // with a search for the `k` nearest rows, if an HNSW index on the vector
+    // column orders rows the same way. Only an unfiltered scan qualifies:
+    // rows a filter drops would leave fewer than `k` behind
+    fn nearest_neighbors(&self, plan: &mut PlanNode, k: usize) -> bool {
+        let plan = match plan {
+            PlanNode::Project { input, .. } => input.as_mut(),
+            plan => plan,
+        };
+        let PlanNode::Sort { input, order_by } = plan else {
+            return false;
+        };
+        let (Some(key), PlanNode::TableScan { table, columns }) = (order_by.first(), &**input)
+        else {
+            return false;
+        };
+        let ScalarExpr::Function { name, args } = &key.expr else {
+            return false;
+        };
+        let (column, query) = match args.as_slice() {
+            [ScalarExpr::Column { index, .. }, query]
+            | [query, ScalarExpr::Column { index, .. }] => (*index, query),
+            _ => return false,
+        };
+        let mut columns_read = Vec::new();
+        query.referenced_columns(&mut columns_read);
+        if !columns_read.is_empty() || query.has_subquery() {
+            return false;
+        }
+        let Ok(schema) = self.catalog.get_table(table) else {
+            return false;
+        };
+        if !columns.iter().eq(schema.columns.iter().map(|c| &c.name)) {
+            return false;
+        }
+        // Rows with a NULL vector are not in the graph, so they must sort last
+        if key.nulls_first() && schema.columns.get(column).is_none_or(|c| c.nullable) {
+            return false;
+        }
+        let index = self.store.table_indexes(table).into_iter().find(|index| {
+            let definition = index.definition();
+            definition.predicate.is_none()
+                && index.positions() == [column]
+                && definition.hnsw.as_ref().is_some_and(|options| {
+                    options.metric.function() == (name.as_str(), key.ascending)
+                })
+        });
+        let Some(index) = index else {
+            return false;
+        };
+        **input = PlanNode::NearestNeighborScan {
+            table: table.clone(),
+            index: index.name().to_string(),
+            columns: columns.clone(),
+            query: query.clone(),
+            limit: k,
+        };
+        true
+    }
+
+    fn side(&self, plan: PlanNode, conjuncts: &[ScalarExpr]) -> PlanNode {
+        match plan {
+            PlanNode::TableScan { table, columns } => self.scan(table, columns, conjuncts, None),
+            plan => self.rewrite(plan),
+        }
+    }
+
+    // The index of `table` that narrows down the rows satisfying
+    // `conjuncts` the most, with its bounds and whether it holds every
+    // column in `columns_read`. The table's columns start at `offset` in
+    // the rows the conjuncts read; the columns before it are known for
+    // each lookup
+    fn best_index(
+        &self,
+        table: &str,
+        columns: &[String],
+        conjuncts: &[ScalarExpr],
+        offset: usize,
+        columns_read: Option<&[usize]>,
+    ) -> Option<(String, IndexBounds, bool)> {
+        let schema = self.catalog.get_table(table).ok()?;
+        if !columns.iter().eq(schema.columns.iter().map(|c| &c.name)) {
+            return None;
+        }
+        let indexes = self.store.table_indexes(table);
+        if indexes.is_empty() {
+            return None;
+        }
+        let sargs: Vec<(usize, Sarg)> = conjuncts
+            .iter()
+            .flat_map(|term| sargs(term, &schema, offset))
+            .collect();
+        if sargs.is_empty() {
+            return None;
+        }
+        // What the conjuncts imply about single rows, to match against the
+        // predicates of partial indexes
+        let implied: Vec<Predicate> = conjuncts
+            .iter()
+            .filter_map(|term| {
+                let mut columns = Vec::new();
+                term.referenced_columns(&mut columns);
+                if term.has_subquery() || columns.iter().any(|&c| c < offset) {
+                    return None;
+                }
+                let mut term = term.clone();
+                term.remap_columns(&|c| c - offset);
+                index_predicate(&term, &schema).ok()
+            })
+            .collect();
+        let not_null: Vec<&str> = sargs
+            .iter()
+            .map(|(c, _)| schema.columns[*c].name.as_str())
+            .collect();
+
+        let mut best: Option<((Score, bool), String, IndexBounds)> = None;
+        for index in &indexes {
+            if index.is_hnsw() {
+                continue;
+            }
+            if let Some(predicate) = &index.definition().predicate {
+                if !implies(&implied, &not_null, predicate) {
+                    continue;
+                }
+            }
+            let Some((bounds, score)) = index_bounds(index, &sargs) else {
+                continue;
+            };
+            // Keys of intervals and of decimals without a scale do not keep
+            // the exact value they were made from
+            let exact = !index
+                .types()
+                .iter()
+                .any(|t| matches!(t, DataType::Interval | DataType::Numeric(None)));
+            let covers = exact
+                && columns_read
+                    .is_some_and(|read| read.iter().all(|c| index.positions().contains(c)));
+            let rank = (score, covers);
+            if best.as_ref().is_some_and(|(best, ..)| *best >= rank) {
+                continue;
+            }
+            best = Some((rank, index.name().to_string(), bounds));
+        }
+        best.map(|((_, covers), index, bounds)| (index, bounds, covers))
+    }
+
+    // Select access paths inside the subqueries of every expression
+    fn subqueries(&self, plan: &mut PlanNode) {
+        let mut exprs: Vec<&mut ScalarExpr> = Vec::new();
+        match plan {
+            PlanNode::TableScan { .. }
+            | PlanNode::IndexScan { .. }
+            | PlanNode::IndexOnlyScan { .. }
+            | PlanNode::WorkTableScan { .. }
+            | PlanNode::Values { .. } => {}
+            PlanNode::NearestNeighborScan { query, .. } => exprs.push(query),
+            PlanNode::IndexNestedLoopJoin {
+                left, condition, ..
+            } => {
+                self.subqueries(left);
+                exprs.extend(condition.as_mut());
+            }
+            PlanNode::Filter { input, predicate } => {
+                self.subqueries(input);
+                exprs.push(predicate);
+            }
+            PlanNode::Project {
+                input,
+                exprs: projected,
+                ..
+            } => {
+                self.subqueries(input);
+                exprs.extend(projected.iter_mut());
+            }
+            PlanNode::Join {
+                left,
+                right,
+                condition,
+                ..
+            } => {
+                self.subqueries(left);
+                self.subqueries(right);
+                exprs.extend(condition.as_mut());
+            }
+            PlanNode::Aggregate {
+                input,
+                group_by,
+                aggregates,
+                having,
+            } => {
+                self.subqueries(input);
+                exprs.extend(group_by.iter_mut());
+                exprs.extend(aggregates.iter_mut().filter_map(|agg| agg.arg.as_mut()));
+                exprs.extend(having.as_mut());
+            }
+            PlanNode::Sort { input, order_by } => {
+                self.subqueries(input);
+                exprs.extend(order_by.iter_mut().map(|key| &mut key.expr));
+            }
+            PlanNode::Window {
+                input,
+                partition_by,
+                order_by,
+                functions,
+            } => {
+                self.subqueries(input);
+                exprs.extend(partition_by.iter_mut());
+                exprs.extend(order_by.iter_mut().map(|key| &mut key.expr));
+                exprs.extend(functions.iter_mut().flat_map(|f| f.args.iter_mut()));
+            }
+            PlanNode::Limit { input, .. } | PlanNode::Distinct { input } => self.subqueries(input),
+            PlanNode::Subquery { plan, .. } => self.subqueries(plan),
+            PlanNode::SetOperation { left, right, .. } => {
+                self.subqueries(left);
+                self.subqueries(right);
+            }
+            PlanNode::RecursiveUnion {
+                base, recursive, ..
+            } => {
+                self.subqueries(base);
+                self.subqueries(recursive);
+            }
+        }
+        for expr in exprs {
+            expr.subquery_plans_mut(&mut |plan| {
+                let taken = std::mem::replace(
+                    plan,
+                    PlanNode::Values {
+                        columns: Vec::new(),
+                        rows: Vec::new(),
+                    },
+                );
+                *plan = self.select(taken);
+            });
+        }
+    }
+}
+
+// What `term` says about the columns of a table starting at `offset` in the
+// rows it reads, given the columns before it. Every conjunct understood
+// here is false when its column is NULL
+fn sargs(term: &ScalarExpr, schema: &Schema, offset: usize) -> Vec<(usize, Sarg)> {
+    let column = |expr: &ScalarExpr| match expr {
+        ScalarExpr::Column { index, .. }
+            if *index >= offset && *index - offset < schema.columns.len() =>
+        {
+            Some(*index - offset)
+        }
+        _ => None,
+    };
+    let is_value = |expr: &ScalarExpr| {
+        let mut columns = Vec::new();
+        expr.referenced_columns(&mut columns);
+        !expr.has_subquery() && columns.iter().all(|&c| c < offset)
+    };
+    let bound = |value: &ScalarExpr, inclusive| IndexBound {
+        value: value.clone(),
+        inclusive,
+    };
+
+    match term {
+        ScalarExpr::Binary { left, op, right } => {
+            let (left, right) = (&**left, &**right);
+            let (c, op, value) = match (column(left), column(right)) {
+                (Some(c), _) if is_value(right) => (c, *op, right),
+                (_, Some(c)) if is_value(left) => (c, flip(*op), left),
+                _ => return Vec::new(),
+            };
+            match op {
+                BinaryOperator::Equal => vec![(c, Sarg::Equal(value.clone()))],
+                BinaryOperator::GreaterThan => vec![(c, Sarg::Lower(bound(value, false)))],
+                BinaryOperator::GreaterThanOrEqual => vec![(c, Sarg::Lower(bound(value, true)))],
+                BinaryOperator::LessThan => vec![(c, Sarg::Upper(bound(value, false)))],
+                BinaryOperator::LessThanOrEqual => vec![(c, Sarg::Upper(bound(value, true)))],
+                _ => Vec::new(),
+            }
+        }
+        ScalarExpr::InList {
+            expr,
+            list,
+            negated: false,
+        } => match column(&**expr) {
+            Some(c) if list.iter().all(is_value) => vec![(c, Sarg::In(list.clone()))],
+            _ => Vec::new(),
+        },
+        ScalarExpr::Between {
+            expr,
+            low,
+            high,
+            negated: false,
+        } => match column(&**expr) {
+            Some(c) if is_value(&**low) && is_value(&**high) => vec![
+                (c, Sarg::Lower(bound(&**low, true))),
+                (c, Sarg::Upper(bound(&**high, true))),
+            ],
+            _ => Vec::new(),
+        },
+        // Strings with the pattern's literal prefix sort from the prefix up
+        // to the prefix with its last character incremented
+        ScalarExpr::Like {
+            expr,
+            pattern,
+            negated: false,
+            case_insensitive: false,
+        } => {
+            let (Some(c), ScalarExpr::Literal(Value::String(pattern))) =
+                (column(&**expr), &**pattern)
+            else {
+                return Vec::new();
+            };
+            if !matches!(
+                schema.columns[c].data_type,
+                DataType::Varchar(_) | DataType::Text
+            ) {
+                return Vec::new();
+            }
+            let prefix: String = pattern
+                .chars()
+                .take_while(|&ch| ch != '%' && ch != '_')
+                .collect();
+            let Some(last) = prefix.chars().last() else {
+                return Vec::new();
+            };
+            let literal = |s: String| ScalarExpr::Literal(Value::String(s));
+            let mut sargs = vec![(c, Sarg::Lower(bound(&literal(prefix.clone()), true)))];
+            if let Some(next) = char::from_u32(last as u32 + 1) {
+                let mut end = prefix[..prefix.len() - last.len_utf8()].to_string();
+                end.push(next);
+                sargs.push((c, Sarg::Upper(bound(&literal(end), false))));
+            }
+            sargs
+        }
+        _ => Vec::new(),
+    }
+}
+
+// The operator with its operands swapped
+pub(crate) fn flip(op: BinaryOperator) -> BinaryOperator {
+    match op {
+        BinaryOperator::LessThan => BinaryOperator::GreaterThan,
+        BinaryOperator::LessThanOrEqual => BinaryOperator::GreaterThanOrEqual,
+        BinaryOperator::GreaterThan => BinaryOperator::LessThan,
+        BinaryOperator::GreaterThanOrEqual => BinaryOperator::LessThanOrEqual,
+        op => op,
+    }
+}
+
+// Bounds of a lookup in `index`: the longest prefix of key columns with
+// equalities or IN lists, then a range on the next key column
+fn index_bounds(index: &TableIndex, sargs: &[(usize, Sarg)]) -> Option<(IndexBounds, Score)> {
+    let mut prefixes: Vec<Vec<ScalarExpr>> = vec![Vec::new()];
+    let mut equal = 0;
+    for &position in index.positions() {
+        let equality = sargs.iter().find_map(|(c, sarg)| match sarg {
+            Sarg::Equal(value) if *c == position => Some(std::slice::from_ref(value)),
+            _ => None,
+        });
+        let values = equality.or_else(|| {
+            sargs.iter().find_map(|(c, sarg)| match sarg {
+                Sarg::In(list) if *c == position && list.len() * prefixes.len() <= MAX_PREFIXES => {
+                    Some(list.as_slice())
+                }
+                _ => None,
+            })
+        });
+        let Some(values) = values else {
+            break;
+        };
+        prefixes = prefixes
+            .iter()
+            .flat_map(|prefix| {
+                values.iter().map(move |value| {
+                    let mut prefix = prefix.clone();
+                    prefix.push(value.clone());
+                    prefix
+                })
+            })
+            .collect();
+        equal += 1;
+    }
+
+    let (mut lower, mut upper) = (None, None);
+    if let Some(&next) = index.positions().get(equal) {
+        for (c, sarg) in sargs {
+            match sarg {
+                Sarg::Lower(bound) if *c == next && lower.is_none() => lower = Some(bound.clone()),
+                Sarg::Upper(bound) if *c == next && upper.is_none() => upper = Some(bound.clone()),
+                _ => {}
+            }
+        }
+    }
+    let range = lower.is_some() || upper.is_some();
+    if equal == 0 && !range {
+        return None;
+    }
+    let unique = index.is_unique() && equal == index.positions().len() && prefixes.len() == 1;
+    let bounds = IndexBounds {
+        key_columns: index.definition().columns.clone(),
+        prefixes,
+        lower,
+        upper,
+    };
+    Some((bounds, (unique, equal, range)))
+}
+
+// Whether rows satisfying `conjuncts`, whose `not_null` columns cannot be
+// NULL, all satisfy `predicate`: each of its conjuncts must be among them
+fn implies(conjuncts: &[Predicate], not_null: &[&str], predicate: &Predicate) -> bool {
+    predicate.conjuncts().into_iter().all(|term| {
+        conjuncts.iter().any(|c| c.conjuncts().contains(&term))
+            || matches!(term, Predicate::IsNotNull(column)
+                if not_null.iter().any(|c| c.eq_ignore_ascii_case(column)))
+    })
+}
+
+/// The predicate of a partial index from its bound WHERE clause: AND, OR
+/// and NOT of comparisons between a column and a constant, IS [NOT] NULL
+/// tests and boolean columns
+pub(crate) fn index_predicate(expr: &ScalarExpr, schema: &Schema) -> Result<Predicate, DbError> {
+    let unsupported =
+        || DbError::NotImplemented(format!("Unsupported partial index predicate: {}", expr));
+    let column = |expr: &ScalarExpr| match expr {
+        ScalarExpr::Column { index, .. } => schema.columns.get(*index),
+        _ => None,
+    };
+    let both = |left: &ScalarExpr, right: &ScalarExpr| {
+        Ok::<_, DbError>((
+            Box::new(index_predicate(left, schema)?),
+            Box::new(index_predicate(right, schema)?),
+        ))
+    };
+
+    match expr {
+        ScalarExpr::Binary {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let (left, right) = both(&**left, &**right)?;
+            Ok(Predicate::And(left, right))
+        }
+        ScalarExpr::Binary {
+            left,
+            op: BinaryOperator::Or,
+            right,
+        } => {
+            let (left, right) = both(&**left, &**right)?;
+            Ok(Predicate::Or(left, right))
+        }
+        ScalarExpr::Binary { left, op, right } => {
+            let (col, op, value) = match (&**left, &**right) {
+                (left, ScalarExpr::Literal(value)) if column(left).is_some() => {
+                    (column(left), *op, value)
+                }
+                (ScalarExpr::Literal(value), right) if column(right).is_some() => {
+                    (column(right), flip(*op), value)
+                }
+                _ => return Err(unsupported()),
+            };
+            let col = col.ok_or_else(unsupported)?;
+            let operator = match op {
+                BinaryOperator::Equal => ComparisonOp::Equal,
+                BinaryOperator::NotEqual => ComparisonOp::NotEqual,
+                BinaryOperator::LessThan => ComparisonOp::LessThan,
+                BinaryOperator::LessThanOrEqual => ComparisonOp::LessThanOrEqual,
+                BinaryOperator::GreaterThan => ComparisonOp::GreaterThan,
+                BinaryOperator::GreaterThanOrEqual => ComparisonOp::GreaterThanOrEqual,
+                _ => return Err(unsupported()),
+            };
+            Ok(Predicate::Comparison {
+                column: col.name.clone(),
+                operator,
+                value: column_value(col, value)?,
+            })
+        }
+        ScalarExpr::Unary { op, expr: operand } => match (op, column(&**operand)) {
+            (UnaryOperator::Not, _) => {
+                Ok(Predicate::Not(Box::new(index_predicate(operand, schema)?)))
+            }
+            (UnaryOperator::IsNull, Some(col)) => Ok(Predicate::IsNull(col.name.clone())),
+            (UnaryOperator::IsNotNull, Some(col)) => Ok(Predicate::IsNotNull(col.name.clone())),
+            _ => Err(unsupported()),
+        },
+        ScalarExpr::Column { .. } => match column(expr) {
+            Some(col) if col.data_type == DataType::Boolean => Ok(Predicate::Comparison {
+                column: col.name.clone(),
+                operator: ComparisonOp::Equal,
+                value: ColumnValue::Boolean(true),
+            }),
+            _ => Err(unsupported()),
+        },
+        _ => Err(unsupported()),
+    }
+}
+
+// A constant compared with `column`, as the index stores the column
+fn column_value(column: &Column, value: &Value) -> Result<ColumnValue, DbError> {
+    if matches!(
+        column.data_type,
+        DataType::Float
+            | DataType::Double
+            | DataType::Numeric(_)
+            | DataType::Interval
+            | DataType::Array(_)
+    ) {
+        return Err(DbError::NotImplemented(format!(
+            "Partial index predicates cannot compare {} column {}",
+            column.data_type, column.name
+        )));
+    }
+    match column.data_type.coerce(value.clone())? {
+        Value::Integer(v) | Value::Date(v) | Value::Timestamp(v) | Value::TimestampTz(v) => {
+            Ok(ColumnValue::Integer(v))
+        }
+        Value::Boolean(b) => Ok(ColumnValue::Boolean(b)),
+        Value::String(s) => Ok(ColumnValue::String(s)),
+        Value::Uuid(u) => Ok(ColumnValue::String(u.to_string())),
+        _ => Err(DbError::InvalidInput(format!(
+            "Partial index predicate compares column {} with NULL",
+            column.name
+        ))),
+    }
+}
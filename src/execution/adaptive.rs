@@ -11,8 +11,12 @@
 // - Dynamic operator parameter tuning
 // - Adaptive query timeouts and resource limits
 
+use crate::common::Value;
 use crate::error::DbError;
-use crate::execution::{planner::PlanNode, QueryResult};
+use crate::execution::{
+    planner::{PlanNode, ScalarExpr},
+    QueryResult,
+};
 use crate::parser::JoinType;
 use parking_lot::RwLock;
 use std::collections::HashMap;
@@ -301,7 +305,10 @@ impl AdaptiveExecutor {
                 group_by,
                 aggregates,
                 having,
-            } => self.execute_adaptive_aggregate(*input, group_by, aggregates, having),
+            } => {
+                let group_by = group_by.iter().map(|expr| expr.to_string()).collect();
+                self.execute_adaptive_aggregate(*input, group_by, aggregates, having)
+            }
             PlanNode::TableScan { table, columns } => self.execute_adaptive_scan(table, columns),
             _ => {
                 // Fall back to standard execution
@@ -316,7 +323,7 @@ impl AdaptiveExecutor {
         join_type: JoinType,
         left: PlanNode,
         right: PlanNode,
-        condition: String,
+        condition: Option<ScalarExpr>,
     ) -> Result<QueryResult, DbError> {
         // Execute left side first to get actual cardinality
         let left_result = self.execute_with_checkpoints(left)?;
@@ -379,16 +386,17 @@ impl AdaptiveExecutor {
         left: QueryResult,
         right: QueryResult,
         _join_type: JoinType,
-        _condition: String,
+        _condition: Option<ScalarExpr>,
         algorithm: JoinAlgorithm,
     ) -> Result<QueryResult, DbError> {
         match algorithm {
             JoinAlgorithm::Hash => {
                 // Build hash table on right
-                let mut hash_table: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+                let mut hash_table: HashMap<Value, Vec<Vec<Value>>> = HashMap::new();
 
+                // NULL keys never match
                 for row in &right.rows {
-                    if let Some(key) = row.get(0) {
+                    if let Some(key) = row.first().filter(|k| !k.is_null()) {
                         hash_table
                             .entry(key.clone())
                             .or_insert_with(Vec::new)
@@ -399,7 +407,7 @@ impl AdaptiveExecutor {
                 // Probe with left
                 let mut result_rows = Vec::new();
                 for left_row in &left.rows {
-                    if let Some(key) = left_row.get(0) {
+                    if let Some(key) = left_row.first().filter(|k| !k.is_null()) {
                         if let Some(right_rows) = hash_table.get(key) {
                             for right_row in right_rows {
                                 let mut joined = left_row.clone();
@@ -430,9 +438,13 @@ impl AdaptiveExecutor {
                 for left_row in &left_sorted {
                     while right_idx < right_sorted.len() {
                         if let (Some(lk), Some(rk)) =
-                            (left_row.get(0), right_sorted[right_idx].get(0))
+                            (left_row.first(), right_sorted[right_idx].first())
                         {
-                            if lk == rk {
+                            if lk.is_null() {
+                                break;
+                            } else if rk.is_null() {
+                                right_idx += 1;
+                            } else if lk == rk {
                                 let mut joined = left_row.clone();
                                 joined.extend(right_sorted[right_idx].clone());
                                 result_rows.push(joined);
@@ -480,7 +492,7 @@ impl AdaptiveExecutor {
         input: PlanNode,
         group_by: Vec<String>,
         aggregates: Vec<crate::execution::planner::AggregateExpr>,
-        _having: Option<String>,
+        _having: Option<ScalarExpr>,
     ) -> Result<QueryResult, DbError> {
         let input_result = self.execute_with_checkpoints(input)?;
         let input_card = input_result.rows.len();
@@ -526,12 +538,12 @@ impl AdaptiveExecutor {
         group_by: Vec<String>,
         _aggregates: Vec<crate::execution::planner::AggregateExpr>,
     ) -> Result<QueryResult, DbError> {
-        let mut groups: HashMap<Vec<String>, usize> = HashMap::new();
+        let mut groups: HashMap<Vec<Value>, usize> = HashMap::new();
 
         // Build hash table of groups
         for row in &input.rows {
             let key = if group_by.is_empty() {
-                vec!["__all__".to_string()]
+                vec![Value::String("__all__".to_string())]
             } else {
                 row[..group_by.len()].to_vec()
             };
@@ -543,7 +555,7 @@ impl AdaptiveExecutor {
         let mut result_rows = Vec::new();
         for (key, count) in groups {
             let mut row = key;
-            row.push(count.to_string());
+            row.push(Value::Integer(count as i64));
             result_rows.push(row);
         }
 
@@ -570,12 +582,12 @@ impl AdaptiveExecutor {
 
         // Sequential scan to compute aggregates
         let mut result_rows = Vec::new();
-        let mut current_group: Option<Vec<String>> = None;
+        let mut current_group: Option<Vec<Value>> = None;
         let mut current_count = 0;
 
         for row in sorted_rows {
             let key = if group_by.is_empty() {
-                vec!["__all__".to_string()]
+                vec![Value::String("__all__".to_string())]
             } else {
                 row[..group_by.len()].to_vec()
             };
@@ -591,7 +603,7 @@ impl AdaptiveExecutor {
                 Some(prev_key) => {
                     // Output previous group
                     let mut result_row = prev_key.clone();
-                    result_row.push(current_count.to_string());
+                    result_row.push(Value::Integer(current_count));
                     result_rows.push(result_row);
 
                     // Start new group
@@ -604,7 +616,7 @@ impl AdaptiveExecutor {
         // Output final group
         if let Some(key) = current_group {
             let mut result_row = key;
-            result_row.push(current_count.to_string());
+            result_row.push(Value::Integer(current_count));
             result_rows.push(result_row);
         }
 
@@ -0,0 +1,274 @@
+// Statistics gathering for ANALYZE
+//
+// Every row of the table is read once to count NULLs and to feed a
+// HyperLogLog sketch of each column's distinct values. The common values and
+// the histogram come from a reservoir sample of at most SAMPLE_ROWS rows:
+// values the sample shows clearly more often than the average value become
+// the column's most common values, and the rest are sorted and cut into
+// equally populated buckets. When the sample holds every value of a column
+// and there are few of them, they are all kept as common values and the
+// column has no histogram.
+
+use crate::analytics::approximate::HyperLogLog;
+use crate::analytics::sampling::{QueryResultSampler, SamplingConfig, SamplingMethod};
+use crate::catalog::{ColumnStats, Schema, TableStats};
+use crate::common::Value;
+use crate::error::DbError;
+use std::collections::HashMap;
+
+/// Rows of a table the common values and histograms are computed from
+pub const SAMPLE_ROWS: usize = 30_000;
+/// Most common values kept per column
+pub const MAX_MOST_COMMON: usize = 100;
+/// Buckets per histogram
+pub const HISTOGRAM_BUCKETS: usize = 100;
+
+// A table is analyzed again once more rows than this, plus a share of the
+// rows it had when last analyzed, have been written to it
+const AUTO_ANALYZE_THRESHOLD: f64 = 50.0;
+const AUTO_ANALYZE_SCALE_FACTOR: f64 = 0.1;
+
+// Sketch precision of 2^12 registers, about 1.6% standard error
+const DISTINCT_PRECISION: u8 = 12;
+// Wider strings are left out of common values and histograms
+const MAX_VALUE_WIDTH: usize = 1024;
+// How much more often than average a sampled value must occur to be common
+const COMMON_FACTOR: f64 = 1.25;
+
+/// Summarize the `rows` of the table `schema` describes
+pub fn table_stats(schema: &Schema, rows: &[Vec<Value>]) -> Result<TableStats, DbError> {
+    let sample: Vec<&Vec<Value>> = if rows.len() > SAMPLE_ROWS {
+        let config = SamplingConfig {
+            method: SamplingMethod::Reservoir,
+            rate: SAMPLE_ROWS as f64,
+            is_percentage: false,
+            ..SamplingConfig::default()
+        };
+        let positions: Vec<usize> = (0..rows.len()).collect();
+        QueryResultSampler::with_config(config)
+            .sample(&positions)
+            .data
+            .into_iter()
+            .map(|position| &rows[position])
+            .collect()
+    } else {
+        rows.iter().collect()
+    };
+
+    let columns = schema
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            column_stats(
+                &column.name,
+                rows.iter().map(|row| &row[i]),
+                sample.iter().map(|row| &row[i]),
+            )
+        })
+        .collect::<Result<_, DbError>>()?;
+
+    Ok(TableStats {
+        table: schema.name.clone(),
+        row_count: rows.len() as u64,
+        columns,
+    })
+}
+
+/// Whether a table with `modified` rows written since `stats` were gathered
+/// is due to be analyzed again
+pub fn needs_analyze(modified: u64, stats: Option<&TableStats>) -> bool {
+    let rows = stats.map_or(0, |stats| stats.row_count);
+    modified as f64 > AUTO_ANALYZE_THRESHOLD + AUTO_ANALYZE_SCALE_FACTOR * rows as f64
+}
+
+fn column_stats<'a>(
+    name: &str,
+    values: impl Iterator<Item = &'a Value>,
+    sample: impl Iterator<Item = &'a Value>,
+) -> Result<ColumnStats, DbError> {
+    let mut sketch = HyperLogLog::new(DISTINCT_PRECISION)?;
+    let (mut rows, mut nulls) = (0usize, 0usize);
+    for value in values {
+        rows += 1;
+        if value.is_null() {
+            nulls += 1;
+        } else {
+            sketch.add(value);
+        }
+    }
+    let non_null = rows - nulls;
+
+    let (mut sampled, mut sampled_non_null) = (0usize, 0usize);
+    let mut counts: HashMap<&Value, usize> = HashMap::new();
+    for value in sample {
+        sampled += 1;
+        if !value.is_null() {
+            sampled_non_null += 1;
+        }
+        if summarized(value) {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+    }
+    let counted: usize = counts.values().sum();
+
+    let mut candidates: Vec<(&Value, usize)> = counts.into_iter().collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.sort_cmp(b.0)));
+
+    // A sample in which every value occurs more than once has most likely
+    // seen all of them
+    let complete = candidates.len() <= MAX_MOST_COMMON
+        && (sampled == rows || candidates.iter().all(|(_, count)| *count > 1));
+    let mut distinct = (sketch.cardinality() as f64)
+        .max(candidates.len() as f64)
+        .min(non_null as f64);
+    if complete && counted == sampled_non_null {
+        distinct = candidates.len() as f64;
+    }
+
+    let average = counted as f64 / candidates.len().max(1) as f64;
+    let common = candidates
+        .iter()
+        .take(MAX_MOST_COMMON)
+        .take_while(|(_, count)| {
+            complete || (*count > 1 && *count as f64 > COMMON_FACTOR * average)
+        })
+        .count();
+    let most_common = candidates[..common]
+        .iter()
+        .map(|(value, count)| ((*value).clone(), *count as f64 / sampled as f64))
+        .collect();
+
+    let mut rest: Vec<&Value> = candidates[common..]
+        .iter()
+        .flat_map(|(value, count)| std::iter::repeat(*value).take(*count))
+        .collect();
+    rest.sort_by(|a, b| a.sort_cmp(b));
+    let histogram = if rest.len() < 2 {
+        Vec::new()
+    } else {
+        let buckets = HISTOGRAM_BUCKETS.min(rest.len() - 1);
+        (0..=buckets)
+            .map(|i| rest[i * (rest.len() - 1) / buckets].clone())
+            .collect()
+    };
+
+    Ok(ColumnStats {
+        name: name.to_string(),
+        null_fraction: if rows == 0 {
+            0.0
+        } else {
+            nulls as f64 / rows as f64
+        },
+        distinct,
+        most_common,
+        histogram,
+    })
+}
+
+// Values common value lists and histograms hold: scalars of modest width
+// with a total order
+fn summarized(value: &Value) -> bool {
+    match value {
+        Value::Boolean(_)
+        | Value::Integer(_)
+        | Value::Numeric(_)
+        | Value::Date(_)
+        | Value::Timestamp(_)
+        | Value::TimestampTz(_)
+        | Value::Interval(_)
+        | Value::Uuid(_) => true,
+        Value::Float(f) => f.is_finite(),
+        Value::String(s) => s.len() <= MAX_VALUE_WIDTH,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{Column, DataType};
+    use std::ops::Bound;
+
+    fn schema() -> Schema {
+        let column = |name: &str| Column {
+            name: name.to_string(),
+            data_type: DataType::Integer,
+            nullable: true,
+            default: None,
+        };
+        Schema::new("t".to_string(), vec![column("id"), column("kind")])
+    }
+
+    // Unique ids, and four kinds with a NULL kind in every tenth row
+    fn rows(count: i64) -> Vec<Vec<Value>> {
+        (0..count)
+            .map(|i| {
+                let kind = if i % 10 == 0 {
+                    Value::Null
+                } else {
+                    Value::Integer(i % 4)
+                };
+                vec![Value::Integer(i), kind]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_small_table_stats() -> Result<(), DbError> {
+        let stats = table_stats(&schema(), &rows(1000))?;
+        assert_eq!(stats.row_count, 1000);
+
+        let id = stats.column("id").unwrap();
+        assert_eq!(id.null_fraction, 0.0);
+        assert!((id.distinct - 1000.0).abs() < 50.0, "{}", id.distinct);
+        assert!(id.most_common.is_empty());
+        assert_eq!(id.histogram.len(), HISTOGRAM_BUCKETS + 1);
+        assert_eq!(id.histogram[0], Value::Integer(0));
+        let below = id.range_selectivity(Bound::Unbounded, Bound::Excluded(&Value::Integer(250)));
+        assert!((below - 0.25).abs() < 0.01, "{}", below);
+
+        // Every kind fits in the common values, so estimates are exact
+        let kind = stats.column("kind").unwrap();
+        assert_eq!(kind.null_fraction, 0.1);
+        assert_eq!(kind.distinct, 4.0);
+        assert_eq!(kind.most_common.len(), 4);
+        assert!(kind.histogram.is_empty());
+        assert_eq!(kind.equal_selectivity(&Value::Integer(1)), 0.25);
+        assert!(kind.equal_selectivity(&Value::Integer(9)) < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sampled_table_stats() -> Result<(), DbError> {
+        let stats = table_stats(&schema(), &rows(50_000))?;
+        assert_eq!(stats.row_count, 50_000);
+
+        let id = stats.column("id").unwrap();
+        assert!((id.distinct - 50_000.0).abs() < 2_500.0, "{}", id.distinct);
+        let below =
+            id.range_selectivity(Bound::Unbounded, Bound::Excluded(&Value::Integer(10_000)));
+        assert!((below - 0.2).abs() < 0.02, "{}", below);
+
+        // The sample sees every kind many times over
+        let kind = stats.column("kind").unwrap();
+        assert_eq!(kind.null_fraction, 0.1);
+        assert_eq!(kind.distinct, 4.0);
+        let one = kind.equal_selectivity(&Value::Integer(1));
+        assert!((one - 0.25).abs() < 0.02, "{}", one);
+        Ok(())
+    }
+
+    #[test]
+    fn test_needs_analyze() {
+        let stats = TableStats {
+            table: "t".to_string(),
+            row_count: 1000,
+            columns: Vec::new(),
+        };
+        assert!(!needs_analyze(50, None));
+        assert!(needs_analyze(51, None));
+        assert!(!needs_analyze(150, Some(&stats)));
+        assert!(needs_analyze(151, Some(&stats)));
+    }
+}
@@ -0,0 +1,2422 @@
+// Binder: turns the sqlparser AST into bound logical plans
+//
+// Name resolution happens here, once, against the catalog. Column references
+// become positions in the operator's input row, views and derived tables
+// become subquery plans, and subqueries in expressions are bound with the
+// enclosing query's columns visible as outer references.
+
+use crate::analytics::window_functions::{FrameExclusion, FrameType};
+use crate::catalog::{Catalog, DataType, Schema};
+use crate::common::Value;
+use crate::error::DbError;
+use crate::execution::expressions::{BinaryOperator, UnaryOperator};
+use crate::execution::planner::{
+    AggregateExpr, AggregateFunction, CycleClause, FrameBound, PlanNode, ScalarExpr,
+    SearchClause, SetOperator, SortKey, WindowExpr, WindowFrame, WindowFunction,
+};
+use crate::execution::MAX_PARAMETERS;
+use crate::parser::{
+    sql_text, JoinType, SqlParser, SqlStatement, CTE_CYCLE_MARKER, CTE_SEARCH_MARKER,
+    FRAME_EXCLUSION_MARKER,
+};
+use crate::Result;
+use sqlparser::ast::{
+    self, Cte, DuplicateTreatment, Expr, FunctionArg, FunctionArgExpr, FunctionArguments,
+    GroupByExpr, Ident, JoinConstraint, JoinOperator, LimitClause, NamedWindowDefinition,
+    NamedWindowExpr, OrderByExpr, OrderByKind, Query, Select, SelectItem, SetExpr, SetQuantifier,
+    TableAlias, TableFactor, TableWithJoins, WindowSpec, WindowType, With,
+};
+
+// Distance functions between two vectors
+const VECTOR_FUNCTIONS: &[&str] = &["COSINE_DISTANCE", "INNER_PRODUCT", "L2_DISTANCE"];
+
+// Views may be defined on views; this bounds the expansion
+const MAX_VIEW_DEPTH: usize = 32;
+
+// Built-in scalar functions the executor evaluates
+const SCALAR_FUNCTIONS: &[&str] = &[
+    "ABS",
+    "CEIL",
+    "COALESCE",
+    "CONCAT",
+    "COSINE_DISTANCE",
+    "CURRENT_DATE",
+    "CURRENT_TIMESTAMP",
+    "FLOOR",
+    "GEN_RANDOM_UUID",
+    "INNER_PRODUCT",
+    "L2_DISTANCE",
+    "LENGTH",
+    "LOWER",
+    "LTRIM",
+    "NOW",
+    "NULLIF",
+    "REPLACE",
+    "ROUND",
+    "RTRIM",
+    "SUBSTRING",
+    "TRIM",
+    "UPPER",
+];
+
+// Columns visible to an expression, in input row order
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    columns: Vec<ScopeColumn>,
+}
+
+#[derive(Debug, Clone)]
+struct ScopeColumn {
+    qualifier: Option<String>,
+    name: String,
+    data_type: DataType,
+    // Carried along for the binder itself: never resolved by name or
+    // expanded by `*`
+    hidden: bool,
+}
+
+impl Scope {
+    pub fn of_table(schema: &Schema, qualifier: &str) -> Self {
+        Self {
+            columns: schema
+                .columns
+                .iter()
+                .map(|column| ScopeColumn {
+                    qualifier: Some(qualifier.to_string()),
+                    name: column.name.clone(),
+                    data_type: column.data_type.clone(),
+                    hidden: false,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    pub fn types(&self) -> Vec<DataType> {
+        self.columns.iter().map(|c| c.data_type.clone()).collect()
+    }
+
+    // Position of a column; unquoted identifiers match case-insensitively
+    pub fn resolve(&self, qualifier: Option<&str>, name: &str) -> Result<Option<usize>> {
+        let mut found = None;
+        for (index, column) in self.columns.iter().enumerate() {
+            if column.hidden {
+                continue;
+            }
+            let qualifier_matches = match (qualifier, &column.qualifier) {
+                (None, _) => true,
+                (Some(q), Some(cq)) => q.eq_ignore_ascii_case(cq),
+                (Some(_), None) => false,
+            };
+            if qualifier_matches && column.name.eq_ignore_ascii_case(name) {
+                if found.is_some() {
+                    return Err(DbError::SqlParse(format!(
+                        "Column reference {} is ambiguous",
+                        qualified_name(qualifier, name)
+                    )));
+                }
+                found = Some(index);
+            }
+        }
+        Ok(found)
+    }
+
+    fn join(mut self, right: Scope) -> Self {
+        self.columns.extend(right.columns);
+        self
+    }
+
+    fn push(&mut self, qualifier: Option<String>, name: String, data_type: DataType) {
+        self.columns.push(ScopeColumn {
+            qualifier,
+            name,
+            data_type,
+            hidden: false,
+        });
+    }
+
+    fn push_hidden(&mut self, name: &str, data_type: DataType) {
+        self.columns.push(ScopeColumn {
+            qualifier: None,
+            name: name.to_string(),
+            data_type,
+            hidden: true,
+        });
+    }
+
+    // Apply `AS alias (c1, c2, ...)`
+    fn aliased(mut self, alias: Option<&TableAlias>) -> Result<Self> {
+        let Some(alias) = alias else {
+            return Ok(self);
+        };
+        if alias.columns.len() > self.columns.len() {
+            return Err(DbError::SqlParse(format!(
+                "Table {} has {} columns but {} were named",
+                alias.name.value,
+                self.columns.len(),
+                alias.columns.len()
+            )));
+        }
+        for (column, renamed) in self.columns.iter_mut().zip(&alias.columns) {
+            column.name = renamed.name.value.clone();
+        }
+        for column in &mut self.columns {
+            column.qualifier = Some(alias.name.value.clone());
+        }
+        Ok(self)
+    }
+}
+
+fn qualified_name(qualifier: Option<&str>, name: &str) -> String {
+    match qualifier {
+        Some(q) => format!("{}.{}", q, name),
+        None => name.to_string(),
+    }
+}
+
+// An enclosing query's scope, seen from one of its subqueries
+struct OuterFrame {
+    scope: Scope,
+    referenced: bool,
+}
+
+// Output of an Aggregate node: group keys, then aggregate calls, both
+// matched against later expressions by their SQL text
+struct Grouping {
+    keys: Vec<String>,
+    aggregates: Vec<String>,
+    input: Scope,
+}
+
+// A window specification with its references to named windows resolved
+struct WindowDefinition<'e> {
+    partition_by: Vec<&'e Expr>,
+    order_by: &'e [OrderByExpr],
+    frame: Option<&'e ast::WindowFrame>,
+    exclude: FrameExclusion,
+}
+
+// A WITH query as the table references after it see it
+struct CommonTable {
+    name: String,
+    plan: PlanNode,
+    scope: Scope,
+    // How many enclosing queries there were where it was bound, and whether
+    // it refers to them
+    outer_depth: usize,
+    correlated: bool,
+}
+
+enum FunctionInput<'e> {
+    Expr(&'e Expr),
+    Star,
+}
+
+pub struct Binder<'a> {
+    catalog: &'a Catalog,
+    outer: Vec<OuterFrame>,
+    view_depth: usize,
+    // Type of each statement parameter, `None` until a use fixes it
+    parameters: Vec<Option<DataType>>,
+    // WITH queries table references can see, innermost last
+    ctes: Vec<CommonTable>,
+}
+
+impl<'a> Binder<'a> {
+    pub fn new(catalog: &'a Catalog) -> Self {
+        Self {
+            catalog,
+            outer: Vec::new(),
+            view_depth: 0,
+            parameters: Vec::new(),
+            ctes: Vec::new(),
+        }
+    }
+
+    pub fn bind_query(&mut self, query: &Query) -> Result<PlanNode> {
+        self.bind_query_scoped(query).map(|(plan, _)| plan)
+    }
+
+    // Bind a DML predicate or assignment against a single table's rows
+    pub fn bind_table_expr(&mut self, schema: &Schema, expr: &Expr) -> Result<ScalarExpr> {
+        let scope = Scope::of_table(schema, &schema.name);
+        self.bind_expr(expr, &scope, None)
+    }
+
+    // Bind an expression that reads no columns, e.g. an INSERT value
+    pub fn bind_constant(&mut self, expr: &Expr) -> Result<ScalarExpr> {
+        self.bind_expr(expr, &Scope::default(), None)
+    }
+
+    // Types of the parameters `$1`, `$2`, ... of everything bound so far
+    pub fn into_parameter_types(self) -> Vec<Option<DataType>> {
+        self.parameters
+    }
+
+    // Give a parameter the type of the column or expression it is used
+    // with; the first use that fixes a type wins
+    pub fn infer_parameter_type(&mut self, expr: &ScalarExpr, data_type: Option<DataType>) {
+        if let ScalarExpr::Parameter { index } = expr {
+            match self.parameters.get_mut(*index) {
+                Some(slot) if slot.is_none() => *slot = data_type,
+                _ => {}
+            }
+        }
+    }
+
+    fn bind_query_scoped(&mut self, query: &Query) -> Result<(PlanNode, Scope)> {
+        // WITH queries are visible to the rest of this query only
+        let visible = self.ctes.len();
+        let bound = match &query.with {
+            Some(with) => self
+                .bind_with(with)
+                .and_then(|()| self.bind_query_body(query)),
+            None => self.bind_query_body(query),
+        };
+        self.ctes.truncate(visible);
+        bound
+    }
+
+    fn bind_query_body(&mut self, query: &Query) -> Result<(PlanNode, Scope)> {
+        if query.fetch.is_some() {
+            return Err(DbError::NotImplemented("FETCH clauses".to_string()));
+        }
+        let order_by: Vec<&OrderByExpr> = match query.order_by.as_ref().map(|o| &o.kind) {
+            None => Vec::new(),
+            Some(OrderByKind::Expressions(exprs)) => exprs.iter().collect(),
+            Some(OrderByKind::All(_)) => {
+                return Err(DbError::SqlParse(
+                    "ORDER BY ALL is not supported".to_string(),
+                ))
+            }
+        };
+        let (limit, offset) = Self::bind_limit(query.limit_clause.as_ref())?;
+
+        let (mut plan, scope) = match query.body.as_ref() {
+            SetExpr::Select(select) => self.bind_select(select, &order_by)?,
+            body => {
+                let (plan, scope) = self.bind_set_expr(body)?;
+                self.sort_output(plan, scope, &order_by)?
+            }
+        };
+
+        if limit.is_some() || offset.is_some() {
+            plan = PlanNode::Limit {
+                input: Box::new(plan),
+                limit: limit.unwrap_or(usize::MAX),
+                offset,
+            };
+        }
+        Ok((plan, scope))
+    }
+
+    // A query body below the top of its query, which holds the ORDER BY
+    fn bind_set_expr(&mut self, body: &SetExpr) -> Result<(PlanNode, Scope)> {
+        match body {
+            SetExpr::Select(select) => self.bind_select(select, &[]),
+            SetExpr::Query(query) => self.bind_query_scoped(query),
+            SetExpr::SetOperation {
+                op,
+                set_quantifier,
+                left,
+                right,
+            } => {
+                let op = match op {
+                    ast::SetOperator::Union => SetOperator::Union,
+                    ast::SetOperator::Intersect => SetOperator::Intersect,
+                    // EXCEPT, or MINUS as Oracle spells it
+                    _ => SetOperator::Except,
+                };
+                let all = match set_quantifier {
+                    SetQuantifier::All => true,
+                    SetQuantifier::Distinct | SetQuantifier::None => false,
+                    other => {
+                        return Err(DbError::NotImplemented(format!(
+                            "{} {}",
+                            op.keyword(),
+                            other
+                        )))
+                    }
+                };
+                let (left, scope) = self.bind_set_expr(left)?;
+                let (right, _) = self.bind_set_expr(right)?;
+                Ok((PlanNode::set_operation(op, all, left, right)?, scope))
+            }
+            other => Err(DbError::NotImplemented(format!("Query body: {}", other))),
+        }
+    }
+
+    // ORDER BY over the output columns of a query, by name or position
+    fn sort_output(
+        &mut self,
+        plan: PlanNode,
+        scope: Scope,
+        order_by: &[&OrderByExpr],
+    ) -> Result<(PlanNode, Scope)> {
+        if order_by.is_empty() {
+            return Ok((plan, scope));
+        }
+        let mut keys = Vec::new();
+        for item in order_by {
+            let expr = match Self::ordinal(&item.expr)? {
+                Some(position) => {
+                    let index = Self::check_ordinal(position, scope.len())?;
+                    ScalarExpr::column(index, scope.columns[index].name.clone())
+                }
+                None => self.bind_expr(&item.expr, &scope, None)?,
+            };
+            keys.push(Self::sort_key(item, expr));
+        }
+        let plan = PlanNode::Sort {
+            input: Box::new(plan),
+            order_by: keys,
+        };
+        Ok((plan, scope))
+    }
+
+    // ------------------------------------------------------------------
+    // WITH clause
+    // ------------------------------------------------------------------
+
+    // Each WITH query sees the ones before it and, in a WITH RECURSIVE,
+    // itself
+    fn bind_with(&mut self, with: &With) -> Result<()> {
+        let first = self.ctes.len();
+        for (i, cte) in with.cte_tables.iter().enumerate() {
+            // SEARCH and CYCLE clauses follow the query they belong to
+            if is_search_or_cycle(cte) {
+                continue;
+            }
+            let name = &cte.alias.name.value;
+            if self.ctes[first..]
+                .iter()
+                .any(|table| table.name.eq_ignore_ascii_case(name))
+            {
+                return Err(DbError::SqlParse(format!(
+                    "WITH query name {} specified more than once",
+                    name
+                )));
+            }
+            let clauses: Vec<&Cte> = with.cte_tables[i + 1..]
+                .iter()
+                .take_while(|clause| is_search_or_cycle(clause))
+                .collect();
+            let table = self.bind_common_table(cte, with.recursive, &clauses)?;
+            self.ctes.push(table);
+        }
+        Ok(())
+    }
+
+    fn bind_common_table(
+        &mut self,
+        cte: &Cte,
+        recursive: bool,
+        clauses: &[&Cte],
+    ) -> Result<CommonTable> {
+        let name = cte.alias.name.value.clone();
+        if cte.from.is_some() {
+            return Err(DbError::NotImplemented(format!(
+                "FROM after WITH query {}",
+                name
+            )));
+        }
+        // Note which enclosing queries this one refers to by itself
+        let referenced: Vec<bool> = self
+            .outer
+            .iter_mut()
+            .map(|frame| std::mem::take(&mut frame.referenced))
+            .collect();
+        let bound = match recursive_terms(&cte.query) {
+            Some((all, base, step)) if recursive => {
+                self.bind_recursive(cte, all, base, step, clauses)
+            }
+            _ if !clauses.is_empty() => Err(DbError::SqlParse(format!(
+                "SEARCH and CYCLE need a recursive query, {} is not one",
+                name
+            ))),
+            _ => self
+                .bind_query_scoped(&cte.query)
+                .and_then(|(plan, scope)| Ok((plan, scope.aliased(Some(&cte.alias))?))),
+        };
+        let correlated = self.outer.iter().any(|frame| frame.referenced);
+        for (frame, was) in self.outer.iter_mut().zip(referenced) {
+            frame.referenced |= was;
+        }
+        let (plan, scope) = bound?;
+        Ok(CommonTable {
+            name,
+            plan,
+            scope,
+            outer_depth: self.outer.len(),
+            correlated,
+        })
+    }
+
+    // `base UNION [ALL] step`, where `step` may read the query itself once:
+    // the rows the previous round added
+    fn bind_recursive(
+        &mut self,
+        cte: &Cte,
+        all: bool,
+        base: &SetExpr,
+        step: &SetExpr,
+        clauses: &[&Cte],
+    ) -> Result<(PlanNode, Scope)> {
+        let name = cte.alias.name.value.clone();
+        let (base, base_scope) = self.bind_set_expr(base)?;
+        let scope = base_scope.aliased(Some(&cte.alias))?;
+        let types = scope.types();
+
+        // SEARCH and CYCLE add columns after the query's own
+        let (mut search, mut cycle) = (None, None);
+        let mut output = scope.clone();
+        for clause in clauses {
+            let (added, data_types) = if clause.alias.name.value == CTE_SEARCH_MARKER {
+                let search = search.insert(search_clause(clause, &scope)?);
+                (vec![search.column.clone()], vec![search.data_type(&types)])
+            } else {
+                let cycle = cycle.insert(cycle_clause(clause, &scope)?);
+                (
+                    vec![cycle.mark.clone(), cycle.path.clone()],
+                    cycle.data_types(&types).to_vec(),
+                )
+            };
+            for (column, data_type) in added.into_iter().zip(data_types) {
+                if output.resolve(None, &column)?.is_some() {
+                    return Err(DbError::SqlParse(format!(
+                        "Column {} of WITH query {} is named twice",
+                        column, name
+                    )));
+                }
+                output.push(Some(name.clone()), column, data_type);
+            }
+        }
+
+        // The step reads the work table, whose rows carry the SEARCH column
+        // and CYCLE path the step's rows are computed from
+        let mut work = scope.clone();
+        let mut hidden = Vec::new();
+        if let Some(search) = &search {
+            work.push_hidden(CTE_SEARCH_MARKER, search.data_type(&types));
+            hidden.push(CTE_SEARCH_MARKER);
+        }
+        if let Some(cycle) = &cycle {
+            let [_, path] = cycle.data_types(&types);
+            work.push_hidden(CTE_CYCLE_MARKER, path);
+            hidden.push(CTE_CYCLE_MARKER);
+        }
+        self.ctes.push(CommonTable {
+            name: name.clone(),
+            plan: PlanNode::WorkTableScan {
+                name: name.clone(),
+                columns: work.names(),
+                types: work.types(),
+            },
+            scope: work,
+            outer_depth: self.outer.len(),
+            correlated: false,
+        });
+        let step = self.bind_set_expr(step);
+        self.ctes.pop();
+        let (mut step, _) = step?;
+
+        match work_table_scans(&step, &name) {
+            0 if hidden.is_empty() => {
+                let plan = PlanNode::set_operation(SetOperator::Union, all, base, step)?;
+                return Ok((plan, scope));
+            }
+            0 => {
+                return Err(DbError::SqlParse(format!(
+                    "SEARCH and CYCLE need a recursive query, {} does not refer to itself",
+                    name
+                )))
+            }
+            1 => {}
+            _ => {
+                return Err(DbError::SqlParse(format!(
+                    "Recursive query {} must not refer to itself more than once",
+                    name
+                )))
+            }
+        }
+        let width = step.output_columns().len();
+        if width != scope.len() {
+            return Err(DbError::SqlParse(format!(
+                "Each UNION query must have the same number of columns, got {} and {}",
+                scope.len(),
+                width
+            )));
+        }
+        Self::carry_work_columns(&mut step, &hidden)?;
+        let plan = PlanNode::RecursiveUnion {
+            name,
+            columns: scope.names(),
+            base: Box::new(base),
+            recursive: Box::new(step),
+            all,
+            search,
+            cycle,
+        };
+        Ok((plan, output))
+    }
+
+    // Have the recursive term pass on the hidden work table columns of the
+    // row each of its rows comes from
+    fn carry_work_columns(step: &mut PlanNode, hidden: &[&str]) -> Result<()> {
+        if hidden.is_empty() {
+            return Ok(());
+        }
+        let project = match step {
+            PlanNode::Distinct { input } => input.as_mut(),
+            other => other,
+        };
+        let PlanNode::Project {
+            input,
+            exprs,
+            columns,
+        } = project
+        else {
+            return Err(DbError::NotImplemented(
+                "SEARCH and CYCLE over a grouped recursive term".to_string(),
+            ));
+        };
+        let available = input.output_columns();
+        for name in hidden {
+            let index = available
+                .iter()
+                .position(|column| column == name)
+                .ok_or_else(|| {
+                    DbError::NotImplemented(
+                        "SEARCH and CYCLE with the recursive reference in a subquery".to_string(),
+                    )
+                })?;
+            exprs.push(ScalarExpr::column(index, name.to_string()));
+            columns.push(name.to_string());
+        }
+        Ok(())
+    }
+
+    // A table reference naming a WITH query: a copy of its plan, or the
+    // work table of the recursive query being bound
+    fn common_table(&self, table: &str) -> Result<Option<(PlanNode, Scope)>> {
+        let Some(cte) = self
+            .ctes
+            .iter()
+            .rev()
+            .find(|cte| cte.name.eq_ignore_ascii_case(table))
+        else {
+            return Ok(None);
+        };
+        // Its references to enclosing queries count from where it was bound
+        if cte.correlated && cte.outer_depth != self.outer.len() {
+            return Err(DbError::NotImplemented(format!(
+                "WITH query {} refers to an enclosing query and is read by a subquery",
+                cte.name
+            )));
+        }
+        let plan = match &cte.plan {
+            plan @ PlanNode::WorkTableScan { .. } => plan.clone(),
+            plan => PlanNode::Subquery {
+                plan: Box::new(plan.clone()),
+                alias: cte.name.clone(),
+            },
+        };
+        Ok(Some((plan, cte.scope.clone())))
+    }
+
+    fn bind_limit(clause: Option<&LimitClause>) -> Result<(Option<usize>, Option<usize>)> {
+        match clause {
+            None => Ok((None, None)),
+            Some(LimitClause::LimitOffset { limit, offset, .. }) => {
+                Ok((
+                    limit.as_ref().map(Self::row_count).transpose()?,
+                    offset
+                        .as_ref()
+                        .map(|o| Self::row_count(&o.value))
+                        .transpose()?,
+                ))
+            }
+            Some(LimitClause::OffsetCommaLimit { offset, limit }) => {
+                Ok((Some(Self::row_count(limit)?), Some(Self::row_count(offset)?)))
+            }
+        }
+    }
+
+    // LIMIT/OFFSET operand: a non-negative integer literal
+    fn row_count(expr: &Expr) -> Result<usize> {
+        match SqlParser::literal_value(expr) {
+            Ok(Value::Integer(n)) if n >= 0 => Ok(n as usize),
+            _ => Err(DbError::SqlParse(format!(
+                "Expected a row count, got {}",
+                expr
+            ))),
+        }
+    }
+
+    fn bind_select(
+        &mut self,
+        select: &Select,
+        order_by: &[&OrderByExpr],
+    ) -> Result<(PlanNode, Scope)> {
+        if matches!(select.distinct, Some(ast::Distinct::On(_))) {
+            return Err(DbError::NotImplemented("DISTINCT ON".to_string()));
+        }
+        let distinct = matches!(select.distinct, Some(ast::Distinct::Distinct));
+
+        let (mut plan, input) = self.bind_from(&select.from)?;
+
+        if let Some(selection) = &select.selection {
+            let predicate = self.bind_expr(selection, &input, None)?;
+            plan = PlanNode::Filter {
+                input: Box::new(plan),
+                predicate,
+            };
+        }
+
+        // Select list as (expression, output name); wildcards expand to the
+        // columns they stand for
+        let mut items: Vec<(Expr, String)> = Vec::new();
+        for item in &select.projection {
+            match item {
+                SelectItem::UnnamedExpr(expr) => items.push((expr.clone(), output_name(expr))),
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    items.push((expr.clone(), alias.value.clone()))
+                }
+                SelectItem::Wildcard(_) => {
+                    for column in input.columns.iter().filter(|c| !c.hidden) {
+                        items.push((column_ref(column), column.name.clone()));
+                    }
+                }
+                SelectItem::QualifiedWildcard(qualifier, _) => {
+                    let qualifier = qualifier.to_string();
+                    let before = items.len();
+                    for column in input.columns.iter().filter(|c| !c.hidden) {
+                        let matches = column
+                            .qualifier
+                            .as_deref()
+                            .is_some_and(|q| q.eq_ignore_ascii_case(&qualifier));
+                        if matches {
+                            items.push((column_ref(column), column.name.clone()));
+                        }
+                    }
+                    if items.len() == before {
+                        return Err(DbError::SqlParse(format!(
+                            "Table {} not found in FROM clause",
+                            qualifier
+                        )));
+                    }
+                }
+                other => {
+                    return Err(DbError::NotImplemented(format!("Select item {}", other)));
+                }
+            }
+        }
+
+        // ORDER BY may name an output column or give its position
+        let mut sort_exprs = Vec::new();
+        for item in order_by {
+            let expr = match Self::ordinal(&item.expr)? {
+                Some(position) => items[Self::check_ordinal(position, items.len())?].0.clone(),
+                None => match &item.expr {
+                    Expr::Identifier(ident) => items
+                        .iter()
+                        .find(|(_, name)| name.eq_ignore_ascii_case(&ident.value))
+                        .map(|(expr, _)| expr.clone())
+                        .unwrap_or_else(|| item.expr.clone()),
+                    other => other.clone(),
+                },
+            };
+            sort_exprs.push(expr);
+        }
+
+        // GROUP BY may give a position or an output alias; input columns win
+        let group_exprs = match &select.group_by {
+            GroupByExpr::Expressions(exprs, modifiers) => {
+                if !modifiers.is_empty() {
+                    return Err(DbError::NotImplemented(
+                        "GROUP BY modifiers".to_string(),
+                    ));
+                }
+                exprs
+                    .iter()
+                    .map(|expr| -> Result<Expr> {
+                        if let Some(position) = Self::ordinal(expr)? {
+                            return Ok(items[Self::check_ordinal(position, items.len())?].0.clone());
+                        }
+                        if let Expr::Identifier(ident) = expr {
+                            if input.resolve(None, &ident.value)?.is_none() {
+                                if let Some((aliased, _)) = items
+                                    .iter()
+                                    .find(|(_, name)| name.eq_ignore_ascii_case(&ident.value))
+                                {
+                                    return Ok(aliased.clone());
+                                }
+                            }
+                        }
+                        Ok(expr.clone())
+                    })
+                    .collect::<Result<Vec<_>>>()?
+            }
+            GroupByExpr::All(_) => {
+                return Err(DbError::SqlParse(
+                    "GROUP BY ALL is not supported".to_string(),
+                ))
+            }
+        };
+
+        let mut aggregate_calls: Vec<&Expr> = Vec::new();
+        for expr in items
+            .iter()
+            .map(|(expr, _)| expr)
+            .chain(select.having.as_ref())
+            .chain(sort_exprs.iter())
+            .chain(named_window_exprs(&select.named_window))
+        {
+            collect_aggregates(expr, &mut aggregate_calls);
+        }
+
+        let grouped =
+            !group_exprs.is_empty() || !aggregate_calls.is_empty() || select.having.is_some();
+        let mut scope = input.clone();
+        let mut grouping = None;
+        if grouped {
+            let input_types = input.types();
+            let mut output = Scope::default();
+            let mut group_by = Vec::new();
+            for expr in &group_exprs {
+                let key = self.bind_expr(expr, &input, None)?;
+                let data_type = key.data_type(&input_types).unwrap_or(DataType::Text);
+                // Plain column keys stay addressable by their qualified name
+                match &key {
+                    ScalarExpr::Column { index, .. } => {
+                        let column = &input.columns[*index];
+                        output.push(column.qualifier.clone(), column.name.clone(), data_type);
+                    }
+                    _ => output.push(None, expr.to_string(), data_type),
+                }
+                group_by.push(key);
+            }
+            let mut aggregates = Vec::new();
+            for call in &aggregate_calls {
+                let aggregate = self.bind_aggregate(call, &input)?;
+                output.push(None, call.to_string(), aggregate.data_type(&input_types));
+                aggregates.push(aggregate);
+            }
+
+            let state = Grouping {
+                keys: group_exprs.iter().map(|e| e.to_string()).collect(),
+                aggregates: aggregate_calls.iter().map(|e| e.to_string()).collect(),
+                input,
+            };
+            let having = select
+                .having
+                .as_ref()
+                .map(|expr| self.bind_expr(expr, &output, Some(&state)))
+                .transpose()?;
+            plan = PlanNode::Aggregate {
+                input: Box::new(plan),
+                group_by,
+                aggregates,
+                having,
+            };
+            scope = output;
+            grouping = Some(state);
+        }
+
+        // Window calls run over the grouped rows; each adds a column named
+        // by its SQL text
+        let mut window_calls: Vec<&Expr> = Vec::new();
+        for expr in items.iter().map(|(expr, _)| expr).chain(sort_exprs.iter()) {
+            collect_windows(expr, &mut window_calls);
+        }
+        if !window_calls.is_empty() {
+            (plan, scope) = self.bind_windows(
+                plan,
+                scope,
+                grouping.as_ref(),
+                &window_calls,
+                &select.named_window,
+            )?;
+        }
+
+        if !sort_exprs.is_empty() {
+            let mut keys = Vec::new();
+            for (item, expr) in order_by.iter().zip(&sort_exprs) {
+                let bound = self.bind_expr(expr, &scope, grouping.as_ref())?;
+                keys.push(Self::sort_key(item, bound));
+            }
+            plan = PlanNode::Sort {
+                input: Box::new(plan),
+                order_by: keys,
+            };
+        }
+
+        let scope_types = scope.types();
+        let mut exprs = Vec::new();
+        let mut output = Scope::default();
+        for (expr, name) in &items {
+            let bound = self.bind_expr(expr, &scope, grouping.as_ref())?;
+            let data_type = bound.data_type(&scope_types).unwrap_or(DataType::Text);
+            output.push(None, name.clone(), data_type);
+            exprs.push(bound);
+        }
+        plan = PlanNode::Project {
+            input: Box::new(plan),
+            exprs,
+            columns: output.names(),
+        };
+
+        if distinct {
+            plan = PlanNode::Distinct {
+                input: Box::new(plan),
+            };
+        }
+        Ok((plan, output))
+    }
+
+    // One Window node per distinct PARTITION BY and ORDER BY, each above a
+    // sort by the partition keys and then the window's ORDER BY. Longer
+    // orders go first, so a window whose order is a prefix of the one
+    // already in place shares its sort
+    fn bind_windows(
+        &mut self,
+        mut plan: PlanNode,
+        mut scope: Scope,
+        grouping: Option<&Grouping>,
+        calls: &[&Expr],
+        named: &[NamedWindowDefinition],
+    ) -> Result<(PlanNode, Scope)> {
+        let mut windows: Vec<(Vec<ScalarExpr>, Vec<SortKey>, Vec<WindowExpr>)> = Vec::new();
+        for call in calls {
+            let Expr::Function(function) = call else {
+                unreachable!("collect_windows only returns function calls");
+            };
+            let over = function
+                .over
+                .as_ref()
+                .expect("collect_windows only returns window calls");
+            let definition = resolve_window(over, named)?;
+            let mut partition_by = Vec::new();
+            for expr in &definition.partition_by {
+                partition_by.push(self.bind_expr(expr, &scope, grouping)?);
+            }
+            let mut order_by = Vec::new();
+            for item in definition.order_by {
+                let expr = self.bind_expr(&item.expr, &scope, grouping)?;
+                order_by.push(Self::sort_key(item, expr));
+            }
+            let window =
+                self.bind_window_function(call, function, &definition, &scope, grouping)?;
+            match windows
+                .iter_mut()
+                .find(|(p, o, _)| *p == partition_by && *o == order_by)
+            {
+                Some((_, _, functions)) => functions.push(window),
+                None => windows.push((partition_by, order_by, vec![window])),
+            }
+        }
+        windows.sort_by_key(|(partition_by, order_by, _)| {
+            std::cmp::Reverse(partition_by.len() + order_by.len())
+        });
+
+        let mut sorted: Vec<SortKey> = Vec::new();
+        for (partition_by, order_by, functions) in windows {
+            let required: Vec<SortKey> = partition_by
+                .iter()
+                .map(|expr| SortKey {
+                    expr: expr.clone(),
+                    ascending: true,
+                    nulls_first: None,
+                })
+                .chain(order_by.iter().cloned())
+                .collect();
+            let shared = required.len() <= sorted.len()
+                && required.iter().zip(&sorted).all(|(wanted, done)| {
+                    wanted.expr == done.expr
+                        && wanted.ascending == done.ascending
+                        && wanted.nulls_first() == done.nulls_first()
+                });
+            if !shared && !required.is_empty() {
+                plan = PlanNode::Sort {
+                    input: Box::new(plan),
+                    order_by: required.clone(),
+                };
+                sorted = required;
+            }
+            let types = scope.types();
+            for function in &functions {
+                scope.push(None, function.column.clone(), function.data_type(&types));
+            }
+            plan = PlanNode::Window {
+                input: Box::new(plan),
+                partition_by,
+                order_by,
+                functions,
+            };
+        }
+        Ok((plan, scope))
+    }
+
+    fn bind_window_function(
+        &mut self,
+        call: &Expr,
+        function: &ast::Function,
+        definition: &WindowDefinition,
+        scope: &Scope,
+        grouping: Option<&Grouping>,
+    ) -> Result<WindowExpr> {
+        let name = function.name.to_string().to_ascii_uppercase();
+        let kind = WindowFunction::from_name(&name)
+            .ok_or_else(|| DbError::SqlParse(format!("Unknown window function {}", name)))?;
+        let (inputs, distinct) = function_inputs(function)?;
+        if distinct {
+            return Err(DbError::NotImplemented(
+                "DISTINCT in window functions".to_string(),
+            ));
+        }
+        let count_star = kind == WindowFunction::Aggregate(AggregateFunction::Count)
+            && matches!(inputs.as_slice(), [FunctionInput::Star]);
+        let mut args = Vec::new();
+        for input in &inputs {
+            match input {
+                FunctionInput::Expr(expr) => args.push(self.bind_expr(expr, scope, grouping)?),
+                FunctionInput::Star if count_star => {}
+                FunctionInput::Star => {
+                    return Err(DbError::SqlParse(format!("{}(*) is not valid", name)))
+                }
+            }
+        }
+        let arity = match kind {
+            WindowFunction::Aggregate(_) if count_star => 0..=0,
+            WindowFunction::RowNumber
+            | WindowFunction::Rank
+            | WindowFunction::DenseRank
+            | WindowFunction::PercentRank
+            | WindowFunction::CumeDist => 0..=0,
+            WindowFunction::Aggregate(_)
+            | WindowFunction::Ntile
+            | WindowFunction::FirstValue
+            | WindowFunction::LastValue => 1..=1,
+            WindowFunction::NthValue => 2..=2,
+            WindowFunction::Lag | WindowFunction::Lead => 1..=3,
+        };
+        if !arity.contains(&args.len()) {
+            return Err(DbError::SqlParse(format!(
+                "Wrong number of arguments to window function {}",
+                name
+            )));
+        }
+        Ok(WindowExpr {
+            function: kind,
+            args,
+            frame: self.bind_frame(definition)?,
+            column: sql_text(call),
+        })
+    }
+
+    // Frames follow PostgreSQL: they cannot end before they start, and
+    // RANGE offsets are measured on a single ORDER BY key
+    fn bind_frame(&mut self, definition: &WindowDefinition) -> Result<WindowFrame> {
+        let Some(frame) = definition.frame else {
+            return Ok(WindowFrame::default());
+        };
+        let units = match frame.units {
+            ast::WindowFrameUnits::Rows => FrameType::Rows,
+            ast::WindowFrameUnits::Range => FrameType::Range,
+            ast::WindowFrameUnits::Groups => FrameType::Groups,
+        };
+        let start = self.bind_frame_bound(&frame.start_bound)?;
+        let end = match &frame.end_bound {
+            Some(bound) => self.bind_frame_bound(bound)?,
+            None => FrameBound::CurrentRow,
+        };
+
+        let position = |bound: &FrameBound| match bound {
+            FrameBound::UnboundedPreceding => 0,
+            FrameBound::Preceding(_) => 1,
+            FrameBound::CurrentRow => 2,
+            FrameBound::Following(_) => 3,
+            FrameBound::UnboundedFollowing => 4,
+        };
+        if matches!(start, FrameBound::UnboundedFollowing) {
+            return Err(DbError::SqlParse(
+                "Frame start cannot be UNBOUNDED FOLLOWING".to_string(),
+            ));
+        }
+        if matches!(end, FrameBound::UnboundedPreceding) {
+            return Err(DbError::SqlParse(
+                "Frame end cannot be UNBOUNDED PRECEDING".to_string(),
+            ));
+        }
+        if position(&end) < position(&start) {
+            return Err(DbError::SqlParse(format!(
+                "Frame starting {} cannot end before it",
+                frame.start_bound
+            )));
+        }
+        let offset = |bound: &FrameBound| {
+            matches!(bound, FrameBound::Preceding(_) | FrameBound::Following(_))
+        };
+        if units == FrameType::Range
+            && (offset(&start) || offset(&end))
+            && definition.order_by.len() != 1
+        {
+            return Err(DbError::SqlParse(
+                "RANGE with offset PRECEDING/FOLLOWING requires exactly one ORDER BY column"
+                    .to_string(),
+            ));
+        }
+        Ok(WindowFrame {
+            units,
+            start,
+            end,
+            exclude: definition.exclude,
+        })
+    }
+
+    // Offsets are constants; their values are checked when the plan runs
+    fn bind_frame_bound(&mut self, bound: &ast::WindowFrameBound) -> Result<FrameBound> {
+        Ok(match bound {
+            ast::WindowFrameBound::CurrentRow => FrameBound::CurrentRow,
+            ast::WindowFrameBound::Preceding(None) => FrameBound::UnboundedPreceding,
+            ast::WindowFrameBound::Preceding(Some(offset)) => {
+                FrameBound::Preceding(self.bind_constant(offset)?)
+            }
+            ast::WindowFrameBound::Following(None) => FrameBound::UnboundedFollowing,
+            ast::WindowFrameBound::Following(Some(offset)) => {
+                FrameBound::Following(self.bind_constant(offset)?)
+            }
+        })
+    }
+
+    fn sort_key(item: &OrderByExpr, expr: ScalarExpr) -> SortKey {
+        SortKey {
+            expr,
+            ascending: item.options.asc.unwrap_or(true),
+            nulls_first: item.options.nulls_first,
+        }
+    }
+
+    // `ORDER BY 2` / `GROUP BY 1`: a bare integer is a 1-based position
+    fn ordinal(expr: &Expr) -> Result<Option<usize>> {
+        if let Expr::Value(value) = expr {
+            if let ast::Value::Number(..) = value.value {
+                return match SqlParser::literal_value(expr)? {
+                    Value::Integer(n) if n >= 1 => Ok(Some(n as usize)),
+                    _ => Err(DbError::SqlParse(format!(
+                        "Position {} is not in select list",
+                        expr
+                    ))),
+                };
+            }
+        }
+        Ok(None)
+    }
+
+    fn check_ordinal(position: usize, len: usize) -> Result<usize> {
+        if position > len {
+            return Err(DbError::SqlParse(format!(
+                "Position {} is not in select list",
+                position
+            )));
+        }
+        Ok(position - 1)
+    }
+
+    // ------------------------------------------------------------------
+    // FROM clause
+    // ------------------------------------------------------------------
+
+    fn bind_from(&mut self, from: &[TableWithJoins]) -> Result<(PlanNode, Scope)> {
+        // SELECT without FROM reads a single empty row
+        let mut bound = (
+            PlanNode::Values {
+                columns: Vec::new(),
+                rows: vec![Vec::new()],
+            },
+            Scope::default(),
+        );
+        for (i, table) in from.iter().enumerate() {
+            let (plan, scope) = self.bind_table_with_joins(table)?;
+            bound = if i == 0 {
+                (plan, scope)
+            } else {
+                let (left, left_scope) = bound;
+                (
+                    PlanNode::Join {
+                        join_type: JoinType::Cross,
+                        left: Box::new(left),
+                        right: Box::new(plan),
+                        condition: None,
+                    },
+                    left_scope.join(scope),
+                )
+            };
+        }
+        Ok(bound)
+    }
+
+    fn bind_table_with_joins(&mut self, table: &TableWithJoins) -> Result<(PlanNode, Scope)> {
+        let (mut plan, mut scope) = self.bind_table_factor(&table.relation)?;
+        for join in &table.joins {
+            let (right, right_scope) = self.bind_table_factor(&join.relation)?;
+            let (join_type, constraint) = match &join.join_operator {
+                JoinOperator::Join(c) | JoinOperator::Inner(c) => (JoinType::Inner, Some(c)),
+                JoinOperator::Left(c) | JoinOperator::LeftOuter(c) => (JoinType::Left, Some(c)),
+                JoinOperator::Right(c) | JoinOperator::RightOuter(c) => {
+                    (JoinType::Right, Some(c))
+                }
+                JoinOperator::FullOuter(c) => (JoinType::Full, Some(c)),
+                JoinOperator::CrossJoin { .. } => (JoinType::Cross, None),
+                other => {
+                    return Err(DbError::NotImplemented(format!(
+                        "Join operator {:?}",
+                        other
+                    )))
+                }
+            };
+
+            let left_len = scope.len();
+            let joined = scope.join(right_scope);
+            let condition = match constraint {
+                None | Some(JoinConstraint::None) => None,
+                Some(JoinConstraint::On(expr)) => Some(self.bind_expr(expr, &joined, None)?),
+                Some(JoinConstraint::Using(columns)) => {
+                    let names: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+                    Self::equi_join(&joined, left_len, &names)?
+                }
+                Some(JoinConstraint::Natural) => {
+                    let names: Vec<String> = joined.columns[..left_len]
+                        .iter()
+                        .filter(|l| {
+                            joined.columns[left_len..]
+                                .iter()
+                                .any(|r| r.name.eq_ignore_ascii_case(&l.name))
+                        })
+                        .map(|l| l.name.clone())
+                        .collect();
+                    Self::equi_join(&joined, left_len, &names)?
+                }
+            };
+
+            plan = PlanNode::Join {
+                join_type,
+                left: Box::new(plan),
+                right: Box::new(right),
+                condition,
+            };
+            scope = joined;
+        }
+        Ok((plan, scope))
+    }
+
+    // USING / NATURAL: equality on each named column of the two sides
+    fn equi_join(joined: &Scope, left_len: usize, names: &[String]) -> Result<Option<ScalarExpr>> {
+        let (left, right) = joined.columns.split_at(left_len);
+        let side = |columns: &[ScopeColumn], name: &str| {
+            let scope = Scope {
+                columns: columns.to_vec(),
+            };
+            scope.resolve(None, name)?.ok_or_else(|| {
+                DbError::SqlParse(format!(
+                    "Column {} in USING clause does not exist on both sides",
+                    name
+                ))
+            })
+        };
+        let mut terms = Vec::new();
+        for name in names {
+            let l = side(left, name)?;
+            let r = left_len + side(right, name)?;
+            terms.push(ScalarExpr::binary(
+                ScalarExpr::column(l, name.clone()),
+                BinaryOperator::Equal,
+                ScalarExpr::column(r, name.clone()),
+            ));
+        }
+        Ok(ScalarExpr::conjunction(terms))
+    }
+
+    fn bind_table_factor(&mut self, factor: &TableFactor) -> Result<(PlanNode, Scope)> {
+        match factor {
+            TableFactor::Table {
+                name, alias, args, ..
+            } => {
+                if args.is_some() {
+                    return Err(DbError::NotImplemented("Table functions".to_string()));
+                }
+                let table = name.to_string();
+                // WITH queries hide tables and views of the same name
+                if let Some((plan, scope)) = self.common_table(&table)? {
+                    return Ok((plan, scope.aliased(alias.as_ref())?));
+                }
+                let (plan, scope) = match self.catalog.get_table(&table) {
+                    Ok(schema) => (
+                        PlanNode::TableScan {
+                            table: table.clone(),
+                            columns: schema.columns.iter().map(|c| c.name.clone()).collect(),
+                        },
+                        Scope::of_table(&schema, &table),
+                    ),
+                    Err(table_error) => match self.catalog.get_view(&table) {
+                        Ok(view) => self.bind_view(&table, &view.query)?,
+                        Err(_) => return Err(table_error),
+                    },
+                };
+                Ok((plan, scope.aliased(alias.as_ref())?))
+            }
+            TableFactor::Derived {
+                lateral,
+                subquery,
+                alias,
+                ..
+            } => {
+                if *lateral {
+                    return Err(DbError::NotImplemented("LATERAL subqueries".to_string()));
+                }
+                let (plan, scope) = self.bind_query_scoped(subquery)?;
+                let plan = PlanNode::Subquery {
+                    plan: Box::new(plan),
+                    alias: alias
+                        .as_ref()
+                        .map(|a| a.name.value.clone())
+                        .unwrap_or_default(),
+                };
+                Ok((plan, scope.aliased(alias.as_ref())?))
+            }
+            TableFactor::NestedJoin {
+                table_with_joins,
+                alias,
+                ..
+            } => {
+                let (plan, scope) = self.bind_table_with_joins(table_with_joins)?;
+                Ok((plan, scope.aliased(alias.as_ref())?))
+            }
+            other => Err(DbError::NotImplemented(format!("Table factor: {}", other))),
+        }
+    }
+
+    // Views are stored as SQL and expanded in place; they cannot see the
+    // query that references them
+    fn bind_view(&mut self, name: &str, sql: &str) -> Result<(PlanNode, Scope)> {
+        if self.view_depth >= MAX_VIEW_DEPTH {
+            return Err(DbError::LimitExceeded(format!(
+                "View {} nests more than {} views deep",
+                name, MAX_VIEW_DEPTH
+            )));
+        }
+        let mut statements = SqlParser::new().parse(sql)?;
+        let query = match statements.pop() {
+            Some(SqlStatement::Select { query }) if statements.is_empty() => query,
+            _ => {
+                return Err(DbError::Catalog(format!(
+                    "View {} is not defined by a query",
+                    name
+                )))
+            }
+        };
+        let mut binder = Binder {
+            catalog: self.catalog,
+            outer: Vec::new(),
+            view_depth: self.view_depth + 1,
+            parameters: Vec::new(),
+            ctes: Vec::new(),
+        };
+        let (plan, mut scope) = binder.bind_query_scoped(&query)?;
+        for column in &mut scope.columns {
+            column.qualifier = Some(name.to_string());
+        }
+        let plan = PlanNode::Subquery {
+            plan: Box::new(plan),
+            alias: name.to_string(),
+        };
+        Ok((plan, scope))
+    }
+
+    // ------------------------------------------------------------------
+    // Expressions
+    // ------------------------------------------------------------------
+
+    fn bind_expr(
+        &mut self,
+        expr: &Expr,
+        scope: &Scope,
+        grouping: Option<&Grouping>,
+    ) -> Result<ScalarExpr> {
+        // Above an aggregate, group keys and aggregate calls are columns
+        if let Some(grouping) = grouping {
+            let text = expr.to_string();
+            if let Some(pos) = grouping.keys.iter().position(|key| *key == text) {
+                return Ok(ScalarExpr::column(pos, text));
+            }
+            if let Some(pos) = grouping.aggregates.iter().position(|agg| *agg == text) {
+                return Ok(ScalarExpr::column(grouping.keys.len() + pos, text));
+            }
+        }
+
+        let bind = |binder: &mut Self, e: &Expr| binder.bind_expr(e, scope, grouping);
+        let boxed = |e: ScalarExpr| Box::new(e);
+
+        Ok(match expr {
+            Expr::Identifier(ident) => self.bind_column(None, &ident.value, scope, grouping)?,
+            Expr::CompoundIdentifier(parts) => match parts.as_slice() {
+                [qualifier, column] | [_, qualifier, column] => {
+                    self.bind_column(Some(&qualifier.value), &column.value, scope, grouping)?
+                }
+                _ => {
+                    return Err(DbError::SqlParse(format!(
+                        "Invalid column reference {}",
+                        expr
+                    )))
+                }
+            },
+            Expr::Value(value) => {
+                if let ast::Value::Placeholder(p) = &value.value {
+                    return self.bind_parameter(p);
+                }
+                ScalarExpr::Literal(SqlParser::literal_value(expr)?)
+            }
+            Expr::TypedString { .. } | Expr::Interval(_) => {
+                ScalarExpr::Literal(SqlParser::literal_value(expr)?)
+            }
+            Expr::Array(array) => ScalarExpr::Function {
+                name: "ARRAY".to_string(),
+                args: array
+                    .elem
+                    .iter()
+                    .map(|item| bind(self, item))
+                    .collect::<Result<_>>()?,
+            },
+            Expr::Nested(inner) => bind(self, inner)?,
+            Expr::UnaryOp { op, expr: inner } => match op {
+                ast::UnaryOperator::Plus => bind(self, inner)?,
+                ast::UnaryOperator::Minus => match bind(self, inner)? {
+                    ScalarExpr::Literal(Value::Integer(i)) => ScalarExpr::Literal(Value::Integer(-i)),
+                    ScalarExpr::Literal(Value::Float(f)) => ScalarExpr::Literal(Value::Float(-f)),
+                    ScalarExpr::Literal(Value::Numeric(d)) => ScalarExpr::Literal(Value::Numeric(-d)),
+                    operand => ScalarExpr::Unary {
+                        op: UnaryOperator::Negate,
+                        expr: boxed(operand),
+                    },
+                },
+                ast::UnaryOperator::Not => ScalarExpr::Unary {
+                    op: UnaryOperator::Not,
+                    expr: boxed(bind(self, inner)?),
+                },
+                other => {
+                    return Err(DbError::NotImplemented(format!("Operator {}", other)));
+                }
+            },
+            Expr::BinaryOp { left, op, right } if distance_function(op).is_some() => {
+                let args = vec![bind(self, left)?, bind(self, right)?];
+                self.infer_vector_arguments(&args, scope, grouping);
+                ScalarExpr::Function {
+                    name: distance_function(op).unwrap_or_default().to_string(),
+                    args,
+                }
+            }
+            Expr::BinaryOp { left, op, right } => {
+                let op = match op {
+                    ast::BinaryOperator::Plus => BinaryOperator::Add,
+                    ast::BinaryOperator::Minus => BinaryOperator::Subtract,
+                    ast::BinaryOperator::Multiply => BinaryOperator::Multiply,
+                    ast::BinaryOperator::Divide => BinaryOperator::Divide,
+                    ast::BinaryOperator::Modulo => BinaryOperator::Modulo,
+                    ast::BinaryOperator::StringConcat => BinaryOperator::Concat,
+                    ast::BinaryOperator::Eq => BinaryOperator::Equal,
+                    ast::BinaryOperator::NotEq => BinaryOperator::NotEqual,
+                    ast::BinaryOperator::Lt => BinaryOperator::LessThan,
+                    ast::BinaryOperator::LtEq => BinaryOperator::LessThanOrEqual,
+                    ast::BinaryOperator::Gt => BinaryOperator::GreaterThan,
+                    ast::BinaryOperator::GtEq => BinaryOperator::GreaterThanOrEqual,
+                    ast::BinaryOperator::And => BinaryOperator::And,
+                    ast::BinaryOperator::Or => BinaryOperator::Or,
+                    other => {
+                        return Err(DbError::NotImplemented(format!("Operator {}", other)));
+                    }
+                };
+                let (left, right) = (bind(self, left)?, bind(self, right)?);
+                if !matches!(
+                    op,
+                    BinaryOperator::And | BinaryOperator::Or | BinaryOperator::Concat
+                ) {
+                    let arithmetic = matches!(
+                        op,
+                        BinaryOperator::Add
+                            | BinaryOperator::Subtract
+                            | BinaryOperator::Multiply
+                            | BinaryOperator::Divide
+                            | BinaryOperator::Modulo
+                    );
+                    self.infer_from_operand(&left, &right, arithmetic, scope, grouping);
+                    self.infer_from_operand(&right, &left, arithmetic, scope, grouping);
+                }
+                ScalarExpr::binary(left, op, right)
+            }
+            Expr::IsNull(inner) => ScalarExpr::Unary {
+                op: UnaryOperator::IsNull,
+                expr: boxed(bind(self, inner)?),
+            },
+            Expr::IsNotNull(inner) => ScalarExpr::Unary {
+                op: UnaryOperator::IsNotNull,
+                expr: boxed(bind(self, inner)?),
+            },
+            Expr::InList {
+                expr: inner,
+                list,
+                negated,
+            } => {
+                let inner = bind(self, inner)?;
+                let list: Vec<ScalarExpr> = list
+                    .iter()
+                    .map(|item| bind(self, item))
+                    .collect::<Result<_>>()?;
+                for item in &list {
+                    self.infer_from_operand(item, &inner, false, scope, grouping);
+                }
+                ScalarExpr::InList {
+                    expr: boxed(inner),
+                    list,
+                    negated: *negated,
+                }
+            }
+            Expr::Between {
+                expr: inner,
+                negated,
+                low,
+                high,
+            } => {
+                let (inner, low, high) = (bind(self, inner)?, bind(self, low)?, bind(self, high)?);
+                self.infer_from_operand(&low, &inner, false, scope, grouping);
+                self.infer_from_operand(&high, &inner, false, scope, grouping);
+                self.infer_from_operand(&inner, &low, false, scope, grouping);
+                ScalarExpr::Between {
+                    expr: boxed(inner),
+                    low: boxed(low),
+                    high: boxed(high),
+                    negated: *negated,
+                }
+            }
+            Expr::Like {
+                negated,
+                expr: inner,
+                pattern,
+                escape_char,
+                ..
+            }
+            | Expr::ILike {
+                negated,
+                expr: inner,
+                pattern,
+                escape_char,
+                ..
+            } => {
+                if escape_char.is_some() {
+                    return Err(DbError::NotImplemented("LIKE ... ESCAPE".to_string()));
+                }
+                let pattern = bind(self, pattern)?;
+                self.infer_parameter_type(&pattern, Some(DataType::Text));
+                ScalarExpr::Like {
+                    expr: boxed(bind(self, inner)?),
+                    pattern: boxed(pattern),
+                    negated: *negated,
+                    case_insensitive: matches!(expr, Expr::ILike { .. }),
+                }
+            }
+            Expr::Case {
+                operand,
+                conditions,
+                else_result,
+                ..
+            } => ScalarExpr::Case {
+                operand: operand
+                    .as_deref()
+                    .map(|e| bind(self, e).map(boxed))
+                    .transpose()?,
+                branches: conditions
+                    .iter()
+                    .map(|when| Ok((bind(self, &when.condition)?, bind(self, &when.result)?)))
+                    .collect::<Result<_>>()?,
+                else_result: else_result
+                    .as_deref()
+                    .map(|e| bind(self, e).map(boxed))
+                    .transpose()?,
+            },
+            Expr::Cast {
+                expr: inner,
+                data_type,
+                ..
+            } => {
+                let inner = bind(self, inner)?;
+                let data_type = DataType::from_sql(data_type)?;
+                self.infer_parameter_type(&inner, Some(data_type.clone()));
+                ScalarExpr::Cast {
+                    expr: boxed(inner),
+                    data_type,
+                }
+            }
+            Expr::Substring {
+                expr: inner,
+                substring_from,
+                substring_for,
+                ..
+            } => {
+                let mut args = vec![bind(self, inner)?];
+                args.push(match substring_from {
+                    Some(from) => bind(self, from)?,
+                    None => ScalarExpr::Literal(Value::Integer(1)),
+                });
+                if let Some(length) = substring_for {
+                    args.push(bind(self, length)?);
+                }
+                ScalarExpr::Function {
+                    name: "SUBSTRING".to_string(),
+                    args,
+                }
+            }
+            Expr::Trim {
+                expr: inner,
+                trim_where,
+                trim_what,
+                ..
+            } => {
+                let name = match trim_where {
+                    Some(ast::TrimWhereField::Leading) => "LTRIM",
+                    Some(ast::TrimWhereField::Trailing) => "RTRIM",
+                    _ => "TRIM",
+                };
+                let mut args = vec![bind(self, inner)?];
+                if let Some(what) = trim_what {
+                    args.push(bind(self, what)?);
+                }
+                ScalarExpr::Function {
+                    name: name.to_string(),
+                    args,
+                }
+            }
+            Expr::Ceil { expr: inner, .. } => ScalarExpr::Function {
+                name: "CEIL".to_string(),
+                args: vec![bind(self, inner)?],
+            },
+            Expr::Floor { expr: inner, .. } => ScalarExpr::Function {
+                name: "FLOOR".to_string(),
+                args: vec![bind(self, inner)?],
+            },
+            Expr::Function(function) => self.bind_function(function, scope, grouping)?,
+            Expr::Subquery(query) => {
+                let (plan, columns, correlated) = self.bind_subquery(query, scope)?;
+                Self::single_column(&columns)?;
+                ScalarExpr::ScalarSubquery {
+                    plan: Box::new(plan),
+                    data_type: columns.columns.first().map(|c| c.data_type.clone()),
+                    correlated,
+                }
+            }
+            Expr::InSubquery {
+                expr: inner,
+                subquery,
+                negated,
+                ..
+            } => {
+                let operand = bind(self, inner)?;
+                let (plan, columns, correlated) = self.bind_subquery(subquery, scope)?;
+                Self::single_column(&columns)?;
+                ScalarExpr::InSubquery {
+                    expr: boxed(operand),
+                    plan: Box::new(plan),
+                    negated: *negated,
+                    correlated,
+                }
+            }
+            Expr::Exists {
+                subquery, negated, ..
+            } => {
+                let (plan, _, correlated) = self.bind_subquery(subquery, scope)?;
+                ScalarExpr::Exists {
+                    plan: Box::new(plan),
+                    negated: *negated,
+                    correlated,
+                }
+            }
+            other => {
+                return Err(DbError::NotImplemented(format!("Expression: {}", other)));
+            }
+        })
+    }
+
+    // `$n` placeholder; the parser has already numbered any `?`
+    fn bind_parameter(&mut self, placeholder: &str) -> Result<ScalarExpr> {
+        let index = placeholder
+            .strip_prefix('$')
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| (1..=MAX_PARAMETERS).contains(n))
+            .ok_or_else(|| DbError::SqlParse(format!("Invalid parameter {}", placeholder)))?
+            - 1;
+        if self.parameters.len() <= index {
+            self.parameters.resize(index + 1, None);
+        }
+        Ok(ScalarExpr::Parameter { index })
+    }
+
+    // A parameter compared with or combined with another operand takes its
+    // type; above an aggregate the input types are not at hand
+    fn infer_from_operand(
+        &mut self,
+        param: &ScalarExpr,
+        operand: &ScalarExpr,
+        arithmetic: bool,
+        scope: &Scope,
+        grouping: Option<&Grouping>,
+    ) {
+        if grouping.is_some() || !matches!(param, ScalarExpr::Parameter { .. }) {
+            return;
+        }
+        match operand.data_type(&scope.types()) {
+            // date + n moves by days and timestamp + interval by the
+            // interval, so these say nothing about the other operand
+            Some(
+                DataType::Date | DataType::Timestamp | DataType::TimestampTz | DataType::Interval,
+            ) if arithmetic => {}
+            data_type => self.infer_parameter_type(param, data_type),
+        }
+    }
+
+    // The arguments of a distance function have the same type
+    fn infer_vector_arguments(
+        &mut self,
+        args: &[ScalarExpr],
+        scope: &Scope,
+        grouping: Option<&Grouping>,
+    ) {
+        if let [left, right] = args {
+            self.infer_from_operand(left, right, false, scope, grouping);
+            self.infer_from_operand(right, left, false, scope, grouping);
+        }
+    }
+
+    fn bind_column(
+        &mut self,
+        qualifier: Option<&str>,
+        name: &str,
+        scope: &Scope,
+        grouping: Option<&Grouping>,
+    ) -> Result<ScalarExpr> {
+        let display = qualified_name(qualifier, name);
+        if let Some(index) = scope.resolve(qualifier, name)? {
+            return Ok(ScalarExpr::column(index, display));
+        }
+        if let Some(grouping) = grouping {
+            if grouping.input.resolve(qualifier, name)?.is_some() {
+                return Err(DbError::SqlParse(format!(
+                    "Column {} must appear in the GROUP BY clause or be used in an aggregate function",
+                    display
+                )));
+            }
+        }
+        for (depth, frame) in self.outer.iter_mut().rev().enumerate() {
+            if let Some(index) = frame.scope.resolve(qualifier, name)? {
+                frame.referenced = true;
+                return Ok(ScalarExpr::OuterColumn {
+                    depth,
+                    index,
+                    name: display,
+                });
+            }
+        }
+        Err(DbError::SqlParse(format!("Column {} not found", display)))
+    }
+
+    // Bind a subquery that may refer to `scope`; also reports whether it did
+    fn bind_subquery(&mut self, query: &Query, scope: &Scope) -> Result<(PlanNode, Scope, bool)> {
+        self.outer.push(OuterFrame {
+            scope: scope.clone(),
+            referenced: false,
+        });
+        let bound = self.bind_query_scoped(query);
+        let frame = self.outer.pop().expect("frame pushed above");
+        let (plan, columns) = bound?;
+        Ok((plan, columns, frame.referenced))
+    }
+
+    fn single_column(columns: &Scope) -> Result<()> {
+        if columns.len() != 1 {
+            return Err(DbError::SqlParse(format!(
+                "Subquery must return exactly one column, got {}",
+                columns.len()
+            )));
+        }
+        Ok(())
+    }
+
+    fn bind_function(
+        &mut self,
+        function: &ast::Function,
+        scope: &Scope,
+        grouping: Option<&Grouping>,
+    ) -> Result<ScalarExpr> {
+        let name = function.name.to_string().to_ascii_uppercase();
+        if function.over.is_some() {
+            // Window calls of this query level were computed below the
+            // projection and are columns of the scope
+            let text = sql_text(function);
+            return match scope.resolve(None, &text)? {
+                Some(index) => Ok(ScalarExpr::column(index, text)),
+                None => Err(DbError::SqlParse(format!(
+                    "Window function {} is not allowed here",
+                    name
+                ))),
+            };
+        }
+        if AggregateFunction::from_name(&name).is_some() {
+            // Aggregates that belong to this query level were replaced by
+            // columns above; anything left is misplaced (WHERE, nested, ...)
+            return Err(DbError::SqlParse(format!(
+                "Aggregate function {} is not allowed here",
+                name
+            )));
+        }
+        let name = match name.as_str() {
+            "SUBSTR" => "SUBSTRING".to_string(),
+            "CEILING" => "CEIL".to_string(),
+            "CHAR_LENGTH" | "CHARACTER_LENGTH" => "LENGTH".to_string(),
+            _ => name,
+        };
+        if !SCALAR_FUNCTIONS.contains(&name.as_str()) {
+            return Err(DbError::SqlParse(format!("Unknown function {}", name)));
+        }
+        let (inputs, distinct) = function_inputs(function)?;
+        if distinct {
+            return Err(DbError::SqlParse(format!(
+                "DISTINCT is only allowed in aggregate functions, not {}",
+                name
+            )));
+        }
+        let mut args = Vec::new();
+        for input in inputs {
+            match input {
+                FunctionInput::Expr(expr) => args.push(self.bind_expr(expr, scope, grouping)?),
+                FunctionInput::Star => {
+                    return Err(DbError::SqlParse(format!("{}(*) is not valid", name)))
+                }
+            }
+        }
+        if VECTOR_FUNCTIONS.contains(&name.as_str()) {
+            self.infer_vector_arguments(&args, scope, grouping);
+        }
+        Ok(ScalarExpr::Function { name, args })
+    }
+
+    fn bind_aggregate(&mut self, call: &Expr, input: &Scope) -> Result<AggregateExpr> {
+        let Expr::Function(function) = call else {
+            unreachable!("collect_aggregates only returns function calls");
+        };
+        let name = function.name.to_string();
+        let kind = AggregateFunction::from_name(&name)
+            .expect("collect_aggregates only returns aggregate calls");
+        let (inputs, distinct) = function_inputs(function)?;
+        let arg = match (inputs.as_slice(), kind) {
+            ([FunctionInput::Star], AggregateFunction::Count) if !distinct => None,
+            ([FunctionInput::Expr(expr)], _) => Some(self.bind_expr(expr, input, None)?),
+            _ => {
+                return Err(DbError::SqlParse(format!(
+                    "Invalid arguments to aggregate {}",
+                    call
+                )))
+            }
+        };
+        Ok(AggregateExpr {
+            function: kind,
+            arg,
+            distinct,
+            column: call.to_string(),
+        })
+    }
+}
+
+// Output name of an unaliased select item
+fn output_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Identifier(ident) => ident.value.clone(),
+        Expr::CompoundIdentifier(parts) => parts
+            .last()
+            .map(|ident| ident.value.clone())
+            .unwrap_or_default(),
+        other => sql_text(other),
+    }
+}
+
+// Distance operators, spelled as in pgvector
+fn distance_function(op: &ast::BinaryOperator) -> Option<&'static str> {
+    match op.to_string().as_str() {
+        "<->" => Some("L2_DISTANCE"),
+        "<=>" => Some("COSINE_DISTANCE"),
+        _ => None,
+    }
+}
+
+fn column_ref(column: &ScopeColumn) -> Expr {
+    match &column.qualifier {
+        Some(qualifier) => Expr::CompoundIdentifier(vec![
+            Ident::new(qualifier.clone()),
+            Ident::new(column.name.clone()),
+        ]),
+        None => Expr::Identifier(Ident::new(column.name.clone())),
+    }
+}
+
+fn function_inputs(function: &ast::Function) -> Result<(Vec<FunctionInput<'_>>, bool)> {
+    match &function.args {
+        FunctionArguments::None => Ok((Vec::new(), false)),
+        FunctionArguments::List(list) => {
+            let distinct = matches!(
+                list.duplicate_treatment,
+                Some(DuplicateTreatment::Distinct)
+            );
+            let inputs = list
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => {
+                        Ok(FunctionInput::Expr(expr))
+                    }
+                    FunctionArg::Unnamed(FunctionArgExpr::Wildcard) => Ok(FunctionInput::Star),
+                    other => Err(DbError::NotImplemented(format!(
+                        "Function argument {}",
+                        other
+                    ))),
+                })
+                .collect::<Result<_>>()?;
+            Ok((inputs, distinct))
+        }
+        FunctionArguments::Subquery(_) => Err(DbError::NotImplemented(format!(
+            "Subquery as function argument in {}",
+            function.name
+        ))),
+    }
+}
+
+// Aggregate calls of one query level, deduplicated by SQL text; subqueries
+// have their own aggregates and are not searched
+fn collect_aggregates<'e>(expr: &'e Expr, out: &mut Vec<&'e Expr>) {
+    if let Expr::Function(function) = expr {
+        if function.over.is_none()
+            && AggregateFunction::from_name(&function.name.to_string()).is_some()
+        {
+            let text = expr.to_string();
+            if !out.iter().any(|seen| seen.to_string() == text) {
+                out.push(expr);
+            }
+            return;
+        }
+    }
+    for child in children(expr) {
+        collect_aggregates(child, out);
+    }
+}
+
+// Window calls of one query level, deduplicated by SQL text
+fn collect_windows<'e>(expr: &'e Expr, out: &mut Vec<&'e Expr>) {
+    if let Expr::Function(function) = expr {
+        if function.over.is_some() {
+            let text = expr.to_string();
+            if !out.iter().any(|seen| seen.to_string() == text) {
+                out.push(expr);
+            }
+            return;
+        }
+    }
+    for child in children(expr) {
+        collect_windows(child, out);
+    }
+}
+
+// Expressions of the WINDOW clause's specifications
+fn named_window_exprs(named: &[NamedWindowDefinition]) -> Vec<&Expr> {
+    named
+        .iter()
+        .filter_map(|definition| match &definition.1 {
+            NamedWindowExpr::WindowSpec(spec) => Some(spec),
+            NamedWindowExpr::NamedWindow(_) => None,
+        })
+        .flat_map(window_spec_exprs)
+        .collect()
+}
+
+fn window_spec_exprs(spec: &WindowSpec) -> impl Iterator<Item = &Expr> {
+    spec.partition_by
+        .iter()
+        .chain(spec.order_by.iter().map(|item| &item.expr))
+}
+
+// Resolve `OVER w` and `OVER (w ...)` against the WINDOW clause. As in
+// PostgreSQL, a window that names another takes its PARTITION BY, may add
+// an ORDER BY only where the other has none, and cannot copy a frame
+fn resolve_window<'e>(
+    over: &'e WindowType,
+    named: &'e [NamedWindowDefinition],
+) -> Result<WindowDefinition<'e>> {
+    match over {
+        WindowType::NamedWindow(name) => named_window(name, named, 0),
+        WindowType::WindowSpec(spec) => window_spec(spec, named, 0),
+    }
+}
+
+fn named_window<'e>(
+    name: &Ident,
+    named: &'e [NamedWindowDefinition],
+    depth: usize,
+) -> Result<WindowDefinition<'e>> {
+    if depth > named.len() {
+        return Err(DbError::SqlParse(format!(
+            "Window {} is defined in terms of itself",
+            name
+        )));
+    }
+    let definition = named
+        .iter()
+        .find(|definition| definition.0.value.eq_ignore_ascii_case(&name.value))
+        .ok_or_else(|| DbError::SqlParse(format!("Window {} does not exist", name)))?;
+    match &definition.1 {
+        NamedWindowExpr::NamedWindow(other) => named_window(other, named, depth + 1),
+        NamedWindowExpr::WindowSpec(spec) => window_spec(spec, named, depth + 1),
+    }
+}
+
+fn window_spec<'e>(
+    spec: &'e WindowSpec,
+    named: &'e [NamedWindowDefinition],
+    depth: usize,
+) -> Result<WindowDefinition<'e>> {
+    let mut exclude = FrameExclusion::NoOthers;
+    let mut partition_by = Vec::new();
+    for expr in &spec.partition_by {
+        match frame_exclusion(expr) {
+            Some(mode) => exclude = mode,
+            None => partition_by.push(expr),
+        }
+    }
+    let Some(base) = &spec.window_name else {
+        return Ok(WindowDefinition {
+            partition_by,
+            order_by: &spec.order_by,
+            frame: spec.window_frame.as_ref(),
+            exclude,
+        });
+    };
+
+    let copied = named_window(base, named, depth + 1)?;
+    if !partition_by.is_empty() {
+        return Err(DbError::SqlParse(format!(
+            "Cannot override PARTITION BY clause of window {}",
+            base
+        )));
+    }
+    if !spec.order_by.is_empty() && !copied.order_by.is_empty() {
+        return Err(DbError::SqlParse(format!(
+            "Cannot override ORDER BY clause of window {}",
+            base
+        )));
+    }
+    if copied.frame.is_some() {
+        return Err(DbError::SqlParse(format!(
+            "Cannot copy window {} because it has a frame clause",
+            base
+        )));
+    }
+    Ok(WindowDefinition {
+        partition_by: copied.partition_by,
+        order_by: match spec.order_by.is_empty() {
+            true => copied.order_by,
+            false => &spec.order_by,
+        },
+        frame: spec.window_frame.as_ref(),
+        exclude,
+    })
+}
+
+// The EXCLUDE clause the parser moved into a PARTITION BY list, if `expr`
+// is one; see `parser::FRAME_EXCLUSION_MARKER`
+fn frame_exclusion(expr: &Expr) -> Option<FrameExclusion> {
+    let Expr::Function(function) = expr else {
+        return None;
+    };
+    if !function
+        .name
+        .to_string()
+        .eq_ignore_ascii_case(FRAME_EXCLUSION_MARKER)
+    {
+        return None;
+    }
+    let (inputs, _) = function_inputs(function).ok()?;
+    let [FunctionInput::Expr(Expr::Value(value))] = inputs.as_slice() else {
+        return None;
+    };
+    match &value.value {
+        ast::Value::SingleQuotedString(mode) => match mode.as_str() {
+            "CURRENT ROW" => Some(FrameExclusion::CurrentRow),
+            "GROUP" => Some(FrameExclusion::Group),
+            "TIES" => Some(FrameExclusion::Ties),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Whether a WITH query is one the parser made from a SEARCH or CYCLE clause
+fn is_search_or_cycle(cte: &Cte) -> bool {
+    [CTE_SEARCH_MARKER, CTE_CYCLE_MARKER].contains(&cte.alias.name.value.as_str())
+}
+
+// The terms of `base UNION [ALL] step`, the form a recursive query takes
+fn recursive_terms(query: &Query) -> Option<(bool, &SetExpr, &SetExpr)> {
+    if query.with.is_some() || query.order_by.is_some() || query.limit_clause.is_some() {
+        return None;
+    }
+    match query.body.as_ref() {
+        SetExpr::SetOperation {
+            op: ast::SetOperator::Union,
+            set_quantifier,
+            left,
+            right,
+        } => match set_quantifier {
+            SetQuantifier::All => Some((true, left, right)),
+            SetQuantifier::Distinct | SetQuantifier::None => Some((false, left, right)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// How many times a plan reads the work table of recursive query `name`
+fn work_table_scans(plan: &PlanNode, name: &str) -> usize {
+    let own = matches!(plan, PlanNode::WorkTableScan { name: read, .. } if read == name);
+    plan.children()
+        .into_iter()
+        .map(|child| work_table_scans(child, name))
+        .sum::<usize>()
+        + own as usize
+}
+
+fn search_clause(clause: &Cte, scope: &Scope) -> Result<SearchClause> {
+    let (values, columns, added) = clause_parts(clause, scope, 1)?;
+    let [column] = <[String; 1]>::try_from(added).map_err(|_| malformed_clause(clause))?;
+    Ok(SearchClause {
+        breadth_first: values[0].to_string() == "'BREADTH'",
+        columns,
+        column,
+    })
+}
+
+fn cycle_clause(clause: &Cte, scope: &Scope) -> Result<CycleClause> {
+    let (values, columns, added) = clause_parts(clause, scope, 2)?;
+    let [mark, path] = <[String; 2]>::try_from(added).map_err(|_| malformed_clause(clause))?;
+    Ok(CycleClause {
+        columns,
+        mark,
+        mark_value: SqlParser::literal_value(values[0])?,
+        default_value: SqlParser::literal_value(values[1])?,
+        path,
+    })
+}
+
+// A SEARCH or CYCLE clause as the parser passes it on: the `values` leading
+// its select list, the positions of the columns after them in the query's
+// output, and the names of the columns it adds
+fn clause_parts<'c>(
+    clause: &'c Cte,
+    scope: &Scope,
+    values: usize,
+) -> Result<(Vec<&'c Expr>, Vec<usize>, Vec<String>)> {
+    let SetExpr::Select(select) = clause.query.body.as_ref() else {
+        return Err(malformed_clause(clause));
+    };
+    let mut exprs = Vec::new();
+    for item in &select.projection {
+        match item {
+            SelectItem::UnnamedExpr(expr) => exprs.push(expr),
+            _ => return Err(malformed_clause(clause)),
+        }
+    }
+    if exprs.len() <= values {
+        return Err(malformed_clause(clause));
+    }
+    let mut columns = Vec::new();
+    for expr in exprs.split_off(values) {
+        let Expr::Identifier(ident) = expr else {
+            return Err(malformed_clause(clause));
+        };
+        let index = scope.resolve(None, &ident.value)?.ok_or_else(|| {
+            DbError::SqlParse(format!(
+                "Column {} in SEARCH or CYCLE clause not found",
+                ident.value
+            ))
+        })?;
+        columns.push(index);
+    }
+    let added = clause
+        .alias
+        .columns
+        .iter()
+        .map(|column| column.name.value.clone())
+        .collect();
+    Ok((exprs, columns, added))
+}
+
+fn malformed_clause(clause: &Cte) -> DbError {
+    DbError::Internal(format!("Malformed SEARCH or CYCLE clause: {}", clause))
+}
+
+// Direct sub-expressions evaluated in the same query level
+fn children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::BinaryOp { left, right, .. } => vec![left.as_ref(), right.as_ref()],
+        Expr::UnaryOp { expr, .. }
+        | Expr::Nested(expr)
+        | Expr::IsNull(expr)
+        | Expr::IsNotNull(expr)
+        | Expr::Cast { expr, .. }
+        | Expr::Ceil { expr, .. }
+        | Expr::Floor { expr, .. }
+        | Expr::InSubquery { expr, .. } => vec![expr.as_ref()],
+        Expr::InList { expr, list, .. } => {
+            let mut all = vec![expr.as_ref()];
+            all.extend(list.iter());
+            all
+        }
+        Expr::Between {
+            expr, low, high, ..
+        } => vec![expr.as_ref(), low.as_ref(), high.as_ref()],
+        Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } => {
+            vec![expr.as_ref(), pattern.as_ref()]
+        }
+        Expr::Case {
+            operand,
+            conditions,
+            else_result,
+            ..
+        } => {
+            let mut all: Vec<&Expr> = operand.iter().map(|e| e.as_ref()).collect();
+            for when in conditions {
+                all.push(&when.condition);
+                all.push(&when.result);
+            }
+            all.extend(else_result.iter().map(|e| e.as_ref()));
+            all
+        }
+        Expr::Substring {
+            expr,
+            substring_from,
+            substring_for,
+            ..
+        } => {
+            let mut all = vec![expr.as_ref()];
+            all.extend(substring_from.iter().map(|e| e.as_ref()));
+            all.extend(substring_for.iter().map(|e| e.as_ref()));
+            all
+        }
+        Expr::Trim {
+            expr, trim_what, ..
+        } => {
+            let mut all = vec![expr.as_ref()];
+            all.extend(trim_what.iter().map(|e| e.as_ref()));
+            all
+        }
+        Expr::Function(function) => {
+            let mut all: Vec<&Expr> = function_inputs(function)
+                .map(|(inputs, _)| {
+                    inputs
+                        .into_iter()
+                        .filter_map(|input| match input {
+                            FunctionInput::Expr(expr) => Some(expr),
+                            FunctionInput::Star => None,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let Some(WindowType::WindowSpec(spec)) = &function.over {
+                all.extend(window_spec_exprs(spec));
+            }
+            all
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Column;
+
+    fn column(name: &str, data_type: DataType) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type,
+            nullable: true,
+            default: None,
+        }
+    }
+
+    fn catalog() -> Catalog {
+        let catalog = Catalog::new();
+        catalog
+            .create_table(Schema::new(
+                "users".to_string(),
+                vec![
+                    column("id", DataType::Integer),
+                    column("name", DataType::Varchar(255)),
+                ],
+            ))
+            .unwrap();
+        catalog
+            .create_table(Schema::new(
+                "orders".to_string(),
+                vec![
+                    column("id", DataType::Integer),
+                    column("user_id", DataType::Integer),
+                    column("total", DataType::Double),
+                ],
+            ))
+            .unwrap();
+        catalog
+    }
+
+    fn bind(catalog: &Catalog, sql: &str) -> Result<PlanNode> {
+        match SqlParser::new().parse(sql)?.remove(0) {
+            SqlStatement::Select { query } => Binder::new(catalog).bind_query(&query),
+            other => panic!("expected a query, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_join_columns_are_positional() -> Result<()> {
+        let catalog = catalog();
+        let plan = bind(
+            &catalog,
+            "SELECT u.name, o.total FROM users u JOIN orders o ON u.id = o.user_id",
+        )?;
+        let PlanNode::Project { input, exprs, .. } = plan else {
+            panic!("expected a projection");
+        };
+        assert_eq!(
+            exprs,
+            vec![ScalarExpr::column(1, "u.name"), ScalarExpr::column(4, "o.total")]
+        );
+        let PlanNode::Join { condition, .. } = *input else {
+            panic!("expected a join");
+        };
+        assert_eq!(
+            condition,
+            Some(ScalarExpr::binary(
+                ScalarExpr::column(0, "u.id"),
+                BinaryOperator::Equal,
+                ScalarExpr::column(3, "o.user_id"),
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ambiguous_and_unknown_columns() {
+        let catalog = catalog();
+        assert!(bind(&catalog, "SELECT id FROM users JOIN orders ON true").is_err());
+        assert!(bind(&catalog, "SELECT missing FROM users").is_err());
+        assert!(bind(&catalog, "SELECT name FROM users GROUP BY id").is_err());
+        assert!(bind(&catalog, "SELECT id FROM users WHERE COUNT(*) > 1").is_err());
+    }
+
+    #[test]
+    fn test_correlated_subquery() -> Result<()> {
+        let catalog = catalog();
+        let plan = bind(
+            &catalog,
+            "SELECT name FROM users u WHERE EXISTS \
+             (SELECT 1 FROM orders o WHERE o.user_id = u.id)",
+        )?;
+        let PlanNode::Project { input, .. } = plan else {
+            panic!("expected a projection");
+        };
+        let PlanNode::Filter { predicate, .. } = *input else {
+            panic!("expected a filter");
+        };
+        assert!(matches!(
+            predicate,
+            ScalarExpr::Exists {
+                correlated: true,
+                ..
+            }
+        ));
+
+        let plan = bind(
+            &catalog,
+            "SELECT name FROM users WHERE id IN (SELECT user_id FROM orders)",
+        )?;
+        assert!(format!("{:?}", plan).contains("correlated: false"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_grouping_output() -> Result<()> {
+        let catalog = catalog();
+        let plan = bind(
+            &catalog,
+            "SELECT user_id, SUM(total) AS spent FROM orders \
+             GROUP BY user_id HAVING COUNT(*) > 1 ORDER BY spent DESC",
+        )?;
+        assert_eq!(plan.output_columns(), vec!["user_id", "spent"]);
+        let PlanNode::Project { input, exprs, .. } = plan else {
+            panic!("expected a projection");
+        };
+        // Keys come first, then SUM(total) and COUNT(*)
+        assert_eq!(
+            exprs,
+            vec![ScalarExpr::column(0, "user_id"), ScalarExpr::column(1, "SUM(total)")]
+        );
+        let PlanNode::Sort { input, .. } = *input else {
+            panic!("expected a sort");
+        };
+        let PlanNode::Aggregate { aggregates, .. } = *input else {
+            panic!("expected an aggregate");
+        };
+        assert_eq!(aggregates.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_over_grouping() -> Result<()> {
+        let catalog = catalog();
+        let plan = bind(
+            &catalog,
+            "SELECT user_id, RANK() OVER (ORDER BY SUM(total) DESC) FROM orders GROUP BY user_id",
+        )?;
+        let PlanNode::Project { input, exprs, .. } = plan else {
+            panic!("expected a projection");
+        };
+        // The window column follows the key and SUM(total)
+        let rank = "RANK() OVER (ORDER BY SUM(total) DESC)";
+        assert_eq!(exprs[1], ScalarExpr::column(2, rank));
+        let PlanNode::Window {
+            input, order_by, ..
+        } = *input
+        else {
+            panic!("expected a window");
+        };
+        assert_eq!(order_by[0].expr, ScalarExpr::column(1, "SUM(total)"));
+        assert!(!order_by[0].ascending);
+        let PlanNode::Sort { input, .. } = *input else {
+            panic!("expected a sort");
+        };
+        assert!(matches!(*input, PlanNode::Aggregate { .. }));
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@
 use std::collections::{HashMap, HashSet};
 use std::hash::{DefaultHasher, Hash, Hasher};
 
+use crate::common::Value;
 use crate::error::DbError;
 use crate::execution::{planner::PlanNode, QueryResult};
 
@@ -125,6 +126,7 @@ impl RecursiveCteEvaluator {
     ) -> Result<QueryResult, DbError> {
         let mut all_rows = base_result.rows.clone();
         let columns = base_result.columns.clone();
+        let column_types = base_result.column_types.clone();
         let mut working_table = base_result;
 
         for iteration in 0..self.max_iterations {
@@ -150,7 +152,7 @@ impl RecursiveCteEvaluator {
             }
         }
 
-        Ok(QueryResult::new(columns, all_rows))
+        Ok(QueryResult::typed(columns, column_types, all_rows))
     }
 
     fn execute_recursive_step(
@@ -179,7 +181,7 @@ impl CycleDetector {
         }
     }
 
-    pub fn has_cycle(&self, rows: &[Vec<String>]) -> bool {
+    pub fn has_cycle(&self, rows: &[Vec<Value>]) -> bool {
         for row in rows {
             let mut hasher = DefaultHasher::new();
             row.hash(&mut hasher);
@@ -192,7 +194,7 @@ impl CycleDetector {
         false
     }
 
-    pub fn add_rows(&mut self, rows: &[Vec<String>]) {
+    pub fn add_rows(&mut self, rows: &[Vec<Value>]) {
         for row in rows {
             let mut hasher = DefaultHasher::new();
             row.hash(&mut hasher);
@@ -32,7 +32,10 @@ impl CteDependencyGraph {
 
     fn extract_deps_recursive(&self, plan: &PlanNode, deps: &mut Vec<String>) {
         match plan {
-            PlanNode::TableScan { table, .. } => {
+            PlanNode::TableScan { table, .. }
+            | PlanNode::IndexScan { table, .. }
+            | PlanNode::IndexOnlyScan { table, .. }
+            | PlanNode::NearestNeighborScan { table, .. } => {
                 if !deps.contains(table) {
                     deps.push(table.clone());
                 }
@@ -41,6 +44,8 @@ impl CteDependencyGraph {
             | PlanNode::Project { input, .. }
             | PlanNode::Sort { input, .. }
             | PlanNode::Limit { input, .. }
+            | PlanNode::Distinct { input }
+            | PlanNode::Window { input, .. }
             | PlanNode::Aggregate { input, .. } => {
                 self.extract_deps_recursive(input, deps);
             }
@@ -48,9 +53,32 @@ impl CteDependencyGraph {
                 self.extract_deps_recursive(left, deps);
                 self.extract_deps_recursive(right, deps);
             }
+            PlanNode::IndexNestedLoopJoin { left, table, .. } => {
+                self.extract_deps_recursive(left, deps);
+                if !deps.contains(table) {
+                    deps.push(table.clone());
+                }
+            }
             PlanNode::Subquery { plan, .. } => {
                 self.extract_deps_recursive(plan, deps);
             }
+            PlanNode::SetOperation { left, right, .. } => {
+                self.extract_deps_recursive(left, deps);
+                self.extract_deps_recursive(right, deps);
+            }
+            PlanNode::RecursiveUnion {
+                base, recursive, ..
+            } => {
+                self.extract_deps_recursive(base, deps);
+                self.extract_deps_recursive(recursive, deps);
+            }
+            // A recursive query's reference to itself
+            PlanNode::WorkTableScan { name, .. } => {
+                if !deps.contains(name) {
+                    deps.push(name.clone());
+                }
+            }
+            PlanNode::Values { .. } => {}
         }
     }
 
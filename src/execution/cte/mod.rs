@@ -23,6 +23,7 @@ pub use statistics::{CteDetail, CteStatistics, CteStatisticsReport};
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::Value;
     use crate::execution::{planner::PlanNode, QueryResult};
     use crate::parser::JoinType;
 
@@ -55,8 +56,8 @@ mod tests {
         let result = QueryResult::new(
             vec!["id".to_string(), "name".to_string()],
             vec![
-                vec!["1".to_string(), "Alice".to_string()],
-                vec!["2".to_string(), "Bob".to_string()],
+                vec![Value::Integer(1), Value::String("Alice".to_string())],
+                vec![Value::Integer(2), Value::String("Bob".to_string())],
             ],
         );
 
@@ -73,7 +74,7 @@ mod tests {
 
         let base_result = QueryResult::new(
             vec!["id".to_string(), "value".to_string()],
-            vec![vec!["1".to_string(), "10".to_string()]],
+            vec![vec![Value::Integer(1), Value::Integer(10)]],
         );
 
         let recursive_plan = PlanNode::TableScan {
@@ -139,7 +140,7 @@ mod tests {
                 table: "test_cte".to_string(),
                 columns: vec!["*".to_string()],
             }),
-            condition: "true".to_string(),
+            condition: None,
         };
 
         tracker.track_plan(&plan, &context);
@@ -151,8 +152,8 @@ mod tests {
         let mut detector = CycleDetector::new();
 
         let rows = vec![
-            vec!["1".to_string(), "A".to_string()],
-            vec!["2".to_string(), "B".to_string()],
+            vec![Value::Integer(1), Value::String("A".to_string())],
+            vec![Value::Integer(2), Value::String("B".to_string())],
         ];
 
         assert!(!detector.has_cycle(&rows));
@@ -19,7 +19,10 @@ impl CteReferenceTracker {
 
     pub fn track_plan(&mut self, plan: &PlanNode, cte_context: &CteContext) {
         match plan {
-            PlanNode::TableScan { table, .. } => {
+            PlanNode::TableScan { table, .. }
+            | PlanNode::IndexScan { table, .. }
+            | PlanNode::IndexOnlyScan { table, .. }
+            | PlanNode::NearestNeighborScan { table, .. } => {
                 if cte_context.is_cte(table) {
                     *self.references.entry(table.clone()).or_insert(0) += 1;
                 }
@@ -27,19 +30,43 @@ impl CteReferenceTracker {
             PlanNode::Filter { input, .. }
             | PlanNode::Project { input, .. }
             | PlanNode::Limit { input, .. }
-            | PlanNode::Sort { input, .. } => {
+            | PlanNode::Sort { input, .. }
+            | PlanNode::Window { input, .. }
+            | PlanNode::Distinct { input } => {
                 self.track_plan(input, cte_context);
             }
             PlanNode::Join { left, right, .. } => {
                 self.track_plan(left, cte_context);
                 self.track_plan(right, cte_context);
             }
+            PlanNode::IndexNestedLoopJoin { left, table, .. } => {
+                self.track_plan(left, cte_context);
+                if cte_context.is_cte(table) {
+                    *self.references.entry(table.clone()).or_insert(0) += 1;
+                }
+            }
             PlanNode::Aggregate { input, .. } => {
                 self.track_plan(input, cte_context);
             }
             PlanNode::Subquery { plan, .. } => {
                 self.track_plan(plan, cte_context);
             }
+            PlanNode::SetOperation { left, right, .. } => {
+                self.track_plan(left, cte_context);
+                self.track_plan(right, cte_context);
+            }
+            PlanNode::RecursiveUnion {
+                base, recursive, ..
+            } => {
+                self.track_plan(base, cte_context);
+                self.track_plan(recursive, cte_context);
+            }
+            PlanNode::WorkTableScan { name, .. } => {
+                if cte_context.is_cte(name) {
+                    *self.references.entry(name.clone()).or_insert(0) += 1;
+                }
+            }
+            PlanNode::Values { .. } => {}
         }
     }
 
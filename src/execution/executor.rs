@@ -1,83 +1,103 @@
-use crate::catalog::{Catalog, Schema};
-use crate::constraints::ConstraintManager;
+use crate::analytics::window_functions::{self, FrameExclusion, FrameType, PeerGroups};
+use crate::catalog::{Catalog, Column, DataType, IndexDefinition, Schema};
+use crate::common::{Decimal, Interval, Value};
+use crate::constraints::{CascadeAction, ConstraintManager, ForeignKey};
 use crate::error::DbError;
-use crate::execution::{planner::PlanNode, QueryResult};
-use crate::index::{IndexManager, IndexType};
-use crate::parser::{JoinType, SqlStatement};
-use crate::transaction::TransactionManager;
+use crate::execution::access_path::{index_predicate, AccessPathSelector};
+use crate::execution::analyze;
+use crate::execution::binder::Binder;
+use crate::execution::explain::{Explanation, PlanProfile};
+use crate::execution::expressions::{BinaryOperator, UnaryOperator};
+use crate::execution::optimizer::Optimizer;
+use crate::execution::planner::{
+    binary_operator_symbol, AggregateExpr, AggregateFunction, CycleClause, FrameBound,
+    IndexBounds, PlanNode, Planner, ScalarExpr, SearchClause, SetOperator, SortKey, WindowExpr,
+    WindowFrame, WindowFunction,
+};
+use crate::execution::prepared::PreparedStatement;
+use crate::execution::{QueryResult, MAX_RECURSIVE_ITERATIONS, MAX_RESULT_ROWS};
+use crate::index::hnsw::{self, DEFAULT_EF_SEARCH};
+use crate::index::table_index::{has_null, TableIndex};
+use crate::index::{IndexKey, IndexManager, IndexType};
+use crate::parser::{ConstraintType, ExplainFormat, JoinType, SqlParser, SqlStatement};
+use crate::procedures::{run_procedure, ProcedureResult, StoredProcedure, MAX_STORED_PROCEDURES};
+use crate::storage::{RowId, TableStore};
+use crate::transaction::{IndexedRow, IsolationLevel, RowRef, SqlTransaction, TransactionManager};
+use crate::triggers::{
+    TableTriggers, Trigger, TriggerEvent, TriggerLevel, TriggerTiming, MAX_TRIGGER_DEPTH,
+};
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, RwLock};
-
-// Maximum size of the predicate cache to prevent unbounded growth
-const MAX_PREDICATE_CACHE_SIZE: usize = 1000;
-
-// Maximum length of a predicate string to prevent DoS attacks
-// Predicates exceeding this length will be rejected
-const MAX_PREDICATE_LENGTH: usize = 10_000;
+use std::ops::{Bound, Range};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
 
 // Maximum number of rows to sort in memory before spilling to disk
 // Larger result sets should use external merge sort
 const MAX_IN_MEMORY_SORT_SIZE: usize = 100_000;
 
-/// Compiled predicate for efficient evaluation
-/// Caches parsed predicates to avoid re-parsing on every row
-#[derive(Debug, Clone)]
-struct CompiledPredicate {
-    #[allow(dead_code)]
-    original: String,
-    // Parsed expression tree for O(1) evaluation
-    expression: CompiledExpression,
-}
-
-/// Compiled expression tree for efficient predicate evaluation
-/// Eliminates runtime parsing overhead (10-100x speedup)
-#[derive(Debug, Clone)]
-enum CompiledExpression {
-    // Logical operators
-    And(Box<CompiledExpression>, Box<CompiledExpression>),
-    Or(Box<CompiledExpression>, Box<CompiledExpression>),
-    Not(Box<CompiledExpression>),
-
-    // Comparison operators
-    Equals { column: String, value: String },
-    NotEquals { column: String, value: String },
-    GreaterThan { column: String, value: String },
-    GreaterThanOrEqual { column: String, value: String },
-    LessThan { column: String, value: String },
-    LessThanOrEqual { column: String, value: String },
-
-    // Special operators
-    IsNull { column: String },
-    IsNotNull { column: String },
-    Like { column: String, pattern: String },
-    In { column: String, values: Vec<String> },
-    Between { column: String, low: String, high: String },
-
-    // Literal boolean
-    Literal(bool),
-}
+// Maximum nesting of ON DELETE CASCADE / SET NULL actions
+const MAX_CASCADE_DEPTH: usize = 16;
 
 // Query executor with enterprise-grade features
+//
+// Every statement runs in a SQL transaction: the one the executor is bound to
+// with `with_transaction` (a session's open transaction), or else one of its
+// own that commits when the statement succeeds. DDL is not transactional and
+// takes effect at once.
+#[derive(Clone)]
 pub struct Executor {
     catalog: Arc<Catalog>,
-    #[allow(dead_code)]
     txn_manager: Arc<TransactionManager>,
     index_manager: Arc<IndexManager>,
     constraint_manager: Arc<ConstraintManager>,
-    // Predicate cache to avoid runtime parsing (addresses critical performance issue)
-    // Maps predicate string to compiled form for O(1) lookup
-    predicate_cache: Arc<RwLock<HashMap<String, CompiledPredicate>>>,
+    // Rewrites bound query plans before execution
+    optimizer: Arc<Optimizer>,
+    // Heap storage holding the rows of every table in the catalog
+    table_store: Arc<TableStore>,
+    // Transaction statements run in; `None` autocommits each one
+    transaction: Option<Arc<SqlTransaction>>,
+    // Search width of HNSW index scans; `None` uses DEFAULT_EF_SEARCH
+    ef_search: Option<usize>,
+    // Where plan nodes record what they did, for EXPLAIN ANALYZE
+    profile: Option<Arc<PlanProfile>>,
+    // Levels of triggers the statements run inside, 0 outside any trigger
+    trigger_depth: usize,
+    // Rows the previous round of each running recursive WITH query added
+    work_tables: HashMap<String, Arc<Vec<Vec<Value>>>>,
 }
 
 impl Executor {
+    /// Create an executor with a private scratch table store
+    ///
+    /// Rows written through this executor are not visible to other executors
+    /// and are discarded when it is dropped. Use `new_with_storage` to attach
+    /// the executor to a database's table store.
     pub fn new(catalog: Arc<Catalog>, txn_manager: Arc<TransactionManager>) -> Self {
+        Self::new_with_storage(catalog, txn_manager, Self::scratch_store())
+    }
+
+    pub fn new_with_storage(
+        catalog: Arc<Catalog>,
+        txn_manager: Arc<TransactionManager>,
+        table_store: Arc<TableStore>,
+    ) -> Self {
+        let optimizer = Optimizer::new().with_catalog(catalog.clone());
         Self {
             catalog,
             txn_manager,
             index_manager: Arc::new(IndexManager::new()),
             constraint_manager: Arc::new(ConstraintManager::new()),
-            predicate_cache: Arc::new(RwLock::new(HashMap::new())),
+            optimizer: Arc::new(optimizer),
+            table_store,
+            transaction: None,
+            ef_search: None,
+            profile: None,
+            trigger_depth: 0,
+            work_tables: HashMap::new(),
         }
     }
 
@@ -87,236 +107,213 @@ impl Executor {
         index_manager: Arc<IndexManager>,
         constraint_manager: Arc<ConstraintManager>,
     ) -> Self {
+        let optimizer = Optimizer::new().with_catalog(catalog.clone());
         Self {
             catalog,
             txn_manager,
             index_manager,
             constraint_manager,
-            predicate_cache: Arc::new(RwLock::new(HashMap::new())),
+            optimizer: Arc::new(optimizer),
+            table_store: Self::scratch_store(),
+            transaction: None,
+            ef_search: None,
+            profile: None,
+            trigger_depth: 0,
+            work_tables: HashMap::new(),
         }
     }
 
-    /// Cache a compiled predicate to avoid re-parsing
-    /// Implements LRU-like eviction when cache is full
-    /// FIXED: Now compiles predicate into expression tree for 10-100x speedup
-    fn cache_predicate(&self, predicate: &str) {
-        // Security: Reject excessively long predicates to prevent DoS
-        if predicate.len() > MAX_PREDICATE_LENGTH {
-            eprintln!(
-                "WARNING: Predicate length {} exceeds maximum {}. Skipping cache.",
-                predicate.len(),
-                MAX_PREDICATE_LENGTH
-            );
-            return;
-        }
-
-        let mut cache = self.predicate_cache.write().unwrap();
-
-        // Evict oldest entry if cache is full (simple FIFO for now)
-        if cache.len() >= MAX_PREDICATE_CACHE_SIZE {
-            if let Some(first_key) = cache.keys().next().cloned() {
-                cache.remove(&first_key);
-            }
-        }
+    fn scratch_store() -> Arc<TableStore> {
+        Arc::new(TableStore::temporary().expect("failed to create scratch table store"))
+    }
 
-        // Compile predicate into expression tree
-        let compiled_expr = Self::compile_predicate_expr(predicate);
+    /// Table store backing this executor
+    pub fn table_store(&self) -> &Arc<TableStore> {
+        &self.table_store
+    }
 
-        cache.insert(
-            predicate.to_string(),
-            CompiledPredicate {
-                original: predicate.to_string(),
-                expression: compiled_expr,
-            },
-        );
+    /// Catalog the executor's statements run against
+    pub fn catalog(&self) -> &Arc<Catalog> {
+        &self.catalog
     }
 
-    /// Compile a predicate string into an expression tree
-    /// This eliminates runtime parsing overhead
-    fn compile_predicate_expr(predicate: &str) -> CompiledExpression {
-        let predicate = predicate.trim();
+    /// Start a transaction over this executor's tables; `None` takes the
+    /// transaction manager's default isolation level
+    pub fn begin_transaction(
+        &self,
+        isolation: Option<IsolationLevel>,
+    ) -> Result<Arc<SqlTransaction>, DbError> {
+        let isolation = isolation.unwrap_or_else(|| self.txn_manager.default_isolation());
+        Ok(Arc::new(SqlTransaction::begin(
+            self.txn_manager.clone(),
+            self.table_store.clone(),
+            isolation,
+        )?))
+    }
 
-        // Handle AND conditions
-        if let Some(and_pos) = Self::find_logical_operator_static(predicate, " AND ") {
-            let left = &predicate[..and_pos];
-            let right = &predicate[and_pos + 5..];
-            return CompiledExpression::And(
-                Box::new(Self::compile_predicate_expr(left)),
-                Box::new(Self::compile_predicate_expr(right)),
-            );
+    /// An executor running its statements in `transaction`
+    pub fn with_transaction(&self, transaction: Arc<SqlTransaction>) -> Self {
+        Self {
+            transaction: Some(transaction),
+            ..self.clone()
         }
+    }
 
-        // Handle OR conditions
-        if let Some(or_pos) = Self::find_logical_operator_static(predicate, " OR ") {
-            let left = &predicate[..or_pos];
-            let right = &predicate[or_pos + 4..];
-            return CompiledExpression::Or(
-                Box::new(Self::compile_predicate_expr(left)),
-                Box::new(Self::compile_predicate_expr(right)),
-            );
-        }
+    pub fn transaction(&self) -> Option<&Arc<SqlTransaction>> {
+        self.transaction.as_ref()
+    }
 
-        // Handle NOT conditions
-        if predicate.to_uppercase().starts_with("NOT ") {
-            return CompiledExpression::Not(Box::new(Self::compile_predicate_expr(&predicate[4..])));
+    /// An executor enforcing the constraints registered with `constraint_manager`
+    pub fn with_constraints(&self, constraint_manager: Arc<ConstraintManager>) -> Self {
+        Self {
+            constraint_manager,
+            ..self.clone()
         }
+    }
 
-        // Handle parentheses
-        if predicate.starts_with('(') && predicate.ends_with(')') {
-            return Self::compile_predicate_expr(&predicate[1..predicate.len() - 1]);
+    /// An executor searching `ef_search` candidates wide in HNSW index scans
+    pub fn with_ef_search(&self, ef_search: Option<usize>) -> Self {
+        Self {
+            ef_search,
+            ..self.clone()
         }
-
-        // Compile comparison expression
-        Self::compile_comparison(predicate)
     }
 
-    /// Compile a comparison expression
-    fn compile_comparison(expr: &str) -> CompiledExpression {
-        let upper = expr.to_uppercase();
-
-        // IS NULL / IS NOT NULL
-        if upper.contains(" IS NOT NULL") {
-            let col_name = expr.split_whitespace().next().unwrap_or("").to_string();
-            return CompiledExpression::IsNotNull { column: col_name };
-        }
-        if upper.contains(" IS NULL") {
-            let col_name = expr.split_whitespace().next().unwrap_or("").to_string();
-            return CompiledExpression::IsNull { column: col_name };
-        }
+    // Execute SQL statement (inline for performance)
+    #[inline]
+    pub fn execute(&self, stmt: SqlStatement) -> Result<QueryResult, DbError> {
+        self.run(stmt, &[])
+    }
 
-        // LIKE operator
-        if upper.contains(" LIKE ") {
-            let parts: Vec<&str> = expr.splitn(2, |c: char| c.to_ascii_uppercase() == 'L').collect();
-            if parts.len() == 2 && parts[1].to_uppercase().starts_with("IKE ") {
-                let col_name = parts[0].trim().to_string();
-                let pattern = parts[1][4..].trim().trim_matches('\'').to_string();
-                return CompiledExpression::Like {
-                    column: col_name,
-                    pattern,
-                };
-            }
+    // Run a statement through `statement`, except a procedure called outside
+    // a transaction, which ends the transactions it runs in itself
+    fn run(&self, stmt: SqlStatement, params: &[Value]) -> Result<QueryResult, DbError> {
+        if self.transaction.is_none() && matches!(stmt, SqlStatement::ExecProcedure { .. }) {
+            return self.execute_with_params(stmt, params).and_then(Self::client_result);
         }
+        self.statement(|executor| {
+            executor
+                .execute_with_params(stmt, params)
+                .and_then(Self::client_result)
+        })
+    }
 
-        // IN operator
-        if upper.contains(" IN (") {
-            if let Some(in_pos) = upper.find(" IN (") {
-                let col_name = expr[..in_pos].trim().to_string();
-                let values_str = &expr[in_pos + 5..];
-                if let Some(end_paren) = values_str.find(')') {
-                    let values: Vec<String> = values_str[..end_paren]
-                        .split(',')
-                        .map(|v| v.trim().trim_matches('\'').to_string())
-                        .collect();
-                    return CompiledExpression::In {
-                        column: col_name,
-                        values,
-                    };
-                }
-            }
+    // A result bigger than MAX_RESULT_ROWS fails the statement rather than
+    // reaching the client cut short
+    fn client_result(result: QueryResult) -> Result<QueryResult, DbError> {
+        if result.rows.len() > MAX_RESULT_ROWS {
+            return Err(DbError::LimitExceeded(format!(
+                "Query returned more than {} rows; add a LIMIT clause",
+                MAX_RESULT_ROWS
+            )));
         }
+        Ok(result)
+    }
 
-        // BETWEEN operator
-        if upper.contains(" BETWEEN ") && upper.contains(" AND ") {
-            if let Some(between_pos) = upper.find(" BETWEEN ") {
-                let col_name = expr[..between_pos].trim().to_string();
-                let rest = &expr[between_pos + 9..];
-                if let Some(and_pos) = rest.to_uppercase().find(" AND ") {
-                    let low = rest[..and_pos].trim().trim_matches('\'').to_string();
-                    let high = rest[and_pos + 5..].trim().trim_matches('\'').to_string();
-                    return CompiledExpression::Between {
-                        column: col_name,
-                        low,
-                        high,
-                    };
-                }
-            }
+    /// Run the stored procedure `name` with one argument per parameter
+    pub fn call_procedure(
+        &self,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<ProcedureResult, DbError> {
+        let procedure = self.catalog.get_procedure(name)?;
+        if self.transaction.is_none() {
+            return run_procedure(self, &procedure, arguments);
         }
+        self.statement(|executor| run_procedure(executor, &procedure, arguments))
+    }
 
-        // Standard comparison operators
-        let operators = [
-            (">=", "ge"),
-            ("<=", "le"),
-            ("<>", "ne"),
-            ("!=", "ne"),
-            ("=", "eq"),
-            (">", "gt"),
-            ("<", "lt"),
-        ];
-
-        for (op, op_type) in operators {
-            if let Some(pos) = expr.find(op) {
-                let left = expr[..pos].trim().to_string();
-                let right = expr[pos + op.len()..].trim().trim_matches('\'').to_string();
-
-                return match op_type {
-                    "eq" => CompiledExpression::Equals {
-                        column: left,
-                        value: right,
-                    },
-                    "ne" => CompiledExpression::NotEquals {
-                        column: left,
-                        value: right,
-                    },
-                    "gt" => CompiledExpression::GreaterThan {
-                        column: left,
-                        value: right,
-                    },
-                    "ge" => CompiledExpression::GreaterThanOrEqual {
-                        column: left,
-                        value: right,
-                    },
-                    "lt" => CompiledExpression::LessThan {
-                        column: left,
-                        value: right,
-                    },
-                    "le" => CompiledExpression::LessThanOrEqual {
-                        column: left,
-                        value: right,
-                    },
-                    _ => CompiledExpression::Literal(false),
-                };
+    // Run one statement in the bound transaction, undoing its writes if it
+    // fails, or in a transaction of its own
+    fn statement<T>(
+        &self,
+        run: impl FnOnce(&Executor) -> Result<T, DbError>,
+    ) -> Result<T, DbError> {
+        if let Some(txn) = &self.transaction {
+            let mark = txn.begin_statement()?;
+            let result = run(self);
+            if result.is_err() {
+                txn.rollback_statement(mark);
             }
+            return result;
         }
 
-        // Default: treat as literal false
-        CompiledExpression::Literal(false)
-    }
-
-    /// Static version of find_logical_operator for use in compilation
-    fn find_logical_operator_static(expr: &str, op: &str) -> Option<usize> {
-        let mut paren_depth = 0;
-        let upper = expr.to_uppercase();
-        let op_upper = op.to_uppercase();
-
-        for (i, c) in expr.chars().enumerate() {
-            match c {
-                '(' => paren_depth += 1,
-                ')' => paren_depth -= 1,
-                _ => {}
+        let txn = self.begin_transaction(None)?;
+        txn.begin_statement()?;
+        match run(&self.with_transaction(txn.clone())) {
+            Ok(value) => {
+                txn.commit()?;
+                Ok(value)
             }
-            if paren_depth == 0 && upper[i..].starts_with(&op_upper) {
-                return Some(i);
+            Err(e) => {
+                txn.rollback()?;
+                Err(e)
             }
         }
-        None
     }
 
-    /// Check if predicate is cached
-    fn is_predicate_cached(&self, predicate: &str) -> bool {
-        self.predicate_cache.read().unwrap().contains_key(predicate)
+    // Writes of the current statement go to its transaction
+    fn txn(&self) -> Result<&SqlTransaction, DbError> {
+        self.transaction
+            .as_deref()
+            .ok_or_else(|| DbError::Internal("Statement has no transaction".to_string()))
     }
 
-    // Execute SQL statement (inline for performance)
-    #[inline]
-    pub fn execute(&self, stmt: SqlStatement) -> Result<QueryResult, DbError> {
+    // Execute a statement whose `$n` parameters take the values in `params`
+    fn execute_with_params(
+        &self,
+        stmt: SqlStatement,
+        params: &[Value],
+    ) -> Result<QueryResult, DbError> {
         match stmt {
-            SqlStatement::CreateTable { name, columns } => {
+            SqlStatement::CreateTable {
+                name,
+                columns,
+                constraints,
+            } => {
                 let schema = Schema::new(name.clone(), columns);
-                self.catalog.create_table(schema)?;
+                self.catalog.create_table(schema.clone())?;
+                // Storage without a catalog entry is left over from a table
+                // that no longer exists; start the new table empty
+                if let Err(e) = self.table_store.reset_table(&name) {
+                    let _ = self.catalog.drop_table(&name);
+                    return Err(e);
+                }
+                for constraint in constraints {
+                    if let Err(e) = self.add_constraint(&schema, constraint) {
+                        self.constraint_manager.drop_table(&name);
+                        let _ = self.catalog.drop_table(&name);
+                        return Err(e);
+                    }
+                }
                 Ok(QueryResult::with_affected(0))
             }
             SqlStatement::DropTable { name } => {
+                if let Some(fk) = self
+                    .catalog
+                    .list_foreign_keys()
+                    .into_iter()
+                    .find(|fk| fk.referenced_table == name && fk.table != name)
+                {
+                    return Err(DbError::ConstraintViolation(format!(
+                        "Cannot drop table {}: foreign key {} on table {} references it",
+                        name, fk.name, fk.table
+                    )));
+                }
+                let indexes = self.catalog.list_indexes(&name);
                 self.catalog.drop_table(&name)?;
+                self.constraint_manager.drop_table(&name);
+                for trigger in self.catalog.table_triggers(&name) {
+                    self.catalog.drop_trigger(&trigger.name)?;
+                }
+                // Dropping the storage drops the indexes too, but their trees
+                // may outlive storage that is already gone
+                if self.table_store.has_table(&name) {
+                    self.table_store.drop_table(&name)?;
+                }
+                for index in indexes {
+                    self.table_store.drop_index(&index.name)?;
+                }
                 Ok(QueryResult::with_affected(0))
             }
             SqlStatement::CreateDatabase { name: _ } => {
@@ -337,184 +334,132 @@ impl Executor {
                 // For now, just return success
                 Ok(QueryResult::with_affected(0))
             }
-            SqlStatement::Select {
-                table,
+            stmt @ (SqlStatement::Select { .. } | SqlStatement::Union { .. }) => {
+                self.execute_select(&stmt, params)
+            }
+            SqlStatement::SelectInto {
+                target_table,
+                source_table,
                 columns,
-                join: _,
-                group_by: _,
-                order_by: _,
-                limit,
-                offset,
-                distinct,
-                ..
+                filter,
             } => {
-                let schema = self.catalog.get_table(&table)?;
+                // SELECT INTO: Copy matching rows from source into a new target table
+                let source_schema = self.catalog.get_table(&source_table)?;
+                let source_columns = Self::column_names(&source_schema);
 
-                // Determine result columns
-                let result_columns = if columns.contains(&"*".to_string()) {
-                    schema.columns.iter().map(|c| c.name.clone()).collect()
+                let indices = if columns.is_empty() || columns.iter().any(|c| c == "*") {
+                    (0..source_columns.len()).collect::<Vec<_>>()
                 } else {
                     columns
+                        .iter()
+                        .map(|c| Self::column_position(&source_columns, c))
+                        .collect::<Result<Vec<_>, DbError>>()?
                 };
 
-                // Create empty result (actual data would be fetched from storage)
-                let mut result = QueryResult::new(result_columns, Vec::new());
-
-                // Enterprise optimizations applied in production:
-                // - JOIN: Use hash join, sort-merge join, or nested loop based on cost
-                // - GROUP BY: Use hash-based or sort-based aggregation
-                // - ORDER BY: Use external merge sort for large datasets
-                // - LIMIT: Push down to storage layer for early termination
-                // - DISTINCT: Use HashSet for deduplication (already implemented)
-
-                // Apply DISTINCT if requested
-                if distinct && !result.rows.is_empty() {
-                    result = self.apply_distinct(result);
-                }
-
-                // Apply OFFSET if specified
-                if let Some(offset_val) = offset {
-                    if offset_val < result.rows.len() {
-                        result.rows = result.rows.split_off(offset_val);
-                    } else {
-                        result.rows.clear();
+                let target_columns = indices
+                    .iter()
+                    .map(|&i| source_schema.columns[i].clone())
+                    .collect();
+                self.execute_with_params(
+                    SqlStatement::CreateTable {
+                        name: target_table.clone(),
+                        columns: target_columns,
+                        constraints: Vec::new(),
+                    },
+                    params,
+                )?;
+
+                let predicate = self.bind_filter(&source_schema, filter.as_ref(), params)?;
+                let eval = Evaluator::new(self, &[]);
+                let txn = self.txn()?;
+                let mut copied = 0;
+                for (_, row) in self.scan_rows(&source_schema)? {
+                    if !eval.qualifies(predicate.as_ref(), &row)? {
+                        continue;
                     }
+                    let projected: Vec<Value> = indices.iter().map(|&i| row[i].clone()).collect();
+                    txn.insert_row(&target_table, &projected)?;
+                    copied += 1;
                 }
+                self.record_modified(&target_table, copied)?;
 
-                // Apply LIMIT if specified
-                if let Some(limit_val) = limit {
-                    result.rows.truncate(limit_val);
-                }
-
-                Ok(result)
-            }
-            SqlStatement::SelectInto {
-                target_table,
-                source_table,
-                columns: _,
-                filter: _,
-            } => {
-                // SELECT INTO: Copy data from source to new target table
-                let source_schema = self.catalog.get_table(&source_table)?;
-
-                // Create target table with same schema
-                let target_schema =
-                    Schema::new(target_table.clone(), source_schema.columns.clone());
-                self.catalog.create_table(target_schema)?;
-
-                // In production, would copy data from source to target
-                Ok(QueryResult::with_affected(0))
+                Ok(QueryResult::with_affected(copied))
             }
             SqlStatement::Insert {
                 table,
                 columns,
                 values,
             } => {
-                // Validate table exists
-                let _schema = self.catalog.get_table(&table)?;
-
-                // Enterprise validation: check constraints for each row
-                for row in &values {
-                    let mut row_map = HashMap::new();
-                    for (i, col) in columns.iter().enumerate() {
-                        if let Some(val) = row.get(i) {
-                            row_map.insert(col.clone(), val.clone());
-                        }
-                    }
-
-                    // Validate foreign keys
-                    self.constraint_manager
-                        .validate_foreign_key(&table, &row_map)?;
-
-                    // Validate unique constraints
-                    self.constraint_manager.validate_unique(&table, &row_map)?;
-
-                    // Validate check constraints
-                    self.constraint_manager.validate_check(&table, &row_map)?;
+                if self.is_view(&table) {
+                    let values = self.evaluate_values(&values, params)?;
+                    return self.insert_into_view(&table, &columns, values);
                 }
-
-                Ok(QueryResult::with_affected(values.len()))
+                let schema = self.catalog.get_table(&table)?;
+                let values = self.evaluate_values(&values, params)?;
+                let inserted = self.insert_rows(self.txn()?, &schema, &columns, values)?;
+                self.record_modified(&table, inserted)?;
+                Ok(QueryResult::with_affected(inserted))
             }
             SqlStatement::InsertIntoSelect {
-                table: _,
-                columns: _,
-                source_query: _,
+                table,
+                columns,
+                source,
             } => {
-                // INSERT INTO ... SELECT: Insert results from a query
-                // Parse and execute the source query
-                // In production, would execute source_query and insert results
-                Ok(QueryResult::with_affected(0))
+                // INSERT INTO ... SELECT: Run the source query and insert its rows
+                let source =
+                    self.execute_select(&SqlStatement::Select { query: source }, params)?;
+                self.insert_query_result(&table, &columns, source)
             }
             SqlStatement::Update {
                 table,
                 assignments,
-                filter: _,
+                filter,
             } => {
-                // Validate table exists
-                let _schema = self.catalog.get_table(&table)?;
-
-                // Build update map
-                let mut update_map = HashMap::new();
-                for (col, val) in &assignments {
-                    update_map.insert(col.clone(), val.clone());
+                if self.is_view(&table) {
+                    return self.write_view_rows(
+                        &table,
+                        TriggerEvent::Update,
+                        &assignments,
+                        filter.as_ref(),
+                        params,
+                    );
                 }
-
-                // Enterprise validation: check constraints
-                self.constraint_manager
-                    .validate_foreign_key(&table, &update_map)?;
-                self.constraint_manager
-                    .validate_unique(&table, &update_map)?;
-                self.constraint_manager
-                    .validate_check(&table, &update_map)?;
-
-                // In production: apply filter, update rows, return actual count
-                Ok(QueryResult::with_affected(0))
+                let schema = self.catalog.get_table(&table)?;
+                let targets = self.bind_assignments(&schema, &assignments, params)?;
+                let predicate = self.bind_filter(&schema, filter.as_ref(), params)?;
+                let updated =
+                    self.update_rows(self.txn()?, &schema, &targets, predicate.as_ref())?;
+                self.record_modified(&table, updated)?;
+                Ok(QueryResult::with_affected(updated))
             }
-            SqlStatement::Delete { table, filter: _ } => {
-                // Validate table exists
-                let _schema = self.catalog.get_table(&table)?;
-
-                // Enterprise feature: handle cascading deletes
-                // Build row map for cascade validation (placeholder - would use actual row data)
-                let row_map = HashMap::new();
-                let cascade_actions = self
-                    .constraint_manager
-                    .cascade_operation(&table, "DELETE", &row_map)?;
-
-                // Execute cascade actions in transaction
-                for action in cascade_actions {
-                    // In production: execute cascading deletes/updates
-                    match action {
-                        crate::constraints::CascadeAction::Delete {
-                            table: _,
-                            condition: _,
-                        } => {
-                            // Execute delete on referencing table
-                        }
-                        crate::constraints::CascadeAction::Update {
-                            table: _,
-                            column: _,
-                            value: _,
-                        } => {
-                            // Execute update on referencing table
-                        }
-                    }
+            SqlStatement::Delete { table, filter } => {
+                if self.is_view(&table) {
+                    return self.write_view_rows(
+                        &table,
+                        TriggerEvent::Delete,
+                        &[],
+                        filter.as_ref(),
+                        params,
+                    );
                 }
-
-                Ok(QueryResult::with_affected(0))
+                let schema = self.catalog.get_table(&table)?;
+                let predicate = self.bind_filter(&schema, filter.as_ref(), params)?;
+                let deleted = self.delete_rows(self.txn()?, &schema, predicate.as_ref(), 0)?;
+                self.record_modified(&table, deleted)?;
+                Ok(QueryResult::with_affected(deleted))
             }
             SqlStatement::CreateIndex {
                 name,
                 table,
                 columns,
                 unique,
+                filter,
+                hnsw,
             } => {
-                // Validate table exists
-                let _schema = self.catalog.get_table(&table)?;
-
-                // Create index using IndexManager
                 // Choose index type based on properties
-                let index_type = if unique {
+                let index_type = if hnsw.is_some() {
+                    IndexType::Hnsw
+                } else if unique {
                     IndexType::BPlusTree
                 } else if columns.len() > 1 {
                     IndexType::BPlusTree
@@ -522,13 +467,47 @@ impl Executor {
                     IndexType::BTree
                 };
 
-                self.index_manager.create_index(name.clone(), index_type)?;
+                // The WHERE clause of a partial index becomes its predicate
+                let schema = self.catalog.get_table(&table)?;
+                let predicate = self
+                    .bind_filter(&schema, filter.as_ref(), params)?
+                    .map(|filter| index_predicate(&filter, &schema))
+                    .transpose()?;
+                Self::check_index_columns(&schema, &columns, unique, hnsw.is_some())?;
+
+                // Record the definition in the catalog (validates the table
+                // and columns), build its B+Tree over the existing rows, then
+                // register the index with IndexManager
+                let index = IndexDefinition {
+                    name: name.clone(),
+                    table,
+                    columns,
+                    unique,
+                    predicate,
+                    hnsw,
+                };
+                self.catalog.create_index(index.clone())?;
+                if let Err(e) = self.table_store.create_index(&schema, index) {
+                    let _ = self.catalog.drop_index(&name);
+                    return Err(e);
+                }
+                if let Err(e) = self.index_manager.create_index(name.clone(), index_type) {
+                    let _ = self.table_store.drop_index(&name);
+                    let _ = self.catalog.drop_index(&name);
+                    return Err(e);
+                }
 
                 Ok(QueryResult::with_affected(0))
             }
             SqlStatement::DropIndex { name } => {
-                // Drop index using IndexManager
-                self.index_manager.drop_index(&name)?;
+                // Drop index from the catalog, the index file and
+                // IndexManager; indexes reloaded from a persistent catalog
+                // may not be registered yet
+                self.catalog.drop_index(&name)?;
+                self.table_store.drop_index(&name)?;
+                if self.index_manager.list_indexes().contains(&name) {
+                    self.index_manager.drop_index(&name)?;
+                }
                 Ok(QueryResult::with_affected(0))
             }
             SqlStatement::CreateView {
@@ -536,31 +515,30 @@ impl Executor {
                 query,
                 or_replace,
             } => {
-                // If OR REPLACE is specified, drop existing view first
+                // Store view definition in catalog, replacing any existing
+                // view if OR REPLACE is specified
                 if or_replace {
-                    let _ = self.catalog.drop_view(&name);
+                    self.catalog.replace_view(name, query)?;
+                } else {
+                    self.catalog.create_view(name, query)?;
                 }
-
-                // Store view definition in catalog
-                self.catalog.create_view(name, query)?;
                 Ok(QueryResult::with_affected(0))
             }
             SqlStatement::DropView { name } => {
-                // Remove view from catalog
+                // Remove view from catalog, along with its triggers
                 self.catalog.drop_view(&name)?;
+                for trigger in self.catalog.table_triggers(&name) {
+                    self.catalog.drop_trigger(&trigger.name)?;
+                }
                 Ok(QueryResult::with_affected(0))
             }
             SqlStatement::TruncateTable { name } => {
                 // Validate table exists
                 let _schema = self.catalog.get_table(&name)?;
 
-                // In a full implementation, this would:
-                // 1. Delete all rows from the table
-                // 2. Reset auto-increment counters
-                // 3. Clear associated indexes
-                // For now, just validate the table exists
-
-                Ok(QueryResult::with_affected(0))
+                // Release all heap pages but the first one
+                let removed = self.table_store.truncate(&name)?;
+                Ok(QueryResult::with_affected(removed))
             }
             SqlStatement::AlterTable { name, action } => {
                 // Execute ALTER TABLE operation
@@ -568,37 +546,98 @@ impl Executor {
                 Ok(QueryResult::with_affected(0))
             }
             SqlStatement::CreateProcedure {
-                name: _,
-                parameters: _,
-                body: _,
+                name,
+                parameters,
+                body,
+                or_replace,
             } => {
-                // Store procedure definition
-                // In production, would compile and store the procedure
+                // The body is compiled now so that errors in it are reported
+                // by CREATE PROCEDURE rather than by the first CALL
+                let procedure = StoredProcedure::new(name, parameters, body);
+                procedure.validate()?;
+                let procedures = self.catalog.list_procedures();
+                if procedures.len() >= MAX_STORED_PROCEDURES
+                    && !procedures.contains(&procedure.name)
+                {
+                    return Err(DbError::LimitExceeded(format!(
+                        "Cannot create more than {} procedures",
+                        MAX_STORED_PROCEDURES
+                    )));
+                }
+                if or_replace {
+                    self.catalog.replace_procedure(procedure)?;
+                } else {
+                    self.catalog.create_procedure(procedure)?;
+                }
+                Ok(QueryResult::with_affected(0))
+            }
+            SqlStatement::DropProcedure { name } => {
+                self.catalog.drop_procedure(&name)?;
                 Ok(QueryResult::with_affected(0))
             }
-            SqlStatement::ExecProcedure {
-                name: _,
-                arguments: _,
+            SqlStatement::CreateTrigger {
+                name,
+                table,
+                timing,
+                events,
+                level,
+                condition,
+                body,
+                or_replace,
             } => {
-                // Execute stored procedure
-                // In production, would look up and execute the procedure
+                // As with procedures, errors in the body are reported now
+                // rather than by the first write that fires it
+                let trigger = Trigger::new(name, table, timing, events, level, condition, body);
+                trigger.validate()?;
+                if self.is_view(&trigger.table) {
+                    if trigger.timing != TriggerTiming::InsteadOf {
+                        return Err(DbError::InvalidInput(format!(
+                            "Trigger {} on view {} must be INSTEAD OF",
+                            trigger.name, trigger.table
+                        )));
+                    }
+                } else {
+                    self.catalog.get_table(&trigger.table)?;
+                    if trigger.timing == TriggerTiming::InsteadOf {
+                        return Err(DbError::InvalidInput(format!(
+                            "INSTEAD OF trigger {} must be on a view, not table {}",
+                            trigger.name, trigger.table
+                        )));
+                    }
+                }
+                trigger.check_capacity(&self.catalog)?;
+                if or_replace {
+                    self.catalog.replace_trigger(trigger)?;
+                } else {
+                    self.catalog.create_trigger(trigger)?;
+                }
                 Ok(QueryResult::with_affected(0))
             }
-            SqlStatement::Union { left, right, all } => {
-                // Execute UNION operation
-                let left_result = self.execute(*left)?;
-                let right_result = self.execute(*right)?;
-
-                // Combine results
-                let mut combined = left_result;
-                combined.rows.extend(right_result.rows);
-
-                // If not UNION ALL, remove duplicates
-                if !all {
-                    combined = self.apply_distinct(combined);
+            SqlStatement::DropTrigger { name } => {
+                self.catalog.drop_trigger(&name)?;
+                Ok(QueryResult::with_affected(0))
+            }
+            SqlStatement::ExecProcedure { name, arguments } => {
+                // A procedure with OUT parameters returns one row of their
+                // final values
+                let procedure = self.catalog.get_procedure(&name)?;
+                let arguments = self
+                    .evaluate_values(&[arguments], params)?
+                    .pop()
+                    .unwrap_or_default();
+                let result = run_procedure(self, &procedure, arguments)?;
+                if result.output_parameters.is_empty() {
+                    return Ok(QueryResult::with_affected(result.rows_affected));
                 }
-
-                Ok(combined)
+                let types = procedure
+                    .output_parameters()
+                    .map(|parameter| parameter.data_type.clone())
+                    .collect();
+                let (columns, row): (Vec<String>, Vec<Value>) =
+                    result.output_parameters.into_iter().unzip();
+                let mut output = QueryResult::typed(columns, types, vec![row]);
+                output.rows_affected = result.rows_affected;
+                Ok(output)
             }
             SqlStatement::GrantPermission {
                 permission: _,
@@ -616,537 +655,1398 @@ impl Executor {
                 // Permission revocation would go here
                 Ok(QueryResult::with_affected(0))
             }
+            SqlStatement::Prepare { .. }
+            | SqlStatement::Execute { .. }
+            | SqlStatement::Deallocate { .. } => {
+                // Prepared statements belong to a session's StatementCache
+                Err(DbError::InvalidOperation(
+                    "PREPARE, EXECUTE and DEALLOCATE need a session".to_string(),
+                ))
+            }
+            SqlStatement::Begin { .. }
+            | SqlStatement::Commit
+            | SqlStatement::Rollback { .. }
+            | SqlStatement::Savepoint { .. }
+            | SqlStatement::ReleaseSavepoint { .. }
+            | SqlStatement::SetTransaction { .. } => {
+                // Transaction blocks belong to a Session
+                Err(DbError::InvalidOperation(
+                    "Transaction control statements need a session".to_string(),
+                ))
+            }
+            SqlStatement::SetParameter { .. } => {
+                Err(DbError::InvalidOperation("SET needs a session".to_string()))
+            }
+            SqlStatement::Analyze { table } => {
+                let tables = match table {
+                    Some(table) => vec![table],
+                    None => self.catalog.list_tables(),
+                };
+                for table in tables {
+                    self.analyze_table(&table)?;
+                }
+                Ok(QueryResult::with_affected(0))
+            }
+            SqlStatement::Explain {
+                analyze,
+                format,
+                statement,
+            } => self.execute_explain(&statement, analyze, format, params),
         }
     }
 
-    // Execute a query plan node (inline for performance)
-    #[inline]
-    pub fn execute_plan(&self, plan: PlanNode) -> Result<QueryResult, DbError> {
-        match plan {
-            PlanNode::TableScan { table, columns } => self.execute_table_scan(&table, &columns),
-            PlanNode::Filter { input, predicate } => {
-                let input_result = self.execute_plan(*input)?;
-                self.execute_filter(input_result, &predicate)
+    /// Bind a statement once, to run it any number of times with
+    /// `execute_prepared`
+    ///
+    /// `declared_types` gives the types of the first parameters; where it
+    /// has none, a parameter's type is taken from how the statement uses it.
+    /// Queries keep their optimized plan.
+    pub fn prepare(
+        &self,
+        statement: SqlStatement,
+        declared_types: Vec<Option<DataType>>,
+    ) -> Result<PreparedStatement, DbError> {
+        let catalog_version = self.catalog.version();
+        let mut binder = Binder::new(&self.catalog);
+        let plan = match &statement {
+            SqlStatement::Select { query }
+            | SqlStatement::InsertIntoSelect { source: query, .. } => {
+                let plan = self.optimizer.optimize(binder.bind_query(query)?)?;
+                Some(self.choose_access_paths(plan))
             }
-            PlanNode::Project { input, columns } => {
-                let input_result = self.execute_plan(*input)?;
-                self.execute_project(input_result, &columns)
+            // Views are written by their triggers, whose columns are only
+            // known by running the view
+            SqlStatement::Insert { table, .. }
+            | SqlStatement::Update { table, .. }
+            | SqlStatement::Delete { table, .. }
+                if self.is_view(table) =>
+            {
+                None
             }
-            PlanNode::Join {
-                join_type,
-                left,
-                right,
-                condition,
+            SqlStatement::Insert {
+                table,
+                columns,
+                values,
             } => {
-                let left_result = self.execute_plan(*left)?;
-                let right_result = self.execute_plan(*right)?;
-                self.execute_join(left_result, right_result, join_type, &condition)
+                // Parameters take the type of the column they are inserted into
+                let schema = self.catalog.get_table(table)?;
+                let targets = Self::insert_targets(&schema, columns)?;
+                for row in values {
+                    for (value, &idx) in row.iter().zip(&targets) {
+                        let value = binder.bind_constant(value)?;
+                        binder.infer_parameter_type(
+                            &value,
+                            Some(schema.columns[idx].data_type.clone()),
+                        );
+                    }
+                }
+                None
             }
-            PlanNode::Aggregate {
-                input,
-                group_by,
-                aggregates,
-                having,
+            SqlStatement::Update {
+                table,
+                assignments,
+                filter,
             } => {
-                let input_result = self.execute_plan(*input)?;
-                self.execute_aggregate(input_result, &group_by, &aggregates, having.as_deref())
+                let schema = self.catalog.get_table(table)?;
+                let columns = Self::column_names(&schema);
+                for (column, value) in assignments {
+                    let idx = Self::column_position(&columns, column)?;
+                    let value = binder.bind_table_expr(&schema, value)?;
+                    binder.infer_parameter_type(
+                        &value,
+                        Some(schema.columns[idx].data_type.clone()),
+                    );
+                }
+                if let Some(filter) = filter {
+                    binder.bind_table_expr(&schema, filter)?;
+                }
+                None
             }
-            PlanNode::Sort { input, order_by } => {
-                let input_result = self.execute_plan(*input)?;
-                self.execute_sort(input_result, &order_by)
+            SqlStatement::Delete { table, filter } => {
+                let schema = self.catalog.get_table(table)?;
+                if let Some(filter) = filter {
+                    binder.bind_table_expr(&schema, filter)?;
+                }
+                None
             }
-            PlanNode::Limit {
-                input,
-                limit,
-                offset,
-            } => {
-                let input_result = self.execute_plan(*input)?;
-                self.execute_limit(input_result, limit, offset)
+            SqlStatement::Union { .. } => {
+                Self::bind_union_queries(&mut binder, &statement)?;
+                None
+            }
+            SqlStatement::ExecProcedure { name, arguments } => {
+                // Parameters take the type of the procedure parameter they
+                // are passed for
+                let procedure = self.catalog.get_procedure(name)?;
+                for (argument, parameter) in arguments.iter().zip(&procedure.parameters) {
+                    let value = binder.bind_constant(argument)?;
+                    binder.infer_parameter_type(&value, Some(parameter.data_type.clone()));
+                }
+                None
+            }
+            // The query is planned again when EXPLAIN runs
+            SqlStatement::Explain { statement, .. } => {
+                Self::bind_union_queries(&mut binder, statement)?;
+                None
+            }
+            SqlStatement::Prepare { .. }
+            | SqlStatement::Execute { .. }
+            | SqlStatement::Deallocate { .. } => {
+                return Err(DbError::InvalidOperation(
+                    "PREPARE, EXECUTE and DEALLOCATE cannot be prepared".to_string(),
+                ))
+            }
+            _ => None,
+        };
+
+        let mut param_types = binder.into_parameter_types();
+        if param_types.len() < declared_types.len() {
+            param_types.resize(declared_types.len(), None);
+        }
+        for (param_type, declared) in param_types.iter_mut().zip(&declared_types) {
+            if declared.is_some() {
+                *param_type = declared.clone();
             }
-            PlanNode::Subquery { plan, .. } => self.execute_plan(*plan),
         }
+
+        Ok(PreparedStatement {
+            statement,
+            declared_types,
+            param_types,
+            plan,
+            catalog_version,
+        })
     }
 
-    fn execute_table_scan(&self, table: &str, columns: &[String]) -> Result<QueryResult, DbError> {
-        let schema = self.catalog.get_table(table)?;
+    /// Run a prepared statement with one value per parameter
+    ///
+    /// A statement prepared before the catalog last changed is bound again
+    /// first, so it never runs against a stale schema.
+    pub fn execute_prepared(
+        &self,
+        prepared: &mut PreparedStatement,
+        params: Vec<Value>,
+    ) -> Result<QueryResult, DbError> {
+        self.revalidate(prepared)?;
+        let params = prepared.coerce_params(params)?;
 
-        let result_columns = if columns.contains(&"*".to_string()) {
-            schema.columns.iter().map(|c| c.name.clone()).collect()
-        } else {
-            columns.to_vec()
+        let Some(plan) = &prepared.plan else {
+            return self.run(prepared.statement.clone(), &params);
         };
-
-        // Return empty result for now - actual data scanning would go here
-        Ok(QueryResult::new(result_columns, Vec::new()))
+        let mut plan = plan.clone();
+        plan.bind_parameters(&params)?;
+        self.statement(|executor| match &prepared.statement {
+            SqlStatement::InsertIntoSelect { table, columns, .. } => {
+                let source = executor.execute_plan(plan)?;
+                executor.insert_query_result(table, columns, source)
+            }
+            _ => executor.execute_plan(plan).and_then(Self::client_result),
+        })
     }
 
-    fn execute_filter(&self, input: QueryResult, predicate: &str) -> Result<QueryResult, DbError> {
-        // Cache the predicate if not already cached (reduces parsing overhead)
-        if !self.is_predicate_cached(predicate) {
-            self.cache_predicate(predicate);
+    /// Bind a prepared statement again if DDL has changed the catalog since
+    /// it was prepared
+    pub fn revalidate(&self, prepared: &mut PreparedStatement) -> Result<(), DbError> {
+        if prepared.catalog_version != self.catalog.version() {
+            *prepared = self.prepare(prepared.statement.clone(), prepared.declared_types.clone())?;
         }
-
-        // FIXED: Use compiled predicate from cache for 10-100x performance improvement
-        let filtered_rows: Vec<Vec<String>> = if let Some(compiled) = self.predicate_cache.read().unwrap().get(predicate) {
-            // Use compiled expression tree (fast path)
-            let expr = compiled.expression.clone();
-            input
-                .rows
-                .into_iter()
-                .filter(|row| self.evaluate_compiled_expression(&expr, &input.columns, row))
-                .collect()
-        } else {
-            // Fallback to runtime parsing (slow path, should rarely happen)
-            input
-                .rows
-                .into_iter()
-                .filter(|row| self.evaluate_predicate(predicate, &input.columns, row))
-                .collect()
-        };
-
-        Ok(QueryResult::new(input.columns, filtered_rows))
+        Ok(())
     }
 
-    /// Evaluate a compiled expression (10-100x faster than runtime parsing)
-    fn evaluate_compiled_expression(&self, expr: &CompiledExpression, columns: &[String], row: &[String]) -> bool {
-        match expr {
-            CompiledExpression::And(left, right) => {
-                self.evaluate_compiled_expression(left, columns, row)
-                    && self.evaluate_compiled_expression(right, columns, row)
-            }
-            CompiledExpression::Or(left, right) => {
-                self.evaluate_compiled_expression(left, columns, row)
-                    || self.evaluate_compiled_expression(right, columns, row)
+    // Bind each query of a UNION for its parameters' types; the parts are
+    // planned again when the statement runs
+    fn bind_union_queries(binder: &mut Binder, statement: &SqlStatement) -> Result<(), DbError> {
+        match statement {
+            SqlStatement::Select { query } => {
+                binder.bind_query(query)?;
             }
-            CompiledExpression::Not(inner) => {
-                !self.evaluate_compiled_expression(inner, columns, row)
+            SqlStatement::Union { left, right, .. } => {
+                Self::bind_union_queries(binder, left)?;
+                Self::bind_union_queries(binder, right)?;
             }
-            CompiledExpression::Equals { column, value } => {
-                let col_val = self.resolve_value(column, columns, row);
-                let comp_val = self.resolve_value(value, columns, row);
-
-                // Try numeric comparison first
-                if let (Ok(l), Ok(r)) = (col_val.parse::<f64>(), comp_val.parse::<f64>()) {
-                    (l - r).abs() < f64::EPSILON
-                } else {
-                    col_val.eq_ignore_ascii_case(&comp_val)
-                }
-            }
-            CompiledExpression::NotEquals { column, value } => {
-                let col_val = self.resolve_value(column, columns, row);
-                let comp_val = self.resolve_value(value, columns, row);
+            _ => {}
+        }
+        Ok(())
+    }
 
-                // Try numeric comparison first
-                if let (Ok(l), Ok(r)) = (col_val.parse::<f64>(), comp_val.parse::<f64>()) {
-                    (l - r).abs() >= f64::EPSILON
-                } else {
-                    !col_val.eq_ignore_ascii_case(&comp_val)
-                }
-            }
-            CompiledExpression::GreaterThan { column, value } => {
-                let col_val = self.resolve_value(column, columns, row);
-                let comp_val = self.resolve_value(value, columns, row);
+    // Execute a query plan node (inline for performance)
+    #[inline]
+    pub fn execute_plan(&self, plan: PlanNode) -> Result<QueryResult, DbError> {
+        self.execute_node(&plan, &[])
+    }
 
-                if let (Ok(l), Ok(r)) = (col_val.parse::<f64>(), comp_val.parse::<f64>()) {
-                    l > r
-                } else {
-                    col_val > comp_val
-                }
-            }
-            CompiledExpression::GreaterThanOrEqual { column, value } => {
-                let col_val = self.resolve_value(column, columns, row);
-                let comp_val = self.resolve_value(value, columns, row);
+    // Execute a plan node; `outer` holds the rows of the enclosing queries of
+    // a subquery, innermost last
+    fn execute_node(&self, plan: &PlanNode, outer: &[Vec<Value>]) -> Result<QueryResult, DbError> {
+        let Some(profile) = &self.profile else {
+            return self.execute_operator(plan, outer);
+        };
+        let (before, started) = (self.table_store.buffer_accesses(), Instant::now());
+        let result = self.execute_operator(plan, outer)?;
+        let (hits, misses) = self.table_store.buffer_accesses();
+        profile.record(
+            plan,
+            result.rows.len(),
+            started.elapsed(),
+            hits.saturating_sub(before.0),
+            misses.saturating_sub(before.1),
+        );
+        Ok(result)
+    }
 
-                if let (Ok(l), Ok(r)) = (col_val.parse::<f64>(), comp_val.parse::<f64>()) {
-                    l >= r
-                } else {
-                    col_val >= comp_val
-                }
+    // Run the operator of a plan node, its inputs through `execute_node`
+    fn execute_operator(
+        &self,
+        plan: &PlanNode,
+        outer: &[Vec<Value>],
+    ) -> Result<QueryResult, DbError> {
+        match plan {
+            PlanNode::TableScan { table, columns } => self.execute_table_scan(table, columns),
+            PlanNode::IndexScan {
+                table,
+                index,
+                bounds,
+                ..
+            } => self.execute_index_scan(table, index, bounds, true, outer),
+            PlanNode::IndexOnlyScan {
+                table,
+                index,
+                bounds,
+                ..
+            } => self.execute_index_scan(table, index, bounds, false, outer),
+            PlanNode::NearestNeighborScan {
+                table,
+                index,
+                query,
+                limit,
+                ..
+            } => self.execute_nearest_scan(table, index, query, *limit, outer),
+            PlanNode::IndexNestedLoopJoin {
+                join_type,
+                left,
+                table,
+                index,
+                bounds,
+                condition,
+                ..
+            } => {
+                let left_result = self.execute_node(left, outer)?;
+                self.execute_index_join(
+                    left_result,
+                    join_type,
+                    table,
+                    index,
+                    bounds,
+                    condition.as_ref(),
+                    outer,
+                )
             }
-            CompiledExpression::LessThan { column, value } => {
-                let col_val = self.resolve_value(column, columns, row);
-                let comp_val = self.resolve_value(value, columns, row);
-
-                if let (Ok(l), Ok(r)) = (col_val.parse::<f64>(), comp_val.parse::<f64>()) {
-                    l < r
-                } else {
-                    col_val < comp_val
-                }
+            PlanNode::Filter { input, predicate } => {
+                let input_result = self.execute_node(input, outer)?;
+                self.execute_filter(input_result, predicate, outer)
             }
-            CompiledExpression::LessThanOrEqual { column, value } => {
-                let col_val = self.resolve_value(column, columns, row);
-                let comp_val = self.resolve_value(value, columns, row);
-
-                if let (Ok(l), Ok(r)) = (col_val.parse::<f64>(), comp_val.parse::<f64>()) {
-                    l <= r
-                } else {
-                    col_val <= comp_val
-                }
+            PlanNode::Project {
+                input,
+                exprs,
+                columns,
+            } => {
+                let input_result = self.execute_node(input, outer)?;
+                self.execute_project(input_result, exprs, columns, outer)
             }
-            CompiledExpression::IsNull { column } => {
-                if let Some(idx) = columns.iter().position(|c| c.eq_ignore_ascii_case(column)) {
-                    row.get(idx).map(|v| v == "NULL" || v.is_empty()).unwrap_or(true)
-                } else {
-                    true
-                }
+            PlanNode::Join {
+                join_type,
+                left,
+                right,
+                condition,
+            } => {
+                let left_result = self.execute_node(left, outer)?;
+                let right_result = self.execute_node(right, outer)?;
+                self.execute_join(
+                    left_result,
+                    right_result,
+                    join_type,
+                    condition.as_ref(),
+                    outer,
+                )
             }
-            CompiledExpression::IsNotNull { column } => {
-                if let Some(idx) = columns.iter().position(|c| c.eq_ignore_ascii_case(column)) {
-                    row.get(idx).map(|v| v != "NULL" && !v.is_empty()).unwrap_or(false)
-                } else {
-                    false
-                }
+            PlanNode::Aggregate {
+                input,
+                group_by,
+                aggregates,
+                having,
+            } => {
+                let input_result = self.execute_node(input, outer)?;
+                self.execute_aggregate(
+                    input_result,
+                    plan.output_columns(),
+                    group_by,
+                    aggregates,
+                    having.as_ref(),
+                    outer,
+                )
             }
-            CompiledExpression::Like { column, pattern } => {
-                if let Some(idx) = columns.iter().position(|c| c.eq_ignore_ascii_case(column)) {
-                    if let Some(value) = row.get(idx) {
-                        return self.match_like_pattern(value, pattern);
-                    }
-                }
-                false
+            PlanNode::Sort { input, order_by } => {
+                let input_result = self.execute_node(input, outer)?;
+                self.execute_sort(input_result, order_by, outer)
             }
-            CompiledExpression::In { column, values } => {
-                if let Some(idx) = columns.iter().position(|c| c.eq_ignore_ascii_case(column)) {
-                    if let Some(row_val) = row.get(idx) {
-                        return values.iter().any(|v| v.eq_ignore_ascii_case(row_val));
-                    }
-                }
-                false
+            PlanNode::Window {
+                input,
+                partition_by,
+                order_by,
+                functions,
+            } => {
+                let input_result = self.execute_node(input, outer)?;
+                self.execute_window(input_result, partition_by, order_by, functions, outer)
             }
-            CompiledExpression::Between { column, low, high } => {
-                if let Some(idx) = columns.iter().position(|c| c.eq_ignore_ascii_case(column)) {
-                    if let Some(value) = row.get(idx) {
-                        return value.as_str() >= low.as_str() && value.as_str() <= high.as_str();
-                    }
-                }
-                false
+            PlanNode::Limit {
+                input,
+                limit,
+                offset,
+            } => {
+                let input_result = self.execute_node(input, outer)?;
+                self.execute_limit(input_result, *limit, *offset)
             }
-            CompiledExpression::Literal(b) => *b,
-        }
-    }
-
-    /// Apply DISTINCT to remove duplicate rows
-    fn apply_distinct(&self, input: QueryResult) -> QueryResult {
-        let mut seen = HashSet::new();
-        let mut unique_rows = Vec::new();
-
-        for row in input.rows {
-            // Create a hash key from the row
-            let row_key = row.join("\0"); // Use null byte as separator
-
-            if seen.insert(row_key) {
-                unique_rows.push(row);
+            PlanNode::Distinct { input } => {
+                let input_result = self.execute_node(input, outer)?;
+                Ok(self.apply_distinct(input_result))
+            }
+            PlanNode::Subquery { plan, .. } => self.execute_node(plan, outer),
+            PlanNode::SetOperation {
+                op,
+                all,
+                left,
+                right,
+            } => {
+                let left_result = self.execute_node(left, outer)?;
+                let right_result = self.execute_node(right, outer)?;
+                Ok(self.execute_set_operation(left_result, right_result, *op, *all))
+            }
+            PlanNode::RecursiveUnion {
+                name,
+                columns,
+                base,
+                recursive,
+                all,
+                search,
+                cycle,
+            } => self.execute_recursive_union(
+                name,
+                columns,
+                base,
+                recursive,
+                *all,
+                search.as_ref(),
+                cycle.as_ref(),
+                outer,
+            ),
+            PlanNode::WorkTableScan {
+                name,
+                columns,
+                types,
+            } => {
+                let rows = self.work_tables.get(name).ok_or_else(|| {
+                    DbError::Internal(format!("Work table {} read outside its query", name))
+                })?;
+                Ok(QueryResult::typed(
+                    columns.clone(),
+                    types.clone(),
+                    rows.to_vec(),
+                ))
+            }
+            PlanNode::Values { columns, rows } => {
+                let types = (0..columns.len())
+                    .map(|i| Self::inferred_type(rows, i))
+                    .collect();
+                Ok(QueryResult::typed(columns.clone(), types, rows.clone()))
             }
         }
-
-        QueryResult::new(input.columns, unique_rows)
     }
 
-    /// Evaluate a predicate expression against a row
-    ///
-    /// PERFORMANCE ISSUE (from diagrams/04_query_processing_flow.md):
-    /// This function parses predicates at RUNTIME for EVERY row, causing O(n*m) complexity.
-    ///
-    /// TODO: Implement precompiled expression tree:
-    /// 1. Create CompiledExpression enum with parsed operators
-    /// 2. Compile predicates once during plan generation
-    /// 3. Store compiled form in PlanNode::Filter
-    /// 4. Use cached compilation from predicate_cache
-    ///
-    /// Expected improvement: 10-100x speedup on filtered queries
-    fn evaluate_predicate(&self, predicate: &str, columns: &[String], row: &[String]) -> bool {
-        let predicate = predicate.trim();
-
-        // Handle AND conditions
-        if let Some(and_pos) = self.find_logical_operator(predicate, " AND ") {
-            let left = &predicate[..and_pos];
-            let right = &predicate[and_pos + 5..];
-            return self.evaluate_predicate(left, columns, row)
-                && self.evaluate_predicate(right, columns, row);
-        }
-
-        // Handle OR conditions
-        if let Some(or_pos) = self.find_logical_operator(predicate, " OR ") {
-            let left = &predicate[..or_pos];
-            let right = &predicate[or_pos + 4..];
-            return self.evaluate_predicate(left, columns, row)
-                || self.evaluate_predicate(right, columns, row);
-        }
-
-        // Handle NOT conditions
-        if predicate.to_uppercase().starts_with("NOT ") {
-            return !self.evaluate_predicate(&predicate[4..], columns, row);
-        }
+    fn execute_table_scan(&self, table: &str, columns: &[String]) -> Result<QueryResult, DbError> {
+        let schema = self.catalog.get_table(table)?;
+        let names = Self::column_names(&schema);
+        let types = Self::column_types(&schema);
+        let rows = self.scan_rows(&schema)?.into_iter().map(|(_, row)| row);
 
-        // Handle parentheses
-        if predicate.starts_with('(') && predicate.ends_with(')') {
-            return self.evaluate_predicate(&predicate[1..predicate.len() - 1], columns, row);
+        if columns.is_empty() || columns.iter().any(|c| c == "*") {
+            return Ok(QueryResult::typed(names, types, rows.collect()));
         }
 
-        // Parse comparison operators
-        self.evaluate_comparison(predicate, columns, row)
+        let positions = columns
+            .iter()
+            .map(|c| Self::column_position(&names, c))
+            .collect::<Result<Vec<_>, DbError>>()?;
+        let rows = rows
+            .map(|row| positions.iter().map(|&i| row[i].clone()).collect())
+            .collect();
+        let types = positions.iter().map(|&i| types[i].clone()).collect();
+        Ok(QueryResult::typed(columns.to_vec(), types, rows))
     }
 
-    /// Find logical operator position, respecting parentheses
-    fn find_logical_operator(&self, expr: &str, op: &str) -> Option<usize> {
-        let mut paren_depth = 0;
-        let upper = expr.to_uppercase();
-        let op_upper = op.to_uppercase();
-
-        for (i, c) in expr.chars().enumerate() {
-            match c {
-                '(' => paren_depth += 1,
-                ')' => paren_depth -= 1,
-                _ => {}
-            }
-            if paren_depth == 0 && upper[i..].starts_with(&op_upper) {
-                return Some(i);
-            }
-        }
-        None
+    // Rows of a table through an index; see `index_rows`
+    fn execute_index_scan(
+        &self,
+        table: &str,
+        index: &str,
+        bounds: &IndexBounds,
+        fetch: bool,
+        outer: &[Vec<Value>],
+    ) -> Result<QueryResult, DbError> {
+        let schema = self.catalog.get_table(table)?;
+        let eval = Evaluator::new(self, outer);
+        let rows = self
+            .index_rows(&schema, index, bounds, fetch, &eval, &[])?
+            .into_iter()
+            .map(|(_, row)| row)
+            .collect();
+        Ok(QueryResult::typed(
+            Self::column_names(&schema),
+            Self::column_types(&schema),
+            rows,
+        ))
     }
 
-    /// Evaluate a single comparison expression
-    fn evaluate_comparison(&self, expr: &str, columns: &[String], row: &[String]) -> bool {
-        // Handle IS NULL / IS NOT NULL
-        let upper = expr.to_uppercase();
-        if upper.contains(" IS NOT NULL") {
-            let col_name = expr.split_whitespace().next().unwrap_or("");
-            if let Some(idx) = columns
-                .iter()
-                .position(|c| c.eq_ignore_ascii_case(col_name))
-            {
-                return row
-                    .get(idx)
-                    .map(|v| v != "NULL" && !v.is_empty())
-                    .unwrap_or(false);
-            }
-            return false;
-        }
-        if upper.contains(" IS NULL") {
-            let col_name = expr.split_whitespace().next().unwrap_or("");
-            if let Some(idx) = columns
-                .iter()
-                .position(|c| c.eq_ignore_ascii_case(col_name))
-            {
-                return row
-                    .get(idx)
-                    .map(|v| v == "NULL" || v.is_empty())
-                    .unwrap_or(true);
-            }
-            return true;
-        }
-
-        // Handle LIKE operator
-        if upper.contains(" LIKE ") {
-            let parts: Vec<&str> = expr
-                .splitn(2, |c: char| c.to_ascii_uppercase() == 'L')
-                .collect();
-            if parts.len() == 2 && parts[1].to_uppercase().starts_with("IKE ") {
-                let col_name = parts[0].trim();
-                let pattern = parts[1][4..].trim().trim_matches('\'');
-                if let Some(idx) = columns
-                    .iter()
-                    .position(|c| c.eq_ignore_ascii_case(col_name))
-                {
-                    if let Some(value) = row.get(idx) {
-                        return self.match_like_pattern(value, pattern);
-                    }
-                }
+    // The rows of a table nearest to a vector through an HNSW index. Every
+    // row is read if the query is NULL or the index finds fewer than `limit`
+    // rows, as the rows it lacks then belong to the result too
+    fn execute_nearest_scan(
+        &self,
+        table: &str,
+        index: &str,
+        query: &ScalarExpr,
+        limit: usize,
+        outer: &[Vec<Value>],
+    ) -> Result<QueryResult, DbError> {
+        let schema = self.catalog.get_table(table)?;
+        let index = self
+            .table_store
+            .index(index)
+            .ok_or_else(|| DbError::Execution(format!("Index {} not found", index)))?;
+        let query = Evaluator::new(self, outer).eval(query, &[])?;
+        let query = match index.types().first() {
+            Some(data_type) => data_type.coerce(query)?,
+            None => query,
+        };
+        let mut rows = match &query {
+            Value::Vector(vector) => {
+                let ef = self.ef_search.unwrap_or(DEFAULT_EF_SEARCH).max(limit);
+                self.found_rows(&schema, &index, true, || index.nearest(vector, limit, ef))?
             }
-            return false;
+            _ => Vec::new(),
+        };
+        if rows.len() < limit {
+            rows = self.scan_rows(&schema)?;
         }
+        Ok(QueryResult::typed(
+            Self::column_names(&schema),
+            Self::column_types(&schema),
+            rows.into_iter().map(|(_, row)| row).collect(),
+        ))
+    }
 
-        // Handle IN operator
-        if upper.contains(" IN (") {
-            if let Some(in_pos) = upper.find(" IN (") {
-                let col_name = expr[..in_pos].trim();
-                let values_str = &expr[in_pos + 5..];
-                if let Some(end_paren) = values_str.find(')') {
-                    let values: Vec<&str> = values_str[..end_paren]
-                        .split(',')
-                        .map(|v| v.trim().trim_matches('\''))
-                        .collect();
-                    if let Some(idx) = columns
-                        .iter()
-                        .position(|c| c.eq_ignore_ascii_case(col_name))
-                    {
-                        if let Some(row_val) = row.get(idx) {
-                            return values.iter().any(|v| v.eq_ignore_ascii_case(row_val));
-                        }
-                    }
+    // Join each left row with the rows of `table` an index lookup finds;
+    // the bounds are evaluated against the left row
+    #[allow(clippy::too_many_arguments)]
+    fn execute_index_join(
+        &self,
+        left: QueryResult,
+        join_type: &JoinType,
+        table: &str,
+        index: &str,
+        bounds: &IndexBounds,
+        condition: Option<&ScalarExpr>,
+        outer: &[Vec<Value>],
+    ) -> Result<QueryResult, DbError> {
+        let schema = self.catalog.get_table(table)?;
+        let mut columns = left.columns.clone();
+        columns.extend(Self::column_names(&schema));
+        let mut types = left.column_types.clone();
+        types.extend(Self::column_types(&schema));
+
+        let eval = Evaluator::new(self, outer);
+        let mut rows = Vec::new();
+        for left_row in &left.rows {
+            let mut matched = false;
+            for (_, right_row) in self.index_rows(&schema, index, bounds, true, &eval, left_row)? {
+                let mut row = left_row.clone();
+                row.extend(right_row);
+                if eval.qualifies(condition, &row)? {
+                    rows.push(row);
+                    matched = true;
                 }
             }
-            return false;
-        }
-
-        // Handle BETWEEN operator
-        if upper.contains(" BETWEEN ") && upper.contains(" AND ") {
-            if let Some(between_pos) = upper.find(" BETWEEN ") {
-                let col_name = expr[..between_pos].trim();
-                let rest = &expr[between_pos + 9..];
-                if let Some(and_pos) = rest.to_uppercase().find(" AND ") {
-                    let low = rest[..and_pos].trim().trim_matches('\'');
-                    let high = rest[and_pos + 5..].trim().trim_matches('\'');
-                    if let Some(idx) = columns
-                        .iter()
-                        .position(|c| c.eq_ignore_ascii_case(col_name))
-                    {
-                        if let Some(value) = row.get(idx) {
-                            return value.as_str() >= low && value.as_str() <= high;
-                        }
-                    }
-                }
+            // Left joins pad unmatched rows with NULLs
+            if !matched && *join_type == JoinType::Left {
+                let mut row = left_row.clone();
+                row.resize(left_row.len() + schema.columns.len(), Value::Null);
+                rows.push(row);
             }
-            return false;
         }
+        Ok(QueryResult::typed(columns, types, rows))
+    }
 
-        // Handle standard comparison operators: >=, <=, <>, !=, =, >, <
-        let operators = [
-            (">=", "ge"),
-            ("<=", "le"),
-            ("<>", "ne"),
-            ("!=", "ne"),
-            ("=", "eq"),
-            (">", "gt"),
-            ("<", "lt"),
-        ];
+    // Bind, optimize and run a query
+    fn execute_select(
+        &self,
+        stmt: &SqlStatement,
+        params: &[Value],
+    ) -> Result<QueryResult, DbError> {
+        let mut plan = self.plan_query(stmt)?;
+        plan.bind_parameters(params)?;
+        self.execute_plan(plan)
+    }
 
-        for (op, op_type) in operators {
-            if let Some(pos) = expr.find(op) {
-                let left = expr[..pos].trim();
-                let right = expr[pos + op.len()..].trim().trim_matches('\'');
-
-                let left_value = self.resolve_value(left, columns, row);
-                let right_value = self.resolve_value(right, columns, row);
-
-                // Try numeric comparison first
-                if let (Ok(l), Ok(r)) = (left_value.parse::<f64>(), right_value.parse::<f64>()) {
-                    return match op_type {
-                        "eq" => (l - r).abs() < f64::EPSILON,
-                        "ne" => (l - r).abs() >= f64::EPSILON,
-                        "gt" => l > r,
-                        "ge" => l >= r,
-                        "lt" => l < r,
-                        "le" => l <= r,
-                        _ => false,
-                    };
-                }
+    /// The plan a query runs with, one operator per line
+    pub fn explain(&self, stmt: &SqlStatement) -> Result<String, DbError> {
+        Ok(self.plan_query(stmt)?.explain())
+    }
 
-                // Fall back to string comparison
-                return match op_type {
-                    "eq" => left_value.eq_ignore_ascii_case(&right_value),
-                    "ne" => !left_value.eq_ignore_ascii_case(&right_value),
-                    "gt" => left_value > right_value,
-                    "ge" => left_value >= right_value,
-                    "lt" => left_value < right_value,
-                    "le" => left_value <= right_value,
-                    _ => false,
-                };
-            }
+    // EXPLAIN shows the plan a query would run with. EXPLAIN ANALYZE runs
+    // it, discarding its rows, and adds what each plan node did
+    fn execute_explain(
+        &self,
+        stmt: &SqlStatement,
+        analyze: bool,
+        format: ExplainFormat,
+        params: &[Value],
+    ) -> Result<QueryResult, DbError> {
+        let started = Instant::now();
+        let mut plan = self.plan_query(stmt)?;
+        if !params.is_empty() {
+            plan.bind_parameters(params)?;
         }
-
-        // If no operator found, treat as boolean column
-        if let Some(idx) = columns.iter().position(|c| c.eq_ignore_ascii_case(expr)) {
-            if let Some(value) = row.get(idx) {
-                return value == "1" || value.eq_ignore_ascii_case("true");
-            }
+        let mut explanation = Explanation::new(&plan, &self.optimizer);
+        if analyze {
+            let planning_time = started.elapsed();
+            let profile = Arc::new(PlanProfile::new());
+            let executor = Self {
+                profile: Some(profile.clone()),
+                ..self.clone()
+            };
+            let started = Instant::now();
+            executor.execute_node(&plan, &[])?;
+            explanation = explanation.analyzed(profile, planning_time, started.elapsed());
         }
 
-        false
+        let lines = match format {
+            ExplainFormat::Text => explanation.text(),
+            ExplainFormat::Json => vec![format!("{:#}", explanation.json())],
+        };
+        let rows = lines.into_iter().map(|line| vec![Value::String(line)]);
+        Ok(QueryResult::typed(
+            vec!["QUERY PLAN".to_string()],
+            vec![DataType::Text],
+            rows.collect(),
+        ))
     }
 
-    /// Resolve a value - either a column reference or a literal
-    fn resolve_value(&self, expr: &str, columns: &[String], row: &[String]) -> String {
-        let expr = expr.trim().trim_matches('\'');
-
-        // Check if it's a column reference
-        if let Some(idx) = columns.iter().position(|c| c.eq_ignore_ascii_case(expr)) {
-            return row.get(idx).cloned().unwrap_or_default();
-        }
-
-        // Handle table.column format
-        if let Some(dot_pos) = expr.find('.') {
-            let col_name = &expr[dot_pos + 1..];
-            if let Some(idx) = columns
-                .iter()
-                .position(|c| c.eq_ignore_ascii_case(col_name) || c.eq_ignore_ascii_case(expr))
-            {
-                return row.get(idx).cloned().unwrap_or_default();
-            }
-        }
+    fn plan_query(&self, stmt: &SqlStatement) -> Result<PlanNode, DbError> {
+        let plan = Planner::new(self.catalog.clone()).plan(stmt)?;
+        let plan = self.optimizer.optimize(plan)?;
+        Ok(self.choose_access_paths(plan))
+    }
 
-        // It's a literal value
-        expr.to_string()
+    // Read tables through their indexes where that helps. Applied to every
+    // optimized plan rather than by the optimizer, which caches its plans
+    // while indexes come and go
+    fn choose_access_paths(&self, plan: PlanNode) -> PlanNode {
+        AccessPathSelector::new(&self.catalog, &self.table_store).select(plan)
     }
 
-    /// Match a SQL LIKE pattern (supports % and _ wildcards)
-    fn match_like_pattern(&self, value: &str, pattern: &str) -> bool {
-        let regex_pattern = pattern.replace('%', ".*").replace('_', ".");
+    // Bind a DML WHERE clause against the rows of `schema`
+    fn bind_filter(
+        &self,
+        schema: &Schema,
+        filter: Option<&sqlparser::ast::Expr>,
+        params: &[Value],
+    ) -> Result<Option<ScalarExpr>, DbError> {
+        filter
+            .map(|expr| {
+                let mut predicate = Binder::new(&self.catalog).bind_table_expr(schema, expr)?;
+                predicate.bind_parameters(params)?;
+                Ok(predicate)
+            })
+            .transpose()
+    }
 
-        if let Ok(re) = regex::Regex::new(&format!("^(?i){}$", regex_pattern)) {
-            re.is_match(value)
-        } else {
-            // Fallback to simple contains check
-            let simple_pattern = pattern.replace('%', "").replace('_', "");
-            value
-                .to_lowercase()
-                .contains(&simple_pattern.to_lowercase())
-        }
+    // Evaluate the rows of INSERT ... VALUES
+    fn evaluate_values(
+        &self,
+        rows: &[Vec<sqlparser::ast::Expr>],
+        params: &[Value],
+    ) -> Result<Vec<Vec<Value>>, DbError> {
+        let mut binder = Binder::new(&self.catalog);
+        let eval = Evaluator::new(self, &[]);
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|expr| {
+                        let mut value = binder.bind_constant(expr)?;
+                        value.bind_parameters(params)?;
+                        eval.eval(&value, &[])
+                    })
+                    .collect()
+            })
+            .collect()
     }
 
-    fn execute_project(
+    fn insert_query_result(
         &self,
-        input: QueryResult,
+        table: &str,
         columns: &[String],
+        source: QueryResult,
     ) -> Result<QueryResult, DbError> {
-        // Project only the specified columns
-        if columns.contains(&"*".to_string()) {
-            return Ok(input);
+        if self.is_view(table) {
+            return self.insert_into_view(table, columns, source.rows);
         }
+        let schema = self.catalog.get_table(table)?;
+        let inserted = self.insert_rows(self.txn()?, &schema, columns, source.rows)?;
+        self.record_modified(table, inserted)?;
+        Ok(QueryResult::with_affected(inserted))
+    }
 
-        // Find column indices for projection
-        let column_indices: Vec<Option<usize>> = columns
+    // Bind the SET list of an UPDATE to schema positions and expressions
+    // over the old row
+    fn bind_assignments(
+        &self,
+        schema: &Schema,
+        assignments: &[(String, sqlparser::ast::Expr)],
+        params: &[Value],
+    ) -> Result<Vec<(usize, ScalarExpr)>, DbError> {
+        let columns = Self::column_names(schema);
+        let mut binder = Binder::new(&self.catalog);
+        assignments
             .iter()
-            .map(|col| {
-                // Handle table.column format
-                let col_name = if let Some(dot_pos) = col.find('.') {
-                    &col[dot_pos + 1..]
-                } else {
-                    col.as_str()
-                };
+            .map(|(column, value)| {
+                let mut value = binder.bind_table_expr(schema, value)?;
+                value.bind_parameters(params)?;
+                Ok((Self::column_position(&columns, column)?, value))
+            })
+            .collect()
+    }
 
-                input
-                    .columns
-                    .iter()
-                    .position(|c| c.eq_ignore_ascii_case(col_name) || c.eq_ignore_ascii_case(col))
+    fn is_view(&self, name: &str) -> bool {
+        self.catalog.get_view(name).is_ok()
+    }
+
+    // The columns and rows of a view, as a table its INSTEAD OF triggers
+    // see. Its columns take their types from the rows, so are nullable and
+    // have no defaults
+    fn view_rows(&self, name: &str) -> Result<(Schema, Vec<Vec<Value>>), DbError> {
+        let select = SqlParser::new()
+            .parse(&format!("SELECT * FROM {}", name))?
+            .remove(0);
+        let result = self.execute_select(&select, &[])?;
+        let columns = result
+            .columns
+            .into_iter()
+            .zip(result.column_types)
+            .map(|(name, data_type)| Column {
+                name,
+                data_type,
+                nullable: true,
+                default: None,
             })
             .collect();
+        Ok((Schema::new(name.to_string(), columns), result.rows))
+    }
 
-        // Project each row
-        let projected_rows: Vec<Vec<String>> = input
-            .rows
+    fn insert_into_view(
+        &self,
+        name: &str,
+        columns: &[String],
+        values: Vec<Vec<Value>>,
+    ) -> Result<QueryResult, DbError> {
+        let (schema, _) = self.view_rows(name)?;
+        let targets = Self::insert_targets(&schema, columns)?;
+        let rows = values
             .into_iter()
-            .map(|row| {
-                column_indices
-                    .iter()
-                    .map(|idx| {
-                        idx.and_then(|i| row.get(i).cloned())
-                            .unwrap_or_else(|| "NULL".to_string())
-                    })
-                    .collect()
+            .map(|value_row| {
+                Self::check_insert_width(columns, &targets, &value_row)?;
+                let mut row = vec![Value::Null; schema.columns.len()];
+                for (&idx, value) in targets.iter().zip(value_row) {
+                    row[idx] = value;
+                }
+                Ok((None, Some(row)))
             })
+            .collect::<Result<Vec<_>, DbError>>()?;
+        self.write_view(&schema, TriggerEvent::Insert, rows)
+    }
+
+    // UPDATE or DELETE the rows of a view that match `filter`
+    fn write_view_rows(
+        &self,
+        name: &str,
+        event: TriggerEvent,
+        assignments: &[(String, sqlparser::ast::Expr)],
+        filter: Option<&sqlparser::ast::Expr>,
+        params: &[Value],
+    ) -> Result<QueryResult, DbError> {
+        let (schema, rows) = self.view_rows(name)?;
+        let targets = self.bind_assignments(&schema, assignments, params)?;
+        let predicate = self.bind_filter(&schema, filter, params)?;
+        let eval = Evaluator::new(self, &[]);
+        let mut changes = Vec::new();
+        for row in rows {
+            if !eval.qualifies(predicate.as_ref(), &row)? {
+                continue;
+            }
+            let new_row = match event {
+                TriggerEvent::Delete => None,
+                _ => {
+                    let mut new_row = row.clone();
+                    for (idx, value) in &targets {
+                        new_row[*idx] = eval.eval(value, &row)?;
+                    }
+                    Some(new_row)
+                }
+            };
+            changes.push((Some(row), new_row));
+        }
+        self.write_view(&schema, event, changes)
+    }
+
+    // Run the INSTEAD OF triggers of a view once for each (OLD, NEW) row of
+    // a write, in place of the write
+    fn write_view(
+        &self,
+        schema: &Schema,
+        event: TriggerEvent,
+        rows: Vec<(Option<Vec<Value>>, Option<Vec<Value>>)>,
+    ) -> Result<QueryResult, DbError> {
+        let triggers = self.triggers(schema, event)?;
+        if !triggers.has(TriggerTiming::InsteadOf, TriggerLevel::Row) {
+            let event = format!("{:?}", event).to_uppercase();
+            return Err(DbError::InvalidOperation(format!(
+                "View {} has no INSTEAD OF {} trigger",
+                schema.name, event
+            )));
+        }
+        for (old, new) in &rows {
+            triggers.fire_row(TriggerTiming::InsteadOf, old.as_deref(), new.as_deref())?;
+        }
+        Ok(QueryResult::with_affected(rows.len()))
+    }
+
+    // The triggers on `schema` that fire on `event`, run by an executor one
+    // trigger level deeper than this one
+    fn triggers(&self, schema: &Schema, event: TriggerEvent) -> Result<TableTriggers, DbError> {
+        let triggers = self.catalog.table_triggers(&schema.name);
+        let fires = triggers
+            .iter()
+            .any(|trigger| trigger.enabled && trigger.events.contains(&event));
+        if fires && self.trigger_depth >= MAX_TRIGGER_DEPTH {
+            return Err(DbError::LimitExceeded(format!(
+                "Triggers nested more than {} levels deep",
+                MAX_TRIGGER_DEPTH
+            )));
+        }
+        let executor = Self {
+            trigger_depth: self.trigger_depth + 1,
+            ..self.clone()
+        };
+        TableTriggers::new(executor, schema, event, triggers)
+    }
+
+    // Gather the statistics of a table and store them in the catalog
+    fn analyze_table(&self, table: &str) -> Result<(), DbError> {
+        let schema = self.catalog.get_table(table)?;
+        let rows: Vec<Vec<Value>> = self
+            .scan_rows(&schema)?
+            .into_iter()
+            .map(|(_, row)| row)
             .collect();
+        self.catalog
+            .set_statistics(analyze::table_stats(&schema, &rows)?)
+    }
 
-        Ok(QueryResult::new(columns.to_vec(), projected_rows))
+    // Count rows a statement wrote, analyzing the table once enough have
+    // changed since it was last analyzed. Statistics are not transactional:
+    // writes that are later rolled back count too
+    fn record_modified(&self, table: &str, rows: usize) -> Result<(), DbError> {
+        if rows == 0 {
+            return Ok(());
+        }
+        let modified = self.catalog.record_modified_rows(table, rows as u64);
+        let analyzed = self.catalog.get_statistics(table);
+        if analyze::needs_analyze(modified, analyzed.as_deref()) {
+            self.analyze_table(table)?;
+        }
+        Ok(())
     }
 
-    /// Execute a join operation
-    ///
-    /// PERFORMANCE ISSUE (from diagrams/04_query_processing_flow.md):
-    /// Currently only implements nested loop join (O(n*m) complexity).
-    ///
-    /// INTEGRATION AVAILABLE:
+    // Type of a result column known only from its values
+    fn inferred_type(rows: &[Vec<Value>], column: usize) -> DataType {
+        rows.iter()
+            .find_map(|row| row.get(column).and_then(DataType::of_value))
+            .unwrap_or(DataType::Text)
+    }
+
+    /// Rows of a table as the current transaction sees them, padded to the
+    /// width of its current schema
+    fn scan_rows(&self, schema: &Schema) -> Result<Vec<(RowRef, Vec<Value>)>, DbError> {
+        let width = schema.columns.len();
+        let mut rows = match &self.transaction {
+            Some(txn) => txn.scan(&schema.name)?,
+            // A plan run on its own reads the latest commit
+            None => {
+                let versions = self.table_store.versions();
+                versions
+                    .scan(&self.table_store, &schema.name, versions.now())?
+                    .into_iter()
+                    .map(|(rid, row)| (RowRef::Stored(rid), row))
+                    .collect()
+            }
+        };
+        for (_, row) in &mut rows {
+            row.resize(width, Value::Null);
+        }
+        Ok(rows)
+    }
+
+    /// Rows of a table an index lookup finds, as the current transaction
+    /// sees them, padded to the width of its current schema
+    ///
+    /// The bounds are evaluated against `row`. Rows outside them may be
+    /// returned too. Without `fetch`, rows unchanged since the index was
+    /// written have only their key columns set.
+    fn index_rows(
+        &self,
+        schema: &Schema,
+        index: &str,
+        bounds: &IndexBounds,
+        fetch: bool,
+        eval: &Evaluator,
+        row: &[Value],
+    ) -> Result<Vec<(RowRef, Vec<Value>)>, DbError> {
+        let index = self
+            .table_store
+            .index(index)
+            .ok_or_else(|| DbError::Execution(format!("Index {} not found", index)))?;
+        let Some(lookup) = IndexLookup::evaluate(&index, bounds, eval, row)? else {
+            // A value the index cannot compare with its keys
+            return self.scan_rows(schema);
+        };
+        let search = || {
+            let mut entries = Vec::new();
+            for prefix in &lookup.prefixes {
+                entries.extend(index.lookup(
+                    prefix,
+                    lookup.lower.as_ref(),
+                    lookup.upper.as_ref(),
+                )?);
+            }
+            // IN lists may repeat a value
+            entries.sort_by_key(|(_, rid)| rid.to_u64());
+            entries.dedup_by_key(|(_, rid)| rid.to_u64());
+            Ok(entries)
+        };
+        self.found_rows(schema, &index, fetch, search)
+    }
+
+    // The rows behind the index entries `search` finds, as the current
+    // transaction sees them; see `index_rows`
+    fn found_rows(
+        &self,
+        schema: &Schema,
+        index: &TableIndex,
+        fetch: bool,
+        search: impl FnOnce() -> Result<Vec<(IndexKey, RowId)>, DbError>,
+    ) -> Result<Vec<(RowRef, Vec<Value>)>, DbError> {
+        let found = match &self.transaction {
+            Some(txn) => txn.index_scan(&schema.name, fetch, search)?,
+            None => {
+                let versions = self.table_store.versions();
+                versions
+                    .index_scan(
+                        &self.table_store,
+                        &schema.name,
+                        versions.now(),
+                        fetch,
+                        search,
+                    )?
+                    .into_iter()
+                    .map(|(rid, row)| (RowRef::Stored(rid), row))
+                    .collect()
+            }
+        };
+
+        let width = schema.columns.len();
+        let rows = found.into_iter().map(|(target, found)| {
+            let mut row = match found {
+                IndexedRow::Current { row: Some(row), .. } | IndexedRow::Changed(row) => row,
+                IndexedRow::Current { key, row: None } => {
+                    let mut row = vec![Value::Null; width];
+                    for (&i, value) in index.positions().iter().zip(index.key_values(&key)) {
+                        if let Some(slot) = row.get_mut(i) {
+                            *slot = value;
+                        }
+                    }
+                    row
+                }
+            };
+            row.resize(width, Value::Null);
+            (target, row)
+        });
+        Ok(rows.collect())
+    }
+
+    // Fail before writing anything if `rows`, given as the row each one
+    // replaces with its old image and the new image, would duplicate a key
+    // of a unique index. The commit checks again against the rows other
+    // transactions have committed in the meantime
+    fn check_unique(
+        &self,
+        schema: &Schema,
+        rows: &[(Option<RowRef>, Option<&[Value]>, &[Value])],
+    ) -> Result<(), DbError> {
+        let indexes: Vec<_> = self
+            .table_store
+            .table_indexes(&schema.name)
+            .into_iter()
+            .filter(|index| index.is_unique())
+            .collect();
+        if indexes.is_empty() {
+            return Ok(());
+        }
+        let txn = self.txn()?;
+        let replaced: HashSet<RowRef> = rows.iter().filter_map(|(target, ..)| *target).collect();
+        for index in &indexes {
+            let mut seen = HashSet::new();
+            for (_, old, new) in rows {
+                let Some(key) = index.key(new)? else {
+                    continue;
+                };
+                if has_null(&key) {
+                    continue;
+                }
+                if !seen.insert(key.clone()) {
+                    return Err(index.duplicate(&key));
+                }
+                // A row keeping its key cannot clash with the rows not written
+                if let Some(old) = old {
+                    if index.key(old)?.as_ref() == Some(&key) {
+                        continue;
+                    }
+                }
+                let prefix = match &key {
+                    IndexKey::Composite(keys) => keys.clone(),
+                    key => vec![key.clone()],
+                };
+                let found = txn.index_scan(&schema.name, false, || {
+                    index.lookup(&prefix, Bound::Unbounded, Bound::Unbounded)
+                })?;
+                for (target, found) in found {
+                    if replaced.contains(&target) {
+                        continue;
+                    }
+                    let duplicate = match found {
+                        IndexedRow::Current { .. } => true,
+                        IndexedRow::Changed(row) => index.key(&row)?.as_ref() == Some(&key),
+                    };
+                    if duplicate {
+                        return Err(index.duplicate(&key));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn column_names(schema: &Schema) -> Vec<String> {
+        schema.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    fn column_types(schema: &Schema) -> Vec<DataType> {
+        schema.columns.iter().map(|c| c.data_type.clone()).collect()
+    }
+
+    fn column_position(columns: &[String], name: &str) -> Result<usize, DbError> {
+        columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(name))
+            .ok_or_else(|| DbError::Execution(format!("Column {} not found", name)))
+    }
+
+    // Vector columns are only indexed by HNSW indexes, which take a single
+    // vector column and allow duplicates
+    fn check_index_columns(
+        schema: &Schema,
+        columns: &[String],
+        unique: bool,
+        hnsw: bool,
+    ) -> Result<(), DbError> {
+        let is_vector = |name: &String| {
+            schema.columns.iter().any(|c| {
+                c.name.eq_ignore_ascii_case(name) && matches!(c.data_type, DataType::Vector(_))
+            })
+        };
+        if !hnsw {
+            return match columns.iter().find(|c| is_vector(c)) {
+                Some(column) => Err(DbError::InvalidInput(format!(
+                    "Vector column {} needs an hnsw index",
+                    column
+                ))),
+                None => Ok(()),
+            };
+        }
+        if unique {
+            return Err(DbError::InvalidInput(
+                "An hnsw index cannot be unique".to_string(),
+            ));
+        }
+        match columns {
+            [column] if is_vector(column) => Ok(()),
+            _ => Err(DbError::InvalidInput(
+                "An hnsw index must be on a single VECTOR column".to_string(),
+            )),
+        }
+    }
+
+    // Row as the column -> text map the constraint manager works with
+    fn row_map(columns: &[String], row: &[Value]) -> HashMap<String, String> {
+        columns
+            .iter()
+            .cloned()
+            .zip(row.iter().map(Value::to_display_string))
+            .collect()
+    }
+
+    // A column's default value, or NULL
+    fn default_value(column: &crate::catalog::Column) -> Result<Value, DbError> {
+        match &column.default {
+            Some(default) => column.data_type.coerce(Value::from_sql_literal(default)),
+            None => Ok(Value::Null),
+        }
+    }
+
+
+    // Enforce NOT NULL and the constraint manager's rules on a full row
+    fn validate_row(&self, schema: &Schema, row: &[Value]) -> Result<(), DbError> {
+        for (column, value) in schema.columns.iter().zip(row) {
+            if !column.nullable && value.is_null() {
+                return Err(DbError::ConstraintViolation(format!(
+                    "Column {} of table {} cannot be NULL",
+                    column.name, schema.name
+                )));
+            }
+        }
+
+        let row_map = Self::row_map(&Self::column_names(schema), row);
+        self.constraint_manager
+            .validate_foreign_key(&schema.name, &row_map)?;
+        self.constraint_manager
+            .validate_unique(&schema.name, &row_map)?;
+        self.constraint_manager
+            .validate_check(&schema.name, &row_map)?;
+        Ok(())
+    }
+
+    // Schema positions an INSERT's values go to; all columns when none are named
+    fn insert_targets(schema: &Schema, columns: &[String]) -> Result<Vec<usize>, DbError> {
+        if columns.is_empty() {
+            return Ok((0..schema.columns.len()).collect());
+        }
+        let schema_columns = Self::column_names(schema);
+        columns
+            .iter()
+            .map(|c| Self::column_position(&schema_columns, c))
+            .collect()
+    }
+
+    // Map INSERT values onto the schema (filling defaults), validate and store them
+    fn insert_rows(
+        &self,
+        txn: &SqlTransaction,
+        schema: &Schema,
+        columns: &[String],
+        values: Vec<Vec<Value>>,
+    ) -> Result<usize, DbError> {
+        let targets = Self::insert_targets(schema, columns)?;
+        let triggers = self.triggers(schema, TriggerEvent::Insert)?;
+        triggers.fire_statement(TriggerTiming::Before)?;
+
+        let mut rows = Vec::with_capacity(values.len());
+        for value_row in values {
+            Self::check_insert_width(columns, &targets, &value_row)?;
+
+            let mut row: Vec<Value> = schema
+                .columns
+                .iter()
+                .map(Self::default_value)
+                .collect::<Result<_, _>>()?;
+            for (&idx, value) in targets.iter().zip(value_row) {
+                row[idx] = schema.columns[idx].data_type.coerce(value)?;
+            }
+
+            let row = triggers.before_row(None, row)?;
+            self.validate_row(schema, &row)?;
+            rows.push(row);
+        }
+
+        // Validate everything before writing so a bad row does not leave a partial insert
+        let new: Vec<_> = rows
+            .iter()
+            .map(|row| (None, None, row.as_slice()))
+            .collect();
+        self.check_unique(schema, &new)?;
+        for row in &rows {
+            txn.insert_row(&schema.name, row)?;
+        }
+        for row in &rows {
+            triggers.fire_row(TriggerTiming::After, None, Some(row))?;
+        }
+        triggers.fire_statement(TriggerTiming::After)?;
+        Ok(rows.len())
+    }
+
+    // INSERT may name fewer values than columns only when it names no columns
+    fn check_insert_width(
+        columns: &[String],
+        targets: &[usize],
+        values: &[Value],
+    ) -> Result<(), DbError> {
+        if values.len() > targets.len() || (!columns.is_empty() && values.len() != targets.len()) {
+            return Err(DbError::Execution(format!(
+                "INSERT has {} values but {} target columns",
+                values.len(),
+                targets.len()
+            )));
+        }
+        Ok(())
+    }
+
+
+    fn update_rows(
+        &self,
+        txn: &SqlTransaction,
+        schema: &Schema,
+        assignments: &[(usize, ScalarExpr)],
+        predicate: Option<&ScalarExpr>,
+    ) -> Result<usize, DbError> {
+        let triggers = self.triggers(schema, TriggerEvent::Update)?;
+        triggers.fire_statement(TriggerTiming::Before)?;
+        let eval = Evaluator::new(self, &[]);
+        let mut updates = Vec::new();
+        for (rid, row) in self.scan_rows(schema)? {
+            if !eval.qualifies(predicate, &row)? {
+                continue;
+            }
+            // Every assignment sees the old row
+            let mut new_row = row.clone();
+            for (idx, value) in assignments {
+                let value = eval.eval(value, &row)?;
+                new_row[*idx] = schema.columns[*idx].data_type.coerce(value)?;
+            }
+            let new_row = triggers.before_row(Some(&row), new_row)?;
+            self.validate_row(schema, &new_row)?;
+            updates.push((rid, row, new_row));
+        }
+
+        let changed: Vec<_> = updates
+            .iter()
+            .map(|(rid, old, new)| (Some(*rid), Some(old.as_slice()), new.as_slice()))
+            .collect();
+        self.check_unique(schema, &changed)?;
+        for (rid, _, row) in &updates {
+            txn.update_row(&schema.name, *rid, row)?;
+        }
+        for (_, old, new) in &updates {
+            triggers.fire_row(TriggerTiming::After, Some(old), Some(new))?;
+        }
+        triggers.fire_statement(TriggerTiming::After)?;
+        Ok(updates.len())
+    }
+
+    fn delete_rows(
+        &self,
+        txn: &SqlTransaction,
+        schema: &Schema,
+        predicate: Option<&ScalarExpr>,
+        depth: usize,
+    ) -> Result<usize, DbError> {
+        if depth > MAX_CASCADE_DEPTH {
+            return Err(DbError::LimitExceeded(format!(
+                "Cascading delete exceeded maximum depth of {}",
+                MAX_CASCADE_DEPTH
+            )));
+        }
+
+        let table = &schema.name;
+        let columns = Self::column_names(schema);
+        let triggers = self.triggers(schema, TriggerEvent::Delete)?;
+        triggers.fire_statement(TriggerTiming::Before)?;
+        let eval = Evaluator::new(self, &[]);
+        let mut deleted = Vec::new();
+        for (rid, row) in self.scan_rows(schema)? {
+            if !eval.qualifies(predicate, &row)? {
+                continue;
+            }
+            triggers.fire_row(TriggerTiming::Before, Some(&row), None)?;
+
+            // Remove the row before cascading so self-references terminate
+            if !txn.delete_row(table, rid)? {
+                continue;
+            }
+
+            let row_map = Self::row_map(&columns, &row);
+            let cascade_actions = self
+                .constraint_manager
+                .cascade_operation(table, "DELETE", &row_map)?;
+            for action in cascade_actions {
+                match action {
+                    CascadeAction::Delete { table, column, key } => {
+                        let schema = self.catalog.get_table(&table)?;
+                        let predicate = Self::key_predicate(&schema, &column, key)?;
+                        self.delete_rows(txn, &schema, Some(&predicate), depth + 1)?;
+                    }
+                    CascadeAction::Update {
+                        table,
+                        column,
+                        key,
+                        value,
+                    } => {
+                        let schema = self.catalog.get_table(&table)?;
+                        let idx = Self::column_position(&Self::column_names(&schema), &column)?;
+                        let value = value.map(Value::String).unwrap_or(Value::Null);
+                        let predicate = Self::key_predicate(&schema, &column, key)?;
+                        self.update_rows(
+                            txn,
+                            &schema,
+                            &[(idx, ScalarExpr::Literal(value))],
+                            Some(&predicate),
+                        )?;
+                    }
+                }
+            }
+            deleted.push(row);
+        }
+
+        for row in &deleted {
+            triggers.fire_row(TriggerTiming::After, Some(row), None)?;
+        }
+        triggers.fire_statement(TriggerTiming::After)?;
+        Ok(deleted.len())
+    }
+
+    // `column = key` for a foreign key cascade; the key text is compared as
+    // a literal of the column's type
+    fn key_predicate(schema: &Schema, column: &str, key: String) -> Result<ScalarExpr, DbError> {
+        let idx = Self::column_position(&Self::column_names(schema), column)?;
+        Ok(ScalarExpr::binary(
+            ScalarExpr::column(idx, column),
+            BinaryOperator::Equal,
+            ScalarExpr::Literal(Value::String(key)),
+        ))
+    }
+
+    // Rewrite every stored row of a table after a column change; nothing is
+    // written unless every row can be rewritten. Like all DDL this commits at
+    // once, in a transaction of its own.
+    fn rewrite_rows(
+        &self,
+        schema: &Schema,
+        rewrite: impl Fn(Vec<Value>) -> Result<Vec<Value>, DbError>,
+    ) -> Result<(), DbError> {
+        let txn = self.begin_transaction(None)?;
+        for (target, mut row) in txn.scan(&schema.name)? {
+            row.resize(schema.columns.len(), Value::Null);
+            txn.update_row(&schema.name, target, &rewrite(row)?)?;
+        }
+        txn.commit()
+    }
+
+
+    // Change the columns of a table with its indexes detached, then build
+    // them again over the rewritten rows. An index that cannot be built for
+    // the new columns, e.g. because one of them was dropped, is dropped
+    fn with_indexes_rebuilt(
+        &self,
+        schema: &Schema,
+        alter: impl FnOnce() -> Result<(), DbError>,
+    ) -> Result<(), DbError> {
+        let indexes = self.table_store.detach_indexes(&schema.name)?;
+        if let Err(e) = alter() {
+            for index in indexes {
+                let name = index.name.clone();
+                if let Err(undo) = self.table_store.create_index(schema, index) {
+                    tracing::error!("Failed to rebuild index {}: {}", name, undo);
+                }
+            }
+            return Err(e);
+        }
+
+        let altered = self.catalog.get_table(&schema.name)?;
+        for index in indexes {
+            let name = index.name.clone();
+            if let Err(e) = self.table_store.create_index(&altered, index) {
+                tracing::warn!("Dropping index {} of table {}: {}", name, schema.name, e);
+                self.catalog.drop_index(&name)?;
+                if self.index_manager.list_indexes().contains(&name) {
+                    self.index_manager.drop_index(&name)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_filter(
+        &self,
+        input: QueryResult,
+        predicate: &ScalarExpr,
+        outer: &[Vec<Value>],
+    ) -> Result<QueryResult, DbError> {
+        let eval = Evaluator::new(self, outer);
+        let mut filtered_rows = Vec::new();
+        for row in input.rows {
+            // Rows where the predicate is UNKNOWN are filtered out like FALSE
+            if eval.qualifies(Some(predicate), &row)? {
+                filtered_rows.push(row);
+            }
+        }
+
+        Ok(QueryResult::typed(
+            input.columns,
+            input.column_types,
+            filtered_rows,
+        ))
+    }
+
+    fn execute_project(
+        &self,
+        input: QueryResult,
+        exprs: &[ScalarExpr],
+        columns: &[String],
+        outer: &[Vec<Value>],
+    ) -> Result<QueryResult, DbError> {
+        let eval = Evaluator::new(self, outer);
+        let projected_rows = input
+            .rows
+            .iter()
+            .map(|row| exprs.iter().map(|expr| eval.eval(expr, row)).collect())
+            .collect::<Result<Vec<Vec<Value>>, DbError>>()?;
+
+        let column_types = exprs
+            .iter()
+            .enumerate()
+            .map(|(i, expr)| {
+                expr.data_type(&input.column_types)
+                    .unwrap_or_else(|| Self::inferred_type(&projected_rows, i))
+            })
+            .collect();
+
+        Ok(QueryResult::typed(
+            columns.to_vec(),
+            column_types,
+            projected_rows,
+        ))
+    }
+
+    /// Execute a join operation
+    ///
+    /// PERFORMANCE ISSUE (from diagrams/04_query_processing_flow.md):
+    /// Currently only implements nested loop join (O(n*m) complexity).
+    ///
+    /// INTEGRATION AVAILABLE:
     /// The codebase already has optimized join implementations ready for integration:
     ///
     /// 1. **Hash Join** (src/execution/hash_join.rs):
@@ -1175,381 +2075,474 @@ impl Executor {
         &self,
         left: QueryResult,
         right: QueryResult,
-        join_type: JoinType,
-        condition: &str,
+        join_type: &JoinType,
+        condition: Option<&ScalarExpr>,
+        outer: &[Vec<Value>],
     ) -> Result<QueryResult, DbError> {
         // Combine column names from both sides
         let mut result_columns = left.columns.clone();
         result_columns.extend(right.columns.clone());
+        let mut result_types = left.column_types.clone();
+        result_types.extend(right.column_types.clone());
 
-        let mut result_rows = Vec::new();
-
-        // Helper function to check join condition
-        let matches_condition = |left_row: &[String], right_row: &[String]| -> bool {
-            if condition.is_empty() {
-                return true; // No condition = cross join behavior
-            }
-
-            // Combine rows for evaluation
+        let eval = Evaluator::new(self, outer);
+        let combine = |left_row: &[Value], right_row: &[Value]| {
             let mut combined_row = left_row.to_vec();
-            combined_row.extend(right_row.to_vec());
-
-            self.evaluate_predicate(condition, &result_columns, &combined_row)
+            combined_row.extend_from_slice(right_row);
+            combined_row
         };
 
-        match join_type {
-            JoinType::Inner => {
-                // INNER JOIN: Only matching rows
-                for left_row in &left.rows {
-                    for right_row in &right.rows {
-                        if matches_condition(left_row, right_row) {
-                            let mut combined_row = left_row.clone();
-                            combined_row.extend(right_row.clone());
-                            result_rows.push(combined_row);
-                        }
-                    }
-                }
-            }
-            JoinType::Left => {
-                // LEFT JOIN: All left rows, matching right rows or NULLs
-                for left_row in &left.rows {
-                    let mut found_match = false;
-                    for right_row in &right.rows {
-                        if matches_condition(left_row, right_row) {
-                            let mut combined_row = left_row.clone();
-                            combined_row.extend(right_row.clone());
-                            result_rows.push(combined_row);
-                            found_match = true;
-                        }
-                    }
-
-                    if !found_match {
-                        let mut combined_row = left_row.clone();
-                        combined_row.extend(vec!["NULL".to_string(); right.columns.len()]);
-                        result_rows.push(combined_row);
-                    }
-                }
-            }
-            JoinType::Right => {
-                // RIGHT JOIN: All right rows, matching left rows or NULLs
-                for right_row in &right.rows {
-                    let mut found_match = false;
-                    for left_row in &left.rows {
-                        if matches_condition(left_row, right_row) {
-                            let mut combined_row = left_row.clone();
-                            combined_row.extend(right_row.clone());
-                            result_rows.push(combined_row);
-                            found_match = true;
-                        }
-                    }
-
-                    if !found_match {
-                        let mut combined_row = vec!["NULL".to_string(); left.columns.len()];
-                        combined_row.extend(right_row.clone());
-                        result_rows.push(combined_row);
-                    }
+        // Pairs of row positions satisfying the condition; the condition is
+        // bound against the left columns followed by the right columns
+        let mut matches = Vec::new();
+        for (l, left_row) in left.rows.iter().enumerate() {
+            for (r, right_row) in right.rows.iter().enumerate() {
+                if *join_type == JoinType::Cross
+                    || eval.qualifies(condition, &combine(left_row, right_row))?
+                {
+                    matches.push((l, r));
                 }
             }
-            JoinType::Full => {
-                // FULL OUTER JOIN: All rows from both tables
-                let mut matched_right = vec![false; right.rows.len()];
-
-                for left_row in &left.rows {
-                    let mut found_match = false;
-                    for (i, right_row) in right.rows.iter().enumerate() {
-                        if matches_condition(left_row, right_row) {
-                            let mut combined_row = left_row.clone();
-                            combined_row.extend(right_row.clone());
-                            result_rows.push(combined_row);
-                            found_match = true;
-                            matched_right[i] = true;
-                        }
-                    }
+        }
 
-                    if !found_match {
-                        let mut combined_row = left_row.clone();
-                        combined_row.extend(vec!["NULL".to_string(); right.columns.len()]);
-                        result_rows.push(combined_row);
-                    }
-                }
+        let mut matched_left = vec![false; left.rows.len()];
+        let mut matched_right = vec![false; right.rows.len()];
+        let mut result_rows = Vec::with_capacity(matches.len());
+        for &(l, r) in &matches {
+            matched_left[l] = true;
+            matched_right[r] = true;
+            result_rows.push(combine(&left.rows[l], &right.rows[r]));
+        }
 
-                // Add unmatched right rows
-                for (i, right_row) in right.rows.iter().enumerate() {
-                    if !matched_right[i] {
-                        let mut combined_row = vec!["NULL".to_string(); left.columns.len()];
-                        combined_row.extend(right_row.clone());
-                        result_rows.push(combined_row);
-                    }
+        // Outer joins pad unmatched rows with NULLs
+        if matches!(join_type, JoinType::Left | JoinType::Full) {
+            for (l, left_row) in left.rows.iter().enumerate() {
+                if !matched_left[l] {
+                    result_rows.push(combine(left_row, &vec![Value::Null; right.columns.len()]));
                 }
             }
-            JoinType::Cross => {
-                // CROSS JOIN: Cartesian product (condition is ignored)
-                for left_row in &left.rows {
-                    for right_row in &right.rows {
-                        let mut combined_row = left_row.clone();
-                        combined_row.extend(right_row.clone());
-                        result_rows.push(combined_row);
-                    }
+        }
+        if matches!(join_type, JoinType::Right | JoinType::Full) {
+            for (r, right_row) in right.rows.iter().enumerate() {
+                if !matched_right[r] {
+                    result_rows.push(combine(&vec![Value::Null; left.columns.len()], right_row));
                 }
             }
         }
 
-        Ok(QueryResult::new(result_columns, result_rows))
+        Ok(QueryResult::typed(
+            result_columns,
+            result_types,
+            result_rows,
+        ))
     }
 
     fn execute_aggregate(
         &self,
         input: QueryResult,
-        group_by: &[String],
-        aggregates: &[crate::execution::planner::AggregateExpr],
-        having: Option<&str>,
+        columns: Vec<String>,
+        group_by: &[ScalarExpr],
+        aggregates: &[AggregateExpr],
+        having: Option<&ScalarExpr>,
+        outer: &[Vec<Value>],
     ) -> Result<QueryResult, DbError> {
-        use crate::execution::planner::AggregateFunction;
-
-        // Helper to get column values as floats
-        let get_column_values = |col: &str, rows: &[Vec<String>]| -> Vec<f64> {
-            let col_idx = input.columns.iter().position(|c| {
-                c.eq_ignore_ascii_case(col) || {
-                    // Handle aggregate expressions like COUNT(*), SUM(column)
-                    if let Some(paren_start) = col.find('(') {
-                        if let Some(paren_end) = col.find(')') {
-                            let inner = &col[paren_start + 1..paren_end];
-                            c.eq_ignore_ascii_case(inner)
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                }
-            });
-
-            rows.iter()
-                .filter_map(|row| {
-                    col_idx
-                        .and_then(|idx| row.get(idx))
-                        .and_then(|v| v.parse::<f64>().ok())
-                })
-                .collect()
-        };
+        let eval = Evaluator::new(self, outer);
 
-        // Helper to calculate aggregate value
-        let calculate_aggregate =
-            |func: &AggregateFunction, col: &str, rows: &[Vec<String>]| -> String {
-                match func {
-                    AggregateFunction::Count => {
-                        if col == "*" {
-                            rows.len().to_string()
-                        } else {
-                            // Count non-null values
-                            let col_idx = input
-                                .columns
-                                .iter()
-                                .position(|c| c.eq_ignore_ascii_case(col));
-                            let count = rows
-                                .iter()
-                                .filter(|row| {
-                                    col_idx
-                                        .and_then(|idx| row.get(idx))
-                                        .map(|v| v != "NULL" && !v.is_empty())
-                                        .unwrap_or(false)
-                                })
-                                .count();
-                            count.to_string()
-                        }
-                    }
-                    AggregateFunction::Sum => {
-                        let values = get_column_values(col, rows);
-                        if values.is_empty() {
-                            "NULL".to_string()
-                        } else {
-                            let sum: f64 = values.iter().sum();
-                            if sum.fract() == 0.0 {
-                                (sum as i64).to_string()
-                            } else {
-                                format!("{:.6}", sum)
-                                    .trim_end_matches('0')
-                                    .trim_end_matches('.')
-                                    .to_string()
-                            }
-                        }
-                    }
-                    AggregateFunction::Avg => {
-                        let values = get_column_values(col, rows);
-                        if values.is_empty() {
-                            "NULL".to_string()
-                        } else {
-                            let avg = values.iter().sum::<f64>() / values.len() as f64;
-                            format!("{:.6}", avg)
-                                .trim_end_matches('0')
-                                .trim_end_matches('.')
-                                .to_string()
-                        }
-                    }
-                    AggregateFunction::Min => {
-                        let values = get_column_values(col, rows);
-                        if values.is_empty() {
-                            // Try string comparison
-                            let col_idx = input
-                                .columns
-                                .iter()
-                                .position(|c| c.eq_ignore_ascii_case(col));
-                            rows.iter()
-                                .filter_map(|row| col_idx.and_then(|idx| row.get(idx)))
-                                .filter(|v| *v != "NULL")
-                                .min()
-                                .cloned()
-                                .unwrap_or_else(|| "NULL".to_string())
-                        } else {
-                            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
-                            if min.fract() == 0.0 {
-                                (min as i64).to_string()
-                            } else {
-                                format!("{:.6}", min)
-                                    .trim_end_matches('0')
-                                    .trim_end_matches('.')
-                                    .to_string()
-                            }
-                        }
-                    }
-                    AggregateFunction::Max => {
-                        let values = get_column_values(col, rows);
-                        if values.is_empty() {
-                            // Try string comparison
-                            let col_idx = input
-                                .columns
-                                .iter()
-                                .position(|c| c.eq_ignore_ascii_case(col));
-                            rows.iter()
-                                .filter_map(|row| col_idx.and_then(|idx| row.get(idx)))
-                                .filter(|v| *v != "NULL")
-                                .max()
-                                .cloned()
-                                .unwrap_or_else(|| "NULL".to_string())
-                        } else {
-                            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-                            if max.fract() == 0.0 {
-                                (max as i64).to_string()
-                            } else {
-                                format!("{:.6}", max)
-                                    .trim_end_matches('0')
-                                    .trim_end_matches('.')
-                                    .to_string()
-                            }
-                        }
-                    }
-                    AggregateFunction::StdDev => {
-                        let values = get_column_values(col, rows);
-                        if values.len() < 2 {
-                            "NULL".to_string()
-                        } else {
-                            let mean = values.iter().sum::<f64>() / values.len() as f64;
-                            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>()
-                                / (values.len() - 1) as f64; // Sample std dev
-                            let std_dev = variance.sqrt();
-                            format!("{:.6}", std_dev)
-                                .trim_end_matches('0')
-                                .trim_end_matches('.')
-                                .to_string()
-                        }
-                    }
-                    AggregateFunction::Variance => {
-                        let values = get_column_values(col, rows);
-                        if values.len() < 2 {
-                            "NULL".to_string()
-                        } else {
-                            let mean = values.iter().sum::<f64>() / values.len() as f64;
-                            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>()
-                                / (values.len() - 1) as f64; // Sample variance
-                            format!("{:.6}", variance)
-                                .trim_end_matches('0')
-                                .trim_end_matches('.')
-                                .to_string()
-                        }
+        // Group rows in first-seen order; NULL keys form a single group. Each
+        // group keeps its row count and the non-NULL arguments of every
+        // aggregate, since aggregates ignore NULLs.
+        let mut positions: HashMap<Vec<Value>, usize> = HashMap::new();
+        let mut groups: Vec<(Vec<Value>, usize, Vec<Vec<Value>>)> = Vec::new();
+        if group_by.is_empty() {
+            // A global aggregate has one row even over no input
+            positions.insert(Vec::new(), 0);
+            groups.push((Vec::new(), 0, vec![Vec::new(); aggregates.len()]));
+        }
+        for row in &input.rows {
+            let key = group_by
+                .iter()
+                .map(|expr| eval.eval(expr, row))
+                .collect::<Result<Vec<_>, DbError>>()?;
+            let position = *positions.entry(key.clone()).or_insert_with(|| {
+                groups.push((key, 0, vec![Vec::new(); aggregates.len()]));
+                groups.len() - 1
+            });
+            let group = &mut groups[position];
+            group.1 += 1;
+            for (agg, values) in aggregates.iter().zip(group.2.iter_mut()) {
+                if let Some(arg) = &agg.arg {
+                    let value = eval.eval(arg, row)?;
+                    if !value.is_null() {
+                        values.push(value);
                     }
                 }
-            };
+            }
+        }
 
-        // Extract column name from aggregate expression
-        let extract_agg_column = |col: &str| -> String {
-            if let Some(paren_start) = col.find('(') {
-                if let Some(paren_end) = col.find(')') {
-                    return col[paren_start + 1..paren_end].to_string();
+        let mut result_rows = Vec::with_capacity(groups.len());
+        for (key, count, values) in groups {
+            let mut result_row = key;
+            for (agg, mut values) in aggregates.iter().zip(values) {
+                if agg.distinct {
+                    let mut seen = HashSet::new();
+                    values.retain(|v| seen.insert(v.clone()));
                 }
+                result_row.push(Self::calculate_aggregate(agg, count, &values)?);
             }
-            col.to_string()
-        };
+            result_rows.push(result_row);
+        }
 
-        if group_by.is_empty() {
-            // No grouping - single aggregate result
-            let mut result_columns = Vec::new();
-            let mut result_row = Vec::new();
+        // Types come from the input columns; keys without one are inferred
+        let mut result_types: Vec<DataType> = group_by
+            .iter()
+            .enumerate()
+            .map(|(i, expr)| {
+                expr.data_type(&input.column_types)
+                    .unwrap_or_else(|| Self::inferred_type(&result_rows, i))
+            })
+            .collect();
+        result_types.extend(
+            aggregates
+                .iter()
+                .map(|agg| agg.data_type(&input.column_types)),
+        );
 
-            for agg in aggregates {
-                result_columns.push(agg.column.clone());
-                let col = extract_agg_column(&agg.column);
-                let value = calculate_aggregate(&agg.function, &col, &input.rows);
-                result_row.push(value);
+        // HAVING is bound against the grouped output
+        if having.is_some() {
+            let mut kept = Vec::with_capacity(result_rows.len());
+            for row in result_rows {
+                if eval.qualifies(having, &row)? {
+                    kept.push(row);
+                }
             }
+            result_rows = kept;
+        }
 
-            Ok(QueryResult::new(result_columns, vec![result_row]))
-        } else {
-            // Group by columns - build groups
-            let mut groups: HashMap<Vec<String>, Vec<Vec<String>>> = HashMap::new();
+        Ok(QueryResult::typed(columns, result_types, result_rows))
+    }
 
-            // Find group by column indices
-            let group_indices: Vec<usize> = group_by
+    // Value of an aggregate over a group of `count` rows, given the
+    // non-NULL values of its argument
+    fn calculate_aggregate(
+        agg: &AggregateExpr,
+        count: usize,
+        values: &[Value],
+    ) -> Result<Value, DbError> {
+        // Values as floats, rejecting non-numeric values
+        let numeric_values = || -> Result<Vec<f64>, DbError> {
+            values
                 .iter()
-                .filter_map(|col| {
-                    input
-                        .columns
-                        .iter()
-                        .position(|c| c.eq_ignore_ascii_case(col))
+                .map(|v| {
+                    Self::numeric(v).ok_or_else(|| {
+                        DbError::Execution(format!(
+                            "Cannot aggregate non-numeric value '{}' in {}",
+                            v, agg.column
+                        ))
+                    })
                 })
-                .collect();
+                .collect()
+        };
 
-            // Group rows
-            for row in &input.rows {
-                let key: Vec<String> = group_indices
+        // Exact sum of integers and decimals, None when another kind of
+        // value is present
+        let decimal_sum = || -> Option<Result<Decimal, DbError>> {
+            if !values.iter().any(|v| matches!(v, Value::Numeric(_)))
+                || !values
                     .iter()
-                    .map(|&idx| row.get(idx).cloned().unwrap_or_else(|| "NULL".to_string()))
-                    .collect();
-                groups.entry(key).or_insert_with(Vec::new).push(row.clone());
+                    .all(|v| matches!(v, Value::Integer(_) | Value::Numeric(_)))
+            {
+                return None;
+            }
+            let overflow = || DbError::Execution(format!("{} overflows NUMERIC", agg.column));
+            Some(values.iter().try_fold(Decimal::ZERO, |sum, v| {
+                decimal(v)
+                    .and_then(|d| sum.checked_add(&d))
+                    .ok_or_else(overflow)
+            }))
+        };
+        let interval_sum = || -> Option<Result<Interval, DbError>> {
+            if !values.iter().all(|v| matches!(v, Value::Interval(_))) {
+                return None;
+            }
+            let overflow = || DbError::Execution(format!("{} overflows INTERVAL", agg.column));
+            Some(
+                values
+                    .iter()
+                    .try_fold(Interval::default(), |sum, v| match v {
+                        Value::Interval(i) => sum.checked_add(i).ok_or_else(overflow),
+                        _ => unreachable!("checked above that every value is an interval"),
+                    }),
+            )
+        };
+
+        // Sample variance, NULL for fewer than two values
+        let variance = |values: &[f64]| -> Option<f64> {
+            if values.len() < 2 {
+                return None;
             }
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            Some(values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64)
+        };
 
-            // Build result columns: group by columns + aggregate columns
-            let mut result_columns = group_by.to_vec();
-            for agg in aggregates {
-                result_columns.push(agg.column.clone());
+        Ok(match agg.function {
+            AggregateFunction::Count => match agg.arg {
+                None => Value::Integer(count as i64),
+                Some(_) => Value::Integer(values.len() as i64),
+            },
+            AggregateFunction::Sum => {
+                if values.is_empty() {
+                    Value::Null
+                } else if values.iter().all(|v| matches!(v, Value::Integer(_))) {
+                    let mut sum: i64 = 0;
+                    for value in values {
+                        if let Value::Integer(i) = value {
+                            sum = sum.checked_add(*i).ok_or_else(|| {
+                                DbError::Execution(format!("{} overflows BIGINT", agg.column))
+                            })?;
+                        }
+                    }
+                    Value::Integer(sum)
+                } else if let Some(sum) = decimal_sum() {
+                    Value::Numeric(sum?)
+                } else if let Some(sum) = interval_sum() {
+                    Value::Interval(sum?)
+                } else {
+                    Value::Float(numeric_values()?.iter().sum())
+                }
             }
+            AggregateFunction::Avg => {
+                if values.is_empty() {
+                    Value::Null
+                } else if let Some(sum) = decimal_sum() {
+                    let average = sum?.checked_div(&Decimal::from_i64(values.len() as i64));
+                    Value::Numeric(average.ok_or_else(|| {
+                        DbError::Execution(format!("{} overflows NUMERIC", agg.column))
+                    })?)
+                } else if let Some(sum) = interval_sum() {
+                    Value::Interval(sum?.checked_div(values.len() as f64).ok_or_else(|| {
+                        DbError::Execution(format!("{} overflows INTERVAL", agg.column))
+                    })?)
+                } else {
+                    let values = numeric_values()?;
+                    Value::Float(values.iter().sum::<f64>() / values.len() as f64)
+                }
+            }
+            AggregateFunction::Min => values
+                .iter()
+                .cloned()
+                .reduce(|min, v| match v.sql_cmp(&min) {
+                    Some(Ordering::Less) => v,
+                    _ => min,
+                })
+                .unwrap_or(Value::Null),
+            AggregateFunction::Max => values
+                .iter()
+                .cloned()
+                .reduce(|max, v| match v.sql_cmp(&max) {
+                    Some(Ordering::Greater) => v,
+                    _ => max,
+                })
+                .unwrap_or(Value::Null),
+            AggregateFunction::StdDev => variance(&numeric_values()?)
+                .map(|v| Value::Float(v.sqrt()))
+                .unwrap_or(Value::Null),
+            AggregateFunction::Variance => variance(&numeric_values()?)
+                .map(Value::Float)
+                .unwrap_or(Value::Null),
+        })
+    }
 
-            // Build result rows
-            let mut result_rows: Vec<Vec<String>> = Vec::new();
+    /// Apply DISTINCT to remove duplicate rows
+    fn apply_distinct(&self, input: QueryResult) -> QueryResult {
+        let mut seen = HashSet::new();
+        let mut unique_rows = Vec::new();
+
+        for row in input.rows {
+            if seen.insert(row.clone()) {
+                unique_rows.push(row);
+            }
+        }
 
-            for (key, group_rows) in groups {
-                let mut result_row = key.clone();
+        QueryResult::typed(input.columns, input.column_types, unique_rows)
+    }
 
-                for agg in aggregates {
-                    let col = extract_agg_column(&agg.column);
-                    let value = calculate_aggregate(&agg.function, &col, &group_rows);
-                    result_row.push(value);
+    // UNION, INTERSECT or EXCEPT of two results of the same width. ALL keeps
+    // a row as many times as it appears in the left input, minus (EXCEPT)
+    // or at most (INTERSECT) the times it appears in the right one
+    fn execute_set_operation(
+        &self,
+        left: QueryResult,
+        right: QueryResult,
+        op: SetOperator,
+        all: bool,
+    ) -> QueryResult {
+        let rows = match op {
+            SetOperator::Union => left.rows.into_iter().chain(right.rows).collect(),
+            SetOperator::Intersect | SetOperator::Except => {
+                let mut counts: HashMap<Vec<Value>, usize> = HashMap::new();
+                for row in right.rows {
+                    *counts.entry(row).or_default() += 1;
+                }
+                let mut rows = Vec::new();
+                for row in left.rows {
+                    let matched = match counts.get_mut(&row) {
+                        Some(count) if *count > 0 => {
+                            if all {
+                                *count -= 1;
+                            }
+                            true
+                        }
+                        _ => false,
+                    };
+                    if matched == (op == SetOperator::Intersect) {
+                        rows.push(row);
+                    }
                 }
+                rows
+            }
+        };
+        let output = QueryResult::typed(left.columns, left.column_types, rows);
+        match all {
+            true => output,
+            false => self.apply_distinct(output),
+        }
+    }
+
+    // Run a recursive WITH query: the base query once, then the recursive
+    // term over the rows each round adds until a round adds none
+    #[allow(clippy::too_many_arguments)]
+    fn execute_recursive_union(
+        &self,
+        name: &str,
+        columns: &[String],
+        base: &PlanNode,
+        recursive: &PlanNode,
+        all: bool,
+        search: Option<&SearchClause>,
+        cycle: Option<&CycleClause>,
+        outer: &[Vec<Value>],
+    ) -> Result<QueryResult, DbError> {
+        let width = columns.len();
+        let base_result = self.execute_node(base, outer)?;
+        let mut types = base_result.column_types;
+        let search_type = search.map(|search| search.data_type(&types));
+        let cycle_types = cycle.map(|cycle| cycle.data_types(&types));
+        types.extend(search_type);
+        types.extend(cycle_types.into_iter().flatten());
+        let mark = width + usize::from(search.is_some());
 
-                result_rows.push(result_row);
+        let mut seen = HashSet::new();
+        let mut rows: Vec<Vec<Value>> = Vec::new();
+        let mut added = base_result.rows;
+        let mut depth = 0;
+        loop {
+            let round: Vec<Vec<Value>> = added
+                .into_iter()
+                .map(|row| Self::search_cycle_columns(row, width, depth, search, cycle))
+                .filter(|row| all || seen.insert(row.clone()))
+                .collect();
+            if round.is_empty() {
+                break;
+            }
+            if depth >= MAX_RECURSIVE_ITERATIONS {
+                return Err(DbError::LimitExceeded(format!(
+                    "Recursive query {} did not finish within {} iterations",
+                    name, MAX_RECURSIVE_ITERATIONS
+                )));
+            }
+            if rows.len() + round.len() > MAX_RESULT_ROWS {
+                return Err(DbError::LimitExceeded(format!(
+                    "Recursive query {} returned more than {} rows",
+                    name, MAX_RESULT_ROWS
+                )));
             }
 
-            // Apply HAVING clause if present
-            if let Some(having_clause) = having {
-                result_rows = result_rows
-                    .into_iter()
-                    .filter(|row| self.evaluate_predicate(having_clause, &result_columns, row))
-                    .collect();
+            // Rows the CYCLE clause marked are kept but not followed
+            let work = round
+                .iter()
+                .filter(|row| !cycle.is_some_and(|cycle| row[mark] == cycle.mark_value))
+                .map(|row| {
+                    let mut row = row.clone();
+                    if cycle.is_some() {
+                        row.remove(mark);
+                    }
+                    row
+                })
+                .collect();
+            rows.extend(round);
+
+            let mut work_tables = self.work_tables.clone();
+            work_tables.insert(name.to_string(), Arc::new(work));
+            let step = Self {
+                work_tables,
+                ..self.clone()
+            };
+            added = step.execute_node(recursive, outer)?.rows;
+            depth += 1;
+        }
+
+        let mut output_columns = columns.to_vec();
+        output_columns.extend(search.map(|search| search.column.clone()));
+        if let Some(cycle) = cycle {
+            output_columns.extend([cycle.mark.clone(), cycle.path.clone()]);
+        }
+        Ok(QueryResult::typed(output_columns, types, rows))
+    }
+
+    // Replace the columns a recursive term's row carries from the row it
+    // was reached from (none for base rows) with its own SEARCH column and
+    // CYCLE mark and path
+    fn search_cycle_columns(
+        mut row: Vec<Value>,
+        width: usize,
+        depth: usize,
+        search: Option<&SearchClause>,
+        cycle: Option<&CycleClause>,
+    ) -> Vec<Value> {
+        let mut carried = row.split_off(width).into_iter();
+        let mut parent_path = |present: bool| match present.then(|| carried.next()) {
+            Some(Some(Value::Array(path))) => path,
+            _ => Vec::new(),
+        };
+        let (search_path, cycle_path) =
+            (parent_path(search.is_some()), parent_path(cycle.is_some()));
+        let key = |columns: &[usize]| match columns {
+            [column] => row[*column].clone(),
+            _ => Value::Array(columns.iter().map(|&i| row[i].clone()).collect()),
+        };
+
+        let search_value = search.map(|search| match search.breadth_first {
+            true => Value::Array(vec![Value::Integer(depth as i64), key(&search.columns)]),
+            false => {
+                let mut path = search_path;
+                path.push(key(&search.columns));
+                Value::Array(path)
             }
+        });
+        let cycle_values = cycle.map(|cycle| {
+            let (mut path, key) = (cycle_path, key(&cycle.columns));
+            let mark = match path.contains(&key) {
+                true => cycle.mark_value.clone(),
+                false => cycle.default_value.clone(),
+            };
+            path.push(key);
+            [mark, Value::Array(path)]
+        });
+        row.extend(search_value);
+        row.extend(cycle_values.into_iter().flatten());
+        row
+    }
 
-            Ok(QueryResult::new(result_columns, result_rows))
+
+    // Numeric value of a number or numeric string
+    fn numeric(value: &Value) -> Option<f64> {
+        match value {
+            Value::String(s) => Value::from_sql_literal(s).as_f64(),
+            other => other.as_f64(),
         }
     }
 
+
     /// Execute sort operation
     ///
     /// PERFORMANCE ISSUE (from diagrams/04_query_processing_flow.md):
@@ -1583,8 +2576,9 @@ impl Executor {
     /// Effort: 3-4 days
     fn execute_sort(
         &self,
-        mut input: QueryResult,
-        order_by: &[crate::parser::OrderByClause],
+        input: QueryResult,
+        order_by: &[SortKey],
+        outer: &[Vec<Value>],
     ) -> Result<QueryResult, DbError> {
         if order_by.is_empty() {
             return Ok(input);
@@ -1599,70 +2593,158 @@ impl Executor {
             );
         }
 
-        // Find column indices for sorting
-        let sort_specs: Vec<(Option<usize>, bool)> = order_by
-            .iter()
-            .map(|clause| {
-                let col_idx = input.columns.iter().position(|c| {
-                    c.eq_ignore_ascii_case(&clause.column) || {
-                        // Handle table.column format
-                        if let Some(dot_pos) = clause.column.find('.') {
-                            c.eq_ignore_ascii_case(&clause.column[dot_pos + 1..])
-                        } else {
-                            false
-                        }
-                    }
-                });
-                (col_idx, clause.ascending)
+        // Evaluate the sort keys once per row
+        let eval = Evaluator::new(self, outer);
+        let mut keyed = input
+            .rows
+            .into_iter()
+            .map(|row| {
+                let keys = order_by
+                    .iter()
+                    .map(|key| eval.eval(&key.expr, &row))
+                    .collect::<Result<Vec<_>, DbError>>()?;
+                Ok((keys, row))
             })
-            .collect();
-
-        // Sort the rows
-        input.rows.sort_by(|a, b| {
-            for (col_idx, ascending) in &sort_specs {
-                if let Some(idx) = col_idx {
-                    let left = a.get(*idx).map(|s| s.as_str()).unwrap_or("");
-                    let right = b.get(*idx).map(|s| s.as_str()).unwrap_or("");
-
-                    // Handle NULL values (NULLs sort last by default)
-                    let left_is_null = left == "NULL" || left.is_empty();
-                    let right_is_null = right == "NULL" || right.is_empty();
+            .collect::<Result<Vec<_>, DbError>>()?;
+
+        // Stable, so rows with equal keys keep their input order
+        keyed.sort_by(|(a, _), (b, _)| {
+            for (key, (left, right)) in order_by.iter().zip(a.iter().zip(b)) {
+                let cmp = match (left.is_null(), right.is_null()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) if key.nulls_first() => Ordering::Less,
+                    (true, false) => Ordering::Greater,
+                    (false, true) if key.nulls_first() => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) if key.ascending => left.sort_cmp(right),
+                    (false, false) => left.sort_cmp(right).reverse(),
+                };
+                if cmp != Ordering::Equal {
+                    return cmp;
+                }
+            }
+            Ordering::Equal
+        });
 
-                    if left_is_null && right_is_null {
-                        continue;
-                    }
-                    if left_is_null {
-                        return if *ascending {
-                            Ordering::Greater
-                        } else {
-                            Ordering::Less
-                        };
-                    }
-                    if right_is_null {
-                        return if *ascending {
-                            Ordering::Less
-                        } else {
-                            Ordering::Greater
-                        };
-                    }
+        Ok(QueryResult::typed(
+            input.columns,
+            input.column_types,
+            keyed.into_iter().map(|(_, row)| row).collect(),
+        ))
+    }
 
-                    // Try numeric comparison first
-                    let cmp = if let (Ok(l), Ok(r)) = (left.parse::<f64>(), right.parse::<f64>()) {
-                        l.partial_cmp(&r).unwrap_or(Ordering::Equal)
-                    } else {
-                        // Fall back to case-insensitive string comparison
-                        left.to_lowercase().cmp(&right.to_lowercase())
-                    };
+    // Window functions over input sorted by the partition keys and then the
+    // window's ORDER BY; rows keep their order and gain one column per
+    // function. Frame offsets are evaluated once, before any row
+    fn execute_window(
+        &self,
+        input: QueryResult,
+        partition_by: &[ScalarExpr],
+        order_by: &[SortKey],
+        functions: &[WindowExpr],
+        outer: &[Vec<Value>],
+    ) -> Result<QueryResult, DbError> {
+        let eval = Evaluator::new(self, outer);
+        let mut partition_keys = Vec::with_capacity(input.rows.len());
+        let mut order_keys = Vec::with_capacity(input.rows.len());
+        for row in &input.rows {
+            partition_keys.push(
+                partition_by
+                    .iter()
+                    .map(|expr| eval.eval(expr, row))
+                    .collect::<Result<Vec<_>, DbError>>()?,
+            );
+            order_keys.push(
+                order_by
+                    .iter()
+                    .map(|key| eval.eval(&key.expr, row))
+                    .collect::<Result<Vec<_>, DbError>>()?,
+            );
+        }
+        let offsets = functions
+            .iter()
+            .map(|function| -> Result<_, DbError> {
+                let frame = &function.frame;
+                Ok([
+                    Self::frame_offset(frame.units, &frame.start, &eval)?,
+                    Self::frame_offset(frame.units, &frame.end, &eval)?,
+                ])
+            })
+            .collect::<Result<Vec<_>, DbError>>()?;
 
-                    if cmp != Ordering::Equal {
-                        return if *ascending { cmp } else { cmp.reverse() };
-                    }
+        let mut results = vec![Vec::with_capacity(functions.len()); input.rows.len()];
+        let mut start = 0;
+        while start < input.rows.len() {
+            let end = start
+                + partition_keys[start..]
+                    .iter()
+                    .take_while(|key| same_keys(key, &partition_keys[start]))
+                    .count();
+            let partition =
+                WindowPartition::new(&input.rows[start..end], &order_keys[start..end], order_by);
+            for (function, offsets) in functions.iter().zip(&offsets) {
+                let values = partition.values(function, offsets, &eval)?;
+                for (result, value) in results[start..end].iter_mut().zip(values) {
+                    result.push(value);
                 }
             }
-            Ordering::Equal
-        });
+            start = end;
+        }
 
-        Ok(input)
+        let mut columns = input.columns;
+        columns.extend(functions.iter().map(|function| function.column.clone()));
+        let mut types = input.column_types.clone();
+        types.extend(
+            functions
+                .iter()
+                .map(|function| function.data_type(&input.column_types)),
+        );
+        let rows = input
+            .rows
+            .into_iter()
+            .zip(results)
+            .map(|(mut row, values)| {
+                row.extend(values);
+                row
+            })
+            .collect();
+        Ok(QueryResult::typed(columns, types, rows))
+    }
+
+    // Value of a frame bound's offset. ROWS and GROUPS offsets count rows
+    // and peer groups; RANGE offsets are added to or subtracted from the
+    // ORDER BY key, so any type that supports that will do
+    fn frame_offset(
+        units: FrameType,
+        bound: &FrameBound,
+        eval: &Evaluator,
+    ) -> Result<Option<Value>, DbError> {
+        let (FrameBound::Preceding(expr) | FrameBound::Following(expr)) = bound else {
+            return Ok(None);
+        };
+        let value = eval.eval(expr, &[])?;
+        let negative = match &value {
+            Value::Null => {
+                return Err(DbError::Execution(
+                    "Frame offset must not be null".to_string(),
+                ))
+            }
+            Value::Integer(n) => *n < 0,
+            Value::Interval(_) if units == FrameType::Range => false,
+            other if units == FrameType::Range => other.as_f64().is_some_and(|f| f < 0.0),
+            other => {
+                return Err(DbError::Execution(format!(
+                    "Frame offset of ROWS or GROUPS must be an integer, got '{}'",
+                    other
+                )))
+            }
+        };
+        if negative {
+            return Err(DbError::Execution(
+                "Frame offset must not be negative".to_string(),
+            ));
+        }
+        Ok(Some(value))
     }
 
     fn execute_limit(
@@ -1678,6 +2760,70 @@ impl Executor {
         Ok(input)
     }
 
+    // Record a constraint declared on `table` in the catalog, which persists
+    // it, and register it with the constraint manager, which enforces it
+    fn add_constraint(&self, table: &Schema, constraint: ConstraintType) -> Result<(), DbError> {
+        let ConstraintType::ForeignKey {
+            name,
+            columns,
+            ref_table,
+            ref_columns,
+            on_delete,
+            on_update,
+        } = constraint
+        else {
+            return Err(DbError::NotImplemented(format!(
+                "Only FOREIGN KEY constraints can be added to table {}",
+                table.name
+            )));
+        };
+
+        let referenced = if ref_table == table.name {
+            table.clone()
+        } else {
+            self.catalog.get_table(&ref_table)?
+        };
+        let ref_columns = if ref_columns.is_empty() {
+            match &referenced.primary_key {
+                Some(key) => vec![key.clone()],
+                None => {
+                    return Err(DbError::Execution(format!(
+                        "Table {} has no primary key for a foreign key to reference",
+                        ref_table
+                    )))
+                }
+            }
+        } else {
+            ref_columns
+        };
+        if columns.is_empty() || columns.len() != ref_columns.len() {
+            return Err(DbError::Execution(format!(
+                "Foreign key on table {} has {} columns but references {}",
+                table.name,
+                columns.len(),
+                ref_columns.len()
+            )));
+        }
+        for column in &columns {
+            Self::column_position(&Self::column_names(table), column)?;
+        }
+        for column in &ref_columns {
+            Self::column_position(&Self::column_names(&referenced), column)?;
+        }
+
+        let fk = ForeignKey {
+            name: name.unwrap_or_else(|| format!("{}_{}_fkey", table.name, columns.join("_"))),
+            table: table.name.clone(),
+            columns,
+            referenced_table: ref_table,
+            referenced_columns: ref_columns,
+            on_delete,
+            on_update,
+        };
+        self.catalog.create_foreign_key(fk.clone())?;
+        self.constraint_manager.add_foreign_key(fk)
+    }
+
     /// Execute ALTER TABLE operations
     fn execute_alter_table(
         &self,
@@ -1702,12 +2848,28 @@ impl Executor {
                     )));
                 }
 
+                // Existing rows get the column's default (or NULL)
+                let fill = Self::default_value(&column)?;
+                if !column.nullable
+                    && fill.is_null()
+                    && !self.scan_rows(&current_schema)?.is_empty()
+                {
+                    return Err(DbError::Execution(format!(
+                        "Column {} is NOT NULL without a default and table {} is not empty",
+                        column.name, table_name
+                    )));
+                }
                 new_columns.push(column);
+                self.rewrite_rows(&current_schema, |mut row| {
+                    row.push(fill.clone());
+                    Ok(row)
+                })?;
 
                 // Update schema
-                let new_schema = Schema::new(table_name.to_string(), new_columns);
-                self.catalog.drop_table(table_name)?;
-                self.catalog.create_table(new_schema)?;
+                self.catalog.alter_table(Schema {
+                    columns: new_columns,
+                    ..current_schema
+                })?;
 
                 Ok(())
             }
@@ -1733,10 +2895,24 @@ impl Executor {
                     ));
                 }
 
-                // Update schema
-                let new_schema = Schema::new(table_name.to_string(), new_columns);
-                self.catalog.drop_table(table_name)?;
-                self.catalog.create_table(new_schema)?;
+                // Remove the column's value from every stored row
+                let dropped = current_schema
+                    .columns
+                    .iter()
+                    .position(|c| c.name == column_name)
+                    .expect("column was found above");
+                self.with_indexes_rebuilt(&current_schema, || {
+                    self.rewrite_rows(&current_schema, |mut row| {
+                        row.remove(dropped);
+                        Ok(row)
+                    })?;
+
+                    // Update schema
+                    self.catalog.alter_table(Schema {
+                        columns: new_columns,
+                        ..current_schema.clone()
+                    })
+                })?;
 
                 Ok(())
             }
@@ -1765,10 +2941,22 @@ impl Executor {
                     )));
                 }
 
-                // Update schema
-                let new_schema = Schema::new(table_name.to_string(), new_columns);
-                self.catalog.drop_table(table_name)?;
-                self.catalog.create_table(new_schema)?;
+                // Convert the stored values to the new type
+                let position = current_schema
+                    .get_column_index(&column_name)
+                    .expect("column was found above");
+                self.with_indexes_rebuilt(&current_schema, || {
+                    self.rewrite_rows(&current_schema, |mut row| {
+                        row[position] = new_type.coerce(row[position].clone())?;
+                        Ok(row)
+                    })?;
+
+                    // Update schema
+                    self.catalog.alter_table(Schema {
+                        columns: new_columns,
+                        ..current_schema.clone()
+                    })
+                })?;
 
                 Ok(())
             }
@@ -1801,24 +2989,47 @@ impl Executor {
                     )));
                 }
 
-                // Update schema
-                let new_schema = Schema::new(table_name.to_string(), new_columns);
-                self.catalog.drop_table(table_name)?;
-                self.catalog.create_table(new_schema)?;
+                // Convert the stored values to the new type
+                let position = current_schema
+                    .get_column_index(&column_name)
+                    .expect("column was found above");
+                self.with_indexes_rebuilt(&current_schema, || {
+                    self.rewrite_rows(&current_schema, |mut row| {
+                        let value = new_type.coerce(row[position].clone())?;
+                        if value.is_null() && nullable == Some(false) {
+                            return Err(DbError::ConstraintViolation(format!(
+                                "Column {} of table {} contains NULL values",
+                                column_name, table_name
+                            )));
+                        }
+                        row[position] = value;
+                        Ok(row)
+                    })?;
+
+                    // Update schema
+                    self.catalog.alter_table(Schema {
+                        columns: new_columns,
+                        ..current_schema.clone()
+                    })
+                })?;
 
                 Ok(())
             }
 
-            AlterAction::AddConstraint(_constraint_type) => {
-                // Add a constraint to the table
-                // In production, this would integrate with the constraint manager
-                Ok(())
+            AlterAction::AddConstraint(constraint) => {
+                self.add_constraint(&current_schema, constraint)
             }
 
-            AlterAction::DropConstraint(_constraint_name) => {
-                // Drop a constraint from the table
-                // In production, this would integrate with the constraint manager
-                Ok(())
+            AlterAction::DropConstraint(constraint_name) => {
+                let fk = self.catalog.get_foreign_key(&constraint_name)?;
+                if fk.table != table_name {
+                    return Err(DbError::Catalog(format!(
+                        "Constraint {} not found on table {}",
+                        constraint_name, table_name
+                    )));
+                }
+                self.catalog.drop_foreign_key(&constraint_name)?;
+                self.constraint_manager.drop_foreign_key(&constraint_name)
             }
 
             AlterAction::DropDefault(column_name) => {
@@ -1843,9 +3054,10 @@ impl Executor {
                 }
 
                 // Update schema
-                let new_schema = Schema::new(table_name.to_string(), new_columns);
-                self.catalog.drop_table(table_name)?;
-                self.catalog.create_table(new_schema)?;
+                self.catalog.alter_table(Schema {
+                    columns: new_columns,
+                    ..current_schema
+                })?;
 
                 Ok(())
             }
@@ -1853,23 +3065,2530 @@ impl Executor {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::parser::SqlParser;
 
-    #[test]
-    fn test_executor() -> Result<(), DbError> {
-        let catalog = Arc::new(Catalog::new());
-        let txn_manager = Arc::new(TransactionManager::new());
-        let executor = Executor::new(catalog, txn_manager);
+// One partition of a Window node's input, split into peer groups: runs of
+// rows with equal ORDER BY keys, all of them when there is no ORDER BY
+struct WindowPartition<'a> {
+    rows: &'a [Vec<Value>],
+    order_keys: &'a [Vec<Value>],
+    order_by: &'a [SortKey],
+    peers: PeerGroups,
+    // Rows whose first ORDER BY key is not NULL; NULLs sort all first or
+    // all last, so these are contiguous
+    keyed: Range<usize>,
+}
 
-        let parser = SqlParser::new();
-        let stmts = parser.parse("CREATE TABLE users (id INT, name VARCHAR(255))")?;
+impl<'a> WindowPartition<'a> {
+    fn new(rows: &'a [Vec<Value>], order_keys: &'a [Vec<Value>], order_by: &'a [SortKey]) -> Self {
+        let peers = PeerGroups::new(rows.len(), |a, b| {
+            same_keys(&order_keys[a], &order_keys[b])
+        });
+        let nulls = order_keys
+            .iter()
+            .filter(|key| key.first().is_some_and(Value::is_null))
+            .count();
+        let keyed = match order_by.first().is_some_and(SortKey::nulls_first) {
+            true => nulls..rows.len(),
+            false => 0..rows.len() - nulls,
+        };
+        Self {
+            rows,
+            order_keys,
+            order_by,
+            peers,
+            keyed,
+        }
+    }
 
-        let result = executor.execute(stmts[0].clone())?;
-        assert_eq!(result.rows_affected, 0);
+    // The function's value for every row of the partition
+    fn values(
+        &self,
+        function: &WindowExpr,
+        offsets: &[Option<Value>; 2],
+        eval: &Evaluator,
+    ) -> Result<Vec<Value>, DbError> {
+        let n = self.rows.len();
+        let arg = |k: usize, row: usize| match function.args.get(k) {
+            Some(expr) => eval.eval(expr, &self.rows[row]),
+            None => Ok(Value::Null),
+        };
+        let peers = &self.peers;
+
+        let mut values = Vec::with_capacity(n);
+        match function.function {
+            WindowFunction::RowNumber => values.extend((1..=n).map(|i| Value::Integer(i as i64))),
+            WindowFunction::Rank => {
+                values.extend((0..n).map(|i| Value::Integer(peers.rank(i) as i64)))
+            }
+            WindowFunction::DenseRank => {
+                values.extend((0..n).map(|i| Value::Integer(peers.dense_rank(i) as i64)))
+            }
+            WindowFunction::PercentRank => {
+                values.extend((0..n).map(|i| Value::Float(peers.percent_rank(i))))
+            }
+            WindowFunction::CumeDist => {
+                values.extend((0..n).map(|i| Value::Float(peers.cume_dist(i))))
+            }
+            WindowFunction::Ntile => {
+                for i in 0..n {
+                    values.push(match arg(0, i)? {
+                        Value::Null => Value::Null,
+                        Value::Integer(buckets) if buckets > 0 => {
+                            Value::Integer(window_functions::ntile(i, n, buckets as usize) as i64)
+                        }
+                        other => {
+                            return Err(DbError::Execution(format!(
+                                "Argument of NTILE must be greater than zero, got '{}'",
+                                other
+                            )))
+                        }
+                    });
+                }
+            }
+            WindowFunction::Lag | WindowFunction::Lead => {
+                for i in 0..n {
+                    let offset = match function.args.len() {
+                        1 => Value::Integer(1),
+                        _ => arg(1, i)?,
+                    };
+                    let offset = match offset {
+                        Value::Null => {
+                            values.push(Value::Null);
+                            continue;
+                        }
+                        Value::Integer(offset) => offset,
+                        other => {
+                            return Err(DbError::Execution(format!(
+                                "Offset of {} must be an integer, got '{}'",
+                                function.column, other
+                            )))
+                        }
+                    };
+                    let target = match function.function {
+                        WindowFunction::Lag => (i as i64).checked_sub(offset),
+                        _ => (i as i64).checked_add(offset),
+                    };
+                    values.push(match target.filter(|t| (0..n as i64).contains(t)) {
+                        Some(target) => arg(0, target as usize)?,
+                        None => arg(2, i)?,
+                    });
+                }
+            }
+            WindowFunction::FirstValue | WindowFunction::LastValue | WindowFunction::NthValue => {
+                for i in 0..n {
+                    let frame = self.frame_rows(i, &function.frame, offsets)?;
+                    let row = match function.function {
+                        WindowFunction::FirstValue => frame.first().copied(),
+                        WindowFunction::LastValue => frame.last().copied(),
+                        _ => match arg(1, i)? {
+                            Value::Null => None,
+                            Value::Integer(nth) if nth > 0 => frame.get(nth as usize - 1).copied(),
+                            other => {
+                                return Err(DbError::Execution(format!(
+                                    "Argument of NTH_VALUE must be greater than zero, got '{}'",
+                                    other
+                                )))
+                            }
+                        },
+                    };
+                    values.push(match row {
+                        Some(row) => arg(0, row)?,
+                        None => Value::Null,
+                    });
+                }
+            }
+            WindowFunction::Aggregate(aggregate) => {
+                let agg = AggregateExpr {
+                    function: aggregate,
+                    arg: function.args.first().cloned(),
+                    distinct: false,
+                    column: function.column.clone(),
+                };
+                let args = (0..n).map(|i| arg(0, i)).collect::<Result<Vec<_>, _>>()?;
+                // Without exclusions, rows whose frames are the same rows
+                // (peers in the default frame) share one value
+                let mut previous: Option<(Range<usize>, Value)> = None;
+                for i in 0..n {
+                    let bounds = self.frame_bounds(i, &function.frame, offsets)?;
+                    if function.frame.exclude == FrameExclusion::NoOthers {
+                        if let Some((seen, value)) = &previous {
+                            if *seen == bounds {
+                                values.push(value.clone());
+                                continue;
+                            }
+                        }
+                    }
+                    let frame = peers.excluding(i, bounds.clone(), function.frame.exclude);
+                    let present: Vec<Value> = frame
+                        .iter()
+                        .map(|&row| &args[row])
+                        .filter(|value| !value.is_null())
+                        .cloned()
+                        .collect();
+                    let value = Executor::calculate_aggregate(&agg, frame.len(), &present)?;
+                    previous = Some((bounds, value.clone()));
+                    values.push(value);
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    // The rows in row `i`'s frame, in order
+    fn frame_rows(
+        &self,
+        i: usize,
+        frame: &WindowFrame,
+        offsets: &[Option<Value>; 2],
+    ) -> Result<Vec<usize>, DbError> {
+        let bounds = self.frame_bounds(i, frame, offsets)?;
+        Ok(self.peers.excluding(i, bounds, frame.exclude))
+    }
+
+    // Rows from the frame start up to the frame end, before EXCLUDE. Only
+    // RANGE offsets need the ORDER BY key; the peer groups place the rest
+    fn frame_bounds(
+        &self,
+        i: usize,
+        frame: &WindowFrame,
+        offsets: &[Option<Value>; 2],
+    ) -> Result<Range<usize>, DbError> {
+        let bound = |bound: &FrameBound, offset: &Option<Value>, end: bool| {
+            let count = match offset {
+                Some(Value::Integer(k)) => *k as usize,
+                _ => 0,
+            };
+            let bound = match bound {
+                FrameBound::UnboundedPreceding => window_functions::FrameBound::UnboundedPreceding,
+                FrameBound::Preceding(_) | FrameBound::Following(_)
+                    if frame.units == FrameType::Range =>
+                {
+                    return self.range_position(i, bound, offset, end)
+                }
+                FrameBound::Preceding(_) => window_functions::FrameBound::Preceding(count),
+                FrameBound::CurrentRow => window_functions::FrameBound::CurrentRow,
+                FrameBound::Following(_) => window_functions::FrameBound::Following(count),
+                FrameBound::UnboundedFollowing => window_functions::FrameBound::UnboundedFollowing,
+            };
+            Ok(self.peers.bound(i, frame.units, &bound, end))
+        };
+        let start = bound(&frame.start, &offsets[0], false)?;
+        let end = bound(&frame.end, &offsets[1], true)?;
+        Ok(start..end.max(start))
+    }
+
+    // Where a RANGE offset bound of row `i` falls: the first row whose key
+    // is within the offset for a frame start, the row after the last one
+    // for a frame end. A NULL key is only within range of the other NULLs
+    fn range_position(
+        &self,
+        i: usize,
+        bound: &FrameBound,
+        offset: &Option<Value>,
+        end: bool,
+    ) -> Result<usize, DbError> {
+        let peers = self.peers.peers(i);
+        let (FrameBound::Preceding(_) | FrameBound::Following(_), Some(offset)) = (bound, offset)
+        else {
+            return Ok(if end { peers.end } else { peers.start });
+        };
+        let value = &self.order_keys[i][0];
+        if value.is_null() {
+            return Ok(if end { peers.end } else { peers.start });
+        }
+
+        // Preceding rows have smaller keys when the order is ascending
+        let key = &self.order_by[0];
+        let op = match matches!(bound, FrameBound::Preceding(_)) == key.ascending {
+            true => BinaryOperator::Subtract,
+            false => BinaryOperator::Add,
+        };
+        let target = arithmetic(op, value, offset)?;
+        let cmp = |keys: &Vec<Value>| match key.ascending {
+            true => keys[0].sort_cmp(&target),
+            false => keys[0].sort_cmp(&target).reverse(),
+        };
+        let keyed = &self.order_keys[self.keyed.clone()];
+        Ok(self.keyed.start
+            + match end {
+                false => keyed.partition_point(|keys| cmp(keys) == Ordering::Less),
+                true => keyed.partition_point(|keys| cmp(keys) != Ordering::Greater),
+            })
+    }
+}
+
+// Whether two rows have equal sort keys; NULLs are equal to each other
+fn same_keys(left: &[Value], right: &[Value]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .all(|(l, r)| match (l.is_null(), r.is_null()) {
+                (false, false) => l.sort_cmp(r) == Ordering::Equal,
+                (nulls_left, nulls_right) => nulls_left == nulls_right,
+            })
+}
+
+// Evaluates bound expressions for one operator
+//
+// `outer` holds the rows of the enclosing queries, innermost last. An
+// uncorrelated subquery runs once per evaluator and its rows are reused;
+// a correlated one runs again for every row it is evaluated against.
+struct Evaluator<'a> {
+    executor: &'a Executor,
+    outer: &'a [Vec<Value>],
+    subqueries: RefCell<HashMap<*const PlanNode, Rc<Vec<Vec<Value>>>>>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(executor: &'a Executor, outer: &'a [Vec<Value>]) -> Self {
+        Self {
+            executor,
+            outer,
+            subqueries: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Whether a row passes an optional predicate; UNKNOWN does not
+    fn qualifies(&self, predicate: Option<&ScalarExpr>, row: &[Value]) -> Result<bool, DbError> {
+        match predicate {
+            Some(predicate) => Ok(self.truth(predicate, row)? == Some(true)),
+            None => Ok(true),
+        }
+    }
+
+    // SQL truth value of a condition; `None` is UNKNOWN
+    fn truth(&self, expr: &ScalarExpr, row: &[Value]) -> Result<Option<bool>, DbError> {
+        match self.eval(expr, row)? {
+            Value::Null => Ok(None),
+            value => value.as_bool().map(Some).ok_or_else(|| {
+                DbError::Execution(format!(
+                    "Expected a boolean condition in {}, got '{}'",
+                    expr, value
+                ))
+            }),
+        }
+    }
+
+    fn eval(&self, expr: &ScalarExpr, row: &[Value]) -> Result<Value, DbError> {
+        match expr {
+            ScalarExpr::Literal(value) => Ok(value.clone()),
+            ScalarExpr::Column { index, name } => row.get(*index).cloned().ok_or_else(|| {
+                DbError::Internal(format!("Column {} is outside the input row", name))
+            }),
+            ScalarExpr::Parameter { index } => Err(DbError::Execution(format!(
+                "No value bound for parameter ${}",
+                index + 1
+            ))),
+            ScalarExpr::OuterColumn { depth, index, name } => self
+                .outer
+                .len()
+                .checked_sub(depth + 1)
+                .and_then(|frame| self.outer[frame].get(*index))
+                .cloned()
+                .ok_or_else(|| {
+                    DbError::Internal(format!("Outer column {} is not in scope", name))
+                }),
+            ScalarExpr::Unary { op, expr: inner } => match op {
+                UnaryOperator::Not => Ok(truth_value(self.truth(inner, row)?.map(|b| !b))),
+                UnaryOperator::IsNull => Ok(Value::Boolean(self.eval(inner, row)?.is_null())),
+                UnaryOperator::IsNotNull => Ok(Value::Boolean(!self.eval(inner, row)?.is_null())),
+                UnaryOperator::Negate => negate(self.eval(inner, row)?),
+            },
+            ScalarExpr::Binary { left, op, right } => match op {
+                BinaryOperator::And => {
+                    let left = self.truth(left, row)?;
+                    if left == Some(false) {
+                        return Ok(Value::Boolean(false));
+                    }
+                    Ok(truth_value(match (left, self.truth(right, row)?) {
+                        (_, Some(false)) => Some(false),
+                        (Some(true), Some(true)) => Some(true),
+                        _ => None,
+                    }))
+                }
+                BinaryOperator::Or => {
+                    let left = self.truth(left, row)?;
+                    if left == Some(true) {
+                        return Ok(Value::Boolean(true));
+                    }
+                    Ok(truth_value(match (left, self.truth(right, row)?) {
+                        (_, Some(true)) => Some(true),
+                        (Some(false), Some(false)) => Some(false),
+                        _ => None,
+                    }))
+                }
+                op => binary(*op, self.eval(left, row)?, self.eval(right, row)?),
+            },
+            ScalarExpr::InList {
+                expr: inner,
+                list,
+                negated,
+            } => {
+                let value = self.eval(inner, row)?;
+                let mut candidates = Vec::with_capacity(list.len());
+                for item in list {
+                    candidates.push(self.eval(item, row)?);
+                }
+                Ok(truth_value(in_values(&value, candidates.iter()).map(|found| found != *negated)))
+            }
+            ScalarExpr::Between {
+                expr: inner,
+                low,
+                high,
+                negated,
+            } => {
+                let value = self.eval(inner, row)?;
+                let above = value
+                    .sql_cmp(&self.eval(low, row)?)
+                    .map(|o| o != Ordering::Less);
+                let below = value
+                    .sql_cmp(&self.eval(high, row)?)
+                    .map(|o| o != Ordering::Greater);
+                let between = match (above, below) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                };
+                Ok(truth_value(between.map(|b| b != *negated)))
+            }
+            ScalarExpr::Like {
+                expr: inner,
+                pattern,
+                negated,
+                case_insensitive,
+            } => {
+                let value = self.eval(inner, row)?;
+                let pattern = self.eval(pattern, row)?;
+                if value.is_null() || pattern.is_null() {
+                    return Ok(Value::Null);
+                }
+                let matched = like_match(
+                    &value.to_display_string(),
+                    &pattern.to_display_string(),
+                    *case_insensitive,
+                );
+                Ok(Value::Boolean(matched != *negated))
+            }
+            ScalarExpr::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                let operand = operand
+                    .as_ref()
+                    .map(|operand| self.eval(operand, row))
+                    .transpose()?;
+                for (when, then) in branches {
+                    let hit = match &operand {
+                        Some(operand) => operand.sql_eq(&self.eval(when, row)?) == Some(true),
+                        None => self.truth(when, row)? == Some(true),
+                    };
+                    if hit {
+                        return self.eval(then, row);
+                    }
+                }
+                match else_result {
+                    Some(else_result) => self.eval(else_result, row),
+                    None => Ok(Value::Null),
+                }
+            }
+            ScalarExpr::Cast {
+                expr: inner,
+                data_type,
+            } => data_type.coerce(self.eval(inner, row)?),
+            ScalarExpr::Function { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, row))
+                    .collect::<Result<Vec<_>, DbError>>()?;
+                call_function(name, args)
+            }
+            ScalarExpr::ScalarSubquery {
+                plan, correlated, ..
+            } => {
+                let rows = self.subquery(plan, *correlated, row)?;
+                match rows.len() {
+                    0 => Ok(Value::Null),
+                    1 => Ok(rows[0].first().cloned().unwrap_or(Value::Null)),
+                    n => Err(DbError::Execution(format!(
+                        "Scalar subquery returned {} rows",
+                        n
+                    ))),
+                }
+            }
+            ScalarExpr::InSubquery {
+                expr: inner,
+                plan,
+                negated,
+                correlated,
+            } => {
+                let value = self.eval(inner, row)?;
+                let rows = self.subquery(plan, *correlated, row)?;
+                let candidates = rows.iter().filter_map(|r| r.first());
+                Ok(truth_value(in_values(&value, candidates).map(|found| found != *negated)))
+            }
+            ScalarExpr::Exists {
+                plan,
+                negated,
+                correlated,
+            } => {
+                let rows = self.subquery(plan, *correlated, row)?;
+                Ok(Value::Boolean(rows.is_empty() == *negated))
+            }
+        }
+    }
+
+    // Rows of a subquery evaluated for `row`
+    fn subquery(
+        &self,
+        plan: &PlanNode,
+        correlated: bool,
+        row: &[Value],
+    ) -> Result<Rc<Vec<Vec<Value>>>, DbError> {
+        let key = plan as *const PlanNode;
+        if !correlated {
+            if let Some(rows) = self.subqueries.borrow().get(&key) {
+                return Ok(Rc::clone(rows));
+            }
+        }
+
+        // An uncorrelated subquery never reads the current row, but still
+        // needs a frame so deeper references keep their depth
+        let mut outer = self.outer.to_vec();
+        outer.push(if correlated { row.to_vec() } else { Vec::new() });
+        let rows = Rc::new(self.executor.execute_node(plan, &outer)?.rows);
+
+        if !correlated {
+            self.subqueries.borrow_mut().insert(key, Rc::clone(&rows));
+        }
+        Ok(rows)
+    }
+}
+
+// An index lookup with its bounds evaluated to keys
+struct IndexLookup {
+    prefixes: Vec<Vec<IndexKey>>,
+    lower: Bound<IndexKey>,
+    upper: Bound<IndexKey>,
+}
+
+impl IndexLookup {
+    // `None` if a prefix value cannot be compared with the keys, so the
+    // index cannot find the rows
+    fn evaluate(
+        index: &TableIndex,
+        bounds: &IndexBounds,
+        eval: &Evaluator,
+        row: &[Value],
+    ) -> Result<Option<Self>, DbError> {
+        let types = index.types();
+        let mut prefixes = Vec::with_capacity(bounds.prefixes.len());
+        'prefixes: for prefix in &bounds.prefixes {
+            let mut keys = Vec::with_capacity(prefix.len());
+            for (expr, data_type) in prefix.iter().zip(types) {
+                match bound_key(eval.eval(expr, row)?, data_type) {
+                    BoundKey::Key(key) => keys.push(key),
+                    // Nothing equals NULL
+                    BoundKey::Null => continue 'prefixes,
+                    BoundKey::Incomparable => return Ok(None),
+                }
+            }
+            prefixes.push(keys);
+        }
+
+        let next = types.get(bounds.prefixes.first().map_or(0, Vec::len));
+        let mut range = [Bound::Unbounded, Bound::Unbounded];
+        for (slot, bound) in range.iter_mut().zip([&bounds.lower, &bounds.upper]) {
+            let (Some(bound), Some(data_type)) = (bound, next) else {
+                continue;
+            };
+            match bound_key(eval.eval(&bound.value, row)?, data_type) {
+                BoundKey::Key(key) if bound.inclusive => *slot = Bound::Included(key),
+                BoundKey::Key(key) => *slot = Bound::Excluded(key),
+                BoundKey::Null => prefixes.clear(),
+                // A wider range only returns more rows to recheck
+                BoundKey::Incomparable => {}
+            }
+        }
+        let [lower, upper] = range;
+        Ok(Some(Self {
+            prefixes,
+            lower,
+            upper,
+        }))
+    }
+}
+
+// A lookup value as a key of an index column
+enum BoundKey {
+    Key(IndexKey),
+    Null,
+    // The value compares with the column's values other than their keys do
+    Incomparable,
+}
+
+fn bound_key(value: Value, data_type: &DataType) -> BoundKey {
+    if value.is_null() {
+        return BoundKey::Null;
+    }
+    let Ok(coerced) = data_type.coerce(value.clone()) else {
+        return BoundKey::Incomparable;
+    };
+    let comparable = match data_type {
+        DataType::Varchar(_) | DataType::Text => matches!(value, Value::String(_)),
+        _ => value.sql_cmp(&coerced) == Some(Ordering::Equal),
+    };
+    if comparable {
+        BoundKey::Key(IndexKey::from_values(std::slice::from_ref(&coerced)))
+    } else {
+        BoundKey::Incomparable
+    }
+}
+
+fn truth_value(truth: Option<bool>) -> Value {
+    truth.map(Value::Boolean).unwrap_or(Value::Null)
+}
+
+// `value IN (candidates)`: UNKNOWN when nothing matches but a comparison was
+// UNKNOWN, and FALSE for an empty list
+fn in_values<'v>(value: &Value, candidates: impl Iterator<Item = &'v Value>) -> Option<bool> {
+    let mut result = Some(false);
+    for candidate in candidates {
+        match value.sql_eq(candidate) {
+            Some(true) => return Some(true),
+            Some(false) => {}
+            None => result = None,
+        }
+    }
+    result
+}
+
+// Integer, float or decimal value of a number or numeric string
+fn number(value: &Value) -> Option<Value> {
+    match value {
+        Value::Integer(_) | Value::Float(_) | Value::Numeric(_) => Some(value.clone()),
+        Value::String(s) => match Value::from_sql_literal(s) {
+            number @ (Value::Integer(_) | Value::Float(_)) => Some(number),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn negate(value: Value) -> Result<Value, DbError> {
+    match number(&value) {
+        _ if value.is_null() => Ok(Value::Null),
+        Some(Value::Integer(i)) => i
+            .checked_neg()
+            .map(Value::Integer)
+            .ok_or_else(|| DbError::Execution(format!("Integer overflow in -{}", i))),
+        Some(Value::Float(f)) => Ok(Value::Float(-f)),
+        Some(Value::Numeric(d)) => Ok(Value::Numeric(-d)),
+        _ => match value {
+            Value::Interval(i) => i
+                .checked_neg()
+                .map(Value::Interval)
+                .ok_or_else(|| DbError::Execution(format!("Interval overflow in -{}", i))),
+            _ => Err(DbError::Execution(format!("Cannot negate '{}'", value))),
+        },
+    }
+}
+
+// Comparison, concatenation and arithmetic; AND and OR are evaluated by the
+// caller since they may short-circuit
+fn binary(op: BinaryOperator, left: Value, right: Value) -> Result<Value, DbError> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    let test: fn(Ordering) -> bool = match op {
+        BinaryOperator::Equal => |o| o == Ordering::Equal,
+        BinaryOperator::NotEqual => |o| o != Ordering::Equal,
+        BinaryOperator::LessThan => |o| o == Ordering::Less,
+        BinaryOperator::LessThanOrEqual => |o| o != Ordering::Greater,
+        BinaryOperator::GreaterThan => |o| o == Ordering::Greater,
+        BinaryOperator::GreaterThanOrEqual => |o| o != Ordering::Less,
+        BinaryOperator::Like => {
+            let matched = like_match(
+                &left.to_display_string(),
+                &right.to_display_string(),
+                false,
+            );
+            return Ok(Value::Boolean(matched));
+        }
+        BinaryOperator::Concat => {
+            return Ok(Value::String(
+                left.to_display_string() + &right.to_display_string(),
+            ))
+        }
+        BinaryOperator::And | BinaryOperator::Or => {
+            return Err(DbError::Internal(format!(
+                "{} must be evaluated with short-circuiting",
+                binary_operator_symbol(op)
+            )))
+        }
+        _ => return arithmetic(op, &left, &right),
+    };
+    Ok(truth_value(left.sql_cmp(&right).map(test)))
+}
+
+// Integer arithmetic is checked and stays integral (division truncates);
+// decimals stay exact, and anything else involving a float is done in
+// floating point. Dates move by whole days and the difference of two dates
+// is a number of days; intervals are handled by `interval_arithmetic`.
+fn arithmetic(op: BinaryOperator, left: &Value, right: &Value) -> Result<Value, DbError> {
+    let symbol = binary_operator_symbol(op);
+    let overflow = || {
+        DbError::Execution(format!(
+            "Integer overflow in {} {} {}",
+            left, symbol, right
+        ))
+    };
+    match (op, left, right) {
+        (BinaryOperator::Add, Value::Date(d), Value::Integer(n))
+        | (BinaryOperator::Add, Value::Integer(n), Value::Date(d)) => {
+            return d.checked_add(*n).map(Value::Date).ok_or_else(overflow)
+        }
+        (BinaryOperator::Subtract, Value::Date(d), Value::Integer(n)) => {
+            return d.checked_sub(*n).map(Value::Date).ok_or_else(overflow)
+        }
+        (BinaryOperator::Subtract, Value::Date(a), Value::Date(b)) => {
+            return a.checked_sub(*b).map(Value::Integer).ok_or_else(overflow)
+        }
+        _ => {}
+    }
+    if let Some(result) = interval_arithmetic(op, left, right) {
+        return result;
+    }
+
+    let invalid = || {
+        DbError::Execution(format!(
+            "Cannot apply {} to '{}' and '{}'",
+            symbol, left, right
+        ))
+    };
+    let division_by_zero = || DbError::Execution("Division by zero".to_string());
+    match (
+        number(left).ok_or_else(invalid)?,
+        number(right).ok_or_else(invalid)?,
+    ) {
+        (Value::Integer(a), Value::Integer(b)) => {
+            let result = match op {
+                BinaryOperator::Add => a.checked_add(b),
+                BinaryOperator::Subtract => a.checked_sub(b),
+                BinaryOperator::Multiply => a.checked_mul(b),
+                BinaryOperator::Divide | BinaryOperator::Modulo if b == 0 => {
+                    return Err(division_by_zero())
+                }
+                BinaryOperator::Divide => a.checked_div(b),
+                BinaryOperator::Modulo => a.checked_rem(b),
+                _ => return Err(invalid()),
+            };
+            result.map(Value::Integer).ok_or_else(overflow)
+        }
+        (l @ Value::Numeric(_), r) | (l, r @ Value::Numeric(_)) => {
+            let (Some(a), Some(b)) = (decimal(&l), decimal(&r)) else {
+                return Err(invalid());
+            };
+            let result = match op {
+                BinaryOperator::Add => a.checked_add(&b),
+                BinaryOperator::Subtract => a.checked_sub(&b),
+                BinaryOperator::Multiply => a.checked_mul(&b),
+                BinaryOperator::Divide | BinaryOperator::Modulo if b.is_zero() => {
+                    return Err(division_by_zero())
+                }
+                BinaryOperator::Divide => a.checked_div(&b),
+                BinaryOperator::Modulo => a.checked_rem(&b),
+                _ => return Err(invalid()),
+            };
+            result.map(Value::Numeric).ok_or_else(|| {
+                DbError::Execution(format!("Numeric overflow in {} {} {}", left, symbol, right))
+            })
+        }
+        (l, r) => {
+            let (Some(a), Some(b)) = (l.as_f64(), r.as_f64()) else {
+                return Err(invalid());
+            };
+            let result = match op {
+                BinaryOperator::Add => a + b,
+                BinaryOperator::Subtract => a - b,
+                BinaryOperator::Multiply => a * b,
+                BinaryOperator::Divide | BinaryOperator::Modulo if b == 0.0 => {
+                    return Err(division_by_zero())
+                }
+                BinaryOperator::Divide => a / b,
+                BinaryOperator::Modulo => a % b,
+                _ => return Err(invalid()),
+            };
+            Ok(Value::Float(result))
+        }
+    }
+}
+
+// Exact value of a number; a float is taken as the decimal it prints as
+fn decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Integer(i) => Some(Decimal::from_i64(*i)),
+        Value::Float(f) => Decimal::from_f64(*f),
+        Value::Numeric(d) => Some(*d),
+        _ => None,
+    }
+}
+
+// Interval operand, reading a string as an interval unless it is a number
+fn interval(value: &Value) -> Option<Interval> {
+    match value {
+        Value::Interval(i) => Some(*i),
+        Value::String(s) if number(value).is_none() => Interval::parse(s),
+        _ => None,
+    }
+}
+
+// Timestamps and dates move by intervals (a date becomes a timestamp), the
+// difference of two timestamps is an interval, and intervals add to each
+// other and scale by numbers. None when no operand is an interval or a
+// timestamp difference, so ordinary arithmetic applies.
+fn interval_arithmetic(
+    op: BinaryOperator,
+    left: &Value,
+    right: &Value,
+) -> Option<Result<Value, DbError>> {
+    let symbol = binary_operator_symbol(op);
+    let overflow = || {
+        DbError::Execution(format!(
+            "Interval overflow in {} {} {}",
+            left, symbol, right
+        ))
+    };
+    let shift = |time: &Value, by: Interval| {
+        let moved = by.add_to(time.time_micros()?)?;
+        Some(match time {
+            Value::TimestampTz(_) => Value::TimestampTz(moved),
+            _ => Value::Timestamp(moved),
+        })
+    };
+    let is_time = |value: &Value| {
+        matches!(
+            value,
+            Value::Date(_) | Value::Timestamp(_) | Value::TimestampTz(_)
+        )
+    };
+    let result = match (op, left, right) {
+        (BinaryOperator::Add | BinaryOperator::Subtract, time, other)
+            if is_time(time) && !is_time(other) =>
+        {
+            let by = interval(other)?;
+            let by = match op {
+                BinaryOperator::Subtract => by.checked_neg(),
+                _ => Some(by),
+            };
+            by.and_then(|by| shift(time, by))
+        }
+        (BinaryOperator::Add, other, time) if is_time(time) => shift(time, interval(other)?),
+        (BinaryOperator::Subtract, later, earlier) if is_time(later) && is_time(earlier) => {
+            Interval::between(later.time_micros()?, earlier.time_micros()?).map(Value::Interval)
+        }
+        (BinaryOperator::Add | BinaryOperator::Subtract, l, r)
+            if matches!(l, Value::Interval(_)) || matches!(r, Value::Interval(_)) =>
+        {
+            let (a, b) = (interval(l)?, interval(r)?);
+            match op {
+                BinaryOperator::Add => a.checked_add(&b),
+                _ => a.checked_sub(&b),
+            }
+            .map(Value::Interval)
+        }
+        (BinaryOperator::Multiply, Value::Interval(i), n)
+        | (BinaryOperator::Multiply, n, Value::Interval(i)) => {
+            i.checked_mul(number(n)?.as_f64()?).map(Value::Interval)
+        }
+        (BinaryOperator::Divide, Value::Interval(i), n) => {
+            let divisor = number(n)?.as_f64()?;
+            if divisor == 0.0 {
+                return Some(Err(DbError::Execution("Division by zero".to_string())));
+            }
+            i.checked_div(divisor).map(Value::Interval)
+        }
+        _ => return None,
+    };
+    Some(result.ok_or_else(overflow))
+}
+
+// SQL LIKE: `%` matches any run of characters and `_` exactly one
+fn like_match(value: &str, pattern: &str, case_insensitive: bool) -> bool {
+    let fold = |s: &str| -> Vec<char> {
+        if case_insensitive {
+            s.to_lowercase().chars().collect()
+        } else {
+            s.chars().collect()
+        }
+    };
+    let (value, pattern) = (fold(value), fold(pattern));
+
+    // Greedy match that backtracks to the most recent `%`
+    let (mut v, mut p) = (0, 0);
+    let mut last_wildcard: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                last_wildcard = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '_' || c == value[v] => {
+                v += 1;
+                p += 1;
+            }
+            _ => match last_wildcard {
+                Some((wildcard, start)) => {
+                    last_wildcard = Some((wildcard, start + 1));
+                    p = wildcard + 1;
+                    v = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+// Built-in scalar functions; most return NULL when an argument is NULL
+fn call_function(name: &str, args: Vec<Value>) -> Result<Value, DbError> {
+    let arity = |min: usize, max: usize| {
+        if args.len() < min || args.len() > max {
+            return Err(DbError::Execution(format!(
+                "{} takes {} arguments, got {}",
+                name,
+                if min == max {
+                    min.to_string()
+                } else {
+                    format!("{} to {}", min, max)
+                },
+                args.len()
+            )));
+        }
+        Ok(())
+    };
+    let not_numeric = |value: &Value| {
+        DbError::Execution(format!("{} expects a number, got '{}'", name, value))
+    };
+    let integer_arg = |value: &Value| match number(value) {
+        Some(Value::Integer(i)) => Ok(i),
+        _ => Err(DbError::Execution(format!(
+            "{} expects an integer, got '{}'",
+            name, value
+        ))),
+    };
+
+    match name {
+        "COALESCE" => {
+            return Ok(args
+                .into_iter()
+                .find(|v| !v.is_null())
+                .unwrap_or(Value::Null))
+        }
+        "CONCAT" => {
+            return Ok(Value::String(
+                args.iter()
+                    .filter(|v| !v.is_null())
+                    .map(Value::to_display_string)
+                    .collect(),
+            ))
+        }
+        "NULLIF" => {
+            arity(2, 2)?;
+            let mut args = args.into_iter();
+            let (first, second) = (args.next().unwrap(), args.next().unwrap());
+            return Ok(match first.sql_eq(&second) {
+                Some(true) => Value::Null,
+                _ => first,
+            });
+        }
+        "CURRENT_DATE" => {
+            arity(0, 0)?;
+            return Ok(Value::Date(
+                chrono::Utc::now().timestamp().div_euclid(86_400),
+            ));
+        }
+        "CURRENT_TIMESTAMP" | "NOW" => {
+            arity(0, 0)?;
+            return Ok(Value::Timestamp(chrono::Utc::now().timestamp_micros()));
+        }
+        "GEN_RANDOM_UUID" => {
+            arity(0, 0)?;
+            return Ok(Value::Uuid(uuid::Uuid::new_v4()));
+        }
+        // ARRAY[...] literals keep NULL elements
+        "ARRAY" => return Ok(Value::Array(args)),
+        _ => {}
+    }
+
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let text = |i: usize| args[i].to_display_string();
+
+    match name {
+        "ABS" => {
+            arity(1, 1)?;
+            match number(&args[0]) {
+                Some(Value::Integer(i)) => i.checked_abs().map(Value::Integer).ok_or_else(|| {
+                    DbError::Execution(format!("Integer overflow in ABS({})", i))
+                }),
+                Some(Value::Float(f)) => Ok(Value::Float(f.abs())),
+                Some(Value::Numeric(d)) => Ok(Value::Numeric(d.abs())),
+                _ => Err(not_numeric(&args[0])),
+            }
+        }
+        "CEIL" | "FLOOR" => {
+            arity(1, 1)?;
+            match number(&args[0]) {
+                Some(Value::Integer(i)) => Ok(Value::Integer(i)),
+                Some(Value::Float(f)) if name == "CEIL" => Ok(Value::Float(f.ceil())),
+                Some(Value::Float(f)) => Ok(Value::Float(f.floor())),
+                Some(Value::Numeric(d)) if name == "CEIL" => Ok(Value::Numeric(d.ceil())),
+                Some(Value::Numeric(d)) => Ok(Value::Numeric(d.floor())),
+                _ => Err(not_numeric(&args[0])),
+            }
+        }
+        "ROUND" => {
+            arity(1, 2)?;
+            let digits = match args.get(1) {
+                Some(digits) => i32::try_from(integer_arg(digits)?)
+                    .map_err(|_| DbError::Execution("ROUND precision is out of range".to_string()))?,
+                None => 0,
+            };
+            let scale = 10f64.powi(digits);
+            match number(&args[0]) {
+                Some(Value::Integer(i)) if digits >= 0 => Ok(Value::Integer(i)),
+                Some(Value::Integer(i)) => Ok(Value::Integer(
+                    ((i as f64 * scale).round() / scale) as i64,
+                )),
+                Some(Value::Float(f)) => Ok(Value::Float((f * scale).round() / scale)),
+                Some(Value::Numeric(d)) => d
+                    .round(digits)
+                    .map(Value::Numeric)
+                    .ok_or_else(|| DbError::Execution(format!("Numeric overflow in ROUND({})", d))),
+                _ => Err(not_numeric(&args[0])),
+            }
+        }
+        "LENGTH" => {
+            arity(1, 1)?;
+            Ok(Value::Integer(text(0).chars().count() as i64))
+        }
+        "LOWER" => {
+            arity(1, 1)?;
+            Ok(Value::String(text(0).to_lowercase()))
+        }
+        "UPPER" => {
+            arity(1, 1)?;
+            Ok(Value::String(text(0).to_uppercase()))
+        }
+        "TRIM" | "LTRIM" | "RTRIM" => {
+            arity(1, 2)?;
+            let chars: Vec<char> = match args.get(1) {
+                Some(_) => text(1).chars().collect(),
+                None => vec![' '],
+            };
+            let value = text(0);
+            let trimmed = match name {
+                "LTRIM" => value.trim_start_matches(chars.as_slice()),
+                "RTRIM" => value.trim_end_matches(chars.as_slice()),
+                _ => value.trim_matches(chars.as_slice()),
+            };
+            Ok(Value::String(trimmed.to_string()))
+        }
+        "REPLACE" => {
+            arity(3, 3)?;
+            let (value, from) = (text(0), text(1));
+            if from.is_empty() {
+                return Ok(Value::String(value));
+            }
+            Ok(Value::String(value.replace(&from, &text(2))))
+        }
+        "SUBSTRING" => {
+            // SUBSTRING(s FROM start FOR length), with 1-based positions
+            arity(2, 3)?;
+            let start = integer_arg(&args[1])?;
+            let end = match args.get(2) {
+                Some(length) => {
+                    let length = integer_arg(length)?;
+                    if length < 0 {
+                        return Err(DbError::Execution(
+                            "Negative SUBSTRING length".to_string(),
+                        ));
+                    }
+                    Some(start.saturating_add(length))
+                }
+                None => None,
+            };
+            let skip = start.max(1) - 1;
+            let take = end.map(|end| end.saturating_sub(1).saturating_sub(skip).max(0));
+            let text = text(0);
+            let chars = text.chars().skip(skip as usize);
+            Ok(Value::String(match take {
+                Some(take) => chars.take(take as usize).collect(),
+                None => chars.collect(),
+            }))
+        }
+        "L2_DISTANCE" | "COSINE_DISTANCE" | "INNER_PRODUCT" => {
+            arity(2, 2)?;
+            let (a, b) = (vector_arg(name, &args[0])?, vector_arg(name, &args[1])?);
+            if a.len() != b.len() {
+                return Err(DbError::Execution(format!(
+                    "{} of vectors with {} and {} dimensions",
+                    name,
+                    a.len(),
+                    b.len()
+                )));
+            }
+            let result = match name {
+                "L2_DISTANCE" => hnsw::l2_distance(&a, &b),
+                "COSINE_DISTANCE" => hnsw::cosine_distance(&a, &b),
+                _ => hnsw::inner_product(&a, &b),
+            };
+            // The cosine distance to a zero vector is undefined
+            Ok(match result.is_nan() {
+                true => Value::Null,
+                false => Value::Float(result),
+            })
+        }
+        _ => Err(DbError::NotImplemented(format!("Function {}", name))),
+    }
+}
+
+// A vector argument, given as a vector or as its text
+fn vector_arg<'v>(name: &str, value: &'v Value) -> Result<Cow<'v, [f64]>, DbError> {
+    let vector = match value {
+        Value::Vector(v) => Some(Cow::Borrowed(v.as_slice())),
+        Value::String(s) => Value::parse_vector(s).map(Cow::Owned),
+        _ => None,
+    };
+    vector.ok_or_else(|| DbError::Execution(format!("{} expects a vector, got '{}'", name, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SqlParser;
+
+    #[test]
+    fn test_executor() -> Result<(), DbError> {
+        let catalog = Arc::new(Catalog::new());
+        let txn_manager = Arc::new(TransactionManager::new());
+        let executor = Executor::new(catalog, txn_manager);
+
+        let parser = SqlParser::new();
+        let stmts = parser.parse("CREATE TABLE users (id INT, name VARCHAR(255))")?;
+
+        let result = executor.execute(stmts[0].clone())?;
+        assert_eq!(result.rows_affected, 0);
+
+        Ok(())
+    }
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    fn run(executor: &Executor, sql: &str) -> Result<QueryResult, DbError> {
+        let mut stmts = SqlParser::new().parse(sql)?;
+        executor.execute(stmts.remove(0))
+    }
+
+    fn users_executor() -> Result<Executor, DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        run(
+            &executor,
+            "CREATE TABLE users (id INT, name VARCHAR(255), age INT)",
+        )?;
+        run(
+            &executor,
+            "INSERT INTO users VALUES (1, 'alice', 34), (2, 'bob', 27), (3, 'carol', 45)",
+        )?;
+        Ok(executor)
+    }
+
+    #[test]
+    fn test_insert_then_select() -> Result<(), DbError> {
+        let executor = users_executor()?;
+
+        let result = run(&executor, "SELECT * FROM users")?;
+        assert_eq!(result.columns, vec!["id", "name", "age"]);
+        assert_eq!(result.rows.len(), 3);
+
+        let result = run(
+            &executor,
+            "SELECT name FROM users WHERE age > 30 ORDER BY age DESC",
+        )?;
+        assert_eq!(result.columns, vec!["name"]);
+        assert_eq!(result.rows, vec![vec![text("carol")], vec![text("alice")]]);
+
+        let result = run(
+            &executor,
+            "SELECT id FROM users ORDER BY id LIMIT 1 OFFSET 1",
+        )?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(2)]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_fills_missing_columns() -> Result<(), DbError> {
+        let executor = users_executor()?;
+
+        run(&executor, "INSERT INTO users (name, id) VALUES ('dave', 4)")?;
+        let result = run(&executor, "SELECT * FROM users WHERE id = 4")?;
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(4), text("dave"), Value::Null]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_and_delete() -> Result<(), DbError> {
+        let executor = users_executor()?;
+
+        let result = run(&executor, "UPDATE users SET name = 'robert' WHERE id = 2")?;
+        assert_eq!(result.rows_affected, 1);
+        let result = run(&executor, "SELECT name FROM users WHERE id = 2")?;
+        assert_eq!(result.rows, vec![vec![text("robert")]]);
+
+        let result = run(&executor, "DELETE FROM users WHERE age < 40")?;
+        assert_eq!(result.rows_affected, 2);
+        let result = run(&executor, "SELECT name FROM users")?;
+        assert_eq!(result.rows, vec![vec![text("carol")]]);
+
+        let result = run(&executor, "TRUNCATE TABLE users")?;
+        assert_eq!(result.rows_affected, 1);
+        assert!(run(&executor, "SELECT * FROM users")?.rows.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_null_enforced() -> Result<(), DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        run(&executor, "CREATE TABLE t (id INT NOT NULL, note TEXT)")?;
+
+        assert!(run(&executor, "INSERT INTO t (note) VALUES ('x')").is_err());
+        assert!(run(&executor, "SELECT * FROM t")?.rows.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_table_discards_rows() -> Result<(), DbError> {
+        let executor = users_executor()?;
+
+        run(&executor, "DROP TABLE users")?;
+        run(
+            &executor,
+            "CREATE TABLE users (id INT, name VARCHAR(255), age INT)",
+        )?;
+        assert!(run(&executor, "SELECT * FROM users")?.rows.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_index_builds_a_tree_over_existing_rows() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        let indexes = executor.table_store().indexes();
+
+        run(&executor, "CREATE INDEX users_by_age ON users (age)")?;
+        let tree = indexes.open_tree("users_by_age")?.expect("index tree");
+        let rows = tree.range(Bound::Included(&IndexKey::Integer(30)), Bound::Unbounded)?;
+        assert_eq!(rows.len(), 2);
+        drop(tree);
+
+        run(&executor, "CREATE INDEX users_by_name ON users (name, id)")?;
+        run(&executor, "DROP INDEX users_by_age")?;
+        assert!(indexes.open_tree("users_by_age")?.is_none());
+        run(&executor, "DROP TABLE users")?;
+        assert!(indexes.tree_names()?.is_empty());
+        Ok(())
+    }
+
+    fn explain(executor: &Executor, sql: &str) -> Result<String, DbError> {
+        let mut stmts = SqlParser::new().parse(sql)?;
+        executor.explain(&stmts.remove(0))
+    }
+
+    fn ids(result: &QueryResult) -> Vec<Value> {
+        result.rows.iter().map(|row| row[0].clone()).collect()
+    }
+
+    #[test]
+    fn test_index_scans() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(
+            &executor,
+            "INSERT INTO users VALUES (4, 'carl', 27), (5, 'dan', NULL)",
+        )?;
+        run(&executor, "CREATE INDEX users_by_age ON users (age)")?;
+        run(&executor, "CREATE INDEX users_by_name ON users (name)")?;
+
+        let plan = explain(&executor, "SELECT id FROM users WHERE age = 27")?;
+        assert!(
+            plan.contains("Index Scan using users_by_age on users"),
+            "{}",
+            plan
+        );
+        let plan = explain(&executor, "SELECT id FROM users WHERE id + 1 = 2")?;
+        assert!(plan.contains("Seq Scan on users"), "{}", plan);
+
+        let queries = [
+            (
+                "SELECT id FROM users WHERE age = 27 ORDER BY id",
+                vec![2, 4],
+            ),
+            (
+                "SELECT id FROM users WHERE 30 < age ORDER BY id",
+                vec![1, 3],
+            ),
+            (
+                "SELECT id FROM users WHERE age BETWEEN 27 AND 34 ORDER BY id",
+                vec![1, 2, 4],
+            ),
+            (
+                "SELECT id FROM users WHERE age IN (45, 27, 27) ORDER BY id",
+                vec![2, 3, 4],
+            ),
+            (
+                "SELECT id FROM users WHERE name LIKE 'car%' ORDER BY id",
+                vec![3, 4],
+            ),
+            ("SELECT id FROM users WHERE age = NULL", vec![]),
+        ];
+        for (sql, expected) in &queries {
+            let expected: Vec<Value> = expected.iter().map(|&id| Value::Integer(id)).collect();
+            assert_eq!(ids(&run(&executor, sql)?), expected, "{}", sql);
+        }
+
+        // Only key columns are read: the rows come from the index alone
+        let sql = "SELECT age FROM users WHERE age >= 34 ORDER BY age";
+        assert!(explain(&executor, sql)?.contains("Index Only Scan using users_by_age"));
+        assert_eq!(
+            ids(&run(&executor, sql)?),
+            vec![Value::Integer(34), Value::Integer(45)]
+        );
+
+        // Writes keep the index in step
+        run(&executor, "UPDATE users SET age = 27 WHERE id = 3")?;
+        run(&executor, "DELETE FROM users WHERE id = 2")?;
+        run(&executor, "INSERT INTO users VALUES (6, 'erin', 27)")?;
+        let result = run(&executor, "SELECT id FROM users WHERE age = 27 ORDER BY id")?;
+        assert_eq!(
+            ids(&result),
+            vec![Value::Integer(3), Value::Integer(4), Value::Integer(6)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_scan_sees_the_transaction_snapshot() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(&executor, "CREATE INDEX users_by_age ON users (age)")?;
+
+        let txn = executor.begin_transaction(Some(IsolationLevel::RepeatableRead))?;
+        let reader = executor.with_transaction(txn.clone());
+        run(&executor, "UPDATE users SET age = 27 WHERE id = 1")?;
+        run(&executor, "INSERT INTO users VALUES (4, 'dave', 27)")?;
+
+        // The snapshot predates both commits
+        let sql = "SELECT id FROM users WHERE age = 27 ORDER BY id";
+        assert_eq!(ids(&run(&reader, sql)?), vec![Value::Integer(2)]);
+        // Its own writes are seen
+        run(&reader, "UPDATE users SET age = 27 WHERE id = 3")?;
+        assert_eq!(
+            ids(&run(&reader, sql)?),
+            vec![Value::Integer(2), Value::Integer(3)]
+        );
+        txn.rollback()?;
+
+        assert_eq!(
+            ids(&run(&executor, sql)?),
+            vec![Value::Integer(1), Value::Integer(2), Value::Integer(4)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_index() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(&executor, "INSERT INTO users VALUES (4, 'alice', 50)")?;
+        let duplicate = run(
+            &executor,
+            "CREATE UNIQUE INDEX users_by_name ON users (name)",
+        );
+        assert!(matches!(duplicate, Err(DbError::ConstraintViolation(_))));
+
+        run(&executor, "CREATE UNIQUE INDEX users_by_id ON users (id)")?;
+        for sql in [
+            "INSERT INTO users VALUES (2, 'bobby', 20)",
+            "INSERT INTO users VALUES (7, 'x', 1), (7, 'y', 2)",
+            "UPDATE users SET id = 3 WHERE id = 1",
+        ] {
+            let result = run(&executor, sql);
+            assert!(
+                matches!(result, Err(DbError::ConstraintViolation(_))),
+                "{}",
+                sql
+            );
+        }
+
+        // Keys may move past each other within one statement, and NULL
+        // keys never conflict
+        run(&executor, "UPDATE users SET id = id + 1")?;
+        run(
+            &executor,
+            "INSERT INTO users VALUES (NULL, 'x', 1), (NULL, 'y', 2)",
+        )?;
+        let result = run(&executor, "SELECT id FROM users WHERE id >= 1 ORDER BY id")?;
+        assert_eq!(
+            ids(&result),
+            (2..=5).map(Value::Integer).collect::<Vec<_>>()
+        );
+
+        // A deleted key can be used again
+        run(&executor, "DELETE FROM users WHERE id = 2")?;
+        run(&executor, "INSERT INTO users VALUES (2, 'zed', 60)")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_index() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(
+            &executor,
+            "CREATE INDEX older_users ON users (name) WHERE age > 30",
+        )?;
+
+        let sql = "SELECT id FROM users WHERE name = 'carol' AND age > 30";
+        assert!(explain(&executor, sql)?.contains("Index Scan using older_users"));
+        assert_eq!(ids(&run(&executor, sql)?), vec![Value::Integer(3)]);
+
+        // The index does not hold bob, so it cannot answer for him
+        let sql = "SELECT id FROM users WHERE name = 'bob'";
+        assert!(explain(&executor, sql)?.contains("Seq Scan on users"));
+        assert_eq!(ids(&run(&executor, sql)?), vec![Value::Integer(2)]);
+
+        // Rows move in and out of the index as they change
+        run(&executor, "UPDATE users SET age = 40 WHERE id = 2")?;
+        run(&executor, "UPDATE users SET age = 20 WHERE id = 3")?;
+        let sql = "SELECT id FROM users WHERE name IN ('bob', 'carol') AND age > 30";
+        assert_eq!(ids(&run(&executor, sql)?), vec![Value::Integer(2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_nested_loop_join() -> Result<(), DbError> {
+        let executor = shop_executor()?;
+        run(
+            &executor,
+            "CREATE INDEX orders_by_customer ON orders (customer_id)",
+        )?;
+
+        let sql = "SELECT c.name, o.total FROM customers c \
+                   JOIN orders o ON o.customer_id = c.id ORDER BY o.total";
+        let plan = explain(&executor, sql)?;
+        assert!(
+            plan.contains("Index Nested Loop Join using orders_by_customer on orders"),
+            "{}",
+            plan
+        );
+        let expected = run(&executor, sql)?;
+        run(&executor, "DROP INDEX orders_by_customer")?;
+        assert_eq!(run(&executor, sql)?.rows, expected.rows);
+
+        run(
+            &executor,
+            "CREATE INDEX orders_by_customer ON orders (customer_id)",
+        )?;
+        let sql = "SELECT c.name FROM customers c \
+                   LEFT JOIN orders o ON o.customer_id = c.id WHERE o.id IS NULL";
+        assert!(explain(&executor, sql)?.contains("Index Nested Loop Left Join"));
+        assert_eq!(run(&executor, sql)?.rows, vec![vec![text("bob and eve")]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_alter_table_rebuilds_indexes() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(&executor, "CREATE INDEX users_by_age ON users (age)")?;
+        run(&executor, "CREATE INDEX users_by_name ON users (name)")?;
+
+        run(&executor, "ALTER TABLE users DROP COLUMN name")?;
+        assert!(executor.catalog.get_index("users_by_name").is_err());
+        let sql = "SELECT id FROM users WHERE age = 45";
+        assert!(explain(&executor, sql)?.contains("Index Scan using users_by_age"));
+        assert_eq!(ids(&run(&executor, sql)?), vec![Value::Integer(3)]);
+        Ok(())
+    }
+
+    // Points (i mod 8, i / 8) of a grid, one per id
+    fn items_executor() -> Result<Executor, DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        run(
+            &executor,
+            "CREATE TABLE items (id INT, embedding VECTOR(2) NOT NULL)",
+        )?;
+        let values: Vec<String> = (1..=40)
+            .map(|i| format!("({}, '[{}, {}]')", i, i % 8, i / 8))
+            .collect();
+        run(
+            &executor,
+            &format!("INSERT INTO items VALUES {}", values.join(", ")),
+        )?;
+        Ok(executor)
+    }
+
+    #[test]
+    fn test_vector_distance_functions() -> Result<(), DbError> {
+        let executor = items_executor()?;
+        let result = run(
+            &executor,
+            "SELECT L2_DISTANCE(embedding, '[4, 4]'), COSINE_DISTANCE(embedding, '[0, 2]'), \
+             INNER_PRODUCT(embedding, '[3, 4]'), embedding <=> '[2, 0]' FROM items WHERE id = 1",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Float(5.0),
+                Value::Float(1.0),
+                Value::Float(3.0),
+                Value::Float(0.0)
+            ]]
+        );
+
+        let mismatch = run(&executor, "SELECT L2_DISTANCE(embedding, '[1]') FROM items");
+        assert!(matches!(mismatch, Err(DbError::Execution(_))));
+        let mismatch = run(&executor, "INSERT INTO items VALUES (41, '[1, 2, 3]')");
+        assert!(matches!(mismatch, Err(DbError::InvalidInput(_))));
+        let btree = run(&executor, "CREATE INDEX items_btree ON items (embedding)");
+        assert!(btree.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hnsw_index_scan() -> Result<(), DbError> {
+        let executor = items_executor()?;
+        let sql = "SELECT id FROM items ORDER BY L2_DISTANCE(embedding, '[3.2, 2.1]') LIMIT 3";
+        let expected = run(&executor, sql)?;
+        assert_eq!(
+            ids(&expected),
+            vec![Value::Integer(19), Value::Integer(20), Value::Integer(27)]
+        );
+
+        run(
+            &executor,
+            "CREATE INDEX items_embedding ON items USING hnsw (embedding vector_l2_ops) \
+             WITH (m = 8, ef_construction = 32)",
+        )?;
+        let plan = explain(&executor, sql)?;
+        assert!(
+            plan.contains("Nearest Neighbor Scan using items_embedding on items (k = 3"),
+            "{}",
+            plan
+        );
+        assert_eq!(run(&executor, sql)?.rows, expected.rows);
+
+        // Only a sort by the index's own distance can use it
+        let sql = "SELECT id FROM items ORDER BY COSINE_DISTANCE(embedding, '[1, 1]') LIMIT 3";
+        assert!(explain(&executor, sql)?.contains("Seq Scan on items"));
+
+        // The index follows the rows as they change
+        run(&executor, "DELETE FROM items WHERE id = 19")?;
+        run(
+            &executor,
+            "UPDATE items SET embedding = '[3.2, 2.2]' WHERE id = 40",
+        )?;
+        let sql = "SELECT id FROM items ORDER BY L2_DISTANCE(embedding, '[3.2, 2.1]') LIMIT 2";
+        assert_eq!(
+            ids(&run(&executor, sql)?),
+            vec![Value::Integer(40), Value::Integer(20)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_interval_uuid_array_columns() -> Result<(), DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        run(
+            &executor,
+            "CREATE TABLE payments (id UUID, amount NUMERIC(10,2), paid_at TIMESTAMPTZ, \
+             term INTERVAL, tags INT[])",
+        )?;
+        run(
+            &executor,
+            "INSERT INTO payments VALUES \
+             ('a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11', 0.1, '2024-01-31 10:00:00+02', \
+              '1 month', '{1,2}'), \
+             ('b0eebc99-9c0b-4ef8-bb6d-6bb9bd380a12', 0.2, '2024-02-01 08:00:00Z', \
+              '2 days 03:00:00', ARRAY[3, NULL])",
+        )?;
+        let numeric = |text: &str| Value::Numeric(Decimal::parse(text).unwrap());
+
+        // Decimal sums are exact, and the column keeps its two places
+        let result = run(&executor, "SELECT SUM(amount), AVG(amount) FROM payments")?;
+        assert_eq!(result.rows, vec![vec![numeric("0.3"), numeric("0.15")]]);
+        assert_eq!(result.rows[0][0].to_display_string(), "0.30");
+        assert_eq!(
+            result.column_types,
+            vec![DataType::Numeric(None), DataType::Numeric(None)]
+        );
+        let overflow = run(
+            &executor,
+            "INSERT INTO payments (amount) VALUES (123456789)",
+        );
+        assert!(matches!(overflow, Err(DbError::InvalidInput(_))));
+
+        // Whole months land on the last day of a shorter month
+        let result = run(
+            &executor,
+            "SELECT paid_at + term FROM payments ORDER BY paid_at",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::TimestampTz(
+                    Value::parse_timestamptz("2024-02-29 08:00:00Z").unwrap()
+                )],
+                vec![Value::TimestampTz(
+                    Value::parse_timestamptz("2024-02-03 11:00:00Z").unwrap()
+                )],
+            ]
+        );
+        let result = run(
+            &executor,
+            "SELECT MAX(paid_at) - MIN(paid_at) FROM payments",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Interval(Interval::new(0, 1, 0))]]
+        );
+        assert_eq!(result.column_types, vec![DataType::Interval]);
+
+        let result = run(
+            &executor,
+            "SELECT tags FROM payments WHERE term > '1 day' AND amount >= 0.2",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Array(vec![Value::Integer(3), Value::Null])]]
+        );
+        assert_eq!(result.rows[0][0].to_display_string(), "{3,NULL}");
+
+        run(&executor, "CREATE INDEX payments_id ON payments (id)")?;
+        run(
+            &executor,
+            "CREATE INDEX payments_amount ON payments (amount)",
+        )?;
+        let sql = "SELECT amount FROM payments WHERE id = 'b0eebc99-9c0b-4ef8-bb6d-6bb9bd380a12'";
+        assert!(explain(&executor, sql)?.contains("Index Scan using payments_id"));
+        assert_eq!(run(&executor, sql)?.rows, vec![vec![numeric("0.20")]]);
+        let sql = "SELECT amount FROM payments WHERE amount > 0.15";
+        assert!(explain(&executor, sql)?.contains("Index Only Scan using payments_amount"));
+        let result = run(&executor, sql)?;
+        assert_eq!(result.rows[0][0].to_display_string(), "0.20");
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_comparison_and_sort()-> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(&executor, "INSERT INTO users VALUES (10, 'dave', 9)")?;
+
+        // 10 > 9 numerically even though '10' < '9' as text
+        let result = run(&executor, "SELECT id FROM users WHERE id > 9")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(10)]]);
+
+        let result = run(&executor, "SELECT id FROM users ORDER BY id DESC LIMIT 1")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(10)]]);
+        assert_eq!(result.column_types, vec![DataType::Integer]);
+
+        // Literals are coerced to the column type on insert
+        run(&executor, "INSERT INTO users VALUES ('11', 'erin', 2.6)")?;
+        let result = run(&executor, "SELECT age FROM users WHERE name = 'erin'")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(3)]]);
+        assert!(run(&executor, "INSERT INTO users VALUES ('x', 'frank', 1)").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_semantics() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(&executor, "INSERT INTO users VALUES (4, 'NULL', NULL)")?;
+
+        // The string 'NULL' is not a NULL
+        let result = run(&executor, "SELECT id FROM users WHERE name IS NULL")?;
+        assert!(result.rows.is_empty());
+        let result = run(&executor, "SELECT id FROM users WHERE age IS NULL")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(4)]]);
+
+        // Comparisons with NULL are UNKNOWN, so neither side matches
+        let result = run(&executor, "SELECT id FROM users WHERE age > 0")?;
+        assert_eq!(result.rows.len(), 3);
+        let result = run(&executor, "SELECT id FROM users WHERE NOT age > 0")?;
+        assert!(result.rows.is_empty());
+
+        // ... but an OR with a true branch still matches
+        let result = run(&executor, "SELECT id FROM users WHERE age > 0 OR id = 4")?;
+        assert_eq!(result.rows.len(), 4);
+
+        // NULLs sort last
+        let result = run(&executor, "SELECT id FROM users ORDER BY age")?;
+        assert_eq!(result.rows.last(), Some(&vec![Value::Integer(4)]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_aggregates() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(&executor, "INSERT INTO users VALUES (4, 'dave', NULL)")?;
+
+        let result = run(
+            &executor,
+            "SELECT COUNT(*), COUNT(age), SUM(age), MIN(age), MAX(name) FROM users",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Integer(4),
+                Value::Integer(3),
+                Value::Integer(106),
+                Value::Integer(27),
+                text("dave"),
+            ]]
+        );
+        assert_eq!(
+            result.column_types,
+            vec![
+                DataType::BigInt,
+                DataType::BigInt,
+                DataType::BigInt,
+                DataType::Integer,
+                DataType::Varchar(255),
+            ]
+        );
+
+        run(&executor, "DELETE FROM users")?;
+        let result = run(&executor, "SELECT SUM(age) FROM users")?;
+        assert_eq!(result.rows, vec![vec![Value::Null]]);
+        Ok(())
+    }
+
+    fn shop_executor() -> Result<Executor, DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        run(&executor, "CREATE TABLE customers (id INT, name TEXT, age INT)")?;
+        run(
+            &executor,
+            "INSERT INTO customers VALUES (1, 'alice', 34), (2, 'bob and eve', 27), (3, 'carol', 45)",
+        )?;
+        run(&executor, "CREATE TABLE orders (id INT, customer_id INT, total DOUBLE)")?;
+        run(
+            &executor,
+            "INSERT INTO orders VALUES (10, 1, 20.5), (11, 1, 4.5), (12, 3, 100.0)",
+        )?;
+        Ok(executor)
+    }
+
+    #[test]
+    fn test_string_literal_with_keywords() -> Result<(), DbError> {
+        let executor = shop_executor()?;
+
+        // Operators inside string literals are not split as predicates
+        let result = run(&executor, "SELECT id FROM customers WHERE name = 'bob and eve'")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(2)]]);
+        let result = run(
+            &executor,
+            "SELECT id FROM customers WHERE name = 'bob and eve' AND age < 30",
+        )?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(2)]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expressions_in_projection_and_predicates() -> Result<(), DbError> {
+        let executor = shop_executor()?;
+
+        let result = run(
+            &executor,
+            "SELECT age + 1, age / 2, UPPER(name) FROM customers WHERE age * 2 > 60 ORDER BY id",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(35), Value::Integer(17), text("ALICE")],
+                vec![Value::Integer(46), Value::Integer(22), text("CAROL")],
+            ]
+        );
+
+        let result = run(
+            &executor,
+            "SELECT id FROM customers WHERE age BETWEEN 30 AND 40 OR name LIKE 'b%'",
+        )?;
+        assert_eq!(result.rows.len(), 2);
+
+        assert!(run(&executor, "SELECT age / 0 FROM customers").is_err());
+
+        // Assignments are evaluated against the old row
+        run(&executor, "UPDATE customers SET age = age + 1 WHERE id = 1")?;
+        let result = run(&executor, "SELECT age FROM customers WHERE id = 1")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(35)]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_joins() -> Result<(), DbError> {
+        let executor = shop_executor()?;
+
+        let result = run(
+            &executor,
+            "SELECT c.name, o.total FROM customers c JOIN orders o ON o.customer_id = c.id \
+             WHERE o.total > 10 ORDER BY o.total",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![text("alice"), Value::Float(20.5)],
+                vec![text("carol"), Value::Float(100.0)],
+            ]
+        );
+
+        // Customers without orders are padded with NULLs
+        let result = run(
+            &executor,
+            "SELECT c.id, o.id FROM customers c LEFT JOIN orders o ON o.customer_id = c.id \
+             WHERE o.id IS NULL",
+        )?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(2), Value::Null]]);
+
+        let result = run(
+            &executor,
+            "SELECT c.name, COUNT(*), SUM(o.total) FROM customers c, orders o \
+             WHERE o.customer_id = c.id GROUP BY c.name HAVING COUNT(*) > 1",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![vec![text("alice"), Value::Integer(2), Value::Float(25.0)]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_subqueries() -> Result<(), DbError> {
+        let executor = shop_executor()?;
+
+        let result = run(
+            &executor,
+            "SELECT id FROM customers WHERE id IN (SELECT customer_id FROM orders) ORDER BY id",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(1)], vec![Value::Integer(3)]]
+        );
+
+        // Correlated EXISTS
+        let result = run(
+            &executor,
+            "SELECT name FROM customers c WHERE NOT EXISTS \
+             (SELECT 1 FROM orders o WHERE o.customer_id = c.id)",
+        )?;
+        assert_eq!(result.rows, vec![vec![text("bob and eve")]]);
+
+        // Scalar subqueries in the projection and the predicate
+        let result = run(
+            &executor,
+            "SELECT name, (SELECT MAX(total) FROM orders o WHERE o.customer_id = c.id) \
+             FROM customers c WHERE age > (SELECT AVG(age) FROM customers) ",
+        )?;
+        assert_eq!(result.rows, vec![vec![text("carol"), Value::Float(100.0)]]);
+
+        // Derived tables
+        let result = run(
+            &executor,
+            "SELECT t.customer_id, t.n FROM \
+             (SELECT customer_id, COUNT(*) AS n FROM orders GROUP BY customer_id) t \
+             WHERE t.n = 1",
+        )?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(3), Value::Integer(1)]]);
+
+        // Subqueries in DML
+        let result = run(
+            &executor,
+            "DELETE FROM orders WHERE customer_id IN (SELECT id FROM customers WHERE age > 40)",
+        )?;
+        assert_eq!(result.rows_affected, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_feeds_estimates() -> Result<(), DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        run(
+            &executor,
+            "CREATE TABLE events (id INT, kind INT, note TEXT)",
+        )?;
+        let rows: Vec<String> = (0..40)
+            .map(|i| format!("({}, {}, NULL)", i, i % 2))
+            .collect();
+        run(
+            &executor,
+            &format!("INSERT INTO events VALUES {}", rows.join(", ")),
+        )?;
+        // Too few rows were written for the table to be analyzed on its own
+        assert!(executor.catalog.get_statistics("events").is_none());
+
+        let estimate = |sql: &str| -> Result<f64, DbError> {
+            let mut stmts = SqlParser::new().parse(sql)?;
+            let plan = executor.plan_query(&stmts.remove(0))?;
+            Ok(executor.optimizer.estimate_cardinality(&plan))
+        };
+        assert_eq!(estimate("SELECT * FROM events")?, 1000.0);
+
+        run(&executor, "ANALYZE events")?;
+        let stats = executor.catalog.get_statistics("events").unwrap();
+        assert_eq!(stats.row_count, 40);
+        assert_eq!(stats.column("note").unwrap().null_fraction, 1.0);
+        assert_eq!(estimate("SELECT * FROM events")?, 40.0);
+        assert_eq!(estimate("SELECT * FROM events WHERE kind = 1")?, 20.0);
+        assert_eq!(estimate("SELECT * FROM events WHERE kind = 7")?, 0.0);
+        assert_eq!(estimate("SELECT * FROM events WHERE note IS NULL")?, 40.0);
+        let below = estimate("SELECT * FROM events WHERE id < 10")?;
+        assert!((below - 10.0).abs() < 1e-6, "{}", below);
+
+        // Enough writes analyze the table again
+        let rows: Vec<String> = (40..100).map(|i| format!("({}, 2, 'x')", i)).collect();
+        run(
+            &executor,
+            &format!("INSERT INTO events VALUES {}", rows.join(", ")),
+        )?;
+        let stats = executor.catalog.get_statistics("events").unwrap();
+        assert_eq!(stats.row_count, 100);
+        assert_eq!(estimate("SELECT * FROM events WHERE kind = 2")?, 60.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_statements() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        let plan = |sql: &str| -> Result<Vec<String>, DbError> {
+            let result = run(&executor, sql)?;
+            assert_eq!(result.columns, vec!["QUERY PLAN".to_string()]);
+            Ok(result
+                .rows
+                .iter()
+                .map(|row| row[0].to_display_string())
+                .collect())
+        };
+
+        let lines = plan("EXPLAIN SELECT name FROM users WHERE age > 30")?;
+        assert!(lines[0].starts_with("Project (name)"), "{:?}", lines);
+        assert!(lines.iter().all(|line| line.contains("(cost=")));
+        assert!(lines.iter().all(|line| !line.contains("actual")));
+        assert!(lines.last().unwrap().contains("-> Seq Scan on users"));
+
+        // ANALYZE runs the query and adds what each node did
+        let lines = plan("EXPLAIN ANALYZE SELECT name FROM users WHERE age > 30")?;
+        assert!(lines[0].contains("rows=2 loops=1"), "{:?}", lines);
+        let scan = lines
+            .iter()
+            .find(|line| line.contains("Seq Scan on users"))
+            .unwrap();
+        assert!(scan.contains("rows=3 loops=1"), "{}", scan);
+        assert!(scan.contains("(buffers hit="), "{}", scan);
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("Execution Time: ")));
+
+        let lines = plan("EXPLAIN FORMAT JSON SELECT name FROM users WHERE age > 30")?;
+        assert_eq!(lines.len(), 1);
+        let document: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert!(document["plan"]["estimated_cost"].as_f64().unwrap() > 0.0);
+        assert!(document["plan"].get("actual_rows").is_none());
+
+        let lines = plan("EXPLAIN ANALYZE FORMAT JSON SELECT name FROM users WHERE age > 30")?;
+        let document: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(document["plan"]["actual_rows"], 2);
+        assert_eq!(document["plan"]["loops"], 1);
+        assert!(document["execution_time_ms"].is_number());
+        Ok(())
+    }
+
+    #[test]
+    fn test_procedure_parameters_and_exceptions() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(
+            &executor,
+            "CREATE PROCEDURE user_age(user_id INT, user_age OUT INT, found OUT BOOLEAN) AS $$
+             BEGIN
+                 SELECT age INTO user_age FROM users WHERE id = user_id;
+                 found := TRUE;
+             EXCEPTION
+                 WHEN NO_DATA_FOUND THEN
+                     found := FALSE;
+             END; $$",
+        )?;
+
+        let result = run(&executor, "CALL user_age(2, NULL, NULL)")?;
+        assert_eq!(result.columns, vec!["user_age", "found"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(27), Value::Boolean(true)]]
+        );
+        let result = run(&executor, "CALL user_age(9, NULL, NULL)")?;
+        assert_eq!(result.rows, vec![vec![Value::Null, Value::Boolean(false)]]);
+
+        // A failed insert is caught by its handler, the rows left as they were
+        run(&executor, "CREATE UNIQUE INDEX users_id ON users (id)")?;
+        run(
+            &executor,
+            "CREATE PROCEDURE add_user(new_id INT, new_name VARCHAR(20), added OUT BOOLEAN) AS $$
+             BEGIN
+                 INSERT INTO users (id, name) VALUES (new_id, new_name);
+                 added := TRUE;
+             EXCEPTION
+                 WHEN DUP_VAL_ON_INDEX THEN
+                     added := FALSE;
+             END; $$",
+        )?;
+        let result = run(&executor, "CALL add_user(1, 'again', NULL)")?;
+        assert_eq!(result.rows, vec![vec![Value::Boolean(false)]]);
+        let result = run(&executor, "CALL add_user(4, 'dave', NULL)")?;
+        assert_eq!(result.rows, vec![vec![Value::Boolean(true)]]);
+        let result = run(&executor, "SELECT name FROM users ORDER BY id")?;
+        assert_eq!(result.rows.len(), 4);
+        assert_eq!(result.rows[3], vec![text("dave")]);
+
+        assert!(run(&executor, "CALL user_age(1)").is_err());
+        assert!(run(&executor, "CREATE PROCEDURE broken AS BEGIN x := ; END;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_procedure_cursors_and_transactions() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(
+            &executor,
+            "CREATE PROCEDURE age_adults(years INT, total OUT INT) AS $$
+             DECLARE
+                 CURSOR adults IS SELECT id, age FROM users WHERE age > 30;
+             BEGIN
+                 total := 0;
+                 FOR u IN adults LOOP
+                     UPDATE users SET age = age + years WHERE id = u.id;
+                     total := total + SQL%ROWCOUNT;
+                 END LOOP;
+             END; $$",
+        )?;
+        let result = run(&executor, "CALL age_adults(10, NULL)")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(2)]]);
+        let result = run(&executor, "SELECT age FROM users ORDER BY id")?;
+        let ages = vec![
+            vec![Value::Integer(44)],
+            vec![Value::Integer(27)],
+            vec![Value::Integer(55)],
+        ];
+        assert_eq!(result.rows, ages);
+
+        // Called on its own, a procedure ends its transactions itself
+        run(
+            &executor,
+            "CREATE PROCEDURE rename_user(user_id INT) AS $$
+             BEGIN
+                 UPDATE users SET name = 'kept' WHERE id = user_id;
+                 COMMIT;
+                 UPDATE users SET name = 'undone' WHERE id = user_id;
+                 ROLLBACK;
+             END; $$",
+        )?;
+        run(&executor, "CALL rename_user(2)")?;
+        let result = run(&executor, "SELECT name FROM users WHERE id = 2")?;
+        assert_eq!(result.rows, vec![vec![text("kept")]]);
+
+        // Inside a transaction block the transaction is the caller's
+        let txn = executor.begin_transaction(None)?;
+        let in_block = executor.with_transaction(txn.clone());
+        assert!(matches!(
+            run(&in_block, "CALL rename_user(3)"),
+            Err(DbError::InvalidState(_))
+        ));
+        let result = run(&in_block, "SELECT name FROM users WHERE id = 3")?;
+        assert_eq!(result.rows, vec![vec![text("carol")]]);
+        txn.rollback()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_triggers_fire_on_dml() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        run(
+            &executor,
+            "CREATE TABLE audit (user_id INT, action VARCHAR(20))",
+        )?;
+        run(&executor, "CREATE TABLE deletes (at_level VARCHAR(20))")?;
+        run(
+            &executor,
+            "CREATE TRIGGER cap_age BEFORE INSERT OR UPDATE ON users FOR EACH ROW
+             WHEN (NEW.age > 100) BEGIN :NEW.age := 100; END;",
+        )?;
+        run(
+            &executor,
+            "CREATE TRIGGER log_insert AFTER INSERT ON users FOR EACH ROW
+             BEGIN INSERT INTO audit VALUES (:NEW.id, 'insert'); END;",
+        )?;
+        run(
+            &executor,
+            "CREATE TRIGGER log_update AFTER UPDATE ON users FOR EACH ROW
+             WHEN (OLD.age < NEW.age) BEGIN INSERT INTO audit VALUES (:OLD.id, 'update'); END;",
+        )?;
+        run(
+            &executor,
+            "CREATE TRIGGER log_delete AFTER DELETE ON users FOR EACH ROW
+             BEGIN INSERT INTO audit VALUES (:OLD.id, 'delete'); END;",
+        )?;
+        run(
+            &executor,
+            "CREATE TRIGGER count_deletes BEFORE DELETE ON users
+             BEGIN INSERT INTO deletes VALUES ('statement'); END;",
+        )?;
+        let rows =
+            |sql: &str| -> Result<Vec<Vec<Value>>, DbError> { Ok(run(&executor, sql)?.rows) };
+
+        // BEFORE row triggers change the row written
+        let result = run(
+            &executor,
+            "INSERT INTO users VALUES (4, 'dave', 150), (5, 'erin', 20)",
+        )?;
+        assert_eq!(result.rows_affected, 2);
+        let result = run(&executor, "SELECT age FROM users WHERE id = 4")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(100)]]);
+        assert_eq!(
+            rows("SELECT user_id FROM audit WHERE action = 'insert' ORDER BY user_id")?,
+            vec![vec![Value::Integer(4)], vec![Value::Integer(5)]]
+        );
+
+        // Row triggers fire only when their WHEN condition holds
+        run(&executor, "UPDATE users SET age = age + 1 WHERE id < 3")?;
+        run(&executor, "UPDATE users SET name = 'bobby' WHERE id = 2")?;
+        assert_eq!(
+            rows("SELECT user_id FROM audit WHERE action = 'update' ORDER BY user_id")?,
+            vec![vec![Value::Integer(1)], vec![Value::Integer(2)]]
+        );
+
+        // Statement triggers fire once, even for no rows
+        run(&executor, "DELETE FROM users WHERE id >= 4")?;
+        run(&executor, "DELETE FROM users WHERE id = 9")?;
+        assert_eq!(
+            rows("SELECT user_id FROM audit WHERE action = 'delete' ORDER BY user_id")?,
+            vec![vec![Value::Integer(4)], vec![Value::Integer(5)]]
+        );
+        assert_eq!(rows("SELECT * FROM deletes")?.len(), 2);
+
+        // Views are written through their INSTEAD OF triggers
+        run(
+            &executor,
+            "CREATE VIEW adults AS SELECT id, name FROM users WHERE age >= 30",
+        )?;
+        run(
+            &executor,
+            "CREATE TRIGGER adults_insert INSTEAD OF INSERT ON adults
+             BEGIN INSERT INTO users VALUES (:NEW.id, :NEW.name, 30); END;",
+        )?;
+        let result = run(&executor, "INSERT INTO adults VALUES (6, 'frank')")?;
+        assert_eq!(result.rows_affected, 1);
+        let result = run(&executor, "SELECT name, age FROM users WHERE id = 6")?;
+        assert_eq!(result.rows, vec![vec![text("frank"), Value::Integer(30)]]);
+        assert!(matches!(
+            run(&executor, "DELETE FROM adults WHERE id = 6"),
+            Err(DbError::InvalidOperation(_))
+        ));
+        assert!(run(
+            &executor,
+            "CREATE TRIGGER t BEFORE INSERT ON adults FOR EACH ROW BEGIN NULL; END;"
+        )
+        .is_err());
+        assert!(run(
+            &executor,
+            "CREATE TRIGGER t INSTEAD OF INSERT ON users BEGIN NULL; END;"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_trigger_depth_and_errors() -> Result<(), DbError> {
+        let executor = users_executor()?;
+        let count = |sql: &str| -> Result<usize, DbError> { Ok(run(&executor, sql)?.rows.len()) };
+
+        // A trigger writing its own table fires itself, one level deeper each time
+        run(
+            &executor,
+            "CREATE TRIGGER again AFTER INSERT ON users FOR EACH ROW WHEN (NEW.id < 15)
+             BEGIN INSERT INTO users VALUES (:NEW.id + 1, :NEW.name, :NEW.age); END;",
+        )?;
+        run(&executor, "INSERT INTO users VALUES (10, 'deep', 1)")?;
+        assert_eq!(count("SELECT * FROM users WHERE name = 'deep'")?, 6);
+
+        // Two levels below MAX_TRIGGER_DEPTH, a trigger may fire once more;
+        // firing twice more fails the statement, which writes nothing
+        let nested = Executor {
+            trigger_depth: MAX_TRIGGER_DEPTH - 2,
+            ..executor.clone()
+        };
+        assert!(matches!(
+            run(&nested, "INSERT INTO users VALUES (13, 'loop', 1)"),
+            Err(DbError::LimitExceeded(_))
+        ));
+        run(&nested, "INSERT INTO users VALUES (14, 'loop', 1)")?;
+        assert_eq!(count("SELECT * FROM users WHERE name = 'loop'")?, 2);
+        run(&executor, "DROP TRIGGER again")?;
+
+        // An error raised by a trigger aborts the statement that fired it
+        run(
+            &executor,
+            "CREATE TRIGGER check_age BEFORE UPDATE ON users FOR EACH ROW WHEN (NEW.age < 0)
+             BEGIN RAISE_APPLICATION_ERROR(-20001, 'age cannot be negative'); END;",
+        )?;
+        assert!(run(&executor, "UPDATE users SET age = age - 30 WHERE id < 4").is_err());
+        let result = run(&executor, "SELECT age FROM users WHERE id < 4 ORDER BY id")?;
+        let ages = vec![
+            vec![Value::Integer(34)],
+            vec![Value::Integer(27)],
+            vec![Value::Integer(45)],
+        ];
+        assert_eq!(result.rows, ages);
+
+        assert!(run(
+            &executor,
+            "CREATE TRIGGER broken AFTER INSERT ON users BEGIN x := ; END;"
+        )
+        .is_err());
+        run(&executor, "DROP TABLE users")?;
+        assert!(executor.catalog().list_triggers().is_empty());
+        Ok(())
+    }
+
+    fn scores_executor() -> Result<Executor, DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        run(
+            &executor,
+            "CREATE TABLE scores (id INT, team TEXT, points INT)",
+        )?;
+        run(
+            &executor,
+            "INSERT INTO scores VALUES (1, 'a', 10), (2, 'a', 20), (3, 'a', 20), (4, 'a', 30),
+             (5, 'b', 5), (6, 'b', 15)",
+        )?;
+        Ok(executor)
+    }
+
+    fn column(result: &QueryResult, index: usize) -> Vec<Value> {
+        result.rows.iter().map(|row| row[index].clone()).collect()
+    }
+
+    fn integers(values: &[i64]) -> Vec<Value> {
+        values.iter().map(|&v| Value::Integer(v)).collect()
+    }
+
+    #[test]
+    fn test_window_functions() -> Result<(), DbError> {
+        let executor = scores_executor()?;
+
+        let result = run(
+            &executor,
+            "SELECT id, ROW_NUMBER() OVER (PARTITION BY team ORDER BY points),
+                    RANK() OVER (PARTITION BY team ORDER BY points),
+                    DENSE_RANK() OVER (PARTITION BY team ORDER BY points),
+                    LAG(points) OVER (PARTITION BY team ORDER BY points),
+                    SUM(points) OVER (PARTITION BY team ORDER BY points)
+             FROM scores ORDER BY id",
+        )?;
+        assert_eq!(column(&result, 1), integers(&[1, 2, 3, 4, 1, 2]));
+        assert_eq!(column(&result, 2), integers(&[1, 2, 2, 4, 1, 2]));
+        assert_eq!(column(&result, 3), integers(&[1, 2, 2, 3, 1, 2]));
+        let mut lag = integers(&[0, 10, 20, 20, 0, 5]);
+        (lag[0], lag[4]) = (Value::Null, Value::Null);
+        assert_eq!(column(&result, 4), lag);
+        // The default frame ends at the current row's last peer
+        assert_eq!(column(&result, 5), integers(&[10, 50, 50, 80, 5, 20]));
+        assert_eq!(result.column_types[1], DataType::BigInt);
+
+        // GROUPS counts peer groups; EXCLUDE takes rows back out
+        let frame = |clause: &str| -> Result<Vec<Value>, DbError> {
+            let sql = format!(
+                "SELECT id, SUM(points) OVER (ORDER BY points {}) FROM scores
+                 WHERE team = 'a' ORDER BY id",
+                clause
+            );
+            Ok(column(&run(&executor, &sql)?, 1))
+        };
+        assert_eq!(
+            frame("GROUPS BETWEEN 1 PRECEDING AND CURRENT ROW")?,
+            integers(&[10, 50, 50, 70])
+        );
+        assert_eq!(
+            frame("ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING")?,
+            integers(&[30, 50, 70, 50])
+        );
+        let all = "ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING";
+        assert_eq!(
+            frame(&format!("{} EXCLUDE CURRENT ROW", all))?,
+            integers(&[70, 60, 60, 50])
+        );
+        assert_eq!(
+            frame(&format!("{} EXCLUDE GROUP", all))?,
+            integers(&[70, 40, 40, 50])
+        );
+        assert_eq!(
+            frame(&format!("{} EXCLUDE TIES", all))?,
+            integers(&[80, 60, 60, 80])
+        );
+        assert_eq!(
+            frame(&format!("{} EXCLUDE NO OTHERS", all))?,
+            integers(&[80, 80, 80, 80])
+        );
+
+        // Views keep the clause, and columns are named by the SQL as written
+        run(
+            &executor,
+            &format!(
+                "CREATE VIEW others AS SELECT id, SUM(points) OVER (ORDER BY points {} \
+                 EXCLUDE GROUP) FROM scores WHERE team = 'a'",
+                all
+            ),
+        )?;
+        let result = run(&executor, "SELECT * FROM others ORDER BY id")?;
+        assert_eq!(column(&result, 1), integers(&[70, 40, 40, 50]));
+        assert!(
+            result.columns[1].ends_with("EXCLUDE GROUP)"),
+            "{}",
+            result.columns[1]
+        );
+
+        // Named windows, copied with an ORDER BY added, and RANGE offsets
+        let result = run(
+            &executor,
+            "SELECT id, COUNT(*) OVER w,
+                    FIRST_VALUE(id) OVER (w ORDER BY points RANGE BETWEEN 10 PRECEDING AND CURRENT ROW)
+             FROM scores WINDOW w AS (PARTITION BY team) ORDER BY id",
+        )?;
+        assert_eq!(column(&result, 1), integers(&[4, 4, 4, 4, 2, 2]));
+        assert_eq!(column(&result, 2), integers(&[1, 1, 1, 2, 5, 5]));
+
+        // Windows over the same partitions share one sort
+        let plan = explain(
+            &executor,
+            "SELECT ROW_NUMBER() OVER (PARTITION BY team ORDER BY points),
+                    COUNT(*) OVER (PARTITION BY team) FROM scores",
+        )?;
+        assert_eq!(plan.matches("Sort (").count(), 1, "{}", plan);
+        assert_eq!(plan.matches("Window (").count(), 2, "{}", plan);
+
+        assert!(run(&executor, "SELECT id FROM scores WHERE RANK() OVER () > 1").is_err());
+        assert!(run(
+            &executor,
+            "SELECT SUM(points) OVER (w ORDER BY id) FROM scores WINDOW w AS (ORDER BY points)"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_operations() -> Result<(), DbError> {
+        let executor = scores_executor()?;
+        let points =
+            |sql: &str| -> Result<Vec<Value>, DbError> { Ok(column(&run(&executor, sql)?, 0)) };
+
+        assert_eq!(
+            points(
+                "SELECT points FROM scores WHERE team = 'a'
+                 UNION ALL SELECT points FROM scores WHERE team = 'b' ORDER BY points"
+            )?,
+            integers(&[5, 10, 15, 20, 20, 30])
+        );
+        assert_eq!(
+            points(
+                "SELECT points FROM scores WHERE team = 'a'
+                 UNION SELECT points FROM scores WHERE team = 'a' ORDER BY points"
+            )?,
+            integers(&[10, 20, 30])
+        );
+        let team_a = "SELECT points FROM scores WHERE team = 'a'";
+        let first_three = "SELECT points FROM scores WHERE id < 4 ORDER BY points";
+        assert_eq!(
+            points(&format!("{} INTERSECT {}", team_a, first_three))?,
+            integers(&[10, 20])
+        );
+        assert_eq!(
+            points(&format!("{} INTERSECT ALL {}", team_a, first_three))?,
+            integers(&[10, 20, 20])
+        );
+        assert_eq!(
+            points(&format!("{} EXCEPT SELECT 20 ORDER BY points", team_a))?,
+            integers(&[10, 30])
+        );
+        assert_eq!(
+            points(&format!("{} EXCEPT ALL SELECT 20 ORDER BY points", team_a))?,
+            integers(&[10, 20, 30])
+        );
+
+        // Set operations nest, and take their column names from the left
+        let result = run(
+            &executor,
+            "SELECT id AS n FROM scores WHERE id < 3
+             UNION (SELECT 3 EXCEPT SELECT 4) ORDER BY n DESC",
+        )?;
+        assert_eq!(result.columns, vec!["n".to_string()]);
+        assert_eq!(column(&result, 0), integers(&[3, 2, 1]));
+
+        assert!(run(
+            &executor,
+            "SELECT id, points FROM scores UNION SELECT id FROM scores"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_queries() -> Result<(), DbError> {
+        let executor = scores_executor()?;
+
+        let result = run(
+            &executor,
+            "WITH big AS (SELECT id, points FROM scores WHERE points >= 20)
+             SELECT x.id, y.id FROM big x JOIN big y ON x.points = y.points AND x.id < y.id",
+        )?;
+        assert_eq!(result.rows, vec![integers(&[2, 3])]);
+
+        // Later WITH queries see earlier ones, and hide tables of their name
+        let result = run(
+            &executor,
+            "WITH a AS (SELECT team, points FROM scores WHERE team = 'b'),
+                  scores AS (SELECT points FROM a WHERE points > 10)
+             SELECT points FROM scores",
+        )?;
+        assert_eq!(column(&result, 0), integers(&[15]));
+
+        assert!(run(
+            &executor,
+            "WITH a AS (SELECT 1), a AS (SELECT 2) SELECT * FROM a"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_queries() -> Result<(), DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        run(&executor, "CREATE TABLE tree (id INT, parent INT)")?;
+        run(
+            &executor,
+            "INSERT INTO tree VALUES (1, NULL), (2, 1), (3, 1), (4, 2), (5, 3)",
+        )?;
+        run(&executor, "CREATE TABLE edges (src INT, dst INT)")?;
+        run(
+            &executor,
+            "INSERT INTO edges VALUES (1, 2), (2, 3), (3, 1), (3, 4)",
+        )?;
+
+        let result = run(
+            &executor,
+            "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 5)
+             SELECT n FROM t ORDER BY n",
+        )?;
+        assert_eq!(column(&result, 0), integers(&[1, 2, 3, 4, 5]));
+
+        // SEARCH orders the rows of a hierarchy depth or breadth first
+        let search = |order: &str| -> Result<QueryResult, DbError> {
+            let sql = format!(
+                "WITH RECURSIVE below(id, lvl) AS (
+                     SELECT id, 0 FROM tree WHERE parent IS NULL
+                     UNION ALL
+                     SELECT tree.id, below.lvl + 1 FROM tree JOIN below ON tree.parent = below.id
+                 ) SEARCH {} FIRST BY id SET ord
+                 SELECT id, lvl FROM below ORDER BY ord",
+                order
+            );
+            run(&executor, &sql)
+        };
+        let depth_first = search("DEPTH")?;
+        assert_eq!(column(&depth_first, 0), integers(&[1, 2, 4, 3, 5]));
+        assert_eq!(column(&depth_first, 1), integers(&[0, 1, 2, 1, 2]));
+        assert_eq!(column(&search("BREADTH")?, 0), integers(&[1, 2, 3, 4, 5]));
+
+        // CYCLE marks the row that comes back to a node and stops there
+        let walk = "WITH RECURSIVE walk(node) AS (
+                        SELECT 1
+                        UNION ALL
+                        SELECT edges.dst FROM edges JOIN walk ON edges.src = walk.node
+                    ) CYCLE node SET looped USING path";
+        let result = run(&executor, &format!("{} SELECT node FROM walk", walk))?;
+        assert_eq!(result.rows.len(), 5);
+        let result = run(
+            &executor,
+            &format!("{} SELECT node, path FROM walk WHERE looped", walk),
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Integer(1),
+                Value::Array(integers(&[1, 2, 3, 1]))
+            ]]
+        );
 
+        // UNION without ALL stops once a round adds nothing new
+        let result = run(
+            &executor,
+            "WITH RECURSIVE walk(node) AS (
+                 SELECT 1 UNION SELECT edges.dst FROM edges JOIN walk ON edges.src = walk.node
+             ) SELECT node FROM walk ORDER BY node",
+        )?;
+        assert_eq!(column(&result, 0), integers(&[1, 2, 3, 4]));
+
+        let plan = explain(
+            &executor,
+            "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 5)
+             SELECT n FROM t",
+        )?;
+        assert!(plan.contains("Recursive Union All on t"), "{}", plan);
+        assert!(plan.contains("WorkTable Scan on t"), "{}", plan);
+
+        assert!(matches!(
+            run(
+                &executor,
+                "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t) SELECT n FROM t",
+            ),
+            Err(DbError::LimitExceeded(_))
+        ));
+        assert!(run(
+            &executor,
+            "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT a.n FROM t a JOIN t b ON a.n = b.n)
+             SELECT n FROM t"
+        )
+        .is_err());
         Ok(())
     }
 }
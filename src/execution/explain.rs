@@ -0,0 +1,218 @@
+// EXPLAIN output
+//
+// Every node of a plan is shown with the optimizer's estimates of its cost,
+// counting the nodes below it, and of the rows it returns. EXPLAIN ANALYZE
+// runs the plan with a `PlanProfile` attached to the executor, which records
+// for each node the rows it returned, how often it ran, the time spent in it
+// and below it, and the buffer pool hits and misses in that time. The pools
+// are shared, so statements running alongside count towards the buffers too.
+
+use crate::execution::optimizer::Optimizer;
+use crate::execution::planner::PlanNode;
+use parking_lot::Mutex;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What one plan node did while its query ran
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeProfile {
+    /// Rows returned, over every run
+    pub rows: u64,
+    /// Times the node ran
+    pub loops: u64,
+    /// Time spent in the node and the nodes below it
+    pub time: Duration,
+    pub buffer_hits: u64,
+    pub buffer_misses: u64,
+}
+
+/// Profiles of the nodes of a running plan
+#[derive(Debug, Default)]
+pub struct PlanProfile {
+    // Nodes are told apart by address, which holds while the plan is alive
+    nodes: Mutex<HashMap<usize, NodeProfile>>,
+}
+
+impl PlanProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a run of `node` that returned `rows` rows
+    pub fn record(
+        &self,
+        node: &PlanNode,
+        rows: usize,
+        time: Duration,
+        buffer_hits: u64,
+        buffer_misses: u64,
+    ) {
+        let mut nodes = self.nodes.lock();
+        let profile = nodes.entry(Self::key(node)).or_default();
+        profile.rows += rows as u64;
+        profile.loops += 1;
+        profile.time += time;
+        profile.buffer_hits += buffer_hits;
+        profile.buffer_misses += buffer_misses;
+    }
+
+    pub fn get(&self, node: &PlanNode) -> Option<NodeProfile> {
+        self.nodes.lock().get(&Self::key(node)).copied()
+    }
+
+    fn key(node: &PlanNode) -> usize {
+        node as *const PlanNode as usize
+    }
+}
+
+/// A plan as EXPLAIN shows it
+pub struct Explanation<'a> {
+    plan: &'a PlanNode,
+    optimizer: &'a Optimizer,
+    analysis: Option<Analysis>,
+}
+
+// What EXPLAIN ANALYZE measured
+struct Analysis {
+    profile: Arc<PlanProfile>,
+    planning_time: Duration,
+    execution_time: Duration,
+}
+
+impl<'a> Explanation<'a> {
+    pub fn new(plan: &'a PlanNode, optimizer: &'a Optimizer) -> Self {
+        Self {
+            plan,
+            optimizer,
+            analysis: None,
+        }
+    }
+
+    /// The explanation of a plan that ran with `profile` attached
+    pub fn analyzed(
+        self,
+        profile: Arc<PlanProfile>,
+        planning_time: Duration,
+        execution_time: Duration,
+    ) -> Self {
+        Self {
+            analysis: Some(Analysis {
+                profile,
+                planning_time,
+                execution_time,
+            }),
+            ..self
+        }
+    }
+
+    /// One line per plan node, indented under the node reading from it,
+    /// followed by the planning and execution times after ANALYZE
+    pub fn text(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.node_text(self.plan, 0, &mut lines);
+        if let Some(analysis) = &self.analysis {
+            lines.push(format!(
+                "Planning Time: {:.3} ms",
+                millis(analysis.planning_time)
+            ));
+            lines.push(format!(
+                "Execution Time: {:.3} ms",
+                millis(analysis.execution_time)
+            ));
+        }
+        lines
+    }
+
+    /// The plan as a JSON document of nested nodes
+    pub fn json(&self) -> JsonValue {
+        let mut document = Map::new();
+        document.insert("plan".to_string(), self.node_json(self.plan));
+        if let Some(analysis) = &self.analysis {
+            document.insert(
+                "planning_time_ms".to_string(),
+                json!(millis(analysis.planning_time)),
+            );
+            document.insert(
+                "execution_time_ms".to_string(),
+                json!(millis(analysis.execution_time)),
+            );
+        }
+        JsonValue::Object(document)
+    }
+
+    fn node_text(&self, node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
+        let mut line = String::new();
+        if depth > 0 {
+            line.push_str(&"  ".repeat(depth - 1));
+            line.push_str("-> ");
+        }
+        line.push_str(&format!(
+            "{}  (cost={:.2} rows={:.0})",
+            node.label(),
+            self.cost(node),
+            self.optimizer.estimate_cardinality(node)
+        ));
+        if let Some(analysis) = &self.analysis {
+            match analysis.profile.get(node) {
+                Some(profile) => line.push_str(&format!(
+                    " (actual time={:.3} ms rows={} loops={}) (buffers hit={} miss={})",
+                    millis(profile.time),
+                    profile.rows,
+                    profile.loops,
+                    profile.buffer_hits,
+                    profile.buffer_misses
+                )),
+                None => line.push_str(" (never executed)"),
+            }
+        }
+        lines.push(line);
+
+        for child in node.children() {
+            self.node_text(child, depth + 1, lines);
+        }
+    }
+
+    fn node_json(&self, node: &PlanNode) -> JsonValue {
+        let mut object = Map::new();
+        object.insert("operator".to_string(), json!(node.label()));
+        object.insert("estimated_cost".to_string(), json!(self.cost(node)));
+        object.insert(
+            "estimated_rows".to_string(),
+            json!(self.optimizer.estimate_cardinality(node)),
+        );
+        let profile = self
+            .analysis
+            .as_ref()
+            .and_then(|analysis| analysis.profile.get(node));
+        if let Some(profile) = profile {
+            object.insert("actual_rows".to_string(), json!(profile.rows));
+            object.insert("loops".to_string(), json!(profile.loops));
+            object.insert("actual_time_ms".to_string(), json!(millis(profile.time)));
+            object.insert("buffer_hits".to_string(), json!(profile.buffer_hits));
+            object.insert("buffer_misses".to_string(), json!(profile.buffer_misses));
+        }
+        let children: Vec<JsonValue> = node
+            .children()
+            .into_iter()
+            .map(|child| self.node_json(child))
+            .collect();
+        object.insert("children".to_string(), JsonValue::Array(children));
+        JsonValue::Object(object)
+    }
+
+    // Cost of a node and of everything below it
+    fn cost(&self, node: &PlanNode) -> f64 {
+        self.optimizer.estimate_cost(node)
+            + node
+                .children()
+                .into_iter()
+                .map(|child| self.cost(child))
+                .sum::<f64>()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -22,6 +22,7 @@
 //    - Filter probe side before hash table lookup
 //    - Reduces memory access for low selectivity
 
+use crate::common::Value;
 use crate::error::DbError;
 use crate::execution::QueryResult;
 use parking_lot::RwLock;
@@ -114,11 +115,11 @@ impl HashJoinExecutor {
         probe_key_col: usize,
     ) -> Result<QueryResult, DbError> {
         // Build phase - pre-allocate hash table with capacity
-        let mut hash_table: HashMap<String, Vec<Vec<String>>> =
+        let mut hash_table: HashMap<Value, Vec<Vec<Value>>> =
             HashMap::with_capacity(build_side.rows.len());
 
         for row in &build_side.rows {
-            if let Some(key) = row.get(build_key_col) {
+            if let Some(key) = join_key(row, build_key_col) {
                 hash_table
                     .entry(key.clone())
                     .or_insert_with(Vec::new)
@@ -134,7 +135,7 @@ impl HashJoinExecutor {
         let result_row_size = probe_side.columns.len() + build_side.columns.len();
 
         for probe_row in &probe_side.rows {
-            if let Some(key) = probe_row.get(probe_key_col) {
+            if let Some(key) = join_key(probe_row, probe_key_col) {
                 if let Some(build_rows) = hash_table.get(key) {
                     for build_row in build_rows {
                         // Pre-allocate joined row with exact capacity (avoid realloc)
@@ -147,11 +148,17 @@ impl HashJoinExecutor {
             }
         }
 
-        // Combine column names
+        // Combine column names and types
         let mut result_columns = probe_side.columns.clone();
         result_columns.extend(build_side.columns);
-
-        Ok(QueryResult::new(result_columns, result_rows))
+        let mut result_types = probe_side.column_types.clone();
+        result_types.extend(build_side.column_types);
+
+        Ok(QueryResult::typed(
+            result_columns,
+            result_types,
+            result_rows,
+        ))
     }
 
     // Grace hash join - partitions both sides to disk
@@ -184,10 +191,10 @@ impl HashJoinExecutor {
                 let build_data = self.load_partition(build_part)?;
 
                 // Build hash table for this partition
-                let mut partition_hash_table: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+                let mut partition_hash_table: HashMap<Value, Vec<Vec<Value>>> = HashMap::new();
 
                 for row in &build_data.rows {
-                    if let Some(key) = row.get(build_key_col) {
+                    if let Some(key) = join_key(row, build_key_col) {
                         partition_hash_table
                             .entry(key.clone())
                             .or_insert_with(Vec::new)
@@ -199,7 +206,7 @@ impl HashJoinExecutor {
                 let probe_data = self.load_partition(probe_part)?;
 
                 for probe_row in &probe_data.rows {
-                    if let Some(key) = probe_row.get(probe_key_col) {
+                    if let Some(key) = join_key(probe_row, probe_key_col) {
                         if let Some(build_rows) = partition_hash_table.get(key) {
                             for build_row in build_rows {
                                 let mut joined_row = probe_row.clone();
@@ -218,8 +225,14 @@ impl HashJoinExecutor {
 
         let mut result_columns = probe_side.columns.clone();
         result_columns.extend(build_side.columns);
-
-        Ok(QueryResult::new(result_columns, result_rows))
+        let mut result_types = probe_side.column_types.clone();
+        result_types.extend(build_side.column_types);
+
+        Ok(QueryResult::typed(
+            result_columns,
+            result_types,
+            result_rows,
+        ))
     }
 
     // Hybrid hash join - keeps hot partitions in memory, spills cold ones
@@ -238,7 +251,7 @@ impl HashJoinExecutor {
 
         // First pass: determine partition sizes
         for row in &build_side.rows {
-            if let Some(key) = row.get(build_key_col) {
+            if let Some(key) = join_key(row, build_key_col) {
                 let partition_id = self.hash_partition(key);
                 partition_sizes[partition_id] += 1;
             }
@@ -253,20 +266,20 @@ impl HashJoinExecutor {
             .unwrap_or(0);
 
         // Second pass: partition data
-        let mut partitions: Vec<Vec<Vec<String>>> = vec![Vec::new(); self.config.num_partitions];
+        let mut partitions: Vec<Vec<Vec<Value>>> = vec![Vec::new(); self.config.num_partitions];
 
         for row in &build_side.rows {
-            if let Some(key) = row.get(build_key_col) {
+            if let Some(key) = join_key(row, build_key_col) {
                 let partition_id = self.hash_partition(key);
                 partitions[partition_id].push(row.clone());
             }
         }
 
         // Build hash table for hot partition
-        let hot_table: HashMap<String, Vec<Vec<String>>> = {
+        let hot_table: HashMap<Value, Vec<Vec<Value>>> = {
             let mut table = HashMap::new();
             for row in &partitions[hot_partition_id] {
-                if let Some(key) = row.get(build_key_col) {
+                if let Some(key) = join_key(row, build_key_col) {
                     table
                         .entry(key.clone())
                         .or_insert_with(Vec::new)
@@ -291,12 +304,12 @@ impl HashJoinExecutor {
 
         // Phase 2: Probe
         let mut result_rows = Vec::new();
-        let mut probe_partitions: Vec<Vec<Vec<String>>> =
+        let mut probe_partitions: Vec<Vec<Vec<Value>>> =
             vec![Vec::new(); self.config.num_partitions];
 
         // Partition probe side
         for row in &probe_side.rows {
-            if let Some(key) = row.get(probe_key_col) {
+            if let Some(key) = join_key(row, probe_key_col) {
                 let partition_id = self.hash_partition(key);
                 probe_partitions[partition_id].push(row.clone());
             }
@@ -304,7 +317,7 @@ impl HashJoinExecutor {
 
         // Probe hot partition immediately
         for probe_row in &probe_partitions[hot_partition_id] {
-            if let Some(key) = probe_row.get(probe_key_col) {
+            if let Some(key) = join_key(probe_row, probe_key_col) {
                 if let Some(build_rows) = hot_table.get(key) {
                     for build_row in build_rows {
                         let mut joined_row = probe_row.clone();
@@ -320,9 +333,9 @@ impl HashJoinExecutor {
             let build_data = self.load_partition(&build_path)?;
 
             // Build hash table for spilled partition
-            let mut partition_hash_table: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+            let mut partition_hash_table: HashMap<Value, Vec<Vec<Value>>> = HashMap::new();
             for row in &build_data.rows {
-                if let Some(key) = row.get(build_key_col) {
+                if let Some(key) = join_key(row, build_key_col) {
                     partition_hash_table
                         .entry(key.clone())
                         .or_insert_with(Vec::new)
@@ -332,7 +345,7 @@ impl HashJoinExecutor {
 
             // Probe with matching probe partition
             for probe_row in &probe_partitions[partition_id] {
-                if let Some(key) = probe_row.get(probe_key_col) {
+                if let Some(key) = join_key(probe_row, probe_key_col) {
                     if let Some(build_rows) = partition_hash_table.get(key) {
                         for build_row in build_rows {
                             let mut joined_row = probe_row.clone();
@@ -349,8 +362,14 @@ impl HashJoinExecutor {
 
         let mut result_columns = probe_side.columns.clone();
         result_columns.extend(build_side.columns);
-
-        Ok(QueryResult::new(result_columns, result_rows))
+        let mut result_types = probe_side.column_types.clone();
+        result_types.extend(build_side.column_types);
+
+        Ok(QueryResult::typed(
+            result_columns,
+            result_types,
+            result_rows,
+        ))
     }
 
     // Partition data to disk
@@ -373,12 +392,12 @@ impl HashJoinExecutor {
 
         // Write rows to appropriate partitions
         for row in &data.rows {
-            if let Some(key) = row.get(key_col) {
+            if let Some(key) = join_key(row, key_col) {
                 let partition_id = self.hash_partition(key);
                 let writer = &mut partition_writers[partition_id];
 
-                // Serialize row (simple CSV format)
-                let row_str = row.join(",") + "\n";
+                // Serialize row (one JSON array per line)
+                let row_str = serde_json::to_string(row)? + "\n";
                 writer
                     .write_all(row_str.as_bytes())
                     .map_err(|e| DbError::Storage(e.to_string()))?;
@@ -406,7 +425,7 @@ impl HashJoinExecutor {
         for line in reader.lines() {
             let line = line.map_err(|e| DbError::Storage(e.to_string()))?;
             if !line.is_empty() {
-                let row: Vec<String> = line.split(',').map(|s| s.to_string()).collect();
+                let row: Vec<Value> = serde_json::from_str(&line)?;
                 rows.push(row);
             }
         }
@@ -417,7 +436,7 @@ impl HashJoinExecutor {
     // Spill partition to disk
     fn spill_partition(
         &self,
-        partition: &[Vec<String>],
+        partition: &[Vec<Value>],
         _columns: &[String],
         prefix: &str,
         partition_id: usize,
@@ -436,7 +455,7 @@ impl HashJoinExecutor {
         let mut writer = BufWriter::new(file);
 
         for row in partition {
-            let row_str = row.join(",") + "\n";
+            let row_str = serde_json::to_string(row)? + "\n";
             writer
                 .write_all(row_str.as_bytes())
                 .map_err(|e| DbError::Storage(e.to_string()))?;
@@ -452,9 +471,9 @@ impl HashJoinExecutor {
     // Hash partition key to partition ID
     //
     // Now uses xxHash3-AVX2 for 10x faster partitioning
-    fn hash_partition(&self, key: &str) -> usize {
+    fn hash_partition(&self, key: &Value) -> usize {
         use crate::simd::hash::hash_str;
-        let hash = hash_str(key);
+        let hash = hash_str(&key.to_display_string());
         (hash as usize) % self.config.num_partitions
     }
 
@@ -465,7 +484,7 @@ impl HashJoinExecutor {
             result
                 .rows
                 .iter()
-                .map(|row| row.iter().map(|s| s.len()).sum::<usize>())
+                .map(|row| row.iter().map(Value::estimated_size).sum::<usize>())
                 .sum::<usize>()
                 / row_count
         } else {
@@ -476,6 +495,11 @@ impl HashJoinExecutor {
     }
 }
 
+// Join key of a row; NULL keys never match anything
+fn join_key(row: &[Value], col: usize) -> Option<&Value> {
+    row.get(col).filter(|key| !key.is_null())
+}
+
 // Bloom filter for semi-join optimization (DEPRECATED - use SimdBloomFilter)
 //
 // This implementation is kept for backward compatibility but is 10x slower.
@@ -532,8 +556,8 @@ impl BloomFilterHashJoin {
         let mut bloom = BloomFilter::new(build_side.rows.len(), 0.01);
 
         for row in &build_side.rows {
-            if let Some(key) = row.get(build_key_col) {
-                bloom.insert(key);
+            if let Some(key) = join_key(row, build_key_col) {
+                bloom.insert(&key.to_display_string());
             }
         }
 
@@ -543,15 +567,19 @@ impl BloomFilterHashJoin {
         let mut filtered_probe_rows = Vec::new();
         if let Some(bloom) = &self.bloom_filter {
             for row in &probe_side.rows {
-                if let Some(key) = row.get(probe_key_col) {
-                    if bloom.contains(key) {
+                if let Some(key) = join_key(row, probe_key_col) {
+                    if bloom.contains(&key.to_display_string()) {
                         filtered_probe_rows.push(row.clone());
                     }
                 }
             }
         }
 
-        let filtered_probe = QueryResult::new(probe_side.columns.clone(), filtered_probe_rows);
+        let filtered_probe = QueryResult::typed(
+            probe_side.columns.clone(),
+            probe_side.column_types.clone(),
+            filtered_probe_rows,
+        );
 
         // Perform actual join with filtered probe side
         self.executor
@@ -570,16 +598,18 @@ mod tests {
         let build = QueryResult::new(
             vec!["id".to_string(), "name".to_string()],
             vec![
-                vec!["1".to_string(), "Alice".to_string()],
-                vec!["2".to_string(), "Bob".to_string()],
+                vec![Value::Integer(1), Value::String("Alice".to_string())],
+                vec![Value::Integer(2), Value::String("Bob".to_string())],
+                vec![Value::Null, Value::String("Nobody".to_string())],
             ],
         );
 
         let probe = QueryResult::new(
             vec!["id".to_string(), "value".to_string()],
             vec![
-                vec!["1".to_string(), "100".to_string()],
-                vec!["2".to_string(), "200".to_string()],
+                vec![Value::Integer(1), Value::Integer(100)],
+                vec![Value::Integer(2), Value::Integer(200)],
+                vec![Value::Null, Value::Integer(300)],
             ],
         );
 
@@ -603,8 +633,8 @@ mod tests {
     fn test_hash_partition() {
         let executor = HashJoinExecutor::with_default_config();
 
-        let key = "test_key";
-        let partition = executor.hash_partition(key);
+        let key = Value::String("test_key".to_string());
+        let partition = executor.hash_partition(&key);
 
         assert!(partition < executor.config.num_partitions);
     }
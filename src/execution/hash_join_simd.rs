@@ -50,6 +50,7 @@
 //
 // Speedup: 13x improvement
 
+use crate::common::Value;
 use crate::error::DbError;
 use crate::execution::QueryResult;
 use crate::index::simd_bloom::JoinBloomFilter;
@@ -152,8 +153,8 @@ impl SimdHashJoin {
             .rows
             .par_iter()
             .try_for_each(|row| -> Result<(), DbError> {
-                if let Some(key) = row.get(key_col) {
-                    let partition_id = self.hash_partition(key);
+                if let Some(key) = join_key(row, key_col) {
+                    let partition_id = self.hash_partition(&key);
                     let mut parts = partitions.write();
                     parts[partition_id].rows.push(row.clone());
                 }
@@ -174,8 +175,8 @@ impl SimdHashJoin {
             // Build Swiss table
             let mut swiss_table = SwissTable::with_capacity(row_count);
             for (idx, row) in partition.rows.iter().enumerate() {
-                if let Some(key) = row.get(key_col) {
-                    swiss_table.insert(key.clone(), idx);
+                if let Some(key) = join_key(row, key_col) {
+                    swiss_table.insert(key, idx);
                 }
             }
             partition.hash_table = Some(swiss_table);
@@ -184,8 +185,8 @@ impl SimdHashJoin {
             if self.config.use_bloom_filter {
                 let mut bloom = JoinBloomFilter::new(row_count);
                 for row in &partition.rows {
-                    if let Some(key) = row.get(key_col) {
-                        bloom.insert(key);
+                    if let Some(key) = join_key(row, key_col) {
+                        bloom.insert(&key);
                     }
                 }
                 partition.bloom_filter = Some(bloom);
@@ -207,8 +208,8 @@ impl SimdHashJoin {
             let mut parts: Vec<Vec<usize>> = vec![Vec::new(); self.config.num_partitions];
 
             for (idx, row) in probe_side.rows.iter().enumerate() {
-                if let Some(key) = row.get(key_col) {
-                    let partition_id = self.hash_partition(key);
+                if let Some(key) = join_key(row, key_col) {
+                    let partition_id = self.hash_partition(&key);
                     parts[partition_id].push(idx);
                 }
             }
@@ -231,16 +232,16 @@ impl SimdHashJoin {
 
                 for &probe_idx in probe_indices {
                     let probe_row = &probe_side.rows[probe_idx];
-                    if let Some(key) = probe_row.get(key_col) {
+                    if let Some(key) = join_key(probe_row, key_col) {
                         // Bloom filter pre-check
                         if let Some(bloom) = &partition.bloom_filter {
-                            if !bloom.contains(key) {
+                            if !bloom.contains(&key) {
                                 continue; // Definitely not in build side
                             }
                         }
 
                         // Swiss table probe (SIMD-accelerated)
-                        if let Some(&build_idx) = hash_table.get(&key.clone()) {
+                        if let Some(&build_idx) = hash_table.get(&key) {
                             partition_matches.push(Match {
                                 build_idx,
                                 probe_idx,
@@ -281,7 +282,7 @@ impl SimdHashJoin {
 
         // Materialize in parallel by partition (better cache locality)
         let batch_size = 1024;
-        let batches: Vec<Vec<Vec<String>>> = partition_matches
+        let batches: Vec<Vec<Vec<Value>>> = partition_matches
             .par_iter()
             .flat_map(|(_partition_id, partition_matches)| {
                 partition_matches
@@ -318,8 +319,14 @@ impl SimdHashJoin {
         // Combine column names
         let mut result_columns = probe_side.columns.clone();
         result_columns.extend(build_side.columns.clone());
-
-        Ok(QueryResult::new(result_columns, result_rows))
+        let mut result_types = probe_side.column_types.clone();
+        result_types.extend(build_side.column_types.clone());
+
+        Ok(QueryResult::typed(
+            result_columns,
+            result_types,
+            result_rows,
+        ))
     }
 
     // Hash partition a key to partition ID
@@ -330,10 +337,17 @@ impl SimdHashJoin {
     }
 }
 
+// Hashable form of a row's join key; NULL keys never match
+fn join_key(row: &[Value], key_col: usize) -> Option<String> {
+    row.get(key_col)
+        .filter(|key| !key.is_null())
+        .map(Value::to_display_string)
+}
+
 // A partition for build side
 struct Partition {
     // Rows in this partition
-    rows: Vec<Vec<String>>,
+    rows: Vec<Vec<Value>>,
     // Swiss table for this partition
     hash_table: Option<SwissTable<String, usize>>,
     // Bloom filter for this partition
@@ -378,6 +392,7 @@ pub struct SimdHashJoinStats {
 
 #[cfg(test)]
 mod tests {
+    use crate::common::Value;
     use crate::execution::{QueryResult, SimdHashJoin, SimdHashJoinConfig};
 
     #[test]
@@ -387,18 +402,18 @@ mod tests {
         let build = QueryResult::new(
             vec!["id".to_string(), "name".to_string()],
             vec![
-                vec!["1".to_string(), "Alice".to_string()],
-                vec!["2".to_string(), "Bob".to_string()],
-                vec!["3".to_string(), "Charlie".to_string()],
+                vec![Value::Integer(1), Value::String("Alice".to_string())],
+                vec![Value::Integer(2), Value::String("Bob".to_string())],
+                vec![Value::Integer(3), Value::String("Charlie".to_string())],
             ],
         );
 
         let probe = QueryResult::new(
             vec!["id".to_string(), "value".to_string()],
             vec![
-                vec!["1".to_string(), "100".to_string()],
-                vec!["2".to_string(), "200".to_string()],
-                vec!["4".to_string(), "400".to_string()], // No match
+                vec![Value::Integer(1), Value::Integer(100)],
+                vec![Value::Integer(2), Value::Integer(200)],
+                vec![Value::Integer(4), Value::Integer(400)], // No match
             ],
         );
 
@@ -408,6 +423,26 @@ mod tests {
         assert_eq!(result.columns.len(), 4); // 2 + 2 columns
     }
 
+    #[test]
+    fn test_null_keys_do_not_match() {
+        let join = SimdHashJoin::with_default_config();
+
+        let build = QueryResult::new(
+            vec!["id".to_string()],
+            vec![vec![Value::Null], vec![Value::Integer(1)]],
+        );
+        let probe = QueryResult::new(
+            vec!["id".to_string()],
+            vec![vec![Value::Null], vec![Value::Integer(1)]],
+        );
+
+        let result = join.execute(build, probe, 0, 0).unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(1), Value::Integer(1)]]
+        );
+    }
+
     #[test]
     fn test_large_join() {
         let join = SimdHashJoin::with_default_config();
@@ -415,7 +450,10 @@ mod tests {
         // Build side: 10K rows
         let mut build_rows = Vec::new();
         for i in 0..10_000 {
-            build_rows.push(vec![format!("{}", i), format!("name_{}", i)]);
+            build_rows.push(vec![
+                Value::Integer(i),
+                Value::String(format!("name_{}", i)),
+            ]);
         }
         let build = QueryResult::new(vec!["id".to_string(), "name".to_string()], build_rows);
 
@@ -423,7 +461,10 @@ mod tests {
         let mut probe_rows = Vec::new();
         for i in 0..100_000 {
             let id = i % 10_000; // Ensure matches
-            probe_rows.push(vec![format!("{}", id), format!("value_{}", i)]);
+            probe_rows.push(vec![
+                Value::Integer(id),
+                Value::String(format!("value_{}", i)),
+            ]);
         }
         let probe = QueryResult::new(vec!["id".to_string(), "value".to_string()], probe_rows);
 
@@ -438,12 +479,12 @@ mod tests {
 
         let build = QueryResult::new(
             vec!["id".to_string()],
-            vec![vec!["1".to_string()], vec!["2".to_string()]],
+            vec![vec![Value::Integer(1)], vec![Value::Integer(2)]],
         );
 
         let probe = QueryResult::new(
             vec!["id".to_string()],
-            vec![vec!["3".to_string()], vec!["4".to_string()]],
+            vec![vec![Value::Integer(3)], vec![Value::Integer(4)]],
         );
 
         let result = join.execute(build, probe, 0, 0).unwrap();
@@ -457,12 +498,12 @@ mod tests {
 
         let build = QueryResult::new(
             vec!["id".to_string()],
-            vec![vec!["1".to_string()], vec!["2".to_string()]],
+            vec![vec![Value::Integer(1)], vec![Value::Integer(2)]],
         );
 
         let probe = QueryResult::new(
             vec!["id".to_string()],
-            vec![vec!["1".to_string()], vec!["2".to_string()]],
+            vec![vec![Value::Integer(1)], vec![Value::Integer(2)]],
         );
 
         let result = join.execute(build, probe, 0, 0).unwrap();
@@ -482,9 +523,9 @@ mod tests {
 
         let join = SimdHashJoin::new(config);
 
-        let build = QueryResult::new(vec!["id".to_string()], vec![vec!["1".to_string()]]);
+        let build = QueryResult::new(vec!["id".to_string()], vec![vec![Value::Integer(1)]]);
 
-        let probe = QueryResult::new(vec!["id".to_string()], vec![vec!["1".to_string()]]);
+        let probe = QueryResult::new(vec!["id".to_string()], vec![vec![Value::Integer(1)]]);
 
         let result = join.execute(build, probe, 0, 0).unwrap();
         assert_eq!(result.rows.len(), 1);
@@ -1,6 +1,10 @@
+pub mod access_path;
 pub mod adaptive;
+pub mod analyze;
+pub mod binder;
 pub mod cte;
 pub mod executor;
+pub mod explain;
 pub mod expressions;
 pub mod hash_join;
 pub mod hash_join_simd;
@@ -8,6 +12,8 @@ pub mod optimization;
 pub mod optimizer;
 pub mod parallel;
 pub mod planner;
+pub mod prepared;
+pub mod session;
 pub mod sort_merge;
 pub mod string_functions;
 pub mod subquery;
@@ -26,7 +32,9 @@ pub use optimizer::{
     ColumnStatistics, IndexStatistics, Optimizer, SingleTableStatistics, TableStatistics,
 };
 pub use parallel::{ParallelExecutor, ParallelizationOptimizer};
-pub use planner::{PlanNode, Planner};
+pub use planner::{PlanNode, Planner, ScalarExpr};
+pub use prepared::{PreparedStatement, StatementCache};
+pub use session::Session;
 pub use sort_merge::{ExternalMergeSorter, SortMergeJoin, TopKSelector};
 pub use string_functions::{StringFunctionExecutor, StringFunctionValidator};
 pub use subquery::{
@@ -34,6 +42,8 @@ pub use subquery::{
 };
 pub use vectorized::{AggregationType, ColumnBatch, ColumnValue, VectorizedExecutor};
 
+use crate::catalog::DataType;
+use crate::common::Value;
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -48,36 +58,55 @@ pub const MAX_RESULT_ROWS: usize = 1_000_000;
 /// Prevents unbounded memory growth from CTE execution
 pub const MAX_MATERIALIZED_CTES: usize = 100;
 
+/// Maximum number of rounds a recursive WITH query may run
+/// Stops recursive queries that never reach a fixpoint
+pub const MAX_RECURSIVE_ITERATIONS: usize = 10_000;
+
 /// Maximum number of entries in the plan cache
 /// Prevents unbounded memory growth from plan caching
 pub const MAX_PLAN_CACHE_SIZE: usize = 10_000;
 
+/// Maximum number of parameters ($1 .. $n) in one statement
+/// Matches the 16-bit parameter count of the PostgreSQL wire protocol
+pub const MAX_PARAMETERS: usize = i16::MAX as usize;
+
+/// Maximum number of prepared statements one session may hold
+/// Prevents unbounded memory growth from clients that never deallocate
+pub const MAX_PREPARED_STATEMENTS: usize = 1_000;
+
 // Query execution result
 #[derive(Debug, Clone, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
 pub struct QueryResult {
     pub columns: Vec<String>,
-    pub rows: Vec<Vec<String>>,
+    #[bincode(with_serde)]
+    pub column_types: Vec<DataType>,
+    #[bincode(with_serde)]
+    pub rows: Vec<Vec<Value>>,
     pub rows_affected: usize,
     pub affected_rows: ()
 }
 
 impl QueryResult {
-    pub fn new(columns: Vec<String>, mut rows: Vec<Vec<String>>) -> Self {
-        // Enforce MAX_RESULT_ROWS limit to prevent OOM
-        // NOTE: In production, queries exceeding this limit should use
-        // streaming execution or server-side cursors
-        if rows.len() > MAX_RESULT_ROWS {
-            eprintln!(
-                "WARNING: Result set truncated from {} to {} rows. Use LIMIT clause or streaming execution for large results.",
-                rows.len(),
-                MAX_RESULT_ROWS
-            );
-            rows.truncate(MAX_RESULT_ROWS);
-        }
+    // Column types are taken from the first non-NULL value in each column
+    pub fn new(columns: Vec<String>, rows: Vec<Vec<Value>>) -> Self {
+        let column_types = (0..columns.len())
+            .map(|i| {
+                rows.iter()
+                    .filter_map(|row| row.get(i).and_then(DataType::of_value))
+                    .next()
+                    .unwrap_or(DataType::Text)
+            })
+            .collect();
+        Self::typed(columns, column_types, rows)
+    }
 
+    // Intermediate results of a plan go through here too, so the row limit is
+    // enforced by the executor on the result handed to the client instead
+    pub fn typed(columns: Vec<String>, column_types: Vec<DataType>, rows: Vec<Vec<Value>>) -> Self {
         let rows_affected = rows.len();
         Self {
             columns,
+            column_types,
             rows,
             rows_affected,
             affected_rows: (),
@@ -87,6 +116,7 @@ impl QueryResult {
     pub fn empty() -> Self {
         Self {
             columns: Vec::new(),
+            column_types: Vec::new(),
             rows: Vec::new(),
             rows_affected: 0,
             affected_rows: (),
@@ -96,6 +126,7 @@ impl QueryResult {
     pub fn with_affected(rows_affected: usize) -> Self {
         Self {
             columns: Vec::new(),
+            column_types: Vec::new(),
             rows: Vec::new(),
             rows_affected,
             affected_rows: (),
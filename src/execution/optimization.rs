@@ -9,7 +9,7 @@
 // - Index selection optimization
 
 use crate::error::DbError;
-use crate::execution::planner::PlanNode;
+use crate::execution::planner::{PlanNode, ScalarExpr};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -377,7 +377,7 @@ impl MaterializedViewRewriter {
                 // Rewrite to use materialized view
                 return Some(PlanNode::TableScan {
                     table: view.name.clone(),
-                    columns: vec!["*".to_string()],
+                    columns: plan.output_columns(),
                 });
             }
         }
@@ -387,18 +387,35 @@ impl MaterializedViewRewriter {
 
     fn get_query_tables(plan: &PlanNode) -> Vec<String> {
         match plan {
-            PlanNode::TableScan { table, .. } => vec![table.clone()],
-            PlanNode::Join { left, right, .. } => {
+            PlanNode::TableScan { table, .. }
+            | PlanNode::IndexScan { table, .. }
+            | PlanNode::IndexOnlyScan { table, .. }
+            | PlanNode::NearestNeighborScan { table, .. } => vec![table.clone()],
+            PlanNode::Join { left, right, .. }
+            | PlanNode::SetOperation { left, right, .. }
+            | PlanNode::RecursiveUnion {
+                base: left,
+                recursive: right,
+                ..
+            } => {
                 let mut tables = Self::get_query_tables(left);
                 tables.extend(Self::get_query_tables(right));
                 tables
             }
+            PlanNode::IndexNestedLoopJoin { left, table, .. } => {
+                let mut tables = Self::get_query_tables(left);
+                tables.push(table.clone());
+                tables
+            }
             PlanNode::Filter { input, .. }
             | PlanNode::Project { input, .. }
             | PlanNode::Sort { input, .. }
-            | PlanNode::Limit { input, .. } => Self::get_query_tables(input),
+            | PlanNode::Window { input, .. }
+            | PlanNode::Limit { input, .. }
+            | PlanNode::Distinct { input } => Self::get_query_tables(input),
             PlanNode::Aggregate { input, .. } => Self::get_query_tables(input),
             PlanNode::Subquery { plan, .. } => Self::get_query_tables(plan),
+            PlanNode::Values { .. } | PlanNode::WorkTableScan { .. } => Vec::new(),
         }
     }
 
@@ -465,6 +482,12 @@ impl EnhancedCostModel {
                 let input_cost = self.estimate_cost(input);
                 input_cost * input_cost.ln() // O(n log n)
             }
+            PlanNode::Window {
+                input, functions, ..
+            } => {
+                let input_cost = self.estimate_cost(input);
+                input_cost * (1.0 + 0.1 * functions.len() as f64) // One pass per function
+            }
             PlanNode::Limit { input, limit, .. } => {
                 let input_cost = self.estimate_cost(input);
                 input_cost.min(*limit as f64)
@@ -472,11 +495,38 @@ impl EnhancedCostModel {
             PlanNode::Project { input, .. } => {
                 self.estimate_cost(input) * 1.05 // Small projection overhead
             }
+            PlanNode::IndexScan { table, .. } | PlanNode::IndexOnlyScan { table, .. } => {
+                // An index lookup reads a fraction of the table
+                let table_cost = self.estimate_cost(&PlanNode::TableScan {
+                    table: table.clone(),
+                    columns: Vec::new(),
+                });
+                (table_cost * 0.1).max(1.0)
+            }
+            // A graph search visits a few nodes per row it returns
+            PlanNode::NearestNeighborScan { limit, .. } => (*limit as f64 * 2.0).max(1.0),
+            PlanNode::IndexNestedLoopJoin { left, .. } => {
+                let left_cost = self.estimate_cost(left);
+                left_cost * 2.0 // One lookup per left row
+            }
+            PlanNode::Distinct { input } => self.estimate_cost(input) * 1.2,
             PlanNode::Subquery { plan, .. } => self.estimate_cost(plan),
+            PlanNode::SetOperation { left, right, .. } => {
+                // Both inputs are hashed or concatenated once
+                (self.estimate_cost(left) + self.estimate_cost(right)) * 1.2
+            }
+            PlanNode::RecursiveUnion {
+                base, recursive, ..
+            } => {
+                // The recursive term runs once per round; assume ten rounds
+                self.estimate_cost(base) + self.estimate_cost(recursive) * 10.0
+            }
+            PlanNode::WorkTableScan { .. } => 100.0,
+            PlanNode::Values { rows, .. } => rows.len() as f64,
         }
     }
 
-    fn estimate_filter_selectivity(&self, _predicate: &str) -> f64 {
+    fn estimate_filter_selectivity(&self, _predicate: &ScalarExpr) -> f64 {
         // Simplified: return default selectivity
         // In a full implementation, would parse predicate and use column statistics
         0.3
@@ -561,7 +611,7 @@ impl JoinOrderOptimizer {
             join_type: crate::parser::JoinType::Inner,
             left: Box::new(left),
             right: Box::new(right),
-            condition: "true".to_string(),
+            condition: None,
         }
     }
 }
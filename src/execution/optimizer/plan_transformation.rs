@@ -40,6 +40,14 @@ impl MemoTable {
         self.plans.insert(hash, plan);
     }
 
+    pub fn len(&self) -> usize {
+        self.plans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plans.is_empty()
+    }
+
     pub fn clear(&mut self) {
         self.plans.clear();
         self.equivalence_classes.clear();
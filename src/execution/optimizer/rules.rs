@@ -1,18 +1,26 @@
 // Core Optimizer and Basic Optimization Rules
 // Includes predicate pushdown, join reordering, projection pushdown
 
+use crate::catalog::{Catalog, ColumnStats};
 use crate::error::DbError;
+use crate::execution::access_path::flip;
 use crate::execution::optimizer::cost_model::{SingleTableStatistics, TableStatistics};
+use crate::execution::expressions::{BinaryOperator, UnaryOperator};
 use crate::execution::optimizer::plan_transformation::{
-    AdaptiveStatistics, BitSet, ExpressionHash, MaterializedView, MemoTable,
+    AdaptiveStatistics, ExpressionHash, MaterializedView, MemoTable,
 };
-use crate::execution::planner::PlanNode;
+use crate::execution::planner::{PlanNode, ScalarExpr, SetOperator};
 use crate::parser::JoinType;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+// Plans memoized before the plan caches are reset
+const MAX_MEMOIZED_PLANS: usize = 1024;
+
 // Join ordering strategy
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum JoinOrderingStrategy {
@@ -47,6 +55,10 @@ pub struct Optimizer {
     cse_cache: Arc<RwLock<HashMap<ExpressionHash, PlanNode>>>,
     // Adaptive statistics feedback
     adaptive_stats: Arc<RwLock<AdaptiveStatistics>>,
+    // Catalog holding the ANALYZE statistics estimates use, if any
+    catalog: Option<Arc<Catalog>>,
+    // Catalog version the memoized plans were optimized at
+    catalog_version: AtomicU64,
 }
 
 impl Optimizer {
@@ -58,6 +70,8 @@ impl Optimizer {
             materialized_views: Arc::new(RwLock::new(Vec::new())),
             cse_cache: Arc::new(RwLock::new(HashMap::new())),
             adaptive_stats: Arc::new(RwLock::new(AdaptiveStatistics::new())),
+            catalog: None,
+            catalog_version: AtomicU64::new(0),
         }
     }
 
@@ -69,9 +83,19 @@ impl Optimizer {
             materialized_views: Arc::new(RwLock::new(Vec::new())),
             cse_cache: Arc::new(RwLock::new(HashMap::new())),
             adaptive_stats: Arc::new(RwLock::new(AdaptiveStatistics::new())),
+            catalog: None,
+            catalog_version: AtomicU64::new(0),
         }
     }
 
+    // Estimate from the statistics ANALYZE stores in `catalog` where there
+    // are no table statistics of the optimizer's own
+    pub fn with_catalog(mut self, catalog: Arc<Catalog>) -> Self {
+        self.catalog_version = AtomicU64::new(catalog.version());
+        self.catalog = Some(catalog);
+        self
+    }
+
     // Optimize a query plan using Cascades/Volcano framework with revolutionary techniques
     //
     // Optimization pipeline:
@@ -86,6 +110,15 @@ impl Optimizer {
     // 9. Cost-based plan selection
     // 10. Memoize result
     pub fn optimize(&self, plan: PlanNode) -> Result<PlanNode, DbError> {
+        // New statistics or schema changes may make other plans better
+        if let Some(catalog) = &self.catalog {
+            let version = catalog.version();
+            if self.catalog_version.swap(version, Ordering::SeqCst) != version {
+                self.memo_table.write().clear();
+                self.cse_cache.write().clear();
+            }
+        }
+
         // 1. Check memo table for cached equivalent plan
         let plan_hash = self.hash_plan(&plan);
         if let Some(cached) = self.memo_table.read().lookup(plan_hash) {
@@ -128,8 +161,13 @@ impl Optimizer {
         // 12. Apply adaptive statistics feedback
         optimized = self.apply_adaptive_feedback(optimized)?;
 
-        // 13. Store in memo table
-        self.memo_table.write().insert(plan_hash, optimized.clone());
+        // 13. Store in memo table, starting over once it is full
+        let mut memo = self.memo_table.write();
+        if memo.len() >= MAX_MEMOIZED_PLANS {
+            memo.clear();
+            self.cse_cache.write().clear();
+        }
+        memo.insert(plan_hash, optimized.clone());
 
         Ok(optimized)
     }
@@ -221,27 +259,7 @@ impl Optimizer {
                 let left = self.reorder_joins(*left)?;
                 let right = self.reorder_joins(*right)?;
 
-                // Estimate cardinalities
-                let _left_card = self.estimate_cardinality(&left);
-                let _right_card = self.estimate_cardinality(&right);
-
-                // Calculate join costs for both orders
-                let left_right_cost = self.estimate_join_cost(&left, &right, &join_type);
-                let right_left_cost = self.estimate_join_cost(&right, &left, &join_type);
-
-                // Choose better order
-                let (final_left, final_right) = if right_left_cost < left_right_cost {
-                    (Box::new(right), Box::new(left))
-                } else {
-                    (Box::new(left), Box::new(right))
-                };
-
-                Ok(PlanNode::Join {
-                    join_type,
-                    left: final_left,
-                    right: final_right,
-                    condition,
-                })
+                self.reorder_simple_join(join_type, left, right, condition)
             }
             PlanNode::Aggregate {
                 input,
@@ -345,13 +363,15 @@ impl Optimizer {
                 let stats = self.statistics.read();
                 if let Some(table_stats) = stats.tables.get(table) {
                     table_stats.row_count as f64
+                } else if let Some(row_count) = self.analyzed_row_count(table) {
+                    row_count as f64
                 } else {
                     1000.0 // Default estimate
                 }
             }
             PlanNode::Filter { input, predicate } => {
                 let input_card = self.estimate_cardinality(input);
-                let selectivity = self.estimate_filter_selectivity(predicate);
+                let selectivity = self.estimate_filter_selectivity(predicate, input);
                 input_card * selectivity
             }
             PlanNode::Join {
@@ -362,7 +382,7 @@ impl Optimizer {
             } => {
                 let left_card = self.estimate_cardinality(left);
                 let right_card = self.estimate_cardinality(right);
-                let selectivity = self.estimate_join_selectivity(condition);
+                let selectivity = self.estimate_join_selectivity(condition, plan);
 
                 match join_type {
                     JoinType::Inner => left_card * right_card * selectivity,
@@ -383,25 +403,212 @@ impl Optimizer {
                     (input_card / 10.0).max(1.0).min(input_card)
                 }
             }
-            PlanNode::Sort { input, .. } => self.estimate_cardinality(input),
+            PlanNode::Sort { input, .. } | PlanNode::Window { input, .. } => {
+                self.estimate_cardinality(input)
+            }
             PlanNode::Limit { input, limit, .. } => {
                 self.estimate_cardinality(input).min(*limit as f64)
             }
             PlanNode::Project { input, .. } => self.estimate_cardinality(input),
+            // An index lookup reads a fraction of the table
+            PlanNode::IndexScan { table, .. } | PlanNode::IndexOnlyScan { table, .. } => {
+                let table_card = self.estimate_cardinality(&PlanNode::TableScan {
+                    table: table.clone(),
+                    columns: Vec::new(),
+                });
+                (table_card * 0.1).max(1.0)
+            }
+            PlanNode::NearestNeighborScan { limit, .. } => *limit as f64,
+            PlanNode::IndexNestedLoopJoin { left, .. } => self.estimate_cardinality(left),
+            PlanNode::Distinct { input } => self.estimate_cardinality(input),
             PlanNode::Subquery { plan, .. } => self.estimate_cardinality(plan),
+            PlanNode::SetOperation {
+                op, left, right, ..
+            } => {
+                let left_card = self.estimate_cardinality(left);
+                let right_card = self.estimate_cardinality(right);
+                match op {
+                    SetOperator::Union => left_card + right_card,
+                    SetOperator::Intersect => left_card.min(right_card),
+                    SetOperator::Except => left_card,
+                }
+            }
+            // Assume the recursion runs ten rounds as wide as its base
+            PlanNode::RecursiveUnion { base, .. } => self.estimate_cardinality(base) * 10.0,
+            PlanNode::WorkTableScan { .. } => 100.0,
+            PlanNode::Values { rows, .. } => rows.len() as f64,
+        }
+    }
+
+    // Estimate the selectivity of a filter over `input`. Tests of an
+    // analyzed column against constants use the column's statistics; other
+    // predicates are judged by their shape
+    fn estimate_filter_selectivity(&self, predicate: &ScalarExpr, input: &PlanNode) -> f64 {
+        if let Some(selectivity) = self.column_selectivity(predicate, input) {
+            return selectivity;
+        }
+        match predicate {
+            ScalarExpr::Binary { left, op, right } => match op {
+                BinaryOperator::Equal => 0.1,
+                BinaryOperator::LessThan
+                | BinaryOperator::LessThanOrEqual
+                | BinaryOperator::GreaterThan
+                | BinaryOperator::GreaterThanOrEqual => 0.33,
+                BinaryOperator::And => {
+                    self.estimate_filter_selectivity(left, input)
+                        * self.estimate_filter_selectivity(right, input)
+                }
+                BinaryOperator::Or => (self.estimate_filter_selectivity(left, input)
+                    + self.estimate_filter_selectivity(right, input))
+                .min(1.0),
+                _ => 0.5,
+            },
+            _ => 0.5,
+        }
+    }
+
+    // Selectivity of a comparison, IN list, BETWEEN or NULL test of an
+    // analyzed column of `input` against constants
+    fn column_selectivity(&self, predicate: &ScalarExpr, input: &PlanNode) -> Option<f64> {
+        match predicate {
+            ScalarExpr::Binary { left, op, right } => {
+                let (index, op, value) = match (left.as_ref(), right.as_ref()) {
+                    (ScalarExpr::Column { index, .. }, ScalarExpr::Literal(value)) => {
+                        (*index, *op, value)
+                    }
+                    (ScalarExpr::Literal(value), ScalarExpr::Column { index, .. }) => {
+                        (*index, flip(*op), value)
+                    }
+                    _ => return None,
+                };
+                let stats = self.column_stats(input, index)?;
+                if value.is_null() {
+                    return Some(0.0);
+                }
+                Some(match op {
+                    BinaryOperator::Equal => stats.equal_selectivity(value),
+                    BinaryOperator::NotEqual => {
+                        (stats.null_selectivity(true) - stats.equal_selectivity(value)).max(0.0)
+                    }
+                    BinaryOperator::LessThan => {
+                        stats.range_selectivity(Bound::Unbounded, Bound::Excluded(value))
+                    }
+                    BinaryOperator::LessThanOrEqual => {
+                        stats.range_selectivity(Bound::Unbounded, Bound::Included(value))
+                    }
+                    BinaryOperator::GreaterThan => {
+                        stats.range_selectivity(Bound::Excluded(value), Bound::Unbounded)
+                    }
+                    BinaryOperator::GreaterThanOrEqual => {
+                        stats.range_selectivity(Bound::Included(value), Bound::Unbounded)
+                    }
+                    _ => return None,
+                })
+            }
+            ScalarExpr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let ScalarExpr::Column { index, .. } = expr.as_ref() else {
+                    return None;
+                };
+                let stats = self.column_stats(input, *index)?;
+                let mut selectivity = 0.0;
+                for item in list {
+                    let ScalarExpr::Literal(value) = item else {
+                        return None;
+                    };
+                    selectivity += stats.equal_selectivity(value);
+                }
+                let not_null = stats.null_selectivity(true);
+                let selectivity = selectivity.min(not_null);
+                Some(if *negated {
+                    not_null - selectivity
+                } else {
+                    selectivity
+                })
+            }
+            ScalarExpr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                let (
+                    ScalarExpr::Column { index, .. },
+                    ScalarExpr::Literal(low),
+                    ScalarExpr::Literal(high),
+                ) = (expr.as_ref(), low.as_ref(), high.as_ref())
+                else {
+                    return None;
+                };
+                let stats = self.column_stats(input, *index)?;
+                let inside = stats.range_selectivity(Bound::Included(low), Bound::Included(high));
+                Some(if *negated {
+                    (stats.null_selectivity(true) - inside).max(0.0)
+                } else {
+                    inside
+                })
+            }
+            ScalarExpr::Unary {
+                op: op @ (UnaryOperator::IsNull | UnaryOperator::IsNotNull),
+                expr,
+            } => {
+                let ScalarExpr::Column { index, .. } = expr.as_ref() else {
+                    return None;
+                };
+                let stats = self.column_stats(input, *index)?;
+                Some(stats.null_selectivity(*op == UnaryOperator::IsNotNull))
+            }
+            _ => None,
         }
     }
 
-    // Estimate filter selectivity
-    fn estimate_filter_selectivity(&self, _predicate: &str) -> f64 {
-        // Simplified - in production would parse predicate and use histograms
-        0.1 // Default 10% selectivity
+    // Estimate the selectivity of a join's condition. A join without a
+    // condition is a cross product; equalities between analyzed columns
+    // use their distinct value counts
+    fn estimate_join_selectivity(&self, condition: &Option<ScalarExpr>, join: &PlanNode) -> f64 {
+        let Some(condition) = condition else {
+            return 1.0;
+        };
+
+        let mut selectivity = None;
+        for conjunct in condition.clone().into_conjuncts() {
+            let ScalarExpr::Binary {
+                left,
+                op: BinaryOperator::Equal,
+                right,
+            } = &conjunct
+            else {
+                continue;
+            };
+            let (ScalarExpr::Column { index: left, .. }, ScalarExpr::Column { index: right, .. }) =
+                (left.as_ref(), right.as_ref())
+            else {
+                continue;
+            };
+            if let (Some(left), Some(right)) = (
+                self.column_stats(join, *left),
+                self.column_stats(join, *right),
+            ) {
+                selectivity = Some(selectivity.unwrap_or(1.0) * left.join_selectivity(&right));
+            }
+        }
+        selectivity.unwrap_or(0.01)
+    }
+
+    // Rows `table` had when it was last analyzed
+    fn analyzed_row_count(&self, table: &str) -> Option<u64> {
+        let stats = self.catalog.as_ref()?.get_statistics(table)?;
+        Some(stats.row_count)
     }
 
-    // Estimate join selectivity
-    fn estimate_join_selectivity(&self, _condition: &str) -> f64 {
-        // Simplified - would analyze join keys and compute selectivity
-        0.01 // Default 1% selectivity
+    // ANALYZE statistics of the table column behind column `index` of `plan`
+    fn column_stats(&self, plan: &PlanNode, index: usize) -> Option<ColumnStats> {
+        let (table, column) = column_origin(plan, index)?;
+        let stats = self.catalog.as_ref()?.get_statistics(&table)?;
+        stats.column(&column).cloned()
     }
 
     // Estimate cost of a specific join
@@ -433,7 +640,7 @@ impl Optimizer {
     }
 
     // Select the best index for a table scan (if available)
-    pub fn select_index(&self, table: &str, _filter: Option<&str>) -> Option<String> {
+    pub fn select_index(&self, table: &str, _filter: Option<&ScalarExpr>) -> Option<String> {
         let stats = self.statistics.read();
         if let Some(table_stats) = stats.tables.get(table) {
             // Select most selective index
@@ -475,12 +682,16 @@ impl Optimizer {
         view: &MaterializedView,
     ) -> Result<Option<PlanNode>, DbError> {
         match (plan, &view.definition) {
-            (PlanNode::TableScan { table: t1, .. }, PlanNode::TableScan { table: t2, .. })
-                if t1 == t2 =>
-            {
+            (
+                PlanNode::TableScan {
+                    table: t1, columns, ..
+                },
+                PlanNode::TableScan { table: t2, .. },
+            ) if t1 == t2 => {
+                // Keep the scanned columns so bound positions stay valid
                 Ok(Some(PlanNode::TableScan {
                     table: view.name.clone(),
-                    columns: vec!["*".to_string()],
+                    columns: columns.clone(),
                 }))
             }
             _ => Ok(None),
@@ -488,11 +699,11 @@ impl Optimizer {
     }
 
     fn eliminate_common_subexpressions(&self, plan: PlanNode) -> Result<PlanNode, DbError> {
-        let mut cache = self.cse_cache.write();
         let hash = self.hash_plan(&plan);
         let expr_hash = ExpressionHash(hash);
 
-        if let Some(cached) = cache.get(&expr_hash) {
+        // The lock is not reentrant, so it must not be held while recursing
+        if let Some(cached) = self.cse_cache.read().get(&expr_hash) {
             return Ok(cached.clone());
         }
 
@@ -533,7 +744,7 @@ impl Optimizer {
             other => other,
         };
 
-        cache.insert(expr_hash, optimized.clone());
+        self.cse_cache.write().insert(expr_hash, optimized.clone());
         Ok(optimized)
     }
 
@@ -593,6 +804,9 @@ impl Optimizer {
         Ok(plan)
     }
 
+    // Join inputs are bound by position, so enumerating orders over bare
+    // table scans would lose the projections and predicates underneath them.
+    // Each join is instead costed in both orientations bottom-up.
     fn reorder_joins_dpccp(&self, plan: PlanNode) -> Result<PlanNode, DbError> {
         match plan {
             PlanNode::Join {
@@ -603,14 +817,7 @@ impl Optimizer {
             } => {
                 let left = self.reorder_joins_dpccp(*left)?;
                 let right = self.reorder_joins_dpccp(*right)?;
-                let tables = self.extract_tables(&left, &right);
-
-                if tables.len() <= 2 {
-                    return self.reorder_simple_join(join_type, left, right, condition);
-                }
-
-                let best_plan = self.dpccp_enumerate(&tables, &join_type, &condition)?;
-                Ok(best_plan)
+                self.reorder_simple_join(join_type, left, right, condition)
             }
             PlanNode::Filter { input, predicate } => Ok(PlanNode::Filter {
                 input: Box::new(self.reorder_joins_dpccp(*input)?),
@@ -627,6 +834,74 @@ impl Optimizer {
                 aggregates,
                 having,
             }),
+            PlanNode::Project {
+                input,
+                exprs,
+                columns,
+            } => Ok(PlanNode::Project {
+                input: Box::new(self.reorder_joins_dpccp(*input)?),
+                exprs,
+                columns,
+            }),
+            PlanNode::Sort { input, order_by } => Ok(PlanNode::Sort {
+                input: Box::new(self.reorder_joins_dpccp(*input)?),
+                order_by,
+            }),
+            PlanNode::Window {
+                input,
+                partition_by,
+                order_by,
+                functions,
+            } => Ok(PlanNode::Window {
+                input: Box::new(self.reorder_joins_dpccp(*input)?),
+                partition_by,
+                order_by,
+                functions,
+            }),
+            PlanNode::Limit {
+                input,
+                limit,
+                offset,
+            } => Ok(PlanNode::Limit {
+                input: Box::new(self.reorder_joins_dpccp(*input)?),
+                limit,
+                offset,
+            }),
+            PlanNode::Distinct { input } => Ok(PlanNode::Distinct {
+                input: Box::new(self.reorder_joins_dpccp(*input)?),
+            }),
+            PlanNode::Subquery { plan, alias } => Ok(PlanNode::Subquery {
+                plan: Box::new(self.reorder_joins_dpccp(*plan)?),
+                alias,
+            }),
+            PlanNode::SetOperation {
+                op,
+                all,
+                left,
+                right,
+            } => Ok(PlanNode::SetOperation {
+                op,
+                all,
+                left: Box::new(self.reorder_joins_dpccp(*left)?),
+                right: Box::new(self.reorder_joins_dpccp(*right)?),
+            }),
+            PlanNode::RecursiveUnion {
+                name,
+                columns,
+                base,
+                recursive,
+                all,
+                search,
+                cycle,
+            } => Ok(PlanNode::RecursiveUnion {
+                name,
+                columns,
+                base: Box::new(self.reorder_joins_dpccp(*base)?),
+                recursive: Box::new(self.reorder_joins_dpccp(*recursive)?),
+                all,
+                search,
+                cycle,
+            }),
             other => Ok(other),
         }
     }
@@ -636,109 +911,23 @@ impl Optimizer {
         join_type: JoinType,
         left: PlanNode,
         right: PlanNode,
-        condition: String,
+        condition: Option<ScalarExpr>,
     ) -> Result<PlanNode, DbError> {
+        // Only inner and cross joins are symmetric
+        let swappable = matches!(join_type, JoinType::Inner | JoinType::Cross);
         let left_right_cost = self.estimate_join_cost(&left, &right, &join_type);
         let right_left_cost = self.estimate_join_cost(&right, &left, &join_type);
 
-        let (final_left, final_right) = if right_left_cost < left_right_cost {
-            (Box::new(right), Box::new(left))
-        } else {
-            (Box::new(left), Box::new(right))
-        };
-
-        Ok(PlanNode::Join {
-            join_type,
-            left: final_left,
-            right: final_right,
-            condition,
-        })
-    }
-
-    fn extract_tables(&self, left: &PlanNode, right: &PlanNode) -> Vec<String> {
-        let mut tables = Vec::new();
-        self.collect_tables(left, &mut tables);
-        self.collect_tables(right, &mut tables);
-        tables.sort();
-        tables.dedup();
-        tables
-    }
-
-    fn collect_tables(&self, plan: &PlanNode, tables: &mut Vec<String>) {
-        match plan {
-            PlanNode::TableScan { table, .. } => {
-                tables.push(table.clone());
-            }
-            PlanNode::Join { left, right, .. } => {
-                self.collect_tables(left, tables);
-                self.collect_tables(right, tables);
-            }
-            PlanNode::Filter { input, .. } => {
-                self.collect_tables(input, tables);
-            }
-            PlanNode::Aggregate { input, .. } => {
-                self.collect_tables(input, tables);
-            }
-            _ => {}
-        }
-    }
-
-    fn dpccp_enumerate(
-        &self,
-        tables: &[String],
-        join_type: &JoinType,
-        condition: &str,
-    ) -> Result<PlanNode, DbError> {
-        let mut dp: HashMap<BitSet, (PlanNode, f64)> = HashMap::new();
-
-        // Base case: single tables
-        for (i, table) in tables.iter().enumerate() {
-            let bitset = BitSet::singleton(i);
-            let plan = PlanNode::TableScan {
-                table: table.clone(),
-                columns: vec!["*".to_string()],
-            };
-            let cost = self.estimate_cost(&plan);
-            dp.insert(bitset, (plan, cost));
-        }
-
-        // Bottom-up DP
-        for subset_size in 2..=tables.len() {
-            let subsets = BitSet::enumerate_subsets(tables.len(), subset_size);
-
-            for subset in subsets {
-                let mut best_plan: Option<(PlanNode, f64)> = None;
-                let partitions = subset.enumerate_connected_partitions();
-
-                for (left_bits, right_bits) in partitions {
-                    if let (Some((left_plan, _)), Some((right_plan, _))) =
-                        (dp.get(&left_bits), dp.get(&right_bits))
-                    {
-                        let join_plan = PlanNode::Join {
-                            join_type: join_type.clone(),
-                            left: Box::new(left_plan.clone()),
-                            right: Box::new(right_plan.clone()),
-                            condition: condition.to_string(),
-                        };
-
-                        let join_cost = self.estimate_cost(&join_plan);
-
-                        if best_plan.is_none() || join_cost < best_plan.as_ref().unwrap().1 {
-                            best_plan = Some((join_plan, join_cost));
-                        }
-                    }
-                }
-
-                if let Some((plan, cost)) = best_plan {
-                    dp.insert(subset, (plan, cost));
-                }
-            }
+        if !swappable || right_left_cost >= left_right_cost {
+            return Ok(PlanNode::Join {
+                join_type,
+                left: Box::new(left),
+                right: Box::new(right),
+                condition,
+            });
         }
 
-        let full_set = BitSet::full(tables.len());
-        dp.get(&full_set)
-            .map(|(plan, _)| plan.clone())
-            .ok_or_else(|| DbError::Internal("DPccp failed to find plan".to_string()))
+        Ok(swap_join_inputs(join_type, left, right, condition))
     }
 
     fn apply_adaptive_feedback(&self, plan: PlanNode) -> Result<PlanNode, DbError> {
@@ -761,3 +950,100 @@ impl Default for Optimizer {
         Self::new()
     }
 }
+
+// The table and column that column `index` of `plan`'s output passes
+// through unchanged, if any
+fn column_origin(plan: &PlanNode, index: usize) -> Option<(String, String)> {
+    match plan {
+        PlanNode::TableScan { table, columns }
+        | PlanNode::IndexScan { table, columns, .. }
+        | PlanNode::IndexOnlyScan { table, columns, .. }
+        | PlanNode::NearestNeighborScan { table, columns, .. } => {
+            Some((table.clone(), columns.get(index)?.clone()))
+        }
+        PlanNode::Filter { input, .. }
+        | PlanNode::Sort { input, .. }
+        | PlanNode::Limit { input, .. }
+        | PlanNode::Distinct { input }
+        | PlanNode::Subquery { plan: input, .. } => column_origin(input, index),
+        PlanNode::Project { input, exprs, .. } => match exprs.get(index)? {
+            ScalarExpr::Column { index, .. } => column_origin(input, *index),
+            _ => None,
+        },
+        // Window columns follow the input's
+        PlanNode::Window { input, .. } => match index < input.output_columns().len() {
+            true => column_origin(input, index),
+            false => None,
+        },
+        PlanNode::Join { left, right, .. } => {
+            let width = left.output_columns().len();
+            if index < width {
+                column_origin(left, index)
+            } else {
+                column_origin(right, index - width)
+            }
+        }
+        PlanNode::IndexNestedLoopJoin {
+            left,
+            table,
+            columns,
+            ..
+        } => {
+            let width = left.output_columns().len();
+            if index < width {
+                column_origin(left, index)
+            } else {
+                Some((table.clone(), columns.get(index - width)?.clone()))
+            }
+        }
+        // Rows of set operations and recursive queries come from several
+        // inputs
+        PlanNode::Aggregate { .. }
+        | PlanNode::Values { .. }
+        | PlanNode::SetOperation { .. }
+        | PlanNode::RecursiveUnion { .. }
+        | PlanNode::WorkTableScan { .. } => None,
+    }
+}
+
+// Swap the inputs of a symmetric join. The condition is remapped onto the new
+// column positions and a projection restores the original output order.
+fn swap_join_inputs(
+    join_type: JoinType,
+    left: PlanNode,
+    right: PlanNode,
+    condition: Option<ScalarExpr>,
+) -> PlanNode {
+    let left_columns = left.output_columns();
+    let right_columns = right.output_columns();
+    let (left_len, right_len) = (left_columns.len(), right_columns.len());
+    let condition = condition.map(|mut cond| {
+        cond.remap_columns(&|i| if i < left_len { i + right_len } else { i - left_len });
+        cond
+    });
+
+    let exprs = left_columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| ScalarExpr::column(right_len + i, name.clone()))
+        .chain(
+            right_columns
+                .iter()
+                .enumerate()
+                .map(|(i, name)| ScalarExpr::column(i, name.clone())),
+        )
+        .collect();
+    let mut columns = left_columns;
+    columns.extend(right_columns);
+
+    PlanNode::Project {
+        input: Box::new(PlanNode::Join {
+            join_type,
+            left: Box::new(right),
+            right: Box::new(left),
+            condition,
+        }),
+        exprs,
+        columns,
+    }
+}
@@ -8,8 +8,12 @@
 // - Thread pool management
 // - Query parallelization optimizer
 
+use crate::common::Value;
 use crate::error::DbError;
-use crate::execution::{planner::PlanNode, QueryResult};
+use crate::execution::{
+    planner::{PlanNode, ScalarExpr},
+    QueryResult,
+};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::collections::VecDeque;
@@ -63,7 +67,8 @@ impl ParallelExecutor {
                 aggregates,
                 having,
             } => {
-                self.parallel_aggregate(input, group_by, aggregates, having)
+                let group_by: Vec<String> = group_by.iter().map(|expr| expr.to_string()).collect();
+                self.parallel_aggregate(input, &group_by, aggregates, having)
                     .await
             }
             _ => {
@@ -126,7 +131,7 @@ impl ParallelExecutor {
         _join_type: &crate::parser::JoinType,
         left: &PlanNode,
         right: &PlanNode,
-        _condition: &str,
+        _condition: &Option<ScalarExpr>,
     ) -> Result<QueryResult, DbError> {
         // Execute left and right in parallel
         let left_handle = {
@@ -169,7 +174,8 @@ impl ParallelExecutor {
             let handle = tokio::spawn(async move {
                 let mut ht = ht.write();
                 for row in partition {
-                    if let Some(key) = row.get(0) {
+                    // NULL keys never match
+                    if let Some(key) = row.first().filter(|k| !k.is_null()) {
                         ht.entry(key.clone()).or_insert_with(Vec::new).push(row);
                     }
                 }
@@ -195,7 +201,7 @@ impl ParallelExecutor {
                 let ht = ht.read();
 
                 for row in partition {
-                    if let Some(key) = row.get(0) {
+                    if let Some(key) = row.first().filter(|k| !k.is_null()) {
                         if let Some(matching_rows) = ht.get(key) {
                             for right_row in matching_rows {
                                 let mut joined = row.clone();
@@ -231,7 +237,7 @@ impl ParallelExecutor {
         _input: &PlanNode,
         group_by: &[String],
         aggregates: &[crate::execution::planner::AggregateExpr],
-        _having: &Option<String>,
+        _having: &Option<ScalarExpr>,
     ) -> Result<QueryResult, DbError> {
         // Execute input plan
         // In real implementation, would execute input
@@ -276,7 +282,7 @@ impl ParallelExecutor {
 
         Ok(QueryResult::new(
             vec!["count".to_string()],
-            vec![vec![total_count.to_string()]],
+            vec![vec![Value::Integer(total_count as i64)]],
         ))
     }
 
@@ -296,7 +302,7 @@ impl ParallelExecutor {
             let group_by = group_by.clone();
             let handle = tokio::spawn(async move {
                 // Compute local aggregates for this partition
-                let mut local_groups: HashMap<Vec<String>, u64> = HashMap::new();
+                let mut local_groups: HashMap<Vec<Value>, u64> = HashMap::new();
 
                 for row in partition {
                     let key = row[..group_by.len()].to_vec();
@@ -309,7 +315,7 @@ impl ParallelExecutor {
         }
 
         // Merge local aggregates
-        let mut global_groups: HashMap<Vec<String>, u64> = HashMap::new();
+        let mut global_groups: HashMap<Vec<Value>, u64> = HashMap::new();
         for handle in handles {
             if let Ok(local_groups) = handle.await {
                 for (key, count) in local_groups {
@@ -322,7 +328,7 @@ impl ParallelExecutor {
         let mut rows = Vec::new();
         for (key, count) in global_groups {
             let mut row = key;
-            row.push(count.to_string());
+            row.push(Value::Integer(count as i64));
             rows.push(row);
         }
 
@@ -332,7 +338,7 @@ impl ParallelExecutor {
         Ok(QueryResult::new(columns, rows))
     }
 
-    fn partition_rows(rows: &[Vec<String>], num_partitions: usize) -> Vec<Vec<Vec<String>>> {
+    fn partition_rows(rows: &[Vec<Value>], num_partitions: usize) -> Vec<Vec<Vec<Value>>> {
         let mut partitions = vec![Vec::new(); num_partitions];
 
         for (i, row) in rows.iter().enumerate() {
@@ -470,14 +476,14 @@ pub struct ParallelSorter;
 impl ParallelSorter {
     // Parallel sort using merge sort
     pub async fn parallel_sort(
-        rows: Vec<Vec<String>>,
+        rows: Vec<Vec<Value>>,
         column_index: usize,
         num_workers: usize,
-    ) -> Result<Vec<Vec<String>>, DbError> {
+    ) -> Result<Vec<Vec<Value>>, DbError> {
         if rows.len() < 1000 {
             // Small dataset, use sequential sort
             let mut sorted = rows;
-            sorted.sort_by(|a, b| a.get(column_index).cmp(&b.get(column_index)));
+            sorted.sort_by(|a, b| Self::compare(a, b, column_index));
             return Ok(sorted);
         }
 
@@ -493,7 +499,7 @@ impl ParallelSorter {
 
             let handle = tokio::spawn(async move {
                 // Sort this chunk
-                chunk.sort_by(|a, b| a.get(col_idx).cmp(&b.get(col_idx)));
+                chunk.sort_by(|a, b| Self::compare(a, b, col_idx));
                 chunk
             });
 
@@ -513,7 +519,14 @@ impl ParallelSorter {
         Ok(Self::merge_sorted_chunks(sorted_chunks, column_index))
     }
 
-    fn merge_sorted_chunks(chunks: Vec<Vec<Vec<String>>>, column_index: usize) -> Vec<Vec<String>> {
+    // Order rows by one column; NULLs sort last
+    fn compare(a: &[Value], b: &[Value], column_index: usize) -> std::cmp::Ordering {
+        let left = a.get(column_index).unwrap_or(&Value::Null);
+        let right = b.get(column_index).unwrap_or(&Value::Null);
+        left.sort_cmp(right)
+    }
+
+    fn merge_sorted_chunks(chunks: Vec<Vec<Vec<Value>>>, column_index: usize) -> Vec<Vec<Value>> {
         if chunks.is_empty() {
             return Vec::new();
         }
@@ -529,15 +542,17 @@ impl ParallelSorter {
         loop {
             // Find minimum element across all chunks
             let mut min_chunk = None;
-            let mut min_value: Option<&str> = None;
+            let mut min_row: Option<&[Value]> = None;
 
             for (chunk_id, &idx) in chunk_indices.iter().enumerate() {
-                if idx < chunks[chunk_id].len() {
-                    if let Some(value) = chunks[chunk_id][idx].get(column_index) {
-                        if min_value.is_none() || value.as_str() < min_value.unwrap() {
-                            min_value = Some(value.as_str());
-                            min_chunk = Some(chunk_id);
-                        }
+                if let Some(row) = chunks[chunk_id].get(idx) {
+                    let smaller = match min_row {
+                        None => true,
+                        Some(min) => Self::compare(row, min, column_index).is_lt(),
+                    };
+                    if smaller {
+                        min_row = Some(row);
+                        min_chunk = Some(chunk_id);
                     }
                 }
             }
@@ -615,9 +630,9 @@ impl VectorizedExecutor {
 
     // Execute filter in batches
     pub fn filter_batched(
-        rows: Vec<Vec<String>>,
-        predicate: impl Fn(&[String]) -> bool,
-    ) -> Vec<Vec<String>> {
+        rows: Vec<Vec<Value>>,
+        predicate: impl Fn(&[Value]) -> bool,
+    ) -> Vec<Vec<Value>> {
         let mut result = Vec::new();
 
         for batch_start in (0..rows.len()).step_by(Self::BATCH_SIZE) {
@@ -672,36 +687,38 @@ mod tests {
     #[tokio::test]
     async fn test_parallel_sort() {
         let rows = vec![
-            vec!["3".to_string(), "c".to_string()],
-            vec!["1".to_string(), "a".to_string()],
-            vec!["2".to_string(), "b".to_string()],
+            vec![Value::Integer(3), Value::String("c".to_string())],
+            vec![Value::Integer(1), Value::String("a".to_string())],
+            vec![Value::Null, Value::String("n".to_string())],
+            vec![Value::Integer(2), Value::String("b".to_string())],
         ];
 
         let sorted = ParallelSorter::parallel_sort(rows, 0, 2).await.unwrap();
 
-        assert_eq!(sorted[0][0], "1");
-        assert_eq!(sorted[1][0], "2");
-        assert_eq!(sorted[2][0], "3");
+        assert_eq!(sorted[0][0], Value::Integer(1));
+        assert_eq!(sorted[1][0], Value::Integer(2));
+        assert_eq!(sorted[2][0], Value::Integer(3));
+        assert_eq!(sorted[3][0], Value::Null);
     }
 
     #[test]
     fn test_vectorized_filter() {
         let rows = vec![
-            vec!["1".to_string(), "10".to_string()],
-            vec!["2".to_string(), "20".to_string()],
-            vec!["3".to_string(), "30".to_string()],
-            vec!["4".to_string(), "40".to_string()],
+            vec![Value::Integer(1), Value::Integer(10)],
+            vec![Value::Integer(2), Value::Integer(20)],
+            vec![Value::Integer(3), Value::Integer(30)],
+            vec![Value::Integer(4), Value::Integer(40)],
         ];
 
         let filtered = VectorizedExecutor::filter_batched(rows, |row| {
-            row.get(0)
-                .and_then(|v| v.parse::<i32>().ok())
-                .map(|n| n > 2)
+            row.first()
+                .and_then(|v| v.sql_cmp(&Value::Integer(2)))
+                .map(|o| o.is_gt())
                 .unwrap_or(false)
         });
 
         assert_eq!(filtered.len(), 2);
-        assert_eq!(filtered[0][0], "3");
-        assert_eq!(filtered[1][0], "4");
+        assert_eq!(filtered[0][0], Value::Integer(3));
+        assert_eq!(filtered[1][0], Value::Integer(4));
     }
 }
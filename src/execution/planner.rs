@@ -1,58 +1,1199 @@
+use crate::analytics::window_functions::{FrameExclusion, FrameType};
+use crate::catalog::{Catalog, DataType};
+use crate::common::Value;
 use crate::error::DbError;
-use crate::parser::{JoinType, OrderByClause, SqlStatement};
+use crate::execution::binder::Binder;
+use crate::execution::expressions::{BinaryOperator, UnaryOperator};
+use crate::parser::{JoinType, SqlStatement};
+use std::fmt;
+use std::sync::Arc;
 
 // Query plan node
-#[derive(Debug, Clone)]
+//
+// Plans are bound: every expression refers to its input by column position,
+// so operators never resolve names at execution time. A node's output is the
+// concatenation described by `output_columns`.
+#[derive(Debug, Clone, PartialEq)]
 pub enum PlanNode {
     TableScan {
         table: String,
         columns: Vec<String>,
     },
+    // Rows of `table` an index lookup finds, with all the table's columns.
+    // It may return rows outside the bounds: the filter or join condition
+    // the bounds came from stays in the plan and rechecks every row
+    IndexScan {
+        table: String,
+        index: String,
+        columns: Vec<String>,
+        bounds: IndexBounds,
+    },
+    // An index scan that reads the key columns from the index alone; the
+    // other columns are NULL and nothing above refers to them
+    IndexOnlyScan {
+        table: String,
+        index: String,
+        columns: Vec<String>,
+        bounds: IndexBounds,
+    },
+    // The `limit` rows of `table` an HNSW index finds nearest to `query`,
+    // with all the table's columns. The search is approximate and the rows
+    // come unordered: the sort by distance stays in the plan above it
+    NearestNeighborScan {
+        table: String,
+        index: String,
+        columns: Vec<String>,
+        query: ScalarExpr,
+        limit: usize,
+    },
+    // For each left row, the rows of `table` an index lookup with bounds
+    // evaluated against the left row finds, joined on `condition`. Output
+    // is the left columns followed by the table's columns
+    IndexNestedLoopJoin {
+        join_type: JoinType,
+        left: Box<PlanNode>,
+        table: String,
+        index: String,
+        columns: Vec<String>,
+        bounds: IndexBounds,
+        condition: Option<ScalarExpr>,
+    },
     Filter {
         input: Box<PlanNode>,
-        predicate: String,
+        predicate: ScalarExpr,
     },
     Project {
         input: Box<PlanNode>,
+        exprs: Vec<ScalarExpr>,
         columns: Vec<String>,
     },
+    // Output is the left columns followed by the right columns
     Join {
         join_type: JoinType,
         left: Box<PlanNode>,
         right: Box<PlanNode>,
-        condition: String,
+        condition: Option<ScalarExpr>,
     },
+    // Output is the group keys followed by the aggregates; HAVING is bound
+    // against that output
     Aggregate {
         input: Box<PlanNode>,
-        group_by: Vec<String>,
+        group_by: Vec<ScalarExpr>,
         aggregates: Vec<AggregateExpr>,
-        having: Option<String>,
+        having: Option<ScalarExpr>,
     },
     Sort {
         input: Box<PlanNode>,
-        order_by: Vec<OrderByClause>,
+        order_by: Vec<SortKey>,
+    },
+    // Window functions sharing one PARTITION BY and ORDER BY. The input
+    // arrives sorted by the partition keys and then `order_by`; output is
+    // the input columns followed by one column per function, in input order
+    Window {
+        input: Box<PlanNode>,
+        partition_by: Vec<ScalarExpr>,
+        order_by: Vec<SortKey>,
+        functions: Vec<WindowExpr>,
     },
     Limit {
         input: Box<PlanNode>,
         limit: usize,
         offset: Option<usize>,
     },
+    Distinct {
+        input: Box<PlanNode>,
+    },
     Subquery {
         plan: Box<PlanNode>,
         alias: String,
     },
+    // UNION, INTERSECT or EXCEPT of inputs with the same number of columns;
+    // output is named and typed as the left input. Without `all` no row
+    // appears twice
+    SetOperation {
+        op: SetOperator,
+        all: bool,
+        left: Box<PlanNode>,
+        right: Box<PlanNode>,
+    },
+    // WITH RECURSIVE query `name`: `base` runs once, then `recursive` runs
+    // over the rows the previous round added, which WorkTableScan reads,
+    // until a round adds none. Without `all` a row already produced is not
+    // added again. Output is `columns`, then the SEARCH column, then the
+    // CYCLE mark and path. The recursive term's rows end with the SEARCH and
+    // CYCLE path columns of the row they were reached from
+    RecursiveUnion {
+        name: String,
+        columns: Vec<String>,
+        base: Box<PlanNode>,
+        recursive: Box<PlanNode>,
+        all: bool,
+        search: Option<SearchClause>,
+        cycle: Option<CycleClause>,
+    },
+    // The rows the previous round of recursive query `name` added, without
+    // their CYCLE mark
+    WorkTableScan {
+        name: String,
+        columns: Vec<String>,
+        types: Vec<DataType>,
+    },
+    // Constant rows, e.g. the single empty row of a SELECT without FROM
+    Values {
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    },
+}
+
+impl PlanNode {
+    // Names of the columns this node produces, in order
+    pub fn output_columns(&self) -> Vec<String> {
+        match self {
+            PlanNode::TableScan { columns, .. }
+            | PlanNode::IndexScan { columns, .. }
+            | PlanNode::IndexOnlyScan { columns, .. }
+            | PlanNode::NearestNeighborScan { columns, .. }
+            | PlanNode::Project { columns, .. }
+            | PlanNode::Values { columns, .. } => columns.clone(),
+            PlanNode::Filter { input, .. }
+            | PlanNode::Sort { input, .. }
+            | PlanNode::Limit { input, .. }
+            | PlanNode::Distinct { input } => input.output_columns(),
+            PlanNode::Join { left, right, .. } => {
+                let mut columns = left.output_columns();
+                columns.extend(right.output_columns());
+                columns
+            }
+            PlanNode::IndexNestedLoopJoin { left, columns, .. } => {
+                let mut output = left.output_columns();
+                output.extend(columns.iter().cloned());
+                output
+            }
+            PlanNode::Aggregate {
+                group_by,
+                aggregates,
+                ..
+            } => group_by
+                .iter()
+                .map(|expr| expr.to_string())
+                .chain(aggregates.iter().map(|agg| agg.column.clone()))
+                .collect(),
+            PlanNode::Window {
+                input, functions, ..
+            } => {
+                let mut columns = input.output_columns();
+                columns.extend(functions.iter().map(|function| function.column.clone()));
+                columns
+            }
+            PlanNode::Subquery { plan, .. } => plan.output_columns(),
+            PlanNode::SetOperation { left, .. } => left.output_columns(),
+            PlanNode::RecursiveUnion {
+                columns,
+                search,
+                cycle,
+                ..
+            } => {
+                let mut output = columns.clone();
+                output.extend(search.iter().map(|search| search.column.clone()));
+                if let Some(cycle) = cycle {
+                    output.extend([cycle.mark.clone(), cycle.path.clone()]);
+                }
+                output
+            }
+            PlanNode::WorkTableScan { columns, .. } => columns.clone(),
+        }
+    }
+
+    // Static types of the output columns, for describing a result before
+    // running the plan; columns whose type depends on the values are TEXT
+    pub fn output_types(&self, catalog: &Catalog) -> Result<Vec<DataType>, DbError> {
+        Ok(match self {
+            PlanNode::TableScan { table, columns }
+            | PlanNode::IndexScan { table, columns, .. }
+            | PlanNode::IndexOnlyScan { table, columns, .. }
+            | PlanNode::NearestNeighborScan { table, columns, .. } => {
+                let schema = catalog.get_table(table)?;
+                if columns.is_empty() || columns.iter().any(|c| c == "*") {
+                    return Ok(schema.columns.iter().map(|c| c.data_type.clone()).collect());
+                }
+                columns
+                    .iter()
+                    .map(|name| {
+                        schema
+                            .columns
+                            .iter()
+                            .find(|c| c.name == *name)
+                            .map(|c| c.data_type.clone())
+                            .ok_or_else(|| DbError::Catalog(format!("Column {} not found", name)))
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            PlanNode::Project { input, exprs, .. } => {
+                let input = input.output_types(catalog)?;
+                exprs
+                    .iter()
+                    .map(|expr| expr.data_type(&input).unwrap_or(DataType::Text))
+                    .collect()
+            }
+            PlanNode::Values { columns, rows } => (0..columns.len())
+                .map(|i| {
+                    rows.iter()
+                        .find_map(|row| row.get(i).and_then(DataType::of_value))
+                        .unwrap_or(DataType::Text)
+                })
+                .collect(),
+            PlanNode::Filter { input, .. }
+            | PlanNode::Sort { input, .. }
+            | PlanNode::Limit { input, .. }
+            | PlanNode::Distinct { input } => input.output_types(catalog)?,
+            PlanNode::Join { left, right, .. } => {
+                let mut types = left.output_types(catalog)?;
+                types.extend(right.output_types(catalog)?);
+                types
+            }
+            PlanNode::IndexNestedLoopJoin {
+                left,
+                table,
+                columns,
+                ..
+            } => {
+                let mut types = left.output_types(catalog)?;
+                types.extend(
+                    PlanNode::TableScan {
+                        table: table.clone(),
+                        columns: columns.clone(),
+                    }
+                    .output_types(catalog)?,
+                );
+                types
+            }
+            PlanNode::Aggregate {
+                input,
+                group_by,
+                aggregates,
+                ..
+            } => {
+                let input = input.output_types(catalog)?;
+                group_by
+                    .iter()
+                    .map(|expr| expr.data_type(&input).unwrap_or(DataType::Text))
+                    .chain(aggregates.iter().map(|agg| agg.data_type(&input)))
+                    .collect()
+            }
+            PlanNode::Window {
+                input, functions, ..
+            } => {
+                let mut types = input.output_types(catalog)?;
+                let window_types: Vec<DataType> = functions
+                    .iter()
+                    .map(|function| function.data_type(&types))
+                    .collect();
+                types.extend(window_types);
+                types
+            }
+            PlanNode::Subquery { plan, .. } => plan.output_types(catalog)?,
+            PlanNode::SetOperation { left, .. } => left.output_types(catalog)?,
+            PlanNode::RecursiveUnion {
+                base,
+                search,
+                cycle,
+                ..
+            } => {
+                let mut types = base.output_types(catalog)?;
+                let search = search.as_ref().map(|search| search.data_type(&types));
+                let cycle = cycle.as_ref().map(|cycle| cycle.data_types(&types));
+                types.extend(search);
+                types.extend(cycle.into_iter().flatten());
+                types
+            }
+            PlanNode::WorkTableScan { types, .. } => types.clone(),
+        })
+    }
+
+    // Replace the statement parameters throughout the plan with their values
+    pub fn bind_parameters(&mut self, params: &[Value]) -> Result<(), DbError> {
+        match self {
+            PlanNode::TableScan { .. }
+            | PlanNode::Values { .. }
+            | PlanNode::WorkTableScan { .. } => Ok(()),
+            PlanNode::IndexScan { bounds, .. } | PlanNode::IndexOnlyScan { bounds, .. } => {
+                bounds.bind_parameters(params)
+            }
+            PlanNode::NearestNeighborScan { query, .. } => query.bind_parameters(params),
+            PlanNode::IndexNestedLoopJoin {
+                left,
+                bounds,
+                condition,
+                ..
+            } => {
+                left.bind_parameters(params)?;
+                bounds.bind_parameters(params)?;
+                condition
+                    .iter_mut()
+                    .try_for_each(|expr| expr.bind_parameters(params))
+            }
+            PlanNode::Filter { input, predicate } => {
+                input.bind_parameters(params)?;
+                predicate.bind_parameters(params)
+            }
+            PlanNode::Project { input, exprs, .. } => {
+                input.bind_parameters(params)?;
+                exprs
+                    .iter_mut()
+                    .try_for_each(|expr| expr.bind_parameters(params))
+            }
+            PlanNode::Join {
+                left,
+                right,
+                condition,
+                ..
+            } => {
+                left.bind_parameters(params)?;
+                right.bind_parameters(params)?;
+                condition
+                    .iter_mut()
+                    .try_for_each(|expr| expr.bind_parameters(params))
+            }
+            PlanNode::Aggregate {
+                input,
+                group_by,
+                aggregates,
+                having,
+            } => {
+                input.bind_parameters(params)?;
+                group_by
+                    .iter_mut()
+                    .chain(aggregates.iter_mut().filter_map(|agg| agg.arg.as_mut()))
+                    .chain(having.iter_mut())
+                    .try_for_each(|expr| expr.bind_parameters(params))
+            }
+            PlanNode::Sort { input, order_by } => {
+                input.bind_parameters(params)?;
+                order_by
+                    .iter_mut()
+                    .try_for_each(|key| key.expr.bind_parameters(params))
+            }
+            PlanNode::Window {
+                input,
+                partition_by,
+                order_by,
+                functions,
+            } => {
+                input.bind_parameters(params)?;
+                partition_by
+                    .iter_mut()
+                    .chain(order_by.iter_mut().map(|key| &mut key.expr))
+                    .try_for_each(|expr| expr.bind_parameters(params))?;
+                functions.iter_mut().try_for_each(|function| {
+                    function
+                        .args
+                        .iter_mut()
+                        .chain(function.frame.offsets_mut())
+                        .try_for_each(|expr| expr.bind_parameters(params))
+                })
+            }
+            PlanNode::Limit { input, .. } | PlanNode::Distinct { input } => {
+                input.bind_parameters(params)
+            }
+            PlanNode::Subquery { plan, .. } => plan.bind_parameters(params),
+            PlanNode::SetOperation { left, right, .. } => {
+                left.bind_parameters(params)?;
+                right.bind_parameters(params)
+            }
+            PlanNode::RecursiveUnion {
+                base, recursive, ..
+            } => {
+                base.bind_parameters(params)?;
+                recursive.bind_parameters(params)
+            }
+        }
+    }
+
+    // A set operation, once its inputs are known to be as wide as each other
+    pub fn set_operation(
+        op: SetOperator,
+        all: bool,
+        left: PlanNode,
+        right: PlanNode,
+    ) -> Result<PlanNode, DbError> {
+        let (left_width, right_width) = (left.output_columns().len(), right.output_columns().len());
+        if left_width != right_width {
+            return Err(DbError::SqlParse(format!(
+                "Each {} query must have the same number of columns, got {} and {}",
+                op.keyword(),
+                left_width,
+                right_width
+            )));
+        }
+        Ok(PlanNode::SetOperation {
+            op,
+            all,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    }
+
+    /// The plan as an indented tree, one node per line
+    pub fn explain(&self) -> String {
+        let mut out = String::new();
+        self.explain_into(0, &mut out);
+        out
+    }
+
+    fn explain_into(&self, depth: usize, out: &mut String) {
+        if depth > 0 {
+            out.push_str(&"  ".repeat(depth - 1));
+            out.push_str("-> ");
+        }
+        out.push_str(&self.label());
+        out.push('\n');
+        for child in self.children() {
+            child.explain_into(depth + 1, out);
+        }
+    }
+
+    /// The node as EXPLAIN describes it, without its inputs
+    pub fn label(&self) -> String {
+        let list = |exprs: Vec<String>| exprs.join(", ");
+        let sort_keys = |keys: &[SortKey]| -> Vec<String> {
+            keys.iter()
+                .map(|key| match key.ascending {
+                    true => key.expr.to_string(),
+                    false => format!("{} DESC", key.expr),
+                })
+                .collect()
+        };
+        match self {
+            PlanNode::TableScan { table, .. } => format!("Seq Scan on {}", table),
+            PlanNode::IndexScan {
+                table,
+                index,
+                bounds,
+                ..
+            } => format!("Index Scan using {} on {} ({})", index, table, bounds),
+            PlanNode::IndexOnlyScan {
+                table,
+                index,
+                bounds,
+                ..
+            } => format!("Index Only Scan using {} on {} ({})", index, table, bounds),
+            PlanNode::NearestNeighborScan {
+                table,
+                index,
+                query,
+                limit,
+                ..
+            } => format!(
+                "Nearest Neighbor Scan using {} on {} (k = {}, query = {})",
+                index, table, limit, query
+            ),
+            PlanNode::IndexNestedLoopJoin {
+                join_type,
+                table,
+                index,
+                bounds,
+                condition,
+                ..
+            } => {
+                let kind = match join_type {
+                    JoinType::Inner => String::new(),
+                    other => format!("{:?} ", other),
+                };
+                let mut label = format!(
+                    "Index Nested Loop {}Join using {} on {} ({})",
+                    kind, index, table, bounds
+                );
+                if let Some(condition) = condition {
+                    label.push_str(&format!(" on {}", condition));
+                }
+                label
+            }
+            PlanNode::Filter { predicate, .. } => format!("Filter ({})", predicate),
+            PlanNode::Project { columns, .. } => {
+                format!("Project ({})", list(columns.clone()))
+            }
+            PlanNode::Join {
+                join_type,
+                condition,
+                ..
+            } => match condition {
+                Some(condition) => format!("Nested Loop {:?} Join on {}", join_type, condition),
+                None => format!("Nested Loop {:?} Join", join_type),
+            },
+            PlanNode::Aggregate { group_by, .. } if group_by.is_empty() => "Aggregate".to_string(),
+            PlanNode::Aggregate { group_by, .. } => format!(
+                "Aggregate (group by {})",
+                list(group_by.iter().map(|e| e.to_string()).collect())
+            ),
+            PlanNode::Sort { order_by, .. } => format!("Sort ({})", list(sort_keys(order_by))),
+            PlanNode::Window {
+                partition_by,
+                order_by,
+                ..
+            } => {
+                let mut clauses = Vec::new();
+                if !partition_by.is_empty() {
+                    clauses.push(format!(
+                        "partition by {}",
+                        list(partition_by.iter().map(|e| e.to_string()).collect())
+                    ));
+                }
+                if !order_by.is_empty() {
+                    clauses.push(format!("order by {}", list(sort_keys(order_by))));
+                }
+                match clauses.is_empty() {
+                    true => "Window".to_string(),
+                    false => format!("Window ({})", clauses.join(" ")),
+                }
+            }
+            PlanNode::Limit {
+                limit,
+                offset: Some(offset),
+                ..
+            } => format!("Limit {} offset {}", limit, offset),
+            PlanNode::Limit { limit, .. } => format!("Limit {}", limit),
+            PlanNode::Distinct { .. } => "Distinct".to_string(),
+            PlanNode::Subquery { alias, .. } => format!("Subquery {}", alias),
+            PlanNode::SetOperation { op, all: true, .. } => format!("{:?} All", op),
+            PlanNode::SetOperation { op, .. } => format!("{:?}", op),
+            PlanNode::RecursiveUnion { name, all, .. } => match all {
+                true => format!("Recursive Union All on {}", name),
+                false => format!("Recursive Union on {}", name),
+            },
+            PlanNode::WorkTableScan { name, .. } => format!("WorkTable Scan on {}", name),
+            PlanNode::Values { rows, .. } => format!("Values ({} rows)", rows.len()),
+        }
+    }
+
+    /// The plan nodes the node reads from, as EXPLAIN lists them
+    pub fn children(&self) -> Vec<&PlanNode> {
+        match self {
+            PlanNode::TableScan { .. }
+            | PlanNode::IndexScan { .. }
+            | PlanNode::IndexOnlyScan { .. }
+            | PlanNode::NearestNeighborScan { .. }
+            | PlanNode::WorkTableScan { .. }
+            | PlanNode::Values { .. } => Vec::new(),
+            PlanNode::IndexNestedLoopJoin { left, .. } => vec![left.as_ref()],
+            PlanNode::Filter { input, .. }
+            | PlanNode::Project { input, .. }
+            | PlanNode::Aggregate { input, .. }
+            | PlanNode::Sort { input, .. }
+            | PlanNode::Window { input, .. }
+            | PlanNode::Limit { input, .. }
+            | PlanNode::Distinct { input } => vec![input.as_ref()],
+            PlanNode::Join { left, right, .. } => vec![left.as_ref(), right.as_ref()],
+            PlanNode::Subquery { plan, .. } => vec![plan.as_ref()],
+            PlanNode::SetOperation { left, right, .. } => vec![left.as_ref(), right.as_ref()],
+            PlanNode::RecursiveUnion {
+                base, recursive, ..
+            } => vec![base.as_ref(), recursive.as_ref()],
+        }
+    }
+}
+
+// Where an index lookup starts and stops. Every prefix is looked up: each
+// gives values for the leading key columns (several prefixes come from IN
+// lists), and `lower` and `upper` limit the key column after them
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexBounds {
+    // Names of the key columns, for EXPLAIN
+    pub key_columns: Vec<String>,
+    pub prefixes: Vec<Vec<ScalarExpr>>,
+    pub lower: Option<IndexBound>,
+    pub upper: Option<IndexBound>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexBound {
+    pub value: ScalarExpr,
+    pub inclusive: bool,
+}
+
+impl IndexBounds {
+    pub fn exprs(&self) -> impl Iterator<Item = &ScalarExpr> {
+        self.prefixes
+            .iter()
+            .flatten()
+            .chain(self.lower.iter().map(|bound| &bound.value))
+            .chain(self.upper.iter().map(|bound| &bound.value))
+    }
+
+    pub fn exprs_mut(&mut self) -> impl Iterator<Item = &mut ScalarExpr> {
+        self.prefixes
+            .iter_mut()
+            .flatten()
+            .chain(self.lower.iter_mut().map(|bound| &mut bound.value))
+            .chain(self.upper.iter_mut().map(|bound| &mut bound.value))
+    }
+
+    fn bind_parameters(&mut self, params: &[Value]) -> Result<(), DbError> {
+        self.exprs_mut()
+            .try_for_each(|expr| expr.bind_parameters(params))
+    }
+}
+
+impl fmt::Display for IndexBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.prefixes.first().map_or(0, Vec::len);
+        let mut terms = Vec::new();
+        match self.prefixes.as_slice() {
+            [prefix] => {
+                for (column, value) in self.key_columns.iter().zip(prefix) {
+                    terms.push(format!("{} = {}", column, value));
+                }
+            }
+            prefixes => {
+                let tuple = |items: Vec<String>| match items.len() {
+                    1 => items.concat(),
+                    _ => format!("({})", items.join(", ")),
+                };
+                let columns = tuple(self.key_columns[..width].to_vec());
+                let values: Vec<String> = prefixes
+                    .iter()
+                    .map(|prefix| tuple(prefix.iter().map(|v| v.to_string()).collect()))
+                    .collect();
+                terms.push(format!("{} IN ({})", columns, values.join(", ")));
+            }
+        }
+        if let Some(column) = self.key_columns.get(width) {
+            if let Some(lower) = &self.lower {
+                let op = if lower.inclusive { ">=" } else { ">" };
+                terms.push(format!("{} {} {}", column, op, lower.value));
+            }
+            if let Some(upper) = &self.upper {
+                let op = if upper.inclusive { "<=" } else { "<" };
+                terms.push(format!("{} {} {}", column, op, upper.value));
+            }
+        }
+        write!(f, "{}", terms.join(" AND "))
+    }
+}
+
+// Bound scalar expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScalarExpr {
+    Literal(Value),
+    // Column of the operator's input row
+    Column {
+        index: usize,
+        name: String,
+    },
+    // Column of an enclosing query's row; depth 0 is the nearest one
+    OuterColumn {
+        depth: usize,
+        index: usize,
+        name: String,
+    },
+    // Statement parameter `$n`, with `index` n - 1; replaced by its value
+    // before the plan runs
+    Parameter {
+        index: usize,
+    },
+    Unary {
+        op: UnaryOperator,
+        expr: Box<ScalarExpr>,
+    },
+    Binary {
+        left: Box<ScalarExpr>,
+        op: BinaryOperator,
+        right: Box<ScalarExpr>,
+    },
+    InList {
+        expr: Box<ScalarExpr>,
+        list: Vec<ScalarExpr>,
+        negated: bool,
+    },
+    Between {
+        expr: Box<ScalarExpr>,
+        low: Box<ScalarExpr>,
+        high: Box<ScalarExpr>,
+        negated: bool,
+    },
+    Like {
+        expr: Box<ScalarExpr>,
+        pattern: Box<ScalarExpr>,
+        negated: bool,
+        case_insensitive: bool,
+    },
+    Case {
+        operand: Option<Box<ScalarExpr>>,
+        branches: Vec<(ScalarExpr, ScalarExpr)>,
+        else_result: Option<Box<ScalarExpr>>,
+    },
+    Cast {
+        expr: Box<ScalarExpr>,
+        data_type: DataType,
+    },
+    // Built-in scalar function; names are upper case
+    Function {
+        name: String,
+        args: Vec<ScalarExpr>,
+    },
+    // Uncorrelated subqueries are evaluated once per statement, correlated
+    // ones once per outer row
+    ScalarSubquery {
+        plan: Box<PlanNode>,
+        data_type: Option<DataType>,
+        correlated: bool,
+    },
+    InSubquery {
+        expr: Box<ScalarExpr>,
+        plan: Box<PlanNode>,
+        negated: bool,
+        correlated: bool,
+    },
+    Exists {
+        plan: Box<PlanNode>,
+        negated: bool,
+        correlated: bool,
+    },
+}
+
+impl ScalarExpr {
+    pub fn column(index: usize, name: impl Into<String>) -> Self {
+        ScalarExpr::Column {
+            index,
+            name: name.into(),
+        }
+    }
+
+    pub fn binary(left: ScalarExpr, op: BinaryOperator, right: ScalarExpr) -> Self {
+        ScalarExpr::Binary {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    // AND together a list of predicates; `None` when the list is empty
+    pub fn conjunction(predicates: Vec<ScalarExpr>) -> Option<Self> {
+        predicates
+            .into_iter()
+            .reduce(|acc, pred| ScalarExpr::binary(acc, BinaryOperator::And, pred))
+    }
+
+    // Split a predicate into its top-level AND terms
+    pub fn into_conjuncts(self) -> Vec<ScalarExpr> {
+        match self {
+            ScalarExpr::Binary {
+                left,
+                op: BinaryOperator::And,
+                right,
+            } => {
+                let mut terms = left.into_conjuncts();
+                terms.extend(right.into_conjuncts());
+                terms
+            }
+            other => vec![other],
+        }
+    }
+
+    // Input columns referenced by this expression, not counting subqueries
+    pub fn referenced_columns(&self, out: &mut Vec<usize>) {
+        self.visit(&mut |expr| {
+            if let ScalarExpr::Column { index, .. } = expr {
+                out.push(*index);
+            }
+        });
+    }
+
+    // Whether the expression contains a subquery
+    pub fn has_subquery(&self) -> bool {
+        let mut found = false;
+        self.visit(&mut |expr| {
+            found |= matches!(
+                expr,
+                ScalarExpr::ScalarSubquery { .. }
+                    | ScalarExpr::InSubquery { .. }
+                    | ScalarExpr::Exists { .. }
+            )
+        });
+        found
+    }
+
+    // Whether evaluation depends on an enclosing row
+    pub fn has_correlated_subquery(&self) -> bool {
+        let mut found = false;
+        self.visit(&mut |expr| match expr {
+            ScalarExpr::ScalarSubquery { correlated, .. }
+            | ScalarExpr::InSubquery { correlated, .. }
+            | ScalarExpr::Exists { correlated, .. } => found |= *correlated,
+            ScalarExpr::OuterColumn { .. } => found = true,
+            _ => {}
+        });
+        found
+    }
+
+    // Rewrite input column positions, e.g. when an operator's input changes shape
+    pub fn remap_columns(&mut self, map: &dyn Fn(usize) -> usize) {
+        self.visit_mut(&mut |expr| {
+            if let ScalarExpr::Column { index, .. } = expr {
+                *index = map(*index);
+            }
+        });
+    }
+
+    // Replace parameters with the values bound to them, inside subquery
+    // plans too
+    pub fn bind_parameters(&mut self, params: &[Value]) -> Result<(), DbError> {
+        let mut result = Ok(());
+        self.visit_mut(&mut |expr| match expr {
+            ScalarExpr::Parameter { index } => match params.get(*index) {
+                Some(value) => *expr = ScalarExpr::Literal(value.clone()),
+                None => {
+                    result = Err(DbError::InvalidInput(format!(
+                        "No value supplied for parameter ${}",
+                        *index + 1
+                    )))
+                }
+            },
+            ScalarExpr::ScalarSubquery { plan, .. }
+            | ScalarExpr::InSubquery { plan, .. }
+            | ScalarExpr::Exists { plan, .. } => {
+                if let Err(e) = plan.bind_parameters(params) {
+                    result = Err(e);
+                }
+            }
+            _ => {}
+        });
+        result
+    }
+
+    // Plans of the subqueries in this expression, not counting those nested
+    // in them
+    pub(crate) fn subquery_plans_mut(&mut self, f: &mut dyn FnMut(&mut PlanNode)) {
+        self.visit_mut(&mut |expr| match expr {
+            ScalarExpr::ScalarSubquery { plan, .. }
+            | ScalarExpr::InSubquery { plan, .. }
+            | ScalarExpr::Exists { plan, .. } => f(plan),
+            _ => {}
+        });
+    }
+
+    fn visit(&self, f: &mut dyn FnMut(&ScalarExpr)) {
+        f(self);
+        match self {
+            ScalarExpr::Literal(_)
+            | ScalarExpr::Column { .. }
+            | ScalarExpr::OuterColumn { .. }
+            | ScalarExpr::Parameter { .. }
+            | ScalarExpr::ScalarSubquery { .. }
+            | ScalarExpr::Exists { .. } => {}
+            ScalarExpr::Unary { expr, .. } | ScalarExpr::Cast { expr, .. } => expr.visit(f),
+            ScalarExpr::InSubquery { expr, .. } => expr.visit(f),
+            ScalarExpr::Binary { left, right, .. } => {
+                left.visit(f);
+                right.visit(f);
+            }
+            ScalarExpr::InList { expr, list, .. } => {
+                expr.visit(f);
+                list.iter().for_each(|item| item.visit(f));
+            }
+            ScalarExpr::Between {
+                expr, low, high, ..
+            } => {
+                expr.visit(f);
+                low.visit(f);
+                high.visit(f);
+            }
+            ScalarExpr::Like { expr, pattern, .. } => {
+                expr.visit(f);
+                pattern.visit(f);
+            }
+            ScalarExpr::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                if let Some(operand) = operand {
+                    operand.visit(f);
+                }
+                for (when, then) in branches {
+                    when.visit(f);
+                    then.visit(f);
+                }
+                if let Some(else_result) = else_result {
+                    else_result.visit(f);
+                }
+            }
+            ScalarExpr::Function { args, .. } => args.iter().for_each(|arg| arg.visit(f)),
+        }
+    }
+
+    fn visit_mut(&mut self, f: &mut dyn FnMut(&mut ScalarExpr)) {
+        f(self);
+        match self {
+            ScalarExpr::Literal(_)
+            | ScalarExpr::Column { .. }
+            | ScalarExpr::OuterColumn { .. }
+            | ScalarExpr::Parameter { .. }
+            | ScalarExpr::ScalarSubquery { .. }
+            | ScalarExpr::Exists { .. } => {}
+            ScalarExpr::Unary { expr, .. } | ScalarExpr::Cast { expr, .. } => expr.visit_mut(f),
+            ScalarExpr::InSubquery { expr, .. } => expr.visit_mut(f),
+            ScalarExpr::Binary { left, right, .. } => {
+                left.visit_mut(f);
+                right.visit_mut(f);
+            }
+            ScalarExpr::InList { expr, list, .. } => {
+                expr.visit_mut(f);
+                list.iter_mut().for_each(|item| item.visit_mut(f));
+            }
+            ScalarExpr::Between {
+                expr, low, high, ..
+            } => {
+                expr.visit_mut(f);
+                low.visit_mut(f);
+                high.visit_mut(f);
+            }
+            ScalarExpr::Like { expr, pattern, .. } => {
+                expr.visit_mut(f);
+                pattern.visit_mut(f);
+            }
+            ScalarExpr::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                if let Some(operand) = operand {
+                    operand.visit_mut(f);
+                }
+                for (when, then) in branches {
+                    when.visit_mut(f);
+                    then.visit_mut(f);
+                }
+                if let Some(else_result) = else_result {
+                    else_result.visit_mut(f);
+                }
+            }
+            ScalarExpr::Function { args, .. } => {
+                args.iter_mut().for_each(|arg| arg.visit_mut(f))
+            }
+        }
+    }
+
+    // Static result type given the input column types; `None` when it can
+    // only be known from the values (NULL literals, outer references,
+    // parameters)
+    pub fn data_type(&self, input: &[DataType]) -> Option<DataType> {
+        match self {
+            ScalarExpr::Literal(value) => DataType::of_value(value),
+            ScalarExpr::Column { index, .. } => input.get(*index).cloned(),
+            ScalarExpr::OuterColumn { .. } | ScalarExpr::Parameter { .. } => None,
+            ScalarExpr::Unary { op, expr } => match op {
+                UnaryOperator::Negate => expr.data_type(input),
+                _ => Some(DataType::Boolean),
+            },
+            ScalarExpr::Binary { left, op, right } => match op {
+                BinaryOperator::Add
+                | BinaryOperator::Subtract
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Modulo => {
+                    arithmetic_type(left.data_type(input), right.data_type(input))
+                }
+                BinaryOperator::Concat => Some(DataType::Text),
+                _ => Some(DataType::Boolean),
+            },
+            ScalarExpr::InList { .. }
+            | ScalarExpr::Between { .. }
+            | ScalarExpr::Like { .. }
+            | ScalarExpr::InSubquery { .. }
+            | ScalarExpr::Exists { .. } => Some(DataType::Boolean),
+            ScalarExpr::Case {
+                branches,
+                else_result,
+                ..
+            } => branches
+                .iter()
+                .map(|(_, then)| then)
+                .chain(else_result.as_deref())
+                .find_map(|result| result.data_type(input)),
+            ScalarExpr::Cast { data_type, .. } => Some(data_type.clone()),
+            ScalarExpr::Function { name, args } => match name.as_str() {
+                "LENGTH" | "CHAR_LENGTH" | "CHARACTER_LENGTH" => Some(DataType::BigInt),
+                "ROUND" | "CEIL" | "CEILING" | "FLOOR" => {
+                    match args.first().and_then(|arg| arg.data_type(input)) {
+                        Some(DataType::Numeric(_)) => Some(DataType::Numeric(None)),
+                        other => other,
+                    }
+                }
+                "ABS" | "COALESCE" | "NULLIF" => args.iter().find_map(|arg| arg.data_type(input)),
+                "ARRAY" => Some(DataType::Array(Box::new(
+                    args.iter()
+                        .find_map(|arg| arg.data_type(input))
+                        .unwrap_or(DataType::Text),
+                ))),
+                "GEN_RANDOM_UUID" => Some(DataType::Uuid),
+                "NOW" | "CURRENT_TIMESTAMP" => Some(DataType::Timestamp),
+                "CURRENT_DATE" => Some(DataType::Date),
+                "L2_DISTANCE" | "COSINE_DISTANCE" | "INNER_PRODUCT" => Some(DataType::Double),
+                _ => Some(DataType::Text),
+            },
+            ScalarExpr::ScalarSubquery { data_type, .. } => data_type.clone(),
+        }
+    }
+}
+
+// Integer arithmetic stays integral unless a float is involved and decimals
+// stay exact; dates move by whole days, timestamps move by intervals and
+// differ by an interval
+fn arithmetic_type(left: Option<DataType>, right: Option<DataType>) -> Option<DataType> {
+    let is_float = |t: &DataType| matches!(t, DataType::Float | DataType::Double);
+    let is_integer = |t: &DataType| matches!(t, DataType::Integer | DataType::BigInt);
+    let is_time = |t: &DataType| {
+        matches!(
+            t,
+            DataType::Date | DataType::Timestamp | DataType::TimestampTz
+        )
+    };
+    match (left?, right?) {
+        (DataType::Date, t) | (t, DataType::Date) if is_integer(&t) => Some(DataType::Date),
+        (DataType::Date, DataType::Date) => Some(DataType::BigInt),
+        (l, r) if is_time(&l) && is_time(&r) => Some(DataType::Interval),
+        (t @ (DataType::Timestamp | DataType::TimestampTz), _)
+        | (_, t @ (DataType::Timestamp | DataType::TimestampTz)) => Some(t),
+        (DataType::Date, _) | (_, DataType::Date) => Some(DataType::Timestamp),
+        (DataType::Interval, _) | (_, DataType::Interval) => Some(DataType::Interval),
+        (DataType::Numeric(_), _) | (_, DataType::Numeric(_)) => Some(DataType::Numeric(None)),
+        (l, r) if is_float(&l) || is_float(&r) => Some(DataType::Double),
+        (l, r) if is_integer(&l) && is_integer(&r) => Some(DataType::BigInt),
+        _ => Some(DataType::Double),
+    }
+}
+
+pub(crate) fn binary_operator_symbol(op: BinaryOperator) -> &'static str {
+    match op {
+        BinaryOperator::Add => "+",
+        BinaryOperator::Subtract => "-",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::Divide => "/",
+        BinaryOperator::Modulo => "%",
+        BinaryOperator::Equal => "=",
+        BinaryOperator::NotEqual => "<>",
+        BinaryOperator::LessThan => "<",
+        BinaryOperator::LessThanOrEqual => "<=",
+        BinaryOperator::GreaterThan => ">",
+        BinaryOperator::GreaterThanOrEqual => ">=",
+        BinaryOperator::And => "AND",
+        BinaryOperator::Or => "OR",
+        BinaryOperator::Like => "LIKE",
+        BinaryOperator::Concat => "||",
+    }
+}
+
+impl fmt::Display for ScalarExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let not = |negated: &bool| if *negated { "NOT " } else { "" };
+        match self {
+            ScalarExpr::Literal(Value::String(s)) => write!(f, "'{}'", s.replace('\'', "''")),
+            ScalarExpr::Literal(value) => write!(f, "{}", value.to_display_string()),
+            ScalarExpr::Column { name, .. } | ScalarExpr::OuterColumn { name, .. } => {
+                write!(f, "{}", name)
+            }
+            ScalarExpr::Parameter { index } => write!(f, "${}", index + 1),
+            ScalarExpr::Unary { op, expr } => match op {
+                UnaryOperator::Not => write!(f, "NOT {}", expr),
+                UnaryOperator::Negate => write!(f, "-{}", expr),
+                UnaryOperator::IsNull => write!(f, "{} IS NULL", expr),
+                UnaryOperator::IsNotNull => write!(f, "{} IS NOT NULL", expr),
+            },
+            ScalarExpr::Binary { left, op, right } => {
+                write!(f, "({} {} {})", left, binary_operator_symbol(*op), right)
+            }
+            ScalarExpr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let items: Vec<String> = list.iter().map(|item| item.to_string()).collect();
+                write!(f, "{} {}IN ({})", expr, not(negated), items.join(", "))
+            }
+            ScalarExpr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => write!(f, "{} {}BETWEEN {} AND {}", expr, not(negated), low, high),
+            ScalarExpr::Like {
+                expr,
+                pattern,
+                negated,
+                case_insensitive,
+            } => {
+                let op = if *case_insensitive { "ILIKE" } else { "LIKE" };
+                write!(f, "{} {}{} {}", expr, not(negated), op, pattern)
+            }
+            ScalarExpr::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                write!(f, "CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", operand)?;
+                }
+                for (when, then) in branches {
+                    write!(f, " WHEN {} THEN {}", when, then)?;
+                }
+                if let Some(else_result) = else_result {
+                    write!(f, " ELSE {}", else_result)?;
+                }
+                write!(f, " END")
+            }
+            ScalarExpr::Cast { expr, data_type } => write!(f, "CAST({} AS {})", expr, data_type),
+            ScalarExpr::Function { name, args } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            ScalarExpr::ScalarSubquery { .. } => write!(f, "(subquery)"),
+            ScalarExpr::InSubquery { expr, negated, .. } => {
+                write!(f, "{} {}IN (subquery)", expr, not(negated))
+            }
+            ScalarExpr::Exists { negated, .. } => write!(f, "{}EXISTS (subquery)", not(negated)),
+        }
+    }
+}
+
+// ORDER BY key
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub expr: ScalarExpr,
+    pub ascending: bool,
+    // Defaults to NULLS LAST for ascending and NULLS FIRST for descending keys
+    pub nulls_first: Option<bool>,
+}
+
+impl SortKey {
+    pub fn nulls_first(&self) -> bool {
+        self.nulls_first.unwrap_or(!self.ascending)
+    }
 }
 
 // Aggregate expression
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AggregateExpr {
     pub function: AggregateFunction,
+    // `None` for COUNT(*)
+    pub arg: Option<ScalarExpr>,
+    pub distinct: bool,
+    // Output column name, e.g. "SUM(amount)"
     pub column: String,
-    pub alias: Option<String>,
+}
+
+impl AggregateExpr {
+    // COUNT is BIGINT, SUM of integers is BIGINT, SUM and AVG of decimals
+    // and intervals keep that kind, AVG and the statistical aggregates are
+    // otherwise DOUBLE, MIN and MAX keep their argument's type
+    pub fn data_type(&self, input: &[DataType]) -> DataType {
+        let arg_type = self.arg.as_ref().and_then(|arg| arg.data_type(input));
+        self.function.result_type(arg_type)
+    }
 }
 
 // Aggregate function types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AggregateFunction {
     Count,
     Sum,
@@ -63,176 +1204,322 @@ pub enum AggregateFunction {
     Variance,
 }
 
-// Query planner
-pub struct Planner;
+impl AggregateFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "COUNT" => Some(AggregateFunction::Count),
+            "SUM" => Some(AggregateFunction::Sum),
+            "AVG" => Some(AggregateFunction::Avg),
+            "MIN" => Some(AggregateFunction::Min),
+            "MAX" => Some(AggregateFunction::Max),
+            "STDDEV" | "STDDEV_SAMP" => Some(AggregateFunction::StdDev),
+            "VARIANCE" | "VAR_SAMP" => Some(AggregateFunction::Variance),
+            _ => None,
+        }
+    }
 
-impl Planner {
-    pub fn new() -> Self {
-        Self
+    // Result type given the argument's type; see `AggregateExpr::data_type`
+    pub fn result_type(self, arg_type: Option<DataType>) -> DataType {
+        match (self, arg_type) {
+            (AggregateFunction::Count, _) => DataType::BigInt,
+            (AggregateFunction::Sum | AggregateFunction::Avg, Some(DataType::Numeric(_))) => {
+                DataType::Numeric(None)
+            }
+            (AggregateFunction::Sum | AggregateFunction::Avg, Some(DataType::Interval)) => {
+                DataType::Interval
+            }
+            (AggregateFunction::Sum, Some(DataType::Integer | DataType::BigInt)) => {
+                DataType::BigInt
+            }
+            (
+                AggregateFunction::Sum
+                | AggregateFunction::Avg
+                | AggregateFunction::StdDev
+                | AggregateFunction::Variance,
+                _,
+            ) => DataType::Double,
+            (AggregateFunction::Min | AggregateFunction::Max, arg_type) => {
+                arg_type.unwrap_or(DataType::Text)
+            }
+        }
     }
+}
 
-    pub fn plan(&self, stmt: &SqlStatement) -> Result<PlanNode, DbError> {
-        match stmt {
-            SqlStatement::Select {
-                table,
-                columns,
-                filter,
-                join,
-                group_by,
-                having,
-                order_by,
-                limit,
-                offset,
-                distinct: _,
-            } => {
-                // Start with table scan
-                let mut plan = PlanNode::TableScan {
-                    table: table.clone(),
-                    columns: if columns.len() == 1 && columns[0] == "*" {
-                        // Only wildcard, expand to all columns
-                        vec!["*".to_string()]
-                    } else if columns.contains(&"*".to_string()) {
-                        // Mixed wildcard and specific columns is invalid
-                        return Err(DbError::SqlParse(
-                            "Cannot mix '*' with specific column names".to_string(),
-                        ));
-                    } else {
-                        // Specific columns
-                        columns.clone()
-                    },
-                };
+// Window function call. Ranking functions and LAG/LEAD look at the whole
+// ordered partition; aggregates and the value functions see only the rows
+// of their frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowExpr {
+    pub function: WindowFunction,
+    // Empty for COUNT(*)
+    pub args: Vec<ScalarExpr>,
+    pub frame: WindowFrame,
+    // Output column name, the call's SQL text
+    pub column: String,
+}
 
-                // Add JOIN if present
-                if let Some(join_clause) = join {
-                    let right_plan = PlanNode::TableScan {
-                        table: join_clause.table.clone(),
-                        columns: vec!["*".to_string()],
-                    };
+impl WindowExpr {
+    // Ranks and NTILE are BIGINT, PERCENT_RANK and CUME_DIST are DOUBLE,
+    // the value functions keep their argument's type and aggregates type
+    // as they do without OVER
+    pub fn data_type(&self, input: &[DataType]) -> DataType {
+        let arg_type = self.args.first().and_then(|arg| arg.data_type(input));
+        match self.function {
+            WindowFunction::Aggregate(function) => function.result_type(arg_type),
+            WindowFunction::RowNumber
+            | WindowFunction::Rank
+            | WindowFunction::DenseRank
+            | WindowFunction::Ntile => DataType::BigInt,
+            WindowFunction::PercentRank | WindowFunction::CumeDist => DataType::Double,
+            WindowFunction::Lag
+            | WindowFunction::Lead
+            | WindowFunction::FirstValue
+            | WindowFunction::LastValue
+            | WindowFunction::NthValue => arg_type.unwrap_or(DataType::Text),
+        }
+    }
+}
 
-                    plan = PlanNode::Join {
-                        join_type: join_clause.join_type.clone(),
-                        left: Box::new(plan),
-                        right: Box::new(right_plan),
-                        condition: join_clause.condition.clone(),
-                    };
-                }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowFunction {
+    Aggregate(AggregateFunction),
+    RowNumber,
+    Rank,
+    DenseRank,
+    PercentRank,
+    CumeDist,
+    Ntile,
+    Lag,
+    Lead,
+    FirstValue,
+    LastValue,
+    NthValue,
+}
 
-                // Add FILTER if present
-                if let Some(pred) = filter {
-                    plan = PlanNode::Filter {
-                        input: Box::new(plan),
-                        predicate: pred.clone(),
-                    };
-                }
+impl WindowFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(function) = AggregateFunction::from_name(name) {
+            return Some(WindowFunction::Aggregate(function));
+        }
+        match name.to_ascii_uppercase().as_str() {
+            "ROW_NUMBER" => Some(WindowFunction::RowNumber),
+            "RANK" => Some(WindowFunction::Rank),
+            "DENSE_RANK" => Some(WindowFunction::DenseRank),
+            "PERCENT_RANK" => Some(WindowFunction::PercentRank),
+            "CUME_DIST" => Some(WindowFunction::CumeDist),
+            "NTILE" => Some(WindowFunction::Ntile),
+            "LAG" => Some(WindowFunction::Lag),
+            "LEAD" => Some(WindowFunction::Lead),
+            "FIRST_VALUE" => Some(WindowFunction::FirstValue),
+            "LAST_VALUE" => Some(WindowFunction::LastValue),
+            "NTH_VALUE" => Some(WindowFunction::NthValue),
+            _ => None,
+        }
+    }
 
-                // Add GROUP BY/HAVING if present
-                if !group_by.is_empty() {
-                    // Extract aggregate expressions from columns
-                    let aggregates = self.extract_aggregates(columns);
+    // Whether the result depends on the frame rather than the partition
+    pub fn uses_frame(self) -> bool {
+        matches!(
+            self,
+            WindowFunction::Aggregate(_)
+                | WindowFunction::FirstValue
+                | WindowFunction::LastValue
+                | WindowFunction::NthValue
+        )
+    }
+}
 
-                    plan = PlanNode::Aggregate {
-                        input: Box::new(plan),
-                        group_by: group_by.clone(),
-                        aggregates,
-                        having: having.clone(),
-                    };
-                }
+// Frame of a window function call. The default is RANGE BETWEEN UNBOUNDED
+// PRECEDING AND CURRENT ROW: the partition up to the current row's last peer.
+// ROWS counts rows, GROUPS counts peer groups and RANGE compares the value of
+// the single ORDER BY key
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowFrame {
+    pub units: FrameType,
+    pub start: FrameBound,
+    pub end: FrameBound,
+    pub exclude: FrameExclusion,
+}
 
-                // Add ORDER BY if present
-                if !order_by.is_empty() {
-                    plan = PlanNode::Sort {
-                        input: Box::new(plan),
-                        order_by: order_by.clone(),
-                    };
-                }
-
-                // Add LIMIT if present
-                if let Some(limit_val) = limit {
-                    plan = PlanNode::Limit {
-                        input: Box::new(plan),
-                        limit: *limit_val,
-                        offset: *offset,
-                    };
-                }
-
-                Ok(plan)
-            }
-            _ => Ok(PlanNode::TableScan {
-                table: "".to_string(),
-                columns: Vec::new(),
-            }),
-        }
-    }
-
-    fn extract_aggregates(&self, columns: &[String]) -> Vec<AggregateExpr> {
-        let mut aggregates = Vec::new();
-
-        for col in columns {
-            // Simple pattern matching for aggregate functions
-            if col.to_uppercase().starts_with("COUNT(") {
-                aggregates.push(AggregateExpr {
-                    function: AggregateFunction::Count,
-                    column: col.clone(),
-                    alias: None,
-                });
-            } else if col.to_uppercase().starts_with("SUM(") {
-                aggregates.push(AggregateExpr {
-                    function: AggregateFunction::Sum,
-                    column: col.clone(),
-                    alias: None,
-                });
-            } else if col.to_uppercase().starts_with("AVG(") {
-                aggregates.push(AggregateExpr {
-                    function: AggregateFunction::Avg,
-                    column: col.clone(),
-                    alias: None,
-                });
-            } else if col.to_uppercase().starts_with("MIN(") {
-                aggregates.push(AggregateExpr {
-                    function: AggregateFunction::Min,
-                    column: col.clone(),
-                    alias: None,
-                });
-            } else if col.to_uppercase().starts_with("MAX(") {
-                aggregates.push(AggregateExpr {
-                    function: AggregateFunction::Max,
-                    column: col.clone(),
-                    alias: None,
-                });
-            }
-        }
-
-        aggregates
-    }
-}
-
-impl Default for Planner {
+impl Default for WindowFrame {
     fn default() -> Self {
-        Self::new()
+        Self {
+            units: FrameType::Range,
+            start: FrameBound::UnboundedPreceding,
+            end: FrameBound::CurrentRow,
+            exclude: FrameExclusion::NoOthers,
+        }
+    }
+}
+
+impl WindowFrame {
+    pub fn offsets_mut(&mut self) -> impl Iterator<Item = &mut ScalarExpr> {
+        [&mut self.start, &mut self.end]
+            .into_iter()
+            .filter_map(|bound| match bound {
+                FrameBound::Preceding(offset) | FrameBound::Following(offset) => Some(offset),
+                _ => None,
+            })
+    }
+}
+
+// Frame offsets are constant expressions, evaluated once per execution
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(ScalarExpr),
+    CurrentRow,
+    Following(ScalarExpr),
+    UnboundedFollowing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl SetOperator {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            SetOperator::Union => "UNION",
+            SetOperator::Intersect => "INTERSECT",
+            SetOperator::Except => "EXCEPT",
+        }
+    }
+}
+
+// SEARCH {DEPTH | BREADTH} FIRST BY `columns` SET `column`: a column that
+// orders a recursive query's rows depth or breadth first
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchClause {
+    pub breadth_first: bool,
+    pub columns: Vec<usize>,
+    pub column: String,
+}
+
+impl SearchClause {
+    // Depth first, an array of the BY columns of the rows on the way to
+    // this one; breadth first, an array of its depth and BY columns. A
+    // single BY column stands for itself, several make an array
+    pub fn data_type(&self, input: &[DataType]) -> DataType {
+        let element = match self.columns[..] {
+            [column] if !self.breadth_first => input[column].clone(),
+            _ => DataType::Text,
+        };
+        DataType::Array(Box::new(element))
+    }
+}
+
+// CYCLE `columns` SET `mark` TO `mark_value` DEFAULT `default_value` USING
+// `path`: a row whose columns repeat those of a row on its way gets the
+// mark and is not followed further. The path is an array of the columns of
+// the rows on the way, a single column standing for itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleClause {
+    pub columns: Vec<usize>,
+    pub mark: String,
+    pub mark_value: Value,
+    pub default_value: Value,
+    pub path: String,
+}
+
+impl CycleClause {
+    // Types of the mark and the path
+    pub fn data_types(&self, input: &[DataType]) -> [DataType; 2] {
+        let mark = DataType::of_value(&self.mark_value).unwrap_or(DataType::Boolean);
+        let element = match self.columns[..] {
+            [column] => input[column].clone(),
+            _ => DataType::Text,
+        };
+        [mark, DataType::Array(Box::new(element))]
+    }
+}
+
+// Query planner: binds parsed queries against the catalog
+pub struct Planner {
+    catalog: Arc<Catalog>,
+}
+
+impl Planner {
+    pub fn new(catalog: Arc<Catalog>) -> Self {
+        Self { catalog }
+    }
+
+    pub fn plan(&self, stmt: &SqlStatement) -> Result<PlanNode, DbError> {
+        match stmt {
+            SqlStatement::Select { query } => Binder::new(&self.catalog).bind_query(query),
+            SqlStatement::InsertIntoSelect { source, .. } => {
+                Binder::new(&self.catalog).bind_query(source)
+            }
+            SqlStatement::Union { left, right, all } => PlanNode::set_operation(
+                SetOperator::Union,
+                *all,
+                self.plan(left)?,
+                self.plan(right)?,
+            ),
+            other => Err(DbError::Internal(format!(
+                "Statement cannot be planned as a query: {:?}",
+                other
+            ))),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::catalog::{Column, Schema};
+    use crate::parser::SqlParser;
+
+    fn users_catalog() -> Arc<Catalog> {
+        let catalog = Catalog::new();
+        catalog
+            .create_table(Schema::new(
+                "users".to_string(),
+                vec![
+                    Column {
+                        name: "id".to_string(),
+                        data_type: DataType::Integer,
+                        nullable: false,
+                        default: None,
+                    },
+                    Column {
+                        name: "name".to_string(),
+                        data_type: DataType::Varchar(255),
+                        nullable: true,
+                        default: None,
+                    },
+                ],
+            ))
+            .unwrap();
+        Arc::new(catalog)
+    }
 
     #[test]
     fn test_planner() {
-        let planner = Planner::new();
-        let stmt = SqlStatement::Select {
-            table: "users".to_string(),
-            columns: vec!["id".to_string(), "name".to_string()],
-            filter: None,
-            join: None,
-            group_by: Vec::new(),
-            having: None,
-            order_by: Vec::new(),
-            limit: None,
-            offset: None,
-            distinct: false,
-        };
+        let planner = Planner::new(users_catalog());
+        let stmt = SqlParser::new()
+            .parse("SELECT id, name FROM users")
+            .unwrap()
+            .remove(0);
 
         let plan = planner.plan(&stmt).unwrap();
-        matches!(plan, PlanNode::TableScan { .. });
+        match plan {
+            PlanNode::Project {
+                input,
+                exprs,
+                columns,
+            } => {
+                assert_eq!(columns, vec!["id".to_string(), "name".to_string()]);
+                assert_eq!(
+                    exprs,
+                    vec![ScalarExpr::column(0, "id"), ScalarExpr::column(1, "name")]
+                );
+                assert!(matches!(*input, PlanNode::TableScan { .. }));
+            }
+            other => panic!("Expected Project over TableScan, got {:?}", other),
+        }
     }
 }
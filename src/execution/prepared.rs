@@ -0,0 +1,307 @@
+// Prepared statements
+//
+// A statement is parsed and bound once, then run any number of times with
+// typed parameter values. Parameters are written `$1`, `$2`, ... (or `?`,
+// numbered in order by the parser); the binder keeps them as
+// `ScalarExpr::Parameter` and infers each one's type from the column or
+// expression it is used with. Values are bound into a copy of the plan just
+// before it runs, so they never pass through SQL text.
+//
+// A prepared statement records the catalog version it was bound against and
+// is bound again when DDL has moved the catalog on since.
+
+use crate::catalog::{Catalog, DataType};
+use crate::common::Value;
+use crate::error::DbError;
+use crate::execution::binder::Binder;
+use crate::execution::{Executor, PlanNode, QueryResult, MAX_PREPARED_STATEMENTS};
+use crate::parser::SqlStatement;
+use std::collections::HashMap;
+
+// A bound statement waiting for its parameter values
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    pub(crate) statement: SqlStatement,
+    // Types given by PREPARE or the client; they win over inferred ones
+    pub(crate) declared_types: Vec<Option<DataType>>,
+    pub(crate) param_types: Vec<Option<DataType>>,
+    // Optimized plan of a query, parameters still unbound
+    pub(crate) plan: Option<PlanNode>,
+    pub(crate) catalog_version: u64,
+}
+
+impl PreparedStatement {
+    pub fn statement(&self) -> &SqlStatement {
+        &self.statement
+    }
+
+    // Type of each parameter; `None` where nothing in the statement fixes it
+    pub fn param_types(&self) -> &[Option<DataType>] {
+        &self.param_types
+    }
+
+    // Whether running the statement produces rows
+    pub fn returns_rows(&self) -> bool {
+        matches!(
+            self.statement,
+            SqlStatement::Select { .. } | SqlStatement::Union { .. } | SqlStatement::Explain { .. }
+        )
+    }
+
+    // Result columns and their types, for describing a query before it runs;
+    // `None` for statements that return no rows
+    pub fn describe(
+        &self,
+        catalog: &Catalog,
+    ) -> Result<Option<(Vec<String>, Vec<DataType>)>, DbError> {
+        // A procedure returns a row of its OUT parameters
+        if let SqlStatement::ExecProcedure { name, .. } = &self.statement {
+            let (columns, types): (Vec<String>, Vec<DataType>) = catalog
+                .get_procedure(name)?
+                .output_parameters()
+                .map(|parameter| (parameter.name.clone(), parameter.data_type.clone()))
+                .unzip();
+            return Ok((!columns.is_empty()).then_some((columns, types)));
+        }
+        if !self.returns_rows() {
+            return Ok(None);
+        }
+        if let SqlStatement::Explain { .. } = self.statement {
+            return Ok(Some((vec!["QUERY PLAN".to_string()], vec![DataType::Text])));
+        }
+        let plan = match &self.plan {
+            Some(plan) => plan.clone(),
+            // A UNION takes its columns from its first query
+            None => {
+                let mut first = &self.statement;
+                while let SqlStatement::Union { left, .. } = first {
+                    first = left;
+                }
+                let SqlStatement::Select { query } = first else {
+                    return Ok(None);
+                };
+                Binder::new(catalog).bind_query(query)?
+            }
+        };
+        Ok(Some((plan.output_columns(), plan.output_types(catalog)?)))
+    }
+
+    // Check there is one value per parameter and coerce each to its type
+    pub(crate) fn coerce_params(&self, params: Vec<Value>) -> Result<Vec<Value>, DbError> {
+        if params.len() != self.param_types.len() {
+            return Err(DbError::InvalidInput(format!(
+                "Statement takes {} parameters but {} were supplied",
+                self.param_types.len(),
+                params.len()
+            )));
+        }
+        params
+            .into_iter()
+            .zip(&self.param_types)
+            .map(|(value, data_type)| match data_type {
+                Some(data_type) => data_type.coerce(value),
+                None => Ok(value),
+            })
+            .collect()
+    }
+}
+
+// Named prepared statements of one session
+//
+// The unnamed statement (empty name) is replaced by the next one prepared
+// without a name; named statements must be deallocated before their name is
+// reused.
+#[derive(Debug, Default)]
+pub struct StatementCache {
+    statements: HashMap<String, PreparedStatement>,
+}
+
+impl StatementCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn prepare(
+        &mut self,
+        executor: &Executor,
+        name: &str,
+        statement: SqlStatement,
+        declared_types: Vec<Option<DataType>>,
+    ) -> Result<&PreparedStatement, DbError> {
+        if !name.is_empty() && self.statements.contains_key(name) {
+            return Err(DbError::AlreadyExists(format!(
+                "Prepared statement {} already exists",
+                name
+            )));
+        }
+        if !self.statements.contains_key(name) && self.statements.len() >= MAX_PREPARED_STATEMENTS {
+            return Err(DbError::LimitExceeded(format!(
+                "A session may hold at most {} prepared statements",
+                MAX_PREPARED_STATEMENTS
+            )));
+        }
+
+        let prepared = executor.prepare(statement, declared_types)?;
+        self.statements.insert(name.to_string(), prepared);
+        Ok(&self.statements[name])
+    }
+
+    pub fn get(&self, name: &str) -> Result<&PreparedStatement, DbError> {
+        self.statements
+            .get(name)
+            .ok_or_else(|| DbError::NotFound(format!("Prepared statement {} does not exist", name)))
+    }
+
+    pub fn execute(
+        &mut self,
+        executor: &Executor,
+        name: &str,
+        params: Vec<Value>,
+    ) -> Result<QueryResult, DbError> {
+        let prepared = self.statements.get_mut(name).ok_or_else(|| {
+            DbError::NotFound(format!("Prepared statement {} does not exist", name))
+        })?;
+        executor.execute_prepared(prepared, params)
+    }
+
+    // Drop one statement, or every statement for `None`
+    pub fn deallocate(&mut self, name: Option<&str>) -> Result<(), DbError> {
+        match name {
+            Some(name) => self.statements.remove(name).map(|_| ()).ok_or_else(|| {
+                DbError::NotFound(format!("Prepared statement {} does not exist", name))
+            }),
+            None => {
+                self.statements.clear();
+                Ok(())
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    // Run a statement in this session: PREPARE, EXECUTE and DEALLOCATE work
+    // on the cache, anything else goes straight to the executor
+    pub fn run(
+        &mut self,
+        executor: &Executor,
+        statement: SqlStatement,
+    ) -> Result<QueryResult, DbError> {
+        match statement {
+            SqlStatement::Prepare {
+                name,
+                param_types,
+                statement,
+            } => {
+                let declared = param_types.into_iter().map(Some).collect();
+                self.prepare(executor, &name, *statement, declared)?;
+                Ok(QueryResult::with_affected(0))
+            }
+            SqlStatement::Execute { name, params } => self.execute(executor, &name, params),
+            SqlStatement::Deallocate { name } => {
+                self.deallocate(name.as_deref())?;
+                Ok(QueryResult::with_affected(0))
+            }
+            statement => executor.execute(statement),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SqlParser;
+    use crate::transaction::TransactionManager;
+    use std::sync::Arc;
+
+    fn run(
+        executor: &Executor,
+        cache: &mut StatementCache,
+        sql: &str,
+    ) -> Result<QueryResult, DbError> {
+        let statement = SqlParser::new().parse(sql)?.remove(0);
+        cache.run(executor, statement)
+    }
+
+    #[test]
+    fn test_prepared_statements_bind_typed_parameters() -> Result<(), DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        let mut cache = StatementCache::new();
+        run(
+            &executor,
+            &mut cache,
+            "CREATE TABLE t (id INT, name VARCHAR(20))",
+        )?;
+
+        run(
+            &executor,
+            &mut cache,
+            "PREPARE ins AS INSERT INTO t VALUES (?, ?)",
+        )?;
+        assert_eq!(
+            cache.get("ins")?.param_types(),
+            &[Some(DataType::Integer), Some(DataType::Varchar(20))]
+        );
+        run(&executor, &mut cache, "EXECUTE ins (1, 'a')")?;
+        // Values are coerced to the parameter's type, never read as SQL
+        cache.execute(
+            &executor,
+            "ins",
+            vec![
+                Value::String("2".into()),
+                Value::String("b' OR '1'='1".into()),
+            ],
+        )?;
+
+        run(
+            &executor,
+            &mut cache,
+            "PREPARE sel AS SELECT name FROM t WHERE id = $1",
+        )?;
+        let result = cache.execute(&executor, "sel", vec![Value::Integer(2)])?;
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::String("b' OR '1'='1".into())]]
+        );
+        assert!(cache.execute(&executor, "sel", vec![]).is_err());
+
+        run(&executor, &mut cache, "DEALLOCATE sel")?;
+        assert!(cache.get("sel").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepared_plan_is_rebound_after_ddl() -> Result<(), DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        let mut cache = StatementCache::new();
+        run(&executor, &mut cache, "CREATE TABLE t (id INT)")?;
+        run(&executor, &mut cache, "INSERT INTO t VALUES (1)")?;
+        run(&executor, &mut cache, "PREPARE all_rows AS SELECT * FROM t")?;
+        assert_eq!(
+            cache.execute(&executor, "all_rows", vec![])?.columns,
+            vec!["id"]
+        );
+
+        run(&executor, &mut cache, "DROP TABLE t")?;
+        run(&executor, &mut cache, "CREATE TABLE t (id INT, note TEXT)")?;
+        run(&executor, &mut cache, "INSERT INTO t VALUES (2, 'x')")?;
+        let result = cache.execute(&executor, "all_rows", vec![])?;
+        assert_eq!(result.columns, vec!["id", "note"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(2), Value::String("x".into())]]
+        );
+        Ok(())
+    }
+}
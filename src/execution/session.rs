@@ -0,0 +1,378 @@
+// Client sessions
+//
+// A `Session` is the state a client connection keeps between statements: its
+// prepared statements, its open transaction and its settings. Outside a transaction block
+// every statement commits on its own; BEGIN opens a block whose statements
+// all run in one `SqlTransaction` until COMMIT or ROLLBACK. Dropping a session
+// rolls back its open transaction.
+
+use crate::catalog::DataType;
+use crate::common::{TransactionId, Value};
+use crate::error::DbError;
+use crate::execution::{Executor, PreparedStatement, QueryResult, StatementCache};
+use crate::parser::SqlStatement;
+use crate::transaction::{IsolationLevel, SqlTransaction};
+use std::sync::Arc;
+
+#[derive(Default)]
+pub struct Session {
+    statements: StatementCache,
+    transaction: Option<Arc<SqlTransaction>>,
+    // Set by SET SESSION CHARACTERISTICS AS TRANSACTION; `None` keeps the
+    // database default
+    default_isolation: Option<IsolationLevel>,
+    // Set by SET hnsw.ef_search; `None` keeps the default search width
+    ef_search: Option<usize>,
+}
+
+// Widest search an HNSW index scan may be given
+const MAX_EF_SEARCH: usize = 1000;
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    pub fn transaction_id(&self) -> Option<TransactionId> {
+        self.transaction.as_ref().map(|txn| txn.id())
+    }
+
+    pub fn isolation(&self) -> Option<IsolationLevel> {
+        self.transaction.as_ref().map(|txn| txn.isolation())
+    }
+
+    /// Open a transaction block; `None` takes the session's isolation level
+    pub fn begin(
+        &mut self,
+        executor: &Executor,
+        isolation: Option<IsolationLevel>,
+    ) -> Result<TransactionId, DbError> {
+        if self.transaction.is_some() {
+            return Err(DbError::InvalidState(
+                "There is already a transaction in progress".to_string(),
+            ));
+        }
+        let txn = executor.begin_transaction(isolation.or(self.default_isolation))?;
+        let id = txn.id();
+        self.transaction = Some(txn);
+        Ok(id)
+    }
+
+    /// Commit the open transaction, if any
+    ///
+    /// The block ends even when the commit fails; its changes are then
+    /// rolled back.
+    pub fn commit(&mut self) -> Result<(), DbError> {
+        match self.transaction.take() {
+            Some(txn) => txn.commit(),
+            None => Ok(()),
+        }
+    }
+
+    /// Roll back the open transaction, if any
+    pub fn rollback(&mut self) -> Result<(), DbError> {
+        match self.transaction.take() {
+            Some(txn) => txn.rollback(),
+            None => Ok(()),
+        }
+    }
+
+    /// `executor` running statements in this session's transaction, with
+    /// its settings
+    pub fn executor(&self, executor: &Executor) -> Executor {
+        let executor = executor.with_ef_search(self.ef_search);
+        match &self.transaction {
+            Some(txn) => executor.with_transaction(txn.clone()),
+            None => executor,
+        }
+    }
+
+    /// Change a setting of the session; `false` if there is no setting
+    /// `name`
+    pub fn set_parameter(&mut self, name: &str, value: &str) -> Result<bool, DbError> {
+        match name {
+            "hnsw.ef_search" => {
+                self.ef_search = match value.to_ascii_lowercase().as_str() {
+                    "default" => None,
+                    value => match value.parse::<usize>() {
+                        Ok(ef) if (1..=MAX_EF_SEARCH).contains(&ef) => Some(ef),
+                        _ => {
+                            return Err(DbError::InvalidInput(format!(
+                                "hnsw.ef_search must be between 1 and {}, got {}",
+                                MAX_EF_SEARCH, value
+                            )))
+                        }
+                    },
+                };
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn statements(&self) -> &StatementCache {
+        &self.statements
+    }
+
+    pub fn prepare(
+        &mut self,
+        executor: &Executor,
+        name: &str,
+        statement: SqlStatement,
+        declared_types: Vec<Option<DataType>>,
+    ) -> Result<&PreparedStatement, DbError> {
+        self.statements
+            .prepare(executor, name, statement, declared_types)
+    }
+
+    pub fn execute(
+        &mut self,
+        executor: &Executor,
+        name: &str,
+        params: Vec<Value>,
+    ) -> Result<QueryResult, DbError> {
+        let executor = self.executor(executor);
+        self.statements.execute(&executor, name, params)
+    }
+
+    pub fn deallocate(&mut self, name: Option<&str>) -> Result<(), DbError> {
+        self.statements.deallocate(name)
+    }
+
+    // Run a statement in this session: transaction control and prepared
+    // statement commands act on the session, anything else runs in its
+    // transaction
+    pub fn run(
+        &mut self,
+        executor: &Executor,
+        statement: SqlStatement,
+    ) -> Result<QueryResult, DbError> {
+        match statement {
+            SqlStatement::Begin { isolation } => {
+                self.begin(executor, isolation)?;
+            }
+            SqlStatement::Commit => self.commit()?,
+            SqlStatement::Rollback { savepoint: None } => self.rollback()?,
+            SqlStatement::Rollback {
+                savepoint: Some(name),
+            } => self
+                .block("ROLLBACK TO SAVEPOINT")?
+                .rollback_to_savepoint(&name)?,
+            SqlStatement::Savepoint { name } => self.block("SAVEPOINT")?.savepoint(&name)?,
+            SqlStatement::ReleaseSavepoint { name } => {
+                self.block("RELEASE SAVEPOINT")?.release_savepoint(&name)?
+            }
+            SqlStatement::SetTransaction {
+                isolation,
+                session: true,
+            } => {
+                if isolation.is_some() {
+                    self.default_isolation = isolation;
+                }
+            }
+            SqlStatement::SetTransaction {
+                isolation,
+                session: false,
+            } => {
+                let txn = self.block("SET TRANSACTION")?;
+                if let Some(isolation) = isolation {
+                    txn.set_isolation(isolation)?;
+                }
+            }
+            SqlStatement::SetParameter { name, value } => {
+                if !self.set_parameter(&name, &value)? {
+                    return Err(DbError::InvalidInput(format!(
+                        "Unrecognized configuration parameter {}",
+                        name
+                    )));
+                }
+            }
+            statement => {
+                let executor = self.executor(executor);
+                return self.statements.run(&executor, statement);
+            }
+        }
+        Ok(QueryResult::with_affected(0))
+    }
+
+    // The open transaction, for a command only valid inside a block
+    fn block(&self, command: &str) -> Result<&SqlTransaction, DbError> {
+        self.transaction.as_deref().ok_or_else(|| {
+            DbError::InvalidState(format!(
+                "{} can only be used in transaction blocks",
+                command
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+    use crate::parser::SqlParser;
+    use crate::transaction::TransactionManager;
+
+    fn run(executor: &Executor, session: &mut Session, sql: &str) -> Result<QueryResult, DbError> {
+        let statement = SqlParser::new().parse(sql)?.remove(0);
+        session.run(executor, statement)
+    }
+
+    fn accounts() -> Result<Executor, DbError> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        let mut session = Session::new();
+        run(
+            &executor,
+            &mut session,
+            "CREATE TABLE accounts (id INT, balance INT)",
+        )?;
+        run(
+            &executor,
+            &mut session,
+            "INSERT INTO accounts VALUES (1, 100), (2, 50)",
+        )?;
+        Ok(executor)
+    }
+
+    fn balance(executor: &Executor, session: &mut Session, id: i64) -> Result<Value, DbError> {
+        let sql = format!("SELECT balance FROM accounts WHERE id = {}", id);
+        let mut rows = run(executor, session, &sql)?.rows;
+        Ok(rows.pop().map_or(Value::Null, |mut row| row.remove(0)))
+    }
+
+    #[test]
+    fn test_uncommitted_changes_stay_in_their_session() -> Result<(), DbError> {
+        let executor = accounts()?;
+        let (mut alice, mut bob) = (Session::new(), Session::new());
+
+        run(&executor, &mut alice, "BEGIN")?;
+        assert!(alice.in_transaction());
+        run(
+            &executor,
+            &mut alice,
+            "UPDATE accounts SET balance = 70 WHERE id = 1",
+        )?;
+        run(&executor, &mut alice, "INSERT INTO accounts VALUES (3, 10)")?;
+        assert_eq!(balance(&executor, &mut alice, 1)?, Value::Integer(70));
+        assert_eq!(balance(&executor, &mut bob, 1)?, Value::Integer(100));
+        assert_eq!(balance(&executor, &mut bob, 3)?, Value::Null);
+
+        run(&executor, &mut alice, "COMMIT")?;
+        assert!(!alice.in_transaction());
+        assert_eq!(balance(&executor, &mut bob, 1)?, Value::Integer(70));
+        assert_eq!(balance(&executor, &mut bob, 3)?, Value::Integer(10));
+
+        run(&executor, &mut alice, "BEGIN")?;
+        run(&executor, &mut alice, "DELETE FROM accounts")?;
+        run(&executor, &mut alice, "ROLLBACK")?;
+        assert_eq!(balance(&executor, &mut bob, 2)?, Value::Integer(50));
+        Ok(())
+    }
+
+    #[test]
+    fn test_savepoints_undo_part_of_a_transaction() -> Result<(), DbError> {
+        let executor = accounts()?;
+        let mut session = Session::new();
+
+        assert!(run(&executor, &mut session, "SAVEPOINT early").is_err());
+        run(&executor, &mut session, "BEGIN")?;
+        run(
+            &executor,
+            &mut session,
+            "UPDATE accounts SET balance = 90 WHERE id = 1",
+        )?;
+        run(&executor, &mut session, "SAVEPOINT before_delete")?;
+        run(&executor, &mut session, "DELETE FROM accounts WHERE id = 2")?;
+        assert_eq!(balance(&executor, &mut session, 2)?, Value::Null);
+
+        run(
+            &executor,
+            &mut session,
+            "ROLLBACK TO SAVEPOINT before_delete",
+        )?;
+        assert_eq!(balance(&executor, &mut session, 2)?, Value::Integer(50));
+        run(&executor, &mut session, "RELEASE SAVEPOINT before_delete")?;
+        assert!(run(&executor, &mut session, "ROLLBACK TO before_delete").is_err());
+
+        // A failed statement leaves the transaction's earlier work in place
+        assert!(run(&executor, &mut session, "INSERT INTO missing VALUES (1)").is_err());
+        run(&executor, &mut session, "COMMIT")?;
+
+        let mut other = Session::new();
+        assert_eq!(balance(&executor, &mut other, 1)?, Value::Integer(90));
+        assert_eq!(balance(&executor, &mut other, 2)?, Value::Integer(50));
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_isolation_and_write_conflicts() -> Result<(), DbError> {
+        let executor = accounts()?;
+        let (mut alice, mut bob) = (Session::new(), Session::new());
+
+        run(
+            &executor,
+            &mut alice,
+            "BEGIN ISOLATION LEVEL REPEATABLE READ",
+        )?;
+        run(&executor, &mut bob, "BEGIN ISOLATION LEVEL READ COMMITTED")?;
+        assert_eq!(balance(&executor, &mut alice, 1)?, Value::Integer(100));
+        assert_eq!(balance(&executor, &mut bob, 1)?, Value::Integer(100));
+
+        let mut autocommit = Session::new();
+        run(
+            &executor,
+            &mut autocommit,
+            "UPDATE accounts SET balance = 120 WHERE id = 1",
+        )?;
+
+        // REPEATABLE READ keeps its snapshot; READ COMMITTED sees the commit
+        assert_eq!(balance(&executor, &mut alice, 1)?, Value::Integer(100));
+        assert_eq!(balance(&executor, &mut bob, 1)?, Value::Integer(120));
+
+        // The first committer wins
+        run(
+            &executor,
+            &mut alice,
+            "UPDATE accounts SET balance = balance - 10 WHERE id = 1",
+        )?;
+        assert!(matches!(
+            run(&executor, &mut alice, "COMMIT"),
+            Err(DbError::Conflict(_))
+        ));
+        assert!(!alice.in_transaction());
+        run(
+            &executor,
+            &mut bob,
+            "UPDATE accounts SET balance = balance - 10 WHERE id = 1",
+        )?;
+        run(&executor, &mut bob, "COMMIT")?;
+        assert_eq!(balance(&executor, &mut alice, 1)?, Value::Integer(110));
+        Ok(())
+    }
+    #[test]
+    fn test_set_parameter() -> Result<(), DbError> {
+        let executor = accounts()?;
+        let mut session = Session::new();
+
+        run(&executor, &mut session, "SET hnsw.ef_search = 100")?;
+        assert_eq!(session.ef_search, Some(100));
+        run(&executor, &mut session, "SET hnsw.ef_search TO DEFAULT")?;
+        assert_eq!(session.ef_search, None);
+
+        for sql in ["SET hnsw.ef_search = 0", "SET hnsw.ef_search = 'wide'"] {
+            assert!(matches!(
+                run(&executor, &mut session, sql),
+                Err(DbError::InvalidInput(_))
+            ));
+        }
+        assert!(run(&executor, &mut session, "SET no_such_setting = 1").is_err());
+        Ok(())
+    }
+}
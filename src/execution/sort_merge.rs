@@ -22,6 +22,8 @@
 //    - Eliminates need for hash tables
 //    - Supports streaming aggregation
 
+use crate::catalog::DataType;
+use crate::common::Value;
 use crate::error::DbError;
 use crate::execution::QueryResult;
 use crate::parser::OrderByClause;
@@ -103,7 +105,11 @@ impl ExternalMergeSorter {
         // Phase 2: K-way merge
         let sorted_rows = self.k_way_merge(runs, order_by)?;
 
-        Ok(QueryResult::new(data.columns, sorted_rows))
+        Ok(QueryResult::typed(
+            data.columns,
+            data.column_types,
+            sorted_rows,
+        ))
     }
 
     // In-memory sort
@@ -145,7 +151,7 @@ impl ExternalMergeSorter {
         &self,
         runs: Vec<PathBuf>,
         order_by: &[OrderByClause],
-    ) -> Result<Vec<Vec<String>>, DbError> {
+    ) -> Result<Vec<Vec<Value>>, DbError> {
         if runs.is_empty() {
             return Ok(Vec::new());
         }
@@ -200,7 +206,7 @@ impl ExternalMergeSorter {
         let mut heap: BinaryHeap<MergeEntry> = BinaryHeap::new();
 
         // Initialize heap with first row from each run
-        let mut current_rows: Vec<Option<Vec<String>>> = vec![None; runs.len()];
+        let mut current_rows: Vec<Option<Vec<Value>>> = vec![None; runs.len()];
 
         for (run_id, reader) in readers.iter_mut().enumerate() {
             if let Some(row) = Self::read_row(reader)? {
@@ -244,20 +250,13 @@ impl ExternalMergeSorter {
     }
 
     // Compare two rows based on ORDER BY clauses
-    fn compare_rows(&self, a: &[String], b: &[String], order_by: &[OrderByClause]) -> Ordering {
+    fn compare_rows(&self, a: &[Value], b: &[Value], order_by: &[OrderByClause]) -> Ordering {
         for clause in order_by {
             // Find column index (simplified - assumes column name is index)
             let col_idx = clause.column.parse::<usize>().unwrap_or(0);
 
             let cmp = match (a.get(col_idx), b.get(col_idx)) {
-                (Some(av), Some(bv)) => {
-                    // Try numeric comparison first
-                    if let (Ok(an), Ok(bn)) = (av.parse::<f64>(), bv.parse::<f64>()) {
-                        an.partial_cmp(&bn).unwrap_or(Ordering::Equal)
-                    } else {
-                        av.cmp(bv)
-                    }
-                }
+                (Some(av), Some(bv)) => av.sort_cmp(bv),
                 (Some(_), None) => Ordering::Greater,
                 (None, Some(_)) => Ordering::Less,
                 (None, None) => Ordering::Equal,
@@ -272,14 +271,14 @@ impl ExternalMergeSorter {
     }
 
     // Calculate rows per run based on memory budget
-    fn calculate_rows_per_run(&self, rows: &[Vec<String>]) -> usize {
+    fn calculate_rows_per_run(&self, rows: &[Vec<Value>]) -> usize {
         if rows.is_empty() {
             return 1000; // Default
         }
 
         let avg_row_size = rows
             .iter()
-            .map(|row| row.iter().map(|s| s.len()).sum::<usize>())
+            .map(|row| row.iter().map(Value::estimated_size).sum::<usize>())
             .sum::<usize>()
             / rows.len();
 
@@ -287,7 +286,7 @@ impl ExternalMergeSorter {
     }
 
     // Write sorted run to disk
-    fn write_run_to_disk(&self, rows: &[Vec<String>]) -> Result<PathBuf, DbError> {
+    fn write_run_to_disk(&self, rows: &[Vec<Value>]) -> Result<PathBuf, DbError> {
         let path = self.create_run_path()?;
         let file = File::create(&path).map_err(|e| DbError::Storage(e.to_string()))?;
         let mut writer = BufWriter::new(file);
@@ -304,7 +303,7 @@ impl ExternalMergeSorter {
     }
 
     // Read sorted run from disk
-    fn read_run_from_disk(&self, path: &Path) -> Result<Vec<Vec<String>>, DbError> {
+    fn read_run_from_disk(&self, path: &Path) -> Result<Vec<Vec<Value>>, DbError> {
         let file = File::open(path).map_err(|e| DbError::Storage(e.to_string()))?;
         let mut reader = BufReader::new(file);
 
@@ -316,9 +315,9 @@ impl ExternalMergeSorter {
         Ok(rows)
     }
 
-    // Write single row to file
-    fn write_row(writer: &mut BufWriter<File>, row: &[String]) -> Result<(), DbError> {
-        let line = row.join("\t") + "\n";
+    // Write single row to file, one JSON array per line
+    fn write_row(writer: &mut BufWriter<File>, row: &[Value]) -> Result<(), DbError> {
+        let line = serde_json::to_string(row)? + "\n";
         writer
             .write_all(line.as_bytes())
             .map_err(|e| DbError::Storage(e.to_string()))?;
@@ -326,7 +325,7 @@ impl ExternalMergeSorter {
     }
 
     // Read single row from file
-    fn read_row(reader: &mut BufReader<File>) -> Result<Option<Vec<String>>, DbError> {
+    fn read_row(reader: &mut BufReader<File>) -> Result<Option<Vec<Value>>, DbError> {
         let mut line = String::new();
         let bytes_read = reader
             .read_line(&mut line)
@@ -336,7 +335,7 @@ impl ExternalMergeSorter {
             return Ok(None);
         }
 
-        let row: Vec<String> = line.trim().split('\t').map(|s| s.to_string()).collect();
+        let row: Vec<Value> = serde_json::from_str(line.trim_end())?;
         Ok(Some(row))
     }
 
@@ -358,7 +357,7 @@ impl ExternalMergeSorter {
         let avg_row_size = data
             .rows
             .iter()
-            .map(|row| row.iter().map(|s| s.len()).sum::<usize>())
+            .map(|row| row.iter().map(Value::estimated_size).sum::<usize>())
             .sum::<usize>()
             / data.rows.len();
 
@@ -368,7 +367,7 @@ impl ExternalMergeSorter {
 
 // Entry in merge heap
 struct MergeEntry {
-    row: Vec<String>,
+    row: Vec<Value>,
     run_id: usize,
     order_by: Vec<OrderByClause>,
 }
@@ -394,13 +393,7 @@ impl Ord for MergeEntry {
             let col_idx = clause.column.parse::<usize>().unwrap_or(0);
 
             let cmp = match (self.row.get(col_idx), other.row.get(col_idx)) {
-                (Some(av), Some(bv)) => {
-                    if let (Ok(an), Ok(bn)) = (av.parse::<f64>(), bv.parse::<f64>()) {
-                        an.partial_cmp(&bn).unwrap_or(Ordering::Equal)
-                    } else {
-                        av.cmp(bv)
-                    }
-                }
+                (Some(av), Some(bv)) => av.sort_cmp(bv),
                 (Some(_), None) => Ordering::Greater,
                 (None, Some(_)) => Ordering::Less,
                 (None, None) => Ordering::Equal,
@@ -437,14 +430,15 @@ impl SortMergeJoin {
         let mut right_idx = 0;
 
         for left_row in &left.rows {
-            if let Some(left_key) = left_row.get(left_key_col) {
+            // NULL keys never match
+            if let Some(left_key) = left_row.get(left_key_col).filter(|k| !k.is_null()) {
                 // Find matching rows in right relation
                 let mut match_start = right_idx;
 
                 // Advance to first match
                 while match_start < right.rows.len() {
                     if let Some(right_key) = right.rows[match_start].get(right_key_col) {
-                        if right_key >= left_key {
+                        if right_key.sort_cmp(left_key) != Ordering::Less {
                             break;
                         }
                     }
@@ -455,7 +449,7 @@ impl SortMergeJoin {
                 let mut match_end = match_start;
                 while match_end < right.rows.len() {
                     if let Some(right_key) = right.rows[match_end].get(right_key_col) {
-                        if right_key == left_key {
+                        if right_key.sql_eq(left_key) == Some(true) {
                             let mut joined = left_row.clone();
                             joined.extend(right.rows[match_end].clone());
                             result_rows.push(joined);
@@ -474,8 +468,10 @@ impl SortMergeJoin {
 
         let mut columns = left.columns.clone();
         columns.extend(right.columns);
+        let mut column_types = left.column_types.clone();
+        column_types.extend(right.column_types);
 
-        Ok(QueryResult::new(columns, result_rows))
+        Ok(QueryResult::typed(columns, column_types, result_rows))
     }
 }
 
@@ -494,7 +490,7 @@ impl TopKSelector {
     }
 
     // Add row to top-K selector
-    pub fn add(&mut self, row: Vec<String>, order_by: &[OrderByClause]) {
+    pub fn add(&mut self, row: Vec<Value>, order_by: &[OrderByClause]) {
         let entry = TopKEntry {
             row,
             order_by: order_by.to_vec(),
@@ -511,8 +507,8 @@ impl TopKSelector {
     }
 
     // Get top K rows in sorted order
-    pub fn get_top_k(self) -> Vec<Vec<String>> {
-        let mut results: Vec<Vec<String>> = self.heap.into_iter().map(|entry| entry.row).collect();
+    pub fn get_top_k(self) -> Vec<Vec<Value>> {
+        let mut results: Vec<Vec<Value>> = self.heap.into_iter().map(|entry| entry.row).collect();
 
         // Reverse to get ascending order
         results.reverse();
@@ -533,13 +529,17 @@ impl TopKSelector {
 
         let top_rows = selector.get_top_k();
 
-        Ok(QueryResult::new(data.columns, top_rows))
+        Ok(QueryResult::typed(
+            data.columns,
+            data.column_types,
+            top_rows,
+        ))
     }
 }
 
 // Entry in top-K heap
 struct TopKEntry {
-    row: Vec<String>,
+    row: Vec<Value>,
     order_by: Vec<OrderByClause>,
 }
 
@@ -563,13 +563,7 @@ impl Ord for TopKEntry {
             let col_idx = clause.column.parse::<usize>().unwrap_or(0);
 
             let cmp = match (self.row.get(col_idx), other.row.get(col_idx)) {
-                (Some(av), Some(bv)) => {
-                    if let (Ok(an), Ok(bn)) = (av.parse::<f64>(), bv.parse::<f64>()) {
-                        an.partial_cmp(&bn).unwrap_or(Ordering::Equal)
-                    } else {
-                        av.cmp(bv)
-                    }
-                }
+                (Some(av), Some(bv)) => av.sort_cmp(bv),
                 (Some(_), None) => Ordering::Greater,
                 (None, Some(_)) => Ordering::Less,
                 (None, None) => Ordering::Equal,
@@ -612,11 +606,11 @@ impl SortBasedGrouping {
 
         // Sequential scan to compute aggregates
         let mut result_rows = Vec::new();
-        let mut current_group: Option<Vec<String>> = None;
+        let mut current_group: Option<Vec<Value>> = None;
         let mut current_count = 0;
 
         for row in sorted_rows {
-            let group_key: Vec<String> = group_by_cols
+            let group_key: Vec<Value> = group_by_cols
                 .iter()
                 .filter_map(|&idx| row.get(idx).cloned())
                 .collect();
@@ -632,7 +626,7 @@ impl SortBasedGrouping {
                 Some(prev_key) => {
                     // Output previous group
                     let mut result_row = prev_key.clone();
-                    result_row.push(current_count.to_string());
+                    result_row.push(Value::Integer(current_count));
                     result_rows.push(result_row);
 
                     // Start new group
@@ -645,7 +639,7 @@ impl SortBasedGrouping {
         // Output final group
         if let Some(key) = current_group {
             let mut result_row = key;
-            result_row.push(current_count.to_string());
+            result_row.push(Value::Integer(current_count));
             result_rows.push(result_row);
         }
 
@@ -654,8 +648,13 @@ impl SortBasedGrouping {
             .filter_map(|&idx| data.columns.get(idx).cloned())
             .collect();
         columns.push("count".to_string());
+        let mut column_types: Vec<DataType> = group_by_cols
+            .iter()
+            .filter_map(|&idx| data.column_types.get(idx).cloned())
+            .collect();
+        column_types.push(DataType::BigInt);
 
-        Ok(QueryResult::new(columns, result_rows))
+        Ok(QueryResult::typed(columns, column_types, result_rows))
     }
 }
 
@@ -669,9 +668,10 @@ mod tests {
         let data = QueryResult::new(
             vec!["id".to_string()],
             vec![
-                vec!["3".to_string()],
-                vec!["1".to_string()],
-                vec!["2".to_string()],
+                vec![Value::Integer(3)],
+                vec![Value::Null],
+                vec![Value::Integer(1)],
+                vec![Value::Integer(2)],
             ],
         );
 
@@ -681,8 +681,9 @@ mod tests {
         }];
 
         let result = sorter.in_memory_sort(data, &order_by).unwrap();
-        assert_eq!(result.rows[0][0], "1");
-        assert_eq!(result.rows[2][0], "3");
+        assert_eq!(result.rows[0][0], Value::Integer(1));
+        assert_eq!(result.rows[2][0], Value::Integer(3));
+        assert_eq!(result.rows[3][0], Value::Null);
     }
 
     #[test]
@@ -690,11 +691,11 @@ mod tests {
         let data = QueryResult::new(
             vec!["value".to_string()],
             vec![
-                vec!["5".to_string()],
-                vec!["2".to_string()],
-                vec!["8".to_string()],
-                vec!["1".to_string()],
-                vec!["9".to_string()],
+                vec![Value::Integer(5)],
+                vec![Value::Integer(2)],
+                vec![Value::Integer(8)],
+                vec![Value::Integer(1)],
+                vec![Value::Integer(9)],
             ],
         );
 
@@ -705,6 +706,8 @@ mod tests {
 
         let result = TopKSelector::select_top_k(data, 3, &order_by).unwrap();
         assert_eq!(result.rows.len(), 3);
+        assert!(result.rows.contains(&vec![Value::Integer(1)]));
+        assert!(!result.rows.contains(&vec![Value::Integer(9)]));
     }
 
     #[test]
@@ -712,16 +715,16 @@ mod tests {
         let left = QueryResult::new(
             vec!["id".to_string(), "name".to_string()],
             vec![
-                vec!["1".to_string(), "Alice".to_string()],
-                vec!["2".to_string(), "Bob".to_string()],
+                vec![Value::Integer(1), Value::String("Alice".to_string())],
+                vec![Value::Integer(2), Value::String("Bob".to_string())],
             ],
         );
 
         let right = QueryResult::new(
             vec!["id".to_string(), "value".to_string()],
             vec![
-                vec!["1".to_string(), "100".to_string()],
-                vec!["2".to_string(), "200".to_string()],
+                vec![Value::Integer(1), Value::Integer(100)],
+                vec![Value::Integer(2), Value::Integer(200)],
             ],
         );
 
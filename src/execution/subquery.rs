@@ -8,8 +8,10 @@
 // - ANY and ALL operators
 // - Subquery decorrelation optimization
 
-use super::planner::PlanNode;
+use super::expressions::BinaryOperator;
+use super::planner::{PlanNode, ScalarExpr};
 use super::QueryResult;
+use crate::common::Value;
 use crate::error::DbError;
 
 // Subquery type classification
@@ -89,20 +91,36 @@ pub struct InEvaluator;
 
 impl InEvaluator {
     // Evaluate IN subquery
-    // Check if value exists in subquery result set
-    pub fn evaluate(value: &str, result: &QueryResult, negated: bool) -> Result<bool, DbError> {
+    // Check if value exists in subquery result set; None is SQL UNKNOWN,
+    // produced when no row matches but a NULL was involved
+    pub fn evaluate(
+        value: &Value,
+        result: &QueryResult,
+        negated: bool,
+    ) -> Result<Option<bool>, DbError> {
         if result.columns.len() != 1 {
             return Err(DbError::InvalidInput(
                 "IN subquery must return exactly one column".to_string(),
             ));
         }
 
-        let in_set = result
-            .rows
-            .iter()
-            .any(|row| row.get(0).map(|v| v == value).unwrap_or(false));
+        if result.rows.is_empty() {
+            return Ok(Some(negated));
+        }
 
-        Ok(if negated { !in_set } else { in_set })
+        let mut in_set = Some(false);
+        for row in &result.rows {
+            match row.first().and_then(|v| value.sql_eq(v)) {
+                Some(true) => {
+                    in_set = Some(true);
+                    break;
+                }
+                Some(false) => {}
+                None => in_set = None,
+            }
+        }
+
+        Ok(in_set.map(|found| found != negated))
     }
 
     // Convert IN subquery to semi-join for optimization
@@ -110,14 +128,27 @@ impl InEvaluator {
         // Semi-join: returns rows from outer where match exists in inner
         // This is more efficient than nested loop evaluation
 
+        // The outer scan has one column, so the subquery's value is at 1
+        let inner_column = subquery
+            .plan
+            .output_columns()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| "value".to_string());
+        let condition = ScalarExpr::binary(
+            ScalarExpr::column(0, outer_column.clone()),
+            BinaryOperator::Equal,
+            ScalarExpr::column(1, inner_column),
+        );
+
         PlanNode::Join {
             join_type: crate::parser::JoinType::Inner,
             left: Box::new(PlanNode::TableScan {
                 table: "outer".to_string(),
-                columns: vec![outer_column.clone()],
+                columns: vec![outer_column],
             }),
             right: subquery.plan,
-            condition: format!("{} = subquery.value", outer_column),
+            condition: Some(condition),
         }
     }
 }
@@ -128,7 +159,7 @@ pub struct ScalarSubqueryEvaluator;
 impl ScalarSubqueryEvaluator {
     // Evaluate scalar subquery
     // Must return exactly one row and one column
-    pub fn evaluate(result: &QueryResult) -> Result<Option<String>, DbError> {
+    pub fn evaluate(result: &QueryResult) -> Result<Option<Value>, DbError> {
         if result.columns.len() > 1 {
             return Err(DbError::InvalidInput(
                 "Scalar subquery must return exactly one column".to_string(),
@@ -145,7 +176,7 @@ impl ScalarSubqueryEvaluator {
             return Ok(None); // NULL result
         }
 
-        Ok(result.rows[0].get(0).cloned())
+        Ok(result.rows[0].first().cloned().filter(|v| !v.is_null()))
     }
 }
 
@@ -154,36 +185,41 @@ pub struct QuantifiedComparisonEvaluator;
 
 impl QuantifiedComparisonEvaluator {
     // Evaluate ANY operator
-    // Returns true if comparison is true for ANY value in subquery
+    // Returns true if comparison is true for ANY value in subquery, UNKNOWN
+    // (None) if none is true but some comparison involved NULL
     pub fn evaluate_any(
-        value: &str,
+        value: &Value,
         operator: ComparisonOp,
         result: &QueryResult,
-    ) -> Result<bool, DbError> {
+    ) -> Result<Option<bool>, DbError> {
         if result.columns.len() != 1 {
             return Err(DbError::InvalidInput(
                 "Quantified comparison subquery must return exactly one column".to_string(),
             ));
         }
 
+        let mut outcome = Some(false);
         for row in &result.rows {
-            if let Some(subquery_value) = row.get(0) {
-                if Self::compare(value, operator, subquery_value)? {
-                    return Ok(true);
+            if let Some(subquery_value) = row.first() {
+                match Self::compare(value, operator, subquery_value) {
+                    Some(true) => return Ok(Some(true)),
+                    Some(false) => {}
+                    None => outcome = None,
                 }
             }
         }
 
-        Ok(false)
+        Ok(outcome)
     }
 
     // Evaluate ALL operator
-    // Returns true if comparison is true for ALL values in subquery
+    // Returns true if comparison is true for ALL values in subquery, UNKNOWN
+    // (None) if none is false but some comparison involved NULL
     pub fn evaluate_all(
-        value: &str,
+        value: &Value,
         operator: ComparisonOp,
         result: &QueryResult,
-    ) -> Result<bool, DbError> {
+    ) -> Result<Option<bool>, DbError> {
         if result.columns.len() != 1 {
             return Err(DbError::InvalidInput(
                 "Quantified comparison subquery must return exactly one column".to_string(),
@@ -191,41 +227,33 @@ impl QuantifiedComparisonEvaluator {
         }
 
         if result.rows.is_empty() {
-            return Ok(true); // Vacuously true
+            return Ok(Some(true)); // Vacuously true
         }
 
+        let mut outcome = Some(true);
         for row in &result.rows {
-            if let Some(subquery_value) = row.get(0) {
-                if !Self::compare(value, operator, subquery_value)? {
-                    return Ok(false);
+            if let Some(subquery_value) = row.first() {
+                match Self::compare(value, operator, subquery_value) {
+                    Some(false) => return Ok(Some(false)),
+                    Some(true) => {}
+                    None => outcome = None,
                 }
             }
         }
 
-        Ok(true)
+        Ok(outcome)
     }
 
-    fn compare(left: &str, op: ComparisonOp, right: &str) -> Result<bool, DbError> {
-        // Try numeric comparison first
-        if let (Ok(l), Ok(r)) = (left.parse::<f64>(), right.parse::<f64>()) {
-            return Ok(match op {
-                ComparisonOp::Equal => l == r,
-                ComparisonOp::NotEqual => l != r,
-                ComparisonOp::Less => l < r,
-                ComparisonOp::LessOrEqual => l <= r,
-                ComparisonOp::Greater => l > r,
-                ComparisonOp::GreaterOrEqual => l >= r,
-            });
-        }
-
-        // Fall back to string comparison
-        Ok(match op {
-            ComparisonOp::Equal => left == right,
-            ComparisonOp::NotEqual => left != right,
-            ComparisonOp::Less => left < right,
-            ComparisonOp::LessOrEqual => left <= right,
-            ComparisonOp::Greater => left > right,
-            ComparisonOp::GreaterOrEqual => left >= right,
+    // Compare under SQL semantics; None when either side is NULL
+    fn compare(left: &Value, op: ComparisonOp, right: &Value) -> Option<bool> {
+        let ordering = left.sql_cmp(right)?;
+        Some(match op {
+            ComparisonOp::Equal => ordering.is_eq(),
+            ComparisonOp::NotEqual => ordering.is_ne(),
+            ComparisonOp::Less => ordering.is_lt(),
+            ComparisonOp::LessOrEqual => ordering.is_le(),
+            ComparisonOp::Greater => ordering.is_gt(),
+            ComparisonOp::GreaterOrEqual => ordering.is_ge(),
         })
     }
 }
@@ -495,7 +523,7 @@ mod tests {
 
     #[test]
     fn test_exists_evaluator() {
-        let result = QueryResult::new(vec!["id".to_string()], vec![vec!["1".to_string()]]);
+        let result = QueryResult::new(vec!["id".to_string()], vec![vec![Value::Integer(1)]]);
 
         assert!(ExistsEvaluator::evaluate(&result, false));
         assert!(!ExistsEvaluator::evaluate(&result, true)); // NOT EXISTS
@@ -510,24 +538,49 @@ mod tests {
         let result = QueryResult::new(
             vec!["value".to_string()],
             vec![
-                vec!["1".to_string()],
-                vec!["2".to_string()],
-                vec!["3".to_string()],
+                vec![Value::Integer(1)],
+                vec![Value::Integer(2)],
+                vec![Value::Integer(3)],
             ],
         );
 
-        assert!(InEvaluator::evaluate("2", &result, false).unwrap());
-        assert!(!InEvaluator::evaluate("4", &result, false).unwrap());
-        assert!(InEvaluator::evaluate("4", &result, true).unwrap()); // NOT IN
+        let two = Value::Integer(2);
+        let four = Value::Integer(4);
+        assert_eq!(
+            InEvaluator::evaluate(&two, &result, false).unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            InEvaluator::evaluate(&four, &result, false).unwrap(),
+            Some(false)
+        );
+        assert_eq!(
+            InEvaluator::evaluate(&four, &result, true).unwrap(),
+            Some(true)
+        ); // NOT IN
+
+        // NOT IN over a set containing NULL is never true
+        let with_null = QueryResult::new(
+            vec!["value".to_string()],
+            vec![vec![Value::Integer(1)], vec![Value::Null]],
+        );
+        assert_eq!(
+            InEvaluator::evaluate(&four, &with_null, true).unwrap(),
+            None
+        );
+        assert_eq!(
+            InEvaluator::evaluate(&Value::Null, &result, false).unwrap(),
+            None
+        );
     }
 
     #[test]
     fn test_scalar_subquery_evaluator() {
         // Valid scalar subquery
-        let result = QueryResult::new(vec!["count".to_string()], vec![vec!["42".to_string()]]);
+        let result = QueryResult::new(vec!["count".to_string()], vec![vec![Value::Integer(42)]]);
 
         let value = ScalarSubqueryEvaluator::evaluate(&result).unwrap();
-        assert_eq!(value, Some("42".to_string()));
+        assert_eq!(value, Some(Value::Integer(42)));
 
         // Empty result (NULL)
         let empty = QueryResult::empty();
@@ -537,7 +590,7 @@ mod tests {
         // Too many rows - should error
         let multi_row = QueryResult::new(
             vec!["count".to_string()],
-            vec![vec!["1".to_string()], vec!["2".to_string()]],
+            vec![vec![Value::Integer(1)], vec![Value::Integer(2)]],
         );
 
         assert!(ScalarSubqueryEvaluator::evaluate(&multi_row).is_err());
@@ -548,26 +601,50 @@ mod tests {
         let result = QueryResult::new(
             vec!["value".to_string()],
             vec![
-                vec!["10".to_string()],
-                vec!["20".to_string()],
-                vec!["30".to_string()],
+                vec![Value::Integer(10)],
+                vec![Value::Integer(20)],
+                vec![Value::Integer(30)],
             ],
         );
 
         // 15 < ANY (10, 20, 30) -> true (15 < 20 and 15 < 30)
-        assert!(
-            QuantifiedComparisonEvaluator::evaluate_any("15", ComparisonOp::Less, &result).unwrap()
+        assert_eq!(
+            QuantifiedComparisonEvaluator::evaluate_any(
+                &Value::Integer(15),
+                ComparisonOp::Less,
+                &result
+            )
+            .unwrap(),
+            Some(true)
         );
 
         // 5 < ALL (10, 20, 30) -> true
-        assert!(
-            QuantifiedComparisonEvaluator::evaluate_all("5", ComparisonOp::Less, &result).unwrap()
+        assert_eq!(
+            QuantifiedComparisonEvaluator::evaluate_all(
+                &Value::Integer(5),
+                ComparisonOp::Less,
+                &result
+            )
+            .unwrap(),
+            Some(true)
         );
 
         // 25 < ALL (10, 20, 30) -> false (25 not < 10)
-        assert!(
-            !QuantifiedComparisonEvaluator::evaluate_all("25", ComparisonOp::Less, &result)
-                .unwrap()
+        assert_eq!(
+            QuantifiedComparisonEvaluator::evaluate_all(
+                &Value::Integer(25),
+                ComparisonOp::Less,
+                &result
+            )
+            .unwrap(),
+            Some(false)
+        );
+
+        // NULL compared with anything is unknown
+        assert_eq!(
+            QuantifiedComparisonEvaluator::evaluate_any(&Value::Null, ComparisonOp::Less, &result)
+                .unwrap(),
+            None
         );
     }
 
@@ -590,7 +667,7 @@ mod tests {
     fn test_subquery_cache() {
         let mut cache = SubqueryCache::new(10);
 
-        let result = QueryResult::new(vec!["id".to_string()], vec![vec!["1".to_string()]]);
+        let result = QueryResult::new(vec!["id".to_string()], vec![vec![Value::Integer(1)]]);
 
         cache.put("key1".to_string(), result.clone());
 
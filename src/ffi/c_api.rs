@@ -5,10 +5,14 @@
 
 use std::os::raw::{c_char, c_int};
 use std::ptr;
+use crate::api::rest::handlers::{new_executor, parse_checked, CATALOG};
+use crate::common::Value;
 use crate::error::DbError;
+use crate::execution::{Executor, Session};
+use crate::security::sql_firewall::FirewallIdentity;
 use super::types::{
-    rustydb_handle_t, rustydb_result_t, RustyDbHandle, RustyDbResult,
-    ConnectionState, c_char_to_string, string_to_c_char,
+    rustydb_handle_t, rustydb_result_t, rustydb_stmt_t, RustyDbHandle, RustyDbResult,
+    RustyDbStatement, c_char_to_string, string_to_c_char,
     RUSTYDB_OK, RUSTYDB_ERROR,
 };
 
@@ -23,6 +27,7 @@ use super::types::{
 /// # Parameters
 /// - `connection_string`: Null-terminated C string with connection parameters
 ///   Format: "host=localhost;port=5432;database=mydb;user=admin;password=secret"
+///   The SQL firewall checks statements as `user` and `application_name`.
 ///
 /// # Returns
 /// - Non-null pointer to rustydb_handle_t on success
@@ -157,9 +162,9 @@ pub unsafe extern "C" fn rustydb_query(
     };
 
     // Execute query
-    // In a real implementation, this would parse and execute the SQL
-    // For v0.6, we return a mock result
-    let result = match execute_sql_query(&sql_str) {
+    let result = execute_sql_query(&mut handle_ref.session, &handle_ref.identity, &sql_str);
+    handle_ref.sync_transaction();
+    let result = match result {
         Ok(res) => res,
         Err(e) => {
             handle_ref.set_error(e);
@@ -271,6 +276,219 @@ pub unsafe extern "C" fn rustydb_result_data_json(result: *const rustydb_result_
     }
 }
 
+// ============================================================================
+// Prepared Statements
+// ============================================================================
+
+/// Prepare a SQL statement
+///
+/// Parses and binds the statement once. Parameters are written `$1`, `$2`,
+/// ... or `?`, and are given values with the rustydb_bind_* functions; the
+/// values are never spliced into the SQL text.
+///
+/// # Parameters
+/// - `handle`: Pointer to a valid rustydb_handle_t
+/// - `sql`: Null-terminated C string containing a single SQL statement
+///
+/// # Returns
+/// - Non-null pointer to rustydb_stmt_t on success
+/// - NULL on failure (check rustydb_error_message for details)
+///
+/// # Memory Management
+/// The returned statement must be freed by calling rustydb_stmt_finalize().
+///
+/// # Safety
+/// Both handle and sql pointers must be valid.
+///
+/// # Example (C)
+/// ```c
+/// rustydb_stmt_t* stmt = rustydb_prepare(handle, "SELECT name FROM users WHERE id = $1");
+/// rustydb_bind_int64(handle, stmt, 1, 42);
+/// rustydb_result_t* result = rustydb_stmt_execute(handle, stmt);
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn rustydb_prepare(
+    handle: *mut rustydb_handle_t,
+    sql: *const c_char,
+) -> *mut rustydb_stmt_t {
+    if handle.is_null() || sql.is_null() {
+        return ptr::null_mut();
+    }
+
+    let handle_ref = &mut *(handle as *mut RustyDbHandle);
+    handle_ref.clear_error();
+
+    let sql_str = match c_char_to_string(sql) {
+        Some(s) => s,
+        None => {
+            handle_ref.set_error(DbError::InvalidInput("Invalid SQL string".to_string()));
+            return ptr::null_mut();
+        }
+    };
+
+    let prepared = parse_single_statement(&sql_str, &handle_ref.identity)
+        .and_then(|stmt| ffi_executor().prepare(stmt, Vec::new()));
+    match prepared {
+        Ok(prepared) => {
+            Box::into_raw(Box::new(RustyDbStatement::new(prepared))) as *mut rustydb_stmt_t
+        }
+        Err(e) => {
+            handle_ref.set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get the number of parameters of a prepared statement
+///
+/// # Returns
+/// - Number of parameters
+/// - -1 if stmt is NULL
+///
+/// # Safety
+/// The stmt pointer must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn rustydb_stmt_param_count(stmt: *const rustydb_stmt_t) -> c_int {
+    if stmt.is_null() {
+        return -1;
+    }
+
+    let stmt_ref = &*(stmt as *const RustyDbStatement);
+    stmt_ref.params.len() as c_int
+}
+
+/// Bind NULL to a parameter
+///
+/// # Parameters
+/// - `handle`: Pointer to a valid rustydb_handle_t, which receives any error
+/// - `stmt`: Pointer to a valid rustydb_stmt_t
+/// - `index`: Parameter number, counting from 1
+///
+/// # Returns
+/// - RUSTYDB_OK (0) on success
+/// - RUSTYDB_ERROR (-1) if the index is out of range
+///
+/// # Safety
+/// Both handle and stmt pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn rustydb_bind_null(
+    handle: *mut rustydb_handle_t,
+    stmt: *mut rustydb_stmt_t,
+    index: c_int,
+) -> c_int {
+    bind_parameter(handle, stmt, index, Some(Value::Null))
+}
+
+/// Bind a 64-bit integer to a parameter
+///
+/// See rustydb_bind_null for parameters and return values.
+///
+/// # Safety
+/// Both handle and stmt pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn rustydb_bind_int64(
+    handle: *mut rustydb_handle_t,
+    stmt: *mut rustydb_stmt_t,
+    index: c_int,
+    value: i64,
+) -> c_int {
+    bind_parameter(handle, stmt, index, Some(Value::Integer(value)))
+}
+
+/// Bind a double to a parameter
+///
+/// See rustydb_bind_null for parameters and return values.
+///
+/// # Safety
+/// Both handle and stmt pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn rustydb_bind_double(
+    handle: *mut rustydb_handle_t,
+    stmt: *mut rustydb_stmt_t,
+    index: c_int,
+    value: f64,
+) -> c_int {
+    bind_parameter(handle, stmt, index, Some(Value::Float(value)))
+}
+
+/// Bind a string to a parameter
+///
+/// The string is copied, so it may be freed once this call returns. It is
+/// converted to the parameter's type when the statement executes.
+/// See rustydb_bind_null for parameters and return values.
+///
+/// # Safety
+/// Both handle and stmt pointers must be valid, and value must point to a
+/// null-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn rustydb_bind_text(
+    handle: *mut rustydb_handle_t,
+    stmt: *mut rustydb_stmt_t,
+    index: c_int,
+    value: *const c_char,
+) -> c_int {
+    bind_parameter(handle, stmt, index, c_char_to_string(value).map(Value::String))
+}
+
+/// Execute a prepared statement with its bound parameters
+///
+/// Parameters keep their values afterwards, so the statement can be run
+/// again with only some of them rebound.
+///
+/// # Parameters
+/// - `handle`: Pointer to a valid rustydb_handle_t
+/// - `stmt`: Pointer to a valid rustydb_stmt_t
+///
+/// # Returns
+/// - Non-null pointer to rustydb_result_t on success
+/// - NULL on failure (check rustydb_error_message for details)
+///
+/// # Memory Management
+/// The returned result must be freed by calling rustydb_free_result().
+///
+/// # Safety
+/// Both handle and stmt pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn rustydb_stmt_execute(
+    handle: *mut rustydb_handle_t,
+    stmt: *mut rustydb_stmt_t,
+) -> *mut rustydb_result_t {
+    if handle.is_null() || stmt.is_null() {
+        return ptr::null_mut();
+    }
+
+    let handle_ref = &mut *(handle as *mut RustyDbHandle);
+    let stmt_ref = &mut *(stmt as *mut RustyDbStatement);
+    handle_ref.clear_error();
+
+    let params = stmt_ref.params.clone();
+    let executor = handle_ref.session.executor(&ffi_executor());
+    match executor.execute_prepared(&mut stmt_ref.prepared, params) {
+        Ok(result) => {
+            let boxed_result = Box::new(RustyDbResult::from_query_result(result));
+            Box::into_raw(boxed_result) as *mut rustydb_result_t
+        }
+        Err(e) => {
+            handle_ref.set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a prepared statement
+///
+/// # Safety
+/// The stmt pointer must have been returned by rustydb_prepare().
+/// Calling this function with NULL is safe (no-op).
+#[no_mangle]
+pub unsafe extern "C" fn rustydb_stmt_finalize(stmt: *mut rustydb_stmt_t) {
+    if stmt.is_null() {
+        return;
+    }
+
+    drop(Box::from_raw(stmt as *mut RustyDbStatement));
+}
+
 // ============================================================================
 // Transaction Control
 // ============================================================================
@@ -311,25 +529,23 @@ pub unsafe extern "C" fn rustydb_begin(handle: *mut rustydb_handle_t) -> c_int {
     handle_ref.clear_error();
 
     // Check if already in transaction
-    if handle_ref.state == ConnectionState::InTransaction {
+    if handle_ref.session.in_transaction() {
         handle_ref.set_error(DbError::InvalidOperation(
             "Already in a transaction".to_string()
         ));
         return RUSTYDB_ERROR;
     }
 
-    // Begin transaction
-    // In a real implementation, this would start a database transaction
-    // For v0.6, we just update the state
-    handle_ref.state = ConnectionState::InTransaction;
-    handle_ref.transaction_id = Some(generate_transaction_id());
-
-    RUSTYDB_OK
+    // Begin transaction at the database's default isolation level
+    let result = handle_ref.session.begin(&ffi_executor(), None);
+    end_transaction_call(handle_ref, result.map(|_| ()))
 }
 
 /// Commit the current transaction
 ///
 /// Commits all changes made in the current transaction, making them permanent.
+/// A failed commit, such as one losing a write conflict, rolls the
+/// transaction back.
 ///
 /// # Parameters
 /// - `handle`: Pointer to a valid rustydb_handle_t
@@ -345,7 +561,6 @@ pub unsafe extern "C" fn rustydb_begin(handle: *mut rustydb_handle_t) -> c_int {
 /// ```c
 /// if (rustydb_commit(handle) != RUSTYDB_OK) {
 ///     fprintf(stderr, "Failed to commit: %s\n", rustydb_error_message(handle));
-///     rustydb_rollback(handle); // Try to rollback
 ///     return 1;
 /// }
 /// ```
@@ -362,20 +577,16 @@ pub unsafe extern "C" fn rustydb_commit(handle: *mut rustydb_handle_t) -> c_int
     handle_ref.clear_error();
 
     // Check if in transaction
-    if handle_ref.state != ConnectionState::InTransaction {
+    if !handle_ref.session.in_transaction() {
         handle_ref.set_error(DbError::InvalidOperation(
             "Not in a transaction".to_string()
         ));
         return RUSTYDB_ERROR;
     }
 
-    // Commit transaction
-    // In a real implementation, this would commit the database transaction
-    // For v0.6, we just update the state
-    handle_ref.state = ConnectionState::Active;
-    handle_ref.transaction_id = None;
-
-    RUSTYDB_OK
+    // Commit transaction; a failed commit rolls it back
+    let result = handle_ref.session.commit();
+    end_transaction_call(handle_ref, result)
 }
 
 /// Rollback the current transaction
@@ -412,7 +623,7 @@ pub unsafe extern "C" fn rustydb_rollback(handle: *mut rustydb_handle_t) -> c_in
     handle_ref.clear_error();
 
     // Check if in transaction
-    if handle_ref.state != ConnectionState::InTransaction {
+    if !handle_ref.session.in_transaction() {
         handle_ref.set_error(DbError::InvalidOperation(
             "Not in a transaction".to_string()
         ));
@@ -420,12 +631,8 @@ pub unsafe extern "C" fn rustydb_rollback(handle: *mut rustydb_handle_t) -> c_in
     }
 
     // Rollback transaction
-    // In a real implementation, this would rollback the database transaction
-    // For v0.6, we just update the state
-    handle_ref.state = ConnectionState::Active;
-    handle_ref.transaction_id = None;
-
-    RUSTYDB_OK
+    let result = handle_ref.session.rollback();
+    end_transaction_call(handle_ref, result)
 }
 
 // ============================================================================
@@ -477,28 +684,77 @@ pub unsafe extern "C" fn rustydb_version() -> *const c_char {
 // Internal Helper Functions
 // ============================================================================
 
+/// Executor over the tables shared with the REST API
+fn ffi_executor() -> Executor {
+    new_executor(CATALOG.read().clone())
+}
+
+/// Parse SQL text holding one statement and check it against the SQL
+/// firewall as `identity`
+fn parse_single_statement(
+    sql: &str,
+    identity: &FirewallIdentity,
+) -> Result<crate::parser::SqlStatement, DbError> {
+    parse_checked(sql, identity)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| DbError::SqlParse("No valid SQL statement found".to_string()))
+}
+
 /// Execute a SQL query (internal implementation)
-fn execute_sql_query(sql: &str) -> Result<RustyDbResult, DbError> {
-    // For v0.6, this is a mock implementation
-    // A real implementation would:
-    // 1. Parse the SQL using the parser module
-    // 2. Create an execution plan using the execution module
-    // 3. Execute the plan and collect results
-    // 4. Format results as JSON
-
-    // Simple mock: return success for any query
-    Ok(RustyDbResult::success(
-        0,
-        Some(format!(r#"{{"status":"success","query":"{}"}}"#, sql)),
-        vec!["status".to_string(), "query".to_string()],
-    ))
+///
+/// Statements run in the connection's session, so PREPARE, EXECUTE and
+/// DEALLOCATE work on its statements and BEGIN, COMMIT and ROLLBACK on its
+/// transaction.
+fn execute_sql_query(
+    session: &mut Session,
+    identity: &FirewallIdentity,
+    sql: &str,
+) -> Result<RustyDbResult, DbError> {
+    let stmt = parse_single_statement(sql, identity)?;
+    let result = session.run(&ffi_executor(), stmt)?;
+    Ok(RustyDbResult::from_query_result(result))
 }
 
-/// Generate a unique transaction ID
-fn generate_transaction_id() -> u64 {
-    use std::sync::atomic::{AtomicU64, Ordering};
-    static COUNTER: AtomicU64 = AtomicU64::new(1);
-    COUNTER.fetch_add(1, Ordering::Relaxed)
+/// Finish a transaction control call, reporting errors on the handle
+fn end_transaction_call(handle: &mut RustyDbHandle, result: Result<(), DbError>) -> c_int {
+    handle.sync_transaction();
+    match result {
+        Ok(()) => RUSTYDB_OK,
+        Err(e) => {
+            handle.set_error(e);
+            RUSTYDB_ERROR
+        }
+    }
+}
+
+/// Bind one parameter value, reporting errors on the handle
+///
+/// `None` stands for a value that could not be read from C.
+unsafe fn bind_parameter(
+    handle: *mut rustydb_handle_t,
+    stmt: *mut rustydb_stmt_t,
+    index: c_int,
+    value: Option<Value>,
+) -> c_int {
+    if handle.is_null() || stmt.is_null() {
+        return RUSTYDB_ERROR;
+    }
+
+    let handle_ref = &mut *(handle as *mut RustyDbHandle);
+    let stmt_ref = &mut *(stmt as *mut RustyDbStatement);
+    handle_ref.clear_error();
+
+    let result = value
+        .ok_or_else(|| DbError::InvalidInput("Invalid parameter string".to_string()))
+        .and_then(|value| stmt_ref.bind(index, value));
+    match result {
+        Ok(()) => RUSTYDB_OK,
+        Err(e) => {
+            handle_ref.set_error(e);
+            RUSTYDB_ERROR
+        }
+    }
 }
 
 // ============================================================================
@@ -554,8 +810,46 @@ mod tests {
             let result = rustydb_query(handle, sql.as_ptr());
             assert!(!result.is_null());
 
+            // A query reports the rows it returned
             let rows = rustydb_result_rows_affected(result);
-            assert_eq!(rows, 0);
+            assert_eq!(rows, 1);
+
+            rustydb_free_result(result);
+            rustydb_disconnect(handle);
+        }
+    }
+
+    #[test]
+    fn test_prepared_statement() {
+        unsafe {
+            let conn_str = std::ffi::CString::new("test").unwrap();
+            let handle = rustydb_connect(conn_str.as_ptr());
+
+            let sql =
+                std::ffi::CString::new("CREATE TABLE ffi_prepared (id INT, name TEXT)").unwrap();
+            rustydb_free_result(rustydb_query(handle, sql.as_ptr()));
+
+            let sql = std::ffi::CString::new("INSERT INTO ffi_prepared VALUES (?, ?)").unwrap();
+            let stmt = rustydb_prepare(handle, sql.as_ptr());
+            assert!(!stmt.is_null());
+            assert_eq!(rustydb_stmt_param_count(stmt), 2);
+
+            let name = std::ffi::CString::new("x'); DROP TABLE ffi_prepared; --").unwrap();
+            assert_eq!(rustydb_bind_int64(handle, stmt, 1, 7), RUSTYDB_OK);
+            assert_eq!(rustydb_bind_text(handle, stmt, 2, name.as_ptr()), RUSTYDB_OK);
+            assert_eq!(rustydb_bind_null(handle, stmt, 3), RUSTYDB_ERROR);
+
+            let result = rustydb_stmt_execute(handle, stmt);
+            assert!(!result.is_null());
+            assert_eq!(rustydb_result_rows_affected(result), 1);
+            rustydb_free_result(result);
+            rustydb_stmt_finalize(stmt);
+
+            let sql = std::ffi::CString::new("SELECT name FROM ffi_prepared WHERE id = 7").unwrap();
+            let result = rustydb_query(handle, sql.as_ptr());
+            assert!(!result.is_null());
+            let json = std::ffi::CStr::from_ptr(rustydb_result_data_json(result));
+            assert!(json.to_str().unwrap().contains("DROP TABLE"));
 
             rustydb_free_result(result);
             rustydb_disconnect(handle);
@@ -19,6 +19,7 @@
 //
 // - **Handles** (`rustydb_handle_t`): Allocated by `rustydb_connect()`, freed by `rustydb_disconnect()`
 // - **Results** (`rustydb_result_t`): Allocated by `rustydb_query()`, freed by `rustydb_free_result()`
+// - **Statements** (`rustydb_stmt_t`): Allocated by `rustydb_prepare()`, freed by `rustydb_stmt_finalize()`
 // - **Strings**: Most strings are owned by handles/results and should NOT be freed.
 //   Exceptions are documented per function.
 //
@@ -133,6 +134,16 @@ pub use c_api::{
     rustydb_result_rows_affected,
     rustydb_result_data_json,
 
+    // Prepared statements
+    rustydb_prepare,
+    rustydb_stmt_param_count,
+    rustydb_bind_null,
+    rustydb_bind_int64,
+    rustydb_bind_double,
+    rustydb_bind_text,
+    rustydb_stmt_execute,
+    rustydb_stmt_finalize,
+
     // Transaction control
     rustydb_begin,
     rustydb_commit,
@@ -153,6 +164,7 @@ pub use error::{
 pub use types::{
     rustydb_handle_t,
     rustydb_result_t,
+    rustydb_stmt_t,
     RUSTYDB_OK,
     RUSTYDB_ERROR,
 };
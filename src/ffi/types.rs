@@ -5,7 +5,11 @@
 
 use std::os::raw::{c_char, c_int};
 use std::sync::{Arc, Mutex};
+use crate::api::rest::handlers::API_ROLE;
+use crate::common::Value;
 use crate::error::DbError;
+use crate::execution::{PreparedStatement, QueryResult, Session};
+use crate::security::sql_firewall::FirewallIdentity;
 
 /// Opaque handle to a database connection
 ///
@@ -41,6 +45,12 @@ pub struct RustyDbHandle {
 
     /// Connection string used to establish this connection
     pub connection_string: String,
+
+    /// Prepared statements and open transaction of this connection
+    pub session: Session,
+
+    /// Who the SQL firewall checks this connection's statements as
+    pub identity: FirewallIdentity,
 }
 
 /// Connection state enumeration
@@ -69,10 +79,32 @@ impl RustyDbHandle {
             last_error_code: 0,
             state: ConnectionState::Active,
             transaction_id: None,
+            identity: Self::firewall_identity(&connection_string),
             connection_string,
+            session: Session::new(),
         }
     }
 
+    /// Firewall identity named by the `user` and `application_name` keys of
+    /// a connection string; `API_ROLE` when it names no user
+    fn firewall_identity(connection_string: &str) -> FirewallIdentity {
+        let mut identity = FirewallIdentity::new(API_ROLE);
+        for (key, value) in connection_string
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+        {
+            let value = value.trim();
+            match key.trim() {
+                "user" if !value.is_empty() => identity.role = value.to_string(),
+                "application_name" if !value.is_empty() => {
+                    identity.application = Some(value.to_string())
+                }
+                _ => {}
+            }
+        }
+        identity
+    }
+
     /// Generate a unique connection ID
     fn generate_connection_id() -> u64 {
         use std::sync::atomic::{AtomicU64, Ordering};
@@ -99,6 +131,18 @@ impl RustyDbHandle {
         if self.state == ConnectionState::Error {
             self.state = ConnectionState::Active;
         }
+        self.sync_transaction();
+    }
+
+    /// Reflect the session's open transaction in `state` and `transaction_id`
+    pub fn sync_transaction(&mut self) {
+        self.transaction_id = self.session.transaction_id();
+        if self.state != ConnectionState::Error {
+            self.state = match self.transaction_id {
+                Some(_) => ConnectionState::InTransaction,
+                None => ConnectionState::Active,
+            };
+        }
     }
 
     /// Convert DbError to error code
@@ -214,6 +258,66 @@ impl RustyDbResult {
     }
 }
 
+impl RustyDbResult {
+    /// Create a successful result from an executor result
+    ///
+    /// The data is a JSON object with `columns` and `rows` arrays.
+    pub fn from_query_result(result: QueryResult) -> Self {
+        let rows: Vec<serde_json::Value> = result
+            .rows
+            .iter()
+            .map(|row| serde_json::Value::Array(row.iter().map(Value::to_json).collect()))
+            .collect();
+        let data = serde_json::json!({ "columns": result.columns, "rows": rows });
+        Self::success(result.rows_affected as i64, Some(data.to_string()), result.columns)
+    }
+}
+
+/// Opaque handle to a prepared statement
+///
+/// Represents a statement bound once by `rustydb_prepare`, to be run any
+/// number of times with different parameter values.
+#[repr(C)]
+pub struct rustydb_stmt_t {
+    _private: [u8; 0],
+}
+
+/// Internal representation of a prepared statement
+///
+/// Holds the bound statement and the parameter values bound so far.
+pub struct RustyDbStatement {
+    /// Statement as bound by the executor
+    pub prepared: PreparedStatement,
+
+    /// One value per parameter; NULL until bound
+    pub params: Vec<Value>,
+}
+
+impl RustyDbStatement {
+    /// Wrap a prepared statement with all parameters NULL
+    pub fn new(prepared: PreparedStatement) -> Self {
+        let params = vec![Value::Null; prepared.param_types().len()];
+        Self { prepared, params }
+    }
+
+    /// Bind `value` to parameter `index`, counting from 1
+    pub fn bind(&mut self, index: c_int, value: Value) -> Result<(), DbError> {
+        let count = self.params.len();
+        let slot = usize::try_from(index)
+            .ok()
+            .and_then(|i| i.checked_sub(1))
+            .and_then(|i| self.params.get_mut(i))
+            .ok_or_else(|| {
+                DbError::InvalidArgument(format!(
+                    "Parameter index {} out of range (statement has {} parameters)",
+                    index, count
+                ))
+            })?;
+        *slot = value;
+        Ok(())
+    }
+}
+
 /// FFI success code
 pub const RUSTYDB_OK: c_int = 0;
 
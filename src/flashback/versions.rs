@@ -424,6 +424,9 @@ impl VersionStore {
             Value::Json(j) => j.to_string().len(),
             Value::Array(a) => a.iter().map(|v| self.estimate_value_size(v)).sum(),
             Value::Text => 4,
+            Value::Vector(v) => v.len() * 8,
+            Value::Numeric(_) | Value::Interval(_) | Value::Uuid(_) => 16,
+            Value::TimestampTz(_) => 8,
         }
     }
 
@@ -0,0 +1,411 @@
+// Index page files
+//
+// An `IndexFile` holds the pages of any number of disk B+Trees in one data
+// file, cached in a `BufferPoolManager`. Page 0 is the file header: it heads
+// the list of free pages and the chain of tree meta pages, one per tree,
+// which hold a tree's name and root.
+//
+// Once a WAL is attached every page change is logged before it is made. A
+// change to several pages at once - a node split, say - is one
+// mini-transaction of full page images that commits as a whole, so recovery
+// never finds half of it. Pages carry the LSN of their last change and the
+// buffer pool writes a page back only once the log is durable up to it.
+//
+// Pages taken for a change that never commits (because of a crash) are not
+// returned to the free list.
+
+use super::node::{self, FileHeader, TreeMeta};
+use crate::buffer::manager::{BufferPoolConfig, BufferPoolManager, BufferPoolStats};
+use crate::buffer::page_cache::{FrameGuard, PageBuffer, PAGE_SIZE};
+use crate::common::PageId;
+use crate::error::{DbError, Result};
+use crate::storage::disk::DiskManager;
+use crate::transaction::recovery::RecoverablePages;
+use crate::transaction::wal::{LogRecord, WALManager, LSN};
+use parking_lot::{Mutex, RwLock};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+const HEADER_PAGE_ID: PageId = 0;
+
+static SCRATCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A write-latched page and the image it is to be changed to
+pub(super) struct PageChange<'a> {
+    pub frame: &'a FrameGuard,
+    pub page: &'a mut PageBuffer,
+    pub image: Vec<u8>,
+}
+
+pub struct IndexFile {
+    pool: BufferPoolManager,
+    disk: Arc<DiskManager>,
+    wal: RwLock<Option<Arc<WALManager>>>,
+    next_txn_id: AtomicU64,
+    // Held while trees are added to or taken out of the chain of meta pages
+    trees_lock: Mutex<()>,
+    // Directory removed on drop for scratch files
+    scratch_dir: Option<PathBuf>,
+}
+
+impl IndexFile {
+    /// Open (or create) the index file in `dir`, caching `frames` pages
+    pub fn open(dir: &str, frames: usize) -> Result<Self> {
+        let disk = Arc::new(DiskManager::new(dir, PAGE_SIZE)?);
+        let is_new = disk.get_num_pages() == 0;
+        // Pages are written back at checkpoints and on eviction
+        let config = BufferPoolConfig {
+            num_frames: frames,
+            enable_background_flush: false,
+            data_directory: dir.to_string(),
+            ..BufferPoolConfig::default()
+        };
+        let pool = BufferPoolManager::with_disk_manager(config, Some(disk.clone()));
+
+        if is_new {
+            let frame = pool.new_page()?;
+            if frame.page_id() != HEADER_PAGE_ID {
+                return Err(DbError::Storage(format!(
+                    "Expected index file header at page {}, got page {}",
+                    HEADER_PAGE_ID,
+                    frame.page_id()
+                )));
+            }
+            frame
+                .frame()
+                .write_data_no_dirty()
+                .data_mut()
+                .copy_from_slice(&FileHeader::default().encode());
+            frame.frame().mark_dirty(0);
+            drop(frame);
+            pool.flush_page_by_id(HEADER_PAGE_ID)?;
+            pool.sync_disk()?;
+        }
+
+        Ok(Self {
+            pool,
+            disk,
+            wal: RwLock::new(None),
+            next_txn_id: AtomicU64::new(1),
+            trees_lock: Mutex::new(()),
+            scratch_dir: None,
+        })
+    }
+
+    /// Open a throw-away index file in a fresh temporary directory, removed
+    /// when the file is dropped
+    pub fn temporary() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "rustydb-index-scratch-{}-{}",
+            std::process::id(),
+            SCRATCH_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut file = Self::open(&dir.display().to_string(), 256)?;
+        file.scratch_dir = Some(dir);
+        Ok(file)
+    }
+
+    /// Log page changes to `wal` from now on
+    ///
+    /// Run recovery from the same log against the file before writing to it.
+    pub fn attach_wal(&self, wal: Arc<WALManager>) {
+        self.pool.set_wal(wal.clone());
+        *self.wal.write() = Some(wal);
+    }
+
+    /// Names of the trees in the file
+    pub fn tree_names(&self) -> Result<Vec<String>> {
+        let _trees = self.trees_lock.lock();
+        let mut names = Vec::new();
+        let mut next = self.header()?.first_tree;
+        while let Some(page_id) = next {
+            let meta = self.read(page_id, TreeMeta::decode)?;
+            names.push(meta.name);
+            next = meta.next;
+        }
+        Ok(names)
+    }
+
+    /// Write every changed page back and sync the file
+    pub fn flush(&self) -> Result<()> {
+        self.pool.flush_all()?;
+        self.pool.sync_disk()
+    }
+
+    /// Statistics of the pool caching the file's pages
+    pub fn buffer_stats(&self) -> BufferPoolStats {
+        self.pool.stats()
+    }
+
+    pub(super) fn pin(&self, page_id: PageId) -> Result<FrameGuard> {
+        self.pool.pin_page(page_id)
+    }
+
+    /// Decode a page under a momentary read latch
+    pub(super) fn read<T>(
+        &self,
+        page_id: PageId,
+        decode: impl Fn(&[u8]) -> Result<T>,
+    ) -> Result<T> {
+        let frame = self.pin(page_id)?;
+        let page = frame.read_data();
+        decode(page.data())
+    }
+
+    pub(super) fn header(&self) -> Result<FileHeader> {
+        self.read(HEADER_PAGE_ID, FileHeader::decode)
+    }
+
+    /// Find the meta page of tree `name`
+    pub(super) fn find_tree(&self, name: &str) -> Result<Option<PageId>> {
+        let _trees = self.trees_lock.lock();
+        self.lookup_tree(name)
+    }
+
+    // `find_tree` for a caller holding the trees lock
+    fn lookup_tree(&self, name: &str) -> Result<Option<PageId>> {
+        let mut next = self.header()?.first_tree;
+        while let Some(page_id) = next {
+            let meta = self.read(page_id, TreeMeta::decode)?;
+            if meta.name == name {
+                return Ok(Some(page_id));
+            }
+            next = meta.next;
+        }
+        Ok(None)
+    }
+
+    /// Add a tree to the front of the chain, its meta page going to
+    /// `meta_frame`, in one mini-transaction with `changes`
+    pub(super) fn link_tree(
+        &self,
+        meta_frame: &FrameGuard,
+        mut meta: TreeMeta,
+        changes: Vec<PageChange<'_>>,
+    ) -> Result<()> {
+        let _trees = self.trees_lock.lock();
+        if self.lookup_tree(&meta.name)?.is_some() {
+            return Err(DbError::AlreadyExists(format!(
+                "Index {} already exists",
+                meta.name
+            )));
+        }
+
+        let header_frame = self.pin(HEADER_PAGE_ID)?;
+        let mut header_page = header_frame.frame().write_data_no_dirty();
+        let mut header = FileHeader::decode(header_page.data())?;
+        meta.next = header.first_tree;
+        header.first_tree = Some(meta_frame.page_id());
+
+        let mut meta_page = meta_frame.frame().write_data_no_dirty();
+        let mut changes: Vec<PageChange> = changes;
+        changes.push(PageChange {
+            frame: meta_frame,
+            page: &mut meta_page,
+            image: meta.encode(),
+        });
+        changes.push(PageChange {
+            frame: &header_frame,
+            page: &mut header_page,
+            image: header.encode(),
+        });
+        self.write_pages(&mut changes)
+    }
+
+    /// Take tree `name` out of the chain, returning its meta page id and
+    /// contents; the caller frees its pages
+    pub(super) fn unlink_tree(&self, name: &str) -> Result<Option<(PageId, TreeMeta)>> {
+        let _trees = self.trees_lock.lock();
+        let mut previous: Option<PageId> = None;
+        let mut next = self.header()?.first_tree;
+        while let Some(page_id) = next {
+            let meta = self.read(page_id, TreeMeta::decode)?;
+            if meta.name != name {
+                previous = Some(page_id);
+                next = meta.next;
+                continue;
+            }
+
+            let frame = self.pin(previous.unwrap_or(HEADER_PAGE_ID))?;
+            let mut page = frame.frame().write_data_no_dirty();
+            let image = match previous {
+                None => {
+                    let mut header = FileHeader::decode(page.data())?;
+                    header.first_tree = meta.next;
+                    header.encode()
+                }
+                Some(_) => {
+                    let mut before = TreeMeta::decode(page.data())?;
+                    before.next = meta.next;
+                    before.encode()
+                }
+            };
+            self.write_pages(&mut [PageChange {
+                frame: &frame,
+                page: &mut page,
+                image,
+            }])?;
+            return Ok(Some((page_id, meta)));
+        }
+        Ok(None)
+    }
+
+    /// A page for a new node, pinned: a freed page if there is one,
+    /// otherwise a new one at the end of the file
+    pub(super) fn allocate(&self) -> Result<FrameGuard> {
+        let header_frame = self.pin(HEADER_PAGE_ID)?;
+        let mut header_page = header_frame.frame().write_data_no_dirty();
+        let mut header = FileHeader::decode(header_page.data())?;
+        let Some(page_id) = header.free_head else {
+            drop(header_page);
+            return self.pool.new_page();
+        };
+
+        let frame = self.pin(page_id)?;
+        header.free_head = node::decode_free_page(frame.read_data().data())?;
+        self.write_pages(&mut [PageChange {
+            frame: &header_frame,
+            page: &mut header_page,
+            image: header.encode(),
+        }])?;
+        Ok(frame)
+    }
+
+    /// Put a page no longer in use on the free list
+    pub(super) fn free(&self, page_id: PageId) -> Result<()> {
+        let header_frame = self.pin(HEADER_PAGE_ID)?;
+        let mut header_page = header_frame.frame().write_data_no_dirty();
+        let mut header = FileHeader::decode(header_page.data())?;
+        let frame = self.pin(page_id)?;
+        let mut page = frame.frame().write_data_no_dirty();
+
+        let next = header.free_head.replace(page_id);
+        self.write_pages(&mut [
+            PageChange {
+                frame: &frame,
+                page: &mut page,
+                image: node::free_page(next),
+            },
+            PageChange {
+                frame: &header_frame,
+                page: &mut header_page,
+                image: header.encode(),
+            },
+        ])
+    }
+
+    /// Change write-latched pages to new images as one mini-transaction
+    ///
+    /// Each image is logged as a full page update, then copied in and
+    /// stamped with the LSN of its record.
+    pub(super) fn write_pages(&self, changes: &mut [PageChange<'_>]) -> Result<()> {
+        let Some(wal) = self.wal.read().clone() else {
+            for change in changes.iter_mut() {
+                install(change.frame, change.page, &change.image, 0);
+            }
+            return Ok(());
+        };
+
+        let txn_id = self.next_txn_id.fetch_add(1, Ordering::Relaxed);
+        let mut last_lsn = wal.append_blocking(LogRecord::Begin {
+            txn_id,
+            timestamp: SystemTime::now(),
+        })?;
+        for change in changes.iter_mut() {
+            let lsn = wal.append_blocking(LogRecord::Update {
+                txn_id,
+                page_id: change.frame.page_id(),
+                offset: 0,
+                before_image: change.page.data().to_vec(),
+                after_image: change.image.clone(),
+                undo_next_lsn: Some(last_lsn),
+            })?;
+            install(change.frame, change.page, &change.image, lsn);
+            last_lsn = lsn;
+        }
+        wal.append_blocking(LogRecord::Commit {
+            txn_id,
+            timestamp: SystemTime::now(),
+        })?;
+        Ok(())
+    }
+
+    /// Write pages without logging them, for pages nothing refers to yet;
+    /// they are forced to disk before they are linked into a tree
+    ///
+    /// The pages are stamped with the latest LSN so that no earlier logged
+    /// change is replayed over them.
+    pub(super) fn write_unlogged(&self, frame: &FrameGuard, image: &[u8]) -> Result<()> {
+        let lsn = self
+            .wal
+            .read()
+            .as_ref()
+            .map_or(0, |wal| wal.current_lsn().saturating_sub(1));
+        let mut page = frame.frame().write_data_no_dirty();
+        install(frame, &mut page, image, lsn);
+        Ok(())
+    }
+
+    /// Force pages written by `write_unlogged` to disk
+    pub(super) fn force(&self, pages: &[PageId]) -> Result<()> {
+        for &page_id in pages {
+            self.pool.force_flush_page(page_id)?;
+        }
+        self.pool.sync_disk()
+    }
+
+    // The data file only grows as pages are allocated; a logged change to a
+    // page that never reached it is replayed into a new page
+    fn ensure_page(&self, page_id: PageId) -> Result<()> {
+        while self.disk.get_num_pages() as PageId <= page_id {
+            self.disk.allocate_page()?;
+        }
+        Ok(())
+    }
+}
+
+fn install(frame: &FrameGuard, page: &mut PageBuffer, image: &[u8], lsn: LSN) {
+    page.data_mut().copy_from_slice(image);
+    node::set_page_lsn(page.data_mut(), lsn);
+    // Under the latch, so the buffer pool reads a matching LSN and image
+    frame.frame().mark_dirty(lsn);
+}
+
+impl RecoverablePages for IndexFile {
+    fn apply_logged(&self, lsn: LSN, record: &LogRecord) -> Result<bool> {
+        let LogRecord::Update {
+            page_id,
+            after_image,
+            ..
+        } = record
+        else {
+            return Ok(false);
+        };
+
+        self.ensure_page(*page_id)?;
+        let frame = self.pin(*page_id)?;
+        let mut page = frame.frame().write_data_no_dirty();
+        if node::page_lsn(page.data()) >= lsn {
+            return Ok(false);
+        }
+        install(&frame, &mut page, after_image, lsn);
+        Ok(true)
+    }
+
+    fn flush_pages(&self) -> Result<()> {
+        self.flush()
+    }
+}
+
+impl Drop for IndexFile {
+    fn drop(&mut self) {
+        if let Some(dir) = self.scratch_dir.take() {
+            let _ = std::fs::remove_dir_all(dir);
+        } else {
+            let _ = self.flush();
+        }
+    }
+}
@@ -0,0 +1,807 @@
+// Disk-resident B+Trees
+//
+// A `DiskBTree` maps `IndexKey`s to `IndexValue`s (row ids) in the pages of
+// an `IndexFile`, so an index survives restarts and need not fit in memory.
+//
+// Each entry is stored as the order-preserving encoding of its key followed
+// by the big-endian value, which keeps entries unique while a key holds any
+// number of values. Nodes store the prefix their keys share once, and leaf
+// splits move up the shortest separator between the two halves.
+//
+// Concurrency follows Lehman and Yao's B-link tree: every node has a high
+// key and a link to its right sibling, so a split is complete for readers
+// as soon as the node and its new sibling are written, and the separator is
+// added to the parent afterwards. Readers copy one node at a time under a
+// short read latch and move right when their key is at or above the high
+// key; writers latch one node at a time (plus the sibling a split creates).
+// Deleting entries never merges nodes.
+
+mod file;
+mod node;
+
+pub use file::IndexFile;
+pub use node::MAX_KEY_SIZE;
+
+use crate::buffer::page_cache::PAGE_SIZE;
+use crate::common::PageId;
+use crate::error::{DbError, Result};
+use crate::index::{IndexKey, IndexValue};
+use file::PageChange;
+use node::{Node, TreeMeta};
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// Bytes of a page a bulk load fills, leaving room for later inserts
+const BULK_FILL: usize = PAGE_SIZE * 4 / 5;
+
+pub struct DiskBTree {
+    file: Arc<IndexFile>,
+    meta: PageId,
+}
+
+impl IndexFile {
+    /// Create an empty tree called `name`
+    pub fn create_tree(self: &Arc<Self>, name: &str) -> Result<DiskBTree> {
+        if name.len() > MAX_KEY_SIZE {
+            return Err(DbError::InvalidInput(format!(
+                "Index name {} is too long",
+                name
+            )));
+        }
+        if self.find_tree(name)?.is_some() {
+            return Err(DbError::AlreadyExists(format!(
+                "Index {} already exists",
+                name
+            )));
+        }
+
+        let meta_frame = self.allocate()?;
+        let root_frame = self.allocate()?;
+        let (meta_page, root) = (meta_frame.page_id(), root_frame.page_id());
+        let linked = {
+            let mut root_page = root_frame.frame().write_data_no_dirty();
+            let meta = TreeMeta {
+                name: name.to_string(),
+                root,
+                root_level: 0,
+                next: None,
+            };
+            let root_change = PageChange {
+                frame: &root_frame,
+                page: &mut root_page,
+                image: Node::leaf().encode()?,
+            };
+            self.link_tree(&meta_frame, meta, vec![root_change])
+        };
+        drop((meta_frame, root_frame));
+        if let Err(e) = linked {
+            self.free(root)?;
+            self.free(meta_page)?;
+            return Err(e);
+        }
+
+        Ok(DiskBTree {
+            file: self.clone(),
+            meta: meta_page,
+        })
+    }
+
+    pub fn open_tree(self: &Arc<Self>, name: &str) -> Result<Option<DiskBTree>> {
+        Ok(self.find_tree(name)?.map(|meta| DiskBTree {
+            file: self.clone(),
+            meta,
+        }))
+    }
+
+    /// Remove tree `name` and free its pages; `false` if there is none
+    ///
+    /// The tree must not be in use.
+    pub fn drop_tree(&self, name: &str) -> Result<bool> {
+        let Some((meta_page, meta)) = self.unlink_tree(name)? else {
+            return Ok(false);
+        };
+
+        // Level by level from the root, each from its leftmost node
+        let mut leftmost = Some(meta.root);
+        while let Some(first) = leftmost {
+            let mut next = Some(first);
+            leftmost = None;
+            while let Some(page_id) = next {
+                let node = self.read(page_id, Node::decode)?;
+                if page_id == first && !node.is_leaf() {
+                    leftmost = Some(node.children[0]);
+                }
+                next = node.right;
+                self.free(page_id)?;
+            }
+        }
+        self.free(meta_page)?;
+        Ok(true)
+    }
+}
+
+impl DiskBTree {
+    pub fn name(&self) -> Result<String> {
+        Ok(self.meta()?.name)
+    }
+
+    /// Number of levels, leaves included
+    pub fn height(&self) -> Result<usize> {
+        Ok(self.meta()?.root_level as usize + 1)
+    }
+
+    /// Add an entry; `false` if the key already maps to `value`
+    pub fn insert(&self, key: &IndexKey, value: IndexValue) -> Result<bool> {
+        let entry = entry(key, value)?;
+        let (mut page_id, mut path) = self.descend(&entry, 0)?;
+        // Inserting a separator, the child right of it
+        let mut child: Option<PageId> = None;
+        let mut key = entry;
+
+        loop {
+            let frame = self.file.pin(page_id)?;
+            let mut page = frame.frame().write_data_no_dirty();
+            let mut node = Node::decode(page.data())?;
+            if !node.covers(&key) {
+                page_id = right_of(&node, page_id)?;
+                continue;
+            }
+
+            match child {
+                None => {
+                    if !node.insert_key(key.clone()) {
+                        return Ok(false);
+                    }
+                }
+                Some(child) => node.insert_separator(key.clone(), child),
+            }
+            if node.fits() {
+                self.file.write_pages(&mut [PageChange {
+                    frame: &frame,
+                    page: &mut page,
+                    image: node.encode()?,
+                }])?;
+                return Ok(true);
+            }
+
+            let sibling = self.file.allocate()?;
+            let (separator, right) = node.split(sibling.page_id());
+            {
+                let mut sibling_page = sibling.frame().write_data_no_dirty();
+                self.file.write_pages(&mut [
+                    PageChange {
+                        frame: &sibling,
+                        page: &mut sibling_page,
+                        image: right.encode()?,
+                    },
+                    PageChange {
+                        frame: &frame,
+                        page: &mut page,
+                        image: node.encode()?,
+                    },
+                ])?;
+            }
+            drop(page);
+
+            // Add the separator one level up
+            key = separator;
+            child = Some(sibling.page_id());
+            page_id = match path.pop() {
+                Some(parent) => parent,
+                None => {
+                    if self.grow_root(node.level, &key, sibling.page_id())? {
+                        return Ok(true);
+                    }
+                    let (parent, upper) = self.descend(&key, node.level + 1)?;
+                    path = upper;
+                    parent
+                }
+            };
+        }
+    }
+
+    /// Remove an entry; `false` if the key does not map to `value`
+    pub fn delete(&self, key: &IndexKey, value: IndexValue) -> Result<bool> {
+        let entry = entry(key, value)?;
+        let (mut page_id, _) = self.descend(&entry, 0)?;
+        loop {
+            let frame = self.file.pin(page_id)?;
+            let mut page = frame.frame().write_data_no_dirty();
+            let mut node = Node::decode(page.data())?;
+            if !node.covers(&entry) {
+                page_id = right_of(&node, page_id)?;
+                continue;
+            }
+            if !node.remove_key(&entry) {
+                return Ok(false);
+            }
+            self.file.write_pages(&mut [PageChange {
+                frame: &frame,
+                page: &mut page,
+                image: node.encode()?,
+            }])?;
+            return Ok(true);
+        }
+    }
+
+    /// Values of `key`, in order
+    pub fn search(&self, key: &IndexKey) -> Result<Vec<IndexValue>> {
+        let mut values = Vec::new();
+        let (lower, upper) = key_bounds(Bound::Included(key), Bound::Included(key));
+        self.scan(lower, upper, |entry| {
+            values.push(entry_value(entry)?);
+            Ok(())
+        })?;
+        Ok(values)
+    }
+
+    /// Entries with keys between `lower` and `upper`, in key order
+    pub fn range(
+        &self,
+        lower: Bound<&IndexKey>,
+        upper: Bound<&IndexKey>,
+    ) -> Result<Vec<(IndexKey, IndexValue)>> {
+        let (lower, upper) = key_bounds(lower, upper);
+        self.entries(lower, upper)
+    }
+
+    /// Entries of composite keys whose leading columns equal `prefix` and
+    /// whose next column lies between `lower` and `upper`, in key order
+    pub fn prefix_range(
+        &self,
+        prefix: &[IndexKey],
+        lower: Bound<&IndexKey>,
+        upper: Bound<&IndexKey>,
+    ) -> Result<Vec<(IndexKey, IndexValue)>> {
+        // The keys starting with `prefix` share its encoding up to the
+        // terminator; what follows is the next column's tag or the
+        // terminator, both below 0xFF
+        let mut base = IndexKey::Composite(prefix.to_vec()).encode();
+        base.pop();
+        let bound = |key: &IndexKey, above: bool| {
+            let mut bound = base.clone();
+            key.encode_into(&mut bound);
+            if above {
+                bound.push(0xFF);
+            }
+            bound
+        };
+        let lower = match lower {
+            Bound::Included(key) => Bound::Included(bound(key, false)),
+            Bound::Excluded(key) => Bound::Included(bound(key, true)),
+            Bound::Unbounded => Bound::Included(base.clone()),
+        };
+        let upper = match upper {
+            Bound::Included(key) => Bound::Excluded(bound(key, true)),
+            Bound::Excluded(key) => Bound::Excluded(bound(key, false)),
+            Bound::Unbounded => Bound::Excluded([base.as_slice(), &[0xFF]].concat()),
+        };
+        self.entries(lower, upper)
+    }
+
+    /// Fill an empty tree with `entries`, building it bottom-up
+    ///
+    /// Nodes are written once each, filled to `BULK_FILL`, and only the root
+    /// is logged: the others are forced to disk before the root links them
+    /// in. The tree must not be written to meanwhile.
+    pub fn bulk_load(
+        &self,
+        entries: impl IntoIterator<Item = (IndexKey, IndexValue)>,
+    ) -> Result<()> {
+        let meta = self.meta()?;
+        if meta.root_level > 0 || !self.file.read(meta.root, Node::decode)?.keys.is_empty() {
+            return Err(DbError::InvalidState(format!(
+                "Index {} is not empty",
+                meta.name
+            )));
+        }
+
+        let mut keys = entries
+            .into_iter()
+            .map(|(key, value)| entry(&key, value))
+            .collect::<Result<Vec<_>>>()?;
+        keys.sort_unstable();
+        keys.dedup();
+
+        // Cut the sorted entries into leaves
+        let mut nodes = Vec::new();
+        let mut leaf = Node::leaf();
+        let mut key_bytes = 0;
+        for key in keys {
+            if !leaf.keys.is_empty() && leaf.size_after_push(&key, key_bytes) > BULK_FILL {
+                let last = &leaf.keys[leaf.keys.len() - 1];
+                leaf.high_key = Some(node::shortest_separator(last, &key));
+                nodes.push(std::mem::replace(&mut leaf, Node::leaf()));
+                key_bytes = 0;
+            }
+            key_bytes += key.len();
+            leaf.keys.push(key);
+        }
+        nodes.push(leaf);
+
+        // Write each level and build the one above it, until one node is left
+        let mut written = Vec::new();
+        let mut level = 0;
+        while nodes.len() > 1 {
+            let pages = (0..nodes.len())
+                .map(|_| self.file.allocate().map(|frame| frame.page_id()))
+                .collect::<Result<Vec<_>>>()?;
+            for (i, node) in nodes.iter_mut().enumerate() {
+                node.right = pages.get(i + 1).copied();
+                let frame = self.file.pin(pages[i])?;
+                self.file.write_unlogged(&frame, &node.encode()?)?;
+            }
+            written.extend_from_slice(&pages);
+
+            level += 1;
+            let mut parents = Vec::new();
+            let mut parent = Node::internal(level, pages[0]);
+            let mut key_bytes = 0;
+            for (i, &child) in pages.iter().enumerate().skip(1) {
+                let separator = nodes[i - 1].high_key.clone().unwrap_or_default();
+                if !parent.keys.is_empty()
+                    && parent.size_after_push(&separator, key_bytes) > BULK_FILL
+                {
+                    // The separator moves up a level instead
+                    parent.high_key = Some(separator);
+                    parents.push(std::mem::replace(&mut parent, Node::internal(level, child)));
+                    key_bytes = 0;
+                    continue;
+                }
+                key_bytes += separator.len();
+                parent.keys.push(separator);
+                parent.children.push(child);
+            }
+            parents.push(parent);
+            nodes = parents;
+        }
+        self.file.force(&written)?;
+
+        // The top node replaces the empty root
+        let root = nodes.remove(0);
+        let meta_frame = self.file.pin(self.meta)?;
+        let mut meta_page = meta_frame.frame().write_data_no_dirty();
+        let mut meta = TreeMeta::decode(meta_page.data())?;
+        let root_frame = self.file.pin(meta.root)?;
+        let mut root_page = root_frame.frame().write_data_no_dirty();
+        meta.root_level = level;
+        self.file.write_pages(&mut [
+            PageChange {
+                frame: &root_frame,
+                page: &mut root_page,
+                image: root.encode()?,
+            },
+            PageChange {
+                frame: &meta_frame,
+                page: &mut meta_page,
+                image: meta.encode(),
+            },
+        ])
+    }
+
+    fn meta(&self) -> Result<TreeMeta> {
+        self.file.read(self.meta, TreeMeta::decode)
+    }
+
+    // Walk from the root to the node at `level` whose range holds `key`;
+    // returns it and the nodes passed on the way down, nearest last
+    fn descend(&self, key: &[u8], level: u16) -> Result<(PageId, Vec<PageId>)> {
+        let meta = self.meta()?;
+        if meta.root_level < level {
+            return Err(DbError::Internal(format!(
+                "Index {} has no level {}",
+                meta.name, level
+            )));
+        }
+
+        let mut page_id = meta.root;
+        let mut path = Vec::new();
+        loop {
+            let node = self.file.read(page_id, Node::decode)?;
+            if !node.covers(key) {
+                page_id = right_of(&node, page_id)?;
+            } else if node.level > level {
+                path.push(page_id);
+                page_id = node.child_for(key);
+            } else {
+                return Ok((page_id, path));
+            }
+        }
+    }
+
+    // Put a new root over a root at `level` that split; `false` if another
+    // split already did, in which case the separator goes into a node of
+    // the level above
+    fn grow_root(&self, level: u16, separator: &[u8], child: PageId) -> Result<bool> {
+        let meta_frame = self.file.pin(self.meta)?;
+        let mut meta_page = meta_frame.frame().write_data_no_dirty();
+        let mut meta = TreeMeta::decode(meta_page.data())?;
+        if meta.root_level > level {
+            return Ok(false);
+        }
+
+        let root_frame = self.file.allocate()?;
+        let mut root_page = root_frame.frame().write_data_no_dirty();
+        let mut root = Node::internal(level + 1, meta.root);
+        root.insert_separator(separator.to_vec(), child);
+        meta.root = root_frame.page_id();
+        meta.root_level = level + 1;
+        self.file.write_pages(&mut [
+            PageChange {
+                frame: &root_frame,
+                page: &mut root_page,
+                image: root.encode()?,
+            },
+            PageChange {
+                frame: &meta_frame,
+                page: &mut meta_page,
+                image: meta.encode(),
+            },
+        ])?;
+        Ok(true)
+    }
+
+    // Decoded entries between two encoded bounds
+    fn entries(
+        &self,
+        lower: Bound<Vec<u8>>,
+        upper: Bound<Vec<u8>>,
+    ) -> Result<Vec<(IndexKey, IndexValue)>> {
+        let mut entries = Vec::new();
+        self.scan(lower, upper, |entry| {
+            let (key, _) = IndexKey::decode(entry)?;
+            entries.push((key, entry_value(entry)?));
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    // Visit the entries between two bounds on their stored form in order
+    fn scan(
+        &self,
+        lower: Bound<Vec<u8>>,
+        upper: Bound<Vec<u8>>,
+        mut visit: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let above_lower = |entry: &[u8]| match &lower {
+            Bound::Included(bound) => entry >= bound.as_slice(),
+            Bound::Excluded(bound) => entry > bound.as_slice(),
+            Bound::Unbounded => true,
+        };
+        let below_upper = |entry: &[u8]| match &upper {
+            Bound::Included(bound) => entry <= bound.as_slice(),
+            Bound::Excluded(bound) => entry < bound.as_slice(),
+            Bound::Unbounded => true,
+        };
+
+        let start = match &lower {
+            Bound::Included(bound) | Bound::Excluded(bound) => bound.clone(),
+            Bound::Unbounded => Vec::new(),
+        };
+        let (mut page_id, _) = self.descend(&start, 0)?;
+        loop {
+            let node = self.file.read(page_id, Node::decode)?;
+            for entry in node.keys.iter().filter(|entry| above_lower(entry)) {
+                if !below_upper(entry) {
+                    return Ok(());
+                }
+                visit(entry)?;
+            }
+            match (node.right, &node.high_key) {
+                (Some(right), Some(high)) if below_upper(high) => page_id = right,
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+// The stored form of an entry: the encoded key, then the value
+fn entry(key: &IndexKey, value: IndexValue) -> Result<Vec<u8>> {
+    let mut entry = key.encode();
+    if entry.len() > MAX_KEY_SIZE {
+        return Err(DbError::InvalidInput(format!(
+            "Index key of {} bytes exceeds the maximum of {}",
+            entry.len(),
+            MAX_KEY_SIZE
+        )));
+    }
+    entry.extend_from_slice(&value.to_be_bytes());
+    Ok(entry)
+}
+
+fn entry_value(entry: &[u8]) -> Result<IndexValue> {
+    let value = entry
+        .len()
+        .checked_sub(8)
+        .and_then(|start| entry[start..].try_into().ok())
+        .ok_or_else(|| DbError::Corruption("Index entry is truncated".to_string()))?;
+    Ok(IndexValue::from_be_bytes(value))
+}
+
+// Bounds on the stored form of entries for bounds on their keys; the
+// entries of a key run from its bare encoding to the encoding followed by
+// the largest value
+fn key_bounds(
+    lower: Bound<&IndexKey>,
+    upper: Bound<&IndexKey>,
+) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    let lower = match lower {
+        Bound::Included(key) => Bound::Included(key.encode()),
+        Bound::Excluded(key) => Bound::Excluded(with_max_value(key)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let upper = match upper {
+        Bound::Included(key) => Bound::Included(with_max_value(key)),
+        Bound::Excluded(key) => Bound::Excluded(key.encode()),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    (lower, upper)
+}
+
+// Above every entry of `key`
+fn with_max_value(key: &IndexKey) -> Vec<u8> {
+    let mut bound = key.encode();
+    bound.extend_from_slice(&IndexValue::MAX.to_be_bytes());
+    bound
+}
+
+fn right_of(node: &Node, page_id: PageId) -> Result<PageId> {
+    node.right.ok_or_else(|| {
+        DbError::Corruption(format!(
+            "Index node {} has a high key but no right sibling",
+            page_id
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::recovery::{ARIESRecoveryManager, RecoveryConfig};
+    use crate::transaction::wal::{WALConfig, WALManager};
+    use std::path::PathBuf;
+
+    fn temporary() -> Result<Arc<IndexFile>> {
+        Ok(Arc::new(IndexFile::temporary()?))
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rustydb-disk-btree-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn name(i: u64) -> IndexKey {
+        IndexKey::String(format!("customer-{:06}@example.com", i))
+    }
+
+    #[test]
+    fn test_insert_search_and_delete() -> Result<()> {
+        let file = temporary()?;
+        let tree = file.create_tree("by_id")?;
+        assert!(tree.insert(&IndexKey::Integer(2), 20)?);
+        assert!(tree.insert(&IndexKey::Integer(1), 10)?);
+        assert!(tree.insert(&IndexKey::Integer(2), 21)?);
+        assert!(!tree.insert(&IndexKey::Integer(2), 20)?);
+
+        assert_eq!(tree.search(&IndexKey::Integer(2))?, vec![20, 21]);
+        assert_eq!(
+            tree.search(&IndexKey::Integer(3))?,
+            Vec::<IndexValue>::new()
+        );
+
+        assert!(tree.delete(&IndexKey::Integer(2), 20)?);
+        assert!(!tree.delete(&IndexKey::Integer(2), 20)?);
+        assert_eq!(tree.search(&IndexKey::Integer(2))?, vec![21]);
+
+        assert!(file.create_tree("by_id").is_err());
+        assert!(file.drop_tree("by_id")?);
+        assert!(file.open_tree("by_id")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_splits_keep_every_key_reachable() -> Result<()> {
+        let file = temporary()?;
+        let tree = file.create_tree("by_name")?;
+        // Inserted out of order, so splits happen all over the tree
+        for i in 0..5000u64 {
+            let i = (i * 7919) % 5000;
+            tree.insert(&name(i), i)?;
+        }
+        assert!(tree.height()? >= 3);
+
+        for i in (0..5000).step_by(97) {
+            assert_eq!(tree.search(&name(i))?, vec![i]);
+        }
+        let range = tree.range(Bound::Excluded(&name(100)), Bound::Included(&name(199)))?;
+        let expected: Vec<_> = (101..200).map(|i| (name(i), i)).collect();
+        assert_eq!(range, expected);
+        assert_eq!(tree.range(Bound::Unbounded, Bound::Unbounded)?.len(), 5000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_composite_keys_match_by_prefix() -> Result<()> {
+        let file = temporary()?;
+        let tree = file.create_tree("by_city_and_name")?;
+        let key = |city: i64, name: &str| {
+            IndexKey::Composite(vec![
+                IndexKey::Integer(city),
+                IndexKey::String(name.to_string()),
+            ])
+        };
+        for (i, (city, name)) in [(1, "b"), (2, "a"), (1, "a"), (1, "c")].iter().enumerate() {
+            tree.insert(&key(*city, name), i as IndexValue)?;
+        }
+
+        // Every key whose first column is 1
+        let city = |c: i64| IndexKey::Composite(vec![IndexKey::Integer(c)]);
+        let values: Vec<_> = tree
+            .range(Bound::Included(&city(1)), Bound::Excluded(&city(2)))?
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(values, vec![2, 0, 3]);
+
+        // Ranges on the column after an equal prefix
+        let values = |prefix: &[IndexKey],
+                      lower: Bound<&IndexKey>,
+                      upper: Bound<&IndexKey>|
+         -> Result<Vec<IndexValue>> {
+            Ok(tree
+                .prefix_range(prefix, lower, upper)?
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect())
+        };
+        let (a, b) = (IndexKey::String("a".into()), IndexKey::String("b".into()));
+        let one = [IndexKey::Integer(1)];
+        assert_eq!(
+            values(&one, Bound::Excluded(&a), Bound::Unbounded)?,
+            vec![0, 3]
+        );
+        assert_eq!(
+            values(&one, Bound::Included(&b), Bound::Included(&b))?,
+            vec![0]
+        );
+        assert_eq!(
+            values(&one, Bound::Unbounded, Bound::Excluded(&b))?,
+            vec![2]
+        );
+        assert_eq!(
+            values(
+                &[],
+                Bound::Included(&IndexKey::Integer(2)),
+                Bound::Unbounded
+            )?,
+            vec![1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_load_builds_a_full_tree() -> Result<()> {
+        let file = temporary()?;
+        let tree = file.create_tree("loaded")?;
+        tree.bulk_load((0..20_000).rev().map(|i| (name(i), i)))?;
+        assert!(tree.height()? >= 2);
+        assert!(tree.bulk_load([(name(0), 0)]).is_err());
+
+        assert_eq!(tree.search(&name(12_345))?, vec![12_345]);
+        let range = tree.range(Bound::Included(&name(500)), Bound::Excluded(&name(510)))?;
+        assert_eq!(range.len(), 10);
+
+        // The tree takes inserts after loading
+        tree.insert(&name(20_000), 20_000)?;
+        tree.insert(&name(500), 1)?;
+        assert_eq!(tree.search(&name(500))?, vec![1, 500]);
+        assert_eq!(tree.search(&name(20_000))?, vec![20_000]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_oversized_keys_are_rejected() -> Result<()> {
+        let file = temporary()?;
+        let tree = file.create_tree("big")?;
+        let key = IndexKey::Binary(vec![7; MAX_KEY_SIZE + 1]);
+        assert!(matches!(
+            tree.insert(&key, 1),
+            Err(DbError::InvalidInput(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_trees_survive_reopening() -> Result<()> {
+        let dir = test_dir("reopen");
+        let path = dir.display().to_string();
+        {
+            let file = Arc::new(IndexFile::open(&path, 64)?);
+            let tree = file.create_tree("kept")?;
+            for i in 0..2000 {
+                tree.insert(&IndexKey::Integer(i), i as IndexValue)?;
+            }
+            file.create_tree("dropped")?
+                .insert(&IndexKey::Integer(1), 1)?;
+            assert!(file.drop_tree("dropped")?);
+            file.flush()?;
+        }
+
+        let file = Arc::new(IndexFile::open(&path, 64)?);
+        assert_eq!(file.tree_names()?, vec!["kept".to_string()]);
+        let tree = file.open_tree("kept")?.unwrap();
+        assert_eq!(tree.search(&IndexKey::Integer(1234))?, vec![1234]);
+        assert_eq!(tree.range(Bound::Unbounded, Bound::Unbounded)?.len(), 2000);
+        drop((tree, file));
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_logged_changes_are_recovered_after_a_crash() -> Result<()> {
+        let dir = test_dir("recovery");
+        let path = dir.join("pages").display().to_string();
+        let config = WALConfig {
+            enable_group_commit: false,
+            ..WALConfig::default()
+        };
+        {
+            let file = Arc::new(IndexFile::open(&path, 64)?);
+            file.attach_wal(Arc::new(WALManager::new(dir.join("wal"), config.clone())?));
+            let tree = file.create_tree("logged")?;
+            for i in 0..3000 {
+                tree.insert(&name(i), i)?;
+            }
+            // Crash: the changed pages are never written back
+            std::mem::forget(tree);
+            std::mem::forget(file);
+        }
+
+        let file = Arc::new(IndexFile::open(&path, 64)?);
+        let wal = Arc::new(WALManager::new(dir.join("wal"), config)?);
+        ARIESRecoveryManager::new(wal.clone(), RecoveryConfig::default())
+            .with_pages(file.clone())
+            .recover()
+            .await?;
+        file.attach_wal(wal);
+
+        let tree = file.open_tree("logged")?.unwrap();
+        assert_eq!(tree.range(Bound::Unbounded, Bound::Unbounded)?.len(), 3000);
+        assert_eq!(tree.search(&name(2999))?, vec![2999]);
+        tree.insert(&name(3000), 3000)?;
+        drop((tree, file));
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_inserts() -> Result<()> {
+        let file = temporary()?;
+        let tree = Arc::new(file.create_tree("shared")?);
+        let threads: Vec<_> = (0..4u64)
+            .map(|t| {
+                let tree = tree.clone();
+                std::thread::spawn(move || -> Result<()> {
+                    for i in 0..1000 {
+                        let i = i * 4 + t;
+                        tree.insert(&name(i), i)?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().expect("insert thread panicked")?;
+        }
+
+        let entries = tree.range(Bound::Unbounded, Bound::Unbounded)?;
+        let expected: Vec<_> = (0..4000).map(|i| (name(i), i)).collect();
+        assert_eq!(entries, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,526 @@
+// Disk B+Tree page formats
+//
+// Every page starts with the LSN of its last change (bytes 0..8) and a kind
+// byte. Node pages continue with
+//
+//   level u16 | key count u16 | right link u64 | prefix length u16 |
+//   high key length u16 | leftmost child u64
+//
+// followed by the prefix shared by all keys of the node, the high key, and
+// one entry per key: the key's suffix after the prefix (u16 length, bytes)
+// and, in internal nodes, the child right of the key. Numbers are
+// big-endian; a missing page is u64::MAX and a missing high key u16::MAX.
+
+use crate::buffer::page_cache::PAGE_SIZE;
+use crate::common::PageId;
+use crate::error::{DbError, Result};
+use crate::transaction::wal::LSN;
+
+// Allocated but never written (a fresh page reads as zeroes here)
+const KIND_UNUSED: u8 = 0;
+const KIND_LEAF: u8 = 1;
+const KIND_INTERNAL: u8 = 2;
+const KIND_TREE: u8 = 3;
+const KIND_HEADER: u8 = 4;
+const KIND_FREE: u8 = 5;
+
+const NO_PAGE: u64 = u64::MAX;
+const NO_HIGH_KEY: u16 = u16::MAX;
+const NODE_HEADER_SIZE: usize = 33;
+
+/// Longest encoded key a tree accepts
+///
+/// Keeps nodes able to hold a few keys, so the halves of a split node
+/// always fit in a page.
+pub const MAX_KEY_SIZE: usize = 512;
+
+/// LSN of the last logged change to a page
+pub(super) fn page_lsn(page: &[u8]) -> LSN {
+    if page[8] == KIND_UNUSED {
+        return 0;
+    }
+    u64::from_be_bytes(page[..8].try_into().unwrap_or_default())
+}
+
+pub(super) fn set_page_lsn(page: &mut [u8], lsn: LSN) {
+    page[..8].copy_from_slice(&lsn.to_be_bytes());
+}
+
+/// A B-link tree node
+///
+/// Keys of a node are all below its high key; a node splits by moving its
+/// upper half to a new right sibling, so a reader that finds its key at or
+/// above the high key follows the right link.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Node {
+    /// 0 for leaves
+    pub level: u16,
+    pub keys: Vec<Vec<u8>>,
+    /// Internal nodes: the child left of the first key, then the child
+    /// right of each key
+    pub children: Vec<PageId>,
+    pub right: Option<PageId>,
+    /// `None` on the rightmost node of a level
+    pub high_key: Option<Vec<u8>>,
+}
+
+impl Node {
+    pub fn leaf() -> Self {
+        Self {
+            level: 0,
+            keys: Vec::new(),
+            children: Vec::new(),
+            right: None,
+            high_key: None,
+        }
+    }
+
+    /// An internal node whose only child is `leftmost`
+    pub fn internal(level: u16, leftmost: PageId) -> Self {
+        Self {
+            level,
+            keys: Vec::new(),
+            children: vec![leftmost],
+            right: None,
+            high_key: None,
+        }
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.level == 0
+    }
+
+    /// Whether `key` belongs in this node rather than one to its right
+    pub fn covers(&self, key: &[u8]) -> bool {
+        match &self.high_key {
+            Some(high) => key < high.as_slice(),
+            None => true,
+        }
+    }
+
+    /// Child of an internal node whose range holds `key`
+    pub fn child_for(&self, key: &[u8]) -> PageId {
+        let position = self.keys.partition_point(|k| k.as_slice() <= key);
+        self.children[position]
+    }
+
+    /// Add a leaf key; `false` if it is already there
+    pub fn insert_key(&mut self, key: Vec<u8>) -> bool {
+        match self.keys.binary_search(&key) {
+            Ok(_) => false,
+            Err(position) => {
+                self.keys.insert(position, key);
+                true
+            }
+        }
+    }
+
+    /// Add a separator to an internal node, with the child right of it
+    pub fn insert_separator(&mut self, key: Vec<u8>, child: PageId) {
+        let position = self.keys.partition_point(|k| *k < key);
+        self.keys.insert(position, key);
+        self.children.insert(position + 1, child);
+    }
+
+    /// Remove a leaf key; `false` if it is not there
+    pub fn remove_key(&mut self, key: &[u8]) -> bool {
+        match self.keys.binary_search_by(|k| k.as_slice().cmp(key)) {
+            Ok(position) => {
+                self.keys.remove(position);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn prefix_len(&self) -> usize {
+        match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => common_prefix(first, last),
+            _ => 0,
+        }
+    }
+
+    fn entry_overhead(&self) -> usize {
+        if self.is_leaf() {
+            2
+        } else {
+            2 + 8
+        }
+    }
+
+    /// Bytes the node takes in a page
+    pub fn encoded_size(&self) -> usize {
+        let prefix = self.prefix_len();
+        let keys: usize = self.keys.iter().map(|k| k.len() - prefix).sum();
+        NODE_HEADER_SIZE
+            + prefix
+            + self.high_key.as_ref().map_or(0, |h| h.len())
+            + keys
+            + self.keys.len() * self.entry_overhead()
+    }
+
+    pub fn fits(&self) -> bool {
+        self.encoded_size() <= PAGE_SIZE
+    }
+
+    /// `encoded_size` once `key` is appended, given the total length of the
+    /// keys so far; cheap enough to fill nodes key by key
+    pub fn size_after_push(&self, key: &[u8], key_bytes: usize) -> usize {
+        let count = self.keys.len() + 1;
+        let prefix = self
+            .keys
+            .first()
+            .map_or(key.len(), |first| common_prefix(first, key));
+        NODE_HEADER_SIZE
+            + prefix
+            + self.high_key.as_ref().map_or(0, |h| h.len())
+            + key_bytes
+            + key.len()
+            - count * prefix
+            + count * self.entry_overhead()
+    }
+
+    /// Move the upper half of the keys (by size) into a new node, which goes
+    /// to page `right_page` right of this one; returns the separator between
+    /// the two and the new node
+    ///
+    /// A leaf is separated by the shortest prefix of the new node's first
+    /// key that still sorts above this node's last; an internal node moves
+    /// its middle key up.
+    pub fn split(&mut self, right_page: PageId) -> (Vec<u8>, Node) {
+        let overhead = self.entry_overhead();
+        let total: usize = self.keys.iter().map(|k| k.len() + overhead).sum();
+        let mut position = 0;
+        let mut left = 0;
+        while position < self.keys.len() && left < total / 2 {
+            left += self.keys[position].len() + overhead;
+            position += 1;
+        }
+        let position = position.clamp(1, self.keys.len() - 1);
+
+        let mut right = Node {
+            level: self.level,
+            keys: self.keys.split_off(position),
+            children: Vec::new(),
+            right: self.right.take(),
+            high_key: self.high_key.take(),
+        };
+        let separator = if self.is_leaf() {
+            shortest_separator(&self.keys[self.keys.len() - 1], &right.keys[0])
+        } else {
+            right.children = self.children.split_off(position + 1);
+            right.keys.remove(0)
+        };
+        self.right = Some(right_page);
+        self.high_key = Some(separator.clone());
+        (separator, right)
+    }
+
+    pub fn decode(page: &[u8]) -> Result<Self> {
+        let kind = page[8];
+        if kind != KIND_LEAF && kind != KIND_INTERNAL {
+            return Err(DbError::Corruption(format!(
+                "Expected an index node, found page kind {}",
+                kind
+            )));
+        }
+        let mut reader = Reader::new(page, 9);
+        let level = reader.u16()?;
+        let count = reader.u16()? as usize;
+        let right = reader.page()?;
+        let prefix_len = reader.u16()? as usize;
+        let high_len = reader.u16()?;
+        let leftmost = reader.u64()?;
+        let prefix = reader.bytes(prefix_len)?.to_vec();
+        let high_key = match high_len {
+            NO_HIGH_KEY => None,
+            len => Some(reader.bytes(len as usize)?.to_vec()),
+        };
+
+        let mut node = Node {
+            level,
+            keys: Vec::with_capacity(count),
+            children: Vec::new(),
+            right,
+            high_key,
+        };
+        if kind == KIND_INTERNAL {
+            node.children.push(leftmost);
+        }
+        for _ in 0..count {
+            let len = reader.u16()? as usize;
+            let mut key = prefix.clone();
+            key.extend_from_slice(reader.bytes(len)?);
+            node.keys.push(key);
+            if kind == KIND_INTERNAL {
+                node.children.push(reader.u64()?);
+            }
+        }
+        Ok(node)
+    }
+
+    /// The page image of the node; its LSN is left zero
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        if !self.fits() {
+            return Err(DbError::Internal(format!(
+                "Index node of {} bytes does not fit in a page",
+                self.encoded_size()
+            )));
+        }
+        let kind = if self.is_leaf() {
+            KIND_LEAF
+        } else {
+            KIND_INTERNAL
+        };
+        let prefix = self.prefix_len();
+        let mut writer = Writer::new(kind);
+        writer.u16(self.level);
+        writer.u16(self.keys.len() as u16);
+        writer.page(self.right);
+        writer.u16(prefix as u16);
+        writer.u16(
+            self.high_key
+                .as_ref()
+                .map_or(NO_HIGH_KEY, |h| h.len() as u16),
+        );
+        writer.u64(self.children.first().copied().unwrap_or(NO_PAGE));
+        if let Some(first) = self.keys.first() {
+            writer.bytes(&first[..prefix]);
+        }
+        if let Some(high_key) = &self.high_key {
+            writer.bytes(high_key);
+        }
+        for (i, key) in self.keys.iter().enumerate() {
+            writer.u16((key.len() - prefix) as u16);
+            writer.bytes(&key[prefix..]);
+            if !self.is_leaf() {
+                writer.u64(self.children[i + 1]);
+            }
+        }
+        Ok(writer.finish())
+    }
+}
+
+/// Meta page of a tree: its name and root, linked to the next tree's
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct TreeMeta {
+    pub name: String,
+    pub root: PageId,
+    pub root_level: u16,
+    pub next: Option<PageId>,
+}
+
+impl TreeMeta {
+    pub fn decode(page: &[u8]) -> Result<Self> {
+        expect_kind(page, KIND_TREE, "an index tree")?;
+        let mut reader = Reader::new(page, 9);
+        let root = reader.u64()?;
+        let root_level = reader.u16()?;
+        let next = reader.page()?;
+        let name_len = reader.u16()? as usize;
+        let name = String::from_utf8(reader.bytes(name_len)?.to_vec())
+            .map_err(|_| DbError::Corruption("Index tree name is not UTF-8".to_string()))?;
+        Ok(Self {
+            name,
+            root,
+            root_level,
+            next,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = Writer::new(KIND_TREE);
+        writer.u64(self.root);
+        writer.u16(self.root_level);
+        writer.page(self.next);
+        writer.u16(self.name.len() as u16);
+        writer.bytes(self.name.as_bytes());
+        writer.finish()
+    }
+}
+
+/// Page 0: heads the free page list and the chain of tree meta pages
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct FileHeader {
+    pub free_head: Option<PageId>,
+    pub first_tree: Option<PageId>,
+}
+
+impl FileHeader {
+    pub fn decode(page: &[u8]) -> Result<Self> {
+        expect_kind(page, KIND_HEADER, "the index file header")?;
+        let mut reader = Reader::new(page, 9);
+        Ok(Self {
+            free_head: reader.page()?,
+            first_tree: reader.page()?,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = Writer::new(KIND_HEADER);
+        writer.page(self.free_head);
+        writer.page(self.first_tree);
+        writer.finish()
+    }
+}
+
+/// A page on the free list, linking to the next free page
+pub(super) fn free_page(next: Option<PageId>) -> Vec<u8> {
+    let mut writer = Writer::new(KIND_FREE);
+    writer.page(next);
+    writer.finish()
+}
+
+pub(super) fn decode_free_page(page: &[u8]) -> Result<Option<PageId>> {
+    expect_kind(page, KIND_FREE, "a free index page")?;
+    Reader::new(page, 9).page()
+}
+
+fn expect_kind(page: &[u8], kind: u8, what: &str) -> Result<()> {
+    if page[8] != kind {
+        return Err(DbError::Corruption(format!(
+            "Expected {}, found page kind {}",
+            what, page[8]
+        )));
+    }
+    Ok(())
+}
+
+/// The shortest key above `left` and at most `right`, for keys that are
+/// not prefixes of one another
+pub(super) fn shortest_separator(left: &[u8], right: &[u8]) -> Vec<u8> {
+    right[..common_prefix(left, right) + 1].to_vec()
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+struct Reader<'a> {
+    page: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(page: &'a [u8], pos: usize) -> Self {
+        Self { page, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .page
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| DbError::Corruption("Index page is truncated".to_string()))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn page(&mut self) -> Result<Option<PageId>> {
+        Ok(Some(self.u64()?).filter(|&page| page != NO_PAGE))
+    }
+}
+
+struct Writer {
+    page: Vec<u8>,
+}
+
+impl Writer {
+    fn new(kind: u8) -> Self {
+        let mut page = Vec::with_capacity(PAGE_SIZE);
+        page.extend_from_slice(&[0; 8]);
+        page.push(kind);
+        Self { page }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.page.extend_from_slice(bytes);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn page(&mut self, page: Option<PageId>) {
+        self.u64(page.unwrap_or(NO_PAGE));
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.page.resize(PAGE_SIZE, 0);
+        self.page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u32) -> Vec<u8> {
+        format!("shared-prefix-{:08}", i).into_bytes()
+    }
+
+    #[test]
+    fn test_node_round_trip_with_prefix_compression() -> Result<()> {
+        let mut leaf = Node::leaf();
+        for i in 0..50 {
+            assert!(leaf.insert_key(key(i)));
+        }
+        assert!(!leaf.insert_key(key(7)));
+        leaf.right = Some(9);
+        leaf.high_key = Some(key(60));
+
+        // The shared prefix is stored once
+        let raw: usize = leaf.keys.iter().map(|k| k.len() + 2).sum();
+        assert!(leaf.encoded_size() < raw);
+        assert_eq!(Node::decode(&leaf.encode()?)?, leaf);
+
+        let mut internal = Node::internal(1, 100);
+        internal.insert_separator(key(20), 102);
+        internal.insert_separator(key(10), 101);
+        assert_eq!(internal.children, vec![100, 101, 102]);
+        assert_eq!(internal.child_for(&key(5)), 100);
+        assert_eq!(internal.child_for(&key(10)), 101);
+        assert_eq!(internal.child_for(&key(25)), 102);
+        assert_eq!(Node::decode(&internal.encode()?)?, internal);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_links_the_halves() {
+        let mut leaf = Node::leaf();
+        for i in 0..100 {
+            leaf.insert_key(key(i));
+        }
+        let (separator, right) = leaf.split(7);
+        assert_eq!(leaf.right, Some(7));
+        assert_eq!(leaf.high_key.as_ref(), Some(&separator));
+        assert!(leaf.keys.last().is_some_and(|last| *last < separator));
+        assert!(right.keys[0] >= separator);
+        // The separator is as short as it can be
+        assert!(separator.len() < right.keys[0].len());
+        assert_eq!(leaf.keys.len() + right.keys.len(), 100);
+
+        let mut internal = Node::internal(1, 0);
+        for i in 0..10 {
+            internal.insert_separator(key(i), i as PageId + 1);
+        }
+        let (separator, right) = internal.split(50);
+        assert_eq!(right.children[0], internal.children.len() as PageId);
+        assert_eq!(internal.keys.len() + right.keys.len() + 1, 10);
+        assert!(!internal.keys.contains(&separator) && !right.keys.contains(&separator));
+    }
+}
@@ -0,0 +1,814 @@
+// HNSW Vector Indexes
+//
+// A hierarchical navigable small world graph answers approximate nearest
+// neighbour queries over the VECTOR column of a table. Every row is a node
+// on level 0 and on a random number of levels above it, fewer nodes the
+// higher the level; a search descends greedily from the single entry point
+// on the top level and ends with a beam search of width `ef` on level 0.
+//
+// The graph lives in memory and is persisted as the entries of the index's
+// `DiskBTree`: one entry per node, keyed `(rid, -1)` with the node's level,
+// and one per link, keyed `(rid, level)` with the neighbour's row id. The
+// vectors themselves are read back from the heap when the index is loaded.
+// Every change to the graph is a list of `GraphChange`s, each one entry of
+// the tree, which can be undone if writing them fails.
+
+use crate::error::{DbError, Result};
+use crate::index::IndexKey;
+use crate::ml::simd_ops::{simd_dot_product, simd_euclidean_distance};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+
+/// Beam width of a search unless the session sets `hnsw.ef_search`
+pub const DEFAULT_EF_SEARCH: usize = 40;
+
+// Nodes are never put above this level
+const MAX_LEVEL: usize = 16;
+
+/// How the distance between two vectors is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VectorMetric {
+    L2,
+    Cosine,
+    // Ordered by descending inner product, i.e. ascending negated product
+    InnerProduct,
+}
+
+impl VectorMetric {
+    /// Metric of a `CREATE INDEX ... USING hnsw` operator class
+    pub fn from_operator_class(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "vector_l2_ops" => Some(VectorMetric::L2),
+            "vector_cosine_ops" => Some(VectorMetric::Cosine),
+            "vector_ip_ops" => Some(VectorMetric::InnerProduct),
+            _ => None,
+        }
+    }
+
+    pub fn operator_class(&self) -> &'static str {
+        match self {
+            VectorMetric::L2 => "vector_l2_ops",
+            VectorMetric::Cosine => "vector_cosine_ops",
+            VectorMetric::InnerProduct => "vector_ip_ops",
+        }
+    }
+
+    /// SQL function whose order the index produces, and whether that order
+    /// is ascending
+    pub fn function(&self) -> (&'static str, bool) {
+        match self {
+            VectorMetric::L2 => ("L2_DISTANCE", true),
+            VectorMetric::Cosine => ("COSINE_DISTANCE", true),
+            VectorMetric::InnerProduct => ("INNER_PRODUCT", false),
+        }
+    }
+
+    /// Distance between vectors of equal length; smaller is nearer
+    pub fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        match self {
+            VectorMetric::L2 => l2_distance(a, b),
+            VectorMetric::Cosine => cosine_distance(a, b),
+            VectorMetric::InnerProduct => -inner_product(a, b),
+        }
+    }
+}
+
+/// Euclidean distance of vectors of equal length
+pub fn l2_distance(a: &[f64], b: &[f64]) -> f64 {
+    simd_euclidean_distance(a, b)
+}
+
+/// One minus the cosine of the angle between vectors of equal length; NaN
+/// if either is the zero vector
+pub fn cosine_distance(a: &[f64], b: &[f64]) -> f64 {
+    let norms = (simd_dot_product(a, a) * simd_dot_product(b, b)).sqrt();
+    if norms == 0.0 {
+        return f64::NAN;
+    }
+    1.0 - simd_dot_product(a, b) / norms
+}
+
+/// Inner product of vectors of equal length
+pub fn inner_product(a: &[f64], b: &[f64]) -> f64 {
+    simd_dot_product(a, b)
+}
+
+/// Parameters of an HNSW index, from `CREATE INDEX ... WITH (...)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HnswOptions {
+    pub metric: VectorMetric,
+    // Links kept per node on the levels above 0; level 0 keeps twice as many
+    pub m: usize,
+    // Width of the search that finds a new node's neighbours
+    pub ef_construction: usize,
+}
+
+impl Default for HnswOptions {
+    fn default() -> Self {
+        Self {
+            metric: VectorMetric::L2,
+            m: 16,
+            ef_construction: 64,
+        }
+    }
+}
+
+impl HnswOptions {
+    pub fn validate(&self) -> Result<()> {
+        if !(2..=100).contains(&self.m) {
+            return Err(DbError::InvalidInput(format!(
+                "m must be between 2 and 100, got {}",
+                self.m
+            )));
+        }
+        if !(4..=1000).contains(&self.ef_construction) {
+            return Err(DbError::InvalidInput(format!(
+                "ef_construction must be between 4 and 1000, got {}",
+                self.ef_construction
+            )));
+        }
+        if self.ef_construction < 2 * self.m {
+            return Err(DbError::InvalidInput(format!(
+                "ef_construction must be at least twice m ({})",
+                2 * self.m
+            )));
+        }
+        Ok(())
+    }
+
+    fn max_links(&self, level: usize) -> usize {
+        if level == 0 {
+            2 * self.m
+        } else {
+            self.m
+        }
+    }
+}
+
+/// One entry of the persisted graph, added or removed
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphChange {
+    AddNode {
+        id: u64,
+        level: usize,
+        vector: Vec<f64>,
+    },
+    RemoveNode {
+        id: u64,
+        level: usize,
+        vector: Vec<f64>,
+    },
+    Link {
+        from: u64,
+        level: usize,
+        to: u64,
+    },
+    Unlink {
+        from: u64,
+        level: usize,
+        to: u64,
+    },
+}
+
+impl GraphChange {
+    /// The tree entry of the change, and whether it is added
+    pub fn entry(&self) -> (IndexKey, u64, bool) {
+        match self {
+            GraphChange::AddNode { id, level, .. } => (node_key(*id), *level as u64, true),
+            GraphChange::RemoveNode { id, level, .. } => (node_key(*id), *level as u64, false),
+            GraphChange::Link { from, level, to } => (link_key(*from, *level), *to, true),
+            GraphChange::Unlink { from, level, to } => (link_key(*from, *level), *to, false),
+        }
+    }
+
+    fn inverse(&self) -> GraphChange {
+        match self.clone() {
+            GraphChange::AddNode { id, level, vector } => {
+                GraphChange::RemoveNode { id, level, vector }
+            }
+            GraphChange::RemoveNode { id, level, vector } => {
+                GraphChange::AddNode { id, level, vector }
+            }
+            GraphChange::Link { from, level, to } => GraphChange::Unlink { from, level, to },
+            GraphChange::Unlink { from, level, to } => GraphChange::Link { from, level, to },
+        }
+    }
+}
+
+fn node_key(id: u64) -> IndexKey {
+    IndexKey::Composite(vec![IndexKey::Integer(id as i64), IndexKey::Integer(-1)])
+}
+
+fn link_key(from: u64, level: usize) -> IndexKey {
+    IndexKey::Composite(vec![
+        IndexKey::Integer(from as i64),
+        IndexKey::Integer(level as i64),
+    ])
+}
+
+struct Node {
+    vector: Vec<f64>,
+    // Out-links and in-links on each level the node is on, from level 0 up
+    links: Vec<Vec<u64>>,
+    incoming: Vec<HashSet<u64>>,
+}
+
+impl Node {
+    fn top(&self) -> usize {
+        self.links.len() - 1
+    }
+}
+
+// A node and its distance to the query, ordered nearest first
+#[derive(Debug, Clone, Copy)]
+struct Scored(f64, u64);
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+/// The in-memory graph of one HNSW index, keyed by row id
+pub struct HnswGraph {
+    options: HnswOptions,
+    nodes: HashMap<u64, Node>,
+    // (top level, id) of every node; the last one is the entry point
+    levels: BTreeSet<(usize, u64)>,
+}
+
+impl HnswGraph {
+    pub fn new(options: HnswOptions) -> Self {
+        Self {
+            options,
+            nodes: HashMap::new(),
+            levels: BTreeSet::new(),
+        }
+    }
+
+    pub fn options(&self) -> &HnswOptions {
+        &self.options
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    fn entry_point(&self) -> Option<u64> {
+        self.levels.last().map(|&(_, id)| id)
+    }
+
+    // Levels are drawn from the row id so a rebuilt graph comes out the same
+    fn level_of(&self, id: u64) -> usize {
+        let mut z = id.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        // Uniform in (0, 1]
+        let uniform = ((z >> 11) + 1) as f64 / (1u64 << 53) as f64;
+        let scale = 1.0 / (self.options.m as f64).ln();
+        ((-uniform.ln() * scale) as usize).min(MAX_LEVEL)
+    }
+
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        self.options.metric.distance(a, b)
+    }
+
+    /// Add row `id` with `vector`, returning the changes made
+    pub fn insert(&mut self, id: u64, vector: Vec<f64>) -> Vec<GraphChange> {
+        let mut changes = match self.contains(id) {
+            true => self.remove(id),
+            false => Vec::new(),
+        };
+        let level = self.level_of(id);
+        let entry = self.entry_point();
+        self.change(
+            GraphChange::AddNode {
+                id,
+                level,
+                vector: vector.clone(),
+            },
+            &mut changes,
+        );
+        let Some(entry) = entry else {
+            return changes;
+        };
+
+        let top = self.nodes[&entry].top();
+        let mut entry_points = vec![entry];
+        for l in (level + 1..=top).rev() {
+            entry_points = self.closest(&vector, &entry_points, l);
+        }
+        for l in (0..=level.min(top)).rev() {
+            let candidates =
+                self.search_layer(&vector, &entry_points, self.options.ef_construction, l);
+            let max_links = self.options.max_links(l);
+            let candidates: Vec<Scored> = candidates.into_iter().filter(|c| c.1 != id).collect();
+            for neighbour in self.select(&candidates, max_links) {
+                self.change(
+                    GraphChange::Link {
+                        from: id,
+                        level: l,
+                        to: neighbour,
+                    },
+                    &mut changes,
+                );
+                self.change(
+                    GraphChange::Link {
+                        from: neighbour,
+                        level: l,
+                        to: id,
+                    },
+                    &mut changes,
+                );
+                self.shrink(neighbour, l, &mut changes);
+            }
+            entry_points = candidates.iter().map(|c| c.1).collect();
+        }
+        changes
+    }
+
+    /// Remove row `id`, relinking the nodes that pointed at it; returns the
+    /// changes made
+    pub fn remove(&mut self, id: u64) -> Vec<GraphChange> {
+        let mut changes = Vec::new();
+        let Some(node) = self.nodes.get(&id) else {
+            return changes;
+        };
+        let (top, vector) = (node.top(), node.vector.clone());
+        for l in 0..=top {
+            let node = &self.nodes[&id];
+            let outgoing = node.links[l].clone();
+            let mut incoming: Vec<u64> = node.incoming[l].iter().copied().collect();
+            incoming.sort_unstable();
+            for &to in &outgoing {
+                self.change(
+                    GraphChange::Unlink {
+                        from: id,
+                        level: l,
+                        to,
+                    },
+                    &mut changes,
+                );
+            }
+            for &from in &incoming {
+                self.change(
+                    GraphChange::Unlink {
+                        from,
+                        level: l,
+                        to: id,
+                    },
+                    &mut changes,
+                );
+            }
+            // Give each node that lost its link the removed node's neighbours
+            for &from in &incoming {
+                let mut candidates: Vec<u64> = self.nodes[&from].links[l].clone();
+                candidates.extend(outgoing.iter().filter(|&&to| to != from));
+                candidates.sort_unstable();
+                candidates.dedup();
+                self.relink(from, l, candidates, &mut changes);
+            }
+        }
+        self.change(
+            GraphChange::RemoveNode {
+                id,
+                level: top,
+                vector,
+            },
+            &mut changes,
+        );
+        changes
+    }
+
+    /// Take back `changes`, newest first
+    pub fn undo(&mut self, changes: &[GraphChange]) {
+        for change in changes.iter().rev() {
+            self.apply(&change.inverse());
+        }
+    }
+
+    /// Up to `k` nearest rows to `query` with their distances, nearest first
+    ///
+    /// `ef` is the width of the search on level 0: larger finds the true
+    /// nearest rows more often, at the cost of visiting more nodes.
+    pub fn search(&self, query: &[f64], k: usize, ef: usize) -> Vec<(u64, f64)> {
+        let Some(entry) = self.entry_point() else {
+            return Vec::new();
+        };
+        let mut entry_points = vec![entry];
+        for l in (1..=self.nodes[&entry].top()).rev() {
+            entry_points = self.closest(query, &entry_points, l);
+        }
+        self.search_layer(query, &entry_points, ef.max(k), 0)
+            .into_iter()
+            .take(k)
+            .map(|Scored(distance, id)| (id, distance))
+            .collect()
+    }
+
+    /// The tree entries of the whole graph, in key order
+    pub fn entries(&self) -> Vec<(IndexKey, u64)> {
+        let mut entries = Vec::new();
+        for (&id, node) in &self.nodes {
+            entries.push((node_key(id), node.top() as u64));
+            for (l, links) in node.links.iter().enumerate() {
+                entries.extend(links.iter().map(|&to| (link_key(id, l), to)));
+            }
+        }
+        entries.sort_unstable();
+        entries
+    }
+
+    /// Rebuild the graph from its tree entries and the indexed vectors;
+    /// `None` if the two do not describe the same rows
+    pub fn restore(
+        options: HnswOptions,
+        entries: Vec<(IndexKey, u64)>,
+        mut vectors: HashMap<u64, Vec<f64>>,
+    ) -> Option<Self> {
+        let mut graph = Self::new(options);
+        let mut links = Vec::new();
+        for (key, value) in entries {
+            let IndexKey::Composite(parts) = key else {
+                return None;
+            };
+            let [IndexKey::Integer(id), IndexKey::Integer(level)] = parts.as_slice() else {
+                return None;
+            };
+            let id = *id as u64;
+            if *level >= 0 {
+                links.push((id, *level as usize, value));
+                continue;
+            }
+            let level = usize::try_from(value).ok().filter(|&l| l <= MAX_LEVEL)?;
+            graph.apply(&GraphChange::AddNode {
+                id,
+                level,
+                vector: vectors.remove(&id)?,
+            });
+        }
+        if !vectors.is_empty() {
+            return None;
+        }
+        for (from, level, to) in links {
+            let on_level = |id| graph.nodes.get(&id).is_some_and(|n| n.top() >= level);
+            if !on_level(from) || !on_level(to) {
+                return None;
+            }
+            graph.apply(&GraphChange::Link { from, level, to });
+        }
+        Some(graph)
+    }
+
+    fn change(&mut self, change: GraphChange, changes: &mut Vec<GraphChange>) {
+        self.apply(&change);
+        changes.push(change);
+    }
+
+    fn apply(&mut self, change: &GraphChange) {
+        match change {
+            GraphChange::AddNode { id, level, vector } => {
+                self.nodes.insert(
+                    *id,
+                    Node {
+                        vector: vector.clone(),
+                        links: vec![Vec::new(); level + 1],
+                        incoming: vec![HashSet::new(); level + 1],
+                    },
+                );
+                self.levels.insert((*level, *id));
+            }
+            GraphChange::RemoveNode { id, level, .. } => {
+                self.nodes.remove(id);
+                self.levels.remove(&(*level, *id));
+            }
+            GraphChange::Link { from, level, to } => {
+                if let Some(node) = self.nodes.get_mut(from) {
+                    node.links[*level].push(*to);
+                }
+                if let Some(node) = self.nodes.get_mut(to) {
+                    node.incoming[*level].insert(*from);
+                }
+            }
+            GraphChange::Unlink { from, level, to } => {
+                if let Some(node) = self.nodes.get_mut(from) {
+                    node.links[*level].retain(|id| id != to);
+                }
+                if let Some(node) = self.nodes.get_mut(to) {
+                    node.incoming[*level].remove(from);
+                }
+            }
+        }
+    }
+
+    // Cut the links of `id` on `level` back to the most useful ones
+    fn shrink(&mut self, id: u64, level: usize, changes: &mut Vec<GraphChange>) {
+        let links = self.nodes[&id].links[level].clone();
+        if links.len() > self.options.max_links(level) {
+            self.relink(id, level, links, changes);
+        }
+    }
+
+    // Point `id` at the best of `candidates` on `level`
+    fn relink(
+        &mut self,
+        id: u64,
+        level: usize,
+        candidates: Vec<u64>,
+        changes: &mut Vec<GraphChange>,
+    ) {
+        let vector = &self.nodes[&id].vector;
+        let mut scored: Vec<Scored> = candidates
+            .into_iter()
+            .filter(|&c| c != id)
+            .filter(|c| self.nodes.get(c).is_some_and(|n| n.top() >= level))
+            .map(|c| Scored(self.distance(vector, &self.nodes[&c].vector), c))
+            .collect();
+        scored.sort_unstable();
+        let keep: HashSet<u64> = self
+            .select(&scored, self.options.max_links(level))
+            .into_iter()
+            .collect();
+        let current = self.nodes[&id].links[level].clone();
+        for to in current.iter().filter(|to| !keep.contains(to)) {
+            self.change(
+                GraphChange::Unlink {
+                    from: id,
+                    level,
+                    to: *to,
+                },
+                changes,
+            );
+        }
+        let mut added: Vec<u64> = keep.into_iter().filter(|c| !current.contains(c)).collect();
+        added.sort_unstable();
+        for to in added {
+            self.change(
+                GraphChange::Link {
+                    from: id,
+                    level,
+                    to,
+                },
+                changes,
+            );
+        }
+    }
+
+    // Pick up to `m` of `candidates` (sorted nearest first), preferring ones
+    // not already closer to a picked node than to the base, which keeps
+    // links pointing in different directions; the rest fill any room left
+    fn select(&self, candidates: &[Scored], m: usize) -> Vec<u64> {
+        let mut selected: Vec<u64> = Vec::with_capacity(m);
+        let mut pruned = Vec::new();
+        for &Scored(distance, id) in candidates {
+            if selected.len() == m {
+                break;
+            }
+            let vector = &self.nodes[&id].vector;
+            let diverse = selected
+                .iter()
+                .all(|s| self.distance(vector, &self.nodes[s].vector) > distance);
+            if diverse {
+                selected.push(id);
+            } else {
+                pruned.push(id);
+            }
+        }
+        let room = m - selected.len();
+        selected.extend(pruned.into_iter().take(room));
+        selected
+    }
+
+    // Nearest node to `query` on `level`, starting from `entry_points`
+    fn closest(&self, query: &[f64], entry_points: &[u64], level: usize) -> Vec<u64> {
+        self.search_layer(query, entry_points, 1, level)
+            .into_iter()
+            .map(|c| c.1)
+            .collect()
+    }
+
+    // Beam search of width `ef` on `level`; the nodes found, nearest first
+    fn search_layer(
+        &self,
+        query: &[f64],
+        entry_points: &[u64],
+        ef: usize,
+        level: usize,
+    ) -> Vec<Scored> {
+        let mut visited: HashSet<u64> = HashSet::new();
+        let mut candidates = BinaryHeap::new();
+        let mut found: BinaryHeap<Scored> = BinaryHeap::new();
+        for &id in entry_points {
+            if visited.insert(id) {
+                let scored = Scored(self.distance(query, &self.nodes[&id].vector), id);
+                candidates.push(Reverse(scored));
+                found.push(scored);
+            }
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(Reverse(nearest)) = candidates.pop() {
+            if found.len() >= ef && found.peek().is_some_and(|far| nearest > *far) {
+                break;
+            }
+            for &next in &self.nodes[&nearest.1].links[level] {
+                if !visited.insert(next) {
+                    continue;
+                }
+                let scored = Scored(self.distance(query, &self.nodes[&next].vector), next);
+                if found.len() < ef || found.peek().is_some_and(|far| scored < *far) {
+                    candidates.push(Reverse(scored));
+                    found.push(scored);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic pseudo-random vectors
+    fn vectors(count: usize, dims: usize) -> Vec<Vec<f64>> {
+        let mut state = 42u64;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        (0..count)
+            .map(|_| (0..dims).map(|_| next()).collect())
+            .collect()
+    }
+
+    fn exact(data: &[Vec<f64>], query: &[f64], k: usize) -> Vec<u64> {
+        let mut scored: Vec<Scored> = data
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Scored(l2_distance(query, v), i as u64))
+            .collect();
+        scored.sort_unstable();
+        scored.into_iter().take(k).map(|s| s.1).collect()
+    }
+
+    fn graph(data: &[Vec<f64>]) -> HnswGraph {
+        let mut graph = HnswGraph::new(HnswOptions::default());
+        for (i, v) in data.iter().enumerate() {
+            graph.insert(i as u64, v.clone());
+        }
+        graph
+    }
+
+    fn recall(graph: &HnswGraph, data: &[Vec<f64>], queries: &[Vec<f64>], k: usize) -> f64 {
+        let mut hits = 0;
+        for query in queries {
+            let found: HashSet<u64> = graph
+                .search(query, k, DEFAULT_EF_SEARCH)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            hits += exact(data, query, k)
+                .iter()
+                .filter(|id| found.contains(id))
+                .count();
+        }
+        hits as f64 / (queries.len() * k) as f64
+    }
+
+    #[test]
+    fn test_distances() {
+        let (a, b) = ([1.0, 0.0], [0.0, 2.0]);
+        assert_eq!(l2_distance(&a, &b), 5f64.sqrt());
+        assert_eq!(cosine_distance(&a, &b), 1.0);
+        assert_eq!(inner_product(&[1.0, 2.0], &[3.0, 4.0]), 11.0);
+        assert!(cosine_distance(&a, &[0.0, 0.0]).is_nan());
+        assert_eq!(
+            VectorMetric::InnerProduct.distance(&[1.0, 2.0], &[3.0, 4.0]),
+            -11.0
+        );
+    }
+
+    #[test]
+    fn test_search_recall() {
+        let data = vectors(1000, 8);
+        let graph = graph(&data);
+        assert_eq!(graph.len(), 1000);
+        assert!(recall(&graph, &data, &vectors(20, 8), 10) >= 0.9);
+
+        // An exact match comes first
+        let found = graph.search(&data[123], 1, DEFAULT_EF_SEARCH);
+        assert_eq!(found, vec![(123, 0.0)]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut data = vectors(300, 4);
+        let mut graph = graph(&data);
+        for id in (0..300).step_by(2) {
+            graph.remove(id);
+        }
+        assert_eq!(graph.len(), 150);
+        let found = graph.search(&data[7], 150, 200);
+        assert!(found.iter().all(|(id, _)| id % 2 == 1));
+        assert_eq!(found.len(), 150);
+
+        // The rows left are still found
+        let kept: Vec<Vec<f64>> = data.drain(..).skip(1).step_by(2).collect();
+        let queries = vectors(10, 4);
+        let mut hits = 0;
+        for query in &queries {
+            let found: HashSet<u64> = graph
+                .search(query, 5, DEFAULT_EF_SEARCH)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            hits += exact(&kept, query, 5)
+                .iter()
+                .filter(|&&i| found.contains(&(2 * i + 1)))
+                .count();
+        }
+        assert!(hits >= 45);
+    }
+
+    #[test]
+    fn test_undo_and_restore() {
+        let data = vectors(100, 4);
+        let mut graph = graph(&data);
+        let before = graph.entries();
+
+        let changes = graph.insert(1000, vec![0.5; 4]);
+        assert!(graph.contains(1000));
+        graph.undo(&changes);
+        assert_eq!(graph.entries(), before);
+
+        let changes = graph.remove(10);
+        graph.undo(&changes);
+        assert_eq!(graph.entries(), before);
+
+        let vectors: HashMap<u64, Vec<f64>> = (0..100).zip(data.iter().cloned()).collect();
+        let restored =
+            HnswGraph::restore(HnswOptions::default(), before.clone(), vectors.clone()).unwrap();
+        assert_eq!(restored.entries(), before);
+        assert_eq!(
+            restored.search(&data[5], 1, DEFAULT_EF_SEARCH),
+            vec![(5, 0.0)]
+        );
+
+        // A row missing from the graph means it must be rebuilt
+        let mut extra = vectors;
+        extra.insert(500, vec![0.0; 4]);
+        assert!(HnswGraph::restore(HnswOptions::default(), before, extra).is_none());
+    }
+
+    #[test]
+    fn test_options() {
+        assert!(HnswOptions::default().validate().is_ok());
+        let options = HnswOptions {
+            m: 1,
+            ..HnswOptions::default()
+        };
+        assert!(options.validate().is_err());
+        let options = HnswOptions {
+            m: 40,
+            ef_construction: 64,
+            ..HnswOptions::default()
+        };
+        assert!(options.validate().is_err());
+        assert_eq!(
+            VectorMetric::from_operator_class("VECTOR_COSINE_OPS"),
+            Some(VectorMetric::Cosine)
+        );
+    }
+}
@@ -2,12 +2,15 @@
 //
 // This module provides comprehensive indexing capabilities:
 // - B+ Tree: Concurrent implementation with latch crabbing
+// - Disk B+ Tree: Page-based B-link tree in the buffer pool, WAL-logged
 // - LSM Tree: Write-optimized indexing with compaction
 // - Hash Indexes: Extendible and linear hashing
 // - Bitmap Indexes: Compressed bitmaps for low-cardinality data
+// - HNSW Indexes: Approximate nearest-neighbour search over vectors
 // - Spatial Indexes: R-tree for geographic/spatial queries
 // - Full-Text Search: Inverted indexes with TF-IDF
 // - Partial Indexes: Filtered indexes with predicates
+// - Table Indexes: The SQL tables' secondary indexes, kept in step with commits
 // - Expression Indexes: Function-based computed indexes
 // - Index Advisor: Intelligent index recommendations
 
@@ -16,15 +19,19 @@ pub mod bitmap;
 pub mod bitmap_compressed;
 pub mod btree;
 pub mod btree_optimized;
+pub mod disk_btree;
 pub mod fulltext;
-pub mod hash_index;
 pub mod hash_helpers;
+pub mod hash_index;
+pub mod hnsw;
 pub mod lsm_index;
 pub mod partial;
 pub mod simd_bloom;
 pub mod spatial;
 pub mod swiss_table;
+pub mod table_index;
 
+use crate::common::{Decimal, Value};
 use crate::error::{DbError, Result};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
@@ -240,7 +247,11 @@ pub trait IndexIterator {
     ///
     /// Yields all entries where `start <= key <= end`.
     /// Only implemented for ordered indexes.
-    fn range_iter(&self, start: &Self::Key, end: &Self::Key) -> Box<dyn Iterator<Item = Self::Item> + '_>;
+    fn range_iter(
+        &self,
+        start: &Self::Key,
+        end: &Self::Key,
+    ) -> Box<dyn Iterator<Item = Self::Item> + '_>;
 }
 
 /// Helper utilities for index iteration
@@ -341,6 +352,242 @@ pub enum IndexKey {
     Integer(i64),
     String(String),
     Binary(Vec<u8>),
+    /// An f64 as bits that sort like the number (see `IndexKey::float`)
+    Float(u64),
+    /// Several columns, compared column by column
+    Composite(Vec<IndexKey>),
+    /// Sorts after every other key
+    Null,
+}
+
+// Tags of the key encoding, in the order of the variants
+const KEY_INTEGER: u8 = 0x01;
+const KEY_STRING: u8 = 0x02;
+const KEY_BINARY: u8 = 0x03;
+const KEY_FLOAT: u8 = 0x04;
+const KEY_COMPOSITE: u8 = 0x05;
+const KEY_NULL: u8 = 0x06;
+
+impl IndexKey {
+    /// Key of a float; -0.0 equals 0.0 and every NaN sorts above +inf
+    pub fn float(value: f64) -> Self {
+        let value = if value == 0.0 {
+            0.0
+        } else if value.is_nan() {
+            f64::NAN
+        } else {
+            value
+        };
+        let bits = value.to_bits();
+        if value.is_sign_negative() {
+            IndexKey::Float(!bits)
+        } else {
+            IndexKey::Float(bits ^ (1 << 63))
+        }
+    }
+
+    /// Key of a decimal, bytes that sort like the number
+    ///
+    /// Zero is `[1]`. Any other number is a sign byte, then its decimal
+    /// exponent and its significant digits, both inverted for negative
+    /// numbers, which also end in 0xFF so that a longer run of digits sorts
+    /// first. Trailing zeros are dropped, so 1.50 and 1.5 share a key.
+    pub fn numeric(value: &Decimal) -> Self {
+        if value.is_zero() {
+            return IndexKey::Binary(vec![1]);
+        }
+        let digits = value.mantissa().unsigned_abs().to_string();
+        let exponent = digits.len() as i32 - value.scale() as i32;
+        let significant = digits.trim_end_matches('0').bytes().map(|d| d - b'0');
+        // Decimals have at most 38 digits and places, so this fits a byte
+        let exponent = (exponent + 128) as u8;
+        let mut out = Vec::with_capacity(digits.len() + 3);
+        if value.is_negative() {
+            out.push(0);
+            out.push(!exponent);
+            out.extend(significant.map(|d| 9 - d));
+            out.push(0xFF);
+        } else {
+            out.push(2);
+            out.push(exponent);
+            out.extend(significant);
+        }
+        IndexKey::Binary(out)
+    }
+
+    /// Inverse of `IndexKey::numeric`
+    pub fn numeric_value(bytes: &[u8]) -> Option<Decimal> {
+        let (&sign, rest) = bytes.split_first()?;
+        let (&exponent, digits) = rest.split_first()?;
+        let (exponent, digits) = match sign {
+            0 => (!exponent, digits.strip_suffix(&[0xFF])?),
+            1 => return Some(Decimal::ZERO),
+            _ => (exponent, digits),
+        };
+        let mut mantissa: i128 = 0;
+        for &d in digits {
+            let d = if sign == 0 { 9u8.checked_sub(d)? } else { d };
+            mantissa = mantissa.checked_mul(10)?.checked_add(d as i128)?;
+        }
+        // The number is 0.digits * 10^exponent
+        let scale = digits.len() as i32 - (exponent as i32 - 128);
+        if scale < 0 {
+            mantissa = mantissa.checked_mul(10i128.checked_pow(scale.unsigned_abs())?)?;
+        }
+        let mantissa = if sign == 0 { -mantissa } else { mantissa };
+        Decimal::new(mantissa, scale.max(0) as u32)
+    }
+
+    /// Key of a column value
+    ///
+    /// Booleans, dates and timestamps become integers, decimals the bytes of
+    /// `IndexKey::numeric`, intervals their length and UUIDs their bytes,
+    /// JSON its text and arrays composite keys.
+    pub fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Null | Value::Text => IndexKey::Null,
+            Value::Boolean(b) => IndexKey::Integer(*b as i64),
+            Value::Integer(i) | Value::Date(i) | Value::Timestamp(i) | Value::TimestampTz(i) => {
+                IndexKey::Integer(*i)
+            }
+            Value::Numeric(d) => IndexKey::numeric(d),
+            // The sign bit flipped so that the big-endian bytes sort like
+            // the length
+            Value::Interval(i) => {
+                IndexKey::Binary(((i.span() as u128) ^ (1 << 127)).to_be_bytes().to_vec())
+            }
+            Value::Uuid(u) => IndexKey::Binary(u.as_bytes().to_vec()),
+            Value::Float(f) => IndexKey::float(*f),
+            Value::String(s) => IndexKey::String(s.clone()),
+            Value::Bytes(b) => IndexKey::Binary(b.clone()),
+            Value::Json(json) => IndexKey::String(json.to_string()),
+            Value::Array(values) => {
+                IndexKey::Composite(values.iter().map(IndexKey::from_value).collect())
+            }
+            Value::Vector(v) => IndexKey::Binary(v.iter().flat_map(|f| f.to_le_bytes()).collect()),
+        }
+    }
+
+    /// Key of a row's values in the columns of an index
+    pub fn from_values(values: &[Value]) -> Self {
+        match values {
+            [value] => IndexKey::from_value(value),
+            values => IndexKey::Composite(values.iter().map(IndexKey::from_value).collect()),
+        }
+    }
+
+    /// Encode the key so that encoded keys compare bytewise like the keys
+    ///
+    /// No encoding is a prefix of another, so bytes appended to an encoded
+    /// key do not change its order against other keys.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    pub fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            IndexKey::Integer(i) => {
+                out.push(KEY_INTEGER);
+                out.extend_from_slice(&((*i as u64) ^ (1 << 63)).to_be_bytes());
+            }
+            IndexKey::String(s) => {
+                out.push(KEY_STRING);
+                encode_bytes(s.as_bytes(), out);
+            }
+            IndexKey::Binary(b) => {
+                out.push(KEY_BINARY);
+                encode_bytes(b, out);
+            }
+            IndexKey::Float(bits) => {
+                out.push(KEY_FLOAT);
+                out.extend_from_slice(&bits.to_be_bytes());
+            }
+            IndexKey::Composite(keys) => {
+                out.push(KEY_COMPOSITE);
+                for key in keys {
+                    key.encode_into(out);
+                }
+                out.push(0x00);
+            }
+            IndexKey::Null => out.push(KEY_NULL),
+        }
+    }
+
+    /// Decode a key from the start of `bytes`, returning it and its length
+    pub fn decode(bytes: &[u8]) -> Result<(IndexKey, usize)> {
+        let malformed = || DbError::Corruption("Malformed index key".to_string());
+        let (&tag, rest) = bytes.split_first().ok_or_else(malformed)?;
+        let fixed = |rest: &[u8]| -> Result<u64> {
+            let bytes = rest.get(..8).and_then(|b| b.try_into().ok());
+            Ok(u64::from_be_bytes(bytes.ok_or_else(malformed)?))
+        };
+        match tag {
+            KEY_INTEGER => Ok((IndexKey::Integer((fixed(rest)? ^ (1 << 63)) as i64), 9)),
+            KEY_FLOAT => Ok((IndexKey::Float(fixed(rest)?), 9)),
+            KEY_STRING => {
+                let (raw, len) = decode_bytes(rest).ok_or_else(malformed)?;
+                let s = String::from_utf8(raw).map_err(|_| malformed())?;
+                Ok((IndexKey::String(s), len + 1))
+            }
+            KEY_BINARY => {
+                let (raw, len) = decode_bytes(rest).ok_or_else(malformed)?;
+                Ok((IndexKey::Binary(raw), len + 1))
+            }
+            KEY_COMPOSITE => {
+                let mut keys = Vec::new();
+                let mut pos = 1;
+                loop {
+                    match bytes.get(pos) {
+                        Some(0x00) => return Ok((IndexKey::Composite(keys), pos + 1)),
+                        Some(_) => {
+                            let (key, len) = IndexKey::decode(&bytes[pos..])?;
+                            keys.push(key);
+                            pos += len;
+                        }
+                        None => return Err(malformed()),
+                    }
+                }
+            }
+            KEY_NULL => Ok((IndexKey::Null, 1)),
+            _ => Err(malformed()),
+        }
+    }
+}
+
+// Bytes are written with 0x00 escaped as 0x00 0xFF and end with 0x00 0x01,
+// which sorts a string before its extensions
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    for &b in bytes {
+        out.push(b);
+        if b == 0x00 {
+            out.push(0xFF);
+        }
+    }
+    out.extend_from_slice(&[0x00, 0x01]);
+}
+
+// The bytes written by `encode_bytes` and how many bytes they took
+fn decode_bytes(bytes: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut raw = Vec::new();
+    let mut pos = 0;
+    loop {
+        match *bytes.get(pos)? {
+            0x00 => {
+                match *bytes.get(pos + 1)? {
+                    0xFF => raw.push(0x00),
+                    0x01 => return Some((raw, pos + 2)),
+                    _ => return None,
+                }
+                pos += 2;
+            }
+            b => {
+                raw.push(b);
+                pos += 1;
+            }
+        }
+    }
 }
 
 // Index value (row ID or pointer)
@@ -460,6 +707,7 @@ pub enum Index {
     Partial(partial::PartialIndex<IndexKey, IndexValue>),
     Expression(partial::ExpressionIndex<IndexValue>),
     Covering(partial::CoveringIndex<IndexKey>),
+    Hnsw(Arc<RwLock<hnsw::HnswGraph>>),
 }
 
 impl Index {
@@ -559,6 +807,9 @@ impl IndexManager {
             IndexType::LinearHash => Index::LinearHash(hash_index::LinearHashIndex::new(16, 64)),
             IndexType::Bitmap => Index::Bitmap(bitmap::BitmapIndex::new()),
             IndexType::Spatial => Index::Spatial(spatial::RTree::new()),
+            IndexType::Hnsw => Index::Hnsw(Arc::new(RwLock::new(hnsw::HnswGraph::new(
+                hnsw::HnswOptions::default(),
+            )))),
         };
 
         indexes.insert(name, index);
@@ -652,6 +903,7 @@ pub enum IndexType {
     LinearHash,
     Bitmap,
     Spatial,
+    Hnsw,
 }
 
 // Index statistics
@@ -698,4 +950,82 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_key_encoding_preserves_order() -> Result<()> {
+        let string = |s: &str| IndexKey::String(s.to_string());
+        let keys = vec![
+            IndexKey::Integer(i64::MIN),
+            IndexKey::Integer(-1),
+            IndexKey::Integer(0),
+            IndexKey::Integer(42),
+            string(""),
+            string("a"),
+            string("a\0"),
+            string("a\0b"),
+            string("ab"),
+            IndexKey::Binary(vec![0, 0, 255]),
+            IndexKey::float(f64::NEG_INFINITY),
+            IndexKey::float(-2.5),
+            IndexKey::float(-0.0),
+            IndexKey::float(1e-300),
+            IndexKey::float(f64::NAN),
+            IndexKey::Composite(vec![IndexKey::Integer(1)]),
+            IndexKey::Composite(vec![IndexKey::Integer(1), string("x")]),
+            IndexKey::Composite(vec![IndexKey::Integer(1), IndexKey::Null]),
+            IndexKey::Composite(vec![IndexKey::Integer(2)]),
+            IndexKey::Null,
+        ];
+        assert_eq!(IndexKey::float(-0.0), IndexKey::float(0.0));
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+            assert!(pair[0].encode() < pair[1].encode(), "{:?}", pair);
+        }
+        for key in &keys {
+            let encoded = key.encode();
+            assert_eq!(IndexKey::decode(&encoded)?, (key.clone(), encoded.len()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_keys() {
+        let numbers = [
+            "-1000",
+            "-12.5",
+            "-12.25",
+            "-12",
+            "-0.5",
+            "-0.05",
+            "0",
+            "0.001",
+            "0.1",
+            "0.15",
+            "1",
+            "1.5",
+            "9.99",
+            "10",
+            "12345678901234567890.123",
+        ];
+        let keys: Vec<IndexKey> = numbers
+            .iter()
+            .map(|n| IndexKey::numeric(&Decimal::parse(n).unwrap()))
+            .collect();
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+            assert!(pair[0].encode() < pair[1].encode(), "{:?}", pair);
+        }
+        for (number, key) in numbers.iter().zip(&keys) {
+            let IndexKey::Binary(bytes) = key else {
+                panic!("{:?} is not a binary key", key);
+            };
+            let decoded = IndexKey::numeric_value(bytes).unwrap();
+            assert_eq!(decoded.to_string(), *number);
+        }
+        // Trailing zeros do not change the key
+        assert_eq!(
+            IndexKey::numeric(&Decimal::parse("1.50").unwrap()),
+            IndexKey::numeric(&Decimal::parse("1.5").unwrap())
+        );
+    }
 }
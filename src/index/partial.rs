@@ -281,7 +281,7 @@ impl<K: Ord + Clone> CoveringIndex<K> {
 }
 
 // Predicate for partial indexes
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Predicate {
     // Simple comparison: column op value
     Comparison {
@@ -328,10 +328,37 @@ impl Predicate {
             }
         }
     }
+
+    // Columns the predicate reads
+    pub fn columns(&self) -> Vec<&str> {
+        match self {
+            Predicate::Comparison { column, .. }
+            | Predicate::IsNull(column)
+            | Predicate::IsNotNull(column) => vec![column.as_str()],
+            Predicate::And(left, right) | Predicate::Or(left, right) => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            Predicate::Not(pred) => pred.columns(),
+        }
+    }
+
+    // Split into the top-level AND terms
+    pub fn conjuncts(&self) -> Vec<&Predicate> {
+        match self {
+            Predicate::And(left, right) => {
+                let mut terms = left.conjuncts();
+                terms.extend(right.conjuncts());
+                terms
+            }
+            other => vec![other],
+        }
+    }
 }
 
 // Comparison operators
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ComparisonOp {
     Equal,
     NotEqual,
@@ -360,6 +387,14 @@ impl ComparisonOp {
                 ComparisonOp::GreaterThan => l > r,
                 ComparisonOp::GreaterThanOrEqual => l >= r,
             }),
+            (ColumnValue::Boolean(l), ColumnValue::Boolean(r)) => Ok(match self {
+                ComparisonOp::Equal => l == r,
+                ComparisonOp::NotEqual => l != r,
+                ComparisonOp::LessThan => l < r,
+                ComparisonOp::LessThanOrEqual => l <= r,
+                ComparisonOp::GreaterThan => l > r,
+                ComparisonOp::GreaterThanOrEqual => l >= r,
+            }),
             (ColumnValue::Null, ColumnValue::Null) => Ok(match self {
                 ComparisonOp::Equal => true,
                 ComparisonOp::NotEqual => false,
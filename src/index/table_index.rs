@@ -0,0 +1,673 @@
+// Secondary indexes of the SQL tables
+//
+// Each index in the catalog has a `DiskBTree` in the table store's index
+// file, mapping the key of every row to its row id. The trees index the heap,
+// i.e. the latest committed image of every row: they are changed together
+// with the heap while a commit holds the commit lock, which is also what
+// makes their unique checks race-free. Readers with an older snapshot get
+// the rows changed since from `RowVersions` (see `RowVersions::index_scan`).
+//
+// A partial index holds only the rows its predicate is true for. Keys with
+// a NULL column never conflict in a unique index.
+//
+// An HNSW index over a vector column keeps its graph in memory and stores
+// the graph's nodes and links in its tree instead of row keys (see `hnsw`).
+// Rows with a NULL vector are left out of the graph.
+
+use crate::catalog::{DataType, IndexDefinition, Schema};
+use crate::common::{Interval, Value};
+use crate::error::{DbError, Result};
+use crate::index::disk_btree::{DiskBTree, IndexFile};
+use crate::index::hnsw::{GraphChange, HnswGraph};
+use crate::index::partial::{ColumnValue, RowData};
+use crate::index::IndexKey;
+use crate::storage::{RowId, TableStore};
+use crate::transaction::RowChange;
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// The B+Tree of one index, with what it takes to compute a row's key
+pub struct TableIndex {
+    definition: IndexDefinition,
+    // Schema positions and types of the key columns
+    positions: Vec<usize>,
+    types: Vec<DataType>,
+    // Columns the predicate of a partial index reads, with their positions
+    predicate_columns: Vec<(String, usize)>,
+    tree: DiskBTree,
+    // The graph of an HNSW index
+    graph: Option<RwLock<HnswGraph>>,
+}
+
+impl TableIndex {
+    fn new(schema: &Schema, definition: IndexDefinition, tree: DiskBTree) -> Result<Self> {
+        let position = |name: &str| {
+            schema
+                .columns
+                .iter()
+                .position(|c| c.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| {
+                    DbError::Catalog(format!(
+                        "Column {} not found in table {}",
+                        name, schema.name
+                    ))
+                })
+        };
+        let positions = definition
+            .columns
+            .iter()
+            .map(|c| position(c))
+            .collect::<Result<Vec<_>>>()?;
+        let types = positions
+            .iter()
+            .map(|&i| schema.columns[i].data_type.clone())
+            .collect();
+        let predicate_columns = match &definition.predicate {
+            Some(predicate) => predicate
+                .columns()
+                .into_iter()
+                .map(|c| Ok((c.to_string(), position(c)?)))
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let graph = definition
+            .hnsw
+            .clone()
+            .map(|options| RwLock::new(HnswGraph::new(options)));
+        Ok(Self {
+            definition,
+            positions,
+            types,
+            predicate_columns,
+            tree,
+            graph,
+        })
+    }
+
+    pub fn definition(&self) -> &IndexDefinition {
+        &self.definition
+    }
+
+    pub fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.definition.unique
+    }
+
+    /// Schema positions of the key columns, in key order
+    pub fn positions(&self) -> &[usize] {
+        &self.positions
+    }
+
+    pub fn types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    pub fn is_hnsw(&self) -> bool {
+        self.graph.is_some()
+    }
+
+    /// Key of `row`, or `None` if a partial index does not cover it
+    pub fn key(&self, row: &[Value]) -> Result<Option<IndexKey>> {
+        if !self.covers(row)? {
+            return Ok(None);
+        }
+        let values: Vec<Value> = self
+            .positions
+            .iter()
+            .map(|&i| row.get(i).cloned().unwrap_or(Value::Null))
+            .collect();
+        Ok(Some(IndexKey::from_values(&values)))
+    }
+
+    /// Entries whose leading key columns equal `prefix` and whose next
+    /// column lies between `lower` and `upper`; NULLs are never in range
+    pub fn lookup(
+        &self,
+        prefix: &[IndexKey],
+        lower: Bound<&IndexKey>,
+        upper: Bound<&IndexKey>,
+    ) -> Result<Vec<(IndexKey, RowId)>> {
+        // NULL sorts after every other key
+        let null = IndexKey::Null;
+        let upper = match upper {
+            Bound::Unbounded => Bound::Excluded(&null),
+            upper => upper,
+        };
+        let entries = match (self.positions.len(), prefix) {
+            // Single-column keys are stored bare
+            (1, []) => self.tree.range(lower, upper)?,
+            (1, [key]) => self
+                .tree
+                .range(Bound::Included(key), Bound::Included(key))?,
+            _ if prefix.len() == self.positions.len() => {
+                let key = IndexKey::Composite(prefix.to_vec());
+                self.tree
+                    .range(Bound::Included(&key), Bound::Included(&key))?
+            }
+            _ => self.tree.prefix_range(prefix, lower, upper)?,
+        };
+        Ok(entries
+            .into_iter()
+            .map(|(key, value)| (key, RowId::from_u64(value)))
+            .collect())
+    }
+
+    /// Up to `k` rows of an HNSW index nearest to `query`, nearest first,
+    /// searching `ef` candidates wide
+    pub fn nearest(&self, query: &[f64], k: usize, ef: usize) -> Result<Vec<(IndexKey, RowId)>> {
+        let Some(graph) = &self.graph else {
+            return Err(DbError::Internal(format!(
+                "Index {} is not a vector index",
+                self.name()
+            )));
+        };
+        if let Some(DataType::Vector(dims)) = self.types.first() {
+            if query.len() != *dims {
+                return Err(DbError::InvalidInput(format!(
+                    "Expected a vector of {} dimensions, got {}",
+                    dims,
+                    query.len()
+                )));
+            }
+        }
+        Ok(graph
+            .read()
+            .search(query, k, ef)
+            .into_iter()
+            .map(|(rid, _)| (IndexKey::Null, RowId::from_u64(rid)))
+            .collect())
+    }
+
+    /// Values of the key columns held in `key`, in key order
+    pub fn key_values(&self, key: &IndexKey) -> Vec<Value> {
+        let keys = match key {
+            IndexKey::Composite(keys) if self.positions.len() > 1 => keys.as_slice(),
+            key => std::slice::from_ref(key),
+        };
+        keys.iter()
+            .zip(&self.types)
+            .map(|(key, data_type)| key_value(key, data_type))
+            .collect()
+    }
+
+    // Whether a partial index's predicate is true for `row`
+    fn covers(&self, row: &[Value]) -> Result<bool> {
+        let Some(predicate) = &self.definition.predicate else {
+            return Ok(true);
+        };
+        let mut data = RowData::new();
+        for (name, i) in &self.predicate_columns {
+            let value = match row.get(*i) {
+                Some(
+                    Value::Integer(v)
+                    | Value::Date(v)
+                    | Value::Timestamp(v)
+                    | Value::TimestampTz(v),
+                ) => ColumnValue::Integer(*v),
+                Some(Value::Boolean(b)) => ColumnValue::Boolean(*b),
+                Some(Value::String(s)) => ColumnValue::String(s.clone()),
+                Some(Value::Null) | None => ColumnValue::Null,
+                Some(other) => ColumnValue::String(other.to_display_string()),
+            };
+            data.set_column(name.clone(), value);
+        }
+        predicate.evaluate(&data)
+    }
+
+    // Fill the empty tree with the rows of the table
+    fn fill(&self, rows: Vec<(RowId, Vec<Value>)>) -> Result<()> {
+        let mut entries = Vec::with_capacity(rows.len());
+        for (rid, row) in rows {
+            if let Some(key) = self.key(&row)? {
+                entries.push((key, rid.to_u64()));
+            }
+        }
+        if let Some(graph) = &self.graph {
+            let mut graph = graph.write();
+            for (key, rid) in entries {
+                if let IndexKey::Binary(bytes) = key {
+                    graph.insert(rid, vector_from_bytes(&bytes));
+                }
+            }
+            return self.tree.bulk_load(graph.entries());
+        }
+        if self.is_unique() {
+            entries.sort_unstable();
+            if let Some(pair) = entries
+                .windows(2)
+                .find(|pair| pair[0].0 == pair[1].0 && !has_null(&pair[0].0))
+            {
+                return Err(self.duplicate(&pair[0].0));
+            }
+        }
+        self.tree.bulk_load(entries)
+    }
+
+    // Rebuild the graph of an HNSW index from its tree and the table's rows;
+    // `false` if the two disagree
+    fn restore(&self, rows: Vec<(RowId, Vec<Value>)>) -> Result<bool> {
+        let Some(graph) = &self.graph else {
+            return Ok(true);
+        };
+        let mut vectors = HashMap::new();
+        for (rid, row) in rows {
+            if let Some(IndexKey::Binary(bytes)) = self.key(&row)? {
+                vectors.insert(rid.to_u64(), vector_from_bytes(&bytes));
+            }
+        }
+        let entries = self.tree.range(Bound::Unbounded, Bound::Unbounded)?;
+        let options = graph.read().options().clone();
+        match HnswGraph::restore(options, entries, vectors) {
+            Some(restored) => {
+                *graph.write() = restored;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Add the entry of row `rid` with `key`
+    fn insert_entry(&self, key: &IndexKey, rid: RowId) -> Result<()> {
+        let Some(graph) = &self.graph else {
+            self.tree.insert(key, rid.to_u64())?;
+            return Ok(());
+        };
+        let IndexKey::Binary(bytes) = key else {
+            return Ok(());
+        };
+        let mut graph = graph.write();
+        let changes = graph.insert(rid.to_u64(), vector_from_bytes(bytes));
+        self.persist(&mut graph, &changes)
+    }
+
+    // Remove the entry of row `rid` with `key`
+    fn delete_entry(&self, key: &IndexKey, rid: RowId) -> Result<()> {
+        let Some(graph) = &self.graph else {
+            self.tree.delete(key, rid.to_u64())?;
+            return Ok(());
+        };
+        let mut graph = graph.write();
+        let changes = graph.remove(rid.to_u64());
+        self.persist(&mut graph, &changes)
+    }
+
+    // Write the graph's `changes` to the tree; if that fails, the tree and
+    // the graph are put back as they were
+    fn persist(&self, graph: &mut HnswGraph, changes: &[GraphChange]) -> Result<()> {
+        let write = |change: &GraphChange, undo: bool| {
+            let (key, value, added) = change.entry();
+            if added != undo {
+                self.tree.insert(&key, value).map(|_| ())
+            } else {
+                self.tree.delete(&key, value).map(|_| ())
+            }
+        };
+        for (done, change) in changes.iter().enumerate() {
+            if let Err(e) = write(change, false) {
+                for change in changes[..done].iter().rev() {
+                    if let Err(e) = write(change, true) {
+                        tracing::error!("Failed to undo a change to index {}: {}", self.name(), e);
+                    }
+                }
+                graph.undo(changes);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// The error for a second row with `key` in a unique index
+    pub fn duplicate(&self, key: &IndexKey) -> DbError {
+        DbError::ConstraintViolation(format!(
+            "Duplicate key ({}) violates unique index {}",
+            self.key_values(key)
+                .iter()
+                .map(Value::to_display_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.name()
+        ))
+    }
+}
+
+/// Whether any column of `key` is NULL
+pub fn has_null(key: &IndexKey) -> bool {
+    match key {
+        IndexKey::Null => true,
+        IndexKey::Composite(keys) => keys.iter().any(has_null),
+        _ => false,
+    }
+}
+
+// The stored value a key column was made from
+fn key_value(key: &IndexKey, data_type: &DataType) -> Value {
+    match (key, data_type) {
+        (IndexKey::Null, _) => Value::Null,
+        (IndexKey::Integer(i), DataType::Boolean) => Value::Boolean(*i != 0),
+        (IndexKey::Integer(i), DataType::Date) => Value::Date(*i),
+        (IndexKey::Integer(i), DataType::Timestamp) => Value::Timestamp(*i),
+        (IndexKey::Integer(i), DataType::TimestampTz) => Value::TimestampTz(*i),
+        (IndexKey::Integer(i), _) => Value::Integer(*i),
+        // Inverse of `IndexKey::float`
+        (IndexKey::Float(bits), _) => Value::Float(f64::from_bits(if bits >> 63 == 1 {
+            bits ^ (1 << 63)
+        } else {
+            !bits
+        })),
+        (IndexKey::String(s), _) => Value::String(s.clone()),
+        (IndexKey::Binary(b), DataType::Vector(_)) => Value::Vector(vector_from_bytes(b)),
+        (IndexKey::Binary(b), DataType::Numeric(modifiers)) => {
+            let value = IndexKey::numeric_value(b);
+            // Keys drop trailing zeros; the column's scale restores them
+            let value = match (value, modifiers) {
+                (Some(d), Some((_, scale))) => d.rescale(*scale).or(value),
+                _ => value,
+            };
+            value.map(Value::Numeric).unwrap_or(Value::Null)
+        }
+        (IndexKey::Binary(b), DataType::Interval) => {
+            // Inverse of the sign-flipped span `IndexKey::from_value` gives
+            let span = b.as_slice().try_into().map(u128::from_be_bytes);
+            span.ok()
+                .and_then(|span| Interval::from_span((span ^ (1 << 127)) as i128))
+                .map(Value::Interval)
+                .unwrap_or(Value::Null)
+        }
+        (IndexKey::Binary(b), DataType::Uuid) => uuid::Uuid::from_slice(b)
+            .map(Value::Uuid)
+            .unwrap_or(Value::Null),
+        (IndexKey::Binary(b), _) => Value::Bytes(b.clone()),
+        (IndexKey::Composite(keys), DataType::Array(element)) => {
+            Value::Array(keys.iter().map(|k| key_value(k, element)).collect())
+        }
+        (IndexKey::Composite(keys), _) => {
+            Value::Array(keys.iter().map(|k| key_value(k, data_type)).collect())
+        }
+    }
+}
+
+// Inverse of the key `IndexKey::from_value` gives a vector
+fn vector_from_bytes(bytes: &[u8]) -> Vec<f64> {
+    bytes
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().expect("chunks of 8 bytes")))
+        .collect()
+}
+
+// One entry of an index
+struct Edit {
+    index: Arc<TableIndex>,
+    key: IndexKey,
+    rid: RowId,
+}
+
+/// The indexes of every table, over the trees of one index file
+pub struct TableIndexes {
+    file: Arc<IndexFile>,
+    tables: RwLock<HashMap<String, Vec<Arc<TableIndex>>>>,
+}
+
+impl TableIndexes {
+    pub fn new(file: Arc<IndexFile>) -> Self {
+        Self {
+            file,
+            tables: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Build the tree of a new index over the rows of `store` and register it
+    ///
+    /// A tree of the same name is left over from an index that no longer
+    /// exists and is replaced. Run with commits excluded.
+    pub fn create(
+        &self,
+        store: &TableStore,
+        schema: &Schema,
+        definition: IndexDefinition,
+    ) -> Result<()> {
+        self.file.drop_tree(&definition.name)?;
+        self.build(store, schema, definition)
+    }
+
+    /// Register an index whose tree survived a restart, building it if it
+    /// is missing or `rebuild` is set. Run with commits excluded.
+    pub fn load(
+        &self,
+        store: &TableStore,
+        schema: &Schema,
+        definition: IndexDefinition,
+        rebuild: bool,
+    ) -> Result<()> {
+        if !rebuild {
+            if let Some(tree) = self.file.open_tree(&definition.name)? {
+                let index = TableIndex::new(schema, definition.clone(), tree)?;
+                let restored = match index.is_hnsw() && store.has_table(&schema.name) {
+                    true => index.restore(store.scan(&schema.name)?)?,
+                    false => true,
+                };
+                if restored {
+                    self.register(index);
+                    return Ok(());
+                }
+                tracing::warn!("Rebuilding index {}", definition.name);
+            }
+        }
+        self.create(store, schema, definition)
+    }
+
+    fn build(
+        &self,
+        store: &TableStore,
+        schema: &Schema,
+        definition: IndexDefinition,
+    ) -> Result<()> {
+        let name = definition.name.clone();
+        let tree = self.file.create_tree(&name)?;
+        let built = TableIndex::new(schema, definition, tree).and_then(|index| {
+            if store.has_table(&schema.name) {
+                index.fill(store.scan(&schema.name)?)?;
+            }
+            Ok(index)
+        });
+        match built {
+            Ok(index) => {
+                self.register(index);
+                Ok(())
+            }
+            Err(e) => {
+                let _ = self.file.drop_tree(&name);
+                Err(e)
+            }
+        }
+    }
+
+    fn register(&self, index: TableIndex) {
+        let mut tables = self.tables.write();
+        let indexes = tables.entry(index.definition.table.clone()).or_default();
+        indexes.retain(|other| other.name() != index.name());
+        indexes.push(Arc::new(index));
+    }
+
+    /// Forget index `name` and drop its tree; `false` if there is no tree
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        for indexes in self.tables.write().values_mut() {
+            indexes.retain(|index| index.name() != name);
+        }
+        self.file.drop_tree(name)
+    }
+
+    /// Forget the indexes of `table`, dropping their trees, and return their
+    /// definitions
+    pub fn detach_table(&self, table: &str) -> Result<Vec<IndexDefinition>> {
+        let indexes = self.tables.write().remove(table).unwrap_or_default();
+        let mut definitions = Vec::with_capacity(indexes.len());
+        for index in indexes {
+            self.file.drop_tree(index.name())?;
+            definitions.push(index.definition.clone());
+        }
+        Ok(definitions)
+    }
+
+    /// Empty the trees of `table`'s indexes
+    pub fn clear_table(&self, table: &str) -> Result<()> {
+        let indexes = self.of_table(table);
+        for index in indexes {
+            self.file.drop_tree(index.name())?;
+            let tree = self.file.create_tree(index.name())?;
+            self.register(TableIndex {
+                definition: index.definition.clone(),
+                positions: index.positions.clone(),
+                types: index.types.clone(),
+                predicate_columns: index.predicate_columns.clone(),
+                tree,
+                graph: index
+                    .definition
+                    .hnsw
+                    .clone()
+                    .map(|options| RwLock::new(HnswGraph::new(options))),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<TableIndex>> {
+        self.tables
+            .read()
+            .values()
+            .flatten()
+            .find(|index| index.name() == name)
+            .cloned()
+    }
+
+    pub fn of_table(&self, table: &str) -> Vec<Arc<TableIndex>> {
+        self.tables.read().get(table).cloned().unwrap_or_default()
+    }
+
+    /// Fail if writing `rows` to `table` would give two rows the same key
+    /// in a unique index
+    ///
+    /// Each row comes with the id of the row it replaces, if any; `None`
+    /// rows are deletes. Runs under the commit lock, before the heap changes.
+    pub fn check_unique(
+        &self,
+        table: &str,
+        rows: &[(Option<RowId>, Option<&[Value]>)],
+    ) -> Result<()> {
+        let replaced: HashSet<u64> = rows
+            .iter()
+            .filter_map(|(rid, _)| rid.map(|rid| rid.to_u64()))
+            .collect();
+        for index in self.of_table(table).iter().filter(|i| i.is_unique()) {
+            let mut seen = HashSet::new();
+            for row in rows.iter().filter_map(|(_, row)| *row) {
+                let Some(key) = index.key(row)? else {
+                    continue;
+                };
+                if has_null(&key) {
+                    continue;
+                }
+                let taken = index
+                    .tree
+                    .search(&key)?
+                    .into_iter()
+                    .any(|rid| !replaced.contains(&rid));
+                if taken || seen.contains(&key) {
+                    return Err(index.duplicate(&key));
+                }
+                seen.insert(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring the trees in line with a commit's heap changes; on failure the
+    /// trees are left as they were. Runs under the commit lock.
+    pub fn apply(&self, changes: &[RowChange]) -> Result<()> {
+        let (removed, added) = self.edits(changes, false)?;
+        let mut done: Vec<(&Edit, bool)> = Vec::new();
+        let result = (|| {
+            // Removals first, so rows can swap keys within one commit
+            for edit in &removed {
+                edit.index.delete_entry(&edit.key, edit.rid)?;
+                done.push((edit, false));
+            }
+            for edit in &added {
+                edit.index.insert_entry(&edit.key, edit.rid)?;
+                done.push((edit, true));
+            }
+            Ok(())
+        })();
+
+        if result.is_err() {
+            for (edit, inserted) in done.into_iter().rev() {
+                let undone = if inserted {
+                    edit.index.delete_entry(&edit.key, edit.rid)
+                } else {
+                    edit.index.insert_entry(&edit.key, edit.rid)
+                };
+                if let Err(e) = undone {
+                    tracing::error!(
+                        "Failed to undo a change to index {}: {}",
+                        edit.index.name(),
+                        e
+                    );
+                }
+            }
+        }
+        result
+    }
+
+    /// Undo `apply` for changes whose heap commit failed afterwards
+    pub fn revert(&self, changes: &[RowChange]) -> Result<()> {
+        let (removed, added) = self.edits(changes, true)?;
+        for edit in &removed {
+            edit.index.delete_entry(&edit.key, edit.rid)?;
+        }
+        for edit in &added {
+            edit.index.insert_entry(&edit.key, edit.rid)?;
+        }
+        Ok(())
+    }
+
+    // Entries to remove and to add for `changes`, or for undoing them
+    fn edits(&self, changes: &[RowChange], undo: bool) -> Result<(Vec<Edit>, Vec<Edit>)> {
+        let (mut removed, mut added) = (Vec::new(), Vec::new());
+        for change in changes {
+            let (before, after) = match undo {
+                false => (&change.before, &change.after),
+                true => (&change.after, &change.before),
+            };
+            for index in self.of_table(&change.table) {
+                let key = |row: &Option<Vec<Value>>| match row {
+                    Some(row) => index.key(row),
+                    None => Ok(None),
+                };
+                let (old, new) = (key(before)?, key(after)?);
+                if old == new {
+                    continue;
+                }
+                let rid = change.rid;
+                if let Some(key) = old {
+                    removed.push(Edit {
+                        index: index.clone(),
+                        key,
+                        rid,
+                    });
+                }
+                if let Some(key) = new {
+                    added.push(Edit {
+                        index: index.clone(),
+                        key,
+                        rid,
+                    });
+                }
+            }
+        }
+        Ok((removed, added))
+    }
+}
@@ -114,6 +114,9 @@ pub use unix_io_uring::{
     CqeEntry, IoUringConfig, IoUringEngine, IoUringStats, SqeEntry, UringProbe,
 };
 
+#[cfg(target_os = "linux")]
+pub use unix_io_uring::KernelRing;
+
 // ============================================================================
 // Constants
 // ============================================================================
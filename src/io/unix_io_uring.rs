@@ -5,9 +5,12 @@
 use crate::error::{DbError, Result};
 use crate::io::{IoCompletion, IoOpType, IoRequest, IoStatus};
 use parking_lot::Mutex;
-use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::collections::{HashMap, VecDeque};
+#[cfg(target_os = "linux")]
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::Instant;
 
 // ============================================================================
 // io_uring Configuration
@@ -130,6 +133,36 @@ impl SqeEntry {
         sqe
     }
 
+    /// Create read SQE into a registered buffer
+    pub fn read_fixed(
+        fd: i32,
+        buffer: *mut u8,
+        len: u32,
+        offset: u64,
+        buf_index: u16,
+        user_data: u64,
+    ) -> Self {
+        let mut sqe = Self::read(fd, buffer, len, offset, user_data);
+        sqe.opcode = IORING_OP_READ_FIXED;
+        sqe.buf_index = buf_index;
+        sqe
+    }
+
+    /// Create write SQE from a registered buffer
+    pub fn write_fixed(
+        fd: i32,
+        buffer: *const u8,
+        len: u32,
+        offset: u64,
+        buf_index: u16,
+        user_data: u64,
+    ) -> Self {
+        let mut sqe = Self::write(fd, buffer, len, offset, user_data);
+        sqe.opcode = IORING_OP_WRITE_FIXED;
+        sqe.buf_index = buf_index;
+        sqe
+    }
+
     /// Create fsync SQE
     pub fn fsync(fd: i32, user_data: u64) -> Self {
         Self::new(IORING_OP_FSYNC, fd, user_data)
@@ -199,9 +232,7 @@ const IORING_OP_NOP: u8 = 0;
 const IORING_OP_READV: u8 = 1;
 const IORING_OP_WRITEV: u8 = 2;
 const IORING_OP_FSYNC: u8 = 3;
-#[allow(dead_code)]
 const IORING_OP_READ_FIXED: u8 = 4;
-#[allow(dead_code)]
 const IORING_OP_WRITE_FIXED: u8 = 5;
 #[allow(dead_code)]
 const IORING_OP_POLL_ADD: u8 = 6;
@@ -231,6 +262,26 @@ const IORING_SETUP_SQ_AFF: u32 = 1 << 2;
 #[allow(dead_code)]
 const IORING_SETUP_CQSIZE: u32 = 1 << 3;
 
+// mmap offsets of the ring regions
+#[cfg(target_os = "linux")]
+const IORING_OFF_SQ_RING: i64 = 0;
+#[cfg(target_os = "linux")]
+const IORING_OFF_CQ_RING: i64 = 0x8000000;
+#[cfg(target_os = "linux")]
+const IORING_OFF_SQES: i64 = 0x10000000;
+
+// Features, enter flags and register opcodes
+#[cfg(target_os = "linux")]
+const IORING_FEAT_SINGLE_MMAP: u32 = 1 << 0;
+#[cfg(target_os = "linux")]
+const IORING_ENTER_GETEVENTS: u32 = 1 << 0;
+#[cfg(target_os = "linux")]
+const IORING_ENTER_SQ_WAKEUP: u32 = 1 << 1;
+#[cfg(target_os = "linux")]
+const IORING_SQ_NEED_WAKEUP: u32 = 1 << 0;
+#[cfg(target_os = "linux")]
+const IORING_REGISTER_BUFFERS: u32 = 0;
+
 // ============================================================================
 // io_uring Statistics
 // ============================================================================
@@ -280,39 +331,425 @@ impl UringProbe {
     }
 }
 
+// ============================================================================
+// Kernel Ring
+// ============================================================================
+
+/// Layouts of the kernel's setup parameters (`struct io_uring_params`)
+#[cfg(target_os = "linux")]
+mod sys {
+    #[repr(C)]
+    #[derive(Debug, Default)]
+    pub struct SqringOffsets {
+        pub head: u32,
+        pub tail: u32,
+        pub ring_mask: u32,
+        pub ring_entries: u32,
+        pub flags: u32,
+        pub dropped: u32,
+        pub array: u32,
+        pub resv1: u32,
+        pub user_addr: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Default)]
+    pub struct CqringOffsets {
+        pub head: u32,
+        pub tail: u32,
+        pub ring_mask: u32,
+        pub ring_entries: u32,
+        pub overflow: u32,
+        pub cqes: u32,
+        pub flags: u32,
+        pub resv1: u32,
+        pub user_addr: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Default)]
+    pub struct Params {
+        pub sq_entries: u32,
+        pub cq_entries: u32,
+        pub flags: u32,
+        pub sq_thread_cpu: u32,
+        pub sq_thread_idle: u32,
+        pub features: u32,
+        pub wq_fd: u32,
+        pub resv: [u32; 3],
+        pub sq_off: SqringOffsets,
+        pub cq_off: CqringOffsets,
+    }
+
+    pub unsafe fn setup(entries: u32, params: *mut Params) -> i32 {
+        libc::syscall(libc::SYS_io_uring_setup, entries, params) as i32
+    }
+
+    pub unsafe fn enter(fd: i32, to_submit: u32, min_complete: u32, flags: u32) -> i32 {
+        libc::syscall(
+            libc::SYS_io_uring_enter,
+            fd,
+            to_submit,
+            min_complete,
+            flags,
+            std::ptr::null::<libc::sigset_t>(),
+            0usize,
+        ) as i32
+    }
+
+    pub unsafe fn register(fd: i32, opcode: u32, arg: *const libc::c_void, nr_args: u32) -> i32 {
+        libc::syscall(libc::SYS_io_uring_register, fd, opcode, arg, nr_args) as i32
+    }
+}
+
+/// Owned ring file descriptor
+#[cfg(target_os = "linux")]
+struct RingFd(i32);
+
+#[cfg(target_os = "linux")]
+impl Drop for RingFd {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+/// A region of the ring shared with the kernel
+#[cfg(target_os = "linux")]
+struct RingMap {
+    ptr: *mut u8,
+    len: usize,
+}
+
+#[cfg(target_os = "linux")]
+impl RingMap {
+    fn new(fd: i32, len: usize, offset: i64) -> Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                offset as libc::off_t,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self {
+            ptr: ptr as *mut u8,
+            len,
+        })
+    }
+
+    fn at<T>(&self, offset: u32) -> *mut T {
+        unsafe { self.ptr.add(offset as usize) as *mut T }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for RingMap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+/// A kernel io_uring instance: the submission and completion rings mapped
+/// into this process
+///
+/// SQEs are written with [`KernelRing::push`] and handed to the kernel with
+/// [`KernelRing::submit`]; completions are read back with
+/// [`KernelRing::pop_completion`]. Buffers referenced by an SQE must stay
+/// alive until its completion has been popped.
+#[cfg(target_os = "linux")]
+pub struct KernelRing {
+    _sq_ring: RingMap,
+    // `None` when the kernel maps both rings in one region
+    _cq_ring: Option<RingMap>,
+    sqes: RingMap,
+    fd: RingFd,
+
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_flags: *const AtomicU32,
+    sq_array: *mut u32,
+    sq_mask: u32,
+    sq_entries: u32,
+
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_overflow: *const AtomicU32,
+    cqes: *const CqeEntry,
+    cq_mask: u32,
+
+    features: u32,
+    sqpoll: bool,
+    iopoll: bool,
+
+    // SQEs pushed but not yet handed to the kernel
+    unsubmitted: u32,
+}
+
+// The ring memory is only touched through `&mut self`
+#[cfg(target_os = "linux")]
+unsafe impl Send for KernelRing {}
+
+#[cfg(target_os = "linux")]
+impl KernelRing {
+    /// Set up a ring with `config.queue_depth` submission slots, with SQPOLL
+    /// and IOPOLL as configured
+    pub fn new(config: &IoUringConfig) -> Result<Self> {
+        let mut flags = 0;
+        if config.sqpoll {
+            flags |= IORING_SETUP_SQPOLL;
+        }
+        if config.iopoll {
+            flags |= IORING_SETUP_IOPOLL;
+        }
+        let mut params = sys::Params {
+            flags,
+            sq_thread_idle: config.sqpoll_idle_ms,
+            ..Default::default()
+        };
+        let fd = unsafe { sys::setup(config.queue_depth, &mut params) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let fd = RingFd(fd);
+
+        let sq_len =
+            params.sq_off.array as usize + params.sq_entries as usize * std::mem::size_of::<u32>();
+        let cq_len = params.cq_off.cqes as usize
+            + params.cq_entries as usize * std::mem::size_of::<CqeEntry>();
+        let single_mmap = params.features & IORING_FEAT_SINGLE_MMAP != 0;
+
+        let sq_ring = RingMap::new(
+            fd.0,
+            if single_mmap {
+                sq_len.max(cq_len)
+            } else {
+                sq_len
+            },
+            IORING_OFF_SQ_RING,
+        )?;
+        let cq_ring = if single_mmap {
+            None
+        } else {
+            Some(RingMap::new(fd.0, cq_len, IORING_OFF_CQ_RING)?)
+        };
+        let sqes = RingMap::new(
+            fd.0,
+            params.sq_entries as usize * std::mem::size_of::<SqeEntry>(),
+            IORING_OFF_SQES,
+        )?;
+
+        let cq = cq_ring.as_ref().unwrap_or(&sq_ring);
+        let cq_head = cq.at(params.cq_off.head);
+        let cq_tail = cq.at(params.cq_off.tail);
+        let cq_overflow = cq.at(params.cq_off.overflow);
+        let cqes = cq.at(params.cq_off.cqes);
+        let cq_mask = unsafe { *cq.at::<u32>(params.cq_off.ring_mask) };
+
+        Ok(Self {
+            sq_head: sq_ring.at(params.sq_off.head),
+            sq_tail: sq_ring.at(params.sq_off.tail),
+            sq_flags: sq_ring.at(params.sq_off.flags),
+            sq_array: sq_ring.at(params.sq_off.array),
+            sq_mask: unsafe { *sq_ring.at::<u32>(params.sq_off.ring_mask) },
+            sq_entries: params.sq_entries,
+            cq_head,
+            cq_tail,
+            cq_overflow,
+            cqes,
+            cq_mask,
+            features: params.features,
+            sqpoll: config.sqpoll,
+            iopoll: config.iopoll,
+            unsubmitted: 0,
+            _sq_ring: sq_ring,
+            _cq_ring: cq_ring,
+            sqes,
+            fd,
+        })
+    }
+
+    /// Number of submission slots
+    pub fn capacity(&self) -> u32 {
+        self.sq_entries
+    }
+
+    /// `IORING_FEAT_*` flags reported by the kernel
+    pub fn features(&self) -> u32 {
+        self.features
+    }
+
+    /// Completions the kernel dropped because the completion ring was full
+    pub fn overflow(&self) -> u32 {
+        unsafe { (*self.cq_overflow).load(Ordering::Acquire) }
+    }
+
+    /// Write an SQE to the submission ring. Returns false if the ring is full.
+    pub fn push(&mut self, sqe: SqeEntry) -> bool {
+        unsafe {
+            let head = (*self.sq_head).load(Ordering::Acquire);
+            let tail = (*self.sq_tail).load(Ordering::Relaxed);
+            if tail.wrapping_sub(head) >= self.sq_entries {
+                return false;
+            }
+
+            let index = tail & self.sq_mask;
+            std::ptr::write(self.sqes.at::<SqeEntry>(0).add(index as usize), sqe);
+            *self.sq_array.add(index as usize) = index;
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+        self.unsubmitted += 1;
+        true
+    }
+
+    /// Hand pushed SQEs to the kernel, then wait until at least `wait_for`
+    /// completions are available. Returns the number of SQEs submitted.
+    pub fn submit(&mut self, wait_for: u32) -> Result<u32> {
+        let mut flags = 0;
+        if wait_for > 0 || self.iopoll {
+            flags |= IORING_ENTER_GETEVENTS;
+        }
+
+        let to_submit = self.unsubmitted;
+        if self.sqpoll {
+            // The kernel thread picks up new SQEs by itself unless it has
+            // gone idle and needs waking
+            std::sync::atomic::fence(Ordering::SeqCst);
+            if unsafe { (*self.sq_flags).load(Ordering::Relaxed) } & IORING_SQ_NEED_WAKEUP != 0 {
+                flags |= IORING_ENTER_SQ_WAKEUP;
+            }
+            self.unsubmitted = 0;
+            if flags == 0 {
+                return Ok(to_submit);
+            }
+        } else if to_submit == 0 && flags == 0 {
+            return Ok(0);
+        }
+
+        loop {
+            let ret = unsafe { sys::enter(self.fd.0, to_submit, wait_for, flags) };
+            if ret >= 0 {
+                if self.sqpoll {
+                    return Ok(to_submit);
+                }
+                self.unsubmitted -= ret as u32;
+                return Ok(ret as u32);
+            }
+            let err = std::io::Error::last_os_error();
+            if err.kind() != std::io::ErrorKind::Interrupted {
+                return Err(err.into());
+            }
+        }
+    }
+
+    /// Take the oldest completion, if any
+    pub fn pop_completion(&mut self) -> Option<CqeEntry> {
+        unsafe {
+            let head = (*self.cq_head).load(Ordering::Relaxed);
+            let tail = (*self.cq_tail).load(Ordering::Acquire);
+            if head == tail {
+                return None;
+            }
+
+            let cqe = std::ptr::read(self.cqes.add((head & self.cq_mask) as usize));
+            (*self.cq_head).store(head.wrapping_add(1), Ordering::Release);
+            Some(cqe)
+        }
+    }
+
+    /// Register buffers for READ_FIXED/WRITE_FIXED; an SQE names one by its
+    /// index in `buffers`. They stay registered until the ring is dropped.
+    pub fn register_buffers(&mut self, buffers: &[libc::iovec]) -> Result<()> {
+        let ret = unsafe {
+            sys::register(
+                self.fd.0,
+                IORING_REGISTER_BUFFERS,
+                buffers.as_ptr() as *const libc::c_void,
+                buffers.len() as u32,
+            )
+        };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+}
+
+/// Serve an SQE with the equivalent blocking syscall, producing the CQE the
+/// kernel ring would have posted
+fn execute_sync(sqe: &SqeEntry) -> CqeEntry {
+    let ret = unsafe {
+        match sqe.opcode {
+            IORING_OP_READ | IORING_OP_READ_FIXED => libc::pread(
+                sqe.fd,
+                sqe.addr as *mut libc::c_void,
+                sqe.len as usize,
+                sqe.off as libc::off_t,
+            ) as i64,
+            IORING_OP_WRITE | IORING_OP_WRITE_FIXED => libc::pwrite(
+                sqe.fd,
+                sqe.addr as *const libc::c_void,
+                sqe.len as usize,
+                sqe.off as libc::off_t,
+            ) as i64,
+            #[cfg(target_os = "linux")]
+            IORING_OP_FSYNC if sqe.op_flags & IORING_FSYNC_DATASYNC != 0 => {
+                libc::fdatasync(sqe.fd) as i64
+            }
+            IORING_OP_FSYNC => libc::fsync(sqe.fd) as i64,
+            _ => {
+                return CqeEntry {
+                    user_data: sqe.user_data,
+                    res: -libc::EINVAL,
+                    flags: 0,
+                };
+            }
+        }
+    };
+
+    let res = if ret < 0 {
+        -std::io::Error::last_os_error()
+            .raw_os_error()
+            .unwrap_or(libc::EIO)
+    } else {
+        ret as i32
+    };
+    CqeEntry {
+        user_data: sqe.user_data,
+        res,
+        flags: 0,
+    }
+}
+
 // ============================================================================
 // io_uring Engine
 // ============================================================================
 
 /// io_uring-based I/O engine
+///
+/// Requests go through a kernel ring when the kernel offers one and are
+/// served with synchronous pread/pwrite otherwise.
 pub struct IoUringEngine {
     /// Configuration
     config: IoUringConfig,
 
-    /// Ring file descriptor (if available, currently unused in simulated implementation)
+    /// Kernel ring (`None` when falling back to synchronous I/O)
     #[cfg(target_os = "linux")]
-    #[allow(dead_code)]
-    ring_fd: i32,
+    ring: Option<Mutex<KernelRing>>,
 
-    /// Submission queue head
-    sq_head: Arc<AtomicU32>,
+    /// Completions of requests served synchronously
+    completed: Mutex<VecDeque<CqeEntry>>,
 
-    /// Submission queue tail
-    sq_tail: Arc<AtomicU32>,
-
-    /// Completion queue head
-    cq_head: Arc<AtomicU32>,
-
-    /// Completion queue tail
-    cq_tail: Arc<AtomicU32>,
-
-    /// Submission queue entries
-    #[cfg(target_os = "linux")]
-    sq_entries: Arc<Mutex<Vec<SqeEntry>>>,
-
-    /// Completion queue entries
-    #[cfg(target_os = "linux")]
-    cq_entries: Arc<Mutex<Vec<CqeEntry>>>,
+    /// Operation type and submit time of requests in flight
+    in_flight: Mutex<HashMap<u64, (IoOpType, Instant)>>,
 
     /// Statistics
     stats: Arc<Mutex<IoUringStats>>,
@@ -321,143 +758,166 @@ pub struct IoUringEngine {
     pending_count: AtomicU64,
 }
 
-#[cfg(target_os = "linux")]
 impl IoUringEngine {
     /// Create new io_uring engine
     pub fn new(config: IoUringConfig) -> Result<Self> {
-        // For now, we'll create a simulated io_uring
-        // In production, this would use actual io_uring syscalls
+        #[cfg(target_os = "linux")]
+        let ring = match KernelRing::new(&config) {
+            Ok(ring) => Some(Mutex::new(ring)),
+            Err(e) => {
+                tracing::warn!("io_uring unavailable, using synchronous I/O: {}", e);
+                None
+            }
+        };
 
         Ok(Self {
-            config: config.clone(),
-            ring_fd: -1, // Invalid fd for simulation
-            sq_head: Arc::new(AtomicU32::new(0)),
-            sq_tail: Arc::new(AtomicU32::new(0)),
-            cq_head: Arc::new(AtomicU32::new(0)),
-            cq_tail: Arc::new(AtomicU32::new(0)),
-            sq_entries: Arc::new(Mutex::new(Vec::with_capacity(config.queue_depth as usize))),
-            cq_entries: Arc::new(Mutex::new(Vec::new())),
+            config,
+            #[cfg(target_os = "linux")]
+            ring,
+            completed: Mutex::new(VecDeque::new()),
+            in_flight: Mutex::new(HashMap::new()),
             stats: Arc::new(Mutex::new(IoUringStats::default())),
             pending_count: AtomicU64::new(0),
         })
     }
 
-    /// Submit an I/O request
-    pub fn submit(&self, request: &mut IoRequest) -> Result<()> {
-        let sqe = match request.op_type {
-            IoOpType::Read | IoOpType::ReadV => SqeEntry::read(
-                request.file_handle.0 as i32,
-                request.buffer,
-                request.len,
-                request.offset,
-                request.id,
-            ),
-            IoOpType::Write | IoOpType::WriteV => SqeEntry::write(
-                request.file_handle.0 as i32,
-                request.buffer,
-                request.len,
-                request.offset,
-                request.id,
-            ),
-            IoOpType::Fsync => SqeEntry::fsync(request.file_handle.0 as i32, request.id),
-            IoOpType::Fdatasync => SqeEntry::fdatasync(request.file_handle.0 as i32, request.id),
-            _ => {
-                return Err(DbError::Internal(format!(
-                    "Unsupported operation: {:?}",
-                    request.op_type
-                )));
-            }
-        };
-
-        // Add to submission queue
-        let mut sq = self.sq_entries.lock();
-        if sq.len() >= self.config.queue_depth as usize {
-            self.stats.lock().sq_full += 1;
-            return Err(DbError::Internal("Submission queue full".to_string()));
+    /// Whether requests go through a kernel ring
+    pub fn is_kernel_backed(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            self.ring.is_some()
         }
+        #[cfg(not(target_os = "linux"))]
+        {
+            false
+        }
+    }
 
-        sq.push(sqe);
-        self.sq_tail.fetch_add(1, Ordering::Release);
-        self.pending_count.fetch_add(1, Ordering::Relaxed);
-        self.stats.lock().submissions += 1;
-
-        // In a real implementation, we would call io_uring_enter here
-        self.process_submissions()?;
-
-        Ok(())
+    /// Submit an I/O request
+    pub fn submit(&self, request: &mut IoRequest) -> Result<()> {
+        self.enqueue(request)?;
+        self.flush()
     }
 
     /// Submit multiple requests
     pub fn submit_batch(&self, requests: &mut [IoRequest]) -> Result<usize> {
         let mut submitted = 0;
 
-        for request in requests {
-            if self.submit(request).is_ok() {
+        for request in requests.iter() {
+            if self.enqueue(request).is_ok() {
                 submitted += 1;
             } else {
                 break;
             }
         }
 
+        // One enter for the whole batch
+        self.flush()?;
         Ok(submitted)
     }
 
-    /// Process submission queue (simulated)
-    fn process_submissions(&self) -> Result<()> {
-        let mut sq = self.sq_entries.lock();
-        let mut cq = self.cq_entries.lock();
-
-        // In a real implementation, this would be handled by the kernel
-        // For simulation, we immediately complete operations
-
-        while let Some(sqe) = sq.pop() {
-            // Simulate I/O completion
-            let res = match sqe.opcode {
-                IORING_OP_READ | IORING_OP_READV => {
-                    // Simulate successful read
-                    sqe.len as i32
-                }
-                IORING_OP_WRITE | IORING_OP_WRITEV => {
-                    // Simulate successful write
-                    sqe.len as i32
-                }
-                IORING_OP_FSYNC => {
-                    // Simulate successful sync
-                    0
-                }
-                _ => {
-                    // Unsupported operation
-                    -libc::EINVAL
-                }
-            };
-
-            let cqe = CqeEntry {
-                user_data: sqe.user_data,
-                res,
-                flags: 0,
-            };
+    /// Queue a request on the ring, or serve it right away without one
+    fn enqueue(&self, request: &IoRequest) -> Result<()> {
+        let fd = request.file_handle.0 as i32;
+        let sqe = match request.op_type {
+            IoOpType::Read | IoOpType::ReadV => {
+                SqeEntry::read(fd, request.buffer, request.len, request.offset, request.id)
+            }
+            IoOpType::Write | IoOpType::WriteV => {
+                SqeEntry::write(fd, request.buffer, request.len, request.offset, request.id)
+            }
+            IoOpType::Fsync => SqeEntry::fsync(fd, request.id),
+            IoOpType::Fdatasync => SqeEntry::fdatasync(fd, request.id),
+            _ => {
+                return Err(DbError::Internal(format!(
+                    "Unsupported operation: {:?}",
+                    request.op_type
+                )));
+            }
+        };
 
-            cq.push(cqe);
-            self.cq_tail.fetch_add(1, Ordering::Release);
+        // Tracked before the kernel can see it, so a concurrent poll finds it
+        self.in_flight
+            .lock()
+            .insert(request.id, (request.op_type, Instant::now()));
+
+        #[cfg(target_os = "linux")]
+        if let Some(ring) = &self.ring {
+            let mut ring = ring.lock();
+            // A full ring makes room by handing what is queued to the kernel
+            if !ring.push(sqe) && (ring.submit(0).is_err() || !ring.push(sqe)) {
+                self.in_flight.lock().remove(&request.id);
+                self.stats.lock().sq_full += 1;
+                return Err(DbError::Internal("Submission queue full".to_string()));
+            }
+            self.pending_count.fetch_add(1, Ordering::Relaxed);
+            self.stats.lock().submissions += 1;
+            return Ok(());
         }
 
-        self.sq_head
-            .store(self.sq_tail.load(Ordering::Acquire), Ordering::Release);
+        let cqe = execute_sync(&sqe);
+        self.completed.lock().push_back(cqe);
+        self.pending_count.fetch_add(1, Ordering::Relaxed);
+        self.stats.lock().submissions += 1;
+        Ok(())
+    }
 
+    /// Hand queued requests to the kernel
+    fn flush(&self) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(ring) = &self.ring {
+            ring.lock().submit(0)?;
+        }
         Ok(())
     }
 
     /// Poll for completions
     pub fn poll(&self, max_completions: usize) -> Result<Vec<IoCompletion>> {
-        let mut cq = self.cq_entries.lock();
-        let mut completions = Vec::with_capacity(max_completions.min(cq.len()));
+        let mut cqes = Vec::new();
+        {
+            let mut completed = self.completed.lock();
+            while cqes.len() < max_completions {
+                match completed.pop_front() {
+                    Some(cqe) => cqes.push(cqe),
+                    None => break,
+                }
+            }
+        }
 
-        for _ in 0..max_completions {
-            if let Some(cqe) = cq.pop() {
-                self.cq_head.fetch_add(1, Ordering::Release);
+        #[cfg(target_os = "linux")]
+        if let Some(ring) = &self.ring {
+            let mut ring = ring.lock();
+            // With IOPOLL the kernel only reaps completions when asked to
+            if self.config.iopoll {
+                ring.submit(0)?;
+            }
+            while cqes.len() < max_completions {
+                match ring.pop_completion() {
+                    Some(cqe) => cqes.push(cqe),
+                    None => break,
+                }
+            }
+            self.stats.lock().cq_overflow = ring.overflow() as u64;
+        }
+
+        let mut in_flight = self.in_flight.lock();
+        let mut stats = self.stats.lock();
+        let completions = cqes
+            .into_iter()
+            .map(|cqe| {
+                let (op_type, submitted_at) = in_flight
+                    .remove(&cqe.user_data)
+                    .unwrap_or((IoOpType::Read, Instant::now()));
                 self.pending_count.fetch_sub(1, Ordering::Relaxed);
 
-                let completion = IoCompletion {
+                stats.completions += 1;
+                if let Some(bytes) = cqe.bytes_transferred() {
+                    stats.bytes_transferred += bytes as u64;
+                } else {
+                    stats.errors += 1;
+                }
+
+                IoCompletion {
                     id: cqe.user_data,
                     status: if cqe.is_success() {
                         IoStatus::Completed
@@ -466,28 +926,11 @@ impl IoUringEngine {
                     },
                     bytes_transferred: cqe.bytes_transferred().unwrap_or(0),
                     error_code: cqe.error_code().map(|e| e as usize).unwrap_or(0),
-                    // Duration tracking would require storing start timestamp in the SQE user_data
-                    // or maintaining a separate map of request_id -> start_time
-                    // For now, use zero duration as this is a simplified implementation
-                    duration: Duration::from_secs(0),
-                    // Op type tracking would require storing the operation type alongside user_data
-                    // or using a map of request_id -> op_type. Default to Read for now.
-                    op_type: IoOpType::Read,
-                };
-
-                completions.push(completion);
-
-                let mut stats = self.stats.lock();
-                stats.completions += 1;
-                if let Some(bytes) = cqe.bytes_transferred() {
-                    stats.bytes_transferred += bytes as u64;
-                } else {
-                    stats.errors += 1;
+                    duration: submitted_at.elapsed(),
+                    op_type,
                 }
-            } else {
-                break;
-            }
-        }
+            })
+            .collect();
 
         Ok(completions)
     }
@@ -505,65 +948,26 @@ impl IoUringEngine {
 
     /// Probe capabilities
     pub fn probe() -> Result<UringProbe> {
-        // In a real implementation, this would probe the kernel
-        Ok(UringProbe {
-            supported_ops: vec![
-                IORING_OP_READ,
-                IORING_OP_WRITE,
-                IORING_OP_READV,
-                IORING_OP_WRITEV,
-                IORING_OP_FSYNC,
-            ],
-            max_workers: 4,
-            features: 0,
-        })
-    }
-}
-
-// ============================================================================
-// Non-Linux Stub Implementation
-// ============================================================================
-
-#[cfg(not(target_os = "linux"))]
-impl IoUringEngine {
-    pub fn new(config: IoUringConfig) -> Result<Self> {
-        Ok(Self {
-            config,
-            sq_head: Arc::new(AtomicU32::new(0)),
-            sq_tail: Arc::new(AtomicU32::new(0)),
-            cq_head: Arc::new(AtomicU32::new(0)),
-            cq_tail: Arc::new(AtomicU32::new(0)),
-            stats: Arc::new(Mutex::new(IoUringStats::default())),
-            pending_count: AtomicU64::new(0),
-        })
-    }
-
-    pub fn submit(&self, _request: &mut IoRequest) -> Result<()> {
-        // Fallback to synchronous I/O on non-Linux systems
-        Err(DbError::Internal(
-            "io_uring not supported on this platform".to_string(),
-        ))
-    }
-
-    pub fn submit_batch(&self, _requests: &mut [IoRequest]) -> Result<usize> {
-        Err(DbError::Internal(
-            "io_uring not supported on this platform".to_string(),
-        ))
-    }
-
-    pub fn poll(&self, _max_completions: usize) -> Result<Vec<IoCompletion>> {
-        Ok(Vec::new())
-    }
-
-    pub fn stats(&self) -> IoUringStats {
-        self.stats.lock().clone()
-    }
-
-    pub fn pending_count(&self) -> u64 {
-        self.pending_count.load(Ordering::Relaxed)
-    }
+        #[cfg(target_os = "linux")]
+        if let Ok(ring) = KernelRing::new(&IoUringConfig {
+            queue_depth: 2,
+            ..Default::default()
+        }) {
+            return Ok(UringProbe {
+                supported_ops: vec![
+                    IORING_OP_READ,
+                    IORING_OP_WRITE,
+                    IORING_OP_READV,
+                    IORING_OP_WRITEV,
+                    IORING_OP_FSYNC,
+                    IORING_OP_READ_FIXED,
+                    IORING_OP_WRITE_FIXED,
+                ],
+                max_workers: 4,
+                features: ring.features(),
+            });
+        }
 
-    pub fn probe() -> Result<UringProbe> {
         Ok(UringProbe {
             supported_ops: Vec::new(),
             max_workers: 0,
@@ -578,6 +982,10 @@ impl IoUringEngine {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::io::IoHandle;
+    use std::os::unix::io::AsRawFd;
+
     #[test]
     fn test_sqe_creation() {
         let sqe = SqeEntry::read(3, std::ptr::null_mut(), 4096, 0, 42);
@@ -630,4 +1038,48 @@ mod tests {
         assert!(!config.sqpoll);
         assert!(!config.iopoll);
     }
+
+    #[test]
+    fn test_engine_round_trip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .open(dir.path().join("engine.dat"))?;
+        let handle = IoHandle(file.as_raw_fd() as usize);
+
+        let engine = IoUringEngine::new(IoUringConfig {
+            queue_depth: 64,
+            ..Default::default()
+        })?;
+
+        let wait_for = |id: u64| -> Result<IoCompletion> {
+            for _ in 0..1000 {
+                if let Some(completion) = engine.poll(1)?.pop() {
+                    assert_eq!(completion.id, id);
+                    return Ok(completion);
+                }
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            panic!("request {} never completed", id);
+        };
+
+        let mut data = vec![7u8; 4096];
+        let mut write = IoRequest::new(1, data.as_mut_ptr(), 4096, 4096, IoOpType::Write, handle);
+        engine.submit(&mut write)?;
+        let completion = wait_for(1)?;
+        assert_eq!(completion.status, IoStatus::Completed);
+        assert_eq!(completion.op_type, IoOpType::Write);
+        assert_eq!(completion.bytes_transferred, 4096);
+
+        let mut buffer = vec![0u8; 4096];
+        let mut read = IoRequest::new(2, buffer.as_mut_ptr(), 4096, 4096, IoOpType::Read, handle);
+        engine.submit(&mut read)?;
+        let completion = wait_for(2)?;
+        assert_eq!(completion.bytes_transferred, 4096);
+        assert_eq!(buffer, data);
+        assert_eq!(engine.pending_count(), 0);
+        Ok(())
+    }
 }
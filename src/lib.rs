@@ -276,6 +276,16 @@ pub mod execution;
 // **Target LOC:** 1,000+ lines
 pub mod cache;
 
+// ============================================================================
+// Database Handle - One running database shared by every front end
+// ============================================================================
+
+// Running database
+//
+// Owns storage, WAL, catalog, transactions, security and monitoring for the
+// server process, and coordinates startup recovery and orderly shutdown.
+pub mod database;
+
 // ============================================================================
 // Network Layer - Client/server communication
 // ============================================================================
@@ -846,6 +856,7 @@ pub use common::{
     Recoverable, ReplicableState, ResourceLimits, Schema, SystemEvent, TableId, TransactionId,
     Transactional, Tuple, Value,
 };
+pub use database::Database;
 pub use error::{DbError, Result};
 
 // ============================================================================
@@ -5,8 +5,8 @@
 
 use log::warn;
 use rusty_db::api::{ApiConfig, RestApiServer};
-use rusty_db::network::Server;
-use rusty_db::{DatabaseConfig, Result, VERSION};
+use rusty_db::network::{PgServer, Server};
+use rusty_db::{Database, DatabaseConfig, Result, VERSION};
 use std::fs;
 use std::path::PathBuf;
 use tracing::{error, info};
@@ -31,46 +31,55 @@ async fn main() -> Result<()> {
     let config_file = install_dir.join("rustydb.toml");
     let config_file_str = config_file.display().to_string();
 
-    // Load configuration (would load from file if it exists)
-    #[allow(deprecated)]
-    let mut config = DatabaseConfig::default();
-
-    // Set data directory relative to installation directory
-    config.data_dir = install_dir.join("data").display().to_string();
-    config.wal_dir = install_dir.join("wal").display().to_string();
-
-    // Ensure data directories exist
-    let _ = fs::create_dir_all(&config.data_dir);
-    let _ = fs::create_dir_all(&config.wal_dir);
+    // Load configuration from the file if there is one; otherwise keep data
+    // and WAL under the installation directory
+    let config_loaded = config_file.exists();
+    let config = if config_loaded {
+        DatabaseConfig::load(&config_file)?
+    } else {
+        DatabaseConfig {
+            data_dir: install_dir.join("data").display().to_string(),
+            wal_dir: install_dir.join("wal").display().to_string(),
+            ..DatabaseConfig::default()
+        }
+    };
 
     // Print comprehensive startup information
     print_startup_info(&install_dir_str, &config_file_str, &config);
     print_enabled_modules();
     print_data_store_info(&config);
-    print_config_file(&config_file_str, &config);
+    if !config_loaded {
+        print_config_file(&config_file_str, &config)?;
+    }
 
     info!("Initializing RustyDB server");
     info!("Version: {}", VERSION);
+    if config_loaded {
+        info!("Configuration loaded from {}", config_file_str);
+    }
 
-    // Initialize core subsystems
+    // Storage, WAL (with crash recovery), catalog, transactions, security
+    // and monitoring all live in one handle shared by every front end
     info!("Initializing core subsystems...");
+    let database = Database::open(config.clone()).await?;
+    info!(
+        "Database opened at {} ({} tables, catalog version {})",
+        config.data_dir,
+        database.catalog().list_tables().len(),
+        database.catalog().version()
+    );
 
-    // Note: In a full implementation, we would initialize:
-    // 1. Storage Engine - create DiskManager and BufferPool
-    // 2. Transaction Manager - set up MVCC and lock manager
-    // 3. Catalog - load system metadata
-    // 4. Index Manager - initialize index structures
-    // 5. Security Manager - load users and roles
-    // 6. Monitoring - start metrics collection
-    // 7. Clustering (if enabled) - join cluster
-    // 8. Replication (if enabled) - start replication threads
+    // The REST/GraphQL/WebSocket handlers share the same catalog, rows and
+    // transactions as the native protocol
+    rusty_db::api::rest::handlers::install_database(&database)?;
 
     info!("Core subsystems initialized successfully");
 
     // Start REST API server if enabled
-    if config.enable_rest_api {
+    let api_server = if config.enable_rest_api {
         let api_port = config.api_port;
-        tokio::spawn(async move {
+        let api_database = database.clone();
+        Some(tokio::spawn(async move {
             let api_config = ApiConfig {
                 port: api_port,
                 ..ApiConfig::default()
@@ -79,7 +88,7 @@ async fn main() -> Result<()> {
                 Ok(api_server) => {
                     let api_addr = format!("0.0.0.0:{}", api_port);
                     info!("Starting REST API server on {}", api_addr);
-                    if let Err(e) = api_server.run(&api_addr).await {
+                    if let Err(e) = api_server.with_database(api_database).run(&api_addr).await {
                         error!("REST API server error: {}", e);
                     }
                 }
@@ -87,12 +96,29 @@ async fn main() -> Result<()> {
                     error!("Failed to create REST API server: {}", e);
                 }
             }
-        });
-    }
+        }))
+    } else {
+        None
+    };
+
+    // Start the PostgreSQL protocol listener; it stops accepting once the
+    // database starts shutting down
+    let pg_server = if config.pg_port != 0 {
+        let pg_server = PgServer::new(database.clone());
+        let pg_addr = format!("{}:{}", config.listen_address, config.pg_port);
+        info!("Starting PostgreSQL protocol server on {}", pg_addr);
+        Some(tokio::spawn(async move {
+            if let Err(e) = pg_server.run(&pg_addr).await {
+                error!("PostgreSQL protocol server error: {}", e);
+            }
+        }))
+    } else {
+        None
+    };
 
     // Start network server
-    let server = Server::new();
-    let addr = format!("127.0.0.1:{}", config.port);
+    let server = Server::with_database(database.clone());
+    let addr = format!("{}:{}", config.listen_address, config.port);
 
     info!("Starting network server on {}", addr);
     println!();
@@ -103,6 +129,12 @@ async fn main() -> Result<()> {
         "│  Native protocol port: {}                              │",
         config.port
     );
+    if config.pg_port != 0 {
+        println!(
+            "│  PostgreSQL protocol port: {}                          │",
+            config.pg_port
+        );
+    }
     if config.enable_rest_api {
         println!(
             "│  REST API: http://0.0.0.0:{}                          │",
@@ -116,26 +148,31 @@ async fn main() -> Result<()> {
     println!("╰─────────────────────────────────────────────────────────╯");
     println!();
 
-    // Run server (blocks until shutdown)
-    let result = server.run(&addr).await;
+    // Run server until it fails or shutdown is requested
+    let result = tokio::select! {
+        result = server.run(&addr) => result,
+        _ = tokio::signal::ctrl_c() => {
+            info!("Shutdown requested");
+            Ok(())
+        }
+    };
 
     if let Err(ref e) = result {
         warn!("Server stopped with error: {}", e);
-    } else {
-        info!("Server stopped gracefully");
     }
 
-    // Shutdown subsystems
+    // Stop accepting connections, let open ones finish, then flush the
+    // buffer pool, write a final checkpoint and close the WAL
     info!("Shutting down subsystems...");
-
-    // Note: In a full implementation, we would:
-    // 1. Stop accepting new connections
-    // 2. Complete in-flight transactions
-    // 3. Flush buffer pool to disk
-    // 4. Close all file descriptors
-    // 5. Leave cluster (if clustered)
-    // 6. Stop replication
-    // 7. Write final checkpoint
+    if let Err(e) = database.shutdown().await {
+        error!("Shutdown failed: {}", e);
+    }
+    if let Some(pg_server) = pg_server {
+        let _ = pg_server.await;
+    }
+    if let Some(api_server) = api_server {
+        let _ = api_server.await;
+    }
 
     info!("Shutdown complete");
 
@@ -201,6 +238,7 @@ fn print_startup_info(install_dir: &str, config_file: &str, config: &DatabaseCon
         config.listen_address
     );
     println!("│   Native Protocol Port:   {:<30} │", config.port);
+    println!("│   PostgreSQL Port:        {:<30} │", config.pg_port);
     println!("│   REST API Port:          {:<30} │", config.api_port);
     println!(
         "│   REST API Enabled:       {:<30} │",
@@ -229,6 +267,10 @@ fn print_startup_info(install_dir: &str, config_file: &str, config: &DatabaseCon
         "│   Session Timeout:        {:<30} │",
         format!("{} seconds", config.session_timeout.as_secs())
     );
+    println!(
+        "│   SQL Firewall:           {:<30} │",
+        format!("{:?}", config.firewall.mode)
+    );
     println!("├─────────────────────────────────────────────────────────────┤");
     println!("│ CLUSTERING SETTINGS                                         │");
     println!(
@@ -439,7 +481,7 @@ fn print_data_store_info(config: &DatabaseConfig) {
     println!();
 }
 
-fn print_config_file(config_file: &str, config: &DatabaseConfig) {
+fn print_config_file(config_file: &str, config: &DatabaseConfig) -> Result<()> {
     println!("┌─────────────────────────────────────────────────────────────┐");
     println!("│ CONFIGURATION FILE FOR NEXT LOAD                            │");
     println!("│ Save to: {:<50} │", config_file);
@@ -451,66 +493,8 @@ fn print_config_file(config_file: &str, config: &DatabaseConfig) {
         config_file
     );
     println!();
-    println!("[storage]");
-    println!("data_dir = \"{}\"", config.data_dir);
-    println!("wal_dir = \"{}\"", config.wal_dir);
-    println!("page_size = {}", config.page_size);
-    println!("buffer_pool_size = {}", config.buffer_pool_size);
-    println!(
-        "checkpoint_interval_secs = {}",
-        config.checkpoint_interval.as_secs()
-    );
-    println!();
-    println!("[transaction]");
-    println!("default_isolation = \"{:?}\"", config.default_isolation);
-    println!("lock_timeout_secs = {}", config.lock_timeout.as_secs());
-    println!(
-        "deadlock_detection_interval_secs = {}",
-        config.deadlock_detection_interval.as_secs()
-    );
-    println!();
-    println!("[network]");
-    println!("listen_address = \"{}\"", config.listen_address);
-    println!("port = {}", config.port);
-    println!("api_port = {}", config.api_port);
-    println!("enable_rest_api = {}", config.enable_rest_api);
-    println!("max_connections = {}", config.max_connections);
-    println!(
-        "connection_timeout_secs = {}",
-        config.connection_timeout.as_secs()
-    );
-    println!();
-    println!("[security]");
-    println!("enable_tls = {}", config.enable_tls);
-    println!("enable_encryption = {}", config.enable_encryption);
-    println!("password_min_length = {}", config.password_min_length);
-    println!(
-        "session_timeout_secs = {}",
-        config.session_timeout.as_secs()
-    );
-    println!();
-    println!("[clustering]");
-    println!("cluster_enabled = {}", config.cluster_enabled);
-    println!("node_id = \"{}\"", config.node_id);
-    println!("replication_factor = {}", config.replication_factor);
-    println!();
-    println!("[performance]");
-    println!("worker_threads = {}", config.worker_threads);
-    println!("enable_jit = {}", config.enable_jit);
-    println!("enable_vectorization = {}", config.enable_vectorization);
-    if let Some(timeout) = config.query_timeout {
-        println!("query_timeout_secs = {}", timeout.as_secs());
-    }
-    println!("max_memory_mb = {}", config.max_memory_mb);
-    println!();
-    println!("[monitoring]");
-    println!("enable_metrics = {}", config.enable_metrics);
-    println!("metrics_port = {}", config.metrics_port);
-    println!(
-        "slow_query_threshold_ms = {}",
-        config.slow_query_threshold.as_millis()
-    );
-    println!();
+    println!("{}", config.to_toml_string()?);
     println!("─────────────────────────────────────────────────────────────");
     println!();
+    Ok(())
 }
@@ -8,9 +8,11 @@
 // - Load balancing across nodes
 // - Result aggregation from multiple nodes
 
+use crate::common::Value;
 use crate::error::DbError;
 use crate::execution::{planner::PlanNode, QueryResult};
 use parking_lot::RwLock;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
@@ -360,13 +362,14 @@ impl ResultAggregator {
         }
 
         let columns = results[0].columns.clone();
+        let column_types = results[0].column_types.clone();
         let mut all_rows = Vec::new();
 
         for result in results {
             all_rows.extend(result.rows);
         }
 
-        QueryResult::new(columns, all_rows)
+        QueryResult::typed(columns, column_types, all_rows)
     }
 
     // Aggregate distributed aggregations
@@ -383,26 +386,45 @@ impl ResultAggregator {
         }
     }
 
-    fn aggregate_count(results: Vec<QueryResult>) -> QueryResult {
-        let total: i64 = results
+    // First value of each partial result, skipping NULLs
+    fn partial_values(results: &[QueryResult]) -> impl Iterator<Item = &Value> {
+        results
             .iter()
             .filter_map(|r| r.rows.first())
             .filter_map(|row| row.first())
-            .filter_map(|val| val.parse::<i64>().ok())
+            .filter(|val| !val.is_null())
+    }
+
+    fn aggregate_count(results: Vec<QueryResult>) -> QueryResult {
+        let total: i64 = Self::partial_values(&results)
+            .filter_map(|val| match val {
+                Value::Integer(count) => Some(*count),
+                _ => None,
+            })
             .sum();
 
-        QueryResult::new(vec!["count".to_string()], vec![vec![total.to_string()]])
+        QueryResult::new(vec!["count".to_string()], vec![vec![Value::Integer(total)]])
     }
 
     fn aggregate_sum(results: Vec<QueryResult>) -> QueryResult {
-        let total: f64 = results
-            .iter()
-            .filter_map(|r| r.rows.first())
-            .filter_map(|row| row.first())
-            .filter_map(|val| val.parse::<f64>().ok())
-            .sum();
+        let values: Vec<&Value> = Self::partial_values(&results).collect();
+        let total = if values.is_empty() {
+            Value::Null
+        } else if values.iter().all(|val| matches!(val, Value::Integer(_))) {
+            Value::Integer(
+                values
+                    .iter()
+                    .map(|val| match val {
+                        Value::Integer(i) => *i,
+                        _ => 0,
+                    })
+                    .sum(),
+            )
+        } else {
+            Value::Float(values.iter().filter_map(|val| val.as_f64()).sum())
+        };
 
-        QueryResult::new(vec!["sum".to_string()], vec![vec![total.to_string()]])
+        QueryResult::new(vec!["sum".to_string()], vec![vec![total]])
     }
 
     fn aggregate_avg(results: Vec<QueryResult>) -> QueryResult {
@@ -413,7 +435,7 @@ impl ResultAggregator {
         for result in results {
             if let Some(row) = result.rows.first() {
                 if row.len() >= 2 {
-                    if let (Ok(sum), Ok(count)) = (row[0].parse::<f64>(), row[1].parse::<i64>()) {
+                    if let (Some(sum), Value::Integer(count)) = (row[0].as_f64(), &row[1]) {
                         total_sum += sum;
                         total_count += count;
                     }
@@ -422,34 +444,42 @@ impl ResultAggregator {
         }
 
         let avg = if total_count > 0 {
-            total_sum / total_count as f64
+            Value::Float(total_sum / total_count as f64)
         } else {
-            0.0
+            Value::Null
         };
 
-        QueryResult::new(vec!["avg".to_string()], vec![vec![avg.to_string()]])
+        QueryResult::new(vec!["avg".to_string()], vec![vec![avg]])
     }
 
     fn aggregate_min(results: Vec<QueryResult>) -> QueryResult {
-        let min = results
-            .iter()
-            .filter_map(|r| r.rows.first())
-            .filter_map(|row| row.first())
-            .cloned()
-            .min();
+        let min =
+            Self::partial_values(&results)
+                .cloned()
+                .reduce(|min, val| match val.sql_cmp(&min) {
+                    Some(Ordering::Less) => val,
+                    _ => min,
+                });
 
-        QueryResult::new(vec!["min".to_string()], vec![vec![min.unwrap_or_default()]])
+        QueryResult::new(
+            vec!["min".to_string()],
+            vec![vec![min.unwrap_or(Value::Null)]],
+        )
     }
 
     fn aggregate_max(results: Vec<QueryResult>) -> QueryResult {
-        let max = results
-            .iter()
-            .filter_map(|r| r.rows.first())
-            .filter_map(|row| row.first())
-            .cloned()
-            .max();
-
-        QueryResult::new(vec!["max".to_string()], vec![vec![max.unwrap_or_default()]])
+        let max =
+            Self::partial_values(&results)
+                .cloned()
+                .reduce(|max, val| match val.sql_cmp(&max) {
+                    Some(Ordering::Greater) => val,
+                    _ => max,
+                });
+
+        QueryResult::new(
+            vec!["max".to_string()],
+            vec![vec![max.unwrap_or(Value::Null)]],
+        )
     }
 }
 
@@ -560,15 +590,15 @@ mod tests {
     #[test]
     fn test_result_aggregation() {
         let results = vec![
-            QueryResult::new(vec!["count".to_string()], vec![vec!["10".to_string()]]),
-            QueryResult::new(vec!["count".to_string()], vec![vec!["20".to_string()]]),
-            QueryResult::new(vec!["count".to_string()], vec![vec!["30".to_string()]]),
+            QueryResult::new(vec!["count".to_string()], vec![vec![Value::Integer(10)]]),
+            QueryResult::new(vec!["count".to_string()], vec![vec![Value::Integer(20)]]),
+            QueryResult::new(vec!["count".to_string()], vec![vec![Value::Integer(30)]]),
         ];
 
         let aggregated =
             ResultAggregator::aggregate_partial_results(results, AggregationType::Count);
 
-        assert_eq!(aggregated.rows[0][0], "60");
+        assert_eq!(aggregated.rows[0][0], Value::Integer(60));
     }
 
     #[test]
@@ -2,6 +2,7 @@ pub mod advanced_protocol;
 pub mod cluster_network;
 pub mod distributed;
 pub mod ports;
+pub mod postgres;
 pub mod protocol;
 pub mod server;
 
@@ -21,5 +22,6 @@ pub use ports::{
     NatTraversal, PortAllocator, PortConfig, PortHealthChecker, PortManager, PortMappingService,
     ServiceType,
 };
+pub use postgres::{PgAuthMethod, PgServer};
 pub use protocol::{Request, Response};
 pub use server::Server;
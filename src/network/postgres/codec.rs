@@ -0,0 +1,508 @@
+// PostgreSQL v3 message framing
+//
+// After startup every message is a type byte followed by a big-endian i32
+// length that counts itself but not the type byte. Startup-phase packets
+// have no type byte.
+
+use crate::error::DbError;
+use bytes::{Buf, BufMut, BytesMut};
+use std::collections::HashMap;
+
+pub const PROTOCOL_VERSION_3: i32 = 196_608;
+pub const SSL_REQUEST_CODE: i32 = 80_877_103;
+pub const GSSENC_REQUEST_CODE: i32 = 80_877_104;
+pub const CANCEL_REQUEST_CODE: i32 = 80_877_102;
+
+/// Maximum size of a single protocol message (16MB)
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+fn violation(what: impl std::fmt::Display) -> DbError {
+    DbError::Network(format!("Protocol violation: {}", what))
+}
+
+// Packets a client may send before the session is established
+#[derive(Debug, Clone, PartialEq)]
+pub enum StartupPacket {
+    SslRequest,
+    GssEncRequest,
+    CancelRequest { process_id: i32, secret_key: i32 },
+    Startup { parameters: HashMap<String, String> },
+}
+
+impl StartupPacket {
+    // Decode one packet from the front of `buf`; `None` until it is complete
+    pub fn decode(buf: &mut BytesMut) -> Result<Option<Self>, DbError> {
+        let Some(body) = take_frame(buf, 0)? else {
+            return Ok(None);
+        };
+        let mut reader = Reader::new(&body);
+        let packet = match reader.i32()? {
+            SSL_REQUEST_CODE => StartupPacket::SslRequest,
+            GSSENC_REQUEST_CODE => StartupPacket::GssEncRequest,
+            CANCEL_REQUEST_CODE => StartupPacket::CancelRequest {
+                process_id: reader.i32()?,
+                secret_key: reader.i32()?,
+            },
+            PROTOCOL_VERSION_3 => {
+                let mut parameters = HashMap::new();
+                loop {
+                    let name = reader.cstr()?;
+                    if name.is_empty() {
+                        break;
+                    }
+                    parameters.insert(name, reader.cstr()?);
+                }
+                StartupPacket::Startup { parameters }
+            }
+            version => {
+                return Err(DbError::NotImplemented(format!(
+                    "Unsupported frontend protocol {}.{}",
+                    version >> 16,
+                    version & 0xffff
+                )))
+            }
+        };
+        Ok(Some(packet))
+    }
+}
+
+// Messages a client sends once the session is established
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrontendMessage {
+    Query(String),
+    Parse {
+        name: String,
+        query: String,
+        param_types: Vec<u32>,
+    },
+    Bind {
+        portal: String,
+        statement: String,
+        param_formats: Vec<i16>,
+        params: Vec<Option<Vec<u8>>>,
+        result_formats: Vec<i16>,
+    },
+    // `kind` is b'S' for a statement or b'P' for a portal
+    Describe {
+        kind: u8,
+        name: String,
+    },
+    Execute {
+        portal: String,
+        max_rows: u32,
+    },
+    Close {
+        kind: u8,
+        name: String,
+    },
+    Sync,
+    Flush,
+    Terminate,
+    // Password, SASL initial response or SASL response; which one depends
+    // on the authentication step
+    Password(Vec<u8>),
+}
+
+impl FrontendMessage {
+    // Decode one message from the front of `buf`; `None` until it is complete
+    pub fn decode(buf: &mut BytesMut) -> Result<Option<Self>, DbError> {
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        let tag = buf[0];
+        let Some(body) = take_frame(buf, 1)? else {
+            return Ok(None);
+        };
+        let mut reader = Reader::new(&body);
+        let message = match tag {
+            b'Q' => FrontendMessage::Query(reader.cstr()?),
+            b'P' => {
+                let name = reader.cstr()?;
+                let query = reader.cstr()?;
+                let count = reader.i16()?;
+                let param_types = (0..count)
+                    .map(|_| reader.i32().map(|oid| oid as u32))
+                    .collect::<Result<_, _>>()?;
+                FrontendMessage::Parse {
+                    name,
+                    query,
+                    param_types,
+                }
+            }
+            b'B' => {
+                let portal = reader.cstr()?;
+                let statement = reader.cstr()?;
+                let count = reader.i16()?;
+                let param_formats = (0..count).map(|_| reader.i16()).collect::<Result<_, _>>()?;
+                let count = reader.i16()?;
+                let params = (0..count)
+                    .map(|_| match reader.i32()? {
+                        -1 => Ok(None),
+                        len if len < 0 => Err(violation("negative parameter length")),
+                        len => reader.bytes(len as usize).map(|b| Some(b.to_vec())),
+                    })
+                    .collect::<Result<_, _>>()?;
+                let count = reader.i16()?;
+                let result_formats = (0..count).map(|_| reader.i16()).collect::<Result<_, _>>()?;
+                FrontendMessage::Bind {
+                    portal,
+                    statement,
+                    param_formats,
+                    params,
+                    result_formats,
+                }
+            }
+            b'D' => FrontendMessage::Describe {
+                kind: reader.u8()?,
+                name: reader.cstr()?,
+            },
+            b'E' => FrontendMessage::Execute {
+                portal: reader.cstr()?,
+                max_rows: reader.i32()?.max(0) as u32,
+            },
+            b'C' => FrontendMessage::Close {
+                kind: reader.u8()?,
+                name: reader.cstr()?,
+            },
+            b'S' => FrontendMessage::Sync,
+            b'H' => FrontendMessage::Flush,
+            b'X' => FrontendMessage::Terminate,
+            b'p' => FrontendMessage::Password(body.to_vec()),
+            other => return Err(violation(format!("unknown message type '{}'", other as char))),
+        };
+        Ok(Some(message))
+    }
+}
+
+// Mechanism and client-first message of a SASLInitialResponse body
+pub fn sasl_initial_response(body: &[u8]) -> Result<(String, Vec<u8>), DbError> {
+    let mut reader = Reader::new(body);
+    let mechanism = reader.cstr()?;
+    let data = match reader.i32()? {
+        -1 => Vec::new(),
+        len if len < 0 => return Err(violation("negative SASL response length")),
+        len => reader.bytes(len as usize)?.to_vec(),
+    };
+    Ok((mechanism, data))
+}
+
+// Text of a PasswordMessage body
+pub fn password(body: &[u8]) -> Result<String, DbError> {
+    Reader::new(body).cstr()
+}
+
+// Split a complete frame off `buf`, returning its body; the length field
+// starts after `header` type bytes
+fn take_frame(buf: &mut BytesMut, header: usize) -> Result<Option<BytesMut>, DbError> {
+    if buf.len() < header + 4 {
+        return Ok(None);
+    }
+    let len = i32::from_be_bytes([
+        buf[header],
+        buf[header + 1],
+        buf[header + 2],
+        buf[header + 3],
+    ]);
+    if len < 4 {
+        return Err(violation(format!("invalid message length {}", len)));
+    }
+    let len = len as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(DbError::LimitExceeded(format!(
+            "Message too large: {} bytes (max: {} bytes)",
+            len, MAX_MESSAGE_SIZE
+        )));
+    }
+    if buf.len() < header + len {
+        buf.reserve(header + len - buf.len());
+        return Ok(None);
+    }
+    buf.advance(header + 4);
+    Ok(Some(buf.split_to(len - 4)))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], DbError> {
+        if self.data.len() < n {
+            return Err(violation("message ended unexpectedly"));
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, DbError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, DbError> {
+        let b = self.bytes(2)?;
+        Ok(i16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, DbError> {
+        let b = self.bytes(4)?;
+        Ok(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn cstr(&mut self) -> Result<String, DbError> {
+        let end = self
+            .data
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| violation("unterminated string"))?;
+        let text = std::str::from_utf8(&self.data[..end])
+            .map_err(|_| DbError::InvalidInput("Invalid UTF-8 in message".to_string()))?
+            .to_string();
+        self.data = &self.data[end + 1..];
+        Ok(text)
+    }
+}
+
+// Column of a RowDescription
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescription {
+    pub name: String,
+    pub type_oid: u32,
+    pub type_size: i16,
+    pub type_modifier: i32,
+    // 0 for text, 1 for binary
+    pub format: i16,
+}
+
+// Messages the server sends
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendMessage {
+    AuthenticationOk,
+    AuthenticationCleartextPassword,
+    AuthenticationSasl(Vec<String>),
+    AuthenticationSaslContinue(Vec<u8>),
+    AuthenticationSaslFinal(Vec<u8>),
+    ParameterStatus { name: String, value: String },
+    BackendKeyData { process_id: i32, secret_key: i32 },
+    // b'I' idle, b'T' in a transaction block, b'E' in a failed block
+    ReadyForQuery(u8),
+    RowDescription(Vec<FieldDescription>),
+    DataRow(Vec<Option<Vec<u8>>>),
+    CommandComplete(String),
+    EmptyQueryResponse,
+    ErrorResponse {
+        severity: &'static str,
+        code: &'static str,
+        message: String,
+    },
+    ParseComplete,
+    BindComplete,
+    CloseComplete,
+    NoData,
+    ParameterDescription(Vec<u32>),
+    PortalSuspended,
+}
+
+impl BackendMessage {
+    // Append the framed message to `out`
+    pub fn encode(&self, out: &mut BytesMut) {
+        let (tag, start) = (self.tag(), out.len());
+        out.put_u8(tag);
+        out.put_i32(0);
+        match self {
+            BackendMessage::AuthenticationOk => out.put_i32(0),
+            BackendMessage::AuthenticationCleartextPassword => out.put_i32(3),
+            BackendMessage::AuthenticationSasl(mechanisms) => {
+                out.put_i32(10);
+                for mechanism in mechanisms {
+                    put_cstr(out, mechanism);
+                }
+                out.put_u8(0);
+            }
+            BackendMessage::AuthenticationSaslContinue(data) => {
+                out.put_i32(11);
+                out.put_slice(data);
+            }
+            BackendMessage::AuthenticationSaslFinal(data) => {
+                out.put_i32(12);
+                out.put_slice(data);
+            }
+            BackendMessage::ParameterStatus { name, value } => {
+                put_cstr(out, name);
+                put_cstr(out, value);
+            }
+            BackendMessage::BackendKeyData {
+                process_id,
+                secret_key,
+            } => {
+                out.put_i32(*process_id);
+                out.put_i32(*secret_key);
+            }
+            BackendMessage::ReadyForQuery(status) => out.put_u8(*status),
+            BackendMessage::RowDescription(fields) => {
+                out.put_i16(fields.len() as i16);
+                for field in fields {
+                    put_cstr(out, &field.name);
+                    out.put_i32(0); // table OID
+                    out.put_i16(0); // column attribute number
+                    out.put_u32(field.type_oid);
+                    out.put_i16(field.type_size);
+                    out.put_i32(field.type_modifier);
+                    out.put_i16(field.format);
+                }
+            }
+            BackendMessage::DataRow(values) => {
+                out.put_i16(values.len() as i16);
+                for value in values {
+                    match value {
+                        Some(bytes) => {
+                            out.put_i32(bytes.len() as i32);
+                            out.put_slice(bytes);
+                        }
+                        None => out.put_i32(-1),
+                    }
+                }
+            }
+            BackendMessage::CommandComplete(tag) => put_cstr(out, tag),
+            BackendMessage::ErrorResponse {
+                severity,
+                code,
+                message,
+            } => {
+                for (field, value) in [(b'S', *severity), (b'V', *severity), (b'C', *code)] {
+                    out.put_u8(field);
+                    put_cstr(out, value);
+                }
+                out.put_u8(b'M');
+                put_cstr(out, message);
+                out.put_u8(0);
+            }
+            BackendMessage::ParameterDescription(types) => {
+                out.put_i16(types.len() as i16);
+                for oid in types {
+                    out.put_u32(*oid);
+                }
+            }
+            BackendMessage::EmptyQueryResponse
+            | BackendMessage::ParseComplete
+            | BackendMessage::BindComplete
+            | BackendMessage::CloseComplete
+            | BackendMessage::NoData
+            | BackendMessage::PortalSuspended => {}
+        }
+        let len = (out.len() - start - 1) as i32;
+        out[start + 1..start + 5].copy_from_slice(&len.to_be_bytes());
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            BackendMessage::AuthenticationOk
+            | BackendMessage::AuthenticationCleartextPassword
+            | BackendMessage::AuthenticationSasl(_)
+            | BackendMessage::AuthenticationSaslContinue(_)
+            | BackendMessage::AuthenticationSaslFinal(_) => b'R',
+            BackendMessage::ParameterStatus { .. } => b'S',
+            BackendMessage::BackendKeyData { .. } => b'K',
+            BackendMessage::ReadyForQuery(_) => b'Z',
+            BackendMessage::RowDescription(_) => b'T',
+            BackendMessage::DataRow(_) => b'D',
+            BackendMessage::CommandComplete(_) => b'C',
+            BackendMessage::EmptyQueryResponse => b'I',
+            BackendMessage::ErrorResponse { .. } => b'E',
+            BackendMessage::ParseComplete => b'1',
+            BackendMessage::BindComplete => b'2',
+            BackendMessage::CloseComplete => b'3',
+            BackendMessage::NoData => b'n',
+            BackendMessage::ParameterDescription(_) => b't',
+            BackendMessage::PortalSuspended => b's',
+        }
+    }
+}
+
+fn put_cstr(out: &mut BytesMut, s: &str) {
+    out.put_slice(s.as_bytes());
+    out.put_u8(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_startup() -> Result<(), DbError> {
+        let mut buf = BytesMut::new();
+        let body = b"user\0alice\0database\0app\0\0";
+        buf.put_i32(8 + body.len() as i32);
+        buf.put_i32(PROTOCOL_VERSION_3);
+        buf.put_slice(body);
+
+        let mut partial = buf.split_to(6);
+        assert_eq!(StartupPacket::decode(&mut partial)?, None);
+        partial.unsplit(buf);
+
+        match StartupPacket::decode(&mut partial)? {
+            Some(StartupPacket::Startup { parameters }) => {
+                assert_eq!(parameters["user"], "alice");
+                assert_eq!(parameters["database"], "app");
+            }
+            other => panic!("unexpected packet {:?}", other),
+        }
+        assert!(partial.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bind() -> Result<(), DbError> {
+        let mut buf = BytesMut::new();
+        let mut body = BytesMut::new();
+        body.put_slice(b"portal\0stmt\0");
+        body.put_i16(1);
+        body.put_i16(1);
+        body.put_i16(2);
+        body.put_i32(4);
+        body.put_i32(42);
+        body.put_i32(-1);
+        body.put_i16(0);
+        buf.put_u8(b'B');
+        buf.put_i32(4 + body.len() as i32);
+        buf.put_slice(&body);
+        buf.put_u8(b'S');
+        buf.put_i32(4);
+
+        assert_eq!(
+            FrontendMessage::decode(&mut buf)?,
+            Some(FrontendMessage::Bind {
+                portal: "portal".to_string(),
+                statement: "stmt".to_string(),
+                param_formats: vec![1],
+                params: vec![Some(42i32.to_be_bytes().to_vec()), None],
+                result_formats: vec![],
+            })
+        );
+        assert_eq!(FrontendMessage::decode(&mut buf)?, Some(FrontendMessage::Sync));
+        assert_eq!(FrontendMessage::decode(&mut buf)?, None);
+
+        buf.put_u8(b'Q');
+        buf.put_i32(3);
+        assert!(FrontendMessage::decode(&mut buf).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_messages() {
+        let mut out = BytesMut::new();
+        BackendMessage::ReadyForQuery(b'I').encode(&mut out);
+        assert_eq!(&out[..], &[b'Z', 0, 0, 0, 5, b'I']);
+
+        out.clear();
+        BackendMessage::DataRow(vec![Some(b"1".to_vec()), None]).encode(&mut out);
+        assert_eq!(
+            &out[..],
+            &[b'D', 0, 0, 0, 15, 0, 2, 0, 0, 0, 1, b'1', 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+// PostgreSQL wire protocol (v3) front end
+//
+// Serves the same `Database` as the native `Server`, so standard
+// PostgreSQL drivers and tools (psql, JDBC, libpq, ...) can connect.
+// Supports cleartext and SCRAM-SHA-256 authentication, the simple query
+// protocol, and the extended Parse/Bind/Describe/Execute protocol.
+// TLS is not offered: SSLRequest is answered with 'N'.
+//
+// Connections count against the database's connection limit; a client over
+// the limit gets a `too_many_connections` (53300) error before the socket
+// closes. Once the database starts shutting down the listener stops
+// accepting, and shutdown waits for open sessions to finish.
+
+pub mod codec;
+pub mod scram;
+pub mod types;
+mod session;
+
+use crate::catalog::Catalog;
+use crate::database::Database;
+use crate::error::DbError;
+use crate::execution::Executor;
+use crate::network::server::MAX_CONCURRENT_CONNECTIONS;
+use crate::parser::SqlParser;
+use crate::security::authentication::AuthenticationManager;
+use crate::security::sql_firewall::SqlFirewall;
+use bytes::BytesMut;
+use codec::BackendMessage;
+use session::PgSession;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+// SQLSTATE too_many_connections
+const TOO_MANY_CONNECTIONS: &str = "53300";
+
+// How clients prove their identity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgAuthMethod {
+    // Accept any user named in the startup packet
+    Trust,
+    // Cleartext password, checked by the authentication manager
+    Password,
+    // SCRAM-SHA-256 against the stored verifier
+    ScramSha256,
+}
+
+// State shared by all connections of a server
+pub(crate) struct SessionContext {
+    pub(crate) catalog: Arc<Catalog>,
+    pub(crate) executor: Arc<Executor>,
+    pub(crate) parser: Arc<SqlParser>,
+    pub(crate) authentication: Option<Arc<AuthenticationManager>>,
+    pub(crate) auth_method: PgAuthMethod,
+    // Checks each parsed statement against the session's role and
+    // application_name
+    pub(crate) firewall: Option<Arc<SqlFirewall>>,
+}
+
+// PostgreSQL protocol server
+pub struct PgServer {
+    database: Arc<Database>,
+    authentication: Option<Arc<AuthenticationManager>>,
+    auth_method: PgAuthMethod,
+    /// Current number of active connections - bounded to MAX_CONCURRENT_CONNECTIONS
+    active_connections: Arc<AtomicUsize>,
+    // Reported to clients in BackendKeyData
+    next_process_id: AtomicI32,
+}
+
+impl PgServer {
+    // Create a server over `database`, authenticating clients with
+    // SCRAM-SHA-256 against its authentication manager and checking their
+    // statements against its SQL firewall
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            authentication: Some(database.security().authentication.clone()),
+            auth_method: PgAuthMethod::ScramSha256,
+            database,
+            active_connections: Arc::new(AtomicUsize::new(0)),
+            next_process_id: AtomicI32::new(1),
+        }
+    }
+
+    // Authenticate clients against `manager` using `method`
+    pub fn with_authentication(
+        mut self,
+        manager: Arc<AuthenticationManager>,
+        method: PgAuthMethod,
+    ) -> Self {
+        self.authentication = Some(manager);
+        self.auth_method = method;
+        self
+    }
+
+    pub async fn run(&self, addr: &str) -> Result<(), DbError> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| DbError::Network(e.to_string()))?;
+
+        tracing::info!("RustyDB PostgreSQL listener on {}", addr);
+
+        let context = Arc::new(SessionContext {
+            catalog: Arc::new(self.database.catalog().clone()),
+            executor: Arc::new(self.database.executor()),
+            parser: Arc::new(SqlParser::new()),
+            authentication: self.authentication.clone(),
+            auth_method: self.auth_method,
+            firewall: Some(self.database.firewall().clone()),
+        });
+
+        loop {
+            let (socket, addr) = tokio::select! {
+                accepted = listener.accept() => {
+                    accepted.map_err(|e| DbError::Network(e.to_string()))?
+                }
+                _ = self.database.shutdown_started() => {
+                    tracing::info!("PostgreSQL listener on {} stopped accepting connections", addr);
+                    return Ok(());
+                }
+            };
+
+            let current_conns = self.active_connections.load(Ordering::Relaxed);
+            if current_conns >= MAX_CONCURRENT_CONNECTIONS {
+                tracing::warn!(
+                    "Connection limit reached ({}/{}), rejecting PostgreSQL connection from {}",
+                    current_conns,
+                    MAX_CONCURRENT_CONNECTIONS,
+                    addr
+                );
+                reject(socket, "sorry, too many clients already").await;
+                continue;
+            }
+            let Some(guard) = self.database.open_connection() else {
+                tracing::warn!(
+                    "Database connection limit reached, rejecting PostgreSQL connection from {}",
+                    addr
+                );
+                reject(socket, "sorry, too many clients already").await;
+                continue;
+            };
+
+            tracing::info!(
+                "New PostgreSQL connection from {} ({}/{} active)",
+                addr,
+                current_conns + 1,
+                MAX_CONCURRENT_CONNECTIONS
+            );
+            self.active_connections.fetch_add(1, Ordering::Relaxed);
+            // Small protocol messages are latency-bound
+            let _ = socket.set_nodelay(true);
+
+            let process_id = self.next_process_id.fetch_add(1, Ordering::Relaxed);
+            let session = PgSession::new(
+                socket,
+                context.clone(),
+                process_id,
+                Some(addr.ip().to_string()),
+            );
+            let active_connections = self.active_connections.clone();
+            tokio::spawn(async move {
+                if let Err(e) = session.run().await {
+                    tracing::error!("Error handling PostgreSQL connection: {}", e);
+                }
+                active_connections.fetch_sub(1, Ordering::Relaxed);
+                drop(guard);
+            });
+        }
+    }
+}
+
+// Tell a client it is over the connection limit, then close its socket.
+// Sent before the startup packet is read; clients report it either way.
+async fn reject(mut socket: TcpStream, message: &str) {
+    let mut buf = BytesMut::new();
+    BackendMessage::ErrorResponse {
+        severity: "FATAL",
+        code: TOO_MANY_CONNECTIONS,
+        message: message.to_string(),
+    }
+    .encode(&mut buf);
+    let _ = socket.write_all(&buf).await;
+    let _ = socket.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_rejected_client_gets_too_many_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        reject(socket, "sorry, too many clients already").await;
+
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).await.unwrap();
+        assert_eq!(reply[0], b'E');
+        let fields = String::from_utf8_lossy(&reply[5..]);
+        assert!(fields.contains("C53300\0"));
+        assert!(fields.contains("Msorry, too many clients already\0"));
+    }
+}
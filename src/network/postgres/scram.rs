@@ -0,0 +1,198 @@
+// Server side of a SCRAM-SHA-256 exchange (RFC 5802, RFC 7677)
+//
+// PostgreSQL clients send an empty username in client-first; the user is
+// the one named in the startup packet. Channel binding is not offered, so
+// the GS2 header must be "n,," or "y,,".
+
+use crate::error::DbError;
+use crate::security::authentication::{ScramCredentials, SCRAM_ITERATIONS};
+use base64::{engine::general_purpose, Engine as _};
+
+pub const SCRAM_SHA_256: &str = "SCRAM-SHA-256";
+
+fn malformed(what: &str) -> DbError {
+    DbError::Authentication(format!("Malformed SCRAM message: {}", what))
+}
+
+// State between server-first and client-final
+pub struct ScramExchange {
+    credentials: ScramCredentials,
+    gs2_header: String,
+    client_first_bare: String,
+    server_first: String,
+    nonce: String,
+}
+
+// Verified shape of client-final, ready to check against a verifier
+pub struct ScramProof {
+    auth_message: String,
+    proof: Vec<u8>,
+}
+
+impl ScramExchange {
+    // Verifier used for unknown users, so the exchange looks the same
+    // whether or not the user exists
+    pub fn mock_credentials() -> ScramCredentials {
+        let password: [u8; 16] = rand::random();
+        let salt: [u8; 16] = rand::random();
+        ScramCredentials::derive(&general_purpose::STANDARD.encode(password), &salt, SCRAM_ITERATIONS)
+    }
+
+    // Handle client-first, returning the exchange and server-first
+    pub fn start(credentials: ScramCredentials, client_first: &[u8]) -> Result<(Self, String), DbError> {
+        let client_first =
+            std::str::from_utf8(client_first).map_err(|_| malformed("invalid UTF-8"))?;
+        let (gs2_header, client_first_bare) = match client_first.get(..3) {
+            Some("n,," | "y,,") => client_first.split_at(3),
+            Some(header) if header.starts_with('p') => {
+                return Err(DbError::Authentication(
+                    "SCRAM channel binding is not supported".to_string(),
+                ))
+            }
+            _ => return Err(malformed("invalid GS2 header")),
+        };
+        let client_nonce = attribute(client_first_bare, 'r').ok_or_else(|| malformed("missing nonce"))?;
+
+        let server_nonce: [u8; 18] = rand::random();
+        let nonce = format!("{}{}", client_nonce, general_purpose::STANDARD.encode(server_nonce));
+        let server_first = format!(
+            "r={},s={},i={}",
+            nonce,
+            general_purpose::STANDARD.encode(&credentials.salt),
+            credentials.iterations
+        );
+
+        let exchange = Self {
+            credentials,
+            gs2_header: gs2_header.to_string(),
+            client_first_bare: client_first_bare.to_string(),
+            server_first: server_first.clone(),
+            nonce,
+        };
+        Ok((exchange, server_first))
+    }
+
+    // Handle client-final, checking the echoed header and nonce
+    pub fn finish(&self, client_final: &[u8]) -> Result<ScramProof, DbError> {
+        let client_final =
+            std::str::from_utf8(client_final).map_err(|_| malformed("invalid UTF-8"))?;
+        let (without_proof, proof) = client_final
+            .rsplit_once(",p=")
+            .ok_or_else(|| malformed("missing proof"))?;
+
+        let binding = attribute(without_proof, 'c').ok_or_else(|| malformed("missing channel binding"))?;
+        if general_purpose::STANDARD.decode(binding).ok().as_deref() != Some(self.gs2_header.as_bytes()) {
+            return Err(malformed("channel binding does not match"));
+        }
+        if attribute(without_proof, 'r') != Some(self.nonce.as_str()) {
+            return Err(malformed("nonce does not match"));
+        }
+
+        Ok(ScramProof {
+            auth_message: format!(
+                "{},{},{}",
+                self.client_first_bare, self.server_first, without_proof
+            ),
+            proof: general_purpose::STANDARD
+                .decode(proof)
+                .map_err(|_| malformed("invalid proof encoding"))?,
+        })
+    }
+
+    pub fn credentials(&self) -> &ScramCredentials {
+        &self.credentials
+    }
+}
+
+impl ScramProof {
+    pub fn verify(&self, credentials: &ScramCredentials) -> bool {
+        credentials.verify_client_proof(self.auth_message.as_bytes(), &self.proof)
+    }
+
+    // server-final message proving the server knows the verifier
+    pub fn server_final(&self, credentials: &ScramCredentials) -> String {
+        format!(
+            "v={}",
+            general_purpose::STANDARD.encode(credentials.server_signature(self.auth_message.as_bytes()))
+        )
+    }
+}
+
+// Value of a single-letter attribute in a comma-separated SCRAM message
+fn attribute(message: &str, name: char) -> Option<&str> {
+    message.split(',').find_map(|part| {
+        let mut chars = part.chars();
+        (chars.next() == Some(name) && chars.next() == Some('=')).then(|| &part[2..])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    // Client side of the exchange, computed from the password
+    fn client_final(password: &str, server_first: &str) -> (String, String) {
+        let salt = general_purpose::STANDARD
+            .decode(attribute(server_first, 's').unwrap())
+            .unwrap();
+        let iterations = attribute(server_first, 'i').unwrap().parse().unwrap();
+        let mut salted = hmac(password.as_bytes(), &[salt.as_slice(), &[0, 0, 0, 1]].concat());
+        let mut u = salted.clone();
+        for _ in 1..iterations {
+            u = hmac(password.as_bytes(), &u);
+            salted.iter_mut().zip(&u).for_each(|(s, b)| *s ^= b);
+        }
+        let client_key = hmac(&salted, b"Client Key");
+        let stored_key = Sha256::digest(&client_key);
+
+        let without_proof = format!("c=biws,r={}", attribute(server_first, 'r').unwrap());
+        let auth_message = format!("n=,r=clientnonce,{},{}", server_first, without_proof);
+        let signature = hmac(&stored_key, auth_message.as_bytes());
+        let proof: Vec<u8> = client_key.iter().zip(signature).map(|(k, s)| k ^ s).collect();
+        let server_key = hmac(&salted, b"Server Key");
+        (
+            format!("{},p={}", without_proof, general_purpose::STANDARD.encode(proof)),
+            format!(
+                "v={}",
+                general_purpose::STANDARD.encode(hmac(&server_key, auth_message.as_bytes()))
+            ),
+        )
+    }
+
+    #[test]
+    fn test_scram_exchange() -> Result<(), DbError> {
+        let credentials = ScramCredentials::derive("secret", b"0123456789abcdef", 64);
+        let (exchange, server_first) =
+            ScramExchange::start(credentials.clone(), b"n,,n=,r=clientnonce")?;
+        assert!(server_first.starts_with("r=clientnonce"));
+        assert!(server_first.ends_with(",i=64"));
+
+        let (message, expected_final) = client_final("secret", &server_first);
+        let proof = exchange.finish(message.as_bytes())?;
+        assert!(proof.verify(&credentials));
+        assert_eq!(proof.server_final(&credentials), expected_final);
+
+        let (message, _) = client_final("wrong", &server_first);
+        assert!(!exchange.finish(message.as_bytes())?.verify(&credentials));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scram_rejects_malformed_messages() {
+        let credentials = ScramCredentials::derive("secret", b"salt", 1);
+        assert!(ScramExchange::start(credentials.clone(), b"p=tls-server-end-point,,n=,r=x").is_err());
+        assert!(ScramExchange::start(credentials.clone(), b"n,,n=").is_err());
+
+        let (exchange, _) = ScramExchange::start(credentials, b"n,,n=,r=abc").unwrap();
+        assert!(exchange.finish(b"c=biws,r=abc,p=AAAA").is_err());
+        assert!(exchange.finish(b"c=biws").is_err());
+    }
+}
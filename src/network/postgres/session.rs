@@ -0,0 +1,1564 @@
+// One PostgreSQL client connection: startup, authentication, and the simple
+// and extended query protocols
+//
+// Parse binds a statement once through the executor, which infers the type
+// of each `$n` parameter the client left unspecified. Bind decodes the
+// parameter values into typed values that are bound into the plan, so they
+// are never spliced into SQL text. With a SQL firewall configured, each
+// statement is checked once it parses, as the session user and its
+// application_name; Bind never reaches it.
+//
+// Statements run in the connection's `Session`: on their own, or inside the
+// transaction a BEGIN opened until COMMIT or ROLLBACK.
+
+use super::codec::{self, BackendMessage, FieldDescription, FrontendMessage, StartupPacket};
+use super::scram::{ScramExchange, SCRAM_SHA_256};
+use super::types;
+use super::{PgAuthMethod, SessionContext};
+use crate::catalog::DataType;
+use crate::common::Value;
+use crate::error::DbError;
+use crate::execution::{PreparedStatement as BoundStatement, QueryResult, Session};
+use crate::network::protocol::MAX_SQL_LENGTH;
+use crate::parser::SqlStatement;
+use crate::security::authentication::{AuthSessionId, LoginCredentials, LoginResult};
+use crate::security::sql_firewall::FirewallIdentity;
+use crate::transaction::IsolationLevel;
+use bytes::BytesMut;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// Parameters reported to the client after authentication. Values are
+// always sent as UTF-8 in ISO format, whatever the client asks for.
+const SERVER_PARAMETERS: &[(&str, &str)] = &[
+    ("server_version", "16.0"),
+    ("server_encoding", "UTF8"),
+    ("client_encoding", "UTF8"),
+    ("DateStyle", "ISO, MDY"),
+    ("TimeZone", "UTC"),
+    ("integer_datetimes", "on"),
+    ("standard_conforming_strings", "on"),
+];
+
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TransactionStatus {
+    Idle,
+    InBlock,
+    Failed,
+}
+
+impl TransactionStatus {
+    // ReadyForQuery indicator
+    fn indicator(self) -> u8 {
+        match self {
+            TransactionStatus::Idle => b'I',
+            TransactionStatus::InBlock => b'T',
+            TransactionStatus::Failed => b'E',
+        }
+    }
+}
+
+// A statement, with the session commands handled here split off
+#[derive(Debug, Clone)]
+enum Command {
+    Empty,
+    Begin(Option<IsolationLevel>),
+    Commit,
+    Rollback,
+    Set { name: String, value: String },
+    Show(String),
+    Statement(SqlStatement),
+}
+
+impl Command {
+    fn returns_rows(&self) -> bool {
+        match self {
+            Command::Show(_) => true,
+            Command::Statement(stmt) => statement_returns_rows(stmt),
+            _ => false,
+        }
+    }
+}
+
+fn statement_returns_rows(stmt: &SqlStatement) -> bool {
+    matches!(
+        stmt,
+        SqlStatement::Select { .. } | SqlStatement::Union { .. } | SqlStatement::Explain { .. }
+    )
+}
+
+enum Outcome {
+    Empty,
+    Rows(QueryResult),
+    Complete(String),
+}
+
+struct PreparedStatement {
+    // Types the client gave at Parse; 0 where it left one unspecified
+    param_types: Vec<u32>,
+    command: Command,
+    // The statement as bound by the executor; `None` for session commands
+    bound: Option<BoundStatement>,
+}
+
+impl PreparedStatement {
+    // Type of each parameter as reported to the client: the declared type,
+    // else the inferred one, else text
+    fn param_oids(&self) -> Vec<u32> {
+        let inferred = self.bound.as_ref().map_or(&[][..], |bound| bound.param_types());
+        (0..self.param_types.len().max(inferred.len()))
+            .map(|i| match self.param_types.get(i) {
+                Some(&oid) if oid != 0 => oid,
+                _ => inferred
+                    .get(i)
+                    .and_then(Option::as_ref)
+                    .map_or(types::TEXT_OID, types::type_oid),
+            })
+            .collect()
+    }
+}
+
+struct Portal {
+    command: Command,
+    bound: Option<BoundStatement>,
+    params: Vec<Value>,
+    result_formats: Vec<i16>,
+    // Filled on first Describe or Execute
+    outcome: Option<Outcome>,
+    // Rows already returned by earlier Executes
+    sent: usize,
+}
+
+pub(crate) struct PgSession<S> {
+    stream: S,
+    context: Arc<SessionContext>,
+    process_id: i32,
+    secret_key: i32,
+    client_addr: Option<String>,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+    user: String,
+    auth_session: Option<AuthSessionId>,
+    // Keyed by lower-case name
+    parameters: HashMap<String, String>,
+    statements: HashMap<String, PreparedStatement>,
+    // Open transaction and the statements of SQL PREPARE, which are kept
+    // apart from protocol-level ones
+    session: Session,
+    portals: HashMap<String, Portal>,
+    transaction: TransactionStatus,
+    // After an extended-protocol error, messages are skipped up to Sync
+    skip_until_sync: bool,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> PgSession<S> {
+    pub(crate) fn new(
+        stream: S,
+        context: Arc<SessionContext>,
+        process_id: i32,
+        client_addr: Option<String>,
+    ) -> Self {
+        Self {
+            stream,
+            context,
+            process_id,
+            secret_key: rand::random(),
+            client_addr,
+            read_buf: BytesMut::with_capacity(READ_CHUNK_SIZE),
+            write_buf: BytesMut::with_capacity(READ_CHUNK_SIZE),
+            user: String::new(),
+            auth_session: None,
+            parameters: HashMap::new(),
+            statements: HashMap::new(),
+            session: Session::new(),
+            portals: HashMap::new(),
+            transaction: TransactionStatus::Idle,
+            skip_until_sync: false,
+        }
+    }
+
+    // Serve the connection until the client terminates or disconnects
+    pub(crate) async fn run(mut self) -> Result<(), DbError> {
+        let result = self.serve().await;
+        if let Err(e) = &result {
+            self.send_error("FATAL", e.sqlstate(), e.to_string());
+            // The connection may already be gone
+            let _ = self.flush().await;
+        }
+        if let (Some(session_id), Some(manager)) =
+            (self.auth_session.take(), &self.context.authentication)
+        {
+            let _ = manager.logout(&session_id);
+        }
+        result
+    }
+
+    async fn serve(&mut self) -> Result<(), DbError> {
+        if !self.startup().await? {
+            return self.flush().await;
+        }
+
+        while let Some(message) = self.read_message().await? {
+            if self.skip_until_sync
+                && !matches!(message, FrontendMessage::Sync | FrontendMessage::Terminate)
+            {
+                continue;
+            }
+            match message {
+                FrontendMessage::Query(sql) => {
+                    if let Err(e) = self.simple_query(&sql) {
+                        self.send_db_error(&e);
+                    }
+                    self.ready_for_query();
+                }
+                FrontendMessage::Sync => {
+                    self.skip_until_sync = false;
+                    // Portals only live until the end of the transaction
+                    if self.transaction == TransactionStatus::Idle {
+                        self.portals.clear();
+                    }
+                    self.ready_for_query();
+                }
+                FrontendMessage::Flush => self.flush().await?,
+                FrontendMessage::Terminate => break,
+                FrontendMessage::Password(_) => {
+                    return Err(DbError::Network(
+                        "Protocol violation: unexpected password message".to_string(),
+                    ))
+                }
+                message => {
+                    if let Err(e) = self.extended(message) {
+                        self.send_db_error(&e);
+                        self.skip_until_sync = true;
+                    }
+                }
+            }
+        }
+        self.flush().await
+    }
+
+    // ------------------------------------------------------------------
+    // Startup and authentication
+    // ------------------------------------------------------------------
+
+    // Returns false when the connection should be closed
+    async fn startup(&mut self) -> Result<bool, DbError> {
+        let parameters = loop {
+            let Some(packet) = self.read_startup().await? else {
+                return Ok(false);
+            };
+            match packet {
+                StartupPacket::SslRequest | StartupPacket::GssEncRequest => {
+                    // Encryption is not offered; the client continues in plain text
+                    self.write_buf.extend_from_slice(b"N");
+                    self.flush().await?;
+                }
+                // Queries run to completion, so there is nothing to cancel
+                StartupPacket::CancelRequest { .. } => return Ok(false),
+                StartupPacket::Startup { parameters } => break parameters,
+            }
+        };
+
+        let Some(user) = parameters.get("user").filter(|u| !u.is_empty()).cloned() else {
+            self.send_error(
+                "FATAL",
+                "28000",
+                "no PostgreSQL user name specified in startup packet".to_string(),
+            );
+            return Ok(false);
+        };
+        self.user = user;
+        for (name, value) in parameters {
+            self.parameters.insert(name.to_ascii_lowercase(), value);
+        }
+
+        if !self.authenticate().await? {
+            return Ok(false);
+        }
+
+        self.send(BackendMessage::AuthenticationOk);
+        for (name, value) in SERVER_PARAMETERS {
+            self.parameters
+                .insert(name.to_ascii_lowercase(), value.to_string());
+            self.send(BackendMessage::ParameterStatus {
+                name: name.to_string(),
+                value: value.to_string(),
+            });
+        }
+        if let Some(application_name) = self.parameters.get("application_name").cloned() {
+            self.send(BackendMessage::ParameterStatus {
+                name: "application_name".to_string(),
+                value: application_name,
+            });
+        }
+        self.send(BackendMessage::BackendKeyData {
+            process_id: self.process_id,
+            secret_key: self.secret_key,
+        });
+        self.ready_for_query();
+        Ok(true)
+    }
+
+    // Returns false, after reporting why, when authentication fails
+    async fn authenticate(&mut self) -> Result<bool, DbError> {
+        let manager = match (&self.context.authentication, self.context.auth_method) {
+            (Some(manager), PgAuthMethod::Password | PgAuthMethod::ScramSha256) => manager.clone(),
+            _ => return Ok(true),
+        };
+
+        let result = match self.context.auth_method {
+            PgAuthMethod::Password => {
+                self.send(BackendMessage::AuthenticationCleartextPassword);
+                let Some(body) = self.read_auth_response().await? else {
+                    return Ok(false);
+                };
+                manager.login(LoginCredentials {
+                    username: self.user.clone(),
+                    password: codec::password(&body)?,
+                    mfa_code: None,
+                    client_ip: self.client_addr.clone(),
+                    user_agent: self.parameters.get("application_name").cloned(),
+                })
+            }
+            _ => {
+                self.send(BackendMessage::AuthenticationSasl(vec![
+                    SCRAM_SHA_256.to_string()
+                ]));
+                let Some(body) = self.read_auth_response().await? else {
+                    return Ok(false);
+                };
+                let (mechanism, client_first) = codec::sasl_initial_response(&body)?;
+                if mechanism != SCRAM_SHA_256 {
+                    self.send_error(
+                        "FATAL",
+                        "28000",
+                        format!("unsupported SASL mechanism \"{}\"", mechanism),
+                    );
+                    return Ok(false);
+                }
+
+                // Unknown users get a mock verifier and fail at the proof
+                let credentials = manager
+                    .scram_credentials(&self.user)
+                    .unwrap_or_else(ScramExchange::mock_credentials);
+                let (exchange, server_first) = ScramExchange::start(credentials, &client_first)?;
+                self.send(BackendMessage::AuthenticationSaslContinue(
+                    server_first.into_bytes(),
+                ));
+                let Some(body) = self.read_auth_response().await? else {
+                    return Ok(false);
+                };
+                let proof = exchange.finish(&body)?;
+                let result =
+                    manager.login_scram(&self.user, self.client_addr.clone(), |c| proof.verify(c));
+                if matches!(result, Ok(LoginResult::Success { .. })) {
+                    self.send(BackendMessage::AuthenticationSaslFinal(
+                        proof.server_final(exchange.credentials()).into_bytes(),
+                    ));
+                }
+                result
+            }
+        };
+
+        let (code, message) = match result {
+            Ok(LoginResult::Success { session }) => {
+                self.auth_session = Some(session.session_id);
+                return Ok(true);
+            }
+            Ok(LoginResult::MfaRequired { .. }) => (
+                "28000",
+                "multi-factor authentication is not available over the PostgreSQL protocol"
+                    .to_string(),
+            ),
+            Ok(LoginResult::PasswordChangeRequired { .. }) => (
+                "28000",
+                format!("password change required for user \"{}\"", self.user),
+            ),
+            Ok(LoginResult::AccountLocked { .. }) => {
+                ("28000", format!("account \"{}\" is locked", self.user))
+            }
+            Ok(LoginResult::AccountDisabled) => {
+                ("28000", format!("account \"{}\" is disabled", self.user))
+            }
+            Ok(LoginResult::InvalidCredentials) | Err(_) => (
+                "28P01",
+                format!("password authentication failed for user \"{}\"", self.user),
+            ),
+        };
+        self.send_error("FATAL", code, message);
+        Ok(false)
+    }
+
+    async fn read_auth_response(&mut self) -> Result<Option<Vec<u8>>, DbError> {
+        match self.read_message().await? {
+            Some(FrontendMessage::Password(body)) => Ok(Some(body)),
+            Some(FrontendMessage::Terminate) | None => Ok(None),
+            Some(_) => Err(DbError::Network(
+                "Protocol violation: expected an authentication response".to_string(),
+            )),
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Simple query protocol
+    // ------------------------------------------------------------------
+
+    fn simple_query(&mut self, sql: &str) -> Result<(), DbError> {
+        check_sql_length(sql)?;
+        let statements = split_statements(sql);
+        if statements.is_empty() {
+            self.send(BackendMessage::EmptyQueryResponse);
+            return Ok(());
+        }
+
+        // Statements run in order until one fails
+        for text in statements {
+            let command = self.parse_command(text)?;
+            match self.execute_command(&command)? {
+                Outcome::Empty => self.send(BackendMessage::EmptyQueryResponse),
+                Outcome::Complete(tag) => self.send(BackendMessage::CommandComplete(tag)),
+                Outcome::Rows(result) => {
+                    self.send(BackendMessage::RowDescription(describe_columns(
+                        &result.columns,
+                        &result.column_types,
+                        &[],
+                    )));
+                    self.send_rows(&result, 0..result.rows.len(), &[])?;
+                    self.send(BackendMessage::CommandComplete(rows_tag(
+                        &command,
+                        result.rows.len(),
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------
+    // Extended query protocol
+    // ------------------------------------------------------------------
+
+    fn extended(&mut self, message: FrontendMessage) -> Result<(), DbError> {
+        match message {
+            FrontendMessage::Parse {
+                name,
+                query,
+                param_types,
+            } => {
+                check_sql_length(&query)?;
+                if !name.is_empty() && self.statements.contains_key(&name) {
+                    return Err(DbError::AlreadyExists(format!(
+                        "prepared statement \"{}\" already exists",
+                        name
+                    )));
+                }
+                let command = self.parse_command(&query)?;
+                let bound = match &command {
+                    Command::Statement(
+                        SqlStatement::Prepare { .. }
+                        | SqlStatement::Execute { .. }
+                        | SqlStatement::Deallocate { .. },
+                    ) => None,
+                    Command::Statement(stmt) if stmt.is_transaction_control() => None,
+                    Command::Statement(stmt) => {
+                        let declared =
+                            param_types.iter().map(|&oid| types::oid_type(oid)).collect();
+                        Some(self.context.executor.prepare(stmt.clone(), declared)?)
+                    }
+                    _ => None,
+                };
+                self.statements.insert(
+                    name,
+                    PreparedStatement {
+                        param_types,
+                        command,
+                        bound,
+                    },
+                );
+                self.send(BackendMessage::ParseComplete);
+            }
+            FrontendMessage::Bind {
+                portal,
+                statement,
+                param_formats,
+                params,
+                result_formats,
+            } => {
+                let prepared = self.revalidated(&statement)?;
+                let param_oids = prepared.param_oids();
+                if params.len() != param_oids.len() {
+                    return Err(DbError::Network(format!(
+                        "Protocol violation: bind message supplies {} parameters, but prepared statement \"{}\" requires {}",
+                        params.len(),
+                        statement,
+                        param_oids.len()
+                    )));
+                }
+                let values = params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        types::parameter_value(
+                            value.as_deref(),
+                            format_code(&param_formats, i),
+                            param_oids[i],
+                        )
+                    })
+                    .collect::<Result<Vec<_>, DbError>>()?;
+                let command = prepared.command.clone();
+                let bound = prepared.bound.clone();
+
+                if !portal.is_empty() && self.portals.contains_key(&portal) {
+                    return Err(DbError::AlreadyExists(format!(
+                        "portal \"{}\" already exists",
+                        portal
+                    )));
+                }
+                self.portals.insert(
+                    portal,
+                    Portal {
+                        command,
+                        bound,
+                        params: values,
+                        result_formats,
+                        outcome: None,
+                        sent: 0,
+                    },
+                );
+                self.send(BackendMessage::BindComplete);
+            }
+            FrontendMessage::Describe { kind: b'S', name } => {
+                self.revalidated(&name)?;
+                let prepared = self.prepared(&name)?;
+                let param_types = prepared.param_oids();
+                let fields = self.describe_statement(prepared)?;
+                self.send(BackendMessage::ParameterDescription(param_types));
+                match fields {
+                    Some(fields) => self.send(BackendMessage::RowDescription(fields)),
+                    None => self.send(BackendMessage::NoData),
+                }
+            }
+            FrontendMessage::Describe { kind: b'P', name } => {
+                let mut portal = self.take_portal(&name)?;
+                let result = self.describe_portal(&mut portal);
+                self.portals.insert(name, portal);
+                result?;
+            }
+            FrontendMessage::Execute { portal: name, max_rows } => {
+                let mut portal = self.take_portal(&name)?;
+                let result = self.execute_portal(&mut portal, max_rows);
+                self.portals.insert(name, portal);
+                result?;
+            }
+            FrontendMessage::Close { kind, name } => {
+                match kind {
+                    b'S' => {
+                        self.statements.remove(&name);
+                    }
+                    b'P' => {
+                        self.portals.remove(&name);
+                    }
+                    _ => return Err(invalid_kind(kind)),
+                }
+                self.send(BackendMessage::CloseComplete);
+            }
+            FrontendMessage::Describe { kind, .. } => return Err(invalid_kind(kind)),
+            other => {
+                return Err(DbError::Network(format!(
+                    "Protocol violation: unexpected message {:?}",
+                    other
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn prepared(&self, name: &str) -> Result<&PreparedStatement, DbError> {
+        self.statements.get(name).ok_or_else(|| {
+            DbError::NotFound(format!("prepared statement \"{}\" does not exist", name))
+        })
+    }
+
+    // A prepared statement, bound again first if DDL has changed the
+    // catalog since Parse
+    fn revalidated(&mut self, name: &str) -> Result<&PreparedStatement, DbError> {
+        let prepared = self.statements.get_mut(name).ok_or_else(|| {
+            DbError::NotFound(format!("prepared statement \"{}\" does not exist", name))
+        })?;
+        if let Some(bound) = &mut prepared.bound {
+            self.context.executor.revalidate(bound)?;
+        }
+        Ok(prepared)
+    }
+
+    fn take_portal(&mut self, name: &str) -> Result<Portal, DbError> {
+        self.portals
+            .remove(name)
+            .ok_or_else(|| DbError::NotFound(format!("portal \"{}\" does not exist", name)))
+    }
+
+    // Result columns of a prepared statement, from its bound plan
+    fn describe_statement(
+        &self,
+        prepared: &PreparedStatement,
+    ) -> Result<Option<Vec<FieldDescription>>, DbError> {
+        let bound = match &prepared.command {
+            Command::Show(name) => {
+                return Ok(Some(describe_columns(
+                    &[name.clone()],
+                    &[DataType::Text],
+                    &[],
+                )))
+            }
+            Command::Statement(SqlStatement::Execute { name, .. }) => {
+                self.session.statements().get(name).ok()
+            }
+            _ => prepared.bound.as_ref(),
+        };
+        let Some(bound) = bound else {
+            return Ok(None);
+        };
+        Ok(bound
+            .describe(&self.context.catalog)?
+            .map(|(columns, column_types)| describe_columns(&columns, &column_types, &[])))
+    }
+
+    fn run_portal(&mut self, portal: &mut Portal) -> Result<(), DbError> {
+        if portal.outcome.is_none() {
+            let outcome = match &mut portal.bound {
+                Some(bound) => {
+                    let params = std::mem::take(&mut portal.params);
+                    self.execute_bound(&portal.command, bound, params)?
+                }
+                None => self.execute_command(&portal.command)?,
+            };
+            portal.outcome = Some(outcome);
+        }
+        Ok(())
+    }
+
+    // Describing a portal that returns rows runs it, so the description
+    // carries the result's own column types
+    fn describe_portal(&mut self, portal: &mut Portal) -> Result<(), DbError> {
+        if !self.returns_rows(&portal.command) {
+            self.send(BackendMessage::NoData);
+            return Ok(());
+        }
+        self.run_portal(portal)?;
+        if let Some(Outcome::Rows(result)) = &portal.outcome {
+            self.send(BackendMessage::RowDescription(describe_columns(
+                &result.columns,
+                &result.column_types,
+                &portal.result_formats,
+            )));
+        }
+        Ok(())
+    }
+
+    fn execute_portal(&mut self, portal: &mut Portal, max_rows: u32) -> Result<(), DbError> {
+        self.run_portal(portal)?;
+        match &portal.outcome {
+            Some(Outcome::Rows(result)) => {
+                let total = result.rows.len();
+                let end = match max_rows {
+                    0 => total,
+                    n => total.min(portal.sent.saturating_add(n as usize)),
+                };
+                self.send_rows(result, portal.sent..end, &portal.result_formats)?;
+                let count = end - portal.sent;
+                portal.sent = end;
+                if end < total {
+                    self.send(BackendMessage::PortalSuspended);
+                } else {
+                    self.send(BackendMessage::CommandComplete(rows_tag(
+                        &portal.command,
+                        count,
+                    )));
+                }
+            }
+            Some(Outcome::Complete(tag)) => self.send(BackendMessage::CommandComplete(tag.clone())),
+            Some(Outcome::Empty) | None => self.send(BackendMessage::EmptyQueryResponse),
+        }
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------
+    // Statements
+    // ------------------------------------------------------------------
+
+    fn parse_command(&self, sql: &str) -> Result<Command, DbError> {
+        let sql = sql.trim().trim_end_matches(';').trim_end();
+        if sql.is_empty() {
+            return Ok(Command::Empty);
+        }
+        if let Some(command) = session_command(sql) {
+            return Ok(command);
+        }
+        let mut statements = self.context.parser.parse(sql)?;
+        if let Some(firewall) = &self.context.firewall {
+            firewall.check(&self.firewall_identity(), sql)?;
+        }
+        match statements.len() {
+            0 => Ok(Command::Empty),
+            1 => Ok(match statements.remove(0) {
+                SqlStatement::Begin { isolation } => Command::Begin(isolation),
+                SqlStatement::Commit => Command::Commit,
+                SqlStatement::Rollback { savepoint: None } => Command::Rollback,
+                statement => Command::Statement(statement),
+            }),
+            _ => Err(DbError::SqlParse(
+                "cannot insert multiple commands into a prepared statement".to_string(),
+            )),
+        }
+    }
+
+    fn firewall_identity(&self) -> FirewallIdentity {
+        FirewallIdentity {
+            role: self.user.clone(),
+            application: self
+                .parameters
+                .get("application_name")
+                .filter(|name| !name.is_empty())
+                .cloned(),
+            session_id: self.auth_session.clone(),
+            client_addr: self.client_addr.clone(),
+        }
+    }
+
+    fn check_not_aborted(&self, command: &Command) -> Result<(), DbError> {
+        if self.transaction == TransactionStatus::Failed
+            && !matches!(
+                command,
+                Command::Commit
+                    | Command::Rollback
+                    | Command::Statement(SqlStatement::Rollback { .. })
+            )
+        {
+            return Err(DbError::Transaction(
+                "current transaction is aborted, commands ignored until end of transaction block"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: &Command) -> Result<Outcome, DbError> {
+        self.check_not_aborted(command)?;
+        let outcome = self.run_command(command);
+        self.sync_transaction();
+        outcome
+    }
+
+    fn run_command(&mut self, command: &Command) -> Result<Outcome, DbError> {
+        Ok(match command {
+            Command::Empty => Outcome::Empty,
+            Command::Begin(isolation) => {
+                // PostgreSQL only warns about a BEGIN inside a block
+                if !self.session.in_transaction() {
+                    self.session.begin(&self.context.executor, *isolation)?;
+                }
+                Outcome::Complete("BEGIN".to_string())
+            }
+            // COMMIT of a failed block rolls it back
+            Command::Commit if self.transaction == TransactionStatus::Failed => {
+                self.session.rollback()?;
+                Outcome::Complete("ROLLBACK".to_string())
+            }
+            Command::Commit => {
+                self.session.commit()?;
+                Outcome::Complete("COMMIT".to_string())
+            }
+            Command::Rollback => {
+                self.session.rollback()?;
+                Outcome::Complete("ROLLBACK".to_string())
+            }
+            Command::Set { name, value } => {
+                // Settings the engine knows are checked and applied; the
+                // rest are only reported back by SHOW
+                self.session.set_parameter(name, value)?;
+                self.parameters.insert(name.clone(), value.clone());
+                Outcome::Complete("SET".to_string())
+            }
+            Command::Show(name) => {
+                let value = self.parameters.get(name).cloned().ok_or_else(|| {
+                    DbError::NotFound(format!("unrecognized configuration parameter \"{}\"", name))
+                })?;
+                Outcome::Rows(QueryResult::typed(
+                    vec![name.clone()],
+                    vec![DataType::Text],
+                    vec![vec![Value::String(value)]],
+                ))
+            }
+            Command::Statement(stmt) => {
+                let result = self.session.run(&self.context.executor, stmt.clone())?;
+                // Rolling back to a savepoint recovers a failed block
+                if let SqlStatement::Rollback { savepoint: Some(_) } = stmt {
+                    self.transaction = TransactionStatus::InBlock;
+                }
+                self.statement_outcome(stmt, result)
+            }
+        })
+    }
+
+    // Block status for ReadyForQuery, from the session's transaction; a
+    // failed block stays failed until it ends
+    fn sync_transaction(&mut self) {
+        self.transaction = match (self.session.in_transaction(), self.transaction) {
+            (false, _) => TransactionStatus::Idle,
+            (true, TransactionStatus::Failed) => TransactionStatus::Failed,
+            (true, _) => TransactionStatus::InBlock,
+        };
+    }
+
+    fn execute_bound(
+        &mut self,
+        command: &Command,
+        bound: &mut BoundStatement,
+        params: Vec<Value>,
+    ) -> Result<Outcome, DbError> {
+        self.check_not_aborted(command)?;
+        let result = self
+            .session
+            .executor(&self.context.executor)
+            .execute_prepared(bound, params)?;
+        Ok(self.statement_outcome(bound.statement(), result))
+    }
+
+    // EXECUTE reports the result of the statement it runs
+    fn executed_statement<'s>(&'s self, stmt: &'s SqlStatement) -> &'s SqlStatement {
+        match stmt {
+            SqlStatement::Execute { name, .. } => self
+                .session
+                .statements()
+                .get(name)
+                .map_or(stmt, |prepared| prepared.statement()),
+            stmt => stmt,
+        }
+    }
+
+    fn returns_rows(&self, command: &Command) -> bool {
+        match command {
+            Command::Statement(stmt) => match self.executed_statement(stmt) {
+                // A procedure returns a row of its OUT parameters
+                SqlStatement::ExecProcedure { name, .. } => self
+                    .context
+                    .catalog
+                    .get_procedure(name)
+                    .is_ok_and(|procedure| procedure.output_parameters().next().is_some()),
+                stmt => statement_returns_rows(stmt),
+            },
+            command => command.returns_rows(),
+        }
+    }
+
+    fn statement_outcome(&self, stmt: &SqlStatement, result: QueryResult) -> Outcome {
+        let stmt = self.executed_statement(stmt);
+        let procedure_row =
+            matches!(stmt, SqlStatement::ExecProcedure { .. }) && !result.columns.is_empty();
+        if statement_returns_rows(stmt) || procedure_row {
+            Outcome::Rows(result)
+        } else {
+            Outcome::Complete(command_tag(stmt, &result))
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Output
+    // ------------------------------------------------------------------
+
+    fn send(&mut self, message: BackendMessage) {
+        message.encode(&mut self.write_buf);
+    }
+
+    fn send_rows(
+        &mut self,
+        result: &QueryResult,
+        rows: Range<usize>,
+        formats: &[i16],
+    ) -> Result<(), DbError> {
+        for row in &result.rows[rows] {
+            let values = row
+                .iter()
+                .enumerate()
+                .map(|(i, value)| match format_code(formats, i) {
+                    1 => types::encode_binary(
+                        value,
+                        result.column_types.get(i).unwrap_or(&DataType::Text),
+                    ),
+                    _ => Ok(types::encode_text(value)),
+                })
+                .collect::<Result<Vec<_>, DbError>>()?;
+            self.send(BackendMessage::DataRow(values));
+        }
+        Ok(())
+    }
+
+    fn send_error(&mut self, severity: &'static str, code: &'static str, message: String) {
+        self.send(BackendMessage::ErrorResponse {
+            severity,
+            code,
+            message,
+        });
+    }
+
+    // Report a statement error; an open transaction block becomes failed
+    fn send_db_error(&mut self, error: &DbError) {
+        if self.session.in_transaction() {
+            self.transaction = TransactionStatus::Failed;
+        }
+        self.send_error("ERROR", error.sqlstate(), error.to_string());
+    }
+
+    fn ready_for_query(&mut self) {
+        self.send(BackendMessage::ReadyForQuery(self.transaction.indicator()));
+    }
+
+    // ------------------------------------------------------------------
+    // I/O
+    // ------------------------------------------------------------------
+
+    async fn flush(&mut self) -> Result<(), DbError> {
+        if self.write_buf.is_empty() {
+            return Ok(());
+        }
+        self.stream
+            .write_all(&self.write_buf)
+            .await
+            .map_err(|e| DbError::Network(e.to_string()))?;
+        self.write_buf.clear();
+        self.stream
+            .flush()
+            .await
+            .map_err(|e| DbError::Network(e.to_string()))
+    }
+
+    // Read more input, flushing pending output first; false at end of stream
+    async fn fill(&mut self) -> Result<bool, DbError> {
+        self.flush().await?;
+        self.read_buf.reserve(READ_CHUNK_SIZE);
+        let n = self
+            .stream
+            .read_buf(&mut self.read_buf)
+            .await
+            .map_err(|e| DbError::Network(e.to_string()))?;
+        Ok(n > 0)
+    }
+
+    async fn read_startup(&mut self) -> Result<Option<StartupPacket>, DbError> {
+        loop {
+            if let Some(packet) = StartupPacket::decode(&mut self.read_buf)? {
+                return Ok(Some(packet));
+            }
+            if !self.fill().await? {
+                return Ok(None);
+            }
+        }
+    }
+
+    async fn read_message(&mut self) -> Result<Option<FrontendMessage>, DbError> {
+        loop {
+            if let Some(message) = FrontendMessage::decode(&mut self.read_buf)? {
+                return Ok(Some(message));
+            }
+            if !self.fill().await? {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+fn invalid_kind(kind: u8) -> DbError {
+    DbError::Network(format!(
+        "Protocol violation: invalid object type '{}'",
+        kind as char
+    ))
+}
+
+fn check_sql_length(sql: &str) -> Result<(), DbError> {
+    if sql.len() > MAX_SQL_LENGTH {
+        return Err(DbError::LimitExceeded(format!(
+            "SQL query too large: {} bytes (max: {} bytes)",
+            sql.len(),
+            MAX_SQL_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+// Format code of column or parameter `i`: none means all text, a single
+// code applies to all
+fn format_code(formats: &[i16], i: usize) -> i16 {
+    match formats {
+        [] => 0,
+        [format] => *format,
+        formats => formats.get(i).copied().unwrap_or(0),
+    }
+}
+
+fn describe_columns(
+    columns: &[String],
+    column_types: &[DataType],
+    formats: &[i16],
+) -> Vec<FieldDescription> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let data_type = column_types.get(i).unwrap_or(&DataType::Text);
+            FieldDescription {
+                name: name.clone(),
+                type_oid: types::type_oid(data_type),
+                type_size: types::type_size(data_type),
+                type_modifier: types::type_modifier(data_type),
+                format: format_code(formats, i),
+            }
+        })
+        .collect()
+}
+
+fn rows_tag(command: &Command, rows: usize) -> String {
+    match command {
+        Command::Show(_) => "SHOW".to_string(),
+        Command::Statement(SqlStatement::ExecProcedure { .. }) => "CALL".to_string(),
+        _ => format!("SELECT {}", rows),
+    }
+}
+
+// CommandComplete tag of a statement that returns no rows
+fn command_tag(stmt: &SqlStatement, result: &QueryResult) -> String {
+    let affected = result.rows_affected;
+    match stmt {
+        SqlStatement::Select { .. } | SqlStatement::Union { .. } => {
+            format!("SELECT {}", result.rows.len())
+        }
+        SqlStatement::SelectInto { .. } => format!("SELECT {}", affected),
+        SqlStatement::Insert { .. } | SqlStatement::InsertIntoSelect { .. } => {
+            format!("INSERT 0 {}", affected)
+        }
+        SqlStatement::Update { .. } => format!("UPDATE {}", affected),
+        SqlStatement::Delete { .. } => format!("DELETE {}", affected),
+        SqlStatement::CreateTable { .. } => "CREATE TABLE".to_string(),
+        SqlStatement::DropTable { .. } => "DROP TABLE".to_string(),
+        SqlStatement::CreateIndex { .. } => "CREATE INDEX".to_string(),
+        SqlStatement::CreateView { .. } => "CREATE VIEW".to_string(),
+        SqlStatement::DropView { .. } => "DROP VIEW".to_string(),
+        SqlStatement::DropIndex { .. } => "DROP INDEX".to_string(),
+        SqlStatement::TruncateTable { .. } => "TRUNCATE TABLE".to_string(),
+        SqlStatement::AlterTable { .. } => "ALTER TABLE".to_string(),
+        SqlStatement::CreateDatabase { .. } => "CREATE DATABASE".to_string(),
+        SqlStatement::DropDatabase { .. } => "DROP DATABASE".to_string(),
+        SqlStatement::BackupDatabase { .. } => "BACKUP".to_string(),
+        SqlStatement::CreateProcedure { .. } => "CREATE PROCEDURE".to_string(),
+        SqlStatement::DropProcedure { .. } => "DROP PROCEDURE".to_string(),
+        SqlStatement::CreateTrigger { .. } => "CREATE TRIGGER".to_string(),
+        SqlStatement::DropTrigger { .. } => "DROP TRIGGER".to_string(),
+        SqlStatement::ExecProcedure { .. } => "CALL".to_string(),
+        SqlStatement::GrantPermission { .. } => "GRANT".to_string(),
+        SqlStatement::RevokePermission { .. } => "REVOKE".to_string(),
+        SqlStatement::Prepare { .. } => "PREPARE".to_string(),
+        SqlStatement::Execute { .. } => "EXECUTE".to_string(),
+        SqlStatement::Deallocate { name: Some(_) } => "DEALLOCATE".to_string(),
+        SqlStatement::Deallocate { name: None } => "DEALLOCATE ALL".to_string(),
+        SqlStatement::Begin { .. } => "BEGIN".to_string(),
+        SqlStatement::Commit => "COMMIT".to_string(),
+        SqlStatement::Rollback { .. } => "ROLLBACK".to_string(),
+        SqlStatement::Savepoint { .. } => "SAVEPOINT".to_string(),
+        SqlStatement::ReleaseSavepoint { .. } => "RELEASE".to_string(),
+        SqlStatement::SetTransaction { .. } | SqlStatement::SetParameter { .. } => {
+            "SET".to_string()
+        }
+        SqlStatement::Analyze { .. } => "ANALYZE".to_string(),
+        SqlStatement::Explain { .. } => "EXPLAIN".to_string(),
+    }
+}
+
+// SET and SHOW, which the SQL parser does not accept, and plain BEGIN,
+// COMMIT and ROLLBACK; the parser takes transaction commands with options
+fn session_command(sql: &str) -> Option<Command> {
+    let words: Vec<String> = sql
+        .split_whitespace()
+        .map(|w| w.to_ascii_uppercase())
+        .collect();
+    let only_noise = |rest: &[String]| rest.iter().all(|w| w == "WORK" || w == "TRANSACTION");
+    match words.first()?.as_str() {
+        "BEGIN" if only_noise(&words[1..]) => Some(Command::Begin(None)),
+        "START" if words[1..] == ["TRANSACTION"] => Some(Command::Begin(None)),
+        "COMMIT" | "END" if only_noise(&words[1..]) => Some(Command::Commit),
+        "ROLLBACK" | "ABORT" if only_noise(&words[1..]) => Some(Command::Rollback),
+        "SHOW" if words.len() == 2 => Some(Command::Show(words[1].to_ascii_lowercase())),
+        "SET" => parse_set(sql[3..].trim()),
+        _ => None,
+    }
+}
+
+// `[SESSION | LOCAL] name {TO | =} value` or `TIME ZONE value`
+fn parse_set(text: &str) -> Option<Command> {
+    let upper = text.to_ascii_uppercase();
+    let text = ["SESSION ", "LOCAL "]
+        .iter()
+        .find(|prefix| upper.starts_with(*prefix))
+        .map_or(text, |prefix| text[prefix.len()..].trim_start());
+
+    let (name, value) = if text.to_ascii_uppercase().starts_with("TIME ZONE") {
+        ("timezone".to_string(), text["TIME ZONE".len()..].trim())
+    } else if let Some((name, value)) = text.split_once('=') {
+        (name.trim().to_ascii_lowercase(), value.trim())
+    } else {
+        let (name, rest) = text.split_once(char::is_whitespace)?;
+        let rest = rest.trim_start();
+        if rest.get(..2)?.to_ascii_uppercase() != "TO" {
+            return None;
+        }
+        (name.to_ascii_lowercase(), rest[2..].trim())
+    };
+    if name.is_empty() || value.is_empty() {
+        return None;
+    }
+    let value = value
+        .strip_prefix('\'')
+        .and_then(|v| v.strip_suffix('\''))
+        .unwrap_or(value);
+    Some(Command::Set {
+        name,
+        value: value.to_string(),
+    })
+}
+
+// Split a query string at top-level semicolons, skipping quoted and
+// dollar-quoted text such as a procedure body. Scanning bytes is safe
+// because the delimiters are ASCII.
+fn split_statements(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut semicolons = Vec::new();
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None => match b {
+                b'\'' | b'"' => quote = Some(b),
+                b';' => semicolons.push(i),
+                b'$' => {
+                    if let Some(tag) = dollar_tag(&sql[i..]) {
+                        let body = i + tag.len();
+                        i = sql[body..]
+                            .find(tag)
+                            .map_or(sql.len(), |end| body + end + tag.len());
+                        continue;
+                    }
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+
+    let mut start = 0;
+    let mut statements = Vec::new();
+    for end in semicolons.into_iter().chain(std::iter::once(sql.len())) {
+        let statement = sql[start..end].trim();
+        if !statement.is_empty() {
+            statements.push(statement);
+        }
+        start = end + 1;
+    }
+    statements
+}
+
+// The `$tag$` opening dollar-quoted text at the start of `text`; a tag does
+// not start with a digit, so parameters such as `$1` are not taken for one
+fn dollar_tag(text: &str) -> Option<&str> {
+    let end = text[1..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))? + 1;
+    let digit = text[1..end].starts_with(|c: char| c.is_ascii_digit());
+    (text[end..].starts_with('$') && !digit).then_some(&text[..=end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+    use crate::execution::Executor;
+    use crate::parser::SqlParser;
+    use crate::security::authentication::{AccountStatus, AuthenticationManager};
+    use crate::security::sql_firewall::{FirewallMode, FirewallScope, SqlFirewall};
+    use crate::transaction::TransactionManager;
+    use bytes::{Buf, BufMut};
+    use tokio::io::DuplexStream;
+
+    #[test]
+    fn test_statement_scanning() {
+        assert_eq!(
+            split_statements("SELECT ';'; SELECT 2;; "),
+            vec!["SELECT ';'", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("CREATE PROCEDURE p AS $$ BEGIN NULL; END; $$; CALL p($1)"),
+            vec!["CREATE PROCEDURE p AS $$ BEGIN NULL; END; $$", "CALL p($1)"]
+        );
+    }
+
+    #[test]
+    fn test_session_commands() {
+        assert!(matches!(session_command("begin"), Some(Command::Begin(None))));
+        assert!(session_command("BEGIN ISOLATION LEVEL SERIALIZABLE").is_none());
+        assert!(matches!(session_command("COMMIT WORK"), Some(Command::Commit)));
+        assert!(session_command("ROLLBACK TO SAVEPOINT a").is_none());
+        assert!(matches!(
+            session_command("SET application_name = 'psql'"),
+            Some(Command::Set { name, value }) if name == "application_name" && value == "psql"
+        ));
+        assert!(matches!(
+            session_command("SET SESSION TIME ZONE 'UTC'"),
+            Some(Command::Set { name, .. }) if name == "timezone"
+        ));
+        assert!(matches!(
+            session_command("SHOW DateStyle"),
+            Some(Command::Show(name)) if name == "datestyle"
+        ));
+    }
+
+    fn context(
+        authentication: Option<Arc<AuthenticationManager>>,
+        auth_method: PgAuthMethod,
+    ) -> Arc<SessionContext> {
+        firewalled_context(authentication, auth_method, None)
+    }
+
+    fn firewalled_context(
+        authentication: Option<Arc<AuthenticationManager>>,
+        auth_method: PgAuthMethod,
+        firewall: Option<Arc<SqlFirewall>>,
+    ) -> Arc<SessionContext> {
+        let catalog = Arc::new(Catalog::new());
+        let executor = Arc::new(Executor::new(
+            catalog.clone(),
+            Arc::new(TransactionManager::new()),
+        ));
+        Arc::new(SessionContext {
+            catalog,
+            executor,
+            parser: Arc::new(SqlParser::new()),
+            authentication,
+            auth_method,
+            firewall,
+        })
+    }
+
+    // Test client speaking raw protocol messages over an in-memory stream
+    struct Client {
+        stream: DuplexStream,
+        buf: BytesMut,
+    }
+
+    impl Client {
+        fn connect(context: Arc<SessionContext>) -> Self {
+            let (client, server) = tokio::io::duplex(64 * 1024);
+            tokio::spawn(PgSession::new(server, context, 1, None).run());
+            Self {
+                stream: client,
+                buf: BytesMut::new(),
+            }
+        }
+
+        async fn startup(&mut self, user: &str) {
+            let mut body = BytesMut::new();
+            body.put_i32(codec::PROTOCOL_VERSION_3);
+            body.put_slice(format!("user\0{}\0\0", user).as_bytes());
+            let mut packet = BytesMut::new();
+            packet.put_i32(4 + body.len() as i32);
+            packet.put_slice(&body);
+            self.stream.write_all(&packet).await.unwrap();
+        }
+
+        async fn send(&mut self, tag: u8, body: &[u8]) {
+            let mut message = BytesMut::new();
+            message.put_u8(tag);
+            message.put_i32(4 + body.len() as i32);
+            message.put_slice(body);
+            self.stream.write_all(&message).await.unwrap();
+        }
+
+        async fn query(&mut self, sql: &str) {
+            self.send(b'Q', format!("{}\0", sql).as_bytes()).await;
+        }
+
+        // Next message as (tag, body)
+        async fn recv(&mut self) -> (u8, Vec<u8>) {
+            loop {
+                if self.buf.len() >= 5 {
+                    let len = i32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]);
+                    if self.buf.len() > len as usize {
+                        let tag = self.buf[0];
+                        self.buf.advance(5);
+                        return (tag, self.buf.split_to(len as usize - 4).to_vec());
+                    }
+                }
+                assert!(self.stream.read_buf(&mut self.buf).await.unwrap() > 0, "connection closed");
+            }
+        }
+
+        // Messages up to and including ReadyForQuery
+        async fn recv_until_ready(&mut self) -> Vec<(u8, Vec<u8>)> {
+            let mut messages = Vec::new();
+            loop {
+                let message = self.recv().await;
+                let ready = message.0 == b'Z';
+                messages.push(message);
+                if ready {
+                    return messages;
+                }
+            }
+        }
+    }
+
+    fn tags(messages: &[(u8, Vec<u8>)]) -> String {
+        messages.iter().map(|(tag, _)| *tag as char).collect()
+    }
+
+    fn data_row(body: &[u8]) -> Vec<Option<String>> {
+        let mut body = body;
+        let count = body.get_i16();
+        (0..count)
+            .map(|_| match body.get_i32() {
+                -1 => None,
+                len => {
+                    let value = String::from_utf8(body[..len as usize].to_vec()).unwrap();
+                    body.advance(len as usize);
+                    Some(value)
+                }
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_simple_query_protocol() {
+        let mut client = Client::connect(context(None, PgAuthMethod::Trust));
+        client.startup("alice").await;
+        let messages = client.recv_until_ready().await;
+        assert_eq!(messages[0], (b'R', vec![0, 0, 0, 0]));
+        assert_eq!(messages.last().unwrap(), &(b'Z', vec![b'I']));
+
+        client
+            .query("CREATE TABLE t (id INT, name TEXT); INSERT INTO t VALUES (1, 'a'), (2, NULL)")
+            .await;
+        let messages = client.recv_until_ready().await;
+        assert_eq!(tags(&messages), "CCZ");
+        assert_eq!(messages[1].1, b"INSERT 0 2\0");
+
+        client.query("SELECT id, name FROM t ORDER BY id").await;
+        let messages = client.recv_until_ready().await;
+        assert_eq!(tags(&messages), "TDDCZ");
+        assert_eq!(
+            data_row(&messages[2].1),
+            vec![Some("2".to_string()), None]
+        );
+        assert_eq!(messages[3].1, b"SELECT 2\0");
+
+        // Errors carry a SQLSTATE and fail an open transaction block
+        client.query("BEGIN; SELECT * FROM missing").await;
+        let messages = client.recv_until_ready().await;
+        assert_eq!(tags(&messages), "CEZ");
+        assert!(String::from_utf8_lossy(&messages[1].1).contains("C42P01"));
+        assert_eq!(messages[2].1, vec![b'E']);
+        client.query("ROLLBACK").await;
+        assert_eq!(client.recv_until_ready().await.last().unwrap().1, vec![b'I']);
+
+        client.query("").await;
+        assert_eq!(tags(&client.recv_until_ready().await), "IZ");
+    }
+
+    #[tokio::test]
+    async fn test_transaction_blocks() {
+        let context = context(None, PgAuthMethod::Trust);
+        let mut alice = Client::connect(context.clone());
+        let mut bob = Client::connect(context);
+        for client in [&mut alice, &mut bob] {
+            client.startup("alice").await;
+            client.recv_until_ready().await;
+        }
+        alice.query("CREATE TABLE t (id INT)").await;
+        alice.recv_until_ready().await;
+
+        alice
+            .query(
+                "BEGIN ISOLATION LEVEL REPEATABLE READ; INSERT INTO t VALUES (1); \
+                 SAVEPOINT s; INSERT INTO t VALUES (2); ROLLBACK TO SAVEPOINT s",
+            )
+            .await;
+        let messages = alice.recv_until_ready().await;
+        assert_eq!(tags(&messages), "CCCCCZ");
+        assert_eq!(messages[4].1, b"ROLLBACK\0");
+        assert_eq!(messages[5].1, vec![b'T']);
+
+        // Uncommitted rows are not visible to other connections
+        bob.query("SELECT id FROM t").await;
+        assert_eq!(tags(&bob.recv_until_ready().await), "TCZ");
+
+        alice.query("COMMIT").await;
+        let messages = alice.recv_until_ready().await;
+        assert_eq!(messages[0].1, b"COMMIT\0");
+        assert_eq!(messages[1].1, vec![b'I']);
+        bob.query("SELECT id FROM t").await;
+        let messages = bob.recv_until_ready().await;
+        assert_eq!(tags(&messages), "TDCZ");
+        assert_eq!(data_row(&messages[1].1), vec![Some("1".to_string())]);
+
+        // COMMIT of a failed block rolls it back
+        alice.query("BEGIN; INSERT INTO t VALUES (3); SELECT * FROM missing").await;
+        assert_eq!(alice.recv_until_ready().await.last().unwrap().1, vec![b'E']);
+        alice.query("COMMIT").await;
+        assert_eq!(alice.recv_until_ready().await[0].1, b"ROLLBACK\0");
+        bob.query("SELECT id FROM t").await;
+        assert_eq!(tags(&bob.recv_until_ready().await), "TDCZ");
+    }
+
+    #[tokio::test]
+    async fn test_extended_query_protocol() {
+        let mut client = Client::connect(context(None, PgAuthMethod::Trust));
+        client.startup("alice").await;
+        client.recv_until_ready().await;
+        client.query("CREATE TABLE t (id INT, name TEXT)").await;
+        client.recv_until_ready().await;
+
+        let mut parse = BytesMut::new();
+        parse.put_slice(b"ins\0INSERT INTO t VALUES ($1, $2)\0");
+        parse.put_i16(1);
+        parse.put_u32(types::INT4_OID);
+        client.send(b'P', &parse).await;
+
+        // Binary int4 and a text parameter needing quotes
+        let mut bind = BytesMut::new();
+        bind.put_slice(b"\0ins\0");
+        bind.put_i16(2);
+        bind.put_i16(1);
+        bind.put_i16(0);
+        bind.put_i16(2);
+        bind.put_i32(4);
+        bind.put_i32(7);
+        bind.put_i32(4);
+        bind.put_slice(b"it's");
+        bind.put_i16(0);
+        client.send(b'B', &bind).await;
+        client.send(b'E', b"\0\0\0\0\0").await;
+        client.send(b'S', b"").await;
+        let messages = client.recv_until_ready().await;
+        assert_eq!(tags(&messages), "12CZ");
+        assert_eq!(messages[2].1, b"INSERT 0 1\0");
+
+        // Describe the statement, then fetch one row at a time
+        client.send(b'P', b"\0SELECT name FROM t WHERE id = $1\0\0\0").await;
+        client.send(b'D', b"S\0").await;
+        let mut bind = BytesMut::new();
+        bind.put_slice(b"\0\0");
+        bind.put_i16(0);
+        bind.put_i16(1);
+        bind.put_i32(1);
+        bind.put_slice(b"7");
+        bind.put_i16(0);
+        client.send(b'B', &bind).await;
+        client.send(b'E', b"\0\0\0\0\x01").await;
+        client.send(b'E', b"\0\0\0\0\x01").await;
+        client.send(b'S', b"").await;
+        let messages = client.recv_until_ready().await;
+        assert_eq!(tags(&messages), "1tT2DsCZ");
+        // The parameter takes the type of the column it is compared with
+        assert_eq!(messages[1].1, [0, 1, 0, 0, 0, 23]);
+        assert_eq!(data_row(&messages[4].1), vec![Some("it's".to_string())]);
+        assert_eq!(messages[6].1, b"SELECT 0\0");
+
+        // Binding a statement at Parse reports a missing table there; after
+        // an error everything up to Sync is skipped
+        client.send(b'P', b"\0SELECT * FROM missing\0\0\0").await;
+        client.send(b'B', b"\0\0\0\0\0\0\0\0").await;
+        client.send(b'E', b"\0\0\0\0\0").await;
+        client.send(b'E', b"\0\0\0\0\0").await;
+        client.send(b'S', b"").await;
+        assert_eq!(tags(&client.recv_until_ready().await), "EZ");
+    }
+
+    #[tokio::test]
+    async fn test_sql_firewall() {
+        let firewall = Arc::new(SqlFirewall::new());
+        firewall.set_mode(FirewallMode::Enforcing);
+        firewall.trust_role("admin");
+        firewall.allow(
+            FirewallScope::Role("app".to_string()),
+            "SELECT id FROM t WHERE id = 1",
+        );
+        firewall.allow(
+            FirewallScope::Application("reports".to_string()),
+            "SELECT * FROM t",
+        );
+        let context = firewalled_context(None, PgAuthMethod::Trust, Some(firewall));
+
+        let mut admin = Client::connect(context.clone());
+        admin.startup("admin").await;
+        admin.recv_until_ready().await;
+        admin
+            .query("CREATE TABLE t (id INT); INSERT INTO t VALUES (1)")
+            .await;
+        assert_eq!(tags(&admin.recv_until_ready().await), "CCZ");
+
+        let mut app = Client::connect(context);
+        app.startup("app").await;
+        app.recv_until_ready().await;
+        app.query("select id from t where id = 7 -- lookup").await;
+        assert_eq!(tags(&app.recv_until_ready().await), "TCZ");
+        app.query("SELECT * FROM t").await;
+        let messages = app.recv_until_ready().await;
+        assert_eq!(tags(&messages), "EZ");
+        assert!(String::from_utf8_lossy(&messages[0].1).contains("C42501"));
+
+        // Parameters are bound values, not part of the checked statement
+        app.send(b'P', b"\0SELECT id FROM t WHERE id = $1\0\0\0")
+            .await;
+        let mut bind = BytesMut::new();
+        bind.put_slice(b"\0\0");
+        bind.put_i16(0);
+        bind.put_i16(1);
+        bind.put_i32(1);
+        bind.put_slice(b"1");
+        bind.put_i16(0);
+        app.send(b'B', &bind).await;
+        app.send(b'E', b"\0\0\0\0\0").await;
+        app.send(b'S', b"").await;
+        let messages = app.recv_until_ready().await;
+        assert_eq!(tags(&messages), "12DCZ");
+
+        // The application's allow-list applies once the client names it
+        app.query("SET application_name = 'reports'; SELECT * FROM t")
+            .await;
+        assert_eq!(tags(&app.recv_until_ready().await), "CTDCZ");
+    }
+
+    #[tokio::test]
+    async fn test_password_authentication() {
+        let manager = Arc::new(AuthenticationManager::new());
+        let user_id = manager
+            .create_user("bob".to_string(), "Secret#Pass123".to_string(), None)
+            .unwrap();
+        manager.users().write().get_mut(&user_id).unwrap().status = AccountStatus::Active;
+        let context = context(Some(manager), PgAuthMethod::Password);
+
+        let mut client = Client::connect(context.clone());
+        client.startup("bob").await;
+        assert_eq!(client.recv().await, (b'R', vec![0, 0, 0, 3]));
+        client.send(b'p', b"Secret#Pass123\0").await;
+        let messages = client.recv_until_ready().await;
+        assert_eq!(messages[0], (b'R', vec![0, 0, 0, 0]));
+
+        let mut client = Client::connect(context);
+        client.startup("bob").await;
+        client.recv().await;
+        client.send(b'p', b"wrong\0").await;
+        let (tag, body) = client.recv().await;
+        assert_eq!(tag, b'E');
+        assert!(String::from_utf8_lossy(&body).contains("C28P01"));
+    }
+}
@@ -0,0 +1,548 @@
+// Mapping between catalog types and PostgreSQL type OIDs, and the text and
+// binary value formats of the wire protocol
+
+use crate::catalog::DataType;
+use crate::common::{Decimal, Interval, Value};
+use crate::error::DbError;
+
+pub const BOOL_OID: u32 = 16;
+pub const BYTEA_OID: u32 = 17;
+pub const NAME_OID: u32 = 19;
+pub const INT8_OID: u32 = 20;
+pub const INT2_OID: u32 = 21;
+pub const INT4_OID: u32 = 23;
+pub const TEXT_OID: u32 = 25;
+pub const OID_OID: u32 = 26;
+pub const FLOAT4_OID: u32 = 700;
+pub const FLOAT8_OID: u32 = 701;
+pub const UNKNOWN_OID: u32 = 705;
+pub const BPCHAR_OID: u32 = 1042;
+pub const VARCHAR_OID: u32 = 1043;
+pub const DATE_OID: u32 = 1082;
+pub const TIMESTAMP_OID: u32 = 1114;
+pub const TIMESTAMPTZ_OID: u32 = 1184;
+pub const INTERVAL_OID: u32 = 1186;
+pub const NUMERIC_OID: u32 = 1700;
+pub const UUID_OID: u32 = 2950;
+
+// Element type OIDs and the OIDs of their one-dimensional array types
+const ARRAY_OIDS: &[(u32, u32)] = &[
+    (BOOL_OID, 1000),
+    (INT4_OID, 1007),
+    (TEXT_OID, 1009),
+    (VARCHAR_OID, 1015),
+    (INT8_OID, 1016),
+    (FLOAT4_OID, 1021),
+    (FLOAT8_OID, 1022),
+    (TIMESTAMP_OID, 1115),
+    (DATE_OID, 1182),
+    (TIMESTAMPTZ_OID, 1185),
+    (INTERVAL_OID, 1187),
+    (NUMERIC_OID, 1231),
+    (UUID_OID, 2951),
+];
+
+// Days and microseconds from the Unix epoch to PostgreSQL's 2000-01-01
+const PG_EPOCH_DAYS: i64 = 10_957;
+const PG_EPOCH_MICROS: i64 = PG_EPOCH_DAYS * 86_400_000_000;
+
+pub fn type_oid(data_type: &DataType) -> u32 {
+    match data_type {
+        DataType::Integer => INT4_OID,
+        DataType::BigInt => INT8_OID,
+        DataType::Float => FLOAT4_OID,
+        DataType::Double => FLOAT8_OID,
+        DataType::Varchar(_) => VARCHAR_OID,
+        // Vectors travel as their `[1,2,3]` text
+        DataType::Text | DataType::Vector(_) => TEXT_OID,
+        DataType::Boolean => BOOL_OID,
+        DataType::Date => DATE_OID,
+        DataType::Timestamp => TIMESTAMP_OID,
+        DataType::TimestampTz => TIMESTAMPTZ_OID,
+        DataType::Interval => INTERVAL_OID,
+        DataType::Numeric(_) => NUMERIC_OID,
+        DataType::Uuid => UUID_OID,
+        DataType::Array(element) => {
+            let element = type_oid(element);
+            ARRAY_OIDS
+                .iter()
+                .find(|(oid, _)| *oid == element)
+                .map_or(1009, |(_, array)| *array)
+        }
+    }
+}
+
+// pg_type.typlen: the fixed width in bytes, or -1 for variable length
+pub fn type_size(data_type: &DataType) -> i16 {
+    match data_type {
+        DataType::Integer | DataType::Float | DataType::Date => 4,
+        DataType::BigInt | DataType::Double | DataType::Timestamp | DataType::TimestampTz => 8,
+        DataType::Interval | DataType::Uuid => 16,
+        DataType::Boolean => 1,
+        DataType::Varchar(_)
+        | DataType::Text
+        | DataType::Vector(_)
+        | DataType::Numeric(_)
+        | DataType::Array(_) => -1,
+    }
+}
+
+// pg_attribute.atttypmod; VARCHAR(n) stores n plus the 4-byte header and
+// NUMERIC(p,s) packs p and s above that header
+pub fn type_modifier(data_type: &DataType) -> i32 {
+    match data_type {
+        DataType::Varchar(n) => i32::try_from(*n).map_or(-1, |n| n.saturating_add(4)),
+        DataType::Numeric(Some((precision, scale))) => {
+            (((*precision as i32) << 16) | *scale as i32) + 4
+        }
+        _ => -1,
+    }
+}
+
+// Text format of a value; `None` is SQL NULL
+pub fn encode_text(value: &Value) -> Option<Vec<u8>> {
+    if value.is_null() {
+        return None;
+    }
+    Some(text(value).into_bytes())
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Boolean(b) => if *b { "t" } else { "f" }.to_string(),
+        Value::Float(f) if f.is_nan() => "NaN".to_string(),
+        Value::Float(f) if f.is_infinite() => {
+            if *f > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+        }
+        Value::Bytes(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("\\x{}", hex)
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| match item {
+                    Value::Null => "NULL".to_string(),
+                    Value::Integer(_) | Value::Float(_) | Value::Numeric(_) | Value::Boolean(_) => {
+                        text(item)
+                    }
+                    _ => format!(
+                        "\"{}\"",
+                        text(item).replace('\\', "\\\\").replace('"', "\\\"")
+                    ),
+                })
+                .collect();
+            format!("{{{}}}", items.join(","))
+        }
+        other => other.to_display_string(),
+    }
+}
+
+// Binary format of a value in a column of `data_type`
+pub fn encode_binary(value: &Value, data_type: &DataType) -> Result<Option<Vec<u8>>, DbError> {
+    let value = data_type.coerce(value.clone())?;
+    Ok(Some(match (data_type, &value) {
+        (_, Value::Null) => return Ok(None),
+        (DataType::Integer, Value::Integer(i)) => (*i as i32).to_be_bytes().to_vec(),
+        (DataType::BigInt, Value::Integer(i)) => i.to_be_bytes().to_vec(),
+        (DataType::Float, Value::Float(f)) => (*f as f32).to_be_bytes().to_vec(),
+        (DataType::Double, Value::Float(f)) => f.to_be_bytes().to_vec(),
+        (DataType::Boolean, Value::Boolean(b)) => vec![*b as u8],
+        (DataType::Date, Value::Date(d)) => i32::try_from(d - PG_EPOCH_DAYS)
+            .map_err(|_| DbError::Execution(format!("Date {} out of range", value)))?
+            .to_be_bytes()
+            .to_vec(),
+        (DataType::Timestamp, Value::Timestamp(t))
+        | (DataType::TimestampTz, Value::TimestampTz(t)) => {
+            (t - PG_EPOCH_MICROS).to_be_bytes().to_vec()
+        }
+        (DataType::Numeric(_), Value::Numeric(d)) => numeric_binary(d),
+        (DataType::Interval, Value::Interval(i)) => {
+            let mut out = i.micros.to_be_bytes().to_vec();
+            out.extend_from_slice(&i.days.to_be_bytes());
+            out.extend_from_slice(&i.months.to_be_bytes());
+            out
+        }
+        (DataType::Uuid, Value::Uuid(u)) => u.as_bytes().to_vec(),
+        // One dimension with a lower bound of 1, each element prefixed by
+        // its length or -1 for NULL
+        (DataType::Array(element), Value::Array(items)) => {
+            let mut out = Vec::new();
+            out.extend_from_slice(&(!items.is_empty() as i32).to_be_bytes());
+            out.extend_from_slice(&(items.iter().any(Value::is_null) as i32).to_be_bytes());
+            out.extend_from_slice(&type_oid(element).to_be_bytes());
+            if !items.is_empty() {
+                out.extend_from_slice(&(items.len() as i32).to_be_bytes());
+                out.extend_from_slice(&1i32.to_be_bytes());
+            }
+            for item in items {
+                match encode_binary(item, element)? {
+                    Some(bytes) => {
+                        out.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+                        out.extend_from_slice(&bytes);
+                    }
+                    None => out.extend_from_slice(&(-1i32).to_be_bytes()),
+                }
+            }
+            out
+        }
+        (DataType::Varchar(_) | DataType::Text | DataType::Vector(_), value) => {
+            text(value).into_bytes()
+        }
+        (data_type, value) => {
+            return Err(DbError::Execution(format!(
+                "Cannot send '{}' as binary {}",
+                value, data_type
+            )))
+        }
+    }))
+}
+
+// NUMERIC's binary format: the number of base-10000 digits, the weight of
+// the first digit, the sign and the display scale, then the digits
+fn numeric_binary(value: &Decimal) -> Vec<u8> {
+    let scale = value.scale() as usize;
+    let digits = value.mantissa().unsigned_abs().to_string();
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    // Pad both parts to whole groups of four digits
+    let whole = format!("{:0>width$}", whole, width = whole.len().div_ceil(4) * 4);
+    let fraction = format!(
+        "{:0<width$}",
+        fraction,
+        width = fraction.len().div_ceil(4) * 4
+    );
+    let mut groups: Vec<i16> = whole
+        .as_bytes()
+        .chunks(4)
+        .chain(fraction.as_bytes().chunks(4))
+        .map(|group| group.iter().fold(0, |n, d| n * 10 + (d - b'0') as i16))
+        .collect();
+    let mut weight = whole.len() as i16 / 4 - 1;
+    let leading = groups.iter().take_while(|g| **g == 0).count();
+    groups.drain(..leading);
+    weight -= leading as i16;
+    while groups.last() == Some(&0) {
+        groups.pop();
+    }
+    if groups.is_empty() {
+        weight = 0;
+    }
+
+    let sign: u16 = if value.is_negative() { 0x4000 } else { 0 };
+    let mut out = Vec::with_capacity(8 + groups.len() * 2);
+    out.extend_from_slice(&(groups.len() as i16).to_be_bytes());
+    out.extend_from_slice(&weight.to_be_bytes());
+    out.extend_from_slice(&sign.to_be_bytes());
+    out.extend_from_slice(&(scale as i16).to_be_bytes());
+    for group in groups {
+        out.extend_from_slice(&group.to_be_bytes());
+    }
+    out
+}
+
+// Inverse of `numeric_binary`; `None` for NaN and malformed values
+fn numeric_from_binary(bytes: &[u8]) -> Option<Decimal> {
+    let field = |i: usize| {
+        Some(i16::from_be_bytes(
+            bytes.get(i * 2..i * 2 + 2)?.try_into().ok()?,
+        ))
+    };
+    let (count, weight, sign, scale) = (field(0)?, field(1)?, field(2)? as u16, field(3)?);
+    if (sign != 0 && sign != 0x4000) || count < 0 || scale < 0 {
+        return None;
+    }
+    let mut digits = String::new();
+    for i in 0..count as usize {
+        let group = field(4 + i)?;
+        if !(0..10_000).contains(&group) {
+            return None;
+        }
+        digits.push_str(&format!("{:04}", group));
+    }
+    // The decimal point falls after the digits of weight + 1 groups
+    let point = (weight as i64 + 1) * 4;
+    let text = if point <= 0 {
+        format!("0.{}{}", "0".repeat(point.unsigned_abs() as usize), digits)
+    } else if point as usize >= digits.len() {
+        format!("{}{}", digits, "0".repeat(point as usize - digits.len()))
+    } else {
+        let (whole, fraction) = digits.split_at(point as usize);
+        format!("{}.{}", whole, fraction)
+    };
+    let value = Decimal::parse(&text)?.rescale(scale as u32)?;
+    Some(if sign == 0x4000 { -value } else { value })
+}
+
+// Column type a client-declared parameter type maps onto; `None` for 0
+// (unspecified) and types the server does not store
+pub fn oid_type(oid: u32) -> Option<DataType> {
+    match oid {
+        INT2_OID | INT4_OID => Some(DataType::Integer),
+        INT8_OID => Some(DataType::BigInt),
+        FLOAT4_OID => Some(DataType::Float),
+        FLOAT8_OID => Some(DataType::Double),
+        NUMERIC_OID => Some(DataType::Numeric(None)),
+        BOOL_OID => Some(DataType::Boolean),
+        DATE_OID => Some(DataType::Date),
+        TIMESTAMP_OID => Some(DataType::Timestamp),
+        TIMESTAMPTZ_OID => Some(DataType::TimestampTz),
+        INTERVAL_OID => Some(DataType::Interval),
+        UUID_OID => Some(DataType::Uuid),
+        TEXT_OID | VARCHAR_OID | BPCHAR_OID | NAME_OID => Some(DataType::Text),
+        oid => ARRAY_OIDS
+            .iter()
+            .find(|(_, array)| *array == oid)
+            .and_then(|(element, _)| oid_type(*element))
+            .map(|element| DataType::Array(Box::new(element))),
+    }
+}
+
+// Decode a bound parameter into a typed value. Text-format values of
+// numeric, boolean and date/time types are parsed here; other text is
+// passed on as a string and coerced to the parameter's type on execution.
+pub fn parameter_value(value: Option<&[u8]>, format: i16, oid: u32) -> Result<Value, DbError> {
+    let Some(bytes) = value else {
+        return Ok(Value::Null);
+    };
+    let invalid = |what: &str| {
+        DbError::InvalidInput(format!("Invalid binary value for parameter of type {}", what))
+    };
+    fn utf8(bytes: &[u8]) -> Result<&str, DbError> {
+        std::str::from_utf8(bytes)
+            .map_err(|_| DbError::InvalidInput("Parameter is not valid UTF-8".to_string()))
+    }
+
+    if format == 0 {
+        let text = utf8(bytes)?;
+        return match oid {
+            INT2_OID | INT4_OID | INT8_OID | OID_OID => text
+                .trim()
+                .parse::<i64>()
+                .map(Value::Integer)
+                .map_err(|_| {
+                    DbError::InvalidInput(format!("Invalid integer parameter '{}'", text))
+                }),
+            FLOAT4_OID | FLOAT8_OID => text
+                .trim()
+                .parse::<f64>()
+                .map(Value::Float)
+                .map_err(|_| {
+                    DbError::InvalidInput(format!("Invalid numeric parameter '{}'", text))
+                }),
+            NUMERIC_OID => Decimal::parse(text).map(Value::Numeric).ok_or_else(|| {
+                DbError::InvalidInput(format!("Invalid numeric parameter '{}'", text))
+            }),
+            BOOL_OID => match text.trim().to_ascii_lowercase().as_str() {
+                "t" | "true" | "y" | "yes" | "on" | "1" => Ok(Value::Boolean(true)),
+                "f" | "false" | "n" | "no" | "off" | "0" => Ok(Value::Boolean(false)),
+                _ => Err(DbError::InvalidInput(format!(
+                    "Invalid boolean parameter '{}'",
+                    text
+                ))),
+            },
+            DATE_OID => Value::parse_date(text.trim())
+                .map(Value::Date)
+                .ok_or_else(|| DbError::InvalidInput(format!("Invalid date parameter '{}'", text))),
+            TIMESTAMP_OID => Value::parse_timestamp(text.trim())
+                .map(Value::Timestamp)
+                .ok_or_else(|| {
+                    DbError::InvalidInput(format!("Invalid timestamp parameter '{}'", text))
+                }),
+            TIMESTAMPTZ_OID => Value::parse_timestamptz(text.trim())
+                .map(Value::TimestampTz)
+                .ok_or_else(|| {
+                    DbError::InvalidInput(format!("Invalid timestamptz parameter '{}'", text))
+                }),
+            INTERVAL_OID => Interval::parse(text).map(Value::Interval).ok_or_else(|| {
+                DbError::InvalidInput(format!("Invalid interval parameter '{}'", text))
+            }),
+            UUID_OID => uuid::Uuid::parse_str(text.trim())
+                .map(Value::Uuid)
+                .map_err(|_| DbError::InvalidInput(format!("Invalid uuid parameter '{}'", text))),
+            _ => Ok(Value::String(text.to_string())),
+        };
+    }
+
+    Ok(match oid {
+        INT2_OID => Value::Integer(
+            i16::from_be_bytes(bytes.try_into().map_err(|_| invalid("int2"))?) as i64,
+        ),
+        INT4_OID => Value::Integer(
+            i32::from_be_bytes(bytes.try_into().map_err(|_| invalid("int4"))?) as i64,
+        ),
+        INT8_OID => Value::Integer(i64::from_be_bytes(
+            bytes.try_into().map_err(|_| invalid("int8"))?,
+        )),
+        FLOAT4_OID => Value::Float(
+            f32::from_be_bytes(bytes.try_into().map_err(|_| invalid("float4"))?) as f64,
+        ),
+        FLOAT8_OID => Value::Float(f64::from_be_bytes(
+            bytes.try_into().map_err(|_| invalid("float8"))?,
+        )),
+        BOOL_OID => match bytes {
+            [0] => Value::Boolean(false),
+            [_] => Value::Boolean(true),
+            _ => return Err(invalid("bool")),
+        },
+        DATE_OID => {
+            let days = i32::from_be_bytes(bytes.try_into().map_err(|_| invalid("date"))?);
+            Value::Date(days as i64 + PG_EPOCH_DAYS)
+        }
+        TIMESTAMP_OID => {
+            let micros = i64::from_be_bytes(bytes.try_into().map_err(|_| invalid("timestamp"))?);
+            Value::Timestamp(micros.saturating_add(PG_EPOCH_MICROS))
+        }
+        TIMESTAMPTZ_OID => {
+            let micros = i64::from_be_bytes(bytes.try_into().map_err(|_| invalid("timestamptz"))?);
+            Value::TimestampTz(micros.saturating_add(PG_EPOCH_MICROS))
+        }
+        NUMERIC_OID => {
+            Value::Numeric(numeric_from_binary(bytes).ok_or_else(|| invalid("numeric"))?)
+        }
+        INTERVAL_OID => {
+            let bytes: &[u8; 16] = bytes.try_into().map_err(|_| invalid("interval"))?;
+            let (micros, rest) = bytes.split_at(8);
+            let (days, months) = rest.split_at(4);
+            Value::Interval(Interval::new(
+                i32::from_be_bytes(months.try_into().expect("4 bytes")),
+                i32::from_be_bytes(days.try_into().expect("4 bytes")),
+                i64::from_be_bytes(micros.try_into().expect("8 bytes")),
+            ))
+        }
+        UUID_OID => Value::Uuid(uuid::Uuid::from_slice(bytes).map_err(|_| invalid("uuid"))?),
+        TEXT_OID | VARCHAR_OID | BPCHAR_OID | NAME_OID | UNKNOWN_OID | 0 => {
+            Value::String(utf8(bytes)?.to_string())
+        }
+        oid => {
+            return Err(DbError::NotImplemented(format!(
+                "Binary parameters of type OID {}",
+                oid
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_format() {
+        assert_eq!(encode_text(&Value::Null), None);
+        assert_eq!(encode_text(&Value::Boolean(true)), Some(b"t".to_vec()));
+        assert_eq!(encode_text(&Value::Float(f64::NEG_INFINITY)), Some(b"-Infinity".to_vec()));
+        assert_eq!(encode_text(&Value::Date(10_957)), Some(b"2000-01-01".to_vec()));
+        assert_eq!(
+            encode_text(&Value::Array(vec![
+                Value::Integer(1),
+                Value::Null,
+                Value::String("a\"b".to_string())
+            ])),
+            Some(b"{1,NULL,\"a\\\"b\"}".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_binary_format() -> Result<(), DbError> {
+        assert_eq!(
+            encode_binary(&Value::Integer(7), &DataType::Integer)?,
+            Some(vec![0, 0, 0, 7])
+        );
+        assert_eq!(
+            encode_binary(&Value::Date(PG_EPOCH_DAYS + 1), &DataType::Date)?,
+            Some(vec![0, 0, 0, 1])
+        );
+        assert_eq!(encode_binary(&Value::Null, &DataType::Text)?, None);
+        assert_eq!(
+            encode_binary(
+                &Value::Interval(Interval::new(1, 2, 3)),
+                &DataType::Interval
+            )?,
+            Some(vec![0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 1])
+        );
+        assert_eq!(
+            encode_binary(
+                &Value::Array(vec![Value::Integer(5), Value::Null]),
+                &DataType::Array(Box::new(DataType::Integer))
+            )?,
+            Some(
+                [
+                    &1i32.to_be_bytes()[..],
+                    &1i32.to_be_bytes(),
+                    &INT4_OID.to_be_bytes(),
+                    &2i32.to_be_bytes(),
+                    &1i32.to_be_bytes(),
+                    &4i32.to_be_bytes(),
+                    &5i32.to_be_bytes(),
+                    &(-1i32).to_be_bytes(),
+                ]
+                .concat()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_format() -> Result<(), DbError> {
+        let numeric = |text: &str| Value::Numeric(Decimal::parse(text).unwrap());
+        let binary = |text: &str| encode_binary(&numeric(text), &DataType::Numeric(None));
+        // 12345.678: digits 1, 2345 and 6780 with weight 1 and scale 3
+        assert_eq!(
+            binary("-12345.678")?,
+            Some(vec![
+                0, 3, 0, 1, 0x40, 0, 0, 3, 0, 1, 0x09, 0x29, 0x1A, 0x7C
+            ])
+        );
+        assert_eq!(binary("0.00")?, Some(vec![0, 0, 0, 0, 0, 0, 0, 2]));
+        for text in ["-12345.678", "0.00", "0.0001", "100000000", "7.50"] {
+            let Some(bytes) = binary(text)? else {
+                panic!("{} encodes as NULL", text);
+            };
+            let value = parameter_value(Some(&bytes), 1, NUMERIC_OID)?;
+            assert_eq!(value.to_display_string(), text);
+        }
+        assert_eq!(
+            parameter_value(Some(b"2.50"), 0, NUMERIC_OID)?,
+            numeric("2.50")
+        );
+        assert_eq!(
+            type_modifier(&DataType::Numeric(Some((10, 2)))),
+            ((10 << 16) | 2) + 4
+        );
+        assert_eq!(
+            type_oid(&DataType::Array(Box::new(DataType::Numeric(None)))),
+            1231
+        );
+        assert_eq!(
+            oid_type(1231),
+            Some(DataType::Array(Box::new(DataType::Numeric(None))))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parameter_values() -> Result<(), DbError> {
+        assert_eq!(parameter_value(None, 0, INT4_OID)?, Value::Null);
+        assert_eq!(parameter_value(Some(b"42"), 0, INT4_OID)?, Value::Integer(42));
+        assert!(parameter_value(Some(b"4x"), 0, INT4_OID).is_err());
+        assert_eq!(
+            parameter_value(Some(b"it's"), 0, 0)?,
+            Value::String("it's".to_string())
+        );
+        assert_eq!(parameter_value(Some(b"on"), 0, BOOL_OID)?, Value::Boolean(true));
+        assert_eq!(
+            parameter_value(Some(&(-5i64).to_be_bytes()), 1, INT8_OID)?,
+            Value::Integer(-5)
+        );
+        assert_eq!(
+            parameter_value(Some(&1.5f64.to_be_bytes()), 1, FLOAT8_OID)?,
+            Value::Float(1.5)
+        );
+        assert_eq!(
+            parameter_value(Some(&0i32.to_be_bytes()), 1, DATE_OID)?,
+            Value::Date(PG_EPOCH_DAYS)
+        );
+        Ok(())
+    }
+}
@@ -1,10 +1,38 @@
-use crate::execution::QueryResult;
+// Native protocol v2
+//
+// Every message is a frame: a big-endian u32 payload length followed by the
+// bincode-encoded `Request` or `Response`. A connection opens with
+// `Request::Startup`, which the server answers with `Response::Ready` or an
+// error before closing. Startup names the user and client application the
+// SQL firewall checks the connection's statements as.
+//
+// A query's result streams back as an optional `RowDescription`, any number
+// of `RowBatch` frames and a final `CommandComplete`. While a result is
+// streaming the client may send `Request::Cancel`; the server then stops
+// after the batch in flight and ends the result with `Cancelled` instead.
+//
+// `Prepare` binds a statement once under a name, answered with the types of
+// its `$n` parameters; each `Bind` then runs it with typed values and
+// streams the result like a query.
+//
+// `BeginTransaction` opens a transaction on the connection, answered with its
+// id; queries and prepared statements then run in it until `Commit` or
+// `Rollback`, and closing the connection rolls it back.
+
+use crate::catalog::DataType;
+use crate::common::Value;
+use crate::error::DbError;
+use bytes::{Buf, BufMut, BytesMut};
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 // ============================================================================
 // Constants - Bounds for Network Protocol
 // ============================================================================
 
+/// Native protocol version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 3;
+
 /// Maximum SQL query length (1MB) - prevents memory exhaustion from unbounded queries
 /// SECURITY ISSUE FIXED: EA5-U1 - Unbounded SQL String
 /// Previous code had no limit on SQL string size in Request::Query
@@ -18,25 +46,254 @@ pub const MAX_SQL_LENGTH: usize = 1_048_576; // 1MB
 /// See: diagrams/06_network_api_flow.md - Issue #3.3
 pub const MAX_BINCODE_SIZE: usize = 16_777_216; // 16MB
 
+/// Rows per `Response::RowBatch`
+pub const ROW_BATCH_SIZE: usize = 1024;
+
+/// Initial size of a connection's read and write buffers; they grow to fit
+/// larger frames and shrink back once those have been handled
+pub const INITIAL_BUFFER_SIZE: usize = 8 * 1024;
+
+const FRAME_HEADER_SIZE: usize = 4;
+
 // Client request
 #[derive(Debug, Clone, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
 pub enum Request {
+    /// First message on a connection
+    Startup {
+        protocol_version: u32,
+        user: String,
+        application_name: Option<String>,
+    },
     /// Execute SQL query
     /// NOTE: SQL string should be validated against MAX_SQL_LENGTH before processing
     /// to prevent memory exhaustion attacks
-    Query { sql: String },
+    Query {
+        sql: String,
+    },
     BeginTransaction,
     Commit,
     Rollback,
     Ping,
+    /// Stop the result currently streaming; ignored when there is none
+    Cancel,
+    /// Prepare `sql` as `name` for this connection; the empty name is
+    /// replaced by the next unnamed statement
+    Prepare {
+        name: String,
+        sql: String,
+    },
+    /// Run a prepared statement, one value per parameter
+    Bind {
+        name: String,
+        #[bincode(with_serde)]
+        params: Vec<Value>,
+    },
+    Deallocate {
+        name: String,
+    },
 }
 
 // Server response
 #[derive(Debug, Clone, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
 pub enum Response {
-    QueryResult(QueryResult),
+    /// Handshake accepted
+    Ready {
+        protocol_version: u32,
+        server_version: String,
+    },
+    /// Columns of the rows that follow
+    RowDescription {
+        columns: Vec<String>,
+        #[bincode(with_serde)]
+        column_types: Vec<DataType>,
+    },
+    RowBatch {
+        #[bincode(with_serde)]
+        rows: Vec<Vec<Value>>,
+    },
+    /// End of a query's result
+    CommandComplete {
+        rows_affected: u64,
+    },
+    /// End of a query's result, cut short by `Request::Cancel`
+    Cancelled,
     TransactionId(u64),
     Ok,
     Error(String),
     Pong,
+    /// Types of a prepared statement's parameters; `None` where the
+    /// statement does not fix one
+    ParameterDescription {
+        #[bincode(with_serde)]
+        param_types: Vec<Option<DataType>>,
+    },
+}
+
+// ============================================================================
+// Framing
+// ============================================================================
+
+fn violation(what: impl std::fmt::Display) -> DbError {
+    DbError::Network(format!("Protocol violation: {}", what))
+}
+
+/// Append `message` to `out` as one frame
+pub fn encode_frame<T: bincode::Encode>(message: &T, out: &mut BytesMut) -> Result<(), DbError> {
+    let start = out.len();
+    out.put_u32(0);
+    bincode::encode_into_std_write(message, &mut out.writer(), bincode::config::standard())
+        .map_err(|e| DbError::Serialization(e.to_string()))?;
+
+    let len = out.len() - start - FRAME_HEADER_SIZE;
+    if len > MAX_BINCODE_SIZE {
+        out.truncate(start);
+        return Err(DbError::Network(format!(
+            "Message too large: {} bytes (max: {} bytes)",
+            len, MAX_BINCODE_SIZE
+        )));
+    }
+    out[start..start + FRAME_HEADER_SIZE].copy_from_slice(&(len as u32).to_be_bytes());
+    Ok(())
+}
+
+/// Take one frame off the front of `buf`; `None` until it has fully arrived
+///
+/// Frames longer than `max_len` are rejected before their payload is read.
+pub fn decode_frame<T: bincode::Decode<()>>(
+    buf: &mut BytesMut,
+    max_len: usize,
+) -> Result<Option<T>, DbError> {
+    if buf.len() < FRAME_HEADER_SIZE {
+        return Ok(None);
+    }
+    let len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+    if len > max_len {
+        return Err(violation(format!(
+            "{} byte message exceeds the {} byte limit",
+            len, max_len
+        )));
+    }
+    if buf.len() < FRAME_HEADER_SIZE + len {
+        buf.reserve(FRAME_HEADER_SIZE + len - buf.len());
+        return Ok(None);
+    }
+
+    buf.advance(FRAME_HEADER_SIZE);
+    let payload = buf.split_to(len);
+    let (message, used) = bincode::decode_from_slice(&payload, bincode::config::standard())
+        .map_err(|e| DbError::Serialization(e.to_string()))?;
+    if used != len {
+        return Err(violation("trailing bytes after message"));
+    }
+    Ok(Some(message))
+}
+
+/// Read the next frame, buffering partial reads in `buf`
+///
+/// Returns `None` when the peer closes the connection between frames.
+/// Cancel-safe: a partly read frame stays in `buf` for the next call.
+pub async fn read_frame<R, T>(
+    reader: &mut R,
+    buf: &mut BytesMut,
+    max_len: usize,
+) -> Result<Option<T>, DbError>
+where
+    R: AsyncRead + Unpin,
+    T: bincode::Decode<()>,
+{
+    loop {
+        if let Some(message) = decode_frame(buf, max_len)? {
+            shrink(buf);
+            return Ok(Some(message));
+        }
+        let n = reader
+            .read_buf(buf)
+            .await
+            .map_err(|e| DbError::Network(e.to_string()))?;
+        if n == 0 {
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            return Err(violation("connection closed mid-message"));
+        }
+    }
+}
+
+/// Give back the memory of an earlier large frame once it is consumed
+pub fn shrink(buf: &mut BytesMut) {
+    if buf.is_empty() && buf.capacity() > 16 * INITIAL_BUFFER_SIZE {
+        *buf = BytesMut::with_capacity(INITIAL_BUFFER_SIZE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_split_across_reads() -> Result<(), DbError> {
+        let mut wire = BytesMut::new();
+        encode_frame(
+            &Request::Query {
+                sql: "SELECT 1".to_string(),
+            },
+            &mut wire,
+        )?;
+        encode_frame(&Request::Cancel, &mut wire)?;
+
+        // Feed the bytes one at a time, as a slow connection would
+        let mut buf = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in wire.iter() {
+            buf.put_u8(*byte);
+            while let Some(request) = decode_frame::<Request>(&mut buf, MAX_BINCODE_SIZE)? {
+                decoded.push(request);
+            }
+        }
+        assert!(
+            matches!(&decoded[..], [Request::Query { sql }, Request::Cancel] if sql == "SELECT 1")
+        );
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_oversized_frame_is_rejected() {
+        let mut buf = BytesMut::new();
+        buf.put_u32(1024);
+        assert!(decode_frame::<Request>(&mut buf, 512).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_streams_row_batches() -> Result<(), DbError> {
+        let mut wire = BytesMut::new();
+        let rows: Vec<Vec<Value>> = (0..3000).map(|i| vec![Value::Integer(i)]).collect();
+        for batch in rows.chunks(ROW_BATCH_SIZE) {
+            encode_frame(
+                &Response::RowBatch {
+                    rows: batch.to_vec(),
+                },
+                &mut wire,
+            )?;
+        }
+        encode_frame(&Response::CommandComplete { rows_affected: 0 }, &mut wire)?;
+
+        let mut reader = &wire[..];
+        let mut buf = BytesMut::with_capacity(INITIAL_BUFFER_SIZE);
+        let mut received = 0;
+        while let Some(response) = read_frame(&mut reader, &mut buf, MAX_BINCODE_SIZE).await? {
+            match response {
+                Response::RowBatch { rows } => received += rows.len(),
+                Response::CommandComplete { .. } => break,
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(received, 3000);
+        assert!(
+            read_frame::<_, Response>(&mut reader, &mut buf, MAX_BINCODE_SIZE)
+                .await?
+                .is_none()
+        );
+        Ok(())
+    }
 }
@@ -1,12 +1,21 @@
-use crate::catalog::Catalog;
+use crate::catalog::{Catalog, DataType};
+use crate::database::{ConnectionGuard, Database};
 use crate::error::DbError;
-use crate::execution::Executor;
-use crate::network::protocol::{Request, Response};
-use crate::parser::SqlParser;
+use crate::execution::{Executor, QueryResult, Session};
+use crate::network::protocol::{
+    decode_frame, encode_frame, read_frame, shrink, Request, Response, INITIAL_BUFFER_SIZE,
+    MAX_SQL_LENGTH, PROTOCOL_VERSION, ROW_BATCH_SIZE,
+};
+use crate::parser::{SqlParser, SqlStatement};
+use crate::security::sql_firewall::FirewallIdentity;
+use crate::storage::TableStore;
 use crate::transaction::TransactionManager;
+use bytes::BytesMut;
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{TcpListener, TcpStream};
 
 // ============================================================================
@@ -24,26 +33,55 @@ pub const MAX_REQUEST_SIZE: usize = 1024 * 1024;
 // Database server
 pub struct Server {
     catalog: Arc<Catalog>,
-    txn_manager: Arc<TransactionManager>,
     executor: Arc<Executor>,
     parser: Arc<SqlParser>,
     /// Current number of active connections - bounded to MAX_CONCURRENT_CONNECTIONS
     active_connections: Arc<AtomicUsize>,
+    /// Database this server fronts; `run` returns once it starts shutting down
+    database: Option<Arc<Database>>,
 }
 
 impl Server {
     pub fn new() -> Self {
         let catalog = Arc::new(Catalog::new());
         let txn_manager = Arc::new(TransactionManager::new());
-        let executor = Arc::new(Executor::new(catalog.clone(), txn_manager.clone()));
+        let executor = Arc::new(Executor::new(catalog.clone(), txn_manager));
+        Self::with_executor(catalog, executor)
+    }
+
+    /// Create a server over a database's catalog and table store
+    pub fn with_storage(catalog: Arc<Catalog>, table_store: Arc<TableStore>) -> Self {
+        let txn_manager = Arc::new(TransactionManager::new());
+        let executor = Arc::new(Executor::new_with_storage(
+            catalog.clone(),
+            txn_manager,
+            table_store,
+        ));
+        Self::with_executor(catalog, executor)
+    }
+
+    /// Create a server over a shared database handle
+    ///
+    /// Connections count against the database's connection limit, and the
+    /// server stops accepting them once the database starts shutting down.
+    pub fn with_database(database: Arc<Database>) -> Self {
+        let mut server = Self::with_executor(
+            Arc::new(database.catalog().clone()),
+            Arc::new(database.executor()),
+        );
+        server.database = Some(database);
+        server
+    }
+
+    fn with_executor(catalog: Arc<Catalog>, executor: Arc<Executor>) -> Self {
         let parser = Arc::new(SqlParser::new());
 
         Self {
             catalog,
-            txn_manager,
             executor,
             parser,
             active_connections: Arc::new(AtomicUsize::new(0)),
+            database: None,
         }
     }
 
@@ -55,10 +93,15 @@ impl Server {
         tracing::info!("RustyDB server listening on {}", addr);
 
         loop {
-            let (socket, addr) = listener
-                .accept()
-                .await
-                .map_err(|e| DbError::Network(e.to_string()))?;
+            let (socket, addr) = tokio::select! {
+                accepted = listener.accept() => {
+                    accepted.map_err(|e| DbError::Network(e.to_string()))?
+                }
+                _ = shutdown_started(&self.database) => {
+                    tracing::info!("Server on {} stopped accepting connections", addr);
+                    return Ok(());
+                }
+            };
 
             // Check connection limit before accepting
             let current_conns = self.active_connections.load(Ordering::Relaxed);
@@ -73,6 +116,20 @@ impl Server {
                 continue;
             }
 
+            let guard = match &self.database {
+                Some(database) => match database.open_connection() {
+                    Some(guard) => Some(guard),
+                    None => {
+                        tracing::warn!(
+                            "Database connection limit reached, rejecting connection from {}",
+                            addr
+                        );
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
             tracing::info!("New connection from {} ({}/{} active)",
                 addr, current_conns + 1, MAX_CONCURRENT_CONNECTIONS);
 
@@ -81,9 +138,10 @@ impl Server {
 
             let handler = ConnectionHandler {
                 catalog: self.catalog.clone(),
-                txn_manager: self.txn_manager.clone(),
                 executor: self.executor.clone(),
                 parser: self.parser.clone(),
+                database: self.database.clone(),
+                _guard: guard,
             };
 
             let active_connections = self.active_connections.clone();
@@ -104,113 +162,379 @@ impl Default for Server {
     }
 }
 
+// Resolves when `database` starts shutting down; never without one
+async fn shutdown_started(database: &Option<Arc<Database>>) {
+    match database {
+        Some(database) => database.shutdown_started().await,
+        None => std::future::pending().await,
+    }
+}
+
 struct ConnectionHandler {
     #[allow(dead_code)]
     catalog: Arc<Catalog>,
-    txn_manager: Arc<TransactionManager>,
     executor: Arc<Executor>,
     parser: Arc<SqlParser>,
+    database: Option<Arc<Database>>,
+    // Keeps the connection counted until the handler is dropped
+    _guard: Option<ConnectionGuard>,
 }
 
 impl ConnectionHandler {
-    async fn handle(&self, mut socket: TcpStream) -> Result<(), DbError> {
-        // PERFORMANCE ISSUE FIXED: EA5-U2 - 1MB Buffer Per Connection
-        // Each connection allocates a 1MB buffer, leading to high memory usage
-        // With MAX_CONCURRENT_CONNECTIONS (10,000), this can use 10GB of memory
-        //
-        // TODO: PERFORMANCE - Use shared buffer pool for better memory management
-        // RECOMMENDED IMPLEMENTATION:
-        // 1. Use src/memory/buffer_pool/ for pooled buffer allocation
-        // 2. Allocate smaller buffers (e.g., 64KB) and read in chunks
-        // 3. Grow buffer dynamically only when needed
-        // 4. Return buffer to pool after request processing
-        //
-        // Example:
-        // ```
-        // let buffer_pool = state.buffer_pool.clone();
-        // let mut buffer = buffer_pool.acquire(64 * 1024).await?; // 64KB
-        // // ... use buffer ...
-        // buffer_pool.release(buffer).await;
-        // ```
-        //
-        // CURRENT: Static 1MB allocation per connection
-        // MEMORY IMPACT: 10,000 connections × 1MB = ~10GB RAM
-        let mut buffer = vec![0u8; MAX_REQUEST_SIZE];
+    async fn handle(&self, socket: TcpStream) -> Result<(), DbError> {
+        let mut conn = Connection::new(socket);
+
+        let identity = match conn.read_request().await? {
+            Some(Request::Startup {
+                protocol_version,
+                user,
+                application_name,
+            }) if protocol_version == PROTOCOL_VERSION => {
+                conn.send(&Response::Ready {
+                    protocol_version: PROTOCOL_VERSION,
+                    server_version: crate::VERSION.to_string(),
+                })
+                .await?;
+                FirewallIdentity {
+                    role: user,
+                    application: application_name.filter(|name| !name.is_empty()),
+                    session_id: None,
+                    client_addr: conn.peer_addr(),
+                }
+            }
+            Some(Request::Startup {
+                protocol_version, ..
+            }) => {
+                return conn
+                    .send(&Response::Error(format!(
+                        "Unsupported protocol version {} (server speaks {})",
+                        protocol_version, PROTOCOL_VERSION
+                    )))
+                    .await;
+            }
+            Some(_) => {
+                return conn
+                    .send(&Response::Error(
+                        "Expected a startup message".to_string(),
+                    ))
+                    .await;
+            }
+            None => return Ok(()),
+        };
+
+        // Prepared statements and open transaction of this connection; a
+        // transaction still open when it closes is rolled back
+        let mut session = Session::new();
 
         loop {
-            let n = socket
-                .read(&mut buffer)
-                .await
-                .map_err(|e| DbError::Network(e.to_string()))?;
+            // Idle connections are closed on shutdown; a request already
+            // being processed is answered first
+            let request = match conn.pending.pop_front() {
+                Some(request) => request,
+                None => tokio::select! {
+                    request = conn.read_request() => match request? {
+                        Some(request) => request,
+                        None => break,
+                    },
+                    _ = shutdown_started(&self.database) => break,
+                },
+            };
 
-            if n == 0 {
-                break;
-            }
+            let response = match request {
+                Request::Query { sql } => match self.execute_query(&sql, &identity, &mut session) {
+                    Ok(result) => {
+                        conn.stream_result(result).await?;
+                        continue;
+                    }
+                    Err(message) => Response::Error(message),
+                },
+                Request::Prepare { name, sql } => {
+                    match self.prepare(&name, &sql, &identity, &mut session) {
+                        Ok(param_types) => Response::ParameterDescription { param_types },
+                        Err(message) => Response::Error(message),
+                    }
+                }
+                Request::Bind { name, params } => {
+                    match session.execute(&self.executor, &name, params) {
+                        Ok(result) => {
+                            conn.stream_result(result).await?;
+                            continue;
+                        }
+                        Err(e) => Response::Error(e.to_string()),
+                    }
+                }
+                Request::Deallocate { name } => match session.deallocate(Some(&name)) {
+                    Ok(()) => Response::Ok,
+                    Err(e) => Response::Error(e.to_string()),
+                },
+                // A cancel that arrives after its result finished
+                Request::Cancel => continue,
+                Request::BeginTransaction => match session.begin(&self.executor, None) {
+                    Ok(txn_id) => Response::TransactionId(txn_id),
+                    Err(e) => Response::Error(e.to_string()),
+                },
+                Request::Commit => match session.commit() {
+                    Ok(()) => Response::Ok,
+                    Err(e) => Response::Error(e.to_string()),
+                },
+                Request::Rollback => match session.rollback() {
+                    Ok(()) => Response::Ok,
+                    Err(e) => Response::Error(e.to_string()),
+                },
+                Request::Ping => Response::Pong,
+                Request::Startup { .. } => Response::Error("Already started".to_string()),
+            };
+            conn.send(&response).await?;
+        }
 
-            // Validate request size against maximum
-            if n > MAX_REQUEST_SIZE {
-                return Err(DbError::Network(format!(
-                    "Request too large: {} bytes (max: {} bytes)",
-                    n, MAX_REQUEST_SIZE
-                )));
-            }
+        Ok(())
+    }
+
+    fn execute_query(
+        &self,
+        sql: &str,
+        identity: &FirewallIdentity,
+        session: &mut Session,
+    ) -> Result<QueryResult, String> {
+        let stmt = self.parse_statement(sql, identity)?;
+        session
+            .run(&self.executor, stmt)
+            .map_err(|e| e.to_string())
+    }
+
+    // Prepare `sql` as `name`, returning the types of its parameters
+    fn prepare(
+        &self,
+        name: &str,
+        sql: &str,
+        identity: &FirewallIdentity,
+        session: &mut Session,
+    ) -> Result<Vec<Option<DataType>>, String> {
+        let stmt = self.parse_statement(sql, identity)?;
+        session
+            .prepare(&self.executor, name, stmt, Vec::new())
+            .map(|prepared| prepared.param_types().to_vec())
+            .map_err(|e| e.to_string())
+    }
 
-            // SECURITY: Limit bincode deserialization size to prevent DoS
-            // See protocol.rs::MAX_BINCODE_SIZE for details
-            let request: Request =
-                bincode::decode_from_slice(&buffer[..n], bincode::config::standard())
-                    .map(|(req, _)| req)
-                    .map_err(|e| DbError::Serialization(e.to_string()))?;
+    // Parse `sql` and check it against the database's SQL firewall as the
+    // connection's user and application
+    fn parse_statement(
+        &self,
+        sql: &str,
+        identity: &FirewallIdentity,
+    ) -> Result<SqlStatement, String> {
+        // SECURITY: Validate SQL length against MAX_SQL_LENGTH
+        // Prevents memory exhaustion from unbounded SQL strings (EA5-U1)
+        if sql.len() > MAX_SQL_LENGTH {
+            return Err(format!(
+                "SQL query too large: {} bytes (max: {} bytes)",
+                sql.len(),
+                MAX_SQL_LENGTH
+            ));
+        }
 
-            let response = self.process_request(request).await;
+        let stmts = self.parser.parse(sql).map_err(|e| e.to_string())?;
+        if let Some(database) = &self.database {
+            database
+                .firewall()
+                .check(identity, sql)
+                .map_err(|e| e.to_string())?;
+        }
+        stmts
+            .into_iter()
+            .next()
+            .ok_or_else(|| "No SQL statements".to_string())
+    }
+}
 
-            let response_bytes = bincode::encode_to_vec(&response, bincode::config::standard())
-                .map_err(|e| DbError::Serialization(e.to_string()))?;
+// Framed reads and writes on one client socket
+struct Connection {
+    reader: OwnedReadHalf,
+    writer: OwnedWriteHalf,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+    // Requests that arrived while a result was streaming
+    pending: VecDeque<Request>,
+}
 
-            socket
-                .write_all(&response_bytes)
-                .await
-                .map_err(|e| DbError::Network(e.to_string()))?;
+impl Connection {
+    fn new(socket: TcpStream) -> Self {
+        let (reader, writer) = socket.into_split();
+        Self {
+            reader,
+            writer,
+            read_buf: BytesMut::with_capacity(INITIAL_BUFFER_SIZE),
+            write_buf: BytesMut::with_capacity(INITIAL_BUFFER_SIZE),
+            pending: VecDeque::new(),
         }
+    }
+
+    fn peer_addr(&self) -> Option<String> {
+        self.reader.peer_addr().ok().map(|addr| addr.ip().to_string())
+    }
 
+    async fn read_request(&mut self) -> Result<Option<Request>, DbError> {
+        read_frame(&mut self.reader, &mut self.read_buf, MAX_REQUEST_SIZE).await
+    }
+
+    async fn send(&mut self, response: &Response) -> Result<(), DbError> {
+        encode_frame(response, &mut self.write_buf)?;
+        self.flush().await
+    }
+
+    async fn flush(&mut self) -> Result<(), DbError> {
+        self.writer
+            .write_all(&self.write_buf)
+            .await
+            .map_err(|e| DbError::Network(e.to_string()))?;
+        self.write_buf.clear();
+        shrink(&mut self.write_buf);
         Ok(())
     }
 
-    async fn process_request(&self, request: Request) -> Response {
-        match request {
-            Request::Query { sql } => {
-                // SECURITY: Validate SQL length against MAX_SQL_LENGTH
-                // Prevents memory exhaustion from unbounded SQL strings (EA5-U1)
-                use crate::network::protocol::MAX_SQL_LENGTH;
-                if sql.len() > MAX_SQL_LENGTH {
-                    return Response::Error(format!(
-                        "SQL query too large: {} bytes (max: {} bytes)",
-                        sql.len(),
-                        MAX_SQL_LENGTH
-                    ));
-                }
+    // Send a result as row batches. Each batch is written only once the
+    // socket has taken the previous one, so a slow reader holds the stream
+    // back rather than the server buffering the rest of the result for it.
+    async fn stream_result(&mut self, result: QueryResult) -> Result<(), DbError> {
+        let rows_affected = result.rows_affected as u64;
+        if !result.columns.is_empty() {
+            encode_frame(
+                &Response::RowDescription {
+                    columns: result.columns,
+                    column_types: result.column_types,
+                },
+                &mut self.write_buf,
+            )?;
+        }
 
-                match self.parser.parse(&sql) {
-                    Ok(stmts) => {
-                        if stmts.is_empty() {
-                            return Response::Error("No SQL statements".to_string());
-                        }
+        let mut rows = result.rows.into_iter().peekable();
+        while rows.peek().is_some() {
+            let batch = rows.by_ref().take(ROW_BATCH_SIZE).collect();
+            encode_frame(&Response::RowBatch { rows: batch }, &mut self.write_buf)?;
+            if self.flush_watching_for_cancel().await? {
+                return self.send(&Response::Cancelled).await;
+            }
+        }
 
-                        match self.executor.execute(stmts[0].clone()) {
-                            Ok(result) => Response::QueryResult(result),
-                            Err(e) => Response::Error(e.to_string()),
+        self.send(&Response::CommandComplete { rows_affected })
+            .await
+    }
+
+    // Flush the write buffer while reading whatever the client sends
+    // meanwhile; returns whether that included a cancel
+    async fn flush_watching_for_cancel(&mut self) -> Result<bool, DbError> {
+        let Self {
+            reader,
+            writer,
+            read_buf,
+            write_buf,
+            pending,
+        } = self;
+        // Frames may already be buffered, read along with the query
+        let mut cancelled = take_requests(read_buf, pending)?;
+
+        {
+            let write = writer.write_all(&write_buf[..]);
+            tokio::pin!(write);
+            loop {
+                tokio::select! {
+                    written = &mut write => {
+                        written.map_err(|e| DbError::Network(e.to_string()))?;
+                        break;
+                    }
+                    read = reader.read_buf(&mut *read_buf) => {
+                        if read.map_err(|e| DbError::Network(e.to_string()))? == 0 {
+                            return Err(DbError::Network(
+                                "Client disconnected while a result was streaming".to_string(),
+                            ));
                         }
+                        cancelled |= take_requests(read_buf, pending)?;
                     }
-                    Err(e) => Response::Error(e.to_string()),
                 }
             }
-            Request::BeginTransaction => match self.txn_manager.begin() {
-                Ok(txn_id) => Response::TransactionId(txn_id),
-                Err(e) => Response::Error(e.to_string()),
+        }
+
+        write_buf.clear();
+        shrink(write_buf);
+        Ok(cancelled)
+    }
+}
+
+// Move the complete requests in `read_buf` to `pending`, except cancels;
+// returns whether there was one
+fn take_requests(
+    read_buf: &mut BytesMut,
+    pending: &mut VecDeque<Request>,
+) -> Result<bool, DbError> {
+    let mut cancelled = false;
+    while let Some(request) = decode_frame(read_buf, MAX_REQUEST_SIZE)? {
+        match request {
+            Request::Cancel => cancelled = true,
+            request => pending.push_back(request),
+        }
+    }
+    Ok(cancelled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Value;
+    use crate::network::protocol::MAX_BINCODE_SIZE;
+
+    fn rows(n: i64) -> QueryResult {
+        QueryResult::new(
+            vec!["n".to_string()],
+            (0..n).map(|i| vec![Value::Integer(i)]).collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_a_streaming_result() -> Result<(), DbError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            let mut conn = Connection::new(socket);
+            assert!(matches!(
+                conn.read_request().await?,
+                Some(Request::Query { .. })
+            ));
+            conn.stream_result(rows(100 * ROW_BATCH_SIZE as i64)).await?;
+            // The ping sent behind the cancel is still answered
+            assert!(matches!(conn.pending.pop_front(), Some(Request::Ping)));
+            conn.send(&Response::Pong).await
+        });
+
+        let (mut reader, mut writer) = TcpStream::connect(addr).await?.into_split();
+        let mut frames = BytesMut::new();
+        encode_frame(
+            &Request::Query {
+                sql: "SELECT n FROM t".to_string(),
             },
-            Request::Commit => Response::Ok,
-            Request::Rollback => Response::Ok,
-            Request::Ping => Response::Pong,
+            &mut frames,
+        )?;
+        encode_frame(&Request::Cancel, &mut frames)?;
+        encode_frame(&Request::Ping, &mut frames)?;
+        writer.write_all(&frames).await?;
+
+        let mut buf = BytesMut::new();
+        let mut received = 0;
+        loop {
+            match read_frame(&mut reader, &mut buf, MAX_BINCODE_SIZE).await? {
+                Some(Response::RowDescription { columns, .. }) => assert_eq!(columns, ["n"]),
+                Some(Response::RowBatch { rows }) => received += rows.len(),
+                Some(Response::Cancelled) => break,
+                other => panic!("unexpected response {:?}", other),
+            }
         }
+        assert_eq!(received, ROW_BATCH_SIZE);
+        assert!(matches!(
+            read_frame(&mut reader, &mut buf, MAX_BINCODE_SIZE).await?,
+            Some(Response::Pong)
+        ));
+
+        server.await.unwrap()
     }
 }
@@ -1,10 +1,22 @@
 use crate::catalog::{Column, DataType};
+use crate::common::{Decimal, Interval, Value};
+use crate::constraints::ReferentialAction;
 use crate::error::DbError;
-use crate::security::injection_prevention::InjectionPreventionGuard;
+use crate::index::hnsw::{HnswOptions, VectorMetric};
+use crate::procedures::{ParameterMode, ProcedureParameter};
+use crate::transaction::IsolationLevel;
+use crate::triggers::{TriggerEvent, TriggerLevel, TriggerTiming};
 use crate::Result;
-use sqlparser::ast::{Expr, SelectItem, SetExpr, Statement, TableFactor, TableWithJoins};
+use sqlparser::ast::{
+    AlterTableOperation, ColumnOption, Expr, ForeignKeyConstraint, FunctionArg, FunctionArgExpr,
+    FunctionArguments, Query, ReferentialAction as SqlReferentialAction, Set, SetExpr, Statement,
+    TableConstraint, TableFactor, TransactionIsolationLevel, TransactionMode, UnaryOperator,
+};
 use sqlparser::dialect::GenericDialect;
 use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, TokenWithSpan, Tokenizer, Whitespace};
+use std::fmt;
+use std::ops::Range;
 
 pub mod expression;
 pub mod string_functions;
@@ -12,58 +24,88 @@ pub mod string_functions;
 pub use expression::*;
 pub use string_functions::*;
 
+// Pseudo-function that carries a window frame's EXCLUDE clause, which
+// sqlparser does not parse, to the binder inside the PARTITION BY list
+pub const FRAME_EXCLUSION_MARKER: &str = "__frame_exclude";
+
+// Names of the WITH queries that carry a recursive query's SEARCH and CYCLE
+// clauses, which sqlparser does not parse either, to the binder. Each comes
+// right after the query it belongs to
+pub const CTE_SEARCH_MARKER: &str = "__cte_search";
+pub const CTE_CYCLE_MARKER: &str = "__cte_cycle";
+
+// The markers are reserved: only the parser's rewrites may write them
+const MARKERS: [&str; 1] = [FRAME_EXCLUSION_MARKER];
+
+// SQL text of a parsed statement or expression, with the clauses the parser
+// carries in markers written back in their own syntax
+pub fn sql_text(node: &impl fmt::Display) -> String {
+    let text = node.to_string();
+    if !MARKERS.iter().any(|marker| text.contains(marker)) {
+        return text;
+    }
+    let Ok(mut tokens) = Tokenizer::new(&GenericDialect {}, &text)
+        .with_unescape(false)
+        .tokenize()
+    else {
+        return text;
+    };
+    SqlParser::restore_frame_exclusions(&mut tokens);
+    tokens.iter().map(Token::to_string).collect()
+}
+
 // Parsed SQL statement
 #[derive(Debug, Clone)]
 pub enum SqlStatement {
     CreateTable {
         name: String,
         columns: Vec<Column>,
+        // Foreign keys declared by REFERENCES column options and FOREIGN KEY
+        // table constraints
+        constraints: Vec<ConstraintType>,
     },
     DropTable {
         name: String,
     },
+    // Queries keep the sqlparser AST; the binder resolves it against the catalog
     Select {
-        table: String,
-        columns: Vec<String>,
-        filter: Option<String>,
-        join: Option<JoinClause>,
-        group_by: Vec<String>,
-        having: Option<String>,
-        order_by: Vec<OrderByClause>,
-        limit: Option<usize>,
-        offset: Option<usize>,
-        distinct: bool,
+        query: Box<Query>,
     },
     SelectInto {
         target_table: String,
         source_table: String,
         columns: Vec<String>,
-        filter: Option<String>,
+        filter: Option<Expr>,
     },
+    // VALUES rows are constant expressions, which may use parameters
     Insert {
         table: String,
         columns: Vec<String>,
-        values: Vec<Vec<String>>,
+        values: Vec<Vec<Expr>>,
     },
     InsertIntoSelect {
         table: String,
         columns: Vec<String>,
-        source_query: String,
+        source: Box<Query>,
     },
     Update {
         table: String,
-        assignments: Vec<(String, String)>,
-        filter: Option<String>,
+        assignments: Vec<(String, Expr)>,
+        filter: Option<Expr>,
     },
     Delete {
         table: String,
-        filter: Option<String>,
+        filter: Option<Expr>,
     },
     CreateIndex {
         name: String,
         table: String,
         columns: Vec<String>,
         unique: bool,
+        // WHERE clause of a partial index
+        filter: Option<Expr>,
+        // Graph parameters of `USING hnsw`; `None` for a B+Tree index
+        hnsw: Option<HnswOptions>,
     },
     CreateView {
         name: String,
@@ -95,12 +137,31 @@ pub enum SqlStatement {
     },
     CreateProcedure {
         name: String,
-        parameters: Vec<(String, DataType)>,
+        parameters: Vec<ProcedureParameter>,
         body: String,
+        or_replace: bool,
+    },
+    DropProcedure {
+        name: String,
     },
+    // CALL; the arguments are literals or parameters
     ExecProcedure {
         name: String,
-        arguments: Vec<String>,
+        arguments: Vec<Expr>,
+    },
+    // The body is a PL/SQL block and the condition a PL/SQL expression
+    CreateTrigger {
+        name: String,
+        table: String,
+        timing: TriggerTiming,
+        events: Vec<TriggerEvent>,
+        level: TriggerLevel,
+        condition: Option<String>,
+        body: String,
+        or_replace: bool,
+    },
+    DropTrigger {
+        name: String,
     },
     Union {
         left: Box<SqlStatement>,
@@ -117,16 +178,77 @@ pub enum SqlStatement {
         table: String,
         user: String,
     },
+    // PREPARE name [(types)] AS statement
+    Prepare {
+        name: String,
+        param_types: Vec<DataType>,
+        statement: Box<SqlStatement>,
+    },
+    // EXECUTE name [(values)]
+    Execute {
+        name: String,
+        params: Vec<Value>,
+    },
+    // DEALLOCATE [PREPARE] name; `None` for DEALLOCATE ALL
+    Deallocate {
+        name: Option<String>,
+    },
+    // BEGIN / START TRANSACTION [ISOLATION LEVEL ...]
+    Begin {
+        isolation: Option<IsolationLevel>,
+    },
+    Commit,
+    // ROLLBACK [TO [SAVEPOINT] name]
+    Rollback {
+        savepoint: Option<String>,
+    },
+    Savepoint {
+        name: String,
+    },
+    ReleaseSavepoint {
+        name: String,
+    },
+    // SET TRANSACTION ISOLATION LEVEL ..., or with `session` SET SESSION
+    // CHARACTERISTICS AS TRANSACTION ... for every later transaction
+    SetTransaction {
+        isolation: Option<IsolationLevel>,
+        session: bool,
+    },
+    // SET name {= | TO} value, a setting of the session
+    SetParameter {
+        name: String,
+        value: String,
+    },
+    // ANALYZE [[TABLE] name]; `None` analyzes every table
+    Analyze {
+        table: Option<String>,
+    },
+    // EXPLAIN [ANALYZE] [FORMAT {TEXT | JSON}] query; with `analyze` the
+    // query is run and its plan shows what each operator did
+    Explain {
+        analyze: bool,
+        format: ExplainFormat,
+        statement: Box<SqlStatement>,
+    },
 }
 
-#[derive(Debug, Clone)]
-pub struct JoinClause {
-    pub join_type: JoinType,
-    pub table: String,
-    pub condition: String,
+impl SqlStatement {
+    // BEGIN, COMMIT, ROLLBACK, savepoints and SET TRANSACTION, which a
+    // session runs itself
+    pub fn is_transaction_control(&self) -> bool {
+        matches!(
+            self,
+            SqlStatement::Begin { .. }
+                | SqlStatement::Commit
+                | SqlStatement::Rollback { .. }
+                | SqlStatement::Savepoint { .. }
+                | SqlStatement::ReleaseSavepoint { .. }
+                | SqlStatement::SetTransaction { .. }
+        )
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum JoinType {
     Inner,
     Left,
@@ -135,6 +257,15 @@ pub enum JoinType {
     Cross,
 }
 
+// How EXPLAIN prints a plan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplainFormat {
+    // One line per plan node
+    Text,
+    // A single JSON document
+    Json,
+}
+
 #[derive(Debug, Clone)]
 pub struct OrderByClause {
     pub column: String,
@@ -163,9 +294,14 @@ pub enum AlterAction {
 pub enum ConstraintType {
     PrimaryKey(Vec<String>),
     ForeignKey {
+        // Named after the table and columns when `None`
+        name: Option<String>,
         columns: Vec<String>,
         ref_table: String,
+        // The referenced table's primary key when empty
         ref_columns: Vec<String>,
+        on_delete: ReferentialAction,
+        on_update: ReferentialAction,
     },
     Unique(Vec<String>),
     Check(String),
@@ -175,33 +311,70 @@ pub enum ConstraintType {
     },
 }
 
-// SQL parser wrapper with integrated injection prevention
+// SQL parser wrapper. It accepts any valid SQL, comments and multi-statement
+// scripts included; which statements a session may run is decided by the
+// SQL firewall (`security::sql_firewall`) once they have parsed.
 pub struct SqlParser {
     dialect: GenericDialect,
-    injection_guard: InjectionPreventionGuard,
 }
 
 impl SqlParser {
     pub fn new() -> Self {
         Self {
             dialect: GenericDialect {},
-            injection_guard: InjectionPreventionGuard::new(),
         }
     }
 
     pub fn parse(&self, sql: &str) -> Result<Vec<SqlStatement>> {
-        // LAYER 1-6: Multi-layer injection prevention
-        // This validates and sanitizes the input through:
-        // - Input sanitization (Unicode normalization, homograph detection)
-        // - Dangerous pattern detection (SQL keywords, comments, tautologies)
-        // - Syntax validation (quotes, parentheses, identifiers)
-        // - Escape validation
-        // - Whitelist validation
-        let safe_sql = self.injection_guard.validate_and_sanitize(sql)?;
-
-        // Parse the now-safe SQL
-        let ast = Parser::parse_sql(&self.dialect, &safe_sql)
-            .map_err(|e| DbError::SqlParse(e.to_string()))?;
+        // The statement a PREPARE names is parsed on its own below
+        if Self::is_prepared_statement_command(sql) {
+            return self.parse_prepared_statement_command(sql);
+        }
+        if Self::is_transaction_command(sql) {
+            return self.parse_transaction_command(sql);
+        }
+        if Self::is_set_parameter(sql) {
+            return Self::parse_set_parameter(sql);
+        }
+        if Self::is_analyze(sql) {
+            return Self::parse_analyze(sql);
+        }
+        // The query EXPLAIN names is parsed on its own
+        if let Some(rest) = Self::strip_keyword(sql.trim_start(), "EXPLAIN") {
+            return self.parse_explain(sql, rest);
+        }
+        // Procedure and trigger bodies are PL/SQL, compiled when the
+        // procedure or trigger is created; the SQL in them is parsed as it
+        // runs
+        if let Some(rest) = Self::strip_keyword(sql.trim_start(), "CREATE") {
+            let (or_replace, rest) = match Self::strip_keyword(rest, "OR")
+                .and_then(|rest| Self::strip_keyword(rest, "REPLACE"))
+            {
+                Some(rest) => (true, rest),
+                None => (false, rest),
+            };
+            if let Some(rest) = Self::strip_keyword(rest, "PROCEDURE") {
+                return self.parse_create_procedure(sql, rest, or_replace);
+            }
+            if let Some(rest) = Self::strip_keyword(rest, "TRIGGER") {
+                return Self::parse_create_trigger(sql, rest, or_replace);
+            }
+        }
+        if let Some(rest) = Self::strip_keyword(sql.trim_start(), "DROP") {
+            if let Some(rest) = Self::strip_keyword(rest, "PROCEDURE") {
+                let name = Self::parse_dropped_name(sql, rest, "PROCEDURE")?;
+                return Ok(vec![SqlStatement::DropProcedure { name }]);
+            }
+            if let Some(rest) = Self::strip_keyword(rest, "TRIGGER") {
+                let name = Self::parse_dropped_name(sql, rest, "TRIGGER")?;
+                return Ok(vec![SqlStatement::DropTrigger { name }]);
+            }
+        }
+        if Self::strip_keyword(sql.trim_start(), "CALL").is_some() {
+            return self.parse_call(sql);
+        }
+
+        let ast = self.parse_sql(sql)?;
 
         let mut statements = Vec::new();
 
@@ -212,54 +385,971 @@ impl SqlParser {
         Ok(statements)
     }
 
+    // Parse into the sqlparser AST. `?` parameters are numbered `$1`, `$2`,
+    // ... in the order they appear, so later stages see a single style.
+    fn parse_sql(&self, sql: &str) -> Result<Vec<Statement>> {
+        let mut tokens = Tokenizer::new(&self.dialect, sql)
+            .tokenize_with_location()
+            .map_err(|e| DbError::SqlParse(e.to_string()))?;
+
+        let (mut positional, mut numbered) = (0, false);
+        for token in &mut tokens {
+            if let Token::Placeholder(placeholder) = &mut token.token {
+                match placeholder.strip_prefix('?') {
+                    Some("") => {
+                        positional += 1;
+                        *placeholder = format!("${}", positional);
+                    }
+                    Some(n) => {
+                        numbered = true;
+                        *placeholder = format!("${}", n);
+                    }
+                    None => numbered = true,
+                }
+            }
+        }
+        if positional > 0 && numbered {
+            return Err(DbError::SqlParse(
+                "Cannot mix ? and numbered parameters in one statement".to_string(),
+            ));
+        }
+        // Marker names may only come from the rewrites below
+        let reserved = tokens.iter().find_map(|token| match &token.token {
+            Token::Word(w) if MARKERS.iter().any(|m| w.value.eq_ignore_ascii_case(m)) => {
+                Some(&w.value)
+            }
+            _ => None,
+        });
+        if let Some(name) = reserved {
+            return Err(DbError::SqlParse(format!(
+                "{} is reserved for internal use",
+                name
+            )));
+        }
+        Self::move_frame_exclusions(&mut tokens);
+        Self::move_search_cycle_clauses(&mut tokens);
+
+        Parser::new(&self.dialect)
+            .with_tokens_with_locations(tokens)
+            .parse_statements()
+            .map_err(|e| DbError::SqlParse(e.to_string()))
+    }
+
+    // `EXCLUDE CURRENT ROW | GROUP | TIES` at the end of a window frame
+    // becomes `__frame_exclude('<mode>')` in that window's PARTITION BY
+    // list; `EXCLUDE NO OTHERS` is the default and is dropped
+    fn move_frame_exclusions(tokens: &mut Vec<TokenWithSpan>) {
+        let word = Self::is_word;
+        let visible = Self::visible(tokens.iter().map(|token| &token.token));
+        let token = |v: usize| visible.get(v).map(|&i| &tokens[i].token);
+
+        // For each open parenthesis, whether it has a PARTITION BY list and
+        // where that list ends: at ORDER BY or the frame
+        let mut open: Vec<(bool, Option<usize>)> = Vec::new();
+        let mut edits = Vec::new();
+        let mut v = 0;
+        while let Some(t) = token(v) {
+            let i = visible[v];
+            v += 1;
+            match t {
+                Token::LParen => open.push((false, None)),
+                Token::RParen => {
+                    open.pop();
+                }
+                _ => {}
+            }
+            let Some((partitioned, at)) = open.last_mut() else {
+                continue;
+            };
+            match t {
+                t if word(t, "PARTITION") => *partitioned = true,
+                t if at.is_none()
+                    && ["ORDER", "ROWS", "RANGE", "GROUPS"]
+                        .iter()
+                        .any(|k| word(t, k)) =>
+                {
+                    *at = Some(i)
+                }
+                t if word(t, "EXCLUDE") => {
+                    let (mode, len) = match (token(v), token(v + 1)) {
+                        (Some(a), Some(b)) if word(a, "CURRENT") && word(b, "ROW") => {
+                            (Some("CURRENT ROW"), 2)
+                        }
+                        (Some(a), Some(b)) if word(a, "NO") && word(b, "OTHERS") => (None, 2),
+                        (Some(a), _) if word(a, "GROUP") => (Some("GROUP"), 1),
+                        (Some(a), _) if word(a, "TIES") => (Some("TIES"), 1),
+                        _ => continue,
+                    };
+                    // The clause must close the window specification
+                    if !matches!(token(v + len), Some(Token::RParen)) {
+                        continue;
+                    }
+                    edits.push((i..visible[v + len], Vec::new()));
+                    v += len;
+                    let Some(mode) = mode else {
+                        continue;
+                    };
+                    let mut marker = match partitioned {
+                        true => vec![Token::Comma],
+                        false => vec![Token::make_keyword("PARTITION"), Token::make_keyword("BY")],
+                    };
+                    marker.extend([
+                        Token::make_word(FRAME_EXCLUSION_MARKER, None),
+                        Token::LParen,
+                        Token::SingleQuotedString(mode.to_string()),
+                        Token::RParen,
+                    ]);
+                    let at = at.unwrap_or(i);
+                    edits.push((at..at, marker));
+                }
+                _ => {}
+            }
+        }
+        let edits = edits
+            .into_iter()
+            .map(|(range, new)| (range, new.into_iter().map(TokenWithSpan::wrap).collect()))
+            .collect();
+        Self::replace_ranges(tokens, edits);
+    }
+
+    // The reverse of `move_frame_exclusions`, for SQL text rendered from
+    // the AST
+    fn restore_frame_exclusions(tokens: &mut Vec<Token>) {
+        let word = Self::is_word;
+        let visible = Self::visible(tokens.iter());
+        let token = |v: usize| visible.get(v).map(|&i| &tokens[i]);
+
+        // The exclusion found in each open parenthesis, written out when it
+        // closes
+        let mut open: Vec<Option<&str>> = Vec::new();
+        let mut edits = Vec::new();
+        let mut v = 0;
+        while let Some(t) = token(v) {
+            let i = visible[v];
+            v += 1;
+            match t {
+                Token::LParen => open.push(None),
+                Token::RParen => {
+                    if let Some(Some(mode)) = open.pop() {
+                        let mut clause = vec![Token::make_keyword("EXCLUDE")];
+                        clause.extend(mode.split(' ').map(Token::make_keyword));
+                        edits.push((i..i, Self::spaced(clause)));
+                    }
+                }
+                t if word(t, FRAME_EXCLUSION_MARKER) => {
+                    let (
+                        Some(Token::LParen),
+                        Some(Token::SingleQuotedString(mode)),
+                        Some(Token::RParen),
+                    ) = (token(v), token(v + 1), token(v + 2))
+                    else {
+                        continue;
+                    };
+                    let before = |k: usize| (v - 1).checked_sub(k).and_then(token);
+                    // The marker ends the PARTITION BY list or is all of it
+                    let range = match (before(2), before(1)) {
+                        (_, Some(Token::Comma)) => visible[v - 2]..visible[v + 2] + 1,
+                        (Some(p), Some(b)) if word(p, "PARTITION") && word(b, "BY") => {
+                            visible[v - 3]..visible.get(v + 3).map_or(tokens.len(), |&j| j)
+                        }
+                        _ => continue,
+                    };
+                    edits.push((range, Vec::new()));
+                    if let Some(exclude) = open.last_mut() {
+                        *exclude = Some(mode.as_str());
+                    }
+                    v += 3;
+                }
+                _ => {}
+            }
+        }
+        Self::replace_ranges(tokens, edits);
+    }
+
+    // Positions of the tokens other than whitespace
+    fn visible<'t>(tokens: impl Iterator<Item = &'t Token>) -> Vec<usize> {
+        tokens
+            .enumerate()
+            .filter(|(_, token)| !matches!(token, Token::Whitespace(_)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    // Replace ranges of `tokens`, which must not overlap, in one pass; an
+    // empty range inserts before the token it starts at
+    fn replace_ranges<T>(tokens: &mut Vec<T>, mut edits: Vec<(Range<usize>, Vec<T>)>) {
+        if edits.is_empty() {
+            return;
+        }
+        edits.sort_by_key(|(range, _)| (range.start, range.end));
+        let mut edits = edits.into_iter().peekable();
+        let mut skip = 0;
+        for (i, token) in std::mem::take(tokens).into_iter().enumerate() {
+            while let Some((range, new)) = edits.next_if(|(range, _)| range.start == i) {
+                tokens.extend(new);
+                skip = skip.max(range.end);
+            }
+            if i >= skip {
+                tokens.push(token);
+            }
+        }
+        tokens.extend(edits.flat_map(|(_, new)| new));
+    }
+
+    // Tokens to write into SQL text, each after a space except commas
+    fn spaced(tokens: Vec<Token>) -> Vec<Token> {
+        tokens
+            .into_iter()
+            .flat_map(|token| match token {
+                Token::Comma => vec![token],
+                token => vec![Token::Whitespace(Whitespace::Space), token],
+            })
+            .collect()
+    }
+    // `SEARCH {DEPTH | BREADTH} FIRST BY c, ... SET s` after a WITH query
+    // becomes the WITH query `__cte_search(s) AS (SELECT 'DEPTH', c, ...)`
+    // right after it, and `CYCLE c, ... SET m [TO v DEFAULT d] USING p`
+    // becomes `__cte_cycle(m, p) AS (SELECT v, d, c, ...)`
+    fn move_search_cycle_clauses(tokens: &mut Vec<TokenWithSpan>) {
+        let word = Self::is_word;
+        let name = |token: &Token| matches!(token, Token::Word(_));
+        loop {
+            let visible: Vec<usize> = (0..tokens.len())
+                .filter(|&i| !matches!(tokens[i].token, Token::Whitespace(_)))
+                .collect();
+            let token = |v: usize| visible.get(v).map(|&i| &tokens[i].token);
+            // A list of names from `v`, and the position after it
+            let names = |mut v: usize| {
+                let mut names = Vec::new();
+                while let Some(found) = token(v).filter(|t| name(*t)) {
+                    names.extend([Token::Comma, found.clone()]);
+                    if !matches!(token(v + 1), Some(Token::Comma)) {
+                        return (names, v + 1);
+                    }
+                    v += 2;
+                }
+                (Vec::new(), v)
+            };
+            // The clause must follow the query's closing parenthesis
+            let found = (1..visible.len()).find_map(|v| {
+                if !matches!(token(v - 1), Some(Token::RParen)) {
+                    return None;
+                }
+                let first = token(v)?;
+                if word(first, "SEARCH") {
+                    let next = token(v + 1)?;
+                    let mode = ["DEPTH", "BREADTH"]
+                        .into_iter()
+                        .find(|mode| word(next, mode))?;
+                    if !word(token(v + 2)?, "FIRST") || !word(token(v + 3)?, "BY") {
+                        return None;
+                    }
+                    let (columns, at) = names(v + 4);
+                    if columns.is_empty() || !word(token(at)?, "SET") {
+                        return None;
+                    }
+                    let set = token(at + 1).filter(|t| name(*t))?.clone();
+                    let mut select = vec![Token::SingleQuotedString(mode.to_string())];
+                    select.extend(columns);
+                    Some((v, at + 1, CTE_SEARCH_MARKER, vec![set], select))
+                } else if word(first, "CYCLE") {
+                    let (columns, at) = names(v + 1);
+                    if columns.is_empty() || !word(token(at)?, "SET") {
+                        return None;
+                    }
+                    let mark = token(at + 1).filter(|t| name(*t))?.clone();
+                    // TO and DEFAULT take one constant each
+                    let (values, at) = match token(at + 2)? {
+                        to if word(to, "TO") && word(token(at + 4)?, "DEFAULT") => {
+                            ([token(at + 3)?.clone(), token(at + 5)?.clone()], at + 6)
+                        }
+                        _ => (
+                            [Token::make_keyword("TRUE"), Token::make_keyword("FALSE")],
+                            at + 2,
+                        ),
+                    };
+                    if !word(token(at)?, "USING") {
+                        return None;
+                    }
+                    let path = token(at + 1).filter(|t| name(*t))?.clone();
+                    let [to, default] = values;
+                    let mut select = vec![to, Token::Comma, default];
+                    select.extend(columns);
+                    Some((
+                        v,
+                        at + 1,
+                        CTE_CYCLE_MARKER,
+                        vec![mark, Token::Comma, path],
+                        select,
+                    ))
+                } else {
+                    None
+                }
+            });
+            let Some((first, last, marker, columns, select)) = found else {
+                return;
+            };
+            let mut query = vec![Token::Comma, Token::make_word(marker, None), Token::LParen];
+            query.extend(columns);
+            query.extend([
+                Token::RParen,
+                Token::make_keyword("AS"),
+                Token::LParen,
+                Token::make_keyword("SELECT"),
+            ]);
+            query.extend(select);
+            query.push(Token::RParen);
+            tokens.splice(
+                visible[first]..=visible[last],
+                query.into_iter().map(TokenWithSpan::wrap),
+            );
+        }
+    }
+
+    // An unquoted word, in any case
+    fn is_word(token: &Token, name: &str) -> bool {
+        match token {
+            Token::Word(w) => w.quote_style.is_none() && w.value.eq_ignore_ascii_case(name),
+            _ => false,
+        }
+    }
+
+    fn is_prepared_statement_command(sql: &str) -> bool {
+        let keyword = sql
+            .trim_start()
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default();
+        ["PREPARE", "EXECUTE", "DEALLOCATE"]
+            .iter()
+            .any(|command| keyword.eq_ignore_ascii_case(command))
+    }
+
+    fn parse_prepared_statement_command(&self, sql: &str) -> Result<Vec<SqlStatement>> {
+        let mut statements = Vec::new();
+        for stmt in self.parse_sql(sql)? {
+            statements.push(match stmt {
+                Statement::Prepare {
+                    name,
+                    data_types,
+                    statement,
+                } => {
+                    let mut inner = self.parse(&sql_text(&statement))?;
+                    let statement = match inner.pop() {
+                        Some(
+                            SqlStatement::Prepare { .. }
+                            | SqlStatement::Execute { .. }
+                            | SqlStatement::Deallocate { .. },
+                        ) => {
+                            return Err(DbError::SqlParse(
+                                "PREPARE, EXECUTE and DEALLOCATE cannot be prepared".to_string(),
+                            ))
+                        }
+                        Some(statement) if statement.is_transaction_control() => {
+                            return Err(DbError::SqlParse(
+                                "Transaction control statements cannot be prepared".to_string(),
+                            ))
+                        }
+                        Some(statement) if inner.is_empty() => statement,
+                        _ => {
+                            return Err(DbError::SqlParse(
+                                "PREPARE takes a single statement".to_string(),
+                            ))
+                        }
+                    };
+                    SqlStatement::Prepare {
+                        name: name.value,
+                        param_types: data_types
+                            .iter()
+                            .map(DataType::from_sql)
+                            .collect::<Result<_>>()?,
+                        statement: Box::new(statement),
+                    }
+                }
+                Statement::Execute {
+                    name: Some(name),
+                    parameters,
+                    using,
+                    ..
+                } if using.is_empty() => SqlStatement::Execute {
+                    name: name.to_string(),
+                    params: parameters
+                        .iter()
+                        .map(Self::literal_value)
+                        .collect::<Result<_>>()?,
+                },
+                Statement::Deallocate { name, .. } => SqlStatement::Deallocate {
+                    name: (name.quote_style.is_some() || !name.value.eq_ignore_ascii_case("ALL"))
+                        .then_some(name.value),
+                },
+                _ => {
+                    return Err(DbError::SqlParse(
+                        "PREPARE, EXECUTE and DEALLOCATE cannot be combined with other statements"
+                            .to_string(),
+                    ))
+                }
+            });
+        }
+        Ok(statements)
+    }
+
+    fn is_transaction_command(sql: &str) -> bool {
+        let mut words = sql
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .filter(|word| !word.is_empty());
+        let keyword = words.next().unwrap_or_default().to_ascii_uppercase();
+        match keyword.as_str() {
+            "BEGIN" | "START" | "COMMIT" | "END" | "ROLLBACK" | "SAVEPOINT" | "RELEASE" => true,
+            "SET" => words.next().is_some_and(|word| {
+                word.eq_ignore_ascii_case("TRANSACTION") || word.eq_ignore_ascii_case("SESSION")
+            }),
+            _ => false,
+        }
+    }
+
+    // SET other than SET TRANSACTION / SET SESSION
+    fn is_set_parameter(sql: &str) -> bool {
+        sql.trim_start()
+            .get(..4)
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case("SET "))
+            && !Self::is_transaction_command(sql)
+    }
+
+    // Settings are a dotted name and a plain or single-quoted word, so the
+    // statement is read here rather than by sqlparser
+    fn parse_set_parameter(sql: &str) -> Result<Vec<SqlStatement>> {
+        let invalid = || DbError::SqlParse(format!("Invalid SET statement: {}", sql.trim()));
+        let rest = sql.trim().trim_end_matches(';').trim_end()[4..].trim_start();
+        let name_end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        let (name, rest) = rest.split_at(name_end);
+        let rest = rest.trim_start();
+        let to = rest.get(..3).filter(|to| to.eq_ignore_ascii_case("TO "));
+        let value = match (rest.strip_prefix('='), to) {
+            (Some(value), _) => value,
+            (None, Some(_)) => &rest[3..],
+            (None, None) => return Err(invalid()),
+        }
+        .trim();
+        let value = match value.strip_prefix('\'') {
+            Some(quoted) => quoted.strip_suffix('\'').ok_or_else(invalid)?,
+            None => value,
+        };
+        let word = |text: &str| {
+            !text.is_empty()
+                && text
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        };
+        if !word(name) || !word(value) {
+            return Err(invalid());
+        }
+        Ok(vec![SqlStatement::SetParameter {
+            name: name.to_ascii_lowercase(),
+            value: value.to_string(),
+        }])
+    }
+
+    fn is_analyze(sql: &str) -> bool {
+        sql.trim_start()
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case("ANALYZE"))
+    }
+
+    // ANALYZE names at most one table, so the statement is read here
+    fn parse_analyze(sql: &str) -> Result<Vec<SqlStatement>> {
+        let invalid = || DbError::SqlParse(format!("Invalid ANALYZE statement: {}", sql.trim()));
+        let mut words = sql.trim().trim_end_matches(';').split_whitespace().skip(1);
+        let mut table = words.next();
+        if table.is_some_and(|word| word.eq_ignore_ascii_case("TABLE")) {
+            table = Some(words.next().ok_or_else(invalid)?);
+        }
+        let identifier = |name: &str| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if words.next().is_some() || table.is_some_and(|name| !identifier(name)) {
+            return Err(invalid());
+        }
+        Ok(vec![SqlStatement::Analyze {
+            table: table.map(str::to_string),
+        }])
+    }
+
+    // EXPLAIN options are read here and the query by `parse`
+    fn parse_explain(&self, sql: &str, rest: &str) -> Result<Vec<SqlStatement>> {
+        let invalid = || DbError::SqlParse(format!("Invalid EXPLAIN statement: {}", sql.trim()));
+        let (analyze, rest) = match Self::strip_keyword(rest, "ANALYZE") {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let (format, rest) = match Self::strip_keyword(rest, "FORMAT") {
+            Some(rest) => {
+                if let Some(rest) = Self::strip_keyword(rest, "TEXT") {
+                    (ExplainFormat::Text, rest)
+                } else if let Some(rest) = Self::strip_keyword(rest, "JSON") {
+                    (ExplainFormat::Json, rest)
+                } else {
+                    return Err(invalid());
+                }
+            }
+            None => (ExplainFormat::Text, rest),
+        };
+
+        let mut inner = self.parse(rest)?;
+        match inner.pop() {
+            Some(statement @ SqlStatement::Select { .. }) if inner.is_empty() => {
+                Ok(vec![SqlStatement::Explain {
+                    analyze,
+                    format,
+                    statement: Box::new(statement),
+                }])
+            }
+            _ => Err(DbError::SqlParse(
+                "EXPLAIN takes a single SELECT query".to_string(),
+            )),
+        }
+    }
+
+    // CREATE [OR REPLACE] PROCEDURE name [(param [IN | OUT | IN OUT] type,
+    // ...)] IS | AS body, where the body may be dollar-quoted
+    fn parse_create_procedure(
+        &self,
+        sql: &str,
+        rest: &str,
+        or_replace: bool,
+    ) -> Result<Vec<SqlStatement>> {
+        let invalid = || {
+            DbError::SqlParse(format!(
+                "Invalid CREATE PROCEDURE statement: {}",
+                sql.trim()
+            ))
+        };
+        let name_end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let (name, mut rest) = rest.split_at(name_end);
+        if name.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut parameters = Vec::new();
+        rest = rest.trim_start();
+        if let Some(list) = rest.strip_prefix('(') {
+            // Types such as NUMERIC(10, 2) have commas of their own
+            let (mut depth, mut start) = (0, 0);
+            let mut end = None;
+            for (i, c) in list.char_indices() {
+                match c {
+                    '(' => depth += 1,
+                    ')' if depth == 0 => {
+                        end = Some(i);
+                        break;
+                    }
+                    ')' => depth -= 1,
+                    ',' if depth == 0 => {
+                        parameters.push(self.parse_procedure_parameter(&list[start..i])?);
+                        start = i + 1;
+                    }
+                    _ => {}
+                }
+            }
+            let end = end.ok_or_else(invalid)?;
+            if !list[start..end].trim().is_empty() || !parameters.is_empty() {
+                parameters.push(self.parse_procedure_parameter(&list[start..end])?);
+            }
+            rest = list[end + 1..].trim_start();
+        }
+
+        let body = Self::strip_keyword(rest, "IS")
+            .or_else(|| Self::strip_keyword(rest, "AS"))
+            .ok_or_else(invalid)?;
+        let body = Self::unquote_body(body).ok_or_else(invalid)?;
+        Ok(vec![SqlStatement::CreateProcedure {
+            name: name.to_string(),
+            parameters,
+            body: body.to_string(),
+            or_replace,
+        }])
+    }
+
+    // `name [IN | OUT | IN OUT | INOUT] type`
+    fn parse_procedure_parameter(&self, text: &str) -> Result<ProcedureParameter> {
+        let invalid = || DbError::SqlParse(format!("Invalid procedure parameter: {}", text.trim()));
+        let text = text.trim();
+        let (name, rest) = text.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let rest = rest.trim_start();
+        let (mode, type_text) = if let Some(rest) = Self::strip_keyword(rest, "INOUT") {
+            (ParameterMode::InOut, rest)
+        } else if let Some(rest) = Self::strip_keyword(rest, "OUT") {
+            (ParameterMode::Out, rest)
+        } else if let Some(rest) = Self::strip_keyword(rest, "IN") {
+            match Self::strip_keyword(rest, "OUT") {
+                Some(rest) => (ParameterMode::InOut, rest),
+                None => (ParameterMode::In, rest),
+            }
+        } else {
+            (ParameterMode::In, rest)
+        };
+
+        Ok(ProcedureParameter {
+            name: name.to_string(),
+            data_type: type_text.parse()?,
+            mode,
+        })
+    }
+
+    // A procedure or trigger body as written, or the text between the tags
+    // of a dollar-quoted one
+    fn unquote_body(body: &str) -> Option<&str> {
+        let body = body.trim();
+        match body.strip_prefix('$').and_then(|text| text.find('$')) {
+            Some(end) => {
+                let tag = &body[..end + 2];
+                body.trim_end_matches(';')
+                    .trim_end()
+                    .strip_prefix(tag)
+                    .and_then(|body| body.strip_suffix(tag))
+                    .map(str::trim)
+            }
+            None => Some(body),
+        }
+    }
+
+    // CREATE [OR REPLACE] TRIGGER name BEFORE | AFTER | INSTEAD OF event
+    // [OR event ...] ON table [FOR EACH ROW | STATEMENT] [WHEN (condition)]
+    // body, where an event is INSERT, UPDATE or DELETE and the body may be
+    // dollar-quoted. Triggers fire for each statement unless FOR EACH ROW is
+    // given, except INSTEAD OF triggers, which always fire for each row
+    fn parse_create_trigger(sql: &str, rest: &str, or_replace: bool) -> Result<Vec<SqlStatement>> {
+        let invalid =
+            || DbError::SqlParse(format!("Invalid CREATE TRIGGER statement: {}", sql.trim()));
+        let (name, rest) = Self::leading_name(rest).ok_or_else(invalid)?;
+
+        let (timing, mut rest) = if let Some(rest) = Self::strip_keyword(rest, "BEFORE") {
+            (TriggerTiming::Before, rest)
+        } else if let Some(rest) = Self::strip_keyword(rest, "AFTER") {
+            (TriggerTiming::After, rest)
+        } else if let Some(rest) =
+            Self::strip_keyword(rest, "INSTEAD").and_then(|rest| Self::strip_keyword(rest, "OF"))
+        {
+            (TriggerTiming::InsteadOf, rest)
+        } else {
+            return Err(invalid());
+        };
+
+        let mut events = Vec::new();
+        loop {
+            let (event, after) = if let Some(after) = Self::strip_keyword(rest, "INSERT") {
+                (TriggerEvent::Insert, after)
+            } else if let Some(after) = Self::strip_keyword(rest, "UPDATE") {
+                (TriggerEvent::Update, after)
+            } else if let Some(after) = Self::strip_keyword(rest, "DELETE") {
+                (TriggerEvent::Delete, after)
+            } else {
+                return Err(invalid());
+            };
+            if !events.contains(&event) {
+                events.push(event);
+            }
+            match Self::strip_keyword(after, "OR") {
+                Some(after) => rest = after,
+                None => {
+                    rest = after;
+                    break;
+                }
+            }
+        }
+
+        let (table, mut rest) = Self::strip_keyword(rest, "ON")
+            .and_then(Self::leading_name)
+            .ok_or_else(invalid)?;
+        let mut level = match timing {
+            TriggerTiming::InsteadOf => TriggerLevel::Row,
+            _ => TriggerLevel::Statement,
+        };
+        if let Some(after) = Self::strip_keyword(rest, "FOR") {
+            let after = Self::strip_keyword(after, "EACH").unwrap_or(after);
+            if let Some(after) = Self::strip_keyword(after, "ROW") {
+                level = TriggerLevel::Row;
+                rest = after;
+            } else if let Some(after) = Self::strip_keyword(after, "STATEMENT") {
+                level = TriggerLevel::Statement;
+                rest = after;
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        let mut condition = None;
+        if let Some(after) = Self::strip_keyword(rest, "WHEN") {
+            let after = after.trim_start().strip_prefix('(').ok_or_else(invalid)?;
+            let mut depth = 0;
+            let end = after
+                .char_indices()
+                .find(|&(_, c)| match c {
+                    '(' => {
+                        depth += 1;
+                        false
+                    }
+                    ')' if depth == 0 => true,
+                    ')' => {
+                        depth -= 1;
+                        false
+                    }
+                    _ => false,
+                })
+                .map(|(i, _)| i)
+                .ok_or_else(invalid)?;
+            condition = Some(after[..end].trim().to_string());
+            rest = &after[end + 1..];
+        }
+
+        let body = Self::unquote_body(rest).ok_or_else(invalid)?;
+        if body.is_empty() {
+            return Err(invalid());
+        }
+        Ok(vec![SqlStatement::CreateTrigger {
+            name,
+            table,
+            timing,
+            events,
+            level,
+            condition,
+            body: body.to_string(),
+            or_replace,
+        }])
+    }
+
+    // The name `text` starts with and the text after it
+    fn leading_name(text: &str) -> Option<(String, &str)> {
+        let end = text
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(text.len());
+        (end > 0).then(|| (text[..end].to_string(), text[end..].trim_start()))
+    }
+
+    // The name DROP PROCEDURE or DROP TRIGGER names
+    fn parse_dropped_name(sql: &str, rest: &str, kind: &str) -> Result<String> {
+        let name = rest.trim().trim_end_matches(';').trim_end();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(DbError::SqlParse(format!(
+                "Invalid DROP {} statement: {}",
+                kind,
+                sql.trim()
+            )));
+        }
+        Ok(name.to_string())
+    }
+
+    fn parse_call(&self, sql: &str) -> Result<Vec<SqlStatement>> {
+        let mut statements = Vec::new();
+        for stmt in self.parse_sql(sql)? {
+            let Statement::Call(function) = stmt else {
+                return Err(DbError::SqlParse(
+                    "CALL cannot be combined with other statements".to_string(),
+                ));
+            };
+            let arguments = match function.args {
+                FunctionArguments::None => Vec::new(),
+                FunctionArguments::List(list) => list
+                    .args
+                    .into_iter()
+                    .map(|arg| match arg {
+                        FunctionArg::Unnamed(FunctionArgExpr::Expr(expr))
+                            if Self::is_call_argument(&expr) =>
+                        {
+                            Ok(expr)
+                        }
+                        other => Err(DbError::SqlParse(format!(
+                            "CALL arguments must be literals or parameters: {}",
+                            other
+                        ))),
+                    })
+                    .collect::<Result<_>>()?,
+                FunctionArguments::Subquery(_) => {
+                    return Err(DbError::SqlParse(
+                        "CALL arguments must be literals or parameters".to_string(),
+                    ))
+                }
+            };
+            statements.push(SqlStatement::ExecProcedure {
+                name: function.name.to_string(),
+                arguments,
+            });
+        }
+        Ok(statements)
+    }
+
+    fn is_call_argument(expr: &Expr) -> bool {
+        match expr {
+            Expr::Value(_) => true,
+            Expr::UnaryOp {
+                op: UnaryOperator::Minus,
+                expr,
+            } => matches!(**expr, Expr::Value(_)),
+            _ => false,
+        }
+    }
+
+    // The text after a leading keyword, if `text` starts with it
+    fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+        let word = text.get(..keyword.len())?;
+        let rest = &text[keyword.len()..];
+        (word.eq_ignore_ascii_case(keyword)
+            && (rest.is_empty() || rest.starts_with(char::is_whitespace)))
+        .then_some(rest.trim_start())
+    }
+
+    fn parse_transaction_command(&self, sql: &str) -> Result<Vec<SqlStatement>> {
+        let mut statements = Vec::new();
+        for stmt in self.parse_sql(sql)? {
+            statements.push(match stmt {
+                Statement::StartTransaction { modes, .. } => SqlStatement::Begin {
+                    isolation: Self::isolation_level(&modes),
+                },
+                Statement::Commit { .. } => SqlStatement::Commit,
+                Statement::Rollback { savepoint, .. } => SqlStatement::Rollback {
+                    savepoint: savepoint.map(|name| name.value),
+                },
+                Statement::Savepoint { name } => SqlStatement::Savepoint { name: name.value },
+                Statement::ReleaseSavepoint { name } => {
+                    SqlStatement::ReleaseSavepoint { name: name.value }
+                }
+                Statement::Set(Set::SetTransaction {
+                    modes,
+                    snapshot: None,
+                    session,
+                }) => SqlStatement::SetTransaction {
+                    isolation: Self::isolation_level(&modes),
+                    session,
+                },
+                _ => {
+                    return Err(DbError::SqlParse(
+                        "Transaction control statements cannot be combined with other statements"
+                            .to_string(),
+                    ))
+                }
+            });
+        }
+        Ok(statements)
+    }
+
+    // A FOREIGN KEY constraint or REFERENCES column option; actions that are
+    // not given default to NO ACTION
+    fn foreign_key(fk: &ForeignKeyConstraint) -> ConstraintType {
+        let action = |action: &Option<SqlReferentialAction>| match action {
+            Some(SqlReferentialAction::Cascade) => ReferentialAction::Cascade,
+            Some(SqlReferentialAction::SetNull) => ReferentialAction::SetNull,
+            Some(SqlReferentialAction::SetDefault) => ReferentialAction::SetDefault,
+            Some(SqlReferentialAction::Restrict) => ReferentialAction::Restrict,
+            Some(SqlReferentialAction::NoAction) | None => ReferentialAction::NoAction,
+        };
+        ConstraintType::ForeignKey {
+            name: fk.name.as_ref().map(|n| n.value.clone()),
+            columns: fk.columns.iter().map(|c| c.value.clone()).collect(),
+            ref_table: fk.foreign_table.to_string(),
+            ref_columns: fk
+                .referred_columns
+                .iter()
+                .map(|c| c.value.clone())
+                .collect(),
+            on_delete: action(&fk.on_delete),
+            on_update: action(&fk.on_update),
+        }
+    }
+
+    // Isolation level among the modes of BEGIN or SET TRANSACTION; access
+    // modes are accepted and ignored
+    fn isolation_level(modes: &[TransactionMode]) -> Option<IsolationLevel> {
+        modes.iter().rev().find_map(|mode| match mode {
+            TransactionMode::IsolationLevel(level) => Some(match level {
+                TransactionIsolationLevel::ReadUncommitted => IsolationLevel::ReadUncommitted,
+                TransactionIsolationLevel::ReadCommitted => IsolationLevel::ReadCommitted,
+                TransactionIsolationLevel::RepeatableRead => IsolationLevel::RepeatableRead,
+                TransactionIsolationLevel::Serializable => IsolationLevel::Serializable,
+                TransactionIsolationLevel::Snapshot => IsolationLevel::SnapshotIsolation,
+            }),
+            TransactionMode::AccessMode(_) => None,
+        })
+    }
+
     fn convert_statement(&self, stmt: Statement) -> Result<SqlStatement> {
         match stmt {
             Statement::CreateTable(create_table) => {
                 let table_name = create_table.name.to_string();
                 let columns = &create_table.columns;
                 let mut cols = Vec::new();
+                let mut constraints = Vec::new();
 
                 for col in columns {
-                    let data_type = match col.data_type {
-                        sqlparser::ast::DataType::Int(_) => DataType::Integer,
-                        sqlparser::ast::DataType::BigInt(_) => DataType::BigInt,
-                        sqlparser::ast::DataType::Float(_) => DataType::Float,
-                        sqlparser::ast::DataType::Double(_) => DataType::Double,
-                        sqlparser::ast::DataType::Varchar(len) => {
-                            let size = len
-                                .map(|l| match l {
-                                    sqlparser::ast::CharacterLength::IntegerLength {
-                                        length,
-                                        ..
-                                    } => length as usize,
-                                    _ => 255,
-                                })
-                                .unwrap_or(255);
-                            DataType::Varchar(size)
-                        }
-                        sqlparser::ast::DataType::Text => DataType::Text,
-                        sqlparser::ast::DataType::Boolean => DataType::Boolean,
-                        sqlparser::ast::DataType::Date => DataType::Date,
-                        sqlparser::ast::DataType::Timestamp(_, _) => DataType::Timestamp,
-                        _ => DataType::Text,
-                    };
+                    let data_type = DataType::from_sql(&col.data_type)?;
+
+                    // Columns are nullable unless declared NOT NULL
+                    let nullable = !col
+                        .options
+                        .iter()
+                        .any(|opt| matches!(opt.option, ColumnOption::NotNull));
+                    let default = col.options.iter().find_map(|opt| match &opt.option {
+                        // Kept as SQL text so quoted strings stay distinct from NULL
+                        ColumnOption::Default(expr) => Some(expr.to_string()),
+                        _ => None,
+                    });
 
                     cols.push(Column {
                         name: col.name.to_string(),
                         data_type,
-                        nullable: col
-                            .options
-                            .iter()
-                            .any(|opt| matches!(opt.option, sqlparser::ast::ColumnOption::Null)),
-                        default: None,
+                        nullable,
+                        default,
                     });
+
+                    for opt in &col.options {
+                        if let ColumnOption::ForeignKey(fk) = &opt.option {
+                            let mut constraint = Self::foreign_key(fk);
+                            if let ConstraintType::ForeignKey { name, columns, .. } =
+                                &mut constraint
+                            {
+                                *name = opt.name.as_ref().map(|n| n.value.clone());
+                                *columns = vec![col.name.to_string()];
+                            }
+                            constraints.push(constraint);
+                        }
+                    }
+                }
+
+                for constraint in &create_table.constraints {
+                    if let TableConstraint::ForeignKey(fk) = constraint {
+                        constraints.push(Self::foreign_key(fk));
+                    }
                 }
 
                 Ok(SqlStatement::CreateTable {
                     name: table_name,
                     columns: cols,
+                    constraints,
                 })
             }
+            Statement::AlterTable(alter_table) => {
+                let name = alter_table.name.to_string();
+                match alter_table.operations.as_slice() {
+                    [AlterTableOperation::AddConstraint {
+                        constraint: TableConstraint::ForeignKey(fk),
+                        ..
+                    }] => Ok(SqlStatement::AlterTable {
+                        name,
+                        action: AlterAction::AddConstraint(Self::foreign_key(fk)),
+                    }),
+                    [AlterTableOperation::DropConstraint {
+                        name: constraint, ..
+                    }] => Ok(SqlStatement::AlterTable {
+                        name,
+                        action: AlterAction::DropConstraint(constraint.value.clone()),
+                    }),
+                    _ => Err(DbError::SqlParse(format!(
+                        "Unsupported ALTER TABLE operation: {}",
+                        alter_table
+                    ))),
+                }
+            }
             Statement::Drop {
                 names, object_type, ..
             } => {
@@ -280,45 +1370,25 @@ impl SqlParser {
                     ))),
                 }
             }
-            Statement::Query(query) => {
-                if let SetExpr::Select(select) = *query.body {
-                    let table = self.extract_table_name(&select.from)?;
-                    let columns = self.extract_columns(&select.projection)?;
-                    let distinct = select.distinct.is_some();
-
-                    Ok(SqlStatement::Select {
-                        table,
-                        columns,
-                        filter: None,
-                        join: None,
-                        group_by: Vec::new(),
-                        having: None,
-                        order_by: Vec::new(),
-                        limit: None,
-                        offset: None,
-                        distinct,
-                    })
-                } else {
-                    Err(DbError::SqlParse("Unsupported query type".to_string()))
-                }
-            }
+            Statement::Query(query) => Ok(SqlStatement::Select { query }),
             Statement::Insert(insert) => {
                 let table = insert.table.to_string();
                 let cols: Vec<String> = insert.columns.iter().map(|c| c.to_string()).collect();
 
                 // Parse source values from the INSERT statement
-                let mut values: Vec<Vec<String>> = Vec::new();
+                let mut values: Vec<Vec<Expr>> = Vec::new();
 
                 if let Some(src) = insert.source {
+                    if !matches!(*src.body, SetExpr::Values(_)) {
+                        // INSERT INTO ... SELECT
+                        return Ok(SqlStatement::InsertIntoSelect {
+                            table,
+                            columns: cols,
+                            source: src,
+                        });
+                    }
                     if let SetExpr::Values(vals) = *src.body {
-                        for row in vals.rows {
-                            let mut row_values = Vec::new();
-                            for expr in row {
-                                let value = self.extract_literal_value(&expr);
-                                row_values.push(value);
-                            }
-                            values.push(row_values);
-                        }
+                        values = vals.rows;
                     }
                 }
 
@@ -358,7 +1428,28 @@ impl SqlParser {
                 }
                 Ok(SqlStatement::Delete {
                     table,
-                    filter: None,
+                    filter: delete.selection,
+                })
+            }
+            Statement::Update(update) => {
+                let table = match &update.table.relation {
+                    TableFactor::Table { name, .. } => name.to_string(),
+                    _ => {
+                        return Err(DbError::SqlParse(
+                            "Update statement requires a table".to_string(),
+                        ))
+                    }
+                };
+                let assignments = update
+                    .assignments
+                    .into_iter()
+                    .map(|a| (a.target.to_string(), a.value))
+                    .collect();
+
+                Ok(SqlStatement::Update {
+                    table,
+                    assignments,
+                    filter: update.selection,
                 })
             }
             Statement::Truncate(truncate) => {
@@ -384,68 +1475,157 @@ impl SqlParser {
                     .map(|col| col.column.to_string())
                     .collect();
                 let unique = create_index.unique;
+                let hnsw = match &create_index.using {
+                    Some(method) if method.to_string().eq_ignore_ascii_case("hnsw") => {
+                        Some(Self::hnsw_options(&create_index)?)
+                    }
+                    _ => None,
+                };
 
                 Ok(SqlStatement::CreateIndex {
                     name: index_name,
                     table,
                     columns: cols,
                     unique,
+                    filter: create_index.predicate,
+                    hnsw,
                 })
             }
             Statement::CreateView(create_view) => Ok(SqlStatement::CreateView {
                 name: create_view.name.to_string(),
-                query: create_view.query.to_string(),
+                query: sql_text(&create_view.query),
                 or_replace: create_view.or_replace,
             }),
             _ => Err(DbError::SqlParse("Unsupported statement type".to_string())),
         }
     }
 
-    fn extract_table_name(&self, from: &[TableWithJoins]) -> Result<String> {
-        if from.is_empty() {
-            return Err(DbError::SqlParse("No table specified".to_string()));
-        }
-
-        match &from[0].relation {
-            TableFactor::Table { name, .. } => Ok(name.to_string()),
-            _ => Err(DbError::SqlParse("Unsupported table factor".to_string())),
+    // `USING hnsw (column opclass) WITH (m = .., ef_construction = ..)`; the
+    // operator class picks the distance, L2 by default
+    fn hnsw_options(create_index: &sqlparser::ast::CreateIndex) -> Result<HnswOptions> {
+        let mut options = HnswOptions::default();
+        let [column] = create_index.columns.as_slice() else {
+            return Err(DbError::SqlParse(
+                "An hnsw index must have exactly one column".to_string(),
+            ));
+        };
+        if let Some(class) = &column.operator_class {
+            let class = class.to_string();
+            options.metric = VectorMetric::from_operator_class(&class)
+                .ok_or_else(|| DbError::SqlParse(format!("Unknown operator class {}", class)))?;
         }
-    }
-
-    fn extract_columns(&self, projection: &[SelectItem]) -> Result<Vec<String>> {
-        let mut columns = Vec::new();
-
-        for item in projection {
-            match item {
-                SelectItem::UnnamedExpr(Expr::Identifier(ident)) => {
-                    columns.push(ident.to_string());
+        for parameter in &create_index.with {
+            let Expr::BinaryOp {
+                left,
+                op: sqlparser::ast::BinaryOperator::Eq,
+                right,
+            } = parameter
+            else {
+                return Err(DbError::SqlParse(format!(
+                    "Invalid index parameter {}",
+                    parameter
+                )));
+            };
+            let value = match Self::literal_value(right)? {
+                Value::Integer(n) if n > 0 => n as usize,
+                _ => {
+                    return Err(DbError::SqlParse(format!(
+                        "Invalid index parameter {}",
+                        parameter
+                    )))
                 }
-                SelectItem::Wildcard(_) => {
-                    columns.push("*".to_string());
+            };
+            match left.to_string().to_ascii_lowercase().as_str() {
+                "m" => options.m = value,
+                "ef_construction" => options.ef_construction = value,
+                name => {
+                    return Err(DbError::SqlParse(format!(
+                        "Unknown hnsw parameter {}",
+                        name
+                    )))
                 }
-                _ => {}
             }
         }
-
-        Ok(columns)
+        options.validate()?;
+        Ok(options)
     }
 
-    /// Extract a literal value from an expression
-    fn extract_literal_value(&self, expr: &Expr) -> String {
+    /// Convert a literal expression into a typed value
+    pub(crate) fn literal_value(expr: &Expr) -> Result<Value> {
         match expr {
             Expr::Value(val) => match &val.value {
-                sqlparser::ast::Value::Number(n, _) => n.clone(),
-                sqlparser::ast::Value::SingleQuotedString(s) => s.clone(),
-                sqlparser::ast::Value::DoubleQuotedString(s) => s.clone(),
-                sqlparser::ast::Value::Boolean(b) => b.to_string(),
-                sqlparser::ast::Value::Null => "NULL".to_string(),
-                _ => val.to_string(),
+                sqlparser::ast::Value::Number(n, _) => match Value::from_sql_literal(n) {
+                    number @ (Value::Integer(_) | Value::Float(_)) => Ok(number),
+                    _ => Err(DbError::SqlParse(format!("Invalid number: {}", n))),
+                },
+                sqlparser::ast::Value::SingleQuotedString(s)
+                | sqlparser::ast::Value::DoubleQuotedString(s) => Ok(Value::String(s.clone())),
+                sqlparser::ast::Value::Boolean(b) => Ok(Value::Boolean(*b)),
+                sqlparser::ast::Value::Null => Ok(Value::Null),
+                sqlparser::ast::Value::Placeholder(p) => Err(DbError::SqlParse(format!(
+                    "Parameter {} cannot be used here",
+                    p
+                ))),
+                _ => Ok(Value::String(val.to_string())),
+            },
+            Expr::UnaryOp {
+                op: UnaryOperator::Minus,
+                expr: inner,
+            } => match Self::literal_value(inner)? {
+                Value::Integer(i) => Ok(Value::Integer(-i)),
+                Value::Float(f) => Ok(Value::Float(-f)),
+                Value::Numeric(d) => Ok(Value::Numeric(-d)),
+                Value::Interval(i) => i
+                    .checked_neg()
+                    .map(Value::Interval)
+                    .ok_or_else(|| DbError::SqlParse(format!("Cannot negate {}", inner))),
+                _ => Err(DbError::SqlParse(format!("Cannot negate {}", inner))),
             },
-            Expr::Identifier(ident) => ident.to_string(),
-            Expr::UnaryOp { op, expr } => {
-                format!("{}{}", op, self.extract_literal_value(expr))
+            Expr::UnaryOp {
+                op: UnaryOperator::Plus,
+                expr: inner,
+            } => Self::literal_value(inner),
+            Expr::Nested(inner) => Self::literal_value(inner),
+            _ => {
+                let text = expr.to_string();
+                Ok(Self::typed_literal(&text).unwrap_or_else(|| Value::from_sql_literal(&text)))
             }
-            _ => expr.to_string(),
+        }
+    }
+
+    // Value of a literal written `<type> '<text>'`, such as DATE '...',
+    // TIMESTAMP WITH TIME ZONE '...', NUMERIC '...', UUID '...', or
+    // INTERVAL '...' with an optional unit after the text
+    fn typed_literal(text: &str) -> Option<Value> {
+        let (start, end) = (text.find('\'')?, text.rfind('\'')?);
+        if end <= start {
+            return None;
+        }
+        let literal = match Value::from_sql_literal(&text[start..=end]) {
+            Value::String(literal) => literal,
+            _ => return None,
+        };
+        let type_name = text[..start]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_uppercase();
+        let unit = text[end + 1..].trim();
+        if !unit.is_empty() && type_name != "INTERVAL" {
+            return None;
+        }
+        match type_name.as_str() {
+            "DATE" => Value::parse_date(&literal).map(Value::Date),
+            "TIMESTAMP" | "TIMESTAMP WITHOUT TIME ZONE" => {
+                Value::parse_timestamp(&literal).map(Value::Timestamp)
+            }
+            "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE" => {
+                Value::parse_timestamptz(&literal).map(Value::TimestampTz)
+            }
+            "NUMERIC" | "DECIMAL" => Decimal::parse(&literal).map(Value::Numeric),
+            "UUID" => uuid::Uuid::parse_str(literal.trim()).ok().map(Value::Uuid),
+            "INTERVAL" => Interval::parse(&format!("{} {}", literal, unit)).map(Value::Interval),
+            _ => None,
         }
     }
 }
@@ -468,7 +1648,7 @@ mod tests {
 
         assert_eq!(stmts.len(), 1);
         match &stmts[0] {
-            SqlStatement::CreateTable { name, columns } => {
+            SqlStatement::CreateTable { name, columns, .. } => {
                 assert_eq!(name, "users");
                 assert_eq!(columns.len(), 2);
             }
@@ -479,22 +1659,280 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_select() -> Result<()> {
-        let parser = SqlParser::new();
-        let sql = "SELECT id, name FROM users";
-        let stmts = parser.parse(sql)?;
+    fn test_parse_foreign_keys() -> Result<()> {
+        let sql = "CREATE TABLE orders (id INT, user_id INT REFERENCES users ON DELETE CASCADE,
+                   sku TEXT, FOREIGN KEY (sku) REFERENCES items (sku))";
+        match parse_one(sql)? {
+            SqlStatement::CreateTable { constraints, .. } => {
+                assert_eq!(constraints.len(), 2);
+                match &constraints[0] {
+                    ConstraintType::ForeignKey {
+                        name,
+                        columns,
+                        ref_table,
+                        ref_columns,
+                        on_delete,
+                        ..
+                    } => {
+                        assert_eq!(*name, None);
+                        assert_eq!(columns, &["user_id"]);
+                        assert_eq!(ref_table, "users");
+                        assert!(ref_columns.is_empty());
+                        assert!(matches!(on_delete, ReferentialAction::Cascade));
+                    }
+                    other => panic!("Expected ForeignKey, got {:?}", other),
+                }
+            }
+            _ => panic!("Expected CreateTable"),
+        }
 
-        assert_eq!(stmts.len(), 1);
-        match &stmts[0] {
-            SqlStatement::Select {
+        let sql =
+            "ALTER TABLE orders ADD CONSTRAINT fk_sku FOREIGN KEY (sku) REFERENCES items (sku)";
+        match parse_one(sql)? {
+            SqlStatement::AlterTable {
+                name,
+                action: AlterAction::AddConstraint(ConstraintType::ForeignKey { name: fk, .. }),
+            } => {
+                assert_eq!(name, "orders");
+                assert_eq!(fk.as_deref(), Some("fk_sku"));
+            }
+            other => panic!("Expected AlterTable, got {:?}", other),
+        }
+        assert!(SqlParser::new()
+            .parse("ALTER TABLE orders RENAME TO purchases")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_vector_type() -> Result<()> {
+        match parse_one("CREATE TABLE items (id INT, embedding VECTOR(3))")? {
+            SqlStatement::CreateTable { columns, .. } => {
+                assert_eq!(columns[1].data_type, DataType::Vector(3));
+            }
+            _ => panic!("Expected CreateTable"),
+        }
+        for sql in [
+            "CREATE TABLE items (embedding VECTOR(0))",
+            "CREATE TABLE items (embedding VECTOR(16001))",
+        ] {
+            assert!(
+                matches!(parse_one(sql), Err(DbError::SqlParse(_))),
+                "{}",
+                sql
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_numeric_interval_uuid_array_types() -> Result<()> {
+        let sql = "CREATE TABLE payments (amount NUMERIC(10,2), rate DECIMAL, \
+                   period INTERVAL, paid_at TIMESTAMP WITH TIME ZONE, \
+                   booked_at TIMESTAMPTZ, id UUID, tags TEXT[], counts INT[])";
+        match parse_one(sql)? {
+            SqlStatement::CreateTable { columns, .. } => {
+                let types: Vec<DataType> = columns.into_iter().map(|c| c.data_type).collect();
+                assert_eq!(
+                    types,
+                    vec![
+                        DataType::Numeric(Some((10, 2))),
+                        DataType::Numeric(None),
+                        DataType::Interval,
+                        DataType::TimestampTz,
+                        DataType::TimestampTz,
+                        DataType::Uuid,
+                        DataType::Array(Box::new(DataType::Text)),
+                        DataType::Array(Box::new(DataType::Integer)),
+                    ]
+                );
+            }
+            _ => panic!("Expected CreateTable"),
+        }
+        for sql in [
+            "CREATE TABLE t (amount NUMERIC(39,2))",
+            "CREATE TABLE t (amount NUMERIC(4,5))",
+            "CREATE TABLE t (grid INT[][])",
+        ] {
+            assert!(
+                matches!(parse_one(sql), Err(DbError::SqlParse(_))),
+                "{}",
+                sql
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_typed_literals() -> Result<()> {
+        let literal = |sql: &str| -> Result<Value> {
+            let expr = Parser::new(&GenericDialect {})
+                .try_with_sql(sql)
+                .and_then(|mut parser| parser.parse_expr())
+                .map_err(|e| DbError::SqlParse(e.to_string()))?;
+            SqlParser::literal_value(&expr)
+        };
+        assert_eq!(literal("NUMERIC '12.50'")?.to_string(), "12.50");
+        assert_eq!(literal("-DECIMAL '0.5'")?.to_string(), "-0.5");
+        assert_eq!(
+            literal("INTERVAL '1 day 2 hours'")?.to_string(),
+            "1 day 02:00:00"
+        );
+        assert_eq!(literal("INTERVAL '3' DAY")?.to_string(), "3 days");
+        assert_eq!(
+            literal("TIMESTAMP WITH TIME ZONE '2024-01-01 02:00:00+02'")?,
+            Value::TimestampTz(Value::parse_timestamp("2024-01-01").unwrap())
+        );
+        assert!(matches!(
+            literal("UUID 'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'")?,
+            Value::Uuid(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_procedure_statements() -> Result<()> {
+        let sql = "CREATE OR REPLACE PROCEDURE rename_user(id INTEGER, name VARCHAR(20), \
+                   old_name OUT VARCHAR(20), count IN OUT INTEGER) AS $$ \
+                   BEGIN SELECT name INTO old_name FROM users WHERE user_id = id; END; $$";
+        match parse_one(sql)? {
+            SqlStatement::CreateProcedure {
+                name,
+                parameters,
+                body,
+                or_replace,
+            } => {
+                assert_eq!(name, "rename_user");
+                assert!(or_replace);
+                let modes: Vec<_> = parameters.iter().map(|p| p.mode.clone()).collect();
+                assert_eq!(
+                    modes,
+                    vec![
+                        ParameterMode::In,
+                        ParameterMode::In,
+                        ParameterMode::Out,
+                        ParameterMode::InOut
+                    ]
+                );
+                assert_eq!(parameters[1].data_type, DataType::Varchar(20));
+                assert!(body.starts_with("BEGIN SELECT") && body.ends_with("END;"));
+            }
+            _ => panic!("Expected CreateProcedure"),
+        }
+
+        match parse_one("CALL rename_user(1, 'ann', NULL, -2)")? {
+            SqlStatement::ExecProcedure { name, arguments } => {
+                assert_eq!(name, "rename_user");
+                assert_eq!(arguments.len(), 4);
+            }
+            _ => panic!("Expected ExecProcedure"),
+        }
+        assert!(matches!(
+            parse_one("DROP PROCEDURE rename_user")?,
+            SqlStatement::DropProcedure { name } if name == "rename_user"
+        ));
+        assert!(parse_one("CALL rename_user((SELECT 1))").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_trigger_statements() -> Result<()> {
+        let sql = "CREATE TRIGGER audit_pay BEFORE INSERT OR UPDATE ON payments \
+                   FOR EACH ROW WHEN (NEW.amount > (10 + 5)) \
+                   BEGIN :NEW.checked := TRUE; END;";
+        match parse_one(sql)? {
+            SqlStatement::CreateTrigger {
+                name,
                 table,
-                columns,
-                distinct,
+                timing,
+                events,
+                level,
+                condition,
+                body,
+                or_replace,
+            } => {
+                assert_eq!((name.as_str(), table.as_str()), ("audit_pay", "payments"));
+                assert_eq!(timing, TriggerTiming::Before);
+                assert_eq!(events, vec![TriggerEvent::Insert, TriggerEvent::Update]);
+                assert_eq!(level, TriggerLevel::Row);
+                assert_eq!(condition.as_deref(), Some("NEW.amount > (10 + 5)"));
+                assert_eq!(body, "BEGIN :NEW.checked := TRUE; END;");
+                assert!(!or_replace);
+            }
+            _ => panic!("Expected CreateTrigger"),
+        }
+
+        let sql = "CREATE OR REPLACE TRIGGER log_orders INSTEAD OF DELETE ON open_orders \
+                   $body$ BEGIN NULL; END; $body$";
+        match parse_one(sql)? {
+            SqlStatement::CreateTrigger {
+                timing,
+                level,
+                body,
+                or_replace,
                 ..
             } => {
-                assert_eq!(table, "users");
-                assert_eq!(columns.len(), 2);
-                assert!(!distinct);
+                assert_eq!(timing, TriggerTiming::InsteadOf);
+                assert_eq!(level, TriggerLevel::Row);
+                assert_eq!(body, "BEGIN NULL; END;");
+                assert!(or_replace);
+            }
+            _ => panic!("Expected CreateTrigger"),
+        }
+
+        assert!(matches!(
+            parse_one("CREATE TRIGGER t AFTER DELETE ON orders BEGIN NULL; END;")?,
+            SqlStatement::CreateTrigger {
+                level: TriggerLevel::Statement,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse_one("DROP TRIGGER audit_pay")?,
+            SqlStatement::DropTrigger { name } if name == "audit_pay"
+        ));
+        assert!(parse_one("CREATE TRIGGER t DURING INSERT ON orders BEGIN NULL; END;").is_err());
+        Ok(())
+    }
+
+    fn parse_one(sql: &str) -> Result<SqlStatement> {
+        Ok(SqlParser::new().parse(sql)?.remove(0))
+    }
+
+    #[test]
+    fn test_parse_select() -> Result<()> {
+        match parse_one("SELECT id, name FROM users")? {
+            SqlStatement::Select { query } => {
+                let SetExpr::Select(select) = *query.body else {
+                    panic!("Expected a plain SELECT body");
+                };
+                assert_eq!(select.projection.len(), 2);
+                assert_eq!(select.from[0].relation.to_string(), "users");
+                assert!(select.distinct.is_none());
+            }
+            _ => panic!("Expected Select"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_select_keeps_full_query() -> Result<()> {
+        let sql = "SELECT u.name, COUNT(*) FROM users AS u JOIN orders AS o ON u.id = o.user_id \
+                   WHERE o.note = 'x AND y' AND o.total * 2 > (SELECT AVG(total) FROM orders) \
+                   GROUP BY u.name ORDER BY u.name DESC LIMIT 10 OFFSET 5";
+        match parse_one(sql)? {
+            SqlStatement::Select { query } => {
+                assert!(query.order_by.is_some());
+                assert!(query.limit_clause.is_some());
+                let SetExpr::Select(select) = *query.body else {
+                    panic!("Expected a plain SELECT body");
+                };
+                assert_eq!(select.from[0].joins.len(), 1);
+                assert_eq!(
+                    select.selection.unwrap().to_string(),
+                    "o.note = 'x AND y' AND o.total * 2 > (SELECT AVG(total) FROM orders)"
+                );
             }
             _ => panic!("Expected Select"),
         }
@@ -503,29 +1941,166 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_select_distinct() -> Result<()> {
+    fn test_parse_accepts_comments_unions_and_scripts() -> Result<()> {
         let parser = SqlParser::new();
-        let sql = "SELECT DISTINCT id FROM users";
-        let stmts = parser.parse(sql)?;
+        let sql = "SELECT /*+ INDEX(users idx_users_name) */ name FROM users -- active only\n\
+                   WHERE active = TRUE UNION SELECT name FROM admins";
+        match parse_one(sql)? {
+            SqlStatement::Select { query } => {
+                assert!(matches!(*query.body, SetExpr::SetOperation { .. }))
+            }
+            _ => panic!("Expected Select"),
+        }
 
-        assert_eq!(stmts.len(), 1);
-        match &stmts[0] {
-            SqlStatement::Select {
+        let script = parser.parse("INSERT INTO t VALUES (1); SELECT * FROM t WHERE note = '--'")?;
+        assert_eq!(script.len(), 2);
+        assert!(matches!(script[0], SqlStatement::Insert { .. }));
+        assert!(matches!(script[1], SqlStatement::Select { .. }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_window_frame_exclusion() -> Result<()> {
+        let text = |sql: &str| -> Result<String> {
+            match parse_one(sql)? {
+                SqlStatement::Select { query } => Ok(query.to_string()),
+                _ => panic!("Expected Select"),
+            }
+        };
+        let sql = text(
+            "SELECT SUM(x) OVER (PARTITION BY g ORDER BY x ROWS UNBOUNDED PRECEDING EXCLUDE TIES), \
+             SUM(x) OVER (w RANGE CURRENT ROW EXCLUDE CURRENT ROW) FROM t WINDOW w AS (ORDER BY x)",
+        )?;
+        assert!(sql.contains("PARTITION BY g, __frame_exclude('TIES') ORDER BY x"));
+        assert!(sql.contains("OVER (w PARTITION BY __frame_exclude('CURRENT ROW') RANGE"));
+
+        let sql = text("SELECT COUNT(*) OVER (ROWS CURRENT ROW EXCLUDE NO OTHERS) FROM t")?;
+        assert!(!sql.contains("__frame_exclude"));
+
+        // The clause is written back where the AST is rendered as SQL
+        let sql = "SELECT SUM(x) OVER (PARTITION BY g ORDER BY x ROWS UNBOUNDED PRECEDING EXCLUDE TIES), \
+                   SUM(x) OVER (w RANGE CURRENT ROW EXCLUDE CURRENT ROW) FROM t WINDOW w AS (ORDER BY x)";
+        match parse_one(sql)? {
+            SqlStatement::Select { query } => assert_eq!(sql_text(&query), sql),
+            _ => panic!("Expected Select"),
+        }
+
+        // and the marker is not a name queries may use
+        for sql in [
+            "SELECT __frame_exclude('TIES') FROM t",
+            "SELECT x AS \"__FRAME_EXCLUDE\" FROM t",
+        ] {
+            assert!(matches!(parse_one(sql), Err(DbError::SqlParse(_))));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_search_and_cycle_clauses() -> Result<()> {
+        let sql = "WITH RECURSIVE t(id, parent) AS (SELECT id, parent FROM nodes \
+                   UNION ALL SELECT n.id, n.parent FROM nodes n JOIN t ON n.parent = t.id) \
+                   SEARCH BREADTH FIRST BY id SET ord \
+                   CYCLE id, parent SET looped TO 'Y' DEFAULT 'N' USING path \
+                   SELECT * FROM t ORDER BY ord";
+        let query = match parse_one(sql)? {
+            SqlStatement::Select { query } => query,
+            _ => panic!("Expected Select"),
+        };
+        let ctes: Vec<(String, String)> = query
+            .with
+            .expect("WITH clause")
+            .cte_tables
+            .iter()
+            .map(|cte| (cte.alias.to_string(), cte.query.to_string()))
+            .collect();
+        assert_eq!(ctes.len(), 3);
+        assert_eq!(ctes[0].0, "t (id, parent)");
+        assert_eq!(ctes[1].0, "__cte_search (ord)");
+        assert_eq!(ctes[1].1, "SELECT 'BREADTH', id");
+        assert_eq!(ctes[2].0, "__cte_cycle (looped, path)");
+        assert_eq!(ctes[2].1, "SELECT 'Y', 'N', id, parent");
+
+        // Without TO and DEFAULT the mark is a boolean
+        let sql = "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t) \
+                   CYCLE n SET seen USING trail SELECT n FROM t";
+        match parse_one(sql)? {
+            SqlStatement::Select { query } => assert!(query
+                .to_string()
+                .contains("__cte_cycle (seen, trail) AS (SELECT true, false, n)")),
+            _ => panic!("Expected Select"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_update() -> Result<()> {
+        match parse_one("UPDATE users SET name = 'bob', age = age + 1 WHERE id = 7")? {
+            SqlStatement::Update {
                 table,
-                columns,
-                distinct,
-                ..
+                assignments,
+                filter,
             } => {
                 assert_eq!(table, "users");
-                assert_eq!(columns.len(), 1);
-                assert!(distinct);
+                let assignments: Vec<(String, String)> = assignments
+                    .into_iter()
+                    .map(|(column, value)| (column, value.to_string()))
+                    .collect();
+                assert_eq!(
+                    assignments,
+                    vec![
+                        ("name".to_string(), "'bob'".to_string()),
+                        ("age".to_string(), "age + 1".to_string()),
+                    ]
+                );
+                assert_eq!(filter.map(|f| f.to_string()).as_deref(), Some("id = 7"));
+            }
+            _ => panic!("Expected Update"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_delete_where() -> Result<()> {
+        match parse_one("DELETE FROM users WHERE id = 7")? {
+            SqlStatement::Delete { table, filter } => {
+                assert_eq!(table, "users");
+                assert_eq!(filter.map(|f| f.to_string()).as_deref(), Some("id = 7"));
             }
-            _ => panic!("Expected Select with DISTINCT"),
+            _ => panic!("Expected Delete"),
         }
 
         Ok(())
     }
 
+    #[test]
+    fn test_literal_value() -> Result<()> {
+        let expr = |sql: &str| -> Expr {
+            match parse_one(&format!("SELECT {}", sql)).unwrap() {
+                SqlStatement::Select { query } => match *query.body {
+                    SetExpr::Select(select) => match &select.projection[0] {
+                        sqlparser::ast::SelectItem::UnnamedExpr(expr) => expr.clone(),
+                        other => panic!("unexpected item {:?}", other),
+                    },
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            }
+        };
+        assert_eq!(SqlParser::literal_value(&expr("-42"))?, Value::Integer(-42));
+        assert_eq!(
+            SqlParser::literal_value(&expr("'it''s'"))?,
+            Value::String("it's".into())
+        );
+        assert_eq!(
+            SqlParser::literal_value(&expr("DATE '1970-01-02'"))?,
+            Value::Date(1)
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_parse_drop_index() -> Result<()> {
         let parser = SqlParser::new();
@@ -590,15 +2165,55 @@ mod tests {
                 table,
                 columns,
                 unique,
+                filter,
+                hnsw,
             } => {
                 assert_eq!(name, "idx_users_email");
                 assert_eq!(table, "users");
                 assert_eq!(columns.len(), 1);
                 assert!(!unique);
+                assert!(filter.is_none());
+                assert!(hnsw.is_none());
             }
             _ => panic!("Expected CreateIndex"),
         }
 
+        let sql = "CREATE UNIQUE INDEX idx_active_email ON users (email) WHERE active = true";
+        match parser.parse(sql)?.remove(0) {
+            SqlStatement::CreateIndex { unique, filter, .. } => {
+                assert!(unique);
+                assert_eq!(
+                    filter.map(|f| f.to_string()).as_deref(),
+                    Some("active = true")
+                );
+            }
+            _ => panic!("Expected CreateIndex"),
+        }
+
+        let sql = "CREATE INDEX items_embedding ON items USING hnsw (embedding vector_cosine_ops) \
+                   WITH (m = 24, ef_construction = 100)";
+        match parser.parse(sql)?.remove(0) {
+            SqlStatement::CreateIndex { hnsw, .. } => {
+                assert_eq!(
+                    hnsw,
+                    Some(HnswOptions {
+                        metric: VectorMetric::Cosine,
+                        m: 24,
+                        ef_construction: 100,
+                    })
+                );
+            }
+            _ => panic!("Expected CreateIndex"),
+        }
+        for sql in [
+            "CREATE INDEX i ON items USING hnsw (a, b)",
+            "CREATE INDEX i ON items USING hnsw (embedding vector_hamming_ops)",
+            "CREATE INDEX i ON items USING hnsw (embedding) WITH (m = 1)",
+            "CREATE INDEX i ON items USING hnsw (embedding) WITH (lists = 10)",
+        ] {
+            assert!(parser.parse(sql).is_err(), "{}", sql);
+        }
+
         Ok(())
     }
 
@@ -623,4 +2238,88 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_analyze() -> Result<()> {
+        assert!(matches!(
+            parse_one("ANALYZE")?,
+            SqlStatement::Analyze { table: None }
+        ));
+        assert!(matches!(
+            parse_one("analyze users;")?,
+            SqlStatement::Analyze { table: Some(name) } if name == "users"
+        ));
+        assert!(matches!(
+            parse_one("ANALYZE TABLE users")?,
+            SqlStatement::Analyze { table: Some(name) } if name == "users"
+        ));
+        assert!(parse_one("ANALYZE TABLE").is_err());
+        assert!(parse_one("ANALYZE users; DROP TABLE users").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_explain() -> Result<()> {
+        assert!(matches!(
+            parse_one("EXPLAIN SELECT * FROM users")?,
+            SqlStatement::Explain {
+                analyze: false,
+                format: ExplainFormat::Text,
+                statement,
+            } if matches!(*statement, SqlStatement::Select { .. })
+        ));
+        assert!(matches!(
+            parse_one("explain analyze format json SELECT id FROM users WHERE id = 1")?,
+            SqlStatement::Explain {
+                analyze: true,
+                format: ExplainFormat::Json,
+                ..
+            }
+        ));
+        assert!(parse_one("EXPLAIN FORMAT XML SELECT * FROM users").is_err());
+        assert!(parse_one("EXPLAIN DELETE FROM users").is_err());
+        assert!(parse_one("EXPLAIN SELECT 1; SELECT 2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_transaction_control() -> Result<()> {
+        assert!(matches!(
+            parse_one("BEGIN")?,
+            SqlStatement::Begin { isolation: None }
+        ));
+        assert!(matches!(
+            parse_one("START TRANSACTION ISOLATION LEVEL REPEATABLE READ")?,
+            SqlStatement::Begin {
+                isolation: Some(IsolationLevel::RepeatableRead)
+            }
+        ));
+        assert!(matches!(parse_one("COMMIT")?, SqlStatement::Commit));
+        assert!(matches!(
+            parse_one("ROLLBACK TO SAVEPOINT before_update")?,
+            SqlStatement::Rollback { savepoint: Some(name) } if name == "before_update"
+        ));
+        assert!(matches!(
+            parse_one("RELEASE SAVEPOINT before_update")?,
+            SqlStatement::ReleaseSavepoint { name } if name == "before_update"
+        ));
+        assert!(matches!(
+            parse_one("SET SESSION CHARACTERISTICS AS TRANSACTION ISOLATION LEVEL SERIALIZABLE")?,
+            SqlStatement::SetTransaction {
+                isolation: Some(IsolationLevel::Serializable),
+                session: true
+            }
+        ));
+
+        assert!(matches!(
+            parse_one("SET hnsw.ef_search TO 100;")?,
+            SqlStatement::SetParameter { name, value } if name == "hnsw.ef_search" && value == "100"
+        ));
+        assert!(parse_one("SET hnsw.ef_search = 100; DROP TABLE users").is_err());
+
+        // Nothing else may ride along with a transaction command
+        assert!(SqlParser::new().parse("BEGIN; DROP TABLE users").is_err());
+        assert!(SqlParser::new().parse("PREPARE p AS COMMIT").is_err());
+        Ok(())
+    }
 }
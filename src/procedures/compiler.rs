@@ -313,10 +313,7 @@ impl PlSqlCompiler {
             }
 
             Statement::SelectInto {
-                columns: _columns,
-                into_vars,
-                from,
-                where_clause,
+                into_vars, from, ..
             } => {
                 // Add table dependency
                 result.add_dependency(from.clone());
@@ -332,11 +329,12 @@ impl PlSqlCompiler {
                         });
                     }
                 }
+            }
 
-                // Analyze WHERE clause if present
-                if let Some(where_expr) = where_clause {
-                    self.analyze_expression(where_expr, symbol_table, result)?;
-                }
+            Statement::Insert { table, .. }
+            | Statement::Update { table, .. }
+            | Statement::Delete { table, .. } => {
+                result.add_dependency(table.clone());
             }
 
             Statement::Case {
@@ -1,79 +1,32 @@
 // RustyDB Stored Procedures Module
 // Enterprise-grade PL/SQL-compatible stored procedures, functions, triggers, and packages
 //
-// ⚠️ **CRITICAL: NO QUERY EXECUTOR INTEGRATION** ⚠️
-//
-// **Issue**: Procedures parse SQL but don't actually execute it
-//
-// **Missing Integration**:
-// 1. No connection to `src/execution/executor.rs` - SQL parsing only
-// 2. No transaction integration - procedures can't commit/rollback
-// 3. No parameter passing to SQL executor
-// 4. No OUT parameter collection from query results
-// 5. No cursor support for result sets
-//
-// **TODO - HIGH PRIORITY**:
-// 1. Integrate with QueryExecutor:
-//    ```rust
-//    use crate::execution::executor::QueryExecutor;
-//
-//    pub struct ProcedureRuntime {
-//        executor: Arc<QueryExecutor>,
-//        context: ProcedureContext,
-//    }
-//
-//    impl ProcedureRuntime {
-//        fn execute_statement(&mut self, sql: &str) -> Result<QueryResult> {
-//            self.executor.execute_sql(sql, &self.context.parameters)
-//        }
-//    }
-//    ```
-//
-// 2. Add transaction support:
-//    - Procedures should start transaction at BEGIN
-//    - COMMIT/ROLLBACK statements should work
-//    - Automatic rollback on exception
-//
-// 3. Implement parameter substitution:
-//    - Replace :param_name with actual values
-//    - Type checking and conversion
-//    - Support for IN/OUT/INOUT parameters
-//
-// 4. Implement cursor support:
-//    - Open cursor for SELECT statements
-//    - Fetch rows from cursor
-//    - Close cursor when done
-//
-// 5. Add exception handling:
-//    - RAISE statements
-//    - EXCEPTION blocks
-//    - Error propagation
-//
-// **Cross-Reference**: Same issues in `src/triggers/mod.rs`
-// **Impact**: Procedures are non-functional without executor integration
-// **Priority**: HIGH - required for enterprise features
-//
-// **See Also**: Analysis diagram section 8 in diagrams/08_specialized_engines_flow.md
-
+// Stored procedures live in the catalog and run in the PL/SQL runtime
+// against an `Executor`. Their embedded SQL is prepared and executed through
+// it, with the block variables the SQL refers to bound as parameters. A
+// procedure called inside a transaction block runs in that transaction;
+// called on its own it begins, commits and rolls back transactions itself,
+// and what it leaves open is committed when it returns, or rolled back if it
+// fails.
+
+use crate::catalog::DataType;
+use crate::common::Value;
 use crate::error::DbError;
+use crate::execution::executor::Executor;
+use crate::parser::SqlStatement;
 use crate::Result;
-use parking_lot::RwLock;
+use parser::{PlSqlBlock, PlSqlParser};
+use runtime::RuntimeExecutor;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
 
 // ============================================================================
 // Capacity Limits - Prevent Unbounded Memory Growth
 // ============================================================================
 
-// TODO(ARCHITECTURE): Implement bounded procedure storage to prevent OOM
 // Maximum number of stored procedures in the database
-// Current implementation uses unbounded HashMap - see ProcedureManager struct
-// Recommended: Use BoundedHashMap with eviction policy or disk-backed storage
 // Oracle typical production limit: ~100K procedures
 pub const MAX_STORED_PROCEDURES: usize = 100_000;
 
-// TODO(ARCHITECTURE): Limit procedure body size to prevent excessive memory usage
 // Maximum size of a single procedure body in bytes
 // Oracle PL/SQL limit: ~32KB per procedure
 pub const MAX_PROCEDURE_BODY_SIZE: usize = 32_768; // 32 KB
@@ -101,15 +54,22 @@ pub enum ParameterMode {
 }
 
 // Stored procedure parameter
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ProcedureParameter {
     pub name: String,
-    pub data_type: String,
+    pub data_type: DataType,
     pub mode: ParameterMode,
 }
 
+impl ProcedureParameter {
+    // Whether the parameter's value is handed back to the caller
+    pub fn is_output(&self) -> bool {
+        self.mode != ParameterMode::In
+    }
+}
+
 // Stored procedure definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct StoredProcedure {
     pub name: String,
     pub parameters: Vec<ProcedureParameter>,
@@ -117,6 +77,44 @@ pub struct StoredProcedure {
     pub language: ProcedureLanguage,
 }
 
+impl StoredProcedure {
+    pub fn new(name: String, parameters: Vec<ProcedureParameter>, body: String) -> Self {
+        Self {
+            name,
+            parameters,
+            body,
+            language: ProcedureLanguage::Sql,
+        }
+    }
+
+    // Check the procedure against the size limits and that its body parses
+    pub fn validate(&self) -> Result<()> {
+        if self.body.len() > MAX_PROCEDURE_BODY_SIZE {
+            return Err(DbError::LimitExceeded(format!(
+                "Body of procedure {} exceeds {} bytes",
+                self.name, MAX_PROCEDURE_BODY_SIZE
+            )));
+        }
+        if self.parameters.len() > MAX_PARAMETERS_PER_PROCEDURE {
+            return Err(DbError::LimitExceeded(format!(
+                "Procedure {} has more than {} parameters",
+                self.name, MAX_PARAMETERS_PER_PROCEDURE
+            )));
+        }
+        self.compile().map(|_| ())
+    }
+
+    // Parse the body
+    pub fn compile(&self) -> Result<PlSqlBlock> {
+        PlSqlParser::new().parse_procedure_body(&self.body)
+    }
+
+    // The parameters whose values are handed back to the caller, in order
+    pub fn output_parameters(&self) -> impl Iterator<Item = &ProcedureParameter> {
+        self.parameters.iter().filter(|p| p.is_output())
+    }
+}
+
 // Language for stored procedure implementation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ProcedureLanguage {
@@ -126,354 +124,150 @@ pub enum ProcedureLanguage {
     // Native,  // For future Rust-based procedures
 }
 
-// Procedure execution context
-#[derive(Debug, Clone)]
-pub struct ProcedureContext {
-    pub parameters: HashMap<String, String>,
+// Run `procedure` against `executor` with one argument per parameter; the
+// arguments of OUT parameters are ignored, the parameters starting NULL
+pub fn run_procedure(
+    executor: &Executor,
+    procedure: &StoredProcedure,
+    arguments: Vec<Value>,
+) -> Result<ProcedureResult> {
+    if arguments.len() != procedure.parameters.len() {
+        return Err(DbError::InvalidInput(format!(
+            "Procedure {} takes {} arguments, got {}",
+            procedure.name,
+            procedure.parameters.len(),
+            arguments.len()
+        )));
+    }
+    let block = procedure.compile()?;
+
+    let runtime = RuntimeExecutor::with_executor(executor.clone());
+    for (parameter, argument) in procedure.parameters.iter().zip(arguments) {
+        let value = match parameter.mode {
+            ParameterMode::Out => Value::Null,
+            ParameterMode::In | ParameterMode::InOut => parameter.data_type.coerce(argument)?,
+        };
+        runtime.set_variable(&parameter.name, value.into());
+    }
+
+    let outcome = runtime.execute(&block).and_then(|result| {
+        let output_parameters = procedure
+            .output_parameters()
+            .map(|parameter| {
+                let value = runtime.get_variable(&parameter.name)?.to_value()?;
+                Ok((parameter.name.clone(), parameter.data_type.coerce(value)?))
+            })
+            .collect::<Result<_>>()?;
+        Ok(ProcedureResult {
+            output_parameters,
+            rows_affected: result.rows_affected,
+            output_lines: result.output_lines,
+        })
+    });
+    runtime.finish(outcome.is_ok())?;
+    outcome
 }
 
 // Stored procedure manager
 //
-// TODO: Add capacity limit - unbounded procedure storage
-// Recommended: 10,000 max procedures (enterprise databases rarely exceed 1,000)
-// TODO: Add integration with query executor (procedures don't actually execute SQL yet)
+// Creates, drops and runs the procedures in an executor's catalog, as
+// CREATE PROCEDURE, DROP PROCEDURE and CALL do
 pub struct ProcedureManager {
-    // WARNING: Unbounded - can store unlimited procedures
-    // TODO: Replace with BoundedHashMap<String, StoredProcedure> (capacity: 10,000)
-    procedures: Arc<RwLock<HashMap<String, StoredProcedure>>>,
+    executor: Executor,
 }
 
 impl ProcedureManager {
-    pub fn new() -> Self {
-        Self {
-            procedures: Arc::new(RwLock::new(HashMap::new())),
-        }
+    pub fn new(executor: Executor) -> Self {
+        Self { executor }
     }
 
     // Create a new stored procedure
     pub fn create_procedure(&self, procedure: StoredProcedure) -> Result<()> {
-        let mut procedures = self.procedures.write();
+        self.define(procedure, false)
+    }
 
-        if procedures.contains_key(&procedure.name) {
-            return Err(DbError::AlreadyExists(format!(
-                "Procedure '{}' already exists",
-                procedure.name
-            )));
-        }
+    // Create a stored procedure, replacing any of the same name
+    pub fn replace_procedure(&self, procedure: StoredProcedure) -> Result<()> {
+        self.define(procedure, true)
+    }
 
-        procedures.insert(procedure.name.clone(), procedure);
+    fn define(&self, procedure: StoredProcedure, or_replace: bool) -> Result<()> {
+        self.executor.execute(SqlStatement::CreateProcedure {
+            name: procedure.name,
+            parameters: procedure.parameters,
+            body: procedure.body,
+            or_replace,
+        })?;
         Ok(())
     }
 
     // Drop a stored procedure
     pub fn drop_procedure(&self, name: &str) -> Result<()> {
-        let mut procedures = self.procedures.write();
-
-        if procedures.remove(name).is_none() {
-            return Err(DbError::NotFound(format!("Procedure '{}' not found", name)));
-        }
-
+        self.executor.execute(SqlStatement::DropProcedure {
+            name: name.to_string(),
+        })?;
         Ok(())
     }
 
     // Get a stored procedure by name
     pub fn get_procedure(&self, name: &str) -> Result<StoredProcedure> {
-        let procedures = self.procedures.read();
-
-        procedures
-            .get(name)
-            .cloned()
-            .ok_or_else(|| DbError::NotFound(format!("Procedure '{}' not found", name)))
+        self.executor.catalog().get_procedure(name)
     }
 
     // List all stored procedures
     pub fn list_procedures(&self) -> Vec<String> {
-        let procedures = self.procedures.read();
-        procedures.keys().cloned().collect()
-    }
-
-    // Execute a stored procedure
-    pub fn execute_procedure(
-        &self,
-        name: &str,
-        context: &ProcedureContext,
-    ) -> Result<ProcedureResult> {
-        let procedure = self.get_procedure(name)?;
-
-        // Validate parameters
-        self.validate_parameters(&procedure, context)?;
-
-        // Execute based on language (only SQL is currently supported)
-        self.execute_sql_procedure(&procedure, context)
-    }
-
-    // Validate procedure parameters
-    fn validate_parameters(
-        &self,
-        procedure: &StoredProcedure,
-        context: &ProcedureContext,
-    ) -> Result<()> {
-        for param in &procedure.parameters {
-            if param.mode == ParameterMode::In || param.mode == ParameterMode::InOut {
-                if !context.parameters.contains_key(&param.name) {
-                    return Err(DbError::InvalidInput(format!(
-                        "Missing parameter '{}'",
-                        param.name
-                    )));
-                }
-            }
-        }
-        Ok(())
-    }
-
-    // Execute SQL-based procedure
-    //
-    // Production-ready implementation of stored procedure execution with enhanced
-    // error handling, validation, and control flow support.
-    //
-    // This implementation provides:
-    // - Robust parameter substitution with SQL injection prevention
-    // - Statement-by-statement execution tracking
-    // - OUT/INOUT parameter handling
-    // - Error propagation and transaction safety
-    // - Control flow statement recognition (IF/ELSE, WHILE, FOR, etc.)
-    // - Exception handling block detection
-    //
-    // INTEGRATION NOTE: For full production use, this should be integrated with:
-    // - Query execution engine (src/execution/executor.rs)
-    // - Transaction manager (src/transaction/mod.rs)
-    // - SQL parser (src/parser/mod.rs)
-    fn execute_sql_procedure(
-        &self,
-        procedure: &StoredProcedure,
-        context: &ProcedureContext,
-    ) -> Result<ProcedureResult> {
-        // Parse and execute SQL statements in procedure body
-        let mut output_parameters = HashMap::new();
-        let mut rows_affected = 0;
-
-        // Validate procedure body is not empty
-        if procedure.body.trim().is_empty() {
-            return Err(DbError::InvalidInput(format!(
-                "Procedure '{}' has empty body",
-                procedure.name
-            )));
-        }
-
-        // Substitute input parameters into the procedure body with SQL injection prevention
-        let mut body = procedure.body.clone();
-        for (param_name, param_value) in &context.parameters {
-            // Escape single quotes to prevent SQL injection
-            let escaped_value = param_value.replace('\'', "''");
-
-            // Replace parameter references like :param_name or @param_name
-            body = body.replace(&format!(":{}", param_name), &escaped_value);
-            body = body.replace(&format!("@{}", param_name), &escaped_value);
-        }
-
-        log::debug!(
-            "Executing procedure '{}' with substituted body: {}",
-            procedure.name,
-            body
-        );
-
-        // Split body into individual statements (separated by semicolons)
-        // Note: This is a simplified approach; production would use proper SQL parsing
-        let statements: Vec<&str> = body
-            .split(';')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .collect();
-
-        if statements.is_empty() {
-            log::warn!(
-                "Procedure '{}' has no executable statements after parsing",
-                procedure.name
-            );
-            return Ok(ProcedureResult {
-                output_parameters,
-                rows_affected: 0,
-            });
-        }
-
-        // Execute each statement in sequence
-        for (stmt_idx, stmt) in statements.iter().enumerate() {
-            let stmt_upper = stmt.to_uppercase();
-
-            log::trace!("Processing statement {}/{}: {}", stmt_idx + 1, statements.len(), stmt);
-
-            // Handle SELECT INTO statements (assign to output parameters)
-            if stmt_upper.starts_with("SELECT") && stmt_upper.contains("INTO") {
-                // Parse SELECT ... INTO variable pattern
-                // Example: SELECT column INTO :output_var FROM table WHERE ...
-                if let Some(into_pos) = stmt_upper.find("INTO") {
-                    let after_into = stmt[into_pos + 4..].trim();
-
-                    // Extract variable name (until FROM or next whitespace)
-                    let var_end = after_into
-                        .find(|c: char| c.is_whitespace() || c == ',')
-                        .unwrap_or(after_into.len());
-
-                    if var_end == 0 {
-                        return Err(DbError::InvalidInput(format!(
-                            "Invalid SELECT INTO syntax in procedure '{}': missing variable name",
-                            procedure.name
-                        )));
-                    }
-
-                    let var_name = after_into[..var_end]
-                        .trim()
-                        .trim_start_matches(':')
-                        .trim_start_matches('@');
-
-                    log::debug!("SELECT INTO detected for variable: {}", var_name);
-
-                    // Validate this is an OUT or INOUT parameter
-                    let mut found = false;
-                    for param in &procedure.parameters {
-                        if param.name.eq_ignore_ascii_case(var_name)
-                            && (param.mode == ParameterMode::Out
-                                || param.mode == ParameterMode::InOut)
-                        {
-                            // In a full implementation with query engine integration:
-                            // 1. Parse SELECT statement
-                            // 2. Execute query through transaction manager
-                            // 3. Fetch single result row
-                            // 4. Assign to output parameter
-                            // For now, we prepare the statement for execution
-                            output_parameters.insert(param.name.clone(), "<query_result>".to_string());
-                            found = true;
-                            log::debug!("Assigned result to OUT parameter: {}", param.name);
-                        }
-                    }
-
-                    if !found {
-                        return Err(DbError::InvalidInput(format!(
-                            "Variable '{}' in SELECT INTO is not a valid OUT/INOUT parameter in procedure '{}'",
-                            var_name, procedure.name
-                        )));
-                    }
-                }
-            } else if stmt_upper.starts_with("INSERT")
-                || stmt_upper.starts_with("UPDATE")
-                || stmt_upper.starts_with("DELETE")
-            {
-                // DML statements - validate and prepare for execution
-                // In full implementation: execute through query engine and get actual row count
-                rows_affected += 1;
-                log::debug!("DML statement prepared for execution: {}", stmt_upper.split_whitespace().next().unwrap_or("DML"));
-
-            } else if stmt_upper.starts_with("SET") {
-                // Variable assignment (SET @var = value)
-                // In full implementation: parse and track local variables
-                log::trace!("Variable assignment detected (requires local variable support)");
-
-            } else if stmt_upper.starts_with("DECLARE") {
-                // Variable declaration
-                log::trace!("Variable declaration detected");
-
-            } else if stmt_upper.starts_with("IF")
-                || stmt_upper.starts_with("ELSE")
-                || stmt_upper.starts_with("ELSEIF")
-                || stmt_upper.starts_with("END IF") {
-                // Control flow: IF/ELSE blocks
-                log::trace!("Control flow statement detected: IF/ELSE");
-
-            } else if stmt_upper.starts_with("WHILE")
-                || stmt_upper.starts_with("END WHILE") {
-                // Control flow: WHILE loops
-                log::trace!("Control flow statement detected: WHILE");
-
-            } else if stmt_upper.starts_with("FOR")
-                || stmt_upper.starts_with("END FOR") {
-                // Control flow: FOR loops
-                log::trace!("Control flow statement detected: FOR");
-
-            } else if stmt_upper.starts_with("BEGIN")
-                || stmt_upper.starts_with("END") {
-                // Block delimiters
-                log::trace!("Block delimiter detected");
-
-            } else if stmt_upper.starts_with("EXCEPTION")
-                || stmt_upper.starts_with("WHEN") {
-                // Exception handling
-                log::trace!("Exception handling block detected");
-
-            } else if stmt_upper.starts_with("RAISE") {
-                // Raise exception
-                log::trace!("RAISE statement detected");
-
-            } else if !stmt_upper.is_empty() {
-                // Unknown statement - log warning but continue
-                log::warn!("Unrecognized statement in procedure '{}': {}", procedure.name, stmt_upper);
-            }
-        }
-
-        // Ensure all OUT parameters are assigned
-        for param in &procedure.parameters {
-            if param.mode == ParameterMode::Out && !output_parameters.contains_key(&param.name) {
-                log::warn!(
-                    "OUT parameter '{}' was not assigned a value in procedure '{}'",
-                    param.name, procedure.name
-                );
-                // Assign NULL-like placeholder
-                output_parameters.insert(param.name.clone(), String::new());
-            } else if param.mode == ParameterMode::InOut && !output_parameters.contains_key(&param.name) {
-                // Copy INOUT parameters that weren't modified to output
-                if let Some(value) = context.parameters.get(&param.name) {
-                    output_parameters.insert(param.name.clone(), value.clone());
-                }
-            }
-        }
-
-        log::info!(
-            "Procedure '{}' execution completed: {} rows affected, {} output parameters",
-            procedure.name, rows_affected, output_parameters.len()
-        );
-
-        Ok(ProcedureResult {
-            output_parameters,
-            rows_affected,
-        })
+        self.executor.catalog().list_procedures()
     }
-}
 
-impl Default for ProcedureManager {
-    fn default() -> Self {
-        Self::new()
+    // Execute a stored procedure with one argument per parameter
+    pub fn execute_procedure(&self, name: &str, arguments: Vec<Value>) -> Result<ProcedureResult> {
+        self.executor.call_procedure(name, arguments)
     }
 }
 
 // Result of stored procedure execution
 #[derive(Debug, Clone)]
 pub struct ProcedureResult {
-    pub output_parameters: HashMap<String, String>,
+    // Final values of the OUT and IN OUT parameters, in order
+    pub output_parameters: Vec<(String, Value)>,
     pub rows_affected: usize,
+    // Lines written with DBMS_OUTPUT
+    pub output_lines: Vec<String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::catalog::Catalog;
+    use crate::transaction::TransactionManager;
+    use std::sync::Arc;
+
+    fn manager() -> ProcedureManager {
+        ProcedureManager::new(Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        ))
+    }
 
     #[test]
     fn test_create_procedure() -> Result<()> {
-        let pm = ProcedureManager::new();
+        let pm = manager();
 
         let procedure = StoredProcedure {
             name: "calculate_discount".to_string(),
             parameters: vec![
                 ProcedureParameter {
                     name: "price".to_string(),
-                    data_type: "FLOAT".to_string(),
+                    data_type: DataType::Float,
                     mode: ParameterMode::In,
                 },
                 ProcedureParameter {
                     name: "discount".to_string(),
-                    data_type: "FLOAT".to_string(),
+                    data_type: DataType::Float,
                     mode: ParameterMode::Out,
                 },
             ],
-            body: "SELECT price * 0.1 INTO discount;".to_string(),
+            body: "BEGIN discount := price * 0.1; END;".to_string(),
             language: ProcedureLanguage::Sql,
         };
 
@@ -483,17 +277,24 @@ mod tests {
         assert_eq!(procedures.len(), 1);
         assert!(procedures.contains(&"calculate_discount".to_string()));
 
+        let result =
+            pm.execute_procedure("calculate_discount", vec![Value::Integer(250), Value::Null])?;
+        assert_eq!(
+            result.output_parameters,
+            vec![("discount".to_string(), Value::Float(25.0))]
+        );
+
         Ok(())
     }
 
     #[test]
     fn test_drop_procedure() -> Result<()> {
-        let pm = ProcedureManager::new();
+        let pm = manager();
 
         let procedure = StoredProcedure {
             name: "test_proc".to_string(),
             parameters: vec![],
-            body: "".to_string(),
+            body: "BEGIN NULL; END;".to_string(),
             language: ProcedureLanguage::Sql,
         };
 
@@ -508,12 +309,12 @@ mod tests {
 
     #[test]
     fn test_duplicate_procedure() -> Result<()> {
-        let pm = ProcedureManager::new();
+        let pm = manager();
 
         let procedure = StoredProcedure {
             name: "duplicate".to_string(),
             parameters: vec![],
-            body: "".to_string(),
+            body: "BEGIN NULL; END;".to_string(),
             language: ProcedureLanguage::Sql,
         };
 
@@ -524,4 +325,18 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_invalid_body_rejected() {
+        let pm = manager();
+
+        let procedure = StoredProcedure::new(
+            "broken".to_string(),
+            vec![],
+            "BEGIN x := ; END;".to_string(),
+        );
+
+        assert!(pm.create_procedure(procedure).is_err());
+        assert!(pm.list_procedures().is_empty());
+    }
 }
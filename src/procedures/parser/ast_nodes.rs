@@ -58,29 +58,33 @@ pub enum Statement {
         target: String,
         value: Expression,
     },
-    // SQL SELECT INTO statement
+    // Assignment to a field of a record variable: record.field := expression
+    FieldAssignment {
+        record: String,
+        field: String,
+        value: Expression,
+    },
+    // SQL SELECT INTO statement; the query is the statement's text without
+    // its INTO clause, and `from` the first table it reads
     SelectInto {
-        columns: Vec<String>,
+        query: String,
         into_vars: Vec<String>,
         from: String,
-        where_clause: Option<Expression>,
     },
-    // SQL INSERT statement
+    // SQL INSERT statement, kept as its text
     Insert {
         table: String,
-        columns: Vec<String>,
-        values: Vec<Expression>,
+        sql: String,
     },
-    // SQL UPDATE statement
+    // SQL UPDATE statement, kept as its text
     Update {
         table: String,
-        assignments: Vec<(String, Expression)>,
-        where_clause: Option<Expression>,
+        sql: String,
     },
-    // SQL DELETE statement
+    // SQL DELETE statement, kept as its text
     Delete {
         table: String,
-        where_clause: Option<Expression>,
+        sql: String,
     },
     // IF-THEN-ELSIF-ELSE control structure
     If {
@@ -210,6 +214,11 @@ pub enum Expression {
         record: String,
         field: String,
     },
+    // Cursor attribute (e.g., c%NOTFOUND, SQL%ROWCOUNT)
+    CursorAttribute {
+        cursor: String,
+        attribute: CursorAttribute,
+    },
     // Collection element access (e.g., array(i))
     CollectionAccess {
         collection: String,
@@ -277,6 +286,15 @@ pub enum AggregateFunction {
     Variance,
 }
 
+// Cursor attributes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CursorAttribute {
+    Found,
+    NotFound,
+    RowCount,
+    IsOpen,
+}
+
 // Exception handler
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ExceptionHandler {
@@ -3,6 +3,7 @@
 // This module provides tokenization functionality for PL/SQL source code.
 
 use crate::{DbError, Result};
+use std::ops::Range;
 
 // Token types for lexical analysis
 #[derive(Debug, Clone, PartialEq)]
@@ -96,11 +97,27 @@ pub enum Token {
 
 // Tokenize PL/SQL source code
 pub fn tokenize(source: &str) -> Result<Vec<Token>> {
+    Ok(tokenize_spanned(source)?
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect())
+}
+
+// Tokenize PL/SQL source code, pairing every token with the range of
+// character positions it was read from
+pub fn tokenize_spanned(source: &str) -> Result<Vec<(Token, Range<usize>)>> {
     let mut tokens = Vec::new();
+    let mut spans = Vec::new();
     let chars: Vec<char> = source.chars().collect();
     let mut i = 0;
+    let mut token_start = 0;
 
     while i < chars.len() {
+        // The token read last ends here
+        if spans.len() < tokens.len() {
+            spans.push(token_start..i);
+        }
+
         // Skip whitespace
         if chars[i].is_whitespace() {
             i += 1;
@@ -126,14 +143,23 @@ pub fn tokenize(source: &str) -> Result<Vec<Token>> {
             continue;
         }
 
-        // String literals
+        token_start = i;
+
+        // String literals, in which '' stands for a quote
         if chars[i] == '\'' {
             i += 1;
-            let start = i;
-            while i < chars.len() && chars[i] != '\'' {
+            let mut string_val = String::new();
+            while i < chars.len() {
+                if chars[i] == '\'' {
+                    if i + 1 < chars.len() && chars[i + 1] == '\'' {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                string_val.push(chars[i]);
                 i += 1;
             }
-            let string_val: String = chars[start..i].iter().collect();
             tokens.push(Token::StringLit(string_val));
             i += 1;
             continue;
@@ -329,6 +355,10 @@ pub fn tokenize(source: &str) -> Result<Vec<Token>> {
         }
     }
 
+    if spans.len() < tokens.len() {
+        spans.push(token_start..i.min(chars.len()));
+    }
     tokens.push(Token::Eof);
-    Ok(tokens)
+    spans.push(chars.len()..chars.len());
+    Ok(tokens.into_iter().zip(spans).collect())
 }
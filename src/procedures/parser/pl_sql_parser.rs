@@ -67,10 +67,15 @@
 use super::ast_nodes::*;
 use super::lexer::Token;
 use crate::{DbError, Result};
+use std::ops::Range;
 
 // PL/SQL Parser
 pub struct PlSqlParser {
     tokens: Vec<Token>,
+    // Character positions each token was read from, to recover the text of
+    // embedded SQL statements
+    spans: Vec<Range<usize>>,
+    source: Vec<char>,
     current: usize,
 }
 
@@ -78,29 +83,75 @@ impl PlSqlParser {
     pub fn new() -> Self {
         Self {
             tokens: Vec::new(),
+            spans: Vec::new(),
+            source: Vec::new(),
             current: 0,
         }
     }
 
     // Parse a PL/SQL block from source text
     pub fn parse(&mut self, source: &str) -> Result<PlSqlBlock> {
-        self.tokens = super::lexer::tokenize(source)?;
-        self.current = 0;
+        self.load(source)?;
         self.parse_block()
     }
 
+    // Parse the body of a stored procedure, whose declarations follow IS or
+    // AS without a DECLARE keyword
+    pub fn parse_procedure_body(&mut self, source: &str) -> Result<PlSqlBlock> {
+        self.load(source)?;
+        self.match_token(&Token::Declare);
+        let declarations = self.parse_declarations()?;
+        self.parse_body(declarations)
+    }
+
+    // Parse a lone expression, such as the WHEN condition of a trigger
+    pub fn parse_condition(&mut self, source: &str) -> Result<Expression> {
+        self.load(source)?;
+        let expression = self.parse_expression()?;
+        if !self.check(&Token::Eof) {
+            return Err(DbError::SqlParse(format!(
+                "Unexpected {:?} after expression",
+                self.peek()
+            )));
+        }
+        Ok(expression)
+    }
+
+    fn load(&mut self, source: &str) -> Result<()> {
+        let (tokens, spans) = super::lexer::tokenize_spanned(source)?.into_iter().unzip();
+        self.tokens = tokens;
+        self.spans = spans;
+        self.source = source.chars().collect();
+        self.current = 0;
+        Ok(())
+    }
+
     // Parse a complete block (DECLARE...BEGIN...EXCEPTION...END)
     fn parse_block(&mut self) -> Result<PlSqlBlock> {
-        let mut declarations = Vec::new();
-        let mut statements = Vec::new();
-        let mut exception_handlers = Vec::new();
+        let declarations = if self.match_token(&Token::Declare) {
+            self.parse_declarations()?
+        } else {
+            Vec::new()
+        };
+        self.parse_body(declarations)
+    }
 
-        // Optional DECLARE section
-        if self.match_token(&Token::Declare) {
-            while !self.check(&Token::Begin) && !self.check(&Token::Eof) {
+    fn parse_declarations(&mut self) -> Result<Vec<Declaration>> {
+        let mut declarations = Vec::new();
+        while !self.check(&Token::Begin) && !self.check(&Token::Eof) {
+            if self.check(&Token::Cursor) {
+                declarations.push(self.parse_cursor_declaration()?);
+            } else {
                 declarations.push(self.parse_declaration()?);
             }
         }
+        Ok(declarations)
+    }
+
+    // Parse BEGIN...EXCEPTION...END
+    fn parse_body(&mut self, declarations: Vec<Declaration>) -> Result<PlSqlBlock> {
+        let mut statements = Vec::new();
+        let mut exception_handlers = Vec::new();
 
         // BEGIN section
         self.consume(&Token::Begin, "Expected BEGIN")?;
@@ -118,6 +169,10 @@ impl PlSqlParser {
         }
 
         self.consume(&Token::End, "Expected END")?;
+        // The procedure name may follow END
+        if self.check_identifier() {
+            self.advance();
+        }
         self.consume(&Token::Semicolon, "Expected semicolon after END")?;
 
         Ok(PlSqlBlock {
@@ -160,6 +215,24 @@ impl PlSqlParser {
         })
     }
 
+    // Parse a cursor declaration: CURSOR name IS query;
+    fn parse_cursor_declaration(&mut self) -> Result<Declaration> {
+        self.consume(&Token::Cursor, "Expected CURSOR")?;
+        let name = self.consume_identifier("Expected cursor name")?;
+        self.consume(&Token::Is, "Expected IS")?;
+        let start = self.current;
+        self.consume(&Token::Select, "Expected SELECT")?;
+        let query = self.sql_statement(start)?;
+
+        Ok(Declaration {
+            name,
+            data_type: PlSqlType::RefCursor,
+            is_constant: true,
+            initial_value: Some(Expression::Subquery { query }),
+            not_null: false,
+        })
+    }
+
     // Parse a data type
     fn parse_type(&mut self) -> Result<PlSqlType> {
         let type_name = self.consume_identifier("Expected type name")?;
@@ -481,16 +554,23 @@ impl PlSqlParser {
 
     // Parse SELECT INTO statement
     fn parse_select_into_statement(&mut self) -> Result<Statement> {
+        let start = self.current;
         self.consume(&Token::Select, "Expected SELECT")?;
 
-        let mut columns = Vec::new();
-        loop {
-            columns.push(self.consume_identifier("Expected column name")?);
-            if !self.match_token(&Token::Comma) {
-                break;
+        // The select list runs up to an INTO outside parentheses
+        let mut depth = 0;
+        while depth > 0 || !self.check(&Token::Into) {
+            match self.peek() {
+                Token::LeftParen => depth += 1,
+                Token::RightParen => depth -= 1,
+                Token::Semicolon | Token::Eof => {
+                    return Err(DbError::SqlParse("Expected INTO".to_string()))
+                }
+                _ => {}
             }
+            self.advance();
         }
-
+        let select_list = self.text(start, self.current);
         self.consume(&Token::Into, "Expected INTO")?;
 
         let mut into_vars = Vec::new();
@@ -501,113 +581,49 @@ impl PlSqlParser {
             }
         }
 
+        let rest = self.current;
         self.consume(&Token::From, "Expected FROM")?;
         let from = self.consume_identifier("Expected table name")?;
-
-        let where_clause = if self.match_token(&Token::Where) {
-            Some(self.parse_expression()?)
-        } else {
-            None
-        };
-
-        self.consume(&Token::Semicolon, "Expected semicolon")?;
+        let query = format!("{} {}", select_list, self.sql_statement(rest)?);
 
         Ok(Statement::SelectInto {
-            columns,
+            query,
             into_vars,
             from,
-            where_clause,
         })
     }
 
     // Parse INSERT statement
     fn parse_insert_statement(&mut self) -> Result<Statement> {
+        let start = self.current;
         self.consume(&Token::Insert, "Expected INSERT")?;
         self.consume(&Token::Into, "Expected INTO")?;
         let table = self.consume_identifier("Expected table name")?;
+        let sql = self.sql_statement(start)?;
 
-        self.consume(&Token::LeftParen, "Expected '('")?;
-        let mut columns = Vec::new();
-        loop {
-            columns.push(self.consume_identifier("Expected column name")?);
-            if !self.match_token(&Token::Comma) {
-                break;
-            }
-        }
-        self.consume(&Token::RightParen, "Expected ')'")?;
-
-        self.consume(&Token::Values, "Expected VALUES")?;
-        self.consume(&Token::LeftParen, "Expected '('")?;
-        let mut values = Vec::new();
-        loop {
-            values.push(self.parse_expression()?);
-            if !self.match_token(&Token::Comma) {
-                break;
-            }
-        }
-        self.consume(&Token::RightParen, "Expected ')'")?;
-
-        self.consume(&Token::Semicolon, "Expected semicolon")?;
-
-        Ok(Statement::Insert {
-            table,
-            columns,
-            values,
-        })
+        Ok(Statement::Insert { table, sql })
     }
 
     // Parse UPDATE statement
     fn parse_update_statement(&mut self) -> Result<Statement> {
+        let start = self.current;
         self.consume(&Token::Update, "Expected UPDATE")?;
         let table = self.consume_identifier("Expected table name")?;
-
         self.consume(&Token::Set, "Expected SET")?;
+        let sql = self.sql_statement(start)?;
 
-        let mut assignments = Vec::new();
-        loop {
-            let column = self.consume_identifier("Expected column name")?;
-            self.consume(&Token::Equal, "Expected '='")?;
-            let value = self.parse_expression()?;
-            assignments.push((column, value));
-
-            if !self.match_token(&Token::Comma) {
-                break;
-            }
-        }
-
-        let where_clause = if self.match_token(&Token::Where) {
-            Some(self.parse_expression()?)
-        } else {
-            None
-        };
-
-        self.consume(&Token::Semicolon, "Expected semicolon")?;
-
-        Ok(Statement::Update {
-            table,
-            assignments,
-            where_clause,
-        })
+        Ok(Statement::Update { table, sql })
     }
 
     // Parse DELETE statement
     fn parse_delete_statement(&mut self) -> Result<Statement> {
+        let start = self.current;
         self.consume(&Token::Delete, "Expected DELETE")?;
         self.consume(&Token::From, "Expected FROM")?;
         let table = self.consume_identifier("Expected table name")?;
+        let sql = self.sql_statement(start)?;
 
-        let where_clause = if self.match_token(&Token::Where) {
-            Some(self.parse_expression()?)
-        } else {
-            None
-        };
-
-        self.consume(&Token::Semicolon, "Expected semicolon")?;
-
-        Ok(Statement::Delete {
-            table,
-            where_clause,
-        })
+        Ok(Statement::Delete { table, sql })
     }
 
     // Parse OPEN cursor statement
@@ -706,9 +722,24 @@ impl PlSqlParser {
         })
     }
 
-    // Parse assignment or procedure call
+    // Parse assignment, to a variable or a field of a record, or procedure
+    // call, of a procedure that may be qualified by its package
     fn parse_assignment_or_call(&mut self) -> Result<Statement> {
-        let name = self.consume_identifier("Expected identifier")?;
+        let mut name = self.consume_identifier("Expected identifier")?;
+
+        if self.match_token(&Token::Dot) {
+            let field = self.consume_identifier("Expected field name")?;
+            if self.match_token(&Token::Assign) {
+                let value = self.parse_expression()?;
+                self.consume(&Token::Semicolon, "Expected semicolon")?;
+                return Ok(Statement::FieldAssignment {
+                    record: name,
+                    field,
+                    value,
+                });
+            }
+            name = format!("{}.{}", name, field);
+        }
 
         if self.match_token(&Token::Assign) {
             // Assignment
@@ -949,6 +980,14 @@ impl PlSqlParser {
                     self.consume(&Token::RightParen, "Expected ')'")?;
 
                     Ok(Expression::FunctionCall { name, arguments })
+                } else if let Some(attribute) = self.cursor_attribute() {
+                    // Cursor attribute
+                    self.advance();
+                    self.advance();
+                    Ok(Expression::CursorAttribute {
+                        cursor: name,
+                        attribute,
+                    })
                 } else if self.match_token(&Token::Dot) {
                     // Field access
                     let field = self.consume_identifier("Expected field name")?;
@@ -974,6 +1013,50 @@ impl PlSqlParser {
         }
     }
 
+    // The cursor attribute of %FOUND, %NOTFOUND, %ROWCOUNT or %ISOPEN at the
+    // current token
+    fn cursor_attribute(&self) -> Option<CursorAttribute> {
+        if !self.check(&Token::Percent) {
+            return None;
+        }
+        match self.tokens.get(self.current + 1) {
+            Some(Token::Identifier(name)) => match name.to_uppercase().as_str() {
+                "FOUND" => Some(CursorAttribute::Found),
+                "NOTFOUND" => Some(CursorAttribute::NotFound),
+                "ROWCOUNT" => Some(CursorAttribute::RowCount),
+                "ISOPEN" => Some(CursorAttribute::IsOpen),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // The text of an SQL statement from token `start` to the next semicolon,
+    // which is consumed
+    fn sql_statement(&mut self, start: usize) -> Result<String> {
+        while !self.check(&Token::Semicolon) {
+            if self.is_at_end() {
+                return Err(DbError::SqlParse(
+                    "Expected semicolon after SQL statement".to_string(),
+                ));
+            }
+            self.advance();
+        }
+        let text = self.text(start, self.current);
+        self.consume(&Token::Semicolon, "Expected semicolon")?;
+        Ok(text)
+    }
+
+    // The source text of tokens `start` up to `end`
+    fn text(&self, start: usize, end: usize) -> String {
+        if end <= start {
+            return String::new();
+        }
+        self.source[self.spans[start].start..self.spans[end - 1].end]
+            .iter()
+            .collect()
+    }
+
     // Helper methods
     fn peek(&self) -> &Token {
         &self.tokens[self.current]
@@ -2,13 +2,29 @@
 //
 // This module provides the runtime environment for executing PL/SQL blocks,
 // managing execution context, variable bindings, and control flow.
-
+//
+// A runtime created with `with_executor` runs the block's SQL against the
+// database. Each statement is prepared once, with every block variable it
+// names replaced by a parameter taking the variable's value; variables
+// therefore hide columns of the same name, so the two are best named apart.
+// Errors raised while a statement runs become PL/SQL exceptions that the
+// block's handlers can catch.
+
+use crate::common::Value;
+use crate::execution::executor::Executor;
+use crate::execution::prepared::PreparedStatement;
+use crate::execution::QueryResult;
+use crate::parser::SqlParser;
+use crate::procedures::parser::lexer::{tokenize_spanned, Token};
 use crate::procedures::parser::{
-    BinaryOperator, Declaration, ExceptionHandler, ExceptionType, Expression, LiteralValue,
-    PlSqlBlock, Statement, UnaryOperator,
+    BinaryOperator, CursorAttribute, Declaration, ExceptionHandler, ExceptionType, Expression,
+    LiteralValue, PlSqlBlock, PlSqlType, Statement, UnaryOperator,
 };
+use crate::procedures::{run_procedure, StoredProcedure};
+use crate::transaction::SqlTransaction;
 use crate::{DbError, Result};
 use parking_lot::RwLock;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -94,6 +110,55 @@ impl RuntimeValue {
     pub fn is_null(&self) -> bool {
         matches!(self, RuntimeValue::Null)
     }
+
+    // Convert to a database value, to bind to SQL
+    pub fn to_value(&self) -> Result<Value> {
+        Ok(match self {
+            RuntimeValue::Integer(v) => Value::Integer(*v),
+            RuntimeValue::Float(v) => Value::Float(*v),
+            RuntimeValue::String(s) => Value::String(s.clone()),
+            RuntimeValue::Boolean(b) => Value::Boolean(*b),
+            RuntimeValue::Date(d) => {
+                Value::parse_date(d).map_or_else(|| Value::String(d.clone()), Value::Date)
+            }
+            RuntimeValue::Timestamp(t) => {
+                Value::parse_timestamp(t).map_or_else(|| Value::String(t.clone()), Value::Timestamp)
+            }
+            RuntimeValue::Null => Value::Null,
+            RuntimeValue::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(RuntimeValue::to_value)
+                    .collect::<Result<_>>()?,
+            ),
+            RuntimeValue::Cursor(_) | RuntimeValue::Record(_) => {
+                return Err(DbError::Runtime(format!(
+                    "Cannot convert {:?} to a value",
+                    self
+                )))
+            }
+        })
+    }
+}
+
+impl From<Value> for RuntimeValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => RuntimeValue::Null,
+            Value::Boolean(b) => RuntimeValue::Boolean(b),
+            Value::Integer(v) => RuntimeValue::Integer(v),
+            Value::Float(v) => RuntimeValue::Float(v),
+            Value::String(s) => RuntimeValue::String(s),
+            date @ Value::Date(_) => RuntimeValue::Date(date.to_display_string()),
+            timestamp @ Value::Timestamp(_) => {
+                RuntimeValue::Timestamp(timestamp.to_display_string())
+            }
+            Value::Array(items) => {
+                RuntimeValue::Array(items.into_iter().map(RuntimeValue::from).collect())
+            }
+            other => RuntimeValue::String(other.to_display_string()),
+        }
+    }
 }
 
 // Cursor state for runtime
@@ -102,10 +167,28 @@ pub struct CursorState {
     pub name: String,
     pub query: String,
     pub is_open: bool,
+    // Rows fetched so far, the cursor's %ROWCOUNT
     pub current_row: usize,
-    pub rows: Vec<HashMap<String, RuntimeValue>>,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<RuntimeValue>>,
+    // Whether the last fetch returned a row; `None` before the first
+    pub found: Option<bool>,
     bulk_exceptions: Vec<BulkException>,
-    position: i32,
+}
+
+impl CursorState {
+    fn declared(name: &str, query: String) -> Self {
+        Self {
+            name: name.to_string(),
+            query,
+            is_open: false,
+            current_row: 0,
+            columns: Vec::new(),
+            rows: Vec::new(),
+            found: None,
+            bulk_exceptions: Vec::new(),
+        }
+    }
 }
 
 // Bulk exception for FORALL operations
@@ -116,6 +199,110 @@ pub struct BulkException {
     pub error_message: String,
 }
 
+// The database a block's SQL runs against
+//
+// A block run by an executor bound to a transaction runs in it; that
+// transaction is the caller's, so the block may not commit or roll it back.
+// Otherwise the block's SQL runs in a transaction begun when first needed,
+// which COMMIT and ROLLBACK end and `finish` ends with the block.
+pub struct Database {
+    executor: Executor,
+    // The block's own transaction, while one is open
+    transaction: Option<Arc<SqlTransaction>>,
+    // Statements of the block by their text, prepared the first time they run
+    statements: HashMap<String, PreparedStatement>,
+}
+
+impl Database {
+    pub fn new(executor: Executor) -> Self {
+        Self {
+            executor,
+            transaction: None,
+            statements: HashMap::new(),
+        }
+    }
+
+    // Whether the transaction belongs to the caller
+    fn is_shared(&self) -> bool {
+        self.executor.transaction().is_some()
+    }
+
+    fn txn(&mut self) -> Result<Arc<SqlTransaction>> {
+        if let Some(txn) = self.executor.transaction().or(self.transaction.as_ref()) {
+            return Ok(txn.clone());
+        }
+        let txn = self.executor.begin_transaction(None)?;
+        self.transaction = Some(txn.clone());
+        Ok(txn)
+    }
+
+    // An executor running statements in the block's transaction
+    fn executor(&mut self) -> Result<Executor> {
+        let txn = self.txn()?;
+        Ok(self.executor.with_transaction(txn))
+    }
+
+    // Run `sql`, whose `$n` parameters take the values in `params`
+    fn run(&mut self, sql: &str, params: Vec<Value>) -> Result<QueryResult> {
+        let executor = self.executor()?;
+        let prepared = match self.statements.entry(sql.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let statement = SqlParser::new()
+                    .parse(sql)?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| DbError::SqlParse(format!("Empty statement: {}", sql)))?;
+                entry.insert(executor.prepare(statement, Vec::new())?)
+            }
+        };
+        executor.execute_prepared(prepared, params)
+    }
+
+    fn check_owned(&self, command: &str) -> Result<()> {
+        if self.is_shared() {
+            return Err(DbError::InvalidState(format!(
+                "{} is not allowed in a procedure called inside a transaction block",
+                command
+            )));
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.check_owned("COMMIT")?;
+        match self.transaction.take() {
+            Some(txn) => txn.commit(),
+            None => Ok(()),
+        }
+    }
+
+    fn rollback(&mut self) -> Result<()> {
+        self.check_owned("ROLLBACK")?;
+        match self.transaction.take() {
+            Some(txn) => txn.rollback(),
+            None => Ok(()),
+        }
+    }
+
+    fn savepoint(&mut self, name: &str) -> Result<()> {
+        self.txn()?.savepoint(name)
+    }
+
+    fn rollback_to_savepoint(&mut self, name: &str) -> Result<()> {
+        self.txn()?.rollback_to_savepoint(name)
+    }
+
+    // End the block's own transaction, committing it if `commit`
+    fn finish(&mut self, commit: bool) -> Result<()> {
+        match self.transaction.take() {
+            Some(txn) if commit => txn.commit(),
+            Some(txn) => txn.rollback(),
+            None => Ok(()),
+        }
+    }
+}
+
 // Execution context for a PL/SQL block
 pub struct ExecutionContext {
     // Variable bindings (name -> value)
@@ -129,6 +316,8 @@ pub struct ExecutionContext {
     return_value: Option<RuntimeValue>,
     // Exception state
     exception_raised: Option<String>,
+    // The error that raised the exception, when an error did
+    error: Option<DbError>,
     // Loop control flags
     exit_loop: bool,
     continue_loop: bool,
@@ -148,6 +337,10 @@ pub struct ExecutionContext {
     dml_operations: Vec<(String, String, usize)>, // (operation, table, rows)
     // Transaction committed flag
     transaction_committed: bool,
+    // Database the block's SQL runs against, if any
+    database: Option<Database>,
+    // Rows the last SQL statement returned or changed, for SQL%ROWCOUNT
+    sql_rowcount: Option<usize>,
 }
 
 impl ExecutionContext {
@@ -158,6 +351,7 @@ impl ExecutionContext {
             parent: None,
             return_value: None,
             exception_raised: None,
+            error: None,
             exit_loop: false,
             continue_loop: false,
             savepoints: Vec::new(),
@@ -168,6 +362,8 @@ impl ExecutionContext {
             procedure_calls: Vec::new(),
             dml_operations: Vec::new(),
             transaction_committed: false,
+            database: None,
+            sql_rowcount: None,
         }
     }
 
@@ -179,6 +375,7 @@ impl ExecutionContext {
             parent: None, // Simplified - in production would link to parent
             return_value: None,
             exception_raised: None,
+            error: None,
             exit_loop: false,
             continue_loop: false,
             savepoints: Vec::new(),
@@ -189,6 +386,8 @@ impl ExecutionContext {
             procedure_calls: Vec::new(),
             dml_operations: Vec::new(),
             transaction_committed: false,
+            database: None,
+            sql_rowcount: None,
         }
     }
 
@@ -236,6 +435,13 @@ impl ExecutionContext {
     // Raise an exception
     pub fn raise_exception(&mut self, exception: String) {
         self.exception_raised = Some(exception);
+        self.error = None;
+    }
+
+    // Raise the exception `error` maps to
+    pub fn raise_error(&mut self, error: DbError) {
+        self.exception_raised = Some(exception_name(&error));
+        self.error = Some(error);
     }
 
     // Check if exception is raised
@@ -251,6 +457,7 @@ impl ExecutionContext {
     // Clear exception
     pub fn clear_exception(&mut self) {
         self.exception_raised = None;
+        self.error = None;
     }
 
     // Set exit loop flag
@@ -330,20 +537,34 @@ impl ExecutionContext {
         self.output_buffer.push(text.to_string());
     }
 
-    // Transaction management
+    // Transaction management; without a database only savepoint names are
+    // tracked
     pub fn commit_transaction(&mut self) -> Result<()> {
+        if let Some(database) = &mut self.database {
+            database.commit()?;
+        }
         self.transaction_committed = true;
         self.savepoints.clear();
         Ok(())
     }
 
     pub fn rollback_transaction(&mut self) -> Result<()> {
+        if let Some(database) = &mut self.database {
+            database.rollback()?;
+        }
         self.transaction_committed = false;
         self.savepoints.clear();
         Ok(())
     }
 
     pub fn rollback_to_savepoint(&mut self, name: &str) -> Result<()> {
+        if let Some(database) = &mut self.database {
+            database.rollback_to_savepoint(name)?;
+            if let Some(pos) = self.savepoints.iter().position(|s| s == name) {
+                self.savepoints.truncate(pos + 1);
+            }
+            return Ok(());
+        }
         // Find savepoint and remove all savepoints after it
         if let Some(pos) = self.savepoints.iter().position(|s| s == name) {
             self.savepoints.truncate(pos + 1);
@@ -354,49 +575,93 @@ impl ExecutionContext {
     }
 
     pub fn create_savepoint(&mut self, name: &str) -> Result<()> {
+        if let Some(database) = &mut self.database {
+            database.savepoint(name)?;
+        }
         self.savepoints.push(name.to_string());
         Ok(())
     }
 
+    // The database SQL runs against
+    fn database(&mut self) -> Result<&mut Database> {
+        self.database
+            .as_mut()
+            .ok_or_else(|| DbError::InvalidState("No database to run SQL against".to_string()))
+    }
+
+    // Record the rows the last SQL statement returned or changed
+    pub fn set_sql_rowcount(&mut self, rows: usize) {
+        self.sql_rowcount = Some(rows);
+    }
+
     // Cursor management
+    pub fn declare_cursor(&mut self, name: &str, query: String) {
+        self.cursors
+            .insert(name.to_string(), CursorState::declared(name, query));
+    }
+
+    pub fn cursor_query(&self, name: &str) -> Result<String> {
+        self.cursors
+            .get(name)
+            .map(|cursor| cursor.query.clone())
+            .ok_or_else(|| DbError::Runtime(format!("Cursor '{}' not found", name)))
+    }
+
     pub fn open_cursor(
         &mut self,
         name: &str,
-        rows: Vec<HashMap<String, RuntimeValue>>,
+        columns: Vec<String>,
+        rows: Vec<Vec<RuntimeValue>>,
     ) -> Result<()> {
-        let cursor = CursorState {
-            name: name.to_string(),
-            query: String::new(),
-            is_open: true,
-            current_row: 0,
-            rows,
-            bulk_exceptions: Vec::new(),
-            position: 0,
-        };
-        self.cursors.insert(name.to_string(), cursor);
+        let cursor = self
+            .cursors
+            .entry(name.to_string())
+            .or_insert_with(|| CursorState::declared(name, String::new()));
+        if cursor.is_open {
+            return Err(DbError::Runtime(format!(
+                "Cursor '{}' is already open",
+                name
+            )));
+        }
+        cursor.is_open = true;
+        cursor.current_row = 0;
+        cursor.columns = columns;
+        cursor.rows = rows;
+        cursor.found = None;
         Ok(())
     }
 
-    pub fn fetch_cursor(&mut self, name: &str) -> Result<Option<HashMap<String, RuntimeValue>>> {
+    pub fn fetch_cursor(&mut self, name: &str) -> Result<Option<Vec<RuntimeValue>>> {
         if let Some(cursor) = self.cursors.get_mut(name) {
             if !cursor.is_open {
                 return Err(DbError::Runtime(format!("Cursor '{}' is not open", name)));
             }
-            if (cursor.position as usize) < cursor.rows.len() {
-                let row = cursor.rows[cursor.position as usize].clone();
-                cursor.position += 1;
-                Ok(Some(row))
-            } else {
-                Ok(None)
+            let row = cursor.rows.get(cursor.current_row).cloned();
+            cursor.found = Some(row.is_some());
+            if row.is_some() {
+                cursor.current_row += 1;
             }
+            Ok(row)
         } else {
             Err(DbError::Runtime(format!("Cursor '{}' not found", name)))
         }
     }
 
+    // Column names of an open cursor's query
+    pub fn cursor_columns(&self, name: &str) -> Result<Vec<String>> {
+        self.cursors
+            .get(name)
+            .map(|cursor| cursor.columns.clone())
+            .ok_or_else(|| DbError::Runtime(format!("Cursor '{}' not found", name)))
+    }
+
     pub fn close_cursor(&mut self, name: &str) -> Result<()> {
         if let Some(cursor) = self.cursors.get_mut(name) {
+            if !cursor.is_open {
+                return Err(DbError::Runtime(format!("Cursor '{}' is not open", name)));
+            }
             cursor.is_open = false;
+            cursor.rows.clear();
             Ok(())
         } else {
             Err(DbError::Runtime(format!("Cursor '{}' not found", name)))
@@ -407,12 +672,56 @@ impl ExecutionContext {
         self.cursors.get(name).map_or(false, |c| c.is_open)
     }
 
-    pub fn set_cursor_not_found(&mut self, _found: bool) {
-        // This sets the SQL%NOTFOUND implicit cursor attribute
-        // In a full implementation, this would update an implicit cursor state
+    // Value of a cursor attribute; the cursor named SQL is the implicit
+    // cursor of the last SQL statement
+    pub fn cursor_attribute(&self, name: &str, attribute: CursorAttribute) -> Result<RuntimeValue> {
+        let (is_open, found, count) = if name.eq_ignore_ascii_case("SQL") {
+            (
+                false,
+                self.sql_rowcount.map(|rows| rows > 0),
+                self.sql_rowcount,
+            )
+        } else {
+            let cursor = self
+                .cursors
+                .get(name)
+                .ok_or_else(|| DbError::Runtime(format!("Cursor '{}' not found", name)))?;
+            if !cursor.is_open && attribute != CursorAttribute::IsOpen {
+                return Err(DbError::Runtime(format!("Cursor '{}' is not open", name)));
+            }
+            (cursor.is_open, cursor.found, Some(cursor.current_row))
+        };
+
+        let boolean = |value: Option<bool>| value.map_or(RuntimeValue::Null, RuntimeValue::Boolean);
+        Ok(match attribute {
+            CursorAttribute::Found => boolean(found),
+            CursorAttribute::NotFound => boolean(found.map(|found| !found)),
+            CursorAttribute::RowCount => count.map_or(RuntimeValue::Null, |count| {
+                RuntimeValue::Integer(count as i64)
+            }),
+            CursorAttribute::IsOpen => RuntimeValue::Boolean(is_open),
+        })
     }
 }
 
+// The predefined exception an error raises; any other error raises an
+// exception named after its SQLSTATE, which only OTHERS handles
+fn exception_name(error: &DbError) -> String {
+    let name = match error {
+        DbError::ConstraintViolation(msg) if msg.starts_with("Duplicate key") => "DUP_VAL_ON_INDEX",
+        DbError::Execution(msg) | DbError::Runtime(msg) if msg.contains("by zero") => "ZERO_DIVIDE",
+        DbError::InvalidInput(msg)
+            if msg.starts_with("Invalid value") || msg.starts_with("Value ") =>
+        {
+            "VALUE_ERROR"
+        }
+        DbError::Runtime(msg) if msg.starts_with("Cannot convert") => "VALUE_ERROR",
+        DbError::Runtime(msg) if msg.starts_with("Cursor ") => "INVALID_CURSOR",
+        _ => return format!("SQLSTATE {}", error.sqlstate()),
+    };
+    name.to_string()
+}
+
 impl Default for ExecutionContext {
     fn default() -> Self {
         Self::new()
@@ -432,6 +741,38 @@ impl RuntimeExecutor {
         }
     }
 
+    // A runtime running the block's SQL through `executor`
+    pub fn with_executor(executor: Executor) -> Self {
+        let mut context = ExecutionContext::new();
+        context.database = Some(Database::new(executor));
+        Self {
+            context: Arc::new(RwLock::new(context)),
+        }
+    }
+
+    // Set a variable before the block runs, such as a parameter
+    pub fn set_variable(&self, name: &str, value: RuntimeValue) {
+        self.context.write().set_variable(name.to_string(), value);
+    }
+
+    // Read a variable after the block ran
+    pub fn get_variable(&self, name: &str) -> Result<RuntimeValue> {
+        self.context.read().get_variable(name)
+    }
+
+    // Evaluate an expression over the variables set so far
+    pub fn evaluate(&self, expr: &Expression) -> Result<RuntimeValue> {
+        self.evaluate_expression(&self.context.read(), expr)
+    }
+
+    // End the block's own transaction, committing it if `commit`
+    pub fn finish(&self, commit: bool) -> Result<()> {
+        match &mut self.context.write().database {
+            Some(database) => database.finish(commit),
+            None => Ok(()),
+        }
+    }
+
     // Execute a PL/SQL block
     pub fn execute(&self, block: &PlSqlBlock) -> Result<ExecutionResult> {
         let mut ctx = self.context.write();
@@ -441,9 +782,11 @@ impl RuntimeExecutor {
             self.execute_declaration(&mut ctx, decl)?;
         }
 
-        // Execute statements
+        // Execute statements; an error raises the exception it maps to
         for stmt in &block.statements {
-            self.execute_statement(&mut ctx, stmt)?;
+            if let Err(error) = self.execute_statement(&mut ctx, stmt) {
+                ctx.raise_error(error);
+            }
 
             // Check for exceptions
             if ctx.has_exception() {
@@ -474,6 +817,13 @@ impl RuntimeExecutor {
 
     // Execute a declaration
     fn execute_declaration(&self, ctx: &mut ExecutionContext, decl: &Declaration) -> Result<()> {
+        if let (PlSqlType::RefCursor, Some(Expression::Subquery { query })) =
+            (&decl.data_type, &decl.initial_value)
+        {
+            ctx.declare_cursor(&decl.name, query.clone());
+            return Ok(());
+        }
+
         let value = if let Some(init_expr) = &decl.initial_value {
             self.evaluate_expression(ctx, init_expr)?
         } else {
@@ -500,6 +850,23 @@ impl RuntimeExecutor {
                 ctx.set_variable(target.clone(), val);
             }
 
+            Statement::FieldAssignment {
+                record,
+                field,
+                value,
+            } => {
+                let val = self.evaluate_expression(ctx, value)?;
+                match ctx.variables.get_mut(record) {
+                    Some(RuntimeValue::Record(fields)) => {
+                        fields.insert(field.clone(), val);
+                    }
+                    Some(_) => {
+                        return Err(DbError::Runtime(format!("'{}' is not a record", record)))
+                    }
+                    None => return Err(ExecutionContext::variable_not_found(record)),
+                }
+            }
+
             Statement::If {
                 condition,
                 then_block,
@@ -671,9 +1038,7 @@ impl RuntimeExecutor {
             }
 
             Statement::Commit => {
-                // Integrate with transaction manager
-                // Mark the current transaction as committed
-                let _ = ctx.commit_transaction();
+                ctx.commit_transaction()?;
 
                 if ctx.is_debug() {
                     ctx.add_output("COMMIT executed".to_string());
@@ -681,7 +1046,6 @@ impl RuntimeExecutor {
             }
 
             Statement::Rollback { to_savepoint } => {
-                // Integrate with transaction manager
                 if let Some(ref sp) = to_savepoint {
                     // Rollback to specific savepoint
                     ctx.rollback_to_savepoint(sp)?;
@@ -690,7 +1054,7 @@ impl RuntimeExecutor {
                     }
                 } else {
                     // Full rollback
-                    let _ = ctx.rollback_transaction();
+                    ctx.rollback_transaction()?;
                     if ctx.is_debug() {
                         ctx.add_output("ROLLBACK executed".to_string());
                     }
@@ -698,8 +1062,7 @@ impl RuntimeExecutor {
             }
 
             Statement::Savepoint { name } => {
-                // Integrate with transaction manager
-                let _ = ctx.create_savepoint(&name);
+                ctx.create_savepoint(name)?;
 
                 if ctx.is_debug() {
                     ctx.add_output(format!("SAVEPOINT {} created", name));
@@ -713,8 +1076,7 @@ impl RuntimeExecutor {
                     arg_values.push(self.evaluate_expression(ctx, arg)?);
                 }
 
-                // Integrate with procedure manager to call other procedures
-                // Check if this is a built-in procedure
+                // Built-in procedures first, then stored ones
                 match name.to_uppercase().as_str() {
                     "DBMS_OUTPUT.PUT_LINE" => {
                         if let Some(arg) = arg_values.first() {
@@ -740,9 +1102,12 @@ impl RuntimeExecutor {
                             error_code, error_msg
                         )));
                     }
+                    _ if ctx.database.is_some() => {
+                        self.call_procedure(ctx, name, arguments, arg_values)?;
+                    }
                     _ => {
                         // Store the call for later execution by the procedure manager
-                        ctx.record_procedure_call(&name, arg_values);
+                        ctx.record_procedure_call(name, arg_values);
 
                         if ctx.is_debug() {
                             ctx.add_output(format!("CALL {}(...)", name));
@@ -756,163 +1121,48 @@ impl RuntimeExecutor {
             }
 
             Statement::SelectInto {
-                columns,
-                into_vars,
-                from,
-                where_clause,
+                query, into_vars, ..
             } => {
-                // Integrate with SQL executor
-                // Build and execute the SELECT statement
-                let where_str = if let Some(ref wc) = where_clause {
-                    format!(" WHERE {:?}", wc)
-                } else {
-                    String::new()
-                };
-
-                if ctx.is_debug() {
-                    ctx.add_output(format!(
-                        "SELECT {} INTO {:?} FROM {}{}",
-                        columns.join(", "),
-                        into_vars,
-                        from,
-                        where_str
-                    ));
-                }
-
-                // Execute the query and fetch one row
-                // In a full implementation, this would call the SQL executor
-                // and fetch the actual values
-
-                // For demonstration, handle some special cases
-                if columns.len() == into_vars.len() {
-                    // Check if any column is a simple expression we can evaluate
-                    for (i, col) in columns.iter().enumerate() {
-                        if let Some(var_name) = into_vars.get(i) {
-                            // Try to evaluate as expression (e.g., "COUNT(*)" or literal)
-                            let value = if col.to_uppercase().starts_with("COUNT") {
-                                RuntimeValue::Integer(0) // Placeholder
-                            } else if col.to_uppercase().starts_with("SUM") {
-                                RuntimeValue::Float(0.0) // Placeholder
-                            } else if col.to_uppercase() == "SYSDATE"
-                                || col.to_uppercase() == "CURRENT_DATE"
-                            {
-                                RuntimeValue::Date(
-                                    chrono::Utc::now().format("%Y-%m-%d").to_string(),
-                                )
-                            } else if col.to_uppercase() == "SYSTIMESTAMP"
-                                || col.to_uppercase() == "CURRENT_TIMESTAMP"
-                            {
-                                RuntimeValue::Timestamp(
-                                    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-                                )
-                            } else {
-                                // Default to NULL for unknown columns
-                                RuntimeValue::Null
-                            };
-                            ctx.set_variable(var_name.clone(), value);
-                        }
+                let result = self.run_sql(ctx, query)?;
+                ctx.set_sql_rowcount(result.rows.len());
+                let mut rows = result.rows.into_iter();
+                let row = match (rows.next(), rows.next()) {
+                    (Some(row), None) => row,
+                    (None, _) => {
+                        ctx.raise_exception("NO_DATA_FOUND".to_string());
+                        return Ok(());
                     }
-                } else {
-                    // Mismatched column count - set all to NULL
-                    for var in into_vars {
-                        ctx.set_variable(var.clone(), RuntimeValue::Null);
+                    (Some(_), Some(_)) => {
+                        ctx.raise_exception("TOO_MANY_ROWS".to_string());
+                        return Ok(());
                     }
-                }
-            }
-
-            Statement::Insert {
-                table,
-                columns,
-                values,
-            } => {
-                // Integrate with SQL executor
-                // Build and execute the INSERT statement
-                let mut evaluated_values = Vec::new();
-                for val_expr in values {
-                    let val = self.evaluate_expression(ctx, val_expr)?;
-                    evaluated_values.push(val);
-                }
-
-                if ctx.is_debug() {
-                    ctx.add_output(format!(
-                        "INSERT INTO {} ({}) VALUES ({:?})",
-                        table,
-                        columns.join(", "),
-                        evaluated_values
-                    ));
-                }
-
-                // Record the DML operation and increment rows affected
-                ctx.record_dml_operation("INSERT", table, 1);
-                ctx.increment_rows_affected(1);
-            }
-
-            Statement::Update {
-                table,
-                assignments,
-                where_clause,
-            } => {
-                // Integrate with SQL executor
-                // Build and execute the UPDATE statement
-                let mut set_clauses = Vec::new();
-                for (col, val_expr) in assignments {
-                    let val = self.evaluate_expression(ctx, val_expr)?;
-                    set_clauses.push(format!("{} = {:?}", col, val));
-                }
-
-                let where_str = if let Some(ref wc) = where_clause {
-                    format!(" WHERE {:?}", wc)
-                } else {
-                    String::new()
                 };
+                self.bind_row(ctx, into_vars, &result.columns, row)?;
 
                 if ctx.is_debug() {
-                    ctx.add_output(format!(
-                        "UPDATE {} SET {}{}",
-                        table,
-                        set_clauses.join(", "),
-                        where_str
-                    ));
+                    ctx.add_output(format!("SELECT INTO {:?}", into_vars));
                 }
-
-                // Record the DML operation - in production would return actual rows affected
-                let rows = 1; // Placeholder - would be actual count from executor
-                ctx.record_dml_operation("UPDATE", table, rows);
-                ctx.increment_rows_affected(rows);
             }
 
-            Statement::Delete {
-                table,
-                where_clause,
-            } => {
-                // Integrate with SQL executor
-                let where_str = if let Some(ref wc) = where_clause {
-                    format!(" WHERE {:?}", wc)
-                } else {
-                    String::new()
-                };
+            Statement::Insert { table, sql } => {
+                self.run_dml(ctx, "INSERT", table, sql)?;
+            }
 
-                if ctx.is_debug() {
-                    ctx.add_output(format!("DELETE FROM {}{}", table, where_str));
-                }
+            Statement::Update { table, sql } => {
+                self.run_dml(ctx, "UPDATE", table, sql)?;
+            }
 
-                // Record the DML operation - in production would return actual rows affected
-                let rows = 1; // Placeholder - would be actual count from executor
-                ctx.record_dml_operation("DELETE", table, rows);
-                ctx.increment_rows_affected(rows);
+            Statement::Delete { table, sql } => {
+                self.run_dml(ctx, "DELETE", table, sql)?;
             }
 
             Statement::OpenCursor { cursor, arguments } => {
-                // Implement cursor opening
-                // Evaluate cursor arguments
-                let mut arg_values = Vec::new();
-                for arg in arguments {
-                    arg_values.push(self.evaluate_expression(ctx, arg)?);
+                if !arguments.is_empty() {
+                    return Err(DbError::NotImplemented(
+                        "Cursor parameters are not supported".to_string(),
+                    ));
                 }
-
-                // Open the cursor in the context with empty result set
-                // In a full implementation, this would execute the cursor query with the arguments
-                ctx.open_cursor(cursor, Vec::new())?;
+                self.open_cursor(ctx, cursor)?;
 
                 if ctx.is_debug() {
                     ctx.add_output(format!("OPEN cursor {}", cursor));
@@ -920,28 +1170,16 @@ impl RuntimeExecutor {
             }
 
             Statement::FetchCursor { cursor, into_vars } => {
-                // Implement cursor fetching
-                // Fetch the next row from the cursor
-                let row = ctx.fetch_cursor(cursor)?;
-
-                if let Some(row_data) = row {
-                    // Bind fetched values to variables
-                    // Since row_data is a HashMap, we need to iterate over its values
-                    let values: Vec<RuntimeValue> = row_data.values().cloned().collect();
-                    for (i, var_name) in into_vars.iter().enumerate() {
-                        let value = values.get(i).cloned().unwrap_or(RuntimeValue::Null);
-                        ctx.set_variable(var_name.clone(), value);
-                    }
-                } else {
-                    // No more rows - cursor is exhausted
-                    // Set cursor %NOTFOUND to true
-                    ctx.set_cursor_not_found(true);
-
-                    // Set variables to NULL
-                    for var_name in into_vars {
-                        ctx.set_variable(var_name.clone(), RuntimeValue::Null);
-                    }
+                if let Some(row) = ctx.fetch_cursor(cursor)? {
+                    let columns = ctx.cursor_columns(cursor)?;
+                    let row = row
+                        .iter()
+                        .map(RuntimeValue::to_value)
+                        .collect::<Result<Vec<_>>>()?;
+                    self.bind_row(ctx, into_vars, &columns, row)?;
                 }
+                // Past the last row the variables keep their values, and
+                // %NOTFOUND tells the loop to stop
 
                 if ctx.is_debug() {
                     ctx.add_output(format!("FETCH {} INTO {:?}", cursor, into_vars));
@@ -949,7 +1187,6 @@ impl RuntimeExecutor {
             }
 
             Statement::CloseCursor { cursor } => {
-                // Implement cursor closing
                 ctx.close_cursor(cursor)?;
 
                 if ctx.is_debug() {
@@ -1006,11 +1243,11 @@ impl RuntimeExecutor {
                 cursor,
                 statements,
             } => {
-                // Implement cursor FOR loops
                 // Implicitly open the cursor if not already open
                 if !ctx.is_cursor_open(cursor) {
-                    ctx.open_cursor(cursor, Vec::new())?;
+                    self.open_cursor(ctx, cursor)?;
                 }
+                let columns = ctx.cursor_columns(cursor)?;
 
                 // Iterate through all rows
                 loop {
@@ -1019,8 +1256,8 @@ impl RuntimeExecutor {
 
                     if let Some(row_data) = row {
                         // Create a record variable with the row data
-                        // The row_data is already a HashMap<String, RuntimeValue>, so use it directly
-                        ctx.set_variable(record.clone(), RuntimeValue::Record(row_data));
+                        let fields = columns.iter().cloned().zip(row_data).collect();
+                        ctx.set_variable(record.clone(), RuntimeValue::Record(fields));
 
                         // Execute loop body
                         for stmt in statements {
@@ -1057,6 +1294,174 @@ impl RuntimeExecutor {
         Ok(())
     }
 
+    // Run `sql` with the block variables it names bound as parameters
+    fn run_sql(&self, ctx: &mut ExecutionContext, sql: &str) -> Result<QueryResult> {
+        let (sql, params) = self.bind_sql(ctx, sql)?;
+        ctx.database()?.run(&sql, params)
+    }
+
+    fn run_dml(
+        &self,
+        ctx: &mut ExecutionContext,
+        operation: &str,
+        table: &str,
+        sql: &str,
+    ) -> Result<()> {
+        let rows = self.run_sql(ctx, sql)?.rows_affected;
+        ctx.set_sql_rowcount(rows);
+        ctx.record_dml_operation(operation, table, rows);
+
+        if ctx.is_debug() {
+            ctx.add_output(format!("{} {}: {} rows", operation, table, rows));
+        }
+        Ok(())
+    }
+
+    // Replace each block variable `sql` names with a parameter taking its
+    // value, and each `record.field` of a record variable likewise. Names
+    // qualified by a table or called as functions are left alone.
+    fn bind_sql(&self, ctx: &ExecutionContext, sql: &str) -> Result<(String, Vec<Value>)> {
+        let chars: Vec<char> = sql.chars().collect();
+        let tokens = tokenize_spanned(sql)?;
+        let mut bound = String::new();
+        let mut params = Vec::new();
+        let mut copied = 0;
+        let mut i = 0;
+        while i < tokens.len() {
+            let (token, span) = &tokens[i];
+            let name = match token {
+                Token::Identifier(name) => name,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let qualified = i > 0 && tokens[i - 1].0 == Token::Dot;
+            let next = tokens.get(i + 1).map(|(token, _)| token);
+            if qualified || next == Some(&Token::LeftParen) {
+                i += 1;
+                continue;
+            }
+
+            let (value, end) = match (ctx.variables.get(name), next) {
+                (Some(RuntimeValue::Record(fields)), Some(Token::Dot)) => match tokens.get(i + 2) {
+                    Some((Token::Identifier(field), field_span)) => match fields.get(field) {
+                        Some(value) => {
+                            i += 2;
+                            (value, field_span.end)
+                        }
+                        None => {
+                            return Err(DbError::Runtime(format!("Field '{}' not found", field)))
+                        }
+                    },
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                },
+                (Some(RuntimeValue::Record(_) | RuntimeValue::Cursor(_)), _) | (None, _) => {
+                    i += 1;
+                    continue;
+                }
+                (Some(value), _) => (value, span.end),
+            };
+            params.push(value.to_value()?);
+            bound.extend(&chars[copied..span.start]);
+            bound.push_str(&format!("${}", params.len()));
+            copied = end;
+            i += 1;
+        }
+        bound.extend(&chars[copied.min(chars.len())..]);
+        Ok((bound, params))
+    }
+
+    // Assign a fetched row to `into_vars`, by position, or field by field to
+    // a single record variable
+    fn bind_row(
+        &self,
+        ctx: &mut ExecutionContext,
+        into_vars: &[String],
+        columns: &[String],
+        row: Vec<Value>,
+    ) -> Result<()> {
+        let is_record = into_vars.len() == 1
+            && matches!(
+                ctx.variables.get(&into_vars[0]),
+                Some(RuntimeValue::Record(_))
+            );
+        if is_record || (into_vars.len() == 1 && columns.len() > 1) {
+            let fields = columns
+                .iter()
+                .cloned()
+                .zip(row.into_iter().map(RuntimeValue::from))
+                .collect();
+            ctx.set_variable(into_vars[0].clone(), RuntimeValue::Record(fields));
+            return Ok(());
+        }
+
+        if into_vars.len() != row.len() {
+            return Err(DbError::Runtime(format!(
+                "Query returns {} columns for {} variables",
+                row.len(),
+                into_vars.len()
+            )));
+        }
+        for (var, value) in into_vars.iter().zip(row) {
+            ctx.set_variable(var.clone(), RuntimeValue::from(value));
+        }
+        Ok(())
+    }
+
+    // Run a cursor's query and open it over the rows
+    fn open_cursor(&self, ctx: &mut ExecutionContext, cursor: &str) -> Result<()> {
+        if ctx.is_cursor_open(cursor) {
+            return Err(DbError::Runtime(format!(
+                "Cursor '{}' is already open",
+                cursor
+            )));
+        }
+        let query = ctx.cursor_query(cursor)?;
+        let result = self.run_sql(ctx, &query)?;
+        let rows = result
+            .rows
+            .into_iter()
+            .map(|row| row.into_iter().map(RuntimeValue::from).collect())
+            .collect();
+        ctx.open_cursor(cursor, result.columns, rows)
+    }
+
+    // Call a stored procedure in the block's transaction, assigning its OUT
+    // parameters to the variables passed for them
+    fn call_procedure(
+        &self,
+        ctx: &mut ExecutionContext,
+        name: &str,
+        arguments: &[Expression],
+        values: Vec<RuntimeValue>,
+    ) -> Result<()> {
+        let database = ctx.database()?;
+        let executor = database.executor()?;
+        let procedure: StoredProcedure = executor.catalog().get_procedure(name)?;
+        let values = values
+            .iter()
+            .map(RuntimeValue::to_value)
+            .collect::<Result<Vec<_>>>()?;
+        let result = run_procedure(&executor, &procedure, values)?;
+
+        for (output, value) in result.output_parameters {
+            let position = procedure
+                .parameters
+                .iter()
+                .position(|param| param.name == output);
+            if let Some(Expression::Variable(var)) = position.and_then(|p| arguments.get(p)) {
+                ctx.set_variable(var.clone(), RuntimeValue::from(value));
+            }
+        }
+        ctx.increment_rows_affected(result.rows_affected);
+        ctx.output_buffer.extend(result.output_lines);
+        Ok(())
+    }
+
     // Evaluate an expression
     fn evaluate_expression(
         &self,
@@ -1095,6 +1500,10 @@ impl RuntimeExecutor {
                 self.call_function(name, arg_values)
             }
 
+            Expression::CursorAttribute { cursor, attribute } => {
+                ctx.cursor_attribute(cursor, *attribute)
+            }
+
             Expression::FieldAccess { record, field } => {
                 let record_val = ctx.get_variable(record)?;
                 if let RuntimeValue::Record(fields) = record_val {
@@ -1367,7 +1776,12 @@ impl RuntimeExecutor {
             };
 
             if matches {
+                let message = match &ctx.error {
+                    Some(error) => error.to_string(),
+                    None => exception_name.to_string(),
+                };
                 ctx.clear_exception();
+                ctx.set_variable("SQLERRM".to_string(), RuntimeValue::String(message));
                 for stmt in &handler.statements {
                     self.execute_statement(ctx, stmt)?;
                 }
@@ -1375,11 +1789,11 @@ impl RuntimeExecutor {
             }
         }
 
-        // Exception not handled - propagate it
-        Err(DbError::Runtime(format!(
-            "Unhandled exception: {}",
-            exception_name
-        )))
+        // Exception not handled - propagate it, as the error that raised it
+        // when there was one
+        Err(ctx.error.take().unwrap_or_else(|| {
+            DbError::Runtime(format!("Unhandled exception: {}", exception_name))
+        }))
     }
 }
 
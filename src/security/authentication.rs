@@ -18,6 +18,8 @@
 //
 // ### Fully Implemented:
 // - Local username/password authentication with Argon2id hashing
+// - SCRAM-SHA-256 verifiers (RFC 5802/7677) for wire protocols that never
+//   send the password itself
 // - TOTP-based MFA with RFC 6238 compliance (HMAC-SHA1, 30s windows, ±1 window skew)
 // - Backup codes for MFA recovery
 // - Session management with timeout and activity tracking
@@ -43,11 +45,16 @@ use hmac::{Hmac, Mac};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use sha1::Sha1;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 type HmacSha1 = Hmac<Sha1>;
+type HmacSha256 = Hmac<Sha256>;
+
+// PBKDF2 iteration count for new SCRAM verifiers, PostgreSQL's default
+pub const SCRAM_ITERATIONS: u32 = 4096;
 
 // User identifier
 pub type UserId = String;
@@ -137,10 +144,85 @@ pub struct UserAccount {
     pub mfa_backup_codes: Vec<String>,
     // Password history (hashes)
     pub password_history: Vec<String>,
+    // SCRAM-SHA-256 verifier, derived whenever the password is set
+    #[serde(default)]
+    pub scram: Option<ScramCredentials>,
     // Account metadata
     pub metadata: HashMap<String, String>,
 }
 
+// Stored SCRAM-SHA-256 verifier (RFC 5802 section 3); the password itself
+// cannot be recovered from it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScramCredentials {
+    pub salt: Vec<u8>,
+    pub iterations: u32,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+}
+
+impl ScramCredentials {
+    // Derive a verifier for a password with a fresh random salt
+    pub fn generate(password: &str) -> Self {
+        let salt: [u8; 16] = rand::random();
+        Self::derive(password, &salt, SCRAM_ITERATIONS)
+    }
+
+    // Derive the verifier for a password, salt and iteration count
+    pub fn derive(password: &str, salt: &[u8], iterations: u32) -> Self {
+        let salted_password = pbkdf2_sha256(password.as_bytes(), salt, iterations);
+        let client_key = hmac_sha256(&salted_password, b"Client Key");
+        Self {
+            salt: salt.to_vec(),
+            iterations,
+            stored_key: Sha256::digest(client_key).to_vec(),
+            server_key: hmac_sha256(&salted_password, b"Server Key").to_vec(),
+        }
+    }
+
+    // Check a client proof over the exchange's AuthMessage: the proof XOR
+    // the client signature must hash to the stored key
+    pub fn verify_client_proof(&self, auth_message: &[u8], proof: &[u8]) -> bool {
+        let signature = hmac_sha256(&self.stored_key, auth_message);
+        if proof.len() != signature.len() {
+            return false;
+        }
+        let client_key: Vec<u8> = proof.iter().zip(signature).map(|(p, s)| p ^ s).collect();
+        constant_time_eq(&Sha256::digest(&client_key), &self.stored_key)
+    }
+
+    // Server signature the client uses to authenticate the server
+    pub fn server_signature(&self, auth_message: &[u8]) -> Vec<u8> {
+        hmac_sha256(&self.server_key, auth_message).to_vec()
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    // HMAC accepts keys of any length
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+// PBKDF2-HMAC-SHA256 with a single output block, SCRAM's Hi() function
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut block = salt.to_vec();
+    block.extend_from_slice(&1u32.to_be_bytes());
+    let mut u = hmac_sha256(password, &block);
+    let mut result = u;
+    for _ in 1..iterations {
+        u = hmac_sha256(password, &u);
+        for (r, b) in result.iter_mut().zip(u) {
+            *r ^= b;
+        }
+    }
+    result
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 // Account status
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum AccountStatus {
@@ -401,6 +483,7 @@ impl AuthenticationManager {
             mfa_secret: None,
             mfa_backup_codes: Vec::new(),
             password_history: vec![password_hash],
+            scram: Some(ScramCredentials::generate(&password)),
             metadata: HashMap::new(),
         };
 
@@ -419,6 +502,45 @@ impl AuthenticationManager {
 
     // Authenticate a user with username and password
     pub fn login(&self, credentials: LoginCredentials) -> Result<LoginResult> {
+        let password = credentials.password.clone();
+        self.authenticate(credentials, |user| {
+            self.verify_password(&password, &user.password_hash)
+        })
+    }
+
+    // SCRAM verifier of a user, if the user exists and has one
+    pub fn scram_credentials(&self, username: &str) -> Option<ScramCredentials> {
+        self.users
+            .read()
+            .values()
+            .find(|u| u.username == username)
+            .and_then(|u| u.scram.clone())
+    }
+
+    // Authenticate a user whose SCRAM exchange has completed; `verify`
+    // checks the client proof against the stored verifier. Lockout, account
+    // status and MFA are handled as for a password login.
+    pub fn login_scram(
+        &self,
+        username: &str,
+        client_ip: Option<String>,
+        verify: impl FnOnce(&ScramCredentials) -> bool,
+    ) -> Result<LoginResult> {
+        let credentials = LoginCredentials {
+            username: username.to_string(),
+            password: String::new(),
+            mfa_code: None,
+            client_ip,
+            user_agent: None,
+        };
+        self.authenticate(credentials, |user| Ok(user.scram.as_ref().is_some_and(verify)))
+    }
+
+    fn authenticate(
+        &self,
+        credentials: LoginCredentials,
+        verify: impl FnOnce(&UserAccount) -> Result<bool>,
+    ) -> Result<LoginResult> {
         // Check if account is locked out from failed attempts
         if self.is_locked_out(&credentials.username) {
             let locked_until = self.get_lockout_expiration(&credentials.username);
@@ -459,7 +581,7 @@ impl AuthenticationManager {
         }
 
         // Verify password
-        if !self.verify_password(&credentials.password, &user.password_hash)? {
+        if !verify(user)? {
             user.failed_login_attempts += 1;
             self.record_failed_login(&credentials.username);
 
@@ -604,6 +726,7 @@ impl AuthenticationManager {
 
         // Update password
         user.password_hash = new_hash.clone();
+        user.scram = Some(ScramCredentials::generate(new_password));
         user.last_password_change = current_timestamp();
 
         // Update password history
@@ -1091,4 +1214,47 @@ mod tests {
         let result = manager.login(credentials).unwrap();
         assert!(matches!(result, LoginResult::Success { .. }));
     }
+
+    #[test]
+    fn test_scram_verifier() {
+        // RFC 7677 section 3 example exchange
+        let credentials = ScramCredentials::derive(
+            "pencil",
+            &general_purpose::STANDARD.decode("W22ZaJ0SNY7soEsUEjb6gQ==").unwrap(),
+            4096,
+        );
+        let auth_message = "n=user,r=rOprNGfwEbeRWgbNEkqO,\
+            r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096,\
+            c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0";
+        let proof = general_purpose::STANDARD
+            .decode("dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=")
+            .unwrap();
+
+        assert!(credentials.verify_client_proof(auth_message.as_bytes(), &proof));
+        assert!(!credentials.verify_client_proof(auth_message.as_bytes(), &proof[1..]));
+        assert_eq!(
+            general_purpose::STANDARD.encode(credentials.server_signature(auth_message.as_bytes())),
+            "6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="
+        );
+    }
+
+    #[test]
+    fn test_scram_login() {
+        let manager = AuthenticationManager::new();
+        let user_id = manager
+            .create_user("scramtest".to_string(), "ScramTest123!".to_string(), None)
+            .unwrap();
+        manager.users().write().get_mut(&user_id).unwrap().status = AccountStatus::Active;
+
+        let stored = manager.scram_credentials("scramtest").unwrap();
+        let derived = ScramCredentials::derive("ScramTest123!", &stored.salt, stored.iterations);
+        assert_eq!(stored, derived);
+
+        let result = manager
+            .login_scram("scramtest", None, |c| c.stored_key == derived.stored_key)
+            .unwrap();
+        assert!(matches!(result, LoginResult::Success { .. }));
+        let result = manager.login_scram("scramtest", None, |_| false).unwrap();
+        assert!(matches!(result, LoginResult::InvalidCredentials));
+    }
 }
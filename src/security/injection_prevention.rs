@@ -979,6 +979,7 @@ pub enum SqlOperation {
     AlterTable,
     DropTable,
     CreateIndex,
+    DropIndex,
     CreateView,
 }
 
@@ -996,6 +997,7 @@ impl QueryWhitelister {
         allowed_operations.insert(SqlOperation::AlterTable);
         allowed_operations.insert(SqlOperation::DropTable);
         allowed_operations.insert(SqlOperation::CreateIndex);
+        allowed_operations.insert(SqlOperation::DropIndex);
         allowed_operations.insert(SqlOperation::CreateView);
 
         let mut allowed_functions = HashSet::new();
@@ -1048,8 +1050,12 @@ impl QueryWhitelister {
             SqlOperation::AlterTable
         } else if upper_sql.starts_with("DROP TABLE") {
             SqlOperation::DropTable
-        } else if upper_sql.starts_with("CREATE INDEX") {
+        } else if upper_sql.starts_with("CREATE INDEX")
+            || upper_sql.starts_with("CREATE UNIQUE INDEX")
+        {
             SqlOperation::CreateIndex
+        } else if upper_sql.starts_with("DROP INDEX") {
+            SqlOperation::DropIndex
         } else if upper_sql.starts_with("CREATE VIEW") {
             SqlOperation::CreateView
         } else {
@@ -1257,5 +1263,9 @@ mod tests {
             .validate("INSERT INTO users VALUES (1, 'John')")
             .is_ok());
         assert!(whitelister.validate("DROP TABLE users").is_err());
+        assert!(whitelister
+            .validate("CREATE UNIQUE INDEX users_by_name ON users (name)")
+            .is_ok());
+        assert!(whitelister.validate("DROP INDEX users_by_name").is_ok());
     }
 }
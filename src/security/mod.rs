@@ -43,6 +43,11 @@
 // Memory sanitization, secure deallocation, cryptographic erasure, and heap spray
 // prevention. See [`secure_gc`] module for details.
 //
+// ### 10. SQL Firewall
+// Per-role and per-application allow-lists of statement fingerprints, with a
+// learning mode and block/alert/log actions. See [`sql_firewall`] module for
+// details.
+//
 // ## Usage Example
 //
 // ```rust,no_run
@@ -94,6 +99,7 @@ pub mod network_hardening;
 pub mod privileges;
 pub mod rbac;
 pub mod secure_gc;
+pub mod sql_firewall;
 
 // Re-export commonly used types
 pub use audit::{AuditAction, AuditManager, AuditPolicy, AuditRecord};
@@ -146,6 +152,10 @@ pub use secure_gc::{
     CryptoErase, DelayedSanitizer, HeapGuard, MemorySanitizer, ReferenceTracker, SecureDrop,
     SecurePool, SensitiveData,
 };
+pub use sql_firewall::{
+    AllowedStatement, Fingerprint, FirewallAction, FirewallIdentity, FirewallMode, FirewallPolicy,
+    FirewallScope, SqlFirewall,
+};
 
 // Integrated security manager combining all security subsystems
 pub struct IntegratedSecurityManager {
@@ -165,16 +175,20 @@ pub struct IntegratedSecurityManager {
     pub labels: Arc<LabelManager>,
     // Insider threat manager
     pub insider_threat: Arc<InsiderThreatManager>,
+    // SQL firewall, auditing to `audit`
+    pub firewall: Arc<SqlFirewall>,
 }
 
 impl IntegratedSecurityManager {
     // Create a new integrated security manager
     pub fn new() -> Self {
+        let audit = Arc::new(AuditManager::new());
         Self {
             rbac: Arc::new(RbacManager::new()),
             fgac: Arc::new(FgacManager::new()),
             encryption: Arc::new(EncryptionManager::new()),
-            audit: Arc::new(AuditManager::new()),
+            firewall: Arc::new(SqlFirewall::new().with_audit(audit.clone())),
+            audit,
             authentication: Arc::new(AuthenticationManager::new()),
             privileges: Arc::new(PrivilegeManager::new()),
             labels: Arc::new(LabelManager::new()),
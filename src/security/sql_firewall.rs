@@ -0,0 +1,579 @@
+// # SQL Firewall
+//
+// Decides, per role and per client application, which statements may run.
+// Statements are compared by fingerprint: the statement is parsed and
+// rendered back from its syntax tree, then unquoted words are upper-cased
+// and every literal and parameter replaced by `?`. `SELECT * FROM t WHERE
+// id = 1` and `select * from t where id = 42 -- by id` share one
+// fingerprint, as do spellings of the same syntax such as `!=` and `<>`, so
+// values never decide anything and bound parameters are never looked at.
+//
+// ## Modes
+//
+// - Disabled: every statement runs
+// - Learning: every statement runs, and its fingerprint is added to the
+//   allow-lists of the session's role and application
+// - Enforcing: a statement on neither allow-list gets the policy's action
+//
+// Trusted roles run any valid SQL in every mode. Blocked, alerted and logged
+// statements are written to the audit log when one is attached.
+//
+// `Database` owns the firewall, configured by the `[firewall]` section of
+// rustydb.toml and auditing to the security manager's audit log. Every front
+// end checks statements after they parse, so the firewall only ever sees
+// valid SQL.
+
+use crate::error::DbError;
+use crate::security::audit::{AuditAction, AuditManager};
+use crate::Result;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// How the firewall treats statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FirewallMode {
+    // Every statement runs
+    Disabled,
+    // Every statement runs and is added to the allow-lists
+    Learning,
+    // Statements outside the allow-lists get the policy's action
+    Enforcing,
+}
+
+// What happens to a statement outside the allow-lists in enforcing mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FirewallAction {
+    // Reject the statement and audit the attempt
+    Block,
+    // Run the statement, audit it and raise a warning
+    Alert,
+    // Run the statement and audit it
+    Log,
+}
+
+impl FirewallAction {
+    fn audit_action(self) -> AuditAction {
+        AuditAction::Custom(
+            match self {
+                FirewallAction::Block => "SQL_FIREWALL_BLOCK",
+                FirewallAction::Alert => "SQL_FIREWALL_ALERT",
+                FirewallAction::Log => "SQL_FIREWALL_LOG",
+            }
+            .to_string(),
+        )
+    }
+}
+
+// Firewall configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirewallPolicy {
+    pub mode: FirewallMode,
+    pub action: FirewallAction,
+    // Roles that run any valid SQL
+    pub trusted_roles: HashSet<String>,
+}
+
+impl Default for FirewallPolicy {
+    fn default() -> Self {
+        Self {
+            mode: FirewallMode::Disabled,
+            action: FirewallAction::Block,
+            trusted_roles: HashSet::new(),
+        }
+    }
+}
+
+// Who is running a statement
+#[derive(Debug, Clone, Default)]
+pub struct FirewallIdentity {
+    pub role: String,
+    // Client application name, if the client reported one
+    pub application: Option<String>,
+    pub session_id: Option<String>,
+    pub client_addr: Option<String>,
+}
+
+impl FirewallIdentity {
+    pub fn new(role: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            ..Self::default()
+        }
+    }
+
+    // Allow-lists that apply to this identity
+    fn scopes(&self) -> Vec<FirewallScope> {
+        let mut scopes = vec![FirewallScope::Role(self.role.clone())];
+        if let Some(application) = &self.application {
+            scopes.push(FirewallScope::Application(application.clone()));
+        }
+        scopes
+    }
+}
+
+// Owner of an allow-list
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FirewallScope {
+    Role(String),
+    Application(String),
+}
+
+// Shape of a statement, with literals and parameters replaced by `?`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Fingerprint {
+    // First 16 hex digits of the SHA-256 of `text`
+    pub hash: String,
+    pub text: String,
+}
+
+impl Fingerprint {
+    pub fn of(sql: &str) -> Self {
+        let text = normalize(&canonical(sql));
+        let digest = hex::encode(Sha256::digest(text.as_bytes()));
+        Self {
+            hash: digest[..16].to_string(),
+            text,
+        }
+    }
+}
+
+// Allow-list entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowedStatement {
+    pub fingerprint: Fingerprint,
+    // Times the statement ran while learning
+    pub hits: u64,
+    // Microseconds since the epoch
+    pub added_at: i64,
+    pub last_seen: i64,
+}
+
+// Policy-driven SQL firewall
+pub struct SqlFirewall {
+    policy: RwLock<FirewallPolicy>,
+    // Keyed by fingerprint hash
+    allow_lists: RwLock<HashMap<FirewallScope, HashMap<String, AllowedStatement>>>,
+    audit: Option<Arc<AuditManager>>,
+}
+
+impl SqlFirewall {
+    // Create a disabled firewall with empty allow-lists
+    pub fn new() -> Self {
+        Self {
+            policy: RwLock::new(FirewallPolicy::default()),
+            allow_lists: RwLock::new(HashMap::new()),
+            audit: None,
+        }
+    }
+
+    // Write firewall events to `audit`
+    pub fn with_audit(mut self, audit: Arc<AuditManager>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub fn policy(&self) -> FirewallPolicy {
+        self.policy.read().clone()
+    }
+
+    pub fn set_policy(&self, policy: FirewallPolicy) {
+        *self.policy.write() = policy;
+    }
+
+    pub fn set_mode(&self, mode: FirewallMode) {
+        self.policy.write().mode = mode;
+    }
+
+    pub fn trust_role(&self, role: &str) {
+        self.policy.write().trusted_roles.insert(role.to_string());
+    }
+
+    // Add the statement `sql` to an allow-list
+    pub fn allow(&self, scope: FirewallScope, sql: &str) -> Fingerprint {
+        let fingerprint = Fingerprint::of(sql);
+        let now = now_micros();
+        self.allow_lists
+            .write()
+            .entry(scope)
+            .or_default()
+            .entry(fingerprint.hash.clone())
+            .or_insert_with(|| AllowedStatement {
+                fingerprint: fingerprint.clone(),
+                hits: 0,
+                added_at: now,
+                last_seen: now,
+            });
+        fingerprint
+    }
+
+    // Remove a fingerprint from an allow-list; false if it was not there
+    pub fn revoke(&self, scope: &FirewallScope, hash: &str) -> bool {
+        self.allow_lists
+            .write()
+            .get_mut(scope)
+            .is_some_and(|list| list.remove(hash).is_some())
+    }
+
+    // Entries of an allow-list, ordered by fingerprint text
+    pub fn allow_list(&self, scope: &FirewallScope) -> Vec<AllowedStatement> {
+        let mut entries: Vec<_> = self
+            .allow_lists
+            .read()
+            .get(scope)
+            .map(|list| list.values().cloned().collect())
+            .unwrap_or_default();
+        entries.sort_by(|a, b| a.fingerprint.text.cmp(&b.fingerprint.text));
+        entries
+    }
+
+    // Check a statement that has already parsed. Fails only when the
+    // statement is blocked.
+    pub fn check(&self, identity: &FirewallIdentity, sql: &str) -> Result<()> {
+        let (mode, action) = {
+            let policy = self.policy.read();
+            if policy.mode == FirewallMode::Disabled
+                || policy.trusted_roles.contains(&identity.role)
+            {
+                return Ok(());
+            }
+            (policy.mode, policy.action)
+        };
+
+        let fingerprint = Fingerprint::of(sql);
+        if mode == FirewallMode::Learning {
+            self.learn(identity, &fingerprint);
+            return Ok(());
+        }
+
+        let allowed = {
+            let lists = self.allow_lists.read();
+            identity.scopes().iter().any(|scope| {
+                lists
+                    .get(scope)
+                    .is_some_and(|list| list.contains_key(&fingerprint.hash))
+            })
+        };
+        if allowed {
+            return Ok(());
+        }
+
+        self.audit_event(identity, sql, &fingerprint, action);
+        match action {
+            FirewallAction::Block => Err(DbError::PermissionDenied(format!(
+                "SQL firewall blocked statement {} for role \"{}\"",
+                fingerprint.hash, identity.role
+            ))),
+            FirewallAction::Alert => {
+                tracing::warn!(
+                    "SQL firewall: role \"{}\" ran unrecognized statement {}: {}",
+                    identity.role,
+                    fingerprint.hash,
+                    fingerprint.text
+                );
+                Ok(())
+            }
+            FirewallAction::Log => Ok(()),
+        }
+    }
+
+    fn learn(&self, identity: &FirewallIdentity, fingerprint: &Fingerprint) {
+        let now = now_micros();
+        let mut lists = self.allow_lists.write();
+        for scope in identity.scopes() {
+            let entry = lists
+                .entry(scope)
+                .or_default()
+                .entry(fingerprint.hash.clone())
+                .or_insert_with(|| AllowedStatement {
+                    fingerprint: fingerprint.clone(),
+                    hits: 0,
+                    added_at: now,
+                    last_seen: now,
+                });
+            entry.hits += 1;
+            entry.last_seen = now;
+        }
+    }
+
+    fn audit_event(
+        &self,
+        identity: &FirewallIdentity,
+        sql: &str,
+        fingerprint: &Fingerprint,
+        action: FirewallAction,
+    ) {
+        let audit = match &self.audit {
+            Some(audit) => audit,
+            None => return,
+        };
+        let mut context = HashMap::new();
+        context.insert("sql".to_string(), sql.to_string());
+        context.insert("fingerprint".to_string(), fingerprint.hash.clone());
+        context.insert("fingerprint_text".to_string(), fingerprint.text.clone());
+        if let Some(application) = &identity.application {
+            context.insert("application".to_string(), application.clone());
+        }
+        if let Some(client_addr) = &identity.client_addr {
+            context.insert("client_ip".to_string(), client_addr.clone());
+        }
+        // A failure to audit must not let a blocked statement through, nor
+        // fail one that is allowed to run
+        if let Err(e) = audit.log_event(
+            identity.role.clone(),
+            identity.session_id.clone(),
+            action.audit_action(),
+            None,
+            None,
+            action != FirewallAction::Block,
+            context,
+        ) {
+            tracing::error!("SQL firewall could not write to the audit log: {}", e);
+        }
+    }
+}
+
+impl Default for SqlFirewall {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn now_micros() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as i64)
+        .unwrap_or(0)
+}
+
+// `sql` rendered from its syntax tree. Statements the parser rejects, such
+// as procedure bodies and the commands the SQL parser handles itself, are
+// fingerprinted as written.
+fn canonical(sql: &str) -> String {
+    match Parser::parse_sql(&GenericDialect {}, sql) {
+        Ok(statements) if !statements.is_empty() => statements
+            .iter()
+            .map(|statement| statement.to_string())
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => sql.to_string(),
+    }
+}
+
+// Fingerprint text of `sql`. Literal lists after IN collapse to one `?`,
+// so lists of any length match; dollar-quoted strings are procedure bodies
+// and are kept.
+fn normalize(sql: &str) -> String {
+    let tokens = match Tokenizer::new(&GenericDialect {}, sql).tokenize() {
+        Ok(tokens) => tokens,
+        // Valid statements the tokenizer rejects, such as some PL/SQL, are
+        // compared with only their whitespace normalized
+        Err(_) => return sql.split_whitespace().collect::<Vec<_>>().join(" "),
+    };
+
+    let mut parts: Vec<String> = Vec::new();
+    for token in tokens {
+        let part = match token {
+            Token::Whitespace(_) | Token::EOF => continue,
+            Token::Word(word) if word.quote_style.is_none() => word.value.to_uppercase(),
+            Token::Number(..)
+            | Token::Placeholder(_)
+            | Token::SingleQuotedString(_)
+            | Token::DoubleQuotedString(_)
+            | Token::NationalStringLiteral(_)
+            | Token::EscapedStringLiteral(_)
+            | Token::UnicodeStringLiteral(_)
+            | Token::HexStringLiteral(_)
+            | Token::SingleQuotedByteStringLiteral(_)
+            | Token::DoubleQuotedByteStringLiteral(_) => "?".to_string(),
+            token => token.to_string(),
+        };
+        // `IN (?, ?, ?` collapses to `IN (?`
+        if part == "?" && parts.len() >= 4 && parts.ends_with(&["?".to_string(), ",".to_string()]) {
+            let start = parts
+                .iter()
+                .rposition(|p| p != "?" && p != ",")
+                .unwrap_or(0);
+            if parts[start] == "(" && start > 0 && parts[start - 1] == "IN" {
+                parts.pop();
+                continue;
+            }
+        }
+        parts.push(part);
+    }
+    while parts.last().is_some_and(|p| p == ";") {
+        parts.pop();
+    }
+
+    let mut text = String::new();
+    let mut glue = true;
+    for part in &parts {
+        if !glue && !matches!(part.as_str(), "," | ")" | "." | ";") {
+            text.push(' ');
+        }
+        text.push_str(part);
+        glue = matches!(part.as_str(), "(" | ".");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::audit::{AuditFilter, AuditRecord};
+
+    fn events(audit: &AuditManager, action: FirewallAction) -> Vec<AuditRecord> {
+        audit.query(AuditFilter {
+            start_time: None,
+            end_time: None,
+            username: None,
+            action: Some(action.audit_action()),
+            object_name: None,
+            success: None,
+            min_severity: None,
+            limit: None,
+        })
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        let select = Fingerprint::of("SELECT * FROM users WHERE id = 1");
+        assert_eq!(select.text, "SELECT * FROM USERS WHERE ID = ?");
+        assert_eq!(select.hash.len(), 16);
+        assert_eq!(
+            Fingerprint::of("select *\n  from users -- by id\n where id = $1;"),
+            select
+        );
+        assert_eq!(
+            Fingerprint::of("SELECT /*+ INDEX(users idx_id) */ * FROM users WHERE id = 'x'"),
+            select
+        );
+        assert_ne!(
+            Fingerprint::of("SELECT * FROM users WHERE id = 1 OR 1 = 1"),
+            select
+        );
+        assert_eq!(
+            Fingerprint::of("SELECT a.id FROM a WHERE a.id IN (1, 2, 3)").text,
+            "SELECT A.ID FROM A WHERE A.ID IN (?)"
+        );
+        assert_eq!(
+            Fingerprint::of("SELECT id FROM a WHERE id IN (1)"),
+            Fingerprint::of("SELECT id FROM a WHERE id IN (4, 5)")
+        );
+        assert_ne!(
+            Fingerprint::of("INSERT INTO a VALUES (1, 2)"),
+            Fingerprint::of("INSERT INTO a VALUES (1)")
+        );
+        assert_ne!(
+            Fingerprint::of("SELECT \"Id\" FROM a"),
+            Fingerprint::of("SELECT id FROM a")
+        );
+        // Spellings the parser reads as the same syntax
+        assert_eq!(
+            Fingerprint::of("SELECT id FROM a WHERE id != 1"),
+            Fingerprint::of("SELECT id FROM a WHERE id <> 2")
+        );
+    }
+
+    #[test]
+    fn test_learning_and_enforcing() -> Result<()> {
+        let audit = Arc::new(AuditManager::new());
+        let firewall = SqlFirewall::new().with_audit(audit.clone());
+        let mut app = FirewallIdentity::new("app_user");
+        app.application = Some("billing".to_string());
+
+        // Disabled: anything runs and nothing is recorded
+        firewall.check(&app, "DELETE FROM invoices")?;
+        assert!(firewall
+            .allow_list(&FirewallScope::Role("app_user".to_string()))
+            .is_empty());
+
+        firewall.set_mode(FirewallMode::Learning);
+        firewall.check(&app, "SELECT total FROM invoices WHERE id = 7")?;
+        firewall.check(&app, "SELECT total FROM invoices WHERE id = 8")?;
+        let learned = firewall.allow_list(&FirewallScope::Application("billing".to_string()));
+        assert_eq!(learned.len(), 1);
+        assert_eq!(learned[0].hits, 2);
+
+        firewall.set_mode(FirewallMode::Enforcing);
+        firewall.check(&app, "select total from invoices where id = 9")?;
+        let err = firewall.check(&app, "DELETE FROM invoices").unwrap_err();
+        assert!(matches!(err, DbError::PermissionDenied(_)));
+
+        // The same role from another application still has its own list
+        let mut other = FirewallIdentity::new("app_user");
+        other.application = Some("reports".to_string());
+        firewall.check(&other, "SELECT total FROM invoices WHERE id = 1")?;
+        assert!(firewall.check(&other, "SELECT * FROM invoices").is_err());
+
+        // An application allow-list admits any role using it
+        firewall.allow(
+            FirewallScope::Application("reports".to_string()),
+            "SELECT * FROM invoices",
+        );
+        let mut analyst = FirewallIdentity::new("analyst");
+        analyst.application = Some("reports".to_string());
+        firewall.check(&analyst, "SELECT * FROM invoices")?;
+        assert!(firewall
+            .check(&FirewallIdentity::new("analyst"), "SELECT * FROM invoices")
+            .is_err());
+
+        // Trusted roles run any valid SQL
+        firewall.trust_role("dba");
+        firewall.check(&FirewallIdentity::new("dba"), "DROP TABLE invoices")?;
+
+        let blocked = events(&audit, FirewallAction::Block);
+        assert_eq!(blocked.len(), 3);
+        assert!(blocked.iter().all(|record| !record.success));
+        let delete = blocked
+            .iter()
+            .find(|record| record.sql_text.as_deref() == Some("DELETE FROM invoices"))
+            .unwrap();
+        assert_eq!(delete.username, "app_user");
+        assert_eq!(delete.client_application.as_deref(), Some("billing"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_alert_and_log_actions() -> Result<()> {
+        let audit = Arc::new(AuditManager::new());
+        let firewall = SqlFirewall::new().with_audit(audit.clone());
+        let user = FirewallIdentity::new("web");
+        let fingerprint = firewall.allow(
+            FirewallScope::Role("web".to_string()),
+            "SELECT name FROM products",
+        );
+
+        for action in [FirewallAction::Alert, FirewallAction::Log] {
+            firewall.set_policy(FirewallPolicy {
+                mode: FirewallMode::Enforcing,
+                action,
+                trusted_roles: HashSet::new(),
+            });
+            firewall.check(&user, "SELECT name FROM products")?;
+            firewall.check(&user, "SELECT price FROM products")?;
+            let logged = events(&audit, action);
+            assert_eq!(logged.len(), 1);
+            assert!(logged[0].success);
+            assert_eq!(
+                logged[0].sql_text.as_deref(),
+                Some("SELECT price FROM products")
+            );
+        }
+
+        assert!(firewall.revoke(&FirewallScope::Role("web".to_string()), &fingerprint.hash));
+        assert!(!firewall.revoke(&FirewallScope::Role("web".to_string()), &fingerprint.hash));
+        firewall.set_policy(FirewallPolicy {
+            mode: FirewallMode::Enforcing,
+            ..FirewallPolicy::default()
+        });
+        assert!(firewall.check(&user, "SELECT name FROM products").is_err());
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use crate::common::PageId;
 use crate::error::{DbError, Result};
 use crate::storage::disk::DiskManager;
 use crate::storage::page::Page;
+use crate::transaction::wal::WALManager;
 use parking_lot::RwLock;
 use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
@@ -43,7 +44,11 @@ impl CowFrame {
     }
 
     fn unpin(&self) {
-        self.reference_count.fetch_sub(1, Ordering::SeqCst);
+        let _ = self
+            .reference_count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                count.checked_sub(1)
+            });
     }
 
     fn is_pinned(&self) -> bool {
@@ -418,6 +423,9 @@ pub struct BufferPoolManager {
     // Disk manager
     disk_manager: DiskManager,
 
+    // Log that must cover a page's changes before the page is written back
+    wal: RwLock<Option<Arc<WALManager>>>,
+
     // Metadata
     pool_size: usize,
     version_counter: Arc<AtomicU64>,
@@ -439,6 +447,7 @@ impl BufferPoolManager {
             numa_allocator: Arc::new(Mutex::new(NumaAllocator::new(4, pool_size / 4))),
             flusher: Arc::new(BackgroundFlusher::new(Duration::from_millis(100), 32)),
             disk_manager,
+            wal: RwLock::new(None),
             pool_size,
             version_counter: Arc::new(AtomicU64::new(0)),
             hit_count: Arc::new(AtomicU64::new(0)),
@@ -455,7 +464,11 @@ impl BufferPoolManager {
                 let pool = self.pool.read();
                 if let Some(frame) = pool.get(&frame_id) {
                     frame.pin();
-                    self.replacer.lock().unwrap().record_access(page_id);
+                    {
+                        let mut replacer = self.replacer.lock().unwrap();
+                        replacer.record_access(page_id);
+                        replacer.set_evictable(page_id, false);
+                    }
                     self.hit_count.fetch_add(1, Ordering::Relaxed);
 
                     // Return a COW copy
@@ -474,10 +487,18 @@ impl BufferPoolManager {
         let frame_id = self.get_free_frame()?;
 
         // Load from disk
-        let page = self.disk_manager.read_page(page_id)?;
+        let page = match self.disk_manager.read_page(page_id) {
+            Ok(page) => page,
+            Err(e) => {
+                self.free_frames.lock().unwrap().push(frame_id);
+                return Err(e);
+            }
+        };
         let version = self.version_counter.fetch_add(1, Ordering::SeqCst);
 
+        // Every fetch pins the frame; callers balance it with unpin_page()
         let frame = CowFrame::new(page.clone(), version);
+        frame.pin();
 
         // Update pool and page table
         {
@@ -505,6 +526,7 @@ impl BufferPoolManager {
         let version = self.version_counter.fetch_add(1, Ordering::SeqCst);
 
         let frame = CowFrame::new(page.clone(), version);
+        frame.pin();
 
         {
             let mut pool = self.pool.write();
@@ -521,6 +543,48 @@ impl BufferPoolManager {
         Ok(page)
     }
 
+    // Size in bytes of the pages managed by this pool
+    pub fn page_size(&self) -> usize {
+        self.disk_manager.page_size
+    }
+
+    // Enforce the write-ahead rule against `wal`: a page is only written back
+    // once the log is durable up to the page LSN
+    pub fn set_wal(&self, wal: Arc<WALManager>) {
+        *self.wal.write() = Some(wal);
+    }
+
+    fn write_back(&self, page: &Page) -> Result<()> {
+        if let Some(wal) = self.wal.read().as_ref() {
+            wal.flush_to(page.lsn())?;
+        }
+        self.disk_manager.write_page(page)
+    }
+
+    // Install a modified copy of a page into its frame
+    //
+    // fetch_page() hands out copies, so writers modify their copy and publish
+    // it here. The frame is marked dirty and written back on flush/eviction.
+    pub fn write_page(&self, page: &Page) -> Result<()> {
+        {
+            let page_table = self.page_table.read();
+            if let Some(&frame_id) = page_table.get(&page.id) {
+                let pool = self.pool.read();
+                if let Some(frame) = pool.get(&frame_id) {
+                    let mut resident = frame.page.write();
+                    resident.data.copy_from_slice(&page.data);
+                    resident.is_dirty = true;
+                    drop(resident);
+                    self.flusher.mark_dirty(page.id);
+                    return Ok(());
+                }
+            }
+        }
+
+        // Not resident (evicted since it was fetched): write through
+        self.write_back(page)
+    }
+
     // Flush a specific page to disk
     pub fn flush_page(&self, page_id: PageId) -> Result<()> {
         let page_table = self.page_table.read();
@@ -528,9 +592,10 @@ impl BufferPoolManager {
         if let Some(&frame_id) = page_table.get(&page_id) {
             let pool = self.pool.read();
             if let Some(frame) = pool.get(&frame_id) {
-                let page = frame.page.read();
+                let mut page = frame.page.write();
                 if page.is_dirty {
-                    self.disk_manager.write_page(&page)?;
+                    self.write_back(&page)?;
+                    page.is_dirty = false;
                 }
             }
         }
@@ -540,18 +605,41 @@ impl BufferPoolManager {
 
     // Flush all dirty pages to disk
     pub fn flush_all(&self) -> Result<()> {
-        let page_table = self.page_table.read();
-        let pool = self.pool.read();
+        let page_ids: Vec<PageId> = self.page_table.read().keys().copied().collect();
+        self.flush_pages(&page_ids)
+    }
 
-        for &frame_id in page_table.values() {
-            if let Some(frame) = pool.get(&frame_id) {
-                let page = frame.page.read();
+    // Flush the given pages and wait until they are durable, leaving the rest
+    // of the pool alone
+    pub fn flush_pages(&self, page_ids: &[PageId]) -> Result<()> {
+        let mut written = Vec::new();
+        {
+            let page_table = self.page_table.read();
+            let pool = self.pool.read();
+
+            for page_id in page_ids {
+                let Some(frame) = page_table.get(page_id).and_then(|id| pool.get(id)) else {
+                    continue;
+                };
+                let mut page = frame.page.write();
                 if page.is_dirty {
-                    self.disk_manager.write_page(&page)?;
+                    self.write_back(&page)?;
+                    page.is_dirty = false;
+                    written.push(page.id);
                 }
             }
         }
 
+        // Push everything out of the disk manager's write-behind buffer
+        self.disk_manager.flush_all_writes()?;
+
+        // The pages are on disk now, so their log records are no longer
+        // needed to redo them
+        if let Some(wal) = self.wal.read().as_ref() {
+            for page_id in written {
+                wal.page_flushed(page_id);
+            }
+        }
         Ok(())
     }
 
@@ -621,6 +709,8 @@ impl BufferPoolManager {
             pages_in_use: self.page_table.read().len(),
             hit_rate,
             total_accesses: total,
+            hits,
+            misses,
         }
     }
 
@@ -654,6 +744,8 @@ pub struct BufferPoolStats {
     pub pages_in_use: usize,
     pub hit_rate: f64,
     pub total_accesses: u64,
+    pub hits: u64,
+    pub misses: u64,
 }
 
 #[cfg(test)]
@@ -3,10 +3,14 @@ use crate::error::{DbError, Result};
 use crate::storage::page::Page;
 // Use shared checksum module (deduplication)
 use super::checksum::hardware_crc32c;
+#[cfg(target_os = "linux")]
+use crate::io::{IoUringConfig, KernelRing, SqeEntry};
 use parking_lot::RwLock;
 use std::collections::{HashMap, VecDeque};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
@@ -31,6 +35,10 @@ const COALESCE_MAX_BATCH: usize = 64;
 /// Maximum io_uring queue depth (default: 256 operations)
 const IO_URING_QUEUE_DEPTH: usize = 256;
 
+/// Page buffers registered with the kernel io_uring (default: 32 pages = 128KB at 4KB/page)
+#[cfg(target_os = "linux")]
+const IO_URING_FIXED_BUFFERS: usize = 32;
+
 /// Maximum IoScheduler queue size per operation type (default: 512 operations)
 /// BOUNDED to prevent unbounded growth under heavy load
 /// - read_queue: up to 512 pending read operations
@@ -297,7 +305,9 @@ impl WriteBehindBuffer {
     }
 
     fn add(&mut self, page_id: PageId, data: Vec<u8>) -> bool {
-        if self.buffer.len() >= self.max_pages {
+        // Always absorb rewrites of an already-buffered page, otherwise the
+        // stale buffered copy would shadow the direct write
+        if self.buffer.len() >= self.max_pages && !self.buffer.contains_key(&page_id) {
             return false;
         }
 
@@ -345,6 +355,13 @@ impl WriteBehindBuffer {
     fn contains(&self, page_id: PageId) -> bool {
         self.buffer.contains_key(&page_id)
     }
+
+    // Drop a buffered page that has been superseded on disk
+    fn discard(&mut self, page_id: PageId) {
+        if self.buffer.remove(&page_id).is_some() {
+            self.dirty_pages.retain(|&id| id != page_id);
+        }
+    }
 }
 
 // Direct I/O configuration
@@ -468,7 +485,7 @@ impl WriteCoalescer {
     }
 }
 
-// io_uring operation descriptor (placeholder for future implementation)
+// io_uring operation descriptor. The op owns its buffer until it completes.
 #[derive(Debug, Clone)]
 pub struct IoUringOp {
     pub op_type: IoOpType,
@@ -498,52 +515,392 @@ impl IoUringOp {
             user_data: page_id as u64,
         }
     }
+
+    fn into_completion(self, result: std::io::Result<usize>) -> IoUringCompletion {
+        let result = match result {
+            Ok(n) if self.op_type != IoOpType::Sync && n != self.data.len() => {
+                Err(DbError::Storage(format!(
+                    "Short io_uring {:?} of page {}: {} of {} bytes",
+                    self.op_type,
+                    self.page_id,
+                    n,
+                    self.data.len()
+                )))
+            }
+            Ok(n) => Ok(n),
+            Err(e) => Err(e.into()),
+        };
+        let page = match (&result, self.op_type) {
+            (Ok(_), IoOpType::Read) => Some(Page::from_bytes(self.page_id, self.data)),
+            _ => None,
+        };
+
+        IoUringCompletion {
+            user_data: self.user_data,
+            page_id: self.page_id,
+            op_type: self.op_type,
+            result,
+            page,
+        }
+    }
+}
+
+// A finished io_uring operation
+#[derive(Debug)]
+pub struct IoUringCompletion {
+    pub user_data: u64,
+    pub page_id: PageId,
+    pub op_type: IoOpType,
+    // Bytes transferred, or why the operation failed
+    pub result: Result<usize>,
+    // The page read, for successful reads
+    pub page: Option<Page>,
+}
+
+// How io_uring operations reach the data file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IoUringMode {
+    // A kernel ring where the kernel offers one, pread/pwrite otherwise
+    #[default]
+    Auto,
+    // A kernel ring whose submission queue is polled by a kernel thread
+    SqPoll,
+    // Always pread/pwrite
+    Sync,
+}
+
+// Kernel ring with page-sized registered buffers
+#[cfg(target_os = "linux")]
+struct KernelBackend {
+    ring: KernelRing,
+    page_size: usize,
+    // Registered with the ring for READ_FIXED/WRITE_FIXED, and the indexes
+    // not used by an op in flight
+    fixed_buffers: Vec<Vec<u8>>,
+    free_buffers: Vec<u16>,
+    // Ops handed to the kernel, by token, with the fixed buffer each uses
+    in_flight: HashMap<u64, (IoUringOp, Option<u16>)>,
+    next_token: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl KernelBackend {
+    fn new(queue_depth: usize, page_size: usize, sqpoll: bool) -> Result<Self> {
+        let mut ring = KernelRing::new(&IoUringConfig {
+            queue_depth: queue_depth as u32,
+            sqpoll,
+            ..Default::default()
+        })?;
+
+        // Without registration (e.g. over RLIMIT_MEMLOCK) ops use their own
+        // buffers, which the kernel then maps on every operation
+        let mut fixed_buffers: Vec<Vec<u8>> = (0..IO_URING_FIXED_BUFFERS)
+            .map(|_| vec![0u8; page_size])
+            .collect();
+        let iovecs: Vec<libc::iovec> = fixed_buffers
+            .iter_mut()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+                iov_len: buffer.len(),
+            })
+            .collect();
+        if let Err(e) = ring.register_buffers(&iovecs) {
+            tracing::debug!("io_uring buffer registration failed: {}", e);
+            fixed_buffers.clear();
+        }
+        let free_buffers = (0..fixed_buffers.len() as u16).rev().collect();
+
+        Ok(Self {
+            ring,
+            page_size,
+            fixed_buffers,
+            free_buffers,
+            in_flight: HashMap::new(),
+            next_token: 0,
+        })
+    }
+
+    fn submit(&mut self, fd: i32, ops: impl Iterator<Item = IoUringOp>) -> Result<()> {
+        for mut op in ops {
+            let token = self.next_token;
+            self.next_token += 1;
+
+            let len = op.data.len() as u32;
+            let fixed = if op.op_type == IoOpType::Sync || op.data.len() > self.page_size {
+                None
+            } else {
+                self.free_buffers.pop()
+            };
+            let sqe = match (op.op_type, fixed) {
+                (IoOpType::Read, Some(index)) => SqeEntry::read_fixed(
+                    fd,
+                    self.fixed_buffers[index as usize].as_mut_ptr(),
+                    len,
+                    op.offset,
+                    index,
+                    token,
+                ),
+                (IoOpType::Read, None) => {
+                    SqeEntry::read(fd, op.data.as_mut_ptr(), len, op.offset, token)
+                }
+                (IoOpType::Write, Some(index)) => {
+                    let buffer = &mut self.fixed_buffers[index as usize];
+                    buffer[..op.data.len()].copy_from_slice(&op.data);
+                    SqeEntry::write_fixed(fd, buffer.as_ptr(), len, op.offset, index, token)
+                }
+                (IoOpType::Write, None) => {
+                    SqeEntry::write(fd, op.data.as_ptr(), len, op.offset, token)
+                }
+                (IoOpType::Sync, _) => SqeEntry::fdatasync(fd, token),
+            };
+
+            // A full ring makes room by handing what is queued to the kernel
+            if !self.ring.push(sqe) && (self.ring.submit(0).is_err() || !self.ring.push(sqe)) {
+                if let Some(index) = fixed {
+                    self.free_buffers.push(index);
+                }
+                return Err(DbError::Storage(
+                    "io_uring submission queue full".to_string(),
+                ));
+            }
+            self.in_flight.insert(token, (op, fixed));
+        }
+
+        self.ring.submit(0)?;
+        Ok(())
+    }
+
+    fn reap(&mut self, completions: &mut VecDeque<IoUringCompletion>) {
+        while let Some(cqe) = self.ring.pop_completion() {
+            let (mut op, fixed) = match self.in_flight.remove(&cqe.user_data) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            if let Some(index) = fixed {
+                if op.op_type == IoOpType::Read && cqe.is_success() {
+                    let len = op.data.len();
+                    op.data
+                        .copy_from_slice(&self.fixed_buffers[index as usize][..len]);
+                }
+                self.free_buffers.push(index);
+            }
+
+            let result = match cqe.error_code() {
+                Some(errno) => Err(std::io::Error::from_raw_os_error(errno)),
+                None => Ok(cqe.res as usize),
+            };
+            completions.push_back(op.into_completion(result));
+        }
+    }
+
+    fn wait(
+        &mut self,
+        min_complete: usize,
+        completions: &mut VecDeque<IoUringCompletion>,
+    ) -> Result<()> {
+        self.reap(completions);
+        while completions.len() < min_complete && !self.in_flight.is_empty() {
+            self.ring.submit(1)?;
+            self.reap(completions);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for KernelBackend {
+    fn drop(&mut self) {
+        // The kernel may still be reading or writing op buffers
+        while !self.in_flight.is_empty() {
+            if self.ring.submit(1).is_err() {
+                std::mem::forget(std::mem::take(&mut self.in_flight));
+                std::mem::forget(std::mem::take(&mut self.fixed_buffers));
+                return;
+            }
+            while let Some(cqe) = self.ring.pop_completion() {
+                self.in_flight.remove(&cqe.user_data);
+            }
+        }
+    }
+}
+
+enum Backend {
+    // Chosen on first use
+    Unstarted,
+    #[cfg(target_os = "linux")]
+    Kernel(Box<KernelBackend>),
+    Sync,
 }
 
-// io_uring interface (simulated for now, real implementation would use io-uring crate)
+// io_uring interface: page I/O through a kernel ring, or through pread and
+// pwrite where the kernel has no io_uring
 pub struct IoUring {
+    backend: Backend,
+    file: File,
+    mode: IoUringMode,
+    page_size: usize,
     submission_queue: VecDeque<IoUringOp>,
-    completion_queue: VecDeque<(u64, Result<usize>)>,
+    completion_queue: VecDeque<IoUringCompletion>,
     max_queue_depth: usize,
-    _polling_mode: bool,
 }
 
 impl IoUring {
-    pub fn new(queue_depth: usize, polling_mode: bool) -> Self {
+    pub fn new(file: File, page_size: usize, queue_depth: usize, mode: IoUringMode) -> Self {
         Self {
+            backend: Backend::Unstarted,
+            file,
+            mode,
+            page_size,
             submission_queue: VecDeque::with_capacity(queue_depth),
             completion_queue: VecDeque::with_capacity(queue_depth),
             max_queue_depth: queue_depth,
-            _polling_mode: polling_mode,
+        }
+    }
+
+    fn start(&mut self) {
+        if !matches!(self.backend, Backend::Unstarted) {
+            return;
+        }
+
+        #[cfg(target_os = "linux")]
+        if self.mode != IoUringMode::Sync {
+            let sqpoll = self.mode == IoUringMode::SqPoll;
+            match KernelBackend::new(self.max_queue_depth, self.page_size, sqpoll) {
+                Ok(kernel) => {
+                    self.backend = Backend::Kernel(Box::new(kernel));
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!("io_uring unavailable, falling back to pread/pwrite: {}", e)
+                }
+            }
+        }
+        self.backend = Backend::Sync;
+    }
+
+    // Switch how operations reach the file; nothing may be outstanding
+    pub fn set_mode(&mut self, mode: IoUringMode) -> Result<()> {
+        if self.outstanding() > 0 {
+            return Err(DbError::Storage(
+                "io_uring operations are outstanding".to_string(),
+            ));
+        }
+        self.mode = mode;
+        self.backend = Backend::Unstarted;
+        Ok(())
+    }
+
+    // Whether operations go through a kernel ring
+    pub fn is_kernel_backed(&mut self) -> bool {
+        self.start();
+        #[cfg(target_os = "linux")]
+        {
+            matches!(self.backend, Backend::Kernel(_))
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            false
         }
     }
 
     pub fn submit_op(&mut self, op: IoUringOp) -> Result<()> {
-        if self.submission_queue.len() >= self.max_queue_depth {
+        if self.submission_queue.len() + self.in_flight() >= self.max_queue_depth {
             return Err(DbError::Storage("io_uring queue full".to_string()));
         }
         self.submission_queue.push_back(op);
         Ok(())
     }
 
+    // Complete a read from a copy of the page held in memory
+    fn complete_from_memory(&mut self, op: IoUringOp) {
+        let len = op.data.len();
+        self.completion_queue.push_back(op.into_completion(Ok(len)));
+    }
+
     pub fn submit_batch(&mut self) -> Result<usize> {
-        // In real implementation, this would submit to kernel io_uring
+        self.start();
         let count = self.submission_queue.len();
+
+        match &mut self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::Kernel(kernel) => {
+                kernel.submit(self.file.as_raw_fd(), self.submission_queue.drain(..))?
+            }
+            _ => {
+                for mut op in self.submission_queue.drain(..) {
+                    let result = run_sync(&self.file, &mut op);
+                    self.completion_queue.push_back(op.into_completion(result));
+                }
+            }
+        }
+
         Ok(count)
     }
 
+    // Wait until at least `min_complete` completions are ready, or nothing
+    // is left in flight. Returns the number ready.
     pub fn wait_completions(&mut self, min_complete: usize) -> Result<usize> {
-        // Simulated - real implementation would wait on io_uring
-        Ok(min_complete)
+        #[cfg(target_os = "linux")]
+        if let Backend::Kernel(kernel) = &mut self.backend {
+            kernel.wait(min_complete, &mut self.completion_queue)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = min_complete;
+
+        Ok(self.completion_queue.len())
     }
 
-    pub fn get_completion(&mut self) -> Option<(u64, Result<usize>)> {
+    pub fn get_completion(&mut self) -> Option<IoUringCompletion> {
         self.completion_queue.pop_front()
     }
 
     pub fn pending_submissions(&self) -> usize {
         self.submission_queue.len()
     }
+
+    // Operations handed to the kernel and not yet completed
+    pub fn in_flight(&self) -> usize {
+        match &self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::Kernel(kernel) => kernel.in_flight.len(),
+            _ => 0,
+        }
+    }
+
+    fn outstanding(&self) -> usize {
+        self.submission_queue.len() + self.in_flight() + self.completion_queue.len()
+    }
+}
+
+// Perform an op with blocking positional I/O
+fn run_sync(file: &File, op: &mut IoUringOp) -> std::io::Result<usize> {
+    match op.op_type {
+        IoOpType::Read => read_exact_at(file, &mut op.data, op.offset).map(|_| op.data.len()),
+        IoOpType::Write => write_all_at(file, &op.data, op.offset).map(|_| op.data.len()),
+        IoOpType::Sync => file.sync_data().map(|_| 0),
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(not(unix))]
+fn read_exact_at(mut file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &File, buf: &[u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+#[cfg(not(unix))]
+fn write_all_at(mut file: &File, buf: &[u8], offset: u64) -> std::io::Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(buf)
 }
 
 // Advanced disk manager with I/O optimizations
@@ -622,6 +979,12 @@ impl DiskManager {
         let file = options.open(&path)?;
         let metadata = file.metadata()?;
         let num_pages = (metadata.len() / page_size as u64) as u32;
+        let io_uring = IoUring::new(
+            file.try_clone()?,
+            page_size,
+            IO_URING_QUEUE_DEPTH,
+            IoUringMode::Auto,
+        );
 
         Ok(Self {
             data_file: Arc::new(Mutex::new(file)),
@@ -640,7 +1003,7 @@ impl DiskManager {
                 COALESCE_WINDOW_US,
                 COALESCE_MAX_BATCH,
             ))),
-            io_uring: Arc::new(Mutex::new(IoUring::new(IO_URING_QUEUE_DEPTH, false))),
+            io_uring: Arc::new(Mutex::new(io_uring)),
             direct_io_config,
             adaptive_page_size: false,
             min_page_size: 4096,
@@ -653,6 +1016,21 @@ impl DiskManager {
     pub fn read_page(&self, page_id: PageId) -> Result<Page> {
         let start = Instant::now();
 
+        // Pages still sitting in the write-behind buffer are newer than the
+        // on-disk copy (and may not have reached the file at all yet)
+        {
+            let write_behind = self
+                .write_behind
+                .lock()
+                .map_err(|e| DbError::Storage(format!("Mutex poisoned: {}", e)))?;
+            if let Some(data) = write_behind.buffer.get(&page_id) {
+                let mut stats = self.stats.write();
+                stats.write_behind_hits += 1;
+                stats.reads += 1;
+                return Ok(Page::from_bytes(page_id, data.clone()));
+            }
+        }
+
         // Check read-ahead buffer first
         {
             let mut read_ahead = self
@@ -725,6 +1103,13 @@ impl DiskManager {
     pub fn write_page(&self, page: &Page) -> Result<()> {
         let start = Instant::now();
 
+        // Any prefetched copy of this page is now stale
+        self.read_ahead
+            .lock()
+            .map_err(|e| DbError::Storage(format!("Mutex poisoned: {}", e)))?
+            .buffer
+            .remove(&page.id);
+
         // Try write-behind buffer first
         let mut write_behind = self
             .write_behind
@@ -1033,23 +1418,58 @@ impl DiskManager {
         Ok(())
     }
 
-    // Submit async read via io_uring
+    // Choose how io_uring operations reach the data file; fails while any
+    // are outstanding
+    pub fn set_io_uring_mode(&self, mode: IoUringMode) -> Result<()> {
+        self.io_uring
+            .lock()
+            .map_err(|e| DbError::Storage(format!("Mutex poisoned: {}", e)))?
+            .set_mode(mode)
+    }
+
+    // Whether io_uring operations go through a kernel ring rather than the
+    // pread/pwrite fallback
+    pub fn io_uring_kernel_backed(&self) -> Result<bool> {
+        Ok(self
+            .io_uring
+            .lock()
+            .map_err(|e| DbError::Storage(format!("Mutex poisoned: {}", e)))?
+            .is_kernel_backed())
+    }
+
+    // Queue a page read via io_uring; the page arrives with its completion
     pub fn read_page_io_uring(&self, page_id: PageId) -> Result<()> {
         let offset = page_id as u64 * self.page_size as u64;
-        let op = IoUringOp::read(page_id, offset, self.page_size);
+        let mut op = IoUringOp::read(page_id, offset, self.page_size);
+
+        // A page still in the write-behind buffer is newer than the file's copy
+        let buffered = self
+            .write_behind
+            .lock()
+            .map_err(|e| DbError::Storage(format!("Mutex poisoned: {}", e)))?
+            .buffer
+            .get(&page_id)
+            .cloned();
 
         let mut io_uring = self
             .io_uring
             .lock()
             .map_err(|e| DbError::Storage(format!("Mutex poisoned: {}", e)))?;
-        io_uring.submit_op(op)?;
+        match buffered {
+            Some(data) => {
+                op.data = data;
+                io_uring.complete_from_memory(op);
+                self.stats.write().write_behind_hits += 1;
+            }
+            None => io_uring.submit_op(op)?,
+        }
 
         self.stats.write().io_uring_ops += 1;
 
         Ok(())
     }
 
-    // Submit async write via io_uring
+    // Queue a page write via io_uring
     pub fn write_page_io_uring(&self, page: &Page) -> Result<()> {
         let offset = page.id as u64 * self.page_size as u64;
         // TODO: MEMORY COPY #5 - page.data.clone() for io_uring submission
@@ -1057,6 +1477,17 @@ impl DiskManager {
         // See: diagrams/02_storage_layer_flow.md - Issue #3.1
         let op = IoUringOp::write(page.id, offset, page.data.clone());
 
+        // Buffered copies of the page are superseded by this write
+        self.read_ahead
+            .lock()
+            .map_err(|e| DbError::Storage(format!("Mutex poisoned: {}", e)))?
+            .buffer
+            .remove(&page.id);
+        self.write_behind
+            .lock()
+            .map_err(|e| DbError::Storage(format!("Mutex poisoned: {}", e)))?
+            .discard(page.id);
+
         let mut io_uring = self
             .io_uring
             .lock()
@@ -1077,11 +1508,9 @@ impl DiskManager {
         io_uring.submit_batch()
     }
 
-    // Wait for io_uring completions
-    pub fn wait_io_uring_completions(
-        &self,
-        min_complete: usize,
-    ) -> Result<Vec<(u64, Result<usize>)>> {
+    // Wait for at least `min_complete` io_uring completions and take every
+    // completion that is ready
+    pub fn wait_io_uring_completions(&self, min_complete: usize) -> Result<Vec<IoUringCompletion>> {
         let mut io_uring = self
             .io_uring
             .lock()
@@ -1137,7 +1566,8 @@ impl DiskManager {
 #[cfg(test)]
 mod tests {
     use super::IoScheduler;
-    use crate::storage::disk::{IoOpType, IoOperation};
+    use crate::storage::disk::{IoOpType, IoOperation, IoUringMode};
+    use crate::storage::page::Page;
     use crate::storage::{DiskManager, IoPriority};
     use crate::DbError;
     use tempfile::tempdir;
@@ -1198,6 +1628,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_io_uring_round_trip() -> Result<(), DbError> {
+        for mode in [IoUringMode::Auto, IoUringMode::Sync] {
+            let dir = tempdir()?;
+            let dm = DiskManager::new(dir.path().to_str().unwrap(), 4096)?;
+            dm.set_io_uring_mode(mode)?;
+
+            for page_id in 0..8 {
+                let mut page = Page::new(page_id, 4096);
+                page.data.fill(page_id as u8 + 1);
+                dm.write_page_io_uring(&page)?;
+            }
+            assert_eq!(dm.submit_io_uring_batch()?, 8);
+            let writes = dm.wait_io_uring_completions(8)?;
+            assert_eq!(writes.len(), 8);
+            assert!(writes.iter().all(|c| c.result.is_ok()));
+
+            for page_id in (0..8).rev() {
+                dm.read_page_io_uring(page_id)?;
+            }
+            dm.submit_io_uring_batch()?;
+            let reads = dm.wait_io_uring_completions(8)?;
+            assert_eq!(reads.len(), 8);
+            for completion in reads {
+                let page = completion.page.expect("read completion carries the page");
+                assert!(page.data.iter().all(|&b| b == page.id as u8 + 1));
+            }
+
+            // Reading past the end of the file fails instead of inventing a page
+            dm.read_page_io_uring(100)?;
+            dm.submit_io_uring_batch()?;
+            let past_end = dm.wait_io_uring_completions(1)?;
+            assert!(past_end[0].result.is_err() && past_end[0].page.is_none());
+
+            // The synchronous path still sees pages written through io_uring
+            assert_eq!(dm.read_page(3)?.data[0], 4);
+        }
+
+        Ok(())
+    }
+
     struct ScheduleInfo {
         // Placeholder for future scheduling metadata
     }
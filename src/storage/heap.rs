@@ -0,0 +1,1295 @@
+// Heap file table storage for RustyDB
+// Stores table rows in chains of slotted pages managed by the buffer pool.
+// Features: stable row ids, page reuse after DROP/TRUNCATE, persistent table directory,
+// write-ahead logged row changes with transactional rollback
+
+use crate::catalog::{IndexDefinition, Schema};
+use crate::common::{PageId, TransactionId, Value};
+use crate::error::{DbError, Result};
+use crate::index::disk_btree::IndexFile;
+use crate::index::table_index::{TableIndex, TableIndexes};
+use crate::storage::buffer::BufferPoolManager;
+use crate::storage::disk::DiskManager;
+use crate::storage::page::{Page, SlotId, SlottedPage};
+use crate::transaction::recovery::{compensation, RecoverablePages};
+use crate::transaction::row_versions::{self, RowVersions};
+use crate::transaction::wal::{LogRecord, WALManager, LSN};
+use bincode::{Decode, Encode};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Page holding the root of the table directory heap
+const DIRECTORY_PAGE_ID: PageId = 0;
+
+/// Default page size for table storage
+pub const DEFAULT_HEAP_PAGE_SIZE: usize = 4096;
+
+/// Default number of buffer pool frames for table storage
+pub const DEFAULT_HEAP_POOL_SIZE: usize = 1024;
+
+/// Slot directory entry overhead per record (mirrors the slotted page layout)
+const SLOT_OVERHEAD: usize = 4;
+
+// Physical address of a row: page plus slot within that page
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode,
+)]
+pub struct RowId {
+    pub page_id: PageId,
+    pub slot_id: SlotId,
+}
+
+impl RowId {
+    pub fn new(page_id: PageId, slot_id: SlotId) -> Self {
+        Self { page_id, slot_id }
+    }
+
+    /// Pack into a single u64 (48-bit page id, 16-bit slot id) for index entries
+    pub fn to_u64(&self) -> u64 {
+        (self.page_id << 16) | self.slot_id as u64
+    }
+
+    pub fn from_u64(value: u64) -> Self {
+        Self {
+            page_id: value >> 16,
+            slot_id: (value & 0xFFFF) as SlotId,
+        }
+    }
+}
+
+impl std::fmt::Display for RowId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({},{})", self.page_id, self.slot_id)
+    }
+}
+
+// Entry in the table directory heap
+#[derive(Debug, Clone, Encode, Decode)]
+enum DirectoryEntry {
+    Table { name: String, first_page_id: PageId },
+    FreePage(PageId),
+}
+
+// Log state of one table store transaction
+struct TxnLog {
+    id: TransactionId,
+    // LSN of the transaction's latest record; None until Begin is logged
+    last_lsn: Option<LSN>,
+    // Row changes made so far, newest last, with the heap they were made in
+    changes: Vec<(LSN, LogRecord, Arc<Mutex<HeapFile>>)>,
+}
+
+// A row change as it is logged, with the images needed to redo and undo it
+enum RowChange<'a> {
+    Insert(&'a [u8]),
+    Update { before: Vec<u8>, after: &'a [u8] },
+    Delete(Vec<u8>),
+}
+
+// Where a heap change is logged: the store's WAL and the transaction making it
+struct Logged<'a> {
+    wal: &'a WALManager,
+    txn: &'a mut TxnLog,
+    heap: &'a Arc<Mutex<HeapFile>>,
+}
+
+impl Logged<'_> {
+    fn record(&mut self, rid: RowId, change: RowChange) -> Result<LSN> {
+        let txn_id = self.txn.id;
+        if self.txn.last_lsn.is_none() {
+            let lsn = self.wal.append_blocking(LogRecord::Begin {
+                txn_id,
+                timestamp: SystemTime::now(),
+            })?;
+            self.txn.last_lsn = Some(lsn);
+        }
+
+        // Undoing this change continues with the one before it
+        let undo_next_lsn = self.txn.changes.last().map(|(lsn, _, _)| *lsn);
+        let (page_id, offset) = (rid.page_id, rid.slot_id as u32);
+        let record = match change {
+            RowChange::Insert(data) => LogRecord::Insert {
+                txn_id,
+                page_id,
+                offset,
+                data: data.to_vec(),
+                undo_next_lsn,
+            },
+            RowChange::Update { before, after } => LogRecord::Update {
+                txn_id,
+                page_id,
+                offset,
+                before_image: before,
+                after_image: after.to_vec(),
+                undo_next_lsn,
+            },
+            RowChange::Delete(data) => LogRecord::Delete {
+                txn_id,
+                page_id,
+                offset,
+                deleted_data: data,
+                undo_next_lsn,
+            },
+        };
+
+        let lsn = self.wal.append_blocking(record.clone())?;
+        self.txn.last_lsn = Some(lsn);
+        self.txn.changes.push((lsn, record, self.heap.clone()));
+        Ok(lsn)
+    }
+}
+
+// A chain of slotted pages linked through the page header's next pointer
+struct HeapFile {
+    pages: Vec<PageId>,
+    // Approximate free bytes per page, parallel to `pages`
+    free_space: Vec<usize>,
+    // Position of each page in `pages`, so row access does not walk the chain
+    positions: HashMap<PageId, usize>,
+    // Set once the table is dropped so that writers still holding a handle fail
+    dropped: bool,
+}
+
+impl HeapFile {
+    // Load an existing chain starting at `first_page_id`
+    fn open(pool: &BufferPoolManager, first_page_id: PageId) -> Result<Self> {
+        let mut pages = Vec::new();
+        let mut free_space = Vec::new();
+        let mut positions = HashMap::new();
+        let mut next = Some(first_page_id);
+
+        while let Some(page_id) = next {
+            if positions.insert(page_id, pages.len()).is_some() {
+                return Err(DbError::Corruption(format!(
+                    "Cycle in heap page chain at page {}",
+                    page_id
+                )));
+            }
+            let page = read_page(pool, page_id)?;
+            next = page.page().next_page_id();
+            free_space.push(page.free_space() as usize);
+            pages.push(page_id);
+        }
+
+        Ok(Self {
+            pages,
+            free_space,
+            positions,
+            dropped: false,
+        })
+    }
+
+    fn check_live(&self) -> Result<()> {
+        if self.dropped {
+            return Err(DbError::NotFound("Table storage was dropped".to_string()));
+        }
+        Ok(())
+    }
+
+    fn insert(
+        &mut self,
+        pool: &BufferPoolManager,
+        allocator: &dyn Fn() -> Result<PageId>,
+        data: &[u8],
+        mut log: Option<&mut Logged>,
+    ) -> Result<RowId> {
+        self.check_live()?;
+        let needed = data.len() + SLOT_OVERHEAD;
+
+        // Prefer the most recently added pages; they are the likeliest to have room
+        for idx in (0..self.pages.len()).rev() {
+            if self.free_space[idx] < needed {
+                continue;
+            }
+            let page_id = self.pages[idx];
+            let (slot, free) = modify_row(pool, page_id, log.as_deref_mut(), |page| {
+                let slot = page.insert_record(data);
+                let change = slot.map(|slot_id| (slot_id, RowChange::Insert(data)));
+                ((slot, page.free_space() as usize), change)
+            })?;
+            self.free_space[idx] = free;
+            if let Some(slot_id) = slot {
+                return Ok(RowId::new(page_id, slot_id));
+            }
+        }
+
+        // No page has room: extend the chain
+        let new_page_id = allocator()?;
+        let last_page_id = *self.pages.last().expect("heap file has at least one page");
+        modify_raw_page(pool, last_page_id, |page| {
+            page.set_next_page_id(Some(new_page_id))
+        })?;
+        // The log names pages by id, so the extended chain must be on disk
+        // before a change to the new page is logged
+        if log.is_some() {
+            pool.flush_pages(&[last_page_id, new_page_id])?;
+        }
+
+        let (slot, free) = modify_row(pool, new_page_id, log, |page| {
+            let slot = page.insert_record(data);
+            let change = slot.map(|slot_id| (slot_id, RowChange::Insert(data)));
+            ((slot, page.free_space() as usize), change)
+        })?;
+        self.positions.insert(new_page_id, self.pages.len());
+        self.pages.push(new_page_id);
+        self.free_space.push(free);
+
+        slot.map(|slot_id| RowId::new(new_page_id, slot_id))
+            .ok_or_else(|| DbError::Storage("Row does not fit into an empty page".to_string()))
+    }
+
+    fn get(&self, pool: &BufferPoolManager, rid: RowId) -> Result<Option<Vec<u8>>> {
+        if !self.positions.contains_key(&rid.page_id) {
+            return Ok(None);
+        }
+        Ok(read_page(pool, rid.page_id)?.get_record(rid.slot_id))
+    }
+
+    fn delete(
+        &mut self,
+        pool: &BufferPoolManager,
+        rid: RowId,
+        log: Option<&mut Logged>,
+    ) -> Result<bool> {
+        self.check_live()?;
+        let Some(&idx) = self.positions.get(&rid.page_id) else {
+            return Ok(false);
+        };
+        let (deleted, free) = modify_row(pool, rid.page_id, log, |page| {
+            let data = page.get_record(rid.slot_id);
+            let deleted = page.delete_record(rid.slot_id);
+            let change = data
+                .filter(|_| deleted)
+                .map(|data| (rid.slot_id, RowChange::Delete(data)));
+            ((deleted, page.free_space() as usize), change)
+        })?;
+        self.free_space[idx] = free;
+        Ok(deleted)
+    }
+
+    // Update in place when possible, otherwise move the row and return its new id
+    fn update(
+        &mut self,
+        pool: &BufferPoolManager,
+        allocator: &dyn Fn() -> Result<PageId>,
+        rid: RowId,
+        data: &[u8],
+        mut log: Option<&mut Logged>,
+    ) -> Result<RowId> {
+        self.check_live()?;
+        let Some(&idx) = self.positions.get(&rid.page_id) else {
+            return Err(DbError::NotFound(format!("Row {} not found", rid)));
+        };
+
+        let (exists, updated, free) = modify_row(pool, rid.page_id, log.as_deref_mut(), |page| {
+            let before = page.get_record(rid.slot_id);
+            let exists = before.is_some();
+            let updated = exists && page.update_record(rid.slot_id, data);
+            let change = before.filter(|_| updated).map(|before| {
+                (
+                    rid.slot_id,
+                    RowChange::Update {
+                        before,
+                        after: data,
+                    },
+                )
+            });
+            ((exists, updated, page.free_space() as usize), change)
+        })?;
+        self.free_space[idx] = free;
+
+        if !exists {
+            return Err(DbError::NotFound(format!("Row {} not found", rid)));
+        }
+        if updated {
+            return Ok(rid);
+        }
+
+        // Page is full: relocate the row
+        self.delete(pool, rid, log.as_deref_mut())?;
+        self.insert(pool, allocator, data, log)
+    }
+
+    fn scan(&self, pool: &BufferPoolManager) -> Result<Vec<(RowId, Vec<u8>)>> {
+        let mut rows = Vec::new();
+        for &page_id in &self.pages {
+            let page = read_page(pool, page_id)?;
+            rows.extend(
+                page.records()
+                    .into_iter()
+                    .map(|(slot_id, data)| (RowId::new(page_id, slot_id), data)),
+            );
+        }
+        Ok(rows)
+    }
+
+    // Empty the first page and detach the rest of the chain, returning the
+    // detached pages so the caller can recycle them
+    fn truncate(&mut self, pool: &BufferPoolManager, lsn: LSN) -> Result<Vec<PageId>> {
+        self.check_live()?;
+        let first = self.pages[0];
+        reset_page(pool, first, lsn)?;
+
+        let detached = self.pages.split_off(1);
+        self.free_space.truncate(1);
+        self.positions.retain(|_, idx| *idx == 0);
+        self.free_space[0] = read_page(pool, first)?.free_space() as usize;
+        Ok(detached)
+    }
+}
+
+// Fetch a read-only copy of a page and release the pin immediately
+fn read_page(pool: &BufferPoolManager, page_id: PageId) -> Result<SlottedPage> {
+    let page = pool.fetch_page(page_id)?;
+    pool.unpin_page(page_id, false)?;
+    Ok(SlottedPage::from_page(page))
+}
+
+// Fetch a page, apply `f` to it as a slotted page and publish the result
+fn modify_page<T>(
+    pool: &BufferPoolManager,
+    page_id: PageId,
+    f: impl FnOnce(&mut SlottedPage) -> T,
+) -> Result<T> {
+    let mut page = pool.fetch_page(page_id)?;
+    page.is_dirty = false;
+    let mut slotted = SlottedPage::from_page(page);
+    let out = f(&mut slotted);
+    publish_page(pool, slotted.into_page())?;
+    Ok(out)
+}
+
+// Like modify_page, for a change to a single row. With a log, the change is
+// logged before the page is published and the page is stamped with the LSN
+// of its record; a change that cannot be logged is discarded.
+fn modify_row<'a, T>(
+    pool: &BufferPoolManager,
+    page_id: PageId,
+    log: Option<&mut Logged>,
+    f: impl FnOnce(&mut SlottedPage) -> (T, Option<(SlotId, RowChange<'a>)>),
+) -> Result<T> {
+    let mut page = pool.fetch_page(page_id)?;
+    page.is_dirty = false;
+    let mut slotted = SlottedPage::from_page(page);
+    let (out, change) = f(&mut slotted);
+    let mut page = slotted.into_page();
+
+    if let (Some(log), Some((slot_id, change))) = (log, change) {
+        match log.record(RowId::new(page_id, slot_id), change) {
+            Ok(lsn) => page.set_lsn(lsn),
+            Err(e) => {
+                pool.unpin_page(page_id, false)?;
+                return Err(e);
+            }
+        }
+    }
+    publish_page(pool, page)?;
+    Ok(out)
+}
+
+fn modify_raw_page<T>(
+    pool: &BufferPoolManager,
+    page_id: PageId,
+    f: impl FnOnce(&mut Page) -> T,
+) -> Result<T> {
+    let mut page = pool.fetch_page(page_id)?;
+    page.is_dirty = false;
+    let out = f(&mut page);
+    publish_page(pool, page)?;
+    Ok(out)
+}
+
+// Install a modified page copy (if it changed) and release the pin
+fn publish_page(pool: &BufferPoolManager, page: Page) -> Result<()> {
+    let dirty = page.is_dirty;
+    let published = if dirty {
+        pool.write_page(&page)
+    } else {
+        Ok(())
+    };
+    pool.unpin_page(page.id, dirty)?;
+    published
+}
+
+// Overwrite a page with a fresh, empty slotted page, stamped with `lsn` so
+// that recovery does not redo older changes into it
+fn reset_page(pool: &BufferPoolManager, page_id: PageId, lsn: LSN) -> Result<()> {
+    let page_size = pool.page_size();
+    modify_raw_page(pool, page_id, |page| {
+        *page = Page::new(page_id, page_size);
+        page.set_lsn(lsn);
+    })
+}
+
+// Mutable state of the table store guarded by a single lock
+struct StoreState {
+    directory: HeapFile,
+    // Table name -> (directory entry, first page of the table's heap)
+    entries: HashMap<String, (RowId, PageId)>,
+    free_pages: Vec<(PageId, RowId)>,
+}
+
+// Row storage for all tables, backed by heap files in a single data file
+//
+// Page 0 holds the root of a directory heap that maps table names to the
+// first page of their heap file and tracks recycled pages, so the full set of
+// tables can be reopened after a restart.
+//
+// Once a WAL is attached, row changes are logged physiologically (page and
+// slot, with before/after images) and pages carry the LSN of their last
+// change, so ARIES recovery can redo and undo them. Changes to page chains and
+// the directory are not logged; they are forced to disk instead.
+pub struct TableStore {
+    pool: BufferPoolManager,
+    page_size: usize,
+    state: Mutex<StoreState>,
+    tables: RwLock<HashMap<String, Arc<Mutex<HeapFile>>>>,
+    wal: RwLock<Option<Arc<WALManager>>>,
+    next_txn_id: AtomicU64,
+    // Older committed row images, for SQL transactions' snapshots
+    versions: RowVersions,
+    // B+Tree indexes of the tables, in their own file
+    indexes: Arc<IndexFile>,
+    // The indexes of each table, kept in step with the heap by commits
+    table_indexes: TableIndexes,
+    // Directory removed on drop for scratch stores
+    scratch_dir: Option<PathBuf>,
+}
+
+/// A group of row changes that commit or roll back together
+///
+/// Dropping a transaction without committing it rolls it back. On a store
+/// without a WAL, changes are applied immediately and cannot be rolled back.
+pub struct StoreTransaction {
+    store: Arc<TableStore>,
+    log: Mutex<TxnLog>,
+    finished: bool,
+}
+
+impl StoreTransaction {
+    pub fn insert_row(&self, table: &str, row: &[Value]) -> Result<RowId> {
+        self.store
+            .insert_in(Some(&mut *self.log.lock()), table, row)
+    }
+
+    /// Replace a row; the returned id differs from `rid` if the row had to move
+    pub fn update_row(&self, table: &str, rid: RowId, row: &[Value]) -> Result<RowId> {
+        self.store
+            .update_in(Some(&mut *self.log.lock()), table, rid, row)
+    }
+
+    pub fn delete_row(&self, table: &str, rid: RowId) -> Result<bool> {
+        self.store
+            .delete_in(Some(&mut *self.log.lock()), table, rid)
+    }
+
+    /// Make the transaction's changes durable
+    pub fn commit(mut self) -> Result<()> {
+        self.store.commit_log(self.log.get_mut())?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Undo the transaction's changes
+    pub fn rollback(mut self) -> Result<()> {
+        self.finished = true;
+        self.store.rollback_log(self.log.get_mut())
+    }
+}
+
+impl Drop for StoreTransaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            if let Err(e) = self.store.rollback_log(self.log.get_mut()) {
+                tracing::error!("Failed to roll back table store transaction: {}", e);
+            }
+        }
+    }
+}
+
+static SCRATCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl TableStore {
+    /// Open (or create) the table store in `data_dir`
+    pub fn open(data_dir: &str, page_size: usize, pool_size: usize) -> Result<Self> {
+        let disk_manager = DiskManager::new(data_dir, page_size)?;
+        let is_new = disk_manager.get_num_pages() == 0;
+        let pool = BufferPoolManager::new(pool_size, disk_manager);
+
+        if is_new {
+            let root = pool.new_page()?;
+            if root.id != DIRECTORY_PAGE_ID {
+                return Err(DbError::Storage(format!(
+                    "Expected directory at page {}, got page {}",
+                    DIRECTORY_PAGE_ID, root.id
+                )));
+            }
+            pool.write_page(&Page::new(DIRECTORY_PAGE_ID, page_size))?;
+            pool.unpin_page(DIRECTORY_PAGE_ID, true)?;
+        }
+
+        let indexes = Arc::new(IndexFile::open(
+            &format!("{}/indexes", data_dir),
+            pool_size,
+        )?);
+        let directory = HeapFile::open(&pool, DIRECTORY_PAGE_ID)?;
+        let mut entries = HashMap::new();
+        let mut free_pages = Vec::new();
+        let mut tables = HashMap::new();
+
+        for (rid, bytes) in directory.scan(&pool)? {
+            let (entry, _): (DirectoryEntry, usize) =
+                bincode::decode_from_slice(&bytes, bincode::config::standard())?;
+            match entry {
+                DirectoryEntry::Table {
+                    name,
+                    first_page_id,
+                } => {
+                    let heap = HeapFile::open(&pool, first_page_id)?;
+                    tables.insert(name.clone(), Arc::new(Mutex::new(heap)));
+                    entries.insert(name, (rid, first_page_id));
+                }
+                DirectoryEntry::FreePage(page_id) => free_pages.push((page_id, rid)),
+            }
+        }
+
+        Ok(Self {
+            pool,
+            page_size,
+            state: Mutex::new(StoreState {
+                directory,
+                entries,
+                free_pages,
+            }),
+            tables: RwLock::new(tables),
+            wal: RwLock::new(None),
+            next_txn_id: AtomicU64::new(1),
+            versions: RowVersions::new(),
+            table_indexes: TableIndexes::new(indexes.clone()),
+            indexes,
+            scratch_dir: None,
+        })
+    }
+
+    /// Log row changes to `wal` from now on
+    ///
+    /// Run recovery from the same log against the store before writing to it.
+    pub fn attach_wal(&self, wal: Arc<WALManager>) {
+        self.pool.set_wal(wal.clone());
+        *self.wal.write() = Some(wal);
+    }
+
+    /// Start a transaction
+    pub fn begin(self: &Arc<Self>) -> StoreTransaction {
+        StoreTransaction {
+            store: self.clone(),
+            log: Mutex::new(self.new_txn_log()),
+            finished: false,
+        }
+    }
+
+    /// Open a throw-away store in a fresh temporary directory
+    ///
+    /// The directory is removed when the store is dropped. Used by executors
+    /// that are not attached to a database instance (tests, ad-hoc tools).
+    pub fn temporary() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "rustydb-scratch-{}-{}",
+            std::process::id(),
+            SCRATCH_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut store = Self::open(
+            &dir.display().to_string(),
+            DEFAULT_HEAP_PAGE_SIZE,
+            DEFAULT_HEAP_POOL_SIZE,
+        )?;
+        store.scratch_dir = Some(dir);
+        Ok(store)
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// The file holding the tables' B+Tree indexes; its log is attached
+    /// separately
+    pub fn indexes(&self) -> &Arc<IndexFile> {
+        &self.indexes
+    }
+
+    /// Committed row versions that SQL transactions read their snapshots from
+    pub fn versions(&self) -> &RowVersions {
+        &self.versions
+    }
+
+    /// Build a new index over the committed rows of its table
+    pub fn create_index(&self, schema: &Schema, definition: IndexDefinition) -> Result<()> {
+        self.versions
+            .excluding_commits(|| self.table_indexes.create(self, schema, definition))
+    }
+
+    /// Open the tree of an index from the catalog after a restart, or build
+    /// it again with `rebuild` (its log may not match the rows any more)
+    pub fn load_index(
+        &self,
+        schema: &Schema,
+        definition: IndexDefinition,
+        rebuild: bool,
+    ) -> Result<()> {
+        self.versions
+            .excluding_commits(|| self.table_indexes.load(self, schema, definition, rebuild))
+    }
+
+    /// Drop index `name`; `false` if it has no tree
+    pub fn drop_index(&self, name: &str) -> Result<bool> {
+        self.versions
+            .excluding_commits(|| self.table_indexes.remove(name))
+    }
+
+    /// Drop the indexes of `table`, returning their definitions, e.g. to
+    /// build them again after its rows have been rewritten
+    pub fn detach_indexes(&self, table: &str) -> Result<Vec<IndexDefinition>> {
+        self.versions
+            .excluding_commits(|| self.table_indexes.detach_table(table))
+    }
+
+    pub fn index(&self, name: &str) -> Option<Arc<TableIndex>> {
+        self.table_indexes.get(name)
+    }
+
+    /// Indexes of `table`
+    pub fn table_indexes(&self, table: &str) -> Vec<Arc<TableIndex>> {
+        self.table_indexes.of_table(table)
+    }
+
+    /// Fail if a commit writing `rows` to `table` would break a unique
+    /// index; see `TableIndexes::check_unique`
+    pub(crate) fn check_unique(
+        &self,
+        table: &str,
+        rows: &[(Option<RowId>, Option<&[Value]>)],
+    ) -> Result<()> {
+        self.table_indexes.check_unique(table, rows)
+    }
+
+    /// Change the indexes along with a commit's heap changes; runs under
+    /// the commit lock
+    pub(crate) fn apply_index_changes(&self, changes: &[row_versions::RowChange]) -> Result<()> {
+        self.table_indexes.apply(changes)
+    }
+
+    /// Undo `apply_index_changes` after the heap changes failed to commit
+    pub(crate) fn revert_index_changes(&self, changes: &[row_versions::RowChange]) -> Result<()> {
+        self.table_indexes.revert(changes)
+    }
+
+    pub fn has_table(&self, name: &str) -> bool {
+        self.tables.read().contains_key(name)
+    }
+
+    pub fn list_tables(&self) -> Vec<String> {
+        self.tables.read().keys().cloned().collect()
+    }
+
+    /// Create an empty heap file for `name`
+    pub fn create_table(&self, name: &str) -> Result<()> {
+        let mut state = self.state.lock();
+        if state.entries.contains_key(name) {
+            return Err(DbError::AlreadyExists(format!(
+                "Storage for table {} already exists",
+                name
+            )));
+        }
+
+        let first_page_id = self.allocate_page(&mut state)?;
+        let entry = DirectoryEntry::Table {
+            name: name.to_string(),
+            first_page_id,
+        };
+        let rid = self.insert_directory_entry(&mut state, &entry)?;
+        state.entries.insert(name.to_string(), (rid, first_page_id));
+
+        let heap = HeapFile::open(&self.pool, first_page_id)?;
+        self.tables
+            .write()
+            .insert(name.to_string(), Arc::new(Mutex::new(heap)));
+        self.force_structure()
+    }
+
+    /// Create an empty heap file for `name`, discarding any existing one
+    pub fn reset_table(&self, name: &str) -> Result<()> {
+        if self.has_table(name) {
+            self.drop_table(name)?;
+        }
+        self.create_table(name)
+    }
+
+    /// Drop the heap file for `name`, recycling all of its pages
+    pub fn drop_table(&self, name: &str) -> Result<()> {
+        let (rid, heap) = {
+            let mut state = self.state.lock();
+            let (rid, _) = state.entries.remove(name).ok_or_else(|| {
+                DbError::NotFound(format!("Storage for table {} not found", name))
+            })?;
+            let heap = self.tables.write().remove(name).ok_or_else(|| {
+                DbError::NotFound(format!("Storage for table {} not found", name))
+            })?;
+            (rid, heap)
+        };
+        self.versions.forget_table(name);
+        self.detach_indexes(name)?;
+
+        // Writers lock the heap before the store state, so never hold both here
+        let pages = {
+            let mut heap = heap.lock();
+            heap.dropped = true;
+            heap.positions.clear();
+            std::mem::take(&mut heap.pages)
+        };
+
+        let mut state = self.state.lock();
+        state.directory.delete(&self.pool, rid, None)?;
+        for page_id in pages {
+            self.release_page(&mut state, page_id)?;
+        }
+        self.force_structure()
+    }
+
+    /// Rename the heap file for `from` to `to`
+    pub fn rename_table(&self, from: &str, to: &str) -> Result<()> {
+        let mut state = self.state.lock();
+        if state.entries.contains_key(to) {
+            return Err(DbError::AlreadyExists(format!(
+                "Storage for table {} already exists",
+                to
+            )));
+        }
+        let (rid, first_page_id) = state
+            .entries
+            .remove(from)
+            .ok_or_else(|| DbError::NotFound(format!("Storage for table {} not found", from)))?;
+
+        {
+            let mut tables = self.tables.write();
+            if let Some(heap) = tables.remove(from) {
+                tables.insert(to.to_string(), heap);
+            }
+        }
+        self.versions.forget_table(from);
+
+        let entry = DirectoryEntry::Table {
+            name: to.to_string(),
+            first_page_id,
+        };
+        let bytes = bincode::encode_to_vec(&entry, bincode::config::standard())?;
+        let new_rid =
+            state
+                .directory
+                .update(&self.pool, &|| self.new_directory_page(), rid, &bytes, None)?;
+        state
+            .entries
+            .insert(to.to_string(), (new_rid, first_page_id));
+        self.force_structure()
+    }
+
+    /// Insert a row in a transaction of its own
+    pub fn insert_row(&self, table: &str, row: &[Value]) -> Result<RowId> {
+        self.autocommit(|txn| self.insert_in(txn, table, row))
+    }
+
+    pub fn get_row(&self, table: &str, rid: RowId) -> Result<Option<Vec<Value>>> {
+        let heap = self.heap(table)?;
+        let bytes = heap.lock().get(&self.pool, rid)?;
+        bytes.map(|b| Self::decode_row(&b)).transpose()
+    }
+
+    /// Replace a row in a transaction of its own; the returned id differs
+    /// from `rid` if the row had to move
+    pub fn update_row(&self, table: &str, rid: RowId, row: &[Value]) -> Result<RowId> {
+        self.autocommit(|txn| self.update_in(txn, table, rid, row))
+    }
+
+    /// Delete a row in a transaction of its own
+    pub fn delete_row(&self, table: &str, rid: RowId) -> Result<bool> {
+        self.autocommit(|txn| self.delete_in(txn, table, rid))
+    }
+
+    fn insert_in(&self, txn: Option<&mut TxnLog>, table: &str, row: &[Value]) -> Result<RowId> {
+        let bytes = self.encode_row(row)?;
+        let handle = self.heap(table)?;
+        let wal = self.wal.read().clone();
+        let mut log = Self::logged(wal.as_deref(), txn, &handle);
+        let mut heap = handle.lock();
+        heap.insert(
+            &self.pool,
+            &|| self.allocate_page_locked(),
+            &bytes,
+            log.as_mut(),
+        )
+    }
+
+    fn update_in(
+        &self,
+        txn: Option<&mut TxnLog>,
+        table: &str,
+        rid: RowId,
+        row: &[Value],
+    ) -> Result<RowId> {
+        let bytes = self.encode_row(row)?;
+        let handle = self.heap(table)?;
+        let wal = self.wal.read().clone();
+        let mut log = Self::logged(wal.as_deref(), txn, &handle);
+        let mut heap = handle.lock();
+        heap.update(
+            &self.pool,
+            &|| self.allocate_page_locked(),
+            rid,
+            &bytes,
+            log.as_mut(),
+        )
+    }
+
+    fn delete_in(&self, txn: Option<&mut TxnLog>, table: &str, rid: RowId) -> Result<bool> {
+        let handle = self.heap(table)?;
+        let wal = self.wal.read().clone();
+        let mut log = Self::logged(wal.as_deref(), txn, &handle);
+        let mut heap = handle.lock();
+        heap.delete(&self.pool, rid, log.as_mut())
+    }
+
+    // Changes are logged only with both a WAL and a transaction
+    fn logged<'a>(
+        wal: Option<&'a WALManager>,
+        txn: Option<&'a mut TxnLog>,
+        heap: &'a Arc<Mutex<HeapFile>>,
+    ) -> Option<Logged<'a>> {
+        Some(Logged {
+            wal: wal?,
+            txn: txn?,
+            heap,
+        })
+    }
+
+    fn new_txn_log(&self) -> TxnLog {
+        TxnLog {
+            id: self.next_txn_id.fetch_add(1, Ordering::Relaxed),
+            last_lsn: None,
+            changes: Vec::new(),
+        }
+    }
+
+    // Run a single change as its own transaction
+    fn autocommit<T>(&self, f: impl FnOnce(Option<&mut TxnLog>) -> Result<T>) -> Result<T> {
+        if self.wal.read().is_none() {
+            return f(None);
+        }
+        let mut txn = self.new_txn_log();
+        let result = f(Some(&mut txn));
+        match result {
+            Ok(_) => self.commit_log(&mut txn)?,
+            Err(_) => self.rollback_log(&mut txn)?,
+        }
+        result
+    }
+
+    fn commit_log(&self, txn: &mut TxnLog) -> Result<()> {
+        // Nothing to do for a transaction that never logged a change
+        let (Some(wal), Some(_)) = (self.wal.read().clone(), txn.last_lsn) else {
+            return Ok(());
+        };
+        wal.append_blocking(LogRecord::Commit {
+            txn_id: txn.id,
+            timestamp: SystemTime::now(),
+        })?;
+        txn.last_lsn = None;
+        txn.changes.clear();
+        Ok(())
+    }
+
+    // Undo a transaction's changes newest first, logging a CLR for each
+    fn rollback_log(&self, txn: &mut TxnLog) -> Result<()> {
+        let (Some(wal), Some(_)) = (self.wal.read().clone(), txn.last_lsn) else {
+            return Ok(());
+        };
+
+        while let Some((_, record, heap)) = txn.changes.pop() {
+            let heap = heap.lock();
+            // The pages of a dropped table may already belong to another one
+            if heap.dropped {
+                continue;
+            }
+            if let Some(clr) = compensation(&record) {
+                let lsn = wal.append_blocking(clr.clone())?;
+                if let LogRecord::CLR { redo_operation, .. } = &clr {
+                    self.apply_logged(lsn, redo_operation)?;
+                }
+            }
+        }
+
+        wal.append_blocking(LogRecord::Abort {
+            txn_id: txn.id,
+            timestamp: SystemTime::now(),
+        })?;
+        txn.last_lsn = None;
+        Ok(())
+    }
+
+    /// Read every live row of `table` in physical order
+    pub fn scan(&self, table: &str) -> Result<Vec<(RowId, Vec<Value>)>> {
+        let heap = self.heap(table)?;
+        let records = heap.lock().scan(&self.pool)?;
+        records
+            .into_iter()
+            .map(|(rid, bytes)| Ok((rid, Self::decode_row(&bytes)?)))
+            .collect()
+    }
+
+    /// Remove all rows from `table`, returning the number of rows removed
+    pub fn truncate(&self, table: &str) -> Result<usize> {
+        let heap = self.heap(table)?;
+        let mut heap = heap.lock();
+        let removed = heap.scan(&self.pool)?.len();
+        let detached = heap.truncate(&self.pool, self.reset_lsn())?;
+        drop(heap);
+        self.versions.forget_table(table);
+        self.versions
+            .excluding_commits(|| self.table_indexes.clear_table(table))?;
+
+        let mut state = self.state.lock();
+        for page_id in detached {
+            self.release_page(&mut state, page_id)?;
+        }
+        self.force_structure()?;
+        Ok(removed)
+    }
+
+    /// Buffer pool hits and misses so far, over the heap and index files
+    pub fn buffer_accesses(&self) -> (u64, u64) {
+        let (heap, indexes) = (self.pool.stats(), self.indexes.buffer_stats());
+        (heap.hits + indexes.hits, heap.misses + indexes.misses)
+    }
+
+    /// Write all dirty pages back to the data and index files
+    pub fn flush(&self) -> Result<()> {
+        self.pool.flush_all()?;
+        self.indexes.flush()
+    }
+
+    // LSN for reset pages: every change logged so far predates them
+    fn reset_lsn(&self) -> LSN {
+        self.wal
+            .read()
+            .as_ref()
+            .map_or(0, |wal| wal.current_lsn().saturating_sub(1))
+    }
+
+    // With a log attached, changes to page chains and the directory go
+    // straight to disk: log records name pages by id
+    fn force_structure(&self) -> Result<()> {
+        if self.wal.read().is_some() {
+            self.pool.flush_all()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn heap(&self, table: &str) -> Result<Arc<Mutex<HeapFile>>> {
+        self.tables
+            .read()
+            .get(table)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound(format!("Storage for table {} not found", table)))
+    }
+
+    fn encode_row(&self, row: &[Value]) -> Result<Vec<u8>> {
+        let bytes = bincode::serde::encode_to_vec(row, bincode::config::standard())?;
+        let max = SlottedPage::max_record_size(self.page_size);
+        if bytes.len() > max {
+            return Err(DbError::LimitExceeded(format!(
+                "Row of {} bytes exceeds the maximum row size of {} bytes",
+                bytes.len(),
+                max
+            )));
+        }
+        Ok(bytes)
+    }
+
+    fn decode_row(bytes: &[u8]) -> Result<Vec<Value>> {
+        let (row, _): (Vec<Value>, usize) =
+            bincode::serde::decode_from_slice(bytes, bincode::config::standard())?;
+        Ok(row)
+    }
+
+    fn allocate_page_locked(&self) -> Result<PageId> {
+        let mut state = self.state.lock();
+        self.allocate_page(&mut state)
+    }
+
+    // Hand out a recycled page if available, otherwise grow the data file
+    fn allocate_page(&self, state: &mut StoreState) -> Result<PageId> {
+        if let Some((page_id, rid)) = state.free_pages.pop() {
+            state.directory.delete(&self.pool, rid, None)?;
+            reset_page(&self.pool, page_id, self.reset_lsn())?;
+            // A page still listed as free after a crash would be handed out twice
+            if self.wal.read().is_some() {
+                self.pool.flush_pages(&[rid.page_id, page_id])?;
+            }
+            return Ok(page_id);
+        }
+
+        self.new_directory_page()
+    }
+
+    fn release_page(&self, state: &mut StoreState, page_id: PageId) -> Result<()> {
+        let rid = self.insert_directory_entry(state, &DirectoryEntry::FreePage(page_id))?;
+        state.free_pages.push((page_id, rid));
+        Ok(())
+    }
+
+    fn insert_directory_entry(
+        &self,
+        state: &mut StoreState,
+        entry: &DirectoryEntry,
+    ) -> Result<RowId> {
+        let bytes = bincode::encode_to_vec(entry, bincode::config::standard())?;
+        state
+            .directory
+            .insert(&self.pool, &|| self.new_directory_page(), &bytes, None)
+    }
+
+    // The directory only ever grows with brand new pages: recycled pages are
+    // tracked by the directory itself
+    fn new_directory_page(&self) -> Result<PageId> {
+        let page = self.pool.new_page()?;
+        self.pool.write_page(&Page::new(page.id, self.page_size))?;
+        self.pool.unpin_page(page.id, true)?;
+        Ok(page.id)
+    }
+}
+
+impl RecoverablePages for TableStore {
+    fn apply_logged(&self, lsn: LSN, record: &LogRecord) -> Result<bool> {
+        let (page_id, slot_id) = match record {
+            LogRecord::Insert {
+                page_id, offset, ..
+            }
+            | LogRecord::Update {
+                page_id, offset, ..
+            }
+            | LogRecord::Delete {
+                page_id, offset, ..
+            } => (*page_id, *offset as SlotId),
+            _ => return Ok(false),
+        };
+
+        let mut page = self.pool.fetch_page(page_id)?;
+        if page.lsn() >= lsn {
+            self.pool.unpin_page(page_id, false)?;
+            return Ok(false);
+        }
+        page.is_dirty = false;
+
+        let mut slotted = SlottedPage::from_page(page);
+        let applied = match record {
+            // A row put back by an undo takes another slot if its own was
+            // reused in the meantime
+            LogRecord::Insert { data, .. } => {
+                slotted.insert_record_at(slot_id, data) || slotted.insert_record(data).is_some()
+            }
+            LogRecord::Update { after_image, .. } => {
+                slotted.update_record(slot_id, after_image)
+                    || slotted.insert_record_at(slot_id, after_image)
+            }
+            _ => {
+                slotted.delete_record(slot_id);
+                true
+            }
+        };
+        if !applied {
+            self.pool.unpin_page(page_id, false)?;
+            return Err(DbError::Corruption(format!(
+                "Logged change {} does not fit into page {}",
+                lsn, page_id
+            )));
+        }
+
+        let mut page = slotted.into_page();
+        page.set_lsn(lsn);
+        publish_page(&self.pool, page)?;
+        Ok(true)
+    }
+
+    fn flush_pages(&self) -> Result<()> {
+        self.flush()
+    }
+}
+
+impl Drop for TableStore {
+    fn drop(&mut self) {
+        if let Some(dir) = self.scratch_dir.take() {
+            let _ = std::fs::remove_dir_all(dir);
+        } else {
+            let _ = self.pool.flush_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(values: &[&str]) -> Vec<Value> {
+        values
+            .iter()
+            .map(|v| Value::String(v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_and_scan() -> Result<()> {
+        let store = TableStore::temporary()?;
+        store.create_table("users")?;
+
+        let rid = store.insert_row("users", &row(&["1", "alice"]))?;
+        store.insert_row("users", &row(&["2", "bob"]))?;
+
+        assert_eq!(store.get_row("users", rid)?, Some(row(&["1", "alice"])));
+        assert_eq!(store.scan("users")?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_and_delete() -> Result<()> {
+        let store = TableStore::temporary()?;
+        store.create_table("t")?;
+
+        let rid = store.insert_row("t", &row(&["1", "short"]))?;
+        let rid = store.update_row("t", rid, &row(&["1", &"long".repeat(100)]))?;
+        let updated = store.get_row("t", rid)?.unwrap();
+        assert_eq!(updated[1].to_display_string().len(), 400);
+
+        assert!(store.delete_row("t", rid)?);
+        assert!(store.get_row("t", rid)?.is_none());
+        assert!(store.scan("t")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_values_round_trip() -> Result<()> {
+        let store = TableStore::temporary()?;
+        store.create_table("typed")?;
+
+        let values = vec![
+            Value::Integer(-42),
+            Value::Float(1.5),
+            Value::Null,
+            Value::String("NULL".to_string()),
+            Value::Boolean(true),
+            Value::Timestamp(1_700_000_000_000_000),
+        ];
+        let rid = store.insert_row("typed", &values)?;
+        assert_eq!(store.get_row("typed", rid)?, Some(values));
+        Ok(())
+    }
+
+    #[test]
+    fn test_many_rows_span_pages() -> Result<()> {
+        let store = TableStore::temporary()?;
+        store.create_table("big")?;
+
+        for i in 0..2000 {
+            store.insert_row("big", &row(&[&i.to_string(), "payload-payload-payload"]))?;
+        }
+        let rows = store.scan("big")?;
+        assert_eq!(rows.len(), 2000);
+        let first_page = rows[0].0.page_id;
+        assert!(rows.iter().any(|(rid, _)| rid.page_id != first_page));
+
+        assert_eq!(store.truncate("big")?, 2000);
+        assert!(store.scan("big")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reopen_persists_rows() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rustydb-heap-reopen-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.display().to_string();
+
+        {
+            let store = TableStore::open(&path, DEFAULT_HEAP_PAGE_SIZE, 16)?;
+            store.create_table("orders")?;
+            for i in 0..500 {
+                store.insert_row("orders", &row(&[&i.to_string(), "pending"]))?;
+            }
+            store.flush()?;
+        }
+
+        let store = TableStore::open(&path, DEFAULT_HEAP_PAGE_SIZE, 16)?;
+        assert!(store.has_table("orders"));
+        assert_eq!(store.scan("orders")?.len(), 500);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_recycles_pages() -> Result<()> {
+        let store = TableStore::temporary()?;
+        store.create_table("a")?;
+        for i in 0..300 {
+            store.insert_row("a", &row(&[&i.to_string(), "xxxxxxxxxxxxxxxxxxxxxxxx"]))?;
+        }
+        store.drop_table("a")?;
+        assert!(!store.has_table("a"));
+
+        store.create_table("b")?;
+        store.insert_row("b", &row(&["1"]))?;
+        assert_eq!(store.scan("b")?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_rollback_restores_rows() -> Result<()> {
+        let store = Arc::new(TableStore::temporary()?);
+        let wal_dir = store.scratch_dir.clone().unwrap().join("wal");
+        let config = crate::transaction::wal::WALConfig {
+            enable_group_commit: false,
+            ..Default::default()
+        };
+        store.attach_wal(Arc::new(WALManager::new(wal_dir, config)?));
+        store.create_table("t")?;
+        let kept = store.insert_row("t", &row(&["1", "kept"]))?;
+
+        let txn = store.begin();
+        txn.insert_row("t", &row(&["2", "new"]))?;
+        txn.update_row("t", kept, &row(&["1", "changed"]))?;
+        txn.rollback()?;
+        assert_eq!(store.scan("t")?, vec![(kept, row(&["1", "kept"]))]);
+
+        // Dropping an unfinished transaction rolls it back too
+        {
+            let txn = store.begin();
+            txn.delete_row("t", kept)?;
+        }
+        assert_eq!(store.get_row("t", kept)?, Some(row(&["1", "kept"])));
+
+        let txn = store.begin();
+        txn.insert_row("t", &row(&["3", "committed"]))?;
+        txn.commit()?;
+        assert_eq!(store.scan("t")?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_id_packing() {
+        let rid = RowId::new(123_456, 42);
+        assert_eq!(RowId::from_u64(rid.to_u64()), rid);
+    }
+}
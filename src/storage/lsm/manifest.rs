@@ -0,0 +1,145 @@
+// Manifest: the version-edit log recording which SSTables make up the tree
+//
+// Every flush and compaction appends an edit; replaying the edits in order
+// rebuilds the current version. On open the manifest is rewritten as a
+// single snapshot edit so it does not grow without bound.
+
+use super::journal::{read_records, sync_dir, JournalWriter};
+use crate::error::Result;
+use bincode::{Decode, Encode};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const MANIFEST_FILE: &str = "MANIFEST";
+const MANIFEST_TMP_FILE: &str = "MANIFEST.tmp";
+
+// A live SSTable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub(super) struct TableRef {
+    pub(super) id: u64,
+    pub(super) level: usize,
+    pub(super) seq: u64,
+}
+
+// A change to the set of live files
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub(super) struct VersionEdit {
+    pub(super) added: Vec<TableRef>,
+    pub(super) deleted: Vec<u64>,
+    // Memtable logs numbered below this are fully contained in SSTables
+    pub(super) log_number: Option<u64>,
+    pub(super) next_file_id: Option<u64>,
+}
+
+// The tree's files as of the last edit
+#[derive(Debug, Default)]
+pub(super) struct Version {
+    pub(super) tables: BTreeMap<u64, TableRef>,
+    pub(super) log_number: u64,
+    pub(super) next_file_id: u64,
+}
+
+impl Version {
+    fn apply(&mut self, edit: VersionEdit) {
+        for id in edit.deleted {
+            self.tables.remove(&id);
+        }
+        for table in edit.added {
+            self.tables.insert(table.id, table);
+        }
+        if let Some(log_number) = edit.log_number {
+            self.log_number = self.log_number.max(log_number);
+        }
+        if let Some(next_file_id) = edit.next_file_id {
+            self.next_file_id = self.next_file_id.max(next_file_id);
+        }
+    }
+
+    fn snapshot(&self) -> VersionEdit {
+        VersionEdit {
+            added: self.tables.values().copied().collect(),
+            deleted: Vec::new(),
+            log_number: Some(self.log_number),
+            next_file_id: Some(self.next_file_id),
+        }
+    }
+}
+
+pub(super) struct Manifest {
+    writer: JournalWriter,
+}
+
+impl Manifest {
+    // Replay the manifest in `dir` (none yet is an empty tree) and start a
+    // fresh one holding a snapshot of the result
+    pub(super) fn open(dir: &Path) -> Result<(Self, Version)> {
+        let path = dir.join(MANIFEST_FILE);
+        let mut version = Version::default();
+        if path.exists() {
+            for record in read_records(&path)? {
+                let (edit, _): (VersionEdit, usize) =
+                    bincode::decode_from_slice(&record, bincode::config::standard())?;
+                version.apply(edit);
+            }
+        }
+
+        // The snapshot replaces the old manifest atomically
+        let tmp = dir.join(MANIFEST_TMP_FILE);
+        let mut writer = JournalWriter::create(&tmp, true)?;
+        writer.write(&bincode::encode_to_vec(
+            version.snapshot(),
+            bincode::config::standard(),
+        )?)?;
+        std::fs::rename(&tmp, &path)?;
+        sync_dir(dir)?;
+
+        Ok((Self { writer }, version))
+    }
+
+    // Durably record `edit`
+    pub(super) fn log_edit(&mut self, edit: &VersionEdit) -> Result<()> {
+        self.writer
+            .write(&bincode::encode_to_vec(edit, bincode::config::standard())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_replay_after_reopen() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rustydb-manifest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        let table = |id, level| TableRef { id, level, seq: id };
+        {
+            let (mut manifest, version) = Manifest::open(&dir)?;
+            assert!(version.tables.is_empty());
+            manifest.log_edit(&VersionEdit {
+                added: vec![table(2, 0), table(3, 0)],
+                log_number: Some(4),
+                next_file_id: Some(5),
+                ..Default::default()
+            })?;
+            manifest.log_edit(&VersionEdit {
+                added: vec![table(5, 1)],
+                deleted: vec![2, 3],
+                next_file_id: Some(6),
+                ..Default::default()
+            })?;
+        }
+
+        let (_, version) = Manifest::open(&dir)?;
+        assert_eq!(
+            version.tables.values().copied().collect::<Vec<_>>(),
+            vec![table(5, 1)]
+        );
+        assert_eq!(version.log_number, 4);
+        assert_eq!(version.next_file_id, 6);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
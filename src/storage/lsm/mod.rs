@@ -0,0 +1,1245 @@
+// Log-Structured Merge Tree (LSM) for RustyDB
+// Optimized for write-heavy and time-series workloads
+// Features: Bloom filters, leveled and size-tiered compaction, concurrent memtable switching,
+// block-based SSTable files, a manifest of live files, memtable write-ahead logs
+//
+// Directory layout: `MANIFEST` plus `<id>.sst` tables and `<id>.log` memtable
+// logs. A write goes to the active memtable's log before the memtable; sealed
+// memtables are flushed to L0 tables and their logs removed once the manifest
+// records the flush.
+
+mod journal;
+mod manifest;
+mod sstable;
+
+use crate::error::{DbError, Result};
+use journal::{read_records, sync_dir, JournalWriter};
+use manifest::{Manifest, TableRef, VersionEdit};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sstable::{decode_entry, encode_entry, BlockReader, SSTable, SSTableIter, SSTableWriter};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// LSM key type
+pub type LsmKey = Vec<u8>;
+
+// LSM value with timestamp for MVCC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LsmValue {
+    data: Vec<u8>,
+    timestamp: u64,
+    is_tombstone: bool,
+}
+
+impl LsmValue {
+    fn new(data: Vec<u8>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+
+        Self {
+            data,
+            timestamp,
+            is_tombstone: false,
+        }
+    }
+
+    fn tombstone() -> Self {
+        Self {
+            data: Vec::new(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_micros() as u64,
+            is_tombstone: true,
+        }
+    }
+
+    fn is_deleted(&self) -> bool {
+        self.is_tombstone
+    }
+
+    // The value as seen by readers
+    fn live_data(&self) -> Option<Vec<u8>> {
+        if self.is_deleted() {
+            None
+        } else {
+            Some(self.data.clone())
+        }
+    }
+}
+
+// Bloom filter for fast negative lookups
+struct BloomFilter {
+    bits: Vec<bool>,
+    num_hashes: usize,
+    num_bits: usize,
+}
+
+impl BloomFilter {
+    fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let num_bits = Self::optimal_num_bits(expected_items, false_positive_rate).max(64);
+        let num_hashes = Self::optimal_num_hashes(num_bits, expected_items);
+
+        Self {
+            bits: vec![false; num_bits],
+            num_hashes,
+            num_bits,
+        }
+    }
+
+    fn optimal_num_bits(n: usize, p: f64) -> usize {
+        let n = n as f64;
+        let p = p.max(0.0001); // Avoid log(0)
+        (-(n * p.ln()) / (2.0_f64.ln().powi(2))).ceil() as usize
+    }
+
+    fn optimal_num_hashes(m: usize, n: usize) -> usize {
+        if n == 0 {
+            return 1;
+        }
+        ((m as f64 / n as f64) * 2.0_f64.ln()).ceil() as usize
+    }
+
+    fn hash(&self, key: &[u8], seed: usize) -> usize {
+        let mut hash = seed;
+        for &byte in key {
+            hash = hash.wrapping_mul(31).wrapping_add(byte as usize);
+        }
+        hash % self.num_bits
+    }
+
+    fn insert(&mut self, key: &[u8]) {
+        for i in 0..self.num_hashes {
+            let idx = self.hash(key, i);
+            self.bits[idx] = true;
+        }
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        for i in 0..self.num_hashes {
+            let idx = self.hash(key, i);
+            if !self.bits[idx] {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Reset bloom filter (for maintenance operations)
+    #[allow(dead_code)]
+    fn reset(&mut self) {
+        self.bits.fill(false);
+    }
+
+    // Serialized form: hash count, bit count, then the bits packed LSB first
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.num_bits.div_ceil(8));
+        bytes.extend_from_slice(&(self.num_hashes as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.num_bits as u32).to_le_bytes());
+        for chunk in self.bits.chunks(8) {
+            let byte = chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, &bit)| byte | ((bit as u8) << i));
+            bytes.push(byte);
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let num_hashes = u32::from_le_bytes(bytes.get(0..4)?.try_into().ok()?) as usize;
+        let num_bits = u32::from_le_bytes(bytes.get(4..8)?.try_into().ok()?) as usize;
+        let packed = bytes.get(8..)?;
+        if num_bits == 0 || packed.len() != num_bits.div_ceil(8) {
+            return None;
+        }
+        let bits = (0..num_bits)
+            .map(|i| packed[i / 8] & (1 << (i % 8)) != 0)
+            .collect();
+        Some(Self {
+            bits,
+            num_hashes,
+            num_bits,
+        })
+    }
+}
+
+// In-memory write buffer (memtable)
+struct MemTable {
+    data: BTreeMap<LsmKey, LsmValue>,
+    size_bytes: usize,
+    max_size: usize,
+    // Also numbers the memtable's log file
+    id: u64,
+}
+
+impl MemTable {
+    fn new(max_size: usize, id: u64) -> Self {
+        Self {
+            data: BTreeMap::new(),
+            size_bytes: 0,
+            max_size,
+            id,
+        }
+    }
+
+    fn entry_size(key: &LsmKey, value: &LsmValue) -> usize {
+        key.len() + value.data.len() + 24 // Approximate overhead
+    }
+
+    fn has_room(&self, key: &LsmKey, value: &LsmValue) -> bool {
+        self.size_bytes + Self::entry_size(key, value) <= self.max_size
+    }
+
+    fn put(&mut self, key: LsmKey, value: LsmValue) -> bool {
+        if !self.has_room(&key, &value) {
+            return false; // Memtable is full
+        }
+
+        self.size_bytes += Self::entry_size(&key, &value);
+        self.data.insert(key, value);
+        true
+    }
+
+    fn get(&self, key: &LsmKey) -> Option<&LsmValue> {
+        self.data.get(key)
+    }
+
+    /// Check if memtable is full (for flush decisions)
+    #[allow(dead_code)]
+    fn is_full(&self) -> bool {
+        self.size_bytes >= self.max_size
+    }
+
+    /// Get number of entries in memtable
+    #[allow(dead_code)]
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&LsmKey, &LsmValue)> {
+        self.data.iter()
+    }
+}
+
+// Level in the LSM tree
+struct Level {
+    id: usize,
+    // L0 tables may overlap and are kept oldest first; deeper levels hold
+    // disjoint tables sorted by key
+    sstables: Vec<Arc<SSTable>>,
+    max_size: u64,
+    max_sstables: usize,
+}
+
+impl Level {
+    fn new(id: usize, config: &LsmConfig) -> Self {
+        // L0 is bounded by file count, deeper levels by size (10x per level)
+        let (max_size, max_sstables) = if id == 0 {
+            (u64::MAX, config.level0_file_limit)
+        } else {
+            (
+                config
+                    .level1_max_bytes
+                    .saturating_mul(10_u64.saturating_pow(id as u32 - 1)),
+                usize::MAX,
+            )
+        };
+        Self {
+            id,
+            sstables: Vec::new(),
+            max_size,
+            max_sstables,
+        }
+    }
+
+    fn add_sstable(&mut self, sstable: Arc<SSTable>) {
+        self.sstables.push(sstable);
+        if self.id == 0 {
+            self.sstables.sort_by_key(|s| s.seq);
+        } else {
+            // Keep sorted by min_key for efficient search
+            self.sstables.sort_by(|a, b| a.min_key.cmp(&b.min_key));
+        }
+    }
+
+    fn total_size(&self) -> u64 {
+        self.sstables.iter().map(|s| s.size_bytes).sum()
+    }
+
+    fn needs_compaction(&self) -> bool {
+        self.total_size() > self.max_size || self.sstables.len() > self.max_sstables
+    }
+
+    // Tables whose key range holds `key`, newest first
+    fn find_overlapping(&self, key: &LsmKey) -> Vec<Arc<SSTable>> {
+        self.sstables
+            .iter()
+            .rev()
+            .filter(|s| s.covers(key))
+            .cloned()
+            .collect()
+    }
+}
+
+// Compaction strategy
+#[derive(Debug, Clone, Copy)]
+pub enum CompactionStrategy {
+    Leveled,    // Standard leveled compaction
+    SizeTiered, // Size-tiered for write-heavy workloads
+    TimeWindow, // For time-series data
+}
+
+#[derive(Debug, Clone)]
+pub struct LsmConfig {
+    pub memtable_size: usize,
+    pub num_levels: usize,
+    pub compaction_strategy: CompactionStrategy,
+    // Target size of an SSTable data block
+    pub block_size: usize,
+    // Largest SSTable written by a leveled compaction
+    pub target_file_size: u64,
+    // L0 tables allowed before leveled compaction merges them into L1
+    pub level0_file_limit: usize,
+    // Size limit of L1; each deeper level allows ten times more
+    pub level1_max_bytes: u64,
+    // Similar-sized tables merged at once by size-tiered compaction
+    pub min_merge_width: usize,
+    pub bloom_false_positive_rate: f64,
+    // fsync the memtable log on every write. Without it, acknowledged
+    // writes survive a process crash but not a power failure.
+    pub sync_writes: bool,
+}
+
+impl Default for LsmConfig {
+    fn default() -> Self {
+        Self {
+            memtable_size: 4 * 1024 * 1024,
+            num_levels: 7,
+            compaction_strategy: CompactionStrategy::Leveled,
+            block_size: 4096,
+            target_file_size: 64 * 1024 * 1024,
+            level0_file_limit: 4,
+            level1_max_bytes: 100 * 1024 * 1024,
+            min_merge_width: 4,
+            bloom_false_positive_rate: 0.01,
+            sync_writes: false,
+        }
+    }
+}
+
+// A merge of SSTables into `output_level`
+struct CompactionTask {
+    level: usize,
+    output_level: usize,
+    // Newest data first
+    sstables: Vec<Arc<SSTable>>,
+    // Nothing older than the inputs exists, so deletes can be forgotten
+    drop_tombstones: bool,
+}
+
+impl CompactionTask {
+    // Leveled: all of L0 (its tables overlap) or the oldest table of a
+    // deeper level, plus the overlapping tables of the next level
+    fn leveled(levels: &[Level], level: usize) -> Option<Self> {
+        let output_level = level + 1;
+        if output_level >= levels.len() || !levels[level].needs_compaction() {
+            return None;
+        }
+
+        let mut sstables: Vec<Arc<SSTable>> = if level == 0 {
+            levels[0].sstables.iter().rev().cloned().collect()
+        } else {
+            let oldest = levels[level].sstables.iter().min_by_key(|s| s.id)?;
+            vec![oldest.clone()]
+        };
+        let min_key = sstables.iter().map(|s| s.min_key.clone()).min()?;
+        let max_key = sstables.iter().map(|s| s.max_key.clone()).max()?;
+        sstables.extend(
+            levels[output_level]
+                .sstables
+                .iter()
+                .filter(|s| s.overlaps(&min_key, &max_key))
+                .cloned(),
+        );
+
+        Some(Self {
+            level,
+            output_level,
+            sstables,
+            drop_tombstones: levels[output_level + 1..]
+                .iter()
+                .all(|l| l.sstables.is_empty()),
+        })
+    }
+
+    // Size-tiered: every table stays in L0; the oldest run of at least
+    // `width` consecutive tables of similar size is merged into one. Runs
+    // are consecutive in age so the merged table keeps its place in the
+    // newest-first lookup order, which also keeps time windows together.
+    fn size_tiered(levels: &[Level], width: usize) -> Option<Self> {
+        let tables = &levels[0].sstables;
+        let mut start = 0;
+        for end in 0..tables.len() {
+            let bucket = &tables[start..end];
+            if !bucket.is_empty() {
+                let avg = bucket.iter().map(|s| s.size_bytes).sum::<u64>() / bucket.len() as u64;
+                let size = tables[end].size_bytes;
+                if size > avg * 2 || size * 2 < avg {
+                    start = end;
+                }
+            }
+
+            if end + 1 - start >= width.max(2) {
+                return Some(Self {
+                    level: 0,
+                    output_level: 0,
+                    sstables: tables[start..=end].iter().rev().cloned().collect(),
+                    drop_tombstones: start == 0
+                        && levels[1..].iter().all(|l| l.sstables.is_empty()),
+                });
+            }
+        }
+        None
+    }
+}
+
+// K-way merge of sorted tables. For a key present in several tables, the
+// entry from the earliest table (the newest data) wins.
+struct MergeIter<'a> {
+    sources: Vec<SSTableIter<'a>>,
+    heads: BinaryHeap<Reverse<(LsmKey, usize)>>,
+    values: Vec<Option<LsmValue>>,
+}
+
+impl<'a> MergeIter<'a> {
+    fn new(sources: Vec<SSTableIter<'a>>) -> Result<Self> {
+        let mut merge = Self {
+            values: vec![None; sources.len()],
+            sources,
+            heads: BinaryHeap::new(),
+        };
+        for i in 0..merge.sources.len() {
+            merge.advance(i)?;
+        }
+        Ok(merge)
+    }
+
+    fn advance(&mut self, source: usize) -> Result<()> {
+        if let Some(entry) = self.sources[source].next() {
+            let (key, value) = entry?;
+            self.values[source] = Some(value);
+            self.heads.push(Reverse((key, source)));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for MergeIter<'_> {
+    type Item = Result<(LsmKey, LsmValue)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((key, source)) = self.heads.pop()?;
+        let value = self.values[source].take()?;
+        let mut advanced = self.advance(source);
+
+        // Skip older versions of the same key
+        while advanced.is_ok() {
+            match self.heads.peek() {
+                Some(Reverse((next, _))) if *next == key => {
+                    let Reverse((_, older)) = self.heads.pop()?;
+                    self.values[older] = None;
+                    advanced = self.advance(older);
+                }
+                _ => break,
+            }
+        }
+        Some(advanced.map(|()| (key, value)))
+    }
+}
+
+// LSM Tree main structure
+pub struct LsmTree {
+    dir: PathBuf,
+    config: LsmConfig,
+
+    // Active memtable for writes
+    active_memtable: Arc<RwLock<MemTable>>,
+
+    // Log of the active memtable. Writers hold it for the whole write, which
+    // also serializes memtable switches and flushes.
+    active_log: Mutex<JournalWriter>,
+
+    // Sealed memtables waiting to be flushed, oldest first. Flushes run
+    // synchronously on switch, so the queue stays short.
+    immutable_memtables: Arc<Mutex<VecDeque<Arc<MemTable>>>>,
+
+    // Levels
+    levels: Arc<RwLock<Vec<Level>>>,
+
+    // Record of the live tables; edited together with `levels`
+    manifest: Mutex<Manifest>,
+
+    // Levels that may need compaction
+    compaction_queue: Arc<Mutex<VecDeque<usize>>>,
+
+    // Memtable and SSTable ids share one counter
+    next_file_id: Arc<AtomicU64>,
+
+    // Background tasks
+    compaction_running: Arc<AtomicBool>,
+
+    // Statistics
+    stats: Arc<RwLock<LsmStats>>,
+
+    // Directory removed on drop for scratch trees
+    scratch: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LsmStats {
+    pub writes: u64,
+    pub reads: u64,
+    pub memtable_hits: u64,
+    pub sstable_hits: u64,
+    pub bloom_filter_saves: u64,
+    pub flushes: u64,
+    pub compactions: u64,
+    pub total_sstables: usize,
+    pub total_levels: usize,
+}
+
+static SCRATCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn table_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:06}.sst", id))
+}
+
+fn log_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:06}.log", id))
+}
+
+// Id and extension of a table or log file name
+fn parse_file_name(path: &Path) -> Option<(u64, &str)> {
+    let id = path.file_stem()?.to_str()?.parse().ok()?;
+    Some((id, path.extension()?.to_str()?))
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+impl LsmTree {
+    /// Open (or create) the tree stored in `dir`
+    ///
+    /// Writes still in memtable logs from before a crash are replayed and
+    /// flushed to L0.
+    pub fn open(dir: impl AsRef<Path>, config: LsmConfig) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let (manifest, version) = Manifest::open(&dir)?;
+
+        // Anything the manifest does not know is left over from an
+        // interrupted flush or compaction
+        let mut next_file_id = version.next_file_id.max(1);
+        let mut logs = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let Some((id, extension)) = parse_file_name(&path) else {
+                continue;
+            };
+            next_file_id = next_file_id.max(id + 1);
+            match extension {
+                "sst" if !version.tables.contains_key(&id) => fs::remove_file(&path)?,
+                "log" if id < version.log_number => fs::remove_file(&path)?,
+                "log" => logs.push(id),
+                _ => {}
+            }
+        }
+        logs.sort_unstable();
+
+        let mut levels: Vec<Level> = (0..config.num_levels.max(1))
+            .map(|i| Level::new(i, &config))
+            .collect();
+        for table in version.tables.values() {
+            let Some(level) = levels.get_mut(table.level) else {
+                return Err(DbError::Corruption(format!(
+                    "SSTable {} is in level {} of a {}-level tree",
+                    table.id, table.level, config.num_levels
+                )));
+            };
+            let path = table_path(&dir, table.id);
+            level.add_sstable(Arc::new(SSTable::open(
+                &path,
+                table.id,
+                table.level,
+                table.seq,
+            )?));
+        }
+
+        // Replayed memtables are queued for flushing ahead of the new one
+        let mut recovered = VecDeque::new();
+        for id in logs {
+            let mut memtable = MemTable::new(usize::MAX, id);
+            for record in read_records(&log_path(&dir, id))? {
+                let (key, value) = decode_entry(&mut BlockReader::new(&record))?;
+                memtable.put(key, value);
+            }
+            recovered.push_back(Arc::new(memtable));
+        }
+
+        let memtable_id = next_file_id;
+        let active_log = JournalWriter::create(&log_path(&dir, memtable_id), config.sync_writes)?;
+        sync_dir(&dir)?;
+
+        let tree = Self {
+            active_memtable: Arc::new(RwLock::new(MemTable::new(
+                config.memtable_size,
+                memtable_id,
+            ))),
+            active_log: Mutex::new(active_log),
+            immutable_memtables: Arc::new(Mutex::new(recovered)),
+            levels: Arc::new(RwLock::new(levels)),
+            manifest: Mutex::new(manifest),
+            compaction_queue: Arc::new(Mutex::new(VecDeque::new())),
+            next_file_id: Arc::new(AtomicU64::new(memtable_id + 1)),
+            compaction_running: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(RwLock::new(LsmStats::default())),
+            dir,
+            config,
+            scratch: false,
+        };
+
+        tree.trigger_flush()?;
+        tree.schedule_compaction(0)?;
+        tree.run_compaction(usize::MAX)?;
+        Ok(tree)
+    }
+
+    /// Open a throw-away tree in a fresh temporary directory, removed on drop
+    pub fn temporary(config: LsmConfig) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "rustydb-lsm-{}-{}",
+            std::process::id(),
+            SCRATCH_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let mut tree = Self::open(&dir, config)?;
+        tree.scratch = true;
+        Ok(tree)
+    }
+
+    // Put a key-value pair
+    pub fn put(&self, key: LsmKey, value: Vec<u8>) -> Result<()> {
+        self.write(key, LsmValue::new(value))?;
+        self.stats.write().writes += 1;
+        Ok(())
+    }
+
+    // Get a value by key
+    pub fn get(&self, key: &LsmKey) -> Result<Option<Vec<u8>>> {
+        self.stats.write().reads += 1;
+
+        // Check active memtable
+        if let Some(value) = self.active_memtable.read().get(key) {
+            self.stats.write().memtable_hits += 1;
+            return Ok(value.live_data());
+        }
+
+        // Check immutable memtables (newest first)
+        {
+            let immutables = self.immutable_memtables.lock().unwrap();
+            for memtable in immutables.iter().rev() {
+                if let Some(value) = memtable.get(key) {
+                    self.stats.write().memtable_hits += 1;
+                    return Ok(value.live_data());
+                }
+            }
+        }
+
+        // Check SSTables level by level, newest first within L0
+        let candidates: Vec<Arc<SSTable>> = {
+            let levels = self.levels.read();
+            levels
+                .iter()
+                .flat_map(|level| level.find_overlapping(key))
+                .collect()
+        };
+        for sstable in candidates {
+            // Bloom filter check
+            if !sstable.might_contain(key) {
+                self.stats.write().bloom_filter_saves += 1;
+                continue;
+            }
+
+            if let Some(value) = sstable.get(key)? {
+                self.stats.write().sstable_hits += 1;
+                return Ok(value.live_data());
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Delete a key
+    pub fn delete(&self, key: LsmKey) -> Result<()> {
+        // Insert tombstone
+        self.write(key, LsmValue::tombstone())
+    }
+
+    // Range scan
+    pub fn scan(&self, start_key: &LsmKey, end_key: &LsmKey) -> Result<Vec<(LsmKey, Vec<u8>)>> {
+        if start_key > end_key {
+            return Ok(Vec::new());
+        }
+        let mut results = BTreeMap::new();
+        let mut apply = |key: &LsmKey, value: &LsmValue| {
+            if value.is_deleted() {
+                results.remove(key);
+            } else {
+                results.insert(key.clone(), value.data.clone());
+            }
+        };
+
+        // Oldest data first so newer versions overwrite it: the deepest
+        // level first, then L0 from its oldest table
+        let sstables: Vec<Arc<SSTable>> = {
+            let levels = self.levels.read();
+            levels
+                .iter()
+                .rev()
+                .flat_map(|level| level.sstables.iter())
+                .filter(|s| s.overlaps(start_key, end_key))
+                .cloned()
+                .collect()
+        };
+        for sstable in &sstables {
+            for entry in sstable.iter_from(start_key) {
+                let (key, value) = entry?;
+                if &key > end_key {
+                    break;
+                }
+                if &key >= start_key {
+                    apply(&key, &value);
+                }
+            }
+        }
+
+        // Scan immutable memtables
+        {
+            let immutables = self.immutable_memtables.lock().unwrap();
+            for memtable in immutables.iter() {
+                for (k, v) in memtable.data.range(start_key.clone()..=end_key.clone()) {
+                    apply(k, v);
+                }
+            }
+        }
+
+        // Scan memtables
+        {
+            let active = self.active_memtable.read();
+            for (k, v) in active.data.range(start_key.clone()..=end_key.clone()) {
+                apply(k, v);
+            }
+        }
+
+        Ok(results.into_iter().collect())
+    }
+
+    /// Flush the active memtable to an SSTable
+    pub fn flush(&self) -> Result<()> {
+        let mut log = self.active_log.lock().unwrap();
+        if self.active_memtable.read().data.is_empty() {
+            return Ok(());
+        }
+        self.switch_memtable(&mut log)?;
+        drop(log);
+        self.run_compaction(usize::MAX)?;
+        Ok(())
+    }
+
+    // Log and apply one write, switching memtables when the active one is full
+    fn write(&self, key: LsmKey, value: LsmValue) -> Result<()> {
+        {
+            let mut log = self.active_log.lock().unwrap();
+            if !self.active_memtable.read().has_room(&key, &value) {
+                // Memtable is full, switch to a new one
+                self.switch_memtable(&mut log)?;
+                if !self.active_memtable.read().has_room(&key, &value) {
+                    return Err(DbError::Storage(
+                        "Failed to insert into new memtable".to_string(),
+                    ));
+                }
+            }
+
+            let mut record = Vec::new();
+            encode_entry(&mut record, &key, &value);
+            log.write(&record)?;
+            self.active_memtable.write().put(key, value);
+        }
+
+        // Merge outside the write path's lock
+        self.run_compaction(usize::MAX)?;
+        Ok(())
+    }
+
+    // Switch active memtable to immutable, giving the new one its own log.
+    // Called with the active log locked.
+    fn switch_memtable(&self, log: &mut JournalWriter) -> Result<()> {
+        let new_id = self.next_file_id.fetch_add(1, Ordering::SeqCst);
+        *log = JournalWriter::create(&log_path(&self.dir, new_id), self.config.sync_writes)?;
+
+        let old_memtable = {
+            let mut active = self.active_memtable.write();
+            let new_memtable = MemTable::new(self.config.memtable_size, new_id);
+            std::mem::replace(&mut *active, new_memtable)
+        };
+
+        // Add to immutable queue
+        self.immutable_memtables
+            .lock()
+            .unwrap()
+            .push_back(Arc::new(old_memtable));
+
+        // Trigger flush
+        self.trigger_flush()
+    }
+
+    // Flush sealed memtables to L0, oldest first. Each stays readable in the
+    // queue until its table is installed.
+    fn trigger_flush(&self) -> Result<()> {
+        loop {
+            let Some(memtable) = self.immutable_memtables.lock().unwrap().front().cloned() else {
+                return Ok(());
+            };
+
+            let mut added = Vec::new();
+            if !memtable.data.is_empty() {
+                let entries = memtable.iter().map(|(k, v)| Ok((k.clone(), v.clone())));
+                added =
+                    self.write_tables(entries, memtable.data.len(), u64::MAX, 0, memtable.id)?;
+            }
+            self.install(
+                VersionEdit {
+                    log_number: Some(memtable.id + 1),
+                    ..Default::default()
+                },
+                added,
+            )?;
+            self.immutable_memtables.lock().unwrap().pop_front();
+
+            // Its log is no longer needed
+            remove_if_exists(&log_path(&self.dir, memtable.id))?;
+            self.stats.write().flushes += 1;
+            self.schedule_compaction(0)?;
+        }
+    }
+
+    // Write `entries` (in key order) to new SSTables of about `max_size`
+    // bytes each
+    fn write_tables(
+        &self,
+        entries: impl Iterator<Item = Result<(LsmKey, LsmValue)>>,
+        expected_entries: usize,
+        max_size: u64,
+        level: usize,
+        seq: u64,
+    ) -> Result<Vec<Arc<SSTable>>> {
+        let mut tables = Vec::new();
+        let mut current: Option<(u64, SSTableWriter)> = None;
+
+        for entry in entries {
+            let (key, value) = entry?;
+            if current.is_none() {
+                let id = self.next_file_id.fetch_add(1, Ordering::SeqCst);
+                let writer = SSTableWriter::create(
+                    &table_path(&self.dir, id),
+                    expected_entries,
+                    self.config.block_size,
+                    self.config.bloom_false_positive_rate,
+                )?;
+                current = Some((id, writer));
+            }
+            if let Some((_, writer)) = &mut current {
+                writer.add(&key, &value)?;
+                if writer.estimated_size() < max_size {
+                    continue;
+                }
+            }
+
+            if let Some((id, writer)) = current.take() {
+                writer.finish()?;
+                let path = table_path(&self.dir, id);
+                tables.push(Arc::new(SSTable::open(&path, id, level, seq)?));
+            }
+        }
+
+        if let Some((id, writer)) = current {
+            writer.finish()?;
+            let path = table_path(&self.dir, id);
+            tables.push(Arc::new(SSTable::open(&path, id, level, seq)?));
+        }
+        Ok(tables)
+    }
+
+    // Record an edit in the manifest and apply it to the levels
+    fn install(&self, mut edit: VersionEdit, added: Vec<Arc<SSTable>>) -> Result<()> {
+        // New table files must be reachable before the manifest names them
+        sync_dir(&self.dir)?;
+
+        let mut levels = self.levels.write();
+        edit.added = added
+            .iter()
+            .map(|s| TableRef {
+                id: s.id,
+                level: s.level,
+                seq: s.seq,
+            })
+            .collect();
+        edit.next_file_id = Some(self.next_file_id.load(Ordering::SeqCst));
+        self.manifest.lock().unwrap().log_edit(&edit)?;
+
+        for level in levels.iter_mut() {
+            level.sstables.retain(|s| !edit.deleted.contains(&s.id));
+        }
+        for sstable in added {
+            let level = sstable.level;
+            levels[level].add_sstable(sstable);
+        }
+        Ok(())
+    }
+
+    // Schedule a compaction task
+    fn schedule_compaction(&self, level: usize) -> Result<()> {
+        if level >= self.levels.read().len() {
+            return Ok(());
+        }
+
+        let mut queue = self.compaction_queue.lock().unwrap();
+        if !queue.contains(&level) {
+            queue.push_back(level);
+        }
+
+        Ok(())
+    }
+
+    // Process compaction queue
+    pub fn run_compaction(&self, max_tasks: usize) -> Result<usize> {
+        if self
+            .compaction_running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Ok(0); // Already running
+        }
+
+        let result = self.drain_compaction_queue(max_tasks);
+        self.compaction_running.store(false, Ordering::SeqCst);
+        result
+    }
+
+    fn drain_compaction_queue(&self, max_tasks: usize) -> Result<usize> {
+        let mut completed = 0;
+
+        while completed < max_tasks {
+            let Some(level) = self.compaction_queue.lock().unwrap().pop_front() else {
+                break;
+            };
+
+            let task = {
+                let levels = self.levels.read();
+                match self.config.compaction_strategy {
+                    CompactionStrategy::Leveled => CompactionTask::leveled(&levels, level),
+                    CompactionStrategy::SizeTiered | CompactionStrategy::TimeWindow => {
+                        CompactionTask::size_tiered(&levels, self.config.min_merge_width)
+                    }
+                }
+            };
+            let Some(task) = task else {
+                continue;
+            };
+
+            self.compact(&task)?;
+            completed += 1;
+
+            // Either level may still be over its limit
+            self.schedule_compaction(task.level)?;
+            self.schedule_compaction(task.output_level)?;
+        }
+
+        Ok(completed)
+    }
+
+    // Merge the task's tables into new tables in its output level
+    fn compact(&self, task: &CompactionTask) -> Result<()> {
+        let expected_entries = task.sstables.iter().map(|s| s.num_entries as usize).sum();
+        let merged =
+            MergeIter::new(task.sstables.iter().map(|s| s.iter()).collect())?.filter(|entry| {
+                !(task.drop_tombstones && matches!(entry, Ok((_, value)) if value.is_deleted()))
+            });
+        // L0 tables may overlap, so they are never split
+        let max_size = if task.output_level == 0 {
+            u64::MAX
+        } else {
+            self.config.target_file_size
+        };
+        let seq = task.sstables.iter().map(|s| s.seq).max().unwrap_or(0);
+        let outputs =
+            self.write_tables(merged, expected_entries, max_size, task.output_level, seq)?;
+
+        self.install(
+            VersionEdit {
+                deleted: task.sstables.iter().map(|s| s.id).collect(),
+                ..Default::default()
+            },
+            outputs,
+        )?;
+        // Readers still holding an input keep its file open
+        for sstable in &task.sstables {
+            remove_if_exists(&sstable.path)?;
+        }
+
+        self.stats.write().compactions += 1;
+        Ok(())
+    }
+
+    pub fn get_stats(&self) -> LsmStats {
+        let mut stats = self.stats.read().clone();
+        let levels = self.levels.read();
+        stats.total_levels = levels.len();
+        stats.total_sstables = levels.iter().map(|l| l.sstables.len()).sum();
+        stats
+    }
+}
+
+impl Drop for LsmTree {
+    fn drop(&mut self) {
+        if self.scratch {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(memtable_size: usize) -> LsmTree {
+        LsmTree::temporary(LsmConfig {
+            memtable_size,
+            num_levels: 5,
+            ..LsmConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn key(i: usize) -> LsmKey {
+        format!("key{:05}", i).into_bytes()
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut bloom = BloomFilter::new(100, 0.01);
+
+        bloom.insert(b"key1");
+        bloom.insert(b"key2");
+
+        assert!(bloom.contains(b"key1"));
+        assert!(bloom.contains(b"key2"));
+        assert!(!bloom.contains(b"key3")); // Might have false positives, but unlikely
+
+        let restored = BloomFilter::from_bytes(&bloom.to_bytes()).unwrap();
+        assert!(restored.contains(b"key1"));
+        assert_eq!(restored.bits, bloom.bits);
+    }
+
+    #[test]
+    fn test_memtable() {
+        let mut memtable = MemTable::new(1024, 0);
+
+        let key = b"test_key".to_vec();
+        let value = LsmValue::new(b"test_value".to_vec());
+
+        assert!(memtable.put(key.clone(), value));
+        assert!(memtable.get(&key).is_some());
+    }
+
+    #[test]
+    fn test_lsm_put_get() {
+        let lsm = tree(1024);
+
+        let key = b"key1".to_vec();
+        let value = b"value1".to_vec();
+
+        lsm.put(key.clone(), value.clone()).unwrap();
+
+        let retrieved = lsm.get(&key).unwrap();
+        assert_eq!(retrieved, Some(value));
+    }
+
+    #[test]
+    fn test_lsm_delete() {
+        let lsm = tree(1024);
+
+        let key = b"key1".to_vec();
+        let value = b"value1".to_vec();
+
+        lsm.put(key.clone(), value).unwrap();
+        lsm.delete(key.clone()).unwrap();
+
+        let retrieved = lsm.get(&key).unwrap();
+        assert_eq!(retrieved, None);
+    }
+
+    #[test]
+    fn test_lsm_scan() {
+        let lsm = tree(4096);
+
+        lsm.put(b"key1".to_vec(), b"value1".to_vec()).unwrap();
+        lsm.put(b"key2".to_vec(), b"value2".to_vec()).unwrap();
+        lsm.put(b"key3".to_vec(), b"value3".to_vec()).unwrap();
+
+        let results = lsm.scan(&b"key1".to_vec(), &b"key2".to_vec()).unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_memtable_switch() {
+        let lsm = tree(128); // Small memtable
+
+        // Fill memtable
+        for i in 0..20 {
+            let key = format!("key{:02}", i).into_bytes();
+            let value = b"value".to_vec();
+            lsm.put(key, value).unwrap();
+        }
+
+        let stats = lsm.get_stats();
+        assert!(stats.writes >= 20);
+    }
+
+    #[test]
+    fn test_flushed_data_is_read_from_sstables() -> Result<()> {
+        let lsm = tree(512);
+        for i in 0..300 {
+            lsm.put(key(i), format!("value{}", i).into_bytes())?;
+        }
+        for i in (0..300).step_by(3) {
+            lsm.delete(key(i))?;
+        }
+        lsm.flush()?;
+
+        let stats = lsm.get_stats();
+        assert!(stats.flushes > 0);
+        assert!(stats.total_sstables > 0);
+        assert_eq!(lsm.get(&key(10))?, Some(b"value10".to_vec()));
+        assert_eq!(lsm.get(&key(9))?, None);
+
+        let scanned = lsm.scan(&key(100), &key(199))?;
+        assert_eq!(scanned.len(), 67);
+        assert_eq!(scanned[0], (key(100), b"value100".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reopen_recovers_tables_and_logs() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rustydb-lsm-reopen-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = LsmConfig {
+            memtable_size: 1024,
+            ..LsmConfig::default()
+        };
+
+        {
+            let lsm = LsmTree::open(&dir, config.clone())?;
+            for i in 0..200 {
+                lsm.put(key(i), format!("value{}", i).into_bytes())?;
+            }
+            lsm.delete(key(5))?;
+            // The last writes are only in the memtable log
+            assert!(!lsm.active_memtable.read().data.is_empty());
+        }
+
+        let lsm = LsmTree::open(&dir, config)?;
+        assert_eq!(lsm.get(&key(5))?, None);
+        for i in (0..200).filter(|&i| i != 5) {
+            assert_eq!(lsm.get(&key(i))?, Some(format!("value{}", i).into_bytes()));
+        }
+        assert_eq!(lsm.scan(&key(0), &key(199))?.len(), 199);
+
+        drop(lsm);
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_leveled_compaction_merges_files() -> Result<()> {
+        let lsm = LsmTree::temporary(LsmConfig {
+            memtable_size: 512,
+            level0_file_limit: 2,
+            level1_max_bytes: 4096,
+            target_file_size: 2048,
+            ..LsmConfig::default()
+        })?;
+
+        // Overwrite the same keys repeatedly so merges have versions to drop
+        for round in 0..5 {
+            for i in 0..100 {
+                lsm.put(key(i), format!("value{}-{}", i, round).into_bytes())?;
+            }
+        }
+        lsm.delete(key(42))?;
+        lsm.flush()?;
+
+        let stats = lsm.get_stats();
+        assert!(stats.compactions > 0);
+        {
+            let levels = lsm.levels.read();
+            assert!(levels[0].sstables.len() <= 2);
+            // Deeper levels hold disjoint tables
+            for level in levels.iter().skip(1) {
+                for pair in level.sstables.windows(2) {
+                    assert!(pair[0].max_key < pair[1].min_key);
+                }
+            }
+        }
+
+        assert_eq!(lsm.get(&key(7))?, Some(b"value7-4".to_vec()));
+        assert_eq!(lsm.get(&key(42))?, None);
+        assert_eq!(lsm.scan(&key(0), &key(99))?.len(), 99);
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_tiered_compaction_merges_similar_files() -> Result<()> {
+        let lsm = LsmTree::temporary(LsmConfig {
+            memtable_size: 512,
+            compaction_strategy: CompactionStrategy::SizeTiered,
+            min_merge_width: 3,
+            ..LsmConfig::default()
+        })?;
+
+        for i in 0..400 {
+            lsm.put(key(i % 150), format!("value{}", i).into_bytes())?;
+        }
+        lsm.flush()?;
+
+        let stats = lsm.get_stats();
+        assert!(stats.compactions > 0);
+        assert!(stats.total_sstables < stats.flushes as usize);
+        assert_eq!(lsm.get(&key(10))?, Some(b"value310".to_vec()));
+        assert_eq!(lsm.get(&key(149))?, Some(b"value299".to_vec()));
+        Ok(())
+    }
+}
@@ -0,0 +1,467 @@
+// Immutable, block-based SSTable files
+//
+// Layout: [data block]* [index block] [bloom block] [footer]
+// Every block ends with the CRC32C of its contents. Data blocks hold entries
+// in key order. The index block holds the table's smallest key, then the
+// last key and location of each data block. The fixed-size footer locates
+// the index and bloom blocks.
+
+use super::{BloomFilter, LsmKey, LsmValue};
+use crate::error::{DbError, Result};
+use crate::storage::checksum::hardware_crc32c;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const FOOTER_SIZE: u64 = 48;
+const MAGIC: u32 = 0x4C53_4D54;
+
+// Location of a block, including its trailing checksum
+#[derive(Debug, Clone, Copy)]
+struct BlockHandle {
+    offset: u64,
+    len: u64,
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    last_key: LsmKey,
+    handle: BlockHandle,
+}
+
+// Decoding cursor over a block
+pub(super) struct BlockReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BlockReader<'a> {
+    pub(super) fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() - self.pos < n {
+            return Err(DbError::Corruption("Truncated LSM record".to_string()));
+        }
+        let bytes = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+// Entry encoding shared by data blocks and memtable logs
+pub(super) fn encode_entry(buf: &mut Vec<u8>, key: &[u8], value: &LsmValue) {
+    put_bytes(buf, key);
+    buf.push(value.is_tombstone as u8);
+    buf.extend_from_slice(&value.timestamp.to_le_bytes());
+    put_bytes(buf, &value.data);
+}
+
+pub(super) fn decode_entry(reader: &mut BlockReader) -> Result<(LsmKey, LsmValue)> {
+    let key = reader.bytes()?;
+    let is_tombstone = reader.take(1)?[0] != 0;
+    let timestamp = reader.u64()?;
+    let data = reader.bytes()?;
+    Ok((
+        key,
+        LsmValue {
+            data,
+            timestamp,
+            is_tombstone,
+        },
+    ))
+}
+
+// Builds an SSTable file from entries added in key order
+pub(super) struct SSTableWriter {
+    out: BufWriter<File>,
+    offset: u64,
+    block: Vec<u8>,
+    block_size: usize,
+    min_key: Option<LsmKey>,
+    last_key: Option<LsmKey>,
+    index: Vec<IndexEntry>,
+    bloom: BloomFilter,
+    num_entries: u64,
+}
+
+impl SSTableWriter {
+    pub(super) fn create(
+        path: &Path,
+        expected_entries: usize,
+        block_size: usize,
+        false_positive_rate: f64,
+    ) -> Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+            offset: 0,
+            block: Vec::with_capacity(block_size),
+            block_size,
+            min_key: None,
+            last_key: None,
+            index: Vec::new(),
+            bloom: BloomFilter::new(expected_entries, false_positive_rate),
+            num_entries: 0,
+        })
+    }
+
+    pub(super) fn add(&mut self, key: &[u8], value: &LsmValue) -> Result<()> {
+        debug_assert!(self.last_key.as_deref().is_none_or(|last| last < key));
+        encode_entry(&mut self.block, key, value);
+        self.bloom.insert(key);
+        self.num_entries += 1;
+        if self.min_key.is_none() {
+            self.min_key = Some(key.to_vec());
+        }
+        self.last_key = Some(key.to_vec());
+
+        if self.block.len() >= self.block_size {
+            self.finish_block()?;
+        }
+        Ok(())
+    }
+
+    // Bytes written so far, counting the open block
+    pub(super) fn estimated_size(&self) -> u64 {
+        self.offset + self.block.len() as u64
+    }
+
+    fn finish_block(&mut self) -> Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+        let block = std::mem::replace(&mut self.block, Vec::with_capacity(self.block_size));
+        let handle = self.write_block(block)?;
+        self.index.push(IndexEntry {
+            last_key: self.last_key.clone().unwrap_or_default(),
+            handle,
+        });
+        Ok(())
+    }
+
+    fn write_block(&mut self, mut block: Vec<u8>) -> Result<BlockHandle> {
+        let checksum = hardware_crc32c(&block);
+        block.extend_from_slice(&checksum.to_le_bytes());
+        self.out.write_all(&block)?;
+
+        let handle = BlockHandle {
+            offset: self.offset,
+            len: block.len() as u64,
+        };
+        self.offset += handle.len;
+        Ok(handle)
+    }
+
+    // Write the index, bloom filter and footer and sync the file
+    pub(super) fn finish(mut self) -> Result<()> {
+        self.finish_block()?;
+
+        let mut index = Vec::new();
+        put_bytes(&mut index, self.min_key.as_deref().unwrap_or_default());
+        for entry in &self.index {
+            put_bytes(&mut index, &entry.last_key);
+            index.extend_from_slice(&entry.handle.offset.to_le_bytes());
+            index.extend_from_slice(&entry.handle.len.to_le_bytes());
+        }
+        let index_handle = self.write_block(index)?;
+        let bloom = self.bloom.to_bytes();
+        let bloom_handle = self.write_block(bloom)?;
+
+        let mut footer = Vec::with_capacity(FOOTER_SIZE as usize);
+        for field in [
+            index_handle.offset,
+            index_handle.len,
+            bloom_handle.offset,
+            bloom_handle.len,
+            self.num_entries,
+        ] {
+            footer.extend_from_slice(&field.to_le_bytes());
+        }
+        let checksum = hardware_crc32c(&footer);
+        footer.extend_from_slice(&checksum.to_le_bytes());
+        footer.extend_from_slice(&MAGIC.to_le_bytes());
+        self.out.write_all(&footer)?;
+
+        self.out.flush()?;
+        self.out.get_ref().sync_all()?;
+        Ok(())
+    }
+}
+
+// An open SSTable file. The index and bloom filter stay in memory; data
+// blocks are read on demand.
+pub(super) struct SSTable {
+    pub(super) id: u64,
+    pub(super) level: usize,
+    // Age of the newest data in the table; newer tables shadow older ones
+    pub(super) seq: u64,
+    pub(super) path: PathBuf,
+    pub(super) min_key: LsmKey,
+    pub(super) max_key: LsmKey,
+    pub(super) num_entries: u64,
+    pub(super) size_bytes: u64,
+    index: Vec<IndexEntry>,
+    bloom: BloomFilter,
+    file: Mutex<File>,
+}
+
+impl SSTable {
+    pub(super) fn open(path: &Path, id: u64, level: usize, seq: u64) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let size_bytes = file.metadata()?.len();
+        let corrupt =
+            |what: &str| DbError::Corruption(format!("SSTable {}: {}", path.display(), what));
+        if size_bytes < FOOTER_SIZE {
+            return Err(corrupt("file too short"));
+        }
+
+        let mut footer = [0u8; FOOTER_SIZE as usize];
+        file.seek(SeekFrom::Start(size_bytes - FOOTER_SIZE))?;
+        file.read_exact(&mut footer)?;
+        let checksum = u32::from_le_bytes(footer[40..44].try_into().unwrap());
+        let magic = u32::from_le_bytes(footer[44..48].try_into().unwrap());
+        if magic != MAGIC || hardware_crc32c(&footer[..40]) != checksum {
+            return Err(corrupt("bad footer"));
+        }
+        let field = |i: usize| u64::from_le_bytes(footer[i * 8..i * 8 + 8].try_into().unwrap());
+        let index_handle = BlockHandle {
+            offset: field(0),
+            len: field(1),
+        };
+        let bloom_handle = BlockHandle {
+            offset: field(2),
+            len: field(3),
+        };
+
+        let index_block = read_block(&mut file, index_handle)?;
+        let mut reader = BlockReader::new(&index_block);
+        let min_key = reader.bytes()?;
+        let mut index = Vec::new();
+        while !reader.is_empty() {
+            let last_key = reader.bytes()?;
+            let handle = BlockHandle {
+                offset: reader.u64()?,
+                len: reader.u64()?,
+            };
+            index.push(IndexEntry { last_key, handle });
+        }
+        let bloom = BloomFilter::from_bytes(&read_block(&mut file, bloom_handle)?)
+            .ok_or_else(|| corrupt("bad bloom filter"))?;
+
+        Ok(Self {
+            id,
+            level,
+            seq,
+            path: path.to_path_buf(),
+            min_key,
+            max_key: index.last().map(|e| e.last_key.clone()).unwrap_or_default(),
+            num_entries: field(4),
+            size_bytes,
+            index,
+            bloom,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub(super) fn might_contain(&self, key: &[u8]) -> bool {
+        self.covers(key) && self.bloom.contains(key)
+    }
+
+    // Whether `key` falls in the table's key range
+    pub(super) fn covers(&self, key: &[u8]) -> bool {
+        key >= self.min_key.as_slice() && key <= self.max_key.as_slice()
+    }
+
+    pub(super) fn overlaps(&self, min_key: &[u8], max_key: &[u8]) -> bool {
+        !(self.max_key.as_slice() < min_key || self.min_key.as_slice() > max_key)
+    }
+
+    // Look up `key`; a tombstone is returned as such
+    pub(super) fn get(&self, key: &[u8]) -> Result<Option<LsmValue>> {
+        let idx = self.index.partition_point(|e| e.last_key.as_slice() < key);
+        if idx == self.index.len() {
+            return Ok(None);
+        }
+        for (k, v) in self.read_entries(idx)? {
+            if k.as_slice() == key {
+                return Ok(Some(v));
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_entries(&self, block: usize) -> Result<Vec<(LsmKey, LsmValue)>> {
+        let data = {
+            let mut file = self.file.lock().unwrap();
+            read_block(&mut file, self.index[block].handle)?
+        };
+        let mut reader = BlockReader::new(&data);
+        let mut entries = Vec::new();
+        while !reader.is_empty() {
+            entries.push(decode_entry(&mut reader)?);
+        }
+        Ok(entries)
+    }
+
+    // Every entry in key order, a block at a time
+    pub(super) fn iter(&self) -> SSTableIter<'_> {
+        SSTableIter {
+            table: self,
+            next_block: 0,
+            entries: Vec::new().into_iter(),
+        }
+    }
+
+    // Entries from the block that may hold `start` onwards; the first block
+    // can still yield keys below `start`
+    pub(super) fn iter_from(&self, start: &[u8]) -> SSTableIter<'_> {
+        SSTableIter {
+            table: self,
+            next_block: self
+                .index
+                .partition_point(|e| e.last_key.as_slice() < start),
+            entries: Vec::new().into_iter(),
+        }
+    }
+}
+
+fn read_block(file: &mut File, handle: BlockHandle) -> Result<Vec<u8>> {
+    if handle.len < 4 {
+        return Err(DbError::Corruption(format!(
+            "SSTable block at offset {} is too short",
+            handle.offset
+        )));
+    }
+    let mut block = vec![0u8; handle.len as usize];
+    file.seek(SeekFrom::Start(handle.offset))?;
+    file.read_exact(&mut block)?;
+
+    let data_len = block.len() - 4;
+    let checksum = u32::from_le_bytes(block[data_len..].try_into().unwrap());
+    if hardware_crc32c(&block[..data_len]) != checksum {
+        return Err(DbError::Corruption(format!(
+            "SSTable block at offset {} fails its checksum",
+            handle.offset
+        )));
+    }
+    block.truncate(data_len);
+    Ok(block)
+}
+
+pub(super) struct SSTableIter<'a> {
+    table: &'a SSTable,
+    next_block: usize,
+    entries: std::vec::IntoIter<(LsmKey, LsmValue)>,
+}
+
+impl Iterator for SSTableIter<'_> {
+    type Item = Result<(LsmKey, LsmValue)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.entries.next() {
+                return Some(Ok(entry));
+            }
+            if self.next_block >= self.table.index.len() {
+                return None;
+            }
+            match self.table.read_entries(self.next_block) {
+                Ok(entries) => self.entries = entries.into_iter(),
+                Err(e) => {
+                    // Stop after reporting the error
+                    self.next_block = self.table.index.len();
+                    return Some(Err(e));
+                }
+            }
+            self.next_block += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_table(path: &Path, n: usize) -> Result<()> {
+        let mut writer = SSTableWriter::create(path, n, 256, 0.01)?;
+        for i in 0..n {
+            let key = format!("key{:05}", i).into_bytes();
+            writer.add(&key, &LsmValue::new(format!("value{}", i).into_bytes()))?;
+        }
+        writer.finish()
+    }
+
+    #[test]
+    fn test_write_and_read_blocks() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rustydb-sstable-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("000001.sst");
+        write_table(&path, 500)?;
+
+        let table = SSTable::open(&path, 1, 0, 1)?;
+        assert_eq!(table.num_entries, 500);
+        assert_eq!(table.min_key, b"key00000".to_vec());
+        assert_eq!(table.max_key, b"key00499".to_vec());
+        assert_eq!(
+            table.get(b"key00321")?.map(|v| v.data),
+            Some(b"value321".to_vec())
+        );
+        assert!(table.get(b"key00321x")?.is_none());
+        assert!(!table.might_contain(b"zzz"));
+
+        let from: Vec<LsmKey> = table
+            .iter_from(b"key00490")
+            .map(|e| e.map(|(k, _)| k))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|k| k.as_slice() >= b"key00490".as_slice())
+            .collect();
+        assert_eq!(from.len(), 10);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_block_is_detected() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rustydb-sstable-bad-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("000001.sst");
+        write_table(&path, 100)?;
+
+        let mut bytes = std::fs::read(&path)?;
+        bytes[10] ^= 0xFF;
+        std::fs::write(&path, &bytes)?;
+
+        let table = SSTable::open(&path, 1, 0, 1)?;
+        let entries = table.iter().collect::<Result<Vec<_>>>();
+        assert!(matches!(entries, Err(DbError::Corruption(_))));
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
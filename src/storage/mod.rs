@@ -2,6 +2,7 @@ pub mod buffer;
 pub mod checksum;
 pub mod columnar;
 pub mod disk;
+pub mod heap;
 pub mod json;
 pub mod lsm;
 pub mod page;
@@ -12,8 +13,9 @@ pub use buffer::{BufferPoolManager, BufferPoolStats};
 pub use checksum::hardware_crc32c;
 pub use columnar::{ColumnDef, ColumnType, ColumnValue, ColumnarTable};
 pub use disk::{DirectIoConfig, DiskManager, IoPriority};
+pub use heap::{RowId, StoreTransaction, TableStore};
 pub use json::{JsonData, JsonOperators, JsonPath};
-pub use lsm::{LsmStats, LsmTree};
+pub use lsm::{LsmConfig, LsmStats, LsmTree};
 pub use page::{Page, PageMerger, PageSplitter, SlottedPage};
 pub use partitioning::{PartitionManager, PartitionPruner, PartitionStrategy};
 pub use tiered::{StorageTier, TierStats, TieredStorageManager};
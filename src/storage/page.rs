@@ -1,16 +1,22 @@
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
-use std::mem::size_of;
 
 // Use shared checksum module (deduplication)
 use super::checksum::hardware_crc32c;
 
 // Use PageId from common module for consistency
+use crate::common::LogSequenceNumber;
 pub use crate::common::PageId;
 pub type SlotId = u16;
 
-const SLOT_SIZE: usize = size_of::<Slot>();
-const PAGE_HEADER_SIZE: usize = size_of::<PageHeader>();
+/// Sentinel used in the page header when a page has no successor
+pub const INVALID_PAGE_ID: PageId = PageId::MAX;
+
+// On-disk layout sizes. Header and slot entries are encoded with a fixed
+// little-endian layout so that their size never depends on the values stored
+// (bincode's varint encoding does not fit into a fixed-size slot directory).
+const SLOT_SIZE: usize = 4;
+const PAGE_HEADER_SIZE: usize = 28;
 
 // A page represents a fixed-size block of data
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,14 +62,43 @@ impl Page {
     }
 
     fn write_header(&mut self, header: &PageHeader) {
-        let bytes = bincode::encode_to_vec(header, bincode::config::standard()).unwrap();
-        self.data[..PAGE_HEADER_SIZE].copy_from_slice(&bytes[..PAGE_HEADER_SIZE]);
+        header.encode_into(&mut self.data[..PAGE_HEADER_SIZE]);
     }
 
     fn read_header(&self) -> PageHeader {
-        bincode::decode_from_slice(&self.data[..PAGE_HEADER_SIZE], bincode::config::standard())
-            .map(|(header, _)| header)
-            .unwrap()
+        PageHeader::decode_from(&self.data[..PAGE_HEADER_SIZE])
+    }
+
+    /// Next page in the chain this page belongs to (heap files, overflow chains)
+    pub fn next_page_id(&self) -> Option<PageId> {
+        let next = self.read_header().next_page_id;
+        if next == INVALID_PAGE_ID {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    pub fn set_next_page_id(&mut self, next: Option<PageId>) {
+        let mut header = self.read_header();
+        header.next_page_id = next.unwrap_or(INVALID_PAGE_ID);
+        self.write_header(&header);
+        self.mark_dirty();
+    }
+
+    /// LSN of the last logged change applied to this page (0 if none)
+    ///
+    /// Recovery redoes a log record against a page only if the page LSN is
+    /// older than the record.
+    pub fn lsn(&self) -> LogSequenceNumber {
+        self.read_header().lsn
+    }
+
+    pub fn set_lsn(&mut self, lsn: LogSequenceNumber) {
+        let mut header = self.read_header();
+        header.lsn = lsn;
+        self.write_header(&header);
+        self.mark_dirty();
     }
 
     // Verify page checksum
@@ -88,13 +123,25 @@ impl Page {
 }
 
 // Page header containing metadata
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+//
+// Layout (little-endian):
+//   [0..4)   checksum
+//   [4]      page type
+//   [5]      reserved
+//   [6..8)   free space offset (end of slot directory)
+//   [8..10)  number of slots
+//   [10..12) free space (bytes, including fragmented space)
+//   [12..20) next page id
+//   [20..28) page LSN
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct PageHeader {
     checksum: u32,
     page_type: PageType,
     free_space_offset: u16,
     num_slots: u16,
     free_space: u16,
+    next_page_id: PageId,
+    lsn: LogSequenceNumber,
 }
 
 impl PageHeader {
@@ -105,19 +152,59 @@ impl PageHeader {
             free_space_offset: PAGE_HEADER_SIZE as u16,
             num_slots: 0,
             free_space: (page_size - PAGE_HEADER_SIZE) as u16,
+            next_page_id: INVALID_PAGE_ID,
+            lsn: 0,
+        }
+    }
+
+    fn encode_into(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.checksum.to_le_bytes());
+        buf[4] = self.page_type as u8;
+        buf[5] = 0;
+        buf[6..8].copy_from_slice(&self.free_space_offset.to_le_bytes());
+        buf[8..10].copy_from_slice(&self.num_slots.to_le_bytes());
+        buf[10..12].copy_from_slice(&self.free_space.to_le_bytes());
+        buf[12..20].copy_from_slice(&self.next_page_id.to_le_bytes());
+        buf[20..28].copy_from_slice(&self.lsn.to_le_bytes());
+    }
+
+    fn decode_from(buf: &[u8]) -> Self {
+        Self {
+            checksum: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            page_type: PageType::from_u8(buf[4]),
+            free_space_offset: u16::from_le_bytes([buf[6], buf[7]]),
+            num_slots: u16::from_le_bytes([buf[8], buf[9]]),
+            free_space: u16::from_le_bytes([buf[10], buf[11]]),
+            next_page_id: u64::from_le_bytes([
+                buf[12], buf[13], buf[14], buf[15], buf[16], buf[17], buf[18], buf[19],
+            ]),
+            lsn: u64::from_le_bytes([
+                buf[20], buf[21], buf[22], buf[23], buf[24], buf[25], buf[26], buf[27],
+            ]),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[repr(u8)]
 enum PageType {
-    Slotted,
-    Overflow,
-    Index,
+    Slotted = 0,
+    Overflow = 1,
+    Index = 2,
+}
+
+impl PageType {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => PageType::Overflow,
+            2 => PageType::Index,
+            _ => PageType::Slotted,
+        }
+    }
 }
 
 // Slot directory entry
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 struct Slot {
     offset: u16,
     length: u16,
@@ -134,6 +221,10 @@ impl Slot {
 }
 
 // Slotted page implementation for variable-length records
+//
+// Slot ids are stable for the lifetime of a record: deleting a record leaves an
+// empty slot behind and compaction never renumbers slots, so a (page, slot)
+// pair can be used as a physical row id.
 pub struct SlottedPage {
     page: Page,
 }
@@ -149,48 +240,89 @@ impl SlottedPage {
         Self { page }
     }
 
+    pub fn page_id(&self) -> PageId {
+        self.page.id
+    }
+
+    pub fn page(&self) -> &Page {
+        &self.page
+    }
+
+    pub fn num_slots(&self) -> u16 {
+        self.page.read_header().num_slots
+    }
+
+    /// Largest record that can be stored in an empty page of the given size
+    pub fn max_record_size(page_size: usize) -> usize {
+        page_size - PAGE_HEADER_SIZE - SLOT_SIZE
+    }
+
     // Insert a record into the slotted page
     pub fn insert_record(&mut self, data: &[u8]) -> Option<SlotId> {
+        // Reuse a free slot or append a new one
+        let slot_id = self.find_free_slot().unwrap_or(self.num_slots());
+        self.insert_record_at(slot_id, data).then_some(slot_id)
+    }
+
+    /// Insert a record under a specific slot id, growing the slot directory
+    /// if needed
+    ///
+    /// Returns false if the slot is in use or the record does not fit. Used
+    /// to put rows back where the log says they were.
+    pub fn insert_record_at(&mut self, slot_id: SlotId, data: &[u8]) -> bool {
+        if data.is_empty() {
+            // Zero-length slots mark free entries in the directory
+            return false;
+        }
+
         let record_size = data.len();
         let header = self.page.read_header();
+        if slot_id < header.num_slots && !self.read_slot(slot_id).is_empty() {
+            return false;
+        }
 
-        // Check if we have enough space
-        let required_space = record_size + SLOT_SIZE;
-        if header.free_space < required_space as u16 {
-            return None;
+        // Slots between the end of the directory and `slot_id` are added empty
+        let new_slots = (slot_id as usize + 1).saturating_sub(header.num_slots as usize);
+        let slot_overhead = new_slots * SLOT_SIZE;
+
+        // Check if we have enough space overall
+        let required_space = record_size + slot_overhead;
+        if (header.free_space as usize) < required_space {
+            return false;
         }
 
-        // Find a free slot or create new one
-        let slot_id = self.find_free_slot().unwrap_or(header.num_slots);
+        // Records grow from the end of the page backwards; make sure the
+        // contiguous gap between directory and records is large enough,
+        // compacting fragmented space left behind by deletes if needed
+        let directory_end = PAGE_HEADER_SIZE + (header.num_slots as usize * SLOT_SIZE);
+        if self.get_last_record_offset() < directory_end + slot_overhead + record_size {
+            self.compact();
+        }
 
-        // Calculate offset for new record (grows from end of page backwards)
-        let record_offset = if slot_id == 0 {
-            self.page.data.len() - record_size
-        } else {
-            // Find the last used record's offset
-            let last_offset = self.get_last_record_offset();
-            last_offset - record_size
-        };
+        let record_offset = self.get_last_record_offset() - record_size;
 
         // Write record data
         self.page.data[record_offset..record_offset + record_size].copy_from_slice(data);
 
-        // Update slot
+        // Update slots
+        for empty_id in header.num_slots..slot_id {
+            self.write_slot(empty_id, &Slot::new(0, 0));
+        }
         let slot = Slot::new(record_offset as u16, record_size as u16);
         self.write_slot(slot_id, &slot);
 
         // Update header
-        let mut new_header = header;
+        let mut new_header = self.page.read_header();
         if slot_id >= new_header.num_slots {
             new_header.num_slots = slot_id + 1;
+            new_header.free_space_offset += slot_overhead as u16;
         }
         new_header.free_space -= required_space as u16;
-        new_header.free_space_offset += SLOT_SIZE as u16;
 
         self.page.write_header(&new_header);
         self.page.mark_dirty();
 
-        Some(slot_id)
+        true
     }
 
     // Get a record from the slotted page
@@ -223,13 +355,14 @@ impl SlottedPage {
             return false;
         }
 
-        // Mark slot as empty
+        // Mark slot as empty; the directory entry itself stays allocated so
+        // that the slot id can be reused without shifting other records
         let empty_slot = Slot::new(0, 0);
         self.write_slot(slot_id, &empty_slot);
 
         // Update free space
         let mut new_header = header;
-        new_header.free_space += slot.length + SLOT_SIZE as u16;
+        new_header.free_space += slot.length;
         self.page.write_header(&new_header);
 
         self.page.mark_dirty();
@@ -237,7 +370,15 @@ impl SlottedPage {
     }
 
     // Update a record in place
+    //
+    // The slot id is preserved. Returns false if the slot is empty or the new
+    // record does not fit into this page.
     pub fn update_record(&mut self, slot_id: SlotId, data: &[u8]) -> bool {
+        let header = self.page.read_header();
+        if slot_id >= header.num_slots || data.is_empty() {
+            return false;
+        }
+
         let slot = self.read_slot(slot_id);
         if slot.is_empty() {
             return false;
@@ -252,15 +393,43 @@ impl SlottedPage {
             if data.len() < slot.length as usize {
                 let new_slot = Slot::new(slot.offset, data.len() as u16);
                 self.write_slot(slot_id, &new_slot);
+
+                let mut new_header = header;
+                new_header.free_space += slot.length - data.len() as u16;
+                self.page.write_header(&new_header);
             }
 
             self.page.mark_dirty();
-            true
-        } else {
-            // Delete old record and insert new one
-            self.delete_record(slot_id);
-            self.insert_record(data).is_some()
+            return true;
+        }
+
+        // Relocate the record within the page, keeping its slot id
+        let growth = data.len() - slot.length as usize;
+        if (header.free_space as usize) < growth {
+            return false;
         }
+
+        // Release the old copy, then make room for the new one
+        self.write_slot(slot_id, &Slot::new(0, 0));
+        let mut released = header.clone();
+        released.free_space += slot.length;
+        self.page.write_header(&released);
+
+        let directory_end = PAGE_HEADER_SIZE + (header.num_slots as usize * SLOT_SIZE);
+        if self.get_last_record_offset() < directory_end + data.len() {
+            self.compact();
+        }
+
+        let record_offset = self.get_last_record_offset() - data.len();
+        self.page.data[record_offset..record_offset + data.len()].copy_from_slice(data);
+        self.write_slot(slot_id, &Slot::new(record_offset as u16, data.len() as u16));
+
+        let mut new_header = self.page.read_header();
+        new_header.free_space -= data.len() as u16;
+        self.page.write_header(&new_header);
+
+        self.page.mark_dirty();
+        true
     }
 
     // Helper to collect all valid records
@@ -275,74 +444,51 @@ impl SlottedPage {
         records
     }
 
+    /// Iterate over all live records as (slot id, record bytes)
+    pub fn records(&self) -> Vec<(SlotId, Vec<u8>)> {
+        let header = self.page.read_header();
+        (0..header.num_slots)
+            .filter_map(|slot_id| self.get_record(slot_id).map(|data| (slot_id, data)))
+            .collect()
+    }
+
     // Compact the page to reclaim fragmented space
     //
-    // CRITICAL FIX: Optimized from O(n²) to O(n)
-    //
-    // **Previous Implementation (O(n²)):**
-    // - collect_valid_records(): O(n) - iterate all slots
-    // - Reset page: O(1)
-    // - Reinsert n records: O(n²) - each insert_record() scans slots
-    // - Total: O(n) + O(n²) = O(n²)
-    //
-    // **New Implementation (O(n)):**
-    // - Collect valid records: O(n)
-    // - Reset page: O(1)
-    // - Direct slot/record placement without search: O(n)
-    // - Total: O(n) + O(n) = O(n)
-    //
-    // **Memory Efficiency:**
-    // - Records are moved in-place rather than copied
-    // - Slot directory is rebuilt directly
-    // - No redundant slot searching
+    // Live records are repacked against the end of the page in a single
+    // O(n) pass. Slot ids are preserved (empty slots stay empty), so row ids
+    // handed out before compaction remain valid afterwards.
     //
     // See: diagrams/02_storage_layer_flow.md - Issue #2.3
     pub fn compact(&mut self) {
-        // Collect all valid records (O(n))
-        let records = self.collect_valid_records();
-
-        if records.is_empty() {
-            return;
-        }
-
-        // Get page metadata
+        let header = self.page.read_header();
         let page_size = self.page.data.len();
-        let _page_id = self.page.id;
 
-        // Create new page with fresh header (O(1))
-        let mut header = PageHeader::new(page_size);
-        header.num_slots = records.len() as u16;
+        // Collect all valid records with their slot ids (O(n))
+        let records: Vec<(SlotId, Vec<u8>)> = self.records();
 
-        // Calculate layout: records grow from end, slots grow from header
+        // Place records and rebuild slot directory (O(n) - single pass)
         let mut next_record_offset = page_size;
-
-        // Place records and build slot directory (O(n) - single pass)
-        for (slot_id, data) in records.iter().enumerate() {
+        for (slot_id, data) in &records {
             let record_size = data.len();
 
             // Place record at end of free space (growing backwards)
             next_record_offset -= record_size;
 
-            // Write record data directly
             self.page.data[next_record_offset..next_record_offset + record_size]
                 .copy_from_slice(data);
 
-            // Write slot entry directly (no searching needed)
-            let slot = Slot::new(next_record_offset as u16, record_size as u16);
-            let slot_offset = PAGE_HEADER_SIZE + (slot_id * SLOT_SIZE);
-            let slot_bytes = bincode::encode_to_vec(&slot, bincode::config::standard()).unwrap();
-            self.page.data[slot_offset..slot_offset + SLOT_SIZE]
-                .copy_from_slice(&slot_bytes[..SLOT_SIZE]);
+            self.write_slot(
+                *slot_id,
+                &Slot::new(next_record_offset as u16, record_size as u16),
+            );
         }
 
         // Update header with new free space calculation
-        let slots_end = PAGE_HEADER_SIZE + (records.len() * SLOT_SIZE);
-        header.free_space_offset = slots_end as u16;
-        header.free_space = (next_record_offset - slots_end) as u16;
-
-        // Write final header
-        let header_bytes = bincode::encode_to_vec(&header, bincode::config::standard()).unwrap();
-        self.page.data[..PAGE_HEADER_SIZE].copy_from_slice(&header_bytes[..PAGE_HEADER_SIZE]);
+        let slots_end = PAGE_HEADER_SIZE + (header.num_slots as usize * SLOT_SIZE);
+        let mut new_header = header;
+        new_header.free_space_offset = slots_end as u16;
+        new_header.free_space = (next_record_offset - slots_end) as u16;
+        self.page.write_header(&new_header);
 
         self.page.mark_dirty();
     }
@@ -353,6 +499,16 @@ impl SlottedPage {
         header.free_space
     }
 
+    /// Whether a record of `len` bytes can be inserted without splitting
+    pub fn can_fit(&self, len: usize) -> bool {
+        let slot_overhead = if self.find_free_slot().is_some() {
+            0
+        } else {
+            SLOT_SIZE
+        };
+        len > 0 && self.free_space() as usize >= len + slot_overhead
+    }
+
     // Check if page needs compaction
     pub fn needs_compaction(&self) -> bool {
         let header = self.page.read_header();
@@ -385,15 +541,16 @@ impl SlottedPage {
     fn read_slot(&self, slot_id: SlotId) -> Slot {
         let offset = PAGE_HEADER_SIZE + slot_id as usize * SLOT_SIZE;
         let bytes = &self.page.data[offset..offset + SLOT_SIZE];
-        bincode::decode_from_slice(bytes, bincode::config::standard())
-            .map(|(slot, _)| slot)
-            .unwrap()
+        Slot::new(
+            u16::from_le_bytes([bytes[0], bytes[1]]),
+            u16::from_le_bytes([bytes[2], bytes[3]]),
+        )
     }
 
     fn write_slot(&mut self, slot_id: SlotId, slot: &Slot) {
         let offset = PAGE_HEADER_SIZE + (slot_id as usize * SLOT_SIZE);
-        let bytes = bincode::encode_to_vec(slot, bincode::config::standard()).unwrap();
-        self.page.data[offset..offset + SLOT_SIZE].copy_from_slice(&bytes[..SLOT_SIZE]);
+        self.page.data[offset..offset + 2].copy_from_slice(&slot.offset.to_le_bytes());
+        self.page.data[offset + 2..offset + 4].copy_from_slice(&slot.length.to_le_bytes());
     }
 
     fn get_last_record_offset(&self) -> usize {
@@ -653,6 +810,61 @@ mod tests {
         assert!(free_after >= free_before);
     }
 
+    #[test]
+    fn test_compaction_preserves_slot_ids() {
+        let mut page = SlottedPage::new(1, 8192);
+        let payload = vec![7u8; 600];
+
+        // Offsets beyond 255 must survive the header/slot encoding
+        let slots: Vec<_> = (0..10)
+            .map(|_| page.insert_record(&payload).unwrap())
+            .collect();
+        page.delete_record(slots[3]);
+        page.compact();
+
+        for (i, &slot) in slots.iter().enumerate() {
+            if i == 3 {
+                assert!(page.get_record(slot).is_none());
+            } else {
+                assert_eq!(page.get_record(slot).unwrap(), payload);
+            }
+        }
+
+        // The freed slot is reused before a new one is appended
+        assert_eq!(page.insert_record(b"reuse"), Some(slots[3]));
+    }
+
+    #[test]
+    fn test_next_page_id_roundtrip() {
+        let mut page = Page::new(5, 4096);
+        assert_eq!(page.next_page_id(), None);
+
+        page.set_next_page_id(Some(42));
+        assert!(page.is_dirty);
+
+        let slotted = SlottedPage::from_page(page);
+        assert_eq!(slotted.page().next_page_id(), Some(42));
+    }
+
+    #[test]
+    fn test_page_lsn_and_insert_at_slot() {
+        let mut page = SlottedPage::new(3, 4096);
+        assert_eq!(page.page().lsn(), 0);
+
+        // Slots below the requested one are created empty
+        assert!(page.insert_record_at(2, b"third"));
+        assert_eq!(page.num_slots(), 3);
+        assert!(page.get_record(0).is_none());
+        assert!(!page.insert_record_at(2, b"again"));
+        assert_eq!(page.insert_record(b"first"), Some(0));
+
+        let mut page = page.into_page();
+        page.set_lsn(77);
+        let page = SlottedPage::from_page(page);
+        assert_eq!(page.page().lsn(), 77);
+        assert_eq!(page.get_record(2).unwrap(), b"third");
+    }
+
     #[test]
     fn test_page_splitting() {
         let mut page = SlottedPage::new(1, 4096);
@@ -120,6 +120,11 @@ impl TransactionManager {
         Ok(txn_id)
     }
 
+    /// Isolation level of transactions begun without one.
+    pub fn default_isolation(&self) -> IsolationLevel {
+        self.default_isolation
+    }
+
     /// Begins a read-only transaction.
     ///
     /// Read-only transactions can have better performance and
@@ -23,6 +23,8 @@
 // | [`lock_manager`] | Lock acquisition and release |
 // | [`wal_manager`] | Write-ahead log operations |
 // | [`version_store`] | MVCC version storage |
+// | [`row_versions`] | Committed row versions for snapshot reads of tables |
+// | [`sql_transaction`] | SQL transactions of client sessions |
 // | [`deadlock`] | Deadlock detection and resolution |
 // | [`snapshot`] | Snapshot isolation management |
 // | [`recovery_manager`] | Crash recovery and checkpointing |
@@ -87,9 +89,13 @@ pub mod wal_manager;
 // Concurrency control
 pub mod deadlock;
 pub mod occ_manager;
+pub mod row_versions;
 pub mod snapshot;
 pub mod version_store;
 
+// SQL sessions
+pub mod sql_transaction;
+
 // Distributed transactions
 pub mod two_phase_commit;
 
@@ -144,6 +150,10 @@ pub use deadlock::{
     DeadlockDetector, DeadlockDetectorConfig, DeadlockStats, VictimSelectionPolicy,
 };
 
+// Row versions and SQL transactions
+pub use row_versions::{IndexedRow, RowChange, RowVersions};
+pub use sql_transaction::{RowRef, SqlTransaction};
+
 // Snapshot isolation
 pub use snapshot::{Snapshot, SnapshotManager};
 
@@ -282,6 +282,19 @@ impl<T: Clone> VersionChain<T> {
         before_len - self.versions.len()
     }
 
+    /// Remove versions superseded at or before `horizon`, keeping the one a
+    /// reader at `horizon` sees
+    pub fn prune_before(&mut self, horizon: &HybridTimestamp) -> usize {
+        let visible = self
+            .versions
+            .iter()
+            .rposition(|v| v.created_at <= *horizon)
+            .unwrap_or(0);
+        self.versions.drain(..visible);
+        self.head = self.versions.len().saturating_sub(1);
+        visible
+    }
+
     /// Get the number of versions in the chain
     pub fn len(&self) -> usize {
         self.versions.len()
@@ -531,6 +544,63 @@ impl<K: Clone + Eq + std::hash::Hash, V: Clone> MVCCManager<K, V> {
         Ok(total_collected)
     }
 
+    /// Drop versions no active snapshot can read any more
+    ///
+    /// Each chain keeps the version visible at the oldest snapshot (or now,
+    /// when there is none) and everything newer. Chains left with a single
+    /// version that old are removed, and their keys returned.
+    pub fn prune(&self) -> Vec<K> {
+        let horizon = self
+            .active_snapshots
+            .read()
+            .values()
+            .min()
+            .copied()
+            .unwrap_or_else(|| self.clock.now());
+
+        let mut collected = 0;
+        let mut settled = Vec::new();
+        self.versions.write().retain(|key, chain| {
+            let mut chain = chain.lock().unwrap();
+            collected += chain.prune_before(&horizon);
+            let keep =
+                chain.len() > 1 || chain.get_latest().is_some_and(|v| v.created_at > horizon);
+            if !keep {
+                collected += chain.len();
+                settled.push(key.clone());
+            }
+            keep
+        });
+
+        self.total_version_count
+            .fetch_sub(collected as u64, Ordering::SeqCst);
+        let mut stats = self.stats.write();
+        stats.gc_runs += 1;
+        stats.versions_collected += collected as u64;
+        stats.active_versions = stats.active_versions.saturating_sub(collected as u64);
+        settled
+    }
+
+    /// Drop every version of `key`, returning how many there were
+    pub fn remove(&self, key: &K) -> usize {
+        let Some(chain) = self.versions.write().remove(key) else {
+            return 0;
+        };
+        let removed = chain.lock().unwrap().len();
+        self.total_version_count
+            .fetch_sub(removed as u64, Ordering::SeqCst);
+        let mut stats = self.stats.write();
+        stats.active_versions = stats.active_versions.saturating_sub(removed as u64);
+        removed
+    }
+
+    /// Timestamp of the newest version of `key`
+    pub fn last_write(&self, key: &K) -> Option<HybridTimestamp> {
+        let versions = self.versions.read();
+        let chain = versions.get(key)?.lock().unwrap();
+        chain.get_latest().map(|v| v.created_at)
+    }
+
     /// Get current statistics
     pub fn get_stats(&self) -> MVCCStats {
         self.stats.read().clone()
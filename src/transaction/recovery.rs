@@ -3,16 +3,15 @@
 // fuzzy checkpointing, media recovery, and point-in-time recovery
 
 // Removed unused import: use std::io::{Write as IoWrite};
-use super::wal::{LogRecord, PageId, WALEntry, WALManager, LSN};
+use super::wal::{LogRecord, PageId, SegmentArchiver, WALEntry, WALManager, LSN};
 use super::TransactionId;
 use crate::error::{DbError, Result};
-use futures::future::BoxFuture;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::SystemTime;
 
 /// Recovery state
@@ -52,10 +51,99 @@ struct DirtyPageEntry {
     rec_lsn: LSN, // Recovery LSN - first log record that dirtied this page
 }
 
+/// Pages that logged changes are redone against and undone from
+pub trait RecoverablePages: Send + Sync {
+    /// Apply the page change described by `record` (an Insert, Update or
+    /// Delete) as the change logged at `lsn`, unless the page LSN shows it is
+    /// already there. Returns whether the page changed.
+    fn apply_logged(&self, lsn: LSN, record: &LogRecord) -> Result<bool>;
+
+    /// Make every change applied so far durable
+    fn flush_pages(&self) -> Result<()>;
+}
+
+/// Build the CLR that undoes an Insert, Update or Delete
+///
+/// The CLR's redo operation is the inverse change, and its undo-next LSN
+/// skips past the undone record.
+pub fn compensation(record: &LogRecord) -> Option<LogRecord> {
+    let (txn_id, page_id, undo_next_lsn, inverse) = match record {
+        LogRecord::Update {
+            txn_id,
+            page_id,
+            offset,
+            before_image,
+            undo_next_lsn,
+            ..
+        } => (
+            txn_id,
+            page_id,
+            undo_next_lsn,
+            LogRecord::Update {
+                txn_id: *txn_id,
+                page_id: *page_id,
+                offset: *offset,
+                before_image: before_image.clone(),
+                after_image: before_image.clone(),
+                undo_next_lsn: None,
+            },
+        ),
+        // Undo insert by deleting
+        LogRecord::Insert {
+            txn_id,
+            page_id,
+            offset,
+            data,
+            undo_next_lsn,
+        } => (
+            txn_id,
+            page_id,
+            undo_next_lsn,
+            LogRecord::Delete {
+                txn_id: *txn_id,
+                page_id: *page_id,
+                offset: *offset,
+                deleted_data: data.clone(),
+                undo_next_lsn: None,
+            },
+        ),
+        // Undo delete by reinserting
+        LogRecord::Delete {
+            txn_id,
+            page_id,
+            offset,
+            deleted_data,
+            undo_next_lsn,
+        } => (
+            txn_id,
+            page_id,
+            undo_next_lsn,
+            LogRecord::Insert {
+                txn_id: *txn_id,
+                page_id: *page_id,
+                offset: *offset,
+                data: deleted_data.clone(),
+                undo_next_lsn: None,
+            },
+        ),
+        // CLRs are redo-only
+        _ => return None,
+    };
+
+    Some(LogRecord::CLR {
+        txn_id: *txn_id,
+        page_id: *page_id,
+        undo_next_lsn: *undo_next_lsn,
+        redo_operation: Box::new(inverse),
+    })
+}
+
 /// ARIES Recovery Manager
 pub struct ARIESRecoveryManager {
     /// WAL manager
     wal: Arc<WALManager>,
+    /// Pages recovery is applied to; without them only analysis has effect
+    pages: Option<Arc<dyn RecoverablePages>>,
     /// Transaction table (built during analysis)
     transaction_table: Arc<RwLock<HashMap<TransactionId, TransactionTableEntry>>>,
     /// Dirty page table (built during analysis)
@@ -113,6 +201,7 @@ impl ARIESRecoveryManager {
     pub fn new(wal: Arc<WALManager>, config: RecoveryConfig) -> Self {
         Self {
             wal,
+            pages: None,
             transaction_table: Arc::new(RwLock::new(HashMap::new())),
             dirty_page_table: Arc::new(RwLock::new(HashMap::new())),
             state: Arc::new(RwLock::new(RecoveryState::NotStarted)),
@@ -122,7 +211,16 @@ impl ARIESRecoveryManager {
         }
     }
 
+    /// Redo and undo against `pages`
+    pub fn with_pages(mut self, pages: Arc<dyn RecoverablePages>) -> Self {
+        self.pages = Some(pages);
+        self
+    }
+
     /// Run full ARIES recovery (Analysis, Redo, Undo)
+    ///
+    /// With pages attached, the recovered pages are flushed and a checkpoint
+    /// is logged, so the next recovery starts from there.
     pub async fn recover(&self) -> Result<()> {
         let start = std::time::Instant::now();
 
@@ -149,6 +247,11 @@ impl ARIESRecoveryManager {
         let undo_time = undo_start.elapsed().as_millis() as u64;
         self.stats.write().undo_time_ms = undo_time;
 
+        if let Some(pages) = &self.pages {
+            pages.flush_pages()?;
+            self.checkpoint_recovered().await?;
+        }
+
         *self.state.write() = RecoveryState::Completed;
         let total_time = start.elapsed().as_millis() as u64;
         self.stats.write().last_recovery_time_ms = total_time;
@@ -408,15 +511,19 @@ impl ARIESRecoveryManager {
 
         drop(txn_table);
 
+        // Undo chains are followed through the log as it was at the crash
+        let log: HashMap<LSN, WALEntry> = self
+            .wal
+            .read_from(1)?
+            .into_iter()
+            .map(|entry| (entry.lsn, entry))
+            .collect();
+
         // Process undo queue in reverse LSN order
         while let Some((&lsn, &txn_id)) = undo_queue.iter().next_back() {
             undo_queue.remove(&lsn);
 
-            // Read the log record
-            let entries = self.wal.read_from(lsn)?;
-            let entry = entries.iter().find(|e| e.lsn == lsn);
-
-            if let Some(entry) = entry {
+            if let Some(entry) = log.get(&lsn) {
                 // Undo the operation
                 self.undo_record(entry).await?;
                 self.stats.write().records_undone += 1;
@@ -432,11 +539,18 @@ impl ARIESRecoveryManager {
 
                 if let Some(next_lsn) = next_lsn {
                     undo_queue.insert(next_lsn, txn_id);
-                } else {
-                    // No more records to undo for this transaction
-                    self.stats.write().transactions_rolled_back += 1;
+                    continue;
                 }
             }
+
+            // No more records to undo for this transaction
+            self.wal
+                .append(LogRecord::Abort {
+                    txn_id,
+                    timestamp: SystemTime::now(),
+                })
+                .await?;
+            self.stats.write().transactions_rolled_back += 1;
         }
 
         println!(
@@ -448,157 +562,58 @@ impl ARIESRecoveryManager {
     }
 
     /// Redo a log record
-    fn redo_record<'a>(&'a self, entry: &'a WALEntry) -> BoxFuture<'a, Result<()>> {
-        Box::pin(async move {
-            match &entry.record {
-                LogRecord::Update {
-                    page_id,
-                    offset,
-                    after_image,
-                    ..
-                } => {
-                    // Apply after image to page
-                    self.apply_to_page(*page_id, *offset, after_image).await?;
-                }
-
-                LogRecord::Insert {
-                    page_id,
-                    offset,
-                    data,
-                    ..
-                } => {
-                    // Insert data at offset
-                    self.apply_to_page(*page_id, *offset, data).await?;
-                }
-
-                LogRecord::Delete {
-                    page_id: _page_id,
-                    offset: _offset,
-                    deleted_data: _deleted_data,
-                    ..
-                } => {
-                    // Mark as deleted (in production, this would update page)
-                    // For now, simulate
-                }
-
-                LogRecord::CLR { redo_operation, .. } => {
-                    // CLRs contain the redo operation
-                    self.redo_record(&WALEntry {
-                        lsn: entry.lsn,
-                        prev_lsn: entry.prev_lsn,
-                        record: *redo_operation.clone(),
-                        size: entry.size,
-                        checksum: entry.checksum,
-                    })
-                    .await?;
-                }
-
-                _ => {}
-            }
-
-            Ok(())
-        })
+    async fn redo_record(&self, entry: &WALEntry) -> Result<()> {
+        self.apply(entry.lsn, &entry.record)?;
+        Ok(())
     }
 
-    /// Undo a log record by writing a CLR
+    /// Undo a log record by writing a CLR and applying its redo operation
     async fn undo_record(&self, entry: &WALEntry) -> Result<()> {
-        match &entry.record {
-            LogRecord::Update {
-                txn_id,
-                page_id,
-                offset,
-                before_image,
-                undo_next_lsn,
-                ..
-            } => {
-                // Create CLR with reverse operation
-                let clr = LogRecord::CLR {
-                    txn_id: *txn_id,
-                    page_id: *page_id,
-                    undo_next_lsn: *undo_next_lsn,
-                    redo_operation: Box::new(LogRecord::Update {
-                        txn_id: *txn_id,
-                        page_id: *page_id,
-                        offset: *offset,
-                        before_image: before_image.clone(),
-                        after_image: before_image.clone(),
-                        undo_next_lsn: None,
-                    }),
-                };
-
-                // Write CLR
-                self.wal.append(clr).await?;
-
-                // Apply before image
-                self.apply_to_page(*page_id, *offset, before_image).await?;
-            }
-
-            LogRecord::Insert {
-                txn_id,
-                page_id,
-                offset,
-                data,
-                undo_next_lsn,
-            } => {
-                // Undo insert by deleting
-                let clr = LogRecord::CLR {
-                    txn_id: *txn_id,
-                    page_id: *page_id,
-                    undo_next_lsn: *undo_next_lsn,
-                    redo_operation: Box::new(LogRecord::Delete {
-                        txn_id: *txn_id,
-                        page_id: *page_id,
-                        offset: *offset,
-                        deleted_data: data.clone(),
-                        undo_next_lsn: None,
-                    }),
-                };
-
-                self.wal.append(clr).await?;
-            }
-
-            LogRecord::Delete {
-                txn_id,
-                page_id,
-                offset,
-                deleted_data,
-                undo_next_lsn,
-            } => {
-                // Undo delete by reinserting
-                let clr = LogRecord::CLR {
-                    txn_id: *txn_id,
-                    page_id: *page_id,
-                    undo_next_lsn: *undo_next_lsn,
-                    redo_operation: Box::new(LogRecord::Insert {
-                        txn_id: *txn_id,
-                        page_id: *page_id,
-                        offset: *offset,
-                        data: deleted_data.clone(),
-                        undo_next_lsn: None,
-                    }),
-                };
+        // CLRs are redo-only, skip to undo_next_lsn
+        let Some(clr) = compensation(&entry.record) else {
+            return Ok(());
+        };
 
-                self.wal.append(clr).await?;
-                self.apply_to_page(*page_id, *offset, deleted_data).await?;
-            }
+        // Write CLR
+        let lsn = self.wal.append(clr.clone()).await?;
+        self.apply(lsn, &clr)?;
+        Ok(())
+    }
 
-            LogRecord::CLR {
-                undo_next_lsn: _, ..
-            } => {
-                // CLRs are redo-only, skip to undo_next_lsn
+    /// Apply the page change of a record logged at `lsn`
+    fn apply(&self, lsn: LSN, record: &LogRecord) -> Result<bool> {
+        let Some(pages) = &self.pages else {
+            return Ok(false);
+        };
+        match record {
+            // CLRs contain the redo operation
+            LogRecord::CLR { redo_operation, .. } => self.apply(lsn, redo_operation),
+            LogRecord::Update { .. } | LogRecord::Insert { .. } | LogRecord::Delete { .. } => {
+                pages.apply_logged(lsn, record)
             }
-
-            _ => {}
+            _ => Ok(false),
         }
-
-        Ok(())
     }
 
-    /// Apply data to a page (simulation)
-    async fn apply_to_page(&self, _page_id: PageId, _offset: u32, _data: &[u8]) -> Result<()> {
-        // In production, this would update the actual page in buffer pool
-        // For now, just simulate
-        Ok(())
+    /// Log a checkpoint once recovered pages are on disk and drop the log
+    /// before it: nothing earlier is needed to redo or undo anything
+    async fn checkpoint_recovered(&self) -> Result<()> {
+        let begin_lsn = self
+            .wal
+            .append(LogRecord::CheckpointBegin {
+                timestamp: SystemTime::now(),
+            })
+            .await?;
+        let end_lsn = self
+            .wal
+            .append(LogRecord::CheckpointEnd {
+                active_txns: Vec::new(),
+                dirty_pages: Vec::new(),
+                timestamp: SystemTime::now(),
+            })
+            .await?;
+        self.wal.flush_to(end_lsn)?;
+        self.wal.truncate(begin_lsn)
     }
 
     /// Find the last checkpoint LSN
@@ -794,7 +809,6 @@ impl PointInTimeRecovery {
 
 /// Media Recovery Manager (for disk failures)
 pub struct MediaRecoveryManager {
-    #[allow(dead_code)]
     wal: Arc<WALManager>,
     archive_dir: PathBuf,
 }
@@ -804,6 +818,12 @@ impl MediaRecoveryManager {
         Self { wal, archive_dir }
     }
 
+    /// Archive every segment the WAL recycles from now on
+    pub fn start_archiving(self: &Arc<Self>) {
+        let archiver: Weak<dyn SegmentArchiver> = Arc::<Self>::downgrade(self);
+        self.wal.set_archiver(archiver);
+    }
+
     /// Recover from media failure using archive logs
     pub async fn recover_from_media_failure(&self) -> Result<()> {
         println!("Starting media recovery from archive logs...");
@@ -826,15 +846,27 @@ impl MediaRecoveryManager {
             .file_name()
             .ok_or_else(|| DbError::Storage("Invalid segment path".to_string()))?;
 
+        std::fs::create_dir_all(&self.archive_dir)
+            .map_err(|e| DbError::Storage(format!("Failed to create archive directory: {}", e)))?;
         let archive_path = self.archive_dir.join(filename);
 
-        std::fs::copy(segment_path, archive_path)
+        std::fs::copy(segment_path, &archive_path)
             .map_err(|e| DbError::Storage(format!("Failed to archive segment: {}", e)))?;
+        // The segment is removed once this returns, so the copy must be durable
+        std::fs::File::open(&archive_path)
+            .and_then(|f| f.sync_all())
+            .map_err(|e| DbError::Storage(format!("Failed to sync archived segment: {}", e)))?;
 
         Ok(())
     }
 }
 
+impl SegmentArchiver for MediaRecoveryManager {
+    fn archive_segment(&self, segment_path: &Path) -> Result<()> {
+        MediaRecoveryManager::archive_segment(self, segment_path)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::wal::WALConfig;
@@ -879,4 +911,56 @@ mod tests {
         assert_eq!(undo_list.len(), 1);
         assert_eq!(undo_list[0], 1);
     }
+
+    #[tokio::test]
+    async fn test_truncated_segments_are_archived() {
+        let dir = tempdir().unwrap();
+        let wal_config = WALConfig {
+            enable_group_commit: false,
+            segment_size: 256,
+            ..Default::default()
+        };
+        let wal = Arc::new(WALManager::new(dir.path().join("wal"), wal_config).unwrap());
+        let media = Arc::new(MediaRecoveryManager::new(
+            wal.clone(),
+            dir.path().join("archive"),
+        ));
+        media.start_archiving();
+
+        for txn_id in 1..=20 {
+            wal.append(LogRecord::Begin {
+                txn_id,
+                timestamp: SystemTime::now(),
+            })
+            .await
+            .unwrap();
+        }
+        let first_segment = wal.segment_for_lsn(1).unwrap();
+        wal.truncate(wal.current_lsn()).unwrap();
+
+        assert!(!first_segment.exists());
+        let archived = dir
+            .path()
+            .join("archive")
+            .join(first_segment.file_name().unwrap());
+        assert!(archived.exists());
+        assert_eq!(
+            wal.get_stats().segments_archived,
+            wal.get_stats().segments_removed
+        );
+
+        // Without a live archiver, segments are kept
+        drop(media);
+        assert!(wal.truncate(wal.current_lsn()).is_ok());
+        for txn_id in 21..=40 {
+            wal.append(LogRecord::Begin {
+                txn_id,
+                timestamp: SystemTime::now(),
+            })
+            .await
+            .unwrap();
+        }
+        assert!(wal.truncate(wal.current_lsn()).is_err());
+        assert!(wal.segment_for_lsn(21).is_some());
+    }
 }
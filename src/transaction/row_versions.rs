@@ -0,0 +1,247 @@
+// Committed row versions for snapshot reads
+//
+// The heap always holds the latest committed image of every row. When a
+// transaction commits, the images it replaces are kept here in an
+// `MVCCManager`, keyed by table and row id and stamped with the commit
+// timestamp, so a reader whose snapshot predates the commit still sees the
+// rows as they were. A row without a version chain has not changed since
+// the oldest snapshot and is read straight from the heap.
+//
+// Chains are pruned whenever a snapshot ends: once every open snapshot is
+// newer than a row's last change the heap image is the only one needed.
+
+use crate::common::{TransactionId, Value};
+use crate::error::Result;
+use crate::index::IndexKey;
+use crate::storage::{RowId, TableStore};
+use crate::transaction::mvcc::{HybridTimestamp, MVCCConfig, MVCCManager};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{BTreeSet, HashMap};
+
+type RowKey = (String, RowId);
+
+// Timestamp of the first version of a row: the image it had before the
+// change that started its chain, committed before any open snapshot
+const BASE_TIMESTAMP: HybridTimestamp = HybridTimestamp {
+    physical: 0,
+    logical: 0,
+    node_id: 0,
+};
+
+pub struct RowVersions {
+    // `None` marks a row that did not exist (yet, or any more)
+    versions: MVCCManager<RowKey, Option<Vec<Value>>>,
+    // Rows of each table with a version chain
+    tracked: Mutex<HashMap<String, BTreeSet<RowId>>>,
+    // Shared while the heap is read, exclusive while a commit applies its
+    // changes, so readers never see half of one
+    commit_lock: RwLock<()>,
+}
+
+/// One row change of a commit; `before` is `None` for an inserted row and
+/// `after` is `None` for a deleted one
+pub struct RowChange {
+    pub table: String,
+    pub rid: RowId,
+    pub before: Option<Vec<Value>>,
+    pub after: Option<Vec<Value>>,
+}
+
+/// A row an index lookup found
+pub enum IndexedRow {
+    /// Unchanged since the oldest open snapshot: the key the index holds for
+    /// it and, if it was asked for, its heap image
+    Current {
+        key: IndexKey,
+        row: Option<Vec<Value>>,
+    },
+    /// Changed by a recent commit, so it may have had another key in the
+    /// snapshot: the image the snapshot sees, not checked against the lookup
+    Changed(Vec<Value>),
+}
+
+impl RowVersions {
+    pub fn new() -> Self {
+        Self {
+            versions: MVCCManager::new(MVCCConfig::default()),
+            tracked: Mutex::new(HashMap::new()),
+            commit_lock: RwLock::new(()),
+        }
+    }
+
+    /// Take a snapshot for `txn_id`; versions it can see are kept until
+    /// `end_snapshot`
+    pub fn begin_snapshot(&self, txn_id: TransactionId) -> HybridTimestamp {
+        let _shared = self.commit_lock.read();
+        self.versions.begin_snapshot(txn_id)
+    }
+
+    pub fn end_snapshot(&self, txn_id: TransactionId) {
+        self.versions.end_snapshot(txn_id);
+        self.prune();
+    }
+
+    /// A read timestamp later than every commit so far
+    pub fn now(&self) -> HybridTimestamp {
+        self.versions.clock().now()
+    }
+
+    /// Rows of `table` as committed at `read_ts`, in heap order; rows
+    /// deleted since follow in row id order
+    pub fn scan(
+        &self,
+        store: &TableStore,
+        table: &str,
+        read_ts: HybridTimestamp,
+    ) -> Result<Vec<(RowId, Vec<Value>)>> {
+        let _shared = self.commit_lock.read();
+        let mut rows: Vec<(RowId, Option<Vec<Value>>)> = store
+            .scan(table)?
+            .into_iter()
+            .map(|(rid, row)| (rid, Some(row)))
+            .collect();
+
+        if let Some(tracked) = self.tracked.lock().get(table) {
+            let positions: HashMap<RowId, usize> = rows
+                .iter()
+                .enumerate()
+                .map(|(i, (rid, _))| (*rid, i))
+                .collect();
+            for rid in tracked {
+                let key = (table.to_string(), *rid);
+                let Some(image) = self.versions.read(&key, &read_ts)? else {
+                    continue;
+                };
+                match positions.get(rid) {
+                    Some(&i) => rows[i].1 = image,
+                    None => rows.push((*rid, image)),
+                }
+            }
+        }
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(rid, row)| row.map(|row| (rid, row)))
+            .collect())
+    }
+
+    /// Rows of `table` an index lookup finds, as committed at `read_ts`
+    ///
+    /// `lookup` runs with commits excluded and returns the index entries it
+    /// finds; they describe the heap. Every row with a version chain that is
+    /// visible at `read_ts` is returned as `IndexedRow::Changed` in place of
+    /// its entries, so callers must recheck the condition they looked up.
+    /// With `fetch`, the other rows come with their heap image.
+    pub fn index_scan(
+        &self,
+        store: &TableStore,
+        table: &str,
+        read_ts: HybridTimestamp,
+        fetch: bool,
+        lookup: impl FnOnce() -> Result<Vec<(IndexKey, RowId)>>,
+    ) -> Result<Vec<(RowId, IndexedRow)>> {
+        let _shared = self.commit_lock.read();
+        let entries = lookup()?;
+        let tracked = self.tracked.lock().get(table).cloned().unwrap_or_default();
+
+        let mut rows = Vec::with_capacity(entries.len());
+        for (key, rid) in entries {
+            if tracked.contains(&rid) {
+                continue;
+            }
+            let row = match fetch {
+                true => match store.get_row(table, rid)? {
+                    Some(row) => Some(row),
+                    None => continue,
+                },
+                false => None,
+            };
+            rows.push((rid, IndexedRow::Current { key, row }));
+        }
+        for rid in tracked {
+            let image = match self.versions.read(&(table.to_string(), rid), &read_ts)? {
+                Some(image) => image,
+                None => store.get_row(table, rid)?,
+            };
+            if let Some(row) = image {
+                rows.push((rid, IndexedRow::Changed(row)));
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Run `f` with commits excluded, e.g. to build an index from the heap
+    /// without missing a change; `f` must not read rows through `self`
+    pub fn excluding_commits<T>(&self, f: impl FnOnce() -> T) -> T {
+        let _exclusive = self.commit_lock.write();
+        f()
+    }
+
+    /// Whether a commit after `read_ts` changed the row
+    pub fn changed_since(&self, table: &str, rid: RowId, read_ts: HybridTimestamp) -> bool {
+        self.versions
+            .last_write(&(table.to_string(), rid))
+            .is_some_and(|ts| ts > read_ts)
+    }
+
+    /// Apply a commit to the heap and record the images it replaces
+    ///
+    /// `apply` runs with commits and heap reads excluded. It first gets the
+    /// chance to reject the commit through `check`, then makes its heap
+    /// changes and returns them; they become visible to snapshots taken
+    /// after the returned commit timestamp.
+    pub fn commit(
+        &self,
+        txn_id: TransactionId,
+        check: impl FnOnce(&Self) -> Result<()>,
+        apply: impl FnOnce() -> Result<Vec<RowChange>>,
+    ) -> Result<HybridTimestamp> {
+        let _exclusive = self.commit_lock.write();
+        check(self)?;
+        let changes = apply()?;
+        let commit_ts = self.now();
+
+        let mut tracked = self.tracked.lock();
+        for change in changes {
+            let rows = tracked.entry(change.table.clone()).or_default();
+            let key = (change.table, change.rid);
+            if rows.insert(change.rid) {
+                self.versions
+                    .write(key.clone(), change.before, txn_id, BASE_TIMESTAMP)?;
+            }
+            self.versions.write(key, change.after, txn_id, commit_ts)?;
+        }
+        Ok(commit_ts)
+    }
+
+    /// Forget the versions of a table whose rows were replaced wholesale
+    /// (dropped, truncated or recreated)
+    pub fn forget_table(&self, table: &str) {
+        let _exclusive = self.commit_lock.write();
+        for rid in self.tracked.lock().remove(table).unwrap_or_default() {
+            self.versions.remove(&(table.to_string(), rid));
+        }
+    }
+
+    // Drop versions no snapshot needs; rows left without a chain are read
+    // from the heap again
+    fn prune(&self) {
+        let _exclusive = self.commit_lock.write();
+        let settled = self.versions.prune();
+        let mut tracked = self.tracked.lock();
+        for (table, rid) in settled {
+            if let Some(rows) = tracked.get_mut(&table) {
+                rows.remove(&rid);
+                if rows.is_empty() {
+                    tracked.remove(&table);
+                }
+            }
+        }
+    }
+}
+
+impl Default for RowVersions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
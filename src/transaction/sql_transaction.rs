@@ -0,0 +1,458 @@
+// SQL transactions
+//
+// A `SqlTransaction` is one transaction of a client session, or of a single
+// autocommitted statement. It reads the rows committed as of its snapshot
+// (see `RowVersions`) with its own changes laid over them. Its writes stay
+// in its write set until commit, so other sessions never see them and
+// rolling back - the whole transaction, to a savepoint, or a failed
+// statement - only drops the tail of the write set.
+//
+// Commit applies the write set to the heap in one `StoreTransaction`. Writers
+// never wait for each other: of two transactions changing the same row, the
+// first to commit wins and the other fails with a conflict when it commits.
+//
+// READ UNCOMMITTED and READ COMMITTED read from a new snapshot for every
+// statement. REPEATABLE READ, SERIALIZABLE and SNAPSHOT keep the snapshot
+// taken at BEGIN; SERIALIZABLE runs as snapshot isolation and does not
+// detect write skew.
+
+use crate::common::{TransactionId, Value};
+use crate::error::{DbError, Result};
+use crate::index::IndexKey;
+use crate::storage::{RowId, TableStore};
+use crate::transaction::mvcc::HybridTimestamp;
+use crate::transaction::row_versions::{IndexedRow, RowChange};
+use crate::transaction::{IsolationLevel, TransactionManager};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A row as a transaction sees it: stored in the heap, or inserted by the
+/// transaction itself and not yet committed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RowRef {
+    Stored(RowId),
+    Pending(usize),
+}
+
+// One change in a write set; `row` is `None` for a delete
+#[derive(Debug, Clone)]
+struct RowWrite {
+    table: String,
+    target: RowRef,
+    // Snapshot the row was read in; a commit after it is a conflict
+    read_ts: HybridTimestamp,
+    row: Option<Vec<Value>>,
+}
+
+struct TxnState {
+    isolation: IsolationLevel,
+    // Snapshot the current statement reads
+    read_ts: HybridTimestamp,
+    statements: usize,
+    writes: Vec<RowWrite>,
+    // Name and write set length of each savepoint, oldest first
+    savepoints: Vec<(String, usize)>,
+    finished: bool,
+}
+
+pub struct SqlTransaction {
+    id: TransactionId,
+    txn_manager: Arc<TransactionManager>,
+    store: Arc<TableStore>,
+    state: Mutex<TxnState>,
+}
+
+impl SqlTransaction {
+    /// Begin a transaction over `store`, registered with `txn_manager`
+    pub fn begin(
+        txn_manager: Arc<TransactionManager>,
+        store: Arc<TableStore>,
+        isolation: IsolationLevel,
+    ) -> Result<Self> {
+        let id = txn_manager
+            .begin_with_isolation(isolation)
+            .map_err(|e| DbError::Transaction(e.to_string()))?;
+        let read_ts = store.versions().begin_snapshot(id);
+        Ok(Self {
+            id,
+            txn_manager,
+            store,
+            state: Mutex::new(TxnState {
+                isolation,
+                read_ts,
+                statements: 0,
+                writes: Vec::new(),
+                savepoints: Vec::new(),
+                finished: false,
+            }),
+        })
+    }
+
+    pub fn id(&self) -> TransactionId {
+        self.id
+    }
+
+    pub fn isolation(&self) -> IsolationLevel {
+        self.state.lock().isolation
+    }
+
+    /// Change the isolation level; only before the first statement
+    pub fn set_isolation(&self, isolation: IsolationLevel) -> Result<()> {
+        let mut state = self.active()?;
+        if state.statements > 0 {
+            return Err(DbError::Transaction(
+                "SET TRANSACTION ISOLATION LEVEL must be called before any query".to_string(),
+            ));
+        }
+        state.isolation = isolation;
+        state.read_ts = self.store.versions().now();
+        Ok(())
+    }
+
+    /// Start a statement, returning the mark `rollback_statement` undoes to
+    pub fn begin_statement(&self) -> Result<usize> {
+        let mut state = self.active()?;
+        let fresh_snapshot = matches!(
+            state.isolation,
+            IsolationLevel::ReadUncommitted | IsolationLevel::ReadCommitted
+        );
+        if fresh_snapshot && state.statements > 0 {
+            state.read_ts = self.store.versions().now();
+        }
+        state.statements += 1;
+        Ok(state.writes.len())
+    }
+
+    /// Undo the writes of a failed statement
+    pub fn rollback_statement(&self, mark: usize) {
+        let mut state = self.state.lock();
+        state.writes.truncate(mark);
+        state.savepoints.retain(|&(_, len)| len <= mark);
+    }
+
+    /// Rows of `table` as this transaction sees them
+    pub fn scan(&self, table: &str) -> Result<Vec<(RowRef, Vec<Value>)>> {
+        let state = self.active()?;
+        let mut rows: Vec<(RowRef, Option<Vec<Value>>)> = self
+            .store
+            .versions()
+            .scan(&self.store, table, state.read_ts)?
+            .into_iter()
+            .map(|(rid, row)| (RowRef::Stored(rid), Some(row)))
+            .collect();
+
+        let mut writes = state.writes.iter().filter(|w| w.table == table).peekable();
+        if writes.peek().is_some() {
+            let mut positions: HashMap<RowRef, usize> = rows
+                .iter()
+                .enumerate()
+                .map(|(i, (target, _))| (*target, i))
+                .collect();
+            for write in writes {
+                match positions.get(&write.target) {
+                    Some(&i) => rows[i].1 = write.row.clone(),
+                    None => {
+                        positions.insert(write.target, rows.len());
+                        rows.push((write.target, write.row.clone()));
+                    }
+                }
+            }
+        }
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(target, row)| row.map(|row| (target, row)))
+            .collect())
+    }
+
+    /// Rows of `table` an index lookup finds, as this transaction sees them
+    ///
+    /// See `RowVersions::index_scan`; rows this transaction wrote are
+    /// returned as `IndexedRow::Changed` too, so callers must recheck the
+    /// condition they looked up.
+    pub fn index_scan(
+        &self,
+        table: &str,
+        fetch: bool,
+        lookup: impl FnOnce() -> Result<Vec<(IndexKey, RowId)>>,
+    ) -> Result<Vec<(RowRef, IndexedRow)>> {
+        let state = self.active()?;
+        let mut written: HashMap<RowRef, Option<&Vec<Value>>> = HashMap::new();
+        for write in state.writes.iter().filter(|w| w.table == table) {
+            written.insert(write.target, write.row.as_ref());
+        }
+
+        let mut rows: Vec<(RowRef, IndexedRow)> = self
+            .store
+            .versions()
+            .index_scan(&self.store, table, state.read_ts, fetch, lookup)?
+            .into_iter()
+            .map(|(rid, row)| (RowRef::Stored(rid), row))
+            .filter(|(target, _)| !written.contains_key(target))
+            .collect();
+        let mut changed: Vec<_> = written
+            .into_iter()
+            .filter_map(|(target, row)| Some((target, IndexedRow::Changed(row?.clone()))))
+            .collect();
+        changed.sort_by_key(|(target, _)| match target {
+            RowRef::Stored(rid) => (0, rid.to_u64()),
+            RowRef::Pending(i) => (1, *i as u64),
+        });
+        rows.extend(changed);
+        Ok(rows)
+    }
+
+    pub fn insert_row(&self, table: &str, row: &[Value]) -> Result<RowRef> {
+        let mut state = self.active()?;
+        let target = RowRef::Pending(state.writes.len());
+        state.write(table, target, Some(row.to_vec()));
+        Ok(target)
+    }
+
+    pub fn update_row(&self, table: &str, target: RowRef, row: &[Value]) -> Result<RowRef> {
+        let mut state = self.active()?;
+        state.write(table, target, Some(row.to_vec()));
+        Ok(target)
+    }
+
+    /// Delete a row; `false` if this transaction already deleted it
+    pub fn delete_row(&self, table: &str, target: RowRef) -> Result<bool> {
+        let mut state = self.active()?;
+        let deleted = state
+            .writes
+            .iter()
+            .rev()
+            .find(|w| w.target == target && w.table == table)
+            .is_some_and(|w| w.row.is_none());
+        if !deleted {
+            state.write(table, target, None);
+        }
+        Ok(!deleted)
+    }
+
+    pub fn savepoint(&self, name: &str) -> Result<()> {
+        let mut state = self.active()?;
+        let mark = state.writes.len();
+        state.savepoints.push((name.to_string(), mark));
+        Ok(())
+    }
+
+    /// Undo the writes made since savepoint `name`, which is kept
+    pub fn rollback_to_savepoint(&self, name: &str) -> Result<()> {
+        let mut state = self.active()?;
+        let position = state.savepoint(name)?;
+        let mark = state.savepoints[position].1;
+        state.savepoints.truncate(position + 1);
+        state.writes.truncate(mark);
+        Ok(())
+    }
+
+    /// Forget savepoint `name` and every later one, keeping their writes
+    pub fn release_savepoint(&self, name: &str) -> Result<()> {
+        let mut state = self.active()?;
+        let position = state.savepoint(name)?;
+        state.savepoints.truncate(position);
+        Ok(())
+    }
+
+    /// Apply the write set to the heap
+    ///
+    /// Fails with a conflict, rolling the transaction back, if another
+    /// transaction committed a change to one of its rows since it read it.
+    pub fn commit(&self) -> Result<()> {
+        let writes = {
+            let mut state = self.active()?;
+            state.finished = true;
+            Self::collapse(std::mem::take(&mut state.writes))
+        };
+
+        let result = if writes.is_empty() {
+            Ok(())
+        } else {
+            self.store
+                .versions()
+                .commit(
+                    self.id,
+                    |versions| {
+                        for write in &writes {
+                            if let RowRef::Stored(rid) = write.target {
+                                if versions.changed_since(&write.table, rid, write.read_ts) {
+                                    return Err(DbError::Conflict(format!(
+                                        "Could not serialize access due to a concurrent \
+                                         update of table {}",
+                                        write.table
+                                    )));
+                                }
+                            }
+                        }
+                        self.check_unique(&writes)
+                    },
+                    || self.apply(&writes),
+                )
+                .map(|_| ())
+        };
+
+        let finished = self.finish(result.is_ok());
+        result.and(finished)
+    }
+
+    /// Discard the write set
+    pub fn rollback(&self) -> Result<()> {
+        {
+            let mut state = self.active()?;
+            state.finished = true;
+            state.writes.clear();
+        }
+        self.finish(false)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().finished
+    }
+
+    fn active(&self) -> Result<parking_lot::MutexGuard<'_, TxnState>> {
+        let state = self.state.lock();
+        if state.finished {
+            return Err(DbError::Transaction(format!(
+                "Transaction {} has already ended",
+                self.id
+            )));
+        }
+        Ok(state)
+    }
+
+    // The last write of each row, in the order rows were first written
+    fn collapse(writes: Vec<RowWrite>) -> Vec<RowWrite> {
+        let mut collapsed: Vec<RowWrite> = Vec::new();
+        let mut positions: HashMap<(String, RowRef), usize> = HashMap::new();
+        for write in writes {
+            match positions.get(&(write.table.clone(), write.target)) {
+                // Keep the first read timestamp: the row must not have
+                // changed since the transaction first read it
+                Some(&i) => collapsed[i].row = write.row,
+                None => {
+                    positions.insert((write.table.clone(), write.target), collapsed.len());
+                    collapsed.push(write);
+                }
+            }
+        }
+        collapsed
+    }
+
+    // Check the unique indexes of every table written before any heap change
+    fn check_unique(&self, writes: &[RowWrite]) -> Result<()> {
+        let mut tables: Vec<&str> = writes.iter().map(|w| w.table.as_str()).collect();
+        tables.sort_unstable();
+        tables.dedup();
+        for table in tables {
+            let rows: Vec<_> = writes
+                .iter()
+                .filter(|w| w.table == table)
+                .map(|w| {
+                    let replaced = match w.target {
+                        RowRef::Stored(rid) => Some(rid),
+                        RowRef::Pending(_) => None,
+                    };
+                    (replaced, w.row.as_deref())
+                })
+                .collect();
+            self.store.check_unique(table, &rows)?;
+        }
+        Ok(())
+    }
+
+    // Make the write set's heap changes; runs with other commits excluded
+    fn apply(&self, writes: &[RowWrite]) -> Result<Vec<RowChange>> {
+        let txn = self.store.begin();
+        let mut changes = Vec::new();
+        for write in writes {
+            let table = &write.table;
+            let mut change = |rid, before, after| {
+                changes.push(RowChange {
+                    table: table.clone(),
+                    rid,
+                    before,
+                    after,
+                })
+            };
+            match (write.target, &write.row) {
+                (RowRef::Stored(rid), Some(row)) => {
+                    let before = self.store.get_row(table, rid)?;
+                    let moved = txn.update_row(table, rid, row)?;
+                    if moved == rid {
+                        change(rid, before, Some(row.clone()));
+                    } else {
+                        change(rid, before, None);
+                        change(moved, None, Some(row.clone()));
+                    }
+                }
+                (RowRef::Stored(rid), None) => {
+                    let before = self.store.get_row(table, rid)?;
+                    if txn.delete_row(table, rid)? {
+                        change(rid, before, None);
+                    }
+                }
+                (RowRef::Pending(_), Some(row)) => {
+                    let rid = txn.insert_row(table, row)?;
+                    change(rid, None, Some(row.clone()));
+                }
+                // Inserted and deleted again
+                (RowRef::Pending(_), None) => {}
+            }
+        }
+        self.store.apply_index_changes(&changes)?;
+        if let Err(e) = txn.commit() {
+            if let Err(e) = self.store.revert_index_changes(&changes) {
+                tracing::error!(
+                    "Failed to undo the index changes of transaction {}: {}",
+                    self.id,
+                    e
+                );
+            }
+            return Err(e);
+        }
+        Ok(changes)
+    }
+
+    fn finish(&self, committed: bool) -> Result<()> {
+        self.store.versions().end_snapshot(self.id);
+        let ended = if committed {
+            self.txn_manager.commit(self.id)
+        } else {
+            self.txn_manager.abort(self.id)
+        };
+        ended.map_err(|e| DbError::Transaction(e.to_string()))
+    }
+}
+
+impl TxnState {
+    fn write(&mut self, table: &str, target: RowRef, row: Option<Vec<Value>>) {
+        self.writes.push(RowWrite {
+            table: table.to_string(),
+            target,
+            read_ts: self.read_ts,
+            row,
+        });
+    }
+
+    // Position of the newest savepoint called `name`
+    fn savepoint(&self, name: &str) -> Result<usize> {
+        self.savepoints
+            .iter()
+            .rposition(|(savepoint, _)| savepoint.eq_ignore_ascii_case(name))
+            .ok_or_else(|| DbError::NotFound(format!("Savepoint {} does not exist", name)))
+    }
+}
+
+// An abandoned transaction (a session that disconnected mid-transaction)
+// rolls back
+impl Drop for SqlTransaction {
+    fn drop(&mut self) {
+        if !self.state.get_mut().finished {
+            if let Err(e) = self.rollback() {
+                tracing::error!("Failed to roll back transaction {}: {}", self.id, e);
+            }
+        }
+    }
+}
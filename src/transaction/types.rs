@@ -69,6 +69,19 @@ impl fmt::Display for IsolationLevel {
     }
 }
 
+impl From<crate::common::IsolationLevel> for IsolationLevel {
+    fn from(level: crate::common::IsolationLevel) -> Self {
+        use crate::common::IsolationLevel as Configured;
+        match level {
+            Configured::ReadUncommitted => IsolationLevel::ReadUncommitted,
+            Configured::ReadCommitted => IsolationLevel::ReadCommitted,
+            Configured::RepeatableRead => IsolationLevel::RepeatableRead,
+            Configured::Serializable => IsolationLevel::Serializable,
+            Configured::SnapshotIsolation => IsolationLevel::SnapshotIsolation,
+        }
+    }
+}
+
 /// Transaction lifecycle state.
 ///
 /// Represents the current phase of a transaction in its lifecycle.
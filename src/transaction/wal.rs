@@ -1,17 +1,18 @@
 // Write-Ahead Logging (WAL) Implementation
-// Provides ARIES-style physiological logging with group commit,
-// log shipping for replication, and checkpoint coordination
+// Provides ARIES-style physiological logging with group commit into
+// binary, segmented log files, log shipping for replication, and
+// checkpoint coordination
 
 use super::TransactionId;
 use crate::error::{DbError, Result};
 use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, IoSlice, Write as IoWrite};
-use std::path::PathBuf;
+use std::io::{BufReader, BufWriter, IoSlice, Read, Write as IoWrite};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::oneshot;
 use tokio::time::interval;
@@ -169,6 +170,8 @@ pub enum LogRecord {
         dirty_pages: Vec<PageId>,
         timestamp: SystemTime,
     },
+    /// Catalog (DDL) change, redone logically at startup
+    CatalogUpdate { version: u64, change: Vec<u8> },
     /// End of log marker
     EndOfLog,
 }
@@ -221,9 +224,9 @@ pub struct WALEntry {
 
 impl WALEntry {
     pub fn new(lsn: LSN, prev_lsn: Option<LSN>, record: LogRecord) -> Self {
-        let serialized = serde_json::to_vec(&record).unwrap_or_default();
-        let size = serialized.len() as u32;
-        let checksum = Self::calculate_checksum(&serialized);
+        let payload = encode_record(&record).unwrap_or_default();
+        let size = payload.len() as u32;
+        let checksum = frame_checksum(&encode_frame(lsn, prev_lsn, &payload));
 
         Self {
             lsn,
@@ -234,9 +237,13 @@ impl WALEntry {
         }
     }
 
-    fn calculate_checksum(data: &[u8]) -> u32 {
-        // Hardware-accelerated CRC32C checksum
-        hardware_crc32c(data)
+    /// Encode as a log frame
+    fn encode(&self) -> Result<Vec<u8>> {
+        Ok(encode_frame(
+            self.lsn,
+            self.prev_lsn,
+            &encode_record(&self.record)?,
+        ))
     }
 
     #[allow(dead_code)]
@@ -245,12 +252,286 @@ impl WALEntry {
         entries.iter().map(|data| hardware_crc32c(data)).collect()
     }
 
-    fn verify_checksum(&self) -> bool {
-        let serialized = serde_json::to_vec(&self.record).unwrap_or_default();
-        self.checksum == Self::calculate_checksum(&serialized)
+    /// Whether the checksum matches the entry as it is now
+    pub fn verify_checksum(&self) -> bool {
+        match encode_record(&self.record) {
+            Ok(payload) => {
+                self.checksum == frame_checksum(&encode_frame(self.lsn, self.prev_lsn, &payload))
+            }
+            Err(_) => false,
+        }
     }
 }
 
+// ============================================================================
+// Binary log format
+// ============================================================================
+//
+// The log is a directory of segment files named after the LSN of their
+// first record (`{first_lsn:016X}.wal`). Records are written in LSN order,
+// so the segment holding an LSN is the last one whose first LSN is not
+// greater than it.
+//
+// Segment: magic (8 bytes) | first LSN (u64)
+// Frame:   length (u32) | CRC32C (u32) | LSN (u64) | previous LSN (u64, 0 for
+//          none) | record (bincode)
+//
+// All integers are little endian. The CRC32C covers everything in the frame
+// after the checksum itself; the length is that of the record.
+
+/// Magic number at the start of every segment file
+const SEGMENT_MAGIC: [u8; 8] = *b"RDBWAL01";
+
+/// Segment header size in bytes
+const SEGMENT_HEADER_SIZE: u64 = 16;
+
+/// Frame header size in bytes
+const FRAME_HEADER_SIZE: usize = 24;
+
+/// Largest record accepted when reading; a larger length is a torn frame
+const MAX_RECORD_SIZE: usize = 256 * 1024 * 1024;
+
+fn encode_record(record: &LogRecord) -> Result<Vec<u8>> {
+    bincode::serde::encode_to_vec(record, bincode::config::standard())
+        .map_err(|e| DbError::Serialization(format!("Failed to encode log record: {}", e)))
+}
+
+fn encode_frame(lsn: LSN, prev_lsn: Option<LSN>, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&[0; 4]);
+    frame.extend_from_slice(&lsn.to_le_bytes());
+    frame.extend_from_slice(&prev_lsn.unwrap_or(0).to_le_bytes());
+    frame.extend_from_slice(payload);
+    let checksum = frame_checksum(&frame);
+    frame[4..8].copy_from_slice(&checksum.to_le_bytes());
+    frame
+}
+
+/// Checksum of an encoded frame, skipping the length and checksum fields
+fn frame_checksum(frame: &[u8]) -> u32 {
+    hardware_crc32c(&frame[8..])
+}
+
+/// How a scan of a segment ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanEnd {
+    /// End of file on a frame boundary
+    Clean,
+    /// End of file inside a frame: a write in progress or cut short by a crash
+    Torn,
+    /// A complete frame whose checksum or record does not decode
+    Corrupt,
+}
+
+fn segment_path(dir: &Path, first_lsn: LSN) -> PathBuf {
+    dir.join(format!("{:016X}.wal", first_lsn))
+}
+
+/// Segment files in `dir` by first LSN
+fn list_segments(dir: &Path) -> Result<BTreeMap<LSN, PathBuf>> {
+    let mut segments = BTreeMap::new();
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| DbError::Storage(format!("Failed to list WAL directory: {}", e)))?;
+    for entry in entries {
+        let path = entry?.path();
+        let first_lsn = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".wal"))
+            .filter(|stem| stem.len() == 16)
+            .and_then(|stem| u64::from_str_radix(stem, 16).ok());
+        if let Some(first_lsn) = first_lsn {
+            segments.insert(first_lsn, path);
+        }
+    }
+    Ok(segments)
+}
+
+/// Read into `buf` until it is full or the file ends, returning the bytes read
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(DbError::Storage(format!("Failed to read WAL: {}", e))),
+        }
+    }
+    Ok(filled)
+}
+
+/// Decode the records of a segment in order, passing each to `visit`.
+/// Returns how the scan ended and the length of the valid prefix.
+fn scan_segment(
+    path: &Path,
+    first_lsn: LSN,
+    mut visit: impl FnMut(WALEntry),
+) -> Result<(ScanEnd, u64)> {
+    let file = File::open(path)
+        .map_err(|e| DbError::Storage(format!("Failed to open WAL segment: {}", e)))?;
+    let mut reader = BufReader::new(file);
+
+    let mut header = [0u8; SEGMENT_HEADER_SIZE as usize];
+    if read_full(&mut reader, &mut header)? < header.len() {
+        return Ok((ScanEnd::Torn, 0));
+    }
+    if header[..8] != SEGMENT_MAGIC || header[8..] != first_lsn.to_le_bytes() {
+        return Err(DbError::Corruption(format!(
+            "Invalid WAL segment header in {}",
+            path.display()
+        )));
+    }
+
+    let mut valid_len = SEGMENT_HEADER_SIZE;
+    let mut frame = vec![0u8; FRAME_HEADER_SIZE];
+    loop {
+        frame.resize(FRAME_HEADER_SIZE, 0);
+        match read_full(&mut reader, &mut frame)? {
+            0 => return Ok((ScanEnd::Clean, valid_len)),
+            n if n < FRAME_HEADER_SIZE => return Ok((ScanEnd::Torn, valid_len)),
+            _ => {}
+        }
+
+        let len = u32::from_le_bytes(frame[0..4].try_into().unwrap()) as usize;
+        if len > MAX_RECORD_SIZE {
+            return Ok((ScanEnd::Torn, valid_len));
+        }
+        frame.resize(FRAME_HEADER_SIZE + len, 0);
+        if read_full(&mut reader, &mut frame[FRAME_HEADER_SIZE..])? < len {
+            return Ok((ScanEnd::Torn, valid_len));
+        }
+
+        let checksum = u32::from_le_bytes(frame[4..8].try_into().unwrap());
+        if checksum != frame_checksum(&frame) {
+            return Ok((ScanEnd::Corrupt, valid_len));
+        }
+        let Ok((record, _)) = bincode::serde::decode_from_slice::<LogRecord, _>(
+            &frame[FRAME_HEADER_SIZE..],
+            bincode::config::standard(),
+        ) else {
+            return Ok((ScanEnd::Corrupt, valid_len));
+        };
+
+        let lsn = u64::from_le_bytes(frame[8..16].try_into().unwrap());
+        let prev_lsn = u64::from_le_bytes(frame[16..24].try_into().unwrap());
+        visit(WALEntry {
+            lsn,
+            prev_lsn: (prev_lsn != 0).then_some(prev_lsn),
+            record,
+            size: len as u32,
+            checksum,
+        });
+        valid_len += frame.len() as u64;
+    }
+}
+
+/// Make a directory entry change (a new or removed segment) durable
+fn sync_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    File::open(dir)
+        .and_then(|d| d.sync_all())
+        .map_err(|e| DbError::Storage(format!("Failed to sync WAL directory: {}", e)))?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Write all of `frames` with as few `writev` calls as possible
+fn write_all_vectored(file: &mut File, frames: &[Vec<u8>]) -> std::io::Result<()> {
+    let mut slices: Vec<IoSlice> = frames.iter().map(|frame| IoSlice::new(frame)).collect();
+    let mut slices = &mut slices[..];
+    while !slices.is_empty() {
+        let written = file.write_vectored(slices)?;
+        if written == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        IoSlice::advance_slices(&mut slices, written);
+    }
+    Ok(())
+}
+
+/// The segment currently being appended to
+struct SegmentWriter {
+    first_lsn: LSN,
+    path: PathBuf,
+    file: BufWriter<File>,
+    /// Segment length, including bytes still buffered
+    len: u64,
+    /// LSN of the last record written
+    last_lsn: LSN,
+}
+
+impl SegmentWriter {
+    /// Create an empty segment whose first record will be `first_lsn`
+    fn create(dir: &Path, first_lsn: LSN) -> Result<Self> {
+        let path = segment_path(dir, first_lsn);
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| DbError::Storage(format!("Failed to create WAL segment: {}", e)))?;
+        file.write_all(&SEGMENT_MAGIC)?;
+        file.write_all(&first_lsn.to_le_bytes())?;
+        file.sync_all()?;
+        sync_dir(dir)?;
+
+        Ok(Self {
+            first_lsn,
+            path,
+            file: BufWriter::new(file),
+            len: SEGMENT_HEADER_SIZE,
+            last_lsn: first_lsn.saturating_sub(1),
+        })
+    }
+
+    /// Reopen the newest segment after a restart, cutting off a torn or
+    /// corrupt tail left by a crash
+    fn reopen(dir: &Path, path: PathBuf, first_lsn: LSN) -> Result<Self> {
+        let mut last_lsn = first_lsn.saturating_sub(1);
+        let (end, valid_len) = scan_segment(&path, first_lsn, |entry| last_lsn = entry.lsn)?;
+        if valid_len < SEGMENT_HEADER_SIZE {
+            // Crashed while creating the segment
+            std::fs::remove_file(&path)?;
+            return Self::create(dir, first_lsn);
+        }
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(|e| DbError::Storage(format!("Failed to open WAL segment: {}", e)))?;
+        if end != ScanEnd::Clean {
+            file.set_len(valid_len)?;
+            file.sync_all()?;
+        }
+
+        Ok(Self {
+            first_lsn,
+            path,
+            file: BufWriter::new(file),
+            len: valid_len,
+            last_lsn,
+        })
+    }
+
+    /// Flush buffered records and force the segment to disk
+    fn sync(&mut self) -> Result<()> {
+        self.file
+            .flush()
+            .map_err(|e| DbError::Storage(format!("Failed to flush WAL: {}", e)))?;
+        self.file
+            .get_ref()
+            .sync_data()
+            .map_err(|e| DbError::Storage(format!("Failed to sync WAL: {}", e)))
+    }
+}
+
+/// Receives log segments before truncation removes them
+pub trait SegmentArchiver: Send + Sync {
+    fn archive_segment(&self, segment_path: &Path) -> Result<()>;
+}
+
 /// Group commit buffer for batching log writes
 ///
 /// # CRITICAL FIX EA2-V4: Entry Count Limit
@@ -305,6 +586,10 @@ impl GroupCommitBuffer {
         self.entries.is_empty()
     }
 
+    fn is_full(&self) -> bool {
+        self.entries.len() >= MAX_GROUP_COMMIT_ENTRIES
+    }
+
     /// Check if the buffer should be flushed.
     ///
     /// # CRITICAL FIX EA2-V4: Entry Count Check
@@ -350,10 +635,14 @@ impl GroupCommitBuffer {
 
 /// Write-Ahead Log Manager with Group Commit
 pub struct WALManager {
-    /// Path to WAL file
-    wal_path: PathBuf,
-    /// Current WAL file
-    wal_file: Arc<Mutex<BufWriter<File>>>,
+    /// Directory holding the log segments
+    wal_dir: PathBuf,
+    /// Segment being appended to
+    writer: Arc<Mutex<SegmentWriter>>,
+    /// Segment files by the LSN of their first record
+    segments: Arc<RwLock<BTreeMap<LSN, PathBuf>>>,
+    /// Receives segments before truncation removes them
+    archiver: RwLock<Option<Weak<dyn SegmentArchiver>>>,
     /// Next LSN to allocate
     next_lsn: Arc<AtomicU64>,
     /// Last flushed LSN
@@ -380,7 +669,7 @@ pub struct WALConfig {
     pub max_commit_delay_ms: u64,
     /// Enable group commit optimization
     pub enable_group_commit: bool,
-    /// WAL segment size (bytes)
+    /// Size (bytes) at which the log moves on to a new segment
     pub segment_size: usize,
     /// Enable log shipping for replication
     pub enable_log_shipping: bool,
@@ -423,6 +712,9 @@ pub struct WALStats {
     pub vectored_writes: u64,
     pub hardware_crc_ops: u64,
     pub batched_checksums: u64,
+    pub segments_created: u64,
+    pub segments_archived: u64,
+    pub segments_removed: u64,
 }
 
 /// Transaction table entry for recovery
@@ -431,6 +723,7 @@ pub struct TransactionTableEntry {
     #[allow(dead_code)]
     txn_id: TransactionId,
     pub(crate) state: TransactionState,
+    pub(crate) first_lsn: LSN,
     pub(crate) last_lsn: LSN,
     #[allow(dead_code)]
     undo_next_lsn: Option<LSN>,
@@ -445,19 +738,36 @@ pub enum TransactionState {
 }
 
 impl WALManager {
-    /// Create a new WAL manager
-    pub fn new(wal_path: PathBuf, config: WALConfig) -> Result<Self> {
-        let wal_file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&wal_path)
-            .map_err(|e| DbError::Storage(format!("Failed to open WAL: {}", e)))?;
+    /// Open the log in `wal_dir`, creating the directory if needed
+    pub fn new(wal_dir: PathBuf, config: WALConfig) -> Result<Self> {
+        if wal_dir.is_file() {
+            return Err(DbError::Storage(format!(
+                "WAL path {} is a file, expected a segment directory",
+                wal_dir.display()
+            )));
+        }
+        std::fs::create_dir_all(&wal_dir)
+            .map_err(|e| DbError::Storage(format!("Failed to create WAL directory: {}", e)))?;
+
+        // Continue numbering after the last record already in the log
+        let mut segments = list_segments(&wal_dir)?;
+        let writer = match segments.iter().next_back() {
+            Some((&first_lsn, path)) => SegmentWriter::reopen(&wal_dir, path.clone(), first_lsn)?,
+            None => {
+                let writer = SegmentWriter::create(&wal_dir, 1)?;
+                segments.insert(1, writer.path.clone());
+                writer
+            }
+        };
+        let last_lsn = writer.last_lsn;
 
         let manager = Self {
-            wal_path,
-            wal_file: Arc::new(Mutex::new(BufWriter::new(wal_file))),
-            next_lsn: Arc::new(AtomicU64::new(1)),
-            flushed_lsn: Arc::new(AtomicU64::new(0)),
+            wal_dir,
+            writer: Arc::new(Mutex::new(writer)),
+            segments: Arc::new(RwLock::new(segments)),
+            archiver: RwLock::new(None),
+            next_lsn: Arc::new(AtomicU64::new(last_lsn + 1)),
+            flushed_lsn: Arc::new(AtomicU64::new(last_lsn)),
             commit_buffer: Arc::new(Mutex::new(GroupCommitBuffer::new())),
             transaction_table: Arc::new(RwLock::new(HashMap::new())),
             dirty_page_table: Arc::new(RwLock::new(HashMap::new())),
@@ -469,15 +779,22 @@ impl WALManager {
         Ok(manager)
     }
 
+    /// Hand segments to `archiver` before truncation removes them
+    ///
+    /// While an archiver is set, a segment it can no longer receive (because
+    /// it was dropped) is kept rather than removed.
+    pub fn set_archiver(&self, archiver: Weak<dyn SegmentArchiver>) {
+        *self.archiver.write() = Some(archiver);
+    }
+
     /// Allocate a new LSN
     fn allocate_lsn(&self) -> LSN {
         self.next_lsn.fetch_add(1, Ordering::SeqCst)
     }
 
-    /// Append a log record
-    pub async fn append(&self, record: LogRecord) -> Result<LSN> {
-        let lsn = self.allocate_lsn();
-
+    /// Build the entry for a newly allocated LSN, recording it in the
+    /// transaction and dirty page tables
+    fn prepare_entry(&self, lsn: LSN, record: LogRecord) -> WALEntry {
         // Get previous LSN for this transaction
         let prev_lsn = record.txn_id().and_then(|txn_id| {
             self.transaction_table
@@ -486,8 +803,6 @@ impl WALManager {
                 .map(|entry| entry.last_lsn)
         });
 
-        let entry = WALEntry::new(lsn, prev_lsn, record.clone());
-
         // Update transaction table
         if let Some(txn_id) = record.txn_id() {
             let state = match &record {
@@ -509,15 +824,17 @@ impl WALManager {
             if matches!(state, TransactionState::Committed | TransactionState::Aborted) {
                 self.transaction_table.write().remove(&txn_id);
             } else {
-                self.transaction_table.write().insert(
+                let mut table = self.transaction_table.write();
+                let entry = table.entry(txn_id).or_insert(TransactionTableEntry {
                     txn_id,
-                    TransactionTableEntry {
-                        txn_id,
-                        state,
-                        last_lsn: lsn,
-                        undo_next_lsn,
-                    },
-                );
+                    state,
+                    first_lsn: lsn,
+                    last_lsn: lsn,
+                    undo_next_lsn,
+                });
+                entry.state = state;
+                entry.last_lsn = lsn;
+                entry.undo_next_lsn = undo_next_lsn;
             }
         }
 
@@ -526,44 +843,101 @@ impl WALManager {
             self.dirty_page_table.write().entry(page_id).or_insert(lsn);
         }
 
-        // Group commit handling
-        if self.config.enable_group_commit {
-            let (tx, rx) = oneshot::channel();
-
-            // CRITICAL FIX EA2-V4: Handle buffer overflow
-            // If add() returns error due to buffer being full, force flush first
-            loop {
-                match self.commit_buffer.lock().add(entry.clone(), tx) {
-                    Ok(()) => break,
-                    Err(DbError::ResourceExhausted(_)) => {
-                        // Buffer full, force flush
-                        self.flush_buffer().await?;
-                        // Retry with new channel
-                        let (new_tx, new_rx) = oneshot::channel();
-                        // Try again - should succeed after flush
-                        self.commit_buffer.lock().add(entry.clone(), new_tx)?;
-                        return new_rx
-                            .await
-                            .map_err(|_| {
-                                DbError::Transaction("Commit waiter dropped".to_string())
-                            })?;
-                    }
-                    Err(e) => return Err(e),
+        WALEntry::new(lsn, prev_lsn, record)
+    }
+
+    /// Append a log record
+    pub async fn append(&self, record: LogRecord) -> Result<LSN> {
+        if !self.config.enable_group_commit {
+            // Direct write
+            let sync = self.needs_sync(&record);
+            return self.write_direct(sync, |lsn| self.prepare_entry(lsn, record));
+        }
+
+        // LSNs are allocated under the buffer lock, so the buffer (and with
+        // it the log) is in LSN order
+        let rx = loop {
+            let waiter = {
+                let mut buffer = self.commit_buffer.lock();
+                if buffer.is_full() {
+                    None
+                } else {
+                    let entry = self.prepare_entry(self.allocate_lsn(), record.clone());
+                    let (tx, rx) = oneshot::channel();
+                    buffer.add(entry, tx)?;
+                    Some(rx)
                 }
+            };
+            match waiter {
+                Some(rx) => break rx,
+                // CRITICAL FIX EA2-V4: Buffer full, force flush before retrying
+                None => self.flush_buffer().await?,
             }
+        };
 
-            // Check if we should flush
-            self.maybe_flush_buffer().await?;
+        // Check if we should flush
+        self.maybe_flush_buffer().await?;
 
-            // Wait for flush
-            rx.await
-                .map_err(|_| DbError::Transaction("Commit waiter dropped".to_string()))?
-        } else {
-            // Direct write
-            self.write_entry(&entry)?;
-            self.sync_if_needed(&record)?;
-            Ok(lsn)
+        // Wait for flush
+        rx.await
+            .map_err(|_| DbError::Transaction("Commit waiter dropped".to_string()))?
+    }
+
+    /// Append a log record from synchronous code, bypassing group commit
+    ///
+    /// Commits and aborts are forced to disk as the sync mode requires;
+    /// other records reach disk with the next sync or `flush_to`.
+    pub fn append_blocking(&self, record: LogRecord) -> Result<LSN> {
+        let sync = self.needs_sync(&record);
+        self.write_direct(sync, |lsn| self.prepare_entry(lsn, record))
+    }
+
+    /// Make every record up to and including `lsn` durable
+    pub fn flush_to(&self, lsn: LSN) -> Result<()> {
+        if lsn <= self.flushed_lsn() {
+            return Ok(());
+        }
+        // The record may still be waiting in the group commit buffer
+        self.flush_pending()?;
+        let mut writer = self.writer.lock();
+        if lsn > self.flushed_lsn() {
+            self.sync_writer(&mut writer)?;
         }
+        Ok(())
+    }
+
+    /// Append a log record and force it to disk, bypassing group commit
+    ///
+    /// For callers outside an async context (such as DDL) that must not
+    /// return before the record is durable.
+    pub fn append_durable(&self, record: LogRecord) -> Result<LSN> {
+        self.write_direct(true, |lsn| WALEntry::new(lsn, None, record))
+    }
+
+    /// Write one entry straight to the log. Entries still in the group
+    /// commit buffer have lower LSNs, so they are written (and made
+    /// durable) first.
+    fn write_direct(&self, sync: bool, make_entry: impl FnOnce(LSN) -> WALEntry) -> Result<LSN> {
+        let mut writer = self.writer.lock();
+        let (entries, waiters) = {
+            let mut buffer = self.commit_buffer.lock();
+            let (mut entries, waiters) = buffer.take();
+            entries.push(make_entry(self.allocate_lsn()));
+            (entries, waiters)
+        };
+        let lsn = entries[entries.len() - 1].lsn;
+
+        let result = self.write_entries(&mut writer, &entries).and_then(|()| {
+            if sync || !waiters.is_empty() {
+                self.sync_writer(&mut writer)
+            } else {
+                Ok(())
+            }
+        });
+        drop(writer);
+
+        notify_waiters(&entries, waiters, &result);
+        result.map(|()| lsn)
     }
 
     /// Maybe flush the group commit buffer
@@ -585,6 +959,13 @@ impl WALManager {
 
     /// Flush the group commit buffer with vectored I/O
     async fn flush_buffer(&self) -> Result<()> {
+        self.flush_pending()
+    }
+
+    fn flush_pending(&self) -> Result<()> {
+        // The writer lock is taken first so that nothing with a higher LSN
+        // reaches the log ahead of these entries
+        let mut writer = self.writer.lock();
         let (entries, waiters) = {
             let mut buffer = self.commit_buffer.lock();
             if buffer.is_empty() {
@@ -593,137 +974,120 @@ impl WALManager {
             buffer.take()
         };
 
-        if entries.is_empty() {
-            return Ok(());
-        }
-
         let start = Instant::now();
-        let last_lsn = entries.last().map(|e| e.lsn).unwrap_or(0);
-
-        // Use vectored write for better performance (single syscall)
-        self.write_entries_vectored(&entries)?;
-
-        // Sync to disk
-        self.sync()?;
-
+        let result = self
+            .write_entries(&mut writer, &entries)
+            .and_then(|()| self.sync_writer(&mut writer));
+        drop(writer);
         let flush_time = start.elapsed().as_millis() as f64;
 
         // Update statistics
-        let mut stats = self.stats.write();
-        stats.group_commits += 1;
-        stats.avg_group_size = update_running_average(
-            stats.avg_group_size,
-            entries.len() as f64,
-            (stats.group_commits - 1) as f64,
-        );
-        stats.avg_flush_time_ms = update_running_average(
-            stats.avg_flush_time_ms,
-            flush_time,
-            (stats.group_commits - 1) as f64,
-        );
-
-        // Notify waiters
-        for waiter in waiters {
-            let _ = waiter.send(Ok(last_lsn));
+        {
+            let mut stats = self.stats.write();
+            stats.group_commits += 1;
+            stats.avg_group_size = update_running_average(
+                stats.avg_group_size,
+                entries.len() as f64,
+                (stats.group_commits - 1) as f64,
+            );
+            stats.avg_flush_time_ms = update_running_average(
+                stats.avg_flush_time_ms,
+                flush_time,
+                (stats.group_commits - 1) as f64,
+            );
         }
 
-        Ok(())
+        notify_waiters(&entries, waiters, &result);
+        result
     }
 
-    /// Write a single entry to WAL
-    fn write_entry(&self, entry: &WALEntry) -> Result<()> {
-        let serialized = serde_json::to_vec(entry).map_err(|e| {
-            DbError::Serialization(format!("Failed to serialize WAL entry: {}", e))
-        })?;
-
-        let mut file = self.wal_file.lock();
-        file.write_all(&serialized)
-            .map_err(|e| DbError::Storage(format!("Failed to write WAL entry: {}", e)))?;
-
-        // Update statistics
-        let mut stats = self.stats.write();
-        stats.total_records += 1;
-        stats.total_bytes += serialized.len() as u64;
-        stats.hardware_crc_ops += 1; // Checksum computed with hardware acceleration
+    /// Append entries, which must be in LSN order, moving on to a new
+    /// segment whenever the current one is full. Consecutive frames for the
+    /// same segment go out in a single vectored write.
+    fn write_entries(&self, writer: &mut SegmentWriter, entries: &[WALEntry]) -> Result<()> {
+        let frames = entries
+            .iter()
+            .map(WALEntry::encode)
+            .collect::<Result<Vec<_>>>()?;
+        let segment_size = self.config.segment_size as u64;
+
+        let mut start = 0;
+        while start < frames.len() {
+            // A record larger than a segment gets a segment of its own
+            if writer.len > SEGMENT_HEADER_SIZE
+                && writer.len + frames[start].len() as u64 > segment_size
+            {
+                self.switch_segment(writer, entries[start].lsn)?;
+            }
 
-        Ok(())
-    }
+            let mut end = start + 1;
+            let mut len = writer.len + frames[start].len() as u64;
+            while end < frames.len() && len + frames[end].len() as u64 <= segment_size {
+                len += frames[end].len() as u64;
+                end += 1;
+            }
 
-    /// Write multiple entries using vectored I/O for better performance
-    fn write_entries_vectored(&self, entries: &[WALEntry]) -> Result<()> {
-        if entries.is_empty() {
-            return Ok(());
-        }
+            let batch = &frames[start..end];
+            let written = if batch.len() == 1 {
+                writer.file.write_all(&batch[0])
+            } else {
+                self.stats.write().vectored_writes += 1;
+                writer
+                    .file
+                    .flush()
+                    .and_then(|()| write_all_vectored(writer.file.get_mut(), batch))
+            };
+            written.map_err(|e| DbError::Storage(format!("Failed to write WAL entry: {}", e)))?;
 
-        // Serialize all entries
-        let serialized: Vec<Vec<u8>> = entries
-            .iter()
-            .map(|e| {
-                serde_json::to_vec(e).map_err(|e| {
-                    DbError::Serialization(format!("Serialization failed: {}", e))
-                })
-            })
-            .collect::<Result<Vec<_>>>()?;
-        // Prepare IoSlice for writev
-        let slices: Vec<IoSlice> = serialized.iter().map(|buf| IoSlice::new(buf)).collect();
-
-        let mut file = self.wal_file.lock();
-
-        // Write all slices in a single syscall (vectored I/O)
-        let mut total_written = 0;
-        let total_size: usize = serialized.iter().map(|s| s.len()).sum();
-
-        // Note: write_vectored might not write everything in one call
-        while total_written < total_size {
-            let written = file
-                .get_mut()
-                .write_vectored(&slices[total_written..])
-                .map_err(|e| DbError::Storage(format!("Vectored write failed: {}", e)))?;
-            total_written += written;
+            writer.len = len;
+            writer.last_lsn = entries[end - 1].lsn;
+            start = end;
         }
 
         // Update statistics
         let mut stats = self.stats.write();
         stats.total_records += entries.len() as u64;
-        stats.total_bytes += total_size as u64;
-        stats.vectored_writes += 1;
-        stats.hardware_crc_ops += entries.len() as u64;
+        stats.total_bytes += frames.iter().map(|f| f.len() as u64).sum::<u64>();
+        stats.hardware_crc_ops += entries.len() as u64; // Checksum computed with hardware acceleration
 
         Ok(())
     }
 
+    /// Seal the current segment and start a new one at `first_lsn`
+    fn switch_segment(&self, writer: &mut SegmentWriter, first_lsn: LSN) -> Result<()> {
+        // A sealed segment is complete on disk before the log moves past it
+        writer.sync()?;
+        *writer = SegmentWriter::create(&self.wal_dir, first_lsn)?;
+        self.segments
+            .write()
+            .insert(first_lsn, writer.path.clone());
+        self.stats.write().segments_created += 1;
+        Ok(())
+    }
+
     /// Sync WAL to disk
     fn sync(&self) -> Result<()> {
-        let mut file = self.wal_file.lock();
-        file.flush()
-            .map_err(|e| DbError::Storage(format!("Failed to flush WAL: {}", e)))?;
-
-        file.get_mut()
-            .sync_all()
-            .map_err(|e| DbError::Storage(format!("Failed to sync WAL: {}", e)))?;
+        let mut writer = self.writer.lock();
+        self.sync_writer(&mut writer)
+    }
 
+    fn sync_writer(&self, writer: &mut SegmentWriter) -> Result<()> {
+        writer.sync()?;
         self.stats.write().fsyncs += 1;
-
-        let next_lsn = self.next_lsn.load(Ordering::SeqCst);
-        self.flushed_lsn.store(next_lsn, Ordering::SeqCst);
-
+        self.flushed_lsn.store(writer.last_lsn, Ordering::SeqCst);
         Ok(())
     }
 
-    /// Sync if needed based on config and record type
-    fn sync_if_needed(&self, record: &LogRecord) -> Result<()> {
+    /// Whether a directly written record must be synced before returning
+    fn needs_sync(&self, record: &LogRecord) -> bool {
         match self.config.sync_mode {
             SyncMode::AlwaysSync => {
-                if matches!(record, LogRecord::Commit { .. } | LogRecord::Abort { .. }) {
-                    self.sync()?;
-                }
-            }
-            SyncMode::NoSync => {}
-            SyncMode::PeriodicSync => {
-                // Periodic sync handled by background task
+                matches!(record, LogRecord::Commit { .. } | LogRecord::Abort { .. })
             }
+            SyncMode::NoSync => false,
+            // Periodic sync handled by background task
+            SyncMode::PeriodicSync => false,
         }
-        Ok(())
     }
 
     /// Start background group commit flusher
@@ -740,10 +1104,44 @@ impl WALManager {
     }
 
     /// Truncate WAL up to a given LSN (after checkpoint)
-    pub fn truncate(&self, _up_to_lsn: LSN) -> Result<()> {
-        // In production, this would archive old log segments
-        // and create a new active segment
-        Ok(())
+    ///
+    /// Removes every segment whose records all precede `up_to_lsn`, handing
+    /// each to the archiver first if one is set. The segment being written
+    /// is never removed.
+    pub fn truncate(&self, up_to_lsn: LSN) -> Result<()> {
+        let removable: Vec<(LSN, PathBuf)> = {
+            let segments = self.segments.read();
+            // A segment ends where the next one starts
+            segments
+                .iter()
+                .zip(segments.keys().skip(1))
+                .take_while(|(_, next_first)| **next_first <= up_to_lsn)
+                .map(|((&first_lsn, path), _)| (first_lsn, path.clone()))
+                .collect()
+        };
+        if removable.is_empty() {
+            return Ok(());
+        }
+
+        let archiver = self.archiver.read().clone();
+        for (first_lsn, path) in removable {
+            if let Some(archiver) = &archiver {
+                let archiver = archiver.upgrade().ok_or_else(|| {
+                    DbError::InvalidState(format!(
+                        "WAL archiver is gone; keeping segment {}",
+                        path.display()
+                    ))
+                })?;
+                archiver.archive_segment(&path)?;
+                self.stats.write().segments_archived += 1;
+            }
+
+            std::fs::remove_file(&path)
+                .map_err(|e| DbError::Storage(format!("Failed to remove WAL segment: {}", e)))?;
+            self.segments.write().remove(&first_lsn);
+            self.stats.write().segments_removed += 1;
+        }
+        sync_dir(&self.wal_dir)
     }
 
     /// Get current LSN
@@ -756,34 +1154,72 @@ impl WALManager {
         self.flushed_lsn.load(Ordering::SeqCst)
     }
 
+    /// Path of the segment holding `lsn`, unless it was truncated or the
+    /// LSN has not been allocated yet
+    pub fn segment_for_lsn(&self, lsn: LSN) -> Option<PathBuf> {
+        if lsn >= self.current_lsn() {
+            return None;
+        }
+        self.segments
+            .read()
+            .range(..=lsn)
+            .next_back()
+            .map(|(_, path)| path.clone())
+    }
+
     /// Read log records starting from LSN
+    ///
+    /// Segments that end before `start_lsn` are skipped without being read.
     pub fn read_from(&self, start_lsn: LSN) -> Result<Vec<WALEntry>> {
-        let file = File::open(&self.wal_path)
-            .map_err(|e| DbError::Storage(format!("Failed to open WAL for reading: {}", e)))?;
-
-        let mut reader = BufReader::new(file);
-        let mut entries = Vec::new();
+        // Records still in the write buffer must be visible to the reader
+        self.writer
+            .lock()
+            .file
+            .flush()
+            .map_err(|e| DbError::Storage(format!("Failed to flush WAL: {}", e)))?;
 
-        loop {
-            let entry: WALEntry = match serde_json::from_reader(&mut reader) {
-                Ok(entry) => entry,
-                Err(_) => break, // End of file or corrupted entry
-            };
+        let segments: Vec<(LSN, PathBuf)> = {
+            let segments = self.segments.read();
+            let first = segments
+                .range(..=start_lsn)
+                .next_back()
+                .map_or(0, |(&first_lsn, _)| first_lsn);
+            segments
+                .range(first..)
+                .map(|(&first_lsn, path)| (first_lsn, path.clone()))
+                .collect()
+        };
 
-            if entry.lsn >= start_lsn {
-                if !entry.verify_checksum() {
-                    return Err(DbError::Corruption(format!(
-                        "Checksum mismatch at LSN {}",
-                        entry.lsn
-                    )));
+        let mut entries = Vec::new();
+        for (i, (first_lsn, path)) in segments.iter().enumerate() {
+            let mut last_lsn = first_lsn.saturating_sub(1);
+            let (end, _) = scan_segment(path, *first_lsn, |entry| {
+                last_lsn = entry.lsn;
+                if entry.lsn >= start_lsn {
+                    entries.push(entry);
                 }
-                entries.push(entry);
+            })?;
+
+            // Only the segment being written can end inside a frame
+            let is_current = i + 1 == segments.len();
+            if end == ScanEnd::Corrupt || (end == ScanEnd::Torn && !is_current) {
+                return Err(DbError::Corruption(format!(
+                    "Checksum mismatch in WAL segment {} after LSN {}",
+                    path.display(),
+                    last_lsn
+                )));
             }
         }
 
         Ok(entries)
     }
 
+    /// Forget a dirty page once it has been written back, so it no longer
+    /// holds back truncation
+    pub fn page_flushed(&self, page_id: PageId) {
+        self.dirty_page_table.write().remove(&page_id);
+    }
+
     /// Get transaction table (for recovery)
     pub fn transaction_table(&self) -> HashMap<TransactionId, TransactionTableEntry> {
         self.transaction_table.read().clone()
@@ -802,11 +1238,24 @@ impl WALManager {
     /// Shutdown WAL manager
     pub fn shutdown(&self) -> Result<()> {
         self.shutdown.store(true, Ordering::SeqCst);
+        self.flush_pending()?;
         self.sync()?;
         Ok(())
     }
 }
 
+/// Report the outcome of a write to the appenders waiting on the first
+/// `waiters.len()` entries
+fn notify_waiters(
+    entries: &[WALEntry],
+    waiters: Vec<oneshot::Sender<Result<LSN>>>,
+    result: &Result<()>,
+) {
+    for (entry, waiter) in entries.iter().zip(waiters) {
+        let _ = waiter.send(result.clone().map(|()| entry.lsn));
+    }
+}
+
 /// Log Shipping Manager for Replication
 pub struct LogShippingManager {
     /// WAL manager
@@ -908,11 +1357,8 @@ impl LogShippingManager {
         let mut stats = self.stats.write();
         stats.total_shipped += entries.len() as u64;
 
-        let serialized_size: usize = entries
-            .iter()
-            .map(|e| serde_json::to_vec(e).unwrap_or_default().len())
-            .sum();
-        stats.total_bytes_shipped += serialized_size as u64;
+        let shipped_bytes: u64 = entries.iter().map(|e| e.size as u64).sum();
+        stats.total_bytes_shipped += shipped_bytes;
 
         stats.avg_batch_size = update_running_average(
             stats.avg_batch_size,
@@ -1026,8 +1472,15 @@ impl CheckpointCoordinator {
 
         self.last_checkpoint_lsn.store(end_lsn, Ordering::SeqCst);
 
-        // Truncate old log entries
-        self.wal.truncate(begin_lsn)?;
+        // Recovery still needs every record of an active transaction and
+        // every change since the oldest unflushed one to a dirty page
+        let keep_from = txn_table
+            .values()
+            .filter(|entry| entry.state == TransactionState::Active)
+            .map(|entry| entry.first_lsn)
+            .chain(dirty_pages.values().copied())
+            .fold(begin_lsn, LSN::min);
+        self.wal.truncate(keep_from)?;
 
         // Update statistics
         let checkpoint_time = start.elapsed().as_millis() as f64;
@@ -1064,9 +1517,29 @@ impl CheckpointCoordinator {
 #[cfg(test)]
 mod tests {
     use crate::transaction::wal::{GroupCommitBuffer, LogRecord, WALConfig, WALEntry, WALManager};
+    use std::io::Write;
+    use std::sync::Arc;
     use std::time::{Duration, SystemTime};
     use tempfile::tempdir;
 
+    fn insert(txn_id: u64, page_id: u64) -> LogRecord {
+        LogRecord::Insert {
+            txn_id,
+            page_id,
+            offset: 0,
+            data: vec![7; 32],
+            undo_next_lsn: None,
+        }
+    }
+
+    fn direct(segment_size: usize) -> WALConfig {
+        WALConfig {
+            enable_group_commit: false,
+            segment_size,
+            ..Default::default()
+        }
+    }
+
     #[tokio::test]
     async fn test_wal_append_and_read() {
         let dir = tempdir().unwrap();
@@ -1123,4 +1596,114 @@ mod tests {
         assert!(!buffer.is_empty());
         assert!(buffer.should_flush(100, Duration::from_secs(1)));
     }
+
+    #[tokio::test]
+    async fn test_segments_and_lsn_lookup() {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("wal");
+        let wal = WALManager::new(wal_path.clone(), direct(256)).unwrap();
+
+        for i in 1..=40 {
+            assert_eq!(wal.append(insert(1, i)).await.unwrap(), i);
+        }
+        wal.shutdown().unwrap();
+        let segments = std::fs::read_dir(&wal_path).unwrap().count();
+        assert!(segments > 5, "expected several segments, found {}", segments);
+
+        let first = wal.segment_for_lsn(1).unwrap();
+        let last = wal.segment_for_lsn(40).unwrap();
+        assert_ne!(first, last);
+        assert!(wal.segment_for_lsn(41).is_none());
+
+        let entries = wal.read_from(25).unwrap();
+        let lsns: Vec<u64> = entries.iter().map(|e| e.lsn).collect();
+        assert_eq!(lsns, (25..=40).collect::<Vec<_>>());
+        assert_eq!(entries[0].prev_lsn, Some(24));
+        assert!(entries.iter().all(|e| e.verify_checksum()));
+        drop(wal);
+
+        // Numbering continues after a restart
+        let wal = WALManager::new(wal_path, direct(256)).unwrap();
+        assert_eq!(wal.current_lsn(), 41);
+    }
+
+    #[tokio::test]
+    async fn test_torn_tail_is_discarded() {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("wal");
+        let wal = WALManager::new(wal_path.clone(), direct(1 << 20)).unwrap();
+        for i in 1..=3 {
+            wal.append(insert(1, i)).await.unwrap();
+        }
+        wal.shutdown().unwrap();
+        let segment = wal.segment_for_lsn(3).unwrap();
+        drop(wal);
+
+        // A frame cut short by a crash
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&segment)
+            .unwrap();
+        file.write_all(&[40, 0, 0, 0, 1, 2, 3]).unwrap();
+        drop(file);
+
+        let wal = WALManager::new(wal_path.clone(), direct(1 << 20)).unwrap();
+        assert_eq!(wal.current_lsn(), 4);
+        wal.append(insert(1, 4)).await.unwrap();
+        wal.shutdown().unwrap();
+        assert_eq!(wal.read_from(1).unwrap().len(), 4);
+
+        // Damage inside the log is corruption, not end of log
+        let mut bytes = std::fs::read(&segment).unwrap();
+        bytes[40] ^= 0xFF;
+        std::fs::write(&segment, bytes).unwrap();
+        assert!(wal.read_from(1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_group_commit_keeps_lsn_order() {
+        let dir = tempdir().unwrap();
+        let config = WALConfig {
+            max_commit_delay_ms: 0,
+            segment_size: 1024,
+            ..Default::default()
+        };
+        let wal = Arc::new(WALManager::new(dir.path().join("wal"), config).unwrap());
+
+        let handles: Vec<_> = (0..32)
+            .map(|i| {
+                let wal = wal.clone();
+                tokio::spawn(async move { wal.append(insert(i, i)).await.unwrap() })
+            })
+            .collect();
+        let mut returned = Vec::new();
+        for handle in handles {
+            returned.push(handle.await.unwrap());
+        }
+        wal.append_durable(LogRecord::EndOfLog).unwrap();
+
+        let lsns: Vec<u64> = wal.read_from(1).unwrap().iter().map(|e| e.lsn).collect();
+        assert_eq!(lsns, (1..=33).collect::<Vec<_>>());
+        returned.sort();
+        assert_eq!(returned, (1..=32).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_truncate_removes_old_segments() {
+        let dir = tempdir().unwrap();
+        let wal = WALManager::new(dir.path().join("wal"), direct(256)).unwrap();
+        for i in 1..=20 {
+            wal.append(insert(1, i)).await.unwrap();
+        }
+
+        wal.truncate(12).unwrap();
+        assert!(wal.segment_for_lsn(1).is_none());
+        let oldest = wal.read_from(1).unwrap()[0].lsn;
+        assert!(oldest > 1 && oldest <= 12);
+        assert!(wal.get_stats().segments_removed > 0);
+
+        // The segment being written survives any truncation
+        wal.truncate(u64::MAX).unwrap();
+        assert!(wal.segment_for_lsn(20).is_some());
+    }
 }
@@ -1,66 +1,37 @@
 // RustyDB Triggers Module
 //
-// ⚠️ **CRITICAL: NO QUERY EXECUTOR INTEGRATION** ⚠️
+// Triggers live in the catalog and fire from the executor's INSERT, UPDATE
+// and DELETE paths, in the transaction of the statement firing them. Their
+// bodies are PL/SQL blocks, run like stored procedures, in which `:NEW` and
+// `:OLD` are records of the row being written with one field per column,
+// holding the column's value. BEFORE row triggers may assign to the fields
+// of `:NEW` to change the row written. INSTEAD OF triggers are row triggers
+// on views, which cannot be written otherwise, and run in place of the write.
 //
-// **Issue**: Triggers parse SQL but don't actually execute it
-//
-// **Missing Integration**:
-// 1. No connection to `src/execution/executor.rs`
-// 2. No :NEW / :OLD value substitution
-// 3. No trigger firing during DML operations
-// 4. No trigger depth tracking (risk of infinite recursion)
-//
-// **TODO - HIGH PRIORITY**:
-// 1. Integrate with DML operations (INSERT/UPDATE/DELETE)
-//    - Hook into storage layer or executor
-//    - Fire BEFORE triggers before operation
-//    - Fire AFTER triggers after operation
-//
-// 2. Implement :NEW / :OLD value substitution:
-//    ```rust
-//    pub fn substitute_trigger_variables(
-//        sql: &str,
-//        old_row: Option<&Row>,
-//        new_row: Option<&Row>,
-//    ) -> String {
-//        // Replace :NEW.column_name and :OLD.column_name
-//    }
-//    ```
-//
-// 3. Add trigger depth tracking (prevent infinite recursion):
-//    - Oracle limit: 32 levels
-//    - Track current trigger depth in thread-local storage
-//    - Return error if depth exceeded
-//
-// 4. Execute trigger action SQL:
-//    - Parse action string into SQL statements
-//    - Execute via QueryExecutor
-//    - Handle exceptions and rollback
-//
-// **Cross-Reference**: Same issues in `src/procedures/mod.rs`
-// **Impact**: Triggers are non-functional without integration
-// **Priority**: HIGH - required for data integrity features
+// Triggers of the same timing fire in order of name. The statements a
+// trigger runs may fire triggers in turn, up to MAX_TRIGGER_DEPTH levels
+// deep. An error in any trigger aborts the statement that fired it.
 
+use crate::catalog::{Catalog, Schema};
+use crate::common::Value;
 use crate::error::DbError;
+use crate::execution::executor::Executor;
+use crate::parser::SqlStatement;
+use crate::procedures::parser::{Expression, PlSqlBlock, PlSqlParser};
+use crate::procedures::runtime::{RuntimeExecutor, RuntimeValue};
+use crate::procedures::MAX_PROCEDURE_BODY_SIZE;
 use crate::Result;
-use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
 
 // ============================================================================
 // Capacity Limits - Prevent Unbounded Memory Growth
 // ============================================================================
 
-// TODO(ARCHITECTURE): Implement bounded trigger storage to prevent OOM
-// Maximum number of tables that can have triggers
-// Current implementation uses unbounded HashMap - see TriggerManager struct
-// Recommended: Use BoundedHashMap with eviction policy
+// Maximum number of tables and views that can have triggers
 pub const MAX_TABLES_WITH_TRIGGERS: usize = 10_000;
 
-// TODO(ARCHITECTURE): Implement bounded trigger storage to prevent OOM
 // Maximum number of triggers per table
-// Current implementation uses unbounded Vec - see TriggerManager struct
 // Oracle typical limit: 100 triggers per table
 pub const MAX_TRIGGERS_PER_TABLE: usize = 100;
 
@@ -68,471 +39,448 @@ pub const MAX_TRIGGERS_PER_TABLE: usize = 100;
 pub const MAX_TOTAL_TRIGGERS: usize = MAX_TABLES_WITH_TRIGGERS * MAX_TRIGGERS_PER_TABLE;
 
 // Maximum trigger recursion depth (Oracle limit: 32)
-// TODO(INTEGRATION): Implement trigger depth tracking in execution context
 pub const MAX_TRIGGER_DEPTH: usize = 32;
 
 // Trigger timing
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TriggerTiming {
     Before,
     After,
+    InsteadOf,
 }
 
 // Trigger event
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TriggerEvent {
     Insert,
     Update,
     Delete,
 }
 
+// Whether a trigger fires for each row written or once per statement
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TriggerLevel {
+    Row,
+    Statement,
+}
+
 // Trigger definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Trigger {
     pub name: String,
+    // Table, or view for an INSTEAD OF trigger
     pub table: String,
     pub timing: TriggerTiming,
-    pub event: TriggerEvent,
+    pub events: Vec<TriggerEvent>,
+    pub level: TriggerLevel,
+    // WHEN condition of a row trigger, a PL/SQL expression over NEW and OLD
     pub condition: Option<String>,
+    // PL/SQL block run when the trigger fires
     pub action: String,
     pub enabled: bool,
 }
 
-// Trigger context for execution
-#[derive(Debug, Clone)]
-pub struct TriggerContext {
-    pub old_values: Option<HashMap<String, String>>,
-    pub new_values: Option<HashMap<String, String>>,
-}
-
-// Trigger manager
-//
-// TODO: Add capacity limits - unbounded trigger storage
-// Recommended: 1,000 max tables, 100 max triggers per table
-// TODO: Add integration with query executor (triggers don't actually execute SQL yet)
-// TODO: Add trigger depth tracking to prevent infinite recursion (Oracle limit: 32)
-pub struct TriggerManager {
-    // WARNING: Unbounded - can store unlimited triggers per table
-    // TODO: Replace with BoundedHashMap<String, BoundedVec<Trigger>>
-    //       Max tables: 10,000, Max triggers per table: 100
-    triggers: Arc<RwLock<HashMap<String, Vec<Trigger>>>>,
-}
-
-impl TriggerManager {
-    pub fn new() -> Self {
+impl Trigger {
+    pub fn new(
+        name: String,
+        table: String,
+        timing: TriggerTiming,
+        events: Vec<TriggerEvent>,
+        level: TriggerLevel,
+        condition: Option<String>,
+        action: String,
+    ) -> Self {
         Self {
-            triggers: Arc::new(RwLock::new(HashMap::new())),
+            name,
+            table,
+            timing,
+            events,
+            level,
+            condition,
+            action,
+            enabled: true,
         }
     }
 
-    // Create a new trigger
-    pub fn create_trigger(&self, trigger: Trigger) -> Result<()> {
-        let mut triggers = self.triggers.write();
-        triggers
-            .entry(trigger.table.clone())
-            .or_insert_with(Vec::new)
-            .push(trigger);
-        Ok(())
-    }
-
-    // Drop a trigger by name
-    pub fn drop_trigger(&self, name: &str) -> Result<()> {
-        let mut triggers = self.triggers.write();
-        for (_, table_triggers) in triggers.iter_mut() {
-            table_triggers.retain(|t| t.name != name);
+    // Check the trigger against the size limit and that its body and
+    // condition parse
+    pub fn validate(&self) -> Result<()> {
+        if self.action.len() > MAX_PROCEDURE_BODY_SIZE {
+            return Err(DbError::LimitExceeded(format!(
+                "Body of trigger {} exceeds {} bytes",
+                self.name, MAX_PROCEDURE_BODY_SIZE
+            )));
         }
-        Ok(())
+        if self.events.is_empty() {
+            return Err(DbError::InvalidInput(format!(
+                "Trigger {} fires on no event",
+                self.name
+            )));
+        }
+        if self.level == TriggerLevel::Statement {
+            if self.timing == TriggerTiming::InsteadOf {
+                return Err(DbError::InvalidInput(format!(
+                    "INSTEAD OF trigger {} must fire for each row",
+                    self.name
+                )));
+            }
+            if self.condition.is_some() {
+                return Err(DbError::InvalidInput(format!(
+                    "Statement trigger {} cannot have a WHEN condition",
+                    self.name
+                )));
+            }
+        }
+        self.compile().map(|_| ())
     }
 
-    // Enable/disable a trigger
-    pub fn set_trigger_enabled(&self, name: &str, enabled: bool) -> Result<()> {
-        let mut triggers = self.triggers.write();
-        for (_, table_triggers) in triggers.iter_mut() {
-            for trigger in table_triggers {
-                if trigger.name == name {
-                    trigger.enabled = enabled;
-                    return Ok(());
-                }
+    // Check that the catalog has room for the trigger, which replaces any
+    // trigger of the same name
+    pub fn check_capacity(&self, catalog: &Catalog) -> Result<()> {
+        let names = catalog.list_triggers();
+        if names.len() >= MAX_TOTAL_TRIGGERS && !names.contains(&self.name) {
+            return Err(DbError::LimitExceeded(format!(
+                "Cannot create more than {} triggers",
+                MAX_TOTAL_TRIGGERS
+            )));
+        }
+        let on_table = catalog
+            .table_triggers(&self.table)
+            .iter()
+            .filter(|trigger| trigger.name != self.name)
+            .count();
+        if on_table >= MAX_TRIGGERS_PER_TABLE {
+            return Err(DbError::LimitExceeded(format!(
+                "Cannot create more than {} triggers on {}",
+                MAX_TRIGGERS_PER_TABLE, self.table
+            )));
+        }
+        if on_table == 0 {
+            let tables: HashSet<String> = names
+                .iter()
+                .filter_map(|name| catalog.get_trigger(name).ok())
+                .map(|trigger| trigger.table)
+                .collect();
+            if tables.len() >= MAX_TABLES_WITH_TRIGGERS {
+                return Err(DbError::LimitExceeded(format!(
+                    "Cannot create triggers on more than {} tables",
+                    MAX_TABLES_WITH_TRIGGERS
+                )));
             }
         }
-        Err(DbError::NotFound(format!("Trigger '{}' not found", name)))
+        Ok(())
     }
 
-    // Get all triggers for a table
-    pub fn get_triggers(&self, table: &str) -> Vec<Trigger> {
-        let triggers = self.triggers.read();
-        triggers.get(table).map(|t| t.clone()).unwrap_or_default()
+    // Parse the body and the condition
+    pub fn compile(&self) -> Result<CompiledTrigger> {
+        let body = PlSqlParser::new().parse(&row_references(&self.action))?;
+        let condition = self
+            .condition
+            .as_deref()
+            .map(|condition| PlSqlParser::new().parse_condition(&row_references(condition)))
+            .transpose()?;
+        Ok(CompiledTrigger { body, condition })
     }
 
-    // Execute triggers for a specific event
-    pub fn execute_triggers(
-        &self,
-        table: &str,
-        event: TriggerEvent,
-        timing: TriggerTiming,
-        context: &TriggerContext,
-    ) -> Result<()> {
-        let triggers = self.triggers.read();
-
-        if let Some(table_triggers) = triggers.get(table) {
-            for trigger in table_triggers {
-                if trigger.enabled && trigger.event == event && trigger.timing == timing {
-                    // Check condition if present
-                    if let Some(condition) = &trigger.condition {
-                        if !self.evaluate_condition(condition, context)? {
-                            continue;
-                        }
-                    }
-
-                    // Execute trigger action
-                    self.execute_action(&trigger.action, context)?;
-                }
-            }
-        }
-
-        Ok(())
+    // Whether the trigger fires on `event` at `timing` and `level`
+    pub fn fires(&self, event: TriggerEvent, timing: TriggerTiming, level: TriggerLevel) -> bool {
+        self.enabled && self.timing == timing && self.level == level && self.events.contains(&event)
     }
+}
 
-    // Evaluate a trigger condition
-    fn evaluate_condition(&self, condition: &str, context: &TriggerContext) -> Result<bool> {
-        // Parse and evaluate the condition expression
-        // Supports simple conditions like: NEW.column = value, OLD.column <> value
-        let condition = condition.trim();
+// The body and condition of a trigger, parsed
+pub struct CompiledTrigger {
+    body: PlSqlBlock,
+    condition: Option<Expression>,
+}
 
-        // Handle NEW.column references
-        if condition.starts_with("NEW.") {
-            if let Some(new_values) = &context.new_values {
-                return self.evaluate_column_condition(condition, "NEW.", new_values);
-            }
-            return Ok(false);
+impl CompiledTrigger {
+    // Run the body against `executor` with NEW and OLD bound to `new` and
+    // `old`, if the condition holds; returns the runtime it ran in
+    fn fire(
+        &self,
+        executor: &Executor,
+        schema: &Schema,
+        old: Option<&[Value]>,
+        new: Option<&[Value]>,
+    ) -> Result<Option<RuntimeExecutor>> {
+        let runtime = RuntimeExecutor::with_executor(executor.clone());
+        if let Some(old) = old {
+            runtime.set_variable("OLD", record(schema, old));
+        }
+        if let Some(new) = new {
+            runtime.set_variable("NEW", record(schema, new));
         }
 
-        // Handle OLD.column references
-        if condition.starts_with("OLD.") {
-            if let Some(old_values) = &context.old_values {
-                return self.evaluate_column_condition(condition, "OLD.", old_values);
+        if let Some(condition) = &self.condition {
+            if !runtime.evaluate(condition)?.as_boolean()? {
+                return Ok(None);
             }
-            return Ok(false);
         }
+        runtime.execute(&self.body)?;
+        Ok(Some(runtime))
+    }
+}
 
-        // Handle comparison between NEW and OLD
-        if condition.contains("NEW.") && condition.contains("OLD.") {
-            return self.evaluate_new_old_comparison(condition, context);
-        }
+// The enabled triggers on a table that fire for one kind of write, compiled
+// once for the statement writing
+pub struct TableTriggers {
+    // Runs the trigger bodies, one trigger level below the statement
+    executor: Executor,
+    schema: Schema,
+    event: TriggerEvent,
+    triggers: Vec<(Trigger, CompiledTrigger)>,
+}
 
-        // Default: condition evaluates to true if non-empty
-        Ok(!condition.is_empty())
+impl TableTriggers {
+    // The triggers of `triggers` that fire on `event`, to run in `executor`
+    pub fn new(
+        executor: Executor,
+        schema: &Schema,
+        event: TriggerEvent,
+        triggers: Vec<Trigger>,
+    ) -> Result<Self> {
+        let triggers = triggers
+            .into_iter()
+            .filter(|trigger| trigger.enabled && trigger.events.contains(&event))
+            .map(|trigger| {
+                let compiled = trigger.compile()?;
+                Ok((trigger, compiled))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            executor,
+            schema: schema.clone(),
+            event,
+            triggers,
+        })
     }
 
-    // Helper to evaluate column conditions like "NEW.status = 'active'"
-    fn evaluate_column_condition(
-        &self,
-        condition: &str,
-        prefix: &str,
-        values: &HashMap<String, String>,
-    ) -> Result<bool> {
-        // Parse: PREFIX.column operator value
-        let rest = condition.strip_prefix(prefix).unwrap_or(condition);
-
-        // Find operator
-        let operators = [
-            "<>",
-            "!=",
-            ">=",
-            "<=",
-            "=",
-            ">",
-            "<",
-            " IS NOT NULL",
-            " IS NULL",
-        ];
-        for op in operators {
-            if let Some(pos) = rest.find(op) {
-                let column = rest[..pos].trim();
-                let value = rest[pos + op.len()..].trim().trim_matches('\'');
-
-                let actual = values.get(column).map(|s| s.as_str()).unwrap_or("");
-
-                return Ok(match op {
-                    "=" => actual == value,
-                    "<>" | "!=" => actual != value,
-                    ">" => actual > value,
-                    "<" => actual < value,
-                    ">=" => actual >= value,
-                    "<=" => actual <= value,
-                    " IS NULL" => actual.is_empty(),
-                    " IS NOT NULL" => !actual.is_empty(),
-                    _ => false,
-                });
-            }
-        }
-
-        Ok(false)
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
     }
 
-    // Helper to compare NEW and OLD values
-    fn evaluate_new_old_comparison(
-        &self,
-        condition: &str,
-        context: &TriggerContext,
-    ) -> Result<bool> {
-        // Handle: NEW.column <> OLD.column (detect changes)
-        if let (Some(new_values), Some(old_values)) = (&context.new_values, &context.old_values) {
-            // Parse NEW.col <> OLD.col pattern
-            if let Some(neq_pos) = condition.find("<>").or_else(|| condition.find("!=")) {
-                let left = condition[..neq_pos].trim();
-                let right = condition[neq_pos + 2..].trim();
-
-                let left_val = if left.starts_with("NEW.") {
-                    new_values.get(left.strip_prefix("NEW.").unwrap_or(""))
-                } else if left.starts_with("OLD.") {
-                    old_values.get(left.strip_prefix("OLD.").unwrap_or(""))
-                } else {
-                    None
-                };
-
-                let right_val = if right.starts_with("NEW.") {
-                    new_values.get(right.strip_prefix("NEW.").unwrap_or(""))
-                } else if right.starts_with("OLD.") {
-                    old_values.get(right.strip_prefix("OLD.").unwrap_or(""))
-                } else {
-                    None
-                };
-
-                return Ok(left_val != right_val);
-            }
+    // Whether any trigger fires at `timing` and `level`
+    pub fn has(&self, timing: TriggerTiming, level: TriggerLevel) -> bool {
+        self.firing(timing, level).next().is_some()
+    }
 
-            // Handle = operator
-            if let Some(eq_pos) = condition.find('=') {
-                let left = condition[..eq_pos].trim();
-                let right = condition[eq_pos + 1..].trim();
-
-                let left_val = if left.starts_with("NEW.") {
-                    new_values.get(left.strip_prefix("NEW.").unwrap_or(""))
-                } else if left.starts_with("OLD.") {
-                    old_values.get(left.strip_prefix("OLD.").unwrap_or(""))
-                } else {
-                    None
-                };
-
-                let right_val = if right.starts_with("NEW.") {
-                    new_values.get(right.strip_prefix("NEW.").unwrap_or(""))
-                } else if right.starts_with("OLD.") {
-                    old_values.get(right.strip_prefix("OLD.").unwrap_or(""))
-                } else {
-                    None
-                };
-
-                return Ok(left_val == right_val);
-            }
+    // Fire the statement triggers of `timing`
+    pub fn fire_statement(&self, timing: TriggerTiming) -> Result<()> {
+        for trigger in self.firing(timing, TriggerLevel::Statement) {
+            trigger.fire(&self.executor, &self.schema, None, None)?;
         }
-
-        Ok(false)
+        Ok(())
     }
 
-    // Execute a trigger action
-    //
-    // Production-ready implementation of trigger action execution with enhanced
-    // error handling, validation, and transaction integration preparation.
-    //
-    // This implementation provides:
-    // - Robust :NEW and :OLD reference substitution with SQL injection prevention
-    // - SQL statement validation and type checking
-    // - Error propagation for rollback support
-    // - Multi-statement action support
-    // - RAISE_APPLICATION_ERROR handling
-    // - Trigger depth tracking to prevent infinite recursion
-    //
-    // INTEGRATION NOTE: For full production use, this should be integrated with:
-    // - Query execution engine (src/execution/executor.rs)
-    // - Transaction manager (src/transaction/mod.rs)
-    // - SQL parser (src/parser/mod.rs)
-    fn execute_action(&self, action: &str, context: &TriggerContext) -> Result<()> {
-        // Validate action is not empty
-        if action.trim().is_empty() {
-            log::trace!("Trigger action is empty, nothing to execute");
-            return Ok(());
+    // Fire the row triggers of `timing` for a row changing from `old` to `new`
+    pub fn fire_row(
+        &self,
+        timing: TriggerTiming,
+        old: Option<&[Value]>,
+        new: Option<&[Value]>,
+    ) -> Result<()> {
+        for trigger in self.firing(timing, TriggerLevel::Row) {
+            trigger.fire(&self.executor, &self.schema, old, new)?;
         }
+        Ok(())
+    }
 
-        log::debug!("Executing trigger action: {}", action);
-
-        // Parse and execute SQL statements in the trigger action
-        // Replace :NEW and :OLD references with actual values
-        let mut sql = action.to_string();
-
-        // Track if any substitutions were made for validation
-        let mut substitutions_made = 0;
-
-        // Replace :NEW.column references with proper escaping
-        if let Some(new_values) = &context.new_values {
-            for (column, value) in new_values {
-                // Escape single quotes to prevent SQL injection
-                let escaped_value = value.replace('\'', "''");
-                let replacement = format!("'{}'", escaped_value);
-
-                // Handle :NEW.column syntax
-                let placeholder = format!(":NEW.{}", column);
-                if sql.contains(&placeholder) {
-                    sql = sql.replace(&placeholder, &replacement);
-                    substitutions_made += 1;
-                    log::trace!("Substituted {} with value", placeholder);
-                }
-
-                // Also handle NEW.column without colon (Oracle-style)
-                let placeholder_no_colon = format!("NEW.{}", column);
-                // Only replace if not preceded by colon to avoid double replacement
-                let temp_sql = sql.replace(&format!(":{}", placeholder_no_colon), &placeholder);
-                sql = temp_sql.replace(&placeholder_no_colon, &replacement);
+    // Fire the BEFORE row triggers for a row about to be written, each seeing
+    // NEW as the one before left it, and return the row to write
+    pub fn before_row(&self, old: Option<&[Value]>, mut new: Vec<Value>) -> Result<Vec<Value>> {
+        for trigger in self.firing(TriggerTiming::Before, TriggerLevel::Row) {
+            if let Some(runtime) = trigger.fire(&self.executor, &self.schema, old, Some(&new))? {
+                new = row(&self.schema, &runtime)?;
             }
         }
+        Ok(new)
+    }
 
-        // Replace :OLD.column references with proper escaping
-        if let Some(old_values) = &context.old_values {
-            for (column, value) in old_values {
-                // Escape single quotes to prevent SQL injection
-                let escaped_value = value.replace('\'', "''");
-                let replacement = format!("'{}'", escaped_value);
-
-                // Handle :OLD.column syntax
-                let placeholder = format!(":OLD.{}", column);
-                if sql.contains(&placeholder) {
-                    sql = sql.replace(&placeholder, &replacement);
-                    substitutions_made += 1;
-                    log::trace!("Substituted {} with value", placeholder);
-                }
-
-                // Also handle OLD.column without colon (Oracle-style)
-                let placeholder_no_colon = format!("OLD.{}", column);
-                // Only replace if not preceded by colon to avoid double replacement
-                let temp_sql = sql.replace(&format!(":{}", placeholder_no_colon), &placeholder);
-                sql = temp_sql.replace(&placeholder_no_colon, &replacement);
-            }
-        }
+    fn firing(
+        &self,
+        timing: TriggerTiming,
+        level: TriggerLevel,
+    ) -> impl Iterator<Item = &CompiledTrigger> {
+        let event = self.event;
+        self.triggers
+            .iter()
+            .filter(move |(trigger, _)| trigger.fires(event, timing, level))
+            .map(|(_, compiled)| compiled)
+    }
+}
 
-        log::debug!(
-            "Trigger action after substitution ({} substitutions): {}",
-            substitutions_made,
-            sql
-        );
+// A row as a record of its column values
+fn record(schema: &Schema, row: &[Value]) -> RuntimeValue {
+    let fields: HashMap<String, RuntimeValue> = schema
+        .columns
+        .iter()
+        .map(|column| column.name.clone())
+        .zip(row.iter().cloned().map(RuntimeValue::from))
+        .collect();
+    RuntimeValue::Record(fields)
+}
 
-        // Execute the SQL statement
-        if !sql.trim().is_empty() {
-            // Split into multiple statements if needed (separated by semicolons)
-            let statements: Vec<&str> = sql
-                .split(';')
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty())
-                .collect();
+// The row NEW holds after a trigger ran, as the column types
+fn row(schema: &Schema, runtime: &RuntimeExecutor) -> Result<Vec<Value>> {
+    let RuntimeValue::Record(mut fields) = runtime.get_variable("NEW")? else {
+        return Err(DbError::Runtime("NEW is not a record".to_string()));
+    };
+    schema
+        .columns
+        .iter()
+        .map(|column| {
+            let value = fields
+                .remove(&column.name)
+                .map_or(Ok(Value::Null), |value| value.to_value())?;
+            column.data_type.coerce(value)
+        })
+        .collect()
+}
 
-            for (idx, stmt) in statements.iter().enumerate() {
-                let sql_upper = stmt.to_uppercase();
-
-                log::trace!("Processing trigger statement {}/{}: {}", idx + 1, statements.len(), stmt);
-
-                // Validate and classify the SQL statement
-                if sql_upper.starts_with("INSERT")
-                    || sql_upper.starts_with("UPDATE")
-                    || sql_upper.starts_with("DELETE")
-                {
-                    // DML statements - these modify data
-                    log::debug!(
-                        "Trigger action: {} statement prepared for execution",
-                        sql_upper.split_whitespace().next().unwrap_or("DML")
-                    );
-
-                    // In full implementation with query engine integration:
-                    // 1. Parse the DML statement
-                    // 2. Execute within the same transaction as the triggering statement
-                    // 3. Propagate any errors to cause rollback
-                    // 4. Track rows affected
-
-                } else if sql_upper.starts_with("SELECT") {
-                    // SELECT statements in triggers (for validation or side effects)
-                    log::debug!("Trigger action: SELECT statement prepared");
-
-                } else if sql_upper.starts_with("RAISE_APPLICATION_ERROR")
-                    || sql_upper.starts_with("RAISE") {
-                    // Error raising - should abort transaction
-                    log::warn!("Trigger action: RAISE statement detected - should abort transaction");
-
-                    // In full implementation: parse error code and message, return error
-                    // Example: RAISE_APPLICATION_ERROR(-20001, 'Custom error message');
-                    return Err(DbError::Internal(
-                        "Trigger raised application error".to_string()
-                    ));
-
-                } else if sql_upper.starts_with("BEGIN")
-                    || sql_upper.starts_with("END")
-                    || sql_upper.starts_with("DECLARE") {
-                    // PL/SQL block delimiters
-                    log::trace!("Trigger action: PL/SQL block delimiter");
-
-                } else if sql_upper.starts_with("IF")
-                    || sql_upper.starts_with("ELSE")
-                    || sql_upper.starts_with("ELSIF") {
-                    // Control flow statements
-                    log::trace!("Trigger action: Control flow statement");
-
-                } else if sql_upper.starts_with("--") || sql_upper.starts_with("/*") {
-                    // Comments
-                    log::trace!("Trigger action: Comment line");
-
-                } else if !sql_upper.is_empty() {
-                    // Unknown or unsupported statement type
-                    log::warn!(
-                        "Trigger action may contain unsupported statement type: {}",
-                        sql_upper.split_whitespace().next().unwrap_or("UNKNOWN")
-                    );
-
-                    // In strict mode, this could return an error
-                    // For now, we log and continue
-                }
+// Write the row references `:NEW.col`, `:old` or `new.col` of a trigger
+// body as `NEW.col`, `OLD` and so on, which the PL/SQL parser reads as
+// record variables. String literals and comments are left alone.
+fn row_references(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut written = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let end = if c == '\'' {
+            chars[i + 1..]
+                .iter()
+                .position(|&c| c == '\'')
+                .map_or(chars.len(), |p| i + p + 2)
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            chars[i..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |p| i + p)
+        } else if c == ':' || c.is_alphanumeric() || c == '_' {
+            let start = if c == ':' { i + 1 } else { i };
+            let mut end = start;
+            while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+                end += 1;
+            }
+            let word: String = chars[start..end].iter().collect();
+            let is_row = word.eq_ignore_ascii_case("NEW") || word.eq_ignore_ascii_case("OLD");
+            if is_row && (c == ':' || chars.get(end) == Some(&'.')) {
+                written.push_str(&word.to_ascii_uppercase());
+                i = end;
+                continue;
             }
+            end.max(i + 1)
+        } else {
+            i + 1
+        };
+        written.extend(&chars[i..end]);
+        i = end;
+    }
+    written
+}
 
-            log::info!(
-                "Trigger action prepared for execution: {} statement(s), {} substitutions",
-                statements.len(),
-                substitutions_made
-            );
+// Trigger manager
+//
+// Creates, drops, enables and disables the triggers in an executor's
+// catalog, as CREATE TRIGGER and DROP TRIGGER do
+pub struct TriggerManager {
+    executor: Executor,
+}
 
-            // In full implementation, this is where we would:
-            // 1. Execute each statement through the query execution engine
-            // 2. Within the same transaction context as the triggering operation
-            // 3. Maintain trigger depth counter to prevent infinite recursion
-            // 4. Handle errors and trigger rollback if needed
-            // 5. Support AUTONOMOUS_TRANSACTION pragma for independent transactions
+impl TriggerManager {
+    pub fn new(executor: Executor) -> Self {
+        Self { executor }
+    }
 
-        } else {
-            log::trace!("Trigger action is empty after substitution");
+    // Create a new trigger
+    pub fn create_trigger(&self, trigger: Trigger) -> Result<()> {
+        let (name, enabled) = (trigger.name.clone(), trigger.enabled);
+        self.executor.execute(SqlStatement::CreateTrigger {
+            name: trigger.name,
+            table: trigger.table,
+            timing: trigger.timing,
+            events: trigger.events,
+            level: trigger.level,
+            condition: trigger.condition,
+            body: trigger.action,
+            or_replace: false,
+        })?;
+        if !enabled {
+            self.set_trigger_enabled(&name, false)?;
         }
+        Ok(())
+    }
 
+    // Drop a trigger by name
+    pub fn drop_trigger(&self, name: &str) -> Result<()> {
+        self.executor.execute(SqlStatement::DropTrigger {
+            name: name.to_string(),
+        })?;
         Ok(())
     }
-}
 
-impl Default for TriggerManager {
-    fn default() -> Self {
-        Self::new()
+    // Enable/disable a trigger
+    pub fn set_trigger_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        let catalog = self.executor.catalog();
+        let mut trigger = catalog.get_trigger(name)?;
+        trigger.enabled = enabled;
+        catalog.replace_trigger(trigger)
+    }
+
+    // Get all triggers for a table
+    pub fn get_triggers(&self, table: &str) -> Vec<Trigger> {
+        self.executor.catalog().table_triggers(table)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transaction::TransactionManager;
+    use std::sync::Arc;
+
+    fn manager() -> Result<TriggerManager> {
+        let executor = Executor::new(
+            Arc::new(Catalog::new()),
+            Arc::new(TransactionManager::new()),
+        );
+        for table in ["users", "products", "orders", "audit_log"] {
+            executor.execute(
+                crate::parser::SqlParser::new()
+                    .parse(&format!(
+                        "CREATE TABLE {} (id INT, name VARCHAR(20))",
+                        table
+                    ))?
+                    .remove(0),
+            )?;
+        }
+        Ok(TriggerManager::new(executor))
+    }
+
+    fn trigger(name: &str, table: &str, timing: TriggerTiming, event: TriggerEvent) -> Trigger {
+        Trigger::new(
+            name.to_string(),
+            table.to_string(),
+            timing,
+            vec![event],
+            TriggerLevel::Row,
+            None,
+            "BEGIN NULL; END;".to_string(),
+        )
+    }
 
     #[test]
     fn test_create_trigger() -> Result<()> {
-        let tm = TriggerManager::new();
-
-        let trigger = Trigger {
-            name: "audit_insert".to_string(),
-            table: "users".to_string(),
-            timing: TriggerTiming::After,
-            event: TriggerEvent::Insert,
-            condition: None,
-            action: "INSERT INTO audit_log VALUES (...)".to_string(),
-            enabled: true,
-        };
+        let tm = manager()?;
+
+        let mut trigger = trigger(
+            "audit_insert",
+            "users",
+            TriggerTiming::After,
+            TriggerEvent::Insert,
+        );
+        trigger.action = "BEGIN INSERT INTO audit_log VALUES (:NEW.id, :new.name); END;".into();
 
         tm.create_trigger(trigger)?;
 
@@ -545,17 +493,14 @@ mod tests {
 
     #[test]
     fn test_drop_trigger() -> Result<()> {
-        let tm = TriggerManager::new();
-
-        let trigger = Trigger {
-            name: "test_trigger".to_string(),
-            table: "products".to_string(),
-            timing: TriggerTiming::Before,
-            event: TriggerEvent::Update,
-            condition: None,
-            action: "".to_string(),
-            enabled: true,
-        };
+        let tm = manager()?;
+
+        let trigger = trigger(
+            "test_trigger",
+            "products",
+            TriggerTiming::Before,
+            TriggerEvent::Update,
+        );
 
         tm.create_trigger(trigger)?;
         assert_eq!(tm.get_triggers("products").len(), 1);
@@ -568,17 +513,14 @@ mod tests {
 
     #[test]
     fn test_disable_trigger() -> Result<()> {
-        let tm = TriggerManager::new();
-
-        let trigger = Trigger {
-            name: "test_trigger".to_string(),
-            table: "orders".to_string(),
-            timing: TriggerTiming::Before,
-            event: TriggerEvent::Delete,
-            condition: None,
-            action: "".to_string(),
-            enabled: true,
-        };
+        let tm = manager()?;
+
+        let trigger = trigger(
+            "test_trigger",
+            "orders",
+            TriggerTiming::Before,
+            TriggerEvent::Delete,
+        );
 
         tm.create_trigger(trigger)?;
         tm.set_trigger_enabled("test_trigger", false)?;
@@ -588,4 +530,16 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_row_references() {
+        assert_eq!(
+            row_references("BEGIN :new.total := :NEW.qty * old.price; END;"),
+            "BEGIN NEW.total := NEW.qty * OLD.price; END;"
+        );
+        assert_eq!(
+            row_references("x := ':new.a'; -- :old.b\nrenew.c := :old;"),
+            "x := ':new.a'; -- :old.b\nrenew.c := OLD;"
+        );
+    }
 }
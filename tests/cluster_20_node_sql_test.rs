@@ -4,6 +4,7 @@
 // with 50 advanced SQL tests covering distributed query execution,
 // failover scenarios, and enterprise-grade SQL features.
 
+use sqlparser::ast::SetExpr;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -781,7 +782,7 @@ fn test_21_create_table_with_types() {
     let stmts = parser.parse(sql).unwrap();
 
     match &stmts[0] {
-        SqlStatement::CreateTable { name, columns } => {
+        SqlStatement::CreateTable { name, columns, .. } => {
             assert_eq!(name, "test_table");
             assert!(columns.len() >= 1);
         }
@@ -1027,12 +1028,13 @@ fn test_35_select_distinct_multiple_columns() {
     let stmts = parser.parse(sql).unwrap();
 
     match &stmts[0] {
-        SqlStatement::Select {
-            distinct, columns, ..
-        } => {
-            assert!(distinct);
-            assert_eq!(columns.len(), 2);
-        }
+        SqlStatement::Select { query } => match query.body.as_ref() {
+            SetExpr::Select(select) => {
+                assert!(select.distinct.is_some());
+                assert_eq!(select.projection.len(), 2);
+            }
+            _ => panic!("Expected Select"),
+        },
         _ => panic!("Expected Select"),
     }
 
@@ -1067,9 +1069,9 @@ fn test_38_select_group_by_having() {
 
     if let Ok(stmts) = result {
         match &stmts[0] {
-            SqlStatement::Select { group_by, .. } => {
+            SqlStatement::Select { query } => {
                 // GROUP BY may or may not be populated depending on parser
-                let _ = group_by;
+                let _ = &query.body;
             }
             _ => {}
         }
@@ -0,0 +1,219 @@
+// # Crash Recovery Tests
+//
+// Runs a write workload in a child process, kills it at a random point, then
+// recovers the table store from its data WAL and checks that every committed
+// transaction survived and nothing uncommitted is visible.
+//
+// The child is this test binary re-run with only `crash_worker` selected.
+
+use rand::Rng;
+use rusty_db::common::Value;
+use rusty_db::storage::TableStore;
+use rusty_db::transaction::recovery::{ARIESRecoveryManager, RecoveryConfig};
+use rusty_db::transaction::wal::{WALConfig, WALManager};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+const DIR_VAR: &str = "RUSTYDB_CRASH_DIR";
+const FIRST_TXN_VAR: &str = "RUSTYDB_CRASH_FIRST_TXN";
+const TABLE: &str = "crash_t";
+
+// Open the store the way the server does: attach the WAL, then recover
+fn open_recovered(dir: &Path) -> Arc<TableStore> {
+    let store = Arc::new(TableStore::open(dir.join("data").to_str().unwrap(), 4096, 8).unwrap());
+    let wal = Arc::new(
+        WALManager::new(
+            dir.join("wal"),
+            WALConfig {
+                enable_group_commit: false,
+                ..WALConfig::default()
+            },
+        )
+        .unwrap(),
+    );
+    store.attach_wal(wal.clone());
+
+    let recovery =
+        ARIESRecoveryManager::new(wal, RecoveryConfig::default()).with_pages(store.clone());
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(recovery.recover())
+        .unwrap();
+    store
+}
+
+// Rows of each transaction number: (row number, payload)
+fn rows_by_txn(store: &TableStore) -> BTreeMap<i64, Vec<(i64, String)>> {
+    let mut by_txn: BTreeMap<i64, Vec<(i64, String)>> = BTreeMap::new();
+    for (_, row) in store.scan(TABLE).unwrap() {
+        match row.as_slice() {
+            [Value::Integer(txn), Value::Integer(n), Value::String(payload)] => {
+                by_txn.entry(*txn).or_default().push((*n, payload.clone()));
+            }
+            other => panic!("Unexpected row {:?}", other),
+        }
+    }
+    by_txn
+}
+
+// Workload run in the child process. Every transaction inserts four rows,
+// updates the first and deletes the last; every seventh one rolls back.
+#[test]
+#[ignore]
+fn crash_worker() {
+    let Ok(dir) = std::env::var(DIR_VAR) else {
+        return;
+    };
+    let first: i64 = std::env::var(FIRST_TXN_VAR).unwrap().parse().unwrap();
+    let store = open_recovered(Path::new(&dir));
+    if !store.has_table(TABLE) {
+        store.create_table(TABLE).unwrap();
+    }
+
+    let mut out = std::io::stdout();
+    for k in first.. {
+        writeln!(out, "begin {}", k).unwrap();
+        out.flush().unwrap();
+
+        let txn = store.begin();
+        let mut rids = Vec::new();
+        for n in 0..4 {
+            let row = [
+                Value::Integer(k),
+                Value::Integer(n),
+                Value::String(format!("row {} of txn {}", n, k).repeat(8)),
+            ];
+            rids.push(txn.insert_row(TABLE, &row).unwrap());
+        }
+        let updated = [
+            Value::Integer(k),
+            Value::Integer(0),
+            Value::String("updated".to_string()),
+        ];
+        txn.update_row(TABLE, rids[0], &updated).unwrap();
+        txn.delete_row(TABLE, rids[3]).unwrap();
+
+        if k % 7 == 0 {
+            txn.rollback().unwrap();
+            writeln!(out, "rolled back {}", k).unwrap();
+        } else {
+            txn.commit().unwrap();
+            writeln!(out, "committed {}", k).unwrap();
+        }
+        out.flush().unwrap();
+    }
+}
+
+#[test]
+fn test_committed_data_survives_random_kills() {
+    let dir = tempfile::tempdir().unwrap();
+    let exe = std::env::current_exe().unwrap();
+    let mut rng = rand::rng();
+
+    let mut committed = BTreeSet::new();
+    let mut rolled_back = BTreeSet::new();
+    let mut next_txn = 1i64;
+
+    for round in 0..6 {
+        let mut child = Command::new(&exe)
+            .args([
+                "crash_worker",
+                "--exact",
+                "--ignored",
+                "--nocapture",
+                "--test-threads=1",
+            ])
+            .env(DIR_VAR, dir.path())
+            .env(FIRST_TXN_VAR, next_txn.to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(rng.random_range(50..400)));
+        let _ = child.kill();
+        let output = child.wait_with_output().unwrap();
+
+        // The last line may be cut short by the kill; only whole lines count
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for line in stdout.lines() {
+            let mut words = line.rsplitn(2, ' ');
+            let (Some(k), Some(event)) = (words.next(), words.next()) else {
+                continue;
+            };
+            let Ok(k) = k.parse::<i64>() else {
+                continue;
+            };
+            match event {
+                "begin" => next_txn = next_txn.max(k + 1),
+                "committed" => {
+                    committed.insert(k);
+                }
+                "rolled back" => {
+                    rolled_back.insert(k);
+                }
+                _ => {}
+            }
+        }
+
+        let store = open_recovered(dir.path());
+        if !store.has_table(TABLE) {
+            // Killed before the table was created
+            assert!(committed.is_empty(), "round {}: table lost", round);
+            continue;
+        }
+        let by_txn = rows_by_txn(&store);
+        let last_committed = committed.iter().next_back().copied().unwrap_or(0);
+
+        for k in &committed {
+            assert!(
+                by_txn.contains_key(k),
+                "round {}: committed txn {} lost",
+                round,
+                k
+            );
+        }
+        for (k, rows) in &by_txn {
+            assert!(
+                !rolled_back.contains(k),
+                "round {}: rolled back txn {} visible",
+                round,
+                k
+            );
+            // Only the transaction in flight at the kill may have committed unreported
+            assert!(
+                committed.contains(k) || *k > last_committed,
+                "round {}: uncommitted txn {} visible",
+                round,
+                k
+            );
+
+            let mut rows = rows.clone();
+            rows.sort();
+            assert_eq!(
+                rows.len(),
+                3,
+                "round {}: txn {} is partial: {:?}",
+                round,
+                k,
+                rows
+            );
+            assert_eq!(
+                rows[0],
+                (0, "updated".to_string()),
+                "round {}: txn {}",
+                round,
+                k
+            );
+            assert_eq!(rows[1].0, 1);
+            assert_eq!(rows[2].0, 2);
+        }
+        // Recovery made whatever it found durable
+        committed.extend(by_txn.keys().copied());
+        store.flush().unwrap();
+    }
+}
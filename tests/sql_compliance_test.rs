@@ -11,8 +11,20 @@ use rusty_db::{
     transaction::TransactionManager,
     Result,
 };
+use sqlparser::ast::{GroupByExpr, Select, SetExpr};
 use std::sync::Arc;
 
+// The SELECT block of a parsed query
+fn select_block(stmt: &SqlStatement) -> &Select {
+    match stmt {
+        SqlStatement::Select { query } => match query.body.as_ref() {
+            SetExpr::Select(select) => select,
+            _ => panic!("Expected a SELECT block"),
+        },
+        _ => panic!("Expected SELECT statement"),
+    }
+}
+
 fn setup_test_environment() -> (
     Arc<Catalog>,
     Arc<TransactionManager>,
@@ -67,20 +79,11 @@ fn test_select_distinct() -> Result<()> {
     let stmts = parser.parse(sql)?;
 
     assert_eq!(stmts.len(), 1);
-    match &stmts[0] {
-        SqlStatement::Select {
-            table,
-            columns,
-            distinct,
-            ..
-        } => {
-            assert_eq!(table, "users");
-            assert_eq!(columns.len(), 1);
-            assert_eq!(columns[0], "email");
-            assert!(distinct, "DISTINCT flag should be true");
-        }
-        _ => panic!("Expected SELECT statement"),
-    }
+    let select = select_block(&stmts[0]);
+    assert_eq!(select.from[0].relation.to_string(), "users");
+    assert_eq!(select.projection.len(), 1);
+    assert_eq!(select.projection[0].to_string(), "email");
+    assert!(select.distinct.is_some(), "DISTINCT flag should be true");
 
     Ok(())
 }
@@ -91,12 +94,8 @@ fn test_select_without_distinct() -> Result<()> {
     let sql = "SELECT email, name FROM users";
     let stmts = parser.parse(sql)?;
 
-    match &stmts[0] {
-        SqlStatement::Select { distinct, .. } => {
-            assert!(!distinct, "DISTINCT flag should be false");
-        }
-        _ => panic!("Expected SELECT statement"),
-    }
+    let select = select_block(&stmts[0]);
+    assert!(select.distinct.is_none(), "DISTINCT flag should be false");
 
     Ok(())
 }
@@ -165,6 +164,7 @@ fn test_create_index_parsing() -> Result<()> {
             table,
             columns,
             unique,
+            ..
         } => {
             assert_eq!(name, "idx_users_email");
             assert_eq!(table, "users");
@@ -284,6 +284,8 @@ fn test_executor_create_index() -> Result<()> {
         table: "users".to_string(),
         columns: vec!["email".to_string()],
         unique: false,
+        filter: None,
+        hnsw: None,
     };
 
     let result = executor.execute(stmt)?;
@@ -473,15 +475,11 @@ fn test_select_where_clause() -> Result<()> {
     let sql = "SELECT * FROM users WHERE id > 10 AND active = true";
     let stmts = parser.parse(sql)?;
 
-    match &stmts[0] {
-        SqlStatement::Select { filter, .. } => {
-            assert!(filter.is_some(), "Filter should be present");
-            let filter_str = filter.as_ref().unwrap();
-            assert!(filter_str.contains("id > 10"));
-            assert!(filter_str.contains("active = true"));
-        }
-        _ => panic!("Expected SELECT statement"),
-    }
+    let select = select_block(&stmts[0]);
+    assert!(select.selection.is_some(), "Filter should be present");
+    let filter_str = select.selection.as_ref().unwrap().to_string();
+    assert!(filter_str.contains("id > 10"));
+    assert!(filter_str.contains("active = true"));
 
     Ok(())
 }
@@ -492,19 +490,18 @@ fn test_select_group_by_having() -> Result<()> {
     let sql = "SELECT name, COUNT(*) FROM users GROUP BY name HAVING COUNT(*) > 5";
     let stmts = parser.parse(sql)?;
 
-    match &stmts[0] {
-        SqlStatement::Select {
-            group_by, having, ..
-        } => {
+    let select = select_block(&stmts[0]);
+    match &select.group_by {
+        GroupByExpr::Expressions(group_by, ..) => {
             assert_eq!(group_by.len(), 1);
-            assert_eq!(group_by[0], "name");
-
-            assert!(having.is_some());
-            let having_str = having.as_ref().unwrap();
-            assert!(having_str.contains("COUNT(*) > 5"));
+            assert_eq!(group_by[0].to_string(), "name");
         }
-        _ => panic!("Expected SELECT statement"),
+        _ => panic!("Expected GROUP BY expressions"),
     }
 
+    assert!(select.having.is_some());
+    let having_str = select.having.as_ref().unwrap().to_string();
+    assert!(having_str.contains("COUNT(*) > 5"));
+
     Ok(())
 }
@@ -8,8 +8,20 @@ use rusty_db::execution::Executor;
 use rusty_db::index::IndexManager;
 use rusty_db::parser::{AlterAction, ConstraintType, SqlParser, SqlStatement};
 use rusty_db::transaction::TransactionManager;
+use sqlparser::ast::{Select, SetExpr};
 use std::sync::Arc;
 
+// The SELECT block of a parsed query
+fn select_block(stmt: &SqlStatement) -> &Select {
+    match stmt {
+        SqlStatement::Select { query } => match query.body.as_ref() {
+            SetExpr::Select(select) => select,
+            _ => panic!("Expected a SELECT block"),
+        },
+        _ => panic!("Expected SELECT statement"),
+    }
+}
+
 #[test]
 fn test_create_table() {
     let parser = SqlParser::new();
@@ -18,7 +30,7 @@ fn test_create_table() {
 
     assert_eq!(stmts.len(), 1);
     match &stmts[0] {
-        SqlStatement::CreateTable { name, columns } => {
+        SqlStatement::CreateTable { name, columns, .. } => {
             assert_eq!(name, "users");
             assert_eq!(columns.len(), 3);
         }
@@ -46,16 +58,10 @@ fn test_select_distinct() {
     let sql = "SELECT DISTINCT category FROM products";
     let stmts = parser.parse(sql).unwrap();
 
-    match &stmts[0] {
-        SqlStatement::Select {
-            distinct, columns, ..
-        } => {
-            assert!(distinct);
-            assert_eq!(columns.len(), 1);
-            assert_eq!(columns[0], "category");
-        }
-        _ => panic!("Expected Select statement"),
-    }
+    let select = select_block(&stmts[0]);
+    assert!(select.distinct.is_some());
+    assert_eq!(select.projection.len(), 1);
+    assert_eq!(select.projection[0].to_string(), "category");
 }
 
 #[test]
@@ -115,6 +121,7 @@ fn test_create_index() {
             table,
             columns,
             unique,
+            ..
         } => {
             assert_eq!(name, "idx_email");
             assert_eq!(table, "users");
@@ -225,6 +232,7 @@ fn test_executor_create_table() {
     let stmt = SqlStatement::CreateTable {
         name: "users".to_string(),
         columns,
+        constraints: vec![],
     };
 
     let result = executor.execute(stmt);
@@ -252,6 +260,7 @@ fn test_executor_alter_table_add_column() {
     let create_stmt = SqlStatement::CreateTable {
         name: "users".to_string(),
         columns,
+        constraints: vec![],
     };
 
     executor.execute(create_stmt).unwrap();
@@ -295,6 +304,7 @@ fn test_executor_create_view() {
     let create_table = SqlStatement::CreateTable {
         name: "users".to_string(),
         columns,
+        constraints: vec![],
     };
 
     executor.execute(create_table).unwrap();
@@ -347,6 +357,7 @@ fn test_executor_create_index() {
     let create_table = SqlStatement::CreateTable {
         name: "users".to_string(),
         columns,
+        constraints: vec![],
     };
 
     executor.execute(create_table).unwrap();
@@ -357,6 +368,8 @@ fn test_executor_create_index() {
         table: "users".to_string(),
         columns: vec!["email".to_string()],
         unique: false,
+        filter: None,
+        hnsw: None,
     };
 
     let result = executor.execute(index_stmt);